@@ -0,0 +1,126 @@
+use std::{collections::BTreeSet, path::PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_error::error::Code;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{ApiToken, Scope, TokenId};
+
+const TOKENS_FILE: &str = "api_tokens.json";
+
+/// Prefixes secrets, so they are recognizable when they leak.
+const SECRET_PREFIX: &str = "gbt_";
+
+/// A token as it's stored, with the hash of its secret.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredToken {
+    #[serde(flatten)]
+    token: ApiToken,
+    /// The SHA-256 of the secret, in hex.
+    secret_hash: String,
+}
+
+/// A token that was just created, along with its secret, which can't be retrieved later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedToken {
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+/// Creates, lists and checks the API tokens, which are stored in the data directory of the application.
+#[derive(Debug, Clone)]
+pub struct Controller {
+    storage: gitbutler_storage::Storage,
+}
+
+impl Controller {
+    pub fn from_path(path: impl Into<PathBuf>) -> Controller {
+        Controller {
+            storage: gitbutler_storage::Storage::new(path),
+        }
+    }
+
+    /// Return all tokens, the oldest first.
+    pub fn list(&self) -> Result<Vec<ApiToken>> {
+        Ok(self
+            .read()?
+            .into_iter()
+            .map(|stored| stored.token)
+            .collect())
+    }
+
+    /// Return `true` if there are tokens, which means that the API can't be used without one.
+    pub fn is_required(&self) -> Result<bool> {
+        Ok(!self.read()?.is_empty())
+    }
+
+    /// Create a token named `name` that grants `scopes`, and return it along with its secret.
+    pub fn create(&self, name: &str, scopes: BTreeSet<Scope>) -> Result<CreatedToken> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("the name of a token can't be empty")).context(Code::Validation);
+        }
+        if scopes.is_empty() {
+            return Err(anyhow!("a token needs at least one scope")).context(Code::Validation);
+        }
+        let mut bytes = [0; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let secret = format!("{SECRET_PREFIX}{}", hex::encode(bytes));
+        let token = ApiToken {
+            id: TokenId::generate(),
+            name: name.to_owned(),
+            scopes,
+            created_timestamp_ms: gitbutler_time::time::now_ms(),
+        };
+        let mut tokens = self.read()?;
+        tokens.push(StoredToken {
+            token: token.clone(),
+            secret_hash: hash(&secret),
+        });
+        self.write(&tokens)?;
+        Ok(CreatedToken { token, secret })
+    }
+
+    /// Revoke the token with `id`, so its secret can't be used anymore.
+    pub fn revoke(&self, id: TokenId) -> Result<()> {
+        let mut tokens = self.read()?;
+        let count = tokens.len();
+        tokens.retain(|stored| stored.token.id != id);
+        if tokens.len() == count {
+            return Err(anyhow!("there is no token with id {id}")).context(Code::Validation);
+        }
+        self.write(&tokens)
+    }
+
+    /// Return the token whose secret is `secret`, or `None` if there is none.
+    pub fn authenticate(&self, secret: &str) -> Result<Option<ApiToken>> {
+        let hash = hash(secret);
+        Ok(self
+            .read()?
+            .into_iter()
+            .find(|stored| stored.secret_hash == hash)
+            .map(|stored| stored.token))
+    }
+
+    fn read(&self) -> Result<Vec<StoredToken>> {
+        match self.storage.read(TOKENS_FILE)? {
+            Some(data) => serde_json::from_str(&data)
+                .with_context(|| format!("failed to parse {TOKENS_FILE}")),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn write(&self, tokens: &[StoredToken]) -> Result<()> {
+        Ok(self
+            .storage
+            .write(TOKENS_FILE, &serde_json::to_string_pretty(tokens)?)?)
+    }
+}
+
+fn hash(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
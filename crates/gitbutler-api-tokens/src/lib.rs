@@ -0,0 +1,48 @@
+//! Tokens that tools like editor plugins authenticate with when they use the API of the application, each
+//! granting only the [scopes](Scope) it was created with.
+//!
+//! The secret of a token is only known when it's [created](Controller::create()). Only a hash of it is stored,
+//! so it can be checked, but not recovered.
+mod controller;
+pub use controller::{Controller, CreatedToken};
+
+use std::collections::BTreeSet;
+
+use gitbutler_id::id::Id;
+use serde::{Deserialize, Serialize};
+
+pub type TokenId = Id<ApiToken>;
+
+/// What a token may be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Scope {
+    /// Read projects, branches and the operations log, which all tokens may do.
+    ReadOnly,
+    /// Create branches and commit to them.
+    Commit,
+    /// Push branches to their remotes.
+    Push,
+    /// Everything, including adding projects and restoring snapshots.
+    Admin,
+}
+
+/// A token, without its secret.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub id: TokenId,
+    /// What the token is for, like the name of the plugin that uses it.
+    pub name: String,
+    pub scopes: BTreeSet<Scope>,
+    pub created_timestamp_ms: u128,
+}
+
+impl ApiToken {
+    /// Return `true` if this token may be used for what needs `scope`.
+    pub fn allows(&self, scope: Scope) -> bool {
+        scope == Scope::ReadOnly
+            || self.scopes.contains(&scope)
+            || self.scopes.contains(&Scope::Admin)
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeSet;
+
+use gitbutler_api_tokens::{Controller, Scope};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+#[test]
+fn tokens_authenticate_with_their_secret_until_revoked() {
+    let data_dir = tempfile::tempdir().unwrap();
+    let controller = Controller::from_path(data_dir.path());
+    assert!(!controller.is_required().unwrap());
+
+    let created = controller
+        .create("editor", BTreeSet::from([Scope::Commit]))
+        .unwrap();
+    assert!(controller.is_required().unwrap());
+    assert_eq!(controller.list().unwrap(), [created.token.clone()]);
+    assert!(
+        !std::fs::read_to_string(data_dir.path().join("api_tokens.json"))
+            .unwrap()
+            .contains(&created.secret),
+        "secrets aren't stored"
+    );
+
+    let token = controller.authenticate(&created.secret).unwrap().unwrap();
+    assert_eq!(token, created.token);
+    assert!(token.allows(Scope::ReadOnly));
+    assert!(token.allows(Scope::Commit));
+    assert!(!token.allows(Scope::Push));
+    assert!(!token.allows(Scope::Admin));
+    assert_eq!(controller.authenticate("gbt_wrong").unwrap(), None);
+
+    controller.revoke(token.id).unwrap();
+    assert_eq!(controller.authenticate(&created.secret).unwrap(), None);
+    assert!(!controller.is_required().unwrap());
+}
+
+#[test]
+fn admin_tokens_allow_everything() {
+    let data_dir = tempfile::tempdir().unwrap();
+    let controller = Controller::from_path(data_dir.path());
+    let token = controller
+        .create("cli", BTreeSet::from([Scope::Admin]))
+        .unwrap()
+        .token;
+    for scope in [Scope::ReadOnly, Scope::Commit, Scope::Push, Scope::Admin] {
+        assert!(token.allows(scope));
+    }
+}
+
+#[test]
+fn invalid_tokens_are_rejected() {
+    let data_dir = tempfile::tempdir().unwrap();
+    let controller = Controller::from_path(data_dir.path());
+    for err in [
+        controller
+            .create(" ", BTreeSet::from([Scope::ReadOnly]))
+            .unwrap_err(),
+        controller.create("editor", BTreeSet::new()).unwrap_err(),
+        controller
+            .revoke(gitbutler_api_tokens::TokenId::generate())
+            .unwrap_err(),
+    ] {
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation)
+        );
+    }
+    assert!(controller.list().unwrap().is_empty());
+}
@@ -0,0 +1,70 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::DiffOptions;
+use gitbutler_project::Project;
+
+/// The amount of files in the fixture, and the amount of them that changed.
+const NUM_FILES: u64 = 60_000;
+const NUM_CHANGED_FILES: u64 = 20_200;
+
+pub fn fixture_project(name: &str, script: &str) -> Project {
+    gitbutler_testsupport::read_only::fixture_project(script, name).unwrap()
+}
+
+/// Compare doing all work on one thread with using all CPUs.
+fn thread_counts() -> [usize; 2] {
+    [
+        1,
+        std::thread::available_parallelism().map_or(1, usize::from),
+    ]
+}
+
+pub fn benchmark_diff_worktree(c: &mut Criterion) {
+    let project = fixture_project("many-files", "large-worktree-benches.sh");
+    let repo = git2::Repository::open(&project.path).unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap().id();
+
+    let mut group = c.benchmark_group("diff-worktree[60k files]");
+    group
+        .sample_size(10)
+        .throughput(Throughput::Elements(NUM_FILES));
+    for threads in thread_counts() {
+        let options = DiffOptions {
+            threads,
+            ..DiffOptions::default()
+        };
+        group.bench_function(format!("{threads} threads"), |b| {
+            b.iter(|| gitbutler_diff::workdir_with_options(black_box(&repo), &head, &options))
+        });
+    }
+}
+
+pub fn benchmark_write_tree(c: &mut Criterion) {
+    let mut project = fixture_project("many-files", "large-worktree-benches.sh");
+
+    let mut group = c.benchmark_group("hunks-onto-tree[20k changed files]");
+    group
+        .sample_size(10)
+        .throughput(Throughput::Elements(NUM_CHANGED_FILES));
+    for threads in thread_counts() {
+        project.parallelism.max_threads = threads;
+        let ctx = CommandContext::open(&project).unwrap();
+        let repo = ctx.repository();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let base_tree = head.tree().unwrap();
+        let diff = gitbutler_diff::workdir(repo, &head.id()).unwrap();
+        assert_eq!(diff.len() as u64, NUM_CHANGED_FILES);
+        group.bench_function(format!("{threads} threads"), |b| {
+            b.iter(|| {
+                gitbutler_diff::write::hunks_onto_tree(
+                    black_box(&ctx),
+                    &base_tree,
+                    diff.iter().map(|(path, file)| (path, &file.hunks)),
+                )
+            })
+        });
+    }
+}
+
+criterion_group!(benches, benchmark_diff_worktree, benchmark_write_tree);
+criterion_main!(benches);
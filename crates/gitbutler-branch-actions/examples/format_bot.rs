@@ -0,0 +1,72 @@
+//! A bot that formats a repository and proposes the changes in a pull request, without touching the changes
+//! that are already in the workspace.
+//!
+//! ```sh
+//! cargo run -p gitbutler-branch-actions --features headless --example format_bot -- \
+//!     <data-dir> <repository> refs/remotes/origin/main cargo fmt
+//! ```
+//!
+//! The token to access the forge is read from `GITBUTLER_FORGE_TOKEN` if it isn't stored for the forge already.
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use gitbutler_branch_actions::{headless::Session, NewPullRequest};
+
+const LANE: &str = "format-bot";
+/// The formatter is expected to change Rust files only, so nothing else ends up in the lane.
+const QUERY: &str = "path:*.rs";
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let (Some(data_dir), Some(repository), Some(target), Some(formatter)) =
+        (args.next(), args.next(), args.next(), args.next())
+    else {
+        bail!("usage: format_bot <data-dir> <repository> <target> <formatter> [<args>...]");
+    };
+    let formatter_args: Vec<_> = args.collect();
+
+    let session = Session::open(data_dir, &repository)?;
+    session.ensure_target(&target.parse()?)?;
+    let synced = session.sync()?;
+    for remote in synced
+        .fetch
+        .remotes
+        .iter()
+        .filter(|remote| remote.error.is_some())
+    {
+        eprintln!("failed to fetch {}", remote.remote);
+    }
+    for branch in &synced.unapplied_branches {
+        eprintln!("unapplied {branch} as it conflicts with the target");
+    }
+
+    let status = Command::new(&formatter)
+        .args(&formatter_args)
+        .current_dir(&repository)
+        .status()
+        .with_context(|| format!("failed to run {formatter}"))?;
+    if !status.success() {
+        bail!("{formatter} failed with {status}");
+    }
+
+    let lane = session.lane(LANE)?;
+    let Some(commit) = session.commit_to_lane(lane, QUERY, "Format the code")? else {
+        println!("everything is formatted already");
+        return Ok(());
+    };
+    println!("committed {commit}");
+
+    session.push(lane)?;
+    let token = std::env::var("GITBUTLER_FORGE_TOKEN").ok();
+    let pull_request = session.open_pull_request(
+        lane,
+        &NewPullRequest {
+            title: "Format the code".into(),
+            body: format!("Formatted by running `{formatter}`."),
+            draft: false,
+        },
+        token.as_deref(),
+    )?;
+    println!("opened {}", pull_request.url);
+    Ok(())
+}
@@ -0,0 +1,239 @@
+//! Move uncommitted hunks into the commits of their branch, either one at a time or by absorbing each into the
+//! most recent commit of the branch that last touched its lines, like `git absorb`.
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_branch::{BranchId, BranchOwnershipClaims, OwnershipClaim};
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::Hunk;
+use gitbutler_error::error::Code;
+use gitbutler_repo::{LogUntil, RepoActionsExt};
+use serde::Serialize;
+
+use crate::{
+    conflicts::RepoConflictsExt, hunk::VirtualBranchHunk, pushed_commits, r#virtual::amend_commit,
+    status::get_applied_status, AmendRequest, VirtualBranchesExt,
+};
+
+/// What absorbing the uncommitted hunks of a branch into its commits did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsorbOutcome {
+    /// The head of the branch after the hunks were absorbed.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub head: git2::Oid,
+    pub absorbed: Vec<AbsorbedHunk>,
+    /// The hunks that stayed uncommitted.
+    pub skipped: Vec<SkippedHunk>,
+}
+
+/// A hunk that was added to a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsorbedHunk {
+    pub file_path: PathBuf,
+    /// The lines of the hunk, as `start-end`.
+    pub hunk: String,
+    /// The commit the hunk was added to, as it was before it was rewritten.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub commit_id: git2::Oid,
+}
+
+/// A hunk that couldn't be absorbed, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedHunk {
+    pub file_path: PathBuf,
+    /// The lines of the hunk, as `start-end`.
+    pub hunk: String,
+    pub reason: SkipReason,
+}
+
+/// Why a hunk couldn't be absorbed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SkipReason {
+    /// No commit of the branch touched the lines the hunk changes, or is next to.
+    NoCommit,
+    /// The hunk doesn't apply to the commit that last touched its lines, or the commits after it conflict with it.
+    Conflict,
+}
+
+/// Add the uncommitted hunk `hunk_id`, like `3-7`, of the file at `file_path` to the commit `commit_oid` of
+/// the branch with `branch_id`, and rewrite its descendants. Returns the id of the rewritten commit.
+pub(crate) fn move_hunk_to_commit(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    file_path: &Path,
+    hunk_id: &str,
+    commit_oid: git2::Oid,
+    rewrite_pushed: bool,
+) -> Result<git2::Oid> {
+    let hunk: Hunk = hunk_id
+        .parse()
+        .map_err(|err| anyhow!("invalid hunk '{hunk_id}': {err}"))
+        .context(Code::Validation)?;
+    let request = AmendRequest {
+        hunks: Some(BranchOwnershipClaims {
+            claims: vec![OwnershipClaim {
+                file_path: file_path.to_owned(),
+                hunks: vec![hunk],
+            }],
+        }),
+        ..Default::default()
+    };
+    amend_commit(ctx, branch_id, commit_oid, &request, rewrite_pushed)
+}
+
+/// Add each uncommitted hunk of the branch with `branch_id` to the most recent of its commits that last touched
+/// the lines the hunk changes, or the lines around the lines it adds. Commits are rewritten from the most
+/// recent one, and hunks that don't apply or conflict with later commits stay uncommitted.
+pub(crate) fn absorb(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    rewrite_pushed: bool,
+) -> Result<AbsorbOutcome> {
+    ctx.assure_resolved()?;
+    let vb_state = ctx.project().virtual_branches();
+    let default_target = vb_state.get_default_target()?;
+    let branch = vb_state.get_branch_in_workspace(branch_id)?;
+    let repo = ctx.repository();
+
+    let commits = ctx.log(
+        branch.head,
+        LogUntil::Commit(branch.base(default_target.sha)),
+    )?;
+    if commits.is_empty() {
+        return Err(anyhow!("branch has no commits to absorb hunks into"))
+            .context(Code::Validation);
+    }
+    if branch.upstream.is_some() && !branch.allow_rebasing {
+        bail!("force-push is not allowed");
+    }
+    // The position of each commit, with the most recent one first.
+    let positions: HashMap<_, _> = commits
+        .iter()
+        .enumerate()
+        .map(|(position, commit)| (commit.id(), position))
+        .collect();
+
+    let files = get_applied_status(ctx, None)?
+        .branches
+        .into_iter()
+        .find(|(branch, _)| branch.id == branch_id)
+        .map(|(_, files)| files)
+        .unwrap_or_default();
+    let mut blames = HashMap::new();
+    let mut by_commit = BTreeMap::<usize, Vec<VirtualBranchHunk>>::new();
+    let mut skipped = Vec::new();
+    for hunk in files.into_iter().flat_map(|file| file.hunks) {
+        let blame = blames.entry(hunk.file_path.clone()).or_insert_with(|| {
+            let mut options = git2::BlameOptions::new();
+            options.newest_commit(branch.head);
+            repo.blame_file(&hunk.file_path, Some(&mut options)).ok()
+        });
+        let position = blame.as_ref().and_then(|blame| {
+            touched_lines(&hunk)
+                .into_iter()
+                .filter_map(|line| blame.get_line(line as usize))
+                .filter_map(|blamed| positions.get(&blamed.final_commit_id()).copied())
+                .min()
+        });
+        match position {
+            Some(position) => by_commit.entry(position).or_default().push(hunk),
+            None => skipped.push(SkippedHunk {
+                file_path: hunk.file_path.clone(),
+                hunk: hunk.id.clone(),
+                reason: SkipReason::NoCommit,
+            }),
+        }
+    }
+
+    pushed_commits::ensure_rewritable(
+        ctx,
+        &branch,
+        &by_commit
+            .keys()
+            .map(|position| commits[*position].id())
+            .collect::<Vec<_>>(),
+        rewrite_pushed,
+    )?;
+
+    // Rewriting a commit only rewrites the more recent ones, so the older ones keep their ids.
+    let mut absorbed = Vec::new();
+    for (position, hunks) in by_commit {
+        let commit_id = commits[position].id();
+        let mut claims = BTreeMap::<PathBuf, Vec<Hunk>>::new();
+        for hunk in &hunks {
+            claims
+                .entry(hunk.file_path.clone())
+                .or_default()
+                .push(Hunk::new(hunk.start, hunk.end, None)?);
+        }
+        let request = AmendRequest {
+            hunks: Some(BranchOwnershipClaims {
+                claims: claims
+                    .into_iter()
+                    .map(|(file_path, hunks)| OwnershipClaim { file_path, hunks })
+                    .collect(),
+            }),
+            ..Default::default()
+        };
+        let reason = match amend_commit(ctx, branch_id, commit_id, &request, true) {
+            Ok(_) => None,
+            Err(err) => {
+                tracing::warn!(?err, %commit_id, "failed to absorb hunks into commit");
+                Some(SkipReason::Conflict)
+            }
+        };
+        for hunk in hunks {
+            match reason {
+                None => absorbed.push(AbsorbedHunk {
+                    file_path: hunk.file_path,
+                    hunk: hunk.id,
+                    commit_id,
+                }),
+                Some(reason) => skipped.push(SkippedHunk {
+                    file_path: hunk.file_path,
+                    hunk: hunk.id,
+                    reason,
+                }),
+            }
+        }
+    }
+
+    Ok(AbsorbOutcome {
+        head: vb_state.get_branch_in_workspace(branch_id)?.head,
+        absorbed,
+        skipped,
+    })
+}
+
+/// Return the numbers of the lines before `hunk` was applied that it removes, or that are around the lines it
+/// adds, which are the lines it depends on.
+fn touched_lines(hunk: &VirtualBranchHunk) -> Vec<u32> {
+    let mut lines = Vec::new();
+    let mut old_line = hunk.old_start;
+    for line in hunk.diff.lines().skip_while(|line| line.starts_with(b"@@")) {
+        match line.first() {
+            Some(b'-') => {
+                lines.push(old_line);
+                old_line += 1;
+            }
+            Some(b'+') => {
+                if old_line > 1 {
+                    lines.push(old_line - 1);
+                }
+                lines.push(old_line);
+            }
+            Some(b'\\') => {}
+            _ => old_line += 1,
+        }
+    }
+    lines.dedup();
+    lines
+}
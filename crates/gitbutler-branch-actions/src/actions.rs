@@ -1,26 +1,101 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
 use anyhow::{Context, Result};
-use gitbutler_branch::{BranchCreateRequest, BranchId, BranchOwnershipClaims, BranchUpdateRequest};
-use gitbutler_command_context::CommandContext;
+use gitbutler_branch::{
+    BranchCreateRequest, BranchEvent, BranchId, BranchOwnershipClaims, BranchUpdateRequest,
+    CommitProvenance, FsckReport, HunkPin, QueuedOperation, QueuedOperationId, QueuedOperationKind,
+    Shelf, ShelfId, TrashEntry, TrashEntryId, TrashOrigin,
+};
+use gitbutler_command_context::{cancellation, CancellationToken, CommandContext};
+use gitbutler_diff::{filter::EolChange, DiffOptions, Hunk, HunkSelection, RangeSet};
+use gitbutler_error::error::{AnyhowContextExt, Code};
 use gitbutler_operating_modes::assure_open_workspace_mode;
 use gitbutler_oplog::{
     entry::{OperationKind, SnapshotDetails},
-    OplogExt, SnapshotExt,
+    AuditEntry, AuditQuery, OplogExt, SnapshotExt,
+};
+use gitbutler_project::{ForgeKind, Project};
+use gitbutler_reference::{LocalRefname, ReferenceName, Refname, RemoteRefname};
+use gitbutler_repo::{
+    credentials::Helper,
+    fetch_remotes,
+    progress::{self, NoProgress, Progress},
+    shallow, Deepen, FetchReport, RepositoryExt,
 };
-use gitbutler_project::{FetchResult, Project};
-use gitbutler_reference::{ReferenceName, Refname, RemoteRefname};
-use gitbutler_repo::{credentials::Helper, RepoActionsExt, RepositoryExt};
 use tracing::instrument;
 
 use super::r#virtual as branch;
 use crate::{
+    absorb::{self, AbsorbOutcome},
+    adopt,
+    automation::{self, AutomationRun},
     base::{
         get_base_branch_data, set_base_branch, set_target_push_remote, update_base_branch,
-        BaseBranch,
+        BaseBranch, BaseUpdateStrategy,
     },
+    blame::{self, BlameHunk},
+    branch_dependencies::{self, BranchDependency},
     branch_manager::BranchManagerExt,
+    branch_metadata,
+    bulk::{self, BulkBranchResult},
+    checkout_preview::{self, CheckoutPreview},
+    cherry_pick::{self, CherryPickOutcome},
+    cleanup::{self, PendingCleanup},
+    clock_skew,
+    commit_graph::{self, CommitGraph},
+    commit_message::{self, CommitTemplate},
+    commit_preview::{self, CommitPreview},
+    conflict_prediction::{self, PredictedConflict},
+    conflicts::{ConflictSession, ConflictSide, ConflictedFile, Resolution},
+    content_search::{self, ContentMatch},
+    discard,
+    export::{self, ExportOutcome, ExportUncommitted},
     file::RemoteBranchFile,
+    file_history::{self, FileHistoryEntry},
+    forge::{self, AutoMerge, CiStatus, NewPullRequest, PullRequest},
+    history::{self, HistoryFilter, HistoryPage},
+    hunk_groups::{self, HunkGroup},
+    hunk_query::{self, HunkQuery},
+    identity::{self, Identity},
+    ignores::{self, IgnoreCheck, IgnoreFile},
+    integration::{self, IntegrationDivergence},
+    layout::{self, LayoutOutcome},
+    leftovers::{self, Leftover},
+    merge_order::{self, MergeOrderSimulation},
+    message_generation,
+    offline::{self, ReplayedOperation},
+    operation_journal::{self, PendingOperation, ResumableOperation},
+    ownership_conflicts::{self, OwnershipConflict},
+    ownership_remap::OwnershipRemap,
+    partial_apply,
+    partial_checkout::{self, PartialCheckout},
+    patch_import::{self, ImportPatchOptions, PatchImportOutcome},
+    patches::{self, PatchFormat},
+    pinned_base,
+    project_search::{self, SearchMatch},
+    push_preview::{self, PushPreview},
+    push_protection::{self, PushProtectionCheck},
+    reconcile::{self, Reconciliation, RecoveryOption},
     remote::{get_branch_data, list_remote_branches, RemoteBranch, RemoteBranchData},
-    VirtualBranchesExt,
+    remote_activity::{self, RemoteBranchActivity},
+    remote_branch_name, remotes,
+    revert::{self, RevertOutcome},
+    setup::{self, SetupPlan},
+    shelf::{self, ShelvesExt},
+    snapshot_branch, stack,
+    stash::{self, StashEntry, StashImport},
+    status::{self, FileStatus, WorkspaceOwnership},
+    status_trace::{self, StatusTrace},
+    submodules::{self, NestedRepository, Submodule},
+    tags::{self, Tag},
+    target_switch::{self, SwitchedBranch},
+    tracking, trash,
+    upstream::{self, IntegrationOutcome, IntegrationStrategy},
+    workspace_check::{self, WorkspaceDesync},
+    AmendRequest, VirtualBranchesExt,
 };
 
 #[derive(Clone, Copy, Default)]
@@ -34,15 +109,53 @@ impl VirtualBranchActions {
         message: &str,
         ownership: Option<&BranchOwnershipClaims>,
         run_hooks: bool,
+    ) -> Result<git2::Oid> {
+        self.create_commit_with_selections(
+            project,
+            branch_id,
+            message,
+            ownership,
+            &BTreeMap::new(),
+            run_hooks,
+            false,
+        )
+    }
+
+    /// Like [`create_commit()`](Self::create_commit()), but only commits the selected lines of the hunks in `selections`.
+    /// New files can only be committed as a whole, so selecting lines of them fails with
+    /// [`Code::Validation`](gitbutler_error::error::Code::Validation).
+    ///
+    /// If the project checks for secrets, committing fails with [`SecretsDetected`](crate::SecretsDetected) if the
+    /// changes contain any, unless `allow_secrets` is `true`.
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip_all, fields(operation = "commit", project_id = %project.id, %branch_id, run_hooks), err(Debug))]
+    pub fn create_commit_with_selections(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        message: &str,
+        ownership: Option<&BranchOwnershipClaims>,
+        selections: &BTreeMap<PathBuf, Vec<HunkSelection>>,
+        run_hooks: bool,
+        allow_secrets: bool,
     ) -> Result<git2::Oid> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
             .context("Creating a commit requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+        identity::ensure_identity(&ctx)?;
+        let mut guard = project.exclusive_operation_access("create_commit")?;
         let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
-        let result =
-            branch::commit(&ctx, branch_id, message, ownership, run_hooks).map_err(Into::into);
-        let _ = snapshot_tree.and_then(|snapshot_tree| {
+        let result = branch::commit_with_selections(
+            &ctx,
+            branch_id,
+            message,
+            ownership,
+            selections,
+            run_hooks,
+            allow_secrets,
+        )
+        .map_err(Into::into);
+        let snapshot = snapshot_tree.and_then(|snapshot_tree| {
             ctx.project().snapshot_commit_creation(
                 snapshot_tree,
                 result.as_ref().err(),
@@ -51,6 +164,9 @@ impl VirtualBranchActions {
                 guard.write_permission(),
             )
         });
+        if let (Ok(commit_id), Ok(Some(snapshot_id))) = (&result, snapshot) {
+            attach_snapshot_to_provenance(project, *commit_id, snapshot_id);
+        }
         result
     }
 
@@ -69,13 +185,31 @@ impl VirtualBranchActions {
         &self,
         project: &Project,
     ) -> Result<(Vec<branch::VirtualBranch>, Vec<gitbutler_diff::FileDiff>)> {
-        let ctx = open_with_verify(project)?;
+        self.list_virtual_branches_with_options(project, &DiffOptions::default())
+    }
+
+    /// Like [`list_virtual_branches()`](Self::list_virtual_branches()), but hides the hunks whose
+    /// changes are ignored by `options`. Only the presentation changes, as ownership always tracks
+    /// the actual contents of files.
+    #[instrument(skip_all, fields(operation = "status", project_id = %project.id), err(Debug))]
+    pub fn list_virtual_branches_with_options(
+        &self,
+        project: &Project,
+        options: &DiffOptions,
+    ) -> Result<(Vec<branch::VirtualBranch>, Vec<gitbutler_diff::FileDiff>)> {
+        let ctx = open_for_reading_with_verify(project)?;
 
         assure_open_workspace_mode(&ctx)
             .context("Listing virtual branches requires open workspace mode")?;
 
-        branch::list_virtual_branches(&ctx, project.exclusive_worktree_access().write_permission())
-            .map_err(Into::into)
+        let (mut branches, skipped_files) = branch::list_virtual_branches(
+            &ctx,
+            project
+                .exclusive_operation_access("list_virtual_branches")?
+                .write_permission(),
+        )?;
+        branch::hide_ignored_hunks(&mut branches, options);
+        Ok((branches, skipped_files))
     }
 
     pub fn create_virtual_branch(
@@ -86,7 +220,7 @@ impl VirtualBranchActions {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
             .context("Creating a branch requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+        let mut guard = project.exclusive_operation_access("create_virtual_branch")?;
         let branch_manager = ctx.branch_manager();
         let branch_id = branch_manager
             .create_virtual_branch(create, guard.write_permission())?
@@ -122,6 +256,7 @@ impl VirtualBranchActions {
             }
             // Deletes the virtual branch entry from the application state
             handle.delete_branch_entry(&vbranch.id)?;
+            ctx.project().branch_activity().forget_branch(vbranch.id)?;
         }
 
         // If a branch reference for this can be found, delete it
@@ -131,6 +266,156 @@ impl VirtualBranchActions {
         Ok(())
     }
 
+    /// Return the local branches that are integrated into the target for long enough to be cleaned up,
+    /// according to the cleanup policy of the project.
+    pub fn list_pending_cleanups(&self, project: &Project) -> Result<Vec<PendingCleanup>> {
+        let ctx = open_for_reading_with_verify(project)?;
+        cleanup::pending_cleanups(&ctx)
+    }
+
+    /// Archive or delete the local branches named `names`, which must be pending cleanup as returned
+    /// by [`list_pending_cleanups()`](Self::list_pending_cleanups()).
+    pub fn clean_up_branches(&self, project: &Project, names: &[String]) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        let _guard = project.exclusive_operation_access("clean_up_branches")?;
+        cleanup::clean_up(&ctx, names)
+    }
+
+    /// Delete the virtual branches identified by `branch_ids`, reporting the outcome for each of them.
+    /// A single snapshot is taken, so all deletions can be undone at once.
+    pub fn delete_virtual_branches(
+        &self,
+        project: &Project,
+        branch_ids: &[BranchId],
+    ) -> Result<Vec<BulkBranchResult>> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Deleting branches requires open workspace mode")?;
+        operation_journal::assure_none_pending(&ctx)?;
+        let mut guard = project.exclusive_operation_access("delete_virtual_branches")?;
+        let snapshot = ctx
+            .project()
+            .create_snapshot(
+                SnapshotDetails::new(OperationKind::DeleteBranches),
+                guard.write_permission(),
+            )
+            .ok()
+            .flatten();
+        let operation = ResumableOperation::DeleteBranches {
+            branch_ids: branch_ids.to_vec(),
+        };
+        operation_journal::journaled(&ctx, operation, snapshot, |journal| {
+            bulk::delete_branches(&ctx, branch_ids, guard.write_permission(), journal)
+        })
+    }
+
+    /// Unapply all virtual branches in the workspace, reporting the outcome for each of them.
+    /// A single snapshot is taken, so all of them can be applied again at once.
+    pub fn unapply_all_branches(&self, project: &Project) -> Result<Vec<BulkBranchResult>> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Unapplying branches requires open workspace mode")?;
+        operation_journal::assure_none_pending(&ctx)?;
+        let mut guard = project.exclusive_operation_access("unapply_all_branches")?;
+        let branch_ids: Vec<_> = ctx
+            .project()
+            .virtual_branches()
+            .list_branches_in_workspace()?
+            .into_iter()
+            .map(|branch| branch.id)
+            .collect();
+        let snapshot = ctx
+            .project()
+            .create_snapshot(
+                SnapshotDetails::new(OperationKind::UnapplyBranches),
+                guard.write_permission(),
+            )
+            .ok()
+            .flatten();
+        let operation = ResumableOperation::UnapplyBranches {
+            branch_ids: branch_ids.clone(),
+        };
+        operation_journal::journaled(&ctx, operation, snapshot, |journal| {
+            bulk::unapply_branches(&ctx, &branch_ids, guard.write_permission(), journal)
+        })
+    }
+
+    /// Apply the local or remote branches named `branches` as virtual branches, reporting the outcome
+    /// for each of them. A single snapshot is taken, so all of them can be unapplied again at once.
+    pub fn apply_branches(
+        &self,
+        project: &Project,
+        branches: &[Refname],
+    ) -> Result<Vec<BulkBranchResult>> {
+        self.apply_branches_with_progress(project, branches, &NoProgress)
+    }
+
+    /// Like [`Self::apply_branches()`], but report each applied branch to `progress`. Once cancelled, the
+    /// branches that weren't applied yet are reported as failed.
+    #[instrument(skip_all, fields(operation = "apply", project_id = %project.id, branches = branches.len()), err(Debug))]
+    pub fn apply_branches_with_progress(
+        &self,
+        project: &Project,
+        branches: &[Refname],
+        progress: &dyn Progress,
+    ) -> Result<Vec<BulkBranchResult>> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Applying branches requires open workspace mode")?;
+        operation_journal::assure_none_pending(&ctx)?;
+        let mut guard = project.exclusive_operation_access("apply_branches")?;
+        let snapshot = ctx
+            .project()
+            .create_snapshot(
+                SnapshotDetails::new(OperationKind::ApplyBranches),
+                guard.write_permission(),
+            )
+            .ok()
+            .flatten();
+        let operation = ResumableOperation::ApplyBranches {
+            branches: branches.to_vec(),
+        };
+        operation_journal::journaled(&ctx, operation, snapshot, |journal| {
+            bulk::apply_branches(&ctx, branches, guard.write_permission(), journal, progress)
+        })
+    }
+
+    /// Archive all local branches that are pending cleanup as returned by
+    /// [`list_pending_cleanups()`](Self::list_pending_cleanups()), reporting the outcome for each of them.
+    pub fn archive_integrated_branches(&self, project: &Project) -> Result<Vec<BulkBranchResult>> {
+        let ctx = open_with_verify(project)?;
+        let mut guard = project.exclusive_operation_access("archive_integrated_branches")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::ArchiveBranches),
+            guard.write_permission(),
+        );
+        bulk::archive_integrated_branches(&ctx)
+    }
+
+    /// Return the multi-step operation that was interrupted before it finished, like by the process ending, if any.
+    /// It can be [resumed](Self::resume_operation()) or [rolled back](Self::roll_back_operation()), and other
+    /// multi-step operations can't start until then.
+    pub fn pending_operation(&self, project: &Project) -> Result<Option<PendingOperation>> {
+        let ctx = CommandContext::open(project)?;
+        operation_journal::pending(&ctx)
+    }
+
+    /// Run the steps of the [interrupted operation](Self::pending_operation()) that weren't completed.
+    pub fn resume_operation(&self, project: &Project) -> Result<()> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Resuming an operation requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("resume_operation")?;
+        operation_journal::resume(&ctx, guard.write_permission())
+    }
+
+    /// Restore the workspace to how it was before the [interrupted operation](Self::pending_operation())
+    /// started.
+    pub fn roll_back_operation(&self, project: &Project) -> Result<()> {
+        let ctx = CommandContext::open(project)?;
+        operation_journal::roll_back(&ctx)
+    }
+
     #[instrument(skip(project), err(Debug))]
     pub fn get_base_branch_data(project: &Project) -> Result<BaseBranch> {
         let ctx = CommandContext::open(project)?;
@@ -141,9 +426,11 @@ impl VirtualBranchActions {
         &self,
         project: &Project,
         commit_oid: git2::Oid,
+        options: &DiffOptions,
     ) -> Result<Vec<RemoteBranchFile>> {
         let ctx = CommandContext::open(project)?;
-        crate::file::list_remote_commit_files(ctx.repository(), commit_oid).map_err(Into::into)
+        crate::file::list_remote_commit_files(ctx.repository(), commit_oid, options)
+            .map_err(Into::into)
     }
 
     pub fn set_base_branch(
@@ -152,7 +439,7 @@ impl VirtualBranchActions {
         target_branch: &RemoteRefname,
     ) -> Result<BaseBranch> {
         let ctx = CommandContext::open(project)?;
-        let mut guard = project.exclusive_worktree_access();
+        let mut guard = project.exclusive_operation_access("set_base_branch")?;
         let _ = ctx.project().create_snapshot(
             SnapshotDetails::new(OperationKind::SetBaseBranch),
             guard.write_permission(),
@@ -160,370 +447,2020 @@ impl VirtualBranchActions {
         set_base_branch(&ctx, target_branch)
     }
 
+    /// Fetch from all remotes of a project that isn't set up yet, and return how it would be set up:
+    /// the proposed target and which local branches would be imported.
+    pub fn plan_setup(&self, project: &Project, askpass: Option<String>) -> Result<SetupPlan> {
+        let ctx = CommandContext::open(project)?;
+        setup::plan_setup(&ctx, askpass)
+    }
+
+    /// Set up a project like a [`SetupPlan`] proposes, with `target_branch` as target and the local
+    /// branches in `import` as applied virtual branches.
+    pub fn set_up_project(
+        &self,
+        project: &Project,
+        target_branch: &RemoteRefname,
+        import: &[LocalRefname],
+    ) -> Result<BaseBranch> {
+        let ctx = CommandContext::open(project)?;
+        let mut guard = project.exclusive_operation_access("set_up_project")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::SetBaseBranch),
+            guard.write_permission(),
+        );
+        setup::set_up(&ctx, target_branch, import, guard.write_permission())
+    }
+
     pub fn set_target_push_remote(&self, project: &Project, push_remote: &str) -> Result<()> {
         let ctx = CommandContext::open(project)?;
         set_target_push_remote(&ctx, push_remote)
     }
 
-    pub fn integrate_upstream_commits(&self, project: &Project, branch_id: BranchId) -> Result<()> {
+    /// Push the branch identified by `branch_id` to `remote_name` instead of the push remote of the target,
+    /// or to the push remote of the target again if `None`.
+    pub fn set_branch_push_remote(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        remote_name: Option<&str>,
+    ) -> Result<()> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Integrating upstream commits requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+            .context("Setting the push remote of a branch requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("set_branch_push_remote")?;
         let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::MergeUpstream),
+            SnapshotDetails::new(OperationKind::UpdateBranchRemoteName),
             guard.write_permission(),
         );
-        branch::integrate_upstream_commits(&ctx, branch_id).map_err(Into::into)
+        remotes::set_push_remote(&ctx, branch_id, remote_name)
     }
 
-    pub fn update_base_branch(&self, project: &Project) -> Result<Vec<ReferenceName>> {
+    /// Rename the remote `old_name` to `new_name`, and update the target and branches that refer to it.
+    pub fn rename_remote(&self, project: &Project, old_name: &str, new_name: &str) -> Result<()> {
+        let ctx = CommandContext::open(project)?;
+        let _guard = project.exclusive_operation_access("rename_remote")?;
+        remotes::rename_remote(&ctx, old_name, new_name)
+    }
+
+    /// Remove the remote `name`, unless the target is fetched from it.
+    pub fn remove_remote(&self, project: &Project, name: &str) -> Result<()> {
+        let ctx = CommandContext::open(project)?;
+        let _guard = project.exclusive_operation_access("remove_remote")?;
+        remotes::remove_remote(&ctx, name)
+    }
+
+    pub fn set_remote_url(&self, project: &Project, name: &str, url: &str) -> Result<()> {
+        let ctx = CommandContext::open(project)?;
+        let _guard = project.exclusive_operation_access("set_remote_url")?;
+        remotes::set_remote_url(&ctx, name, url)
+    }
+
+    pub fn integrate_upstream_commits(&self, project: &Project, branch_id: BranchId) -> Result<()> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Updating base branch requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+            .context("Integrating upstream commits requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("integrate_upstream_commits")?;
         let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::UpdateWorkspaceBase),
+            SnapshotDetails::new(OperationKind::MergeUpstream),
             guard.write_permission(),
         );
-        update_base_branch(&ctx, guard.write_permission()).map_err(Into::into)
+        branch::integrate_upstream_commits(&ctx, branch_id).map_err(Into::into)
     }
 
-    pub fn update_virtual_branch(
+    /// Bring the new commits of the upstream branch of the applied branch identified by `branch_id` into it,
+    /// by rebasing or merging as chosen by `strategy`.
+    /// Conflicts are left in the worktree to be resolved through the [conflict session](ConflictSession).
+    pub fn integrate_upstream(
         &self,
         project: &Project,
-        branch_update: BranchUpdateRequest,
-    ) -> Result<()> {
+        branch_id: BranchId,
+        strategy: IntegrationStrategy,
+    ) -> Result<IntegrationOutcome> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Updating a branch requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
-        let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
-        let old_branch = ctx
-            .project()
-            .virtual_branches()
-            .get_branch_in_workspace(branch_update.id)?;
-        let result = branch::update_branch(&ctx, &branch_update);
-        let _ = snapshot_tree.and_then(|snapshot_tree| {
-            ctx.project().snapshot_branch_update(
-                snapshot_tree,
-                &old_branch,
-                &branch_update,
-                result.as_ref().err(),
-                guard.write_permission(),
-            )
-        });
-        result?;
-        Ok(())
+            .context("Integrating upstream commits requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("integrate_upstream")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::MergeUpstream),
+            guard.write_permission(),
+        );
+        upstream::integrate_upstream(&ctx, branch_id, strategy, guard.write_permission())
     }
 
-    pub fn update_branch_order(
+    /// Make `new_target` the target of the project, and rebase all applied branches onto it.
+    /// Branches that conflict with the new target are unapplied.
+    pub fn switch_base_branch(
         &self,
         project: &Project,
-        branch_updates: Vec<BranchUpdateRequest>,
-    ) -> Result<()> {
+        new_target: &RemoteRefname,
+    ) -> Result<Vec<SwitchedBranch>> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Updating branch order requires open workspace mode")?;
-        for branch_update in branch_updates {
-            let branch = ctx
-                .project()
-                .virtual_branches()
-                .get_branch_in_workspace(branch_update.id)?;
-            if branch_update.order != Some(branch.order) {
-                branch::update_branch(&ctx, &branch_update)?;
-            }
-        }
-        Ok(())
+            .context("Switching the base branch requires open workspace mode")?;
+        submodules::assure_submodules_unchanged(&ctx)?;
+        let mut guard = project.exclusive_operation_access("switch_base_branch")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::SetBaseBranch),
+            guard.write_permission(),
+        );
+        target_switch::switch_target(&ctx, new_target, guard.write_permission())
     }
 
-    pub fn delete_virtual_branch(&self, project: &Project, branch_id: BranchId) -> Result<()> {
-        let ctx = open_with_verify(project)?;
-        assure_open_workspace_mode(&ctx)
-            .context("Deleting a branch order requires open workspace mode")?;
-        let branch_manager = ctx.branch_manager();
-        let mut guard = project.exclusive_worktree_access();
-        let default_target = ctx.project().virtual_branches().get_default_target()?;
-        let target_commit = ctx.repository().find_commit(default_target.sha)?;
-        branch_manager.delete_branch(branch_id, guard.write_permission(), &target_commit)
+    /// Return the default branch of the remote of the target, if a fetch found it to be another branch
+    /// than the target.
+    pub fn remote_default_branch_change(&self, project: &Project) -> Result<Option<RemoteRefname>> {
+        let ctx = CommandContext::open(project)?;
+        target_switch::remote_default_branch_change(&ctx)
     }
 
-    pub fn unapply_ownership(
+    pub fn migrate_target_to_remote_default(
         &self,
         project: &Project,
-        ownership: &BranchOwnershipClaims,
-    ) -> Result<()> {
+    ) -> Result<Vec<SwitchedBranch>> {
         let ctx = open_with_verify(project)?;
-        assure_open_workspace_mode(&ctx).context("Unapply a patch requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+        assure_open_workspace_mode(&ctx)
+            .context("Migrating the base branch requires open workspace mode")?;
+        submodules::assure_submodules_unchanged(&ctx)?;
+        let mut guard = project.exclusive_operation_access("migrate_target_to_remote_default")?;
         let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::DiscardHunk),
+            SnapshotDetails::new(OperationKind::SetBaseBranch),
             guard.write_permission(),
         );
-        branch::unapply_ownership(&ctx, ownership, guard.write_permission()).map_err(Into::into)
+        target_switch::migrate_target_to_remote_default(&ctx, guard.write_permission())
     }
 
-    pub fn reset_files(&self, project: &Project, files: &Vec<String>) -> Result<()> {
+    pub fn update_base_branch(&self, project: &Project) -> Result<Vec<ReferenceName>> {
+        self.update_base_branch_with_remap(project, BaseUpdateStrategy::default())
+            .map(|(unapplied_branches, _remap)| unapplied_branches)
+    }
+
+    /// Like [`Self::update_base_branch()`], but bring the commits of branches onto the new target with
+    /// `strategy`, and also return how the hunks of the branches that stayed applied were remapped, as the
+    /// new target shifts their lines.
+    pub fn update_base_branch_with_remap(
+        &self,
+        project: &Project,
+        strategy: BaseUpdateStrategy,
+    ) -> Result<(Vec<ReferenceName>, OwnershipRemap)> {
+        self.update_base_branch_with_progress(project, strategy, &NoProgress)
+    }
+
+    /// Like [`Self::update_base_branch_with_remap()`], but report each branch brought onto the new target
+    /// to `progress`.
+    ///
+    /// If the update is cancelled through `progress` or the current cancellation token, the snapshot taken
+    /// before it is restored and it fails with [`Code::Cancelled`].
+    /// Without a snapshot to go back to, it can't be cancelled once it started.
+    #[instrument(skip_all, fields(operation = "update_base", project_id = %project.id, ?strategy), err(Debug))]
+    pub fn update_base_branch_with_progress(
+        &self,
+        project: &Project,
+        strategy: BaseUpdateStrategy,
+        progress: &dyn Progress,
+    ) -> Result<(Vec<ReferenceName>, OwnershipRemap)> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Resetting a file requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
-        let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::DiscardFile),
-            guard.write_permission(),
+            .context("Updating base branch requires open workspace mode")?;
+        submodules::assure_submodules_unchanged(&ctx)?;
+        operation_journal::assure_none_pending(&ctx)?;
+        let mut guard = project.exclusive_operation_access("update_base_branch")?;
+        let snapshot = ctx
+            .project()
+            .create_snapshot(
+                SnapshotDetails::new(OperationKind::UpdateWorkspaceBase),
+                guard.write_permission(),
+            )
+            .ok()
+            .flatten();
+        let result = operation_journal::journaled(
+            &ctx,
+            ResumableOperation::UpdateBaseBranch { strategy },
+            snapshot,
+            |journal| {
+                let outcome = match snapshot {
+                    Some(_) => {
+                        update_base_branch(&ctx, strategy, guard.write_permission(), progress)?
+                    }
+                    None => progress::uncancellable(progress, |progress| {
+                        update_base_branch(&ctx, strategy, guard.write_permission(), progress)
+                    })?,
+                };
+                journal.step_completed();
+                Ok(outcome)
+            },
         );
-        branch::reset_files(&ctx, files).map_err(Into::into)
+        match (result, snapshot) {
+            (Err(err), Some(snapshot))
+                if err.custom_context().map(|ctx| ctx.code) == Some(Code::Cancelled) =>
+            {
+                // The restore takes the worktree itself, and has to complete despite the cancellation.
+                drop(guard);
+                cancellation::scoped(&CancellationToken::new(), || {
+                    project.restore_snapshot(snapshot)
+                })
+                .context("failed to roll back the cancelled update")?;
+                Err(err)
+            }
+            (result, _) => result,
+        }
     }
 
-    pub fn amend(
+    /// Base the branch identified by `branch_id` on the commit `base`, like a release tag, instead of the target.
+    /// Its commits are rebased onto `base`, and it stays there when the target is updated.
+    pub fn pin_branch_base(
         &self,
         project: &Project,
         branch_id: BranchId,
-        commit_oid: git2::Oid,
-        ownership: &BranchOwnershipClaims,
-    ) -> Result<git2::Oid> {
+        base: git2::Oid,
+    ) -> Result<()> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Amending a commit requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+            .context("Pinning the base of a branch requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("pin_branch_base")?;
         let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::AmendCommit),
+            SnapshotDetails::new(OperationKind::PinBranchBase),
             guard.write_permission(),
         );
-        branch::amend(&ctx, branch_id, commit_oid, ownership)
+        pinned_base::pin_base(&ctx, branch_id, base, guard.write_permission())
     }
 
-    pub fn move_commit_file(
-        &self,
-        project: &Project,
-        branch_id: BranchId,
-        from_commit_oid: git2::Oid,
-        to_commit_oid: git2::Oid,
-        ownership: &BranchOwnershipClaims,
-    ) -> Result<git2::Oid> {
+    /// Rebase the commits of the pinned branch identified by `branch_id` onto the target, and have it follow
+    /// the target again.
+    pub fn rebase_branch_onto_target(&self, project: &Project, branch_id: BranchId) -> Result<()> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Amending a commit requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+            .context("Rebasing a branch onto the target requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("rebase_branch_onto_target")?;
         let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::MoveCommitFile),
+            SnapshotDetails::new(OperationKind::RebaseBranchOntoTarget),
             guard.write_permission(),
         );
-        branch::move_commit_file(&ctx, branch_id, from_commit_oid, to_commit_oid, ownership)
-            .map_err(Into::into)
+        pinned_base::rebase_onto_target(&ctx, branch_id, guard.write_permission())
     }
 
-    pub fn undo_commit(
+    /// Stack the branch identified by `branch_id` on the branch identified by `parent_id`, so its commits are
+    /// kept on top of those of the parent. Use [`Self::rebase_branch_onto_target()`] to take it off again.
+    pub fn stack_branch(
         &self,
         project: &Project,
         branch_id: BranchId,
-        commit_oid: git2::Oid,
+        parent_id: BranchId,
     ) -> Result<()> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Undoing a commit requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
-        let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
-        let result: Result<()> =
-            branch::undo_commit(&ctx, branch_id, commit_oid).map_err(Into::into);
-        let _ = snapshot_tree.and_then(|snapshot_tree| {
-            ctx.project().snapshot_commit_undo(
-                snapshot_tree,
-                result.as_ref(),
-                commit_oid,
-                guard.write_permission(),
-            )
-        });
-        result
+            .context("Stacking a branch requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("stack_branch")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::StackBranch),
+            guard.write_permission(),
+        );
+        stack::stack_on(&ctx, branch_id, parent_id, guard.write_permission())
     }
 
-    pub fn insert_blank_commit(
+    /// Copy `commits`, oldest first, from remote branches or other virtual branches onto the applied branch
+    /// identified by `branch_id`. If one of them conflicts, it's left for resolution with the conflict API
+    /// and the ones after it aren't picked.
+    pub fn cherry_pick(
         &self,
         project: &Project,
         branch_id: BranchId,
-        commit_oid: git2::Oid,
-        offset: i32,
-    ) -> Result<()> {
+        commits: &[git2::Oid],
+    ) -> Result<CherryPickOutcome> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Inserting a blank commit requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+            .context("Cherry-picking commits requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("cherry_pick")?;
         let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::InsertBlankCommit),
+            SnapshotDetails::new(OperationKind::CherryPick),
             guard.write_permission(),
         );
-        branch::insert_blank_commit(&ctx, branch_id, commit_oid, offset).map_err(Into::into)
+        cherry_pick::cherry_pick(&ctx, branch_id, commits, guard.write_permission())
     }
 
-    pub fn reorder_commit(
+    /// Apply `patch`, a unified diff or a mailbox as `git format-patch` writes it, as commits onto the
+    /// applied branch identified by `branch_id`, keeping the authorship the emails carry.
+    pub fn import_patch(
         &self,
         project: &Project,
         branch_id: BranchId,
-        commit_oid: git2::Oid,
-        offset: i32,
-    ) -> Result<()> {
+        patch: &[u8],
+        options: &ImportPatchOptions,
+    ) -> Result<PatchImportOutcome> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Reordering a commit requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+            .context("Importing patches requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("import_patch")?;
         let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::ReorderCommit),
+            SnapshotDetails::new(OperationKind::ImportPatch),
             guard.write_permission(),
         );
-        branch::reorder_commit(&ctx, branch_id, commit_oid, offset).map_err(Into::into)
+        patch_import::import_patch(&ctx, branch_id, patch, options, guard.write_permission())
     }
 
-    pub fn reset_virtual_branch(
+    /// Commit the inverse of the commit identified by `commit_id` onto the applied branch identified by
+    /// `branch_id`. If that conflicts, the revert is left for resolution with the conflict API.
+    pub fn revert_commit(
         &self,
         project: &Project,
         branch_id: BranchId,
-        target_commit_oid: git2::Oid,
-    ) -> Result<()> {
+        commit_id: git2::Oid,
+    ) -> Result<RevertOutcome> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Resetting a branch requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+            .context("Reverting a commit requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("revert_commit")?;
         let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::UndoCommit),
+            SnapshotDetails::new(OperationKind::RevertCommit),
             guard.write_permission(),
         );
-        branch::reset_branch(&ctx, branch_id, target_commit_oid).map_err(Into::into)
+        revert::revert_commit(&ctx, branch_id, commit_id, guard.write_permission())
     }
 
-    pub fn convert_to_real_branch(
-        &self,
+    /// Return the template to start new commit messages with, if the project or the Git
+    /// configuration has one.
+    pub fn commit_template(&self, project: &Project) -> Result<Option<CommitTemplate>> {
+        let ctx = CommandContext::open(project)?;
+        commit_message::template(&ctx)
+    }
+
+    /// Fail with the rule of the commit conventions of the project that `message` violates, if any.
+    pub fn check_commit_message(&self, project: &Project, message: &str) -> Result<()> {
+        commit_message::check_conventions(&project.commit_conventions, message)
+    }
+
+    /// Return what committing the changes of the branch with `branch_id` with `message` would do, limited
+    /// to `ownership` if it's set, along with what's noteworthy about the message.
+    pub fn commit_preview(
+        &self,
         project: &Project,
         branch_id: BranchId,
-    ) -> Result<ReferenceName> {
+        message: &str,
+        ownership: Option<&BranchOwnershipClaims>,
+    ) -> Result<CommitPreview> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Previewing a commit requires open workspace mode")?;
+        commit_preview::commit_preview(&ctx, branch_id, message, ownership)
+    }
+
+    /// Generate a message for committing the uncommitted changes of the branch with `branch_id`, with the model
+    /// configured for the project.
+    pub fn generate_commit_message(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+    ) -> Result<String> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Generating a commit message requires open workspace mode")?;
+        message_generation::generate(&ctx, branch_id)
+    }
+
+    /// Store `api_key` to authenticate with the model that generates commit messages for `project`, or remove
+    /// the stored one if it's empty.
+    pub fn set_message_generation_api_key(&self, project: &Project, api_key: &str) -> Result<()> {
+        message_generation::set_api_key(project.id, api_key)
+    }
+
+    /// Return the metadata that automation keeps on the branch identified by `branch_id`, by key.
+    pub fn branch_metadata(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+    ) -> Result<BTreeMap<String, serde_json::Value>> {
+        let ctx = CommandContext::open(project)?;
+        branch_metadata::get(&ctx, branch_id)
+    }
+
+    /// Set the metadata `key` of the branch identified by `branch_id` to `value`, or remove it if
+    /// `value` is `None`.
+    pub fn set_branch_metadata(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        key: &str,
+        value: Option<&serde_json::Value>,
+    ) -> Result<()> {
+        let ctx = CommandContext::open(project)?;
+        let mut guard = project.exclusive_operation_access("set_branch_metadata")?;
+        branch_metadata::set(&ctx, branch_id, key, value, guard.write_permission())
+    }
+
+    /// Return `true` if the applied branch identified by `branch_id` builds on its base, which is
+    /// its pinned base or the target.
+    pub fn is_based_on_target(&self, project: &Project, branch_id: BranchId) -> Result<bool> {
+        let ctx = CommandContext::open(project)?;
+        integration::is_based_on_target(&ctx, branch_id)
+    }
+
+    /// Check the applied branches against the integration commit, and return how they diverge
+    /// from it, if at all.
+    pub fn verify_integration(&self, project: &Project) -> Result<Vec<IntegrationDivergence>> {
+        let ctx = CommandContext::open(project)?;
+        integration::verify_integration(&ctx)
+    }
+
+    /// Compare the integration commit and the worktree with what is recorded for the applied branches,
+    /// and return how they differ, if at all.
+    pub fn check_workspace(&self, project: &Project) -> Result<WorkspaceDesync> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Checking the workspace requires open workspace mode")?;
+        workspace_check::check_workspace(&ctx)
+    }
+
+    /// Look for changes that Git clients made to the references of the workspace, like by committing in a terminal,
+    /// and fold them into the applied branches if possible. What can't be folded is returned to recover from.
+    pub fn reconcile_external_changes(&self, project: &Project) -> Result<Reconciliation> {
+        let ctx = CommandContext::open(project)?;
+        let mut guard = project.exclusive_operation_access("reconcile_external_changes")?;
+        reconcile::reconcile(&ctx, guard.write_permission())
+    }
+
+    /// Recover from a change to the references of the workspace that couldn't be folded into the applied
+    /// branches, with `option`.
+    pub fn recover_from_external_change(
+        &self,
+        project: &Project,
+        option: RecoveryOption,
+    ) -> Result<()> {
+        let ctx = CommandContext::open(project)?;
+        let mut guard = project.exclusive_operation_access("recover_from_external_change")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::ReconcileExternalChanges),
+            guard.write_permission(),
+        );
+        reconcile::recover(&ctx, option, guard.write_permission())
+    }
+
+    pub fn update_virtual_branch(
+        &self,
+        project: &Project,
+        branch_update: BranchUpdateRequest,
+    ) -> Result<()> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Converting branch to a real branch requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+            .context("Updating a branch requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("update_virtual_branch")?;
         let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
+        let old_branch = ctx
+            .project()
+            .virtual_branches()
+            .get_branch_in_workspace(branch_update.id)?;
+        let result = branch::update_branch(&ctx, &branch_update);
+        let _ = snapshot_tree.and_then(|snapshot_tree| {
+            ctx.project().snapshot_branch_update(
+                snapshot_tree,
+                &old_branch,
+                &branch_update,
+                result.as_ref().err(),
+                guard.write_permission(),
+            )
+        });
+        result?;
+        Ok(())
+    }
+
+    pub fn update_branch_order(
+        &self,
+        project: &Project,
+        branch_updates: Vec<BranchUpdateRequest>,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Updating branch order requires open workspace mode")?;
+        for branch_update in branch_updates {
+            let branch = ctx
+                .project()
+                .virtual_branches()
+                .get_branch_in_workspace(branch_update.id)?;
+            if branch_update.order != Some(branch.order) {
+                branch::update_branch(&ctx, &branch_update)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn delete_virtual_branch(&self, project: &Project, branch_id: BranchId) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Deleting a branch order requires open workspace mode")?;
+        integration::assure_branch_integrated(&ctx, branch_id)?;
         let branch_manager = ctx.branch_manager();
-        let result = branch_manager.convert_to_real_branch(branch_id, guard.write_permission());
+        let mut guard = project.exclusive_operation_access("delete_virtual_branch")?;
+        let default_target = ctx.project().virtual_branches().get_default_target()?;
+        let target_commit = ctx.repository().find_commit(default_target.sha)?;
+        branch_manager.delete_branch(branch_id, guard.write_permission(), &target_commit)
+    }
+
+    pub fn unapply_ownership(
+        &self,
+        project: &Project,
+        ownership: &BranchOwnershipClaims,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx).context("Unapply a patch requires open workspace mode")?;
+        submodules::assure_submodules_unchanged(&ctx)?;
+        let mut guard = project.exclusive_operation_access("unapply_ownership")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::DiscardHunk),
+            guard.write_permission(),
+        );
+        let files: Vec<_> = ownership
+            .claims
+            .iter()
+            .map(|claim| (claim.file_path.clone(), None))
+            .collect();
+        trash::discard_into_trash(&ctx, &files, TrashOrigin::DiscardOwnership, || {
+            branch::unapply_ownership(&ctx, ownership, guard.write_permission())
+        })
+    }
+
+    /// Discard the uncommitted hunk identified by `hunk_id`, like `3-7` or `3-7-<hash>`, in the file at `path`,
+    /// relative to the worktree, leaving all other changes to the file alone. A snapshot is taken first, and
+    /// nothing is discarded if that fails.
+    pub fn discard_hunk(&self, project: &Project, path: &Path, hunk_id: &str) -> Result<()> {
+        self.discard(project, path, hunk_id, None)
+    }
+
+    /// Like [`discard_hunk()`](Self::discard_hunk()), but only discard the changes on `lines` of the hunk,
+    /// numbered from 1 and counted from the first line after the hunk header.
+    pub fn discard_lines(
+        &self,
+        project: &Project,
+        path: &Path,
+        hunk_id: &str,
+        lines: &RangeSet,
+    ) -> Result<()> {
+        self.discard(project, path, hunk_id, Some(lines))
+    }
+
+    fn discard(
+        &self,
+        project: &Project,
+        path: &Path,
+        hunk_id: &str,
+        lines: Option<&RangeSet>,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Discarding changes requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("discard")?;
+        ctx.project()
+            .create_snapshot(
+                SnapshotDetails::new(OperationKind::DiscardHunk),
+                guard.write_permission(),
+            )
+            .context("failed to take a snapshot before discarding")?;
+        let origin = match lines {
+            Some(_) => TrashOrigin::DiscardLines,
+            None => TrashOrigin::DiscardHunk,
+        };
+        trash::discard_into_trash(
+            &ctx,
+            &[(path.to_owned(), Some(hunk_id.to_owned()))],
+            origin,
+            || discard::discard_hunk(&ctx, path, hunk_id, lines, guard.write_permission()),
+        )
+    }
+
+    pub fn reset_files(&self, project: &Project, files: &Vec<String>) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Resetting a file requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("reset_files")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::DiscardFile),
+            guard.write_permission(),
+        );
+        let paths: Vec<_> = files
+            .iter()
+            .map(|file| (PathBuf::from(file), None))
+            .collect();
+        trash::discard_into_trash(&ctx, &paths, TrashOrigin::ResetFile, || {
+            branch::reset_files(&ctx, files)
+        })
+    }
+
+    /// Return what was discarded, most recent first.
+    pub fn list_trash(&self, project: &Project) -> Result<Vec<TrashEntry>> {
+        project.trash().list()
+    }
+
+    /// Bring back the changes of the trash entry identified by `id`, on top of the changes made to its file
+    /// since, and remove it from the trash.
+    pub fn restore_trash(&self, project: &Project, id: TrashEntryId) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Restoring from the trash requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("restore_trash")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::RestoreFromTrash),
+            guard.write_permission(),
+        );
+        trash::restore_trash(&ctx, id, guard.write_permission())
+    }
+
+    /// Add the changes of `ownership` to the commit `commit_oid`, with `rewrite_pushed` allowing it even
+    /// if the commit was pushed and the project [warns](gitbutler_project::PushedCommitRewrites::Warn) about it.
+    pub fn amend(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        commit_oid: git2::Oid,
+        ownership: &BranchOwnershipClaims,
+        rewrite_pushed: bool,
+    ) -> Result<git2::Oid> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Amending a commit requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("amend")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::AmendCommit),
+            guard.write_permission(),
+        );
+        branch::amend(&ctx, branch_id, commit_oid, ownership, rewrite_pushed)
+    }
+
+    /// Change the hunks, message or author of the commit `commit_oid` as described by `request`, rewriting
+    /// its descendants. `rewrite_pushed` allows it even if the commit was pushed and the project
+    /// [warns](gitbutler_project::PushedCommitRewrites::Warn) about it. Returns the id of the amended commit.
+    pub fn amend_commit(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        commit_oid: git2::Oid,
+        request: &AmendRequest,
+        rewrite_pushed: bool,
+    ) -> Result<git2::Oid> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Amending a commit requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("amend_commit")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::AmendCommit),
+            guard.write_permission(),
+        );
+        branch::amend_commit(&ctx, branch_id, commit_oid, request, rewrite_pushed)
+    }
+
+    /// Add the uncommitted hunk `hunk_id`, like `3-7`, of the file at `file_path` to the commit `commit_oid`,
+    /// rewriting its descendants. Returns the id of the rewritten commit.
+    pub fn move_hunk_to_commit(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        file_path: &Path,
+        hunk_id: &str,
+        commit_oid: git2::Oid,
+        rewrite_pushed: bool,
+    ) -> Result<git2::Oid> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Moving a hunk to a commit requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("move_hunk_to_commit")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::AmendCommit),
+            guard.write_permission(),
+        );
+        absorb::move_hunk_to_commit(
+            &ctx,
+            branch_id,
+            file_path,
+            hunk_id,
+            commit_oid,
+            rewrite_pushed,
+        )
+    }
+
+    /// Add each uncommitted hunk of the branch with `branch_id` to the most recent of its commits that last
+    /// touched its lines, and report the hunks that couldn't be absorbed.
+    pub fn absorb(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        rewrite_pushed: bool,
+    ) -> Result<AbsorbOutcome> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx).context("Absorbing hunks requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("absorb")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::AbsorbHunks),
+            guard.write_permission(),
+        );
+        absorb::absorb(&ctx, branch_id, rewrite_pushed)
+    }
+
+    pub fn move_commit_file(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        from_commit_oid: git2::Oid,
+        to_commit_oid: git2::Oid,
+        ownership: &BranchOwnershipClaims,
+    ) -> Result<git2::Oid> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Amending a commit requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("move_commit_file")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::MoveCommitFile),
+            guard.write_permission(),
+        );
+        branch::move_commit_file(&ctx, branch_id, from_commit_oid, to_commit_oid, ownership)
+            .map_err(Into::into)
+    }
+
+    pub fn undo_commit(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        commit_oid: git2::Oid,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Undoing a commit requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("undo_commit")?;
+        let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
+        let result: Result<()> =
+            branch::undo_commit(&ctx, branch_id, commit_oid).map_err(Into::into);
+        let _ = snapshot_tree.and_then(|snapshot_tree| {
+            ctx.project().snapshot_commit_undo(
+                snapshot_tree,
+                result.as_ref(),
+                commit_oid,
+                guard.write_permission(),
+            )
+        });
+        result
+    }
+
+    pub fn insert_blank_commit(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        commit_oid: git2::Oid,
+        offset: i32,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Inserting a blank commit requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("insert_blank_commit")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::InsertBlankCommit),
+            guard.write_permission(),
+        );
+        branch::insert_blank_commit(&ctx, branch_id, commit_oid, offset).map_err(Into::into)
+    }
+
+    pub fn reorder_commit(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        commit_oid: git2::Oid,
+        offset: i32,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Reordering a commit requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("reorder_commit")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::ReorderCommit),
+            guard.write_permission(),
+        );
+        branch::reorder_commit(&ctx, branch_id, commit_oid, offset).map_err(Into::into)
+    }
+
+    /// Rewrite the commits of a branch to be in `new_order`, from head to base, unless that leads to conflicts.
+    pub fn reorder_commits(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        new_order: &[git2::Oid],
+    ) -> Result<branch::ReorderOutcome> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Reordering commits requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("reorder_commits")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::ReorderCommit),
+            guard.write_permission(),
+        );
+        branch::reorder_commits(&ctx, branch_id, new_order)
+    }
+
+    pub fn reset_virtual_branch(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        target_commit_oid: git2::Oid,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Resetting a branch requires open workspace mode")?;
+        integration::assure_branch_integrated(&ctx, branch_id)?;
+        let mut guard = project.exclusive_operation_access("reset_virtual_branch")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::UndoCommit),
+            guard.write_permission(),
+        );
+        branch::reset_branch(&ctx, branch_id, target_commit_oid).map_err(Into::into)
+    }
+
+    pub fn convert_to_real_branch(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+    ) -> Result<ReferenceName> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Converting branch to a real branch requires open workspace mode")?;
+        submodules::assure_submodules_unchanged(&ctx)?;
+        let mut guard = project.exclusive_operation_access("convert_to_real_branch")?;
+        let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
+        let branch_manager = ctx.branch_manager();
+        let result = branch_manager.convert_to_real_branch(branch_id, guard.write_permission());
+
+        let _ = snapshot_tree.and_then(|snapshot_tree| {
+            ctx.project().snapshot_branch_unapplied(
+                snapshot_tree,
+                result.as_ref(),
+                guard.write_permission(),
+            )
+        });
+
+        result
+    }
+
+    pub fn push_virtual_branch(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        with_force: bool,
+        askpass: Option<Option<BranchId>>,
+    ) -> Result<()> {
+        self.push_virtual_branch_with_progress(
+            project,
+            branch_id,
+            with_force,
+            false,
+            false,
+            askpass,
+            &NoProgress,
+        )
+    }
+
+    /// Force-push the virtual branch with `branch_id`, but only if its upstream branch didn't change
+    /// since it was fetched last, so commits pushed by others in the meantime aren't dropped.
+    pub fn push_virtual_branch_with_lease(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        askpass: Option<Option<BranchId>>,
+    ) -> Result<()> {
+        self.push_virtual_branch_with_progress(
+            project,
+            branch_id,
+            true,
+            true,
+            false,
+            askpass,
+            &NoProgress,
+        )
+    }
+
+    /// Push the virtual branch with `branch_id` like [`Self::push_virtual_branch()`] does, or like
+    /// [`Self::push_virtual_branch_with_lease()`] if `with_lease` is `true`, and report how far the transfer
+    /// got to `progress`. Once cancelled, nothing more is sent.
+    ///
+    /// If the project checks for secrets, pushing fails with [`SecretsDetected`](crate::SecretsDetected) if the
+    /// commits to send contain any, unless `allow_secrets` is `true`.
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip_all, fields(operation = "push", project_id = %project.id, %branch_id, with_force, with_lease), err(Debug))]
+    pub fn push_virtual_branch_with_progress(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        with_force: bool,
+        with_lease: bool,
+        allow_secrets: bool,
+        askpass: Option<Option<BranchId>>,
+        progress: &dyn Progress,
+    ) -> Result<()> {
+        let helper = Helper::default();
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Pushing a branch requires open workspace mode")?;
+        branch::push(
+            &ctx,
+            branch_id,
+            with_force,
+            if with_lease {
+                branch::Lease::LastFetched
+            } else {
+                branch::Lease::None
+            },
+            allow_secrets,
+            &helper,
+            askpass,
+            progress,
+        )
+    }
+
+    /// Push the virtual branch with `branch_id` like [`Self::push_virtual_branch()`] does, but if the network
+    /// is unavailable, queue the push to run once it's back and return it instead of failing.
+    pub fn push_virtual_branch_or_queue(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        with_force: bool,
+        askpass: Option<Option<BranchId>>,
+    ) -> Result<Option<QueuedOperation>> {
+        match self.push_virtual_branch(project, branch_id, with_force, askpass) {
+            Ok(()) => Ok(None),
+            Err(err) if offline::is_offline(&err) => {
+                let ctx = CommandContext::open(project)?;
+                offline::enqueue(
+                    project,
+                    QueuedOperationKind::Push {
+                        branch_id,
+                        with_force,
+                        upstream_head: branch::last_fetched_upstream_head(&ctx, branch_id)?,
+                    },
+                )
+                .map(Some)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Return the operations of `project` that wait for the network to be available again, in the order
+    /// they will run in.
+    pub fn list_queued_operations(&self, project: &Project) -> Result<Vec<QueuedOperation>> {
+        project.operation_queue().list()
+    }
+
+    /// Remove the operation identified by `id` from the queue of `project` so it won't run, and return it.
+    pub fn cancel_queued_operation(
+        &self,
+        project: &Project,
+        id: QueuedOperationId,
+    ) -> Result<QueuedOperation> {
+        project.operation_queue().remove(id)
+    }
+
+    /// Run the queued operations of `project` in order, and return how each one that ran went. Stops once the
+    /// network turns out to be still unavailable, keeping the operations that didn't run. Pushes run without
+    /// asking for credentials.
+    ///
+    /// This happens by itself once fetching finds the network available again.
+    pub fn replay_queued_operations(&self, project: &Project) -> Result<Vec<ReplayedOperation>> {
+        offline::replay(project, |kind| match kind {
+            QueuedOperationKind::Push {
+                branch_id,
+                with_force,
+                upstream_head,
+            } => self.push_queued(project, branch_id, with_force, upstream_head),
+            QueuedOperationKind::Fetch => {
+                offline::fetch_outcome(&self.fetch_all_remotes(project, None, &NoProgress)?)
+            }
+        })
+    }
+
+    /// Run a push that was queued while offline, without asking for credentials. A forced push only replaces
+    /// the upstream branch if it's still at `upstream_head`, where it was when the push was queued.
+    fn push_queued(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        with_force: bool,
+        upstream_head: Option<git2::Oid>,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Pushing a branch requires open workspace mode")?;
+        branch::push(
+            &ctx,
+            branch_id,
+            with_force,
+            branch::Lease::At(upstream_head),
+            false,
+            &Helper::default(),
+            None,
+            &NoProgress,
+        )
+    }
+
+    /// Tell how many commits and how much data pushing the virtual branch with `branch_id` would upload.
+    pub fn push_preview(&self, project: &Project, branch_id: BranchId) -> Result<PushPreview> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Previewing a push requires open workspace mode")?;
+        push_preview::push_preview(&ctx, branch_id)
+    }
+
+    /// Fail with the exact violation if `name` doesn't follow the policy of the project for the names of
+    /// remote branches.
+    pub fn validate_remote_branch_name(&self, project: &Project, name: &str) -> Result<()> {
+        let ctx = CommandContext::open(project)?;
+        remote_branch_name::validate(&ctx, name)
+    }
+
+    /// Check the push of the virtual branch with `branch_id` against the rules of the branch it goes to,
+    /// as known to the forge, which is authenticated with `github_token` if it's GitHub, or as configured in Git.
+    pub fn check_push_protection(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        github_token: Option<&str>,
+    ) -> Result<PushProtectionCheck> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx).context("Checking a push requires open workspace mode")?;
+        push_protection::check_push_protection(&ctx, branch_id, github_token)
+    }
+
+    /// Configure the upstream branches of all pushed virtual branches in Git where it's missing, and
+    /// return the names of the branches that were configured.
+    pub fn repair_upstream_config(&self, project: &Project) -> Result<Vec<String>> {
+        let ctx = open_with_verify(project)?;
+        tracking::repair_upstream_config(&ctx)
+    }
+
+    /// Create a tag named `name` for the commit `target`, annotated with `message` if it's set. Annotated tags
+    /// are signed like commits if `sign` is set.
+    pub fn create_tag(
+        &self,
+        project: &Project,
+        name: &str,
+        target: git2::Oid,
+        message: Option<&str>,
+        sign: bool,
+    ) -> Result<Tag> {
+        let ctx = CommandContext::open(project)?;
+        tags::create_tag(&ctx, name, target, message, sign)
+    }
+
+    /// Return all tags along with the commits they point to, the ones with the newest commits first.
+    pub fn list_tags(&self, project: &Project) -> Result<Vec<Tag>> {
+        let ctx = CommandContext::open(project)?;
+        tags::list_tags(&ctx)
+    }
+
+    /// Push the tag named `name` to `remote`, or to the remote the target is pushed to if it's `None`.
+    pub fn push_tag(
+        &self,
+        project: &Project,
+        name: &str,
+        remote: Option<&str>,
+        askpass: Option<Option<BranchId>>,
+    ) -> Result<()> {
+        let ctx = CommandContext::open(project)?;
+        tags::push_tag(&ctx, name, remote, askpass)
+    }
+
+    pub fn list_remote_branches(project: Project) -> Result<Vec<RemoteBranch>> {
+        let ctx = CommandContext::open(&project)?;
+        list_remote_branches(&ctx)
+    }
+
+    /// List all remote branches with how far they are ahead and behind the target, and who changed
+    /// them last and when.
+    pub fn list_remote_branch_activity(
+        &self,
+        project: &Project,
+    ) -> Result<Vec<RemoteBranchActivity>> {
+        let ctx = CommandContext::open(project)?;
+        remote_activity::list_remote_branch_activity(&ctx)
+    }
+
+    /// Return who commits of `project` are created by and how they are signed.
+    pub fn identity(&self, project: &Project) -> Result<Identity> {
+        let ctx = CommandContext::open(project)?;
+        identity::identity(&ctx)
+    }
+
+    /// Set the name and email commits of `project` are authored with in the configuration of its repository, and
+    /// return the identity it has then. `None` leaves a value as it is, and an empty value removes it so the one
+    /// of the user applies again.
+    pub fn set_identity(
+        &self,
+        project: &Project,
+        name: Option<&str>,
+        email: Option<&str>,
+    ) -> Result<Identity> {
+        let ctx = CommandContext::open(project)?;
+        identity::set_identity(&ctx, name, email)
+    }
+
+    pub fn get_remote_branch_data(
+        &self,
+        project: &Project,
+        refname: &Refname,
+    ) -> Result<RemoteBranchData> {
+        let ctx = CommandContext::open(project)?;
+        get_branch_data(&ctx, refname)
+    }
+
+    /// Squash the commit `commit_oid` into its parent, with `rewrite_pushed` allowing it even if the
+    /// parent was pushed and the project [warns](gitbutler_project::PushedCommitRewrites::Warn) about it.
+    pub fn squash(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        commit_oid: git2::Oid,
+        rewrite_pushed: bool,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Squashing a commit requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("squash")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::SquashCommit),
+            guard.write_permission(),
+        );
+        branch::squash(&ctx, branch_id, commit_oid, rewrite_pushed).map_err(Into::into)
+    }
+
+    pub fn squash_commits(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        commits: &[git2::Oid],
+        into: git2::Oid,
+        new_message: Option<&str>,
+    ) -> Result<git2::Oid> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Squashing commits requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("squash_commits")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::SquashCommit),
+            guard.write_permission(),
+        );
+        branch::squash_commits(&ctx, branch_id, commits, into, new_message)
+    }
+
+    pub fn split_commit(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        commit_oid: git2::Oid,
+        groups: &[BranchOwnershipClaims],
+    ) -> Result<git2::Oid> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Splitting a commit requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("split_commit")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::SplitCommit),
+            guard.write_permission(),
+        );
+        branch::split_commit(&ctx, branch_id, commit_oid, groups)
+    }
+
+    /// Reword the commit `commit_oid`, with `rewrite_pushed` allowing it even if the commit was pushed
+    /// and the project [warns](gitbutler_project::PushedCommitRewrites::Warn) about it.
+    pub fn update_commit_message(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        commit_oid: git2::Oid,
+        message: &str,
+        rewrite_pushed: bool,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Updating a commit message requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("update_commit_message")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::UpdateCommitMessage),
+            guard.write_permission(),
+        );
+        branch::update_commit_message(&ctx, branch_id, commit_oid, message, rewrite_pushed)
+            .map_err(Into::into)
+    }
+
+    /// Fix the times of the commits of the branch with `branch_id` that are [skewed](crate::ClockSkew),
+    /// with `rewrite_pushed` allowing it even if they were pushed and the project
+    /// [warns](gitbutler_project::PushedCommitRewrites::Warn) about it. Returns the new head of the branch.
+    pub fn normalize_commit_times(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        rewrite_pushed: bool,
+    ) -> Result<git2::Oid> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Normalizing commit times requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("normalize_commit_times")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::NormalizeCommitTimes),
+            guard.write_permission(),
+        );
+        clock_skew::normalize_commit_times(&ctx, branch_id, rewrite_pushed)
+    }
+
+    /// Fetch all remotes of `project` concurrently, and report the outcome for each of them.
+    pub fn fetch_from_remotes(
+        &self,
+        project: &Project,
+        askpass: Option<String>,
+    ) -> Result<FetchReport> {
+        self.fetch_from_remotes_with_progress(project, askpass, &NoProgress)
+    }
+
+    /// Like [`Self::fetch_from_remotes()`], but report how far the fetch of each remote got to `progress`.
+    /// Once cancelled, the remotes that weren't fetched yet fail with [`Code::Cancelled`].
+    pub fn fetch_from_remotes_with_progress(
+        &self,
+        project: &Project,
+        askpass: Option<String>,
+        progress: &dyn Progress,
+    ) -> Result<FetchReport> {
+        let report = self.fetch_all_remotes(project, askpass, progress)?;
+        if offline::is_offline_report(&report) {
+            offline::enqueue(project, QueuedOperationKind::Fetch)?;
+        } else if offline::is_online_report(&report)
+            && !project.operation_queue().list()?.is_empty()
+        {
+            // The network is back, so what was put off runs now, except for fetching which just happened.
+            let replayed = offline::replay(project, |kind| match kind {
+                QueuedOperationKind::Push {
+                    branch_id,
+                    with_force,
+                    upstream_head,
+                } => self.push_queued(project, branch_id, with_force, upstream_head),
+                QueuedOperationKind::Fetch => Ok(()),
+            });
+            match replayed {
+                Ok(replayed) => tracing::info!(?replayed, "ran operations queued while offline"),
+                Err(err) => tracing::warn!(?err, "failed to run operations queued while offline"),
+            }
+        }
+        Ok(report)
+    }
+
+    fn fetch_all_remotes(
+        &self,
+        project: &Project,
+        askpass: Option<String>,
+        progress: &dyn Progress,
+    ) -> Result<FetchReport> {
+        let ctx = CommandContext::open(project)?;
+        let remotes = ctx.repository().remotes_as_string()?;
+        fetch_remotes(
+            &ctx,
+            &remotes,
+            askpass,
+            project.settings.fetch_schedule.max_concurrent_fetches,
+            progress,
+        )
+    }
+
+    /// Return `true` if the repository of `project` is a shallow clone, whose history ends before the
+    /// first commit.
+    pub fn is_shallow_clone(&self, project: &Project) -> Result<bool> {
+        let ctx = CommandContext::open(project)?;
+        Ok(ctx.repository().is_shallow())
+    }
+
+    /// Fetch the history of the shallow clone of `project` further back from `remote`, or from the remote of
+    /// the target if `None`, as `deepen` says, and report how far it got to `progress`.
+    ///
+    /// Does nothing if the repository isn't a shallow clone.
+    pub fn deepen_history(
+        &self,
+        project: &Project,
+        remote: Option<&str>,
+        deepen: Deepen,
+        askpass: Option<String>,
+        progress: &dyn Progress,
+    ) -> Result<()> {
+        if deepen == Deepen::By(0) {
+            return Err(anyhow::anyhow!(
+                "the history has to be deepened by at least one commit"
+            ))
+            .context(Code::Validation);
+        }
+        let ctx = CommandContext::open(project)?;
+        let remote = match remote {
+            Some(remote) => remote.to_owned(),
+            None => project
+                .virtual_branches()
+                .get_default_target()?
+                .branch
+                .remote()
+                .to_owned(),
+        };
+        shallow::deepen(&ctx, &remote, deepen, askpass, progress)
+    }
+
+    pub fn move_commit(
+        &self,
+        project: &Project,
+        target_branch_id: BranchId,
+        commit_oid: git2::Oid,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx).context("Moving a commit requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("move_commit")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::MoveCommit),
+            guard.write_permission(),
+        );
+        branch::move_commit(&ctx, target_branch_id, commit_oid).map_err(Into::into)
+    }
+
+    /// Move the uncommitted hunks claimed by `ownership` onto a new shelf named `name`, removing them from the worktree.
+    pub fn shelve_changes(
+        &self,
+        project: &Project,
+        name: &str,
+        ownership: &BranchOwnershipClaims,
+    ) -> Result<ShelfId> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Shelving changes requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("shelve_changes")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::ShelveChanges),
+            guard.write_permission(),
+        );
+        shelf::shelve(&ctx, name, ownership, guard.write_permission())
+    }
+
+    /// Apply the changes on the shelf identified by `shelf_id` to the worktree, and assign them to
+    /// the branch identified by `branch_id` if set. The shelf is removed afterwards.
+    pub fn unshelve_changes(
+        &self,
+        project: &Project,
+        shelf_id: ShelfId,
+        branch_id: Option<BranchId>,
+    ) -> Result<()> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Unshelving changes requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("unshelve_changes")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::UnshelveChanges),
+            guard.write_permission(),
+        );
+        shelf::unshelve(&ctx, shelf_id, branch_id, guard.write_permission())
+    }
+
+    /// Return all shelves along with their changes, most recent first.
+    pub fn list_shelves(&self, project: &Project) -> Result<Vec<Shelf>> {
+        project.shelves().list()
+    }
+
+    /// Drop the shelf identified by `shelf_id` along with its changes.
+    pub fn delete_shelf(&self, project: &Project, shelf_id: ShelfId) -> Result<()> {
+        project.shelves().remove(shelf_id).map(|_| ())
+    }
+
+    /// Return the entries of `git stash list`, most recent first.
+    pub fn list_stashes(&self, project: &Project) -> Result<Vec<StashEntry>> {
+        let ctx = CommandContext::open(project)?;
+        stash::list_stashes(&ctx)
+    }
+
+    /// Return all submodules of the project, and whether they are new or changed compared to the workspace.
+    pub fn list_submodules(&self, project: &Project) -> Result<Vec<Submodule>> {
+        let ctx = CommandContext::open(project)?;
+        submodules::list_submodules(&ctx)
+    }
+
+    /// Return the Git repositories within the worktree that aren't submodules, and whose files are
+    /// thus left out of the workspace.
+    pub fn list_nested_repositories(&self, project: &Project) -> Result<Vec<NestedRepository>> {
+        let ctx = CommandContext::open(project)?;
+        submodules::list_nested_repositories(&ctx)
+    }
+
+    /// Return the files whose line endings in the worktree differ from those they would be checked out
+    /// with, as told by `.gitattributes` and `core.autocrlf`, and which would thus show every line as changed.
+    pub fn list_eol_changes(&self, project: &Project) -> Result<Vec<EolChange>> {
+        let ctx = CommandContext::open(project)?;
+        gitbutler_diff::filter::eol_changes(ctx.repository())
+    }
+
+    /// Create a new virtual branch from the changes of the stash entry at `index`, committed or
+    /// uncommitted depending on `import`, and return its id. The stash entry is kept.
+    pub fn import_stash(
+        &self,
+        project: &Project,
+        index: usize,
+        import: StashImport,
+    ) -> Result<BranchId> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Importing a stash requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("import_stash")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::ImportStash),
+            guard.write_permission(),
+        );
+        stash::import_stash(&ctx, index, import, guard.write_permission())
+    }
+
+    /// Bring the changes of the unapplied `branch` that are selected by `selection` into the workspace,
+    /// as uncommitted changes of a new virtual branch whose id is returned. `branch` itself is left as is.
+    ///
+    /// A claim without hunks selects all changes of its file.
+    pub fn apply_branch_partially(
+        &self,
+        project: &Project,
+        branch: &Refname,
+        selection: &BranchOwnershipClaims,
+    ) -> Result<BranchId> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Applying a branch partially requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("apply_branch_partially")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::ApplyBranchPartially),
+            guard.write_permission(),
+        );
+        partial_apply::apply_partially(&ctx, branch, selection, guard.write_permission())
+    }
+
+    /// Bring the files at `paths` back as they were in the snapshot `snapshot_commit_id`, as uncommitted
+    /// changes of a new virtual branch named `name` whose id is returned. The rest of the workspace is left
+    /// as is.
+    pub fn create_virtual_branch_from_snapshot(
+        &self,
+        project: &Project,
+        snapshot_commit_id: git2::Oid,
+        paths: &[PathBuf],
+        name: Option<String>,
+    ) -> Result<BranchId> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Creating a branch from a snapshot requires open workspace mode")?;
+        let mut guard =
+            project.exclusive_operation_access("create_virtual_branch_from_snapshot")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::CreateBranchFromSnapshot),
+            guard.write_permission(),
+        );
+        snapshot_branch::create_virtual_branch_from_snapshot(
+            &ctx,
+            snapshot_commit_id,
+            paths,
+            name,
+            guard.write_permission(),
+        )
+    }
+
+    /// Return where the commit `commit_id` came from, or `None` if it wasn't created in the workspace
+    /// or was rewritten since.
+    pub fn commit_provenance(
+        &self,
+        project: &Project,
+        commit_id: git2::Oid,
+    ) -> Result<Option<CommitProvenance>> {
+        project.commit_provenance().get(commit_id)
+    }
+
+    /// Group the uncommitted hunks of the branch identified by `branch_id` by the kind of file they are in,
+    /// like tests or docs.
+    pub fn hunk_groups(&self, project: &Project, branch_id: BranchId) -> Result<Vec<HunkGroup>> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx).context("Grouping hunks requires open workspace mode")?;
+        hunk_groups::group_hunks(&ctx, branch_id)
+    }
 
-        let _ = snapshot_tree.and_then(|snapshot_tree| {
-            ctx.project().snapshot_branch_unapplied(
-                snapshot_tree,
-                result.as_ref(),
-                guard.write_permission(),
-            )
-        });
+    /// Return the uncommitted hunks of all applied branches that match `query`, written in the
+    /// [query language](HunkQuery), to be passed along when moving or committing them.
+    pub fn select_hunks(&self, project: &Project, query: &str) -> Result<BranchOwnershipClaims> {
+        let query: HunkQuery = query.parse()?;
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx).context("Selecting hunks requires open workspace mode")?;
+        hunk_query::select_hunks(&ctx, &query)
+    }
 
-        result
+    /// Check if each of `paths`, relative to the worktree, is ignored, along with the rule that decided it.
+    pub fn check_ignored(&self, project: &Project, paths: &[PathBuf]) -> Result<Vec<IgnoreCheck>> {
+        let ctx = CommandContext::open(project)?;
+        ignores::check_ignored(&ctx, paths)
     }
 
-    pub fn push_virtual_branch(
+    /// Append `patterns` to `file` unless it has them already, and return the ones that were added.
+    pub fn add_ignore_patterns(
         &self,
         project: &Project,
-        branch_id: BranchId,
-        with_force: bool,
-        askpass: Option<Option<BranchId>>,
-    ) -> Result<()> {
-        let helper = Helper::default();
-        let ctx = open_with_verify(project)?;
+        file: IgnoreFile,
+        patterns: &[String],
+    ) -> Result<Vec<String>> {
+        let ctx = CommandContext::open(project)?;
+        let _guard = project.exclusive_operation_access("add_ignore_patterns")?;
+        ignores::add_ignore_patterns(&ctx, file, patterns)
+    }
+
+    /// Return the uncommitted changes to the file at `path`, relative to the worktree, along with the
+    /// branch they belong to, or `None` if the file has no changes.
+    pub fn file_status(&self, project: &Project, path: &Path) -> Result<Option<FileStatus>> {
+        let ctx = CommandContext::open(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Pushing a branch requires open workspace mode")?;
-        branch::push(&ctx, branch_id, with_force, &helper, askpass)
+            .context("Getting the status of a file requires open workspace mode")?;
+        status::file_status(&ctx, path)
     }
 
-    pub fn list_remote_branches(project: Project) -> Result<Vec<RemoteBranch>> {
-        let ctx = CommandContext::open(&project)?;
-        list_remote_branches(&ctx)
+    /// Return where each line of the file at `path`, relative to the worktree, comes from, which is the commit
+    /// that last changed it and the branch that commit is on, or the branch that owns it if it's uncommitted.
+    pub fn blame(&self, project: &Project, path: &Path) -> Result<Vec<BlameHunk>> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx).context("Blaming a file requires open workspace mode")?;
+        blame::blame(&ctx, path)
     }
 
-    pub fn get_remote_branch_data(
+    /// Return which uncommitted changes each applied branch owns.
+    pub fn workspace_ownership(&self, project: &Project) -> Result<WorkspaceOwnership> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Getting the ownership of changes requires open workspace mode")?;
+        status::workspace_ownership(&ctx)
+    }
+
+    /// Start recording why each file is part of the computed status and how long computing it took if
+    /// `enabled` is `true`, or stop it and drop all traces otherwise.
+    pub fn set_status_tracing(&self, project: &Project, enabled: bool) {
+        status_trace::set_enabled(project.id, enabled);
+    }
+
+    /// Return the traces of the latest status computations, oldest first, if tracing is enabled.
+    pub fn status_traces(&self, project: &Project) -> Vec<StatusTrace> {
+        status_trace::traces(project.id)
+    }
+
+    /// Return the latest runs of the automations of `project`, oldest first. Automations run in the
+    /// background, so runs appear once they finished.
+    pub fn automation_runs(&self, project: &Project) -> Vec<AutomationRun> {
+        automation::runs(project.id)
+    }
+
+    /// Return the hunks of `ownership` whose files other applied branches than the one identified by
+    /// `branch_id` claim changes in, so assigning them to it doesn't silently take them away.
+    pub fn ownership_conflicts(
         &self,
         project: &Project,
-        refname: &Refname,
-    ) -> Result<RemoteBranchData> {
+        branch_id: BranchId,
+        ownership: &BranchOwnershipClaims,
+    ) -> Result<Vec<OwnershipConflict>> {
         let ctx = CommandContext::open(project)?;
-        get_branch_data(&ctx, refname)
+        assure_open_workspace_mode(&ctx)
+            .context("Finding ownership conflicts requires open workspace mode")?;
+        ownership_conflicts::ownership_conflicts(&ctx, branch_id, ownership)
     }
 
-    pub fn squash(
+    /// Assign the hunks of `ownership` to the branch identified by `branch_id` only if no other applied
+    /// branch claims changes in their files, and return the conflicts otherwise.
+    pub fn claim_ownership_exclusively(
         &self,
         project: &Project,
         branch_id: BranchId,
-        commit_oid: git2::Oid,
-    ) -> Result<()> {
+        ownership: &BranchOwnershipClaims,
+    ) -> Result<Vec<OwnershipConflict>> {
         let ctx = open_with_verify(project)?;
-        assure_open_workspace_mode(&ctx)
-            .context("Squashing a commit requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+        assure_open_workspace_mode(&ctx).context("Assigning hunks requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("claim_ownership_exclusively")?;
         let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::SquashCommit),
+            SnapshotDetails::new(OperationKind::MoveHunk),
             guard.write_permission(),
         );
-        branch::squash(&ctx, branch_id, commit_oid).map_err(Into::into)
+        ownership_conflicts::claim_exclusively(&ctx, branch_id, ownership, guard.write_permission())
     }
 
-    pub fn update_commit_message(
+    /// Open a pull request for the pushed branch identified by `branch_id` into the target branch,
+    /// authenticated with `github_token`.
+    pub fn create_pull_request(
         &self,
         project: &Project,
         branch_id: BranchId,
-        commit_oid: git2::Oid,
-        message: &str,
+        pull_request: &NewPullRequest,
+        github_token: Option<&str>,
+    ) -> Result<PullRequest> {
+        let ctx = CommandContext::open(project)?;
+        forge::create_pull_request(&ctx, branch_id, pull_request, github_token)
+    }
+
+    /// Return the description a pull request of the branch identified by `branch_id` would get,
+    /// which is its draft description or the pull request template of the repository.
+    pub fn pull_request_description(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+    ) -> Result<String> {
+        let branch = project.virtual_branches().get_branch(branch_id)?;
+        forge::pull_request_description(project, &branch)
+    }
+
+    /// Fetch the current state of the pull request of the branch identified by `branch_id`,
+    /// or return `None` if it has none.
+    pub fn refresh_pull_request(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        github_token: Option<&str>,
+    ) -> Result<Option<PullRequest>> {
+        let ctx = CommandContext::open(project)?;
+        forge::refresh_pull_request(&ctx, branch_id, github_token)
+    }
+
+    /// Have the forge merge the pull request of the branch identified by `branch_id` as `auto_merge` says
+    /// once its checks pass, like by adding it to the merge queue.
+    pub fn enable_auto_merge(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        auto_merge: AutoMerge,
+        github_token: Option<&str>,
+    ) -> Result<PullRequest> {
+        let ctx = CommandContext::open(project)?;
+        forge::enable_auto_merge(&ctx, branch_id, auto_merge, github_token)
+    }
+
+    /// Fetch the state of the pull requests of applied branches the forge was asked to merge, and return
+    /// those that changed. Branches whose pull request was merged are removed with the next base update
+    /// that brings in the merge.
+    pub fn refresh_auto_merges(
+        &self,
+        project: &Project,
+        github_token: Option<&str>,
+    ) -> Result<Vec<PullRequest>> {
+        let ctx = CommandContext::open(project)?;
+        forge::refresh_auto_merges(&ctx, github_token)
+    }
+
+    /// Fetch how CI is doing for the pushed head of each virtual branch where what's known is stale,
+    /// and return it by the name of the branch as it was pushed. The result also shows in the branch listing.
+    pub fn refresh_ci_statuses(
+        &self,
+        project: &Project,
+        github_token: Option<&str>,
+    ) -> Result<BTreeMap<String, CiStatus>> {
+        let ctx = CommandContext::open(project)?;
+        forge::refresh_ci_statuses(&ctx, github_token)
+    }
+
+    /// Return the kind of forge pull requests of `project` are opened on, as set in the project or
+    /// as detected from the remote of the target.
+    pub fn forge_kind(&self, project: &Project) -> Result<ForgeKind> {
+        forge::forge_kind(project)
+    }
+
+    /// Store `token` to authenticate with the forge at `host`, like `gitlab.com`,
+    /// or remove the stored one if it's empty.
+    pub fn set_forge_access_token(&self, host: &str, token: &str) -> Result<()> {
+        forge::set_access_token(host, token)
+    }
+
+    /// Attach `note` to the uncommitted `hunk` of the file at `file_path`, or remove its note if `note` is `None`.
+    ///
+    /// Notes are shown along with the hunk until it's committed or discarded.
+    pub fn set_hunk_note(
+        &self,
+        project: &Project,
+        file_path: &Path,
+        hunk: &Hunk,
+        note: Option<String>,
+    ) -> Result<()> {
+        project.hunk_notes().set(file_path, hunk, note)
+    }
+
+    /// Pin the uncommitted `hunk` of the file at `file_path` to the applied branch with `branch_id`, so it's
+    /// assigned to it from now on, and can't be moved to another branch until it's unpinned.
+    pub fn pin_hunk(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        file_path: &Path,
+        hunk: &Hunk,
     ) -> Result<()> {
+        project
+            .virtual_branches()
+            .get_branch_in_workspace(branch_id)?;
+        project.hunk_pins().pin(file_path, hunk, branch_id)
+    }
+
+    /// Unpin the uncommitted `hunk` of the file at `file_path`, returning `true` if it was pinned.
+    pub fn unpin_hunk(&self, project: &Project, file_path: &Path, hunk: &Hunk) -> Result<bool> {
+        project.hunk_pins().unpin(file_path, hunk)
+    }
+
+    /// Return the hunks that are pinned to a branch.
+    pub fn list_hunk_pins(&self, project: &Project) -> Result<Vec<HunkPin>> {
+        project.hunk_pins().list()
+    }
+
+    /// Return the activity of the branch identified by `branch_id` that was recorded after the event at `cursor`,
+    /// or all retained activity if `cursor` is `None`.
+    pub fn branch_events_since(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        cursor: Option<u64>,
+    ) -> Result<Vec<BranchEvent>> {
+        project.branch_activity().events_since(branch_id, cursor)
+    }
+
+    /// Return the entries of the audit log of the project that match `query`, oldest first.
+    pub fn audit_log(&self, project: &Project, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+        project.audit_log().query(query)
+    }
+
+    /// Search all `projects` for the commit `query` is the id of, branches whose name contains it, and changes
+    /// of virtual branches adding lines that contain it.
+    pub fn search_projects(&self, projects: &[Project], query: &str) -> Result<Vec<SearchMatch>> {
+        project_search::search_projects(projects, query)
+    }
+
+    /// Lay out the graph of at most `limit` commits of the applied branches, their upstream branches and the
+    /// target, from the most recent one.
+    pub fn commit_graph(&self, project: &Project, limit: usize) -> Result<CommitGraph> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Laying out the commit graph requires open workspace mode")?;
+        commit_graph::commit_graph(&ctx, limit)
+    }
+
+    /// Return a page of at most `limit` commits of the remote branch `reference`, like the target branch or
+    /// the upstream of a virtual branch, that match `filter`, starting after `cursor` as returned with the
+    /// previous page.
+    pub fn log(
+        &self,
+        project: &Project,
+        reference: &RemoteRefname,
+        cursor: Option<&str>,
+        limit: usize,
+        filter: &HistoryFilter,
+    ) -> Result<HistoryPage> {
+        let ctx = CommandContext::open(project)?;
+        history::log(&ctx, reference, cursor, limit, filter)
+    }
+
+    /// Return the at most `limit` most recent commits of the target and the applied branches that changed
+    /// the file at `path`, relative to the worktree, following it across renames.
+    pub fn file_history(
+        &self,
+        project: &Project,
+        path: &Path,
+        limit: usize,
+    ) -> Result<Vec<FileHistoryEntry>> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Listing the history of a file requires open workspace mode")?;
+        file_history::file_history(&ctx, path, limit)
+    }
+
+    /// Return at most `limit` lines matching the regular expression `pattern` in the files of the worktree
+    /// and in the lines added by the commits of the applied branches, telling which branch each comes from.
+    pub fn search_content(
+        &self,
+        project: &Project,
+        pattern: &str,
+        limit: usize,
+    ) -> Result<Vec<ContentMatch>> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Searching the content of the workspace requires open workspace mode")?;
+        content_search::search_content(&ctx, pattern, limit)
+    }
+
+    /// Find the pairs of applied branches whose commits change overlapping lines, and would thus conflict
+    /// once one of them is merged.
+    pub fn predict_conflicts(&self, project: &Project) -> Result<Vec<PredictedConflict>> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Predicting conflicts requires open workspace mode")?;
+        conflict_prediction::predict_conflicts(&ctx)
+    }
+
+    /// Simulate merging the applied branches into the target one after the other in at most `limit` orders,
+    /// to find the orders in which they merge without conflicts.
+    pub fn simulate_merge_orders(
+        &self,
+        project: &Project,
+        limit: usize,
+    ) -> Result<MergeOrderSimulation> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Simulating merge orders requires open workspace mode")?;
+        merge_order::simulate_merge_orders(&ctx, limit)
+    }
+
+    /// Find the applied branches with uncommitted changes that build on the commits of other applied
+    /// branches, so they can't be unapplied independently.
+    pub fn branch_dependencies(&self, project: &Project) -> Result<Vec<BranchDependency>> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Finding branch dependencies requires open workspace mode")?;
+        branch_dependencies::branch_dependencies(&ctx)
+    }
+
+    /// Return which part of the worktree is checked out.
+    pub fn partial_checkout(&self, project: &Project) -> Result<PartialCheckout> {
+        let ctx = CommandContext::open(project)?;
+        partial_checkout::partial_checkout(&ctx)
+    }
+
+    /// Check out only `directories` and those with uncommitted changes, so only these are diffed.
+    pub fn enable_partial_checkout(
+        &self,
+        project: &Project,
+        directories: &[PathBuf],
+    ) -> Result<PartialCheckout> {
         let ctx = open_with_verify(project)?;
         assure_open_workspace_mode(&ctx)
-            .context("Updating a commit message requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+            .context("Enabling a partial checkout requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("enable_partial_checkout")?;
+        partial_checkout::enable(&ctx, directories, guard.write_permission())
+    }
+
+    /// Check out `paths` in addition to what is checked out already.
+    pub fn hydrate_partial_checkout(
+        &self,
+        project: &Project,
+        paths: &[PathBuf],
+    ) -> Result<PartialCheckout> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Checking out more files requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("hydrate_partial_checkout")?;
+        partial_checkout::hydrate(&ctx, paths, guard.write_permission())
+    }
+
+    /// Check out the whole worktree again.
+    pub fn disable_partial_checkout(&self, project: &Project) -> Result<PartialCheckout> {
+        let ctx = open_with_verify(project)?;
+        let mut guard = project.exclusive_operation_access("disable_partial_checkout")?;
+        partial_checkout::disable(&ctx, guard.write_permission())
+    }
+
+    /// Find what previous runs that crashed or were killed left behind in the repository.
+    pub fn list_leftovers(&self, project: &Project) -> Result<Vec<Leftover>> {
+        let ctx = CommandContext::open(project)?;
+        leftovers::find(&ctx)
+    }
+
+    /// Remove what previous runs left behind in the repository, and return what it was.
+    pub fn remove_leftovers(&self, project: &Project) -> Result<Vec<Leftover>> {
+        let ctx = CommandContext::open(project)?;
+        let mut guard = project.exclusive_operation_access("remove_leftovers")?;
+        leftovers::remove(&ctx, guard.write_permission())
+    }
+
+    /// Check the stored state of the virtual branches against the repository, and repair what can be
+    /// if `repair` is set. This works even if the state can't be verified, as it's meant to fix it.
+    pub fn fsck(&self, project: &Project, repair: bool) -> Result<FsckReport> {
+        let ctx = CommandContext::open(project)?;
+        let _guard = project.exclusive_operation_access("fsck")?;
+        gitbutler_branch::fsck(&project.virtual_branches(), ctx.repository(), repair)
+    }
+
+    /// Turn the applied virtual branches into local branches, keeping their uncommitted changes as
+    /// `uncommitted` says, and leave the workspace for plain Git with one of them checked out.
+    pub fn export_to_git(
+        &self,
+        project: &Project,
+        uncommitted: ExportUncommitted,
+    ) -> Result<ExportOutcome> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Exporting to plain Git requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("export_to_git")?;
         let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::UpdateCommitMessage),
+            SnapshotDetails::new(OperationKind::ExportToGit),
             guard.write_permission(),
         );
-        branch::update_commit_message(&ctx, branch_id, commit_oid, message).map_err(Into::into)
+        export::export(&ctx, uncommitted, guard.write_permission())
     }
 
-    pub fn fetch_from_remotes(
+    /// Write the commits of the virtual branch identified by `branch_id` as patches into `dir`, as `git
+    /// format-patch` would, with its description as cover letter, and return the paths of the written files.
+    pub fn export_patches(
         &self,
         project: &Project,
-        askpass: Option<String>,
-    ) -> Result<FetchResult> {
+        branch_id: BranchId,
+        dir: &Path,
+        format: PatchFormat,
+    ) -> Result<Vec<PathBuf>> {
         let ctx = CommandContext::open(project)?;
+        patches::export_patches(&ctx, branch_id, dir, format)
+    }
 
-        let helper = Helper::default();
-        let remotes = ctx.repository().remotes_as_string()?;
-        let fetch_errors: Vec<_> = remotes
-            .iter()
-            .filter_map(|remote| {
-                ctx.fetch(remote, &helper, askpass.clone())
-                    .err()
-                    .map(|err| err.to_string())
-            })
-            .collect();
+    /// Turn every virtual branch into a local branch, check out `onto` or the branch selected for changes
+    /// with all uncommitted changes, and remove everything GitButler stored in the repository.
+    /// Nothing is snapshotted, as the operations log is removed as well.
+    pub fn eject(&self, project: &Project, onto: Option<BranchId>) -> Result<ExportOutcome> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx).context("Ejecting requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("eject")?;
+        export::eject(&ctx, onto, guard.write_permission())
+    }
 
-        let timestamp = std::time::SystemTime::now();
-        let project_data_last_fetched = if fetch_errors.is_empty() {
-            FetchResult::Fetched { timestamp }
-        } else {
-            FetchResult::Error {
-                timestamp,
-                error: fetch_errors.join("\n"),
-            }
-        };
+    /// Set up the target and lanes as declared in the layout file at `path`, relative to the worktree,
+    /// or in `gitbutler.toml` if `None`.
+    pub fn apply_layout(&self, project: &Project, path: Option<&Path>) -> Result<LayoutOutcome> {
+        let ctx = CommandContext::open(project)?;
+        let mut guard = project.exclusive_operation_access("apply_layout")?;
+        let _ = ctx.project().create_snapshot(
+            SnapshotDetails::new(OperationKind::ApplyLayout),
+            guard.write_permission(),
+        );
+        layout::apply_layout(&ctx, path, guard.write_permission())
+    }
 
-        Ok(project_data_last_fetched)
+    /// List the files that are still conflicting, or an empty list if there is no conflict to resolve.
+    pub fn list_conflicted_files(&self, project: &Project) -> Result<Vec<ConflictedFile>> {
+        let ctx = CommandContext::open(project)?;
+        ConflictSession::open(&ctx).map_or(Ok(Vec::new()), |session| session.files())
     }
 
-    pub fn move_commit(
+    pub fn conflicted_file_blob(
         &self,
         project: &Project,
-        target_branch_id: BranchId,
-        commit_oid: git2::Oid,
+        path: &Path,
+        side: ConflictSide,
+    ) -> Result<Option<Vec<u8>>> {
+        let ctx = CommandContext::open(project)?;
+        let session = ConflictSession::open(&ctx).context("there are no conflicts to resolve")?;
+        session.blob(path, side)
+    }
+
+    pub fn resolve_conflict(
+        &self,
+        project: &Project,
+        path: &Path,
+        resolution: Resolution,
     ) -> Result<()> {
-        let ctx = open_with_verify(project)?;
-        assure_open_workspace_mode(&ctx).context("Moving a commit requires open workspace mode")?;
-        let mut guard = project.exclusive_worktree_access();
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Resolving a conflict requires open workspace mode")?;
+        let session = ConflictSession::open(&ctx).context("there are no conflicts to resolve")?;
+        let mut guard = project.exclusive_operation_access("resolve_conflict")?;
         let _ = ctx.project().create_snapshot(
-            SnapshotDetails::new(OperationKind::MoveCommit),
+            SnapshotDetails::new(OperationKind::ResolveConflict),
             guard.write_permission(),
         );
-        branch::move_commit(&ctx, target_branch_id, commit_oid).map_err(Into::into)
+        session.resolve(path, resolution)
+    }
+
+    pub fn finalize_conflict_resolution(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+        message: &str,
+    ) -> Result<git2::Oid> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Finalizing a conflict resolution requires open workspace mode")?;
+        let session = ConflictSession::open(&ctx).context("there are no conflicts to resolve")?;
+        let mut guard = project.exclusive_operation_access("finalize_conflict_resolution")?;
+        let snapshot_tree = ctx.project().prepare_snapshot(guard.read_permission());
+        let result = session.finalize(branch_id, message);
+        let snapshot = snapshot_tree.and_then(|snapshot_tree| {
+            ctx.project().snapshot_commit_creation(
+                snapshot_tree,
+                result.as_ref().err(),
+                message.to_owned(),
+                None,
+                guard.write_permission(),
+            )
+        });
+        if let (Ok(commit_id), Ok(Some(snapshot_id))) = (&result, snapshot) {
+            attach_snapshot_to_provenance(project, *commit_id, snapshot_id);
+        }
+        result
+    }
+
+    /// Return the files of the worktree that applying the branch `branch` refers to would overwrite or delete,
+    /// without applying it.
+    pub fn preview_apply_branch(
+        &self,
+        project: &Project,
+        branch: &Refname,
+    ) -> Result<CheckoutPreview> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Previewing applying a branch requires open workspace mode")?;
+        checkout_preview::preview_apply_branch(&ctx, branch)
+    }
+
+    /// Return the files of the worktree that unapplying the branch with `branch_id` would overwrite or delete,
+    /// without unapplying it.
+    pub fn preview_unapply_branch(
+        &self,
+        project: &Project,
+        branch_id: BranchId,
+    ) -> Result<CheckoutPreview> {
+        let ctx = CommandContext::open(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Previewing unapplying a branch requires open workspace mode")?;
+        checkout_preview::preview_unapply_branch(&ctx, branch_id)
     }
 
+    #[instrument(skip_all, fields(operation = "apply", project_id = %project.id, %branch), err(Debug))]
     pub fn create_virtual_branch_from_branch(
         &self,
         project: &Project,
@@ -534,16 +2471,49 @@ impl VirtualBranchActions {
         assure_open_workspace_mode(&ctx)
             .context("Creating a virtual branch from a branch open workspace mode")?;
         let branch_manager = ctx.branch_manager();
-        let mut guard = project.exclusive_worktree_access();
+        let mut guard = project.exclusive_operation_access("create_virtual_branch_from_branch")?;
         branch_manager
             .create_virtual_branch_from_branch(branch, remote, guard.write_permission())
             .map_err(Into::into)
     }
+
+    /// Turn the local branch named `name` that has commits which aren't on the target into an applied
+    /// virtual branch, and return its id.
+    pub fn adopt_branch(&self, project: &Project, name: &str) -> Result<BranchId> {
+        let ctx = open_with_verify(project)?;
+        assure_open_workspace_mode(&ctx)
+            .context("Adopting a local branch requires open workspace mode")?;
+        let mut guard = project.exclusive_operation_access("adopt_branch")?;
+        adopt::adopt_branch(&ctx, name, guard.write_permission())
+    }
 }
 
 fn open_with_verify(project: &Project) -> Result<CommandContext> {
     let ctx = CommandContext::open(project)?;
-    let mut guard = project.exclusive_worktree_access();
+    gitbutler_repo::permissions::probe_writable(&ctx)?;
+    verify(project, ctx)
+}
+
+/// Like [`open_with_verify()`], but for operations that don't write to the worktree or the Git
+/// directory, so their permissions aren't checked.
+fn open_for_reading_with_verify(project: &Project) -> Result<CommandContext> {
+    verify(project, CommandContext::open(project)?)
+}
+
+fn verify(project: &Project, ctx: CommandContext) -> Result<CommandContext> {
+    gitbutler_repo::repo_state::ensure_none_in_progress(ctx.repository())?;
+    let mut guard = project.exclusive_operation_access("verify_branch")?;
     crate::integration::verify_branch(&ctx, guard.write_permission())?;
     Ok(ctx)
 }
+
+/// Remember that `snapshot_id` holds the workspace `commit_id` was created from.
+/// Failures are only logged as the commit was created already.
+fn attach_snapshot_to_provenance(project: &Project, commit_id: git2::Oid, snapshot_id: git2::Oid) {
+    if let Err(err) = project
+        .commit_provenance()
+        .set_snapshot(commit_id, snapshot_id)
+    {
+        tracing::warn!(%commit_id, ?err, "failed to attach snapshot to commit provenance");
+    }
+}
@@ -0,0 +1,67 @@
+//! Adopt local branches that exist only in the repository as virtual branches, so work that was done outside of
+//! GitButler, or before it was used, isn't stranded.
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_operating_modes::INTEGRATION_BRANCH_REF;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_reference::Refname;
+
+use crate::{branch_manager::BranchManagerExt, VirtualBranchesExt};
+
+/// Turn the local branch named `name`, like `my-feature`, into an applied virtual branch and return its id.
+///
+/// The commits of the branch that aren't on the target yet are kept, and rebased or merged onto the target if
+/// the branch is behind it. The branch owns the changes of its commits just like a branch applied from a remote.
+/// It fails if the branch has no commits that aren't on the target. If it conflicts with the workspace, it's
+/// left unapplied.
+pub(crate) fn adopt_branch(
+    ctx: &CommandContext,
+    name: &str,
+    perm: &mut WorktreeWritePermission,
+) -> Result<BranchId> {
+    let repo = ctx.repository();
+    let local = repo
+        .find_branch(name, git2::BranchType::Local)
+        .map_err(|err| match err.code() {
+            git2::ErrorCode::NotFound => {
+                anyhow!("there is no local branch named '{name}'").context(Code::Validation)
+            }
+            _ => err.into(),
+        })?;
+    let refname = Refname::try_from(&local)?;
+    if refname.to_string() == INTEGRATION_BRANCH_REF {
+        return Err(anyhow!("the workspace branch can't be adopted")).context(Code::Validation);
+    }
+
+    let vb_state = ctx.project().virtual_branches();
+    if let Some(branch) = vb_state
+        .list_branches_in_workspace()?
+        .into_iter()
+        .find(|branch| branch.source_refname.as_ref() == Some(&refname))
+    {
+        return Err(anyhow!(
+            "'{name}' is in the workspace already as '{}'",
+            branch.name
+        ))
+        .context(Code::Validation);
+    }
+
+    let head = local
+        .get()
+        .peel_to_commit()
+        .context("failed to peel branch to commit")?;
+    let target = vb_state.get_default_target()?;
+    let merge_base = repo.merge_base(target.sha, head.id())?;
+    if merge_base == head.id() {
+        return Err(anyhow!(
+            "'{name}' has no commits that aren't on {} already",
+            target.branch
+        ))
+        .context(Code::Validation);
+    }
+
+    ctx.branch_manager()
+        .create_virtual_branch_from_branch(&refname, None, perm)
+}
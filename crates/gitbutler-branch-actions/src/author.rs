@@ -26,3 +26,21 @@ impl From<git2::Signature<'_>> for Author {
         }
     }
 }
+
+/// Return the mailmap of `repo`, as configured and found in `.mailmap`, or `None` if it can't be read.
+pub(crate) fn mailmap(repo: &git2::Repository) -> Option<git2::Mailmap> {
+    repo.mailmap()
+        .map_err(|err| tracing::warn!(?err, "failed to read mailmap"))
+        .ok()
+}
+
+/// Return `signature` with the name and email `mailmap` maps it to, if any.
+pub(crate) fn resolve<'a>(
+    mailmap: Option<&git2::Mailmap>,
+    signature: git2::Signature<'a>,
+) -> git2::Signature<'a> {
+    match mailmap.map(|mailmap| mailmap.resolve_signature(&signature)) {
+        Some(Ok(resolved)) => resolved,
+        _ => signature,
+    }
+}
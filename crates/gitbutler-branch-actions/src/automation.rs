@@ -0,0 +1,191 @@
+//! Run the commands that are configured as [automations](gitbutler_project::Automation) of a project when
+//! events happen in it, like to trigger a build or notify a chat.
+//!
+//! Commands run in the background so they don't hold up the operation that caused the event, and receive
+//! the event as JSON on stdin. What they wrote and how they ended is kept in memory for the most recent runs.
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::Path,
+    sync::Mutex,
+    thread,
+    time::Instant,
+};
+
+use gitbutler_branch::BranchId;
+use gitbutler_project::{Automation, AutomationTrigger, Project, ProjectId};
+use gitbutler_repo::hooks;
+use gitbutler_time::time::{now_ms, now_since_unix_epoch_ms};
+use serde::Serialize;
+
+/// The amount of runs kept per project, with older ones being dropped.
+const MAX_RUNS: usize = 100;
+
+static RUNS: Mutex<BTreeMap<ProjectId, VecDeque<AutomationRun>>> = Mutex::new(BTreeMap::new());
+
+/// An event that automations run on, which is passed to their command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "event")]
+pub enum AutomationEvent {
+    #[serde(rename_all = "camelCase")]
+    BranchApplied {
+        branch_id: BranchId,
+        branch_name: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    CommitCreated {
+        branch_id: BranchId,
+        branch_name: String,
+        #[serde(with = "gitbutler_serde::oid")]
+        commit: git2::Oid,
+    },
+    /// The branch was pushed with `head` to `remote`, a remote tracking reference like `refs/remotes/origin/feature`.
+    #[serde(rename_all = "camelCase")]
+    PushCompleted {
+        branch_id: BranchId,
+        branch_name: String,
+        #[serde(with = "gitbutler_serde::oid")]
+        head: git2::Oid,
+        remote: String,
+    },
+    /// The workspace was updated from `previous_base` to `base` of `target_branch`, which unapplied
+    /// `unapplied_branches` as they conflicted with it.
+    #[serde(rename_all = "camelCase")]
+    BaseUpdated {
+        target_branch: String,
+        #[serde(with = "gitbutler_serde::oid")]
+        previous_base: git2::Oid,
+        #[serde(with = "gitbutler_serde::oid")]
+        base: git2::Oid,
+        unapplied_branches: Vec<String>,
+    },
+}
+
+impl AutomationEvent {
+    /// Return the trigger of the automations that run on this event.
+    pub fn trigger(&self) -> AutomationTrigger {
+        match self {
+            AutomationEvent::BranchApplied { .. } => AutomationTrigger::BranchApplied,
+            AutomationEvent::CommitCreated { .. } => AutomationTrigger::CommitCreated,
+            AutomationEvent::PushCompleted { .. } => AutomationTrigger::PushCompleted,
+            AutomationEvent::BaseUpdated { .. } => AutomationTrigger::BaseUpdated,
+        }
+    }
+}
+
+/// What is written to the stdin of commands.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Payload<'a> {
+    project_id: ProjectId,
+    project_path: &'a Path,
+    /// The time of the event in milliseconds since the Unix epoch.
+    timestamp_ms: i64,
+    #[serde(flatten)]
+    event: &'a AutomationEvent,
+}
+
+/// A run of the command of an automation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationRun {
+    /// The name of the automation.
+    pub automation: String,
+    pub event: AutomationEvent,
+    /// When the command was started, in milliseconds since the Unix epoch.
+    pub started_at: u128,
+    pub duration_ms: u64,
+    /// The exit code, which is `None` if the command couldn't be started, timed out or was killed by a signal.
+    pub exit_code: Option<i32>,
+    /// Whether the command was killed as it ran for longer than its timeout.
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+    /// Why the command couldn't be run, if it couldn't.
+    pub error: Option<String>,
+}
+
+/// Run the automations of `project` that run on `event` in the background.
+pub(crate) fn trigger(project: &Project, event: AutomationEvent) {
+    let automations: Vec<Automation> = project
+        .settings
+        .automations
+        .iter()
+        .filter(|automation| automation.runs_on(event.trigger()))
+        .cloned()
+        .collect();
+    if automations.is_empty() {
+        return;
+    }
+    let payload = match serde_json::to_vec(&Payload {
+        project_id: project.id,
+        project_path: &project.path,
+        timestamp_ms: now_since_unix_epoch_ms(),
+        event: &event,
+    }) {
+        Ok(payload) => payload,
+        Err(err) => {
+            tracing::warn!(?err, "failed to serialize automation event");
+            return;
+        }
+    };
+    for automation in automations {
+        let project = project.clone();
+        let event = event.clone();
+        let payload = payload.clone();
+        thread::spawn(move || run(&project, &automation, event, &payload));
+    }
+}
+
+/// Return the runs kept for the project with `project_id`, oldest first.
+pub(crate) fn runs(project_id: ProjectId) -> Vec<AutomationRun> {
+    RUNS.lock()
+        .expect("no panics while holding the lock")
+        .get(&project_id)
+        .map(|runs| runs.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn run(project: &Project, automation: &Automation, event: AutomationEvent, payload: &[u8]) {
+    let started_at = now_ms();
+    let started = Instant::now();
+    let output =
+        hooks::run_script_with_output(project, &automation.command, payload, automation.timeout());
+    let mut run = AutomationRun {
+        automation: automation.name.clone(),
+        event,
+        started_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+        exit_code: None,
+        timed_out: false,
+        stdout: String::new(),
+        stderr: String::new(),
+        error: None,
+    };
+    match output {
+        Ok(output) => {
+            if !output.success() {
+                tracing::warn!(
+                    automation = %automation.name,
+                    exit_code = ?output.exit_code,
+                    timed_out = output.timed_out,
+                    "automation failed"
+                );
+            }
+            run.exit_code = output.exit_code;
+            run.timed_out = output.timed_out;
+            run.stdout = output.stdout;
+            run.stderr = output.stderr;
+        }
+        Err(err) => {
+            tracing::warn!(automation = %automation.name, ?err, "failed to run automation");
+            run.error = Some(format!("{err:#}"));
+        }
+    }
+
+    let mut runs = RUNS.lock().expect("no panics while holding the lock");
+    let runs = runs.entry(project.id).or_default();
+    if runs.len() == MAX_RUNS {
+        runs.pop_front();
+    }
+    runs.push_back(run);
+}
@@ -3,21 +3,33 @@ use std::{path::Path, time};
 use anyhow::{anyhow, Context, Result};
 use git2::Index;
 use gitbutler_branch::{
-    self, Branch, BranchId, BranchOwnershipClaims, Target, VirtualBranchesHandle,
+    self, Branch, BranchEventKind, BranchId, BranchOwnershipClaims, Target, VirtualBranchesHandle,
     GITBUTLER_INTEGRATION_REFERENCE,
 };
-use gitbutler_command_context::CommandContext;
+use gitbutler_command_context::{cancellation, CancellationToken, CommandContext};
 use gitbutler_error::error::Marker;
 use gitbutler_project::{access::WorktreeWritePermission, FetchResult};
 use gitbutler_reference::{ReferenceName, Refname, RemoteRefname};
-use gitbutler_repo::{rebase::cherry_rebase, LogUntil, RepoActionsExt, RepositoryExt};
-use serde::Serialize;
+use gitbutler_repo::{
+    hooks::{self, Hook},
+    merge_drivers,
+    progress::{NoProgress, Progress, Task},
+    rebase::{cherry_rebase, cherry_rebase_group},
+    shallow, LogUntil, RepoActionsExt, RepositoryExt,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    author,
+    automation::{self, AutomationEvent},
     branch_manager::BranchManagerExt,
     conflicts::RepoConflictsExt,
     hunk::VirtualBranchHunk,
     integration::update_gitbutler_integration,
+    linear_history,
+    ownership_remap::{ownership_remap, OwnershipRemap},
+    patch_ids::PatchIdIndex,
+    r#virtual::record_branch_event,
     remote::{commit_to_remote_commit, RemoteCommit},
     status::get_applied_status,
     VirtualBranchesExt,
@@ -41,6 +53,23 @@ pub struct BaseBranch {
     pub last_fetched_ms: Option<u128>,
 }
 
+/// How the commits of applied branches are brought onto the target when it advances.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BaseUpdateStrategy {
+    /// Rebase the commits of branches onto the new target, leaving out the ones it has merged. Branches that
+    /// were pushed and may not be force-pushed, or whose commits don't rebase cleanly, get the new target
+    /// merged into them instead.
+    #[default]
+    Rebase,
+    /// Like [`Rebase`](Self::Rebase), but also leave out commits that the target has with other ids, like when
+    /// it rebased or squashed them, which would otherwise end up in the branch twice.
+    DropIntegrated,
+    /// Merge the new target into every branch with commits, which keeps their commits and merge commits as
+    /// they are.
+    Merge,
+}
+
 pub(crate) fn get_base_branch_data(ctx: &CommandContext) -> Result<BaseBranch> {
     let target = default_target(&ctx.project().gb_dir())?;
     let base = target_to_base_branch(ctx, &target)?;
@@ -151,13 +180,18 @@ pub(crate) fn set_base_branch(
         .context("Failed to peel HEAD reference to commit")?;
 
     // calculate the commit as the merge-base between HEAD in ctx and this target commit
-    let target_commit_oid = repo
-        .merge_base(current_head_commit.id(), target_branch_head.id())
-        .context(format!(
-            "Failed to calculate merge base between {} and {}",
-            current_head_commit.id(),
-            target_branch_head.id()
-        ))?;
+    let target_commit_oid = shallow::merge_base(
+        ctx,
+        target_branch_ref.remote(),
+        current_head_commit.id(),
+        target_branch_head.id(),
+        &NoProgress,
+    )
+    .context(format!(
+        "Failed to calculate merge base between {} and {}",
+        current_head_commit.id(),
+        target_branch_head.id()
+    ))?;
 
     let target = Target {
         branch: target_branch_ref.clone(),
@@ -251,6 +285,14 @@ pub(crate) fn set_base_branch(
                 applied: true,
                 in_workspace: true,
                 not_in_workspace_wip_change_id: None,
+                pinned_base: None,
+                parent: None,
+                push_remote_name: None,
+                allowed_paths: Vec::new(),
+                metadata: Default::default(),
+                description: String::new(),
+                reviewers: Vec::new(),
+                forge_merge: None,
             };
 
             vb_state.set_branch(branch)?;
@@ -319,10 +361,20 @@ fn _print_tree(repo: &git2::Repository, tree: &git2::Tree) -> Result<()> {
 // determine if what the target branch is now pointing to is mergeable with our current working directory
 // merge the target branch into our current working directory
 // update the target sha
+/// Update the workspace to the latest commit of the target, bringing the commits of branches onto it with
+/// `strategy`, and return the names of the branches that had to be unapplied as they conflict with it, along
+/// with how the hunks of the remaining branches were remapped.
+///
+/// Each branch brought onto the new target is a step as far as `progress` is concerned. The update can be
+/// cancelled before each branch and before the worktree is checked out, which fails with `Code::Cancelled`
+/// and leaves the workspace partially updated, so callers have to roll back to the state before. Once the
+/// worktree is changed, the update completes.
 pub(crate) fn update_base_branch(
     ctx: &CommandContext,
+    strategy: BaseUpdateStrategy,
     perm: &mut WorktreeWritePermission,
-) -> anyhow::Result<Vec<ReferenceName>> {
+    progress: &dyn Progress,
+) -> anyhow::Result<(Vec<ReferenceName>, OwnershipRemap)> {
     ctx.assure_resolved()?;
 
     // look up the target and see if there is a new oid
@@ -341,7 +393,7 @@ pub(crate) fn update_base_branch(
     let mut unapplied_branch_names: Vec<ReferenceName> = Vec::new();
 
     if new_target_commit.id() == target.sha {
-        return Ok(unapplied_branch_names);
+        return Ok((unapplied_branch_names, OwnershipRemap::default()));
     }
 
     let new_target_tree = new_target_commit
@@ -354,15 +406,40 @@ pub(crate) fn update_base_branch(
     ))?;
 
     let vb_state = ctx.project().virtual_branches();
+    let patch_ids = match strategy {
+        BaseUpdateStrategy::DropIntegrated => {
+            Some(PatchIdIndex::new(repo, new_target_commit.id(), target.sha)?)
+        }
+        BaseUpdateStrategy::Rebase | BaseUpdateStrategy::Merge => None,
+    };
 
     // try to update every branch
-    let updated_vbranches = get_applied_status(ctx, None)?
-        .branches
-        .into_iter()
-        .map(|(branch, _)| branch)
+    let status_before = get_applied_status(ctx, None)?.branches;
+    let task = Task::new(progress, "Updating workspace");
+    task.set_total(status_before.len() as u64);
+    let updated_vbranches = status_before
+        .iter()
+        .map(|(branch, _)| branch.clone())
         .map(|mut branch: Branch| -> Result<Option<Branch>> {
+            task.check_cancelled()?;
             let branch_tree = repo.find_tree(branch.tree)?;
 
+            if let Some(pinned_base) = branch.pinned_base {
+                // the branch stays on its pinned base, it just has to merge cleanly with the new target
+                let pinned_tree = repo.find_commit(pinned_base)?.tree()?;
+                let merge_index =
+                    merge_drivers::merge_trees(repo, &pinned_tree, &new_target_tree, &branch_tree)
+                        .context(format!("failed to merge trees for branch {}", branch.id))?;
+                if merge_index.has_conflicts() {
+                    let branch_manager = ctx.branch_manager();
+                    let unapplied_real_branch =
+                        branch_manager.convert_to_real_branch(branch.id, perm)?;
+                    unapplied_branch_names.push(unapplied_real_branch);
+                    return Ok(None);
+                }
+                return Ok(Some(branch));
+            }
+
             let branch_head_commit = repo.find_commit(branch.head).context(format!(
                 "failed to find commit {} for branch {}",
                 branch.head, branch.id
@@ -401,10 +478,24 @@ pub(crate) fn update_base_branch(
                 return result_integrated_detected(branch);
             }
 
+            // the pull request of the branch was merged on the forge, and the new target has the merge.
+            // the target may have changed it since, so only take it as integrated if the branch didn't change either.
+            let merged_on_forge = branch.forge_merge.is_some_and(|merge| {
+                merge.head == branch.head
+                    && branch_tree.id() == branch_head_tree.id()
+                    && (merge.merge_commit == new_target_commit.id()
+                        || repo
+                            .graph_descendant_of(new_target_commit.id(), merge.merge_commit)
+                            .unwrap_or(false))
+            });
+            if merged_on_forge {
+                return result_integrated_detected(branch);
+            }
+
             // try to merge branch head with new target
-            let mut branch_tree_merge_index = repo
-                .merge_trees(&old_target_tree, &branch_tree, &new_target_tree, None)
-                .context(format!("failed to merge trees for branch {}", branch.id))?;
+            let mut branch_tree_merge_index =
+                merge_drivers::merge_trees(repo, &old_target_tree, &branch_tree, &new_target_tree)
+                    .context(format!("failed to merge trees for branch {}", branch.id))?;
 
             if branch_tree_merge_index.has_conflicts() {
                 // branch tree conflicts with new target, unapply branch for now. we'll handle it later, when user applies it back.
@@ -432,12 +523,16 @@ pub(crate) fn update_base_branch(
                 return Ok(Some(branch));
             }
 
-            let mut branch_head_merge_index = repo
-                .merge_trees(&old_target_tree, &branch_head_tree, &new_target_tree, None)
-                .context(format!(
-                    "failed to merge head tree for branch {}",
-                    branch.id
-                ))?;
+            let mut branch_head_merge_index = merge_drivers::merge_trees(
+                repo,
+                &old_target_tree,
+                &branch_head_tree,
+                &new_target_tree,
+            )
+            .context(format!(
+                "failed to merge head tree for branch {}",
+                branch.id
+            ))?;
 
             if branch_head_merge_index.has_conflicts() {
                 // branch commits conflict with new target, make sure the branch is
@@ -463,6 +558,15 @@ pub(crate) fn update_base_branch(
             let result_merge = |mut branch: Branch| -> Result<Option<Branch>> {
                 // branch was pushed to upstream, and user doesn't like force pushing.
                 // create a merge commit to avoid the need of force pushing then.
+                linear_history::ensure_merge_allowed(
+                    ctx,
+                    &format!(
+                        "merge {}/{} into {}",
+                        target.branch.remote(),
+                        target.branch.branch(),
+                        branch.name,
+                    ),
+                )?;
                 let branch_head_merge_tree = repo
                     .find_tree(branch_head_merge_tree_oid)
                     .context("failed to find tree")?;
@@ -485,20 +589,34 @@ pub(crate) fn update_base_branch(
                 branch.head = new_target_head;
                 branch.tree = branch_merge_index_tree_oid;
                 vb_state.set_branch(branch.clone())?;
+                record_branch_event(
+                    ctx,
+                    branch.id,
+                    BranchEventKind::CommitCreated {
+                        commit: new_target_head,
+                    },
+                );
                 Ok(Some(branch))
             };
 
-            if branch.upstream.is_some() && !ok_with_force_push {
+            if strategy == BaseUpdateStrategy::Merge
+                || (branch.upstream.is_some() && !ok_with_force_push)
+            {
                 return result_merge(branch);
             }
 
             // branch was not pushed to upstream yet. attempt a rebase,
-            let rebased_head_oid = cherry_rebase(
-                ctx,
-                new_target_commit.id(),
-                new_target_commit.id(),
-                branch.head,
-            );
+            let rebased_head_oid = match &patch_ids {
+                Some(patch_ids) => {
+                    rebase_without_integrated(ctx, patch_ids, new_target_commit.id(), branch.head)
+                }
+                None => cherry_rebase(
+                    ctx,
+                    new_target_commit.id(),
+                    new_target_commit.id(),
+                    branch.head,
+                ),
+            };
 
             // rebase failed, just do the merge
             if rebased_head_oid.is_err() {
@@ -507,14 +625,24 @@ pub(crate) fn update_base_branch(
 
             if let Some(rebased_head_oid) = rebased_head_oid? {
                 // rebase worked out, rewrite the branch head
+                let old_head = branch.head;
                 branch.head = rebased_head_oid;
                 branch.tree = branch_merge_index_tree_oid;
                 vb_state.set_branch(branch.clone())?;
+                record_branch_event(
+                    ctx,
+                    branch.id,
+                    BranchEventKind::Rebased {
+                        old_head,
+                        new_head: rebased_head_oid,
+                    },
+                );
                 return Ok(Some(branch));
             }
 
             result_merge(branch)
         })
+        .inspect(|_| task.inc())
         .collect::<Result<Vec<_>>>()?
         .into_iter()
         .flatten()
@@ -529,27 +657,80 @@ pub(crate) fn update_base_branch(
             let repo: &git2::Repository = repo;
             let final_tree = final_tree?;
             let branch_tree = repo.find_tree(branch.tree)?;
+            let base_tree = match branch.pinned_base {
+                Some(pinned_base) => repo.find_commit(pinned_base)?.tree()?,
+                None => new_target_tree.clone(),
+            };
             let mut merge_result: Index =
-                repo.merge_trees(&new_target_tree, &final_tree, &branch_tree, None)?;
+                repo.merge_trees(&base_tree, &final_tree, &branch_tree, None)?;
             let final_tree_oid = merge_result.write_tree_to(repo)?;
             repo.find_tree(final_tree_oid)
         })
         .context("failed to calculate final tree")?;
 
-    repo.checkout_tree_builder(&final_tree)
-        .force()
-        .checkout()
-        .context("failed to checkout index, this should not have happened, we should have already detected this")?;
-
-    // write new target oid
-    vb_state.set_default_target(Target {
-        sha: new_target_commit.id(),
-        ..target
+    let base_updated = AutomationEvent::BaseUpdated {
+        target_branch: target.branch.to_string(),
+        previous_base: target.sha,
+        base: new_target_commit.id(),
+        unapplied_branches: unapplied_branch_names
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+    };
+    task.check_cancelled()?;
+    let status_after = cancellation::scoped(&CancellationToken::new(), || -> Result<_> {
+        repo.checkout_tree_builder(&final_tree)
+            .force()
+            .checkout()
+            .context("failed to checkout index, this should not have happened, we should have already detected this")?;
+
+        // write new target oid
+        vb_state.set_default_target(Target {
+            sha: new_target_commit.id(),
+            ..target
+        })?;
+
+        // Rewriting the integration commit is necessary after changing target sha.
+        crate::integration::update_gitbutler_integration(&vb_state, ctx)?;
+        hooks::run(ctx, Hook::PostMerge, &["0"], &[])?;
+
+        Ok(get_applied_status(ctx, None)?.branches)
     })?;
+    task.finish();
+    automation::trigger(ctx.project(), base_updated);
+    Ok((
+        unapplied_branch_names,
+        ownership_remap(&status_before, &status_after),
+    ))
+}
 
-    // Rewriting the integration commit is necessary after changing target sha.
-    crate::integration::update_gitbutler_integration(&vb_state, ctx)?;
-    Ok(unapplied_branch_names)
+/// Rebase the commits of `head` that aren't reachable from `onto` onto it, leaving out the ones that make the same
+/// changes as a commit in `patch_ids`. Return the new head, which is `onto` if all commits were left out, or
+/// `None` if there were no commits to rebase.
+fn rebase_without_integrated(
+    ctx: &CommandContext,
+    patch_ids: &PatchIdIndex,
+    onto: git2::Oid,
+    head: git2::Oid,
+) -> Result<Option<git2::Oid>> {
+    let repo = ctx.repository();
+    let commits = ctx.l(head, LogUntil::Commit(onto))?;
+    if commits.is_empty() {
+        return Ok(None);
+    }
+    let mut remaining = Vec::new();
+    for commit_id in commits {
+        match patch_ids.find(repo, &repo.find_commit(commit_id)?)? {
+            Some(integrated_as) => {
+                tracing::info!(%commit_id, %integrated_as, "leaving out commit the target has already");
+            }
+            None => remaining.push(commit_id),
+        }
+    }
+    if remaining.is_empty() {
+        return Ok(Some(onto));
+    }
+    cherry_rebase_group(ctx, onto, &mut remaining).map(Some)
 }
 
 pub(crate) fn target_to_base_branch(ctx: &CommandContext, target: &Target) -> Result<BaseBranch> {
@@ -559,13 +740,15 @@ pub(crate) fn target_to_base_branch(ctx: &CommandContext, target: &Target) -> Re
         .ok_or(anyhow!("failed to get branch"))?;
     let commit = branch.get().peel_to_commit()?;
     let oid = commit.id();
+    let format = &ctx.project().listing_format;
+    let mailmap = author::mailmap(repo);
 
     // gather a list of commits between oid and target.sha
     let upstream_commits = ctx
         .log(oid, LogUntil::Commit(target.sha))
         .context("failed to get upstream commits")?
         .iter()
-        .map(commit_to_remote_commit)
+        .map(|commit| commit_to_remote_commit(commit, format, mailmap.as_ref()))
         .collect::<Vec<_>>();
 
     // get some recent commits
@@ -573,7 +756,7 @@ pub(crate) fn target_to_base_branch(ctx: &CommandContext, target: &Target) -> Re
         .log(target.sha, LogUntil::Take(20))
         .context("failed to get recent commits")?
         .iter()
-        .map(commit_to_remote_commit)
+        .map(|commit| commit_to_remote_commit(commit, format, mailmap.as_ref()))
         .collect::<Vec<_>>();
 
     // there has got to be a better way to do this.
@@ -0,0 +1,162 @@
+//! Tell for each line of a file in the worktree which commit last changed it and which branch that commit is
+//! on, or which virtual branch owns it if it's changed but not committed yet.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_repo::{LogUntil, RepoActionsExt};
+use serde::Serialize;
+
+use crate::{
+    author::{self, Author},
+    status::get_applied_status,
+    VirtualBranchesExt,
+};
+
+/// Consecutive lines of a file that have the same origin.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlameHunk {
+    /// The number of the first line, starting at 1.
+    pub start_line: u32,
+    /// The amount of lines.
+    pub lines: u32,
+    pub origin: LineOrigin,
+}
+
+/// Where lines of a file come from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum LineOrigin {
+    /// The lines were last changed by a commit.
+    #[serde(rename_all = "camelCase")]
+    Committed {
+        #[serde(with = "gitbutler_serde::oid")]
+        commit_id: git2::Oid,
+        summary: String,
+        author: Author,
+        /// The time of the commit, in seconds since the Unix epoch.
+        created_at: i64,
+        /// The applied virtual branch the commit is on, or `None` if it's on the target.
+        branch_id: Option<BranchId>,
+        /// The path of the file in the commit, if it was renamed since.
+        original_path: Option<PathBuf>,
+    },
+    /// The lines are changed in the worktree, but not committed yet.
+    #[serde(rename_all = "camelCase")]
+    Uncommitted {
+        /// The applied virtual branch that owns the change, or `None` if none claimed it yet.
+        branch_id: Option<BranchId>,
+    },
+}
+
+/// Return where each line of the file at `path`, relative to the worktree, comes from, by combining what
+/// `git blame` says about its committed lines with the branches that own its uncommitted ones.
+pub(crate) fn blame(ctx: &CommandContext, path: &Path) -> Result<Vec<BlameHunk>> {
+    if path.as_os_str().is_empty() || path.is_absolute() {
+        return Err(anyhow!(
+            "the path of a file in the worktree is needed, not {path:?}"
+        ))
+        .context(Code::Validation);
+    }
+    let content = std::fs::read(ctx.project().worktree_path().join(path))
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let line_count = content.split(|byte| *byte == b'\n').count()
+        - usize::from(content.is_empty() || content.ends_with(b"\n"));
+    let line_count = u32::try_from(line_count)?;
+
+    // The uncommitted lines of the file, by the branch that owns them.
+    let mut owners = Vec::new();
+    for (branch, files) in get_applied_status(ctx, None)?.branches {
+        for hunk in files
+            .into_iter()
+            .filter(|file| file.path == path)
+            .flat_map(|file| file.hunks)
+        {
+            owners.push((hunk.start..hunk.end, branch.id));
+        }
+    }
+    let owner = |line: u32| {
+        owners
+            .iter()
+            .find(|(lines, _)| lines.contains(&line))
+            .map(|(_, branch_id)| *branch_id)
+    };
+
+    let repo = ctx.repository();
+    let head_tree = repo.head()?.peel_to_tree()?;
+    if head_tree.get_path(path).is_err() {
+        return Ok(uncommitted(1, line_count, &owner));
+    }
+    let blame = repo
+        .blame_file(path, None)
+        .with_context(|| format!("failed to blame {}", path.display()))?
+        .blame_buffer(&content)
+        .context("failed to blame the worktree")?;
+
+    // The commits of the applied branches, by the branch they are on.
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    let mut branch_by_commit = HashMap::new();
+    for branch in vb_state.list_branches_in_workspace()? {
+        for commit_id in ctx.l(branch.head, LogUntil::Commit(branch.base(target.sha)))? {
+            branch_by_commit.insert(commit_id, branch.id);
+        }
+    }
+
+    let mailmap = author::mailmap(repo);
+    let mut hunks = Vec::new();
+    for hunk in blame.iter() {
+        let start_line = u32::try_from(hunk.final_start_line())?;
+        let lines = u32::try_from(hunk.lines_in_hunk())?;
+        let commit_id = hunk.final_commit_id();
+        if commit_id.is_zero() {
+            hunks.extend(uncommitted(start_line, lines, &owner));
+            continue;
+        }
+        let commit = repo.find_commit(commit_id)?;
+        let original_path = hunk.path().filter(|original| *original != path);
+        hunks.push(BlameHunk {
+            start_line,
+            lines,
+            origin: LineOrigin::Committed {
+                commit_id,
+                summary: commit.summary().unwrap_or_default().to_owned(),
+                author: author::resolve(mailmap.as_ref(), commit.author()).into(),
+                created_at: commit.time().seconds(),
+                branch_id: branch_by_commit.get(&commit_id).copied(),
+                original_path: original_path.map(Path::to_owned),
+            },
+        });
+    }
+    Ok(hunks)
+}
+
+/// Return the hunks of the `lines` uncommitted lines from `start_line`, split by the branch `owner` says
+/// owns each line.
+fn uncommitted(
+    start_line: u32,
+    lines: u32,
+    owner: impl Fn(u32) -> Option<BranchId>,
+) -> Vec<BlameHunk> {
+    let mut hunks: Vec<BlameHunk> = Vec::new();
+    for line in start_line..start_line + lines {
+        let origin = LineOrigin::Uncommitted {
+            branch_id: owner(line),
+        };
+        match hunks.last_mut() {
+            Some(hunk) if hunk.origin == origin => hunk.lines += 1,
+            _ => hunks.push(BlameHunk {
+                start_line: line,
+                lines: 1,
+                origin,
+            }),
+        }
+    }
+    hunks
+}
@@ -1,4 +1,4 @@
-use crate::VirtualBranchesExt;
+use crate::{author, CiStatus, PullRequest, VirtualBranchesExt};
 use anyhow::{Context, Result};
 use bstr::{BStr, ByteSlice};
 use core::fmt;
@@ -6,8 +6,11 @@ use gitbutler_branch::{
     Branch as GitButlerBranch, BranchId, BranchIdentity, ReferenceExtGix, Target,
 };
 use gitbutler_command_context::CommandContext;
+use gitbutler_project::ListingFormat;
 use gitbutler_reference::normalize_branch_name;
+use gitbutler_repo::commit_graph;
 use gitbutler_serde::BStringForFrontend;
+use gitbutler_time::time::now_since_unix_epoch_ms;
 use gix::prelude::ObjectIdExt;
 use gix::reference::Category;
 use serde::{Deserialize, Serialize};
@@ -27,7 +30,7 @@ pub fn list_branches(
     filter_branch_names: Option<Vec<BranchIdentity>>,
 ) -> Result<Vec<BranchListing>> {
     let mut repo = gix::open(ctx.repository().path())?;
-    repo.object_cache_size_if_unset(1024 * 1024);
+    repo.object_cache_size_if_unset(gitbutler_diff::memory_limits().object_cache_bytes);
     let has_filter = filter.is_some();
     let filter = filter.unwrap_or_default();
     let vb_handle = ctx.project().virtual_branches();
@@ -65,11 +68,24 @@ pub fn list_branches(
     for branch in virtual_branches {
         branches.push(GroupBranch::Virtual(branch));
     }
-    let mut branches = combine_branches(branches, &repo, vb_handle.get_default_target()?)?;
+    let mut branches = combine_branches(
+        branches,
+        &repo,
+        vb_handle.get_default_target()?,
+        &ctx.project().listing_format,
+    )?;
 
     // Apply the filter
     branches.retain(|branch| !has_filter || matches_all(branch, filter));
 
+    let mut pull_requests = ctx.project().pull_requests().list()?;
+    let mut ci_statuses = ctx.project().ci_statuses().list()?;
+    for branch in branches.iter_mut() {
+        let name = branch.name.to_str_lossy();
+        branch.pull_request = pull_requests.remove(&*name);
+        branch.ci_status = ci_statuses.remove(&*name);
+    }
+
     // Filter out virtual branches which have no local or remote branches
     branches.retain(|branch| {
         // If there is no virtual branch, keep the grouping
@@ -122,9 +138,11 @@ fn combine_branches(
     group_branches: Vec<GroupBranch>,
     repo: &gix::Repository,
     target_branch: Target,
+    format: &ListingFormat,
 ) -> Result<Vec<BranchListing>> {
     let remotes = repo.remote_names();
     let packed = repo.refs.cached_packed_buffer()?;
+    let mailmap = author::mailmap(ctx.repository());
 
     // Group branches by identity
     let mut groups: HashMap<BranchIdentity, Vec<GroupBranch>> = HashMap::new();
@@ -150,6 +168,8 @@ fn combine_branches(
                 packed.as_ref().map(|p| &***p),
                 &remotes,
                 &target_branch,
+                format,
+                mailmap.as_ref(),
             );
             match res {
                 Ok(branch_entry) => branch_entry,
@@ -174,6 +194,8 @@ fn branch_group_to_branch(
     packed: Option<&gix::refs::packed::Buffer>,
     remotes: &BTreeSet<Cow<'_, BStr>>,
     target: &Target,
+    format: &ListingFormat,
+    mailmap: Option<&git2::Mailmap>,
 ) -> Result<Option<BranchListing>> {
     let (local_branches, remote_branches, mut vbranches) =
         group_branches
@@ -243,15 +265,42 @@ fn branch_group_to_branch(
         (head_commit.time().seconds * 1000) as u128,
         virtual_branch.map_or(0, |x| x.updated_timestamp_ms),
     );
-    let last_commiter = head_commit.author().into();
+    let updated_at_display = format.format_time(
+        (last_modified_ms / 1000) as i64,
+        head_commit.time().offset / 60,
+        now_since_unix_epoch_ms() / 1000,
+    );
+    let author = head_commit.author();
+    // Only authors `git2` can represent are mapped with the mailmap.
+    let (last_commiter_display, last_commiter) =
+        match gitbutler_branch::gix_to_git2_signature(author) {
+            Ok(signature) => {
+                let signature = author::resolve(mailmap, signature);
+                (
+                    format.format_author(
+                        &signature.name_bytes().to_str_lossy(),
+                        &signature.email_bytes().to_str_lossy(),
+                    ),
+                    signature.into(),
+                )
+            }
+            Err(_) => (
+                format.format_author(&author.name.to_str_lossy(), &author.email.to_str_lossy()),
+                author.into(),
+            ),
+        };
 
     Ok(Some(BranchListing {
         name: identity.to_owned(),
         remotes,
         virtual_branch: virtual_branch_reference,
         updated_at: last_modified_ms,
+        updated_at_display,
         last_commiter,
+        last_commiter_display,
         has_local,
+        pull_request: None,
+        ci_status: None,
         head,
     }))
 }
@@ -362,10 +411,18 @@ pub struct BranchListing {
     /// Timestamp in milliseconds since the branch was last updated.
     /// This includes any commits, uncommited changes or even updates to the branch metadata (e.g. renaming).
     pub updated_at: u128,
+    /// `updated_at` rendered according to the project's listing format.
+    pub updated_at_display: String,
     /// The person who commited the head commit.
     pub last_commiter: Author,
+    /// `last_commiter` rendered according to the project's listing format.
+    pub last_commiter_display: String,
     /// Whether there is a local branch under the name.
     pub has_local: bool,
+    /// The pull request that was opened for the branch, as it was when it was last refreshed.
+    pub pull_request: Option<PullRequest>,
+    /// How CI is doing for the pushed head of the branch, as it was when it was last refreshed.
+    pub ci_status: Option<CiStatus>,
     /// The head of interest for the branch group, used for calculating branch statistics.
     /// If there is a virtual branch, a local branch and remote branches, the head is determined in the following order:
     /// 1. The head of the virtual branch
@@ -437,6 +494,7 @@ pub fn get_branch_listing_details(
         repo.find_branch(default_target.branch.branch(), git2::BranchType::Local)?;
     let default_branch = default_local_branch.upstream()?;
     let head_commit = default_branch.get().peel_to_commit()?;
+    let mailmap = author::mailmap(repo);
 
     for branch in branches {
         let merge_base_comparison = if let Some(virtual_branch) = branch.virtual_branch {
@@ -448,7 +506,7 @@ pub fn get_branch_listing_details(
         } else {
             head_commit.id()
         };
-        if let Ok(base) = repo.merge_base(merge_base_comparison, branch.head) {
+        if let Ok(base) = commit_graph::merge_base(repo, merge_base_comparison, branch.head) {
             let base_tree = repo.find_commit(base)?.tree()?;
             let head_tree = repo.find_commit(branch.head)?.tree()?;
             let diff_stats = repo
@@ -464,7 +522,7 @@ pub fn get_branch_listing_details(
             let mut authors = HashSet::new();
             for oid in revwalk {
                 let commit = repo.find_commit(oid?)?;
-                authors.insert(commit.author().into());
+                authors.insert(author::resolve(mailmap.as_ref(), commit.author()).into());
                 commits.push(commit);
             }
             let branch_data = BranchListingDetails {
@@ -511,7 +569,7 @@ pub struct BranchListingDetails {
     pub number_of_commits: usize,
     /// A list of authors that have contributes commits to this branch.
     /// In the case of multiple remote tracking branches, or branches whose commits are evaluated,
-    /// it takes the full list of unique authors, without applying a mailmap.
+    /// it takes the full list of unique authors, as mapped by the mailmap of the repository.
     pub authors: Vec<Author>,
 }
 /// Represents a local branch
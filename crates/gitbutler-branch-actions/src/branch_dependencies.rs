@@ -0,0 +1,97 @@
+//! Find uncommitted changes of applied branches that build on the commits of other applied branches,
+//! so these branches can't be unapplied independently of each other.
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::Result;
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::GitHunk;
+use serde::Serialize;
+
+use crate::{status::get_applied_status, VirtualBranchesExt};
+
+/// An applied branch with uncommitted changes that touch or are next to lines changed by the commits
+/// of another applied branch.
+///
+/// The changes only apply on top of the other branch, so unapplying either of them on its own
+/// leaves the changes without the lines they were made to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchDependency {
+    /// The branch owning the uncommitted changes.
+    pub branch_id: BranchId,
+    /// The branch whose commits the changes build on.
+    pub depends_on: BranchId,
+    /// The uncommitted hunks of `branch_id` that build on `depends_on`.
+    pub hunks: Vec<DependentHunk>,
+}
+
+/// An uncommitted hunk that builds on the commits of another branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependentHunk {
+    /// The worktree-relative path of the file the hunk is in.
+    pub path: PathBuf,
+    /// The id of the hunk, as listed with its branch.
+    pub hunk_id: String,
+}
+
+/// Find all applied branches whose uncommitted hunks overlap with or are adjacent to the changes
+/// the commits of another applied branch make to the target, in the order of the branches.
+pub(crate) fn branch_dependencies(ctx: &CommandContext) -> Result<Vec<BranchDependency>> {
+    let repo = ctx.repository();
+    let target = ctx.project().virtual_branches().get_default_target()?;
+    let target_tree = repo.find_commit(target.sha)?.tree()?;
+    let mut diff_opts = git2::DiffOptions::new();
+    diff_opts
+        .show_binary(true)
+        .ignore_submodules(true)
+        .context_lines(3);
+
+    let mut branches = get_applied_status(ctx, None)?.branches;
+    branches.sort_by_key(|(branch, _)| branch.order);
+    let mut committed = BTreeMap::new();
+    for (branch, _) in &branches {
+        let tree = repo.find_commit(branch.head)?.tree()?;
+        let diff = repo.diff_tree_to_tree(Some(&target_tree), Some(&tree), Some(&mut diff_opts))?;
+        committed.insert(
+            branch.id,
+            gitbutler_diff::hunks_by_filepath(Some(repo), &diff)?,
+        );
+    }
+
+    let mut dependencies = Vec::new();
+    for (branch, files) in &branches {
+        for (other, _) in &branches {
+            if other.id == branch.id {
+                continue;
+            }
+            let hunks: Vec<_> = files
+                .iter()
+                .filter_map(|file| Some((file, committed[&other.id].get(&file.path)?)))
+                .flat_map(|(file, committed_file)| {
+                    file.hunks
+                        .iter()
+                        .filter(|hunk| {
+                            let hunk = GitHunk::from((*hunk).clone());
+                            committed_file.hunks.iter().any(|committed_hunk| {
+                                GitHunk::integration_intersects_unapplied(committed_hunk, &hunk)
+                            })
+                        })
+                        .map(|hunk| DependentHunk {
+                            path: file.path.clone(),
+                            hunk_id: hunk.id.clone(),
+                        })
+                })
+                .collect();
+            if !hunks.is_empty() {
+                dependencies.push(BranchDependency {
+                    branch_id: branch.id,
+                    depends_on: other.id,
+                    hunks,
+                });
+            }
+        }
+    }
+    Ok(dependencies)
+}
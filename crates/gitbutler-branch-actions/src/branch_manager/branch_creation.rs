@@ -1,22 +1,28 @@
 use std::borrow::Cow;
 
 use anyhow::{anyhow, bail, Context, Result};
-use gitbutler_branch::{self, dedup, Branch, BranchCreateRequest, BranchId, BranchOwnershipClaims};
+use gitbutler_branch::{
+    self, dedup, Branch, BranchCreateRequest, BranchId, BranchOwnershipClaims, BranchUpdateRequest,
+};
 use gitbutler_commit::commit_headers::HasCommitHeaders;
 use gitbutler_error::error::Marker;
-use gitbutler_oplog::SnapshotExt;
+use gitbutler_oplog::{record_operation, AuditOperation, SnapshotExt};
 use gitbutler_project::access::WorktreeWritePermission;
 use gitbutler_reference::{Refname, RemoteRefname};
-use gitbutler_repo::{rebase::cherry_rebase, RepoActionsExt, RepositoryExt};
+use gitbutler_repo::{
+    hooks, merge_drivers, partial_clone, rebase::cherry_rebase, RepoActionsExt, RepositoryExt,
+};
 use gitbutler_time::time::now_since_unix_epoch_ms;
 
 use super::BranchManager;
 use crate::{
+    automation::{self, AutomationEvent},
+    branch_naming::{self, NamingStrategy},
     conflicts::{self, RepoConflictsExt},
-    ensure_selected_for_changes,
+    ensure_not_pinned_elsewhere, ensure_selected_for_changes, get_applied_status,
     hunk::VirtualBranchHunk,
     integration::update_gitbutler_integration,
-    set_ownership, undo_commit, VirtualBranchesExt,
+    set_ownership, undo_commit, update_branch, VirtualBranchesExt,
 };
 
 impl BranchManager<'_> {
@@ -28,35 +34,56 @@ impl BranchManager<'_> {
         let vb_state = self.ctx.project().virtual_branches();
         let default_target = vb_state.get_default_target()?;
 
+        // A stacked branch starts out at the head of its parent.
+        let parent = create
+            .parent
+            .map(|parent_id| vb_state.get_branch_in_workspace(parent_id))
+            .transpose()?;
+        let head = parent
+            .as_ref()
+            .map_or(default_target.sha, |parent| parent.head);
+
         let commit = self
             .ctx
             .repository()
-            .find_commit(default_target.sha)
-            .context("failed to find default target commit")?;
+            .find_commit(head)
+            .context("failed to find base commit")?;
 
-        let tree = commit
-            .tree()
-            .context("failed to find default target commit tree")?;
+        let tree = commit.tree().context("failed to find base commit tree")?;
 
         let mut all_virtual_branches = vb_state
             .list_branches_in_workspace()
             .context("failed to read virtual branches")?;
 
+        let named = match (&create.name, &create.summary) {
+            (None, Some(summary)) => branch_naming::name_branch(self.ctx, summary)?,
+            _ => None,
+        };
+        let existing_names = all_virtual_branches
+            .iter()
+            .map(|b| b.name.as_str())
+            .collect::<Vec<_>>();
+        let default_name = self
+            .ctx
+            .project()
+            .settings
+            .default_branch_name
+            .default_name(&existing_names, create.summary.as_deref());
         let name = dedup(
-            &all_virtual_branches
-                .iter()
-                .map(|b| b.name.as_str())
-                .collect::<Vec<_>>(),
+            &existing_names,
             create
                 .name
-                .as_ref()
-                .unwrap_or(&"Virtual branch".to_string()),
+                .as_deref()
+                .or(named.as_ref().map(|named| named.name.trim()))
+                .unwrap_or(&default_name),
         );
 
-        _ = self
-            .ctx
-            .project()
-            .snapshot_branch_creation(name.clone(), perm);
+        if self.take_snapshots {
+            _ = self
+                .ctx
+                .project()
+                .snapshot_branch_creation(name.clone(), perm);
+        }
 
         all_virtual_branches.sort_by_key(|branch| branch.order);
 
@@ -97,11 +124,13 @@ impl BranchManager<'_> {
         let mut branch = Branch {
             id: BranchId::generate(),
             name: name.clone(),
-            notes: String::new(),
+            notes: named
+                .and_then(|named| named.description)
+                .unwrap_or_default(),
             upstream: None,
             upstream_head: None,
             tree: tree.id(),
-            head: default_target.sha,
+            head,
             created_timestamp_ms: now,
             updated_timestamp_ms: now,
             ownership: BranchOwnershipClaims::default(),
@@ -111,10 +140,19 @@ impl BranchManager<'_> {
             applied: true,
             in_workspace: true,
             not_in_workspace_wip_change_id: None,
+            pinned_base: parent.as_ref().map(|parent| parent.head),
+            parent: create.parent,
+            push_remote_name: None,
+            allowed_paths: Vec::new(),
+            metadata: Default::default(),
+            description: String::new(),
+            reviewers: Vec::new(),
+            forge_merge: None,
             source_refname: None,
         };
 
         if let Some(ownership) = &create.ownership {
+            ensure_not_pinned_elsewhere(self.ctx, &branch, ownership)?;
             set_ownership(&vb_state, &mut branch, ownership).context("failed to set ownership")?;
         }
 
@@ -124,6 +162,47 @@ impl BranchManager<'_> {
         Ok(branch)
     }
 
+    /// Create a new virtual branch named `name` that claims all changes in the worktree that aren't
+    /// owned by any branch yet, like those that were just written to it, and return its id.
+    ///
+    /// The branch that was selected for changes before stays selected.
+    pub(crate) fn create_virtual_branch_for_new_changes(
+        &self,
+        name: String,
+        perm: &mut WorktreeWritePermission,
+    ) -> Result<BranchId> {
+        let previously_selected = self
+            .ctx
+            .project()
+            .virtual_branches()
+            .list_branches_in_workspace()?
+            .into_iter()
+            .find(|branch| branch.selected_for_changes.is_some());
+        let branch_id = self
+            .create_virtual_branch(
+                &BranchCreateRequest {
+                    name: Some(name),
+                    selected_for_changes: Some(true),
+                    ..Default::default()
+                },
+                perm,
+            )?
+            .id;
+        // Being selected for changes, the new branch claims all changes that aren't owned yet.
+        get_applied_status(self.ctx, Some(perm))?;
+        if let Some(branch) = previously_selected {
+            update_branch(
+                self.ctx,
+                &BranchUpdateRequest {
+                    id: branch.id,
+                    selected_for_changes: Some(true),
+                    ..Default::default()
+                },
+            )?;
+        }
+        Ok(branch_id)
+    }
+
     pub fn create_virtual_branch_from_branch(
         &self,
         target: &Refname,
@@ -150,10 +229,12 @@ impl BranchManager<'_> {
             .expect("always a branch reference")
             .to_string();
 
-        let _ = self
-            .ctx
-            .project()
-            .snapshot_branch_creation(branch_name.clone(), perm);
+        if self.take_snapshots {
+            let _ = self
+                .ctx
+                .project()
+                .snapshot_branch_creation(branch_name.clone(), perm);
+        }
 
         let vb_state = self.ctx.project().virtual_branches();
 
@@ -256,14 +337,26 @@ impl BranchManager<'_> {
                 applied: true,
                 in_workspace: true,
                 not_in_workspace_wip_change_id: None,
+                pinned_base: None,
+                parent: None,
+                push_remote_name: None,
+                allowed_paths: Vec::new(),
+                metadata: Default::default(),
+                description: String::new(),
+                reviewers: Vec::new(),
+                forge_merge: None,
             }
         };
 
         vb_state.set_branch(branch.clone())?;
         self.ctx.add_branch_reference(&branch)?;
 
+        let previous_head = hooks::head_id(self.ctx.repository());
         match self.apply_branch(branch.id, perm) {
-            Ok(_) => Ok(branch.id),
+            Ok(_) => {
+                hooks::run_post_checkout(self.ctx, previous_head)?;
+                Ok(branch.id)
+            }
             Err(err)
                 if err
                     .downcast_ref()
@@ -319,6 +412,11 @@ impl BranchManager<'_> {
                 .find_tree(branch.tree)
                 .context("failed to find branch tree")?;
 
+            partial_clone::fetch_changed_blobs(
+                self.ctx,
+                &merge_base_tree,
+                &[&branch_tree, &target_tree],
+            )?;
             let mut merge_index = repo
                 .merge_trees(&merge_base_tree, &branch_tree, &target_tree, None)
                 .context("failed to merge trees")?;
@@ -356,6 +454,8 @@ impl BranchManager<'_> {
                     }
                 }
                 conflicts::mark(self.ctx, &merge_conflicts, Some(default_target.sha))?;
+                conflicts::record_stages(self.ctx, &merge_index)?;
+                self.record_applied(&branch);
 
                 return Ok(branch.name);
             }
@@ -458,10 +558,11 @@ impl BranchManager<'_> {
             .find_tree(branch.tree)
             .context("failed to find branch tree")?;
 
+        partial_clone::fetch_changed_blobs(self.ctx, &target_tree, &[&wd_tree, &branch_tree])?;
         // check index for conflicts
-        let mut merge_index = repo
-            .merge_trees(&target_tree, &wd_tree, &branch_tree, None)
-            .context("failed to merge trees")?;
+        let mut merge_index =
+            merge_drivers::merge_trees(repo, &target_tree, &wd_tree, &branch_tree)
+                .context("failed to merge trees")?;
 
         if merge_index.has_conflicts() {
             // mark conflicts
@@ -476,6 +577,7 @@ impl BranchManager<'_> {
                 }
             }
             conflicts::mark(self.ctx, &merge_conflicts, Some(default_target.sha))?;
+            conflicts::record_stages(self.ctx, &merge_index)?;
         }
 
         // apply the branch
@@ -507,7 +609,26 @@ impl BranchManager<'_> {
         }
 
         update_gitbutler_integration(&vb_state, self.ctx)?;
+        self.record_applied(&branch);
 
         Ok(branch.name)
     }
+
+    fn record_applied(&self, branch: &Branch) {
+        record_operation(
+            self.ctx.project(),
+            self.ctx.repository(),
+            AuditOperation::BranchApplied {
+                branch_id: branch.id,
+                branch_name: branch.name.clone(),
+            },
+        );
+        automation::trigger(
+            self.ctx.project(),
+            AutomationEvent::BranchApplied {
+                branch_id: branch.id,
+                branch_name: branch.name.clone(),
+            },
+        );
+    }
 }
@@ -3,18 +3,19 @@ use std::path::PathBuf;
 use anyhow::{Context, Result};
 use git2::Commit;
 use gitbutler_branch::{Branch, BranchExt, BranchId, SignaturePurpose};
+use gitbutler_command_context::CommandContext;
 use gitbutler_commit::commit_headers::CommitHeadersV2;
-use gitbutler_oplog::SnapshotExt;
+use gitbutler_oplog::{record_operation, AuditOperation, SnapshotExt};
 use gitbutler_project::access::WorktreeWritePermission;
 use gitbutler_reference::{normalize_branch_name, ReferenceName, Refname};
-use gitbutler_repo::{RepoActionsExt, RepositoryExt};
+use gitbutler_repo::{hooks, RepoActionsExt, RepositoryExt};
 
 use super::BranchManager;
 use crate::{
     conflicts::{self},
     ensure_selected_for_changes, get_applied_status,
     hunk::VirtualBranchHunk,
-    VirtualBranchesExt,
+    VirtualBranchFile, VirtualBranchesExt,
 };
 
 impl BranchManager<'_> {
@@ -31,6 +32,7 @@ impl BranchManager<'_> {
             .find_commit(vb_state.get_default_target()?.sha)?;
 
         let mut target_branch = vb_state.get_branch(branch_id)?;
+        let previous_head = hooks::head_id(self.ctx.repository());
 
         // Convert the vbranch to a real branch
         let real_branch = self.build_real_branch(&mut target_branch)?;
@@ -48,6 +50,15 @@ impl BranchManager<'_> {
         ensure_selected_for_changes(&vb_state).context("failed to ensure selected for changes")?;
 
         crate::integration::update_gitbutler_integration(&vb_state, self.ctx)?;
+        hooks::run_post_checkout(self.ctx, previous_head)?;
+        record_operation(
+            self.ctx.project(),
+            self.ctx.repository(),
+            AuditOperation::BranchUnapplied {
+                branch_id,
+                branch_name: target_branch.name.clone(),
+            },
+        );
 
         real_branch.reference_name()
     }
@@ -68,15 +79,15 @@ impl BranchManager<'_> {
             return Ok(());
         }
 
-        _ = self
-            .ctx
-            .project()
-            .snapshot_branch_deletion(branch.name.clone(), perm);
+        if self.take_snapshots {
+            _ = self
+                .ctx
+                .project()
+                .snapshot_branch_deletion(branch.name.clone(), perm);
+        }
 
         let repo = self.ctx.repository();
 
-        let base_tree = target_commit.tree().context("failed to get target tree")?;
-
         let applied_statuses = get_applied_status(self.ctx, None)
             .context("failed to get status by branch")?
             .branches;
@@ -86,31 +97,8 @@ impl BranchManager<'_> {
             .mark_as_not_in_workspace(branch.id)
             .context("Failed to remove branch")?;
 
-        // go through the other applied branches and merge them into the final tree
-        // then check that out into the working directory
-        let final_tree = applied_statuses
-            .into_iter()
-            .filter(|(branch, _)| branch.id != branch_id)
-            .fold(
-                target_commit.tree().context("failed to get target tree"),
-                |final_tree, status| {
-                    let final_tree = final_tree?;
-                    let branch = status.0;
-                    let files = status
-                        .1
-                        .into_iter()
-                        .map(|file| (file.path, file.hunks))
-                        .collect::<Vec<(PathBuf, Vec<VirtualBranchHunk>)>>();
-                    let tree_oid =
-                        gitbutler_diff::write::hunks_onto_oid(self.ctx, &branch.head, files)?;
-                    let branch_tree = repo.find_tree(tree_oid)?;
-                    let mut result =
-                        repo.merge_trees(&base_tree, &final_tree, &branch_tree, None)?;
-                    let final_tree_oid = result.write_tree_to(repo)?;
-                    repo.find_tree(final_tree_oid)
-                        .context("failed to find tree")
-                },
-            )?;
+        let final_tree =
+            workspace_tree_without(self.ctx, applied_statuses, branch_id, target_commit)?;
 
         // checkout final_tree into the working directory
         repo.checkout_tree_builder(&final_tree)
@@ -127,6 +115,40 @@ impl BranchManager<'_> {
     }
 }
 
+/// Return the tree of the worktree once the branch with `branch_id` is unapplied, which has the changes
+/// of the other branches in `applied_statuses` on top of `target_commit`.
+pub(crate) fn workspace_tree_without<'repo>(
+    ctx: &'repo CommandContext,
+    applied_statuses: Vec<(Branch, Vec<VirtualBranchFile>)>,
+    branch_id: BranchId,
+    target_commit: &Commit<'repo>,
+) -> Result<git2::Tree<'repo>> {
+    let repo = ctx.repository();
+    let base_tree = target_commit.tree().context("failed to get target tree")?;
+    // go through the other applied branches and merge them into the final tree
+    applied_statuses
+        .into_iter()
+        .filter(|(branch, _)| branch.id != branch_id)
+        .fold(
+            target_commit.tree().context("failed to get target tree"),
+            |final_tree, status| {
+                let final_tree = final_tree?;
+                let branch = status.0;
+                let files = status
+                    .1
+                    .into_iter()
+                    .map(|file| (file.path, file.hunks))
+                    .collect::<Vec<(PathBuf, Vec<VirtualBranchHunk>)>>();
+                let tree_oid = gitbutler_diff::write::hunks_onto_oid(ctx, &branch.head, files)?;
+                let branch_tree = repo.find_tree(tree_oid)?;
+                let mut result = repo.merge_trees(&base_tree, &final_tree, &branch_tree, None)?;
+                let final_tree_oid = result.write_tree_to(repo)?;
+                repo.find_tree(final_tree_oid)
+                    .context("failed to find tree")
+            },
+        )
+}
+
 impl BranchManager<'_> {
     fn build_real_branch(&self, vbranch: &mut Branch) -> Result<git2::Branch<'_>> {
         let repo = self.ctx.repository();
@@ -2,9 +2,20 @@ use gitbutler_command_context::CommandContext;
 
 mod branch_creation;
 mod branch_removal;
+pub(crate) use branch_removal::workspace_tree_without;
 
 pub struct BranchManager<'l> {
     ctx: &'l CommandContext,
+    /// If `false`, operations don't snapshot the workspace, as the caller took a snapshot covering them.
+    take_snapshots: bool,
+}
+
+impl BranchManager<'_> {
+    /// Don't take a snapshot before each operation, for when a single snapshot covers several of them.
+    pub(crate) fn without_snapshots(mut self) -> Self {
+        self.take_snapshots = false;
+        self
+    }
 }
 
 pub trait BranchManagerExt {
@@ -13,6 +24,9 @@ pub trait BranchManagerExt {
 
 impl BranchManagerExt for CommandContext {
     fn branch_manager(&self) -> BranchManager {
-        BranchManager { ctx: self }
+        BranchManager {
+            ctx: self,
+            take_snapshots: true,
+        }
     }
 }
@@ -0,0 +1,96 @@
+//! Values that automation, like ticket bots or release tooling, keeps on branches, so it can store
+//! its own state without changes to the branch schema.
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::access::WorktreeWritePermission;
+
+use crate::VirtualBranchesExt;
+
+/// The maximal length of a key, in bytes.
+pub const MAX_METADATA_KEY_LEN: usize = 128;
+/// The maximal length of a value as JSON text, in bytes.
+pub const MAX_METADATA_VALUE_LEN: usize = 16 * 1024;
+/// The maximal number of keys on a branch.
+pub const MAX_METADATA_ENTRIES: usize = 64;
+
+/// Return the metadata of the branch identified by `branch_id` by key.
+pub(crate) fn get(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+) -> Result<BTreeMap<String, serde_json::Value>> {
+    let branch = ctx.project().virtual_branches().get_branch(branch_id)?;
+    branch
+        .metadata
+        .iter()
+        .map(|(key, value)| {
+            let value = serde_json::from_str(value)
+                .with_context(|| format!("metadata '{key}' of branch {branch_id} isn't JSON"))?;
+            Ok((key.clone(), value))
+        })
+        .collect()
+}
+
+/// Set the metadata `key` of the branch identified by `branch_id` to `value`, or remove it if
+/// `value` is `None`.
+pub(crate) fn set(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    key: &str,
+    value: Option<&serde_json::Value>,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    validate_key(key)?;
+    let vb_state = ctx.project().virtual_branches();
+    let mut branch = vb_state.get_branch(branch_id)?;
+    match value {
+        Some(value) => {
+            let text = serde_json::to_string(value)?;
+            if text.len() > MAX_METADATA_VALUE_LEN {
+                return Err(anyhow!(
+                    "metadata '{key}' takes {} bytes, but may take at most {MAX_METADATA_VALUE_LEN}",
+                    text.len()
+                )
+                .context(Code::Validation));
+            }
+            if !branch.metadata.contains_key(key) && branch.metadata.len() >= MAX_METADATA_ENTRIES {
+                return Err(anyhow!(
+                    "branch '{}' has {MAX_METADATA_ENTRIES} metadata keys already",
+                    branch.name
+                )
+                .context(Code::Validation));
+            }
+            branch.metadata.insert(key.to_owned(), text);
+        }
+        None => {
+            if branch.metadata.remove(key).is_none() {
+                return Ok(());
+            }
+        }
+    }
+    vb_state.set_branch(branch)
+}
+
+/// Keys are namespaced by the automation that owns them, like `jira.ticket` or `acme/release`.
+fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty() || key.len() > MAX_METADATA_KEY_LEN {
+        return Err(anyhow!(
+            "metadata keys must have 1 to {MAX_METADATA_KEY_LEN} bytes, but '{key}' has {}",
+            key.len()
+        )
+        .context(Code::Validation));
+    }
+    if let Some(invalid) = key
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '/')))
+    {
+        return Err(anyhow!(
+            "metadata key '{key}' contains '{invalid}', but may only contain ASCII letters, digits, '.', '-', '_' and '/'"
+        )
+        .context(Code::Validation));
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+//! Name new virtual branches with the [command or endpoint](BranchNaming) configured for the project, so
+//! organizations can enforce conventions like prefixing branches with their ticket in a single place,
+//! and with the [default name](DefaultBranchName) of the project otherwise.
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::{BranchNaming, DefaultBranchName};
+use gitbutler_repo::hooks;
+use serde::{Deserialize, Serialize};
+
+use crate::forge::block_on;
+
+/// How long the command or endpoint may take to answer, as branch creation waits for it.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The name of new virtual branches if there is nothing better to name them after.
+const FALLBACK_NAME: &str = "Virtual branch";
+
+/// Picks the name of a new virtual branch that wasn't given one.
+pub trait NamingStrategy {
+    /// Return the name of a new branch for the work described by `summary`, if known, in a workspace
+    /// with branches named `existing`. The name is made unique afterwards.
+    fn default_name(&self, existing: &[&str], summary: Option<&str>) -> String;
+}
+
+impl NamingStrategy for DefaultBranchName {
+    fn default_name(&self, existing: &[&str], summary: Option<&str>) -> String {
+        match self {
+            DefaultBranchName::Fixed => FALLBACK_NAME.to_owned(),
+            DefaultBranchName::Numbered => (1..)
+                .map(|number| format!("Lane {number}"))
+                .find(|name| !existing.contains(&name.as_str()))
+                .expect("there are fewer branches than numbers"),
+            DefaultBranchName::Dated => {
+                gitbutler_branch::current_time().format(gix::date::time::format::SHORT)
+            }
+            DefaultBranchName::Summary => summary
+                .and_then(|summary| summary.lines().map(str::trim).find(|line| !line.is_empty()))
+                .unwrap_or(FALLBACK_NAME)
+                .to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct NamingRequest<'a> {
+    summary: &'a str,
+}
+
+/// The answer of the command or endpoint.
+#[derive(Debug, Deserialize)]
+pub(crate) struct NamedBranch {
+    pub name: String,
+    /// Becomes the notes of the branch.
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Ask the configured command or endpoint to name a branch for the work described by `summary`,
+/// or return `None` if the project has none configured.
+///
+/// Fails if it can't be reached, fails itself, or answers without a name, as it's configured to
+/// enforce the conventions of the project.
+pub(crate) fn name_branch(ctx: &CommandContext, summary: &str) -> Result<Option<NamedBranch>> {
+    let Some(naming) = &ctx.project().settings.branch_naming else {
+        return Ok(None);
+    };
+    let request = serde_json::to_vec(&NamingRequest { summary })?;
+    let answer = match naming {
+        BranchNaming::Command { command } => {
+            hooks::run_script(ctx, "branch naming command", command, &request, TIMEOUT)?
+        }
+        BranchNaming::Http { url } => block_on(async {
+            let response = reqwest::Client::new()
+                .post(url)
+                .timeout(TIMEOUT)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(request)
+                .send()
+                .await
+                .with_context(|| format!("failed to reach the branch naming endpoint at {url}"))?
+                .error_for_status()
+                .context("the branch naming endpoint failed")?;
+            Ok(response.text().await?)
+        })?,
+    };
+    let named: NamedBranch = serde_json::from_str(&answer)
+        .context("the branch name couldn't be read from the answer")?;
+    if named.name.trim().is_empty() {
+        return Err(anyhow!("branch naming answered without a name")).context(Code::Validation);
+    }
+    Ok(Some(named))
+}
@@ -0,0 +1,120 @@
+//! Administer many branches at once. Each operation is attempted on every branch, and the outcome
+//! is reported per branch so that one failing branch doesn't hide what happened to the others.
+use anyhow::Result;
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::{access::WorktreeWritePermission, BranchCleanupAction};
+use gitbutler_reference::Refname;
+use gitbutler_repo::progress::{Progress, Task};
+use serde::Serialize;
+
+use crate::{
+    branch_manager::BranchManagerExt,
+    cleanup::{self, PendingCleanup},
+    operation_journal::OperationJournal,
+    VirtualBranchesExt,
+};
+
+/// The outcome of a bulk operation for a single branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkBranchResult {
+    /// The branch the operation was attempted on, as virtual branch id or reference name.
+    pub branch: String,
+    /// Why the operation failed for this branch, or `None` if it succeeded.
+    pub error: Option<String>,
+}
+
+impl BulkBranchResult {
+    fn new(branch: impl ToString, result: Result<impl Sized>) -> Self {
+        BulkBranchResult {
+            branch: branch.to_string(),
+            error: result.err().map(|err| format!("{err:#}")),
+        }
+    }
+}
+
+/// Delete the virtual branches identified by `branch_ids` from the workspace.
+pub(crate) fn delete_branches(
+    ctx: &CommandContext,
+    branch_ids: &[BranchId],
+    perm: &mut WorktreeWritePermission,
+    journal: &mut OperationJournal,
+) -> Result<Vec<BulkBranchResult>> {
+    let default_target = ctx.project().virtual_branches().get_default_target()?;
+    let target_commit = ctx.repository().find_commit(default_target.sha)?;
+    let branch_manager = ctx.branch_manager().without_snapshots();
+    Ok(branch_ids
+        .iter()
+        .map(|branch_id| {
+            let result = branch_manager.delete_branch(*branch_id, perm, &target_commit);
+            journal.step_completed();
+            BulkBranchResult::new(branch_id, result)
+        })
+        .collect())
+}
+
+/// Unapply the virtual branches identified by `branch_ids`, turning each of them into a real branch.
+pub(crate) fn unapply_branches(
+    ctx: &CommandContext,
+    branch_ids: &[BranchId],
+    perm: &mut WorktreeWritePermission,
+    journal: &mut OperationJournal,
+) -> Result<Vec<BulkBranchResult>> {
+    let branch_manager = ctx.branch_manager().without_snapshots();
+    Ok(branch_ids
+        .iter()
+        .map(|branch_id| {
+            let result = branch_manager.convert_to_real_branch(*branch_id, perm);
+            journal.step_completed();
+            BulkBranchResult::new(branch_id, result)
+        })
+        .collect())
+}
+
+/// Apply the local or remote branches named `branches` to the workspace as virtual branches.
+pub(crate) fn apply_branches(
+    ctx: &CommandContext,
+    branches: &[Refname],
+    perm: &mut WorktreeWritePermission,
+    journal: &mut OperationJournal,
+    progress: &dyn Progress,
+) -> Result<Vec<BulkBranchResult>> {
+    let branch_manager = ctx.branch_manager().without_snapshots();
+    let task = Task::new(progress, "Applying branches");
+    task.set_total(branches.len() as u64);
+    let results = branches
+        .iter()
+        .map(|refname| {
+            // Once cancelled, the branches that weren't applied yet stay as they are.
+            if let Err(err) = task.check_cancelled() {
+                return BulkBranchResult::new(refname, Err::<(), _>(err));
+            }
+            let result = branch_manager.create_virtual_branch_from_branch(refname, None, perm);
+            journal.step_completed();
+            task.inc();
+            BulkBranchResult::new(refname, result)
+        })
+        .collect();
+    if !task.is_cancelled() {
+        task.finish();
+    }
+    Ok(results)
+}
+
+/// Archive all branches that are pending cleanup, regardless of the action configured in the
+/// cleanup policy. Their upstream branches are left alone.
+pub(crate) fn archive_integrated_branches(ctx: &CommandContext) -> Result<Vec<BulkBranchResult>> {
+    Ok(cleanup::pending_cleanups(ctx)?
+        .into_iter()
+        .map(|pending| {
+            let cleanup = PendingCleanup {
+                action: BranchCleanupAction::Archive,
+                remote: None,
+                ..pending
+            };
+            let result = cleanup::clean_up_branch(ctx, &cleanup);
+            BulkBranchResult::new(format!("refs/heads/{}", cleanup.name), result)
+        })
+        .collect())
+}
@@ -0,0 +1,151 @@
+//! Tell which files applying or unapplying a branch would overwrite or delete in the worktree, so callers
+//! can ask before uncommitted changes are touched.
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_reference::Refname;
+use gitbutler_repo::RepositoryExt;
+use serde::Serialize;
+
+use crate::{
+    branch_manager::workspace_tree_without, integration::get_workspace_head,
+    status::get_applied_status, VirtualBranchesExt,
+};
+
+/// The files of the worktree that applying or unapplying a branch would change.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckoutPreview {
+    /// The files that would change, sorted by path.
+    pub files: Vec<WorktreeChange>,
+}
+
+impl CheckoutPreview {
+    /// Return `true` if no file of the worktree would change.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Return `true` if a file with uncommitted changes would be overwritten or deleted.
+    pub fn touches_uncommitted_changes(&self) -> bool {
+        self.files.iter().any(|file| file.uncommitted)
+    }
+}
+
+/// A file of the worktree that would be changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeChange {
+    /// The path of the file, relative to the worktree.
+    pub path: PathBuf,
+    pub kind: WorktreeChangeKind,
+    /// Whether the file has changes that aren't committed to any branch yet.
+    pub uncommitted: bool,
+}
+
+/// How a file of the worktree would be changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WorktreeChangeKind {
+    /// The file would be created, or have its content replaced or merged with conflicts.
+    Overwritten,
+    /// The file would be removed.
+    Deleted,
+}
+
+/// Return the files of the worktree that applying the branch `branch_name` refers to would change, without
+/// changing anything.
+pub(crate) fn preview_apply_branch(
+    ctx: &CommandContext,
+    branch_name: &Refname,
+) -> Result<CheckoutPreview> {
+    let repo = ctx.repository();
+    let default_target = ctx.project().virtual_branches().get_default_target()?;
+    let target_tree = repo
+        .find_commit(default_target.sha)
+        .context("failed to find target commit")?
+        .tree()?;
+    let head_commit = repo
+        .find_reference(&branch_name.to_string())
+        .with_context(|| format!("branch {branch_name} was not found"))?
+        .peel_to_commit()
+        .context("failed to peel to commit")?;
+
+    // Like applying, bring a branch that is behind the target up to date first.
+    let merge_base = repo.merge_base(default_target.sha, head_commit.id())?;
+    let branch_tree = if merge_base == default_target.sha {
+        head_commit.tree()?
+    } else {
+        let merge_base_tree = repo.find_commit(merge_base)?.tree()?;
+        let mut merge_index =
+            repo.merge_trees(&merge_base_tree, &head_commit.tree()?, &target_tree, None)?;
+        if merge_index.has_conflicts() {
+            // The conflicting files are checked out with conflict markers, over the worktree.
+            let wd_tree = repo.get_wd_tree()?;
+            return changes(ctx, &wd_tree, &mut merge_index);
+        }
+        repo.find_tree(merge_index.write_tree_to(repo)?)?
+    };
+
+    let wd_tree = repo.get_wd_tree()?;
+    let mut merge_index = repo
+        .merge_trees(&target_tree, &wd_tree, &branch_tree, None)
+        .context("failed to merge trees")?;
+    changes(ctx, &wd_tree, &mut merge_index)
+}
+
+/// Return the files of the worktree that unapplying the branch with `branch_id` would change, without
+/// changing anything.
+pub(crate) fn preview_unapply_branch(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+) -> Result<CheckoutPreview> {
+    let vb_state = ctx.project().virtual_branches();
+    vb_state.get_branch_in_workspace(branch_id)?;
+    let repo = ctx.repository();
+    let target_commit = repo.find_commit(vb_state.get_default_target()?.sha)?;
+    let applied_statuses = get_applied_status(ctx, None)
+        .context("failed to get status by branch")?
+        .branches;
+
+    let wd_tree = repo.get_wd_tree()?;
+    let final_tree = workspace_tree_without(ctx, applied_statuses, branch_id, &target_commit)?;
+    let mut final_index = git2::Index::new()?;
+    final_index.read_tree(&final_tree)?;
+    changes(ctx, &wd_tree, &mut final_index)
+}
+
+/// Return the files that checking out `index` over the worktree, whose content is `wd_tree`, would change.
+fn changes(
+    ctx: &CommandContext,
+    wd_tree: &git2::Tree,
+    index: &mut git2::Index,
+) -> Result<CheckoutPreview> {
+    let repo = ctx.repository();
+    let head_tree = repo.find_commit(get_workspace_head(ctx)?)?.tree()?;
+    let diff = repo.diff_tree_to_index(Some(wd_tree), Some(index), None)?;
+
+    let mut files = Vec::new();
+    for delta in diff.deltas() {
+        let (kind, file) = match delta.status() {
+            git2::Delta::Deleted => (WorktreeChangeKind::Deleted, delta.old_file()),
+            git2::Delta::Unmodified => continue,
+            _ => (WorktreeChangeKind::Overwritten, delta.new_file()),
+        };
+        let Some(path) = file.path().or_else(|| delta.old_file().path()) else {
+            continue;
+        };
+        let uncommitted = wd_tree.get_path(path).ok().map(|entry| entry.id())
+            != head_tree.get_path(path).ok().map(|entry| entry.id());
+        files.push(WorktreeChange {
+            path: path.to_owned(),
+            kind,
+            uncommitted,
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files.dedup_by(|a, b| a.path == b.path);
+    Ok(CheckoutPreview { files })
+}
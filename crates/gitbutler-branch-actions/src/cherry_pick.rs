@@ -0,0 +1,160 @@
+//! Copy commits from remote branches or other virtual branches onto a virtual branch.
+use std::borrow::Cow;
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::{Branch, BranchEventKind, BranchId};
+use gitbutler_command_context::CommandContext;
+use gitbutler_commit::commit_headers::CommitHeadersV2;
+use gitbutler_error::error::Code;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_repo::{RepoActionsExt, RepositoryExt};
+use serde::Serialize;
+
+use crate::{
+    conflicts::{self, RepoConflictsExt},
+    integration::get_workspace_head,
+    r#virtual::record_branch_event,
+    VirtualBranchesExt,
+};
+
+/// The result of [cherry-picking](cherry_pick()) commits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CherryPickOutcome {
+    /// The ids of the new commits on the branch, in the order they were picked.
+    #[serde(with = "gitbutler_serde::oid_vec")]
+    pub picked: Vec<git2::Oid>,
+    /// The commit that conflicted. Its changes are in the worktree with conflict markers, and the
+    /// conflict API finishes picking it.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub conflicted: Option<git2::Oid>,
+    /// The commits after the conflicting one, which weren't picked.
+    #[serde(with = "gitbutler_serde::oid_vec")]
+    pub skipped: Vec<git2::Oid>,
+}
+
+/// Copy `commits`, oldest first, onto the applied branch identified by `branch_id`. Each copy
+/// remembers the id of its original, so it's recognised when the original shows up upstream.
+///
+/// Picking stops at the first commit that conflicts with the branch, which is then applied to the
+/// worktree with conflict markers so it can be resolved and committed with the conflict API.
+pub(crate) fn cherry_pick(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    commits: &[git2::Oid],
+    _perm: &mut WorktreeWritePermission,
+) -> Result<CherryPickOutcome> {
+    ctx.assure_resolved()?;
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let mut branch = vb_state.get_branch_in_workspace(branch_id)?;
+    let (_, committer) = ctx.signatures()?;
+
+    let mut outcome = CherryPickOutcome {
+        picked: Vec::new(),
+        conflicted: None,
+        skipped: Vec::new(),
+    };
+    let mut head = repo.find_commit(branch.head)?;
+    for (idx, commit_id) in commits.iter().enumerate() {
+        let commit = repo
+            .find_commit(*commit_id)
+            .with_context(|| format!("commit {commit_id} not found"))?;
+        if commit.parent_count() != 1 {
+            return Err(anyhow!("commit {commit_id} has to have exactly one parent"))
+                .context(Code::Validation);
+        }
+        let mut index = repo.cherrypick_commit(&commit, &head, 0, None)?;
+        if index.has_conflicts() {
+            outcome.conflicted = Some(*commit_id);
+            outcome.skipped = commits[idx + 1..].to_vec();
+            break;
+        }
+        let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+        let headers = CommitHeadersV2 {
+            cherry_picked_from: Some(commit_id.to_string()),
+            ..CommitHeadersV2::new()
+        };
+        let new_id = repo.commit_with_signature(
+            None,
+            &commit.author(),
+            &committer,
+            commit.message().unwrap_or_default(),
+            &tree,
+            &[&head],
+            Some(headers),
+        )?;
+        outcome.picked.push(new_id);
+        head = repo.find_commit(new_id)?;
+    }
+
+    if !outcome.picked.is_empty() {
+        set_branch_head(ctx, &mut branch, &head)?;
+    }
+
+    if let Some(conflicted) = outcome.conflicted {
+        let commit = repo.find_commit(conflicted)?;
+        apply_conflicted(ctx, &commit.parent(0)?.tree()?, &commit.tree()?)?;
+        conflicts::mark_cherry_pick(ctx, conflicted)?;
+    }
+    Ok(outcome)
+}
+
+/// Make `head`, a descendant of the current head of `branch`, the new head of `branch`, and bring
+/// the changes between both into the worktree.
+pub(crate) fn set_branch_head(
+    ctx: &CommandContext,
+    branch: &mut Branch,
+    head: &git2::Commit<'_>,
+) -> Result<()> {
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let integration_tree = repo.find_commit(get_workspace_head(ctx)?)?.tree()?;
+    let mut merge_index =
+        repo.merge_trees(&integration_tree, &head.tree()?, &repo.get_wd_tree()?, None)?;
+    if merge_index.has_conflicts() {
+        return Err(anyhow!(
+            "the new commits conflict with uncommitted changes, commit or shelve them first"
+        ))
+        .context(Code::Validation);
+    }
+    branch.head = head.id();
+    branch.tree = head.tree_id();
+    branch.updated_timestamp_ms = gitbutler_time::time::now_ms();
+    vb_state.set_branch(branch.clone())?;
+    record_branch_event(
+        ctx,
+        branch.id,
+        BranchEventKind::CommitCreated { commit: head.id() },
+    );
+    repo.checkout_index_builder(&mut merge_index)
+        .force()
+        .checkout()?;
+    crate::integration::update_gitbutler_integration(&vb_state, ctx)?;
+    Ok(())
+}
+
+/// Apply the changes from `base_tree` to `their_tree` to the worktree with conflict markers, and
+/// mark the conflicts so they can be resolved with the conflict API.
+pub(crate) fn apply_conflicted(
+    ctx: &CommandContext,
+    base_tree: &git2::Tree<'_>,
+    their_tree: &git2::Tree<'_>,
+) -> Result<()> {
+    let repo = ctx.repository();
+    let mut merge_index = repo.merge_trees(base_tree, &repo.get_wd_tree()?, their_tree, None)?;
+    let merge_conflicts = merge_index
+        .conflicts()?
+        .flatten()
+        .filter_map(|c| c.our.or(c.their))
+        .map(|entry| gix::path::try_from_bstr(Cow::Owned(entry.path.into())))
+        .collect::<Result<Vec<_>, _>>()?;
+    conflicts::mark(ctx, merge_conflicts, None)?;
+    conflicts::record_stages(ctx, &merge_index)?;
+    repo.checkout_index_builder(&mut merge_index)
+        .allow_conflicts()
+        .conflict_style_merge()
+        .force()
+        .checkout()?;
+    Ok(())
+}
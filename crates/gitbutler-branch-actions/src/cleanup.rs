@@ -0,0 +1,220 @@
+//! Propose local branches that were integrated into the target a while ago for cleanup, and
+//! archive or delete them once the user reviewed them.
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::IntegratedBranchesHandle;
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::BranchCleanupAction;
+use gitbutler_reference::RemoteRefname;
+use gitbutler_repo::{credentials::Helper, ForcePush, RepoActionsExt, RepositoryExt};
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use serde::Serialize;
+
+use crate::VirtualBranchesExt;
+
+/// The prefix under which archived branches are kept, like `refs/archive/feature` for `refs/heads/feature`.
+const ARCHIVE_REF_PREFIX: &str = "refs/archive/";
+
+/// A local branch that can be cleaned up as it's integrated into the target for long enough.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingCleanup {
+    /// The name of the branch, like `feature` for `refs/heads/feature`.
+    pub name: String,
+    #[serde(with = "gitbutler_serde::oid")]
+    pub head: git2::Oid,
+    /// The time at which the branch was first seen to be integrated, in milliseconds since the Unix epoch.
+    pub integrated_since_ms: i64,
+    /// What will happen to the branch.
+    pub action: BranchCleanupAction,
+    /// The upstream branch that will be deleted on its remote as well, if any.
+    pub remote: Option<RemoteRefname>,
+}
+
+/// Return the local branches that are integrated into the target for longer than the cleanup policy
+/// of the project allows, and remember since when all others are integrated.
+///
+/// The branch checked out, the target branch, branches applied in the workspace and GitButler's own
+/// branches are never proposed.
+pub(crate) fn pending_cleanups(ctx: &CommandContext) -> Result<Vec<PendingCleanup>> {
+    let policy = &ctx.project().branch_cleanup;
+    if !policy.enabled {
+        return Ok(Vec::new());
+    }
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    let upstream_head = repo
+        .find_branch_by_refname(&target.branch.clone().into())?
+        .ok_or_else(|| anyhow!("failed to find target branch {}", target.branch))?
+        .get()
+        .peel_to_commit()?;
+    let applied_sources: Vec<String> = vb_state
+        .list_branches_in_workspace()?
+        .into_iter()
+        .filter_map(|branch| branch.source_refname.map(|refname| refname.to_string()))
+        .collect();
+    let inmemory_repo = repo.in_memory_repo()?;
+
+    let mut integrated = Vec::new();
+    for branch in repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.name()?.map(ToOwned::to_owned) else {
+            continue;
+        };
+        if branch.is_head()
+            || name == target.branch.branch()
+            || name.starts_with("gitbutler/")
+            || applied_sources.contains(&format!("refs/heads/{name}"))
+        {
+            continue;
+        }
+        let head = branch.get().peel_to_commit()?;
+        if is_integrated(repo, &inmemory_repo, &head, &upstream_head)? {
+            integrated.push((name, head.id()));
+        }
+    }
+
+    let now_ms = now_since_unix_epoch_ms();
+    let grace_period_ms = i64::try_from(policy.grace_period().as_millis()).unwrap_or(i64::MAX);
+    IntegratedBranchesHandle::new(ctx.project().gb_dir())
+        .observe(integrated, now_ms)?
+        .into_iter()
+        .filter(|branch| now_ms.saturating_sub(branch.integrated_since_ms) >= grace_period_ms)
+        .map(|branch| {
+            let remote = if policy.delete_remote {
+                integrated_upstream(repo, &inmemory_repo, &branch.name, &upstream_head)?
+            } else {
+                None
+            };
+            Ok(PendingCleanup {
+                name: branch.name,
+                head: branch.head,
+                integrated_since_ms: branch.integrated_since_ms,
+                action: policy.action,
+                remote,
+            })
+        })
+        .collect()
+}
+
+/// Archive or delete the branches named `names`, which must all be pending cleanup, along with
+/// their upstream branches if the cleanup policy says so.
+pub(crate) fn clean_up(ctx: &CommandContext, names: &[String]) -> Result<()> {
+    let pending = pending_cleanups(ctx)?;
+    let cleanups = names
+        .iter()
+        .map(|name| {
+            pending
+                .iter()
+                .find(|cleanup| &cleanup.name == name)
+                .ok_or_else(|| anyhow!("branch '{name}' isn't pending cleanup"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    for cleanup in cleanups {
+        clean_up_branch(ctx, cleanup)?;
+    }
+    Ok(())
+}
+
+/// Archive or delete the branch of `cleanup`, along with its upstream branch if it has one.
+pub(crate) fn clean_up_branch(ctx: &CommandContext, cleanup: &PendingCleanup) -> Result<()> {
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    if let Some(remote) = &cleanup.remote {
+        ctx.push(
+            &cleanup.head,
+            remote,
+            ForcePush::No,
+            &Helper::default(),
+            Some(format!(":refs/heads/{}", remote.branch())),
+            None,
+        )
+        .with_context(|| format!("failed to delete {remote} on its remote"))?;
+        if let Ok(mut reference) = repo.find_reference(&remote.to_string()) {
+            reference.delete()?;
+        }
+    }
+
+    if cleanup.action == BranchCleanupAction::Archive {
+        repo.reference(
+            &format!("{ARCHIVE_REF_PREFIX}{}", cleanup.name),
+            cleanup.head,
+            true,
+            &format!("archived integrated branch {}", cleanup.name),
+        )?;
+    }
+    repo.find_branch(&cleanup.name, git2::BranchType::Local)?
+        .delete()?;
+
+    let refname = format!("refs/heads/{}", cleanup.name);
+    let vbranch = vb_state.list_all_branches()?.into_iter().find(|branch| {
+        !branch.in_workspace
+            && branch
+                .source_refname
+                .as_ref()
+                .map_or(false, |source_refname| {
+                    source_refname.to_string() == refname
+                })
+    });
+    if let Some(vbranch) = vbranch {
+        vb_state.delete_branch_entry(&vbranch.id)?;
+        ctx.project().branch_activity().forget_branch(vbranch.id)?;
+    }
+    Ok(())
+}
+
+/// Return the upstream of the local branch `name` if it's integrated into `upstream_head` as well.
+fn integrated_upstream(
+    repo: &git2::Repository,
+    inmemory_repo: &git2::Repository,
+    name: &str,
+    upstream_head: &git2::Commit<'_>,
+) -> Result<Option<RemoteRefname>> {
+    let Ok(upstream) = repo.find_branch(name, git2::BranchType::Local)?.upstream() else {
+        return Ok(None);
+    };
+    let Some(refname) = upstream.get().name() else {
+        return Ok(None);
+    };
+    let refname = RemoteRefname::from_str(refname)?;
+    let head = upstream.get().peel_to_commit()?;
+    Ok(is_integrated(repo, inmemory_repo, &head, upstream_head)?.then_some(refname))
+}
+
+/// Return `true` if all changes of `commit` are contained in `upstream_head`, either because it's
+/// reachable from it, or because merging it wouldn't change anything as after a squash merge.
+fn is_integrated(
+    repo: &git2::Repository,
+    inmemory_repo: &git2::Repository,
+    commit: &git2::Commit<'_>,
+    upstream_head: &git2::Commit<'_>,
+) -> Result<bool> {
+    if commit.id() == upstream_head.id()
+        || repo.graph_descendant_of(upstream_head.id(), commit.id())?
+    {
+        return Ok(true);
+    }
+    let Ok(merge_base_id) = repo.merge_base(upstream_head.id(), commit.id()) else {
+        // unrelated histories
+        return Ok(false);
+    };
+    let merge_base = repo.find_commit(merge_base_id)?;
+    let mut merge_index = repo
+        .merge_trees(
+            &merge_base.tree()?,
+            &commit.tree()?,
+            &upstream_head.tree()?,
+            None,
+        )
+        .context("failed to merge trees")?;
+    if merge_index.has_conflicts() {
+        return Ok(false);
+    }
+    let merge_tree_id = merge_index
+        .write_tree_to(inmemory_repo)
+        .context("failed to write tree")?;
+    Ok(merge_tree_id == upstream_head.tree_id())
+}
@@ -0,0 +1,140 @@
+//! Find commits whose time can't be right, like those made on a machine whose clock was off, which is common
+//! with virtual machines. Such commits sort wrongly wherever commits are ordered by time, so they are flagged
+//! in listings and their times can be normalized.
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_commit::commit_headers::HasCommitHeaders;
+use gitbutler_repo::{LogUntil, RepoActionsExt, RepositoryExt};
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use serde::Serialize;
+
+use crate::{conflicts::RepoConflictsExt, pushed_commits, VirtualBranchesExt};
+
+/// How far in the future a commit may be before it's flagged, to allow for clocks that are slightly off.
+const MAX_FUTURE_SECONDS: i64 = 5 * 60;
+
+/// How much older than its parent a commit may be before it's flagged, as rebasing and cherry-picking
+/// legitimately keep the time of the original commit.
+const MAX_BEFORE_PARENT_SECONDS: i64 = 24 * 60 * 60;
+
+/// Why the time of a commit can't be right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClockSkew {
+    /// The commit was made in the future.
+    InFuture,
+    /// The commit was made long before one of its parents, which it can only have been made after.
+    BeforeParent,
+}
+
+/// Return why the time of `commit` can't be right, if it can't, with `now` in seconds since the Unix epoch.
+pub(crate) fn detect(commit: &git2::Commit, now: i64) -> Option<ClockSkew> {
+    let time = commit.time().seconds();
+    if time > now + MAX_FUTURE_SECONDS {
+        return Some(ClockSkew::InFuture);
+    }
+    commit
+        .parents()
+        .any(|parent| parent.time().seconds() > time + MAX_BEFORE_PARENT_SECONDS)
+        .then_some(ClockSkew::BeforeParent)
+}
+
+/// Return the current time in seconds since the Unix epoch, to [detect] skew with.
+pub(crate) fn now() -> i64 {
+    now_since_unix_epoch_ms() / 1000
+}
+
+/// Rewrite the commits of the branch with `branch_id` whose time can't be right, setting it to the time of
+/// their latest parent if they were made before it, or to now if they were made in the future.
+/// Their descendants are rewritten on top of them and keep their times.
+///
+/// Returns the new head of the branch, which is unchanged if no commit is skewed.
+pub(crate) fn normalize_commit_times(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    rewrite_pushed: bool,
+) -> Result<git2::Oid> {
+    ctx.assure_unconflicted()?;
+    let vb_state = ctx.project().virtual_branches();
+    let default_target = vb_state.get_default_target()?;
+    let mut branch = vb_state.get_branch_in_workspace(branch_id)?;
+    let repo = ctx.repository();
+
+    let mut commits = ctx.log(
+        branch.head,
+        LogUntil::Commit(branch.base(default_target.sha)),
+    )?;
+    commits.reverse();
+    let now = now();
+    let Some(first_skewed) = commits
+        .iter()
+        .position(|commit| detect(commit, now).is_some())
+    else {
+        return Ok(branch.head);
+    };
+    let to_rewrite = &commits[first_skewed..];
+    pushed_commits::ensure_rewritable(
+        ctx,
+        &branch,
+        &to_rewrite.iter().map(git2::Commit::id).collect::<Vec<_>>(),
+        rewrite_pushed,
+    )?;
+
+    let mut rewritten = HashMap::new();
+    for commit in to_rewrite {
+        let parents = commit
+            .parents()
+            .map(|parent| match rewritten.get(&parent.id()) {
+                Some(id) => repo.find_commit(*id),
+                None => Ok(parent),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let earliest = parents.iter().map(|parent| parent.time().seconds()).max();
+        let new_id = repo
+            .commit_with_signature(
+                None,
+                &with_normalized_time(&commit.author(), earliest, now)?,
+                &with_normalized_time(&commit.committer(), earliest, now)?,
+                commit.message().unwrap_or_default(),
+                &commit.tree().context("failed to find tree")?,
+                &parents.iter().collect::<Vec<_>>(),
+                commit.gitbutler_headers(),
+            )
+            .context("failed to commit")?;
+        rewritten.insert(commit.id(), new_id);
+    }
+
+    branch.head = rewritten[&branch.head];
+    branch.updated_timestamp_ms = gitbutler_time::time::now_ms();
+    vb_state.set_branch(branch.clone())?;
+    crate::integration::update_gitbutler_integration(&vb_state, ctx)
+        .context("failed to update gitbutler integration")?;
+    Ok(branch.head)
+}
+
+/// Return `signature` with its time moved to `earliest` if it was made well before it, or to `now`
+/// if it was made in the future, keeping its time zone.
+fn with_normalized_time(
+    signature: &git2::Signature<'_>,
+    earliest: Option<i64>,
+    now: i64,
+) -> Result<git2::Signature<'static>> {
+    let when = signature.when();
+    let mut seconds = when.seconds();
+    if seconds > now + MAX_FUTURE_SECONDS {
+        seconds = now;
+    }
+    if let Some(earliest) = earliest {
+        if earliest > seconds + MAX_BEFORE_PARENT_SECONDS {
+            seconds = earliest;
+        }
+    }
+    Ok(git2::Signature::new(
+        &String::from_utf8_lossy(signature.name_bytes()),
+        &String::from_utf8_lossy(signature.email_bytes()),
+        &git2::Time::new(seconds, when.offset_minutes()),
+    )?)
+}
@@ -1,5 +1,6 @@
 use crate::{
-    author::Author,
+    author::{self, Author},
+    clock_skew::{self, ClockSkew},
     file::{list_virtual_commit_files, VirtualBranchFile},
 };
 use anyhow::{Context, Result};
@@ -7,6 +8,7 @@ use gitbutler_branch::{Branch, BranchId};
 use gitbutler_command_context::CommandContext;
 use gitbutler_commit::commit_ext::CommitExt;
 use gitbutler_serde::BStringForFrontend;
+use gitbutler_time::time::now_since_unix_epoch_ms;
 use serde::Serialize;
 
 // this is the struct that maps to the view `Commit` type in Typescript
@@ -24,7 +26,9 @@ pub struct VirtualBranchCommit {
     pub id: git2::Oid,
     pub description: BStringForFrontend,
     pub created_at: u128,
+    pub created_at_display: String,
     pub author: Author,
+    pub author_display: String,
     pub is_remote: bool,
     pub files: Vec<VirtualBranchFile>,
     pub is_integrated: bool,
@@ -33,6 +37,8 @@ pub struct VirtualBranchCommit {
     pub branch_id: BranchId,
     pub change_id: Option<String>,
     pub is_signed: bool,
+    /// Set if the time of the commit can't be right, like if it's in the future.
+    pub clock_skew: Option<ClockSkew>,
 }
 
 pub(crate) fn commit_to_vbranch_commit(
@@ -41,6 +47,7 @@ pub(crate) fn commit_to_vbranch_commit(
     commit: &git2::Commit,
     is_integrated: bool,
     is_remote: bool,
+    mailmap: Option<&git2::Mailmap>,
 ) -> Result<VirtualBranchCommit> {
     let timestamp = u128::try_from(commit.time().seconds())?;
     let message = commit.message_bstr().to_owned();
@@ -56,10 +63,18 @@ pub(crate) fn commit_to_vbranch_commit(
         })
         .collect::<Vec<_>>();
 
+    let format = &repository.project().listing_format;
+    let author: Author = author::resolve(mailmap, commit.author()).into();
     let commit = VirtualBranchCommit {
         id: commit.id(),
         created_at: timestamp * 1000,
-        author: commit.author().into(),
+        created_at_display: format.format_time(
+            commit.time().seconds(),
+            commit.time().offset_minutes(),
+            now_since_unix_epoch_ms() / 1000,
+        ),
+        author_display: format.format_author(&author.name, &author.email),
+        author,
         description: message.into(),
         is_remote,
         files,
@@ -68,6 +83,7 @@ pub(crate) fn commit_to_vbranch_commit(
         branch_id: branch.id,
         change_id: commit.change_id(),
         is_signed: commit.is_signed(),
+        clock_skew: clock_skew::detect(commit, clock_skew::now()),
     };
 
     Ok(commit)
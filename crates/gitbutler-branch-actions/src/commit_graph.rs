@@ -0,0 +1,214 @@
+//! Lay out the graph of the commits of the applied branches and the target, so all frontends draw the same
+//! graph without each having to lay out thousands of commits themselves.
+//!
+//! Commits are placed in rows from the most recent one, and in columns, the lanes, that each follow the
+//! first parents of a commit. Lanes are reused once they end, so the graph stays as narrow as it can be
+//! without lines crossing commits.
+use std::collections::HashMap;
+
+use anyhow::Result;
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use serde::Serialize;
+
+use crate::VirtualBranchesExt;
+
+/// The layout of the commit graph.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitGraph {
+    /// One row for each commit, with the most recent commits first.
+    pub rows: Vec<GraphRow>,
+    /// The amount of columns needed to draw all rows.
+    pub columns: usize,
+    /// Whether there are older commits than those of the last row that were left out.
+    pub truncated: bool,
+}
+
+/// A commit of the graph, along with the lines that go down from its row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphRow {
+    #[serde(with = "gitbutler_serde::oid")]
+    pub commit_id: git2::Oid,
+    /// The column the commit is drawn in.
+    pub column: usize,
+    /// The branches that point to the commit.
+    pub refs: Vec<GraphRef>,
+    /// The lines from this row to the next one, sorted and without duplicates.
+    pub edges: Vec<GraphEdge>,
+}
+
+/// A line from the column `from` in a row to the column `to` in the next row. Lines from the column of the
+/// commit of the row start at the commit, all others pass through the row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+}
+
+/// A branch that points to a commit of the graph.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum GraphRef {
+    /// The head of an applied virtual branch.
+    #[serde(rename_all = "camelCase")]
+    VirtualBranch { branch_id: BranchId, name: String },
+    /// A remote branch, like the upstream of a virtual branch or the target branch.
+    Remote { name: String },
+    /// The commit of the target the workspace is based on.
+    Base { name: String },
+    /// A tag, which only decorates the entries of the history of a branch.
+    Tag { name: String },
+}
+
+/// Lay out the graph of the commits reachable from the applied virtual branches, their upstream branches
+/// and the target of the workspace of `ctx`, with at most `limit` commits.
+pub(crate) fn commit_graph(ctx: &CommandContext, limit: usize) -> Result<CommitGraph> {
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+
+    let mut refs = HashMap::<git2::Oid, Vec<GraphRef>>::new();
+    let remote_ref = |refs: &mut HashMap<git2::Oid, Vec<GraphRef>>, name: String| {
+        if let Ok(id) = repo.refname_to_id(&name) {
+            refs.entry(id).or_default().push(GraphRef::Remote { name });
+        }
+    };
+    let mut branches = vb_state.list_branches_in_workspace()?;
+    branches.sort_by_key(|branch| branch.order);
+    for branch in branches {
+        refs.entry(branch.head)
+            .or_default()
+            .push(GraphRef::VirtualBranch {
+                branch_id: branch.id,
+                name: branch.name,
+            });
+        if let Some(upstream) = branch.upstream {
+            remote_ref(&mut refs, upstream.to_string());
+        }
+    }
+    refs.entry(target.sha).or_default().push(GraphRef::Base {
+        name: target.branch.to_string(),
+    });
+    remote_ref(&mut refs, target.branch.to_string());
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+    let mut tips: Vec<_> = refs.keys().copied().collect();
+    tips.sort();
+    for tip in tips {
+        revwalk.push(tip)?;
+    }
+    let mut commits = Vec::new();
+    let mut truncated = false;
+    for id in revwalk {
+        if commits.len() == limit {
+            truncated = true;
+            break;
+        }
+        let commit = repo.find_commit(id?)?;
+        commits.push((commit.id(), commit.parent_ids().collect::<Vec<_>>()));
+    }
+
+    let mut graph = layout(&commits);
+    graph.truncated = truncated;
+    for row in &mut graph.rows {
+        row.refs = refs.remove(&row.commit_id).unwrap_or_default();
+    }
+    Ok(graph)
+}
+
+/// Lay out `commits`, each with the ids of its parents, which are sorted so children come before their
+/// parents.
+fn layout(commits: &[(git2::Oid, Vec<git2::Oid>)]) -> CommitGraph {
+    // The commit each lane leads to, or `None` if the lane is free.
+    let mut lanes: Vec<Option<git2::Oid>> = Vec::new();
+    let mut columns = 0;
+    let mut rows = Vec::with_capacity(commits.len());
+    for (index, (commit_id, parent_ids)) in commits.iter().enumerate() {
+        // All lanes that lead to the commit end in it, and it's drawn in the leftmost one.
+        let column = match lanes.iter().position(|lane| *lane == Some(*commit_id)) {
+            Some(column) => column,
+            None => free_lane(&mut lanes),
+        };
+        for lane in &mut lanes {
+            if *lane == Some(*commit_id) {
+                *lane = None;
+            }
+        }
+
+        // The first parent continues the lane of the commit, other parents join the lanes that already
+        // lead to them, or get new lanes.
+        let mut parent_lanes = Vec::with_capacity(parent_ids.len());
+        let mut new_lanes = Vec::with_capacity(parent_ids.len());
+        for (nth, parent_id) in parent_ids.iter().enumerate() {
+            let lane = if nth == 0 {
+                lanes[column] = Some(*parent_id);
+                new_lanes.push(column);
+                column
+            } else if let Some(lane) = lanes.iter().position(|lane| *lane == Some(*parent_id)) {
+                lane
+            } else {
+                let lane = free_lane(&mut lanes);
+                lanes[lane] = Some(*parent_id);
+                new_lanes.push(lane);
+                lane
+            };
+            parent_lanes.push(lane);
+        }
+        columns = columns.max(lanes.len());
+
+        // Lanes that lead to the next commit bend towards the column it will be drawn in.
+        let next = commits.get(index + 1).map(|(id, _)| *id);
+        let next_column = next.and_then(|next| lanes.iter().position(|lane| *lane == Some(next)));
+        let destination = |lane: usize| match next_column {
+            Some(next_column) if lanes[lane] == next => next_column,
+            _ => lane,
+        };
+        // Lanes that started before this row pass through it, and the commit connects to the lanes of
+        // its parents.
+        let mut edges: Vec<_> = lanes
+            .iter()
+            .enumerate()
+            .filter(|(lane, leads_to)| leads_to.is_some() && !new_lanes.contains(lane))
+            .map(|(lane, _)| GraphEdge {
+                from: lane,
+                to: destination(lane),
+            })
+            .chain(parent_lanes.iter().map(|lane| GraphEdge {
+                from: column,
+                to: destination(*lane),
+            }))
+            .collect();
+        edges.sort();
+        edges.dedup();
+
+        rows.push(GraphRow {
+            commit_id: *commit_id,
+            column,
+            refs: Vec::new(),
+            edges,
+        });
+        while lanes.last() == Some(&None) {
+            lanes.pop();
+        }
+    }
+    CommitGraph {
+        rows,
+        columns,
+        truncated: false,
+    }
+}
+
+/// Return the leftmost free lane of `lanes`, which is added if there is none.
+fn free_lane(lanes: &mut Vec<Option<git2::Oid>>) -> usize {
+    match lanes.iter().position(Option::is_none) {
+        Some(lane) => lane,
+        None => {
+            lanes.push(None);
+            lanes.len() - 1
+        }
+    }
+}
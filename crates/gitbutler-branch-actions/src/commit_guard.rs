@@ -0,0 +1,132 @@
+//! Notice when files change while a commit is built from them, so a commit never captures a file that
+//! is only half-written, like an editor buffer that is being saved.
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::{Hunk, HunkHash};
+use serde::Serialize;
+
+use crate::{integration::get_workspace_head, workdir_cache::workdir_diff_of_paths};
+
+/// How often building a commit is attempted before giving up on files that keep changing.
+pub(crate) const MAX_ATTEMPTS: usize = 3;
+/// How long to let writes to the worktree settle before building a commit again.
+pub(crate) const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Files that changed while a commit was built from them, and kept changing when trying again.
+///
+/// It's returned as error along with [`Code::FilesChanged`](gitbutler_error::error::Code::FilesChanged).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilesChangedDuringCommit {
+    /// The worktree-relative paths of the files that changed.
+    pub paths: Vec<PathBuf>,
+}
+
+impl fmt::Display for FilesChangedDuringCommit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "nothing was committed as {} file(s) kept changing while the commit was created: ",
+            self.paths.len()
+        )?;
+        for (index, path) in self.paths.iter().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FilesChangedDuringCommit {}
+
+/// What a file in the worktree looked like at one point in time.
+#[derive(Debug, PartialEq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    id: git2::Oid,
+}
+
+/// The stamps of the files a commit is built from, or `None` for files that don't exist.
+pub(crate) struct FileStamps(BTreeMap<PathBuf, Option<Stamp>>);
+
+impl FileStamps {
+    /// Stamp the files at `paths`, relative to the worktree of `ctx`.
+    pub(crate) fn read<'a>(
+        ctx: &CommandContext,
+        paths: impl IntoIterator<Item = &'a PathBuf>,
+    ) -> Result<Self> {
+        let worktree = ctx.project().worktree_path();
+        paths
+            .into_iter()
+            .map(|path| Ok((path.clone(), stamp(&worktree.join(path))?)))
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+
+    /// Return the paths of the files that aren't the same anymore as when they were stamped.
+    pub(crate) fn changed(&self, ctx: &CommandContext) -> Result<Vec<PathBuf>> {
+        let worktree = ctx.project().worktree_path();
+        let mut changed = Vec::new();
+        for (path, before) in &self.0 {
+            if stamp(&worktree.join(path))? != *before {
+                changed.push(path.clone());
+            }
+        }
+        Ok(changed)
+    }
+}
+
+fn stamp(path: &Path) -> Result<Option<Stamp>> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    // Directories are submodules, whose changes aren't read from files.
+    let id = if metadata.is_file() {
+        git2::Oid::hash_file(git2::ObjectType::Blob, path)?
+    } else {
+        git2::Oid::zero()
+    };
+    Ok(Some(Stamp {
+        modified: metadata.modified().ok(),
+        len: metadata.len(),
+        id,
+    }))
+}
+
+/// Return the paths of the files whose `hunks`, as the status listed them, aren't in the worktree anymore,
+/// as the files changed since the status was computed.
+pub(crate) fn changed_since_status(
+    ctx: &CommandContext,
+    hunks: &BTreeMap<PathBuf, Vec<HunkHash>>,
+) -> Result<Vec<PathBuf>> {
+    let paths: Vec<_> = hunks.keys().cloned().collect();
+    let diffs = workdir_diff_of_paths(ctx, get_workspace_head(ctx)?, &paths)?;
+    Ok(hunks
+        .iter()
+        .filter(|(path, hunks)| {
+            let current: HashSet<_> = diffs
+                .get(*path)
+                .map(|diff| {
+                    diff.hunks
+                        .iter()
+                        .map(|hunk| Hunk::hash_diff(&hunk.diff_lines))
+                        .collect()
+                })
+                .unwrap_or_default();
+            !hunks.iter().all(|hunk| current.contains(hunk))
+        })
+        .map(|(path, _)| path.clone())
+        .collect())
+}
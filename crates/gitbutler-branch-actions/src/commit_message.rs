@@ -0,0 +1,94 @@
+//! The templates for commit messages and the conventions messages are checked against before
+//! commits are created.
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::CommitConventions;
+use serde::Serialize;
+
+/// A template to start new commit messages with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitTemplate {
+    pub text: String,
+    pub source: CommitTemplateSource,
+}
+
+/// Where a [`CommitTemplate`] comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum CommitTemplateSource {
+    /// The commit conventions of the project.
+    Project,
+    /// The file `commit.template` of the Git configuration points to.
+    #[serde(rename_all = "camelCase")]
+    GitConfig { path: PathBuf },
+}
+
+/// Return the template of the project, or the one `commit.template` of the Git configuration points
+/// to, or `None` if there is neither.
+pub(crate) fn template(ctx: &CommandContext) -> Result<Option<CommitTemplate>> {
+    if let Some(text) = &ctx.project().commit_conventions.template {
+        return Ok(Some(CommitTemplate {
+            text: text.clone(),
+            source: CommitTemplateSource::Project,
+        }));
+    }
+
+    let config = ctx.repository().config()?;
+    let Ok(path) = config.get_path("commit.template") else {
+        return Ok(None);
+    };
+    // Like Git, relative paths are resolved against the worktree.
+    let path = ctx.project().worktree_path().join(path);
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read commit template at {}", path.display()))?;
+    Ok(Some(CommitTemplate {
+        text,
+        source: CommitTemplateSource::GitConfig { path },
+    }))
+}
+
+/// Fail with the rule of the `conventions` that `message` violates, if any.
+pub(crate) fn check_conventions(conventions: &CommitConventions, message: &str) -> Result<()> {
+    let mut lines = message.lines();
+    let subject = lines.next().unwrap_or_default();
+
+    if let Some(pattern) = &conventions.subject_pattern {
+        let regex = regex::Regex::new(pattern)
+            .with_context(|| {
+                format!("the subject pattern '{pattern}' of the commit conventions is invalid")
+            })
+            .context(Code::Validation)?;
+        if !regex.is_match(subject) {
+            return Err(violation(format!(
+                "the subject '{subject}' doesn't match the pattern '{pattern}'"
+            )));
+        }
+    }
+    if let Some(max_subject_length) = conventions.max_subject_length {
+        let length = subject.chars().count();
+        if length > max_subject_length {
+            return Err(violation(format!(
+                "the subject has {length} characters, but may have at most {max_subject_length}"
+            )));
+        }
+    }
+    if conventions.blank_line_after_subject {
+        if let Some(line) = lines.next() {
+            if !line.trim().is_empty() {
+                return Err(violation(
+                    "the subject has to be followed by a blank line".to_owned(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn violation(rule: String) -> anyhow::Error {
+    anyhow!("commit message violates the commit conventions of the project: {rule}")
+        .context(Code::Validation)
+}
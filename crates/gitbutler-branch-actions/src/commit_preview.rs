@@ -0,0 +1,95 @@
+//! Tell what a commit would contain and what's noteworthy about its message, without committing.
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use gitbutler_branch::{BranchId, BranchOwnershipClaims};
+use gitbutler_command_context::CommandContext;
+use serde::Serialize;
+
+use crate::{
+    message_check::{check_message, MessageFinding},
+    status::get_applied_status,
+};
+
+/// What committing the uncommitted changes of a branch with a message would do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitPreview {
+    /// The files that would be committed, in the order of the branch.
+    pub files: Vec<CommitPreviewFile>,
+    /// The rule of the commit conventions of the project that the message violates, which prevents committing.
+    pub convention_violation: Option<String>,
+    /// What the message checkers noticed about the message.
+    pub message_findings: Vec<MessageFinding>,
+}
+
+impl CommitPreview {
+    /// Return `true` if committing would fail because of the message.
+    pub fn is_blocked(&self) -> bool {
+        self.convention_violation.is_some()
+            || self
+                .message_findings
+                .iter()
+                .any(|finding| finding.severity == crate::FindingSeverity::Error)
+    }
+}
+
+/// A file that would be committed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitPreviewFile {
+    /// The path of the file, relative to the worktree.
+    pub path: PathBuf,
+    /// The amount of its hunks that would be committed.
+    pub hunks: usize,
+}
+
+/// Return what committing the changes of the branch identified by `branch_id` with `message` would do,
+/// limited to `ownership` if it's set, like [`commit()`](crate::VirtualBranchActions::create_commit()).
+pub(crate) fn commit_preview(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    message: &str,
+    ownership: Option<&BranchOwnershipClaims>,
+) -> Result<CommitPreview> {
+    let (_, files) = get_applied_status(ctx, None)?
+        .branches
+        .into_iter()
+        .find(|(branch, _)| branch.id == branch_id)
+        .with_context(|| format!("branch {branch_id} not found"))?;
+
+    let files = files
+        .into_iter()
+        .filter_map(|file| {
+            let claim = ownership
+                .map(|ownership| ownership.claims.iter().find(|f| f.file_path == file.path));
+            let hunks = file
+                .hunks
+                .iter()
+                .filter(|hunk| {
+                    claim.map_or(true, |claim| {
+                        claim.map_or(false, |f| {
+                            f.hunks
+                                .iter()
+                                .any(|h| h.start == hunk.start && h.end == hunk.end)
+                        })
+                    })
+                })
+                .count();
+            (hunks > 0).then_some(CommitPreviewFile {
+                path: file.path,
+                hunks,
+            })
+        })
+        .collect();
+
+    let convention_violation =
+        crate::commit_message::check_conventions(&ctx.project().commit_conventions, message)
+            .err()
+            .map(|err| err.to_string());
+    Ok(CommitPreview {
+        files,
+        convention_violation,
+        message_findings: check_message(message),
+    })
+}
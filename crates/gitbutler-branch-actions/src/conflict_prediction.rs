@@ -0,0 +1,158 @@
+//! Predict conflicts between applied branches before they happen, by finding the lines of files that the
+//! commits of more than one branch change.
+use std::{collections::BTreeMap, ops::Range, path::PathBuf};
+
+use anyhow::Result;
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use serde::Serialize;
+
+use crate::VirtualBranchesExt;
+
+/// Two applied branches whose commits change the same or adjacent lines of files.
+///
+/// They apply side by side in the workspace, but once one of them is merged, the other one
+/// will conflict when it's updated or pushed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PredictedConflict {
+    pub branch_ids: [BranchId; 2],
+    pub files: Vec<OverlappingFile>,
+}
+
+/// A file that is changed by the commits of two branches in overlapping places.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlappingFile {
+    /// The worktree-relative path of the file.
+    pub path: PathBuf,
+    /// The 1-based lines of the file as of the base of both branches that are changed by both of them,
+    /// with exclusive ends, or empty if the file is binary and conflicts as a whole.
+    pub lines: Vec<Range<u32>>,
+}
+
+/// The changed lines of each file, or `None` if the file is binary.
+type ChangedLines = BTreeMap<PathBuf, Option<Vec<Range<u32>>>>;
+
+/// Find all pairs of applied branches whose commits change overlapping lines, in the order of the branches.
+///
+/// Only branches with the same base are compared, so branches that are pinned to a different base are skipped.
+pub(crate) fn predict_conflicts(ctx: &CommandContext) -> Result<Vec<PredictedConflict>> {
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    let mut branches = vb_state.list_branches_in_workspace()?;
+    branches.sort_by_key(|branch| branch.order);
+
+    let mut changes = Vec::new();
+    for branch in branches {
+        let base = branch.base(target.sha);
+        if branch.head == base {
+            continue;
+        }
+        changes.push((branch.id, base, changed_lines(repo, base, branch.head)?));
+    }
+
+    let mut predictions = Vec::new();
+    for (idx, (first_id, first_base, first)) in changes.iter().enumerate() {
+        for (second_id, second_base, second) in &changes[idx + 1..] {
+            if first_base != second_base {
+                continue;
+            }
+            let files = overlapping_files(first, second);
+            if !files.is_empty() {
+                predictions.push(PredictedConflict {
+                    branch_ids: [*first_id, *second_id],
+                    files,
+                });
+            }
+        }
+    }
+    Ok(predictions)
+}
+
+/// Return the lines of each file in `base` that the changes up to `head` touch.
+fn changed_lines(
+    repo: &git2::Repository,
+    base: git2::Oid,
+    head: git2::Oid,
+) -> Result<ChangedLines> {
+    let base_tree = repo.find_commit(base)?.tree()?;
+    let head_tree = repo.find_commit(head)?.tree()?;
+    let mut opts = git2::DiffOptions::new();
+    opts.context_lines(0);
+    let diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), Some(&mut opts))?;
+
+    let mut changes = ChangedLines::new();
+    for idx in 0..diff.deltas().len() {
+        let Some(delta) = diff.get_delta(idx) else {
+            continue;
+        };
+        let Some(path) = delta.old_file().path().or(delta.new_file().path()) else {
+            continue;
+        };
+        let patch = git2::Patch::from_diff(&diff, idx)?;
+        let lines = match patch {
+            Some(patch) if !patch.delta().flags().is_binary() => {
+                let mut lines = Vec::with_capacity(patch.num_hunks());
+                for hunk_idx in 0..patch.num_hunks() {
+                    let (hunk, _) = patch.hunk(hunk_idx)?;
+                    // Lines that are only added go after `old_start`, so they are placed on the line after it.
+                    let start = if hunk.old_lines() == 0 {
+                        hunk.old_start() + 1
+                    } else {
+                        hunk.old_start()
+                    };
+                    lines.push(start..start + hunk.old_lines());
+                }
+                Some(lines)
+            }
+            _ => None,
+        };
+        changes.insert(path.to_owned(), lines);
+    }
+    Ok(changes)
+}
+
+/// Return the files that both `first` and `second` change, along with the lines where their changes
+/// overlap or are adjacent, just like Git would consider them to conflict.
+fn overlapping_files(first: &ChangedLines, second: &ChangedLines) -> Vec<OverlappingFile> {
+    let mut files = Vec::new();
+    for (path, first_lines) in first {
+        let Some(second_lines) = second.get(path) else {
+            continue;
+        };
+        let (Some(first_lines), Some(second_lines)) = (first_lines, second_lines) else {
+            files.push(OverlappingFile {
+                path: path.clone(),
+                lines: Vec::new(),
+            });
+            continue;
+        };
+
+        let mut overlaps: Vec<Range<u32>> = first_lines
+            .iter()
+            .flat_map(|a| {
+                second_lines
+                    .iter()
+                    .filter(|b| a.start <= b.end && b.start <= a.end)
+                    .map(|b| a.start.min(b.start)..a.end.max(b.end))
+            })
+            .collect();
+        overlaps.sort_by_key(|range| range.start);
+        let mut lines: Vec<Range<u32>> = Vec::new();
+        for overlap in overlaps {
+            match lines.last_mut() {
+                Some(last) if last.end >= overlap.start => last.end = last.end.max(overlap.end),
+                _ => lines.push(overlap),
+            }
+        }
+        if !lines.is_empty() {
+            files.push(OverlappingFile {
+                path: path.clone(),
+                lines,
+            });
+        }
+    }
+    files
+}
@@ -3,6 +3,8 @@ use std::ffi::OsStr;
 /// This is the dumbest possible way to do this, but it is a placeholder.
 /// Conflicts are stored one path per line in .git/conflicts.
 /// Merge parent is stored in .git/base_merge_parent.
+/// A commit that is cherry-picked is stored in .git/cherry_picked_commit instead,
+/// and a commit that is reverted in .git/reverted_commit.
 /// Conflicts are removed as they are resolved, the conflicts file is removed when there are no more conflicts
 /// or when the merge is complete.
 use std::{
@@ -12,8 +14,11 @@ use std::{
 
 use anyhow::{anyhow, bail, Context, Result};
 use bstr::ByteSlice;
+use gitbutler_branch::BranchId;
 use gitbutler_command_context::CommandContext;
-use gitbutler_error::error::Marker;
+use gitbutler_error::error::{Code, Marker};
+use gitbutler_fs::os_path;
+use serde::{Deserialize, Serialize};
 
 pub(crate) fn mark<P: AsRef<Path>, A: AsRef<[P]>>(
     ctx: &CommandContext,
@@ -45,14 +50,89 @@ pub(crate) fn mark<P: AsRef<Path>, A: AsRef<[P]>>(
     Ok(())
 }
 
+/// Remember the blobs of all sides of each conflict in `index`, one conflict per line in .git/conflict_stages,
+/// so they can still be retrieved once the conflicts were checked out into the worktree.
+pub(crate) fn record_stages(ctx: &CommandContext, index: &git2::Index) -> Result<()> {
+    let mut buf = Vec::<u8>::with_capacity(512);
+    for conflict in index
+        .conflicts()
+        .context("failed to get merge index conflicts")?
+    {
+        let conflict = conflict?;
+        let Some(path) = conflict
+            .our
+            .as_ref()
+            .or(conflict.their.as_ref())
+            .or(conflict.ancestor.as_ref())
+            .map(|entry| entry.path.clone())
+        else {
+            continue;
+        };
+        for entry in [&conflict.ancestor, &conflict.our, &conflict.their] {
+            match entry {
+                Some(entry) => write!(buf, "{} ", entry.id)?,
+                None => buf.write_all(b"- ")?,
+            }
+        }
+        buf.write_all(&path)?;
+        buf.write_all(b"\n")?;
+    }
+    gitbutler_fs::write(stages_path(ctx), &buf)?;
+    Ok(())
+}
+
 fn conflicts_path(ctx: &CommandContext) -> PathBuf {
     ctx.repository().path().join("conflicts")
 }
 
+fn stages_path(ctx: &CommandContext) -> PathBuf {
+    ctx.repository().path().join("conflict_stages")
+}
+
 fn merge_parent_path(ctx: &CommandContext) -> PathBuf {
     ctx.repository().path().join("base_merge_parent")
 }
 
+fn cherry_pick_path(ctx: &CommandContext) -> PathBuf {
+    ctx.repository().path().join("cherry_picked_commit")
+}
+
+/// Remember that the conflicts are the result of cherry-picking `commit`, so the resolution is
+/// committed as a copy of it rather than as merge commit.
+pub(crate) fn mark_cherry_pick(ctx: &CommandContext, commit: git2::Oid) -> Result<()> {
+    gitbutler_fs::write(cherry_pick_path(ctx), commit.to_string().as_bytes())?;
+    Ok(())
+}
+
+fn revert_path(ctx: &CommandContext) -> PathBuf {
+    ctx.repository().path().join("reverted_commit")
+}
+
+/// Remember that the conflicts are the result of reverting `commit`, so the resolution is
+/// committed as its revert rather than as merge commit.
+pub(crate) fn mark_revert(ctx: &CommandContext, commit: git2::Oid) -> Result<()> {
+    gitbutler_fs::write(revert_path(ctx), commit.to_string().as_bytes())?;
+    Ok(())
+}
+
+/// Return the commit that is being reverted, if the conflicts are the result of a revert.
+pub(crate) fn reverted(ctx: &CommandContext) -> Result<Option<git2::Oid>> {
+    let path = revert_path(ctx);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(std::fs::read_to_string(path)?.trim().parse()?))
+}
+
+/// Return the commit that is being cherry-picked, if the conflicts are the result of a cherry-pick.
+pub(crate) fn cherry_pick(ctx: &CommandContext) -> Result<Option<git2::Oid>> {
+    let path = cherry_pick_path(ctx);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(std::fs::read_to_string(path)?.trim().parse()?))
+}
+
 pub(crate) fn merge_parent(ctx: &CommandContext) -> Result<Option<git2::Oid>> {
     use std::io::BufRead;
 
@@ -129,15 +209,181 @@ pub(crate) fn is_conflicting(repository: &CommandContext, path: Option<&Path>) -
 // is this project still in a resolving conflict state?
 // - could be that there are no more conflicts, but the state is not committed
 pub(crate) fn is_resolving(ctx: &CommandContext) -> bool {
-    merge_parent_path(ctx).exists()
+    merge_parent_path(ctx).exists() || cherry_pick_path(ctx).exists() || revert_path(ctx).exists()
 }
 
 pub(crate) fn clear(ctx: &CommandContext) -> Result<()> {
     remove_file_ignore_missing(merge_parent_path(ctx))?;
+    remove_file_ignore_missing(cherry_pick_path(ctx))?;
+    remove_file_ignore_missing(revert_path(ctx))?;
     remove_file_ignore_missing(conflicts_path(ctx))?;
+    remove_file_ignore_missing(stages_path(ctx))?;
     Ok(())
 }
 
+/// The blobs of each side of a conflicting file, as far as they are known.
+fn stages(ctx: &CommandContext) -> Result<Vec<ConflictedFile>> {
+    let stages_path = stages_path(ctx);
+    if !stages_path.exists() {
+        return Ok(vec![]);
+    }
+
+    let line_per_conflict = std::fs::read(stages_path)?;
+    let mut files = Vec::new();
+    for line in line_per_conflict.lines() {
+        let mut fields = line.splitn(4, |b| *b == b' ');
+        let mut next_oid = || -> Result<Option<git2::Oid>> {
+            match fields.next() {
+                Some(b"-") => Ok(None),
+                Some(hex) => Ok(Some(git2::Oid::from_str(hex.to_str()?)?)),
+                None => bail!("conflict stages are malformed"),
+            }
+        };
+        let (base, ours, theirs) = (next_oid()?, next_oid()?, next_oid()?);
+        let path = fields.next().context("conflict stages are malformed")?;
+        files.push(ConflictedFile {
+            path: unsafe { OsStr::from_encoded_bytes_unchecked(path) }.into(),
+            base,
+            ours,
+            theirs,
+        });
+    }
+    Ok(files)
+}
+
+/// A file that is still conflicting, along with the blobs of all sides of the conflict.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictedFile {
+    /// The worktree-relative path of the file.
+    pub path: PathBuf,
+    /// The blob of the merge-base, or `None` if the file didn't exist there or it's unknown.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub base: Option<git2::Oid>,
+    /// The blob of the workspace side, or `None` if it was deleted or it's unknown.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub ours: Option<git2::Oid>,
+    /// The blob of the side being applied, or `None` if it was deleted or it's unknown.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub theirs: Option<git2::Oid>,
+}
+
+/// Identifies one side of a conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictSide {
+    Base,
+    Ours,
+    Theirs,
+}
+
+/// How to resolve a conflicting file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", content = "content", rename_all = "camelCase")]
+pub enum Resolution {
+    /// Use the workspace side of the conflict.
+    Ours,
+    /// Use the side that was applied.
+    Theirs,
+    /// Use the given content, as merged by the user, which may be binary.
+    Manual(Vec<u8>),
+}
+
+/// A structured view on the conflicts left behind by applying a branch, integrating upstream changes
+/// or cherry-picking commits.
+///
+/// Resolve each [file](Self::files()) with [`resolve()`](Self::resolve()), then
+/// [`finalize()`](Self::finalize()) to record the result as merge commit, or as copy of the cherry-picked commit.
+pub struct ConflictSession<'a> {
+    ctx: &'a CommandContext,
+}
+
+impl<'a> ConflictSession<'a> {
+    /// Return a session if the project is currently resolving conflicts, or `None` otherwise.
+    pub fn open(ctx: &'a CommandContext) -> Option<Self> {
+        is_resolving(ctx).then_some(ConflictSession { ctx })
+    }
+
+    /// Return all files that are still conflicting.
+    pub fn files(&self) -> Result<Vec<ConflictedFile>> {
+        let stages = stages(self.ctx)?;
+        Ok(conflicting_files(self.ctx)?
+            .into_iter()
+            .map(|path| {
+                stages
+                    .iter()
+                    .find(|file| file.path == path)
+                    .cloned()
+                    .unwrap_or(ConflictedFile {
+                        path,
+                        base: None,
+                        ours: None,
+                        theirs: None,
+                    })
+            })
+            .collect())
+    }
+
+    /// Return the content of `side` of the conflicting file at `path`, or `None` if that side
+    /// doesn't have the file.
+    pub fn blob(&self, path: impl AsRef<Path>, side: ConflictSide) -> Result<Option<Vec<u8>>> {
+        let file = self.file(path.as_ref())?;
+        let oid = match side {
+            ConflictSide::Base => file.base,
+            ConflictSide::Ours => file.ours,
+            ConflictSide::Theirs => file.theirs,
+        };
+        oid.map(|oid| -> Result<_> {
+            Ok(self.ctx.repository().find_blob(oid)?.content().to_owned())
+        })
+        .transpose()
+    }
+
+    /// Write the `resolution` of the conflicting file at `path` into the worktree and mark it as resolved.
+    pub fn resolve(&self, path: impl AsRef<Path>, resolution: Resolution) -> Result<()> {
+        let path = path.as_ref();
+        let file = self.file(path)?;
+        let repo = self.ctx.repository();
+        let content = match resolution {
+            Resolution::Ours => self
+                .blob(path, ConflictSide::Ours)?
+                .map(|blob| gitbutler_diff::filter::smudge(repo, &file.path, &blob))
+                .transpose()?,
+            Resolution::Theirs => self
+                .blob(path, ConflictSide::Theirs)?
+                .map(|blob| gitbutler_diff::filter::smudge(repo, &file.path, &blob))
+                .transpose()?,
+            Resolution::Manual(content) => Some(content),
+        };
+        let worktree_dir = self.ctx.project().worktree_path();
+        let worktree_path = worktree_dir.join(&file.path);
+        match content {
+            Some(content) => os_path::write_file(&worktree_dir, &worktree_path, content)?,
+            None => os_path::remove_file(&worktree_path)?,
+        }
+        resolve(self.ctx, path)
+    }
+
+    /// Commit the resolved conflicts onto the branch with `branch_id` as merge commit, or as copy
+    /// of the cherry-picked commit, using `message`.
+    ///
+    /// Fails if there are still unresolved conflicts.
+    pub fn finalize(self, branch_id: BranchId, message: &str) -> Result<git2::Oid> {
+        if is_conflicting(self.ctx, None)? {
+            return Err(anyhow!("there are unresolved conflicts"))
+                .context(Code::CommitMergeConflictFailure);
+        }
+        crate::r#virtual::commit(self.ctx, branch_id, message, None, false)
+    }
+
+    fn file(&self, path: &Path) -> Result<ConflictedFile> {
+        self.files()?
+            .into_iter()
+            .find(|file| file.path == path)
+            .with_context(|| format!("{} is not conflicting", path.display()))
+    }
+}
+
 fn remove_file_ignore_missing(path: impl AsRef<Path>) -> std::io::Result<()> {
     std::fs::remove_file(path).or_else(|err| {
         if err.kind() == std::io::ErrorKind::NotFound {
@@ -0,0 +1,190 @@
+//! Search the files of the worktree like `git grep`, and the lines added by the commits of the applied virtual
+//! branches like `git log -G`, to tell which files contain something and which branch introduces it.
+use std::{
+    collections::HashMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_repo::{LogUntil, RepoActionsExt, RepositoryExt};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{status::get_applied_status, VirtualBranchesExt};
+
+/// Files larger than this aren't searched, as they are most likely generated.
+const MAX_SEARCHED_FILE_BYTES: usize = 5 * 1024 * 1024;
+
+/// A line that matched a content search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentMatch {
+    /// The path of the file with the line, relative to the worktree.
+    pub path: PathBuf,
+    /// The number of the line in the file, starting at 1.
+    pub line_number: u32,
+    pub line: String,
+    pub origin: ContentOrigin,
+}
+
+/// Where a line that matched a content search was found.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum ContentOrigin {
+    /// The line is in a file of the worktree.
+    #[serde(rename_all = "camelCase")]
+    Worktree {
+        /// The applied virtual branch whose uncommitted changes add the line, or `None` if it isn't changed.
+        branch_id: Option<BranchId>,
+    },
+    /// The line is added by a commit of an applied virtual branch.
+    #[serde(rename_all = "camelCase")]
+    Commit {
+        branch_id: BranchId,
+        #[serde(with = "gitbutler_serde::oid")]
+        commit_id: git2::Oid,
+    },
+}
+
+/// Return at most `limit` lines matching the regular expression `pattern`, first those of the files in the
+/// worktree that aren't ignored, then those added by the commits of the applied virtual branches, most recent
+/// first. Binary files and files larger than [`MAX_SEARCHED_FILE_BYTES`] are skipped.
+pub(crate) fn search_content(
+    ctx: &CommandContext,
+    pattern: &str,
+    limit: usize,
+) -> Result<Vec<ContentMatch>> {
+    if pattern.is_empty() {
+        return Err(anyhow!("the search can not be empty")).context(Code::Validation);
+    }
+    let regex = Regex::new(pattern)
+        .map_err(|err| anyhow!("invalid search pattern '{pattern}': {err}"))
+        .context(Code::Validation)?;
+    let mut matches = Vec::new();
+    search_worktree(ctx, &regex, limit, &mut matches)?;
+    search_commits(ctx, &regex, limit, &mut matches)?;
+    Ok(matches)
+}
+
+/// Add the lines of the files in the worktree that match `regex` to `matches`, until there are `limit`.
+fn search_worktree(
+    ctx: &CommandContext,
+    regex: &Regex,
+    limit: usize,
+    matches: &mut Vec<ContentMatch>,
+) -> Result<()> {
+    // The uncommitted lines of each file, by the branch that owns them.
+    let mut owners = HashMap::<PathBuf, Vec<(Range<u32>, BranchId)>>::new();
+    for (branch, files) in get_applied_status(ctx, None)?.branches {
+        for file in files {
+            let owned = owners.entry(file.path).or_default();
+            owned.extend(
+                file.hunks
+                    .iter()
+                    .map(|hunk| (hunk.start..hunk.end, branch.id)),
+            );
+        }
+    }
+
+    let repo = ctx.repository();
+    let tree = repo.get_wd_tree().context("failed to read the worktree")?;
+    let mut blobs = Vec::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.kind() == Some(git2::ObjectType::Blob) {
+            if let Some(name) = entry.name() {
+                blobs.push((Path::new(dir).join(name), entry.id()));
+            }
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+
+    for (path, blob_id) in blobs {
+        let blob = repo.find_blob(blob_id)?;
+        if blob.is_binary() || blob.size() > MAX_SEARCHED_FILE_BYTES {
+            continue;
+        }
+        let owned = owners.get(&path);
+        for (idx, line) in blob.content().lines().enumerate() {
+            if matches.len() >= limit {
+                return Ok(());
+            }
+            let line = line.to_str_lossy();
+            if !regex.is_match(&line) {
+                continue;
+            }
+            let line_number = u32::try_from(idx + 1)?;
+            let branch_id = owned.and_then(|owned| {
+                owned
+                    .iter()
+                    .find(|(lines, _)| lines.contains(&line_number))
+                    .map(|(_, branch_id)| *branch_id)
+            });
+            matches.push(ContentMatch {
+                path: path.clone(),
+                line_number,
+                line: line.into_owned(),
+                origin: ContentOrigin::Worktree { branch_id },
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Add the lines added by the commits of the applied virtual branches that match `regex` to `matches`,
+/// until there are `limit`.
+fn search_commits(
+    ctx: &CommandContext,
+    regex: &Regex,
+    limit: usize,
+    matches: &mut Vec<ContentMatch>,
+) -> Result<()> {
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    for branch in vb_state.list_branches_in_workspace()? {
+        let commits = ctx
+            .log(branch.head, LogUntil::Commit(branch.base(target.sha)))
+            .context("failed to list commits of branch")?;
+        for commit in commits {
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => parent.tree()?,
+                Err(_) => repo.find_tree(repo.treebuilder(None)?.write()?)?,
+            };
+            for (path, diff) in gitbutler_diff::trees(repo, &parent_tree, &commit.tree()?)? {
+                for hunk in diff.hunks.iter().filter(|hunk| !hunk.binary) {
+                    let mut line_number = hunk.new_start;
+                    for line in hunk.diff_lines.lines() {
+                        match line.first() {
+                            Some(b'+') => {
+                                if matches.len() >= limit {
+                                    return Ok(());
+                                }
+                                let added = line[1..].to_str_lossy();
+                                if regex.is_match(&added) {
+                                    matches.push(ContentMatch {
+                                        path: path.clone(),
+                                        line_number,
+                                        line: added.into_owned(),
+                                        origin: ContentOrigin::Commit {
+                                            branch_id: branch.id,
+                                            commit_id: commit.id(),
+                                        },
+                                    });
+                                }
+                                line_number += 1;
+                            }
+                            Some(b' ') => line_number += 1,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,90 @@
+//! Discard a single uncommitted hunk, or some of its lines, by applying its reverse to the file in the worktree,
+//! which leaves all other changes to the file alone.
+//!
+//! Hunks can be identified with their hash, like `3-7-<hash>`, to find them even if lines before them were
+//! added or removed since they were listed, and the reverse is placed by its context, so it still applies then.
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::{ChangeType, GitHunk, Hunk, RangeSet};
+use gitbutler_error::error::Code;
+use gitbutler_fs::os_path;
+use gitbutler_project::access::WorktreeWritePermission;
+
+use crate::status::get_applied_status;
+
+/// Discard the changes of the uncommitted hunk identified by `hunk_id`, like `3-7` or `3-7-<hash>`, in the file
+/// at `path`, relative to the worktree, or only the ones on `lines` of it if set, which are numbered like in a
+/// [selection](gitbutler_diff::HunkSelection).
+///
+/// With a hash, the hunk with the same changes is discarded wherever it is now.
+pub(crate) fn discard_hunk(
+    ctx: &CommandContext,
+    path: &Path,
+    hunk_id: &str,
+    lines: Option<&RangeSet>,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    ctx.assure_resolved()?;
+    let wanted: Hunk = hunk_id
+        .parse()
+        .map_err(|err| anyhow!("invalid hunk '{hunk_id}': {err}"))
+        .context(Code::Validation)?;
+    let hunk: GitHunk = get_applied_status(ctx, None)?
+        .branches
+        .into_iter()
+        .flat_map(|(_, files)| files)
+        .filter(|file| file.path == path)
+        .flat_map(|file| file.hunks)
+        .find(|hunk| match wanted.hash {
+            Some(hash) => hunk.hash == hash,
+            None => hunk.start == wanted.start && hunk.end == wanted.end,
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "hunk {hunk_id} of {} not found, it may have changed",
+                path.display()
+            )
+        })
+        .context(Code::Validation)?
+        .into();
+    let change_type = hunk.change_type;
+
+    let reverse = gitbutler_diff::reverse_hunk(&hunk)
+        .ok_or_else(|| anyhow!("binary changes can't be discarded by hunk"))
+        .context(Code::Validation)?;
+    // The reverse turns additions into deletions, so unselected additions stay as context and unselected
+    // deletions are left out, like in the worktree.
+    let reverse = match lines {
+        Some(lines) => reverse
+            .select_lines(lines)
+            .ok_or_else(|| anyhow!("no changes of hunk {hunk_id} are selected"))
+            .context(Code::Validation)?,
+        None => reverse,
+    };
+
+    let worktree_dir = ctx.project().worktree_path();
+    let full_path = worktree_dir.join(path);
+    let current = match std::fs::read(os_path::for_io(&full_path)) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err).context(format!("failed to read {}", full_path.display())),
+    };
+    let patch = diffy::Patch::from_bytes(&reverse.diff_lines)?;
+    let contents = gitbutler_diff::write::apply(&current, &patch)
+        .map_err(|err| {
+            anyhow!(
+                "hunk {hunk_id} of {} doesn't apply anymore: {err}",
+                path.display()
+            )
+        })
+        .context(Code::Validation)?;
+
+    if contents.is_empty() && lines.is_none() && change_type == ChangeType::Added {
+        os_path::remove_file(&full_path)?;
+    } else {
+        os_path::write_file(&worktree_dir, &full_path, contents)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,372 @@
+//! Leave GitButler behind by turning the virtual branches of the workspace into plain Git branches, so the
+//! repository can be used with any other Git client without losing any work.
+use anyhow::{Context, Result};
+use gitbutler_branch::{Branch, BranchId, SignaturePurpose};
+use gitbutler_command_context::CommandContext;
+use gitbutler_commit::commit_headers::CommitHeadersV2;
+use gitbutler_error::error::Code;
+use gitbutler_operating_modes::INTEGRATION_BRANCH_REF;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_reference::{normalize_branch_name, Refname};
+use gitbutler_repo::{RepoActionsExt, RepositoryExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{conflicts::RepoConflictsExt, status::get_applied_status, VirtualBranchesExt};
+
+const WIP_MESSAGE: &str = "GitButler WIP Commit";
+
+/// How the uncommitted changes of a virtual branch are kept when exporting it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportUncommitted {
+    /// As a commit on top of the exported branch.
+    Commit,
+    /// As an entry of `git stash`, with the exported branch left at its last commit.
+    Stash,
+}
+
+/// A virtual branch that was turned into a local branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedBranch {
+    pub branch_id: BranchId,
+    /// The full name of the local branch, like `refs/heads/my-feature`.
+    pub refname: String,
+    /// The commit the local branch points to.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub head: git2::Oid,
+    /// The commit or stash entry with the uncommitted changes of the branch, if it had any.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub uncommitted: Option<git2::Oid>,
+}
+
+/// What exporting the workspace did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOutcome {
+    /// The applied branches, in the order of the workspace.
+    pub branches: Vec<ExportedBranch>,
+    /// The full name of the local branch that is checked out now.
+    pub checked_out: String,
+}
+
+/// Turn each applied virtual branch into a local branch with its commits, keeping its uncommitted changes
+/// as configured by `uncommitted`, then check out the branch that was selected for changes and remove
+/// `gitbutler/integration`.
+///
+/// The virtual branches are unapplied, and unapplied ones are left as they are. The operations log is kept,
+/// so the export can be undone as long as GitButler is around. Untracked files that aren't part of the
+/// checked out branch are left in the worktree.
+pub(crate) fn export(
+    ctx: &CommandContext,
+    uncommitted: ExportUncommitted,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<ExportOutcome> {
+    ctx.assure_resolved()?;
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let applied = get_applied_status(ctx, None)?.branches;
+
+    let mut exported = Vec::new();
+    for (branch, _files) in &applied {
+        let name = local_branch_name(repo, branch)?;
+        let head = repo.find_commit(branch.head)?;
+        let local = repo.branch(&name, &head, true)?;
+        let refname = Refname::try_from(&local)?;
+
+        let mut exported_head = branch.head;
+        let mut wip = None;
+        if branch.tree != head.tree_id() {
+            let tree = repo.find_tree(branch.tree)?;
+            match uncommitted {
+                ExportUncommitted::Commit => {
+                    exported_head = wip_commit(repo, &refname, &head, &tree)?;
+                    wip = Some(exported_head);
+                }
+                ExportUncommitted::Stash => wip = Some(stash(repo, &name, &head, &tree)?),
+            }
+        }
+
+        let mut branch = branch.clone();
+        branch.source_refname = Some(refname.clone());
+        vb_state.set_branch(branch.clone())?;
+        vb_state
+            .mark_as_not_in_workspace(branch.id)
+            .context("failed to unapply exported branch")?;
+        ctx.delete_branch_reference(&branch)?;
+
+        exported.push(ExportedBranch {
+            branch_id: branch.id,
+            refname: refname.to_string(),
+            head: exported_head,
+            uncommitted: wip,
+        });
+    }
+
+    let checked_out = match applied
+        .iter()
+        .zip(&exported)
+        .rev()
+        .max_by_key(|((branch, _files), _exported)| branch.selected_for_changes)
+    {
+        Some((_, exported)) => exported.refname.clone(),
+        None => target_branch(ctx)?,
+    };
+
+    let commit = repo.find_reference(&checked_out)?.peel_to_commit()?;
+    repo.checkout_tree_builder(&commit.tree()?)
+        .force()
+        .checkout()
+        .context("failed to check out exported branch")?;
+    repo.set_head(&checked_out)?;
+    remove_integration_branch(repo)?;
+
+    Ok(ExportOutcome {
+        branches: exported,
+        checked_out,
+    })
+}
+
+/// Turn every virtual branch into a local branch with its commits, and check out the applied branch identified
+/// by `onto`, or the one selected for changes if `None`, with the uncommitted changes of all applied branches in
+/// the worktree. Then remove all references and data of GitButler, which can't be undone.
+///
+/// Uncommitted changes of other branches that conflict with the checked out branch are kept as entries of
+/// `git stash` instead. Unapplied branches that are local branches already are left as they are.
+pub(crate) fn eject(
+    ctx: &CommandContext,
+    onto: Option<BranchId>,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<ExportOutcome> {
+    ctx.assure_resolved()?;
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let mut applied: Vec<_> = get_applied_status(ctx, None)?
+        .branches
+        .into_iter()
+        .map(|(branch, _files)| branch)
+        .collect();
+    let onto = match onto {
+        Some(onto) => Some(
+            applied
+                .iter()
+                .position(|branch| branch.id == onto)
+                .with_context(|| format!("branch {onto} isn't applied"))
+                .context(Code::Validation)?,
+        ),
+        None => applied
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_idx, branch)| branch.selected_for_changes)
+            .map(|(idx, _branch)| idx),
+    };
+    // The changes of the checked out branch go first, so they always apply.
+    if let Some(onto) = onto {
+        let branch = applied.remove(onto);
+        applied.insert(0, branch);
+    }
+
+    let (mut checked_out, base) = match applied.first() {
+        Some(branch) => (None, repo.find_commit(branch.head)?),
+        None => {
+            let checked_out = target_branch(ctx)?;
+            let base = repo.find_reference(&checked_out)?.peel_to_commit()?;
+            (Some(checked_out), base)
+        }
+    };
+    let mut worktree = base.tree()?;
+    let mut exported = Vec::new();
+    for branch in &applied {
+        let name = local_branch_name(repo, branch)?;
+        let head = repo.find_commit(branch.head)?;
+        let local = repo.branch(&name, &head, true)?;
+        let refname = Refname::try_from(&local)?;
+        checked_out.get_or_insert_with(|| refname.to_string());
+
+        let mut stashed = None;
+        if branch.tree != head.tree_id() {
+            let tree = repo.find_tree(branch.tree)?;
+            let mut merge = repo.merge_trees(&head.tree()?, &worktree, &tree, None)?;
+            if merge.has_conflicts() {
+                stashed = Some(stash(repo, &name, &head, &tree)?);
+            } else {
+                worktree = repo.find_tree(merge.write_tree_to(repo)?)?;
+            }
+        }
+        exported.push(ExportedBranch {
+            branch_id: branch.id,
+            refname: refname.to_string(),
+            head: branch.head,
+            uncommitted: stashed,
+        });
+    }
+
+    for branch in vb_state
+        .list_all_branches()?
+        .into_iter()
+        .filter(|branch| !applied.iter().any(|applied| applied.id == branch.id))
+    {
+        if let Some(Refname::Local(local)) = &branch.source_refname {
+            if let Ok(existing) = repo.find_reference(&local.to_string()) {
+                exported.push(ExportedBranch {
+                    branch_id: branch.id,
+                    refname: local.to_string(),
+                    head: existing.peel_to_commit()?.id(),
+                    uncommitted: None,
+                });
+                continue;
+            }
+        }
+        let name = local_branch_name(repo, &branch)?;
+        let head = repo.find_commit(branch.head)?;
+        let local = repo.branch(&name, &head, true)?;
+        let refname = Refname::try_from(&local)?;
+        // Branches that were unapplied long ago still keep their uncommitted changes as tree.
+        let wip = if branch.tree != head.tree_id() {
+            Some(wip_commit(
+                repo,
+                &refname,
+                &head,
+                &repo.find_tree(branch.tree)?,
+            )?)
+        } else {
+            None
+        };
+        exported.push(ExportedBranch {
+            branch_id: branch.id,
+            refname: refname.to_string(),
+            head: wip.unwrap_or(branch.head),
+            uncommitted: wip,
+        });
+    }
+
+    let checked_out = checked_out.expect("set to the first applied branch or the target branch");
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.force();
+    repo.checkout_tree(worktree.as_object(), Some(&mut checkout))
+        .context("failed to check out ejected branch")?;
+    // The uncommitted changes show as such as the index is the one of the checked out commit.
+    let mut index = repo.index()?;
+    index.read_tree(&base.tree()?)?;
+    index.write()?;
+    repo.set_head(&checked_out)?;
+
+    remove_integration_branch(repo)?;
+    let gitbutler_refs = repo
+        .references_glob("refs/gitbutler/*")?
+        .names()
+        .map(|name| name.map(ToOwned::to_owned))
+        .collect::<Result<Vec<_>, _>>()?;
+    for name in gitbutler_refs {
+        repo.find_reference(&name)?.delete()?;
+    }
+    match std::fs::remove_dir_all(ctx.project().gb_dir()) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            return Err(err).context("failed to remove the data of GitButler")
+        }
+        _ => {}
+    }
+
+    Ok(ExportOutcome {
+        branches: exported,
+        checked_out,
+    })
+}
+
+/// Return the full name of the local branch of the target, which is created if it doesn't exist yet.
+/// Without applied branches, it's all there is to check out.
+fn target_branch(ctx: &CommandContext) -> Result<String> {
+    let repo = ctx.repository();
+    let target = ctx.project().virtual_branches().get_default_target()?;
+    let name = target.branch.branch();
+    let local = match repo.find_branch(name, git2::BranchType::Local) {
+        Ok(local) => local,
+        Err(_) => repo.branch(name, &repo.find_commit(target.sha)?, false)?,
+    };
+    Ok(Refname::try_from(&local)?.to_string())
+}
+
+fn remove_integration_branch(repo: &git2::Repository) -> Result<()> {
+    if let Ok(mut integration) = repo.find_reference(INTEGRATION_BRANCH_REF) {
+        integration.delete()?;
+    }
+    Ok(())
+}
+
+/// Commit `tree` on top of `head` as work in progress, and point `refname` to the new commit.
+fn wip_commit(
+    repo: &git2::Repository,
+    refname: &Refname,
+    head: &git2::Commit<'_>,
+    tree: &git2::Tree<'_>,
+) -> Result<git2::Oid> {
+    let author = gitbutler_branch::signature(SignaturePurpose::Author)?;
+    let committer = gitbutler_branch::signature(SignaturePurpose::Committer)?;
+    repo.commit_with_signature(
+        Some(refname),
+        &author,
+        &committer,
+        WIP_MESSAGE,
+        tree,
+        &[head],
+        Some(CommitHeadersV2::new()),
+    )
+}
+
+/// Return the name of the local branch to export `branch` to, which is its normalized name unless another
+/// local branch has this name already.
+fn local_branch_name(repo: &git2::Repository, branch: &Branch) -> Result<String> {
+    let base = normalize_branch_name(&branch.name)?;
+    let mut name = base.clone();
+    let mut suffix = 1;
+    while let Ok(existing) = repo.find_branch(&name, git2::BranchType::Local) {
+        if existing.get().target() == Some(branch.head) {
+            break;
+        }
+        name = format!("{base}-{suffix}");
+        suffix += 1;
+    }
+    Ok(name)
+}
+
+/// Add `tree` as the most recent entry of `git stash`, created on `head` of the branch named `branch_name`,
+/// and return its id.
+///
+/// Like with `git stash`, the entry merges `head` with a commit of the index, which is left as in `head`
+/// as GitButler doesn't use the index.
+fn stash(
+    repo: &git2::Repository,
+    branch_name: &str,
+    head: &git2::Commit<'_>,
+    tree: &git2::Tree<'_>,
+) -> Result<git2::Oid> {
+    let signature = gitbutler_branch::signature(SignaturePurpose::Committer)?;
+    let index_message = format!(
+        "index on {branch_name}: {} {}",
+        &head.id().to_string()[..7],
+        head.summary().unwrap_or_default()
+    );
+    let index_commit = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &index_message,
+        &head.tree()?,
+        &[head],
+    )?;
+    let index_commit = repo.find_commit(index_commit)?;
+
+    let message = format!("On {branch_name}: {WIP_MESSAGE}");
+    let stash = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &message,
+        tree,
+        &[head, &index_commit],
+    )?;
+    repo.reference_ensure_log("refs/stash")?;
+    repo.reference("refs/stash", stash, true, &message)?;
+    Ok(stash)
+}
@@ -5,7 +5,7 @@ use std::{
 
 use anyhow::{anyhow, Context, Result};
 use gitbutler_command_context::CommandContext;
-use gitbutler_diff::FileDiff;
+use gitbutler_diff::{DiffOptions, FileDiff};
 use serde::Serialize;
 
 use crate::{
@@ -19,11 +19,16 @@ pub struct RemoteBranchFile {
     pub path: path::PathBuf,
     pub hunks: Vec<gitbutler_diff::GitHunk>,
     pub binary: bool,
+    /// Set if the file was renamed or copied, in which case `hunks` are relative to the old path.
+    pub path_change: Option<gitbutler_diff::PathChange>,
+    /// Set if the file is a submodule whose commit changed, in which case there are no `hunks`.
+    pub submodule: Option<gitbutler_diff::SubmoduleChange>,
 }
 
 pub(crate) fn list_remote_commit_files(
     repository: &git2::Repository,
     commit_id: git2::Oid,
+    options: &DiffOptions,
 ) -> Result<Vec<RemoteBranchFile>> {
     let commit = repository
         .find_commit(commit_id)
@@ -40,9 +45,10 @@ pub(crate) fn list_remote_commit_files(
     let parent = commit.parent(0).context("failed to get parent commit")?;
     let commit_tree = commit.tree().context("failed to get commit tree")?;
     let parent_tree = parent.tree().context("failed to get parent tree")?;
-    let diff_files = gitbutler_diff::trees(repository, &parent_tree, &commit_tree)?;
+    let diff_files =
+        gitbutler_diff::trees_with_options(repository, &parent_tree, &commit_tree, options)?;
 
-    Ok(diff_files
+    let mut files: Vec<RemoteBranchFile> = diff_files
         .into_iter()
         .map(|(path, file)| {
             let binary = file.hunks.iter().any(|h| h.binary);
@@ -50,9 +56,27 @@ pub(crate) fn list_remote_commit_files(
                 path,
                 hunks: file.hunks,
                 binary,
+                path_change: file.path_change,
+                submodule: None,
             }
         })
-        .collect())
+        .collect();
+    for change in gitbutler_diff::submodule_changes(repository, &parent_tree, &commit_tree)? {
+        match files.iter_mut().find(|file| file.path == change.path) {
+            Some(file) => {
+                file.hunks.clear();
+                file.submodule = Some(change);
+            }
+            None => files.push(RemoteBranchFile {
+                path: change.path.clone(),
+                hunks: vec![],
+                binary: false,
+                path_change: None,
+                submodule: Some(change),
+            }),
+        }
+    }
+    Ok(files)
 }
 
 // this struct is a mapping to the view `File` type in Typescript
@@ -74,6 +98,9 @@ pub struct VirtualBranchFile {
     pub conflicted: bool,
     pub binary: bool,
     pub large: bool,
+    /// Whether the file is outside the [allowed paths](gitbutler_branch::Branch::allowed_paths) of its
+    /// branch, like when its changes depend on the branch, or no other branch allows it.
+    pub outside_allowed_paths: bool,
 }
 
 pub trait Get<T> {
@@ -131,6 +158,7 @@ pub(crate) fn virtual_hunks_into_virtual_files(
                 hunks,
                 binary,
                 large: false,
+                outside_allowed_paths: false,
                 modified_at,
                 conflicted,
             }
@@ -0,0 +1,161 @@
+//! List the commits that changed a file across the target and the applied branches, following it across
+//! renames like `git log --follow`, so the history of a file can be shown without running `git log`.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use serde::Serialize;
+
+use crate::{
+    author,
+    remote::{commit_to_remote_commit, RemoteCommit},
+    VirtualBranchesExt,
+};
+
+/// A commit that changed a file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileHistoryEntry {
+    pub commit: RemoteCommit,
+    /// The path of the file in the commit, which differs from the one that was asked for if it was
+    /// renamed since.
+    pub path: PathBuf,
+    /// The path of the file before the commit, if the commit renamed it.
+    pub previous_path: Option<PathBuf>,
+    /// Whether the commit is on the target branch.
+    pub on_target: bool,
+    /// The applied virtual branches the commit is part of, which is empty if it's on the target.
+    pub branch_ids: Vec<BranchId>,
+}
+
+/// What is known about a commit that is yet to be walked.
+#[derive(Default)]
+struct Pending {
+    /// The path of the file in the commit, or `None` if the file doesn't exist on the way to it.
+    path: Option<PathBuf>,
+    on_target: bool,
+    branch_ids: Vec<BranchId>,
+}
+
+/// Return the at most `limit` most recent commits of the target and of the applied virtual branches of
+/// `ctx` that changed the file at `path`, relative to the worktree. Renames are followed, so commits
+/// from before the file was moved to `path` are included.
+pub(crate) fn file_history(
+    ctx: &CommandContext,
+    path: &Path,
+    limit: usize,
+) -> Result<Vec<FileHistoryEntry>> {
+    if path.as_os_str().is_empty() || path.is_absolute() {
+        return Err(anyhow!(
+            "the path of a file in the worktree is needed, not {path:?}"
+        ))
+        .context(Code::Validation);
+    }
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+
+    let mut pending = HashMap::<git2::Oid, Pending>::new();
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+    let mut target_tips = vec![target.sha];
+    target_tips.extend(repo.refname_to_id(&target.branch.to_string()).ok());
+    for tip in target_tips {
+        let tip_state = pending.entry(tip).or_default();
+        tip_state.path = Some(path.to_owned());
+        tip_state.on_target = true;
+        revwalk.push(tip)?;
+    }
+    for branch in vb_state.list_branches_in_workspace()? {
+        let tip_state = pending.entry(branch.head).or_default();
+        tip_state.path = Some(path.to_owned());
+        tip_state.branch_ids.push(branch.id);
+        revwalk.push(branch.head)?;
+    }
+
+    let format = &ctx.project().listing_format;
+    let mailmap = author::mailmap(repo);
+    let mut history = Vec::new();
+    for id in revwalk {
+        if history.len() >= limit {
+            break;
+        }
+        let id = id?;
+        let Some(state) = pending.remove(&id) else {
+            continue;
+        };
+        let commit = repo.find_commit(id).context("failed to find commit")?;
+        let tree = commit.tree()?;
+        let blob_id = state
+            .path
+            .as_deref()
+            .and_then(|path| tree.get_path(path).ok())
+            .map(|entry| entry.id());
+
+        // Like `git log`, a commit changed the file unless it's the same as in one of its parents.
+        let mut changed = state.path.is_some() && (commit.parent_count() > 0 || blob_id.is_some());
+        let mut previous_path = None;
+        for parent in commit.parents() {
+            let parent_tree = parent.tree()?;
+            let mut parent_path = state.path.clone();
+            if let Some(path) = state.path.as_deref() {
+                let parent_blob_id = parent_tree.get_path(path).ok().map(|entry| entry.id());
+                if parent_blob_id.is_none() && blob_id.is_some() {
+                    parent_path = renamed_from(repo, &parent_tree, &tree, path)?;
+                    if parent_path.is_some() {
+                        previous_path.clone_from(&parent_path);
+                    }
+                } else {
+                    changed &= parent_blob_id != blob_id;
+                }
+            }
+            let parent_state = pending.entry(parent.id()).or_default();
+            if parent_state.path.is_none() {
+                parent_state.path = parent_path;
+            }
+            parent_state.on_target |= state.on_target;
+            for branch_id in &state.branch_ids {
+                if !parent_state.branch_ids.contains(branch_id) {
+                    parent_state.branch_ids.push(*branch_id);
+                }
+            }
+        }
+
+        if changed {
+            history.push(FileHistoryEntry {
+                commit: commit_to_remote_commit(&commit, format, mailmap.as_ref()),
+                path: state.path.unwrap_or_default(),
+                previous_path,
+                on_target: state.on_target,
+                branch_ids: if state.on_target {
+                    Vec::new()
+                } else {
+                    state.branch_ids
+                },
+            });
+        }
+    }
+    Ok(history)
+}
+
+/// Return the path that the file at `path` in `new_tree` had in `old_tree`, if it was renamed.
+fn renamed_from(
+    repo: &git2::Repository,
+    old_tree: &git2::Tree,
+    new_tree: &git2::Tree,
+    path: &Path,
+) -> Result<Option<PathBuf>> {
+    let mut diff = repo.diff_tree_to_tree(Some(old_tree), Some(new_tree), None)?;
+    diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
+    Ok(diff
+        .deltas()
+        .find(|delta| {
+            delta.status() == git2::Delta::Renamed && delta.new_file().path() == Some(path)
+        })
+        .and_then(|delta| delta.old_file().path().map(Path::to_owned)))
+}
@@ -0,0 +1,197 @@
+//! The state of CI for the pushed head of each virtual branch, combined from the commit statuses and
+//! check runs the forge has for it.
+//!
+//! Statuses are kept in a file so they show in the branch listing without asking the forge, which is only
+//! asked again once they are stale, or once the head changed. If the forge limits the rate of requests,
+//! it isn't asked again until the limit is lifted, and the statuses known so far are used instead.
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_fs::TomlFile;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use serde::{Deserialize, Serialize};
+
+use super::{block_on, forge, head_repo, Check, CheckOutcome, ForgeRepo, RateLimited};
+use crate::VirtualBranchesExt;
+
+/// How long the status of checks that are still running is used before the forge is asked again.
+const PENDING_TTL_MS: i64 = 30 * 1000;
+/// How long the status of checks that are all done is used before the forge is asked again, which
+/// only changes if they are run again.
+const DONE_TTL_MS: i64 = 5 * 60 * 1000;
+
+/// How CI is doing overall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CiState {
+    /// Some checks are queued or still running, and none failed so far.
+    Pending,
+    /// All checks passed.
+    Success,
+    /// At least one check failed, whether others are still running or not.
+    Failure,
+}
+
+/// The state of CI for a commit, combined from all of its checks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CiStatus {
+    pub state: CiState,
+    /// The names of the checks that failed, sorted.
+    pub failing: Vec<String>,
+    /// The amount of checks, each counted once even if the forge reports it more than once.
+    pub total: u32,
+    /// The commit the checks ran on, which is the pushed head of the branch.
+    pub sha: String,
+    /// The time at which the checks were fetched from the forge, in milliseconds since the Unix epoch.
+    pub updated_timestamp_ms: i64,
+}
+
+impl CiStatus {
+    /// Combine `checks` of the commit `sha`, with those that come first taking precedence over later
+    /// ones with the same name, or return `None` if there are none.
+    pub(crate) fn aggregate(
+        sha: &str,
+        checks: impl IntoIterator<Item = Check>,
+        updated_timestamp_ms: i64,
+    ) -> Option<Self> {
+        let mut by_name = BTreeMap::new();
+        for check in checks {
+            by_name.entry(check.name).or_insert(check.outcome);
+        }
+        if by_name.is_empty() {
+            return None;
+        }
+        let failing: Vec<String> = by_name
+            .iter()
+            .filter(|(_, outcome)| **outcome == CheckOutcome::Failed)
+            .map(|(name, _)| name.clone())
+            .collect();
+        let state = if !failing.is_empty() {
+            CiState::Failure
+        } else if by_name
+            .values()
+            .any(|outcome| *outcome == CheckOutcome::Pending)
+        {
+            CiState::Pending
+        } else {
+            CiState::Success
+        };
+        Some(CiStatus {
+            state,
+            failing,
+            total: by_name.len() as u32,
+            sha: sha.to_owned(),
+            updated_timestamp_ms,
+        })
+    }
+
+    /// Return `true` if this is still the status of `sha` at `now_ms`.
+    fn is_fresh(&self, sha: &str, now_ms: i64) -> bool {
+        let ttl = match self.state {
+            CiState::Pending => PENDING_TTL_MS,
+            CiState::Success | CiState::Failure => DONE_TTL_MS,
+        };
+        self.sha == sha && now_ms - self.updated_timestamp_ms < ttl
+    }
+}
+
+/// The CI statuses of all pushed branches, as persisted in a TOML file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CiStatuses {
+    /// The time until which the forge isn't asked, in milliseconds since the Unix epoch, as it limited
+    /// the rate of requests.
+    rate_limited_until_ms: Option<i64>,
+    /// The statuses by the name of the branch as it was pushed.
+    branches: BTreeMap<String, CiStatus>,
+}
+
+/// A handle to the last known CI statuses of the pushed heads of virtual branches.
+///
+/// Statuses are keyed by the name of the branch as it was pushed, like pull requests.
+///
+/// The statuses are kept in `ci_statuses.toml`, and branches have none until they were first fetched.
+pub struct CiStatusesHandle {
+    /// The file containing the CI statuses of all branches.
+    file: TomlFile<CiStatuses>,
+}
+
+impl CiStatusesHandle {
+    /// Creates a new handle to the CI statuses stored in `base_path`.
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        Self {
+            file: TomlFile::new(base_path.as_ref().join("ci_statuses.toml")),
+        }
+    }
+
+    /// Returns the CI statuses of all branches, keyed by branch name.
+    ///
+    /// Errors if the file cannot be read.
+    pub fn list(&self) -> Result<BTreeMap<String, CiStatus>> {
+        Ok(self.file.read()?.branches)
+    }
+}
+
+/// Bring the CI statuses of the pushed heads of all virtual branches up to date where they are stale,
+/// and return them keyed by the name of the branch as it was pushed.
+///
+/// Branches whose checks can't be fetched keep their last known status. Once the forge limits the rate
+/// of requests, no more are made until the limit is lifted.
+pub(crate) fn refresh_ci_statuses(
+    ctx: &CommandContext,
+    github_token: Option<&str>,
+) -> Result<BTreeMap<String, CiStatus>> {
+    let project = ctx.project();
+    let vb_state = project.virtual_branches();
+    let handle = project.ci_statuses();
+    let mut known = handle.file.read()?;
+    let default_target = vb_state.get_default_target()?;
+    let target_repo =
+        ForgeRepo::from_remote_url(&default_target.remote_url).context(Code::Forge)?;
+
+    let mut statuses = BTreeMap::new();
+    for branch in vb_state.list_all_branches()? {
+        let (Some(upstream), Some(upstream_head)) = (&branch.upstream, branch.upstream_head) else {
+            continue;
+        };
+        let name = upstream.branch().to_owned();
+        let sha = upstream_head.to_string();
+        let cached = known.branches.remove(&name);
+        let now_ms = now_since_unix_epoch_ms();
+        let rate_limited = known
+            .rate_limited_until_ms
+            .is_some_and(|until_ms| now_ms < until_ms);
+        if rate_limited || cached.as_ref().is_some_and(|ci| ci.is_fresh(&sha, now_ms)) {
+            statuses.extend(cached.map(|ci| (name, ci)));
+            continue;
+        }
+
+        let repo = head_repo(ctx, upstream, &target_repo)?;
+        let forge = forge(project, &repo, github_token)?;
+        match block_on(forge.commit_checks(&repo, &sha)) {
+            Ok(checks) => {
+                statuses.extend(CiStatus::aggregate(&sha, checks, now_ms).map(|ci| (name, ci)));
+            }
+            Err(err) => {
+                if let Some(limited) = err.downcast_ref::<RateLimited>() {
+                    tracing::info!(
+                        until_ms = limited.reset_timestamp_ms,
+                        "forge limited the rate of requests, using known CI statuses"
+                    );
+                    known.rate_limited_until_ms = Some(limited.reset_timestamp_ms);
+                } else {
+                    tracing::warn!(?err, branch = %name, "failed to fetch the CI status");
+                }
+                statuses.extend(cached.map(|ci| (name, ci)));
+            }
+        }
+    }
+
+    handle.file.write(&CiStatuses {
+        rate_limited_until_ms: known.rate_limited_until_ms,
+        branches: statuses.clone(),
+    })?;
+    Ok(statuses)
+}
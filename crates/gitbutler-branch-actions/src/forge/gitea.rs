@@ -0,0 +1,407 @@
+//! A client for the REST API of Gitea, which Forgejo shares, where checks are commit statuses.
+use anyhow::{anyhow, Context, Result};
+use gitbutler_error::error::Code;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use reqwest::{header, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    send, send_optional, AutoMerge, BranchProtection, Check, CheckOutcome, ChecksSummary, Forge,
+    ForgeFuture, ForgeRepo, MergeMethod, NewPullRequest, PullRequest, PullRequestHead,
+    PullRequestState,
+};
+
+pub(crate) struct Gitea {
+    client: reqwest::Client,
+    api_url: String,
+    token: String,
+}
+
+impl Gitea {
+    /// Talk to the API of the forge that hosts `repo`, authenticated with `token`.
+    pub(crate) fn new(repo: &ForgeRepo, token: String) -> Self {
+        Gitea {
+            client: reqwest::Client::new(),
+            api_url: format!("{}/api/v1", repo.web_url()),
+            token,
+        }
+    }
+
+    async fn create(
+        &self,
+        repo: &ForgeRepo,
+        head: &PullRequestHead,
+        base: &str,
+        pull_request: &NewPullRequest,
+    ) -> Result<PullRequest> {
+        #[derive(Serialize)]
+        struct Body<'a> {
+            title: String,
+            body: &'a str,
+            head: &'a str,
+            base: &'a str,
+        }
+
+        let head = head.qualified_branch(repo);
+        // Pull requests are drafts if their title says they are a work in progress.
+        let title = if pull_request.draft {
+            format!("WIP: {}", pull_request.title)
+        } else {
+            pull_request.title.clone()
+        };
+        let created: ApiPullRequest = send(
+            "Gitea",
+            self.request(reqwest::Method::POST, &repo_path(repo, "pulls"))
+                .json(&Body {
+                    title,
+                    body: &pull_request.body,
+                    head: &head,
+                    base,
+                }),
+        )
+        .await
+        .with_context(|| format!("failed to open a pull request for {head}"))?;
+        self.with_checks(repo, created).await
+    }
+
+    async fn get(&self, repo: &ForgeRepo, number: u64) -> Result<PullRequest> {
+        let pull_request: ApiPullRequest = send(
+            "Gitea",
+            self.request(
+                reqwest::Method::GET,
+                &repo_path(repo, &format!("pulls/{number}")),
+            ),
+        )
+        .await
+        .with_context(|| format!("failed to get pull request #{number}"))?;
+        self.with_checks(repo, pull_request).await
+    }
+
+    async fn find_open(&self, repo: &ForgeRepo, head: &PullRequestHead) -> Result<Option<u64>> {
+        // Pull requests can't be filtered by their head, so the open ones are searched instead.
+        let pull_requests: Vec<ApiPullRequest> = send(
+            "Gitea",
+            self.request(reqwest::Method::GET, &repo_path(repo, "pulls"))
+                .query(&[("state", "open"), ("limit", "50")]),
+        )
+        .await
+        .with_context(|| format!("failed to look for pull requests of {}", head.branch))?;
+        let head_repo = format!("{}/{}", head.repo.owner, head.repo.name);
+        Ok(pull_requests
+            .iter()
+            .find(|pull_request| {
+                pull_request.head.name == head.branch
+                    && pull_request
+                        .head
+                        .repo
+                        .as_ref()
+                        .map_or(true, |repo| repo.full_name.eq_ignore_ascii_case(&head_repo))
+            })
+            .map(|pull_request| pull_request.number))
+    }
+
+    async fn request_review(
+        &self,
+        repo: &ForgeRepo,
+        number: u64,
+        reviewers: &[String],
+    ) -> Result<()> {
+        #[derive(Serialize)]
+        struct Body<'a> {
+            reviewers: &'a [String],
+        }
+
+        let _: serde::de::IgnoredAny = send(
+            "Gitea",
+            self.request(
+                reqwest::Method::POST,
+                &repo_path(repo, &format!("pulls/{number}/requested_reviewers")),
+            )
+            .json(&Body { reviewers }),
+        )
+        .await
+        .with_context(|| format!("failed to request reviews of pull request #{number}"))?;
+        Ok(())
+    }
+
+    async fn with_checks(
+        &self,
+        repo: &ForgeRepo,
+        pull_request: ApiPullRequest,
+    ) -> Result<PullRequest> {
+        let checks = self.checks(repo, &pull_request.head.sha).await?;
+        Ok(PullRequest {
+            checks,
+            ..into_pull_request(pull_request)
+        })
+    }
+
+    /// Summarize the statuses of the commit `sha`, or return `None` if there are none.
+    async fn checks(&self, repo: &ForgeRepo, sha: &str) -> Result<Option<ChecksSummary>> {
+        Ok(ChecksSummary::of(&self.statuses(repo, sha).await?))
+    }
+
+    /// Return the latest status of each context of the commit `sha`, which is also how Gitea Actions
+    /// report their jobs.
+    async fn statuses(&self, repo: &ForgeRepo, sha: &str) -> Result<Vec<Check>> {
+        #[derive(Deserialize)]
+        struct CombinedStatus {
+            #[serde(default)]
+            statuses: Vec<Status>,
+        }
+        #[derive(Deserialize)]
+        struct Status {
+            #[serde(default)]
+            context: String,
+            /// One of `pending`, `success`, `error`, `failure` or `warning`.
+            status: String,
+        }
+
+        let combined: CombinedStatus = send(
+            "Gitea",
+            self.request(
+                reqwest::Method::GET,
+                &repo_path(repo, &format!("commits/{sha}/status")),
+            ),
+        )
+        .await
+        .with_context(|| format!("failed to get the statuses of {sha}"))?;
+        Ok(combined
+            .statuses
+            .into_iter()
+            .map(|status| Check {
+                outcome: match status.status.as_str() {
+                    "success" | "warning" => CheckOutcome::Passed,
+                    "error" | "failure" => CheckOutcome::Failed,
+                    _ => CheckOutcome::Pending,
+                },
+                name: status.context,
+            })
+            .collect())
+    }
+
+    /// Read whether `branch` is protected, and the rules of its protection if the token may see them.
+    /// Merge commits can't be forbidden for pushes on Gitea.
+    async fn protection(&self, repo: &ForgeRepo, branch: &str) -> Result<BranchProtection> {
+        #[derive(Deserialize)]
+        struct ApiBranchInfo {
+            protected: bool,
+        }
+        #[derive(Deserialize)]
+        struct ApiProtection {
+            #[serde(default)]
+            require_signed_commits: bool,
+            /// Only known to Gitea 1.22 and later, which allowed no force-pushes before.
+            #[serde(default)]
+            enable_force_push: bool,
+        }
+
+        let branch_path = format!("branches/{}", urlencoding::encode(branch));
+        let info: Option<ApiBranchInfo> = send_optional(
+            "Gitea",
+            self.request(reqwest::Method::GET, &repo_path(repo, &branch_path)),
+            true,
+        )
+        .await
+        .with_context(|| format!("failed to get branch {branch}"))?;
+        if !info.is_some_and(|info| info.protected) {
+            return Ok(BranchProtection::default());
+        }
+
+        let mut protection = BranchProtection {
+            protected: true,
+            forbids_force_push: true,
+            ..Default::default()
+        };
+        // Only administrators of the repository can read the rules of protected branches.
+        let rules: Result<Option<ApiProtection>> = send_optional(
+            "Gitea",
+            self.request(
+                reqwest::Method::GET,
+                &repo_path(
+                    repo,
+                    &format!("branch_protections/{}", urlencoding::encode(branch)),
+                ),
+            ),
+            true,
+        )
+        .await;
+        match rules {
+            Ok(Some(rules)) => {
+                protection.requires_signed_commits = rules.require_signed_commits;
+                protection.forbids_force_push = !rules.enable_force_push;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::debug!(?err, branch, "failed to read the protection of the branch");
+            }
+        }
+        Ok(protection)
+    }
+
+    /// Have pull request `number` merged with `method` once its checks succeed. Gitea has no merge queues.
+    async fn auto_merge(&self, repo: &ForgeRepo, number: u64, auto_merge: AutoMerge) -> Result<()> {
+        #[derive(Serialize)]
+        struct Body {
+            #[serde(rename = "Do")]
+            method: &'static str,
+            merge_when_checks_succeed: bool,
+        }
+
+        let AutoMerge::WhenChecksPass { method } = auto_merge else {
+            return Err(anyhow!("Gitea has no merge queues").context(Code::Forge));
+        };
+        let _: serde::de::IgnoredAny = send(
+            "Gitea",
+            self.request(
+                reqwest::Method::POST,
+                &repo_path(repo, &format!("pulls/{number}/merge")),
+            )
+            .json(&Body {
+                method: match method {
+                    MergeMethod::Merge => "merge",
+                    MergeMethod::Squash => "squash",
+                    MergeMethod::Rebase => "rebase",
+                },
+                merge_when_checks_succeed: true,
+            }),
+        )
+        .await
+        .with_context(|| format!("failed to merge pull request #{number} automatically"))?;
+        Ok(())
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, format!("{}/{path}", self.api_url))
+            .header(header::ACCEPT, "application/json")
+            .header(header::USER_AGENT, "GitButler")
+            .header(header::AUTHORIZATION, format!("token {}", self.token))
+    }
+}
+
+impl Forge for Gitea {
+    fn create_pull_request<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        head: &'a PullRequestHead,
+        base: &'a str,
+        pull_request: &'a NewPullRequest,
+    ) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(self.create(repo, head, base, pull_request))
+    }
+
+    fn pull_request<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        number: u64,
+    ) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(self.get(repo, number))
+    }
+
+    fn request_reviewers<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        number: u64,
+        reviewers: &'a [String],
+    ) -> ForgeFuture<'a, ()> {
+        Box::pin(self.request_review(repo, number, reviewers))
+    }
+
+    fn find_open_pull_request<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        head: &'a PullRequestHead,
+    ) -> ForgeFuture<'a, Option<u64>> {
+        Box::pin(self.find_open(repo, head))
+    }
+
+    fn branch_protection<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        branch: &'a str,
+    ) -> ForgeFuture<'a, BranchProtection> {
+        Box::pin(self.protection(repo, branch))
+    }
+
+    fn commit_checks<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        sha: &'a str,
+    ) -> ForgeFuture<'a, Vec<Check>> {
+        Box::pin(self.statuses(repo, sha))
+    }
+
+    fn enable_auto_merge<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        number: u64,
+        auto_merge: AutoMerge,
+    ) -> ForgeFuture<'a, ()> {
+        Box::pin(self.auto_merge(repo, number, auto_merge))
+    }
+}
+
+/// A pull request as the API returns it.
+#[derive(Deserialize)]
+struct ApiPullRequest {
+    number: u64,
+    html_url: String,
+    title: String,
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    merged: bool,
+    #[serde(default)]
+    mergeable: Option<bool>,
+    /// Once merged, the merge commit or the last commit that was added to the base.
+    #[serde(default)]
+    merge_commit_sha: Option<String>,
+    head: ApiBranch,
+    base: ApiBranch,
+}
+
+#[derive(Deserialize)]
+struct ApiBranch {
+    #[serde(rename = "ref")]
+    name: String,
+    #[serde(default)]
+    sha: String,
+    repo: Option<ApiRepo>,
+}
+
+#[derive(Deserialize)]
+struct ApiRepo {
+    full_name: String,
+}
+
+fn into_pull_request(pull_request: ApiPullRequest) -> PullRequest {
+    let state = if pull_request.merged {
+        PullRequestState::Merged
+    } else if pull_request.state == "open" {
+        PullRequestState::Open
+    } else {
+        PullRequestState::Closed
+    };
+    PullRequest {
+        number: pull_request.number,
+        url: pull_request.html_url,
+        title: pull_request.title,
+        state,
+        draft: pull_request.draft,
+        mergeable: pull_request.mergeable,
+        checks: None,
+        head: pull_request.head.name,
+        base: pull_request.base.name,
+        updated_timestamp_ms: now_since_unix_epoch_ms(),
+        auto_merge: None,
+        merge_commit: (state == PullRequestState::Merged)
+            .then_some(pull_request.merge_commit_sha)
+            .flatten(),
+    }
+}
+
+fn repo_path(repo: &ForgeRepo, path: &str) -> String {
+    format!("repos/{}/{}/{path}", repo.owner, repo.name)
+}
@@ -0,0 +1,506 @@
+//! A client for the REST API of GitHub, and of GitHub Enterprise Server for repositories on other hosts.
+use anyhow::{anyhow, Context, Result};
+use gitbutler_error::error::Code;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use reqwest::{header, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    send, send_optional, AutoMerge, BranchProtection, Check, CheckOutcome, ChecksSummary, Forge,
+    ForgeFuture, ForgeRepo, MergeMethod, NewPullRequest, PullRequest, PullRequestHead,
+    PullRequestState,
+};
+
+const API_VERSION: &str = "2022-11-28";
+
+const ENABLE_AUTO_MERGE: &str = "mutation($id: ID!, $method: PullRequestMergeMethod!) {
+  enablePullRequestAutoMerge(input: {pullRequestId: $id, mergeMethod: $method}) { clientMutationId }
+}";
+const ENQUEUE: &str = "mutation($id: ID!) {
+  enqueuePullRequest(input: {pullRequestId: $id}) { clientMutationId }
+}";
+
+pub(crate) struct GitHub {
+    client: reqwest::Client,
+    api_url: String,
+    /// The endpoint of the GraphQL API, which is the only one to merge pull requests automatically.
+    graphql_url: String,
+    token: String,
+}
+
+impl GitHub {
+    /// Talk to the API of the forge that hosts `repo`, authenticated with `token`.
+    pub(crate) fn new(repo: &ForgeRepo, token: String) -> Self {
+        let (api_url, graphql_url) = if repo.host == "github.com" {
+            (
+                "https://api.github.com".to_owned(),
+                "https://api.github.com/graphql".to_owned(),
+            )
+        } else {
+            (
+                format!("{}/api/v3", repo.web_url()),
+                format!("{}/api/graphql", repo.web_url()),
+            )
+        };
+        GitHub {
+            client: reqwest::Client::new(),
+            api_url,
+            graphql_url,
+            token,
+        }
+    }
+
+    async fn create(
+        &self,
+        repo: &ForgeRepo,
+        head: &PullRequestHead,
+        base: &str,
+        pull_request: &NewPullRequest,
+    ) -> Result<PullRequest> {
+        #[derive(Serialize)]
+        struct Body<'a> {
+            title: &'a str,
+            body: &'a str,
+            head: &'a str,
+            base: &'a str,
+            draft: bool,
+        }
+
+        let head = head.qualified_branch(repo);
+        let created: ApiPullRequest = send(
+            "GitHub",
+            self.request(reqwest::Method::POST, &repo_path(repo, "pulls"))
+                .json(&Body {
+                    title: &pull_request.title,
+                    body: &pull_request.body,
+                    head: &head,
+                    base,
+                    draft: pull_request.draft,
+                }),
+        )
+        .await
+        .with_context(|| format!("failed to open a pull request for {head}"))?;
+        self.with_checks(repo, created).await
+    }
+
+    async fn get(&self, repo: &ForgeRepo, number: u64) -> Result<PullRequest> {
+        let pull_request: ApiPullRequest = send(
+            "GitHub",
+            self.request(
+                reqwest::Method::GET,
+                &repo_path(repo, &format!("pulls/{number}")),
+            ),
+        )
+        .await
+        .with_context(|| format!("failed to get pull request #{number}"))?;
+        self.with_checks(repo, pull_request).await
+    }
+
+    async fn find_open(&self, repo: &ForgeRepo, head: &PullRequestHead) -> Result<Option<u64>> {
+        // The head has to be qualified by its owner, even if it's in the same repository.
+        let head = format!("{}:{}", head.repo.owner, head.branch);
+        let pull_requests: Vec<ApiPullRequest> = send(
+            "GitHub",
+            self.request(reqwest::Method::GET, &repo_path(repo, "pulls"))
+                .query(&[("head", head.as_str()), ("state", "open")]),
+        )
+        .await
+        .with_context(|| format!("failed to look for pull requests of {head}"))?;
+        Ok(pull_requests
+            .first()
+            .map(|pull_request| pull_request.number))
+    }
+
+    async fn request_review(
+        &self,
+        repo: &ForgeRepo,
+        number: u64,
+        reviewers: &[String],
+    ) -> Result<()> {
+        #[derive(Serialize)]
+        struct Body<'a> {
+            reviewers: &'a [String],
+        }
+
+        let _: serde::de::IgnoredAny = send(
+            "GitHub",
+            self.request(
+                reqwest::Method::POST,
+                &repo_path(repo, &format!("pulls/{number}/requested_reviewers")),
+            )
+            .json(&Body { reviewers }),
+        )
+        .await
+        .with_context(|| format!("failed to request reviews of pull request #{number}"))?;
+        Ok(())
+    }
+
+    async fn with_checks(
+        &self,
+        repo: &ForgeRepo,
+        pull_request: ApiPullRequest,
+    ) -> Result<PullRequest> {
+        let checks = self.checks(repo, &pull_request.head.sha).await?;
+        Ok(PullRequest {
+            checks,
+            ..into_pull_request(pull_request)
+        })
+    }
+
+    /// Summarize the check runs of the commit `sha`, or return `None` if there are none.
+    async fn checks(&self, repo: &ForgeRepo, sha: &str) -> Result<Option<ChecksSummary>> {
+        Ok(ChecksSummary::of(&self.check_runs(repo, sha).await?))
+    }
+
+    /// Return the check runs of the commit `sha`, which are reported by GitHub Actions and apps.
+    async fn check_runs(&self, repo: &ForgeRepo, sha: &str) -> Result<Vec<Check>> {
+        #[derive(Deserialize)]
+        struct CheckRuns {
+            check_runs: Vec<CheckRun>,
+        }
+        #[derive(Deserialize)]
+        struct CheckRun {
+            #[serde(default)]
+            name: String,
+            status: String,
+            conclusion: Option<String>,
+        }
+
+        let runs: CheckRuns = send(
+            "GitHub",
+            self.request(
+                reqwest::Method::GET,
+                &repo_path(repo, &format!("commits/{sha}/check-runs")),
+            )
+            .query(&[("per_page", "100")]),
+        )
+        .await
+        .with_context(|| format!("failed to get the checks of {sha}"))?;
+        Ok(runs
+            .check_runs
+            .into_iter()
+            .map(|run| Check {
+                outcome: match (run.status.as_str(), run.conclusion.as_deref()) {
+                    ("completed", Some("success" | "neutral" | "skipped")) => CheckOutcome::Passed,
+                    ("completed", _) => CheckOutcome::Failed,
+                    _ => CheckOutcome::Pending,
+                },
+                name: run.name,
+            })
+            .collect())
+    }
+
+    /// Return the check runs of the commit `sha` along with its commit statuses, which other CI
+    /// services report.
+    async fn commit_checks(&self, repo: &ForgeRepo, sha: &str) -> Result<Vec<Check>> {
+        #[derive(Deserialize)]
+        struct CombinedStatus {
+            statuses: Vec<Status>,
+        }
+        #[derive(Deserialize)]
+        struct Status {
+            context: String,
+            /// One of `error`, `failure`, `pending` or `success`.
+            state: String,
+        }
+
+        let mut checks = self.check_runs(repo, sha).await?;
+        // Only the latest status of each context is listed.
+        let combined: CombinedStatus = send(
+            "GitHub",
+            self.request(
+                reqwest::Method::GET,
+                &repo_path(repo, &format!("commits/{sha}/status")),
+            )
+            .query(&[("per_page", "100")]),
+        )
+        .await
+        .with_context(|| format!("failed to get the statuses of {sha}"))?;
+        checks.extend(combined.statuses.into_iter().map(|status| Check {
+            outcome: match status.state.as_str() {
+                "success" => CheckOutcome::Passed,
+                "error" | "failure" => CheckOutcome::Failed,
+                _ => CheckOutcome::Pending,
+            },
+            name: status.context,
+        }));
+        Ok(checks)
+    }
+
+    /// Enable auto-merge for pull request `number`, or add it to the merge queue of its base, both of which
+    /// are only possible with the GraphQL API that refers to the pull request by its node id.
+    async fn auto_merge(&self, repo: &ForgeRepo, number: u64, auto_merge: AutoMerge) -> Result<()> {
+        #[derive(Serialize)]
+        struct Body<'a> {
+            query: &'a str,
+            variables: serde_json::Value,
+        }
+        #[derive(Deserialize)]
+        struct Response {
+            #[serde(default)]
+            errors: Vec<GraphQlError>,
+        }
+        #[derive(Deserialize)]
+        struct GraphQlError {
+            message: String,
+        }
+
+        let pull_request: ApiPullRequest = send(
+            "GitHub",
+            self.request(
+                reqwest::Method::GET,
+                &repo_path(repo, &format!("pulls/{number}")),
+            ),
+        )
+        .await
+        .with_context(|| format!("failed to get pull request #{number}"))?;
+        let (query, variables) = match auto_merge {
+            AutoMerge::WhenChecksPass { method } => (
+                ENABLE_AUTO_MERGE,
+                serde_json::json!({
+                    "id": pull_request.node_id,
+                    "method": match method {
+                        MergeMethod::Merge => "MERGE",
+                        MergeMethod::Squash => "SQUASH",
+                        MergeMethod::Rebase => "REBASE",
+                    },
+                }),
+            ),
+            AutoMerge::MergeQueue => (ENQUEUE, serde_json::json!({ "id": pull_request.node_id })),
+        };
+        // Errors are reported in the body of a successful response.
+        let response: Response = send(
+            "GitHub",
+            self.client
+                .post(&self.graphql_url)
+                .header(header::USER_AGENT, "GitButler")
+                .bearer_auth(&self.token)
+                .json(&Body { query, variables }),
+        )
+        .await
+        .with_context(|| format!("failed to merge pull request #{number} automatically"))?;
+        if !response.errors.is_empty() {
+            let messages: Vec<_> = response.errors.into_iter().map(|err| err.message).collect();
+            return Err(anyhow!("GitHub responded with: {}", messages.join(", "))
+                .context(format!(
+                    "failed to merge pull request #{number} automatically"
+                ))
+                .context(Code::Forge));
+        }
+        Ok(())
+    }
+
+    /// Combine the protection of `branch` with the rules of the rulesets that apply to it.
+    async fn protection(&self, repo: &ForgeRepo, branch: &str) -> Result<BranchProtection> {
+        #[derive(Deserialize)]
+        struct ApiBranchInfo {
+            protected: bool,
+        }
+        #[derive(Deserialize)]
+        struct Enabled {
+            enabled: bool,
+        }
+        #[derive(Deserialize)]
+        struct ApiProtection {
+            required_signatures: Option<Enabled>,
+            required_linear_history: Option<Enabled>,
+            allow_force_pushes: Option<Enabled>,
+        }
+        #[derive(Deserialize)]
+        struct Rule {
+            #[serde(rename = "type")]
+            kind: String,
+        }
+
+        let branch_path = format!("branches/{}", urlencoding::encode(branch));
+        let mut protection = BranchProtection::default();
+        // Branches that don't exist yet aren't protected, but rulesets may still apply to them.
+        let info: Option<ApiBranchInfo> = send_optional(
+            "GitHub",
+            self.request(reqwest::Method::GET, &repo_path(repo, &branch_path)),
+            true,
+        )
+        .await
+        .with_context(|| format!("failed to get branch {branch}"))?;
+        if info.is_some_and(|info| info.protected) {
+            // Protected branches reject force-pushes unless allowed, which only administrators can see.
+            protection.protected = true;
+            protection.forbids_force_push = true;
+            let rules: Result<Option<ApiProtection>> = send_optional(
+                "GitHub",
+                self.request(
+                    reqwest::Method::GET,
+                    &repo_path(repo, &format!("{branch_path}/protection")),
+                ),
+                true,
+            )
+            .await;
+            match rules {
+                Ok(Some(rules)) => {
+                    let enabled = |rule: Option<Enabled>| rule.is_some_and(|rule| rule.enabled);
+                    protection.requires_signed_commits = enabled(rules.required_signatures);
+                    protection.requires_linear_history = enabled(rules.required_linear_history);
+                    protection.forbids_force_push = !enabled(rules.allow_force_pushes);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    tracing::debug!(?err, branch, "failed to read the protection of the branch");
+                }
+            }
+        }
+
+        let rules: Vec<Rule> = send(
+            "GitHub",
+            self.request(
+                reqwest::Method::GET,
+                &repo_path(repo, &format!("rules/{branch_path}")),
+            ),
+        )
+        .await
+        .with_context(|| format!("failed to get the rules of branch {branch}"))?;
+        for rule in rules {
+            match rule.kind.as_str() {
+                "required_signatures" => protection.requires_signed_commits = true,
+                "required_linear_history" => protection.requires_linear_history = true,
+                "non_fast_forward" => protection.forbids_force_push = true,
+                _ => {}
+            }
+            protection.protected = true;
+        }
+        Ok(protection)
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, format!("{}/{path}", self.api_url))
+            .header(header::ACCEPT, "application/vnd.github+json")
+            .header(header::USER_AGENT, "GitButler")
+            .header("X-GitHub-Api-Version", API_VERSION)
+            .bearer_auth(&self.token)
+    }
+}
+
+impl Forge for GitHub {
+    fn create_pull_request<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        head: &'a PullRequestHead,
+        base: &'a str,
+        pull_request: &'a NewPullRequest,
+    ) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(self.create(repo, head, base, pull_request))
+    }
+
+    fn pull_request<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        number: u64,
+    ) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(self.get(repo, number))
+    }
+
+    fn request_reviewers<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        number: u64,
+        reviewers: &'a [String],
+    ) -> ForgeFuture<'a, ()> {
+        Box::pin(self.request_review(repo, number, reviewers))
+    }
+
+    fn find_open_pull_request<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        head: &'a PullRequestHead,
+    ) -> ForgeFuture<'a, Option<u64>> {
+        Box::pin(self.find_open(repo, head))
+    }
+
+    fn branch_protection<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        branch: &'a str,
+    ) -> ForgeFuture<'a, BranchProtection> {
+        Box::pin(self.protection(repo, branch))
+    }
+
+    fn commit_checks<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        sha: &'a str,
+    ) -> ForgeFuture<'a, Vec<Check>> {
+        Box::pin(self.commit_checks(repo, sha))
+    }
+
+    fn enable_auto_merge<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        number: u64,
+        auto_merge: AutoMerge,
+    ) -> ForgeFuture<'a, ()> {
+        Box::pin(self.auto_merge(repo, number, auto_merge))
+    }
+}
+
+/// A pull request as the API returns it.
+#[derive(Deserialize)]
+struct ApiPullRequest {
+    number: u64,
+    /// The id of the pull request in the GraphQL API.
+    #[serde(default)]
+    node_id: String,
+    html_url: String,
+    title: String,
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    /// Only set when listing pull requests.
+    merged_at: Option<String>,
+    /// Not set when listing pull requests.
+    #[serde(default)]
+    merged: bool,
+    #[serde(default)]
+    mergeable: Option<bool>,
+    /// Once merged, the merge commit, the squashed commit or the last rebased commit in the base.
+    #[serde(default)]
+    merge_commit_sha: Option<String>,
+    head: ApiBranch,
+    base: ApiBranch,
+}
+
+#[derive(Deserialize)]
+struct ApiBranch {
+    #[serde(rename = "ref")]
+    name: String,
+    sha: String,
+}
+
+fn into_pull_request(pull_request: ApiPullRequest) -> PullRequest {
+    let state = if pull_request.merged || pull_request.merged_at.is_some() {
+        PullRequestState::Merged
+    } else if pull_request.state == "open" {
+        PullRequestState::Open
+    } else {
+        PullRequestState::Closed
+    };
+    PullRequest {
+        number: pull_request.number,
+        url: pull_request.html_url,
+        title: pull_request.title,
+        state,
+        draft: pull_request.draft,
+        mergeable: pull_request.mergeable,
+        checks: None,
+        head: pull_request.head.name,
+        base: pull_request.base.name,
+        updated_timestamp_ms: now_since_unix_epoch_ms(),
+        auto_merge: None,
+        merge_commit: (state == PullRequestState::Merged)
+            .then_some(pull_request.merge_commit_sha)
+            .flatten(),
+    }
+}
+
+fn repo_path(repo: &ForgeRepo, path: &str) -> String {
+    format!("repos/{}/{}/{path}", repo.owner, repo.name)
+}
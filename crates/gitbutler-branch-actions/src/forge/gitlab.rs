@@ -0,0 +1,488 @@
+//! A client for the REST API of GitLab, where pull requests are called merge requests and checks are
+//! the jobs of pipelines.
+use anyhow::{Context, Result};
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use reqwest::{header, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    send, send_optional, AutoMerge, BranchProtection, Check, CheckOutcome, ChecksSummary, Forge,
+    ForgeFuture, ForgeRepo, MergeMethod, NewPullRequest, PullRequest, PullRequestHead,
+    PullRequestState,
+};
+
+pub(crate) struct GitLab {
+    client: reqwest::Client,
+    api_url: String,
+    token: String,
+}
+
+impl GitLab {
+    /// Talk to the API of the forge that hosts `repo`, authenticated with `token`.
+    pub(crate) fn new(repo: &ForgeRepo, token: String) -> Self {
+        GitLab {
+            client: reqwest::Client::new(),
+            api_url: format!("{}/api/v4", repo.web_url()),
+            token,
+        }
+    }
+
+    async fn create(
+        &self,
+        repo: &ForgeRepo,
+        head: &PullRequestHead,
+        base: &str,
+        pull_request: &NewPullRequest,
+    ) -> Result<PullRequest> {
+        #[derive(Serialize)]
+        struct Body<'a> {
+            source_branch: &'a str,
+            target_branch: &'a str,
+            title: String,
+            description: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            target_project_id: Option<u64>,
+        }
+        #[derive(Deserialize)]
+        struct ApiProject {
+            id: u64,
+        }
+
+        // Merge requests from forks are opened in the fork, pointing at the project they go into.
+        let in_fork = head.repo != *repo;
+        let target_project_id = if in_fork {
+            let project: ApiProject = send(
+                "GitLab",
+                self.request(reqwest::Method::GET, &project_path(repo, "")),
+            )
+            .await
+            .with_context(|| format!("failed to get project {}/{}", repo.owner, repo.name))?;
+            Some(project.id)
+        } else {
+            None
+        };
+        let title = if pull_request.draft {
+            format!("Draft: {}", pull_request.title)
+        } else {
+            pull_request.title.clone()
+        };
+        let created: ApiMergeRequest = send(
+            "GitLab",
+            self.request(
+                reqwest::Method::POST,
+                &project_path(&head.repo, "/merge_requests"),
+            )
+            .json(&Body {
+                source_branch: &head.branch,
+                target_branch: base,
+                title,
+                description: &pull_request.body,
+                target_project_id,
+            }),
+        )
+        .await
+        .with_context(|| format!("failed to open a merge request for {}", head.branch))?;
+        self.with_checks(created).await
+    }
+
+    async fn get(&self, repo: &ForgeRepo, number: u64) -> Result<PullRequest> {
+        let merge_request: ApiMergeRequest = send(
+            "GitLab",
+            self.request(
+                reqwest::Method::GET,
+                &project_path(repo, &format!("/merge_requests/{number}")),
+            ),
+        )
+        .await
+        .with_context(|| format!("failed to get merge request !{number}"))?;
+        self.with_checks(merge_request).await
+    }
+
+    async fn find_open(&self, repo: &ForgeRepo, head: &PullRequestHead) -> Result<Option<u64>> {
+        let merge_requests: Vec<ApiMergeRequest> = send(
+            "GitLab",
+            self.request(reqwest::Method::GET, &project_path(repo, "/merge_requests"))
+                .query(&[("state", "opened"), ("source_branch", head.branch.as_str())]),
+        )
+        .await
+        .with_context(|| format!("failed to look for merge requests of {}", head.branch))?;
+        Ok(merge_requests
+            .first()
+            .map(|merge_request| merge_request.iid))
+    }
+
+    /// Make the users named `reviewers` the reviewers of merge request `number`, which GitLab only
+    /// knows by their ids.
+    async fn request_review(
+        &self,
+        repo: &ForgeRepo,
+        number: u64,
+        reviewers: &[String],
+    ) -> Result<()> {
+        #[derive(Deserialize)]
+        struct User {
+            id: u64,
+        }
+        #[derive(Serialize)]
+        struct Body {
+            reviewer_ids: Vec<u64>,
+        }
+
+        let mut reviewer_ids = Vec::with_capacity(reviewers.len());
+        for reviewer in reviewers {
+            let users: Vec<User> = send(
+                "GitLab",
+                self.request(reqwest::Method::GET, "users")
+                    .query(&[("username", reviewer.as_str())]),
+            )
+            .await
+            .with_context(|| format!("failed to look up user {reviewer}"))?;
+            let user = users
+                .first()
+                .with_context(|| format!("there is no user named {reviewer}"))?;
+            reviewer_ids.push(user.id);
+        }
+        let _: serde::de::IgnoredAny = send(
+            "GitLab",
+            self.request(
+                reqwest::Method::PUT,
+                &project_path(repo, &format!("/merge_requests/{number}")),
+            )
+            .json(&Body { reviewer_ids }),
+        )
+        .await
+        .with_context(|| format!("failed to request reviews of merge request !{number}"))?;
+        Ok(())
+    }
+
+    async fn with_checks(&self, merge_request: ApiMergeRequest) -> Result<PullRequest> {
+        let checks = match &merge_request.head_pipeline {
+            Some(pipeline) => self.checks(pipeline).await?,
+            None => None,
+        };
+        Ok(PullRequest {
+            checks,
+            ..into_pull_request(merge_request)
+        })
+    }
+
+    /// Summarize the jobs of `pipeline`, or return `None` if it has none.
+    async fn checks(&self, pipeline: &ApiPipeline) -> Result<Option<ChecksSummary>> {
+        #[derive(Deserialize)]
+        struct Job {
+            status: String,
+        }
+
+        // The pipeline of a merge request from a fork runs in the fork.
+        let jobs: Vec<Job> = send(
+            "GitLab",
+            self.request(
+                reqwest::Method::GET,
+                &format!(
+                    "projects/{}/pipelines/{}/jobs",
+                    pipeline.project_id, pipeline.id
+                ),
+            )
+            .query(&[("per_page", "100")]),
+        )
+        .await
+        .with_context(|| format!("failed to get the jobs of pipeline {}", pipeline.id))?;
+        let mut summary = ChecksSummary::default();
+        for job in jobs {
+            match job.status.as_str() {
+                "success" | "skipped" => summary.passed += 1,
+                "failed" | "canceled" => summary.failed += 1,
+                // Jobs that only run when started by hand don't hold up the merge request.
+                "manual" => continue,
+                _ => summary.pending += 1,
+            }
+            summary.total += 1;
+        }
+        Ok((summary.total > 0).then_some(summary))
+    }
+
+    /// Return the latest status of each job that ran on the commit `sha`, whether in a pipeline of the
+    /// project or reported by an external CI service.
+    async fn commit_checks(&self, repo: &ForgeRepo, sha: &str) -> Result<Vec<Check>> {
+        #[derive(Deserialize)]
+        struct CommitStatus {
+            name: String,
+            status: String,
+            #[serde(default)]
+            allow_failure: bool,
+        }
+
+        let statuses: Vec<CommitStatus> = send(
+            "GitLab",
+            self.request(
+                reqwest::Method::GET,
+                &project_path(repo, &format!("/repository/commits/{sha}/statuses")),
+            )
+            .query(&[("per_page", "100")]),
+        )
+        .await
+        .with_context(|| format!("failed to get the statuses of {sha}"))?;
+        Ok(statuses
+            .into_iter()
+            .filter_map(|status| {
+                let outcome = match status.status.as_str() {
+                    "success" | "skipped" => CheckOutcome::Passed,
+                    // Jobs that may fail don't hold up the merge request.
+                    "failed" | "canceled" if status.allow_failure => CheckOutcome::Passed,
+                    "failed" | "canceled" => CheckOutcome::Failed,
+                    // Jobs that only run when started by hand don't hold up the merge request.
+                    "manual" => return None,
+                    _ => CheckOutcome::Pending,
+                };
+                Some(Check {
+                    name: status.name,
+                    outcome,
+                })
+            })
+            .collect())
+    }
+
+    /// Have merge request `number` merged when its pipeline succeeds, or add it to the merge train of its
+    /// target branch. Whether its commits are rebased or merged is up to the project, only squashing can be
+    /// asked for.
+    async fn auto_merge(&self, repo: &ForgeRepo, number: u64, auto_merge: AutoMerge) -> Result<()> {
+        #[derive(Serialize)]
+        struct MergeBody {
+            merge_when_pipeline_succeeds: bool,
+            squash: bool,
+        }
+        #[derive(Serialize)]
+        struct TrainBody {
+            when_pipeline_succeeds: bool,
+        }
+
+        let request = match auto_merge {
+            AutoMerge::WhenChecksPass { method } => self
+                .request(
+                    reqwest::Method::PUT,
+                    &project_path(repo, &format!("/merge_requests/{number}/merge")),
+                )
+                .json(&MergeBody {
+                    merge_when_pipeline_succeeds: true,
+                    squash: method == MergeMethod::Squash,
+                }),
+            AutoMerge::MergeQueue => self
+                .request(
+                    reqwest::Method::POST,
+                    &project_path(repo, &format!("/merge_trains/merge_requests/{number}")),
+                )
+                .json(&TrainBody {
+                    when_pipeline_succeeds: true,
+                }),
+        };
+        let _: serde::de::IgnoredAny = send("GitLab", request)
+            .await
+            .with_context(|| format!("failed to merge merge request !{number} automatically"))?;
+        Ok(())
+    }
+
+    /// Read whether `branch` is protected, and the push rules of the project. Merge commits can't be
+    /// forbidden for pushes on GitLab.
+    async fn protection(&self, repo: &ForgeRepo, branch: &str) -> Result<BranchProtection> {
+        #[derive(Deserialize)]
+        struct ApiBranchInfo {
+            protected: bool,
+        }
+        #[derive(Deserialize)]
+        struct ProtectedBranch {
+            #[serde(default)]
+            allow_force_push: bool,
+        }
+        #[derive(Deserialize)]
+        struct PushRule {
+            #[serde(default)]
+            reject_unsigned_commits: bool,
+        }
+
+        let encoded = urlencoding::encode(branch).into_owned();
+        let mut protection = BranchProtection::default();
+        let info: Option<ApiBranchInfo> = send_optional(
+            "GitLab",
+            self.request(
+                reqwest::Method::GET,
+                &project_path(repo, &format!("/repository/branches/{encoded}")),
+            ),
+            true,
+        )
+        .await
+        .with_context(|| format!("failed to get branch {branch}"))?;
+        if info.is_some_and(|info| info.protected) {
+            protection.protected = true;
+            // Branches protected by a wildcard aren't found by their name, and forbid force-pushes by default.
+            let protected: Option<ProtectedBranch> = send_optional(
+                "GitLab",
+                self.request(
+                    reqwest::Method::GET,
+                    &project_path(repo, &format!("/protected_branches/{encoded}")),
+                ),
+                true,
+            )
+            .await
+            .with_context(|| format!("failed to get the protection of branch {branch}"))?;
+            protection.forbids_force_push =
+                protected.map_or(true, |protected| !protected.allow_force_push);
+        }
+
+        // Push rules are only available in some editions, and are `null` if the project has none.
+        let push_rule: Result<Option<Option<PushRule>>> = send_optional(
+            "GitLab",
+            self.request(reqwest::Method::GET, &project_path(repo, "/push_rule")),
+            true,
+        )
+        .await;
+        match push_rule {
+            Ok(push_rule) => {
+                protection.requires_signed_commits = push_rule
+                    .flatten()
+                    .is_some_and(|push_rule| push_rule.reject_unsigned_commits);
+            }
+            Err(err) => tracing::debug!(?err, "failed to read the push rules of the project"),
+        }
+        Ok(protection)
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, format!("{}/{path}", self.api_url))
+            .header(header::ACCEPT, "application/json")
+            .header(header::USER_AGENT, "GitButler")
+            .bearer_auth(&self.token)
+    }
+}
+
+impl Forge for GitLab {
+    fn create_pull_request<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        head: &'a PullRequestHead,
+        base: &'a str,
+        pull_request: &'a NewPullRequest,
+    ) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(self.create(repo, head, base, pull_request))
+    }
+
+    fn pull_request<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        number: u64,
+    ) -> ForgeFuture<'a, PullRequest> {
+        Box::pin(self.get(repo, number))
+    }
+
+    fn request_reviewers<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        number: u64,
+        reviewers: &'a [String],
+    ) -> ForgeFuture<'a, ()> {
+        Box::pin(self.request_review(repo, number, reviewers))
+    }
+
+    fn find_open_pull_request<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        head: &'a PullRequestHead,
+    ) -> ForgeFuture<'a, Option<u64>> {
+        Box::pin(self.find_open(repo, head))
+    }
+
+    fn branch_protection<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        branch: &'a str,
+    ) -> ForgeFuture<'a, BranchProtection> {
+        Box::pin(self.protection(repo, branch))
+    }
+
+    fn commit_checks<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        sha: &'a str,
+    ) -> ForgeFuture<'a, Vec<Check>> {
+        Box::pin(self.commit_checks(repo, sha))
+    }
+
+    fn enable_auto_merge<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        number: u64,
+        auto_merge: AutoMerge,
+    ) -> ForgeFuture<'a, ()> {
+        Box::pin(self.auto_merge(repo, number, auto_merge))
+    }
+}
+
+/// A merge request as the API returns it.
+#[derive(Deserialize)]
+struct ApiMergeRequest {
+    /// The number of the merge request within its project.
+    iid: u64,
+    web_url: String,
+    title: String,
+    /// One of `opened`, `closed`, `locked` or `merged`.
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    has_conflicts: bool,
+    /// Whether GitLab is done checking if the merge request can be merged, like `unchecked` or `can_be_merged`.
+    merge_status: Option<String>,
+    source_branch: String,
+    target_branch: String,
+    /// The merge commit once merged, unless it was fast-forwarded.
+    merge_commit_sha: Option<String>,
+    /// The squashed commit once merged, if it was squashed.
+    squash_commit_sha: Option<String>,
+    /// Only set when getting a single merge request.
+    head_pipeline: Option<ApiPipeline>,
+}
+
+#[derive(Deserialize)]
+struct ApiPipeline {
+    id: u64,
+    project_id: u64,
+}
+
+fn into_pull_request(merge_request: ApiMergeRequest) -> PullRequest {
+    let state = match merge_request.state.as_str() {
+        "opened" => PullRequestState::Open,
+        "merged" => PullRequestState::Merged,
+        _ => PullRequestState::Closed,
+    };
+    let checking = matches!(
+        merge_request.merge_status.as_deref(),
+        Some("unchecked" | "checking" | "cannot_be_merged_recheck")
+    );
+    PullRequest {
+        number: merge_request.iid,
+        url: merge_request.web_url,
+        title: merge_request.title,
+        state,
+        draft: merge_request.draft,
+        mergeable: (!checking).then_some(!merge_request.has_conflicts),
+        checks: None,
+        head: merge_request.source_branch,
+        base: merge_request.target_branch,
+        updated_timestamp_ms: now_since_unix_epoch_ms(),
+        auto_merge: None,
+        merge_commit: (state == PullRequestState::Merged)
+            .then(|| {
+                merge_request
+                    .merge_commit_sha
+                    .or(merge_request.squash_commit_sha)
+            })
+            .flatten(),
+    }
+}
+
+/// Return the path of `path` within the project `repo`, which is referred to by its URL-encoded path,
+/// as it may be in nested groups.
+fn project_path(repo: &ForgeRepo, path: &str) -> String {
+    let project = urlencoding::encode(&format!("{}/{}", repo.owner, repo.name)).into_owned();
+    format!("projects/{project}{path}")
+}
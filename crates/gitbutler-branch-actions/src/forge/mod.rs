@@ -0,0 +1,841 @@
+//! Work with the forge that hosts the repository of a project, like GitHub, GitLab or Gitea, to open
+//! pull requests for pushed virtual branches and keep track of their state.
+use std::{collections::BTreeMap, fmt, future::Future, path::Path, pin::Pin, str::FromStr};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::{Branch, BranchId, ForgeMerge};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_fs::TomlFile;
+use gitbutler_project::{ForgeKind, Project};
+use gitbutler_reference::RemoteRefname;
+use gitbutler_secret::{secret, Sensitive};
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use gitbutler_url::{Scheme, Url};
+use reqwest::RequestBuilder;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::VirtualBranchesExt;
+
+mod ci;
+pub(crate) use ci::refresh_ci_statuses;
+pub use ci::{CiState, CiStatus, CiStatusesHandle};
+mod gitea;
+mod github;
+mod gitlab;
+
+/// How long to wait before asking a forge again that limited the rate of requests without saying for how long.
+const RATE_LIMIT_BACKOFF_MS: i64 = 60 * 1000;
+
+/// The files forges read pull request templates from, relative to the worktree, in the order they are
+/// looked for.
+const PULL_REQUEST_TEMPLATES: [&str; 6] = [
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    "docs/pull_request_template.md",
+    "pull_request_template.md",
+    ".gitlab/merge_request_templates/Default.md",
+    ".gitea/pull_request_template.md",
+];
+
+/// A repository on a forge, as derived from the URL of a remote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForgeRepo {
+    /// The host of the forge, like `github.com`, along with its port if it isn't the default one.
+    pub host: String,
+    /// The user or organization that owns the repository.
+    pub owner: String,
+    /// The name of the repository, without a `.git` suffix.
+    pub name: String,
+    /// Whether the forge is reached over plain HTTP, which is only the case if the remote uses it.
+    pub insecure: bool,
+}
+
+impl ForgeRepo {
+    /// Derive the repository from the URL of a remote, like `git@github.com:owner/repo.git`
+    /// or `https://github.com/owner/repo`.
+    pub fn from_remote_url(remote_url: &str) -> Result<Self> {
+        let url = Url::from_str(remote_url)
+            .map_err(|err| anyhow!("{err}"))
+            .with_context(|| format!("remote url {remote_url} can't be parsed"))?;
+        let host = url
+            .host
+            .as_deref()
+            .filter(|host| !host.is_empty())
+            .with_context(|| format!("remote url {remote_url} has no host"))?;
+        let host = match (url.port, &url.scheme) {
+            (Some(port), Scheme::Http | Scheme::Https) => format!("{host}:{port}"),
+            _ => host.to_owned(),
+        };
+        let path = url.path.to_string();
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        let (owner, name) = path
+            .rsplit_once('/')
+            .filter(|(owner, name)| !owner.is_empty() && !name.is_empty())
+            .with_context(|| format!("remote url {remote_url} doesn't point to a repository"))?;
+        Ok(ForgeRepo {
+            host,
+            owner: owner.to_owned(),
+            name: name.to_owned(),
+            insecure: url.scheme == Scheme::Http,
+        })
+    }
+
+    /// The URL of the web interface of the forge, like `https://github.com`.
+    pub fn web_url(&self) -> String {
+        let scheme = if self.insecure { "http" } else { "https" };
+        format!("{scheme}://{}", self.host)
+    }
+}
+
+/// What a pull request is made of when it's created.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewPullRequest {
+    pub title: String,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub draft: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PullRequestState {
+    Open,
+    Merged,
+    /// Closed without being merged.
+    Closed,
+}
+
+/// How the checks that run on the head of a pull request, like CI, are doing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecksSummary {
+    pub total: u32,
+    pub passed: u32,
+    pub failed: u32,
+    /// The checks that are queued or still running.
+    pub pending: u32,
+}
+
+impl ChecksSummary {
+    /// Count `checks` by their outcome, or return `None` if there are none.
+    pub(crate) fn of(checks: &[Check]) -> Option<Self> {
+        let mut summary = ChecksSummary::default();
+        for check in checks {
+            summary.total += 1;
+            match check.outcome {
+                CheckOutcome::Passed => summary.passed += 1,
+                CheckOutcome::Failed => summary.failed += 1,
+                CheckOutcome::Pending => summary.pending += 1,
+            }
+        }
+        (summary.total > 0).then_some(summary)
+    }
+}
+
+/// A single check of a commit, like a check run, a commit status or a CI job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Check {
+    /// The name of the check, like the name of a CI job or the context of a commit status.
+    pub name: String,
+    pub outcome: CheckOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CheckOutcome {
+    Passed,
+    Failed,
+    /// The check is queued or still running.
+    Pending,
+}
+
+/// How a pull request is to be merged by the forge once its checks pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum AutoMerge {
+    /// Merge it with `method` as soon as all required checks pass.
+    WhenChecksPass { method: MergeMethod },
+    /// Add it to the merge queue, or the merge train on GitLab, which merges it once the checks pass
+    /// with the pull requests ahead of it, using the method the queue is set up with.
+    MergeQueue,
+}
+
+/// How the commits of a pull request end up in its base.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MergeMethod {
+    /// With a merge commit.
+    Merge,
+    /// As a single commit.
+    Squash,
+    /// Each commit rebased onto the base, without a merge commit.
+    Rebase,
+}
+
+/// A pull request as it was when it was last looked at on the forge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequest {
+    pub number: u64,
+    /// The URL of the pull request in the web interface of the forge.
+    pub url: String,
+    pub title: String,
+    pub state: PullRequestState,
+    pub draft: bool,
+    /// Whether the pull request can be merged without conflicts, or `None` if the forge didn't compute it yet.
+    pub mergeable: Option<bool>,
+    /// The checks of the head of the pull request, or `None` if there are none.
+    pub checks: Option<ChecksSummary>,
+    /// The name of the branch that is to be merged.
+    pub head: String,
+    /// The name of the branch that it is to be merged into.
+    pub base: String,
+    /// The time at which the state was fetched from the forge, in milliseconds since the Unix epoch.
+    pub updated_timestamp_ms: i64,
+    /// How the forge was asked to merge the pull request once its checks pass, if it was.
+    #[serde(default)]
+    pub auto_merge: Option<AutoMerge>,
+    /// The commit the pull request was merged as into its base, once it's merged.
+    #[serde(default)]
+    pub merge_commit: Option<String>,
+}
+
+/// The rules a forge enforces for pushes to a branch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchProtection {
+    /// Whether the branch is protected at all, which may mean that it can't be pushed to directly.
+    pub protected: bool,
+    pub requires_signed_commits: bool,
+    /// Whether merge commits are rejected.
+    pub requires_linear_history: bool,
+    /// Whether pushes that drop commits of the branch are rejected.
+    pub forbids_force_push: bool,
+}
+
+/// The pull requests of all branches, as persisted in a TOML file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PullRequests {
+    /// The pull requests by the name of the branch they were opened for.
+    branches: BTreeMap<String, PullRequest>,
+}
+
+/// A handle to the last known state of the pull requests of pushed branches.
+///
+/// Pull requests are keyed by the name of the branch they were opened for, as it was pushed,
+/// so they are shown for a branch whether it's applied or not.
+///
+/// The pull requests are kept in `pull_requests.toml`, and branches have none until they were fetched.
+pub struct PullRequestsHandle {
+    /// The file containing the pull requests of all branches.
+    file: TomlFile<PullRequests>,
+}
+
+impl PullRequestsHandle {
+    /// Creates a new handle to the pull requests stored in `base_path`.
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        Self {
+            file: TomlFile::new(base_path.as_ref().join("pull_requests.toml")),
+        }
+    }
+
+    /// Returns the pull request of the branch `branch_name`, or `None` if it has none.
+    ///
+    /// Errors if the file cannot be read.
+    pub fn get(&self, branch_name: &str) -> Result<Option<PullRequest>> {
+        Ok(self.file.read()?.branches.remove(branch_name))
+    }
+
+    /// Returns the pull requests of all branches, keyed by branch name.
+    ///
+    /// Errors if the file cannot be read.
+    pub fn list(&self) -> Result<BTreeMap<String, PullRequest>> {
+        Ok(self.file.read()?.branches)
+    }
+
+    /// Stores `pull_request` as the pull request of the branch it was opened for.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn set(&self, pull_request: PullRequest) -> Result<()> {
+        let mut pull_requests = self.file.read()?;
+        pull_requests
+            .branches
+            .insert(pull_request.head.clone(), pull_request);
+        self.file.write(&pull_requests)
+    }
+}
+
+/// The operations on pull requests, or merge requests as some forges call them, that all forges support.
+pub(crate) trait Forge: Send + Sync {
+    /// Open a pull request in `repo` to merge `head` into the branch `base`.
+    fn create_pull_request<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        head: &'a PullRequestHead,
+        base: &'a str,
+        pull_request: &'a NewPullRequest,
+    ) -> ForgeFuture<'a, PullRequest>;
+
+    /// Return the pull request `number` of `repo`, along with the checks of its head.
+    fn pull_request<'a>(&'a self, repo: &'a ForgeRepo, number: u64)
+        -> ForgeFuture<'a, PullRequest>;
+
+    /// Ask the users named `reviewers` to review the pull request `number` of `repo`.
+    fn request_reviewers<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        number: u64,
+        reviewers: &'a [String],
+    ) -> ForgeFuture<'a, ()>;
+
+    /// Return the number of the open pull request of `repo` that merges `head`, if there is one.
+    fn find_open_pull_request<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        head: &'a PullRequestHead,
+    ) -> ForgeFuture<'a, Option<u64>>;
+
+    /// Return the rules for pushes to the branch named `branch` of `repo`. Rules that can't be read
+    /// with the permissions of the token are assumed not to apply.
+    fn branch_protection<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        branch: &'a str,
+    ) -> ForgeFuture<'a, BranchProtection>;
+
+    /// Return all checks of the commit `sha` in `repo`, from all the ways the forge has to report them,
+    /// with the latest ones first.
+    fn commit_checks<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        sha: &'a str,
+    ) -> ForgeFuture<'a, Vec<Check>>;
+
+    /// Have the forge merge the open pull request `number` of `repo` as `auto_merge` says once its checks pass.
+    fn enable_auto_merge<'a>(
+        &'a self,
+        repo: &'a ForgeRepo,
+        number: u64,
+        auto_merge: AutoMerge,
+    ) -> ForgeFuture<'a, ()>;
+}
+
+/// What a [`Forge`] returns, which can be run on any thread.
+pub(crate) type ForgeFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// The branch a pull request merges.
+pub(crate) struct PullRequestHead {
+    /// The repository the branch was pushed to, which is a fork if it isn't the one of the target.
+    pub repo: ForgeRepo,
+    pub branch: String,
+}
+
+impl PullRequestHead {
+    /// Return the branch prefixed with the owner of its repository if it's in a fork of `repo`,
+    /// like `owner:branch`, which is how GitHub and Gitea refer to branches of forks.
+    fn qualified_branch(&self, repo: &ForgeRepo) -> String {
+        if self.repo.owner == repo.owner && self.repo.name == repo.name {
+            self.branch.clone()
+        } else {
+            format!("{}:{}", self.repo.owner, self.branch)
+        }
+    }
+}
+
+/// Where the pull request of a branch goes.
+struct PullRequestTarget {
+    /// The repository that the target branch is fetched from, which the pull request is opened in.
+    repo: ForgeRepo,
+    head: PullRequestHead,
+    /// The name of the target branch.
+    base: String,
+}
+
+/// Return the kind of forge that hosts the repository of the target of `project`, as set in the project
+/// or as detected from the host of the remote.
+pub(crate) fn forge_kind(project: &Project) -> Result<ForgeKind> {
+    if project.forge != ForgeKind::Detect {
+        return Ok(project.forge);
+    }
+    let target = project.virtual_branches().get_default_target()?;
+    let repo = ForgeRepo::from_remote_url(&target.remote_url).context(Code::Forge)?;
+    Ok(detect(&repo.host))
+}
+
+/// Tell the kind of forge by its `host`. Hosts that don't give it away are assumed to run
+/// GitHub Enterprise Server, and otherwise need the kind of forge set in the project.
+fn detect(host: &str) -> ForgeKind {
+    let host = host.split(':').next().unwrap_or(host).to_lowercase();
+    if host.contains("gitlab") {
+        ForgeKind::GitLab
+    } else if host.contains("gitea") || host.contains("forgejo") || host == "codeberg.org" {
+        ForgeKind::Gitea
+    } else {
+        ForgeKind::GitHub
+    }
+}
+
+/// Store `token` to authenticate with the forge at `host`, or remove the stored one if it's empty.
+pub(crate) fn set_access_token(host: &str, token: &str) -> Result<()> {
+    secret::persist(
+        &access_token_handle(host),
+        &Sensitive(token.to_owned()),
+        secret::Namespace::BuildKind,
+    )
+}
+
+fn access_token_handle(host: &str) -> String {
+    format!("forge_access_token:{host}")
+}
+
+/// Return a client for the forge of `project`, which hosts `repo`.
+///
+/// It's authenticated with the access token stored for the host of `repo`, or with `github_token`
+/// if it's GitHub and there is none.
+fn forge(
+    project: &Project,
+    repo: &ForgeRepo,
+    github_token: Option<&str>,
+) -> Result<Box<dyn Forge>> {
+    let kind = match project.forge {
+        ForgeKind::Detect => detect(&repo.host),
+        kind => kind,
+    };
+    let token = secret::retrieve(
+        &access_token_handle(&repo.host),
+        secret::Namespace::BuildKind,
+    )?
+    .map(|token| token.0);
+    Ok(match kind {
+        ForgeKind::Detect | ForgeKind::GitHub => {
+            let token = token
+                .or_else(|| github_token.map(ToOwned::to_owned))
+                .context("log in to GitHub to work with pull requests")
+                .context(Code::Forge)?;
+            Box::new(github::GitHub::new(repo, token))
+        }
+        ForgeKind::GitLab => Box::new(gitlab::GitLab::new(repo, missing_token(token, repo)?)),
+        ForgeKind::Gitea => Box::new(gitea::Gitea::new(repo, missing_token(token, repo)?)),
+    })
+}
+
+fn missing_token(token: Option<String>, repo: &ForgeRepo) -> Result<String> {
+    token
+        .with_context(|| {
+            format!(
+                "add an access token for {} to work with pull requests",
+                repo.host
+            )
+        })
+        .context(Code::Forge)
+}
+
+/// Open a pull request for the pushed branch identified by `branch_id` into the target branch, and remember it.
+///
+/// Without a body, the [description](pull_request_description()) of the branch is used, and reviews are
+/// requested from the reviewers of the branch once it's open.
+pub(crate) fn create_pull_request(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    pull_request: &NewPullRequest,
+    github_token: Option<&str>,
+) -> Result<PullRequest> {
+    let branch = ctx.project().virtual_branches().get_branch(branch_id)?;
+    let pull_request = if pull_request.body.trim().is_empty() {
+        NewPullRequest {
+            body: pull_request_description(ctx.project(), &branch)?,
+            ..pull_request.clone()
+        }
+    } else {
+        pull_request.clone()
+    };
+    let target = pull_request_target(ctx, branch_id)?;
+    let forge = forge(ctx.project(), &target.repo, github_token)?;
+    let created = block_on(forge.create_pull_request(
+        &target.repo,
+        &target.head,
+        &target.base,
+        &pull_request,
+    ))?;
+    let created = PullRequest {
+        head: target.head.branch,
+        ..created
+    };
+    ctx.project().pull_requests().set(created.clone())?;
+    if !branch.reviewers.is_empty() {
+        block_on(forge.request_reviewers(&target.repo, created.number, &branch.reviewers))
+            .with_context(|| {
+                format!(
+                    "opened pull request #{}, but failed to request reviews",
+                    created.number
+                )
+            })?;
+    }
+    Ok(created)
+}
+
+/// Return the description for the pull request of `branch`, which is its draft description, or the pull
+/// request template of the repository if it has none, or nothing if there is no template either.
+pub(crate) fn pull_request_description(project: &Project, branch: &Branch) -> Result<String> {
+    if !branch.description.trim().is_empty() {
+        return Ok(branch.description.clone());
+    }
+    let worktree = project.worktree_path();
+    for template in PULL_REQUEST_TEMPLATES {
+        let path = worktree.join(template);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => return Ok(content),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()))
+            }
+        }
+    }
+    Ok(String::new())
+}
+
+/// Fetch the current state of the pull request of the branch identified by `branch_id` from the forge and
+/// remember it. If none was opened from here, an open one for the branch is looked up, like one that was
+/// opened in the web interface.
+///
+/// Returns `None` if the branch isn't pushed or has no pull request.
+pub(crate) fn refresh_pull_request(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    github_token: Option<&str>,
+) -> Result<Option<PullRequest>> {
+    let branch = ctx.project().virtual_branches().get_branch(branch_id)?;
+    if branch.upstream.is_none() {
+        return Ok(None);
+    }
+    let target = pull_request_target(ctx, branch_id)?;
+    let forge = forge(ctx.project(), &target.repo, github_token)?;
+    let known = ctx.project().pull_requests().get(&target.head.branch)?;
+    let auto_merge = known.as_ref().and_then(|known| known.auto_merge);
+    let refreshed = block_on(async {
+        let number = match known {
+            Some(known) => Some(known.number),
+            None => {
+                forge
+                    .find_open_pull_request(&target.repo, &target.head)
+                    .await?
+            }
+        };
+        match number {
+            Some(number) => forge.pull_request(&target.repo, number).await.map(Some),
+            None => Ok(None),
+        }
+    })?;
+    let Some(refreshed) = refreshed else {
+        return Ok(None);
+    };
+    let refreshed = PullRequest {
+        head: target.head.branch,
+        auto_merge,
+        ..refreshed
+    };
+    ctx.project().pull_requests().set(refreshed.clone())?;
+    record_merge(ctx, branch, &refreshed)?;
+    Ok(Some(refreshed))
+}
+
+/// Remember on `branch` that its pull request was merged if `pull_request` says so, and all of the branch
+/// was pushed when it was, so the next base update takes it as integrated.
+fn record_merge(
+    ctx: &CommandContext,
+    mut branch: Branch,
+    pull_request: &PullRequest,
+) -> Result<()> {
+    if pull_request.state != PullRequestState::Merged || branch.upstream_head != Some(branch.head) {
+        return Ok(());
+    }
+    let Some(merge_commit) = &pull_request.merge_commit else {
+        return Ok(());
+    };
+    let merge_commit = git2::Oid::from_str(merge_commit)
+        .with_context(|| format!("the forge merged into an invalid commit {merge_commit}"))
+        .context(Code::Forge)?;
+    let forge_merge = ForgeMerge {
+        head: branch.head,
+        merge_commit,
+    };
+    if branch.forge_merge == Some(forge_merge) {
+        return Ok(());
+    }
+    branch.forge_merge = Some(forge_merge);
+    ctx.project().virtual_branches().set_branch(branch)
+}
+
+/// Have the forge merge the open pull request of the branch identified by `branch_id` as `auto_merge` says
+/// once its checks pass, and remember that it will.
+///
+/// Once it's merged, which [`refresh_auto_merges()`] finds out, the next base update removes the branch.
+pub(crate) fn enable_auto_merge(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    auto_merge: AutoMerge,
+    github_token: Option<&str>,
+) -> Result<PullRequest> {
+    let pull_request = refresh_pull_request(ctx, branch_id, github_token)?
+        .context("the branch has no pull request to merge")
+        .context(Code::Forge)?;
+    if pull_request.state != PullRequestState::Open {
+        return Err(
+            anyhow!("pull request #{} isn't open", pull_request.number).context(Code::Forge)
+        );
+    }
+    let target = pull_request_target(ctx, branch_id)?;
+    let forge = forge(ctx.project(), &target.repo, github_token)?;
+    block_on(forge.enable_auto_merge(&target.repo, pull_request.number, auto_merge))?;
+    let pull_request = PullRequest {
+        auto_merge: Some(auto_merge),
+        ..pull_request
+    };
+    ctx.project().pull_requests().set(pull_request.clone())?;
+    Ok(pull_request)
+}
+
+/// Fetch the state of the open pull requests of all virtual branches the forge was asked to merge, and
+/// return those that changed state, like the ones that were merged since.
+///
+/// Pull requests that can't be fetched are tried again next time. Once the forge limits the rate of
+/// requests, no more are made.
+pub(crate) fn refresh_auto_merges(
+    ctx: &CommandContext,
+    github_token: Option<&str>,
+) -> Result<Vec<PullRequest>> {
+    let project = ctx.project();
+    let pull_requests = project.pull_requests();
+    let mut changed = Vec::new();
+    for branch in project.virtual_branches().list_branches_in_workspace()? {
+        let Some(upstream) = &branch.upstream else {
+            continue;
+        };
+        let Some(known) = pull_requests.get(upstream.branch())? else {
+            continue;
+        };
+        if known.auto_merge.is_none() || known.state != PullRequestState::Open {
+            continue;
+        }
+        match refresh_pull_request(ctx, branch.id, github_token) {
+            Ok(Some(refreshed)) if refreshed.state != known.state => changed.push(refreshed),
+            Ok(_) => {}
+            Err(err) if err.downcast_ref::<RateLimited>().is_some() => {
+                tracing::info!("forge limited the rate of requests, stopping to poll auto-merges");
+                break;
+            }
+            Err(err) => {
+                tracing::warn!(?err, branch = %branch.name, "failed to refresh the pull request");
+            }
+        }
+    }
+    Ok(changed)
+}
+
+/// Ask the forge that hosts `repo` for the rules of pushes to its branch named `branch`.
+pub(crate) fn branch_protection(
+    project: &Project,
+    repo: &ForgeRepo,
+    branch: &str,
+    github_token: Option<&str>,
+) -> Result<BranchProtection> {
+    let forge = forge(project, repo, github_token)?;
+    block_on(forge.branch_protection(repo, branch))
+}
+
+fn pull_request_target(ctx: &CommandContext, branch_id: BranchId) -> Result<PullRequestTarget> {
+    let vb_state = ctx.project().virtual_branches();
+    let default_target = vb_state.get_default_target()?;
+    let branch = vb_state.get_branch(branch_id)?;
+    let upstream = branch
+        .upstream
+        .as_ref()
+        .context("the branch has to be pushed before a pull request can be opened")
+        .context(Code::Forge)?;
+
+    // Stacked branches are merged into their parent, so their pull requests only show their own commits.
+    let base = match branch.parent {
+        Some(parent_id) => vb_state
+            .get_branch(parent_id)?
+            .upstream
+            .context(
+                "the branch it's stacked on has to be pushed before a pull request can be opened",
+            )
+            .context(Code::Forge)?
+            .branch()
+            .to_owned(),
+        None => default_target.branch.branch().to_owned(),
+    };
+    let repo = ForgeRepo::from_remote_url(&default_target.remote_url).context(Code::Forge)?;
+    Ok(PullRequestTarget {
+        head: PullRequestHead {
+            repo: head_repo(ctx, upstream, &repo)?,
+            branch: upstream.branch().to_owned(),
+        },
+        repo,
+        base,
+    })
+}
+
+/// Return the repository that `upstream` was pushed to, or `target_repo` if its remote has no URL.
+fn head_repo(
+    ctx: &CommandContext,
+    upstream: &RemoteRefname,
+    target_repo: &ForgeRepo,
+) -> Result<ForgeRepo> {
+    let pushed_to = ctx
+        .repository()
+        .find_remote(upstream.remote())
+        .context(format!("failed to find remote {}", upstream.remote()))?
+        .url()
+        .map(ForgeRepo::from_remote_url)
+        .transpose()
+        .context(Code::Forge)?;
+    Ok(pushed_to.unwrap_or_else(|| target_repo.clone()))
+}
+
+/// Send `request` to `forge` and deserialize its response, or turn the message of an unsuccessful
+/// response into an error.
+pub(crate) async fn send<T: DeserializeOwned>(forge: &str, request: RequestBuilder) -> Result<T> {
+    Ok(send_optional(forge, request, false)
+        .await?
+        .expect("only missing resources are `None`"))
+}
+
+/// Like [`send()`], but return `None` if the forge responds that there is nothing to return and
+/// `not_found_is_none` is set, for resources that only exist if something is configured.
+pub(crate) async fn send_optional<T: DeserializeOwned>(
+    forge: &str,
+    request: RequestBuilder,
+    not_found_is_none: bool,
+) -> Result<Option<T>> {
+    /// The error messages of all forges, which are either text or a list of texts.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Message {
+        Text(String),
+        Texts(Vec<String>),
+    }
+    #[derive(Deserialize)]
+    struct ApiError {
+        message: Option<Message>,
+        error: Option<String>,
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|err| anyhow!(err).context(Code::Forge))?;
+    let status = response.status();
+    if let Some(reset_timestamp_ms) = rate_limit_reset(&response) {
+        return Err(anyhow::Error::from(RateLimited { reset_timestamp_ms })
+            .context(format!(
+                "{forge} rejected the request as too many were made"
+            ))
+            .context(Code::Forge));
+    }
+    if not_found_is_none && status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let message = match response.json::<ApiError>().await {
+            Ok(ApiError {
+                message: Some(Message::Text(message)),
+                ..
+            }) => message,
+            Ok(ApiError {
+                message: Some(Message::Texts(messages)),
+                ..
+            }) => messages.join(", "),
+            Ok(ApiError {
+                error: Some(error), ..
+            }) => error,
+            _ => status.to_string(),
+        };
+        return Err(anyhow!("{forge} responded with {status}: {message}").context(Code::Forge));
+    }
+    let body = response
+        .bytes()
+        .await
+        .map_err(|err| anyhow!(err).context(Code::Forge))?;
+    // Some operations succeed without a body, which is read as `null`.
+    let body: &[u8] = if body.is_empty() { b"null" } else { &body };
+    serde_json::from_slice(body)
+        .map(Some)
+        .with_context(|| format!("{forge} responded with unexpected data"))
+        .context(Code::Forge)
+}
+
+/// The forge rejected a request as too many were made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RateLimited {
+    /// The time at which requests are accepted again, in milliseconds since the Unix epoch.
+    pub reset_timestamp_ms: i64,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "requests are accepted again at {} (Unix time)",
+            self.reset_timestamp_ms / 1000
+        )
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Return the time at which requests are accepted again if `response` rejected one as too many were made,
+/// in milliseconds since the Unix epoch, or `None` if it didn't.
+///
+/// GitHub says so with `403 Forbidden` and no remaining requests, others with `429 Too Many Requests`,
+/// and all of them name the time of the reset or the seconds to wait in headers.
+fn rate_limit_reset(response: &reqwest::Response) -> Option<i64> {
+    let status = response.status();
+    if status != reqwest::StatusCode::FORBIDDEN && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+    {
+        return None;
+    }
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<i64>().ok())
+    };
+    let now_ms = now_since_unix_epoch_ms();
+    if let Some(seconds) = header("retry-after") {
+        return Some(now_ms + seconds * 1000);
+    }
+    let exhausted =
+        header("x-ratelimit-remaining").or_else(|| header("ratelimit-remaining")) == Some(0);
+    let reset_ms = header("x-ratelimit-reset")
+        .or_else(|| header("ratelimit-reset"))
+        .map(|seconds| seconds * 1000);
+    if exhausted {
+        reset_ms.or(Some(now_ms + RATE_LIMIT_BACKOFF_MS))
+    } else if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        Some(reset_ms.unwrap_or(now_ms + RATE_LIMIT_BACKOFF_MS))
+    } else {
+        None
+    }
+}
+
+/// Run `future` to completion on a runtime of its own thread, so requests to the forge and other
+/// services can be made whether or not the caller is already running on a runtime.
+pub(crate) fn block_on<T: Send>(future: impl Future<Output = Result<T>> + Send) -> Result<T> {
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()?
+                    .block_on(future)
+            })
+            .join()
+            .map_err(|_| anyhow!("request panicked"))?
+    })
+}
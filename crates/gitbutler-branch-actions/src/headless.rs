@@ -0,0 +1,149 @@
+//! Embed GitButler into automation that runs without the app, like a bot that keeps a repository formatted.
+//!
+//! A [`Session`] works with a single project, adding it to the projects in the data directory when it's opened
+//! the first time, and ties together what such automation does most: keeping the workspace up to date with the
+//! target, committing some of the uncommitted changes to a lane of their own, pushing that lane and opening a
+//! pull request for it. Everything else is available through [`VirtualBranchActions`].
+//!
+//! See `examples/format_bot.rs` for a bot that does all of this after running a formatter.
+//! Only available with the `headless` feature.
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use gitbutler_branch::{BranchCreateRequest, BranchId, BranchUpdateRequest};
+use gitbutler_project::Project;
+use gitbutler_reference::{ReferenceName, RemoteRefname};
+use gitbutler_repo::FetchReport;
+
+use crate::{NewPullRequest, PullRequest, VirtualBranchActions, VirtualBranchesExt};
+
+/// A project opened for automation.
+pub struct Session {
+    projects: gitbutler_project::Controller,
+    project: Project,
+}
+
+/// What [syncing](Session::sync()) did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncOutcome {
+    pub fetch: FetchReport,
+    /// The branches that were unapplied as they conflicted with the updated target.
+    pub unapplied_branches: Vec<ReferenceName>,
+}
+
+impl Session {
+    /// Open the repository with its worktree at `worktree` as project, keeping what GitButler knows about it in
+    /// `data_dir`, where the app keeps it as well if both are to share it.
+    pub fn open(data_dir: impl Into<PathBuf>, worktree: impl AsRef<Path>) -> Result<Self> {
+        let projects = gitbutler_project::Controller::from_path(data_dir);
+        let worktree = worktree.as_ref();
+        let project = match projects
+            .list()?
+            .into_iter()
+            .find(|project| project.path == worktree)
+        {
+            Some(project) => project,
+            None => projects.add(worktree)?,
+        };
+        Ok(Session { projects, project })
+    }
+
+    pub fn project(&self) -> &Project {
+        &self.project
+    }
+
+    /// Read the project again, to see changes that were made to its settings since it was opened.
+    pub fn reload(&mut self) -> Result<()> {
+        self.project = self.projects.get(self.project.id)?;
+        Ok(())
+    }
+
+    /// Set the branch the workspace is based on to `target`, unless the project has a target already.
+    pub fn ensure_target(&self, target: &RemoteRefname) -> Result<()> {
+        if self
+            .project
+            .virtual_branches()
+            .get_default_target()
+            .is_err()
+        {
+            VirtualBranchActions.set_base_branch(&self.project, target)?;
+        }
+        Ok(())
+    }
+
+    /// Fetch all remotes and update the workspace to the target.
+    pub fn sync(&self) -> Result<SyncOutcome> {
+        let fetch = VirtualBranchActions.fetch_from_remotes(&self.project, None)?;
+        let unapplied_branches = VirtualBranchActions.update_base_branch(&self.project)?;
+        Ok(SyncOutcome {
+            fetch,
+            unapplied_branches,
+        })
+    }
+
+    /// Return the id of the applied branch named `name`, creating it if there is none.
+    pub fn lane(&self, name: &str) -> Result<BranchId> {
+        let (branches, _) = VirtualBranchActions.list_virtual_branches(&self.project)?;
+        if let Some(branch) = branches.iter().find(|branch| branch.name == name) {
+            return Ok(branch.id);
+        }
+        VirtualBranchActions.create_virtual_branch(
+            &self.project,
+            &BranchCreateRequest {
+                name: Some(name.to_owned()),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Move the uncommitted hunks of all applied branches that match `query`, written in the
+    /// [query language](crate::HunkQuery), to the branch identified by `lane` and commit them there with `message`.
+    ///
+    /// Return the id of the commit, or `None` if no hunk matched.
+    pub fn commit_to_lane(
+        &self,
+        lane: BranchId,
+        query: &str,
+        message: &str,
+    ) -> Result<Option<git2::Oid>> {
+        let selected = VirtualBranchActions.select_hunks(&self.project, query)?;
+        if selected.claims.is_empty() {
+            return Ok(None);
+        }
+        let mut ownership = self
+            .project
+            .virtual_branches()
+            .get_branch_in_workspace(lane)?
+            .ownership;
+        for claim in &selected.claims {
+            ownership.put(claim.clone());
+        }
+        VirtualBranchActions.update_virtual_branch(
+            &self.project,
+            BranchUpdateRequest {
+                id: lane,
+                ownership: Some(ownership),
+                ..Default::default()
+            },
+        )?;
+        VirtualBranchActions
+            .create_commit(&self.project, lane, message, Some(&selected), false)
+            .map(Some)
+    }
+
+    /// Push the branch identified by `lane`, without force so commits pushed by others are never dropped.
+    pub fn push(&self, lane: BranchId) -> Result<()> {
+        VirtualBranchActions.push_virtual_branch(&self.project, lane, false, None)
+    }
+
+    /// Open a pull request for the pushed branch identified by `lane`, with `token` to access the forge
+    /// instead of the one stored for it.
+    pub fn open_pull_request(
+        &self,
+        lane: BranchId,
+        pull_request: &NewPullRequest,
+        token: Option<&str>,
+    ) -> Result<PullRequest> {
+        VirtualBranchActions.create_pull_request(&self.project, lane, pull_request, token)
+    }
+}
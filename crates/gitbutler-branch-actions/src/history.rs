@@ -0,0 +1,255 @@
+//! Page through the history of the target branch and of the upstream branches of virtual branches, so it can
+//! be scrolled through without loading all of it.
+//!
+//! Commits are walked from the most recent one by commit time, which libgit2 does without reading the whole
+//! history first, unlike topological order. A page ends with a cursor, the last commit that was walked, and
+//! the next page is found by walking again up to it.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_reference::RemoteRefname;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    author,
+    commit_graph::GraphRef,
+    remote::{commit_to_remote_commit, RemoteCommit},
+    VirtualBranchesExt,
+};
+
+/// The start of the signature of each format, as found in the header of signed commits.
+const SIGNATURE_FORMATS: [(&str, SignatureFormat); 3] = [
+    ("-----BEGIN PGP SIGNATURE-----", SignatureFormat::OpenPgp),
+    ("-----BEGIN SSH SIGNATURE-----", SignatureFormat::Ssh),
+    ("-----BEGIN SIGNED MESSAGE-----", SignatureFormat::X509),
+];
+
+/// Which commits to list. All filters that are set have to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryFilter {
+    /// Text the name or the email of the author has to contain, ignoring case.
+    pub author: Option<String>,
+    /// The file or directory, relative to the worktree, the commit has to change compared to its first parent.
+    pub path: Option<PathBuf>,
+    /// The earliest time of the commit, in milliseconds since the Unix epoch.
+    pub since_timestamp_ms: Option<u128>,
+    /// The latest time of the commit, in milliseconds since the Unix epoch.
+    pub until_timestamp_ms: Option<u128>,
+    /// Text the message has to contain, ignoring case.
+    pub grep: Option<String>,
+}
+
+/// A page of the history of a branch.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    /// The commits of the page, most recent first.
+    pub entries: Vec<HistoryEntry>,
+    /// What to pass to get the next page, or `None` if there are no more commits.
+    pub next_cursor: Option<String>,
+}
+
+/// A commit in the history of a branch.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub commit: RemoteCommit,
+    /// The format of the signature of the commit, which isn't verified, or `None` if it isn't signed.
+    pub signature: Option<SignatureFormat>,
+    /// The branches and tags that point to the commit.
+    pub refs: Vec<GraphRef>,
+}
+
+/// The format of the signature of a commit, named like the values of `gpg.format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureFormat {
+    OpenPgp,
+    Ssh,
+    X509,
+}
+
+/// Return the at most `limit` most recent commits of the remote branch `reference` that match `filter`,
+/// after the commit `cursor` points to if it's set.
+pub(crate) fn log(
+    ctx: &CommandContext,
+    reference: &RemoteRefname,
+    cursor: Option<&str>,
+    limit: usize,
+    filter: &HistoryFilter,
+) -> Result<HistoryPage> {
+    if limit == 0 {
+        return Err(anyhow!("at least one commit has to be asked for")).context(Code::Validation);
+    }
+    if let Some(path) = &filter.path {
+        if path.as_os_str().is_empty() || path.is_absolute() {
+            return Err(anyhow!(
+                "the path of a file or directory in the worktree is needed, not {path:?}"
+            ))
+            .context(Code::Validation);
+        }
+    }
+    let after = cursor
+        .map(|cursor| {
+            git2::Oid::from_str(cursor)
+                .map_err(|_| anyhow!("'{cursor}' isn't a valid cursor"))
+                .context(Code::Validation)
+        })
+        .transpose()?;
+
+    let repo = ctx.repository();
+    let tip = repo
+        .refname_to_id(&reference.to_string())
+        .map_err(|_| anyhow!("branch {reference} doesn't exist"))
+        .context(Code::Validation)?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TIME)?;
+    revwalk.push(tip)?;
+
+    let mut skipping = after.is_some();
+    let mut refs = decorations(ctx)?;
+    let format = &ctx.project().listing_format;
+    let mailmap = author::mailmap(repo);
+    let mut entries: Vec<HistoryEntry> = Vec::new();
+    let mut next_cursor = None;
+    for id in revwalk {
+        let id = id?;
+        if skipping {
+            skipping = Some(id) != after;
+            continue;
+        }
+        let commit = repo.find_commit(id).context("failed to find commit")?;
+        if !matches(&commit, filter, mailmap.as_ref())? {
+            continue;
+        }
+        if entries.len() == limit {
+            next_cursor = entries.last().map(|entry| entry.commit.id.clone());
+            break;
+        }
+        entries.push(HistoryEntry {
+            commit: commit_to_remote_commit(&commit, format, mailmap.as_ref()),
+            signature: signature_format(repo, id),
+            refs: refs.remove(&id).unwrap_or_default(),
+        });
+    }
+    if skipping {
+        return Err(anyhow!(
+            "the cursor isn't part of the history of {reference} anymore"
+        ))
+        .context(Code::Validation);
+    }
+    Ok(HistoryPage {
+        entries,
+        next_cursor,
+    })
+}
+
+/// Return `true` if `commit` matches all filters of `filter`.
+fn matches(
+    commit: &git2::Commit<'_>,
+    filter: &HistoryFilter,
+    mailmap: Option<&git2::Mailmap>,
+) -> Result<bool> {
+    let timestamp_ms = u128::try_from(commit.time().seconds()).unwrap_or_default() * 1000;
+    if filter
+        .since_timestamp_ms
+        .is_some_and(|since| timestamp_ms < since)
+        || filter
+            .until_timestamp_ms
+            .is_some_and(|until| timestamp_ms > until)
+    {
+        return Ok(false);
+    }
+    if let Some(text) = &filter.author {
+        let author = author::resolve(mailmap, commit.author());
+        let text = text.to_lowercase();
+        let contains = |part: &[u8]| String::from_utf8_lossy(part).to_lowercase().contains(&text);
+        if !contains(author.name_bytes()) && !contains(author.email_bytes()) {
+            return Ok(false);
+        }
+    }
+    if let Some(text) = &filter.grep {
+        let message = String::from_utf8_lossy(commit.message_bytes()).to_lowercase();
+        if !message.contains(&text.to_lowercase()) {
+            return Ok(false);
+        }
+    }
+    if let Some(path) = &filter.path {
+        return changes_path(commit, path);
+    }
+    Ok(true)
+}
+
+/// Return `true` if the file or directory at `path` differs between `commit` and its first parent.
+fn changes_path(commit: &git2::Commit<'_>, path: &Path) -> Result<bool> {
+    let entry_id = |tree: git2::Tree<'_>| tree.get_path(path).ok().map(|entry| entry.id());
+    let id = entry_id(commit.tree()?);
+    let parent_id = match commit.parent(0) {
+        Ok(parent) => entry_id(parent.tree()?),
+        Err(_) => None,
+    };
+    Ok(id != parent_id)
+}
+
+/// Return the branches and tags that point to each commit, which are the applied virtual branches, the remote
+/// branches, the tags and the commit of the target the workspace is based on.
+fn decorations(ctx: &CommandContext) -> Result<HashMap<git2::Oid, Vec<GraphRef>>> {
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let mut refs = HashMap::<git2::Oid, Vec<GraphRef>>::new();
+
+    let mut branches = vb_state.list_branches_in_workspace()?;
+    branches.sort_by_key(|branch| branch.order);
+    for branch in branches {
+        refs.entry(branch.head)
+            .or_default()
+            .push(GraphRef::VirtualBranch {
+                branch_id: branch.id,
+                name: branch.name,
+            });
+    }
+    if let Ok(target) = vb_state.get_default_target() {
+        refs.entry(target.sha).or_default().push(GraphRef::Base {
+            name: target.branch.to_string(),
+        });
+    }
+    for reference in repo.references()? {
+        let reference = reference?;
+        let Some(name) = reference.name() else {
+            continue;
+        };
+        let decoration = if name.starts_with("refs/remotes/") && !name.ends_with("/HEAD") {
+            GraphRef::Remote {
+                name: name.to_owned(),
+            }
+        } else if let Some(tag) = name.strip_prefix("refs/tags/") {
+            GraphRef::Tag {
+                name: tag.to_owned(),
+            }
+        } else {
+            continue;
+        };
+        if let Ok(commit) = reference.peel_to_commit() {
+            refs.entry(commit.id()).or_default().push(decoration);
+        }
+    }
+    Ok(refs)
+}
+
+/// Return the format of the signature of the commit `id`, or `None` if it isn't signed.
+fn signature_format(repo: &git2::Repository, id: git2::Oid) -> Option<SignatureFormat> {
+    let (signature, _) = repo.extract_signature(&id, None).ok()?;
+    let signature = signature.as_str().unwrap_or_default().trim_start();
+    Some(
+        SIGNATURE_FORMATS
+            .iter()
+            .find(|(start, _)| signature.starts_with(start))
+            .map_or(SignatureFormat::OpenPgp, |(_, format)| *format),
+    )
+}
@@ -40,6 +40,10 @@ pub struct VirtualBranchHunk {
     pub change_type: gitbutler_diff::ChangeType,
     /// Indicates that the hunk depends on multiple branches. In this case the hunk cant be moved or comitted.
     pub poisoned: bool,
+    /// A note the user attached to this hunk, like `needs test`.
+    pub note: Option<String>,
+    /// Whether the hunk is pinned to its branch, so it's never assigned to another one.
+    pub pinned: bool,
 }
 
 // A hunk is locked when it depends on changes in commits that are in your
@@ -88,6 +92,8 @@ impl VirtualBranchHunk {
             locked_to: Some(locked_to.clone().into_boxed_slice()),
             change_type: hunk.change_type,
             poisoned: branch_deps_count > 1,
+            note: None,
+            pinned: false,
         }
     }
 }
@@ -102,6 +108,7 @@ impl From<VirtualBranchHunk> for GitHunk {
             diff_lines: val.diff,
             binary: val.binary,
             change_type: val.change_type,
+            highlights: None,
         }
     }
 }
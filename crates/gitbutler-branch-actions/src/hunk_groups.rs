@@ -0,0 +1,151 @@
+//! Group the uncommitted hunks of a branch by the kind of file they are in, so parts of a large change,
+//! like only its tests, can be committed without picking hunks one by one.
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use gitbutler_branch::{BranchId, BranchOwnershipClaims, OwnershipClaim};
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::Hunk;
+use serde::Serialize;
+
+use crate::status::get_applied_status;
+
+/// The attribute that can be set in `.gitattributes` to put files into a category,
+/// like `fixtures/** gitbutler-category=test`.
+const CATEGORY_ATTRIBUTE: &str = "gitbutler-category";
+
+/// The kind of file a hunk is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HunkCategory {
+    Test,
+    Docs,
+    Config,
+    Source,
+}
+
+/// The uncommitted hunks of a branch that are in files of the same category.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkGroup {
+    pub category: HunkCategory,
+    /// The files with hunks in this group, relative to the worktree.
+    pub files: Vec<PathBuf>,
+    /// The hunks of this group, to be passed along when creating a commit of only them.
+    pub ownership: BranchOwnershipClaims,
+}
+
+/// Group the uncommitted hunks of the applied branch identified by `branch_id` by the category of their file,
+/// in the order of [`HunkCategory`]. Categories without hunks are left out.
+///
+/// The category is read from the `gitbutler-category` attribute first, then files marked with
+/// `linguist-documentation` are docs, and the rest is categorized by its path.
+pub(crate) fn group_hunks(ctx: &CommandContext, branch_id: BranchId) -> Result<Vec<HunkGroup>> {
+    let (_, files) = get_applied_status(ctx, None)?
+        .branches
+        .into_iter()
+        .find(|(branch, _)| branch.id == branch_id)
+        .with_context(|| format!("branch {branch_id} not found"))?;
+
+    let mut groups: Vec<HunkGroup> = Vec::new();
+    for file in files {
+        let category = categorize(ctx.repository(), &file.path);
+        let claim = OwnershipClaim {
+            file_path: file.path.clone(),
+            hunks: file
+                .hunks
+                .iter()
+                .map(|hunk| Hunk {
+                    hash: Some(hunk.hash),
+                    start: hunk.start,
+                    end: hunk.end,
+                })
+                .collect(),
+        };
+        match groups.iter_mut().find(|group| group.category == category) {
+            Some(group) => {
+                group.files.push(file.path);
+                group.ownership.claims.push(claim);
+            }
+            None => groups.push(HunkGroup {
+                category,
+                files: vec![file.path],
+                ownership: BranchOwnershipClaims {
+                    claims: vec![claim],
+                },
+            }),
+        }
+    }
+    groups.sort_by_key(|group| group.category);
+    Ok(groups)
+}
+
+/// Return the category of the file at `path`, relative to the worktree.
+fn categorize(repo: &git2::Repository, path: &Path) -> HunkCategory {
+    let attribute = |name| {
+        repo.get_attr(path, name, git2::AttrCheckFlags::FILE_THEN_INDEX)
+            .ok()
+            .map(git2::AttrValue::from_string)
+    };
+    match attribute(CATEGORY_ATTRIBUTE) {
+        Some(git2::AttrValue::String("test")) => return HunkCategory::Test,
+        Some(git2::AttrValue::String("docs")) => return HunkCategory::Docs,
+        Some(git2::AttrValue::String("config")) => return HunkCategory::Config,
+        Some(git2::AttrValue::String("source")) => return HunkCategory::Source,
+        _ => {}
+    }
+    if attribute("linguist-documentation") == Some(git2::AttrValue::True) {
+        return HunkCategory::Docs;
+    }
+    categorize_path(path)
+}
+
+/// Tell the category of the file at `path` by the conventions of common languages and tools.
+fn categorize_path(path: &Path) -> HunkCategory {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let stem = file_name
+        .strip_suffix(&format!(".{extension}"))
+        .unwrap_or(&file_name);
+    let in_directory = |names: &[&str]| {
+        path.parent().is_some_and(|parent| {
+            parent
+                .components()
+                .any(|component| names.contains(&&*component.as_os_str().to_string_lossy()))
+        })
+    };
+
+    if in_directory(&["test", "tests", "__tests__", "spec", "specs", "testdata"])
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with(".test")
+        || stem.ends_with("_spec")
+        || stem.ends_with(".spec")
+    {
+        return HunkCategory::Test;
+    }
+    if in_directory(&["doc", "docs", "documentation"])
+        || matches!(extension.as_str(), "md" | "mdx" | "rst" | "adoc")
+        || ["readme", "changelog", "license", "contributing"]
+            .iter()
+            .any(|name| stem == *name)
+    {
+        return HunkCategory::Docs;
+    }
+    if file_name.starts_with('.')
+        || matches!(
+            extension.as_str(),
+            "toml" | "yaml" | "yml" | "json" | "ini" | "cfg" | "conf" | "lock" | "xml" | "plist"
+        )
+        || matches!(file_name.as_str(), "dockerfile" | "makefile" | "justfile")
+    {
+        return HunkCategory::Config;
+    }
+    HunkCategory::Source
+}
@@ -0,0 +1,355 @@
+//! Select uncommitted hunks with a small query language, to script what is done with them, like committing
+//! every hunk that touches a Markdown file to the branch with the docs.
+//!
+//! A query combines conditions with `and`, `or`, `not` and parentheses, with `and` binding stronger than `or`.
+//! Conditions next to each other have to match both, like `path:*.md not content:TODO`.
+//!
+//! * `path:GLOB` matches hunks in files matching `GLOB`, like [allowed paths](gitbutler_branch::Branch::allowed_paths).
+//! * `content:REGEX` matches hunks with an added or removed line matching `REGEX`.
+//! * `lines<N`, `lines>N` and so on match hunks by the amount of lines they add and remove.
+//! * `age<DURATION` and so on match hunks by how long ago they were last changed, like `age>2h`.
+//!
+//! Values with spaces or parentheses can be put in double quotes, like `content:"fn main()"`.
+use std::{fmt, path::Path, str::FromStr, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_branch::{path_matches, BranchOwnershipClaims, OwnershipClaim};
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::Hunk;
+use gitbutler_error::error::Code;
+use regex::Regex;
+
+use crate::{hunk::VirtualBranchHunk, status::get_applied_status};
+
+/// A parsed query that hunks can be matched against.
+#[derive(Debug, Clone)]
+pub struct HunkQuery {
+    expr: Expr,
+}
+
+impl HunkQuery {
+    /// Return `true` if `hunk` of the file at `path`, relative to the worktree, matches the query,
+    /// with `now_ms` being the current time in milliseconds since the Unix epoch.
+    pub(crate) fn matches(&self, path: &Path, hunk: &VirtualBranchHunk, now_ms: u128) -> bool {
+        self.expr.matches(path, hunk, now_ms)
+    }
+}
+
+impl FromStr for HunkQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(query: &str) -> Result<Self> {
+        let tokens = tokenize(query)
+            .map_err(|err| anyhow!("invalid hunk query '{query}': {err}"))
+            .context(Code::Validation)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser
+            .parse()
+            .map_err(|err| anyhow!("invalid hunk query '{query}': {err}"))
+            .context(Code::Validation)?;
+        Ok(HunkQuery { expr })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Path(String),
+    Content(Regex),
+    Lines(Comparison, u64),
+    Age(Comparison, Duration),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+impl Expr {
+    fn matches(&self, path: &Path, hunk: &VirtualBranchHunk, now_ms: u128) -> bool {
+        match self {
+            Expr::Path(pattern) => path_matches(pattern, path),
+            Expr::Content(regex) => {
+                changed_lines(hunk).any(|line| regex.is_match(&line.to_str_lossy()))
+            }
+            Expr::Lines(comparison, lines) => {
+                comparison.holds(changed_lines(hunk).count() as u64, *lines)
+            }
+            Expr::Age(comparison, age) => {
+                let hunk_age = now_ms.saturating_sub(hunk.modified_at);
+                comparison.holds(
+                    u64::try_from(hunk_age).unwrap_or(u64::MAX),
+                    u64::try_from(age.as_millis()).unwrap_or(u64::MAX),
+                )
+            }
+            Expr::Not(expr) => !expr.matches(path, hunk, now_ms),
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.matches(path, hunk, now_ms)),
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.matches(path, hunk, now_ms)),
+        }
+    }
+}
+
+/// Return the added and removed lines of `hunk`, without their `+` or `-`.
+fn changed_lines(hunk: &VirtualBranchHunk) -> impl Iterator<Item = &[u8]> {
+    hunk.diff
+        .lines()
+        .filter(|line| !line.starts_with(b"@@"))
+        .filter_map(|line| line.strip_prefix(b"+").or_else(|| line.strip_prefix(b"-")))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn holds(self, actual: u64, expected: u64) -> bool {
+        match self {
+            Comparison::Less => actual < expected,
+            Comparison::LessOrEqual => actual <= expected,
+            Comparison::Equal => actual == expected,
+            Comparison::GreaterOrEqual => actual >= expected,
+            Comparison::Greater => actual > expected,
+        }
+    }
+
+    /// Split `value` into the comparison it starts with and the rest.
+    fn parse(value: &str) -> Option<(Self, &str)> {
+        [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ]
+        .into_iter()
+        .find_map(|(prefix, comparison)| Some((comparison, value.strip_prefix(prefix)?)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Open => f.write_str("("),
+            Token::Close => f.write_str(")"),
+            Token::Word(word) => f.write_str(word),
+        }
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if c != '"' {
+                        word.push(c);
+                        continue;
+                    }
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some(escaped) => word.push(escaped),
+                                None => bail!("unterminated quote"),
+                            },
+                            Some(c) => word.push(c),
+                            None => bail!("unterminated quote"),
+                        }
+                    }
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn parse(&mut self) -> Result<Expr> {
+        if self.tokens.is_empty() {
+            bail!("it's empty");
+        }
+        let expr = self.or()?;
+        match self.tokens.get(self.pos) {
+            Some(token) => bail!("unexpected '{token}'"),
+            None => Ok(expr),
+        }
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.and()?];
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            exprs.push(self.and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::Or(exprs)
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.not()?];
+        loop {
+            if self.peek_keyword("and") {
+                self.pos += 1;
+            } else if self.peek_keyword("or")
+                || matches!(self.tokens.get(self.pos), None | Some(Token::Close))
+            {
+                break;
+            }
+            exprs.push(self.not()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::And(exprs)
+        })
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.peek_keyword("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .context("a condition is missing at the end")?;
+        self.pos += 1;
+        match token {
+            Token::Open => {
+                let expr = self.or()?;
+                match self.tokens.get(self.pos) {
+                    Some(Token::Close) => {
+                        self.pos += 1;
+                        Ok(expr)
+                    }
+                    _ => bail!("'(' isn't closed"),
+                }
+            }
+            Token::Close => bail!("unexpected ')'"),
+            Token::Word(word) => condition(&word),
+        }
+    }
+}
+
+fn condition(word: &str) -> Result<Expr> {
+    if let Some(pattern) = word.strip_prefix("path:") {
+        if pattern.is_empty() {
+            bail!("'path:' needs a pattern");
+        }
+        return Ok(Expr::Path(pattern.to_owned()));
+    }
+    if let Some(regex) = word.strip_prefix("content:") {
+        let regex = Regex::new(regex).with_context(|| format!("invalid regex '{regex}'"))?;
+        return Ok(Expr::Content(regex));
+    }
+    if let Some(rest) = word.strip_prefix("lines") {
+        let (comparison, lines) = Comparison::parse(rest)
+            .with_context(|| format!("'{word}' needs a comparison like 'lines>10'"))?;
+        let lines = lines
+            .parse()
+            .with_context(|| format!("'{lines}' isn't an amount of lines"))?;
+        return Ok(Expr::Lines(comparison, lines));
+    }
+    if let Some(rest) = word.strip_prefix("age") {
+        let (comparison, age) = Comparison::parse(rest)
+            .with_context(|| format!("'{word}' needs a comparison like 'age<1h'"))?;
+        return Ok(Expr::Age(comparison, parse_duration(age)?));
+    }
+    bail!("unknown condition '{word}'")
+}
+
+/// Parse a duration like `30s`, `5m`, `2h` or `7d`.
+fn parse_duration(duration: &str) -> Result<Duration> {
+    let unit_pos = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .with_context(|| format!("'{duration}' needs a unit like 's', 'm', 'h' or 'd'"))?;
+    let (amount, unit) = duration.split_at(unit_pos);
+    let amount: u64 = amount
+        .parse()
+        .with_context(|| format!("'{duration}' isn't a duration"))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => bail!("'{unit}' isn't a unit, use 's', 'm', 'h' or 'd'"),
+    };
+    Ok(Duration::from_secs(amount.saturating_mul(seconds)))
+}
+
+/// Return the uncommitted hunks of all applied branches that match `query`, to be passed along
+/// when moving or committing them.
+pub(crate) fn select_hunks(
+    ctx: &CommandContext,
+    query: &HunkQuery,
+) -> Result<BranchOwnershipClaims> {
+    let now_ms = gitbutler_time::time::now_ms();
+    let mut ownership = BranchOwnershipClaims::default();
+    for (_branch, files) in get_applied_status(ctx, None)?.branches {
+        for file in files {
+            let hunks: Vec<_> = file
+                .hunks
+                .iter()
+                .filter(|hunk| query.matches(&file.path, hunk, now_ms))
+                .map(|hunk| Hunk {
+                    hash: Some(hunk.hash),
+                    start: hunk.start,
+                    end: hunk.end,
+                })
+                .collect();
+            if !hunks.is_empty() {
+                ownership.put(OwnershipClaim {
+                    file_path: file.path.clone(),
+                    hunks,
+                });
+            }
+        }
+    }
+    Ok(ownership)
+}
@@ -0,0 +1,174 @@
+//! Tell who commits of a project are created by and how they are signed, as Git resolves it for the
+//! repository, so a missing identity can be set before committing fails.
+//!
+//! The configuration of the repository includes conditional includes, like `includeIf "gitdir:~/work/"`,
+//! so projects can have different identities even if nothing is configured in the repositories themselves.
+//! Where the identity comes from is reported along with it, so a wrong one can be told apart from a missing
+//! one, and it can be set for a single repository.
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_repo::Config;
+use serde::Serialize;
+
+const SIGN_COMMITS: &str = "gitbutler.signCommits";
+
+/// The identity that commits of a project are created with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Identity {
+    /// The name of the author, from `GIT_AUTHOR_NAME` or `user.name`.
+    pub name: Option<String>,
+    /// The email of the author, from `GIT_AUTHOR_EMAIL` or `user.email`.
+    pub email: Option<String>,
+    /// Where [`name`](Self::name) comes from, if it's set.
+    pub name_source: Option<IdentitySource>,
+    /// Where [`email`](Self::email) comes from, if it's set.
+    pub email_source: Option<IdentitySource>,
+    /// Whether new commits are signed, as set with `gitbutler.signCommits`.
+    pub signs_commits: bool,
+    /// The format of signatures as set with `gpg.format`, like `openpgp` or `ssh`.
+    pub signing_format: String,
+    /// The key commits are signed with, which for OpenPGP is the identity itself unless `user.signingkey`
+    /// is set, just like in Git.
+    pub signing_key: Option<String>,
+}
+
+impl Identity {
+    /// Return the configuration keys that have to be set before commits can be created.
+    pub fn missing(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.name.is_none() {
+            missing.push("user.name");
+        }
+        if self.email.is_none() {
+            missing.push("user.email");
+        }
+        missing
+    }
+}
+
+/// Where a value of an [`Identity`] comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentitySource {
+    pub scope: IdentityScope,
+    /// Whether the value is in a file that the configuration includes, like with `includeIf`.
+    pub included: bool,
+}
+
+/// The place a value of an [`Identity`] is set in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IdentityScope {
+    /// An environment variable, like `GIT_AUTHOR_EMAIL`.
+    Environment,
+    /// The configuration of the repository.
+    Repository,
+    /// The configuration of the user, like `~/.gitconfig`.
+    Global,
+    /// The configuration of the system.
+    System,
+}
+
+impl From<git2::ConfigLevel> for IdentityScope {
+    fn from(level: git2::ConfigLevel) -> Self {
+        match level {
+            git2::ConfigLevel::ProgramData | git2::ConfigLevel::System => IdentityScope::System,
+            git2::ConfigLevel::XDG | git2::ConfigLevel::Global => IdentityScope::Global,
+            _ => IdentityScope::Repository,
+        }
+    }
+}
+
+/// Return the identity commits in the repository of `ctx` are created with.
+pub(crate) fn identity(ctx: &CommandContext) -> Result<Identity> {
+    let config = ctx.repository().config()?.snapshot()?;
+    let value = |env: &str, key: &str| {
+        let (value, source) = match std::env::var(env) {
+            Ok(value) => (
+                value,
+                IdentitySource {
+                    scope: IdentityScope::Environment,
+                    included: false,
+                },
+            ),
+            Err(_) => {
+                let entry = config.get_entry(key).ok()?;
+                (
+                    entry.value()?.to_owned(),
+                    IdentitySource {
+                        scope: entry.level().into(),
+                        included: entry.include_depth() > 0,
+                    },
+                )
+            }
+        };
+        (!value.trim().is_empty()).then_some((value, source))
+    };
+    let (name, name_source) = value("GIT_AUTHOR_NAME", "user.name").unzip();
+    let (email, email_source) = value("GIT_AUTHOR_EMAIL", "user.email").unzip();
+    let signing_format = config
+        .get_string("gpg.format")
+        .unwrap_or_else(|_| "openpgp".into());
+    let signing_key = config.get_string("user.signingkey").ok().or_else(|| {
+        if signing_format == "ssh" {
+            return None;
+        }
+        Some(format!("{} <{}>", name.as_deref()?, email.as_deref()?))
+    });
+    Ok(Identity {
+        name,
+        email,
+        name_source,
+        email_source,
+        signs_commits: config.get_bool(SIGN_COMMITS).unwrap_or(false),
+        signing_format,
+        signing_key,
+    })
+}
+
+/// Fail with a validation error if the identity commits are created with isn't complete.
+pub(crate) fn ensure_identity(ctx: &CommandContext) -> Result<()> {
+    let missing = identity(ctx)?.missing();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Git doesn't know who you are: set {} to commit",
+        missing.join(" and ")
+    ))
+    .context(Code::Validation)
+}
+
+/// Set the name and email commits in the repository of `ctx` are authored with in its configuration, which
+/// takes precedence over the configuration of the user and what it includes, and return the identity as it
+/// is then.
+///
+/// `None` leaves a value as it is, and an empty value removes it from the repository so the one of the user
+/// applies again.
+pub(crate) fn set_identity(
+    ctx: &CommandContext,
+    name: Option<&str>,
+    email: Option<&str>,
+) -> Result<Identity> {
+    for value in name.iter().chain(email.iter()) {
+        if value.contains(['<', '>', '\n']) {
+            return Err(anyhow!("'{value}' can't be part of an identity"))
+                .context(Code::Validation);
+        }
+    }
+    if let Some(email) = email.filter(|email| !email.trim().is_empty() && !email.contains('@')) {
+        return Err(anyhow!("'{email}' isn't an email address")).context(Code::Validation);
+    }
+
+    let config = Config::from(ctx.repository());
+    for (key, value) in [("user.name", name), ("user.email", email)] {
+        match value.map(str::trim) {
+            None => {}
+            Some("") => config.remove_local(key)?,
+            Some(value) => config.set_local(key, value)?,
+        }
+    }
+    identity(ctx)
+}
@@ -0,0 +1,147 @@
+//! Tell whether paths are ignored and by which rule, to explain why changes don't show up, and add rules to
+//! ignore files of the project.
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use serde::{Deserialize, Serialize};
+
+/// The ignore files rules can be added to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IgnoreFile {
+    /// The `.gitignore` file at the root of the worktree, which is shared with everyone.
+    Gitignore,
+    /// `.git/info/exclude`, which only applies to this clone.
+    InfoExclude,
+}
+
+/// Whether a path is ignored, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IgnoreCheck {
+    /// The path, relative to the worktree.
+    pub path: PathBuf,
+    pub ignored: bool,
+    /// `true` if the path is in the index, in which case its changes show up even if it's ignored.
+    pub tracked: bool,
+    /// The rule that decided whether the path is ignored, which is `None` if no rule matched.
+    pub rule: Option<IgnoreRule>,
+}
+
+/// A rule of an ignore file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IgnoreRule {
+    /// The rule as it's written, like `!/target/`.
+    pub pattern: String,
+    /// `true` if the rule stops matching paths from being ignored, like `!keep.txt`.
+    pub negated: bool,
+    /// The file with the rule, relative to the worktree if it's inside of it, or `None` if it wasn't read
+    /// from a file.
+    pub source: Option<PathBuf>,
+    /// The line of the rule in `source`, starting at 1.
+    pub line: usize,
+}
+
+/// Check if each of `paths`, relative to the worktree, is ignored, like `git check-ignore --verbose` does.
+pub(crate) fn check_ignored(ctx: &CommandContext, paths: &[PathBuf]) -> Result<Vec<IgnoreCheck>> {
+    let worktree = ctx.project().worktree_path();
+    let repo = gix::open(ctx.repository().path())?;
+    let index = repo.index_or_empty()?;
+    let mut excludes = repo.excludes(
+        &index,
+        None,
+        gix::worktree::stack::state::ignore::Source::WorktreeThenIdMappingIfNotSkipped,
+    )?;
+    let git_index = ctx.repository().index()?;
+    paths
+        .iter()
+        .map(|path| {
+            if path.is_absolute()
+                || path
+                    .components()
+                    .any(|c| c == std::path::Component::ParentDir)
+            {
+                return Err(anyhow!("'{}' isn't a path in the worktree", path.display()))
+                    .context(Code::Validation);
+            }
+            let platform = excludes.at_path(path, None)?;
+            let rule = platform.matching_exclude_pattern().map(|found| IgnoreRule {
+                pattern: found.pattern.to_string(),
+                negated: found
+                    .pattern
+                    .mode
+                    .contains(gix::glob::pattern::Mode::NEGATIVE),
+                source: found.source.map(|source| {
+                    source
+                        .strip_prefix(&worktree)
+                        .map(Path::to_owned)
+                        .unwrap_or_else(|_| source.to_owned())
+                }),
+                line: found.sequence_number,
+            });
+            Ok(IgnoreCheck {
+                path: path.clone(),
+                ignored: platform.is_excluded(),
+                tracked: git_index.get_path(path, 0).is_some(),
+                rule,
+            })
+        })
+        .collect()
+}
+
+/// Append `patterns` to `file`, skipping the ones it has already, and return the ones that were added.
+pub(crate) fn add_ignore_patterns(
+    ctx: &CommandContext,
+    file: IgnoreFile,
+    patterns: &[String],
+) -> Result<Vec<String>> {
+    if let Some(invalid) = patterns
+        .iter()
+        .find(|pattern| pattern.trim().is_empty() || pattern.contains(['\n', '\r']))
+    {
+        return Err(anyhow!("'{invalid}' isn't a valid ignore pattern")).context(Code::Validation);
+    }
+    let path = match file {
+        IgnoreFile::Gitignore => ctx.project().worktree_path().join(".gitignore"),
+        IgnoreFile::InfoExclude => ctx.repository().commondir().join("info").join("exclude"),
+    };
+    let existing = match std::fs::read_to_string(&path) {
+        Ok(existing) => existing,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let mut added: Vec<String> = Vec::new();
+    for pattern in patterns {
+        if !existing.lines().any(|line| line == pattern) && !added.contains(pattern) {
+            added.push(pattern.clone());
+        }
+    }
+    if added.is_empty() {
+        return Ok(added);
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut content = String::new();
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        content.push('\n');
+    }
+    for pattern in &added {
+        content.push_str(pattern);
+        content.push('\n');
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut ignore_file| ignore_file.write_all(content.as_bytes()))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(added)
+}
@@ -3,19 +3,20 @@ use std::{path::PathBuf, vec};
 use anyhow::{anyhow, Context, Result};
 use bstr::ByteSlice;
 use gitbutler_branch::{
-    self, Branch, BranchCreateRequest, SignaturePurpose, VirtualBranchesHandle,
+    self, Branch, BranchCreateRequest, BranchId, SignaturePurpose, VirtualBranchesHandle,
     GITBUTLER_INTEGRATION_REFERENCE,
 };
 use gitbutler_command_context::CommandContext;
 use gitbutler_commit::commit_ext::CommitExt;
 use gitbutler_error::error::Marker;
 use gitbutler_project::access::WorktreeWritePermission;
-use gitbutler_repo::{LogUntil, RepoActionsExt, RepositoryExt};
+use gitbutler_repo::{sparse_checkout, LogUntil, RepoActionsExt, RepositoryExt};
+use serde::Serialize;
 
-use crate::{branch_manager::BranchManagerExt, conflicts, VirtualBranchesExt};
+use crate::{branch_manager::BranchManagerExt, conflicts, stack, VirtualBranchesExt};
 
 const WORKSPACE_HEAD: &str = "Workspace Head";
-const GITBUTLER_INTEGRATION_COMMIT_TITLE: &str = "GitButler Integration Commit";
+pub(crate) const GITBUTLER_INTEGRATION_COMMIT_TITLE: &str = "GitButler Integration Commit";
 
 // Creates and returns a merge commit of all active branch heads.
 //
@@ -33,7 +34,11 @@ pub(crate) fn get_workspace_head(ctx: &CommandContext) -> Result<git2::Oid> {
     let target_commit = repo.find_commit(target.sha)?;
     let mut workspace_tree = target_commit.tree()?;
 
-    if conflicts::is_conflicting(ctx, None)? {
+    // Cherry-picks and reverts are committed on top of the branch, so the workspace is unaffected by their conflicts.
+    if conflicts::is_conflicting(ctx, None)?
+        && conflicts::cherry_pick(ctx)?.is_none()
+        && conflicts::reverted(ctx)?.is_none()
+    {
         let merge_parent = conflicts::merge_parent(ctx)?.ok_or(anyhow!("No merge parent"))?;
         let first_branch = virtual_branches.first().ok_or(anyhow!("No branches"))?;
 
@@ -42,7 +47,7 @@ pub(crate) fn get_workspace_head(ctx: &CommandContext) -> Result<git2::Oid> {
     } else {
         for branch in virtual_branches.iter_mut() {
             let branch_tree = repo.find_commit(branch.head)?.tree()?;
-            let merge_tree = repo.find_commit(target.sha)?.tree()?;
+            let merge_tree = repo.find_commit(branch.base(target.sha))?.tree()?;
             let mut index = repo.merge_trees(&merge_tree, &workspace_tree, &branch_tree, None)?;
 
             if !index.has_conflicts() {
@@ -140,6 +145,8 @@ pub fn update_gitbutler_integration(
     }
 
     let vb_state = ctx.project().virtual_branches();
+    // Stacked branches have to be on top of their parents before they are merged into the workspace.
+    stack::restack(ctx)?;
 
     // get all virtual branches, we need to try to update them all
     let virtual_branches: Vec<Branch> = vb_state
@@ -220,6 +227,8 @@ pub fn update_gitbutler_integration(
     let mut index = repo.index()?;
     index.read_tree(&workspace_tree)?;
     index.write()?;
+    // Rewriting the index dropped the marks of the files that aren't checked out.
+    sparse_checkout::reapply(ctx)?;
 
     // finally, update the refs/gitbutler/ heads to the states of the current virtual branches
     for branch in &virtual_branches {
@@ -395,6 +404,180 @@ fn verify_head_is_clean(ctx: &CommandContext, perm: &mut WorktreeWritePermission
     Ok(())
 }
 
+/// A way in which the integration commit or an applied branch doesn't match what is recorded for
+/// the virtual branches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum IntegrationDivergence {
+    /// The integration branch doesn't point to an integration commit.
+    #[serde(rename_all = "camelCase")]
+    MissingIntegrationCommit,
+    /// The head of the branch doesn't descend from its base, which is its pinned base or the target.
+    #[serde(rename_all = "camelCase")]
+    NotBasedOnBase {
+        branch_id: BranchId,
+        branch_name: String,
+        #[serde(with = "gitbutler_serde::oid")]
+        base: git2::Oid,
+        #[serde(with = "gitbutler_serde::oid")]
+        head: git2::Oid,
+    },
+    /// The head of the branch isn't a parent of the integration commit, so the workspace doesn't
+    /// contain what the branch records.
+    #[serde(rename_all = "camelCase")]
+    NotIntegrated {
+        branch_id: BranchId,
+        branch_name: String,
+        #[serde(with = "gitbutler_serde::oid")]
+        head: git2::Oid,
+    },
+    /// A parent of the integration commit is neither the head of an applied branch nor the target.
+    #[serde(rename_all = "camelCase")]
+    UnknownParent {
+        #[serde(with = "gitbutler_serde::oid")]
+        commit: git2::Oid,
+    },
+}
+
+impl IntegrationDivergence {
+    /// The branch that diverges, if it's about a branch.
+    pub fn branch_id(&self) -> Option<BranchId> {
+        match self {
+            IntegrationDivergence::NotBasedOnBase { branch_id, .. }
+            | IntegrationDivergence::NotIntegrated { branch_id, .. } => Some(*branch_id),
+            IntegrationDivergence::MissingIntegrationCommit
+            | IntegrationDivergence::UnknownParent { .. } => None,
+        }
+    }
+}
+
+impl std::fmt::Display for IntegrationDivergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntegrationDivergence::MissingIntegrationCommit => write!(
+                f,
+                "{} doesn't point to an integration commit",
+                GITBUTLER_INTEGRATION_REFERENCE.branch()
+            ),
+            IntegrationDivergence::NotBasedOnBase {
+                branch_name,
+                base,
+                head,
+                ..
+            } => write!(
+                f,
+                "branch '{branch_name}' at {head} doesn't build on its base {base}"
+            ),
+            IntegrationDivergence::NotIntegrated {
+                branch_name, head, ..
+            } => write!(
+                f,
+                "branch '{branch_name}' at {head} isn't part of the integration commit"
+            ),
+            IntegrationDivergence::UnknownParent { commit } => write!(
+                f,
+                "the integration commit has {commit} as parent, which no applied branch points to"
+            ),
+        }
+    }
+}
+
+/// Return `true` if the head of `branch` descends from its base, which is its pinned base or the
+/// target commit `target_sha`.
+fn builds_on_base(ctx: &CommandContext, branch: &Branch, target_sha: git2::Oid) -> Result<bool> {
+    let base = branch.base(target_sha);
+    Ok(branch.head == base
+        || ctx
+            .repository()
+            .graph_descendant_of(branch.head, base)
+            .context("failed to check if branch builds on its base")?)
+}
+
+/// Return `true` if the applied branch identified by `branch_id` builds on its base, which is its
+/// pinned base or the target.
+pub(crate) fn is_based_on_target(ctx: &CommandContext, branch_id: BranchId) -> Result<bool> {
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    let branch = vb_state.get_branch_in_workspace(branch_id)?;
+    builds_on_base(ctx, &branch, target.sha)
+}
+
+/// Check that every applied branch builds on its base and is a parent of the integration commit,
+/// and that the integration commit has no other parents, returning each divergence found.
+pub(crate) fn verify_integration(ctx: &CommandContext) -> Result<Vec<IntegrationDivergence>> {
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    let branches = vb_state.list_branches_in_workspace()?;
+
+    let mut divergences = vec![];
+    for branch in &branches {
+        if !builds_on_base(ctx, branch, target.sha)? {
+            divergences.push(IntegrationDivergence::NotBasedOnBase {
+                branch_id: branch.id,
+                branch_name: branch.name.clone(),
+                base: branch.base(target.sha),
+                head: branch.head,
+            });
+        }
+    }
+
+    let integration_commit = ctx
+        .repository()
+        .find_reference(&GITBUTLER_INTEGRATION_REFERENCE.to_string())
+        .and_then(|reference| reference.peel_to_commit())
+        .ok()
+        .filter(|commit| {
+            commit
+                .message()
+                .is_some_and(|message| message.starts_with(GITBUTLER_INTEGRATION_COMMIT_TITLE))
+        });
+    let Some(integration_commit) = integration_commit else {
+        divergences.push(IntegrationDivergence::MissingIntegrationCommit);
+        return Ok(divergences);
+    };
+
+    // Branches without commits of their own are only represented by the target, just like when the
+    // integration commit is created.
+    let mut expected_parents: Vec<git2::Oid> = branches
+        .iter()
+        .map(|branch| branch.head)
+        .filter(|head| *head != target.sha)
+        .collect();
+    if expected_parents.is_empty() {
+        expected_parents.push(target.sha);
+    }
+    let parents: Vec<git2::Oid> = integration_commit.parent_ids().collect();
+    for branch in &branches {
+        if branch.head != target.sha && !parents.contains(&branch.head) {
+            divergences.push(IntegrationDivergence::NotIntegrated {
+                branch_id: branch.id,
+                branch_name: branch.name.clone(),
+                head: branch.head,
+            });
+        }
+    }
+    for parent in parents {
+        if !expected_parents.contains(&parent) {
+            divergences.push(IntegrationDivergence::UnknownParent { commit: parent });
+        }
+    }
+    Ok(divergences)
+}
+
+/// Fail if [`verify_integration()`] finds the branch identified by `branch_id` diverging, before
+/// an operation that could lose its commits.
+pub(crate) fn assure_branch_integrated(ctx: &CommandContext, branch_id: BranchId) -> Result<()> {
+    let divergences: Vec<_> = verify_integration(ctx)?
+        .into_iter()
+        .filter(|divergence| divergence.branch_id() == Some(branch_id))
+        .map(|divergence| divergence.to_string())
+        .collect();
+    if !divergences.is_empty() {
+        return Err(anyhow!("{}", divergences.join(", ")).context(Marker::VerificationFailure));
+    }
+    Ok(())
+}
+
 fn invalid_head_err(head_name: &str) -> anyhow::Error {
     anyhow!(
         "project is on {head_name}. Please checkout {} to continue",
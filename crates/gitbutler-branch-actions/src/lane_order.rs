@@ -0,0 +1,26 @@
+//! Order the virtual branches of the workspace as [configured](LaneOrder) for the project when listing them.
+use gitbutler_project::LaneOrder;
+
+use crate::VirtualBranch;
+
+/// Decides the order in which virtual branches are listed.
+pub trait SortingStrategy {
+    /// Sort `branches` in the order they should be listed in.
+    fn sort(&self, branches: &mut [VirtualBranch]);
+}
+
+impl SortingStrategy for LaneOrder {
+    fn sort(&self, branches: &mut [VirtualBranch]) {
+        // Branches that are otherwise equal stay in the order they were arranged in.
+        branches.sort_by_key(|branch| branch.order);
+        match self {
+            LaneOrder::Manual => {}
+            LaneOrder::RecentActivity => {
+                branches.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+            }
+            LaneOrder::Alphabetical => {
+                branches.sort_by_cached_key(|branch| branch.name.to_lowercase());
+            }
+        }
+    }
+}
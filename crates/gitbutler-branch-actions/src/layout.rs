@@ -0,0 +1,198 @@
+//! Set up the workspace as declared in a layout file, usually `gitbutler.toml` checked into the repository, so
+//! everyone on a team works with the same lanes and routes changes to them the same way.
+//!
+//! ```toml
+//! target = "origin/main"
+//!
+//! [[lanes]]
+//! name = "docs"
+//! paths = ["docs/", "*.md"]
+//!
+//! [[lanes]]
+//! name = "app"
+//! paths = ["src/"]
+//! selected = true
+//! ```
+use std::{collections::HashSet, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::{BranchCreateRequest, BranchId, BranchUpdateRequest};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_operating_modes::assure_open_workspace_mode;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_reference::RemoteRefname;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    base::set_base_branch,
+    branch_manager::BranchManagerExt,
+    r#virtual::update_branch,
+    target_switch::{switch_target, SwitchedBranch},
+    VirtualBranchesExt,
+};
+
+/// The layout file that is used if no other is given, relative to the worktree.
+pub const LAYOUT_FILE_NAME: &str = "gitbutler.toml";
+
+/// How the workspace should be set up.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Layout {
+    /// The remote branch to use as target, like `origin/main`.
+    target: Option<String>,
+    #[serde(default)]
+    lanes: Vec<Lane>,
+}
+
+/// An applied virtual branch, identified by its name.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Lane {
+    name: String,
+    /// The [allowed paths](gitbutler_branch::Branch::allowed_paths) of the branch, which route changes
+    /// to the paths to it. All paths are allowed if there are none.
+    #[serde(default)]
+    paths: Vec<String>,
+    /// `true` if new changes go to this branch by default.
+    #[serde(default)]
+    selected: bool,
+}
+
+/// A lane of a layout, and the branch that is it now.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutLane {
+    pub name: String,
+    pub branch_id: BranchId,
+    /// `true` if the branch was created as it didn't exist yet.
+    pub created: bool,
+}
+
+/// What applying a layout changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutOutcome {
+    /// `true` if the target was set or switched.
+    pub target_changed: bool,
+    /// What happened to the applied branches if the target was switched.
+    pub switched: Vec<SwitchedBranch>,
+    /// The lanes of the layout, in its order.
+    pub lanes: Vec<LayoutLane>,
+}
+
+/// Reconcile the workspace with the layout in the file at `path`, relative to the worktree, or with
+/// [`LAYOUT_FILE_NAME`] if `None`.
+///
+/// The target is set or switched to the one of the layout, and each lane is matched with the applied branch
+/// of the same name, which is created if there is none. The branches are ordered like the lanes, and their
+/// allowed paths are replaced by those of their lane. Applied branches that aren't in the layout are left
+/// as they are, so no work is lost.
+pub(crate) fn apply_layout(
+    ctx: &CommandContext,
+    path: Option<&Path>,
+    perm: &mut WorktreeWritePermission,
+) -> Result<LayoutOutcome> {
+    let path = ctx
+        .project()
+        .worktree_path()
+        .join(path.unwrap_or(Path::new(LAYOUT_FILE_NAME)));
+    let layout = read_layout(&path)?;
+
+    let mut outcome = LayoutOutcome {
+        target_changed: false,
+        switched: Vec::new(),
+        lanes: Vec::new(),
+    };
+    let vb_state = ctx.project().virtual_branches();
+    if let Some(target) = layout.target.as_deref().map(parse_target).transpose()? {
+        match vb_state.get_default_target() {
+            Ok(current) if current.branch == target => {}
+            Ok(_current) => {
+                assure_open_workspace_mode(ctx)
+                    .context("Switching the target requires open workspace mode")?;
+                outcome.switched = switch_target(ctx, &target, perm)?;
+                outcome.target_changed = true;
+            }
+            Err(_) => {
+                set_base_branch(ctx, &target)?;
+                outcome.target_changed = true;
+            }
+        }
+    }
+    if layout.lanes.is_empty() {
+        return Ok(outcome);
+    }
+
+    assure_open_workspace_mode(ctx).context("Setting up lanes requires open workspace mode")?;
+    let branch_manager = ctx.branch_manager();
+    for (order, lane) in layout.lanes.into_iter().enumerate() {
+        let existing = vb_state
+            .list_branches_in_workspace()?
+            .into_iter()
+            .find(|branch| branch.name == lane.name);
+        let (branch_id, created) = match existing {
+            Some(branch) => (branch.id, false),
+            None => {
+                let branch = branch_manager.create_virtual_branch(
+                    &BranchCreateRequest {
+                        name: Some(lane.name.clone()),
+                        order: Some(order),
+                        ..Default::default()
+                    },
+                    perm,
+                )?;
+                (branch.id, true)
+            }
+        };
+        update_branch(
+            ctx,
+            &BranchUpdateRequest {
+                id: branch_id,
+                order: Some(order),
+                allowed_paths: Some(lane.paths),
+                selected_for_changes: lane.selected.then_some(true),
+                ..Default::default()
+            },
+        )?;
+        outcome.lanes.push(LayoutLane {
+            name: lane.name,
+            branch_id,
+            created,
+        });
+    }
+    Ok(outcome)
+}
+
+fn read_layout(path: &Path) -> Result<Layout> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read layout at '{}'", path.display()))?;
+    let layout: Layout = toml::from_str(&content)
+        .map_err(|err| anyhow!("invalid layout at '{}': {err}", path.display()))
+        .context(Code::Validation)?;
+
+    let mut names = HashSet::new();
+    for lane in &layout.lanes {
+        if !names.insert(lane.name.as_str()) {
+            return Err(anyhow!("lane '{}' is in the layout twice", lane.name))
+                .context(Code::Validation);
+        }
+    }
+    if layout.lanes.iter().filter(|lane| lane.selected).count() > 1 {
+        return Err(anyhow!("only one lane can be selected")).context(Code::Validation);
+    }
+    Ok(layout)
+}
+
+/// Parse `target` as remote branch, with `origin/main` being short for `refs/remotes/origin/main`.
+fn parse_target(target: &str) -> Result<RemoteRefname> {
+    let refname = if target.starts_with("refs/") {
+        target.to_owned()
+    } else {
+        format!("refs/remotes/{target}")
+    };
+    refname
+        .parse()
+        .map_err(|_| anyhow!("target '{target}' isn't a remote branch"))
+        .context(Code::Validation)
+}
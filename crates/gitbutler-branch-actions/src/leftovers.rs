@@ -0,0 +1,188 @@
+//! Find what runs that crashed or were killed left behind in the repository, like temporary worktrees,
+//! lock files and references of virtual branches that don't exist anymore, and remove it.
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::access::{WorktreeWritePermission, OPERATION_LOCK_FILE, PROJECT_LOCK_FILE};
+use serde::Serialize;
+
+use crate::VirtualBranchesExt;
+
+/// Where temporary worktrees, like those to sign commits in, are created, relative to the GitButler directory.
+const WORKTREES_DIR: &str = ".wt";
+/// The prefix under which references that can't be deleted without losing commits are kept instead.
+const ARCHIVE_REF_PREFIX: &str = "refs/archive/";
+
+/// Something a previous run left behind.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Leftover {
+    /// A temporary worktree, like one to sign a commit in.
+    Worktree { path: PathBuf },
+    /// A lock file of a file or reference that only GitButler writes.
+    LockFile { path: PathBuf },
+    /// A reference of a virtual branch that doesn't exist anymore.
+    Reference {
+        name: String,
+        #[serde(with = "gitbutler_serde::oid")]
+        target: git2::Oid,
+    },
+}
+
+/// Return everything previous runs left behind in the repository of `ctx`.
+pub(crate) fn find(ctx: &CommandContext) -> Result<Vec<Leftover>> {
+    let repo = ctx.repository();
+    let gb_dir = ctx.project().gb_dir();
+    let mut leftovers = Vec::new();
+
+    let worktrees_dir = gb_dir.join(WORKTREES_DIR);
+    if let Ok(entries) = std::fs::read_dir(&worktrees_dir) {
+        for entry in entries {
+            leftovers.push(Leftover::Worktree {
+                path: entry?.path(),
+            });
+        }
+    }
+
+    let mut lock_files = Vec::new();
+    collect_lock_files(&gb_dir, &worktrees_dir, &mut lock_files)?;
+    lock_files.retain(|path| {
+        path != &gb_dir.join(PROJECT_LOCK_FILE) && path != &gb_dir.join(OPERATION_LOCK_FILE)
+    });
+    for refs_dir in ["refs/gitbutler", "refs/heads/gitbutler"] {
+        collect_lock_files(&repo.path().join(refs_dir), &worktrees_dir, &mut lock_files)?;
+    }
+    leftovers.extend(
+        lock_files
+            .into_iter()
+            .map(|path| Leftover::LockFile { path }),
+    );
+
+    let known_refnames = ctx
+        .project()
+        .virtual_branches()
+        .list_all_branches()?
+        .iter()
+        .map(|branch| branch.refname().map(|refname| refname.to_string()))
+        .collect::<Result<Vec<_>>>()?;
+    for reference in repo.references_glob("refs/gitbutler/*")? {
+        let reference = reference?;
+        let (Some(name), Some(target)) = (reference.name(), reference.target()) else {
+            continue;
+        };
+        // The operations log is pushed there, but never kept locally.
+        if name == "refs/gitbutler/oplog" || known_refnames.iter().any(|known| known == name) {
+            continue;
+        }
+        leftovers.push(Leftover::Reference {
+            name: name.to_owned(),
+            target,
+        });
+    }
+    Ok(leftovers)
+}
+
+/// Remove everything previous runs left behind in the repository of `ctx`, and return what it was.
+///
+/// References whose commits aren't reachable from any other reference are moved below `refs/archive/`
+/// instead, so no commits are lost.
+pub(crate) fn remove(
+    ctx: &CommandContext,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<Vec<Leftover>> {
+    let repo = ctx.repository();
+    let leftovers = find(ctx)?;
+    let worktrees_dir = ctx.project().gb_dir().join(WORKTREES_DIR);
+    let leftover_refs: Vec<_> = leftovers
+        .iter()
+        .filter_map(|leftover| match leftover {
+            Leftover::Reference { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    for leftover in &leftovers {
+        match leftover {
+            Leftover::Worktree { path } => {
+                if path.exists() {
+                    std::fs::remove_dir_all(path)
+                        .with_context(|| format!("failed to remove {}", path.display()))?;
+                }
+            }
+            Leftover::LockFile { path } => {
+                std::fs::remove_file(path)
+                    .with_context(|| format!("failed to remove {}", path.display()))?;
+            }
+            Leftover::Reference { name, target } => {
+                if !is_reachable_otherwise(repo, *target, &leftover_refs)? {
+                    let archived =
+                        format!("{ARCHIVE_REF_PREFIX}{}", name.trim_start_matches("refs/"));
+                    repo.reference(&archived, *target, true, "archived leftover reference")?;
+                }
+                repo.find_reference(name)?.delete()?;
+            }
+        }
+    }
+
+    // Git still knows the removed worktrees until they are pruned.
+    for name in repo.worktrees()?.iter().flatten() {
+        let worktree = repo.find_worktree(name)?;
+        if worktree.path().starts_with(&worktrees_dir) {
+            worktree.prune(Some(
+                git2::WorktreePruneOptions::new()
+                    .valid(true)
+                    .locked(true)
+                    .working_tree(true),
+            ))?;
+        }
+    }
+    Ok(leftovers)
+}
+
+/// Collect all `*.lock` files in `dir` and its subdirectories into `out`, except for those in `skip`.
+fn collect_lock_files(dir: &Path, skip: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if path != skip {
+                collect_lock_files(&path, skip, out)?;
+            }
+        } else if path
+            .extension()
+            .map_or(false, |extension| extension == "lock")
+        {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Return `true` if `commit` is reachable from any reference but those named `ignored`.
+fn is_reachable_otherwise(
+    repo: &git2::Repository,
+    commit: git2::Oid,
+    ignored: &[&str],
+) -> Result<bool> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(commit)?;
+    for reference in repo.references()? {
+        let reference = reference?;
+        let Some(name) = reference.name() else {
+            continue;
+        };
+        if ignored.contains(&name) {
+            continue;
+        }
+        if let Ok(target) = reference.peel_to_commit() {
+            revwalk.hide(target.id())?;
+        }
+    }
+    Ok(revwalk.next().is_none())
+}
@@ -9,31 +9,215 @@ mod branch_manager;
 pub use branch_manager::{BranchManager, BranchManagerExt};
 
 mod base;
-pub use base::BaseBranch;
+pub use base::{BaseBranch, BaseUpdateStrategy};
 
 mod integration;
-pub use integration::{update_gitbutler_integration, verify_branch};
+pub use integration::{update_gitbutler_integration, verify_branch, IntegrationDivergence};
 
 mod file;
 pub use file::{Get, RemoteBranchFile};
 
 mod remote;
 pub use remote::{list_remote_branches, RemoteBranch, RemoteBranchData, RemoteCommit};
+mod remote_activity;
+mod remotes;
+pub use remote_activity::RemoteBranchActivity;
 
 pub mod conflicts;
 
+mod conflict_prediction;
+pub use conflict_prediction::{OverlappingFile, PredictedConflict};
+
+mod absorb;
+pub use absorb::{AbsorbOutcome, AbsorbedHunk, SkipReason, SkippedHunk};
+mod adopt;
 mod author;
+mod automation;
+pub use automation::{AutomationEvent, AutomationRun};
+mod blame;
+pub use blame::{BlameHunk, LineOrigin};
+mod branch_dependencies;
+pub use branch_dependencies::{BranchDependency, DependentHunk};
+mod branch_metadata;
+mod branch_naming;
+pub use branch_naming::NamingStrategy;
+mod checkout_preview;
+pub use checkout_preview::{CheckoutPreview, WorktreeChange, WorktreeChangeKind};
+mod cherry_pick;
+pub use cherry_pick::CherryPickOutcome;
+mod clock_skew;
+pub use clock_skew::ClockSkew;
+mod commit_graph;
+pub use commit_graph::{CommitGraph, GraphEdge, GraphRef, GraphRow};
+mod commit_message;
+mod commit_preview;
+pub use commit_preview::{CommitPreview, CommitPreviewFile};
+mod content_search;
+mod discard;
+pub use branch_metadata::{MAX_METADATA_ENTRIES, MAX_METADATA_KEY_LEN, MAX_METADATA_VALUE_LEN};
+pub use commit_message::{CommitTemplate, CommitTemplateSource};
+pub use content_search::{ContentMatch, ContentOrigin};
+mod file_history;
+pub use file_history::FileHistoryEntry;
+mod forge;
+pub use forge::{
+    AutoMerge, BranchProtection, ChecksSummary, CiState, CiStatus, CiStatusesHandle, ForgeRepo,
+    MergeMethod, NewPullRequest, PullRequest, PullRequestState, PullRequestsHandle,
+};
+#[cfg(feature = "headless")]
+pub mod headless;
+mod history;
+pub use history::{HistoryEntry, HistoryFilter, HistoryPage, SignatureFormat};
+mod hunk_groups;
+mod message_check;
+pub use message_check::{
+    register_message_checker, BasicMessageChecker, FindingSeverity, MessageChecker, MessageFinding,
+};
+mod identity;
+mod ignores;
+pub use hunk_groups::{HunkCategory, HunkGroup};
+pub use identity::{Identity, IdentityScope, IdentitySource};
+pub use ignores::{IgnoreCheck, IgnoreFile, IgnoreRule};
+mod hunk_query;
+pub use hunk_query::HunkQuery;
+mod bulk;
+pub use bulk::BulkBranchResult;
+mod cleanup;
+pub use cleanup::PendingCleanup;
+mod commit_guard;
+pub use commit_guard::FilesChangedDuringCommit;
+mod export;
+pub use export::{ExportOutcome, ExportUncommitted, ExportedBranch};
+mod lane_order;
+pub use lane_order::SortingStrategy;
+mod layout;
+pub use layout::{LayoutLane, LayoutOutcome, LAYOUT_FILE_NAME};
+mod leftovers;
+pub use leftovers::Leftover;
+mod linear_history;
+mod merge_order;
+mod message_generation;
+pub use merge_order::{MergeOrder, MergeOrderConflict, MergeOrderSimulation};
+pub use message_generation::MessageGenerator;
+mod offline;
+pub use offline::ReplayedOperation;
+mod operation_journal;
+pub use operation_journal::{PendingOperation, ResumableOperation};
+mod ownership_conflicts;
+pub use ownership_conflicts::{ConflictingClaim, OwnershipConflict};
+mod ownership_remap;
+pub use ownership_remap::{BranchRemap, HunkLocation, OwnershipRemap, RemapOutcome, RemappedHunk};
+mod partial_apply;
+mod partial_checkout;
+pub use partial_checkout::PartialCheckout;
+mod patch_import;
+pub use patch_import::{ImportPatchOptions, PatchConflict, PatchImportOutcome};
+mod patch_ids;
+mod patches;
+pub use patches::PatchFormat;
+mod pinned_base;
+mod project_search;
+pub use project_search::{SearchLocation, SearchMatch};
+mod push_preview;
+pub use push_preview::PushPreview;
+mod push_protection;
+pub use push_protection::{ProtectionSource, PushProtectionCheck, PushWarning};
+mod push_rejection;
+pub use push_rejection::{OverwrittenCommit, PushRejection};
+mod pushed_commits;
+pub use pushed_commits::PushedCommitRewrite;
+mod reconcile;
+pub use reconcile::{
+    ExternalChange, ExternalChangeKind, FoldBlocker, Reconciliation, RecoveryOption,
+};
+mod remote_branch_name;
+mod revert;
+pub use revert::RevertOutcome;
+mod secret_scan;
+pub use secret_scan::{SecretFinding, SecretRule, SecretsDetected};
+mod setup;
+pub use setup::{BranchImport, RemoteAccess, SetupBranch, SetupPlan, SetupRemote};
+mod shelf;
+mod snapshot_branch;
+mod stack;
+mod stash;
+pub use stash::{StashEntry, StashImport};
 mod status;
-use gitbutler_branch::VirtualBranchesHandle;
-pub use status::get_applied_status;
+mod status_trace;
+pub use status_trace::{FileTrace, PhaseTrace, StatusInput, StatusPhase, StatusTrace};
+mod submodules;
+mod tags;
+pub use tags::Tag;
+mod target_switch;
+pub use target_switch::{SwitchStatus, SwitchedBranch};
+mod tracking;
+mod trash;
+mod upstream;
+pub use upstream::{IntegrationOutcome, IntegrationStrategy};
+pub mod workspace;
+mod workspace_check;
+pub use workspace_check::WorkspaceDesync;
+mod workdir_cache;
+use gitbutler_branch::{
+    BranchActivityHandle, HunkNotesHandle, HunkPinsHandle, OperationQueueHandle, ProvenanceHandle,
+    TrashHandle, VirtualBranchesHandle,
+};
+use gitbutler_oplog::AuditLogHandle;
+pub use status::{get_applied_status, BranchOwnership, FileStatus, WorkspaceOwnership};
+pub use submodules::{NestedRepository, NestedRepositorySuggestion, Submodule, SubmoduleStatus};
+pub use workdir_cache::{cache_workdir_diff, invalidate_workdir_cache, WorkdirCacheGuard};
 trait VirtualBranchesExt {
     fn virtual_branches(&self) -> VirtualBranchesHandle;
+    fn branch_activity(&self) -> BranchActivityHandle;
+    fn audit_log(&self) -> AuditLogHandle;
+    fn hunk_notes(&self) -> HunkNotesHandle;
+    fn hunk_pins(&self) -> HunkPinsHandle;
+    fn commit_provenance(&self) -> ProvenanceHandle;
+    fn pull_requests(&self) -> PullRequestsHandle;
+    fn ci_statuses(&self) -> CiStatusesHandle;
+    fn trash(&self) -> TrashHandle;
+    fn operation_queue(&self) -> OperationQueueHandle;
 }
 
 impl VirtualBranchesExt for gitbutler_project::Project {
     fn virtual_branches(&self) -> VirtualBranchesHandle {
         VirtualBranchesHandle::new(self.gb_dir())
     }
+
+    fn branch_activity(&self) -> BranchActivityHandle {
+        BranchActivityHandle::new(self.gb_dir())
+    }
+
+    fn audit_log(&self) -> AuditLogHandle {
+        AuditLogHandle::new(self.gb_dir())
+    }
+
+    fn hunk_notes(&self) -> HunkNotesHandle {
+        HunkNotesHandle::new(self.gb_dir())
+    }
+    fn hunk_pins(&self) -> HunkPinsHandle {
+        HunkPinsHandle::new(self.gb_dir())
+    }
+
+    fn commit_provenance(&self) -> ProvenanceHandle {
+        ProvenanceHandle::new(self.gb_dir())
+    }
+
+    fn pull_requests(&self) -> PullRequestsHandle {
+        PullRequestsHandle::new(self.gb_dir())
+    }
+
+    fn ci_statuses(&self) -> CiStatusesHandle {
+        CiStatusesHandle::new(self.gb_dir())
+    }
+
+    fn trash(&self) -> TrashHandle {
+        TrashHandle::new(self.gb_dir())
+    }
+
+    fn operation_queue(&self) -> OperationQueueHandle {
+        OperationQueueHandle::new(self.gb_dir())
+    }
 }
 
 mod branch;
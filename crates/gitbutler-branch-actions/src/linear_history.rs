@@ -0,0 +1,17 @@
+//! Keep the history of branches free of merge commits for projects that require the target to have a linear
+//! history, so each branch can be integrated by rebasing or squashing it.
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+
+/// Fail with [`Code::LinearHistory`] if the project requires a linear history, with `merge` describing the
+/// merge commit that was about to be created, like `merge origin/feature into feature`.
+pub(crate) fn ensure_merge_allowed(ctx: &CommandContext, merge: &str) -> Result<()> {
+    if !ctx.project().settings.linear_history {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "refusing to {merge}, as the project requires a linear history without merge commits"
+    ))
+    .context(Code::LinearHistory)
+}
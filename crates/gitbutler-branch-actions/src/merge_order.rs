@@ -0,0 +1,157 @@
+//! Simulate merging the applied branches into the target one after the other, in every order, to tell which
+//! orders merge cleanly before any branch is actually merged.
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_repo::merge_drivers;
+use serde::Serialize;
+
+use crate::VirtualBranchesExt;
+
+/// An order to merge the applied branches in, and whether it merges cleanly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeOrder {
+    /// The branches in the order they are merged, up to the one that conflicts if there is one, as the order
+    /// of the others doesn't matter then.
+    pub branch_ids: Vec<BranchId>,
+    /// The conflict of the last branch with those merged before it, or `None` if all branches merge cleanly.
+    pub conflict: Option<MergeOrderConflict>,
+}
+
+/// A branch that conflicts with the branches merged before it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeOrderConflict {
+    pub branch_id: BranchId,
+    /// The paths of the conflicting files, relative to the worktree.
+    pub paths: Vec<PathBuf>,
+}
+
+/// The outcome of simulating the orders to merge the applied branches in.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeOrderSimulation {
+    pub orders: Vec<MergeOrder>,
+    /// Whether there are more orders than the ones that were simulated.
+    pub truncated: bool,
+}
+
+/// A branch to merge, with the trees of its base and of its head.
+struct Mergeable<'repo> {
+    id: BranchId,
+    base_tree: git2::Tree<'repo>,
+    head_tree: git2::Tree<'repo>,
+}
+
+/// Simulate merging the applied branches of `ctx` with commits into the default target one after the other,
+/// in at most `limit` orders, starting with the order of the branches in the workspace.
+///
+/// Each branch is merged as its changes since its base, honoring the merge drivers of `.gitattributes`.
+/// Orders that share the first branches share their simulation, so the cost grows with the amount of
+/// distinct orders rather than their length.
+pub(crate) fn simulate_merge_orders(
+    ctx: &CommandContext,
+    limit: usize,
+) -> Result<MergeOrderSimulation> {
+    if limit == 0 {
+        return Err(anyhow!("at least one order has to be simulated")).context(Code::Validation);
+    }
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    let mut branches = vb_state.list_branches_in_workspace()?;
+    branches.sort_by_key(|branch| branch.order);
+
+    let mut mergeables = Vec::new();
+    for branch in branches {
+        let base = branch.base(target.sha);
+        if branch.head == base {
+            continue;
+        }
+        mergeables.push(Mergeable {
+            id: branch.id,
+            base_tree: repo.find_commit(base)?.tree()?,
+            head_tree: repo.find_commit(branch.head)?.tree()?,
+        });
+    }
+
+    let mut simulation = MergeOrderSimulation::default();
+    if mergeables.is_empty() {
+        return Ok(simulation);
+    }
+    let target_tree = repo.find_commit(target.sha)?.tree()?;
+    simulate(
+        repo,
+        &mergeables,
+        &target_tree,
+        &mut Vec::new(),
+        limit,
+        &mut simulation,
+    )?;
+    Ok(simulation)
+}
+
+/// Merge each of the `mergeables` that isn't in `order` yet into `tree`, the result of merging those in
+/// `order`, and continue with the others until all are merged or one conflicts, recording each order in
+/// `simulation` until there are `limit`.
+fn simulate(
+    repo: &git2::Repository,
+    mergeables: &[Mergeable],
+    tree: &git2::Tree,
+    order: &mut Vec<usize>,
+    limit: usize,
+    simulation: &mut MergeOrderSimulation,
+) -> Result<()> {
+    let branch_ids = |order: &[usize]| -> Vec<BranchId> {
+        order.iter().map(|idx| mergeables[*idx].id).collect()
+    };
+    if order.len() == mergeables.len() {
+        simulation.orders.push(MergeOrder {
+            branch_ids: branch_ids(order),
+            conflict: None,
+        });
+        return Ok(());
+    }
+    for (idx, mergeable) in mergeables.iter().enumerate() {
+        if order.contains(&idx) {
+            continue;
+        }
+        if simulation.orders.len() >= limit {
+            simulation.truncated = true;
+            return Ok(());
+        }
+        order.push(idx);
+        let mut index =
+            merge_drivers::merge_trees(repo, &mergeable.base_tree, tree, &mergeable.head_tree)?;
+        if index.has_conflicts() {
+            let mut paths = Vec::new();
+            for conflict in index.conflicts()? {
+                let conflict = conflict?;
+                if let Some(entry) = [conflict.our, conflict.their, conflict.ancestor]
+                    .into_iter()
+                    .flatten()
+                    .next()
+                {
+                    paths.push(gix::path::from_bstr(entry.path.as_bstr()).into_owned());
+                }
+            }
+            simulation.orders.push(MergeOrder {
+                branch_ids: branch_ids(order),
+                conflict: Some(MergeOrderConflict {
+                    branch_id: mergeable.id,
+                    paths,
+                }),
+            });
+        } else {
+            let merged_tree = repo.find_tree(index.write_tree_to(repo)?)?;
+            simulate(repo, mergeables, &merged_tree, order, limit, simulation)?;
+        }
+        order.pop();
+    }
+    Ok(())
+}
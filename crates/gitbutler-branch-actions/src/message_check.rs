@@ -0,0 +1,220 @@
+//! Check commit messages for mistakes of spelling and style before committing, with the checkers that ship
+//! with the application and any that embedders [registered](register_message_checker()).
+//!
+//! Unlike the [commit conventions](gitbutler_project::CommitConventions) of a project, findings are advice
+//! shown with the [commit preview](crate::CommitPreview), and only prevent committing if a checker reports them
+//! as [errors](FindingSeverity::Error), which the built-in checkers never do.
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, Result};
+use gitbutler_error::error::Code;
+use serde::Serialize;
+
+/// The checkers that embedders registered, which run after the built-in ones.
+static CHECKERS: RwLock<Vec<Arc<dyn MessageChecker>>> = RwLock::new(Vec::new());
+
+/// How much a finding matters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FindingSeverity {
+    /// A suggestion that is fine to ignore.
+    Info,
+    /// Likely a mistake, but committing is still possible.
+    Warning,
+    /// Committing isn't possible until it's fixed.
+    Error,
+}
+
+/// Something a checker noticed about a commit message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageFinding {
+    /// The name of the checker that reported it.
+    pub checker: String,
+    /// What the checker looks for, like `subject-length`, so findings can be told apart without reading them.
+    pub rule: String,
+    pub severity: FindingSeverity,
+    /// The number of the line of the message it's about, starting at `1`.
+    pub line: usize,
+    pub message: String,
+}
+
+/// Checks commit messages, to be [registered](register_message_checker()) by embedders with their own rules.
+pub trait MessageChecker: Send + Sync {
+    /// The name findings are reported with.
+    fn name(&self) -> &str;
+
+    /// Return what's noteworthy about `message`, with the name of the checker left empty as it's filled in.
+    fn check(&self, message: &str) -> Vec<MessageFinding>;
+}
+
+/// Run `checker` after the built-in checkers whenever a commit message is checked, in the whole process.
+pub fn register_message_checker(checker: Arc<dyn MessageChecker>) {
+    CHECKERS
+        .write()
+        .expect("no panics while holding the lock")
+        .push(checker);
+}
+
+/// Return the findings of all checkers about `message`.
+pub(crate) fn check_message(message: &str) -> Vec<MessageFinding> {
+    let registered = CHECKERS
+        .read()
+        .expect("no panics while holding the lock")
+        .clone();
+    let builtin: Arc<dyn MessageChecker> = Arc::new(BasicMessageChecker::default());
+    std::iter::once(builtin)
+        .chain(registered)
+        .flat_map(|checker| {
+            let name = checker.name().to_owned();
+            checker
+                .check(message)
+                .into_iter()
+                .map(move |finding| MessageFinding {
+                    checker: name.clone(),
+                    ..finding
+                })
+        })
+        .collect()
+}
+
+/// Fail if a checker reported an [error](FindingSeverity::Error) about `message`.
+pub(crate) fn reject_errors(message: &str) -> Result<()> {
+    let errors: Vec<_> = check_message(message)
+        .into_iter()
+        .filter(|finding| finding.severity == FindingSeverity::Error)
+        .map(|finding| finding.message)
+        .collect();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "the commit message can't be used: {}",
+        errors.join("; ")
+    ))
+    .context(Code::Validation)
+}
+
+/// Checks the length of lines, that the subject is written in the imperative mood, and for trailing whitespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BasicMessageChecker {
+    /// The length of the subject in characters above which it's reported.
+    pub max_subject_length: usize,
+    /// The length of the lines of the body in characters above which they are reported.
+    pub max_body_line_length: usize,
+}
+
+impl Default for BasicMessageChecker {
+    fn default() -> Self {
+        BasicMessageChecker {
+            max_subject_length: 72,
+            max_body_line_length: 72,
+        }
+    }
+}
+
+/// Verbs that are commonly used in the wrong mood in subjects, like `Adds` or `Fixes`, separated by spaces.
+const COMMON_VERBS: &str = "add allow bump change clean create delete disable document enable ensure fix \
+     handle implement improve make merge move prevent refactor remove rename replace revert show simplify \
+     support update use";
+
+impl BasicMessageChecker {
+    fn finding(
+        rule: &str,
+        severity: FindingSeverity,
+        line: usize,
+        message: String,
+    ) -> MessageFinding {
+        MessageFinding {
+            checker: String::new(),
+            rule: rule.to_owned(),
+            severity,
+            line,
+            message,
+        }
+    }
+}
+
+impl MessageChecker for BasicMessageChecker {
+    fn name(&self) -> &str {
+        "basic"
+    }
+
+    fn check(&self, message: &str) -> Vec<MessageFinding> {
+        let mut findings = Vec::new();
+        for (idx, line) in message.lines().enumerate() {
+            let number = idx + 1;
+            let length = line.chars().count();
+            let max_length = if idx == 0 {
+                self.max_subject_length
+            } else {
+                self.max_body_line_length
+            };
+            if length > max_length {
+                let (rule, what) = if idx == 0 {
+                    ("subject-length", "the subject")
+                } else {
+                    ("body-line-length", "the line")
+                };
+                findings.push(Self::finding(
+                    rule,
+                    FindingSeverity::Warning,
+                    number,
+                    format!("{what} has {length} characters, more than {max_length}"),
+                ));
+            }
+            if line.ends_with([' ', '\t']) {
+                findings.push(Self::finding(
+                    "trailing-whitespace",
+                    FindingSeverity::Info,
+                    number,
+                    "the line ends with whitespace".to_owned(),
+                ));
+            }
+        }
+
+        let subject = message.lines().next().unwrap_or_default();
+        if let Some((word, verb)) = non_imperative_verb(subject) {
+            findings.push(Self::finding(
+                "imperative-mood",
+                FindingSeverity::Info,
+                1,
+                format!("the subject should be in the imperative mood, like '{verb}' instead of '{word}'"),
+            ));
+        }
+        if message
+            .lines()
+            .nth(1)
+            .is_some_and(|line| !line.trim().is_empty())
+        {
+            findings.push(Self::finding(
+                "blank-line-after-subject",
+                FindingSeverity::Info,
+                2,
+                "the subject should be followed by a blank line".to_owned(),
+            ));
+        }
+        findings
+    }
+}
+
+/// Return the first word of `subject` along with the verb it stands for if it isn't in the imperative mood,
+/// like `Added`, `Fixing` or `Updates`. A prefix like `fix(ui): ` of conventional commits is skipped.
+fn non_imperative_verb(subject: &str) -> Option<(&str, &'static str)> {
+    let description = match subject.split_once(": ") {
+        Some((prefix, rest)) if !prefix.contains(' ') => rest,
+        _ => subject,
+    };
+    let word = description.split_whitespace().next()?;
+    let lower = word.to_lowercase();
+    let verb = ["ed", "d", "ing", "es", "s"]
+        .into_iter()
+        .find_map(|suffix| {
+            let stem = lower.strip_suffix(suffix)?;
+            // Verbs like `move` lose their `e` in `Moved` and `Moving`.
+            COMMON_VERBS
+                .split_whitespace()
+                .find(|verb| *verb == stem || verb.strip_suffix('e') == Some(stem))
+        })?;
+    Some((word, verb))
+}
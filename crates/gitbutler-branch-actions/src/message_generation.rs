@@ -0,0 +1,303 @@
+//! Generate commit messages from the changes of a branch with the [model](MessageGenerationBackend)
+//! configured for the project, which may be a hosted API, a proxy in front of one, or a model on the
+//! local machine.
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::{MessageGeneration, MessageGenerationBackend, ProjectId};
+use gitbutler_secret::{secret, Sensitive};
+use reqwest::RequestBuilder;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+
+use crate::{file::VirtualBranchFile, forge::block_on, status::get_applied_status};
+
+/// How long models may take to answer, which is generous for local models on slow machines.
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+const ANTHROPIC_URL: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const MAX_TOKENS: u32 = 1024;
+
+/// The prompt used if the project has none.
+const DEFAULT_PROMPT: &str =
+    "Write a commit message for the changes of the branch '{branch}' below.
+Start with a subject line of at most 50 characters in the imperative mood, followed by a blank line
+and a body that explains what changed and why, wrapped at 72 characters.
+Only answer with the commit message, without quoting it.
+
+{diff}";
+
+/// Something that turns a prompt into the text of a commit message.
+pub trait MessageGenerator {
+    /// Return the answer of the model to `prompt`.
+    fn generate(&self, prompt: &str) -> Result<String>;
+}
+
+/// A server that speaks the chat completions API of OpenAI.
+struct OpenAiCompatible {
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl MessageGenerator for OpenAiCompatible {
+    fn generate(&self, prompt: &str) -> Result<String> {
+        #[derive(Deserialize)]
+        struct Answer {
+            choices: Vec<Choice>,
+        }
+        #[derive(Deserialize)]
+        struct Choice {
+            message: ChatMessage,
+        }
+
+        let mut request = reqwest::Client::new()
+            .post(endpoint(&self.base_url, "chat/completions"))
+            .json(&json!({
+                "model": self.model,
+                "messages": [{ "role": "user", "content": prompt }],
+            }));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let answer: Answer = send(&self.base_url, request)?;
+        answer
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .with_context(|| format!("{} answered without a message", self.base_url))
+    }
+}
+
+/// The messages API of Anthropic.
+struct Anthropic {
+    base_url: String,
+    model: String,
+    api_key: String,
+}
+
+impl MessageGenerator for Anthropic {
+    fn generate(&self, prompt: &str) -> Result<String> {
+        #[derive(Deserialize)]
+        struct Answer {
+            content: Vec<Content>,
+        }
+        #[derive(Deserialize)]
+        struct Content {
+            #[serde(default)]
+            text: String,
+        }
+
+        let request = reqwest::Client::new()
+            .post(endpoint(&self.base_url, "v1/messages"))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&json!({
+                "model": self.model,
+                "max_tokens": MAX_TOKENS,
+                "messages": [{ "role": "user", "content": prompt }],
+            }));
+        let answer: Answer = send(&self.base_url, request)?;
+        answer
+            .content
+            .into_iter()
+            .next()
+            .map(|content| content.text)
+            .with_context(|| format!("{} answered without a message", self.base_url))
+    }
+}
+
+/// A model served by Ollama.
+struct Ollama {
+    base_url: String,
+    model: String,
+}
+
+impl MessageGenerator for Ollama {
+    fn generate(&self, prompt: &str) -> Result<String> {
+        #[derive(Deserialize)]
+        struct Answer {
+            message: ChatMessage,
+        }
+
+        let request = reqwest::Client::new()
+            .post(endpoint(&self.base_url, "api/chat"))
+            .json(&json!({
+                "model": self.model,
+                "messages": [{ "role": "user", "content": prompt }],
+                "stream": false,
+            }));
+        let answer: Answer = send(&self.base_url, request)?;
+        Ok(answer.message.content)
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: String,
+}
+
+/// Return the generator for `backend`, authenticated with the API key stored for the project with `project_id`.
+fn generator(
+    project_id: ProjectId,
+    backend: &MessageGenerationBackend,
+) -> Result<Box<dyn MessageGenerator>> {
+    let api_key = secret::retrieve(&api_key_handle(project_id), secret::Namespace::BuildKind)?
+        .map(|key| key.0);
+    Ok(match backend {
+        MessageGenerationBackend::OpenAiCompatible { base_url, model } => {
+            Box::new(OpenAiCompatible {
+                base_url: base_url.clone(),
+                model: model.clone(),
+                api_key,
+            })
+        }
+        MessageGenerationBackend::Anthropic { base_url, model } => Box::new(Anthropic {
+            base_url: base_url.clone().unwrap_or_else(|| ANTHROPIC_URL.to_owned()),
+            model: model.clone(),
+            api_key: api_key
+                .context("add an API key for Anthropic to generate commit messages")
+                .context(Code::MessageGeneration)?,
+        }),
+        MessageGenerationBackend::Ollama { base_url, model } => Box::new(Ollama {
+            base_url: base_url.clone(),
+            model: model.clone(),
+        }),
+    })
+}
+
+/// Store `api_key` to generate commit messages for the project with `project_id`, or remove the stored one if
+/// it's empty.
+pub(crate) fn set_api_key(project_id: ProjectId, api_key: &str) -> Result<()> {
+    secret::persist(
+        &api_key_handle(project_id),
+        &Sensitive(api_key.to_owned()),
+        secret::Namespace::BuildKind,
+    )
+}
+
+fn api_key_handle(project_id: ProjectId) -> String {
+    format!("message_generation_api_key:{project_id}")
+}
+
+/// Generate a commit message for the uncommitted changes of the branch identified by `branch_id` with the
+/// model configured for the project.
+pub(crate) fn generate(ctx: &CommandContext, branch_id: BranchId) -> Result<String> {
+    let project = ctx.project();
+    let settings = &project.settings.message_generation;
+    let backend = settings
+        .backend
+        .as_ref()
+        .context("there is no model configured to generate commit messages with")
+        .context(Code::Validation)?;
+    let (branch, files) = get_applied_status(ctx, None)?
+        .branches
+        .into_iter()
+        .find(|(branch, _)| branch.id == branch_id)
+        .with_context(|| format!("branch {branch_id} not found"))?;
+    if files.is_empty() {
+        return Err(anyhow!(
+            "branch '{}' has no changes to describe",
+            branch.name
+        ))
+        .context(Code::Validation);
+    }
+
+    let prompt = build_prompt(settings, &branch.name, &files);
+    let message = generator(project.id, backend)?
+        .generate(&prompt)
+        .context(Code::MessageGeneration)?;
+    let message = message.trim();
+    if message.is_empty() {
+        return Err(anyhow!("the model answered with an empty message"))
+            .context(Code::MessageGeneration);
+    }
+    Ok(message.to_owned())
+}
+
+/// Return the prompt for the changes `files` of the branch `branch_name`.
+///
+/// Changes are added hunk by hunk, and those that would make them longer than the limit of `settings` are
+/// left out, so smaller changes that come after a large one still make it. The files of all hunks that were
+/// left out are listed instead.
+fn build_prompt(
+    settings: &MessageGeneration,
+    branch_name: &str,
+    files: &[VirtualBranchFile],
+) -> String {
+    let mut diff = String::new();
+    let mut left_out = Vec::new();
+    for file in files {
+        if file.binary || file.large {
+            left_out.push(file.path.display().to_string());
+            continue;
+        }
+        let header = format!("--- {}\n", file.path.display());
+        let mut added_header = false;
+        let mut complete = true;
+        for hunk in &file.hunks {
+            let hunk = String::from_utf8_lossy(&hunk.diff);
+            let needed = hunk.len() + if added_header { 0 } else { header.len() };
+            if diff.len() + needed > settings.max_diff_chars {
+                complete = false;
+                continue;
+            }
+            if !added_header {
+                diff.push_str(&header);
+                added_header = true;
+            }
+            diff.push_str(&hunk);
+            if !hunk.ends_with('\n') {
+                diff.push('\n');
+            }
+        }
+        if !complete {
+            left_out.push(file.path.display().to_string());
+        }
+    }
+    if !left_out.is_empty() {
+        diff.push_str(&format!(
+            "\nChanges to these files were left out as they are too large: {}\n",
+            left_out.join(", ")
+        ));
+    }
+
+    settings
+        .prompt_template
+        .as_deref()
+        .unwrap_or(DEFAULT_PROMPT)
+        .replace("{branch}", branch_name)
+        .replace("{diff}", &diff)
+}
+
+fn endpoint(base_url: &str, path: &str) -> String {
+    format!("{}/{path}", base_url.trim_end_matches('/'))
+}
+
+/// Send `request` to the API at `base_url` and read its answer, failing with the error it answered with.
+fn send<T: DeserializeOwned + Send>(base_url: &str, request: RequestBuilder) -> Result<T> {
+    block_on(async {
+        let response = request
+            .timeout(TIMEOUT)
+            .send()
+            .await
+            .with_context(|| format!("failed to reach {base_url}"))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!(
+                "{base_url} answered with {status}: {}",
+                body.trim()
+            ));
+        }
+        response
+            .json()
+            .await
+            .with_context(|| format!("the answer of {base_url} couldn't be read"))
+    })
+}
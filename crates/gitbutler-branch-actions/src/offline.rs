@@ -0,0 +1,101 @@
+//! Pushes and fetches that are put off while the network is unavailable, and run once it's back.
+//!
+//! The network counts as unavailable if a transfer fails with [`Code::Offline`], and as available again
+//! once any remote could be fetched.
+use anyhow::{anyhow, Result};
+use gitbutler_branch::{QueuedOperation, QueuedOperationKind};
+use gitbutler_error::error::Code;
+use gitbutler_project::{FetchFailure, Project};
+use gitbutler_repo::FetchReport;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use serde::Serialize;
+
+use crate::VirtualBranchesExt;
+
+/// A queued operation that ran, and how it went.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayedOperation {
+    pub operation: QueuedOperation,
+    /// What went wrong, or `None` if the operation succeeded.
+    pub error: Option<String>,
+}
+
+/// Return `true` if `err` says the network is unavailable.
+pub(crate) fn is_offline(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<Code>() == Some(&Code::Offline)
+}
+
+/// Return `true` if `report` says the network is unavailable, which is if no remote could be fetched
+/// for that reason.
+pub(crate) fn is_offline_report(report: &FetchReport) -> bool {
+    !report.remotes.is_empty()
+        && report
+            .remotes
+            .iter()
+            .all(|remote| remote.failure == Some(FetchFailure::Offline))
+}
+
+/// Return `true` if `report` says the network is available, which is if any remote could be fetched.
+pub(crate) fn is_online_report(report: &FetchReport) -> bool {
+    report.remotes.iter().any(|remote| remote.is_success())
+}
+
+/// Turn `report` into the result of a queued fetch, which fails if the network is still unavailable or
+/// any remote couldn't be fetched.
+pub(crate) fn fetch_outcome(report: &FetchReport) -> Result<()> {
+    if is_offline_report(report) {
+        return Err(anyhow!("the network is still unavailable").context(Code::Offline));
+    }
+    let errors: Vec<_> = report
+        .remotes
+        .iter()
+        .filter_map(|remote| {
+            remote
+                .error
+                .as_deref()
+                .map(|error| format!("{}: {error}", remote.remote))
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("{}", errors.join("; ")))
+    }
+}
+
+/// Queue an operation of `kind` for `project` to run once the network is available again, and return it.
+pub(crate) fn enqueue(project: &Project, kind: QueuedOperationKind) -> Result<QueuedOperation> {
+    let operation = project
+        .operation_queue()
+        .enqueue(kind, now_since_unix_epoch_ms())?;
+    tracing::info!(project_id = %project.id, ?operation, "queued operation until the network is available");
+    Ok(operation)
+}
+
+/// Run the queued operations of `project` with `run` in the order they were queued, and remove each
+/// one that ran from the queue, whether it succeeded or not.
+///
+/// Stops once an operation fails as the network is still unavailable, keeping it and the ones after it.
+pub(crate) fn replay(
+    project: &Project,
+    mut run: impl FnMut(QueuedOperationKind) -> Result<()>,
+) -> Result<Vec<ReplayedOperation>> {
+    let queue = project.operation_queue();
+    let mut replayed = Vec::new();
+    for operation in queue.list()? {
+        let result = run(operation.kind);
+        if result.as_ref().is_err_and(is_offline) {
+            tracing::info!(project_id = %project.id, "the network is still unavailable, keeping queued operations");
+            break;
+        }
+        if let Err(err) = queue.remove(operation.id) {
+            tracing::debug!(?err, "the queued operation was cancelled while it ran");
+        }
+        replayed.push(ReplayedOperation {
+            operation,
+            error: result.err().map(|err| format!("{err:#}")),
+        });
+    }
+    Ok(replayed)
+}
@@ -0,0 +1,231 @@
+//! Remember multi-step operations while they run, so one that was interrupted by the process ending, like
+//! updating the target with many branches applied, can be resumed or rolled back the next time the project
+//! is opened instead of leaving the workspace half-done.
+//!
+//! The journal is removed once an operation returns, be it successfully or with an error, as errors leave the
+//! workspace in a state that the operation itself decided on. Only if the process ends in between does it
+//! stay behind.
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_oplog::OplogExt;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_reference::Refname;
+use gitbutler_repo::progress::NoProgress;
+use serde::{Deserialize, Serialize};
+
+use crate::{base, bulk, BaseUpdateStrategy};
+
+const JOURNAL_FILE: &str = "operation.json";
+
+/// The directories of the projects that run an operation in this process right now, whose journal
+/// isn't left over.
+static RUNNING: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// An operation that can be resumed with the steps it didn't complete.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ResumableOperation {
+    /// Updating the workspace to the target, which is a single step.
+    UpdateBaseBranch {
+        #[serde(default)]
+        strategy: BaseUpdateStrategy,
+    },
+    #[serde(rename_all = "camelCase")]
+    DeleteBranches { branch_ids: Vec<BranchId> },
+    #[serde(rename_all = "camelCase")]
+    UnapplyBranches { branch_ids: Vec<BranchId> },
+    #[serde(rename_all = "camelCase")]
+    ApplyBranches { branches: Vec<Refname> },
+}
+
+impl ResumableOperation {
+    /// The amount of steps the operation has.
+    pub fn steps(&self) -> usize {
+        match self {
+            ResumableOperation::UpdateBaseBranch { .. } => 1,
+            ResumableOperation::DeleteBranches { branch_ids }
+            | ResumableOperation::UnapplyBranches { branch_ids } => branch_ids.len(),
+            ResumableOperation::ApplyBranches { branches } => branches.len(),
+        }
+    }
+
+    /// Return the operation with only the steps after the first `completed` ones.
+    fn remaining(&self, completed: usize) -> ResumableOperation {
+        match self {
+            ResumableOperation::UpdateBaseBranch { strategy } => {
+                ResumableOperation::UpdateBaseBranch {
+                    strategy: *strategy,
+                }
+            }
+            ResumableOperation::DeleteBranches { branch_ids } => {
+                ResumableOperation::DeleteBranches {
+                    branch_ids: branch_ids.iter().skip(completed).copied().collect(),
+                }
+            }
+            ResumableOperation::UnapplyBranches { branch_ids } => {
+                ResumableOperation::UnapplyBranches {
+                    branch_ids: branch_ids.iter().skip(completed).copied().collect(),
+                }
+            }
+            ResumableOperation::ApplyBranches { branches } => ResumableOperation::ApplyBranches {
+                branches: branches.iter().skip(completed).cloned().collect(),
+            },
+        }
+    }
+}
+
+/// An operation that didn't finish, as recorded in the journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingOperation {
+    pub operation: ResumableOperation,
+    /// The snapshot taken right before the operation started, which it's rolled back to.
+    #[serde(with = "gitbutler_serde::oid_opt", default)]
+    pub snapshot: Option<git2::Oid>,
+    /// The amount of steps that were completed before the operation was interrupted.
+    pub completed_steps: usize,
+    pub started_timestamp_ms: u128,
+}
+
+/// The journal of an operation that runs right now.
+pub(crate) struct OperationJournal {
+    gb_dir: PathBuf,
+    pending: PendingOperation,
+}
+
+impl OperationJournal {
+    /// Remember that another step of the operation was completed. Failures are only logged, as the step
+    /// was completed already.
+    pub(crate) fn step_completed(&mut self) {
+        self.pending.completed_steps += 1;
+        if let Err(err) = write(&self.gb_dir, &self.pending) {
+            tracing::warn!(?err, "failed to record the progress of an operation");
+        }
+    }
+}
+
+/// Run `operation` with `run`, which reports the completed steps to the journal it's passed, after recording it
+/// along with the `snapshot` taken before it.
+pub(crate) fn journaled<T>(
+    ctx: &CommandContext,
+    operation: ResumableOperation,
+    snapshot: Option<git2::Oid>,
+    run: impl FnOnce(&mut OperationJournal) -> Result<T>,
+) -> Result<T> {
+    let gb_dir = ctx.project().gb_dir();
+    let mut journal = OperationJournal {
+        gb_dir: gb_dir.clone(),
+        pending: PendingOperation {
+            operation,
+            snapshot,
+            completed_steps: 0,
+            started_timestamp_ms: gitbutler_time::time::now_ms(),
+        },
+    };
+    write(&gb_dir, &journal.pending).context("failed to record the operation")?;
+    running().insert(gb_dir.clone());
+    let result = run(&mut journal);
+    running().remove(&gb_dir);
+    if let Err(err) = remove(&gb_dir) {
+        tracing::warn!(?err, "failed to remove the journal of a finished operation");
+    }
+    result
+}
+
+/// Return the operation that was interrupted before it finished, if any.
+pub(crate) fn pending(ctx: &CommandContext) -> Result<Option<PendingOperation>> {
+    let gb_dir = ctx.project().gb_dir();
+    if running().contains(&gb_dir) {
+        return Ok(None);
+    }
+    let content = match std::fs::read_to_string(gb_dir.join(JOURNAL_FILE)) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    Ok(Some(serde_json::from_str(&content).with_context(|| {
+        format!("failed to parse {JOURNAL_FILE}")
+    })?))
+}
+
+/// Run the steps of the interrupted operation that weren't completed.
+pub(crate) fn resume(ctx: &CommandContext, perm: &mut WorktreeWritePermission) -> Result<()> {
+    let pending = assure_pending(ctx)?;
+    let operation = pending.operation.remaining(pending.completed_steps);
+    journaled(ctx, operation.clone(), pending.snapshot, |journal| {
+        match &operation {
+            ResumableOperation::UpdateBaseBranch { strategy } => {
+                base::update_base_branch(ctx, *strategy, perm, &NoProgress)?;
+                journal.step_completed();
+            }
+            ResumableOperation::DeleteBranches { branch_ids } => {
+                bulk::delete_branches(ctx, branch_ids, perm, journal)?;
+            }
+            ResumableOperation::UnapplyBranches { branch_ids } => {
+                bulk::unapply_branches(ctx, branch_ids, perm, journal)?;
+            }
+            ResumableOperation::ApplyBranches { branches } => {
+                bulk::apply_branches(ctx, branches, perm, journal, &NoProgress)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Restore the snapshot taken before the interrupted operation started, and forget about the operation.
+///
+/// The snapshot is restored with its own access to the worktree, so none may be held.
+pub(crate) fn roll_back(ctx: &CommandContext) -> Result<()> {
+    let pending = assure_pending(ctx)?;
+    let snapshot = pending
+        .snapshot
+        .ok_or_else(|| {
+            anyhow!("the operation can't be rolled back as no snapshot was taken before it started")
+        })
+        .context(Code::Validation)?;
+    ctx.project().restore_snapshot(snapshot)?;
+    remove(&ctx.project().gb_dir())
+}
+
+/// Fail if there is an interrupted operation, as starting another one would lose what's needed to roll it back.
+pub(crate) fn assure_none_pending(ctx: &CommandContext) -> Result<()> {
+    if pending(ctx)?.is_some() {
+        return Err(anyhow!(
+            "an interrupted operation needs to be resumed or rolled back first"
+        ))
+        .context(Code::Validation);
+    }
+    Ok(())
+}
+
+fn assure_pending(ctx: &CommandContext) -> Result<PendingOperation> {
+    pending(ctx)?
+        .ok_or_else(|| anyhow!("there is no interrupted operation"))
+        .context(Code::Validation)
+}
+
+fn running() -> std::sync::MutexGuard<'static, BTreeSet<PathBuf>> {
+    RUNNING.lock().expect("no panics while holding the lock")
+}
+
+fn write(gb_dir: &Path, pending: &PendingOperation) -> Result<()> {
+    Ok(gitbutler_fs::create_dirs_then_write(
+        gb_dir.join(JOURNAL_FILE),
+        serde_json::to_string_pretty(pending)?,
+    )?)
+}
+
+fn remove(gb_dir: &Path) -> Result<()> {
+    match std::fs::remove_file(gb_dir.join(JOURNAL_FILE)) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
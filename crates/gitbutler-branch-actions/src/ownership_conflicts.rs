@@ -0,0 +1,128 @@
+//! Tell which other applied branches claim changes in the files of hunks before assigning the hunks to a
+//! branch, as assigning them takes the hunks from whichever branch claimed them before.
+use std::path::PathBuf;
+
+use anyhow::Result;
+use gitbutler_branch::{BranchId, BranchOwnershipClaims, BranchUpdateRequest};
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::Hunk;
+use gitbutler_project::access::WorktreeWritePermission;
+use serde::Serialize;
+
+use crate::{r#virtual::update_branch, status::get_applied_status};
+
+/// A hunk to be assigned to a branch, and the other applied branches that claim changes in its file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnershipConflict {
+    pub path: PathBuf,
+    /// The lines of the hunk, as `start-end`.
+    pub hunk: String,
+    pub claimed_by: Vec<ConflictingClaim>,
+}
+
+/// The claims of another applied branch on the file of a hunk to be assigned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictingClaim {
+    pub branch_id: BranchId,
+    pub branch_name: String,
+    /// The lines of all hunks of the file the branch claims, as `start-end`.
+    pub hunks: Vec<String>,
+    /// `true` if the branch claims the hunk itself, which assigning it takes away from the branch.
+    pub reassigned: bool,
+    /// `true` if the branch claims a different hunk with lines of the hunk, so both branches would
+    /// claim these lines after assigning it.
+    pub overlapping: bool,
+}
+
+/// Return the hunks of `ownership` whose files other applied branches than the one identified by
+/// `branch_id` claim changes in, along with their claims.
+pub(crate) fn ownership_conflicts(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    ownership: &BranchOwnershipClaims,
+) -> Result<Vec<OwnershipConflict>> {
+    let status = get_applied_status(ctx, None)?;
+    let mut conflicts = Vec::new();
+    for claim in &ownership.claims {
+        for hunk in &claim.hunks {
+            let claimed_by: Vec<_> = status
+                .branches
+                .iter()
+                .filter(|(branch, _files)| branch.id != branch_id)
+                .filter_map(|(branch, _files)| {
+                    let hunks: Vec<_> = branch
+                        .ownership
+                        .claims
+                        .iter()
+                        .filter(|other| other.file_path == claim.file_path)
+                        .flat_map(|other| &other.hunks)
+                        .collect();
+                    if hunks.is_empty() {
+                        return None;
+                    }
+                    Some(ConflictingClaim {
+                        branch_id: branch.id,
+                        branch_name: branch.name.clone(),
+                        hunks: hunks.iter().map(|other| lines(other)).collect(),
+                        reassigned: hunks.iter().any(|other| *other == hunk),
+                        overlapping: hunks
+                            .iter()
+                            .any(|other| *other != hunk && overlaps(other, hunk)),
+                    })
+                })
+                .collect();
+            if !claimed_by.is_empty() {
+                conflicts.push(OwnershipConflict {
+                    path: claim.file_path.clone(),
+                    hunk: lines(hunk),
+                    claimed_by,
+                });
+            }
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Assign the hunks of `ownership` to the branch identified by `branch_id` in addition to those it claims
+/// already, but only if no other applied branch claims changes in their files.
+///
+/// Return the [conflicts](ownership_conflicts()) that kept the hunks from being assigned, which is
+/// empty if they were.
+pub(crate) fn claim_exclusively(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    ownership: &BranchOwnershipClaims,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<Vec<OwnershipConflict>> {
+    let conflicts = ownership_conflicts(ctx, branch_id, ownership)?;
+    if !conflicts.is_empty() {
+        return Ok(conflicts);
+    }
+    let mut claims = ctx
+        .project()
+        .virtual_branches()
+        .get_branch_in_workspace(branch_id)?
+        .ownership;
+    for claim in &ownership.claims {
+        claims.put(claim.clone());
+    }
+    update_branch(
+        ctx,
+        &BranchUpdateRequest {
+            id: branch_id,
+            ownership: Some(claims),
+            ..Default::default()
+        },
+    )?;
+    Ok(Vec::new())
+}
+
+fn lines(hunk: &Hunk) -> String {
+    format!("{}-{}", hunk.start, hunk.end)
+}
+
+fn overlaps(a: &Hunk, b: &Hunk) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
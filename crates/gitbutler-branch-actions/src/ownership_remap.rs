@@ -0,0 +1,197 @@
+//! Tell how the hunks claimed by the applied branches were remapped when the target was updated, as the new
+//! target shifts the lines of uncommitted changes and so their hunks have to be matched again. This lets users
+//! verify that no change silently fell out of its branch.
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use bstr::ByteSlice;
+use gitbutler_branch::{Branch, BranchId};
+use serde::Serialize;
+
+use crate::{hunk::VirtualBranchHunk, VirtualBranchFile};
+
+/// How the hunks claimed by the applied branches were remapped by an update of the target.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnershipRemap {
+    /// The branches that stayed applied and had hunks that weren't kept as they were.
+    pub branches: Vec<BranchRemap>,
+}
+
+impl OwnershipRemap {
+    /// Return `true` if every hunk was kept as it was.
+    pub fn is_empty(&self) -> bool {
+        self.branches.is_empty()
+    }
+}
+
+/// The hunks of a branch that weren't kept as they were.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchRemap {
+    pub branch_id: BranchId,
+    pub branch_name: String,
+    pub hunks: Vec<RemappedHunk>,
+}
+
+/// A hunk claimed by a branch before the update, and what became of it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemappedHunk {
+    pub file_path: PathBuf,
+    /// The lines of the hunk before the update, as `start-end`.
+    pub hunk: String,
+    pub outcome: RemapOutcome,
+}
+
+/// What became of a hunk when the target was updated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum RemapOutcome {
+    /// The changes of the hunk are now on other lines, or in another branch.
+    Moved { to: HunkLocation },
+    /// The changes of the hunk are now in several hunks.
+    Split { into: Vec<HunkLocation> },
+    /// The changes of the hunk are in no hunk anymore, for instance because the target contains them now.
+    Dropped,
+}
+
+/// A hunk after the update, and the branch claiming it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkLocation {
+    pub branch_id: BranchId,
+    /// The lines of the hunk, as `start-end`.
+    pub hunk: String,
+}
+
+/// Compare the hunks of the applied branches `before` the target was updated with those `after` it, and
+/// return how they were remapped. Branches that were unapplied by the update took their hunks with them
+/// and aren't reported.
+///
+/// A hunk moved if a hunk with the same changes is found on other lines or in another branch, and it was split
+/// if its changes are found in several hunks that are entirely made of them.
+pub(crate) fn ownership_remap(
+    before: &[(Branch, Vec<VirtualBranchFile>)],
+    after: &[(Branch, Vec<VirtualBranchFile>)],
+) -> OwnershipRemap {
+    let branches = before
+        .iter()
+        .filter(|(branch, _)| after.iter().any(|(other, _)| other.id == branch.id))
+        .filter_map(|(branch, files)| {
+            let mut hunks: Vec<_> = files
+                .iter()
+                .flat_map(|file| file.hunks.iter())
+                .filter_map(|hunk| {
+                    let outcome = remap_hunk(branch.id, hunk, before, after)?;
+                    Some(RemappedHunk {
+                        file_path: hunk.file_path.clone(),
+                        hunk: lines(hunk),
+                        outcome,
+                    })
+                })
+                .collect();
+            if hunks.is_empty() {
+                return None;
+            }
+            hunks.sort_by(|a, b| (&a.file_path, &a.hunk).cmp(&(&b.file_path, &b.hunk)));
+            Some(BranchRemap {
+                branch_id: branch.id,
+                branch_name: branch.name.clone(),
+                hunks,
+            })
+        })
+        .collect();
+    OwnershipRemap { branches }
+}
+
+/// Return what became of `hunk` claimed by the branch with `branch_id`, or `None` if it was kept as it was.
+fn remap_hunk(
+    branch_id: BranchId,
+    hunk: &VirtualBranchHunk,
+    before: &[(Branch, Vec<VirtualBranchFile>)],
+    after: &[(Branch, Vec<VirtualBranchFile>)],
+) -> Option<RemapOutcome> {
+    let (old_hunks, new_hunks) = (
+        hunks_of_file(before, &hunk.file_path),
+        hunks_of_file(after, &hunk.file_path),
+    );
+
+    let same_changes: Vec<_> = new_hunks
+        .iter()
+        .filter(|(_, new)| new.hash == hunk.hash)
+        .collect();
+    if same_changes
+        .iter()
+        .any(|(id, new)| *id == branch_id && lines(new) == lines(hunk))
+    {
+        return None;
+    }
+    if let Some((id, new)) = same_changes
+        .iter()
+        .find(|(id, _)| *id == branch_id)
+        .or_else(|| same_changes.first())
+    {
+        return Some(RemapOutcome::Moved {
+            to: location(*id, new),
+        });
+    }
+
+    // Hunks whose changes were all in the hunk, and which aren't an old hunk that moved.
+    let changes = changed_lines(hunk);
+    let pieces: Vec<_> = new_hunks
+        .iter()
+        .filter(|(_, new)| !old_hunks.iter().any(|(_, old)| old.hash == new.hash))
+        .filter(|(_, new)| {
+            let new_changes = changed_lines(new);
+            !new_changes.is_empty() && new_changes.is_subset(&changes)
+        })
+        .map(|(id, new)| location(*id, new))
+        .collect();
+    Some(match pieces.len() {
+        0 => RemapOutcome::Dropped,
+        1 => RemapOutcome::Moved {
+            to: pieces.into_iter().next().expect("one piece"),
+        },
+        _ => RemapOutcome::Split { into: pieces },
+    })
+}
+
+/// Return the hunks of the file at `path` in `status`, along with the id of the branch claiming them.
+fn hunks_of_file<'a>(
+    status: &'a [(Branch, Vec<VirtualBranchFile>)],
+    path: &Path,
+) -> Vec<(BranchId, &'a VirtualBranchHunk)> {
+    status
+        .iter()
+        .flat_map(|(branch, files)| {
+            files
+                .iter()
+                .filter(|file| file.path == path)
+                .flat_map(|file| &file.hunks)
+                .map(move |hunk| (branch.id, hunk))
+        })
+        .collect()
+}
+
+/// Return the added and removed lines of `hunk`, with their `+` or `-` prefix.
+fn changed_lines(hunk: &VirtualBranchHunk) -> HashSet<&[u8]> {
+    hunk.diff
+        .lines()
+        .skip_while(|line| line.starts_with(b"@@"))
+        .filter(|line| line.starts_with(b"+") || line.starts_with(b"-"))
+        .collect()
+}
+
+fn lines(hunk: &VirtualBranchHunk) -> String {
+    format!("{}-{}", hunk.start, hunk.end)
+}
+
+fn location(branch_id: BranchId, hunk: &VirtualBranchHunk) -> HunkLocation {
+    HunkLocation {
+        branch_id,
+        hunk: lines(hunk),
+    }
+}
@@ -0,0 +1,97 @@
+//! Bring only some of the changes of a branch that isn't applied into the workspace, leaving the
+//! branch itself as it is.
+use anyhow::{anyhow, bail, Context, Result};
+use gitbutler_branch::{BranchId, BranchOwnershipClaims};
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_reference::Refname;
+use gitbutler_repo::RepositoryExt;
+
+use crate::{branch_manager::BranchManagerExt, conflicts::RepoConflictsExt, VirtualBranchesExt};
+
+/// Merge the changes of `branch` that are selected by `selection` into the worktree, as uncommitted
+/// changes of a new virtual branch, and return its id.
+///
+/// The changes of `branch` are those since it forked off the target. A claim selects the hunks of its
+/// file that it matches exactly, or all hunks of the file if it has none.
+pub(crate) fn apply_partially(
+    ctx: &CommandContext,
+    branch: &Refname,
+    selection: &BranchOwnershipClaims,
+    perm: &mut WorktreeWritePermission,
+) -> Result<BranchId> {
+    ctx.assure_resolved()?;
+    if selection.claims.is_empty() {
+        bail!("no changes were selected");
+    }
+    let repo = ctx.repository();
+    let head = repo
+        .find_reference(&branch.to_string())
+        .map_err(|err| match err.code() {
+            git2::ErrorCode::NotFound => anyhow!("branch {branch} was not found"),
+            _ => err.into(),
+        })?
+        .peel_to_commit()?;
+    let target = ctx.project().virtual_branches().get_default_target()?;
+    let merge_base = repo.find_commit(repo.merge_base(target.sha, head.id())?)?;
+    let base_tree = merge_base.tree()?;
+    let head_tree = head.tree()?;
+
+    let diffs = gitbutler_diff::trees(repo, &base_tree, &head_tree)?;
+    for claim in &selection.claims {
+        let file_diff = diffs
+            .get(&claim.file_path)
+            .ok_or_else(|| anyhow!("{} isn't changed by {branch}", claim.file_path.display()))?;
+        for claimed in &claim.hunks {
+            let exists = file_diff.hunks.iter().any(|hunk| {
+                claimed.start == hunk.new_start && claimed.end == hunk.new_start + hunk.new_lines
+            });
+            if !exists {
+                bail!(
+                    "hunk {}:{claimed} isn't part of the changes of {branch}",
+                    claim.file_path.display()
+                );
+            }
+        }
+    }
+    let selected_tree_id = gitbutler_diff::write::tree_with_selected_hunks(
+        repo,
+        &base_tree,
+        &head_tree,
+        &diffs,
+        |path, hunk| {
+            selection
+                .claims
+                .iter()
+                .filter(|claim| claim.file_path == path)
+                .any(|claim| {
+                    claim.hunks.is_empty()
+                        || claim.hunks.iter().any(|claimed| {
+                            claimed.start == hunk.new_start
+                                && claimed.end == hunk.new_start + hunk.new_lines
+                        })
+                })
+        },
+    )?;
+    let selected_tree = repo.find_tree(selected_tree_id)?;
+
+    let mut worktree_index = repo.index()?;
+    worktree_index.add_all(["."], git2::IndexAddOption::DEFAULT, None)?;
+    let worktree_tree = repo.find_tree(worktree_index.write_tree()?)?;
+    let mut merge_index = repo.merge_trees(&base_tree, &worktree_tree, &selected_tree, None)?;
+    if merge_index.has_conflicts() {
+        bail!("the selected changes of {branch} conflict with the workspace");
+    }
+    let merged_tree = repo.find_tree(merge_index.write_tree_to(repo)?)?;
+    repo.checkout_tree_builder(&merged_tree)
+        .force()
+        .checkout()
+        .context("failed to checkout tree")?;
+
+    let name = branch
+        .branch()
+        .map_or_else(|| branch.to_string(), ToOwned::to_owned);
+    ctx.branch_manager()
+        .without_snapshots()
+        .create_virtual_branch_for_new_changes(name, perm)
+}
@@ -0,0 +1,100 @@
+//! Check out and diff only the directories of the worktree that are worked on, for repositories too
+//! large to handle as a whole, and check out more of them on demand.
+//!
+//! This is a [sparse checkout](gitbutler_repo::sparse_checkout) in cone mode, which the status only
+//! diffs the checked out part of.
+use std::{collections::BTreeSet, path::PathBuf};
+
+use anyhow::Result;
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_repo::sparse_checkout;
+use serde::Serialize;
+
+use crate::{integration::get_workspace_head, workdir_cache::workdir_diff};
+
+/// Which part of the worktree is checked out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialCheckout {
+    /// `true` if only part of the worktree is checked out.
+    pub enabled: bool,
+    /// The worktree-relative directories that are checked out with everything below them, in addition
+    /// to the files directly inside their parents and the root of the worktree.
+    pub directories: Vec<PathBuf>,
+}
+
+pub(crate) fn partial_checkout(ctx: &CommandContext) -> Result<PartialCheckout> {
+    let directories = sparse_checkout::directories(ctx.repository())?;
+    Ok(PartialCheckout {
+        enabled: directories.is_some(),
+        directories: directories.unwrap_or_default(),
+    })
+}
+
+/// Check out only `directories`, along with the directories of all files that have uncommitted changes
+/// so none of them are hidden from the status.
+pub(crate) fn enable(
+    ctx: &CommandContext,
+    directories: &[PathBuf],
+    _perm: &mut WorktreeWritePermission,
+) -> Result<PartialCheckout> {
+    let mut to_check_out: BTreeSet<_> = directories.iter().cloned().collect();
+    let changes = workdir_diff(ctx, get_workspace_head(ctx)?)?;
+    to_check_out.extend(changes.keys().filter_map(|path| parent_directory(path)));
+    sparse_checkout::set(ctx, &Vec::from_iter(to_check_out))?;
+    partial_checkout(ctx)
+}
+
+/// Check out `paths` in addition to what is checked out already, with directories being checked out
+/// with everything below them, and files along with the directory they are in.
+///
+/// Does nothing if the whole worktree is checked out.
+pub(crate) fn hydrate(
+    ctx: &CommandContext,
+    paths: &[PathBuf],
+    _perm: &mut WorktreeWritePermission,
+) -> Result<PartialCheckout> {
+    if sparse_checkout::directories(ctx.repository())?.is_none() {
+        return partial_checkout(ctx);
+    }
+    let tree = ctx
+        .repository()
+        .find_commit(get_workspace_head(ctx)?)?
+        .tree()?;
+    let directories: BTreeSet<_> = paths
+        .iter()
+        .filter_map(|path| {
+            let is_directory = tree
+                .get_path(path)
+                .map_or(false, |entry| entry.kind() == Some(git2::ObjectType::Tree));
+            if is_directory {
+                Some(path.clone())
+            } else {
+                parent_directory(path)
+            }
+        })
+        .collect();
+    if !directories.is_empty() {
+        sparse_checkout::add(ctx, &Vec::from_iter(directories))?;
+    }
+    partial_checkout(ctx)
+}
+
+/// Check out the whole worktree again.
+pub(crate) fn disable(
+    ctx: &CommandContext,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<PartialCheckout> {
+    if sparse_checkout::directories(ctx.repository())?.is_some() {
+        sparse_checkout::disable(ctx)?;
+    }
+    partial_checkout(ctx)
+}
+
+/// Return the directory `path` is in, or `None` if it's in the root of the worktree, which is always checked out.
+fn parent_directory(path: &std::path::Path) -> Option<PathBuf> {
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map(ToOwned::to_owned)
+}
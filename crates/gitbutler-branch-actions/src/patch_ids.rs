@@ -0,0 +1,53 @@
+//! Find commits that make the same changes as others, by comparing their patch ids like `git cherry` does.
+//!
+//! This detects commits of a branch that were integrated into the target with new ids, like when the target
+//! rebased or squashed them, so they don't end up in the branch twice.
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+/// The commits that were added to the target, by their patch id.
+#[derive(Debug)]
+pub(crate) struct PatchIdIndex {
+    commits: HashMap<git2::Oid, git2::Oid>,
+}
+
+impl PatchIdIndex {
+    /// Index the commits that are reachable from `tip`, but not from `exclude`.
+    pub(crate) fn new(repo: &git2::Repository, tip: git2::Oid, exclude: git2::Oid) -> Result<Self> {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(tip)?;
+        revwalk.hide(exclude)?;
+        let mut commits = HashMap::new();
+        for id in revwalk {
+            let commit = repo.find_commit(id?)?;
+            if let Some(patch_id) = patch_id(repo, &commit)? {
+                commits.entry(patch_id).or_insert(commit.id());
+            }
+        }
+        Ok(PatchIdIndex { commits })
+    }
+
+    /// Return the indexed commit that makes the same changes as `commit`, if there is one.
+    pub(crate) fn find(
+        &self,
+        repo: &git2::Repository,
+        commit: &git2::Commit<'_>,
+    ) -> Result<Option<git2::Oid>> {
+        Ok(patch_id(repo, commit)?.and_then(|patch_id| self.commits.get(&patch_id).copied()))
+    }
+}
+
+/// Return the patch id of the changes `commit` makes to its first parent, or `None` if it doesn't change
+/// anything, as commits without changes would all be the same.
+fn patch_id(repo: &git2::Repository, commit: &git2::Commit<'_>) -> Result<Option<git2::Oid>> {
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    if diff.deltas().len() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(diff.patchid(None)?))
+}
@@ -0,0 +1,397 @@
+//! Apply patches onto a virtual branch, like the `.patch` or `.diff` files reviewers receive, or the mailboxes
+//! `git format-patch` writes with one email for each commit.
+//!
+//! Each email becomes a commit with the author, date and message it carries, like with `git am`. Plain diffs
+//! become a single commit by the current user. Patches that don't apply as they are can be merged with the
+//! branch in three ways, with the files the patch was made against as common ancestor, if they are in the
+//! repository, like `git am -3` does.
+use std::{path::PathBuf, time::SystemTime};
+
+use anyhow::{anyhow, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_repo::{RepoActionsExt, RepositoryExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{cherry_pick::set_branch_head, conflicts::RepoConflictsExt, VirtualBranchesExt};
+
+const DEFAULT_MESSAGE: &str = "Apply patch";
+
+/// The most lines the signature at the end of an email may have, including the `-- ` line.
+const MAX_SIGNATURE_LINES: usize = 4;
+
+/// How to import patches.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPatchOptions {
+    /// The message of the commit of a plain diff, which carries none, or `Apply patch` if unset.
+    pub message: Option<String>,
+    /// Merge patches that don't apply as they are in three ways, instead of stopping at them.
+    #[serde(default)]
+    pub three_way: bool,
+}
+
+/// What [importing](import_patch()) patches did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchImportOutcome {
+    /// The ids of the new commits on the branch, one for each patch that was applied, in order.
+    #[serde(with = "gitbutler_serde::oid_vec")]
+    pub commits: Vec<git2::Oid>,
+    /// The patch that couldn't be applied, if any. It and the patches after it weren't applied.
+    pub conflict: Option<PatchConflict>,
+    /// The amount of patches after the conflicting one.
+    pub skipped: usize,
+}
+
+/// A patch that couldn't be applied to a branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchConflict {
+    /// The first line of the message of the patch.
+    pub subject: String,
+    /// The files that conflicted, or are changed by the patch if it couldn't be merged in three ways at all.
+    pub paths: Vec<PathBuf>,
+    /// Why the patch couldn't be applied.
+    pub reason: String,
+}
+
+/// A patch to turn into a commit.
+struct Patch<'a> {
+    /// The author of the patch, or `None` if it doesn't say.
+    author: Option<git2::Signature<'static>>,
+    /// The commit message, or `None` if the patch carries none.
+    message: Option<String>,
+    diff: &'a [u8],
+}
+
+/// Apply the patches in `patch`, which is a unified diff or a mailbox with one email for each patch, as
+/// commits onto the applied branch identified by `branch_id`, as configured by `options`.
+///
+/// Importing stops at the first patch that doesn't apply, which is then reported, while the patches before it
+/// stay applied.
+pub(crate) fn import_patch(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    patch: &[u8],
+    options: &ImportPatchOptions,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<PatchImportOutcome> {
+    ctx.assure_resolved()?;
+    let patches = parse(patch)?;
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let mut branch = vb_state.get_branch_in_workspace(branch_id)?;
+    let (user, committer) = ctx.signatures()?;
+
+    let mut outcome = PatchImportOutcome {
+        commits: Vec::new(),
+        conflict: None,
+        skipped: 0,
+    };
+    let mut head = repo.find_commit(branch.head)?;
+    for (idx, patch) in patches.iter().enumerate() {
+        let message = patch
+            .message
+            .clone()
+            .or_else(|| options.message.clone())
+            .unwrap_or_else(|| DEFAULT_MESSAGE.to_owned());
+        let diff = git2::Diff::from_buffer(patch.diff)
+            .map_err(|err| anyhow!("patch '{}' can't be read: {err}", subject(&message)))
+            .context(Code::Validation)?;
+        let tree = match apply(repo, &head.tree()?, &diff, options.three_way)? {
+            Ok(tree) => tree,
+            Err((paths, reason)) => {
+                outcome.conflict = Some(PatchConflict {
+                    subject: subject(&message).to_owned(),
+                    paths,
+                    reason,
+                });
+                outcome.skipped = patches.len() - idx - 1;
+                break;
+            }
+        };
+        let new_id = repo.commit_with_signature(
+            None,
+            patch.author.as_ref().unwrap_or(&user),
+            &committer,
+            &message,
+            &repo.find_tree(tree)?,
+            &[&head],
+            None,
+        )?;
+        outcome.commits.push(new_id);
+        head = repo.find_commit(new_id)?;
+    }
+
+    if !outcome.commits.is_empty() {
+        set_branch_head(ctx, &mut branch, &head)?;
+    }
+    Ok(outcome)
+}
+
+/// Return the id of the tree `diff` turns `tree` into, or the paths that conflicted along with why.
+fn apply(
+    repo: &git2::Repository,
+    tree: &git2::Tree<'_>,
+    diff: &git2::Diff<'_>,
+    three_way: bool,
+) -> Result<std::result::Result<git2::Oid, (Vec<PathBuf>, String)>> {
+    let changed_paths = || {
+        diff.deltas()
+            .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+            .map(PathBuf::from)
+            .collect::<Vec<_>>()
+    };
+    if let Ok(mut index) = repo.apply_to_tree(tree, diff, None) {
+        return Ok(Ok(index.write_tree_to(repo)?));
+    }
+    if !three_way {
+        return Ok(Err((
+            changed_paths(),
+            "the patch doesn't apply to the branch".to_owned(),
+        )));
+    }
+
+    // The common ancestor is the branch with the files the patch was made against.
+    let mut ancestor = git2::build::TreeUpdateBuilder::new();
+    for delta in diff.deltas() {
+        let old = delta.old_file();
+        let Some(path) = old.path() else {
+            continue;
+        };
+        if delta.status() == git2::Delta::Added {
+            ancestor.remove(path);
+            continue;
+        }
+        match find_blob(repo, old.id()) {
+            Some(blob) => {
+                ancestor.upsert(path, blob, old.mode());
+            }
+            None => {
+                return Ok(Err((
+                    changed_paths(),
+                    format!(
+                        "the patch doesn't apply to the branch, and the version of {} it was made against \
+                         isn't in the repository",
+                        path.display()
+                    ),
+                )))
+            }
+        }
+    }
+    let ancestor = repo.find_tree(ancestor.create_updated(repo, tree)?)?;
+    let theirs = match repo.apply_to_tree(&ancestor, diff, None) {
+        Ok(mut index) => repo.find_tree(index.write_tree_to(repo)?)?,
+        Err(_) => {
+            return Ok(Err((
+                changed_paths(),
+                "the patch doesn't apply to the files it was made against".to_owned(),
+            )))
+        }
+    };
+    let mut index = repo.merge_trees(&ancestor, tree, &theirs, None)?;
+    if index.has_conflicts() {
+        let mut paths = Vec::new();
+        for conflict in index.conflicts()? {
+            let conflict = conflict?;
+            if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+                paths.push(PathBuf::from(entry.path.to_str_lossy().into_owned()));
+            }
+        }
+        return Ok(Err((
+            paths,
+            "the patch conflicts with the branch".to_owned(),
+        )));
+    }
+    Ok(Ok(index.write_tree_to(repo)?))
+}
+
+/// Return the blob `id` refers to, which may be abbreviated as patches usually do, or `None` if it isn't in
+/// `repo`.
+fn find_blob(repo: &git2::Repository, id: git2::Oid) -> Option<git2::Oid> {
+    if id.is_zero() {
+        return None;
+    }
+    if let Ok(blob) = repo.find_blob(id) {
+        return Some(blob.id());
+    }
+    // Abbreviated ids are padded with zeros, which are dropped to look up the prefix.
+    let hex = id.to_string();
+    let prefix = hex.trim_end_matches('0');
+    let prefix = &hex[..prefix.len().max(7)];
+    repo.revparse_single(prefix)
+        .ok()?
+        .peel_to_blob()
+        .ok()
+        .map(|blob| blob.id())
+}
+
+/// Split `input` into the patches it contains, which are the emails of a mailbox, or a single plain diff.
+fn parse(input: &[u8]) -> Result<Vec<Patch<'_>>> {
+    let lines: Vec<&[u8]> = input.lines_with_terminator().collect();
+    let is_separator = |idx: usize| {
+        lines[idx].starts_with(b"From ")
+            && lines
+                .get(idx + 1)
+                .is_some_and(|next| is_header(next.trim_end()))
+    };
+    let starts: Vec<usize> = (0..lines.len()).filter(|idx| is_separator(*idx)).collect();
+    let patches = if starts.is_empty() {
+        if is_header(lines.first().map_or(&[][..], |line| line.trim_end())) {
+            vec![parse_email(input)?]
+        } else {
+            vec![Patch {
+                author: None,
+                message: None,
+                diff: input,
+            }]
+        }
+    } else {
+        let offset = |line: usize| lines[..line].iter().map(|line| line.len()).sum::<usize>();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(nth, start)| {
+                let end = starts
+                    .get(nth + 1)
+                    .map_or(input.len(), |next| offset(*next));
+                // The separator line is skipped, which leaves the headers.
+                parse_email(&input[offset(*start + 1)..end])
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+    // Emails without changes, like cover letters, don't become commits.
+    let patches: Vec<_> = patches
+        .into_iter()
+        .filter(|patch| {
+            patch
+                .diff
+                .lines()
+                .any(|line| line.starts_with(b"diff --git ") || line.starts_with(b"+++ "))
+        })
+        .collect();
+    if patches.is_empty() {
+        return Err(anyhow!("there are no changes in the patch")).context(Code::Validation);
+    }
+    Ok(patches)
+}
+
+/// Return `true` if `line` looks like the header of an email, like `From: A U Thor <author@example.com>`.
+fn is_header(line: &[u8]) -> bool {
+    line.find(": ").is_some_and(|colon| {
+        colon > 0
+            && line[..colon]
+                .iter()
+                .all(|byte| byte.is_ascii_alphanumeric() || *byte == b'-')
+    })
+}
+
+/// Read the patch from the headers and body of an email as `git format-patch` writes it.
+fn parse_email(email: &[u8]) -> Result<Patch<'_>> {
+    let (headers, body) = match email.find("\n\n") {
+        Some(end) => (&email[..end], &email[end + 2..]),
+        None => (email, &[][..]),
+    };
+    // Headers continue on lines that start with whitespace.
+    let mut header_values: Vec<(String, String)> = Vec::new();
+    for line in headers.lines() {
+        let line = line.to_str_lossy();
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = header_values.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(": ") {
+            header_values.push((name.to_ascii_lowercase(), value.trim().to_owned()));
+        }
+    }
+    let header = |name: &str| {
+        header_values
+            .iter()
+            .find(|(known, _)| known == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    // The message ends where the diffstat or the diff starts.
+    let diff_start = body
+        .find("\ndiff --git ")
+        .map(|idx| idx + 1)
+        .or_else(|| body.starts_with(b"diff --git ").then_some(0))
+        .or_else(|| body.find("\n--- ").map(|idx| idx + 1))
+        .unwrap_or(body.len());
+    let mut description = &body[..diff_start];
+    let mut offset = 0;
+    for line in description.lines_with_terminator() {
+        if line.trim_end() == b"---" {
+            description = &description[..offset];
+            break;
+        }
+        offset += line.len();
+    }
+    // The signature that ends the email, `-- ` as Git writes it or `--` as libgit2 does, isn't part of
+    // the diff.
+    let mut diff = &body[diff_start..];
+    if let Some(idx) = diff.rfind("\n-- \n").or_else(|| diff.rfind("\n--\n")) {
+        if diff[idx + 1..].lines().count() <= MAX_SIGNATURE_LINES {
+            diff = &diff[..idx + 1];
+        }
+    }
+
+    let subject = strip_subject_prefix(header("subject").unwrap_or_default());
+    let description = description.to_str_lossy();
+    let message = match description.trim() {
+        "" => subject.to_owned(),
+        description => format!("{subject}\n\n{description}\n"),
+    };
+    Ok(Patch {
+        author: header("from")
+            .map(|from| signature(from, header("date")))
+            .transpose()?,
+        message: (!message.trim().is_empty()).then_some(message),
+        diff,
+    })
+}
+
+/// Return the author in `from`, like `A U Thor <author@example.com>`, who wrote the patch at `date`, or now
+/// if it isn't set or can't be read.
+fn signature(from: &str, date: Option<&str>) -> Result<git2::Signature<'static>> {
+    let (name, email) = match from.rsplit_once('<') {
+        Some((name, email)) => (
+            name.trim().trim_matches('"'),
+            email.trim_end().trim_end_matches('>'),
+        ),
+        None => ("", from.trim()),
+    };
+    let name = if name.is_empty() { email } else { name };
+    let time = date
+        .and_then(|date| gix::date::parse(date, Some(SystemTime::now())).ok())
+        .unwrap_or_else(gitbutler_branch::current_time);
+    git2::Signature::new(
+        name,
+        email,
+        &git2::Time::new(time.seconds, time.offset / 60),
+    )
+    .with_context(|| format!("'{from}' isn't a valid author"))
+    .context(Code::Validation)
+}
+
+/// Return `subject` without the prefixes `git format-patch` adds, like `[PATCH 1/2]`.
+fn strip_subject_prefix(subject: &str) -> &str {
+    let mut subject = subject.trim();
+    while let Some(rest) = subject.strip_prefix('[') {
+        match rest.split_once(']') {
+            Some((_, rest)) => subject = rest.trim_start(),
+            None => break,
+        }
+    }
+    subject
+}
+
+/// Return the first line of `message`.
+fn subject(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
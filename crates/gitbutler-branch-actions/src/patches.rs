@@ -0,0 +1,185 @@
+//! Export the commits of a virtual branch as patches like `git format-patch` does, so branches can be sent by
+//! email or attached to review systems that don't use pull requests.
+//!
+//! Each commit becomes an email, numbered like `[PATCH 2/3]`, which `git am` applies. The description of the
+//! branch, if it has one, becomes the cover letter, `[PATCH 0/3]`, along with a summary of the commits.
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_repo::{LogUntil, RepoActionsExt};
+use serde::{Deserialize, Serialize};
+
+use crate::VirtualBranchesExt;
+
+/// The most characters of the summary of a commit that make it into the name of its patch file, as with Git.
+const MAX_FILE_NAME_SUMMARY: usize = 52;
+
+/// How patches are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PatchFormat {
+    /// One file for each patch, like `0001-add-a-feature.patch`, with the cover letter in
+    /// `0000-cover-letter.patch`.
+    Files,
+    /// All patches in a single mailbox file named after the branch, like `git format-patch --stdout` writes.
+    Mbox,
+}
+
+/// Write the commits of the virtual branch identified by `branch_id` that aren't on the target as patches into
+/// `dir`, which is created if needed, and return the paths of the written files.
+pub(crate) fn export_patches(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    dir: &Path,
+    format: PatchFormat,
+) -> Result<Vec<PathBuf>> {
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let branch = vb_state.get_branch(branch_id)?;
+    let target = vb_state.get_default_target()?;
+    let base = repo.merge_base(target.sha, branch.head)?;
+    let mut commits = ctx.log(branch.head, LogUntil::Commit(base))?;
+    commits.reverse();
+    if commits.is_empty() {
+        return Err(anyhow!("branch '{}' has no commits to export", branch.name))
+            .context(Code::Validation);
+    }
+
+    let count = commits.len();
+    let mut patches = Vec::with_capacity(count + 1);
+    if !branch.description.trim().is_empty() {
+        patches.push((
+            "0000-cover-letter.patch".to_owned(),
+            cover_letter(ctx, &branch.name, &branch.description, base, &commits)?,
+        ));
+    }
+    for (index, commit) in commits.iter().enumerate() {
+        let number = index + 1;
+        let summary = commit.summary().unwrap_or_default();
+        let body = commit.body().unwrap_or_default();
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+        let email = git2::Email::from_diff(
+            &diff,
+            number,
+            count,
+            &commit.id(),
+            summary,
+            body,
+            &commit.author(),
+            &mut git2::EmailCreateOptions::new(),
+        )
+        .with_context(|| format!("failed to create the patch of commit {}", commit.id()))?;
+        patches.push((patch_file_name(number, summary), email.as_slice().to_vec()));
+    }
+
+    std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let files = match format {
+        PatchFormat::Files => patches
+            .into_iter()
+            .map(|(name, content)| {
+                let path = dir.join(name);
+                std::fs::write(&path, content)
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+                Ok(path)
+            })
+            .collect::<Result<Vec<_>>>()?,
+        PatchFormat::Mbox => {
+            let path = dir.join(format!("{}.mbox", file_name_slug(&branch.name)));
+            let mbox: Vec<u8> = patches
+                .into_iter()
+                .flat_map(|(_, content)| content)
+                .collect();
+            std::fs::write(&path, mbox)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            vec![path]
+        }
+    };
+    Ok(files)
+}
+
+/// Write the cover letter for the series of `commits`, which are on top of `base`, introducing them with
+/// `description`.
+fn cover_letter(
+    ctx: &CommandContext,
+    branch_name: &str,
+    description: &str,
+    base: git2::Oid,
+    commits: &[git2::Commit<'_>],
+) -> Result<Vec<u8>> {
+    let repo = ctx.repository();
+    let (author, _) = ctx.signatures()?;
+    let date = gitbutler_branch::current_time().format(gix::date::time::format::RFC2822);
+
+    // Like `git shortlog`, the summaries are grouped by author, in the order they first appear.
+    let mut shortlog: Vec<(String, Vec<&str>)> = Vec::new();
+    for commit in commits {
+        let name = String::from_utf8_lossy(commit.author().name_bytes()).into_owned();
+        let summary = commit.summary().unwrap_or_default();
+        match shortlog.iter_mut().find(|(known, _)| *known == name) {
+            Some((_, summaries)) => summaries.push(summary),
+            None => shortlog.push((name, vec![summary])),
+        }
+    }
+
+    let head = commits.last().expect("there is at least one commit");
+    let diff = repo.diff_tree_to_tree(
+        Some(&repo.find_commit(base)?.tree()?),
+        Some(&head.tree()?),
+        None,
+    )?;
+    let stats = diff.stats()?.to_buf(git2::DiffStatsFormat::FULL, 72)?;
+
+    let mut letter = format!(
+        "From {zero} Mon Sep 17 00:00:00 2001\n\
+         From: {name} <{email}>\n\
+         Date: {date}\n\
+         Subject: [PATCH 0/{count}] {branch_name}\n\
+         MIME-Version: 1.0\n\
+         Content-Type: text/plain; charset=UTF-8\n\
+         Content-Transfer-Encoding: 8bit\n\
+         \n\
+         {description}\n\n",
+        zero = git2::Oid::zero(),
+        name = String::from_utf8_lossy(author.name_bytes()),
+        email = String::from_utf8_lossy(author.email_bytes()),
+        count = commits.len(),
+        description = description.trim_end(),
+    );
+    for (name, summaries) in shortlog {
+        letter.push_str(&format!("{name} ({}):\n", summaries.len()));
+        for summary in summaries {
+            letter.push_str(&format!("  {summary}\n"));
+        }
+        letter.push('\n');
+    }
+    letter.push_str(stats.as_str().unwrap_or_default());
+    letter.push_str("\n-- \nGitButler\n\n");
+    Ok(letter.into_bytes())
+}
+
+/// Return the name of the file of the patch `number` with `summary`, like `0001-add-a-feature.patch`.
+fn patch_file_name(number: usize, summary: &str) -> String {
+    format!("{number:04}-{}.patch", file_name_slug(summary))
+}
+
+/// Return `text` with everything but ASCII letters, digits, `.` and `_` turned into single dashes, and no
+/// longer than file names of patches can be.
+fn file_name_slug(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.chars().take(MAX_FILE_NAME_SUMMARY).collect();
+    slug.trim_end_matches(['-', '.']).to_owned()
+}
@@ -0,0 +1,113 @@
+//! Base virtual branches on a fixed commit, like a release tag, instead of the moving target.
+use anyhow::{bail, Context, Result};
+use gitbutler_branch::{Branch, BranchEventKind, BranchId};
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_repo::rebase::cherry_rebase;
+
+use crate::{conflicts::RepoConflictsExt, r#virtual::record_branch_event, VirtualBranchesExt};
+
+/// Rebase the commits of the branch identified by `branch_id` onto `base`, and keep it there when the
+/// target is updated. Stacked branches are taken off their parent this way.
+pub(crate) fn pin_base(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    base: git2::Oid,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    ctx.assure_resolved()?;
+    ctx.repository()
+        .find_commit(base)
+        .with_context(|| format!("commit {base} not found"))?;
+    let mut branch = ctx
+        .project()
+        .virtual_branches()
+        .get_branch_in_workspace(branch_id)?;
+    rebase_onto(ctx, &mut branch, base)?;
+    branch.pinned_base = Some(base);
+    branch.parent = None;
+    save(ctx, branch)
+}
+
+/// Rebase the commits of the pinned branch identified by `branch_id` onto the target, and let it follow
+/// the target again. Stacked branches are taken off their parent this way.
+pub(crate) fn rebase_onto_target(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    ctx.assure_resolved()?;
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    let mut branch = vb_state.get_branch_in_workspace(branch_id)?;
+    if branch.pinned_base.is_none() {
+        bail!("branch {} isn't pinned to a base", branch.name);
+    }
+    rebase_onto(ctx, &mut branch, target.sha)?;
+    branch.pinned_base = None;
+    branch.parent = None;
+    save(ctx, branch)
+}
+
+/// Move the commits of `branch` from its current base onto `new_base`, making sure the result still
+/// merges cleanly into the workspace.
+pub(crate) fn rebase_onto(
+    ctx: &CommandContext,
+    branch: &mut Branch,
+    new_base: git2::Oid,
+) -> Result<()> {
+    let repo = ctx.repository();
+    let target = ctx.project().virtual_branches().get_default_target()?;
+    let old_base = branch.base(target.sha);
+    if old_base == new_base {
+        return Ok(());
+    }
+    let has_commits = branch.head != old_base;
+    if has_commits && branch.upstream.is_some() && !branch.allow_rebasing {
+        bail!(
+            "branch {} was pushed and doesn't allow rebasing",
+            branch.name
+        );
+    }
+
+    let new_head = if has_commits {
+        cherry_rebase(ctx, new_base, old_base, branch.head)
+            .with_context(|| format!("the commits of {} don't apply onto {new_base}", branch.name))?
+            .unwrap_or(new_base)
+    } else {
+        new_base
+    };
+
+    let new_base_tree = repo.find_commit(new_base)?.tree()?;
+    let target_tree = repo.find_commit(target.sha)?.tree()?;
+    let new_head_tree = repo.find_commit(new_head)?.tree()?;
+    if repo
+        .merge_trees(&new_base_tree, &target_tree, &new_head_tree, None)?
+        .has_conflicts()
+    {
+        bail!(
+            "the commits of {} conflict with the target when based on {new_base}",
+            branch.name
+        );
+    }
+
+    if has_commits {
+        record_branch_event(
+            ctx,
+            branch.id,
+            BranchEventKind::Rebased {
+                old_head: branch.head,
+                new_head,
+            },
+        );
+    }
+    branch.head = new_head;
+    Ok(())
+}
+
+fn save(ctx: &CommandContext, branch: Branch) -> Result<()> {
+    let vb_state = ctx.project().virtual_branches();
+    vb_state.set_branch(branch)?;
+    crate::integration::update_gitbutler_integration(&vb_state, ctx)?;
+    Ok(())
+}
@@ -0,0 +1,244 @@
+//! Search all projects for a commit, a branch or a change, for when it's not known which repository
+//! contains it.
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_branch::{Branch, BranchId};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::{Project, ProjectId};
+use gitbutler_repo::{LogUntil, RepoActionsExt};
+use serde::Serialize;
+
+use crate::VirtualBranchesExt;
+
+/// The shortest abbreviated commit id that is looked up, as shorter ones are likely to be ambiguous.
+const MIN_COMMIT_ID_LEN: usize = 7;
+
+/// The most matches to report for a single project, as common snippets would match countless changes.
+const MAX_MATCHES_PER_PROJECT: usize = 100;
+
+/// Something in a project that matched a search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMatch {
+    pub project_id: ProjectId,
+    pub project_title: String,
+    pub location: SearchLocation,
+}
+
+/// Where in a project a search matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum SearchLocation {
+    /// The search is the id of a commit, which is contained in these branches.
+    #[serde(rename_all = "camelCase")]
+    Commit {
+        #[serde(with = "gitbutler_serde::oid")]
+        commit_id: git2::Oid,
+        summary: String,
+        /// The names of the Git branches and the virtual branches whose history contains the commit.
+        branches: Vec<String>,
+    },
+    /// The name of a virtual branch contains the search.
+    #[serde(rename_all = "camelCase")]
+    VirtualBranch { branch_id: BranchId, name: String },
+    /// The name of a local or remote Git branch contains the search.
+    #[serde(rename_all = "camelCase")]
+    GitBranch { name: String, remote: bool },
+    /// A line added by a virtual branch contains the search.
+    #[serde(rename_all = "camelCase")]
+    Change {
+        branch_id: BranchId,
+        branch_name: String,
+        /// The commit that added the line, or `None` if it isn't committed yet.
+        #[serde(with = "gitbutler_serde::oid_opt")]
+        commit_id: Option<git2::Oid>,
+        /// The path of the file with the line, relative to the worktree.
+        path: PathBuf,
+        line: String,
+    },
+}
+
+/// Search `projects` for `query`, which matches commits it's an id of, virtual and Git branches whose name contains
+/// it, and lines added by the commits and uncommitted changes of virtual branches that contain it.
+/// Branch names and lines are matched regardless of case.
+///
+/// Projects that can't be searched, for instance as their repository was removed, are skipped.
+pub(crate) fn search_projects(projects: &[Project], query: &str) -> Result<Vec<SearchMatch>> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(anyhow!("the search can not be empty")).context(Code::Validation);
+    }
+    let mut matches = Vec::new();
+    for project in projects {
+        match search_project(project, query) {
+            Ok(locations) => matches.extend(locations.into_iter().map(|location| SearchMatch {
+                project_id: project.id,
+                project_title: project.title.clone(),
+                location,
+            })),
+            Err(err) => {
+                tracing::warn!(project_id = %project.id, ?err, "failed to search project, skipping it");
+            }
+        }
+    }
+    Ok(matches)
+}
+
+fn search_project(project: &Project, query: &str) -> Result<Vec<SearchLocation>> {
+    let ctx = CommandContext::open(project)?;
+    let branches = project.virtual_branches().list_all_branches()?;
+    let lowercase_query = query.to_lowercase();
+
+    let mut locations = Vec::new();
+    if let Some(commit) = find_commit(&ctx, query) {
+        locations.push(SearchLocation::Commit {
+            commit_id: commit.id(),
+            summary: commit.summary().unwrap_or_default().to_owned(),
+            branches: branches_containing(&ctx, &branches, commit.id())?,
+        });
+    }
+    locations.extend(
+        branches
+            .iter()
+            .filter(|branch| branch.name.to_lowercase().contains(&lowercase_query))
+            .map(|branch| SearchLocation::VirtualBranch {
+                branch_id: branch.id,
+                name: branch.name.clone(),
+            }),
+    );
+    locations.extend(
+        git_branches(ctx.repository())?
+            .into_iter()
+            .filter(|git_branch| git_branch.name.to_lowercase().contains(&lowercase_query))
+            .map(|git_branch| SearchLocation::GitBranch {
+                name: git_branch.name,
+                remote: git_branch.remote,
+            }),
+    );
+    search_changes(&ctx, &branches, &lowercase_query, &mut locations)?;
+    locations.truncate(MAX_MATCHES_PER_PROJECT);
+    Ok(locations)
+}
+
+/// Return the commit `query` is the full or abbreviated id of, if there is one.
+fn find_commit<'repo>(ctx: &'repo CommandContext, query: &str) -> Option<git2::Commit<'repo>> {
+    if query.len() < MIN_COMMIT_ID_LEN || !query.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    ctx.repository()
+        .revparse_single(query)
+        .and_then(|object| object.peel_to_commit())
+        .ok()
+}
+
+/// Return the names of the Git branches and virtual `branches` whose history contains `commit_id`.
+fn branches_containing(
+    ctx: &CommandContext,
+    branches: &[Branch],
+    commit_id: git2::Oid,
+) -> Result<Vec<String>> {
+    let repo = ctx.repository();
+    let contains = |head: git2::Oid| -> Result<bool> {
+        Ok(head == commit_id || repo.graph_descendant_of(head, commit_id)?)
+    };
+    let mut names = Vec::new();
+    for branch in branches {
+        if contains(branch.head)? {
+            names.push(branch.name.clone());
+        }
+    }
+    for git_branch in git_branches(repo)? {
+        if contains(git_branch.head)? {
+            names.push(git_branch.name);
+        }
+    }
+    Ok(names)
+}
+
+struct GitBranch {
+    name: String,
+    remote: bool,
+    head: git2::Oid,
+}
+
+/// Return the local and remote branches of `repo`, without the technical branches of GitButler like
+/// `gitbutler/integration`.
+fn git_branches(repo: &git2::Repository) -> Result<Vec<GitBranch>> {
+    let mut git_branches = Vec::new();
+    for git_branch in repo.branches(None)? {
+        let (git_branch, kind) = git_branch?;
+        let (Some(name), Some(head)) = (git_branch.name()?, git_branch.get().target()) else {
+            continue;
+        };
+        let remote = kind == git2::BranchType::Remote;
+        if !remote && name.starts_with("gitbutler/") {
+            continue;
+        }
+        git_branches.push(GitBranch {
+            name: name.to_owned(),
+            remote,
+            head,
+        });
+    }
+    Ok(git_branches)
+}
+
+/// Add the lines added by the commits and uncommitted changes of the virtual `branches` that contain
+/// `lowercase_query` to `locations`, one per hunk, until there are enough.
+fn search_changes(
+    ctx: &CommandContext,
+    branches: &[Branch],
+    lowercase_query: &str,
+    locations: &mut Vec<SearchLocation>,
+) -> Result<()> {
+    let Ok(target) = ctx.project().virtual_branches().get_default_target() else {
+        return Ok(());
+    };
+    let repo = ctx.repository();
+    for branch in branches {
+        let commits = ctx
+            .log(branch.head, LogUntil::Commit(branch.base(target.sha)))
+            .context("failed to list commits of branch")?;
+        let mut changes = vec![(
+            None,
+            repo.find_commit(branch.head)?.tree()?,
+            repo.find_tree(branch.tree)?,
+        )];
+        for commit in &commits {
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => parent.tree()?,
+                Err(_) => repo.find_tree(repo.treebuilder(None)?.write()?)?,
+            };
+            changes.push((Some(commit.id()), parent_tree, commit.tree()?));
+        }
+        for (commit_id, old_tree, new_tree) in changes {
+            for (path, diff) in gitbutler_diff::trees(repo, &old_tree, &new_tree)? {
+                for hunk in &diff.hunks {
+                    if locations.len() >= MAX_MATCHES_PER_PROJECT {
+                        return Ok(());
+                    }
+                    let Some(line) = hunk
+                        .diff_lines
+                        .lines()
+                        .filter_map(|line| line.strip_prefix(b"+"))
+                        .map(|line| line.to_str_lossy())
+                        .find(|line| line.to_lowercase().contains(lowercase_query))
+                    else {
+                        continue;
+                    };
+                    locations.push(SearchLocation::Change {
+                        branch_id: branch.id,
+                        branch_name: branch.name.clone(),
+                        commit_id,
+                        path: path.clone(),
+                        line: line.trim().to_owned(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
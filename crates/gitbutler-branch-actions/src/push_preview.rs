@@ -0,0 +1,128 @@
+//! Tell what pushing a virtual branch would upload before doing it, which matters on slow connections.
+use std::collections::HashSet;
+
+use anyhow::Result;
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_reference::RemoteRefname;
+use serde::Serialize;
+
+use crate::{r#virtual::push_target, VirtualBranchesExt};
+
+/// What pushing a virtual branch would upload, as far as it can be told from the remote branches
+/// that are known locally.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushPreview {
+    /// The remote branch that the virtual branch would be pushed to.
+    pub remote_branch: RemoteRefname,
+    /// Whether the remote branch exists already, as of the last fetch.
+    pub remote_branch_exists: bool,
+    /// Whether the remote branch has commits that the push would drop, so it has to be forced.
+    pub requires_force: bool,
+    /// The amount of commits that the remote doesn't have yet.
+    pub commits: usize,
+    /// The amount of commits, trees and blobs that the remote doesn't have yet.
+    pub objects: usize,
+    /// The size of these objects in bytes before compression. Less is sent as objects are compressed,
+    /// and may be sent as deltas to objects the remote has.
+    pub size_bytes: u64,
+}
+
+/// Return what pushing the virtual branch with `branch_id` would upload.
+///
+/// All objects reachable from the remote branches of the remote that is pushed to are assumed to be
+/// known to it, so fetching first makes the preview more accurate.
+pub(crate) fn push_preview(ctx: &CommandContext, branch_id: BranchId) -> Result<PushPreview> {
+    let vbranch = ctx
+        .project()
+        .virtual_branches()
+        .get_branch_in_workspace(branch_id)?;
+    let remote_branch = push_target(ctx, &vbranch)?;
+    let repo = ctx.repository();
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(vbranch.head)?;
+    for reference in repo.references_glob(&format!("refs/remotes/{}/*", remote_branch.remote()))? {
+        if let Some(target) = reference?.target() {
+            revwalk.hide(target)?;
+        }
+    }
+    let commits = revwalk.collect::<Result<Vec<_>, _>>()?;
+
+    let remote_head = repo
+        .find_reference(&remote_branch.to_string())
+        .ok()
+        .and_then(|reference| reference.target());
+    let requires_force = match remote_head {
+        Some(remote_head) => {
+            remote_head != vbranch.head && !repo.graph_descendant_of(vbranch.head, remote_head)?
+        }
+        None => false,
+    };
+
+    let mut objects = HashSet::new();
+    for commit_id in &commits {
+        let commit = repo.find_commit(*commit_id)?;
+        objects.insert(*commit_id);
+        // Objects the parent has are either known to the remote, or sent with the parent.
+        let parent_tree = commit
+            .parent(0)
+            .ok()
+            .map(|parent| parent.tree())
+            .transpose()?;
+        collect_new_objects(repo, &commit.tree()?, parent_tree.as_ref(), &mut objects)?;
+    }
+    let odb = repo.odb()?;
+    let mut size_bytes = 0;
+    for id in &objects {
+        size_bytes += odb.read_header(*id)?.0 as u64;
+    }
+
+    Ok(PushPreview {
+        remote_branch,
+        remote_branch_exists: remote_head.is_some(),
+        requires_force,
+        commits: commits.len(),
+        objects: objects.len(),
+        size_bytes,
+    })
+}
+
+/// Add the ids of `tree` and of all trees and blobs in it to `objects`, except for those that are at
+/// the same path in `base`.
+fn collect_new_objects(
+    repo: &git2::Repository,
+    tree: &git2::Tree,
+    base: Option<&git2::Tree>,
+    objects: &mut HashSet<git2::Oid>,
+) -> Result<()> {
+    if base.map_or(false, |base| base.id() == tree.id()) || !objects.insert(tree.id()) {
+        return Ok(());
+    }
+    for entry in tree.iter() {
+        let base_entry = entry.name().and_then(|name| base?.get_name(name));
+        if base_entry
+            .as_ref()
+            .map_or(false, |base_entry| base_entry.id() == entry.id())
+        {
+            continue;
+        }
+        match entry.kind() {
+            Some(git2::ObjectType::Tree) => {
+                let base_tree = base_entry
+                    .filter(|base_entry| base_entry.kind() == Some(git2::ObjectType::Tree))
+                    .map(|base_entry| repo.find_tree(base_entry.id()))
+                    .transpose()?;
+                let tree = repo.find_tree(entry.id())?;
+                collect_new_objects(repo, &tree, base_tree.as_ref(), objects)?;
+            }
+            Some(git2::ObjectType::Blob) => {
+                objects.insert(entry.id());
+            }
+            // Commits of submodules aren't sent along.
+            _ => {}
+        }
+    }
+    Ok(())
+}
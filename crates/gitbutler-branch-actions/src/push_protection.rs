@@ -0,0 +1,198 @@
+//! Check a push against the rules of the branch it goes to before pushing, so the UI can block it or adjust,
+//! like by signing commits, instead of having the remote reject it.
+//!
+//! The rules are asked from the forge if it's known and reachable, and otherwise read from the Git
+//! configuration of the repository:
+//!
+//! ```ini
+//! [gitbutler]
+//!     # May be given more than once, and end in `*` to match all branches starting with what's before it.
+//!     protectedBranch = main
+//!     protectedBranch = release/*
+//!     # These apply to protected branches.
+//!     requireSignedCommits = true
+//!     requireLinearHistory = true
+//! ```
+use anyhow::Result;
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_reference::RemoteRefname;
+use serde::Serialize;
+
+use crate::{
+    forge::{self, BranchProtection, ForgeRepo},
+    push_preview,
+    r#virtual::push_target,
+    VirtualBranchesExt,
+};
+
+const PROTECTED_BRANCH: &str = "gitbutler.protectedBranch";
+const REQUIRE_SIGNED_COMMITS: &str = "gitbutler.requireSignedCommits";
+const REQUIRE_LINEAR_HISTORY: &str = "gitbutler.requireLinearHistory";
+const SIGN_COMMITS: &str = "gitbutler.signCommits";
+
+/// The rules of the branch a virtual branch is pushed to, and how the push would break them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushProtectionCheck {
+    /// The remote branch that the virtual branch would be pushed to.
+    pub remote_branch: RemoteRefname,
+    pub source: ProtectionSource,
+    pub protection: BranchProtection,
+    /// The rules the push would break, which are empty if it's expected to be accepted.
+    pub warnings: Vec<PushWarning>,
+}
+
+/// Where the rules of a branch were read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProtectionSource {
+    Forge,
+    /// The Git configuration of the repository, as the forge couldn't be asked.
+    Config,
+}
+
+/// A rule of the branch that the push would break.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum PushWarning {
+    /// The branch is protected, which may mean that it can only be changed with pull requests.
+    Protected,
+    /// The push drops commits of the branch, which isn't allowed.
+    ForcePushForbidden,
+    /// Commits to push aren't signed, but have to be.
+    #[serde(rename_all = "camelCase")]
+    UnsignedCommits {
+        #[serde(with = "gitbutler_serde::oid_vec")]
+        commits: Vec<git2::Oid>,
+        /// Whether new commits are signed already, so only existing ones need to be signed again.
+        signing_enabled: bool,
+    },
+    /// Merge commits to push, which aren't allowed.
+    #[serde(rename_all = "camelCase")]
+    MergeCommits {
+        #[serde(with = "gitbutler_serde::oid_vec")]
+        commits: Vec<git2::Oid>,
+    },
+}
+
+/// Return the rules of the branch that the virtual branch with `branch_id` would be pushed to, and which of
+/// them the push would break. The forge is authenticated with `github_token` if it's GitHub and no other
+/// token is stored for it.
+pub(crate) fn check_push_protection(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    github_token: Option<&str>,
+) -> Result<PushProtectionCheck> {
+    let vbranch = ctx
+        .project()
+        .virtual_branches()
+        .get_branch_in_workspace(branch_id)?;
+    let remote_branch = push_target(ctx, &vbranch)?;
+    let repo = ctx.repository();
+
+    let (source, protection) = match forge_protection(ctx, &remote_branch, github_token) {
+        Ok(protection) => (ProtectionSource::Forge, protection),
+        Err(err) => {
+            tracing::debug!(?err, %remote_branch, "asking the forge for branch rules failed, using Git config");
+            (
+                ProtectionSource::Config,
+                config_protection(repo, remote_branch.branch())?,
+            )
+        }
+    };
+
+    let mut warnings = Vec::new();
+    if protection.protected {
+        warnings.push(PushWarning::Protected);
+    }
+    if protection.forbids_force_push && push_preview::push_preview(ctx, branch_id)?.requires_force {
+        warnings.push(PushWarning::ForcePushForbidden);
+    }
+    if protection.requires_signed_commits || protection.requires_linear_history {
+        let commits = commits_to_push(repo, vbranch.head, &remote_branch)?;
+        if protection.requires_signed_commits {
+            let unsigned: Vec<_> = commits
+                .iter()
+                .filter(|commit| repo.extract_signature(&commit.id(), None).is_err())
+                .map(git2::Commit::id)
+                .collect();
+            if !unsigned.is_empty() {
+                warnings.push(PushWarning::UnsignedCommits {
+                    commits: unsigned,
+                    signing_enabled: repo.config()?.get_bool(SIGN_COMMITS).unwrap_or(false),
+                });
+            }
+        }
+        if protection.requires_linear_history {
+            let merges: Vec<_> = commits
+                .iter()
+                .filter(|commit| commit.parent_count() > 1)
+                .map(git2::Commit::id)
+                .collect();
+            if !merges.is_empty() {
+                warnings.push(PushWarning::MergeCommits { commits: merges });
+            }
+        }
+    }
+
+    Ok(PushProtectionCheck {
+        remote_branch,
+        source,
+        protection,
+        warnings,
+    })
+}
+
+fn forge_protection(
+    ctx: &CommandContext,
+    remote_branch: &RemoteRefname,
+    github_token: Option<&str>,
+) -> Result<BranchProtection> {
+    let remote = ctx.repository().find_remote(remote_branch.remote())?;
+    let repo = ForgeRepo::from_remote_url(remote.url().unwrap_or_default())?;
+    forge::branch_protection(ctx.project(), &repo, remote_branch.branch(), github_token)
+}
+
+/// Read the rules of the branch named `branch` from the Git configuration of `repo`.
+fn config_protection(repo: &git2::Repository, branch: &str) -> Result<BranchProtection> {
+    let config = repo.config()?.snapshot()?;
+    let mut protected = false;
+    if let Ok(patterns) = config.multivar(PROTECTED_BRANCH, None) {
+        for entry in &patterns {
+            let entry = entry?;
+            let Some(pattern) = entry.value() else {
+                continue;
+            };
+            protected |= match pattern.strip_suffix('*') {
+                Some(prefix) => branch.starts_with(prefix),
+                None => branch == pattern,
+            };
+        }
+    }
+    if !protected {
+        return Ok(BranchProtection::default());
+    }
+    Ok(BranchProtection {
+        protected,
+        requires_signed_commits: config.get_bool(REQUIRE_SIGNED_COMMITS).unwrap_or(false),
+        requires_linear_history: config.get_bool(REQUIRE_LINEAR_HISTORY).unwrap_or(false),
+        forbids_force_push: true,
+    })
+}
+
+/// Return the commits reachable from `head` that no remote branch of the remote of `remote_branch` has.
+fn commits_to_push<'repo>(
+    repo: &'repo git2::Repository,
+    head: git2::Oid,
+    remote_branch: &RemoteRefname,
+) -> Result<Vec<git2::Commit<'repo>>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head)?;
+    for reference in repo.references_glob(&format!("refs/remotes/{}/*", remote_branch.remote()))? {
+        if let Some(target) = reference?.target() {
+            revwalk.hide(target)?;
+        }
+    }
+    revwalk.map(|id| Ok(repo.find_commit(id?)?)).collect()
+}
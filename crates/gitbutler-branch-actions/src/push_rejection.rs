@@ -0,0 +1,75 @@
+//! Tell which commits of a remote branch a rejected push would have dropped, so they can be looked at
+//! before deciding to force the push.
+use std::fmt;
+
+use anyhow::Result;
+use gitbutler_reference::RemoteRefname;
+use serde::Serialize;
+
+use crate::author::Author;
+
+/// A push that was rejected as the remote branch has commits that the pushed branch doesn't contain,
+/// either because the push wasn't forced or because the remote branch moved since it was last seen.
+///
+/// It's returned as error along with [`Code::PushRejected`](gitbutler_error::error::Code::PushRejected).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushRejection {
+    pub remote_branch: RemoteRefname,
+    /// Where the remote branch points to as of the last fetch, or `None` if it wasn't seen yet.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub remote_head: Option<git2::Oid>,
+    /// The commits of the remote branch that the push would overwrite, newest first.
+    pub overwritten: Vec<OverwrittenCommit>,
+}
+
+/// A commit of a remote branch that a push would overwrite.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverwrittenCommit {
+    #[serde(with = "gitbutler_serde::oid")]
+    pub id: git2::Oid,
+    pub author: Author,
+    pub summary: String,
+}
+
+impl fmt::Display for PushRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the push was rejected as {} has {} commit(s) that it would overwrite",
+            self.remote_branch,
+            self.overwritten.len()
+        )
+    }
+}
+
+impl std::error::Error for PushRejection {}
+
+/// Describe the commits of `remote_branch`, as it was fetched last, that pushing `head` would overwrite.
+pub(crate) fn describe(
+    repo: &git2::Repository,
+    head: git2::Oid,
+    remote_branch: &RemoteRefname,
+) -> Result<PushRejection> {
+    let remote_head = repo.refname_to_id(&remote_branch.to_string()).ok();
+    let mut overwritten = Vec::new();
+    if let Some(remote_head) = remote_head {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(remote_head)?;
+        revwalk.hide(head)?;
+        for id in revwalk {
+            let commit = repo.find_commit(id?)?;
+            overwritten.push(OverwrittenCommit {
+                id: commit.id(),
+                author: commit.author().into(),
+                summary: commit.summary().unwrap_or_default().to_owned(),
+            });
+        }
+    }
+    Ok(PushRejection {
+        remote_branch: remote_branch.clone(),
+        remote_head,
+        overwritten,
+    })
+}
@@ -0,0 +1,81 @@
+//! Protect commits that are on the remote branch already from being amended, reworded or squashed by
+//! accident, as pushing them again requires a force push that rewrites what collaborators may build on.
+use std::fmt;
+
+use anyhow::Result;
+use gitbutler_branch::Branch;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::PushedCommitRewrites;
+use gitbutler_reference::RemoteRefname;
+use serde::Serialize;
+
+/// Commits that are on the remote branch already and were about to be rewritten, which the
+/// [policy](PushedCommitRewrites) of the project refused.
+///
+/// It's returned as error along with [`Code::PushedCommitRewrite`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushedCommitRewrite {
+    pub remote_branch: RemoteRefname,
+    /// The commits that are on the remote branch.
+    #[serde(with = "gitbutler_serde::oid_vec")]
+    pub commits: Vec<git2::Oid>,
+    /// `true` if the commits can't be rewritten even if asked to anyway.
+    pub blocked: bool,
+}
+
+impl fmt::Display for PushedCommitRewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} commit(s) are on {} already, and rewriting them requires a force push",
+            self.commits.len(),
+            self.remote_branch
+        )?;
+        if self.blocked {
+            f.write_str(", which the project doesn't allow")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PushedCommitRewrite {}
+
+/// Fail if any of `commits` of `branch` is on its remote branch and the policy of the project refuses
+/// to rewrite it, with `rewrite_pushed` being `true` if the user asked to rewrite it anyway.
+pub(crate) fn ensure_rewritable(
+    ctx: &CommandContext,
+    branch: &Branch,
+    commits: &[git2::Oid],
+    rewrite_pushed: bool,
+) -> Result<()> {
+    let blocked = match ctx.project().pushed_commit_rewrites {
+        PushedCommitRewrites::Allow => return Ok(()),
+        PushedCommitRewrites::Warn if rewrite_pushed => return Ok(()),
+        PushedCommitRewrites::Warn => false,
+        PushedCommitRewrites::Block => true,
+    };
+    let Some(remote_branch) = &branch.upstream else {
+        return Ok(());
+    };
+    let repo = ctx.repository();
+    let Ok(remote_head) = repo.refname_to_id(&remote_branch.to_string()) else {
+        return Ok(());
+    };
+    let mut pushed = Vec::new();
+    for commit in commits {
+        if *commit == remote_head || repo.graph_descendant_of(remote_head, *commit)? {
+            pushed.push(*commit);
+        }
+    }
+    if pushed.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow::Error::from(PushedCommitRewrite {
+        remote_branch: remote_branch.clone(),
+        commits: pushed,
+        blocked,
+    })
+    .context(Code::PushedCommitRewrite))
+}
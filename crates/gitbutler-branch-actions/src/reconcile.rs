@@ -0,0 +1,271 @@
+//! Notice when Git clients like a terminal move the references of the workspace, by committing, pulling or checking
+//! out, and fold what they did into the applied branches if that's possible without losing anything, or tell
+//! about it along with the ways to recover otherwise.
+//!
+//! Nothing needs to be recorded to notice changes, as the integration reference is expected to point to an
+//! integration commit, and `HEAD` to the integration reference, while in the workspace.
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::{Branch, BranchId, GITBUTLER_INTEGRATION_REFERENCE};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_operating_modes::in_edit_mode;
+use gitbutler_oplog::{
+    entry::{OperationKind, SnapshotDetails},
+    OplogExt,
+};
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_repo::rebase::{cherry_rebase_group, find_rebase_conflicts, ConflictedCommit};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    integration::{self, GITBUTLER_INTEGRATION_COMMIT_TITLE},
+    VirtualBranchesExt,
+};
+
+/// How many commits on top of the integration commit are looked at before giving up on finding it.
+const MAX_EXTERNAL_COMMITS: usize = 1000;
+
+/// What was found when looking for changes to the references of the workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Reconciliation {
+    /// The references are where they are expected to be.
+    Unchanged,
+    /// Commits were made on top of the integration commit, and were moved onto the branch selected for changes.
+    #[serde(rename_all = "camelCase")]
+    Folded {
+        branch_id: BranchId,
+        /// The ids of the commits on the branch, the oldest first.
+        #[serde(with = "gitbutler_serde::oid_vec")]
+        commits: Vec<git2::Oid>,
+    },
+    /// The references changed in a way that can't be folded into the applied branches.
+    #[serde(rename_all = "camelCase")]
+    Detected { change: ExternalChange },
+}
+
+/// A change to the references of the workspace that needs a decision on how to recover from it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalChange {
+    pub kind: ExternalChangeKind,
+    /// The ways to [recover](crate::VirtualBranchActions::recover_from_external_change()), the one to suggest first.
+    pub recovery: Vec<RecoveryOption>,
+}
+
+/// What changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ExternalChangeKind {
+    /// Commits were made on top of the integration commit, like by `git commit` or `git pull`.
+    #[serde(rename_all = "camelCase")]
+    CommitsOnWorkspace {
+        /// The new commits, the newest first.
+        #[serde(with = "gitbutler_serde::oid_vec")]
+        commits: Vec<git2::Oid>,
+        /// Why they couldn't be moved onto a branch.
+        blocker: FoldBlocker,
+    },
+    /// The integration reference points to a commit that doesn't build on an integration commit, like after
+    /// `git reset` or `git rebase`.
+    #[serde(rename_all = "camelCase")]
+    IntegrationMoved {
+        #[serde(with = "gitbutler_serde::oid")]
+        commit: git2::Oid,
+    },
+    /// `HEAD` points to another branch than the integration branch, like after `git checkout`.
+    #[serde(rename_all = "camelCase")]
+    LeftWorkspace {
+        /// The name of the reference `HEAD` points to, or the commit if it's detached.
+        head: String,
+    },
+}
+
+/// Why commits on top of the integration commit couldn't be moved onto a branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum FoldBlocker {
+    /// There is no applied branch to move them to.
+    NoAppliedBranch,
+    /// A commit has more than one parent, like the merge commit of `git pull`.
+    #[serde(rename_all = "camelCase")]
+    MergeCommit {
+        #[serde(with = "gitbutler_serde::oid")]
+        commit: git2::Oid,
+    },
+    /// Commits conflict with the branch selected for changes.
+    #[serde(rename_all = "camelCase")]
+    Conflicts {
+        branch_id: BranchId,
+        commits: Vec<ConflictedCommit>,
+    },
+}
+
+/// A way to recover from an external change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecoveryOption {
+    /// Create the integration commit again from the applied branches and point `HEAD` to it, keeping the worktree,
+    /// so what the change did shows up as uncommitted changes.
+    RebuildWorkspace,
+    /// Move the commits on top of the integration commit onto a new branch.
+    MoveToNewBranch,
+}
+
+/// Look for changes to the references of the workspace of `ctx`, and fold commits made on top of the integration
+/// commit into the branch selected for changes if they apply to it cleanly.
+///
+/// Nothing is looked at while in edit mode, or if the integration reference doesn't exist anymore, as the
+/// workspace was left for good then.
+pub(crate) fn reconcile(
+    ctx: &CommandContext,
+    perm: &mut WorktreeWritePermission,
+) -> Result<Reconciliation> {
+    if in_edit_mode(ctx) {
+        return Ok(Reconciliation::Unchanged);
+    }
+    let repo = ctx.repository();
+    let Ok(integration) = repo.find_reference(&GITBUTLER_INTEGRATION_REFERENCE.to_string()) else {
+        return Ok(Reconciliation::Unchanged);
+    };
+    let head = repo.head().context("failed to get head")?;
+    if head.name() != integration.name() {
+        let head = if repo.head_detached()? {
+            head.target().map(|id| id.to_string()).unwrap_or_default()
+        } else {
+            head.name().unwrap_or_default().to_owned()
+        };
+        return Ok(detected(ExternalChangeKind::LeftWorkspace { head }));
+    }
+
+    let head_commit = integration.peel_to_commit()?;
+    let Some(commits) = commits_on_integration_commit(&head_commit)? else {
+        return Ok(detected(ExternalChangeKind::IntegrationMoved {
+            commit: head_commit.id(),
+        }));
+    };
+    if commits.is_empty() {
+        return Ok(Reconciliation::Unchanged);
+    }
+    let commit_ids: Vec<git2::Oid> = commits.iter().map(|commit| commit.id()).collect();
+    let blocked = |blocker| {
+        detected(ExternalChangeKind::CommitsOnWorkspace {
+            commits: commit_ids.clone(),
+            blocker,
+        })
+    };
+
+    if let Some(merge) = commits.iter().find(|commit| commit.parent_count() > 1) {
+        return Ok(blocked(FoldBlocker::MergeCommit { commit: merge.id() }));
+    }
+    let vb_state = ctx.project().virtual_branches();
+    let Some(mut branch) = selected_for_changes(vb_state.list_branches_in_workspace()?) else {
+        return Ok(blocked(FoldBlocker::NoAppliedBranch));
+    };
+    let conflicts = find_rebase_conflicts(ctx, branch.head, &commit_ids)?;
+    if !conflicts.is_empty() {
+        return Ok(blocked(FoldBlocker::Conflicts {
+            branch_id: branch.id,
+            commits: conflicts,
+        }));
+    }
+
+    let _ = ctx.project().create_snapshot(
+        SnapshotDetails::new(OperationKind::ReconcileExternalChanges),
+        perm,
+    );
+    let old_head_tree = repo.find_commit(branch.head)?.tree()?;
+    let new_head = cherry_rebase_group(ctx, branch.head, &mut commit_ids.clone())?;
+    let new_head_tree = repo.find_commit(new_head)?.tree()?;
+    // Keep the uncommitted changes of the branch, which may overlap with what was committed.
+    let mut tree = repo.merge_trees(
+        &old_head_tree,
+        &repo.find_tree(branch.tree)?,
+        &new_head_tree,
+        None,
+    )?;
+    branch.tree = if tree.has_conflicts() {
+        new_head_tree.id()
+    } else {
+        tree.write_tree_to(repo)?
+    };
+    branch.head = new_head;
+    vb_state.set_branch(branch.clone())?;
+    integration::update_gitbutler_integration(&vb_state, ctx)?;
+
+    let mut commits: Vec<_> = std::iter::successors(repo.find_commit(new_head).ok(), |commit| {
+        commit.parent(0).ok()
+    })
+    .take(commit_ids.len())
+    .map(|commit| commit.id())
+    .collect();
+    commits.reverse();
+    Ok(Reconciliation::Folded {
+        branch_id: branch.id,
+        commits,
+    })
+}
+
+/// Recover from an external change found by [`reconcile()`] with `option`.
+pub(crate) fn recover(
+    ctx: &CommandContext,
+    option: RecoveryOption,
+    perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    if in_edit_mode(ctx) {
+        return Err(anyhow!("can't recover from external changes in edit mode"))
+            .context(Code::Validation);
+    }
+    if option == RecoveryOption::MoveToNewBranch {
+        integration::verify_branch(ctx, perm)?;
+    }
+    // The integration commit is created again either way, so it has the heads of the branches as parents.
+    integration::update_gitbutler_integration(&ctx.project().virtual_branches(), ctx)?;
+    Ok(())
+}
+
+fn detected(kind: ExternalChangeKind) -> Reconciliation {
+    let recovery = match kind {
+        ExternalChangeKind::CommitsOnWorkspace { .. } => vec![
+            RecoveryOption::MoveToNewBranch,
+            RecoveryOption::RebuildWorkspace,
+        ],
+        ExternalChangeKind::IntegrationMoved { .. } | ExternalChangeKind::LeftWorkspace { .. } => {
+            vec![RecoveryOption::RebuildWorkspace]
+        }
+    };
+    Reconciliation::Detected {
+        change: ExternalChange { kind, recovery },
+    }
+}
+
+/// Return the commits on top of the integration commit that `head` is or builds on, the newest first,
+/// or `None` if there is no integration commit among its first parents.
+fn commits_on_integration_commit<'repo>(
+    head: &git2::Commit<'repo>,
+) -> Result<Option<Vec<git2::Commit<'repo>>>> {
+    let mut commits = Vec::new();
+    let mut commit = head.clone();
+    while commits.len() < MAX_EXTERNAL_COMMITS {
+        if commit
+            .message()
+            .is_some_and(|message| message.starts_with(GITBUTLER_INTEGRATION_COMMIT_TITLE))
+        {
+            return Ok(Some(commits));
+        }
+        let Ok(parent) = commit.parent(0) else {
+            break;
+        };
+        commits.push(commit);
+        commit = parent;
+    }
+    Ok(None)
+}
+
+/// Return the branch that changes go to, which is the one selected most recently.
+fn selected_for_changes(branches: Vec<Branch>) -> Option<Branch> {
+    branches
+        .into_iter()
+        .filter(|branch| branch.selected_for_changes.is_some())
+        .max_by_key(|branch| branch.selected_for_changes)
+}
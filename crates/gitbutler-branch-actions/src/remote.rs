@@ -1,13 +1,18 @@
 use std::path::Path;
 
-use crate::author::Author;
+use crate::{
+    author::{self, Author},
+    clock_skew::{self, ClockSkew},
+};
 use anyhow::{Context, Result};
 use gitbutler_branch::{ReferenceExt, Target, VirtualBranchesHandle};
 use gitbutler_command_context::CommandContext;
 use gitbutler_commit::commit_ext::CommitExt;
+use gitbutler_project::ListingFormat;
 use gitbutler_reference::{Refname, RemoteRefname};
 use gitbutler_repo::{LogUntil, RepoActionsExt, RepositoryExt};
 use gitbutler_serde::BStringForFrontend;
+use gitbutler_time::time::now_since_unix_epoch_ms;
 use serde::Serialize;
 
 // this struct is a mapping to the view `RemoteBranch` type in Typescript
@@ -51,10 +56,14 @@ pub struct RemoteCommit {
     pub id: String,
     pub description: BStringForFrontend,
     pub created_at: u128,
+    pub created_at_display: String,
     pub author: Author,
+    pub author_display: String,
     pub change_id: Option<String>,
     #[serde(with = "gitbutler_serde::oid_vec")]
     pub parent_ids: Vec<git2::Oid>,
+    /// Set if the time of the commit can't be right, like if it's in the future.
+    pub clock_skew: Option<ClockSkew>,
 }
 
 // for legacy purposes, this is still named "remote" branches, but it's actually
@@ -137,7 +146,12 @@ pub(crate) fn branch_to_remote_branch(
             .try_into()
             .map(|t: u128| t * 1000)
             .ok(),
-        last_commit_author: commit.author().name().map(std::string::ToString::to_string),
+        last_commit_author: author::resolve(
+            author::mailmap(ctx.repository()).as_ref(),
+            commit.author(),
+        )
+        .name()
+        .map(std::string::ToString::to_string),
         is_remote: branch.get().is_remote(),
     })
 }
@@ -162,6 +176,8 @@ pub(crate) fn branch_to_remote_branch_data(
                 .context("failed to get behind count")?;
 
             let fork_point = ahead.last().and_then(|c| c.parent(0).ok()).map(|c| c.id());
+            let format = &ctx.project().listing_format;
+            let mailmap = author::mailmap(ctx.repository());
 
             Ok(RemoteBranchData {
                 sha,
@@ -174,7 +190,7 @@ pub(crate) fn branch_to_remote_branch_data(
                 behind: count_behind,
                 commits: ahead
                     .into_iter()
-                    .map(|commit| commit_to_remote_commit(&commit))
+                    .map(|commit| commit_to_remote_commit(&commit, format, mailmap.as_ref()))
                     .collect::<Vec<_>>(),
                 fork_point,
             })
@@ -182,15 +198,27 @@ pub(crate) fn branch_to_remote_branch_data(
         .transpose()
 }
 
-pub(crate) fn commit_to_remote_commit(commit: &git2::Commit) -> RemoteCommit {
+pub(crate) fn commit_to_remote_commit(
+    commit: &git2::Commit,
+    format: &ListingFormat,
+    mailmap: Option<&git2::Mailmap>,
+) -> RemoteCommit {
     let parent_ids = commit.parents().map(|c| c.id()).collect();
+    let author: Author = author::resolve(mailmap, commit.author()).into();
     RemoteCommit {
         id: commit.id().to_string(),
         description: commit.message_bstr().into(),
         created_at: commit.time().seconds().try_into().unwrap(),
-        author: commit.author().into(),
+        created_at_display: format.format_time(
+            commit.time().seconds(),
+            commit.time().offset_minutes(),
+            now_since_unix_epoch_ms() / 1000,
+        ),
+        author_display: format.format_author(&author.name, &author.email),
+        author,
         change_id: commit.change_id(),
         parent_ids,
+        clock_skew: clock_skew::detect(commit, clock_skew::now()),
     }
 }
 
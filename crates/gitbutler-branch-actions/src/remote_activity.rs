@@ -0,0 +1,185 @@
+//! List remote branches with how they compare to the target, to show which ones are worth reviewing
+//! or applying.
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use gitbutler_branch::VirtualBranchesHandle;
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::ProjectId;
+use gitbutler_reference::{Refname, RemoteRefname};
+use serde::Serialize;
+
+use crate::author::{self, Author};
+
+/// The ahead and behind counts of the last listing of each project, by the tip of each branch,
+/// along with the target they were counted against.
+static CACHES: Mutex<BTreeMap<ProjectId, (git2::Oid, HashMap<git2::Oid, AheadBehind>)>> =
+    Mutex::new(BTreeMap::new());
+
+/// A remote branch and how it compares to the target.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteBranchActivity {
+    pub name: RemoteRefname,
+    #[serde(with = "gitbutler_serde::oid")]
+    pub sha: git2::Oid,
+    /// The amount of commits on the branch that aren't on the target.
+    pub ahead: usize,
+    /// The amount of commits on the target that aren't on the branch.
+    pub behind: usize,
+    pub last_commit_timestamp_ms: u128,
+    pub last_commit_author: Author,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct AheadBehind {
+    ahead: usize,
+    behind: usize,
+}
+
+/// List all remote branches except for the target branch, with the most recently changed ones first.
+///
+/// The ahead and behind counts of all branches are computed with a single walk, and are kept for
+/// branches that didn't move since the last listing so only the others have to be walked again.
+pub(crate) fn list_remote_branch_activity(
+    ctx: &CommandContext,
+) -> Result<Vec<RemoteBranchActivity>> {
+    let target = VirtualBranchesHandle::new(ctx.project().gb_dir()).get_default_target()?;
+    let repo = ctx.repository();
+
+    let mut branches = Vec::new();
+    for branch in repo
+        .branches(Some(git2::BranchType::Remote))
+        .context("failed to list remote branches")?
+    {
+        let (branch, _) = branch?;
+        // Symbolic references like `origin/HEAD` have no target of their own.
+        let Some(sha) = branch.get().target() else {
+            continue;
+        };
+        let Ok(Refname::Remote(name)) = Refname::try_from(&branch) else {
+            continue;
+        };
+        if name != target.branch {
+            branches.push((name, sha));
+        }
+    }
+
+    let mut caches = CACHES.lock().expect("no panics while holding the lock");
+    let (cached_target, counts) = caches
+        .entry(ctx.project().id)
+        .or_insert_with(|| (target.sha, HashMap::new()));
+    if *cached_target != target.sha {
+        *cached_target = target.sha;
+        counts.clear();
+    }
+    let mut uncounted: Vec<_> = branches
+        .iter()
+        .map(|(_, sha)| *sha)
+        .filter(|sha| !counts.contains_key(sha))
+        .collect();
+    uncounted.sort();
+    uncounted.dedup();
+    let new_counts = ahead_behind(repo, target.sha, &uncounted)?;
+    counts.extend(uncounted.into_iter().zip(new_counts));
+    // Forget the branches that moved or are gone.
+    counts.retain(|sha, _| branches.iter().any(|(_, tip)| tip == sha));
+
+    let mailmap = author::mailmap(repo);
+    let mut activity = branches
+        .into_iter()
+        .map(|(name, sha)| {
+            let commit = repo.find_commit(sha)?;
+            let AheadBehind { ahead, behind } = counts[&sha];
+            Ok(RemoteBranchActivity {
+                name,
+                sha,
+                ahead,
+                behind,
+                last_commit_timestamp_ms: u128::try_from(commit.time().seconds()).unwrap_or(0)
+                    * 1000,
+                last_commit_author: author::resolve(mailmap.as_ref(), commit.author()).into(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // In low-memory mode, only the counts of the current project are kept, and only if there are few.
+    let limits = gitbutler_diff::memory_limits();
+    if counts.len() > limits.max_cache_entries {
+        counts.clear();
+    }
+    if gitbutler_diff::is_low_memory() {
+        caches.retain(|project_id, _| *project_id == ctx.project().id);
+    }
+    drop(caches);
+    activity.sort_by(|a, b| {
+        b.last_commit_timestamp_ms
+            .cmp(&a.last_commit_timestamp_ms)
+            .then_with(|| a.name.to_string().cmp(&b.name.to_string()))
+    });
+    Ok(activity)
+}
+
+/// Count the commits of each of `tips` that aren't reachable from `target`, and the other way around,
+/// with a single walk through the history of all of them.
+///
+/// Each commit is marked with the tips it's reachable from, which children pass on to their parents
+/// as they are walked in topological order.
+fn ahead_behind(
+    repo: &git2::Repository,
+    target: git2::Oid,
+    tips: &[git2::Oid],
+) -> Result<Vec<AheadBehind>> {
+    let mut counts = vec![AheadBehind::default(); tips.len()];
+    if tips.is_empty() {
+        return Ok(counts);
+    }
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL)?;
+    walk.push(target)?;
+    for tip in tips {
+        walk.push(*tip)?;
+    }
+    // Commits reachable from all tips and the target count for none of them.
+    let mut all = vec![target];
+    all.extend_from_slice(tips);
+    if let Ok(base) = repo.merge_base_octopus(&all) {
+        walk.hide(base)?;
+    }
+
+    // Bit 0 marks commits reachable from the target, bit `n` those reachable from `tips[n - 1]`.
+    let words = (tips.len() + 1).div_ceil(64);
+    let mut marks: HashMap<git2::Oid, Vec<u64>> = HashMap::new();
+    for (bit, id) in std::iter::once(target)
+        .chain(tips.iter().copied())
+        .enumerate()
+    {
+        marks.entry(id).or_insert_with(|| vec![0; words])[bit / 64] |= 1 << (bit % 64);
+    }
+    let is_marked = |mark: &[u64], bit: usize| (mark[bit / 64] >> (bit % 64)) & 1 == 1;
+
+    for id in walk {
+        let id = id?;
+        let Some(mark) = marks.remove(&id) else {
+            continue;
+        };
+        let on_target = is_marked(&mark, 0);
+        for (idx, count) in counts.iter_mut().enumerate() {
+            match (is_marked(&mark, idx + 1), on_target) {
+                (true, false) => count.ahead += 1,
+                (false, true) => count.behind += 1,
+                _ => {}
+            }
+        }
+        for parent in repo.find_commit(id)?.parent_ids() {
+            let parent_mark = marks.entry(parent).or_insert_with(|| vec![0; words]);
+            for (parent_word, word) in parent_mark.iter_mut().zip(&mark) {
+                *parent_word |= word;
+            }
+        }
+    }
+    Ok(counts)
+}
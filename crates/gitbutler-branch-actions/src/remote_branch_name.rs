@@ -0,0 +1,113 @@
+//! Name the remote branches that virtual branches are pushed to for the first time after the
+//! [policy](RemoteBranchNames) of the project, and check names given by hand against it.
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::dedup_fmt;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::RemoteBranchNames;
+use gitbutler_reference::normalize_branch_name;
+
+use crate::identity::identity;
+
+const USER: &str = "{user}";
+const TITLE: &str = "{normalized-title}";
+
+/// Return the name of a new remote branch for the virtual branch named `title`, made after the policy of the
+/// project and made unique among the `existing` remote branches, which are compared in lower case.
+///
+/// Forbidden characters are replaced with `-`, and names that are too long are shortened, including the
+/// suffix that makes them unique.
+pub(crate) fn generate(ctx: &CommandContext, title: &str, existing: &[&str]) -> Result<String> {
+    let policy = &ctx.project().settings.remote_branch_names;
+    let mut rendered = policy.template.clone();
+    if rendered.contains(USER) {
+        rendered = rendered.replace(USER, &user(ctx)?);
+    }
+    rendered = rendered.replace(TITLE, &normalize_branch_name(title)?);
+    let rendered: String = rendered
+        .chars()
+        .map(|c| {
+            if policy.forbidden_characters.contains(c) {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let mut name = normalize_branch_name(&rendered)?;
+    if let Some(max_length) = policy.max_length {
+        name = shorten(&name, max_length)?;
+    }
+
+    let mut unique = dedup_fmt(existing, &name, "-");
+    if let Some(max_length) = policy.max_length {
+        let excess = unique.chars().count().saturating_sub(max_length);
+        if excess > 0 {
+            let shortened = shorten(&name, name.chars().count().saturating_sub(excess))?;
+            unique = dedup_fmt(existing, &shortened, "-");
+        }
+    }
+    validate(ctx, &unique)
+        .with_context(|| format!("the template '{}' can't be used", policy.template))?;
+    Ok(unique)
+}
+
+/// Fail with the exact violation if `name` doesn't follow the policy of the project for remote branch names.
+pub(crate) fn validate(ctx: &CommandContext, name: &str) -> Result<()> {
+    let policy = &ctx.project().settings.remote_branch_names;
+    if name.is_empty() {
+        return Err(anyhow!("the remote branch name can't be empty")).context(Code::Validation);
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| policy.forbidden_characters.contains(*c))
+    {
+        return Err(anyhow!(
+            "the remote branch name '{name}' contains the forbidden character '{c}'"
+        ))
+        .context(Code::Validation);
+    }
+    let length = name.chars().count();
+    if let Some(max_length) = policy.max_length.filter(|max_length| length > *max_length) {
+        return Err(anyhow!(
+            "the remote branch name '{name}' has {length} characters, more than the maximum of {max_length}"
+        ))
+        .context(Code::Validation);
+    }
+    let prefix = policy.template.split(TITLE).next().unwrap_or_default();
+    let prefix = if prefix.contains(USER) {
+        prefix.replace(USER, &user(ctx)?)
+    } else {
+        prefix.to_owned()
+    };
+    if !name.starts_with(&prefix) {
+        return Err(anyhow!(
+            "the remote branch name '{name}' doesn't start with '{prefix}' as the template '{}' requires",
+            policy.template
+        ))
+        .context(Code::Validation);
+    }
+    Ok(())
+}
+
+/// Return the user that `{user}` stands for, as valid branch name.
+fn user(ctx: &CommandContext) -> Result<String> {
+    let identity = identity(ctx)?;
+    let user = identity
+        .email
+        .as_deref()
+        .and_then(|email| email.split('@').next())
+        .filter(|local| !local.trim().is_empty())
+        .or(identity.name.as_deref())
+        .ok_or_else(|| anyhow!("{USER} stands for the author of commits, but neither user.email nor user.name is set"))
+        .context(Code::Validation)?;
+    normalize_branch_name(user)
+}
+
+/// Return `name` with at most `max_length` characters, without the characters that branch names can't end with.
+fn shorten(name: &str, max_length: usize) -> Result<String> {
+    if name.chars().count() <= max_length {
+        return Ok(name.to_owned());
+    }
+    normalize_branch_name(&name.chars().take(max_length).collect::<String>())
+}
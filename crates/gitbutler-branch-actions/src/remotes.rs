@@ -0,0 +1,144 @@
+//! Manage the remotes of a project along with the target and branches that refer to them,
+//! like for fork-based workflows that fetch from `upstream` and push to `origin`.
+use anyhow::{bail, Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_reference::RemoteRefname;
+
+use crate::VirtualBranchesExt;
+
+/// Push the branch identified by `branch_id` to `remote_name`, or to the push remote of the target if `None`.
+///
+/// If the branch was already pushed to another remote, it loses its upstream branch so the next push
+/// creates one on the new remote.
+pub(crate) fn set_push_remote(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    remote_name: Option<&str>,
+) -> Result<()> {
+    if let Some(remote_name) = remote_name {
+        ctx.repository()
+            .find_remote(remote_name)
+            .context(format!("failed to find remote {remote_name}"))?;
+    }
+    let vb_state = ctx.project().virtual_branches();
+    let mut branch = vb_state.get_branch_in_workspace(branch_id)?;
+    branch.push_remote_name = remote_name.map(ToOwned::to_owned);
+    let push_remote =
+        crate::r#virtual::push_remote(ctx.project(), &vb_state.get_default_target()?, &branch);
+    if branch
+        .upstream
+        .as_ref()
+        .is_some_and(|upstream| upstream.remote() != push_remote)
+    {
+        branch.upstream = None;
+        branch.upstream_head = None;
+    }
+    vb_state.set_branch(branch)
+}
+
+/// Rename the remote `old_name` to `new_name`, along with its remote tracking branches and all references
+/// to it by the target and branches.
+pub(crate) fn rename_remote(ctx: &CommandContext, old_name: &str, new_name: &str) -> Result<()> {
+    let problems = ctx
+        .repository()
+        .remote_rename(old_name, new_name)
+        .context(format!("failed to rename remote {old_name} to {new_name}"))?;
+    for problem in problems.iter().flatten() {
+        tracing::warn!(
+            refspec = problem,
+            "refspec of renamed remote wasn't updated"
+        );
+    }
+
+    let rename = |refname: &RemoteRefname| {
+        if refname.remote() == old_name {
+            RemoteRefname::new(new_name, refname.branch())
+        } else {
+            refname.clone()
+        }
+    };
+    let rename_remote_name = |remote_name: &mut Option<String>| {
+        if remote_name.as_deref() == Some(old_name) {
+            *remote_name = Some(new_name.to_owned());
+        }
+    };
+
+    let vb_state = ctx.project().virtual_branches();
+    if let Ok(mut target) = vb_state.get_default_target() {
+        target.branch = rename(&target.branch);
+        rename_remote_name(&mut target.push_remote_name);
+        vb_state.set_default_target(target)?;
+    }
+    for mut branch in vb_state.list_all_branches()? {
+        branch.upstream = branch.upstream.as_ref().map(rename);
+        rename_remote_name(&mut branch.push_remote_name);
+        vb_state.set_branch(branch)?;
+    }
+    Ok(())
+}
+
+/// Remove the remote `name` along with its remote tracking branches. Branches that were pushed to it
+/// lose their upstream branch, and those that were set to be pushed to it are pushed to the push remote of
+/// the target again.
+///
+/// It's an error if the target is fetched from the remote.
+pub(crate) fn remove_remote(ctx: &CommandContext, name: &str) -> Result<()> {
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target().ok();
+    if let Some(target) = target
+        .as_ref()
+        .filter(|target| target.branch.remote() == name)
+    {
+        bail!(
+            "remote {name} can't be removed as the target {} is fetched from it",
+            target.branch
+        );
+    }
+    ctx.repository()
+        .remote_delete(name)
+        .context(format!("failed to remove remote {name}"))?;
+
+    if let Some(mut target) =
+        target.filter(|target| target.push_remote_name.as_deref() == Some(name))
+    {
+        target.push_remote_name = None;
+        vb_state.set_default_target(target)?;
+    }
+
+    for mut branch in vb_state.list_all_branches()? {
+        let mut changed = false;
+        if branch.push_remote_name.as_deref() == Some(name) {
+            branch.push_remote_name = None;
+            changed = true;
+        }
+        if branch
+            .upstream
+            .as_ref()
+            .is_some_and(|upstream| upstream.remote() == name)
+        {
+            branch.upstream = None;
+            branch.upstream_head = None;
+            changed = true;
+        }
+        if changed {
+            vb_state.set_branch(branch)?;
+        }
+    }
+    Ok(())
+}
+
+/// Fetch from and push to `url` for the remote `name`.
+pub(crate) fn set_remote_url(ctx: &CommandContext, name: &str, url: &str) -> Result<()> {
+    ctx.repository()
+        .remote_set_url(name, url)
+        .context(format!("failed to set url of remote {name}"))?;
+    let vb_state = ctx.project().virtual_branches();
+    if let Ok(mut target) = vb_state.get_default_target() {
+        if target.branch.remote() == name {
+            target.remote_url = url.to_owned();
+            vb_state.set_default_target(target)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Undo the changes of a commit by committing their inverse onto a virtual branch.
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_repo::RepoActionsExt;
+use serde::Serialize;
+
+use crate::{
+    cherry_pick::{apply_conflicted, set_branch_head},
+    conflicts::{self, RepoConflictsExt},
+    VirtualBranchesExt,
+};
+
+/// The result of [reverting](revert_commit()) a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevertOutcome {
+    /// The id of the revert commit on the branch, or `None` if the revert conflicted.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub commit: Option<git2::Oid>,
+    /// The message of the revert commit, which is also the one to finish a conflicting revert with.
+    pub message: String,
+}
+
+/// Commit the inverse of the changes of the commit identified by `commit_id` onto the applied
+/// branch identified by `branch_id`.
+///
+/// If undoing the changes conflicts with the branch, the revert is applied to the worktree with
+/// conflict markers instead, so it can be resolved and committed with the conflict API.
+pub(crate) fn revert_commit(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    commit_id: git2::Oid,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<RevertOutcome> {
+    ctx.assure_resolved()?;
+    let repo = ctx.repository();
+    let mut branch = ctx
+        .project()
+        .virtual_branches()
+        .get_branch_in_workspace(branch_id)?;
+    let commit = repo
+        .find_commit(commit_id)
+        .with_context(|| format!("commit {commit_id} not found"))?;
+    if commit.parent_count() != 1 {
+        return Err(anyhow!("commit {commit_id} has to have exactly one parent"))
+            .context(Code::Validation);
+    }
+    let message = revert_message(&commit);
+
+    let head = repo.find_commit(branch.head)?;
+    let mut index = repo.revert_commit(&commit, &head, 0, None)?;
+    if index.has_conflicts() {
+        apply_conflicted(ctx, &commit.tree()?, &commit.parent(0)?.tree()?)?;
+        conflicts::mark_revert(ctx, commit_id)?;
+        return Ok(RevertOutcome {
+            commit: None,
+            message,
+        });
+    }
+
+    let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+    let revert_id = ctx.commit(&message, &tree, &[&head], None)?;
+    set_branch_head(ctx, &mut branch, &repo.find_commit(revert_id)?)?;
+    Ok(RevertOutcome {
+        commit: Some(revert_id),
+        message,
+    })
+}
+
+/// Return the message `git revert` would use for reverting `commit`.
+fn revert_message(commit: &git2::Commit<'_>) -> String {
+    format!(
+        "Revert \"{}\"\n\nThis reverts commit {}.\n",
+        commit.summary().unwrap_or_default(),
+        commit.id()
+    )
+}
@@ -0,0 +1,230 @@
+//! Work out how to set up a project that was just added, so the first run only needs a confirmation.
+use anyhow::{Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_reference::{LocalRefname, Refname, RemoteRefname};
+use gitbutler_repo::{
+    commit_graph, credentials::Helper, remote_default_branch, RemoteDefaultBranch, RepoActionsExt,
+    RepositoryExt,
+};
+use serde::Serialize;
+
+use crate::{
+    base::{set_base_branch, BaseBranch},
+    branch_manager::BranchManagerExt,
+};
+
+/// The remote whose default branch is proposed as target if there are several remotes.
+const PREFERRED_REMOTE: &str = "origin";
+
+/// Everything a project needs to be set up, as found in its repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupPlan {
+    pub remotes: Vec<SetupRemote>,
+    /// The branch proposed as target, or `None` if there is no remote branch to propose.
+    pub target: Option<RemoteDefaultBranch>,
+    /// The local branches with commits that the proposed target doesn't have.
+    pub branches: Vec<SetupBranch>,
+}
+
+/// A remote of the repository, and whether it could be fetched from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupRemote {
+    pub name: String,
+    pub url: Option<String>,
+    pub access: RemoteAccess,
+    /// What went wrong when fetching, unless the access was verified.
+    pub error: Option<String>,
+}
+
+/// The result of fetching from a remote with the credentials of the project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RemoteAccess {
+    Verified,
+    /// The remote was reached, but none of the credentials were accepted.
+    AuthenticationFailed,
+    /// The remote couldn't be reached at all.
+    Unreachable,
+    Failed,
+}
+
+/// A local branch that is ahead of the proposed target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupBranch {
+    pub refname: LocalRefname,
+    /// Whether the branch is checked out.
+    pub head: bool,
+    /// The amount of commits the branch has that the target doesn't.
+    pub ahead: usize,
+    /// The amount of commits the target has that the branch doesn't.
+    pub behind: usize,
+    pub import: BranchImport,
+}
+
+/// What becomes of a local branch when the project is set up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BranchImport {
+    /// It's turned into a virtual branch that is applied to the workspace.
+    Apply,
+    /// It's left as it is, as it conflicts with the target or with branches that are applied before it.
+    /// It can still be applied later.
+    Skip,
+}
+
+/// Look into the repository of `ctx` and return how it would be set up.
+///
+/// All remotes are fetched from to verify their access, which also brings their branches up to date.
+pub(crate) fn plan_setup(ctx: &CommandContext, askpass: Option<String>) -> Result<SetupPlan> {
+    let repo = ctx.repository();
+    let helper = Helper::default();
+
+    let mut remotes = Vec::new();
+    for name in repo.remotes_as_string()? {
+        let url = repo
+            .find_remote(&name)
+            .ok()
+            .and_then(|remote| remote.url().map(ToOwned::to_owned));
+        let (access, error) = match ctx.fetch(&name, &helper, askpass.clone()) {
+            Ok(()) => (RemoteAccess::Verified, None),
+            Err(err) => {
+                let access = match err.custom_context().map(|ctx| ctx.code) {
+                    Some(Code::ProjectGitAuth) => RemoteAccess::AuthenticationFailed,
+                    Some(Code::ProjectGitRemote | Code::Offline) => RemoteAccess::Unreachable,
+                    _ => RemoteAccess::Failed,
+                };
+                (access, Some(err.to_string()))
+            }
+        };
+        remotes.push(SetupRemote {
+            name,
+            url,
+            access,
+            error,
+        });
+    }
+
+    let mut remote_names: Vec<&str> = remotes.iter().map(|remote| remote.name.as_str()).collect();
+    remote_names.sort_by_key(|name| *name != PREFERRED_REMOTE);
+    let mut target = None;
+    for name in remote_names {
+        target = remote_default_branch(ctx, name, &helper)?;
+        if target.is_some() {
+            break;
+        }
+    }
+
+    let branches = match &target {
+        Some(target) => plan_branches(ctx, &target.branch)?,
+        None => vec![],
+    };
+    Ok(SetupPlan {
+        remotes,
+        target,
+        branches,
+    })
+}
+
+/// Return the local branches that are ahead of `target`, and decide which of them can be applied
+/// together, most recently changed first.
+fn plan_branches(ctx: &CommandContext, target: &RemoteRefname) -> Result<Vec<SetupBranch>> {
+    let repo = ctx.repository();
+    let target_commit = repo
+        .find_reference(&target.to_string())
+        .with_context(|| format!("remote branch {target} not found"))?
+        .peel_to_commit()?;
+    let head_name = repo
+        .head()
+        .ok()
+        .and_then(|head| head.name().map(ToOwned::to_owned));
+
+    let mut candidates = Vec::new();
+    for branch in repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.get().name().map(ToOwned::to_owned) else {
+            continue;
+        };
+        let Ok(refname) = name.parse::<LocalRefname>() else {
+            continue;
+        };
+        if refname.branch().starts_with("gitbutler/") {
+            continue;
+        }
+        let commit = branch.get().peel_to_commit()?;
+        let (ahead, behind) = commit_graph::ahead_behind(repo, commit.id(), target_commit.id())?;
+        if ahead == 0 {
+            continue;
+        }
+        candidates.push((
+            commit,
+            refname,
+            head_name.as_deref() == Some(name.as_str()),
+            ahead,
+            behind,
+        ));
+    }
+    // The checked out branch comes first, as it's always applied.
+    candidates.sort_by_key(|(commit, _, head, ..)| (!head, -commit.time().seconds()));
+
+    let mut workspace_tree = target_commit.tree()?;
+    let mut branches = Vec::new();
+    for (commit, refname, head, ahead, behind) in candidates {
+        let merge_base = repo.find_commit(commit_graph::merge_base(
+            repo,
+            commit.id(),
+            target_commit.id(),
+        )?)?;
+        let mut merge_index =
+            repo.merge_trees(&merge_base.tree()?, &workspace_tree, &commit.tree()?, None)?;
+        let clean = !merge_index.has_conflicts();
+        if clean {
+            workspace_tree = repo.find_tree(merge_index.write_tree_to(repo)?)?;
+        }
+        let import = if head || clean {
+            BranchImport::Apply
+        } else {
+            BranchImport::Skip
+        };
+        branches.push(SetupBranch {
+            refname,
+            head,
+            ahead,
+            behind,
+            import,
+        });
+    }
+    Ok(branches)
+}
+
+/// Set up the project of `ctx` with `target` as target, and turn the local branches in `import`
+/// into applied virtual branches.
+///
+/// The branch that is checked out always becomes a virtual branch if it's ahead of the target.
+pub(crate) fn set_up(
+    ctx: &CommandContext,
+    target: &RemoteRefname,
+    import: &[LocalRefname],
+    perm: &mut WorktreeWritePermission,
+) -> Result<BaseBranch> {
+    let head_name = ctx
+        .repository()
+        .head()
+        .ok()
+        .and_then(|head| head.name().map(ToOwned::to_owned));
+    let base = set_base_branch(ctx, target)?;
+    let branch_manager = ctx.branch_manager().without_snapshots();
+    for refname in import {
+        if head_name.as_deref() == Some(refname.to_string().as_str()) {
+            continue;
+        }
+        branch_manager
+            .create_virtual_branch_from_branch(&Refname::Local(refname.clone()), None, perm)
+            .with_context(|| format!("failed to import {refname}"))?;
+    }
+    Ok(base)
+}
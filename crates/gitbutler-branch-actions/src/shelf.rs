@@ -0,0 +1,194 @@
+//! Park uncommitted hunks outside of any virtual branch, similar to `git stash push -p`,
+//! and apply them again later, possibly to another branch.
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use bstr::{BString, ByteVec};
+use gitbutler_branch::{
+    BranchId, BranchOwnershipClaims, BranchUpdateRequest, OwnershipClaim, Shelf, ShelfId,
+    ShelvedFile, ShelvedHunk, ShelvesHandle,
+};
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::{ChangeType, GitHunk, Hunk};
+use gitbutler_fs::os_path;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+
+use crate::{
+    conflicts::RepoConflictsExt, get_applied_status, integration::get_workspace_head,
+    unapply_ownership, update_branch,
+};
+
+pub(crate) trait ShelvesExt {
+    fn shelves(&self) -> ShelvesHandle;
+}
+
+impl ShelvesExt for gitbutler_project::Project {
+    fn shelves(&self) -> ShelvesHandle {
+        ShelvesHandle::new(self.gb_dir())
+    }
+}
+
+/// Move the uncommitted hunks claimed by `ownership` out of the worktree and onto a new shelf named `name`.
+pub(crate) fn shelve(
+    ctx: &CommandContext,
+    name: &str,
+    ownership: &BranchOwnershipClaims,
+    perm: &mut WorktreeWritePermission,
+) -> Result<ShelfId> {
+    ctx.assure_resolved()?;
+    let base = get_workspace_head(ctx)?;
+
+    let mut files: Vec<ShelvedFile> = Vec::new();
+    for (_branch, branch_files) in get_applied_status(ctx, None)?.branches {
+        for file in branch_files {
+            let claimed: Vec<&Hunk> = ownership
+                .claims
+                .iter()
+                .filter(|claim| claim.file_path == file.path)
+                .flat_map(|claim| &claim.hunks)
+                .collect();
+            let mut hunks = file
+                .hunks
+                .into_iter()
+                .map(GitHunk::from)
+                .filter(|hunk| claimed.contains(&&Hunk::from(hunk)))
+                .map(|hunk| {
+                    ShelvedHunk::try_from(&hunk)
+                        .with_context(|| format!("failed to shelve {}", file.path.display()))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if hunks.is_empty() {
+                continue;
+            }
+            hunks.sort_by_key(|hunk| hunk.old_start);
+            files.push(ShelvedFile {
+                path: file.path,
+                hunks,
+            });
+        }
+    }
+    if files.is_empty() {
+        bail!("there are no changes to shelve");
+    }
+
+    let shelf = Shelf {
+        id: ShelfId::generate(),
+        name: name.to_owned(),
+        created_timestamp_ms: now_since_unix_epoch_ms(),
+        base,
+        files,
+    };
+    let shelves = ctx.project().shelves();
+    shelves.set(shelf.clone())?;
+
+    if let Err(err) = unapply_ownership(ctx, ownership, perm) {
+        shelves.remove(shelf.id)?;
+        return Err(err.context("failed to remove shelved changes from the worktree"));
+    }
+    Ok(shelf.id)
+}
+
+/// Apply the changes of the shelf identified by `shelf_id` to the worktree and remove the shelf.
+///
+/// If `branch_id` is set, the changes are claimed by that branch, otherwise they end up in the
+/// branch selected for changes. Nothing is changed if any file doesn't accept its changes anymore.
+pub(crate) fn unshelve(
+    ctx: &CommandContext,
+    shelf_id: ShelfId,
+    branch_id: Option<BranchId>,
+    perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    ctx.assure_resolved()?;
+    let shelves = ctx.project().shelves();
+    let shelf = shelves.get(shelf_id)?;
+    let worktree = ctx.project().worktree_path();
+
+    let mut updates: Vec<(PathBuf, Option<BString>)> = Vec::new();
+    for file in &shelf.files {
+        let full_path = worktree.join(&file.path);
+        let current = match std::fs::read(os_path::for_io(&full_path)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err).context(format!("failed to read {}", full_path.display())),
+        };
+        let mut all_diffs = BString::default();
+        for hunk in &file.hunks {
+            all_diffs.push_str(&hunk.diff);
+        }
+        let patch = diffy::Patch::from_bytes(&all_diffs)?;
+        let contents = gitbutler_diff::write::apply(&current, &patch).with_context(|| {
+            format!(
+                "the shelved changes of {} don't apply anymore",
+                file.path.display()
+            )
+        })?;
+        let deletes_file = contents.is_empty()
+            && file
+                .hunks
+                .iter()
+                .all(|hunk| hunk.change_type == ChangeType::Deleted);
+        updates.push((full_path, (!deletes_file).then_some(contents)));
+    }
+
+    for (path, contents) in updates {
+        match contents {
+            Some(contents) => os_path::write_file(&worktree, &path, contents)?,
+            None => os_path::remove_file(&path)?,
+        }
+    }
+
+    if let Some(branch_id) = branch_id {
+        claim_for_branch(ctx, &shelf, branch_id, perm)?;
+    }
+    shelves.remove(shelf_id)?;
+    Ok(())
+}
+
+/// Make the branch identified by `branch_id` claim all hunks that overlap with the changes of `shelf`.
+fn claim_for_branch(
+    ctx: &CommandContext,
+    shelf: &Shelf,
+    branch_id: BranchId,
+    perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    let statuses = get_applied_status(ctx, Some(perm))?.branches;
+    let mut ownership = statuses
+        .iter()
+        .find(|(branch, _)| branch.id == branch_id)
+        .map(|(branch, _)| branch.ownership.clone())
+        .ok_or_else(|| anyhow!("branch {branch_id} is not in the workspace"))?;
+
+    for file in &shelf.files {
+        let shelved_ranges = file
+            .hunks
+            .iter()
+            .map(|hunk| Hunk::new(hunk.new_start, hunk.new_start + hunk.new_lines, None))
+            .collect::<Result<Vec<_>>>()?;
+        let hunks: Vec<Hunk> = statuses
+            .iter()
+            .flat_map(|(_, files)| files)
+            .filter(|status| status.path == file.path)
+            .flat_map(|status| &status.hunks)
+            .map(|hunk| GitHunk::from(hunk.clone()))
+            .filter(|hunk| shelved_ranges.iter().any(|range| range.intersects(hunk)))
+            .map(|hunk| Hunk::from(&hunk))
+            .collect();
+        if !hunks.is_empty() {
+            ownership.put(OwnershipClaim {
+                file_path: file.path.clone(),
+                hunks,
+            });
+        }
+    }
+
+    update_branch(
+        ctx,
+        &BranchUpdateRequest {
+            id: branch_id,
+            ownership: Some(ownership),
+            ..Default::default()
+        },
+    )?;
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Bring files back as they were in a snapshot, as the uncommitted changes of a new virtual branch, to pick
+//! up earlier work without restoring the whole workspace.
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_oplog::OplogExt;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_repo::RepositoryExt;
+
+use crate::{branch_manager::BranchManagerExt, conflicts::RepoConflictsExt};
+
+/// The name of a branch created from a snapshot if none is given.
+const DEFAULT_BRANCH_NAME: &str = "Restored from snapshot";
+
+/// Make the files at `paths`, relative to the worktree, match their state in the snapshot `snapshot_commit_id`
+/// with all its applied branches merged, and return the id of a new virtual branch named `name` that owns
+/// the resulting changes.
+///
+/// A path can be a directory, which selects all files within it, and files that didn't exist in the
+/// snapshot are removed. This fails if any of the files has uncommitted changes, as these would be lost.
+pub(crate) fn create_virtual_branch_from_snapshot(
+    ctx: &CommandContext,
+    snapshot_commit_id: git2::Oid,
+    paths: &[PathBuf],
+    name: Option<String>,
+    perm: &mut WorktreeWritePermission,
+) -> Result<BranchId> {
+    ctx.assure_resolved()?;
+    if paths.is_empty() {
+        return Err(anyhow!("no files were selected")).context(Code::Validation);
+    }
+    for path in paths {
+        if path.as_os_str().is_empty()
+            || path
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(anyhow!(
+                "path '{}' must be relative to the worktree",
+                path.display()
+            ))
+            .context(Code::Validation);
+        }
+    }
+    let is_selected = |path: &Path| paths.iter().any(|selected| path.starts_with(selected));
+
+    let repo = ctx.repository();
+    let snapshot_tree = repo.find_tree(
+        ctx.project()
+            .snapshot_worktree_tree(snapshot_commit_id)
+            .with_context(|| format!("failed to read snapshot {snapshot_commit_id}"))?,
+    )?;
+    let head_tree = repo.head()?.peel_to_tree()?;
+    let worktree_tree = repo.get_wd_tree().context("failed to read the worktree")?;
+
+    let diffs = gitbutler_diff::trees(repo, &worktree_tree, &snapshot_tree)?;
+    let mut selected_paths = diffs.keys().filter(|path| is_selected(path)).peekable();
+    if selected_paths.peek().is_none() {
+        return Err(anyhow!(
+            "the selected files are the same as in the snapshot, so there is nothing to bring back"
+        ))
+        .context(Code::Validation);
+    }
+    for path in selected_paths {
+        let head_id = head_tree.get_path(path).ok().map(|entry| entry.id());
+        let worktree_id = worktree_tree.get_path(path).ok().map(|entry| entry.id());
+        if head_id != worktree_id {
+            return Err(anyhow!(
+                "{} has uncommitted changes, which would be overwritten",
+                path.display()
+            ))
+            .context(Code::Validation);
+        }
+    }
+
+    let restored_tree_id = gitbutler_diff::write::tree_with_selected_hunks(
+        repo,
+        &worktree_tree,
+        &snapshot_tree,
+        &diffs,
+        |path, _hunk| is_selected(path),
+    )?;
+    let restored_tree = repo.find_tree(restored_tree_id)?;
+    repo.checkout_tree_builder(&restored_tree)
+        .force()
+        .checkout()
+        .context("failed to checkout tree")?;
+
+    ctx.branch_manager()
+        .without_snapshots()
+        .create_virtual_branch_for_new_changes(
+            name.unwrap_or_else(|| DEFAULT_BRANCH_NAME.to_owned()),
+            perm,
+        )
+}
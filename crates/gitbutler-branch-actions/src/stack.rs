@@ -0,0 +1,114 @@
+//! Stack virtual branches onto each other, so a branch builds on the commits of another one, like a
+//! series of pull requests where each is based on the one before it.
+//!
+//! A stacked branch is [pinned](crate::pinned_base) to the head of its parent, and is rebased along
+//! whenever the parent gets new commits or is rebased itself.
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::{Branch, BranchId};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::access::WorktreeWritePermission;
+
+use crate::{conflicts::RepoConflictsExt, pinned_base::rebase_onto, VirtualBranchesExt};
+
+/// Rebase the commits of the branch identified by `branch_id` onto the head of the branch identified by
+/// `parent_id`, and keep them on top of it from now on.
+pub(crate) fn stack_on(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    parent_id: BranchId,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    ctx.assure_resolved()?;
+    let vb_state = ctx.project().virtual_branches();
+    let mut branch = vb_state.get_branch_in_workspace(branch_id)?;
+    let parent = vb_state.get_branch_in_workspace(parent_id)?;
+    let branches: HashMap<_, _> = vb_state
+        .list_branches_in_workspace()?
+        .into_iter()
+        .map(|branch| (branch.id, branch))
+        .collect();
+    if parent_id == branch_id || ancestors(&branches, &parent).any(|ancestor| ancestor == branch_id)
+    {
+        return Err(anyhow!(
+            "{} can't be stacked on {} as it would end up being stacked on itself",
+            branch.name,
+            parent.name
+        ))
+        .context(Code::Validation);
+    }
+
+    rebase_onto(ctx, &mut branch, parent.head)?;
+    branch.pinned_base = Some(parent.head);
+    branch.parent = Some(parent_id);
+    vb_state.set_branch(branch)?;
+    crate::integration::update_gitbutler_integration(&vb_state, ctx)?;
+    Ok(())
+}
+
+/// Rebase all stacked branches in the workspace whose parent moved onto where their parent is now,
+/// parents before their children.
+///
+/// Branches that can't be rebased stay where they are, and branches whose parent isn't in the
+/// workspace anymore stay on the last head of their parent, without being stacked.
+pub(crate) fn restack(ctx: &CommandContext) -> Result<()> {
+    let vb_state = ctx.project().virtual_branches();
+    let branches: HashMap<_, _> = vb_state
+        .list_branches_in_workspace()?
+        .into_iter()
+        .map(|branch| (branch.id, branch))
+        .collect();
+    let mut stacked: Vec<_> = branches
+        .values()
+        .filter(|branch| branch.parent.is_some())
+        .collect();
+    if stacked.is_empty() {
+        return Ok(());
+    }
+    stacked.sort_by_key(|branch| ancestors(&branches, branch).count());
+
+    let mut heads: HashMap<_, _> = branches
+        .values()
+        .map(|branch| (branch.id, branch.head))
+        .collect();
+    for branch in stacked {
+        let mut branch = branch.clone();
+        let Some(parent_head) = branch.parent.and_then(|parent| heads.get(&parent).copied()) else {
+            branch.parent = None;
+            vb_state.set_branch(branch)?;
+            continue;
+        };
+        if branch.pinned_base == Some(parent_head) {
+            continue;
+        }
+        if let Err(err) = rebase_onto(ctx, &mut branch, parent_head) {
+            tracing::warn!(?err, "failed to restack {}", branch.name);
+            continue;
+        }
+        branch.pinned_base = Some(parent_head);
+        heads.insert(branch.id, branch.head);
+        vb_state.set_branch(branch)?;
+    }
+    Ok(())
+}
+
+/// Return the ids of the parent of `branch`, its parent and so on, as far as they are in `branches`.
+fn ancestors<'a>(
+    branches: &'a HashMap<BranchId, Branch>,
+    branch: &Branch,
+) -> impl Iterator<Item = BranchId> + 'a {
+    let mut next = branch.parent;
+    // Stacks can't be cyclic, but broken state shouldn't hang.
+    let mut remaining = branches.len();
+    std::iter::from_fn(move || {
+        let id = next?;
+        if remaining == 0 {
+            return None;
+        }
+        remaining -= 1;
+        next = branches.get(&id).and_then(|parent| parent.parent);
+        Some(id)
+    })
+}
@@ -0,0 +1,147 @@
+//! Turn entries of `git stash` into virtual branches, so stashed work isn't lost when moving to GitButler.
+use anyhow::{anyhow, bail, Context, Result};
+use gitbutler_branch::{BranchId, SignaturePurpose};
+use gitbutler_command_context::CommandContext;
+use gitbutler_commit::commit_headers::CommitHeadersV2;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_reference::{normalize_branch_name, LocalRefname, Refname};
+use gitbutler_repo::RepositoryExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{branch_manager::BranchManagerExt, conflicts::RepoConflictsExt};
+
+/// An entry of `git stash list`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StashEntry {
+    /// The position of the entry, with `0` being the most recent, like in `stash@{0}`.
+    pub index: usize,
+    /// The message of the entry, like `On main: wip`.
+    pub message: String,
+    #[serde(with = "gitbutler_serde::oid")]
+    pub id: git2::Oid,
+}
+
+/// How the changes of a stash entry end up in a virtual branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StashImport {
+    /// As a single commit on top of the commit the stash was created on.
+    Commit,
+    /// As uncommitted changes owned by the new branch.
+    Uncommitted,
+}
+
+/// Return all stash entries of the repository, most recent first.
+pub(crate) fn list_stashes(ctx: &CommandContext) -> Result<Vec<StashEntry>> {
+    let reflog = ctx.repository().reflog("refs/stash")?;
+    Ok(reflog
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| StashEntry {
+            index,
+            message: entry.message().unwrap_or_default().to_owned(),
+            id: entry.id_new(),
+        })
+        .collect())
+}
+
+/// Create a new virtual branch with the changes of the stash entry at `index`, and return its id.
+/// The stash entry itself is kept.
+///
+/// Untracked files that were stashed are included. Staged and unstaged changes aren't told apart,
+/// as GitButler doesn't use the index.
+pub(crate) fn import_stash(
+    ctx: &CommandContext,
+    index: usize,
+    import: StashImport,
+    perm: &mut WorktreeWritePermission,
+) -> Result<BranchId> {
+    ctx.assure_resolved()?;
+    let repo = ctx.repository();
+    let entry = list_stashes(ctx)?
+        .into_iter()
+        .find(|entry| entry.index == index)
+        .ok_or_else(|| anyhow!("there is no stash entry stash@{{{index}}}"))?;
+    let stash_commit = repo.find_commit(entry.id)?;
+    let base_commit = stash_commit
+        .parent(0)
+        .context("a stash entry must have the commit it was created on as first parent")?;
+    let stash_tree = stashed_tree(repo, &stash_commit)?;
+    let name = branch_name(&entry);
+
+    match import {
+        StashImport::Commit => {
+            let local_name = normalize_branch_name(&name)?;
+            if repo
+                .find_branch(&local_name, git2::BranchType::Local)
+                .is_ok()
+            {
+                bail!("branch '{local_name}' already exists");
+            }
+            let committer = gitbutler_branch::signature(SignaturePurpose::Committer)?;
+            let commit_id = repo.commit_with_signature(
+                None,
+                &stash_commit.author(),
+                &committer,
+                &name,
+                &stash_tree,
+                &[&base_commit],
+                Some(CommitHeadersV2::new()),
+            )?;
+            repo.branch(&local_name, &repo.find_commit(commit_id)?, false)?;
+            let refname = Refname::Local(LocalRefname::new(&local_name, None));
+            ctx.branch_manager()
+                .without_snapshots()
+                .create_virtual_branch_from_branch(&refname, None, perm)
+        }
+        StashImport::Uncommitted => {
+            let mut diff_opts = git2::DiffOptions::new();
+            diff_opts.show_binary(true);
+            let diff = repo.diff_tree_to_tree(
+                Some(&base_commit.tree()?),
+                Some(&stash_tree),
+                Some(&mut diff_opts),
+            )?;
+            repo.apply(&diff, git2::ApplyLocation::WorkDir, None)
+                .context("the stashed changes don't apply to the worktree")?;
+
+            ctx.branch_manager()
+                .without_snapshots()
+                .create_virtual_branch_for_new_changes(name, perm)
+        }
+    }
+}
+
+/// Return the tree of the worktree as it was stashed in `stash_commit`, including untracked files.
+fn stashed_tree<'repo>(
+    repo: &'repo git2::Repository,
+    stash_commit: &git2::Commit<'repo>,
+) -> Result<git2::Tree<'repo>> {
+    let tree = stash_commit.tree()?;
+    // The third parent, if present, holds the untracked files, and only them.
+    let Ok(untracked_commit) = stash_commit.parent(2) else {
+        return Ok(tree);
+    };
+    let empty_tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+    let mut merge_index = repo.merge_trees(&empty_tree, &tree, &untracked_commit.tree()?, None)?;
+    if merge_index.has_conflicts() {
+        bail!("the untracked files of the stash entry conflict with its tracked files");
+    }
+    let tree_id = merge_index.write_tree_to(repo)?;
+    Ok(repo.find_tree(tree_id)?)
+}
+
+/// Derive a branch name from the message of `entry`, like `wip` for `On main: wip`.
+fn branch_name(entry: &StashEntry) -> String {
+    let description = entry
+        .message
+        .split_once(": ")
+        .map_or(entry.message.as_str(), |(_, description)| description)
+        .trim();
+    if description.is_empty() {
+        format!("stash-{}", entry.index)
+    } else {
+        description.to_owned()
+    }
+}
@@ -1,4 +1,8 @@
-use std::{collections::HashMap, path::PathBuf, vec};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    vec,
+};
 
 use anyhow::{bail, Context, Result};
 use git2::Tree;
@@ -6,28 +10,116 @@ use gitbutler_branch::{
     Branch, BranchCreateRequest, BranchId, BranchOwnershipClaims, OwnershipClaim,
 };
 use gitbutler_command_context::CommandContext;
-use gitbutler_diff::{diff_files_into_hunks, GitHunk, Hunk, HunkHash};
+use gitbutler_diff::{
+    diff_files_into_hunks, GitHunk, Hunk, HunkHash, PathNormalization, DEFAULT_RENAME_THRESHOLD,
+};
 use gitbutler_operating_modes::assure_open_workspace_mode;
 use gitbutler_project::access::WorktreeWritePermission;
+use serde::Serialize;
 
 use crate::{
     conflicts::RepoConflictsExt,
     file::{virtual_hunks_into_virtual_files, VirtualBranchFile},
     hunk::{file_hunks_from_diffs, HunkLock, VirtualBranchHunk},
     integration::get_workspace_head,
+    status_trace::{StatusInput, StatusPhase, Tracer},
+    workdir_cache::{status_snapshot, workdir_diff_with_reasons},
     BranchManagerExt, VirtualBranchesExt,
 };
 
 /// Represents the uncommitted status of the applied virtual branches in the workspace.
+#[derive(Debug, Clone)]
 pub struct VirtualBranchesStatus {
+    /// Increases each time the status of a watched worktree is computed anew, so results of queries
+    /// with the same generation were read from the same status. It's `0` if the worktree isn't watched.
+    pub generation: u64,
     /// A collection of branches and their associated uncommitted file changes.
     pub branches: Vec<(Branch, Vec<VirtualBranchFile>)>,
     /// A collection of files that were skipped during the diffing process (due to being very large and unprocessable).
     pub skipped_files: Vec<gitbutler_diff::FileDiff>,
 }
 
+/// The uncommitted changes to a single file, and the branch they belong to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStatus {
+    /// The [generation](VirtualBranchesStatus::generation) of the status this was read from.
+    pub generation: u64,
+    pub branch_id: BranchId,
+    pub file: VirtualBranchFile,
+}
+
+/// Which changes each applied branch owns.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceOwnership {
+    /// The [generation](VirtualBranchesStatus::generation) of the status this was read from.
+    pub generation: u64,
+    pub branches: Vec<BranchOwnership>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchOwnership {
+    pub branch_id: BranchId,
+    pub ownership: BranchOwnershipClaims,
+}
+
+/// A computed status along with everything it was computed from.
+pub(crate) struct StatusSnapshot {
+    inputs: StatusInputs,
+    status: VirtualBranchesStatus,
+}
+
+/// Everything the status depends on. As long as none of it changes, neither does the status.
+#[derive(PartialEq)]
+struct StatusInputs {
+    integration_commit: git2::Oid,
+    diff: gitbutler_diff::DiffByPathMap,
+    /// The stored state of the virtual branches, which holds their ownership.
+    state: Vec<u8>,
+    resolving: bool,
+}
+
+impl StatusInputs {
+    fn read(ctx: &CommandContext, tracer: &mut Tracer) -> Result<Self> {
+        let integration_commit = get_workspace_head(ctx)?;
+        let (diff, rediffed) =
+            workdir_diff_with_reasons(ctx, integration_commit).context("failed to diff workdir")?;
+        tracer.diffed(&diff, &rediffed);
+        Ok(StatusInputs {
+            integration_commit,
+            diff,
+            state: ctx.project().virtual_branches().raw_state()?,
+            resolving: ctx.is_resolving(),
+        })
+    }
+
+    /// Return what differs between these inputs and `other`.
+    fn changed_since(&self, other: &StatusInputs) -> Vec<StatusInput> {
+        [
+            (
+                StatusInput::WorkspaceHead,
+                self.integration_commit != other.integration_commit,
+            ),
+            (StatusInput::Worktree, self.diff != other.diff),
+            (StatusInput::BranchState, self.state != other.state),
+            (
+                StatusInput::ConflictResolution,
+                self.resolving != other.resolving,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(input, changed)| changed.then_some(input))
+        .collect()
+    }
+}
+
 /// Returns branches and their associated file changes, in addition to a list
 /// of skipped files.
+///
+/// If the worktree is watched, the status is computed only if anything it depends on changed since
+/// the last time, so all queries in between read from the same status.
 // TODO(kv): make this side effect free
 pub fn get_applied_status(
     ctx: &CommandContext,
@@ -35,13 +127,125 @@ pub fn get_applied_status(
 ) -> Result<VirtualBranchesStatus> {
     assure_open_workspace_mode(ctx)
         .context("Getting applied status requires open workspace mode")?;
-    let integration_commit = get_workspace_head(ctx)?;
+    let mut tracer = Tracer::start(ctx.project().id);
+    let Some(snapshot) = status_snapshot(ctx.project().id) else {
+        let (base_file_diffs, rediffed) = workdir_diff_with_reasons(ctx, get_workspace_head(ctx)?)
+            .context("failed to diff workdir")?;
+        tracer.diffed(&base_file_diffs, &rediffed);
+        tracer.phase_done(StatusPhase::DiffWorktree);
+        let status = compute_applied_status(ctx, base_file_diffs, 0, perm)?;
+        tracer.phase_done(StatusPhase::AssignHunks);
+        tracer.finish(&status);
+        return Ok(status);
+    };
+    // Queries that come in while the status is computed wait for it instead of computing it as well.
+    let mut snapshot = snapshot.lock().expect("no panics while holding the lock");
+    let inputs = StatusInputs::read(ctx, &mut tracer)?;
+    if let Some(snapshot) = snapshot
+        .as_ref()
+        .filter(|snapshot| snapshot.inputs == inputs)
+    {
+        return Ok(snapshot.status.clone());
+    }
+    tracer.phase_done(StatusPhase::DiffWorktree);
+    if let Some(snapshot) = snapshot.as_ref() {
+        tracer.changed_inputs(inputs.changed_since(&snapshot.inputs));
+    }
+    let generation = snapshot
+        .as_ref()
+        .map_or(1, |snapshot| snapshot.status.generation + 1);
+    let status = compute_applied_status(ctx, inputs.diff.clone(), generation, perm)?;
+    tracer.phase_done(StatusPhase::AssignHunks);
+    tracer.finish(&status);
+    // Computing the status stores the ownership it found, which is what it depends on from now on.
+    let inputs = StatusInputs {
+        state: ctx.project().virtual_branches().raw_state()?,
+        ..inputs
+    };
+    *snapshot = Some(StatusSnapshot {
+        inputs,
+        status: status.clone(),
+    });
+    Ok(status)
+}
+
+/// Return the uncommitted changes to the file at `path`, relative to the worktree, or `None` if it has none.
+pub(crate) fn file_status(ctx: &CommandContext, path: &Path) -> Result<Option<FileStatus>> {
+    let status = get_applied_status(ctx, None)?;
+    Ok(status
+        .branches
+        .into_iter()
+        .find_map(|(branch, files)| {
+            files
+                .into_iter()
+                .find(|file| file.path == path)
+                .map(|file| (branch.id, file))
+        })
+        .map(|(branch_id, file)| FileStatus {
+            generation: status.generation,
+            branch_id,
+            file,
+        }))
+}
+
+/// Return which uncommitted changes each applied branch owns.
+pub(crate) fn workspace_ownership(ctx: &CommandContext) -> Result<WorkspaceOwnership> {
+    let status = get_applied_status(ctx, None)?;
+    Ok(WorkspaceOwnership {
+        generation: status.generation,
+        branches: status
+            .branches
+            .into_iter()
+            .map(|(branch, _files)| BranchOwnership {
+                branch_id: branch.id,
+                ownership: branch.ownership,
+            })
+            .collect(),
+    })
+}
+
+/// Return the branch each pinned hunk in `base_diffs` belongs to, by its file and hash. Pins of hunks that
+/// are gone are dropped, unless their branch isn't one of `virtual_branches`.
+fn pinned_hunks(
+    ctx: &CommandContext,
+    base_diffs: &HashMap<PathBuf, Vec<GitHunk>>,
+    virtual_branches: &[Branch],
+) -> Result<HashMap<(PathBuf, HunkHash), BranchId>> {
+    let hunks: Vec<(&Path, Hunk)> = base_diffs
+        .iter()
+        .flat_map(|(path, hunks)| {
+            hunks.iter().map(move |hunk| {
+                (
+                    path.as_path(),
+                    Hunk::from(hunk).with_hash(Hunk::hash_diff(&hunk.diff_lines)),
+                )
+            })
+        })
+        .collect();
+    let branch_ids: Vec<_> = virtual_branches.iter().map(|branch| branch.id).collect();
+    let pins = ctx
+        .project()
+        .hunk_pins()
+        .reconcile(hunks.iter().map(|(path, hunk)| (*path, hunk)), &branch_ids)?;
+    Ok(pins
+        .into_iter()
+        .filter_map(|pin| {
+            let hash = pin.hunk.parse::<Hunk>().ok()?.hash?;
+            Some(((pin.file_path, hash), pin.branch_id))
+        })
+        .collect())
+}
+
+fn compute_applied_status(
+    ctx: &CommandContext,
+    base_file_diffs: gitbutler_diff::DiffByPathMap,
+    generation: u64,
+    perm: Option<&mut WorktreeWritePermission>,
+) -> Result<VirtualBranchesStatus> {
     let mut virtual_branches = ctx
         .project()
         .virtual_branches()
         .list_branches_in_workspace()?;
-    let base_file_diffs = gitbutler_diff::workdir(ctx.repository(), &integration_commit.to_owned())
-        .context("failed to diff workdir")?;
 
     let mut skipped_files: Vec<gitbutler_diff::FileDiff> = Vec::new();
     for file_diff in base_file_diffs.values() {
@@ -78,6 +282,13 @@ pub fn get_applied_status(
         .find_commit(vb_state.get_default_target()?.sha)?
         .tree()?;
     let locks = compute_locks(ctx.repository(), &base_diffs, &virtual_branches, base_tree)?;
+    respell_claims(
+        PathNormalization::from_repo(ctx.repository()),
+        &mut virtual_branches,
+        &base_diffs,
+    );
+    follow_renames(&mut virtual_branches, &base_diffs, &locks);
+    let pinned = pinned_hunks(ctx, &base_diffs, &virtual_branches)?;
 
     for branch in &mut virtual_branches {
         let old_claims = branch.ownership.claims.clone();
@@ -99,8 +310,13 @@ pub fn get_applied_status(
                                 || claimed_hunk.intersects(git_diff_hunk)
                             {
                                 let hash = Hunk::hash_diff(&git_diff_hunk.diff_lines);
-                                if locks.contains_key(&hash) {
-                                    return None; // Defer allocation to unclaimed hunks processing
+                                // Defer allocation to unclaimed hunks processing if the hunk is pinned to
+                                // another branch, or locked to one without being pinned to this one.
+                                let pinned_to = pinned.get(&(claim.file_path.clone(), hash));
+                                if pinned_to
+                                    .map_or(locks.contains_key(&hash), |id| *id != branch.id)
+                                {
+                                    return None;
                                 }
                                 diffs_by_branch
                                     .entry(branch.id)
@@ -152,17 +368,22 @@ pub fn get_applied_status(
         for hunk in hunks {
             let hash = Hunk::hash_diff(&hunk.diff_lines);
             let locked_to = locks.get(&hash);
+            let pinned_pos = pinned
+                .get(&(filepath.clone(), hash))
+                .and_then(|branch_id| virtual_branches.iter().position(|vb| vb.id == *branch_id));
 
-            let vbranch_pos = if let Some(locks) = locked_to {
+            let vbranch_pos = if let Some(p) = pinned_pos {
+                p
+            } else if let Some(locks) = locked_to {
                 let p = virtual_branches
                     .iter()
                     .position(|vb| vb.id == locks[0].branch_id);
                 match p {
                     Some(p) => p,
-                    _ => default_vbranch_pos,
+                    _ => allowed_vbranch_pos(&virtual_branches, default_vbranch_pos, &filepath),
                 }
             } else {
-                default_vbranch_pos
+                allowed_vbranch_pos(&virtual_branches, default_vbranch_pos, &filepath)
             };
 
             virtual_branches[vbranch_pos].ownership.put(OwnershipClaim {
@@ -219,11 +440,111 @@ pub fn get_applied_status(
         .collect();
 
     Ok(VirtualBranchesStatus {
+        generation,
         branches: files_by_branch,
         skipped_files,
     })
 }
 
+/// Return the position of the branch that new changes to `path` go to, which is the default branch unless
+/// `path` is outside of its allowed paths. Then it's the first branch that allows `path`, or the default
+/// branch if there is none, where the file is flagged as being outside its allowed paths.
+fn allowed_vbranch_pos(
+    virtual_branches: &[Branch],
+    default_vbranch_pos: usize,
+    path: &Path,
+) -> usize {
+    if virtual_branches[default_vbranch_pos].allows_path(path) {
+        return default_vbranch_pos;
+    }
+    virtual_branches
+        .iter()
+        .position(|branch| branch.allows_path(path))
+        .unwrap_or(default_vbranch_pos)
+}
+
+/// Move the claims of all branches on paths that have changes under another spelling to that spelling, as
+/// `normalization` says both are the same path, so claims don't split across spellings of the same file.
+fn respell_claims(
+    normalization: PathNormalization,
+    virtual_branches: &mut [Branch],
+    base_diffs: &HashMap<PathBuf, Vec<GitHunk>>,
+) {
+    if !normalization.is_enabled() {
+        return;
+    }
+    let spellings: HashMap<_, _> = base_diffs
+        .keys()
+        .map(|path| (normalization.key(path).into_owned(), path))
+        .collect();
+    for branch in virtual_branches {
+        let needs_respelling = branch
+            .ownership
+            .claims
+            .iter()
+            .any(|claim| !base_diffs.contains_key(&claim.file_path));
+        if !needs_respelling {
+            continue;
+        }
+        // Claims are put in reverse, as each is put first, which merges claims on the same path.
+        let claims = std::mem::take(&mut branch.ownership.claims);
+        for mut claim in claims.into_iter().rev() {
+            if !base_diffs.contains_key(&claim.file_path) {
+                if let Some(spelling) = spellings.get(&*normalization.key(&claim.file_path)) {
+                    claim.file_path = (*spelling).clone();
+                }
+            }
+            branch.ownership.put(claim);
+        }
+    }
+}
+
+/// Make the branch that owns a file that was renamed own the file at its new path as well, so its
+/// changes stay together. A file is owned by a branch if the branch claims it or its hunks are locked to it.
+fn follow_renames(
+    virtual_branches: &mut [Branch],
+    base_diffs: &HashMap<PathBuf, Vec<GitHunk>>,
+    locks: &HashMap<HunkHash, Vec<HunkLock>>,
+) {
+    let claims_path = |branch: &Branch, path: &PathBuf| {
+        branch
+            .ownership
+            .claims
+            .iter()
+            .any(|claim| &claim.file_path == path)
+    };
+    for (old_path, new_path) in gitbutler_diff::renames(base_diffs, DEFAULT_RENAME_THRESHOLD) {
+        if virtual_branches
+            .iter()
+            .any(|branch| claims_path(branch, &new_path))
+        {
+            continue;
+        }
+        let locked_to = base_diffs[&old_path].iter().find_map(|hunk| {
+            locks
+                .get(&Hunk::hash_diff(&hunk.diff_lines))
+                .and_then(|locks| locks.first())
+                .map(|lock| lock.branch_id)
+        });
+        let Some(owner) = virtual_branches
+            .iter_mut()
+            .find(|branch| claims_path(branch, &old_path) || Some(branch.id) == locked_to)
+        else {
+            continue;
+        };
+        for path in [old_path, new_path] {
+            let hunks = base_diffs[&path]
+                .iter()
+                .map(|hunk| Hunk::from(hunk).with_hash(Hunk::hash_diff(&hunk.diff_lines)))
+                .collect();
+            owner.ownership.put(OwnershipClaim {
+                file_path: path,
+                hunks,
+            });
+        }
+    }
+}
+
 fn compute_locks(
     repository: &git2::Repository,
     unstaged_hunks_by_path: &HashMap<PathBuf, Vec<gitbutler_diff::GitHunk>>,
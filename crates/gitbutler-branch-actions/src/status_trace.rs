@@ -0,0 +1,209 @@
+//! Record why each file ended up in a computed [status](crate::get_applied_status()) and how long computing it
+//! took, to tell why GitButler thinks a file changed. Tracing is off unless enabled for a project, and traces
+//! are only kept in memory. How long it took and how much of the cached diff was used is always counted in the
+//! [metrics](gitbutler_metrics) though.
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
+    sync::Mutex,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use gitbutler_branch::BranchId;
+use gitbutler_diff::{DiffByPathMap, RediffReason};
+use gitbutler_project::ProjectId;
+use serde::Serialize;
+
+use crate::status::VirtualBranchesStatus;
+
+/// The amount of traces kept per project, with older ones being dropped.
+const MAX_TRACES: usize = 100;
+
+static TRACES: Mutex<BTreeMap<ProjectId, VecDeque<StatusTrace>>> = Mutex::new(BTreeMap::new());
+
+/// How a single status was computed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusTrace {
+    /// The [generation](crate::VirtualBranchesStatus::generation) of the status.
+    pub generation: u64,
+    /// When computing the status started, in milliseconds since the Unix epoch.
+    pub started_at: u128,
+    /// What changed since the status computed before, which is empty if there is none to compare with
+    /// as the worktree isn't watched or nothing was computed yet.
+    pub changed_inputs: Vec<StatusInput>,
+    /// The files with changes, ordered by their path.
+    pub files: Vec<FileTrace>,
+    /// How long each phase took, in the order they ran.
+    pub phases: Vec<PhaseTrace>,
+}
+
+/// Something a status depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StatusInput {
+    /// The commit the worktree is diffed against.
+    WorkspaceHead,
+    /// The diff of the worktree.
+    Worktree,
+    /// The stored state of the virtual branches, which holds their ownership.
+    BranchState,
+    /// Whether a conflict is being resolved.
+    ConflictResolution,
+}
+
+/// A file with changes in a status.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTrace {
+    pub path: PathBuf,
+    /// Why the file was diffed again, or `None` if its diff was taken from the cache as it was.
+    pub rediffed: Option<RediffReason>,
+    /// The branch that owns the changes, or `None` if the file was skipped.
+    pub branch_id: Option<BranchId>,
+}
+
+/// A phase of computing a status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StatusPhase {
+    /// Diffing the worktree against the workspace head.
+    DiffWorktree,
+    /// Assigning the changes to the applied branches.
+    AssignHunks,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseTrace {
+    pub phase: StatusPhase,
+    pub micros: u64,
+}
+
+/// Start keeping traces of the status computations of the project with `project_id` if `enabled` is `true`,
+/// or stop it and drop all of its traces otherwise.
+pub(crate) fn set_enabled(project_id: ProjectId, enabled: bool) {
+    let mut traces = TRACES.lock().expect("no panics while holding the lock");
+    if enabled {
+        traces.entry(project_id).or_default();
+    } else {
+        traces.remove(&project_id);
+    }
+}
+
+/// Return the traces kept for the project with `project_id`, oldest first.
+pub(crate) fn traces(project_id: ProjectId) -> Vec<StatusTrace> {
+    TRACES
+        .lock()
+        .expect("no panics while holding the lock")
+        .get(&project_id)
+        .map(|traces| traces.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Collects the trace of a single status computation, doing nothing if tracing isn't enabled.
+pub(crate) struct Tracer {
+    project_id: ProjectId,
+    enabled: bool,
+    started_at: SystemTime,
+    started: Instant,
+    phase_started: Instant,
+    phases: Vec<PhaseTrace>,
+    rediffed: BTreeMap<PathBuf, Option<RediffReason>>,
+    changed_inputs: Vec<StatusInput>,
+}
+
+impl Tracer {
+    pub(crate) fn start(project_id: ProjectId) -> Self {
+        Tracer {
+            project_id,
+            enabled: TRACES
+                .lock()
+                .expect("no panics while holding the lock")
+                .contains_key(&project_id),
+            started_at: SystemTime::now(),
+            started: Instant::now(),
+            phase_started: Instant::now(),
+            phases: Vec::new(),
+            rediffed: BTreeMap::new(),
+            changed_inputs: Vec::new(),
+        }
+    }
+
+    /// Note that `phase` ended now, having started when the previous one ended.
+    pub(crate) fn phase_done(&mut self, phase: StatusPhase) {
+        if !self.enabled {
+            return;
+        }
+        self.phases.push(PhaseTrace {
+            phase,
+            micros: u64::try_from(self.phase_started.elapsed().as_micros()).unwrap_or(u64::MAX),
+        });
+        self.phase_started = Instant::now();
+    }
+
+    /// Note that the worktree diffed as `diff`, with `rediffed` telling why files were diffed again.
+    pub(crate) fn diffed(
+        &mut self,
+        diff: &DiffByPathMap,
+        rediffed: &BTreeMap<PathBuf, RediffReason>,
+    ) {
+        let misses = diff
+            .keys()
+            .filter(|path| rediffed.contains_key(*path))
+            .count();
+        gitbutler_metrics::count_workdir_cache(diff.len() - misses, misses);
+        if !self.enabled {
+            return;
+        }
+        self.rediffed = diff
+            .keys()
+            .map(|path| (path.clone(), rediffed.get(path).copied()))
+            .collect();
+    }
+
+    /// Note that `inputs` differ from the ones the previous status was computed from.
+    pub(crate) fn changed_inputs(&mut self, inputs: Vec<StatusInput>) {
+        self.changed_inputs = inputs;
+    }
+
+    /// Keep the trace of computing `status`.
+    pub(crate) fn finish(self, status: &VirtualBranchesStatus) {
+        gitbutler_metrics::observe_status_duration(self.started.elapsed());
+        if !self.enabled {
+            return;
+        }
+        let files = self
+            .rediffed
+            .into_iter()
+            .map(|(path, rediffed)| FileTrace {
+                branch_id: status
+                    .branches
+                    .iter()
+                    .find(|(_branch, files)| files.iter().any(|file| file.path == path))
+                    .map(|(branch, _files)| branch.id),
+                path,
+                rediffed,
+            })
+            .collect();
+        let trace = StatusTrace {
+            generation: status.generation,
+            started_at: self
+                .started_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis()),
+            changed_inputs: self.changed_inputs,
+            files,
+            phases: self.phases,
+        };
+
+        let mut traces = TRACES.lock().expect("no panics while holding the lock");
+        // Tracing may have been disabled in the meantime.
+        if let Some(traces) = traces.get_mut(&self.project_id) {
+            if traces.len() == MAX_TRACES {
+                traces.pop_front();
+            }
+            traces.push_back(trace);
+        }
+    }
+}
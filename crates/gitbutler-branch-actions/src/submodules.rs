@@ -0,0 +1,166 @@
+//! Tell the state of submodules, which virtual branches don't track, and protect it from operations
+//! that rewrite the worktree.
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use serde::Serialize;
+
+/// A submodule of the repository, and how it differs from the workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Submodule {
+    pub name: String,
+    /// The path of the submodule, relative to the worktree.
+    pub path: PathBuf,
+    pub url: Option<String>,
+    pub status: SubmoduleStatus,
+    /// The commit the workspace points the submodule to, or `None` if it isn't committed yet.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub head_id: Option<git2::Oid>,
+    /// The commit checked out in the submodule, or `None` if it isn't checked out.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub workdir_id: Option<git2::Oid>,
+    /// Whether the submodule has changes of its own, or untracked files.
+    pub dirty: bool,
+}
+
+/// How a submodule differs from what the workspace points it to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubmoduleStatus {
+    /// It was added, but isn't part of the workspace yet.
+    New,
+    /// Another commit than the one the workspace points it to is checked out.
+    Changed,
+    /// It's part of the workspace, but wasn't cloned or checked out.
+    Uninitialized,
+    /// The commit the workspace points it to is checked out.
+    Unchanged,
+}
+
+impl SubmoduleStatus {
+    fn from_flags(flags: git2::SubmoduleStatus) -> Self {
+        if !flags.contains(git2::SubmoduleStatus::IN_HEAD)
+            || flags
+                .intersects(git2::SubmoduleStatus::INDEX_ADDED | git2::SubmoduleStatus::WD_ADDED)
+        {
+            SubmoduleStatus::New
+        } else if flags.contains(git2::SubmoduleStatus::WD_UNINITIALIZED) {
+            SubmoduleStatus::Uninitialized
+        } else if flags
+            .intersects(git2::SubmoduleStatus::INDEX_MODIFIED | git2::SubmoduleStatus::WD_MODIFIED)
+        {
+            SubmoduleStatus::Changed
+        } else {
+            SubmoduleStatus::Unchanged
+        }
+    }
+}
+
+/// Return all submodules of the repository, ordered by path.
+pub(crate) fn list_submodules(ctx: &CommandContext) -> Result<Vec<Submodule>> {
+    let repo = ctx.repository();
+    let mut submodules = repo
+        .submodules()
+        .context("failed to list submodules")?
+        .into_iter()
+        .map(|submodule| {
+            let name = submodule.name().unwrap_or_default().to_owned();
+            let flags = repo
+                .submodule_status(&name, git2::SubmoduleIgnore::None)
+                .with_context(|| format!("failed to get the status of submodule {name}"))?;
+            Ok(Submodule {
+                path: submodule.path().to_path_buf(),
+                url: submodule.url().map(ToOwned::to_owned),
+                status: SubmoduleStatus::from_flags(flags),
+                head_id: submodule.head_id(),
+                workdir_id: submodule.workdir_id(),
+                dirty: flags.intersects(
+                    git2::SubmoduleStatus::WD_INDEX_MODIFIED
+                        | git2::SubmoduleStatus::WD_WD_MODIFIED
+                        | git2::SubmoduleStatus::WD_UNTRACKED,
+                ),
+                name,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    submodules.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(submodules)
+}
+
+/// A Git repository within the worktree that isn't a submodule, like a vendored clone.
+///
+/// Its files are left out of the workspace, as they belong to that repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NestedRepository {
+    /// The path of the repository, relative to the worktree.
+    pub path: PathBuf,
+    /// The URL of its `origin` remote, if it has one.
+    pub url: Option<String>,
+    pub suggestion: NestedRepositorySuggestion,
+}
+
+/// How a nested repository could be dealt with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NestedRepositorySuggestion {
+    /// Add it to `.gitignore`, as it can't be cloned from anywhere.
+    Ignore,
+    /// Turn it into a submodule of the URL it was cloned from.
+    ConvertToSubmodule,
+}
+
+/// Return all nested repositories that aren't ignored, ordered by path.
+pub(crate) fn list_nested_repositories(ctx: &CommandContext) -> Result<Vec<NestedRepository>> {
+    let repo = ctx.repository();
+    let workdir = repo.workdir().context("a worktree is needed")?;
+    gitbutler_diff::nested_repositories(repo)?
+        .into_iter()
+        .map(|path| {
+            let nested = git2::Repository::open(workdir.join(&path))
+                .with_context(|| format!("failed to open nested repository {}", path.display()))?;
+            let url = nested
+                .find_remote("origin")
+                .ok()
+                .and_then(|remote| remote.url().map(ToOwned::to_owned));
+            Ok(NestedRepository {
+                path,
+                suggestion: if url.is_some() {
+                    NestedRepositorySuggestion::ConvertToSubmodule
+                } else {
+                    NestedRepositorySuggestion::Ignore
+                },
+                url,
+            })
+        })
+        .collect()
+}
+
+/// Return an error with [`Code::Submodules`] if a submodule is new or points to another commit than
+/// the workspace does.
+///
+/// Neither is owned by a virtual branch, so operations that check out a new worktree would drop or
+/// reset them. Changes within a submodule are left alone by checkouts and don't matter here.
+pub(crate) fn assure_submodules_unchanged(ctx: &CommandContext) -> Result<()> {
+    let changed: Vec<String> = list_submodules(ctx)?
+        .into_iter()
+        .filter(|submodule| {
+            matches!(
+                submodule.status,
+                SubmoduleStatus::New | SubmoduleStatus::Changed
+            )
+        })
+        .map(|submodule| submodule.path.display().to_string())
+        .collect();
+    if changed.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "submodule(s) {} are new or point to another commit than the workspace, which would be undone",
+        changed.join(", ")
+    ))
+    .context(Code::Submodules)
+}
@@ -0,0 +1,221 @@
+//! Create, list and push tags, so releases can be tagged without leaving GitButler.
+//!
+//! Annotated tags are signed with the same configuration as commits, like `user.signingkey` and `gpg.format`.
+use anyhow::{anyhow, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_commit::commit_buffer::CommitBuffer;
+use gitbutler_error::error::Code;
+use gitbutler_reference::RemoteRefname;
+use gitbutler_repo::{credentials::Helper, ForcePush, RepoActionsExt, RepositoryExt};
+use serde::Serialize;
+
+use crate::{author::Author, VirtualBranchesExt};
+
+/// The lines that start the signatures Git appends to the message of signed tags.
+const SIGNATURE_STARTS: [&str; 3] = [
+    "-----BEGIN PGP SIGNATURE-----",
+    "-----BEGIN SSH SIGNATURE-----",
+    "-----BEGIN SIGNED MESSAGE-----",
+];
+
+/// A tag, along with the commit it points to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    /// The name of the tag, without `refs/tags/`.
+    pub name: String,
+    /// `false` for lightweight tags, which are references to the commit and nothing else.
+    pub annotated: bool,
+    /// The message of an annotated tag, without its signature.
+    pub message: Option<String>,
+    /// Who created an annotated tag.
+    pub tagger: Option<Author>,
+    /// `true` if the annotated tag carries a signature, which isn't verified.
+    pub signed: bool,
+    #[serde(with = "gitbutler_serde::oid")]
+    pub commit: git2::Oid,
+    /// The first line of the message of the commit.
+    pub commit_summary: String,
+    pub commit_author: Author,
+    /// When the commit was created, in milliseconds since the Unix epoch.
+    pub commit_timestamp_ms: u128,
+}
+
+/// Create a tag named `name` for the commit `target`, which is annotated with `message` if it's set, and signed
+/// if `sign` is set. Only annotated tags can be signed.
+pub(crate) fn create_tag(
+    ctx: &CommandContext,
+    name: &str,
+    target: git2::Oid,
+    message: Option<&str>,
+    sign: bool,
+) -> Result<Tag> {
+    let repo = ctx.repository();
+    let refname = format!("refs/tags/{name}");
+    if name.is_empty() || !git2::Reference::is_valid_name(&refname) {
+        return Err(anyhow!("'{name}' isn't a valid tag name")).context(Code::Validation);
+    }
+    if repo.find_reference(&refname).is_ok() {
+        return Err(anyhow!("tag '{name}' already exists")).context(Code::Validation);
+    }
+    let commit = repo
+        .find_commit(target)
+        .with_context(|| format!("commit {target} not found"))?;
+
+    match message {
+        None if sign => {
+            return Err(anyhow!(
+                "lightweight tags can't be signed, a message is needed"
+            ))
+            .context(Code::Validation);
+        }
+        None => {
+            repo.tag_lightweight(name, commit.as_object(), false)?;
+        }
+        Some(message) => {
+            let tagger = repo
+                .signature()
+                .context("failed to get the identity to tag with")?;
+            if sign {
+                create_signed_tag(repo, &refname, name, &commit, &tagger, message)?;
+            } else {
+                repo.tag(name, commit.as_object(), &tagger, message, false)?;
+            }
+        }
+    }
+    let reference = repo.find_reference(&refname)?;
+    tag_from_reference(repo, &reference)?.context("the tag that was just created wasn't found")
+}
+
+/// Return all tags that point to commits, the ones with the newest commits first.
+pub(crate) fn list_tags(ctx: &CommandContext) -> Result<Vec<Tag>> {
+    let repo = ctx.repository();
+    let mut tags = Vec::new();
+    for reference in repo.references_glob("refs/tags/*")? {
+        if let Some(tag) = tag_from_reference(repo, &reference?)? {
+            tags.push(tag);
+        }
+    }
+    tags.sort_by(|a, b| {
+        b.commit_timestamp_ms
+            .cmp(&a.commit_timestamp_ms)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(tags)
+}
+
+/// Push the tag named `name` to `remote`, or to the remote the target is pushed to.
+/// Tags are never replaced on the remote.
+pub(crate) fn push_tag(
+    ctx: &CommandContext,
+    name: &str,
+    remote: Option<&str>,
+    askpass: Option<Option<BranchId>>,
+) -> Result<()> {
+    let refname = format!("refs/tags/{name}");
+    let commit = ctx
+        .repository()
+        .find_reference(&refname)
+        .map_err(|_| anyhow!("tag '{name}' not found"))
+        .context(Code::Validation)?
+        .peel_to_commit()?;
+    let remote = match remote {
+        Some(remote) => remote.to_owned(),
+        None => {
+            let target = ctx.project().virtual_branches().get_default_target()?;
+            target
+                .push_remote_name
+                .clone()
+                .or_else(|| ctx.project().settings.default_push_remote.clone())
+                .unwrap_or_else(|| target.branch.remote().to_owned())
+        }
+    };
+    ctx.push(
+        &commit.id(),
+        &RemoteRefname::new(&remote, name),
+        ForcePush::No,
+        &Helper::default(),
+        Some(format!("{refname}:{refname}")),
+        askpass,
+    )
+    .with_context(|| format!("failed to push tag '{name}' to {remote}"))
+}
+
+/// Write an annotated tag object signed like commits are, and point `refname` to it.
+fn create_signed_tag(
+    repo: &git2::Repository,
+    refname: &str,
+    name: &str,
+    commit: &git2::Commit<'_>,
+    tagger: &git2::Signature<'_>,
+    message: &str,
+) -> Result<()> {
+    let time = tagger.when();
+    let offset = time.offset_minutes().abs();
+    let buffer = CommitBuffer::new(
+        format!(
+            "object {}\ntype commit\ntag {name}\ntagger {} <{}> {} {}{:02}{:02}\n\n{}\n",
+            commit.id(),
+            tagger.name().unwrap_or_default(),
+            tagger.email().unwrap_or_default(),
+            time.seconds(),
+            time.sign(),
+            offset / 60,
+            offset % 60,
+            message.trim_end(),
+        )
+        .as_bytes(),
+    );
+    let signature = repo.sign_buffer(&buffer)?;
+    let mut content = buffer.as_bstring();
+    content.extend_from_slice(&signature);
+    let tag_id = repo.odb()?.write(git2::ObjectType::Tag, &content)?;
+    repo.reference(refname, tag_id, false, &format!("tag: {name}"))?;
+    Ok(())
+}
+
+/// Return the tag `reference` is, or `None` if it doesn't point to a commit.
+fn tag_from_reference(
+    repo: &git2::Repository,
+    reference: &git2::Reference<'_>,
+) -> Result<Option<Tag>> {
+    let Some(name) = reference.shorthand() else {
+        return Ok(None);
+    };
+    let Ok(commit) = reference.peel_to_commit() else {
+        return Ok(None);
+    };
+    let annotation = reference
+        .target()
+        .and_then(|target| repo.find_tag(target).ok());
+    let (message, signed) = match annotation
+        .as_ref()
+        .and_then(|tag| tag.message_bytes().map(|message| message.to_str_lossy()))
+    {
+        Some(message) => {
+            let signature_start = SIGNATURE_STARTS
+                .iter()
+                .filter_map(|start| message.find(start))
+                .min();
+            let text = &message[..signature_start.unwrap_or(message.len())];
+            (Some(text.trim_end().to_owned()), signature_start.is_some())
+        }
+        None => (None, false),
+    };
+    Ok(Some(Tag {
+        name: name.to_owned(),
+        annotated: annotation.is_some(),
+        message,
+        tagger: annotation
+            .as_ref()
+            .and_then(|tag| tag.tagger())
+            .map(|tagger| tagger.to_owned().into()),
+        signed,
+        commit: commit.id(),
+        commit_summary: commit.summary().unwrap_or_default().to_owned(),
+        commit_author: commit.author().into(),
+        commit_timestamp_ms: u128::try_from(commit.time().seconds()).unwrap_or_default() * 1000,
+    }))
+}
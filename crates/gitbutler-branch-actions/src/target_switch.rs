@@ -0,0 +1,213 @@
+//! Switch the target of a project to another remote branch, like from `origin/main` to `origin/release-1.2`,
+//! and move the applied branches along with it.
+use anyhow::{anyhow, bail, Context, Result};
+use gitbutler_branch::{Branch, BranchEventKind, BranchId, Target};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_reference::{ReferenceName, RemoteRefname};
+use gitbutler_repo::{rebase::cherry_rebase, remote_head_branch, RepoActionsExt, RepositoryExt};
+use serde::Serialize;
+
+use crate::{
+    branch_manager::BranchManagerExt, integration::update_gitbutler_integration,
+    r#virtual::record_branch_event, status::get_applied_status, VirtualBranchesExt,
+};
+
+/// What happened to an applied branch when the target was switched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitchedBranch {
+    pub branch_id: BranchId,
+    pub name: String,
+    pub status: SwitchStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum SwitchStatus {
+    /// The commits and uncommitted changes of the branch were rebased onto the new target.
+    #[serde(rename_all = "camelCase")]
+    Rebased {
+        #[serde(with = "gitbutler_serde::oid")]
+        new_head: git2::Oid,
+    },
+    /// The branch is pinned to its own base, and merges cleanly with the new target.
+    Pinned,
+    /// The branch conflicts with the new target, so it was unapplied into the real branch `reference`.
+    /// Conflicts will be dealt with when it's applied again.
+    #[serde(rename_all = "camelCase")]
+    Unapplied { reference: ReferenceName },
+}
+
+/// Make `new_target` the target of the project, and rebase all applied branches from the old target onto it.
+///
+/// Branches that conflict with the new target are unapplied, and the remaining ones are checked out
+/// on top of it. Returns what happened to each of the previously applied branches.
+pub(crate) fn switch_target(
+    ctx: &CommandContext,
+    new_target: &RemoteRefname,
+    perm: &mut WorktreeWritePermission,
+) -> Result<Vec<SwitchedBranch>> {
+    ctx.assure_resolved()?;
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    if &target.branch == new_target {
+        bail!("{new_target} already is the target");
+    }
+
+    let new_target_commit = repo
+        .find_branch_by_refname(&new_target.clone().into())?
+        .ok_or(anyhow!("remote branch '{}' not found", new_target))?
+        .get()
+        .peel_to_commit()
+        .context(format!("failed to peel branch {} to commit", new_target))?;
+    let new_target_tree = new_target_commit.tree()?;
+    let remote_url = repo
+        .find_remote(new_target.remote())
+        .context(format!("failed to find remote {}", new_target.remote()))?
+        .url()
+        .context(format!(
+            "failed to get remote url for {}",
+            new_target.remote()
+        ))?
+        .to_owned();
+
+    let mut switched = Vec::new();
+    let mut updated_branches = Vec::new();
+    for (mut branch, _) in get_applied_status(ctx, None)?.branches {
+        let branch_tree = repo.find_tree(branch.tree)?;
+
+        if let Some(pinned_base) = branch.pinned_base {
+            // the branch stays on its pinned base, it just has to merge cleanly with the new target
+            let pinned_tree = repo.find_commit(pinned_base)?.tree()?;
+            let status = if repo
+                .merge_trees(&pinned_tree, &new_target_tree, &branch_tree, None)?
+                .has_conflicts()
+            {
+                unapply(ctx, &branch, perm)?
+            } else {
+                updated_branches.push(branch.clone());
+                SwitchStatus::Pinned
+            };
+            switched.push(SwitchedBranch {
+                branch_id: branch.id,
+                name: branch.name,
+                status,
+            });
+            continue;
+        }
+
+        // commits that are reachable from the old target but not from the new one are not part of the branch
+        let new_head = match cherry_rebase(ctx, new_target_commit.id(), target.sha, branch.head) {
+            Ok(rebased_head) => rebased_head.unwrap_or(new_target_commit.id()),
+            Err(_) => {
+                let status = unapply(ctx, &branch, perm)?;
+                switched.push(SwitchedBranch {
+                    branch_id: branch.id,
+                    name: branch.name,
+                    status,
+                });
+                continue;
+            }
+        };
+
+        // uncommitted changes move from the old head of the branch to the new one
+        let old_head_tree = repo.find_commit(branch.head)?.tree()?;
+        let new_head_tree = repo.find_commit(new_head)?.tree()?;
+        let mut tree_merge_index =
+            repo.merge_trees(&old_head_tree, &new_head_tree, &branch_tree, None)?;
+        if tree_merge_index.has_conflicts() {
+            let status = unapply(ctx, &branch, perm)?;
+            switched.push(SwitchedBranch {
+                branch_id: branch.id,
+                name: branch.name,
+                status,
+            });
+            continue;
+        }
+
+        let old_head = branch.head;
+        branch.head = new_head;
+        branch.tree = tree_merge_index.write_tree_to(repo)?;
+        vb_state.set_branch(branch.clone())?;
+        record_branch_event(
+            ctx,
+            branch.id,
+            BranchEventKind::Rebased { old_head, new_head },
+        );
+        switched.push(SwitchedBranch {
+            branch_id: branch.id,
+            name: branch.name.clone(),
+            status: SwitchStatus::Rebased { new_head },
+        });
+        updated_branches.push(branch);
+    }
+
+    let final_tree = updated_branches
+        .iter()
+        .try_fold(new_target_tree.clone(), |final_tree, branch| {
+            let branch_tree = repo.find_tree(branch.tree)?;
+            let base_tree = match branch.pinned_base {
+                Some(pinned_base) => repo.find_commit(pinned_base)?.tree()?,
+                None => new_target_tree.clone(),
+            };
+            let final_tree_oid = repo
+                .merge_trees(&base_tree, &final_tree, &branch_tree, None)?
+                .write_tree_to(repo)?;
+            repo.find_tree(final_tree_oid)
+        })
+        .context("failed to calculate final tree")?;
+    repo.checkout_tree_builder(&final_tree)
+        .force()
+        .checkout()
+        .context("failed to checkout the branches on top of the new target")?;
+
+    vb_state.set_default_target(Target {
+        branch: new_target.clone(),
+        remote_url,
+        sha: new_target_commit.id(),
+        ..target
+    })?;
+    update_gitbutler_integration(&vb_state, ctx)?;
+    Ok(switched)
+}
+
+/// Return the default branch of the remote of the target if it's another branch than the target, like after
+/// the remote renamed `master` to `main`, or `None` if it's the target or isn't known.
+pub(crate) fn remote_default_branch_change(ctx: &CommandContext) -> Result<Option<RemoteRefname>> {
+    let target = ctx.project().virtual_branches().get_default_target()?;
+    let remote = target.branch.remote();
+    let Some(default_branch) = remote_head_branch(ctx.repository(), remote) else {
+        return Ok(None);
+    };
+    let default_branch = RemoteRefname::new(remote, &default_branch);
+    Ok((default_branch != target.branch).then_some(default_branch))
+}
+
+/// Make the default branch of the remote of the target the new target, and rebase all applied branches onto
+/// it like [`switch_target()`] does.
+pub(crate) fn migrate_target_to_remote_default(
+    ctx: &CommandContext,
+    perm: &mut WorktreeWritePermission,
+) -> Result<Vec<SwitchedBranch>> {
+    let Some(new_target) = remote_default_branch_change(ctx)? else {
+        return Err(anyhow!(
+            "the default branch of the remote is unknown or already the target"
+        ))
+        .context(Code::Validation);
+    };
+    switch_target(ctx, &new_target, perm)
+}
+
+fn unapply(
+    ctx: &CommandContext,
+    branch: &Branch,
+    perm: &mut WorktreeWritePermission,
+) -> Result<SwitchStatus> {
+    let reference = ctx
+        .branch_manager()
+        .convert_to_real_branch(branch.id, perm)?;
+    Ok(SwitchStatus::Unapplied { reference })
+}
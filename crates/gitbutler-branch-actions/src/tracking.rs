@@ -0,0 +1,43 @@
+//! Keep the tracking configuration of Git, `branch.<name>.remote` and `branch.<name>.merge`, in line with
+//! the upstream branches of virtual branches, so plain Git and other tools know where they are pushed to.
+use anyhow::Result;
+use gitbutler_branch::Branch;
+use gitbutler_command_context::CommandContext;
+use gitbutler_reference::normalize_branch_name;
+use gitbutler_repo::Config;
+
+use crate::VirtualBranchesExt;
+
+/// Configure the upstream branch of `vbranch` as the branch that the local branch of the same name tracks,
+/// and return `true` if any configuration was written.
+///
+/// Nothing is written for branches without upstream, and existing configuration is left alone as it may
+/// belong to a local branch that tracks something else.
+pub(crate) fn configure_upstream(repo: &git2::Repository, vbranch: &Branch) -> Result<bool> {
+    let Some(upstream) = &vbranch.upstream else {
+        return Ok(false);
+    };
+    let name = normalize_branch_name(&vbranch.name)?;
+    let config = Config::from(repo);
+    let remote_key = format!("branch.{name}.remote");
+    let merge_key = format!("branch.{name}.merge");
+    if config.get_local(&remote_key)?.is_some() || config.get_local(&merge_key)?.is_some() {
+        return Ok(false);
+    }
+    config.set_local(&remote_key, upstream.remote())?;
+    config.set_local(&merge_key, &format!("refs/heads/{}", upstream.branch()))?;
+    Ok(true)
+}
+
+/// Configure the upstream branches of all virtual branches that are missing it, like those pushed before
+/// it was written on push, and return the names of the branches that were configured.
+pub(crate) fn repair_upstream_config(ctx: &CommandContext) -> Result<Vec<String>> {
+    let mut repaired = Vec::new();
+    for vbranch in ctx.project().virtual_branches().list_all_branches()? {
+        if configure_upstream(ctx.repository(), &vbranch)? {
+            repaired.push(normalize_branch_name(&vbranch.name)?);
+        }
+    }
+    repaired.sort();
+    Ok(repaired)
+}
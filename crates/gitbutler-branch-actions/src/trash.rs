@@ -0,0 +1,108 @@
+//! Keep what's discarded in a trash, so accidentally discarded changes can be restored even if no snapshot
+//! was taken.
+//!
+//! Each entry keeps the content of a file from before and after its changes were discarded. Restoring applies
+//! the difference between them to the file as it is now, so changes made to the file since are kept.
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_branch::{TrashEntry, TrashEntryId, TrashOrigin};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_fs::os_path;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+
+use crate::VirtualBranchesExt;
+
+/// Run `discard`, which discards changes to `files`, each relative to the worktree and optionally along with
+/// the hunk that is discarded, and put the content of each file it changed into the trash as discarded by
+/// `origin`.
+pub(crate) fn discard_into_trash<T>(
+    ctx: &CommandContext,
+    files: &[(PathBuf, Option<String>)],
+    origin: TrashOrigin,
+    discard: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let worktree = ctx.project().worktree_path();
+    let before = files
+        .iter()
+        .map(|(path, _)| read_file(&worktree.join(path)))
+        .collect::<Result<Vec<_>>>()?;
+    let outcome = discard()?;
+
+    let trash = ctx.project().trash();
+    for ((path, hunk_id), before) in files.iter().zip(before) {
+        let after = read_file(&worktree.join(path))?;
+        if before == after {
+            continue;
+        }
+        trash.add(
+            TrashEntry {
+                id: TrashEntryId::generate(),
+                path: path.clone(),
+                discarded_timestamp_ms: now_since_unix_epoch_ms(),
+                origin,
+                hunk_id: hunk_id.clone(),
+                existed_before: before.is_some(),
+                existed_after: after.is_some(),
+            },
+            before.as_deref(),
+            after.as_deref(),
+        )?;
+    }
+    Ok(outcome)
+}
+
+/// Bring back the changes of the trash entry identified by `id` and remove it from the trash.
+///
+/// Fails if the file was changed since in a way that conflicts with them.
+pub(crate) fn restore_trash(
+    ctx: &CommandContext,
+    id: TrashEntryId,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<()> {
+    let trash = ctx.project().trash();
+    let entry = trash.get(id).context(Code::Validation)?;
+    let (before, after) = trash.content(id)?;
+    let worktree_dir = ctx.project().worktree_path();
+    let path = worktree_dir.join(&entry.path);
+    let current = read_file(&path)?;
+
+    let restored = if current == after {
+        before
+    } else {
+        let conflict = || {
+            anyhow!(
+                "{} was changed since, and the discarded changes can't be restored on top",
+                entry.path.display()
+            )
+        };
+        let (Some(before), Some(after), Some(current)) = (before, after, current) else {
+            return Err(conflict()).context(Code::Validation);
+        };
+        let patch = diffy::create_patch_bytes(&after, &before);
+        let restored = gitbutler_diff::write::apply(&current, &patch)
+            .map_err(|_| conflict())
+            .context(Code::Validation)?;
+        Some(restored.into())
+    };
+
+    match restored {
+        Some(content) => {
+            os_path::write_file(&worktree_dir, &path, content)?;
+        }
+        None => os_path::remove_file(&path)?,
+    }
+    trash.remove(id)?;
+    Ok(())
+}
+
+/// Return the content of the file at `path`, or `None` if there is none.
+fn read_file(path: &Path) -> Result<Option<Vec<u8>>> {
+    match std::fs::read(os_path::for_io(path)) {
+        Ok(content) => Ok(Some(content)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
@@ -0,0 +1,163 @@
+//! Bring commits that were pushed to the remote counterpart of an applied branch, like by a colleague
+//! or from another machine, into the branch.
+use anyhow::{anyhow, bail, Context, Result};
+use gitbutler_branch::{BranchEventKind, BranchId};
+use gitbutler_command_context::CommandContext;
+use gitbutler_commit::commit_ext::CommitExt;
+use gitbutler_error::error::Marker;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_repo::{
+    progress::NoProgress, rebase::cherry_rebase_group, shallow, RepoActionsExt, RepositoryExt,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conflicts::RepoConflictsExt,
+    integration::get_workspace_head,
+    r#virtual::{checkout_integrated_head, integrate_with_merge},
+    VirtualBranchesExt,
+};
+
+/// How upstream commits are brought into a branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IntegrationStrategy {
+    /// Replay the commits that only exist locally on top of the upstream commits, like `git pull --rebase`.
+    /// If that conflicts, the upstream commits are merged instead.
+    Rebase,
+    /// Merge the upstream commits into the branch with a merge commit, like `git pull --no-rebase`.
+    Merge,
+}
+
+/// What happened when integrating upstream commits into a branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum IntegrationOutcome {
+    /// There were no upstream commits that the branch doesn't have yet.
+    UpToDate,
+    /// The upstream commits are now part of the branch.
+    #[serde(rename_all = "camelCase")]
+    Integrated {
+        #[serde(with = "gitbutler_serde::oid")]
+        new_head: git2::Oid,
+    },
+    /// The upstream commits conflict with the branch. The conflicts are checked out into the worktree
+    /// to be resolved through the [conflict session](crate::conflicts::ConflictSession).
+    Conflicted,
+}
+
+/// Bring the commits of the upstream branch of the applied branch identified by `branch_id` into it,
+/// with `strategy`.
+pub(crate) fn integrate_upstream(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    strategy: IntegrationStrategy,
+    _perm: &mut WorktreeWritePermission,
+) -> Result<IntegrationOutcome> {
+    ctx.assure_resolved()?;
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let mut branch = vb_state.get_branch_in_workspace(branch_id)?;
+    let target = vb_state.get_default_target()?;
+    let base = branch.base(target.sha);
+
+    let upstream_branch = branch
+        .upstream
+        .clone()
+        .with_context(|| format!("branch {} has no upstream branch", branch.name))?;
+    let upstream_commit = repo
+        .find_reference(&upstream_branch.to_string())
+        .and_then(|reference| reference.peel_to_commit())
+        .with_context(|| format!("upstream branch {upstream_branch} wasn't fetched"))?;
+    if upstream_commit.id() == branch.head {
+        return Ok(IntegrationOutcome::UpToDate);
+    }
+
+    let upstream_commits = ctx.list_commits(upstream_commit.id(), base)?;
+    let branch_commits = ctx.list_commits(branch.head, base)?;
+    let is_known = |commit: &git2::Commit, known: &[git2::Commit]| {
+        known.iter().any(|other| {
+            other.id() == commit.id()
+                || commit
+                    .change_id()
+                    .is_some_and(|change_id| other.change_id() == Some(change_id))
+        })
+    };
+    if upstream_commits
+        .iter()
+        .all(|commit| is_known(commit, &branch_commits))
+    {
+        return Ok(IntegrationOutcome::UpToDate);
+    }
+
+    let old_head = branch.head;
+    let merge_base = shallow::merge_base(
+        ctx,
+        upstream_branch.remote(),
+        base,
+        upstream_commit.id(),
+        &NoProgress,
+    )?;
+    let rebased = match strategy {
+        IntegrationStrategy::Rebase => {
+            let mut local_commits: Vec<_> = branch_commits
+                .iter()
+                .filter(|commit| !is_known(commit, &upstream_commits))
+                .map(|commit| commit.id())
+                .collect();
+            if local_commits.is_empty() {
+                Some(upstream_commit.id())
+            } else {
+                match cherry_rebase_group(ctx, upstream_commit.id(), &mut local_commits) {
+                    Ok(new_head) => Some(new_head),
+                    Err(err) if is_conflict(&err, Marker::BranchConflict) => None,
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        IntegrationStrategy::Merge => {
+            let has_rebased_commits = upstream_commits.iter().any(|commit| {
+                is_known(commit, &branch_commits)
+                    && !branch_commits.iter().any(|other| other.id() == commit.id())
+            });
+            if has_rebased_commits {
+                return Err(anyhow!("Cannot merge rebased commits")
+                    .context("Aborted because the upstream branch has rebased local commits")
+                    .context(Marker::ProjectConflict));
+            }
+            None
+        }
+    };
+
+    let (new_head, event) = match rebased {
+        Some(new_head) => (new_head, BranchEventKind::Rebased { old_head, new_head }),
+        None => match integrate_with_merge(ctx, &mut branch, &upstream_commit, merge_base) {
+            Ok(new_head) => (
+                new_head,
+                BranchEventKind::CommitCreated { commit: new_head },
+            ),
+            Err(err) if is_conflict(&err, Marker::ProjectConflict) => {
+                return Ok(IntegrationOutcome::Conflicted);
+            }
+            Err(err) => return Err(err),
+        },
+    };
+
+    let workspace_tree = repo.find_commit(get_workspace_head(ctx)?)?.tree()?;
+    let new_head_tree = repo.find_commit(new_head)?.tree()?;
+    if repo
+        .merge_trees(&workspace_tree, &new_head_tree, &repo.get_wd_tree()?, None)?
+        .has_conflicts()
+    {
+        bail!(
+            "the upstream commits of {} conflict with uncommitted changes, which have to be committed first",
+            branch.name
+        );
+    }
+    checkout_integrated_head(ctx, &mut branch, new_head, event)?;
+    Ok(IntegrationOutcome::Integrated { new_head })
+}
+
+fn is_conflict(err: &anyhow::Error, marker: Marker) -> bool {
+    err.downcast_ref::<Marker>() == Some(&marker)
+}
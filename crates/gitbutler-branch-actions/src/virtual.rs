@@ -1,41 +1,57 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     path::{Path, PathBuf},
     vec,
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 use bstr::ByteSlice;
-use git2_hooks::HookResult;
 use gitbutler_branch::{
-    dedup, dedup_fmt, reconcile_claims, Branch, BranchId, BranchOwnershipClaims,
-    BranchUpdateRequest, OwnershipClaim, Target, VirtualBranchesHandle,
+    dedup, reconcile_claims, Branch, BranchEventKind, BranchId, BranchOwnershipClaims,
+    BranchUpdateRequest, ClaimOutcome, CommittedHunk, OwnershipClaim, Target,
+    VirtualBranchesHandle,
 };
 use gitbutler_command_context::CommandContext;
-use gitbutler_commit::{commit_ext::CommitExt, commit_headers::HasCommitHeaders};
-use gitbutler_diff::{trees, GitHunk, Hunk};
-use gitbutler_error::error::{Code, Marker};
+use gitbutler_commit::{
+    commit_ext::CommitExt,
+    commit_headers::{CommitHeadersV2, HasCommitHeaders},
+};
+use gitbutler_diff::{trees, ChangeType, DiffOptions, GitHunk, Hunk, HunkHash, HunkSelection};
+use gitbutler_error::error::{AnyhowContextExt, Code, Marker};
 use gitbutler_operating_modes::assure_open_workspace_mode;
-use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_oplog::{record_operation, AuditOperation};
+use gitbutler_project::{access::WorktreeWritePermission, Project};
 use gitbutler_reference::{normalize_branch_name, Refname, RemoteRefname};
 use gitbutler_repo::{
+    commit_graph,
     credentials::Helper,
-    rebase::{cherry_rebase, cherry_rebase_group},
-    LogUntil, RepoActionsExt, RepositoryExt,
+    hooks::{self, Hook},
+    merge_drivers,
+    progress::{Progress, Task},
+    rebase::{cherry_rebase, cherry_rebase_group, find_rebase_conflicts, ConflictedCommit},
+    ForcePush, LogUntil, RepoActionsExt, RepositoryExt,
 };
 use gitbutler_time::time::now_since_unix_epoch_ms;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    author,
+    automation::{self, AutomationEvent},
     branch_manager::BranchManagerExt,
     commit::{commit_to_vbranch_commit, VirtualBranchCommit},
+    commit_guard::{self, FileStamps, FilesChangedDuringCommit},
     conflicts::{self, RepoConflictsExt},
     file::VirtualBranchFile,
     hunk::VirtualBranchHunk,
     integration::get_workspace_head,
+    lane_order::SortingStrategy,
+    linear_history, push_rejection, pushed_commits,
     remote::{branch_to_remote_branch, RemoteBranch},
+    remote_branch_name, secret_scan,
     status::get_applied_status,
+    tracking,
+    workdir_cache::workdir_diff,
     Get, VirtualBranchesExt,
 };
 
@@ -76,6 +92,19 @@ pub struct VirtualBranch {
     /// The fork point between the target branch and the virtual branch
     #[serde(with = "gitbutler_serde::oid_opt", default)]
     pub fork_point: Option<git2::Oid>,
+    /// The commit the branch is pinned to instead of following the target, if any.
+    #[serde(with = "gitbutler_serde::oid_opt", default)]
+    pub pinned_base: Option<git2::Oid>,
+    /// The virtual branch this branch is stacked on, if any.
+    pub parent: Option<BranchId>,
+    /// The remote the branch is pushed to instead of the push remote of the target, if any.
+    pub push_remote_name: Option<String>,
+    /// Patterns of the paths the branch may have changes in, or all paths if empty.
+    pub allowed_paths: Vec<String>,
+    /// The draft description of the pull request of the branch, in Markdown.
+    pub description: String,
+    /// The users to request reviews of the pull request of the branch from.
+    pub reviewers: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize)]
@@ -212,8 +241,7 @@ fn find_base_tree<'a>(
     target_commit: &'a git2::Commit<'a>,
 ) -> Result<git2::Tree<'a>> {
     // find merge base between target_commit and branch_commit
-    let merge_base = repo
-        .merge_base(target_commit.id(), branch_commit.id())
+    let merge_base = commit_graph::merge_base(repo, target_commit.id(), branch_commit.id())
         .context("failed to find merge base")?;
     // turn oid into a commit
     let merge_base_commit = repo
@@ -271,13 +299,20 @@ pub fn list_virtual_branches(
         .get_default_target()
         .context("failed to get default target")?;
 
-    let status = get_applied_status(ctx, Some(perm))?;
+    let mut status = get_applied_status(ctx, Some(perm))?;
+    if let Err(err) = attach_hunk_notes(ctx, &mut status.branches) {
+        tracing::warn!(?err, "failed to attach hunk notes");
+    }
+    if let Err(err) = mark_pinned_hunks(ctx, &mut status.branches) {
+        tracing::warn!(?err, "failed to mark pinned hunks");
+    }
     let max_selected_for_changes = status
         .branches
         .iter()
         .filter_map(|(branch, _)| branch.selected_for_changes)
         .max()
         .unwrap_or(-1);
+    let mailmap = author::mailmap(ctx.repository());
 
     for (branch, mut files) in status.branches {
         let repo = ctx.repository();
@@ -300,13 +335,12 @@ pub fn list_virtual_branches(
         // find upstream commits if we found an upstream reference
         let mut pushed_commits = HashMap::new();
         if let Some(upstream) = &upstram_branch_commit {
-            let merge_base =
-                repo.merge_base(upstream.id(), default_target.sha)
-                    .context(format!(
-                        "failed to find merge base between {} and {}",
-                        upstream.id(),
-                        default_target.sha
-                    ))?;
+            let merge_base = commit_graph::merge_base(repo, upstream.id(), default_target.sha)
+                .context(format!(
+                    "failed to find merge base between {} and {}",
+                    upstream.id(),
+                    default_target.sha
+                ))?;
             for oid in ctx.l(upstream.id(), LogUntil::Commit(merge_base))? {
                 pushed_commits.insert(oid, true);
             }
@@ -315,8 +349,9 @@ pub fn list_virtual_branches(
         let mut is_integrated = false;
         let mut is_remote = false;
 
-        // find all commits on head that are not on target.sha
-        let commits = ctx.log(branch.head, LogUntil::Commit(default_target.sha))?;
+        // find all commits on head that are not on its base
+        let base = branch.base(default_target.sha);
+        let commits = ctx.log(branch.head, LogUntil::Commit(base))?;
         let check_commit = IsCommitIntegrated::new(ctx, &default_target)?;
         let vbranch_commits = commits
             .iter()
@@ -332,12 +367,18 @@ pub fn list_virtual_branches(
                     is_integrated = check_commit.is_integrated(commit)?
                 };
 
-                commit_to_vbranch_commit(ctx, &branch, commit, is_integrated, is_remote)
+                commit_to_vbranch_commit(
+                    ctx,
+                    &branch,
+                    commit,
+                    is_integrated,
+                    is_remote,
+                    mailmap.as_ref(),
+                )
             })
             .collect::<Result<Vec<_>>>()?;
 
-        let merge_base = repo
-            .merge_base(default_target.sha, branch.head)
+        let merge_base = commit_graph::merge_base(repo, base, branch.head)
             .context("failed to find merge base")?;
         let base_current = true;
 
@@ -359,6 +400,10 @@ pub fn list_virtual_branches(
                 .cmp(path_claim_positions.get(&b.path).unwrap_or(&usize::MAX))
         });
 
+        for file in &mut files {
+            file.outside_allowed_paths = !branch.allows_path(&file.path);
+        }
+
         let requires_force = is_requires_force(ctx, &branch)?;
 
         let fork_point = commits
@@ -388,16 +433,98 @@ pub fn list_virtual_branches(
             head: branch.head,
             merge_base,
             fork_point,
+            pinned_base: branch.pinned_base,
+            parent: branch.parent,
+            push_remote_name: branch.push_remote_name,
+            allowed_paths: branch.allowed_paths,
+            description: branch.description,
+            reviewers: branch.reviewers,
         };
         branches.push(branch);
     }
 
     let mut branches = branches_with_large_files_abridged(branches);
-    branches.sort_by(|a, b| a.order.cmp(&b.order));
+    ctx.project().settings.lane_order.sort(&mut branches);
 
     Ok((branches, status.skipped_files))
 }
 
+/// Set the notes of all hunks in `branches`, and drop notes of hunks that are gone.
+fn attach_hunk_notes(
+    ctx: &CommandContext,
+    branches: &mut [(Branch, Vec<VirtualBranchFile>)],
+) -> Result<()> {
+    let as_hunk = |hunk: &VirtualBranchHunk| Hunk {
+        hash: Some(hunk.hash),
+        start: hunk.start,
+        end: hunk.end,
+    };
+    let hunks: Vec<(PathBuf, Hunk)> = branches
+        .iter()
+        .flat_map(|(_, files)| files)
+        .flat_map(|file| &file.hunks)
+        .map(|hunk| (hunk.file_path.clone(), as_hunk(hunk)))
+        .collect();
+    let notes = ctx
+        .project()
+        .hunk_notes()
+        .reconcile(hunks.iter().map(|(path, hunk)| (path.as_path(), hunk)))?;
+    if notes.is_empty() {
+        return Ok(());
+    }
+    for hunk in branches
+        .iter_mut()
+        .flat_map(|(_, files)| files)
+        .flat_map(|file| &mut file.hunks)
+    {
+        let id = as_hunk(hunk).to_string();
+        hunk.note = notes
+            .iter()
+            .find(|note| note.file_path == hunk.file_path && note.hunk == id)
+            .map(|note| note.text.clone());
+    }
+    Ok(())
+}
+
+/// Mark the hunks of `branches` that are pinned to their branch.
+fn mark_pinned_hunks(
+    ctx: &CommandContext,
+    branches: &mut [(Branch, Vec<VirtualBranchFile>)],
+) -> Result<()> {
+    let pins = ctx.project().hunk_pins().list()?;
+    if pins.is_empty() {
+        return Ok(());
+    }
+    for (branch, files) in branches.iter_mut() {
+        for hunk in files.iter_mut().flat_map(|file| &mut file.hunks) {
+            let as_hunk = Hunk {
+                hash: Some(hunk.hash),
+                start: hunk.start,
+                end: hunk.end,
+            };
+            hunk.pinned = pins
+                .iter()
+                .any(|pin| pin.branch_id == branch.id && pin.covers(&hunk.file_path, &as_hunk));
+        }
+    }
+    Ok(())
+}
+
+/// Hide the hunks of `branches` whose changes are all ignored by `options`, along with the files
+/// that have no hunks left. Ownership isn't affected, so hidden hunks still belong to their branch.
+pub(crate) fn hide_ignored_hunks(branches: &mut [VirtualBranch], options: &DiffOptions) {
+    for branch in branches {
+        branch.files.retain_mut(|file| {
+            if file.hunks.is_empty() {
+                return true;
+            }
+            file.hunks
+                .retain(|hunk| hunk.binary || !options.ignores(hunk.diff.as_ref()));
+            !file.hunks.is_empty()
+        });
+    }
+}
+
 fn branches_with_large_files_abridged(mut branches: Vec<VirtualBranch>) -> Vec<VirtualBranch> {
     for branch in &mut branches {
         for file in &mut branch.files {
@@ -436,9 +563,7 @@ fn is_requires_force(ctx: &CommandContext, branch: &Branch) -> Result<bool> {
         .find_commit(reference)
         .context("failed to find upstream commit")?;
 
-    let merge_base = ctx
-        .repository()
-        .merge_base(upstream_commit.id(), branch.head)?;
+    let merge_base = commit_graph::merge_base(ctx.repository(), upstream_commit.id(), branch.head)?;
 
     Ok(merge_base != upstream_commit.id())
 }
@@ -495,6 +620,11 @@ pub fn integrate_upstream_commits(ctx: &CommandContext, branch_id: BranchId) ->
         .iter()
         .filter_map(|c| c.change_id())
         .collect::<Vec<_>>();
+    // Commits that were cherry-picked into the branch are known already.
+    let picked_commit_ids = branch_commits
+        .iter()
+        .filter_map(|c| c.cherry_picked_from())
+        .collect::<Vec<_>>();
 
     let mut unknown_commits: Vec<git2::Oid> = upstream_commits
         .iter()
@@ -502,6 +632,7 @@ pub fn integrate_upstream_commits(ctx: &CommandContext, branch_id: BranchId) ->
             (!c.change_id()
                 .is_some_and(|cid| branch_change_ids.contains(&cid)))
                 && !branch_commit_ids.contains(&c.id())
+                && !picked_commit_ids.contains(&c.id())
         })
         .map(|c| c.id())
         .collect::<Vec<_>>();
@@ -559,8 +690,30 @@ pub fn integrate_upstream_commits(ctx: &CommandContext, branch_id: BranchId) ->
     };
 
     let new_head = integration_result?;
-    let new_head_tree = repo.find_commit(new_head)?.tree()?;
+    let old_head = branch.head;
+    let event = if can_use_force {
+        BranchEventKind::Rebased { old_head, new_head }
+    } else {
+        BranchEventKind::CommitCreated { commit: new_head }
+    };
+    checkout_integrated_head(ctx, &mut branch, new_head, event)
+}
+
+/// Point `branch` to `new_head`, which integrated upstream commits, and bring its changes into the worktree
+/// while keeping uncommitted changes. `event` is recorded if the branch was updated.
+///
+/// If the changes conflict with uncommitted changes, they are checked out with conflict markers and the
+/// branch is left as it was.
+pub(crate) fn checkout_integrated_head(
+    ctx: &CommandContext,
+    branch: &mut Branch,
+    new_head: git2::Oid,
+    event: BranchEventKind,
+) -> Result<()> {
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
     let head_commit = repo.find_commit(new_head)?;
+    let new_head_tree = head_commit.tree()?;
 
     let wd_tree = ctx.repository().get_wd_tree()?;
     let integration_tree = repo.find_commit(get_workspace_head(ctx)?)?.tree()?;
@@ -575,14 +728,16 @@ pub fn integrate_upstream_commits(ctx: &CommandContext, branch_id: BranchId) ->
             .checkout()?;
     } else {
         branch.head = new_head;
-        branch.tree = head_commit.tree()?.id();
+        branch.tree = new_head_tree.id();
         vb_state.set_branch(branch.clone())?;
+        record_branch_event(ctx, branch.id, event);
         repo.checkout_index_builder(&mut merge_index)
             .force()
             .checkout()?;
     };
 
     crate::integration::update_gitbutler_integration(&vb_state, ctx)?;
+    hooks::run(ctx, Hook::PostMerge, &["0"], &[])?;
     Ok(())
 }
 
@@ -600,15 +755,24 @@ pub(crate) fn integrate_with_merge(
     upstream_commit: &git2::Commit,
     merge_base: git2::Oid,
 ) -> Result<git2::Oid> {
+    let upstream_branch = branch.upstream.as_ref().context("upstream not found")?;
+    linear_history::ensure_merge_allowed(
+        ctx,
+        &format!(
+            "merge {}/{} into {}",
+            upstream_branch.remote(),
+            upstream_branch.branch(),
+            branch.name
+        ),
+    )?;
     let wd_tree = ctx.repository().get_wd_tree()?;
     let repo = ctx.repository();
     let remote_tree = upstream_commit.tree().context("failed to get tree")?;
-    let upstream_branch = branch.upstream.as_ref().context("upstream not found")?;
     // let merge_tree = repo.find_commit(merge_base).and_then(|c| c.tree())?;
     let merge_tree = repo.find_commit(merge_base)?;
     let merge_tree = merge_tree.tree()?;
 
-    let mut merge_index = repo.merge_trees(&merge_tree, &wd_tree, &remote_tree, None)?;
+    let mut merge_index = merge_drivers::merge_trees(repo, &merge_tree, &wd_tree, &remote_tree)?;
 
     if merge_index.has_conflicts() {
         let conflicts = merge_index.conflicts()?;
@@ -618,6 +782,7 @@ pub(crate) fn integrate_with_merge(
             .map(|our| gix::path::try_from_bstr(Cow::Owned(our.path.into())))
             .collect::<Result<Vec<_>, _>>()?;
         conflicts::mark(ctx, merge_conflicts, Some(upstream_commit.id()))?;
+        conflicts::record_stages(ctx, &merge_index)?;
         repo.checkout_index_builder(&mut merge_index)
             .allow_conflicts()
             .conflict_style_merge()
@@ -648,8 +813,36 @@ pub fn update_branch(ctx: &CommandContext, branch_update: &BranchUpdateRequest)
     let vb_state = ctx.project().virtual_branches();
     let mut branch = vb_state.get_branch_in_workspace(branch_update.id)?;
 
+    if let Some(allowed_paths) = &branch_update.allowed_paths {
+        branch.allowed_paths = allowed_paths
+            .iter()
+            .map(|pattern| pattern.trim())
+            .filter(|pattern| !pattern.is_empty())
+            .map(ToOwned::to_owned)
+            .collect();
+    }
+
     if let Some(ownership) = &branch_update.ownership {
-        set_ownership(&vb_state, &mut branch, ownership).context("failed to set ownership")?;
+        ensure_allowed_paths(&branch, ownership)?;
+        ensure_not_pinned_elsewhere(ctx, &branch, ownership)?;
+        let claim_outcomes =
+            set_ownership(&vb_state, &mut branch, ownership).context("failed to set ownership")?;
+        for claim_outcome in claim_outcomes {
+            if claim_outcome.removed_claims.is_empty() {
+                continue;
+            }
+            let event = BranchEventKind::HunksMoved {
+                from: claim_outcome.updated_branch.id,
+                to: branch.id,
+                paths: claim_outcome
+                    .removed_claims
+                    .into_iter()
+                    .map(|claim| claim.file_path)
+                    .collect(),
+            };
+            record_branch_event(ctx, claim_outcome.updated_branch.id, event.clone());
+            record_branch_event(ctx, branch.id, event);
+        }
     }
 
     if let Some(name) = &branch_update.name {
@@ -672,18 +865,13 @@ pub fn update_branch(ctx: &CommandContext, branch_update: &BranchUpdateRequest)
 
     if let Some(updated_upstream) = &branch_update.upstream {
         let default_target = vb_state.get_default_target()?;
-        let upstream_remote = match default_target.push_remote_name {
-            Some(remote) => remote.clone(),
-            None => default_target.branch.remote().to_owned(),
-        };
+        let upstream_remote = push_remote(ctx.project(), &default_target, &branch);
 
-        let remote_branch = format!(
-            "refs/remotes/{}/{}",
-            upstream_remote,
-            normalize_branch_name(updated_upstream)?
-        )
-        .parse::<RemoteRefname>()
-        .unwrap();
+        let name = normalize_branch_name(updated_upstream)?;
+        remote_branch_name::validate(ctx, &name)?;
+        let remote_branch = format!("refs/remotes/{upstream_remote}/{name}")
+            .parse::<RemoteRefname>()
+            .unwrap();
         branch.upstream = Some(remote_branch);
     };
 
@@ -691,6 +879,14 @@ pub fn update_branch(ctx: &CommandContext, branch_update: &BranchUpdateRequest)
         branch.notes = notes;
     };
 
+    if let Some(description) = branch_update.description.clone() {
+        branch.description = description;
+    };
+
+    if let Some(reviewers) = &branch_update.reviewers {
+        branch.reviewers = normalize_reviewers(reviewers)?;
+    };
+
     if let Some(order) = branch_update.order {
         branch.order = order;
     };
@@ -720,6 +916,88 @@ pub fn update_branch(ctx: &CommandContext, branch_update: &BranchUpdateRequest)
     Ok(branch)
 }
 
+/// Return `reviewers` without the `@` they may be mentioned with, blank names and duplicates, or fail if a
+/// name contains whitespace.
+fn normalize_reviewers(reviewers: &[String]) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for reviewer in reviewers {
+        let reviewer = reviewer.trim();
+        let reviewer = reviewer.strip_prefix('@').unwrap_or(reviewer);
+        if reviewer.is_empty() || normalized.iter().any(|known| known == reviewer) {
+            continue;
+        }
+        if reviewer.contains(char::is_whitespace) {
+            return Err(
+                anyhow!("'{reviewer}' isn't a valid reviewer name").context(Code::Validation)
+            );
+        }
+        normalized.push(reviewer.to_owned());
+    }
+    Ok(normalized)
+}
+
+/// Fail if `ownership` claims files for `branch` which it doesn't own yet and which are outside its
+/// allowed paths. Files it already owns are kept, like ones with changes that depend on the branch.
+fn ensure_allowed_paths(branch: &Branch, ownership: &BranchOwnershipClaims) -> Result<()> {
+    let disallowed = ownership
+        .claims
+        .iter()
+        .map(|claim| &claim.file_path)
+        .find(|path| {
+            !branch.allows_path(path)
+                && !branch
+                    .ownership
+                    .claims
+                    .iter()
+                    .any(|claim| &claim.file_path == *path)
+        });
+    if let Some(path) = disallowed {
+        return Err(anyhow!(
+            "branch '{}' only allows changes to {}, but not to {}",
+            branch.name,
+            branch.allowed_paths.join(", "),
+            path.display()
+        )
+        .context(Code::Validation));
+    }
+    Ok(())
+}
+
+/// Fail if `ownership` claims hunks for `branch` which are pinned to another branch.
+pub(crate) fn ensure_not_pinned_elsewhere(
+    ctx: &CommandContext,
+    branch: &Branch,
+    ownership: &BranchOwnershipClaims,
+) -> Result<()> {
+    let pins = ctx.project().hunk_pins().list()?;
+    let pinned_elsewhere = ownership.claims.iter().find_map(|claim| {
+        claim.hunks.iter().find_map(|hunk| {
+            pins.iter()
+                .find(|pin| pin.branch_id != branch.id && pin.covers(&claim.file_path, hunk))
+                .map(|pin| (&claim.file_path, hunk, pin.branch_id))
+        })
+    });
+    if let Some((path, hunk, branch_id)) = pinned_elsewhere {
+        let pinned_to = ctx
+            .project()
+            .virtual_branches()
+            .get_branch(branch_id)
+            .map_or_else(
+                |_| "another branch".to_owned(),
+                |branch| format!("'{}'", branch.name),
+            );
+        return Err(anyhow!(
+            "lines {}-{} of {} are pinned to {pinned_to}, and have to be unpinned to be moved to '{}'",
+            hunk.start,
+            hunk.end,
+            path.display(),
+            branch.name
+        )
+        .context(Code::Validation));
+    }
+    Ok(())
+}
+
 pub(crate) fn ensure_selected_for_changes(vb_state: &VirtualBranchesHandle) -> Result<()> {
     let mut virtual_branches = vb_state
         .list_branches_in_workspace()
@@ -749,10 +1027,10 @@ pub(crate) fn set_ownership(
     vb_state: &VirtualBranchesHandle,
     target_branch: &mut Branch,
     ownership: &BranchOwnershipClaims,
-) -> Result<()> {
+) -> Result<Vec<ClaimOutcome>> {
     if target_branch.ownership.eq(ownership) {
         // nothing to update
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     let virtual_branches = vb_state
@@ -772,7 +1050,7 @@ pub(crate) fn set_ownership(
     // TODO: remove mutable reference to target_branch
     target_branch.ownership = ownership.clone();
 
-    Ok(())
+    Ok(claim_outcomes)
 }
 
 pub type BranchStatus = HashMap<PathBuf, Vec<gitbutler_diff::GitHunk>>;
@@ -858,82 +1136,70 @@ pub fn commit(
     ownership: Option<&BranchOwnershipClaims>,
     run_hooks: bool,
 ) -> Result<git2::Oid> {
+    commit_with_selections(
+        ctx,
+        branch_id,
+        message,
+        ownership,
+        &BTreeMap::new(),
+        run_hooks,
+        false,
+    )
+}
+
+/// Like [`commit()`], but hunks in `selections` are only committed partially, limited to the selected lines.
+/// Hunks with a selection are committed even if they aren't part of `ownership`.
+///
+/// New files can only be committed as a whole, so selecting lines of them fails with [`Code::Validation`].
+///
+/// If the project checks for secrets, committing fails if the changes contain any, unless `allow_secrets` is `true`.
+pub fn commit_with_selections(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    message: &str,
+    ownership: Option<&BranchOwnershipClaims>,
+    selections: &BTreeMap<PathBuf, Vec<HunkSelection>>,
+    run_hooks: bool,
+    allow_secrets: bool,
+) -> Result<git2::Oid> {
+    ensure_no_selections_in_new_files(ctx, branch_id, selections)?;
+
     let mut message_buffer = message.to_owned();
 
     if run_hooks {
-        let hook_result = git2_hooks::hooks_commit_msg(
-            ctx.repository(),
-            Some(&["../.husky"]),
-            &mut message_buffer,
-        )
-        .context("failed to run hook")
-        .context(Code::CommitHookFailed)?;
+        hooks::run_commit_msg(ctx, &mut message_buffer)?;
+    }
+    crate::commit_message::check_conventions(&ctx.project().commit_conventions, &message_buffer)?;
+    crate::message_check::reject_errors(&message_buffer)?;
 
-        if let HookResult::RunNotSuccessful { stdout, .. } = hook_result {
-            return Err(anyhow!("commit-msg hook rejected: {}", stdout.trim())
-                .context(Code::CommitHookFailed));
-        }
+    if run_hooks {
+        hooks::run(ctx, Hook::PreCommit, &[], &[])?;
+    }
 
-        let hook_result = git2_hooks::hooks_pre_commit(ctx.repository(), Some(&["../.husky"]))
-            .context("failed to run hook")
-            .context(Code::CommitHookFailed)?;
+    let message = &message_buffer;
 
-        if let HookResult::RunNotSuccessful { stdout, .. } = hook_result {
+    // Files that change while the commit is built from them are read again once they settled.
+    let mut attempt = 1;
+    let (ref mut branch, tree_oid, committed_hunks) = loop {
+        let tree = build_commit_tree(ctx, branch_id, ownership, selections)?;
+        let mut changed = tree.stamps.changed(ctx)?;
+        changed.extend(commit_guard::changed_since_status(
+            ctx,
+            &tree.committed_hashes,
+        )?);
+        if changed.is_empty() {
+            break (tree.branch, tree.id, tree.committed_hunks);
+        }
+        if attempt == commit_guard::MAX_ATTEMPTS {
+            changed.sort();
+            changed.dedup();
             return Err(
-                anyhow!("commit hook rejected: {}", stdout.trim()).context(Code::CommitHookFailed)
+                anyhow::Error::from(FilesChangedDuringCommit { paths: changed })
+                    .context(Code::FilesChanged),
             );
         }
-    }
-
-    let message = &message_buffer;
-
-    // get the files to commit
-    let statuses = get_applied_status(ctx, None)
-        .context("failed to get status by branch")?
-        .branches;
-
-    let (ref mut branch, files) = statuses
-        .into_iter()
-        .find(|(branch, _)| branch.id == branch_id)
-        .with_context(|| format!("branch {branch_id} not found"))?;
-
-    update_conflict_markers(ctx, files.clone()).context(Code::CommitMergeConflictFailure)?;
-
-    ctx.assure_unconflicted()
-        .context(Code::CommitMergeConflictFailure)?;
-
-    let tree_oid = if let Some(ownership) = ownership {
-        let files = files.into_iter().filter_map(|file| {
-            let hunks = file
-                .hunks
-                .into_iter()
-                .filter(|hunk| {
-                    let hunk: GitHunk = hunk.clone().into();
-                    ownership
-                        .claims
-                        .iter()
-                        .find(|f| f.file_path.eq(&file.path))
-                        .map_or(false, |f| {
-                            f.hunks.iter().any(|h| {
-                                h.start == hunk.new_start
-                                    && h.end == hunk.new_start + hunk.new_lines
-                            })
-                        })
-                })
-                .collect::<Vec<_>>();
-            if hunks.is_empty() {
-                None
-            } else {
-                Some((file.path, hunks))
-            }
-        });
-        gitbutler_diff::write::hunks_onto_commit(ctx, branch.head, files)?
-    } else {
-        let files = files
-            .into_iter()
-            .map(|file| (file.path, file.hunks))
-            .collect::<Vec<(PathBuf, Vec<VirtualBranchHunk>)>>();
-        gitbutler_diff::write::hunks_onto_commit(ctx, branch.head, files)?
+        attempt += 1;
+        std::thread::sleep(commit_guard::SETTLE_TIME);
     };
 
     let git_repository = ctx.repository();
@@ -943,6 +1209,7 @@ pub fn commit(
     let tree = git_repository
         .find_tree(tree_oid)
         .context(format!("failed to find tree {:?}", tree_oid))?;
+    secret_scan::ensure_commit_is_clean(ctx, &parent_commit.tree()?, &tree, allow_secrets)?;
 
     // now write a commit, using a merge parent if it exists
     let extra_merge_parent = conflicts::merge_parent(ctx)
@@ -960,13 +1227,36 @@ pub fn commit(
                 .context(Code::CommitMergeConflictFailure)?;
             commit_oid
         }
-        None => ctx.commit(message, &tree, &[&parent_commit], None)?,
+        None => match conflicts::cherry_pick(ctx)
+            .context("failed to get cherry-picked commit")
+            .context(Code::CommitMergeConflictFailure)?
+        {
+            Some(picked) => {
+                let headers = CommitHeadersV2 {
+                    cherry_picked_from: Some(picked.to_string()),
+                    ..CommitHeadersV2::new()
+                };
+                let commit_oid = ctx.commit(message, &tree, &[&parent_commit], Some(headers))?;
+                conflicts::clear(ctx)
+                    .context("failed to clear conflicts")
+                    .context(Code::CommitMergeConflictFailure)?;
+                commit_oid
+            }
+            None => {
+                let commit_oid = ctx.commit(message, &tree, &[&parent_commit], None)?;
+                // Resolving the conflicts of a revert ends with committing it.
+                if conflicts::reverted(ctx)?.is_some() {
+                    conflicts::clear(ctx)
+                        .context("failed to clear conflicts")
+                        .context(Code::CommitMergeConflictFailure)?;
+                }
+                commit_oid
+            }
+        },
     };
 
     if run_hooks {
-        git2_hooks::hooks_post_commit(ctx.repository(), Some(&["../.husky"]))
-            .context("failed to run hook")
-            .context(Code::CommitHookFailed)?;
+        hooks::run(ctx, Hook::PostCommit, &[], &[])?;
     }
 
     let vb_state = ctx.project().virtual_branches();
@@ -974,6 +1264,35 @@ pub fn commit(
     branch.head = commit_oid;
     branch.updated_timestamp_ms = gitbutler_time::time::now_ms();
     vb_state.set_branch(branch.clone())?;
+    record_branch_event(
+        ctx,
+        branch.id,
+        BranchEventKind::CommitCreated { commit: commit_oid },
+    );
+    record_operation(
+        ctx.project(),
+        ctx.repository(),
+        AuditOperation::CommitCreated {
+            branch_id: branch.id,
+            branch_name: branch.name.clone(),
+            commit: commit_oid,
+        },
+    );
+    automation::trigger(
+        ctx.project(),
+        AutomationEvent::CommitCreated {
+            branch_id: branch.id,
+            branch_name: branch.name.clone(),
+            commit: commit_oid,
+        },
+    );
+    if let Err(err) =
+        ctx.project()
+            .commit_provenance()
+            .record(commit_oid, branch.id, committed_hunks)
+    {
+        tracing::warn!(%commit_oid, ?err, "failed to record commit provenance");
+    }
 
     crate::integration::update_gitbutler_integration(&vb_state, ctx)
         .context("failed to update gitbutler integration")?;
@@ -981,95 +1300,423 @@ pub fn commit(
     Ok(commit_oid)
 }
 
-pub(crate) fn push(
+/// The tree of a commit that is about to be created, along with what it was built from.
+/// Fail with [`Code::Validation`] if `selections` pick lines of files that aren't in the head commit
+/// of the branch identified by `branch_id`, before any hooks run.
+fn ensure_no_selections_in_new_files(
     ctx: &CommandContext,
     branch_id: BranchId,
-    with_force: bool,
-    credentials: &Helper,
-    askpass: Option<Option<BranchId>>,
+    selections: &BTreeMap<PathBuf, Vec<HunkSelection>>,
 ) -> Result<()> {
-    let vb_state = ctx.project().virtual_branches();
+    let mut selected_paths = selections
+        .iter()
+        .filter(|(_, selections)| !selections.is_empty())
+        .map(|(path, _)| path)
+        .peekable();
+    if selected_paths.peek().is_none() {
+        return Ok(());
+    }
+    let branch = ctx
+        .project()
+        .virtual_branches()
+        .get_branch_in_workspace(branch_id)?;
+    let head_tree = ctx.repository().find_commit(branch.head)?.tree()?;
+    for path in selected_paths {
+        if head_tree.get_path(path).is_err() {
+            return Err(anyhow!(
+                "cannot commit selected lines of new file {}",
+                path.display()
+            ))
+            .context(Code::Validation);
+        }
+    }
+    Ok(())
+}
 
-    let mut vbranch = vb_state.get_branch_in_workspace(branch_id)?;
-    let remote_branch = if let Some(upstream_branch) = &vbranch.upstream {
-        upstream_branch.clone()
-    } else {
-        let default_target = vb_state.get_default_target()?;
-        let upstream_remote = match default_target.push_remote_name {
-            Some(remote) => remote.clone(),
-            None => default_target.branch.remote().to_owned(),
-        };
+struct CommitTree {
+    branch: Branch,
+    id: git2::Oid,
+    committed_hunks: Vec<CommittedHunk>,
+    /// The hashes of the hunks that were committed, as the status listed them.
+    committed_hashes: BTreeMap<PathBuf, Vec<HunkHash>>,
+    /// The files that the committed hunks are in, as they were before building the tree.
+    stamps: FileStamps,
+}
 
-        let remote_branch = format!(
-            "refs/remotes/{}/{}",
-            upstream_remote,
-            normalize_branch_name(&vbranch.name)?
-        )
-        .parse::<RemoteRefname>()
-        .context("failed to parse remote branch name")?;
+/// Build the tree of a commit onto the branch identified by `branch_id`, with the uncommitted changes
+/// of the branch as the status lists them, limited to `ownership` and `selections` as in [`commit_with_selections()`].
+fn build_commit_tree(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    ownership: Option<&BranchOwnershipClaims>,
+    selections: &BTreeMap<PathBuf, Vec<HunkSelection>>,
+) -> Result<CommitTree> {
+    // get the files to commit
+    let statuses = get_applied_status(ctx, None)
+        .context("failed to get status by branch")?
+        .branches;
 
-        let remote_branches = ctx.repository().remote_branches()?;
-        let existing_branches = remote_branches
-            .iter()
-            .map(RemoteRefname::branch)
-            .map(str::to_lowercase) // git is weird about case sensitivity here, assume not case sensitive
-            .collect::<Vec<_>>();
+    let (branch, files) = statuses
+        .into_iter()
+        .find(|(branch, _)| branch.id == branch_id)
+        .with_context(|| format!("branch {branch_id} not found"))?;
 
-        remote_branch.with_branch(&dedup_fmt(
-            &existing_branches
-                .iter()
-                .map(String::as_str)
-                .collect::<Vec<_>>(),
-            remote_branch.branch(),
-            "-",
-        ))
-    };
+    update_conflict_markers(ctx, files.clone()).context(Code::CommitMergeConflictFailure)?;
 
-    ctx.push(
-        &vbranch.head,
-        &remote_branch,
-        with_force,
-        credentials,
-        None,
-        askpass,
-    )?;
+    ctx.assure_unconflicted()
+        .context(Code::CommitMergeConflictFailure)?;
 
-    vbranch.upstream = Some(remote_branch.clone());
-    vbranch.upstream_head = Some(vbranch.head);
-    vb_state
-        .set_branch(vbranch.clone())
-        .context("failed to write target branch after push")?;
-    ctx.fetch(
-        remote_branch.remote(),
-        credentials,
-        askpass.map(|_| "modal".to_string()),
-    )?;
+    let mut committed_hunks = Vec::new();
+    let mut committed_hashes: BTreeMap<PathBuf, Vec<HunkHash>> = BTreeMap::new();
+
+    let files_to_write: Vec<(PathBuf, Vec<GitHunk>)> =
+        if ownership.is_some() || !selections.is_empty() {
+            let mut selected_files = Vec::new();
+            for file in files {
+                let claim = ownership
+                    .map(|ownership| ownership.claims.iter().find(|f| f.file_path.eq(&file.path)));
+                let file_selections = selections.get(&file.path);
+                let mut hunks = Vec::new();
+                for hunk in file.hunks {
+                    let selection = file_selections
+                        .and_then(|selections| selections.iter().find(|s| s.hunk_id == hunk.id));
+                    let committed_hunk = as_committed_hunk(&hunk);
+                    let hash = hunk.hash;
+                    let hunk: GitHunk = hunk.into();
+                    match selection {
+                        Some(selection) => {
+                            if hunk.change_type == ChangeType::Added {
+                                return Err(anyhow!(
+                                    "cannot commit selected lines of new file {}",
+                                    file.path.display()
+                                ))
+                                .context(Code::Validation);
+                            }
+                            committed_hunks.push(committed_hunk);
+                            committed_hashes
+                                .entry(file.path.clone())
+                                .or_default()
+                                .push(hash);
+                            hunks.extend(hunk.select_lines(&selection.lines));
+                        }
+                        None => {
+                            let is_claimed = claim.map_or(true, |claim| {
+                                claim.map_or(false, |f| {
+                                    f.hunks.iter().any(|h| {
+                                        h.start == hunk.new_start
+                                            && h.end == hunk.new_start + hunk.new_lines
+                                    })
+                                })
+                            });
+                            if is_claimed {
+                                committed_hunks.push(committed_hunk);
+                                committed_hashes
+                                    .entry(file.path.clone())
+                                    .or_default()
+                                    .push(hash);
+                                hunks.push(hunk);
+                            }
+                        }
+                    }
+                }
+                if !hunks.is_empty() {
+                    selected_files.push((file.path, hunks));
+                }
+            }
+            selected_files
+        } else {
+            let files = files
+                .into_iter()
+                .map(|file| (file.path, file.hunks))
+                .collect::<Vec<(PathBuf, Vec<VirtualBranchHunk>)>>();
+            committed_hunks.extend(
+                files
+                    .iter()
+                    .flat_map(|(_, hunks)| hunks)
+                    .map(as_committed_hunk),
+            );
+            committed_hashes.extend(
+                files.iter().map(|(path, hunks)| {
+                    (path.clone(), hunks.iter().map(|hunk| hunk.hash).collect())
+                }),
+            );
+            files
+                .into_iter()
+                .map(|(path, hunks)| (path, hunks.into_iter().map(Into::into).collect()))
+                .collect()
+        };
+    // Whatever happens to the files from here on is noticed, as is what happened to them since the status.
+    let stamps = FileStamps::read(ctx, committed_hashes.keys())?;
+    let id = gitbutler_diff::write::hunks_onto_commit(ctx, branch.head, files_to_write)?;
+    Ok(CommitTree {
+        branch,
+        id,
+        committed_hunks,
+        committed_hashes,
+        stamps,
+    })
+}
 
-    Ok(())
+fn as_committed_hunk(hunk: &VirtualBranchHunk) -> CommittedHunk {
+    let id = Hunk {
+        hash: Some(hunk.hash),
+        start: hunk.start,
+        end: hunk.end,
+    };
+    CommittedHunk {
+        file_path: hunk.file_path.clone(),
+        hunk: id.to_string(),
+    }
 }
 
-struct IsCommitIntegrated<'repo> {
-    repo: &'repo git2::Repository,
-    target_commit_id: git2::Oid,
-    remote_head_id: git2::Oid,
-    upstream_commits: Vec<git2::Oid>,
-    /// A repository opened at the same path as `repo`, but with an in-memory ODB attached
-    /// to avoid writing intermediate objects.
-    inmemory_repo: git2::Repository,
+/// Return the name of the remote that `vbranch` is pushed to, which is its own push remote, or the push remote
+/// of `default_target`, or the remote of `default_target`.
+pub(crate) fn push_remote(project: &Project, default_target: &Target, vbranch: &Branch) -> String {
+    vbranch
+        .push_remote_name
+        .clone()
+        .or_else(|| default_target.push_remote_name.clone())
+        .or_else(|| project.settings.default_push_remote.clone())
+        .unwrap_or_else(|| default_target.branch.remote().to_owned())
 }
 
-impl<'repo> IsCommitIntegrated<'repo> {
-    fn new(ctx: &'repo CommandContext, target: &Target) -> anyhow::Result<Self> {
-        let remote_branch = ctx
-            .repository()
-            .find_branch_by_refname(&target.branch.clone().into())?
-            .ok_or(anyhow!("failed to get branch"))?;
-        let remote_head = remote_branch.get().peel_to_commit()?;
-        let upstream_commits = ctx.l(remote_head.id(), LogUntil::Commit(target.sha))?;
-        let inmemory_repo = ctx.repository().in_memory_repo()?;
-        Ok(Self {
-            repo: ctx.repository(),
-            target_commit_id: target.sha,
+/// Add `event` to the activity feed of the branch identified by `branch_id`.
+/// Failures are only logged as the operation the event describes already happened.
+pub(crate) fn record_branch_event(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    event: BranchEventKind,
+) {
+    if let Err(err) = ctx.project().branch_activity().record(branch_id, event) {
+        tracing::warn!(%branch_id, ?err, "failed to record branch activity");
+    }
+}
+
+/// Return the remote branch that `vbranch` is pushed to, which is its upstream branch or a new branch
+/// named after it on its [push remote](push_remote()).
+pub(crate) fn push_target(ctx: &CommandContext, vbranch: &Branch) -> Result<RemoteRefname> {
+    let vb_state = ctx.project().virtual_branches();
+    if let Some(upstream_branch) = &vbranch.upstream {
+        return Ok(upstream_branch.clone());
+    }
+
+    let default_target = vb_state.get_default_target()?;
+    let upstream_remote = push_remote(ctx.project(), &default_target, vbranch);
+
+    let remote_branches = ctx.repository().remote_branches()?;
+    let existing_branches = remote_branches
+        .iter()
+        .map(RemoteRefname::branch)
+        .map(str::to_lowercase) // git is weird about case sensitivity here, assume not case sensitive
+        .collect::<Vec<_>>();
+    let name = remote_branch_name::generate(
+        ctx,
+        &vbranch.name,
+        &existing_branches
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>(),
+    )?;
+
+    format!("refs/remotes/{upstream_remote}/{name}")
+        .parse::<RemoteRefname>()
+        .context("failed to parse remote branch name")
+}
+
+/// What a forced push expects the upstream branch to be at before replacing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Lease {
+    /// Replace the upstream branch no matter where it is.
+    None,
+    /// Only replace the upstream branch if it didn't change since it was fetched last.
+    LastFetched,
+    /// Only replace the upstream branch if it's at the given commit, or doesn't exist if `None`.
+    At(Option<git2::Oid>),
+}
+
+/// Return the commit the upstream branch of the virtual branch with `branch_id` was at when it was
+/// fetched last, or `None` if there is no such branch.
+pub(crate) fn last_fetched_upstream_head(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+) -> Result<Option<git2::Oid>> {
+    let vbranch = ctx
+        .project()
+        .virtual_branches()
+        .get_branch_in_workspace(branch_id)?;
+    let remote_branch = push_target(ctx, &vbranch)?;
+    Ok(ctx
+        .repository()
+        .refname_to_id(&remote_branch.to_string())
+        .ok())
+}
+
+/// Push the virtual branch with `branch_id` to its upstream branch.
+///
+/// A forced push replaces the upstream branch, but only if it's where `lease` expects it to be. If the
+/// push is rejected, the commits it would have overwritten are returned as
+/// [`PushRejection`](crate::PushRejection).
+///
+/// If the project checks for secrets, pushing fails if the commits to send contain any, unless
+/// `allow_secrets` is `true`.
+///
+/// Sending the commits and fetching the remote afterwards are reported to `progress` as tasks of their own.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn push(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    with_force: bool,
+    lease: Lease,
+    allow_secrets: bool,
+    credentials: &Helper,
+    askpass: Option<Option<BranchId>>,
+    progress: &dyn Progress,
+) -> Result<()> {
+    let vb_state = ctx.project().virtual_branches();
+
+    let mut vbranch = vb_state.get_branch_in_workspace(branch_id)?;
+    let task = Task::new(progress, format!("Pushing {}", vbranch.name));
+    let remote_branch = push_target(ctx, &vbranch)?;
+    secret_scan::ensure_push_is_clean(ctx, &vbranch, &remote_branch, allow_secrets)?;
+
+    run_pre_push_hook(ctx, &vbranch, &remote_branch)?;
+
+    let force = match (with_force, lease) {
+        (false, _) => ForcePush::No,
+        (true, Lease::None) => ForcePush::Yes,
+        (true, Lease::LastFetched) => ForcePush::WithLease(
+            ctx.repository()
+                .refname_to_id(&remote_branch.to_string())
+                .ok()
+                .map(|id| id.to_string()),
+        ),
+        (true, Lease::At(expected)) => ForcePush::WithLease(expected.map(|id| id.to_string())),
+    };
+    let push_task = task.child(format!("Pushing to {remote_branch}"));
+    if let Err(err) = ctx.push_with_progress(
+        &vbranch.head,
+        &remote_branch,
+        force,
+        credentials,
+        None,
+        askpass,
+        &push_task,
+    ) {
+        if err.custom_context().map(|context| context.code) != Some(Code::PushRejected) {
+            return Err(err);
+        }
+        // The remote branch has commits that weren't seen yet, which have to be fetched to tell what they are.
+        if let Err(err) = ctx.fetch(
+            remote_branch.remote(),
+            credentials,
+            askpass.map(|_| "modal".to_string()),
+        ) {
+            tracing::warn!(
+                ?err,
+                remote = remote_branch.remote(),
+                "failed to fetch after rejected push"
+            );
+        }
+        let rejection = push_rejection::describe(ctx.repository(), vbranch.head, &remote_branch)?;
+        return Err(anyhow::Error::from(rejection).context(Code::PushRejected));
+    }
+    push_task.finish();
+
+    vbranch.upstream = Some(remote_branch.clone());
+    vbranch.upstream_head = Some(vbranch.head);
+    vb_state
+        .set_branch(vbranch.clone())
+        .context("failed to write target branch after push")?;
+    // The push happened, so failing to tell Git about it isn't worth failing for.
+    if let Err(err) = tracking::configure_upstream(ctx.repository(), &vbranch) {
+        tracing::warn!(?err, branch = %vbranch.name, "failed to configure upstream branch");
+    }
+    record_branch_event(
+        ctx,
+        vbranch.id,
+        BranchEventKind::Pushed {
+            head: vbranch.head,
+            remote: remote_branch.to_string(),
+        },
+    );
+    record_operation(
+        ctx.project(),
+        ctx.repository(),
+        AuditOperation::Pushed {
+            branch_id: vbranch.id,
+            branch_name: vbranch.name.clone(),
+            head: vbranch.head,
+            remote: remote_branch.to_string(),
+            force: with_force,
+        },
+    );
+    automation::trigger(
+        ctx.project(),
+        AutomationEvent::PushCompleted {
+            branch_id: vbranch.id,
+            branch_name: vbranch.name.clone(),
+            head: vbranch.head,
+            remote: remote_branch.to_string(),
+        },
+    );
+    let fetch_task = task.child(format!("Fetching {}", remote_branch.remote()));
+    ctx.fetch_with_progress(
+        remote_branch.remote(),
+        credentials,
+        askpass.map(|_| "modal".to_string()),
+        &fetch_task,
+    )?;
+    fetch_task.finish();
+    task.finish();
+
+    Ok(())
+}
+
+/// Run the `pre-push` hook for pushing the head of `vbranch` to `remote_branch`, passing the ref update on stdin
+/// like `git push` does, as `<local ref> <local sha> <remote ref> <remote sha>`.
+fn run_pre_push_hook(
+    ctx: &CommandContext,
+    vbranch: &Branch,
+    remote_branch: &RemoteRefname,
+) -> Result<()> {
+    let remote_name = remote_branch.remote();
+    let remote = ctx.repository().find_remote(remote_name)?;
+    let url = remote.pushurl().or(remote.url()).unwrap_or_default();
+    let remote_head = ctx
+        .repository()
+        .refname_to_id(&remote_branch.to_string())
+        .unwrap_or_else(|_| git2::Oid::zero());
+    let remote_ref = format!("refs/heads/{}", remote_branch.branch());
+    let local_ref = vbranch.refname()?;
+    let stdin = format!(
+        "{local_ref} {head} {remote_ref} {remote_head}\n",
+        head = vbranch.head
+    );
+    hooks::run(ctx, Hook::PrePush, &[remote_name, url], stdin.as_bytes())
+}
+
+struct IsCommitIntegrated<'repo> {
+    repo: &'repo git2::Repository,
+    target_commit_id: git2::Oid,
+    remote_head_id: git2::Oid,
+    upstream_commits: Vec<git2::Oid>,
+    /// A repository opened at the same path as `repo`, but with an in-memory ODB attached
+    /// to avoid writing intermediate objects.
+    inmemory_repo: git2::Repository,
+}
+
+impl<'repo> IsCommitIntegrated<'repo> {
+    fn new(ctx: &'repo CommandContext, target: &Target) -> anyhow::Result<Self> {
+        let remote_branch = ctx
+            .repository()
+            .find_branch_by_refname(&target.branch.clone().into())?
+            .ok_or(anyhow!("failed to get branch"))?;
+        let remote_head = remote_branch.get().peel_to_commit()?;
+        let upstream_commits = ctx.l(remote_head.id(), LogUntil::Commit(target.sha))?;
+        let inmemory_repo = ctx.repository().in_memory_repo()?;
+        Ok(Self {
+            repo: ctx.repository(),
+            target_commit_id: target.sha,
             remote_head_id: remote_head.id(),
             upstream_commits,
             inmemory_repo,
@@ -1089,7 +1736,11 @@ impl IsCommitIntegrated<'_> {
             return Ok(false);
         }
 
-        if self.upstream_commits.contains(&commit.id()) {
+        if self.upstream_commits.contains(&commit.id())
+            || commit
+                .cherry_picked_from()
+                .is_some_and(|original| self.upstream_commits.contains(&original))
+        {
             return Ok(true);
         }
 
@@ -1198,8 +1849,8 @@ pub(crate) fn move_commit_file(
     let mut upstream_commits = ctx.l(target_branch.head, LogUntil::Commit(amend_commit.id()))?;
 
     // get a list of all the diffs across all the virtual branches
-    let base_file_diffs = gitbutler_diff::workdir(ctx.repository(), &default_target.sha)
-        .context("failed to diff workdir")?;
+    let base_file_diffs =
+        workdir_diff(ctx, default_target.sha).context("failed to diff workdir")?;
 
     // filter base_file_diffs to HashMap<filepath, Vec<GitHunk>> only for hunks in target_ownership
     // this is essentially the group of patches that we're "moving"
@@ -1400,12 +2051,94 @@ pub(crate) fn move_commit_file(
 // takes a list of file ownership and a commit oid and rewrites that commit to
 // add the file changes. The branch is then rebased onto the new commit
 // and the respective branch head is updated
+/// What to change about a commit when [amending](amend_commit()) it. Unset fields are left as they are.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmendRequest {
+    /// The uncommitted changes of the branch to add to the commit.
+    pub hunks: Option<BranchOwnershipClaims>,
+    /// The new message of the commit.
+    pub message: Option<String>,
+    /// The new author of the commit.
+    pub author: Option<AmendAuthor>,
+}
+
+/// Changes to the author of a commit. Unset fields are left as they are.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmendAuthor {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    /// The time at which the commit was authored, in milliseconds since the Unix epoch.
+    /// The time zone of the commit is kept.
+    pub time_ms: Option<i64>,
+}
+
+impl AmendRequest {
+    fn is_empty(&self) -> bool {
+        self.hunks.is_none() && self.message.is_none() && self.author.is_none()
+    }
+}
+
+/// Return the author of `commit` with the changes of `update` applied.
+fn amended_author(
+    commit: &git2::Commit<'_>,
+    update: Option<&AmendAuthor>,
+) -> Result<git2::Signature<'static>> {
+    let author = commit.author();
+    let Some(update) = update else {
+        return Ok(author.to_owned());
+    };
+    let name = match &update.name {
+        Some(name) => Cow::Borrowed(name.as_str()),
+        None => String::from_utf8_lossy(author.name_bytes()),
+    };
+    let email = match &update.email {
+        Some(email) => Cow::Borrowed(email.as_str()),
+        None => String::from_utf8_lossy(author.email_bytes()),
+    };
+    if name.trim().is_empty() || email.trim().is_empty() {
+        return Err(anyhow!("the author needs a name and an email")).context(Code::Validation);
+    }
+    let time = match update.time_ms {
+        Some(time_ms) => git2::Time::new(time_ms.div_euclid(1000), author.when().offset_minutes()),
+        None => author.when(),
+    };
+    Ok(git2::Signature::new(&name, &email, &time)?)
+}
+
 pub(crate) fn amend(
     ctx: &CommandContext,
     branch_id: BranchId,
     commit_oid: git2::Oid,
     target_ownership: &BranchOwnershipClaims,
+    rewrite_pushed: bool,
 ) -> Result<git2::Oid> {
+    let request = AmendRequest {
+        hunks: Some(target_ownership.clone()),
+        ..Default::default()
+    };
+    amend_commit(ctx, branch_id, commit_oid, &request, rewrite_pushed)
+}
+
+/// Change the commit `commit_oid` of the branch with `branch_id` as described by `request`, and rewrite
+/// its descendants on top of it. Returns the id of the amended commit.
+pub(crate) fn amend_commit(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    commit_oid: git2::Oid,
+    request: &AmendRequest,
+    rewrite_pushed: bool,
+) -> Result<git2::Oid> {
+    if request.is_empty() {
+        return Err(anyhow!("there is nothing to amend")).context(Code::Validation);
+    }
+    if let Some(message) = &request.message {
+        if message.is_empty() {
+            return Err(anyhow!("commit message can not be empty")).context(Code::Validation);
+        }
+        crate::commit_message::check_conventions(&ctx.project().commit_conventions, message)?;
+    }
     ctx.assure_resolved()?;
     let vb_state = ctx.project().virtual_branches();
 
@@ -1430,6 +2163,7 @@ pub(crate) fn amend(
         // amending to a pushed head commit will cause a force push that is not allowed
         bail!("force-push is not allowed");
     }
+    pushed_commits::ensure_rewritable(ctx, target_branch, &[commit_oid], rewrite_pushed)?;
 
     if ctx
         .l(target_branch.head, LogUntil::Commit(default_target.sha))?
@@ -1444,53 +2178,62 @@ pub(crate) fn amend(
         .find_commit(commit_oid)
         .context("failed to find commit")?;
 
-    let diffs_to_amend = target_ownership
-        .claims
-        .iter()
-        .filter_map(|file_ownership| {
-            let hunks = target_status
-                .get(&file_ownership.file_path)
-                .map(|file| {
-                    file.hunks
-                        .iter()
-                        .filter(|hunk| {
-                            let hunk: GitHunk = (*hunk).clone().into();
-                            file_ownership.hunks.iter().any(|owned_hunk| {
-                                owned_hunk.start == hunk.new_start
-                                    && owned_hunk.end == hunk.new_start + hunk.new_lines
-                            })
+    let new_tree = match &request.hunks {
+        Some(target_ownership) => {
+            let diffs_to_amend = target_ownership
+                .claims
+                .iter()
+                .filter_map(|file_ownership| {
+                    let hunks = target_status
+                        .get(&file_ownership.file_path)
+                        .map(|file| {
+                            file.hunks
+                                .iter()
+                                .filter(|hunk| {
+                                    let hunk: GitHunk = (*hunk).clone().into();
+                                    file_ownership.hunks.iter().any(|owned_hunk| {
+                                        owned_hunk.start == hunk.new_start
+                                            && owned_hunk.end == hunk.new_start + hunk.new_lines
+                                    })
+                                })
+                                .cloned()
+                                .collect::<Vec<_>>()
                         })
-                        .cloned()
-                        .collect::<Vec<_>>()
+                        .unwrap_or_default();
+                    if hunks.is_empty() {
+                        None
+                    } else {
+                        Some((file_ownership.file_path.clone(), hunks))
+                    }
                 })
-                .unwrap_or_default();
-            if hunks.is_empty() {
-                None
-            } else {
-                Some((file_ownership.file_path.clone(), hunks))
-            }
-        })
-        .collect::<HashMap<_, _>>();
+                .collect::<HashMap<_, _>>();
 
-    if diffs_to_amend.is_empty() {
-        bail!("target ownership not found");
-    }
+            if diffs_to_amend.is_empty() {
+                bail!("target ownership not found");
+            }
 
-    // apply diffs_to_amend to the commit tree
-    let new_tree_oid = gitbutler_diff::write::hunks_onto_commit(ctx, commit_oid, &diffs_to_amend)?;
-    let new_tree = ctx
-        .repository()
-        .find_tree(new_tree_oid)
-        .context("failed to find new tree")?;
+            // apply diffs_to_amend to the commit tree
+            let new_tree_oid =
+                gitbutler_diff::write::hunks_onto_commit(ctx, commit_oid, &diffs_to_amend)?;
+            ctx.repository()
+                .find_tree(new_tree_oid)
+                .context("failed to find new tree")?
+        }
+        None => amend_commit.tree().context("failed to find tree")?,
+    };
 
+    let message = match &request.message {
+        Some(message) => Cow::Borrowed(message.as_str()),
+        None => amend_commit.message_bstr().to_str_lossy(),
+    };
     let parents: Vec<_> = amend_commit.parents().collect();
     let commit_oid = ctx
         .repository()
         .commit_with_signature(
             None,
-            &amend_commit.author(),
+            &amended_author(&amend_commit, request.author.as_ref())?,
             &amend_commit.committer(),
-            &amend_commit.message_bstr().to_str_lossy(),
+            &message,
             &new_tree,
             &parents.iter().collect::<Vec<_>>(),
             amend_commit.gitbutler_headers(),
@@ -1562,6 +2305,14 @@ pub(crate) fn reorder_commit(
 
         let new_head =
             cherry_rebase_group(ctx, parent_oid, &mut ids_to_rebase).context("rebase failed")?;
+        record_branch_event(
+            ctx,
+            branch.id,
+            BranchEventKind::Rebased {
+                old_head: branch.head,
+                new_head,
+            },
+        );
         branch.head = new_head;
         branch.updated_timestamp_ms = gitbutler_time::time::now_ms();
         vb_state.set_branch(branch.clone())?;
@@ -1595,6 +2346,14 @@ pub(crate) fn reorder_commit(
 
         let new_head =
             cherry_rebase_group(ctx, target_oid, &mut ids_to_rebase).context("rebase failed")?;
+        record_branch_event(
+            ctx,
+            branch.id,
+            BranchEventKind::Rebased {
+                old_head: branch.head,
+                new_head,
+            },
+        );
 
         branch.head = new_head;
         branch.updated_timestamp_ms = gitbutler_time::time::now_ms();
@@ -1607,6 +2366,89 @@ pub(crate) fn reorder_commit(
     Ok(())
 }
 
+/// The result of [`reorder_commits()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderOutcome {
+    /// The new head of the branch, or `None` if the branch was left unchanged due to conflicts.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub head: Option<git2::Oid>,
+    /// The commits that would conflict in the requested order, in the order they would be applied.
+    pub conflicts: Vec<ConflictedCommit>,
+}
+
+/// Rewrite the commits of the branch identified by `branch_id` so they are in `new_order`,
+/// which lists all commits of the branch from its head down to its base.
+///
+/// The new order is tried in memory first, and the branch is only changed if no commit conflicts.
+/// Otherwise, all conflicting commits are returned.
+pub(crate) fn reorder_commits(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    new_order: &[git2::Oid],
+) -> Result<ReorderOutcome> {
+    ctx.assure_resolved()?;
+
+    let vb_state = ctx.project().virtual_branches();
+    let default_target = vb_state.get_default_target()?;
+    let mut branch = vb_state.get_branch_in_workspace(branch_id)?;
+    let branch_commit_oids = ctx.l(branch.head, LogUntil::Commit(default_target.sha))?;
+
+    if branch_commit_oids.len() != new_order.len()
+        || !branch_commit_oids.iter().all(|id| new_order.contains(id))
+    {
+        bail!("the new order must contain each commit of the branch exactly once");
+    }
+    if branch_commit_oids == new_order {
+        return Ok(ReorderOutcome {
+            head: Some(branch.head),
+            conflicts: Vec::new(),
+        });
+    }
+
+    let repo = ctx.repository();
+    for id in &branch_commit_oids {
+        if repo.find_commit(*id)?.parent_count() > 1 {
+            bail!("cannot reorder commits of a branch that contains merge commit {id}");
+        }
+    }
+    let base = repo
+        .find_commit(*branch_commit_oids.last().expect("not empty as it differs"))?
+        .parent_id(0)
+        .context("failed to find base of branch")?;
+
+    let conflicts = find_rebase_conflicts(ctx, base, new_order)?;
+    if !conflicts.is_empty() {
+        return Ok(ReorderOutcome {
+            head: None,
+            conflicts,
+        });
+    }
+
+    let new_head = cherry_rebase_group(ctx, base, &mut new_order.to_vec())
+        .context("rebase failed")
+        .context(Code::Unknown)?;
+    record_branch_event(
+        ctx,
+        branch.id,
+        BranchEventKind::Rebased {
+            old_head: branch.head,
+            new_head,
+        },
+    );
+    branch.head = new_head;
+    branch.updated_timestamp_ms = gitbutler_time::time::now_ms();
+    vb_state.set_branch(branch.clone())?;
+
+    crate::integration::update_gitbutler_integration(&vb_state, ctx)
+        .context("failed to update gitbutler integration")?;
+
+    Ok(ReorderOutcome {
+        head: Some(new_head),
+        conflicts: Vec::new(),
+    })
+}
+
 // create and insert a blank commit (no tree change) either above or below a commit
 // if offset is positive, insert below, if negative, insert above
 // return the oid of the new head commit of the branch with the inserted blank commit
@@ -1709,6 +2551,7 @@ pub(crate) fn squash(
     ctx: &CommandContext,
     branch_id: BranchId,
     commit_id: git2::Oid,
+    rewrite_pushed: bool,
 ) -> Result<()> {
     ctx.assure_resolved()?;
 
@@ -1739,6 +2582,7 @@ pub(crate) fn squash(
         // squashing into a pushed commit will cause a force push that is not allowed
         bail!("force push not allowed");
     }
+    pushed_commits::ensure_rewritable(ctx, &branch, &[parent_commit.id()], rewrite_pushed)?;
 
     if !branch_commit_oids.contains(&parent_commit.id()) {
         bail!("can not squash root commit");
@@ -1779,6 +2623,14 @@ pub(crate) fn squash(
 
     match cherry_rebase_group(ctx, new_commit_oid, &mut ids_to_rebase) {
         Ok(new_head_id) => {
+            record_branch_event(
+                ctx,
+                branch.id,
+                BranchEventKind::Rebased {
+                    old_head: branch.head,
+                    new_head: new_head_id,
+                },
+            );
             // save new branch head
             branch.head = new_head_id;
             branch.updated_timestamp_ms = gitbutler_time::time::now_ms();
@@ -1792,16 +2644,336 @@ pub(crate) fn squash(
     }
 }
 
+/// Squash `commits` of the branch identified by `branch_id` into the commit `into`, like `git rebase --autosquash`.
+///
+/// The resulting commit takes the place of `into`, keeps its author, committer and change id, and has
+/// `new_message` as message, or the message of `into` followed by those of all squashed commits that
+/// aren't `fixup!` commits. Commits that were squashed are removed from their previous position,
+/// and nothing is changed if any commit would conflict in its new place.
+///
+/// Return the new head of the branch.
+pub(crate) fn squash_commits(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    commits: &[git2::Oid],
+    into: git2::Oid,
+    new_message: Option<&str>,
+) -> Result<git2::Oid> {
+    ctx.assure_resolved()?;
+    if commits.is_empty() {
+        bail!("no commits to squash were given");
+    }
+    if commits.contains(&into) {
+        bail!("commit {into} can not be squashed into itself");
+    }
+    if new_message.map_or(false, str::is_empty) {
+        bail!("commit message can not be empty");
+    }
+
+    let vb_state = ctx.project().virtual_branches();
+    let default_target = vb_state.get_default_target()?;
+    let mut branch = vb_state.get_branch_in_workspace(branch_id)?;
+    let branch_commit_oids = ctx.l(branch.head, LogUntil::Commit(default_target.sha))?;
+
+    let position = |id: &git2::Oid| {
+        branch_commit_oids
+            .iter()
+            .position(|candidate| candidate == id)
+            .with_context(|| format!("commit {id} not in the branch"))
+    };
+    let oldest_affected = commits
+        .iter()
+        .chain(Some(&into))
+        .map(position)
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .max()
+        .expect("at least `into`");
+    // All commits from the oldest one involved to the head are rewritten.
+    let affected = &branch_commit_oids[..=oldest_affected];
+
+    let repo = ctx.repository();
+    for id in affected {
+        if repo.find_commit(*id)?.parent_count() > 1 {
+            bail!("cannot squash commits of a branch that contains merge commit {id}");
+        }
+    }
+
+    let pushed_commit_oids = branch.upstream_head.map_or_else(
+        || Ok(vec![]),
+        |upstream_head| ctx.l(upstream_head, LogUntil::Commit(default_target.sha)),
+    )?;
+    if affected.iter().any(|id| pushed_commit_oids.contains(id)) && !branch.allow_rebasing {
+        // rewriting pushed commits will cause a force push that is not allowed
+        bail!("force push not allowed");
+    }
+
+    // Partition the affected commits, newest first, into those above `into`, the group to squash
+    // and those below `into`.
+    let squashed: Vec<_> = affected
+        .iter()
+        .filter(|id| commits.contains(id))
+        .copied()
+        .collect();
+    let into_position = position(&into)?;
+    let mut above: Vec<_> = affected[..into_position]
+        .iter()
+        .filter(|id| !commits.contains(id))
+        .copied()
+        .collect();
+    let mut group: Vec<_> = squashed.iter().copied().chain(Some(into)).collect();
+    let mut below: Vec<_> = affected[into_position + 1..]
+        .iter()
+        .filter(|id| !commits.contains(id))
+        .copied()
+        .collect();
+
+    let base = repo
+        .find_commit(affected[oldest_affected])?
+        .parent_id(0)
+        .context("failed to find base of the squashed commits")?;
+    let new_order: Vec<_> = above.iter().chain(&group).chain(&below).copied().collect();
+    let conflicts = find_rebase_conflicts(ctx, base, &new_order)?;
+    if let Some(conflict) = conflicts.first() {
+        return Err(anyhow!(
+            "squashing would cause commit {} to conflict",
+            conflict.commit_id
+        ))
+        .context(Marker::BranchConflict);
+    }
+
+    let new_base = if below.is_empty() {
+        base
+    } else {
+        cherry_rebase_group(ctx, base, &mut below).context("failed to rebase commits below")?
+    };
+    let group_head = cherry_rebase_group(ctx, new_base, &mut group)
+        .context("failed to rebase the commits to squash")?;
+
+    let into_commit = repo.find_commit(into).context("failed to find commit")?;
+    let message = match new_message {
+        Some(message) => message.to_owned(),
+        None => squashed
+            .iter()
+            .rev()
+            .map(|id| repo.find_commit(*id))
+            .collect::<Result<Vec<_>, _>>()?
+            .iter()
+            .filter(|commit| !commit.message_bstr().starts_with_str("fixup! "))
+            .fold(
+                into_commit.message_bstr().to_str_lossy().into_owned(),
+                |message, commit| format!("{message}\n{}", commit.message_bstr()),
+            ),
+    };
+    let new_commit_oid = repo
+        .commit_with_signature(
+            None,
+            &into_commit.author(),
+            &into_commit.committer(),
+            &message,
+            &repo
+                .find_commit(group_head)?
+                .tree()
+                .context("failed to find tree")?,
+            &[&repo.find_commit(new_base)?],
+            into_commit.gitbutler_headers(),
+        )
+        .context("failed to commit")?;
+
+    let new_head = if above.is_empty() {
+        new_commit_oid
+    } else {
+        cherry_rebase_group(ctx, new_commit_oid, &mut above)
+            .context("rebase error")
+            .context(Code::Unknown)?
+    };
+
+    record_branch_event(
+        ctx,
+        branch.id,
+        BranchEventKind::Rebased {
+            old_head: branch.head,
+            new_head,
+        },
+    );
+    branch.head = new_head;
+    branch.updated_timestamp_ms = gitbutler_time::time::now_ms();
+    vb_state.set_branch(branch.clone())?;
+
+    crate::integration::update_gitbutler_integration(&vb_state, ctx)
+        .context("failed to update gitbutler integration")?;
+    Ok(new_head)
+}
+
+/// Split the commit `commit_id` of the branch identified by `branch_id` into one commit per group in `groups`,
+/// each containing the hunks claimed by the group, and rebase all commits above it.
+///
+/// Hunks are identified like in [`move_commit_file()`]. The new commits are in the order of `groups`,
+/// oldest first, and all hunks not claimed by any group remain in a last commit.
+/// All new commits keep the message, author and committer of the original commit, but only the first one keeps its change id.
+///
+/// Return the new head of the branch.
+pub(crate) fn split_commit(
+    ctx: &CommandContext,
+    branch_id: BranchId,
+    commit_id: git2::Oid,
+    groups: &[BranchOwnershipClaims],
+) -> Result<git2::Oid> {
+    ctx.assure_resolved()?;
+    if groups.is_empty() {
+        bail!("no groups to split the commit into were given");
+    }
+
+    let vb_state = ctx.project().virtual_branches();
+    let default_target = vb_state.get_default_target()?;
+    let mut branch = vb_state.get_branch_in_workspace(branch_id)?;
+    let branch_commit_oids = ctx.l(branch.head, LogUntil::Commit(default_target.sha))?;
+    if !branch_commit_oids.contains(&commit_id) {
+        bail!("commit {commit_id} not in the branch");
+    }
+
+    let pushed_commit_oids = branch.upstream_head.map_or_else(
+        || Ok(vec![]),
+        |upstream_head| ctx.l(upstream_head, LogUntil::Commit(default_target.sha)),
+    )?;
+    if pushed_commit_oids.contains(&commit_id) && !branch.allow_rebasing {
+        // splitting a pushed commit will cause a force push that is not allowed
+        bail!("force push not allowed");
+    }
+
+    let repo = ctx.repository();
+    let commit = repo
+        .find_commit(commit_id)
+        .context("failed to find commit")?;
+    if commit.parent_count() != 1 {
+        bail!("can only split commits with exactly one parent");
+    }
+    let parent = commit.parent(0).context("failed to find parent")?;
+    let parent_tree = parent.tree().context("failed to find parent tree")?;
+    let commit_tree = commit.tree().context("failed to find tree")?;
+    let diffs =
+        gitbutler_diff::trees(repo, &parent_tree, &commit_tree).context("failed to diff trees")?;
+
+    let group_of = |path: &Path, hunk: &GitHunk| {
+        groups.iter().position(|group| {
+            group.claims.iter().any(|claim| {
+                claim.file_path == path
+                    && claim.hunks.iter().any(|owned_hunk| {
+                        owned_hunk.start == hunk.new_start
+                            && owned_hunk.end == hunk.new_start + hunk.new_lines
+                    })
+            })
+        })
+    };
+    for (index, group) in groups.iter().enumerate() {
+        for claim in &group.claims {
+            for owned_hunk in &claim.hunks {
+                let exists = diffs.get(&claim.file_path).map_or(false, |file_diff| {
+                    file_diff.hunks.iter().any(|hunk| {
+                        owned_hunk.start == hunk.new_start
+                            && owned_hunk.end == hunk.new_start + hunk.new_lines
+                            && group_of(&claim.file_path, hunk) == Some(index)
+                    })
+                });
+                if !exists {
+                    bail!(
+                        "hunk {}:{} is not part of commit {commit_id}, or claimed by an earlier group",
+                        claim.file_path.display(),
+                        owned_hunk
+                    );
+                }
+            }
+        }
+        if group.claims.iter().all(|claim| claim.hunks.is_empty()) {
+            bail!("group {index} doesn't contain any hunks");
+        }
+    }
+    let has_remainder = diffs
+        .values()
+        .any(|file_diff| file_diff.hunks.is_empty() || file_diff.skipped)
+        || diffs.iter().any(|(path, file_diff)| {
+            file_diff
+                .hunks
+                .iter()
+                .any(|hunk| group_of(path, hunk).is_none())
+        });
+
+    let commit_count = groups.len() + usize::from(has_remainder);
+    let mut new_commit = parent;
+    for index in 0..commit_count {
+        let tree = if index + 1 == commit_count {
+            commit_tree.clone()
+        } else {
+            let tree_id = gitbutler_diff::write::tree_with_selected_hunks(
+                repo,
+                &parent_tree,
+                &commit_tree,
+                &diffs,
+                |path, hunk| group_of(path, hunk).map_or(false, |group| group <= index),
+            )?;
+            repo.find_tree(tree_id).context("failed to find new tree")?
+        };
+        let new_commit_oid = repo
+            .commit_with_signature(
+                None,
+                &commit.author(),
+                &commit.committer(),
+                &commit.message_bstr().to_str_lossy(),
+                &tree,
+                &[&new_commit],
+                (index == 0).then(|| commit.gitbutler_headers()).flatten(),
+            )
+            .context("failed to commit")?;
+        new_commit = repo
+            .find_commit(new_commit_oid)
+            .context("failed to find commit")?;
+    }
+
+    let ids_to_rebase = {
+        let ids = branch_commit_oids
+            .split(|oid| oid.eq(&commit_id))
+            .collect::<Vec<_>>();
+        ids.first().copied()
+    }
+    .with_context(|| format!("commit {commit_id} not in the branch"))?;
+    let mut ids_to_rebase = ids_to_rebase.to_vec();
+    let new_head = if ids_to_rebase.is_empty() {
+        new_commit.id()
+    } else {
+        cherry_rebase_group(ctx, new_commit.id(), &mut ids_to_rebase)
+            .context("rebase error")
+            .context(Code::Unknown)?
+    };
+
+    record_branch_event(
+        ctx,
+        branch.id,
+        BranchEventKind::Rebased {
+            old_head: branch.head,
+            new_head,
+        },
+    );
+    branch.head = new_head;
+    branch.updated_timestamp_ms = gitbutler_time::time::now_ms();
+    vb_state.set_branch(branch.clone())?;
+
+    crate::integration::update_gitbutler_integration(&vb_state, ctx)
+        .context("failed to update gitbutler integration")?;
+    Ok(new_head)
+}
+
 // changes a commit message for commit_oid, rebases everything above it, updates branch head if successful
 pub(crate) fn update_commit_message(
     ctx: &CommandContext,
     branch_id: BranchId,
     commit_id: git2::Oid,
     message: &str,
+    rewrite_pushed: bool,
 ) -> Result<()> {
     if message.is_empty() {
         bail!("commit message can not be empty");
     }
+    crate::commit_message::check_conventions(&ctx.project().commit_conventions, message)?;
     ctx.assure_unconflicted()?;
 
     let vb_state = ctx.project().virtual_branches();
@@ -1823,6 +2995,7 @@ pub(crate) fn update_commit_message(
         // updating the message of a pushed commit will cause a force push that is not allowed
         bail!("force push not allowed");
     }
+    pushed_commits::ensure_rewritable(ctx, &branch, &[commit_id], rewrite_pushed)?;
 
     let target_commit = ctx
         .repository()
@@ -0,0 +1,162 @@
+//! Keep the worktree diff of watched projects in memory, so only the files that changed are diffed again,
+//! along with the [status](crate::get_applied_status()) computed from it so all queries share it.
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::{DiffOptions, RediffReason, WorkdirCache};
+use gitbutler_project::ProjectId;
+use gitbutler_repo::{partial_clone, sparse_checkout};
+
+use crate::status::StatusSnapshot;
+
+static CACHES: Mutex<BTreeMap<ProjectId, Arc<ProjectCache>>> = Mutex::new(BTreeMap::new());
+
+/// Everything that is cached for a single project.
+#[derive(Default)]
+struct ProjectCache {
+    workdir: Mutex<WorkdirCache>,
+    /// The status computed last, shared by all queries as long as its inputs don't change.
+    status: Arc<Mutex<Option<StatusSnapshot>>>,
+}
+
+/// Keeps the worktree diff and status of a project cached while it's alive.
+#[must_use = "the cache is dropped with the guard"]
+pub struct WorkdirCacheGuard {
+    project_id: ProjectId,
+}
+
+impl Drop for WorkdirCacheGuard {
+    fn drop(&mut self) {
+        CACHES
+            .lock()
+            .expect("no panics while holding the lock")
+            .remove(&self.project_id);
+    }
+}
+
+/// Cache the worktree diff of the project with `project_id` until the returned guard is dropped.
+///
+/// The worktree has to be watched for as long, and all changed files have to be passed to
+/// [`invalidate_workdir_cache()`], as otherwise changes to files that had no changes before are missed.
+pub fn cache_workdir_diff(project_id: ProjectId) -> WorkdirCacheGuard {
+    CACHES
+        .lock()
+        .expect("no panics while holding the lock")
+        .insert(project_id, Arc::default());
+    WorkdirCacheGuard { project_id }
+}
+
+/// Note that the files at `paths`, relative to the worktree of the project with `project_id`, changed.
+pub fn invalidate_workdir_cache(project_id: ProjectId, paths: impl IntoIterator<Item = PathBuf>) {
+    if let Some(cache) = cache_of(project_id) {
+        cache
+            .workdir
+            .lock()
+            .expect("no panics while holding the lock")
+            .invalidate(paths);
+    }
+}
+
+/// Return the diff of the worktree of `ctx` against `commit_oid`, from the cache if the worktree is watched.
+///
+/// In a [partial checkout](crate::partial_checkout), only the checked out part of the worktree is diffed.
+/// In a partial clone, the blobs the diff needs are fetched first.
+pub(crate) fn workdir_diff(
+    ctx: &CommandContext,
+    commit_oid: git2::Oid,
+) -> Result<gitbutler_diff::DiffByPathMap> {
+    workdir_diff_with_reasons(ctx, commit_oid).map(|(diff, _rediffed)| diff)
+}
+
+/// Like [`workdir_diff()`], but also return why each file was diffed again instead of being taken from the
+/// cache, which is all of them if the worktree isn't watched.
+pub(crate) fn workdir_diff_with_reasons(
+    ctx: &CommandContext,
+    commit_oid: git2::Oid,
+) -> Result<(
+    gitbutler_diff::DiffByPathMap,
+    BTreeMap<PathBuf, RediffReason>,
+)> {
+    let options = diff_options(ctx);
+    let scope = sparse_checkout::scope(ctx.repository())?;
+    partial_clone::fetch_worktree_blobs(ctx, scope.as_deref())?;
+    let diff = match (cache_of(ctx.project().id), scope) {
+        (Some(cache), scope) => {
+            let mut cache = cache
+                .workdir
+                .lock()
+                .expect("no panics while holding the lock");
+            let diff =
+                cache.workdir_in_scope(ctx.repository(), commit_oid, &options, scope.as_deref())?;
+            return Ok((diff, cache.rediffed().clone()));
+        }
+        (None, Some(scope)) => {
+            gitbutler_diff::workdir_in_scope(ctx.repository(), &commit_oid, &options, &scope)?
+        }
+        (None, None) => {
+            gitbutler_diff::workdir_with_options(ctx.repository(), &commit_oid, &options)?
+        }
+    };
+    let rediffed = diff
+        .keys()
+        .map(|path| (path.clone(), RediffReason::FullDiff))
+        .collect();
+    Ok((diff, rediffed))
+}
+
+/// Return the diff of the files at `paths` in the worktree of `ctx` against `commit_oid`, bypassing the cache
+/// so the files are read as they are now.
+pub(crate) fn workdir_diff_of_paths(
+    ctx: &CommandContext,
+    commit_oid: git2::Oid,
+    paths: &[PathBuf],
+) -> Result<gitbutler_diff::DiffByPathMap> {
+    let options = diff_options(ctx);
+    partial_clone::fetch_worktree_blobs(ctx, Some(paths))?;
+    gitbutler_diff::workdir_in_scope(ctx.repository(), &commit_oid, &options, paths)
+}
+
+/// Return the options to diff the worktree of `ctx` with, as configured in the settings of its project.
+fn diff_options(ctx: &CommandContext) -> DiffOptions {
+    let project = ctx.project();
+    let diff = &project.settings.diff;
+    DiffOptions {
+        context_lines: diff.context_lines,
+        ignore_whitespace: diff.ignore_whitespace,
+        ignore_blank_lines: diff.ignore_blank_lines,
+        rename_threshold: diff.rename_threshold,
+        semantic_hunks: diff.semantic_hunks,
+        persistent_cache: diff.persistent_cache,
+        threads: project.parallelism.threads(),
+        ..DiffOptions::default()
+    }
+}
+
+/// Return the place to keep the status of the project with `project_id` in, if the worktree is watched
+/// and [low-memory mode](gitbutler_diff::set_low_memory()) doesn't forbid keeping it.
+pub(crate) fn status_snapshot(project_id: ProjectId) -> Option<Arc<Mutex<Option<StatusSnapshot>>>> {
+    let cache = cache_of(project_id)?;
+    if !gitbutler_diff::memory_limits().cache_status {
+        // Drop what was kept before the mode was turned on.
+        cache
+            .status
+            .lock()
+            .expect("no panics while holding the lock")
+            .take();
+        return None;
+    }
+    Some(Arc::clone(&cache.status))
+}
+
+fn cache_of(project_id: ProjectId) -> Option<Arc<ProjectCache>> {
+    CACHES
+        .lock()
+        .expect("no panics while holding the lock")
+        .get(&project_id)
+        .cloned()
+}
@@ -0,0 +1,141 @@
+//! Work with all projects at once, like fetching all of them or finding the branches with commits that weren't
+//! pushed in any of them, so users with many repositories don't have to go through each project in turn.
+//!
+//! Each operation is attempted on every project, and the outcome is reported per project so that one failing
+//! project doesn't hide what happened to the others.
+use anyhow::{anyhow, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::{Project, ProjectId};
+use gitbutler_reference::RemoteRefname;
+use gitbutler_repo::FetchReport;
+use serde::Serialize;
+
+use crate::{VirtualBranchActions, VirtualBranchesExt};
+
+/// The projects to work with at once.
+pub struct Workspace {
+    projects: Vec<Project>,
+}
+
+/// The outcome of an operation for a single project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectOutcome<T> {
+    pub project_id: ProjectId,
+    pub title: String,
+    /// What the operation returned, or `None` if it failed.
+    pub value: Option<T>,
+    /// Why the operation failed for this project, or `None` if it succeeded.
+    pub error: Option<String>,
+}
+
+impl<T> ProjectOutcome<T> {
+    fn new(project: &Project, result: Result<T>) -> Self {
+        let (value, error) = match result {
+            Ok(value) => (Some(value), None),
+            Err(err) => (None, Some(format!("{err:#}"))),
+        };
+        ProjectOutcome {
+            project_id: project.id,
+            title: project.title.clone(),
+            value,
+            error,
+        }
+    }
+}
+
+/// A virtual branch with commits that aren't on its upstream branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnpushedBranch {
+    pub branch_id: BranchId,
+    pub name: String,
+    /// The branch it's pushed to, or `None` if it was never pushed.
+    pub upstream: Option<RemoteRefname>,
+    /// The amount of commits that aren't on the upstream branch, or that aren't on the target if the branch was
+    /// never pushed.
+    pub unpushed_commits: usize,
+}
+
+impl Workspace {
+    pub fn new(projects: Vec<Project>) -> Self {
+        Workspace { projects }
+    }
+
+    /// Work with all projects known to `projects`.
+    pub fn from_controller(projects: &gitbutler_project::Controller) -> Result<Self> {
+        Ok(Workspace::new(projects.list()?))
+    }
+
+    pub fn projects(&self) -> &[Project] {
+        &self.projects
+    }
+
+    /// Fetch the remotes of all projects, with all projects fetched at the same time.
+    pub fn fetch_all(&self, askpass: Option<String>) -> Vec<ProjectOutcome<FetchReport>> {
+        std::thread::scope(|scope| {
+            let fetches: Vec<_> = self
+                .projects
+                .iter()
+                .map(|project| {
+                    let askpass = askpass.clone();
+                    scope.spawn(move || VirtualBranchActions.fetch_from_remotes(project, askpass))
+                })
+                .collect();
+            self.projects
+                .iter()
+                .zip(fetches)
+                .map(|(project, fetch)| {
+                    let result = fetch
+                        .join()
+                        .unwrap_or_else(|_| Err(anyhow!("fetching panicked")));
+                    ProjectOutcome::new(project, result)
+                })
+                .collect()
+        })
+    }
+
+    /// Return the applied virtual branches of each project that have commits which weren't pushed. Projects
+    /// without a target have none.
+    pub fn unpushed_branches(&self) -> Vec<ProjectOutcome<Vec<UnpushedBranch>>> {
+        self.projects
+            .iter()
+            .map(|project| ProjectOutcome::new(project, unpushed_branches(project)))
+            .collect()
+    }
+}
+
+fn unpushed_branches(project: &Project) -> Result<Vec<UnpushedBranch>> {
+    let vb_state = project.virtual_branches();
+    let Ok(target) = vb_state.get_default_target() else {
+        return Ok(Vec::new());
+    };
+    let ctx = CommandContext::open(project)?;
+    let repo = ctx.repository();
+    let mut branches = vb_state.list_branches_in_workspace()?;
+    branches.sort_by_key(|branch| branch.order);
+
+    let mut unpushed = Vec::new();
+    for branch in branches {
+        let pushed = branch
+            .upstream
+            .as_ref()
+            .and_then(|upstream| repo.refname_to_id(&upstream.to_string()).ok())
+            .or(branch.upstream_head)
+            .unwrap_or(target.sha);
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(branch.head)?;
+        revwalk.hide(pushed)?;
+        let unpushed_commits = revwalk.count();
+        if unpushed_commits > 0 {
+            unpushed.push(UnpushedBranch {
+                branch_id: branch.id,
+                name: branch.name,
+                upstream: branch.upstream,
+                unpushed_commits,
+            });
+        }
+    }
+    Ok(unpushed)
+}
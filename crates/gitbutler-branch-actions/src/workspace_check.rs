@@ -0,0 +1,107 @@
+//! Compare the integration commit and the worktree with what is recorded for the applied branches, to notice
+//! when they drifted apart, be it by a bug or by another tool, before the next operation fails on it.
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use gitbutler_branch::GITBUTLER_INTEGRATION_REFERENCE;
+use gitbutler_command_context::CommandContext;
+use gitbutler_repo::RepositoryExt;
+use serde::Serialize;
+
+use crate::{
+    conflicts::RepoConflictsExt,
+    integration::{self, IntegrationDivergence},
+    VirtualBranchesExt,
+};
+
+/// How the workspace differs from what is recorded for the applied branches.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceDesync {
+    /// How the parents of the integration commit differ from the heads of the applied branches.
+    pub divergences: Vec<IntegrationDivergence>,
+    /// The files whose content in the integration commit differs from the merged heads of the applied
+    /// branches, sorted by path.
+    pub integration_paths: Vec<PathBuf>,
+    /// The files whose content in the worktree differs from the merged changes of the applied branches,
+    /// committed or not, sorted by path.
+    pub worktree_paths: Vec<PathBuf>,
+}
+
+impl WorkspaceDesync {
+    /// Return `true` if the workspace matches what is recorded.
+    pub fn is_empty(&self) -> bool {
+        self.divergences.is_empty()
+            && self.integration_paths.is_empty()
+            && self.worktree_paths.is_empty()
+    }
+}
+
+/// Compare the integration commit of the workspace of `ctx` and its worktree with the applied branches.
+///
+/// Nothing is compared while conflicts are resolved, as the worktree is expected to differ then, and
+/// branches that conflict with the others are left out like when the integration commit is created.
+/// Uncommitted changes are compared as they were last recorded, so changes to the worktree since
+/// show up until the branches are listed again.
+pub(crate) fn check_workspace(ctx: &CommandContext) -> Result<WorkspaceDesync> {
+    if ctx.is_resolving() {
+        return Ok(WorkspaceDesync::default());
+    }
+    let mut desync = WorkspaceDesync {
+        divergences: integration::verify_integration(ctx)?,
+        ..Default::default()
+    };
+
+    let repo = ctx.repository();
+    let vb_state = ctx.project().virtual_branches();
+    let target = vb_state.get_default_target()?;
+    let target_tree = repo.find_commit(target.sha)?.tree()?;
+    let mut committed_tree = target_tree.clone();
+    let mut uncommitted_tree = target_tree;
+    for branch in vb_state.list_branches_in_workspace()? {
+        let base_tree = repo.find_commit(branch.base(target.sha))?.tree()?;
+        let head_tree = repo.find_commit(branch.head)?.tree()?;
+        let mut committed = repo.merge_trees(&base_tree, &committed_tree, &head_tree, None)?;
+        if committed.has_conflicts() {
+            continue;
+        }
+        committed_tree = repo.find_tree(committed.write_tree_to(repo)?)?;
+        let branch_tree = repo.find_tree(branch.tree)?;
+        let mut uncommitted =
+            repo.merge_trees(&base_tree, &uncommitted_tree, &branch_tree, None)?;
+        if !uncommitted.has_conflicts() {
+            uncommitted_tree = repo.find_tree(uncommitted.write_tree_to(repo)?)?;
+        }
+    }
+
+    if let Ok(integration_commit) = repo
+        .find_reference(&GITBUTLER_INTEGRATION_REFERENCE.to_string())
+        .and_then(|reference| reference.peel_to_commit())
+    {
+        desync.integration_paths =
+            changed_paths(repo, &integration_commit.tree()?, &committed_tree)?;
+    }
+    let wd_tree = repo.get_wd_tree().context("failed to read the worktree")?;
+    desync.worktree_paths = changed_paths(repo, &wd_tree, &uncommitted_tree)?;
+    Ok(desync)
+}
+
+/// Return the paths of the files that differ between `old` and `new`, sorted.
+fn changed_paths(
+    repo: &git2::Repository,
+    old: &git2::Tree,
+    new: &git2::Tree,
+) -> Result<Vec<PathBuf>> {
+    if old.id() == new.id() {
+        return Ok(Vec::new());
+    }
+    let diff = repo.diff_tree_to_tree(Some(old), Some(new), None)?;
+    let mut paths: Vec<_> = diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(PathBuf::from)
+        .collect();
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
@@ -21,6 +21,7 @@ use gitbutler_branch_actions::{
     verify_branch, BranchManagerExt, Get,
 };
 use gitbutler_commit::{commit_ext::CommitExt, commit_headers::CommitHeadersV2};
+use gitbutler_error::error::Code;
 use gitbutler_reference::{Refname, RemoteRefname};
 use gitbutler_repo::RepositoryExt;
 use gitbutler_testsupport::{commit_all, virtual_branches::set_test_target, Case, Suite};
@@ -742,6 +743,7 @@ fn commit_id_can_be_generated_or_specified() -> Result<()> {
             // The change ID should always be generated by calling CommitHeadersV2::new
             Some(CommitHeadersV2 {
                 change_id: "my-change-id".to_string(),
+                cherry_picked_from: None,
             }),
         )
         .expect("failed to commit");
@@ -2016,10 +2018,13 @@ fn pre_commit_hook_rejection() -> Result<()> {
     let res = commit(ctx, branch1_id, "test commit", None, true);
 
     let err = res.unwrap_err();
-    assert_eq!(
-        err.source().unwrap().to_string(),
-        "commit hook rejected: rejected"
+    assert_eq!(err.downcast_ref::<Code>(), Some(&Code::HookFailed));
+    let message = format!("{err:#}");
+    assert!(
+        message.contains("pre-commit hook failed with exit code 1"),
+        "{message}"
     );
+    assert!(message.contains("rejected"), "{message}");
 
     Ok(())
 }
@@ -2095,9 +2100,50 @@ fn commit_msg_hook_rejection() -> Result<()> {
     let res = commit(ctx, branch1_id, "test commit", None, true);
 
     let err = res.unwrap_err();
+    assert_eq!(err.downcast_ref::<Code>(), Some(&Code::HookFailed));
+    let message = format!("{err:#}");
+    assert!(
+        message.contains("commit-msg hook failed with exit code 1"),
+        "{message}"
+    );
+    assert!(message.contains("rejected"), "{message}");
+
+    Ok(())
+}
+
+#[test]
+fn commit_msg_hook_can_change_the_message() -> Result<()> {
+    let suite = Suite::default();
+    let Case { project, ctx, .. } = &suite.new_case_with_files(HashMap::from([(
+        PathBuf::from("test.txt"),
+        "line1\nline2\n",
+    )]));
+
+    set_test_target(ctx)?;
+
+    let branch_manager = ctx.branch_manager();
+    let mut guard = project.exclusive_worktree_access();
+    let branch1_id = branch_manager
+        .create_virtual_branch(&BranchCreateRequest::default(), guard.write_permission())
+        .expect("failed to create virtual branch")
+        .id;
+
+    std::fs::write(Path::new(&project.path).join("test.txt"), "line0\nline1\n")?;
+
+    let hook = b"#!/bin/sh
+    echo \"[ticket] $(cat \"$1\")\" > \"$1\"
+            ";
+
+    git2_hooks::create_hook(ctx.repository(), git2_hooks::HOOK_COMMIT_MSG, hook);
+
+    let commit_id = commit(ctx, branch1_id, "test commit", None, true)?;
+
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "commit-msg hook rejected: rejected"
+        ctx.repository()
+            .find_commit(commit_id)?
+            .message()
+            .map(str::trim_end),
+        Some("[ticket] test commit")
     );
 
     Ok(())
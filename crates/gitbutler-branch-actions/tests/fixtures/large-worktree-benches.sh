@@ -0,0 +1,24 @@
+#!/usr/bin/env bash
+set -eu -o pipefail
+
+dir_count=600
+files_per_dir=100
+
+git init many-files
+(cd many-files
+  for dir in $(seq $dir_count); do
+    mkdir "dir-$dir"
+    for file in $(seq $files_per_dir); do
+      echo "content of file $file in $dir" > "dir-$dir/file-$file"
+    done
+  done
+  git add . && git commit -m "init" >/dev/null
+
+  # Change every third directory, so there is plenty to hash but most of the worktree is unchanged.
+  for dir in $(seq 1 3 $dir_count); do
+    for file in $(seq $files_per_dir); do
+      echo "changed" >> "dir-$dir/file-$file"
+    done
+    echo "new" > "dir-$dir/untracked"
+  done
+)
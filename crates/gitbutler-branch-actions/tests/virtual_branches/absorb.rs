@@ -0,0 +1,120 @@
+use gitbutler_branch::{BranchCreateRequest, BranchId};
+use gitbutler_branch_actions::SkipReason;
+
+use super::*;
+
+fn numbered_lines(name: &str) -> String {
+    (1..=20).map(|line| format!("{name} {line}\n")).collect()
+}
+
+/// Create a branch with a commit adding `a.txt`, followed by one adding `b.txt`, and return their ids.
+fn branch_with_two_commits(test: &Test) -> (BranchId, git2::Oid, git2::Oid) {
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(test.repository.path().join("a.txt"), numbered_lines("a")).unwrap();
+    let first = test
+        .controller
+        .create_commit(&test.project, branch_id, "add a", None, false)
+        .unwrap();
+    fs::write(test.repository.path().join("b.txt"), numbered_lines("b")).unwrap();
+    let second = test
+        .controller
+        .create_commit(&test.project, branch_id, "add b", None, false)
+        .unwrap();
+    (branch_id, first, second)
+}
+
+fn file_in_commit(test: &Test, commit_id: git2::Oid, path: &str) -> String {
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    let commit = repo.find_commit(commit_id).unwrap();
+    let entry = commit.tree().unwrap().get_path(path.as_ref()).unwrap();
+    let blob = entry.to_object(&repo).unwrap().peel_to_blob().unwrap();
+    String::from_utf8(blob.content().to_vec()).unwrap()
+}
+
+#[test]
+fn hunks_are_absorbed_into_the_commits_that_last_touched_their_lines() {
+    let test = Test::default();
+    let (branch_id, first, second) = branch_with_two_commits(&test);
+
+    let a = numbered_lines("a").replace("a 5\n", "a five\n");
+    let b = numbered_lines("b").replace("b 10\n", "b ten\n");
+    fs::write(test.repository.path().join("a.txt"), &a).unwrap();
+    fs::write(test.repository.path().join("b.txt"), &b).unwrap();
+    fs::write(test.repository.path().join("c.txt"), "new\n").unwrap();
+
+    let outcome = test
+        .controller
+        .absorb(&test.project, branch_id, false)
+        .unwrap();
+    let mut absorbed: Vec<_> = outcome
+        .absorbed
+        .iter()
+        .map(|hunk| (hunk.file_path.to_str().unwrap(), hunk.commit_id))
+        .collect();
+    absorbed.sort();
+    assert_eq!(absorbed, [("a.txt", first), ("b.txt", second)]);
+    assert_eq!(outcome.skipped.len(), 1);
+    assert_eq!(outcome.skipped[0].file_path, PathBuf::from("c.txt"));
+    assert_eq!(outcome.skipped[0].reason, SkipReason::NoCommit);
+
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    let branch = &branches[0];
+    assert_eq!(branch.head, outcome.head);
+    assert_eq!(branch.commits.len(), 2);
+    assert_eq!(branch.files.len(), 1, "only the new file is left");
+    let (new_second, new_first) = (branch.commits[0].id, branch.commits[1].id);
+    assert_eq!(file_in_commit(&test, new_first, "a.txt"), a);
+    assert_eq!(file_in_commit(&test, new_second, "b.txt"), b);
+}
+
+#[test]
+fn hunks_can_be_moved_to_a_chosen_commit() {
+    let test = Test::default();
+    let (branch_id, first, _second) = branch_with_two_commits(&test);
+
+    let b = numbered_lines("b").replace("b 10\n", "b ten\n");
+    fs::write(test.repository.path().join("b.txt"), &b).unwrap();
+    let a = numbered_lines("a").replace("a 5\n", "a five\n");
+    fs::write(test.repository.path().join("a.txt"), &a).unwrap();
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    let file = branches[0]
+        .files
+        .iter()
+        .find(|file| file.path == PathBuf::from("a.txt"))
+        .unwrap();
+    let new_first = test
+        .controller
+        .move_hunk_to_commit(
+            &test.project,
+            branch_id,
+            "a.txt".as_ref(),
+            &file.hunks[0].id,
+            first,
+            false,
+        )
+        .unwrap();
+    assert_eq!(file_in_commit(&test, new_first, "a.txt"), a);
+
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    assert_eq!(branches[0].commits.len(), 2);
+    assert_eq!(branches[0].commits[1].id, new_first);
+    assert_eq!(branches[0].files.len(), 1, "the hunk of b.txt is left");
+}
@@ -0,0 +1,75 @@
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_reference::LocalRefname;
+
+use super::*;
+
+#[test]
+fn local_branch_ahead_of_target() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let local: LocalRefname = "refs/heads/local-work".parse().unwrap();
+    repository.checkout(&local);
+    fs::write(repository.path().join("file.txt"), "local\n").unwrap();
+    let commit_id = repository.commit_all("local work");
+    repository.checkout(&"refs/heads/master".parse().unwrap());
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller.adopt_branch(project, "local-work").unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].id, branch_id);
+    assert!(branches[0].active);
+    assert_eq!(branches[0].commits.len(), 1);
+    assert_eq!(branches[0].commits[0].id, commit_id);
+    assert!(branches[0].files.is_empty());
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "local\n"
+    );
+
+    let err = controller.adopt_branch(project, "local-work").unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation),
+        "it's in the workspace already"
+    );
+}
+
+#[test]
+fn branch_without_own_commits() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    repository.checkout(&"refs/heads/no-work".parse().unwrap());
+    repository.checkout(&"refs/heads/master".parse().unwrap());
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    for name in ["no-work", "missing"] {
+        let err = controller.adopt_branch(project, name).unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation),
+            "{name}"
+        );
+    }
+    assert!(controller
+        .list_virtual_branches(project)
+        .unwrap()
+        .0
+        .is_empty());
+}
@@ -0,0 +1,172 @@
+use gitbutler_branch::{BranchCreateRequest, BranchId, BranchOwnershipClaims, BranchUpdateRequest};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+/// Create a branch named `name` that only allows `allowed_paths`.
+fn branch_allowing(
+    controller: &VirtualBranchActions,
+    project: &Project,
+    name: &str,
+    allowed_paths: &[&str],
+) -> BranchId {
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some(name.to_owned()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    controller
+        .update_virtual_branch(
+            project,
+            BranchUpdateRequest {
+                id: branch_id,
+                allowed_paths: Some(allowed_paths.iter().map(|path| path.to_string()).collect()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    branch_id
+}
+
+#[test]
+fn new_changes_go_to_a_branch_that_allows_them() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let code_id = branch_allowing(controller, project, "code", &[]);
+    let docs_id = branch_allowing(controller, project, "docs", &["docs/", "*.md"]);
+    controller
+        .update_virtual_branch(
+            project,
+            BranchUpdateRequest {
+                id: docs_id,
+                selected_for_changes: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    fs::create_dir_all(repository.path().join("docs/guide")).unwrap();
+    fs::create_dir_all(repository.path().join("src")).unwrap();
+    fs::write(repository.path().join("docs/guide/intro.txt"), "intro").unwrap();
+    fs::write(repository.path().join("src/README.md"), "readme").unwrap();
+    fs::write(repository.path().join("src/main.rs"), "fn main() {}").unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let paths = |branch_id: BranchId| {
+        let branch = branches.iter().find(|b| b.id == branch_id).unwrap();
+        let mut paths: Vec<_> = branch
+            .files
+            .iter()
+            .inspect(|file| assert!(!file.outside_allowed_paths))
+            .map(|file| file.path.to_str().unwrap().to_owned())
+            .collect();
+        paths.sort();
+        paths
+    };
+    assert_eq!(paths(docs_id), ["docs/guide/intro.txt", "src/README.md"]);
+    assert_eq!(
+        paths(code_id),
+        ["src/main.rs"],
+        "changes the default branch doesn't allow go to the first branch that does"
+    );
+}
+
+#[test]
+fn moving_changes_outside_allowed_paths_is_rejected() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let code_id = branch_allowing(controller, project, "code", &[]);
+    let docs_id = branch_allowing(controller, project, "docs", &["docs/**"]);
+
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(
+        branches
+            .iter()
+            .find(|b| b.id == code_id)
+            .unwrap()
+            .files
+            .len(),
+        1
+    );
+
+    let err = controller
+        .update_virtual_branch(
+            project,
+            BranchUpdateRequest {
+                id: docs_id,
+                ownership: Some("file.txt:1-2".parse::<BranchOwnershipClaims>().unwrap()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(
+        branches
+            .iter()
+            .find(|b| b.id == code_id)
+            .unwrap()
+            .files
+            .len(),
+        1,
+        "the change stays where it was"
+    );
+    assert!(branches
+        .iter()
+        .find(|b| b.id == docs_id)
+        .unwrap()
+        .files
+        .is_empty());
+}
+
+#[test]
+fn changes_no_branch_allows_are_flagged() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let docs_id = branch_allowing(controller, project, "docs", &["/docs"]);
+
+    fs::create_dir_all(repository.path().join("src/docs")).unwrap();
+    fs::write(repository.path().join("src/docs/file.txt"), "content").unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let docs = branches.iter().find(|b| b.id == docs_id).unwrap();
+    assert_eq!(docs.allowed_paths, ["/docs"]);
+    assert_eq!(docs.files.len(), 1);
+    assert!(
+        docs.files[0].outside_allowed_paths,
+        "anchored patterns only match at the top of the worktree"
+    );
+}
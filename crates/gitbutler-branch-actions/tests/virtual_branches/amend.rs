@@ -1,4 +1,6 @@
 use gitbutler_branch::{BranchCreateRequest, BranchOwnershipClaims, BranchUpdateRequest};
+use gitbutler_branch_actions::{AmendAuthor, AmendRequest};
+use gitbutler_error::error::{AnyhowContextExt, Code};
 
 use super::*;
 
@@ -50,7 +52,7 @@ fn forcepush_allowed() {
         fs::write(repository.path().join("file2.txt"), "content2").unwrap();
         let to_amend: BranchOwnershipClaims = "file2.txt:1-2".parse().unwrap();
         controller
-            .amend(project, branch_id, commit_id, &to_amend)
+            .amend(project, branch_id, commit_id, &to_amend, false)
             .unwrap();
 
         let branch = controller
@@ -110,7 +112,7 @@ fn forcepush_forbidden() {
         let to_amend: BranchOwnershipClaims = "file2.txt:1-2".parse().unwrap();
         assert_eq!(
             controller
-                .amend(project, branch_id, commit_oid, &to_amend)
+                .amend(project, branch_id, commit_oid, &to_amend, false)
                 .unwrap_err()
                 .to_string(),
             "force-push is not allowed"
@@ -157,7 +159,7 @@ fn non_locked_hunk() {
         fs::write(repository.path().join("file2.txt"), "content2").unwrap();
         let to_amend: BranchOwnershipClaims = "file2.txt:1-2".parse().unwrap();
         controller
-            .amend(project, branch_id, commit_oid, &to_amend)
+            .amend(project, branch_id, commit_oid, &to_amend, false)
             .unwrap();
 
         let branch = controller
@@ -216,7 +218,7 @@ fn locked_hunk() {
         fs::write(repository.path().join("file.txt"), "more content").unwrap();
         let to_amend: BranchOwnershipClaims = "file.txt:1-2".parse().unwrap();
         controller
-            .amend(project, branch_id, commit_oid, &to_amend)
+            .amend(project, branch_id, commit_oid, &to_amend, false)
             .unwrap();
 
         let branch = controller
@@ -276,10 +278,132 @@ fn non_existing_ownership() {
         let to_amend: BranchOwnershipClaims = "file2.txt:1-2".parse().unwrap();
         assert_eq!(
             controller
-                .amend(project, branch_id, commit_oid, &to_amend)
+                .amend(project, branch_id, commit_oid, &to_amend, false)
                 .unwrap_err()
                 .to_string(),
             "target ownership not found"
         );
     }
 }
+
+#[test]
+fn message_and_author() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let first = controller
+        .create_commit(project, branch_id, "commit one", None, false)
+        .unwrap();
+    fs::write(repository.path().join("file2.txt"), "content2").unwrap();
+    controller
+        .create_commit(project, branch_id, "commit two", None, false)
+        .unwrap();
+
+    let amended = controller
+        .amend_commit(
+            project,
+            branch_id,
+            first,
+            &AmendRequest {
+                message: Some("first commit".into()),
+                author: Some(AmendAuthor {
+                    name: Some("Someone Else".into()),
+                    email: Some("someone@example.com".into()),
+                    time_ms: Some(1_000_000_000_000),
+                }),
+                ..Default::default()
+            },
+            false,
+        )
+        .unwrap();
+
+    let (original, amended) = (
+        repository.find_commit(first).unwrap(),
+        repository.find_commit(amended).unwrap(),
+    );
+    assert_eq!(amended.message(), Some("first commit"));
+    assert_eq!(
+        amended.tree_id(),
+        original.tree_id(),
+        "the changes are kept"
+    );
+    let author = amended.author();
+    assert_eq!(author.name(), Some("Someone Else"));
+    assert_eq!(author.email(), Some("someone@example.com"));
+    assert_eq!(author.when().seconds(), 1_000_000_000);
+
+    let branch = controller
+        .list_virtual_branches(project)
+        .unwrap()
+        .0
+        .into_iter()
+        .find(|b| b.id == branch_id)
+        .unwrap();
+    assert_eq!(branch.commits.len(), 2);
+    assert_eq!(branch.commits[0].description, "commit two");
+    assert_eq!(
+        branch.commits[1].id,
+        amended.id(),
+        "descendants are rebased onto the amended commit"
+    );
+}
+
+#[test]
+fn nothing_to_amend() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_oid = controller
+        .create_commit(project, branch_id, "commit one", None, false)
+        .unwrap();
+
+    for request in [
+        AmendRequest::default(),
+        AmendRequest {
+            message: Some(String::new()),
+            ..Default::default()
+        },
+        AmendRequest {
+            author: Some(AmendAuthor {
+                email: Some(String::new()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    ] {
+        let err = controller
+            .amend_commit(project, branch_id, commit_oid, &request, false)
+            .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation),
+            "{request:?}"
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use gitbutler_branch::BranchCreateRequest;
+use gitbutler_oplog::{AuditOperation, AuditOperationKind, AuditQuery, OplogExt};
+
+use super::*;
+
+#[test]
+fn operations_are_recorded_and_queryable_by_kind_and_time() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some("feature".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit one", None, false)
+        .unwrap();
+    controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap();
+    let snapshot = project.list_snapshots(1, None).unwrap()[0].commit_id;
+    project.restore_snapshot(snapshot).unwrap();
+
+    let entries = controller
+        .audit_log(project, &AuditQuery::default())
+        .unwrap();
+    let kinds: Vec<_> = entries.iter().map(|entry| entry.operation.kind()).collect();
+    assert_eq!(
+        kinds,
+        [
+            AuditOperationKind::CommitCreated,
+            AuditOperationKind::Pushed,
+            AuditOperationKind::SnapshotRestored
+        ]
+    );
+    assert_eq!(
+        entries[0].operation,
+        AuditOperation::CommitCreated {
+            branch_id,
+            branch_name: "feature".into(),
+            commit: commit_id,
+        }
+    );
+    assert!(entries[0].actor.is_some(), "the Git identity is recorded");
+
+    let pushes = controller
+        .audit_log(
+            project,
+            &AuditQuery {
+                kinds: vec![AuditOperationKind::Pushed],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(pushes, &entries[1..2]);
+
+    let before_first = controller
+        .audit_log(
+            project,
+            &AuditQuery {
+                until_ms: Some(entries[0].timestamp_ms),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(before_first.is_empty(), "the end of the range is exclusive");
+
+    let latest = controller
+        .audit_log(
+            project,
+            &AuditQuery {
+                limit: Some(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(latest, &entries[2..]);
+}
+
+#[test]
+fn unreadable_lines_are_skipped() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    controller
+        .create_commit(project, branch_id, "commit one", None, false)
+        .unwrap();
+
+    let log_path = project.gb_dir().join(gitbutler_oplog::AUDIT_LOG_FILE_NAME);
+    let mut log = fs::read_to_string(&log_path).unwrap();
+    log.push_str("{\"timestampMs\": 1, \"operat");
+    fs::write(&log_path, log).unwrap();
+
+    fs::write(repository.path().join("file.txt"), "more content").unwrap();
+    controller
+        .create_commit(project, branch_id, "commit two", None, false)
+        .unwrap();
+
+    let entries = controller
+        .audit_log(project, &AuditQuery::default())
+        .unwrap();
+    assert_eq!(
+        entries.len(),
+        2,
+        "only the line that was cut short is lost, entries after it start a new line"
+    );
+}
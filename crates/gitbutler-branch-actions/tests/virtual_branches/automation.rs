@@ -0,0 +1,125 @@
+#![cfg(unix)]
+
+use std::time::{Duration, Instant};
+
+use gitbutler_branch_actions::{AutomationEvent, AutomationRun};
+use gitbutler_project::{Automation, AutomationTrigger, Settings};
+
+use super::*;
+
+fn with_automations(test: &Test, automations: Vec<Automation>) -> Project {
+    let settings = Settings {
+        automations,
+        ..test.project.settings.clone()
+    };
+    test.projects
+        .update_settings(test.project.id, settings)
+        .unwrap();
+    test.projects.get(test.project.id).unwrap()
+}
+
+/// Wait for `count` runs of automations, which run in the background.
+fn wait_for_runs(test: &Test, project: &Project, count: usize) -> Vec<AutomationRun> {
+    let started = Instant::now();
+    loop {
+        let runs = test.controller.automation_runs(project);
+        if runs.len() >= count || started.elapsed() > Duration::from_secs(20) {
+            return runs;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn commands_receive_the_event_and_their_output_is_kept() {
+    let test = Test::default();
+    let project = with_automations(
+        &test,
+        vec![
+            Automation {
+                name: "notify".into(),
+                events: [AutomationTrigger::CommitCreated].into(),
+                command: "cat; echo notified >&2".into(),
+                ..Default::default()
+            },
+            Automation {
+                name: "on-push".into(),
+                events: [AutomationTrigger::PushCompleted].into(),
+                command: "echo pushed".into(),
+                ..Default::default()
+            },
+        ],
+    );
+    test.controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "content").unwrap();
+    let commit = test
+        .controller
+        .create_commit(&project, branch_id, "commit", None, false)
+        .unwrap();
+
+    let runs = wait_for_runs(&test, &project, 1);
+    assert_eq!(runs.len(), 1, "only automations of the event run");
+    let run = &runs[0];
+    assert_eq!(run.automation, "notify");
+    assert!(matches!(
+        run.event,
+        AutomationEvent::CommitCreated { branch_id: id, commit: c, .. } if id == branch_id && c == commit
+    ));
+    assert_eq!(run.exit_code, Some(0));
+    assert_eq!(run.stderr, "notified");
+
+    let payload: serde_json::Value = serde_json::from_str(&run.stdout).unwrap();
+    assert_eq!(payload["event"], "commitCreated");
+    assert_eq!(payload["projectId"], project.id.to_string());
+    assert_eq!(payload["branchId"], branch_id.to_string());
+    assert_eq!(payload["commit"], commit.to_string());
+}
+
+#[test]
+fn commands_are_killed_after_their_timeout() {
+    let test = Test::default();
+    let project = with_automations(
+        &test,
+        vec![Automation {
+            name: "slow".into(),
+            events: [AutomationTrigger::BranchApplied].into(),
+            command: "sleep 30".into(),
+            timeout_secs: 1,
+            ..Default::default()
+        }],
+    );
+    test.controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "content").unwrap();
+    test.controller
+        .create_commit(&project, branch_id, "commit", None, false)
+        .unwrap();
+    let branch_name = test
+        .controller
+        .convert_to_real_branch(&project, branch_id)
+        .unwrap();
+    test.controller
+        .create_virtual_branch_from_branch(
+            &project,
+            &Refname::from_str(&branch_name).unwrap(),
+            None,
+        )
+        .unwrap();
+
+    let runs = wait_for_runs(&test, &project, 1);
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].automation, "slow");
+    assert!(runs[0].timed_out);
+    assert_eq!(runs[0].exit_code, None);
+}
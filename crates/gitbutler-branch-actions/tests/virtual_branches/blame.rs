@@ -0,0 +1,66 @@
+use gitbutler_branch::BranchId;
+use gitbutler_branch_actions::{BlameHunk, LineOrigin};
+
+use super::*;
+
+/// Return the line ranges of `hunks`, along with their commit and branch.
+fn origins(hunks: &[BlameHunk]) -> Vec<(u32, u32, Option<git2::Oid>, Option<BranchId>)> {
+    hunks
+        .iter()
+        .map(|hunk| match &hunk.origin {
+            LineOrigin::Committed {
+                commit_id,
+                branch_id,
+                ..
+            } => (hunk.start_line, hunk.lines, Some(*commit_id), *branch_id),
+            LineOrigin::Uncommitted { branch_id } => {
+                (hunk.start_line, hunk.lines, None, *branch_id)
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn lines_are_attributed_to_commits_or_the_branch_owning_their_changes() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "one\ntwo\nthree\n").unwrap();
+    let commit_id = test
+        .controller
+        .create_commit(&test.project, branch_id, "add file", None, false)
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "one\nTWO\nthree\n").unwrap();
+    fs::write(test.repository.path().join("new.txt"), "a\nb\n").unwrap();
+
+    let hunks = test
+        .controller
+        .blame(&test.project, "file.txt".as_ref())
+        .unwrap();
+    assert_eq!(
+        origins(&hunks),
+        [
+            (1, 1, Some(commit_id), Some(branch_id)),
+            (2, 1, None, Some(branch_id)),
+            (3, 1, Some(commit_id), Some(branch_id)),
+        ]
+    );
+    let LineOrigin::Committed { summary, .. } = &hunks[0].origin else {
+        unreachable!("checked above");
+    };
+    assert_eq!(summary, "add file");
+
+    let hunks = test
+        .controller
+        .blame(&test.project, "new.txt".as_ref())
+        .unwrap();
+    assert_eq!(origins(&hunks), [(1, 2, None, Some(branch_id))]);
+}
@@ -0,0 +1,97 @@
+use gitbutler_branch::BranchId;
+
+use super::*;
+
+fn lines(changed: &[usize]) -> String {
+    (1..=30)
+        .map(|line| {
+            if changed.contains(&line) {
+                format!("changed {line}\n")
+            } else {
+                format!("line {line}\n")
+            }
+        })
+        .collect()
+}
+
+/// Set up a target with a 30-line `file.txt`, and two applied branches with one commit each, the first
+/// changing line 5 and the second changing line 15. The second branch is selected for changes.
+fn two_branches_with_commits(test: &Test) -> (BranchId, BranchId) {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = test;
+    let path = repository.path().join("file.txt");
+    fs::write(&path, lines(&[])).unwrap();
+    repository.commit_all("thirty lines");
+    repository.push();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let first = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(&path, lines(&[5])).unwrap();
+    controller
+        .create_commit(project, first, "first", None, false)
+        .unwrap();
+
+    let second = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                selected_for_changes: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(&path, lines(&[5, 15])).unwrap();
+    controller
+        .create_commit(project, second, "second", None, false)
+        .unwrap();
+    (first, second)
+}
+
+#[test]
+fn changes_between_the_commits_of_two_branches_depend_on_both() {
+    let test = Test::default();
+    let (first, second) = two_branches_with_commits(&test);
+    fs::write(test.repository.path().join("file.txt"), lines(&[5, 10, 15])).unwrap();
+
+    let dependencies = test.controller.branch_dependencies(&test.project).unwrap();
+    assert_eq!(dependencies.len(), 1);
+    let dependency = &dependencies[0];
+    let mut ids = [dependency.branch_id, dependency.depends_on];
+    ids.sort();
+    let mut expected = [first, second];
+    expected.sort();
+    assert_eq!(ids, expected);
+    assert_eq!(dependency.hunks.len(), 1);
+    assert_eq!(dependency.hunks[0].path, PathBuf::from("file.txt"));
+
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    let owner = branches
+        .iter()
+        .find(|branch| branch.id == dependency.branch_id)
+        .unwrap();
+    assert_eq!(owner.files[0].hunks[0].id, dependency.hunks[0].hunk_id);
+}
+
+#[test]
+fn distant_changes_are_independent() {
+    let test = Test::default();
+    two_branches_with_commits(&test);
+    fs::write(test.repository.path().join("file.txt"), lines(&[5, 15, 28])).unwrap();
+
+    assert!(test
+        .controller
+        .branch_dependencies(&test.project)
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,57 @@
+use gitbutler_branch::{BranchCreateRequest, BranchEventKind};
+
+use super::*;
+
+#[test]
+fn events_are_resumable_by_cursor() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit one", None, false)
+        .unwrap();
+    controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap();
+
+    let events = controller
+        .branch_events_since(project, branch_id, None)
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[0].event,
+        BranchEventKind::CommitCreated { commit: commit_id }
+    );
+    assert!(matches!(&events[1].event, BranchEventKind::Pushed { head, .. } if *head == commit_id));
+    assert!(events[0].cursor < events[1].cursor);
+
+    let resumed = controller
+        .branch_events_since(project, branch_id, Some(events[0].cursor))
+        .unwrap();
+    assert_eq!(
+        resumed,
+        &events[1..],
+        "only events after the cursor are returned"
+    );
+
+    let other_branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    assert!(controller
+        .branch_events_since(project, other_branch_id, None)
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,79 @@
+use gitbutler_branch::BranchCreateRequest;
+use gitbutler_branch_actions::MAX_METADATA_VALUE_LEN;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use serde_json::json;
+
+use super::*;
+
+#[test]
+fn values_are_kept_until_removed() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    assert!(controller
+        .branch_metadata(project, branch_id)
+        .unwrap()
+        .is_empty());
+
+    let ticket = json!({ "id": "PROJ-42", "labels": ["bug"], "assignee": null });
+    controller
+        .set_branch_metadata(project, branch_id, "jira.ticket", Some(&ticket))
+        .unwrap();
+    controller
+        .set_branch_metadata(project, branch_id, "acme/release", Some(&json!(3)))
+        .unwrap();
+    let metadata = controller.branch_metadata(project, branch_id).unwrap();
+    assert_eq!(metadata.len(), 2);
+    assert_eq!(metadata["jira.ticket"], ticket);
+    assert_eq!(metadata["acme/release"], json!(3));
+
+    controller
+        .set_branch_metadata(project, branch_id, "acme/release", None)
+        .unwrap();
+    let metadata = controller.branch_metadata(project, branch_id).unwrap();
+    assert_eq!(metadata.keys().collect::<Vec<_>>(), ["jira.ticket"]);
+}
+
+#[test]
+fn invalid_keys_and_large_values_are_rejected() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    for (key, value) in [
+        ("", json!(1)),
+        ("has space", json!(1)),
+        ("large", json!("x".repeat(MAX_METADATA_VALUE_LEN))),
+    ] {
+        let err = controller
+            .set_branch_metadata(project, branch_id, key, Some(&value))
+            .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation),
+            "{key}"
+        );
+    }
+    assert!(controller
+        .branch_metadata(project, branch_id)
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,102 @@
+#![cfg(unix)]
+
+use gitbutler_project::{BranchNaming, Settings};
+
+use super::*;
+
+fn with_naming_command(test: &Test, command: &str) -> Project {
+    let settings = Settings {
+        branch_naming: Some(BranchNaming::Command {
+            command: command.into(),
+        }),
+        ..test.project.settings.clone()
+    };
+    test.projects
+        .update_settings(test.project.id, settings)
+        .unwrap();
+    test.projects.get(test.project.id).unwrap()
+}
+
+#[test]
+fn branches_created_from_a_summary_are_named_by_the_command() {
+    let test = Test::default();
+    let project = with_naming_command(
+        &test,
+        r#"sed 's/{"summary":"\(.*\)"}/{"name":"PROJ-1 \1","description":"Tracked in PROJ-1"}/'"#,
+    );
+    test.controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = test
+        .controller
+        .create_virtual_branch(
+            &project,
+            &BranchCreateRequest {
+                summary: Some("Fix login".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let (branches, _) = test.controller.list_virtual_branches(&project).unwrap();
+    let branch = branches
+        .iter()
+        .find(|branch| branch.id == branch_id)
+        .unwrap();
+    assert_eq!(branch.name, "PROJ-1 Fix login");
+    assert_eq!(branch.notes, "Tracked in PROJ-1");
+}
+
+#[test]
+fn explicit_names_are_used_as_is() {
+    let test = Test::default();
+    let project = with_naming_command(&test, "exit 1");
+    test.controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = test
+        .controller
+        .create_virtual_branch(
+            &project,
+            &BranchCreateRequest {
+                name: Some("mine".into()),
+                summary: Some("Fix login".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let (branches, _) = test.controller.list_virtual_branches(&project).unwrap();
+    let branch = branches
+        .iter()
+        .find(|branch| branch.id == branch_id)
+        .unwrap();
+    assert_eq!(branch.name, "mine", "the command isn't run");
+}
+
+#[test]
+fn failing_commands_prevent_the_branch_from_being_created() {
+    let test = Test::default();
+    let project = with_naming_command(&test, "echo 'no such ticket' >&2; exit 1");
+    test.controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let (before, _) = test.controller.list_virtual_branches(&project).unwrap();
+
+    let err = test
+        .controller
+        .create_virtual_branch(
+            &project,
+            &BranchCreateRequest {
+                summary: Some("Fix login".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("no such ticket"), "{err:#}");
+
+    let (after, _) = test.controller.list_virtual_branches(&project).unwrap();
+    assert_eq!(after.len(), before.len(), "no branch was created");
+}
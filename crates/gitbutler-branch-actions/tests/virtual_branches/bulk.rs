@@ -0,0 +1,103 @@
+use gitbutler_branch::{BranchCreateRequest, BranchId};
+use gitbutler_oplog::{entry::OperationKind, OplogExt};
+
+use super::*;
+
+fn create_branch_with_file(
+    controller: &VirtualBranchActions,
+    project: &Project,
+    name: &str,
+) -> BranchId {
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some(name.to_owned()),
+                selected_for_changes: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(project.path.join(format!("{name}.txt")), "content").unwrap();
+    controller.list_virtual_branches(project).unwrap();
+    branch_id
+}
+
+#[test]
+fn delete_several_branches_in_one_snapshot() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let a = create_branch_with_file(controller, project, "a");
+    let b = create_branch_with_file(controller, project, "b");
+    let snapshot_count = project.list_snapshots(100, None).unwrap().len();
+
+    let results = controller
+        .delete_virtual_branches(project, &[a, b])
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.error.is_none()));
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert!(branches.is_empty());
+    assert!(!repository.path().join("a.txt").exists());
+    assert!(!repository.path().join("b.txt").exists());
+
+    let snapshots = project.list_snapshots(100, None).unwrap();
+    assert_eq!(snapshots.len(), snapshot_count + 1, "a single snapshot");
+    assert_eq!(
+        snapshots[0].details.as_ref().unwrap().operation,
+        OperationKind::DeleteBranches
+    );
+}
+
+#[test]
+fn unapply_all_and_apply_again_by_name() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    create_branch_with_file(controller, project, "a");
+    create_branch_with_file(controller, project, "b");
+
+    let results = controller.unapply_all_branches(project).unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.error.is_none()));
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert!(branches.is_empty());
+
+    let results = controller
+        .apply_branches(
+            project,
+            &[
+                "refs/heads/a".parse().unwrap(),
+                "refs/heads/missing".parse().unwrap(),
+            ],
+        )
+        .unwrap();
+    assert_eq!(results[0].branch, "refs/heads/a");
+    assert!(results[0].error.is_none());
+    assert_eq!(results[1].branch, "refs/heads/missing");
+    assert!(results[1].error.is_some(), "the branch doesn't exist");
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].name, "a");
+    assert!(repository.path().join("a.txt").exists());
+    assert!(!repository.path().join("b.txt").exists());
+}
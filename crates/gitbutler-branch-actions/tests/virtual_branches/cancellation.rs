@@ -0,0 +1,116 @@
+use gitbutler_branch_actions::BaseUpdateStrategy;
+use gitbutler_command_context::{cancellation, CancellationToken};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_oplog::{entry::OperationKind, OplogExt};
+use gitbutler_repo::progress;
+
+use super::*;
+
+fn cancelled() -> CancellationToken {
+    let token = CancellationToken::new();
+    token.cancel();
+    token
+}
+
+#[test]
+fn cancelled_base_updates_are_rolled_back() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+    {
+        fs::write(repository.path().join("file.txt"), "first").unwrap();
+        let first_commit_oid = repository.commit_all("first");
+        fs::write(repository.path().join("file.txt"), "second").unwrap();
+        repository.commit_all("second");
+        repository.push();
+        repository.reset_hard(Some(first_commit_oid));
+    }
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("other.txt"), "change").unwrap();
+    controller.list_virtual_branches(project).unwrap();
+
+    let (progress, _updates) = progress::channel();
+    progress.cancel();
+    let err = controller
+        .update_base_branch_with_progress(project, BaseUpdateStrategy::default(), &progress)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Cancelled)
+    );
+
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "first",
+        "the worktree is still on the old target"
+    );
+    let base = VirtualBranchActions::get_base_branch_data(project).unwrap();
+    assert_eq!(base.behind, 1);
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].files.len(), 1);
+    let snapshot = &project.list_snapshots(1, None).unwrap()[0];
+    assert_eq!(
+        snapshot.details.as_ref().map(|details| details.operation),
+        Some(OperationKind::RestoreFromSnapshot)
+    );
+
+    controller
+        .update_base_branch_with_progress(
+            project,
+            BaseUpdateStrategy::default(),
+            &progress::NoProgress,
+        )
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "second"
+    );
+}
+
+#[test]
+fn cancelled_snapshot_restores_change_nothing() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    let snapshots = project.list_snapshots(10, None).unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+
+    let err = cancellation::scoped(&cancelled(), || {
+        project.restore_snapshot(snapshots[0].commit_id)
+    })
+    .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Cancelled)
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "content"
+    );
+    assert_eq!(
+        project.list_snapshots(10, None).unwrap().len(),
+        snapshots.len(),
+        "the state before isn't recorded either"
+    );
+
+    project.restore_snapshot(snapshots[0].commit_id).unwrap();
+    assert!(!repository.path().join("file.txt").exists());
+}
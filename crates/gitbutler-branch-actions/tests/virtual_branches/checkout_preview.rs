@@ -0,0 +1,115 @@
+use gitbutler_branch::BranchCreateRequest;
+use gitbutler_branch_actions::{WorktreeChange, WorktreeChangeKind};
+use gitbutler_reference::Refname;
+
+use super::*;
+
+fn change(path: &str, kind: WorktreeChangeKind, uncommitted: bool) -> WorktreeChange {
+    WorktreeChange {
+        path: path.into(),
+        kind,
+        uncommitted,
+    }
+}
+
+#[test]
+fn unapplying_reports_deleted_files_and_whether_they_are_committed() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(test.repository.path().join("committed.txt"), "committed\n").unwrap();
+    test.controller
+        .create_commit(&test.project, branch_id, "commit", None, false)
+        .unwrap();
+    fs::write(test.repository.path().join("uncommitted.txt"), "new\n").unwrap();
+
+    let preview = test
+        .controller
+        .preview_unapply_branch(&test.project, branch_id)
+        .unwrap();
+    assert_eq!(
+        preview.files,
+        [
+            change("committed.txt", WorktreeChangeKind::Deleted, false),
+            change("uncommitted.txt", WorktreeChangeKind::Deleted, true),
+        ]
+    );
+    assert!(preview.touches_uncommitted_changes());
+    assert!(
+        test.repository.path().join("uncommitted.txt").exists(),
+        "previews leave the worktree alone"
+    );
+}
+
+#[test]
+fn applying_reports_files_with_uncommitted_changes_it_would_overwrite() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "branch\n").unwrap();
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    let unapplied_branch = test
+        .controller
+        .convert_to_real_branch(&test.project, branches[0].id)
+        .unwrap();
+    let unapplied_branch = Refname::from_str(&unapplied_branch).unwrap();
+
+    let preview = test
+        .controller
+        .preview_apply_branch(&test.project, &unapplied_branch)
+        .unwrap();
+    assert_eq!(
+        preview.files,
+        [change("file.txt", WorktreeChangeKind::Overwritten, false)]
+    );
+
+    fs::write(test.repository.path().join("file.txt"), "workspace\n").unwrap();
+    let preview = test
+        .controller
+        .preview_apply_branch(&test.project, &unapplied_branch)
+        .unwrap();
+    assert_eq!(
+        preview.files,
+        [change("file.txt", WorktreeChangeKind::Overwritten, true)]
+    );
+    assert_eq!(
+        fs::read_to_string(test.repository.path().join("file.txt")).unwrap(),
+        "workspace\n"
+    );
+}
+
+#[test]
+fn unapplying_a_branch_without_changes_touches_nothing() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+
+    assert!(test
+        .controller
+        .preview_unapply_branch(&test.project, branch_id)
+        .unwrap()
+        .is_empty());
+}
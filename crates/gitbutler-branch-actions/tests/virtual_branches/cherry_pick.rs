@@ -0,0 +1,112 @@
+use gitbutler_branch_actions::conflicts::Resolution;
+use gitbutler_commit::commit_ext::CommitExt;
+
+use super::*;
+
+/// Create a commit on top of the target that writes `content` to `path`, without any reference pointing to it.
+fn commit_onto_target(project: &Project, path: &str, content: &str, message: &str) -> git2::Oid {
+    let repo = git2::Repository::open(&project.path).unwrap();
+    let parent = repo
+        .find_reference("refs/remotes/origin/master")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    let blob = repo.blob(content.as_bytes()).unwrap();
+    let mut tree = repo.treebuilder(Some(&parent.tree().unwrap())).unwrap();
+    tree.insert(path, blob, 0o100644).unwrap();
+    let tree = repo.find_tree(tree.write().unwrap()).unwrap();
+    let author = git2::Signature::now("original author", "original@example.com").unwrap();
+    repo.commit(None, &author, &author, message, &tree, &[&parent])
+        .unwrap()
+}
+
+#[test]
+fn picks_commits_and_remembers_their_origin() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    let first = commit_onto_target(project, "first.txt", "first", "first");
+    let second = commit_onto_target(project, "second.txt", "second", "second");
+
+    let outcome = controller
+        .cherry_pick(project, branch_id, &[first, second])
+        .unwrap();
+    assert_eq!(outcome.picked.len(), 2);
+    assert_eq!(outcome.conflicted, None);
+    assert!(outcome.skipped.is_empty());
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].commits.len(), 2);
+    assert!(branches[0].files.is_empty(), "everything was committed");
+    assert_eq!(
+        fs::read_to_string(repository.path().join("second.txt")).unwrap(),
+        "second"
+    );
+
+    let picked = repository.find_commit(outcome.picked[1]).unwrap();
+    assert_eq!(picked.message(), Some("second"));
+    assert_eq!(picked.author().name(), Some("original author"));
+    assert_eq!(picked.cherry_picked_from(), Some(second));
+    assert_eq!(picked.parent_id(0).unwrap(), outcome.picked[0]);
+}
+
+#[test]
+fn conflicting_commit_is_resolved_with_the_conflict_api() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("file.txt"), "base").unwrap();
+    repository.commit_all("base");
+    repository.push();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "ours").unwrap();
+    controller
+        .create_commit(project, branch_id, "ours", None, false)
+        .unwrap();
+    let conflicting = commit_onto_target(project, "file.txt", "theirs", "theirs");
+    let skipped = commit_onto_target(project, "other.txt", "other", "other");
+
+    let outcome = controller
+        .cherry_pick(project, branch_id, &[conflicting, skipped])
+        .unwrap();
+    assert!(outcome.picked.is_empty());
+    assert_eq!(outcome.conflicted, Some(conflicting));
+    assert_eq!(outcome.skipped, vec![skipped]);
+
+    let files = controller.list_conflicted_files(project).unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, PathBuf::from("file.txt"));
+
+    controller
+        .resolve_conflict(project, path::Path::new("file.txt"), Resolution::Theirs)
+        .unwrap();
+    let commit_oid = controller
+        .finalize_conflict_resolution(project, branch_id, "theirs")
+        .unwrap();
+    let commit = repository.find_commit(commit_oid).unwrap();
+    assert_eq!(commit.parent_count(), 1, "picks aren't merges");
+    assert_eq!(commit.cherry_picked_from(), Some(conflicting));
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].commits.len(), 2);
+    assert!(!branches[0].conflicted);
+}
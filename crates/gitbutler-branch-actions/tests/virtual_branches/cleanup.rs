@@ -0,0 +1,129 @@
+use gitbutler_project::{BranchCleanupAction, BranchCleanupPolicy};
+
+use super::*;
+
+fn with_policy(project: &Project, action: BranchCleanupAction) -> Project {
+    let mut project = project.clone();
+    project.branch_cleanup = BranchCleanupPolicy {
+        integrated_for_days: 0,
+        action,
+        ..Default::default()
+    };
+    project
+}
+
+#[test]
+fn only_integrated_branches_are_pending() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    repository.checkout(&"refs/heads/merged".parse().unwrap());
+    repository.checkout(&"refs/heads/unmerged".parse().unwrap());
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    repository.commit_all("not upstream");
+    repository.checkout(&"refs/heads/master".parse().unwrap());
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let project = with_policy(project, BranchCleanupAction::Archive);
+    let pending = controller.list_pending_cleanups(&project).unwrap();
+    assert_eq!(
+        pending
+            .iter()
+            .map(|cleanup| cleanup.name.as_str())
+            .collect::<Vec<_>>(),
+        ["merged"]
+    );
+
+    let mut waiting = project.clone();
+    waiting.branch_cleanup.integrated_for_days = 1;
+    assert!(
+        controller
+            .list_pending_cleanups(&waiting)
+            .unwrap()
+            .is_empty(),
+        "the branch wasn't integrated for long enough"
+    );
+}
+
+#[test]
+fn archive_keeps_a_reference() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    repository.checkout(&"refs/heads/merged".parse().unwrap());
+    repository.checkout(&"refs/heads/master".parse().unwrap());
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let project = with_policy(project, BranchCleanupAction::Archive);
+    controller
+        .clean_up_branches(&project, &["merged".to_owned()])
+        .unwrap();
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    assert!(repo.find_reference("refs/heads/merged").is_err());
+    assert!(repo.find_reference("refs/archive/merged").is_ok());
+    assert!(controller
+        .list_pending_cleanups(&project)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn delete_removes_the_branch() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    repository.checkout(&"refs/heads/merged".parse().unwrap());
+    repository.checkout(&"refs/heads/master".parse().unwrap());
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let project = with_policy(project, BranchCleanupAction::Delete);
+    controller
+        .clean_up_branches(&project, &["merged".to_owned()])
+        .unwrap();
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    assert!(repo.find_reference("refs/heads/merged").is_err());
+    assert!(repo.find_reference("refs/archive/merged").is_err());
+}
+
+#[test]
+fn branches_not_pending_are_refused() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let project = with_policy(project, BranchCleanupAction::Delete);
+    assert_eq!(
+        controller
+            .clean_up_branches(&project, &["master".to_owned()])
+            .unwrap_err()
+            .to_string(),
+        "branch 'master' isn't pending cleanup"
+    );
+}
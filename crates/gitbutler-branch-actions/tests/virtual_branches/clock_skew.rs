@@ -0,0 +1,137 @@
+use std::time::{Duration, SystemTime};
+
+use gitbutler_branch::BranchId;
+use gitbutler_branch_actions::ClockSkew;
+use gitbutler_time::clock::{use_clock_in_thread, SteppingClock};
+
+use super::*;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn commit_at(test: &Test, branch_id: BranchId, content: &str, time: SystemTime) -> git2::Oid {
+    let _clock = use_clock_in_thread(SteppingClock::new(time, Duration::from_secs(1)));
+    fs::write(test.repository.path().join("file.txt"), content).unwrap();
+    test.controller
+        .create_commit(&test.project, branch_id, content, None, false)
+        .unwrap()
+}
+
+fn skew_of_commits(test: &Test, branch_id: BranchId) -> Vec<Option<ClockSkew>> {
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    let branch = branches
+        .iter()
+        .find(|branch| branch.id == branch_id)
+        .unwrap();
+    branch
+        .commits
+        .iter()
+        .map(|commit| commit.clock_skew)
+        .collect()
+}
+
+#[test]
+fn commits_in_the_future_are_flagged_and_normalized_to_now() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let skewed = commit_at(&test, branch_id, "one", SystemTime::now() + 2 * DAY);
+    assert_eq!(
+        skew_of_commits(&test, branch_id),
+        [Some(ClockSkew::InFuture)]
+    );
+
+    let head = test
+        .controller
+        .normalize_commit_times(&test.project, branch_id, false)
+        .unwrap();
+    assert_ne!(head, skewed);
+    assert_eq!(skew_of_commits(&test, branch_id), [None]);
+
+    let repo = &test.repository;
+    let (normalized, skewed) = (
+        repo.find_commit(head).unwrap(),
+        repo.find_commit(skewed).unwrap(),
+    );
+    assert_eq!(normalized.tree_id(), skewed.tree_id());
+    assert_eq!(normalized.message(), skewed.message());
+    assert!(normalized.time().seconds() < skewed.time().seconds() - DAY.as_secs() as i64);
+}
+
+#[test]
+fn commits_long_before_their_parent_are_moved_to_its_time() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let parent = commit_at(&test, branch_id, "one", SystemTime::now());
+    commit_at(&test, branch_id, "two", SystemTime::now() - 2 * DAY);
+    assert_eq!(
+        skew_of_commits(&test, branch_id),
+        [Some(ClockSkew::BeforeParent), None],
+        "commits are listed newest first"
+    );
+
+    let head = test
+        .controller
+        .normalize_commit_times(&test.project, branch_id, false)
+        .unwrap();
+    assert_eq!(skew_of_commits(&test, branch_id), [None, None]);
+
+    let normalized = test.repository.find_commit(head).unwrap();
+    assert_eq!(
+        normalized.parent_id(0).unwrap(),
+        parent,
+        "commits before the first skewed one are kept"
+    );
+    assert_eq!(
+        normalized.time().seconds(),
+        test.repository
+            .find_commit(parent)
+            .unwrap()
+            .time()
+            .seconds()
+    );
+}
+
+#[test]
+fn branches_without_skew_are_left_alone() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+    let head = commit_at(&test, branch_id, "one", SystemTime::now());
+
+    assert_eq!(
+        test.controller
+            .normalize_commit_times(&test.project, branch_id, false)
+            .unwrap(),
+        head
+    );
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeSet;
+
+use gitbutler_branch_actions::{GraphEdge, GraphRef};
+
+use super::*;
+
+#[test]
+fn branches_get_lanes_that_join_at_the_base() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let mut branch_ids = BTreeSet::new();
+    for name in ["a", "b"] {
+        let branch_id = test
+            .controller
+            .create_virtual_branch(
+                &test.project,
+                &BranchCreateRequest {
+                    name: Some(name.into()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        fs::write(test.repository.path().join(format!("{name}.txt")), name).unwrap();
+        test.controller
+            .create_commit(&test.project, branch_id, name, None, false)
+            .unwrap();
+        branch_ids.insert(branch_id);
+    }
+    let base = test
+        .controller
+        .get_base_branch_data(&test.project)
+        .unwrap()
+        .base_sha;
+
+    let graph = test.controller.commit_graph(&test.project, 100).unwrap();
+    assert!(!graph.truncated);
+    assert_eq!(graph.columns, 2);
+    let columns: Vec<_> = graph.rows.iter().map(|row| row.column).take(3).collect();
+    assert_eq!(columns, [0, 1, 0]);
+    let heads: BTreeSet<_> = graph.rows[..2]
+        .iter()
+        .flat_map(|row| &row.refs)
+        .filter_map(|r#ref| match r#ref {
+            GraphRef::VirtualBranch { branch_id, .. } => Some(*branch_id),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(heads, branch_ids);
+    assert_eq!(graph.rows[0].edges, [GraphEdge { from: 0, to: 0 }]);
+    assert_eq!(
+        graph.rows[1].edges,
+        [GraphEdge { from: 0, to: 0 }, GraphEdge { from: 1, to: 0 }],
+        "the lane of the second branch joins the first one at the base"
+    );
+    assert_eq!(graph.rows[2].commit_id, base);
+    assert!(graph.rows[2]
+        .refs
+        .iter()
+        .any(|r#ref| matches!(r#ref, GraphRef::Base { .. })));
+
+    let graph = test.controller.commit_graph(&test.project, 2).unwrap();
+    assert!(graph.truncated);
+    assert_eq!(graph.rows.len(), 2);
+}
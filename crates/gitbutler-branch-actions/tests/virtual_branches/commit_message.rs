@@ -0,0 +1,254 @@
+use std::sync::Arc;
+
+use gitbutler_branch::{BranchCreateRequest, BranchOwnershipClaims};
+use gitbutler_branch_actions::{
+    register_message_checker, CommitPreviewFile, CommitTemplate, CommitTemplateSource,
+    FindingSeverity, MessageChecker, MessageFinding,
+};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+#[test]
+fn messages_violating_the_conventions_are_rejected_before_committing() {
+    let Test {
+        repository,
+        project_id,
+        controller,
+        projects,
+        ..
+    } = &Test::default();
+
+    let project = &projects
+        .update(&projects::UpdateRequest {
+            id: *project_id,
+            commit_conventions: Some(projects::CommitConventions {
+                subject_pattern: Some(projects::CONVENTIONAL_COMMITS_PATTERN.into()),
+                max_subject_length: Some(30),
+                blank_line_after_subject: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+
+    for (message, rule) in [
+        ("add parser", "doesn't match the pattern"),
+        (
+            "feat: add a parser for the configuration files",
+            "may have at most 30",
+        ),
+        ("feat: add parser\nwith a body", "followed by a blank line"),
+    ] {
+        let err = controller
+            .create_commit(project, branch_id, message, None, false)
+            .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation)
+        );
+        assert!(err.to_string().contains(rule), "{err:#}");
+    }
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert!(branches[0].commits.is_empty());
+
+    controller
+        .create_commit(
+            project,
+            branch_id,
+            "feat(config): add parser\n\nwith a body",
+            None,
+            false,
+        )
+        .unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].commits.len(), 1);
+}
+
+#[test]
+fn template_of_the_project_takes_precedence_over_git_config() {
+    let Test {
+        repository,
+        project_id,
+        project,
+        controller,
+        projects,
+        ..
+    } = &Test::default();
+
+    let template_path = repository.path().join(".git").join("commit-template");
+    fs::write(&template_path, "Subject\n\n# Why?\n").unwrap();
+    git2::Repository::open(repository.path())
+        .unwrap()
+        .config()
+        .unwrap()
+        .set_str("commit.template", template_path.to_str().unwrap())
+        .unwrap();
+    assert_eq!(
+        controller.commit_template(project).unwrap(),
+        Some(CommitTemplate {
+            text: "Subject\n\n# Why?\n".into(),
+            source: CommitTemplateSource::GitConfig {
+                path: template_path
+            },
+        })
+    );
+
+    let project = &projects
+        .update(&projects::UpdateRequest {
+            id: *project_id,
+            commit_conventions: Some(projects::CommitConventions {
+                template: Some("feat: ".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(
+        controller.commit_template(project).unwrap(),
+        Some(CommitTemplate {
+            text: "feat: ".into(),
+            source: CommitTemplateSource::Project,
+        })
+    );
+}
+
+/// Rejects messages that say they aren't meant to be committed.
+struct DoNotCommitChecker;
+
+impl MessageChecker for DoNotCommitChecker {
+    fn name(&self) -> &str {
+        "do-not-commit"
+    }
+
+    fn check(&self, message: &str) -> Vec<MessageFinding> {
+        message
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.contains("DO NOT COMMIT"))
+            .map(|(idx, _)| MessageFinding {
+                checker: String::new(),
+                rule: "marker".into(),
+                severity: FindingSeverity::Error,
+                line: idx + 1,
+                message: "the message says not to commit".into(),
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn preview_lists_the_files_and_findings_of_a_commit() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("a.txt"), "content\n").unwrap();
+    fs::write(repository.path().join("b.txt"), "content\n").unwrap();
+
+    let preview = controller
+        .commit_preview(
+            project,
+            branch_id,
+            "Added a parser \nwhich reads the configuration",
+            None,
+        )
+        .unwrap();
+    let mut paths: Vec<_> = preview.files.iter().map(|file| file.path.clone()).collect();
+    paths.sort();
+    assert_eq!(paths, ["a.txt", "b.txt"].map(std::path::PathBuf::from));
+    assert_eq!(preview.convention_violation, None);
+    assert!(!preview.is_blocked());
+    let rules: Vec<_> = preview
+        .message_findings
+        .iter()
+        .map(|finding| {
+            (
+                finding.checker.as_str(),
+                finding.rule.as_str(),
+                finding.line,
+            )
+        })
+        .collect();
+    assert_eq!(
+        rules,
+        [
+            ("basic", "trailing-whitespace", 1),
+            ("basic", "imperative-mood", 1),
+            ("basic", "blank-line-after-subject", 2),
+        ]
+    );
+
+    let ownership: BranchOwnershipClaims = "a.txt:1-2".parse().unwrap();
+    let preview = controller
+        .commit_preview(project, branch_id, "Add a parser", Some(&ownership))
+        .unwrap();
+    assert_eq!(
+        preview.files,
+        [CommitPreviewFile {
+            path: "a.txt".into(),
+            hunks: 1,
+        }]
+    );
+    assert!(preview.message_findings.is_empty());
+}
+
+#[test]
+fn errors_of_registered_checkers_prevent_committing() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    register_message_checker(Arc::new(DoNotCommitChecker));
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+
+    let message = "Add a parser\n\nDO NOT COMMIT";
+    let preview = controller
+        .commit_preview(project, branch_id, message, None)
+        .unwrap();
+    assert!(preview.is_blocked());
+    assert!(preview
+        .message_findings
+        .iter()
+        .any(|finding| { finding.checker == "do-not-commit" && finding.line == 3 }));
+
+    let err = controller
+        .create_commit(project, branch_id, message, None, false)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+    assert!(err.to_string().contains("says not to commit"), "{err:#}");
+
+    controller
+        .create_commit(project, branch_id, "Add a parser", None, false)
+        .unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].commits.len(), 1);
+}
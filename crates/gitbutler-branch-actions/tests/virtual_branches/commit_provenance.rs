@@ -0,0 +1,90 @@
+use gitbutler_branch::{BranchCreateRequest, BranchOwnershipClaims};
+use gitbutler_diff::Hunk;
+use gitbutler_oplog::OplogExt;
+
+use super::*;
+
+#[test]
+fn records_committed_hunks_and_snapshot() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::write(repository.path().join("committed.txt"), "content\n").unwrap();
+    fs::write(repository.path().join("uncommitted.txt"), "content\n").unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let hunk = branches[0]
+        .files
+        .iter()
+        .find(|file| file.path == path::Path::new("committed.txt"))
+        .map(|file| file.hunks[0].clone())
+        .unwrap();
+
+    let ownership: BranchOwnershipClaims = "committed.txt:1-2".parse().unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit", Some(&ownership), false)
+        .unwrap();
+
+    let provenance = controller
+        .commit_provenance(project, commit_id)
+        .unwrap()
+        .expect("the commit was created in the workspace");
+    assert_eq!(provenance.branch_id, branch_id);
+    assert_eq!(
+        provenance.hunks.len(),
+        1,
+        "only committed hunks are recorded"
+    );
+    assert_eq!(
+        provenance.hunks[0].file_path,
+        path::Path::new("committed.txt")
+    );
+    assert_eq!(
+        provenance.hunks[0].hunk,
+        Hunk::new(hunk.start, hunk.end, Some(hunk.hash))
+            .unwrap()
+            .to_string()
+    );
+
+    let snapshots = project.list_snapshots(1, None).unwrap();
+    assert_eq!(
+        provenance.snapshot_id,
+        Some(snapshots[0].commit_id),
+        "the snapshot of the workspace before the commit is linked"
+    );
+}
+
+#[test]
+fn unknown_commits_have_no_provenance() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let head = git2::Repository::open(repository.path())
+        .unwrap()
+        .head()
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+
+    assert_eq!(
+        controller.commit_provenance(project, head.id()).unwrap(),
+        None
+    );
+}
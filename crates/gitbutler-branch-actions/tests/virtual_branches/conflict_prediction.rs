@@ -0,0 +1,171 @@
+use gitbutler_branch::{BranchId, VirtualBranchesHandle};
+
+use super::*;
+
+fn lines(changed: Option<usize>) -> String {
+    (1..=10)
+        .map(|line| {
+            if Some(line) == changed {
+                format!("changed {line}\n")
+            } else {
+                format!("line {line}\n")
+            }
+        })
+        .collect()
+}
+
+/// Set up a target with a 10-line `file.txt`, and return the id of the target commit.
+fn set_base_branch(test: &Test) -> git2::Oid {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = test;
+    fs::write(repository.path().join("file.txt"), lines(None)).unwrap();
+    let target = repository.commit_all("ten lines");
+    repository.push();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    target
+}
+
+/// Create a branch whose only commit changes `changed_line` of `path` on top of `target`.
+fn branch_changing_line(
+    test: &Test,
+    target: git2::Oid,
+    path: &str,
+    changed_line: usize,
+) -> BranchId {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = test;
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let target = repo.find_commit(target).unwrap();
+    let mut tree = git2::build::TreeUpdateBuilder::new();
+    tree.upsert(
+        path,
+        repo.blob(lines(Some(changed_line)).as_bytes()).unwrap(),
+        git2::FileMode::Blob,
+    );
+    let tree = tree.create_updated(&repo, &target.tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("author", "author@example.com").unwrap();
+    let head = repo
+        .commit(
+            None,
+            &signature,
+            &signature,
+            "change",
+            &repo.find_tree(tree).unwrap(),
+            &[&target],
+        )
+        .unwrap();
+
+    let vb_state = VirtualBranchesHandle::new(project.gb_dir());
+    let mut branch = vb_state.get_branch(branch_id).unwrap();
+    branch.head = head;
+    branch.tree = repo.find_commit(head).unwrap().tree_id();
+    vb_state.set_branch(branch).unwrap();
+    branch_id
+}
+
+#[test]
+fn adjacent_changes_are_predicted_to_conflict() {
+    let test = Test::default();
+    let target = set_base_branch(&test);
+    let first = branch_changing_line(&test, target, "file.txt", 3);
+    let second = branch_changing_line(&test, target, "file.txt", 4);
+
+    let predictions = test.controller.predict_conflicts(&test.project).unwrap();
+    assert_eq!(predictions.len(), 1);
+    assert_eq!(predictions[0].branch_ids, [first, second]);
+    assert_eq!(predictions[0].files.len(), 1);
+    assert_eq!(predictions[0].files[0].path, path::Path::new("file.txt"));
+    assert_eq!(predictions[0].files[0].lines, [3..5]);
+}
+
+#[test]
+fn distant_changes_and_other_files_are_fine() {
+    let test = Test::default();
+    let target = set_base_branch(&test);
+    branch_changing_line(&test, target, "file.txt", 2);
+    branch_changing_line(&test, target, "file.txt", 8);
+    branch_changing_line(&test, target, "other.txt", 2);
+
+    assert!(test
+        .controller
+        .predict_conflicts(&test.project)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn merge_orders_stop_at_the_first_conflict() {
+    let test = Test::default();
+    let target = set_base_branch(&test);
+    let first = branch_changing_line(&test, target, "file.txt", 3);
+    let second = branch_changing_line(&test, target, "file.txt", 4);
+    let third = branch_changing_line(&test, target, "other.txt", 2);
+
+    let simulation = test
+        .controller
+        .simulate_merge_orders(&test.project, 10)
+        .unwrap();
+    assert!(!simulation.truncated);
+    let orders: Vec<_> = simulation
+        .orders
+        .iter()
+        .map(|order| order.branch_ids.clone())
+        .collect();
+    assert_eq!(
+        orders,
+        [
+            vec![first, second],
+            vec![first, third, second],
+            vec![second, first],
+            vec![second, third, first],
+            vec![third, first, second],
+            vec![third, second, first],
+        ]
+    );
+    for order in &simulation.orders {
+        let conflict = order.conflict.as_ref().unwrap();
+        assert_eq!(conflict.branch_id, *order.branch_ids.last().unwrap());
+        assert_eq!(conflict.paths, [path::PathBuf::from("file.txt")]);
+    }
+}
+
+#[test]
+fn merge_orders_are_limited() {
+    let test = Test::default();
+    let target = set_base_branch(&test);
+    let first = branch_changing_line(&test, target, "file.txt", 2);
+    let second = branch_changing_line(&test, target, "file.txt", 8);
+
+    let simulation = test
+        .controller
+        .simulate_merge_orders(&test.project, 10)
+        .unwrap();
+    assert!(!simulation.truncated);
+    assert_eq!(simulation.orders.len(), 2);
+    assert!(simulation
+        .orders
+        .iter()
+        .all(|order| order.conflict.is_none()));
+
+    let simulation = test
+        .controller
+        .simulate_merge_orders(&test.project, 1)
+        .unwrap();
+    assert!(simulation.truncated);
+    assert_eq!(simulation.orders.len(), 1);
+    assert_eq!(simulation.orders[0].branch_ids, [first, second]);
+}
@@ -0,0 +1,89 @@
+use gitbutler_branch_actions::ContentOrigin;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+#[test]
+fn matches_tell_which_branch_adds_the_line() {
+    let Test {
+        repository,
+        controller,
+        project,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(
+        repository.path().join("committed.txt"),
+        "first\nneedle one\n",
+    )
+    .unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+    fs::write(repository.path().join("uncommitted.txt"), "needle two\n").unwrap();
+    fs::write(repository.path().join(".gitignore"), "ignored.txt\n").unwrap();
+    fs::write(repository.path().join("ignored.txt"), "needle three\n").unwrap();
+
+    let matches = controller.search_content(project, "needle", 100).unwrap();
+    let found = |path: &str, origin: ContentOrigin| {
+        matches
+            .iter()
+            .any(|m| m.path == path::Path::new(path) && m.origin == origin)
+    };
+    assert!(found(
+        "committed.txt",
+        ContentOrigin::Worktree { branch_id: None }
+    ));
+    assert!(found(
+        "committed.txt",
+        ContentOrigin::Commit {
+            branch_id,
+            commit_id
+        }
+    ));
+    assert!(found(
+        "uncommitted.txt",
+        ContentOrigin::Worktree {
+            branch_id: Some(branch_id)
+        }
+    ));
+    assert_eq!(matches.len(), 3, "ignored files aren't searched");
+    let committed = matches
+        .iter()
+        .find(|m| matches!(m.origin, ContentOrigin::Commit { .. }))
+        .unwrap();
+    assert_eq!(committed.line_number, 2);
+    assert_eq!(committed.line, "needle one");
+
+    assert_eq!(
+        controller
+            .search_content(project, "needle", 1)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn invalid_patterns_are_rejected() {
+    let Test {
+        controller,
+        project,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let err = controller.search_content(project, "(", 10).unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+}
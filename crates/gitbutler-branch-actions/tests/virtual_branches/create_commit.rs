@@ -1,5 +1,11 @@
+use std::collections::BTreeMap;
+
 use gitbutler_branch::{Branch, BranchCreateRequest, BranchUpdateRequest};
-use gitbutler_branch_actions::VirtualBranch;
+use gitbutler_branch_actions::{
+    cache_workdir_diff, invalidate_workdir_cache, FilesChangedDuringCommit, VirtualBranch,
+};
+use gitbutler_diff::HunkSelection;
+use gitbutler_error::error::{AnyhowContextExt, Code};
 use gitbutler_id::id::Id;
 
 use super::*;
@@ -114,6 +120,180 @@ fn should_reset_into_same_branch() {
     assert_eq!(files.len(), 1);
 }
 
+#[test]
+fn should_commit_selected_lines_of_hunk() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    let mut lines = repository.gen_file("file.txt", 10);
+    commit_and_push_initial(repository);
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    lines[2] = "changed 2".to_string();
+    lines[4] = "changed 4".to_string();
+    repository.write_file("file.txt", &lines);
+
+    let hunk = {
+        let branch = get_virtual_branch(controller, project, branch_id);
+        assert_eq!(
+            branch.files[0].hunks.len(),
+            1,
+            "both changes are in one hunk"
+        );
+        branch.files[0].hunks[0].clone()
+    };
+
+    // Lines 3 and 4 of the hunk are `-line 2` and `+changed 2`.
+    let selections = BTreeMap::from([(
+        PathBuf::from("file.txt"),
+        vec![HunkSelection {
+            hunk_id: hunk.id,
+            lines: [3..=4].into_iter().collect(),
+        }],
+    )]);
+    controller
+        .create_commit_with_selections(
+            project,
+            branch_id,
+            "partial",
+            None,
+            &selections,
+            false,
+            false,
+        )
+        .unwrap();
+
+    let branch = get_virtual_branch(controller, project, branch_id);
+    let committed = branch.commits[0].files[0].hunks[0].diff.to_string();
+    assert!(committed.contains("+changed 2"));
+    assert!(!committed.contains("changed 4"));
+
+    assert_eq!(
+        branch.files.len(),
+        1,
+        "the rest of the hunk remains uncommitted"
+    );
+    let uncommitted = branch.files[0].hunks[0].diff.to_string();
+    assert!(uncommitted.contains("+changed 4"));
+    assert!(!uncommitted.contains("changed 2"));
+}
+
+#[test]
+fn selected_lines_of_new_files_are_rejected() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    commit_and_push_initial(repository);
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    repository.gen_file("new.txt", 3);
+
+    let hunk = get_virtual_branch(controller, project, branch_id).files[0].hunks[0].clone();
+    let selections = BTreeMap::from([(
+        PathBuf::from("new.txt"),
+        vec![HunkSelection {
+            hunk_id: hunk.id,
+            lines: [2..=2].into_iter().collect(),
+        }],
+    )]);
+    let err = controller
+        .create_commit_with_selections(
+            project,
+            branch_id,
+            "partial",
+            None,
+            &selections,
+            false,
+            false,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+    assert_eq!(
+        err.root_cause().to_string(),
+        "cannot commit selected lines of new file new.txt"
+    );
+
+    let branch = get_virtual_branch(controller, project, branch_id);
+    assert!(branch.commits.is_empty());
+    assert_eq!(branch.files.len(), 1, "the new file remains uncommitted");
+}
+
+#[test]
+fn files_changed_since_the_status_are_not_committed() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    commit_and_push_initial(repository);
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let _cache = cache_workdir_diff(project.id);
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "half-saved").unwrap();
+    assert_eq!(
+        get_virtual_branch(controller, project, branch_id)
+            .files
+            .len(),
+        1
+    );
+
+    // The cached status still shows the file as it was, as if the change wasn't seen yet.
+    fs::write(repository.path().join("file.txt"), "saved\n").unwrap();
+    let err = controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::FilesChanged)
+    );
+    assert_eq!(
+        err.downcast_ref::<FilesChangedDuringCommit>()
+            .map(|changed| changed.paths.clone()),
+        Some(vec![PathBuf::from("file.txt")])
+    );
+    assert!(get_virtual_branch(controller, project, branch_id)
+        .commits
+        .is_empty());
+
+    invalidate_workdir_cache(project.id, [PathBuf::from("file.txt")]);
+    controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+    let branch = get_virtual_branch(controller, project, branch_id);
+    assert_eq!(branch.commits.len(), 1);
+    assert!(branch.files.is_empty());
+}
+
 fn commit_and_push_initial(repository: &TestProject) {
     repository.commit_all("initial commit");
     repository.push();
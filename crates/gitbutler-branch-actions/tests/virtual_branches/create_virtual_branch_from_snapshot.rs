@@ -0,0 +1,91 @@
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_oplog::OplogExt;
+
+use super::*;
+
+#[test]
+fn selected_files_are_owned_by_the_new_branch() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        controller,
+        project,
+        ..
+    } = &Test::default();
+
+    controller.set_base_branch(project, &"refs/remotes/origin/master".parse()?)?;
+    let branch_id = controller.create_virtual_branch(project, &BranchCreateRequest::default())?;
+    fs::write(repository.path().join("file.txt"), "wip")?;
+    fs::write(repository.path().join("other.txt"), "wip")?;
+    controller.list_virtual_branches(project)?;
+    controller.create_virtual_branch(project, &BranchCreateRequest::default())?;
+    let snapshot = project.list_snapshots(1, None)?[0].commit_id;
+
+    fs::remove_file(repository.path().join("file.txt"))?;
+    fs::write(repository.path().join("other.txt"), "more wip")?;
+    controller.list_virtual_branches(project)?;
+
+    let new_branch_id = controller.create_virtual_branch_from_snapshot(
+        project,
+        snapshot,
+        &[PathBuf::from("file.txt")],
+        Some("resurrected".into()),
+    )?;
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt"))?,
+        "wip"
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("other.txt"))?,
+        "more wip",
+        "other files are left as they are"
+    );
+
+    let (branches, _) = controller.list_virtual_branches(project)?;
+    let new_branch = branches
+        .iter()
+        .find(|branch| branch.id == new_branch_id)
+        .unwrap();
+    assert_eq!(new_branch.name, "resurrected");
+    assert_eq!(new_branch.files.len(), 1);
+    assert_eq!(new_branch.files[0].path, PathBuf::from("file.txt"));
+    let branch = branches
+        .iter()
+        .find(|branch| branch.id == branch_id)
+        .unwrap();
+    assert_eq!(branch.files.len(), 1);
+    assert_eq!(branch.files[0].path, PathBuf::from("other.txt"));
+    Ok(())
+}
+
+#[test]
+fn uncommitted_changes_are_not_overwritten() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        controller,
+        project,
+        ..
+    } = &Test::default();
+
+    controller.set_base_branch(project, &"refs/remotes/origin/master".parse()?)?;
+    controller.create_virtual_branch(project, &BranchCreateRequest::default())?;
+    fs::write(repository.path().join("file.txt"), "wip")?;
+    controller.list_virtual_branches(project)?;
+    controller.create_virtual_branch(project, &BranchCreateRequest::default())?;
+    let snapshot = project.list_snapshots(1, None)?[0].commit_id;
+
+    fs::write(repository.path().join("file.txt"), "other wip")?;
+    controller.list_virtual_branches(project)?;
+
+    let err = controller
+        .create_virtual_branch_from_snapshot(project, snapshot, &[PathBuf::from("file.txt")], None)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt"))?,
+        "other wip"
+    );
+    Ok(())
+}
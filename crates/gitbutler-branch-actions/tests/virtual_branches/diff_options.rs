@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use gitbutler_diff::DiffOptions;
+
+use super::*;
+
+#[test]
+fn ignored_changes_are_hidden_but_still_owned() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("file.txt"), "fn main() {}\n").unwrap();
+    repository.commit_all("initial");
+    repository.push();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "fn main()  {}\n\n").unwrap();
+    fs::write(repository.path().join("other.txt"), "content\n").unwrap();
+
+    let options = DiffOptions {
+        ignore_whitespace: true,
+        ignore_blank_lines: true,
+        ..Default::default()
+    };
+    let (branches, _) = controller
+        .list_virtual_branches_with_options(project, &options)
+        .unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(
+        branches[0]
+            .files
+            .iter()
+            .map(|file| file.path.as_path())
+            .collect::<Vec<_>>(),
+        [Path::new("other.txt")]
+    );
+    assert!(
+        branches[0]
+            .ownership
+            .claims
+            .iter()
+            .any(|claim| claim.file_path == Path::new("file.txt")),
+        "the hidden changes still belong to the branch"
+    );
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].files.len(), 2);
+}
@@ -0,0 +1,167 @@
+use gitbutler_diff::{Hunk, RangeSet};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_oplog::{entry::OperationKind, OplogExt};
+
+use super::*;
+
+fn lines(range: std::ops::RangeInclusive<u32>) -> String {
+    range.map(|n| format!("line {n}\n")).collect()
+}
+
+/// Set the base branch to a commit with `file.txt`, which has 20 lines.
+fn set_base(
+    Test {
+        repository,
+        project,
+        controller,
+        ..
+    }: &Test,
+) {
+    fs::write(repository.path().join("file.txt"), lines(1..=20)).unwrap();
+    repository.commit_all("twenty lines");
+    repository.push();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+}
+
+/// The uncommitted hunks of `file.txt`, with their hash.
+fn hunks(test: &Test) -> Vec<Hunk> {
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    branches[0]
+        .files
+        .iter()
+        .filter(|file| file.path == path::Path::new("file.txt"))
+        .flat_map(|file| &file.hunks)
+        .map(|hunk| Hunk::new(hunk.start, hunk.end, Some(hunk.hash)).unwrap())
+        .collect()
+}
+
+fn id(hunk: &Hunk) -> String {
+    format!("{}-{}", hunk.start, hunk.end)
+}
+
+#[test]
+fn only_the_discarded_hunk_is_reverted() {
+    let test = Test::default();
+    set_base(&test);
+    let content = lines(1..=20)
+        .replace("line 2\n", "changed 2\n")
+        .replace("line 18\n", "changed 18\n");
+    fs::write(test.repository.path().join("file.txt"), &content).unwrap();
+
+    let hunk = hunks(&test)
+        .into_iter()
+        .find(|hunk| hunk.start > 10)
+        .unwrap();
+    test.controller
+        .discard_hunk(&test.project, path::Path::new("file.txt"), &id(&hunk))
+        .unwrap();
+
+    assert_eq!(
+        fs::read_to_string(test.repository.path().join("file.txt")).unwrap(),
+        lines(1..=20).replace("line 2\n", "changed 2\n")
+    );
+    assert_eq!(hunks(&test).len(), 1);
+
+    let snapshots = test.project.list_snapshots(1, None).unwrap();
+    assert_eq!(
+        snapshots[0].details.as_ref().unwrap().operation,
+        OperationKind::DiscardHunk,
+        "a snapshot is taken before discarding"
+    );
+}
+
+#[test]
+fn hunks_are_found_by_hash_after_lines_were_added_before_them() {
+    let test = Test::default();
+    set_base(&test);
+    let path = test.repository.path().join("file.txt");
+    fs::write(&path, lines(1..=20).replace("line 18\n", "changed 18\n")).unwrap();
+    let hunk = hunks(&test).remove(0);
+    let hunk_id = hunk.to_string();
+
+    fs::write(
+        &path,
+        format!("new 1\nnew 2\n{}", fs::read_to_string(&path).unwrap()),
+    )
+    .unwrap();
+    test.controller
+        .discard_hunk(&test.project, path::Path::new("file.txt"), &hunk_id)
+        .unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        format!("new 1\nnew 2\n{}", lines(1..=20))
+    );
+}
+
+#[test]
+fn only_selected_lines_are_discarded() {
+    let test = Test::default();
+    set_base(&test);
+    let path = test.repository.path().join("file.txt");
+    fs::write(
+        &path,
+        lines(1..=20).replace("line 10\n", "line 10\nadded a\nadded b\n"),
+    )
+    .unwrap();
+    let hunk = hunks(&test).remove(0);
+    // The hunk starts with three lines of context, so the additions are its lines 4 and 5.
+    let selected: RangeSet = [5..=5].into_iter().collect();
+
+    test.controller
+        .discard_lines(
+            &test.project,
+            path::Path::new("file.txt"),
+            &id(&hunk),
+            &selected,
+        )
+        .unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        lines(1..=20).replace("line 10\n", "line 10\nadded a\n")
+    );
+}
+
+#[test]
+fn discarding_all_of_a_new_file_removes_it() {
+    let test = Test::default();
+    set_base(&test);
+    let path = test.repository.path().join("new.txt");
+    fs::write(&path, "new\n").unwrap();
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    let hunk_id = branches[0].files[0].hunks[0].id.clone();
+
+    test.controller
+        .discard_hunk(&test.project, path::Path::new("new.txt"), &hunk_id)
+        .unwrap();
+
+    assert!(!path.exists());
+}
+
+#[test]
+fn unknown_hunks_are_rejected() {
+    let test = Test::default();
+    set_base(&test);
+    let path = test.repository.path().join("file.txt");
+    let content = lines(1..=20).replace("line 2\n", "changed 2\n");
+    fs::write(&path, &content).unwrap();
+
+    let err = test
+        .controller
+        .discard_hunk(&test.project, path::Path::new("file.txt"), "40-44")
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), content);
+}
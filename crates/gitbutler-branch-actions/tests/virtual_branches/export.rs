@@ -0,0 +1,138 @@
+use gitbutler_branch_actions::ExportUncommitted;
+
+use super::*;
+
+#[test]
+fn applied_branches_become_local_branches() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+    fs::write(repository.path().join("wip.txt"), "wip").unwrap();
+
+    let outcome = controller
+        .export_to_git(project, ExportUncommitted::Stash)
+        .unwrap();
+    assert_eq!(outcome.branches.len(), 1);
+    let exported = &outcome.branches[0];
+    assert_eq!(exported.branch_id, branch_id);
+    assert_eq!(exported.head, commit_id, "the stash keeps the branch as is");
+    assert_eq!(outcome.checked_out, exported.refname);
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    assert_eq!(repo.head().unwrap().name(), Some(exported.refname.as_str()));
+    assert_eq!(repo.head().unwrap().target(), Some(commit_id));
+    assert!(repo
+        .find_reference("refs/heads/gitbutler/integration")
+        .is_err());
+    assert!(repository.path().join("file.txt").exists());
+
+    let stash = repo
+        .find_reference("refs/stash")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(Some(stash.id()), exported.uncommitted);
+    assert_eq!(stash.parent_id(0).unwrap(), commit_id);
+    assert!(stash
+        .tree()
+        .unwrap()
+        .get_path(path::Path::new("wip.txt"))
+        .is_ok());
+}
+
+#[test]
+fn uncommitted_changes_can_become_a_commit() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("wip.txt"), "wip").unwrap();
+
+    let outcome = controller
+        .export_to_git(project, ExportUncommitted::Commit)
+        .unwrap();
+    let exported = &outcome.branches[0];
+    assert_eq!(exported.branch_id, branch_id);
+    assert_eq!(exported.uncommitted, Some(exported.head));
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.id(), exported.head);
+    assert_eq!(head.message(), Some("GitButler WIP Commit"));
+    assert!(repo.find_reference("refs/stash").is_err());
+    assert_eq!(
+        fs::read_to_string(repository.path().join("wip.txt")).unwrap(),
+        "wip"
+    );
+}
+
+#[test]
+fn ejecting_leaves_plain_git_behind() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+    fs::write(repository.path().join("wip.txt"), "wip").unwrap();
+
+    let outcome = controller.eject(project, Some(branch_id)).unwrap();
+    assert_eq!(outcome.branches.len(), 1);
+    let exported = &outcome.branches[0];
+    assert_eq!(exported.head, commit_id);
+    assert_eq!(exported.uncommitted, None);
+    assert_eq!(outcome.checked_out, exported.refname);
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    assert_eq!(repo.head().unwrap().name(), Some(exported.refname.as_str()));
+    assert_eq!(repo.head().unwrap().target(), Some(commit_id));
+    assert_eq!(
+        fs::read_to_string(repository.path().join("wip.txt")).unwrap(),
+        "wip",
+        "uncommitted changes are in the worktree"
+    );
+    assert_eq!(
+        repo.status_file(path::Path::new("wip.txt")).unwrap(),
+        git2::Status::WT_NEW
+    );
+
+    assert!(repo
+        .find_reference("refs/heads/gitbutler/integration")
+        .is_err());
+    assert_eq!(repo.references_glob("refs/gitbutler/*").unwrap().count(), 0);
+    assert!(!project.gb_dir().exists());
+}
@@ -0,0 +1,117 @@
+use gitbutler_repo::{
+    progress::{self, TaskState},
+    UpdatedRef,
+};
+
+use super::*;
+
+#[test]
+fn reports_each_remote() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let mut tracking_ref = repo.find_reference("refs/remotes/origin/master").unwrap();
+    let master = tracking_ref.target().unwrap();
+    tracking_ref.delete().unwrap();
+    let missing = tempfile::tempdir().unwrap();
+    repo.remote("missing", missing.path().join("repo.git").to_str().unwrap())
+        .unwrap();
+
+    let report = controller.fetch_from_remotes(project, None).unwrap();
+    assert_eq!(report.remotes.len(), 2);
+    assert!(!report.is_success());
+
+    let origin = report
+        .remotes
+        .iter()
+        .find(|fetch| fetch.remote == "origin")
+        .unwrap();
+    assert!(origin.is_success());
+    assert_eq!(
+        origin.updated_refs,
+        [UpdatedRef {
+            name: "refs/remotes/origin/master".into(),
+            old: None,
+            new: Some(master),
+        }],
+        "the remote branch was created again"
+    );
+
+    let missing = report
+        .remotes
+        .iter()
+        .find(|fetch| fetch.remote == "missing")
+        .unwrap();
+    assert!(missing.error.is_some());
+    assert!(missing.failure.is_some());
+    assert!(missing.updated_refs.is_empty());
+}
+
+#[test]
+fn nothing_changes_without_new_commits() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let report = controller.fetch_from_remotes(project, None).unwrap();
+    assert!(report.is_success());
+    assert_eq!(report.remotes.len(), 1);
+    assert!(report.remotes[0].updated_refs.is_empty());
+}
+
+#[test]
+fn reports_progress_of_each_remote() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let (progress, updates) = progress::channel();
+    let report = controller
+        .fetch_from_remotes_with_progress(project, None, &progress)
+        .unwrap();
+    assert!(report.is_success());
+    drop(progress);
+
+    let updates: Vec<_> = updates.into_iter().collect();
+    let root = updates.last().unwrap();
+    assert_eq!(root.name, "Fetching remotes");
+    assert_eq!(root.state, TaskState::Finished);
+    assert_eq!((root.done, root.total), (1, Some(1)));
+    let origin = updates
+        .iter()
+        .filter(|update| update.parent_id == Some(root.task_id))
+        .last()
+        .unwrap();
+    assert_eq!(origin.name, "Fetching origin");
+    assert_eq!(origin.state, TaskState::Finished);
+}
+
+#[test]
+fn cancelled_fetches_fail() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let (progress, _updates) = progress::channel();
+    progress.cancel();
+    let report = controller
+        .fetch_from_remotes_with_progress(project, None, &progress)
+        .unwrap();
+    assert!(!report.is_success());
+    assert!(report.remotes[0]
+        .error
+        .as_deref()
+        .unwrap()
+        .contains("cancelled"));
+}
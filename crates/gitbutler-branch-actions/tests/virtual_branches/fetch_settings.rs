@@ -0,0 +1,144 @@
+use gitbutler_project::{FetchRefspecs, FetchSettings, FetchTags, Settings};
+
+use super::*;
+
+fn set_fetch(test: &Test, fetch: FetchSettings) -> Project {
+    let settings = Settings {
+        fetch,
+        ..test.project.settings.clone()
+    };
+    test.projects
+        .update_settings(test.project.id, settings)
+        .unwrap();
+    test.projects.get(test.project.id).unwrap()
+}
+
+/// Return the local repository and the remote repository of `test`.
+fn repositories(test: &Test) -> (git2::Repository, git2::Repository) {
+    let local = git2::Repository::open(test.repository.path()).unwrap();
+    let remote_url = local
+        .find_remote("origin")
+        .unwrap()
+        .url()
+        .unwrap()
+        .to_owned();
+    let remote = git2::Repository::open(remote_url).unwrap();
+    (local, remote)
+}
+
+fn has_ref(repo: &git2::Repository, name: &str) -> bool {
+    repo.find_reference(name).is_ok()
+}
+
+#[test]
+fn only_the_target_branch_is_fetched_if_asked_to() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let (local, remote) = repositories(&test);
+    let master = remote.refname_to_id("refs/heads/master").unwrap();
+    remote
+        .reference("refs/heads/feature", master, false, "")
+        .unwrap();
+
+    let project = set_fetch(
+        &test,
+        FetchSettings {
+            refspecs: FetchRefspecs::TargetBranch,
+            ..Default::default()
+        },
+    );
+    assert!(test
+        .controller
+        .fetch_from_remotes(&project, None)
+        .unwrap()
+        .is_success());
+    assert!(has_ref(&local, "refs/remotes/origin/master"));
+    assert!(!has_ref(&local, "refs/remotes/origin/feature"));
+
+    let project = set_fetch(&test, FetchSettings::default());
+    test.controller.fetch_from_remotes(&project, None).unwrap();
+    assert!(has_ref(&local, "refs/remotes/origin/feature"));
+}
+
+#[test]
+fn custom_refspecs_name_the_remote_with_a_placeholder() {
+    let test = Test::default();
+    let (local, remote) = repositories(&test);
+    let master = remote.refname_to_id("refs/heads/master").unwrap();
+    remote
+        .reference("refs/heads/feature", master, false, "")
+        .unwrap();
+
+    let project = set_fetch(
+        &test,
+        FetchSettings {
+            refspecs: FetchRefspecs::Custom {
+                refspecs: vec!["+refs/heads/feature:refs/remotes/{remote}/mine".into()],
+            },
+            ..Default::default()
+        },
+    );
+    let report = test.controller.fetch_from_remotes(&project, None).unwrap();
+    assert!(report.is_success(), "{report:?}");
+    assert_eq!(
+        local.refname_to_id("refs/remotes/origin/mine").unwrap(),
+        master
+    );
+}
+
+#[test]
+fn deleted_remote_branches_are_kept_without_pruning() {
+    let test = Test::default();
+    let (local, _remote) = repositories(&test);
+    let master = local.refname_to_id("refs/remotes/origin/master").unwrap();
+    local
+        .reference("refs/remotes/origin/gone", master, false, "")
+        .unwrap();
+
+    let project = set_fetch(
+        &test,
+        FetchSettings {
+            prune: false,
+            ..Default::default()
+        },
+    );
+    test.controller.fetch_from_remotes(&project, None).unwrap();
+    assert!(has_ref(&local, "refs/remotes/origin/gone"));
+
+    let project = set_fetch(&test, FetchSettings::default());
+    test.controller.fetch_from_remotes(&project, None).unwrap();
+    assert!(!has_ref(&local, "refs/remotes/origin/gone"));
+}
+
+#[test]
+fn tags_are_not_fetched_if_asked_to() {
+    let test = Test::default();
+    let (local, remote) = repositories(&test);
+    let master = remote.refname_to_id("refs/heads/master").unwrap();
+    remote.reference("refs/tags/v1", master, false, "").unwrap();
+
+    let project = set_fetch(
+        &test,
+        FetchSettings {
+            tags: FetchTags::None,
+            ..Default::default()
+        },
+    );
+    test.controller.fetch_from_remotes(&project, None).unwrap();
+    assert!(!has_ref(&local, "refs/tags/v1"));
+
+    let project = set_fetch(
+        &test,
+        FetchSettings {
+            tags: FetchTags::All,
+            ..Default::default()
+        },
+    );
+    test.controller.fetch_from_remotes(&project, None).unwrap();
+    assert!(has_ref(&local, "refs/tags/v1"));
+}
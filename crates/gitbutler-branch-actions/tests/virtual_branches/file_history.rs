@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+#[test]
+fn history_follows_renames_within_a_branch() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let content: String = (1..=10).map(|line| format!("line {line}\n")).collect();
+    fs::write(test.repository.path().join("old.txt"), &content).unwrap();
+    let added = test
+        .controller
+        .create_commit(&test.project, branch_id, "add", None, false)
+        .unwrap();
+    fs::rename(
+        test.repository.path().join("old.txt"),
+        test.repository.path().join("new.txt"),
+    )
+    .unwrap();
+    let renamed = test
+        .controller
+        .create_commit(&test.project, branch_id, "rename", None, false)
+        .unwrap();
+    fs::write(test.repository.path().join("other.txt"), "other\n").unwrap();
+    test.controller
+        .create_commit(&test.project, branch_id, "unrelated", None, false)
+        .unwrap();
+    fs::write(
+        test.repository.path().join("new.txt"),
+        format!("{content}line 11\n"),
+    )
+    .unwrap();
+    let changed = test
+        .controller
+        .create_commit(&test.project, branch_id, "change", None, false)
+        .unwrap();
+
+    let history = test
+        .controller
+        .file_history(&test.project, "new.txt".as_ref(), 10)
+        .unwrap();
+    let commits: Vec<_> = history
+        .iter()
+        .map(|entry| {
+            (
+                entry.commit.id.clone(),
+                entry.path.to_str().unwrap(),
+                entry.previous_path.as_deref().and_then(Path::to_str),
+            )
+        })
+        .collect();
+    assert_eq!(
+        commits,
+        [
+            (changed.to_string(), "new.txt", None),
+            (renamed.to_string(), "new.txt", Some("old.txt")),
+            (added.to_string(), "old.txt", None),
+        ]
+    );
+    assert!(history
+        .iter()
+        .all(|entry| !entry.on_target && entry.branch_ids == [branch_id]));
+
+    let history = test
+        .controller
+        .file_history(&test.project, "new.txt".as_ref(), 1)
+        .unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].commit.id, changed.to_string());
+}
+
+#[test]
+fn paths_outside_of_the_worktree_are_rejected() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let err = test
+        .controller
+        .file_history(&test.project, "".as_ref(), 10)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+}
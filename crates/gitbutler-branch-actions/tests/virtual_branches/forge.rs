@@ -0,0 +1,925 @@
+use gitbutler_branch::{BranchId, BranchUpdateRequest};
+use gitbutler_branch_actions::{
+    AutoMerge, ChecksSummary, CiState, ForgeRepo, MergeMethod, NewPullRequest, PullRequest,
+    PullRequestState,
+};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_project::ForgeKind;
+use gitbutler_testsupport::forge_server::ForgeServer;
+use serde_json::json;
+
+use super::*;
+
+const PULLS: &str = "/api/v3/repos/owner/repo/pulls";
+
+/// Push a branch named `feature` with a commit, and let the remote of the target be a repository
+/// on the stub forge `server` of the kind `forge`.
+fn pushed_branch(test: &Test, server: &ForgeServer, forge: ForgeKind) -> (Project, BranchId) {
+    gitbutler_testsupport::secrets::setup_in_memory_store();
+    let Test {
+        repository,
+        project_id,
+        projects,
+        controller,
+        ..
+    } = test;
+    let project = projects
+        .update(&projects::UpdateRequest {
+            id: *project_id,
+            forge: Some(forge),
+            ..Default::default()
+        })
+        .unwrap();
+    controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(
+            &project,
+            &BranchCreateRequest {
+                name: Some("feature".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    controller
+        .create_commit(&project, branch_id, "commit", None, false)
+        .unwrap();
+    controller
+        .push_virtual_branch(&project, branch_id, false, None)
+        .unwrap();
+    controller
+        .set_remote_url(&project, "origin", &server.repo_url("owner", "repo"))
+        .unwrap();
+    (project, branch_id)
+}
+
+fn api_pull_request(state: &str, merged: bool) -> serde_json::Value {
+    json!({
+        "number": 7,
+        "html_url": "https://github.com/owner/repo/pull/7",
+        "title": "Add a feature",
+        "state": state,
+        "draft": false,
+        "merged": merged,
+        "mergeable": true,
+        "head": { "ref": "feature", "sha": "abc" },
+        "base": { "ref": "master", "sha": "def" },
+    })
+}
+
+fn respond_with_checks(server: &ForgeServer) {
+    server.respond(
+        "GET",
+        "/api/v3/repos/owner/repo/commits/abc/check-runs",
+        200,
+        json!({
+            "total_count": 3,
+            "check_runs": [
+                { "status": "completed", "conclusion": "success" },
+                { "status": "completed", "conclusion": "failure" },
+                { "status": "in_progress", "conclusion": null },
+            ],
+        }),
+    );
+}
+
+#[test]
+fn repo_from_remote_url() {
+    for url in [
+        "https://github.com/owner/repo.git",
+        "https://github.com/owner/repo",
+        "git@github.com:owner/repo.git",
+        "ssh://git@github.com/owner/repo.git",
+    ] {
+        assert_eq!(
+            ForgeRepo::from_remote_url(url).unwrap(),
+            ForgeRepo {
+                host: "github.com".into(),
+                owner: "owner".into(),
+                name: "repo".into(),
+                insecure: false,
+            },
+            "{url}"
+        );
+    }
+
+    let repo = ForgeRepo::from_remote_url("http://localhost:8080/owner/repo.git").unwrap();
+    assert_eq!(repo.host, "localhost:8080");
+    assert_eq!(repo.web_url(), "http://localhost:8080");
+
+    assert!(ForgeRepo::from_remote_url("/path/to/repo.git").is_err());
+}
+
+#[test]
+fn create_pull_request_for_pushed_branch() {
+    let test = &Test::default();
+    let controller = &test.controller;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, branch_id) = pushed_branch(test, &server, ForgeKind::Detect);
+    server.respond("POST", PULLS, 201, api_pull_request("open", false));
+    respond_with_checks(&server);
+
+    let pull_request = controller
+        .create_pull_request(
+            project,
+            branch_id,
+            &NewPullRequest {
+                title: "Add a feature".into(),
+                body: "description".into(),
+                draft: true,
+            },
+            Some("token"),
+        )
+        .unwrap();
+    assert_eq!(pull_request.number, 7);
+    assert_eq!(pull_request.state, PullRequestState::Open);
+    assert_eq!(pull_request.mergeable, Some(true));
+    assert_eq!(
+        pull_request.checks,
+        Some(ChecksSummary {
+            total: 3,
+            passed: 1,
+            failed: 1,
+            pending: 1,
+        })
+    );
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, PULLS);
+    assert_eq!(requests[0].authorization.as_deref(), Some("Bearer token"));
+    assert_eq!(
+        requests[0].body,
+        json!({
+            "title": "Add a feature",
+            "body": "description",
+            "head": "feature",
+            "base": "master",
+            "draft": true,
+        })
+    );
+
+    let ctx = CommandContext::open(project).unwrap();
+    let listing =
+        gitbutler_branch_actions::list_branches(&ctx, None, Some(vec!["feature".into()])).unwrap();
+    assert_eq!(
+        listing[0].pull_request.as_ref().map(|pr| pr.number),
+        Some(7),
+        "the pull request shows in the branch listing"
+    );
+}
+
+#[test]
+fn create_pull_request_with_draft_description_and_reviewers() {
+    let test = &Test::default();
+    let controller = &test.controller;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, branch_id) = pushed_branch(test, &server, ForgeKind::Detect);
+    controller
+        .update_virtual_branch(
+            project,
+            BranchUpdateRequest {
+                id: branch_id,
+                description: Some("## Why\n\nBecause.".into()),
+                reviewers: Some(vec!["@alice".into(), "bob".into(), "alice".into()]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let branch = controller
+        .list_virtual_branches(project)
+        .unwrap()
+        .0
+        .into_iter()
+        .find(|branch| branch.id == branch_id)
+        .unwrap();
+    assert_eq!(branch.description, "## Why\n\nBecause.");
+    assert_eq!(
+        branch.reviewers,
+        ["alice", "bob"],
+        "reviewers are normalized"
+    );
+
+    server.respond("POST", PULLS, 201, api_pull_request("open", false));
+    server.respond(
+        "POST",
+        &format!("{PULLS}/7/requested_reviewers"),
+        201,
+        json!({}),
+    );
+    respond_with_checks(&server);
+    controller
+        .create_pull_request(
+            project,
+            branch_id,
+            &NewPullRequest {
+                title: "Add a feature".into(),
+                ..Default::default()
+            },
+            Some("token"),
+        )
+        .unwrap();
+
+    let requests = server.requests();
+    assert_eq!(
+        requests[0].body["body"], "## Why\n\nBecause.",
+        "the draft description is used without a body"
+    );
+    let review_request = requests
+        .iter()
+        .find(|request| request.path == format!("{PULLS}/7/requested_reviewers"))
+        .expect("reviews are requested");
+    assert_eq!(
+        review_request.body,
+        json!({ "reviewers": ["alice", "bob"] })
+    );
+}
+
+#[test]
+fn pull_request_description_falls_back_to_template() {
+    let test = &Test::default();
+    let Test {
+        repository,
+        controller,
+        ..
+    } = test;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, branch_id) = pushed_branch(test, &server, ForgeKind::Detect);
+    assert_eq!(
+        controller
+            .pull_request_description(project, branch_id)
+            .unwrap(),
+        "",
+        "there is neither a description nor a template"
+    );
+
+    fs::create_dir_all(repository.path().join(".github")).unwrap();
+    fs::write(
+        repository.path().join(".github/pull_request_template.md"),
+        "## Summary\n",
+    )
+    .unwrap();
+    assert_eq!(
+        controller
+            .pull_request_description(project, branch_id)
+            .unwrap(),
+        "## Summary\n"
+    );
+
+    let update = |description: &str| {
+        controller
+            .update_virtual_branch(
+                project,
+                BranchUpdateRequest {
+                    id: branch_id,
+                    description: Some(description.into()),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+    };
+    update("Details");
+    assert_eq!(
+        controller
+            .pull_request_description(project, branch_id)
+            .unwrap(),
+        "Details"
+    );
+    update("  ");
+    assert_eq!(
+        controller
+            .pull_request_description(project, branch_id)
+            .unwrap(),
+        "## Summary\n",
+        "a blank description falls back to the template"
+    );
+}
+
+#[test]
+fn refresh_pull_request_picks_up_merge() {
+    let test = &Test::default();
+    let controller = &test.controller;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, branch_id) = pushed_branch(test, &server, ForgeKind::Detect);
+    respond_with_checks(&server);
+    server.respond("GET", PULLS, 200, json!([]));
+
+    assert_eq!(
+        controller
+            .refresh_pull_request(project, branch_id, Some("token"))
+            .unwrap(),
+        None,
+        "there is no pull request for the branch yet"
+    );
+
+    server.respond("GET", PULLS, 200, json!([api_pull_request("open", false)]));
+    server.respond(
+        "GET",
+        &format!("{PULLS}/7"),
+        200,
+        api_pull_request("open", false),
+    );
+    let pull_request = controller
+        .refresh_pull_request(project, branch_id, Some("token"))
+        .unwrap()
+        .expect("a pull request that was opened elsewhere is found");
+    assert_eq!(pull_request.state, PullRequestState::Open);
+    assert!(server
+        .requests()
+        .iter()
+        .any(|request| request.path == PULLS && request.query.contains("head=owner%3Afeature")));
+
+    server.respond(
+        "GET",
+        &format!("{PULLS}/7"),
+        200,
+        api_pull_request("closed", true),
+    );
+    let pull_request: PullRequest = controller
+        .refresh_pull_request(project, branch_id, Some("token"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(pull_request.state, PullRequestState::Merged);
+}
+
+#[test]
+fn forge_errors_are_classified() {
+    let test = &Test::default();
+    let controller = &test.controller;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, branch_id) = pushed_branch(test, &server, ForgeKind::Detect);
+    let pull_request = NewPullRequest {
+        title: "Add a feature".into(),
+        ..Default::default()
+    };
+
+    let err = controller
+        .create_pull_request(project, branch_id, &pull_request, None)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Forge),
+        "the user isn't logged in to GitHub"
+    );
+
+    server.respond(
+        "POST",
+        PULLS,
+        422,
+        json!({ "message": "A pull request already exists for owner:feature." }),
+    );
+    let err = controller
+        .create_pull_request(project, branch_id, &pull_request, Some("token"))
+        .unwrap_err();
+    assert_eq!(err.custom_context().map(|ctx| ctx.code), Some(Code::Forge));
+    assert!(format!("{err:#}").contains("A pull request already exists"));
+}
+
+#[test]
+fn forge_is_detected_from_remote_unless_overridden() {
+    let Test {
+        project,
+        projects,
+        project_id,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    for (url, kind) in [
+        ("https://github.com/owner/repo.git", ForgeKind::GitHub),
+        ("git@gitlab.com:group/sub/repo.git", ForgeKind::GitLab),
+        (
+            "https://gitlab.example.com/owner/repo.git",
+            ForgeKind::GitLab,
+        ),
+        ("https://codeberg.org/owner/repo.git", ForgeKind::Gitea),
+        (
+            "https://forgejo.example.com/owner/repo.git",
+            ForgeKind::Gitea,
+        ),
+        ("https://git.example.com/owner/repo.git", ForgeKind::GitHub),
+    ] {
+        controller.set_remote_url(project, "origin", url).unwrap();
+        assert_eq!(controller.forge_kind(project).unwrap(), kind, "{url}");
+    }
+
+    let project = projects
+        .update(&projects::UpdateRequest {
+            id: *project_id,
+            forge: Some(ForgeKind::Gitea),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(controller.forge_kind(&project).unwrap(), ForgeKind::Gitea);
+}
+
+#[test]
+fn create_merge_request_on_gitlab() {
+    const PROJECT: &str = "/api/v4/projects/owner%2Frepo";
+    let test = &Test::default();
+    let controller = &test.controller;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, branch_id) = pushed_branch(test, &server, ForgeKind::GitLab);
+    let merge_request = |state: &str| {
+        json!({
+            "iid": 3,
+            "web_url": "https://gitlab.com/owner/repo/-/merge_requests/3",
+            "title": "Draft: Add a feature",
+            "state": state,
+            "draft": true,
+            "has_conflicts": false,
+            "merge_status": "can_be_merged",
+            "source_branch": "feature",
+            "target_branch": "master",
+            "head_pipeline": { "id": 11, "project_id": 42 },
+        })
+    };
+    server.respond(
+        "POST",
+        &format!("{PROJECT}/merge_requests"),
+        201,
+        merge_request("opened"),
+    );
+    server.respond(
+        "GET",
+        "/api/v4/projects/42/pipelines/11/jobs",
+        200,
+        json!([
+            { "status": "success" },
+            { "status": "running" },
+            { "status": "manual" },
+        ]),
+    );
+
+    let err = controller
+        .create_pull_request(project, branch_id, &NewPullRequest::default(), None)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Forge),
+        "there is no access token for the host yet"
+    );
+
+    controller
+        .set_forge_access_token(&server.host(), "token")
+        .unwrap();
+    let pull_request = controller
+        .create_pull_request(
+            project,
+            branch_id,
+            &NewPullRequest {
+                title: "Add a feature".into(),
+                body: "description".into(),
+                draft: true,
+            },
+            None,
+        )
+        .unwrap();
+    assert_eq!(pull_request.number, 3);
+    assert!(pull_request.draft);
+    assert_eq!(pull_request.mergeable, Some(true));
+    assert_eq!(
+        pull_request.checks,
+        Some(ChecksSummary {
+            total: 2,
+            passed: 1,
+            failed: 0,
+            pending: 1,
+        }),
+        "manual jobs don't count"
+    );
+
+    let create = &server.requests()[0];
+    assert_eq!(create.authorization.as_deref(), Some("Bearer token"));
+    assert_eq!(
+        create.body,
+        json!({
+            "source_branch": "feature",
+            "target_branch": "master",
+            "title": "Draft: Add a feature",
+            "description": "description",
+        })
+    );
+
+    server.respond(
+        "GET",
+        &format!("{PROJECT}/merge_requests/3"),
+        200,
+        merge_request("merged"),
+    );
+    let pull_request = controller
+        .refresh_pull_request(project, branch_id, None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(pull_request.state, PullRequestState::Merged);
+}
+
+#[test]
+fn find_pull_request_on_gitea() {
+    const REPO: &str = "/api/v1/repos/owner/repo";
+    let test = &Test::default();
+    let controller = &test.controller;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, branch_id) = pushed_branch(test, &server, ForgeKind::Gitea);
+    controller
+        .set_forge_access_token(&server.host(), "token")
+        .unwrap();
+    let pull_request = |number: u64, branch: &str, owner: &str| {
+        json!({
+            "number": number,
+            "html_url": format!("https://codeberg.org/owner/repo/pulls/{number}"),
+            "title": "Add a feature",
+            "state": "open",
+            "merged": false,
+            "mergeable": true,
+            "head": { "ref": branch, "sha": "abc", "repo": { "full_name": format!("{owner}/repo") } },
+            "base": { "ref": "master", "sha": "def", "repo": { "full_name": "owner/repo" } },
+        })
+    };
+    server.respond(
+        "GET",
+        &format!("{REPO}/pulls"),
+        200,
+        json!([
+            pull_request(4, "other", "owner"),
+            pull_request(5, "feature", "someone"),
+            pull_request(6, "feature", "owner"),
+        ]),
+    );
+    server.respond(
+        "GET",
+        &format!("{REPO}/pulls/6"),
+        200,
+        pull_request(6, "feature", "owner"),
+    );
+    server.respond(
+        "GET",
+        &format!("{REPO}/commits/abc/status"),
+        200,
+        json!({
+            "state": "failure",
+            "statuses": [{ "status": "success" }, { "status": "failure" }],
+        }),
+    );
+
+    let pull_request = controller
+        .refresh_pull_request(project, branch_id, None)
+        .unwrap()
+        .expect("the pull request of the branch in the same repository is found");
+    assert_eq!(pull_request.number, 6);
+    assert_eq!(
+        pull_request.checks,
+        Some(ChecksSummary {
+            total: 2,
+            passed: 1,
+            failed: 1,
+            pending: 0,
+        })
+    );
+    assert!(server
+        .requests()
+        .iter()
+        .all(|request| request.authorization.as_deref() == Some("token token")));
+}
+
+/// Return the commit that the branch `feature` was pushed at.
+fn pushed_head(project: &Project) -> String {
+    git2::Repository::open(&project.path)
+        .unwrap()
+        .refname_to_id("refs/remotes/origin/feature")
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn ci_status_combines_check_runs_and_commit_statuses() {
+    let test = &Test::default();
+    let controller = &test.controller;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, _branch_id) = pushed_branch(test, &server, ForgeKind::Detect);
+    let sha = pushed_head(project);
+    server.respond(
+        "GET",
+        &format!("/api/v3/repos/owner/repo/commits/{sha}/check-runs"),
+        200,
+        json!({
+            "check_runs": [
+                { "name": "test", "status": "completed", "conclusion": "failure" },
+                { "name": "build", "status": "in_progress", "conclusion": null },
+                { "name": "lint", "status": "completed", "conclusion": "success" },
+            ],
+        }),
+    );
+    server.respond(
+        "GET",
+        &format!("/api/v3/repos/owner/repo/commits/{sha}/status"),
+        200,
+        json!({
+            "statuses": [
+                { "context": "ci/deploy", "state": "error" },
+                { "context": "lint", "state": "pending" },
+            ],
+        }),
+    );
+
+    let statuses = controller
+        .refresh_ci_statuses(project, Some("token"))
+        .unwrap();
+    let ci = &statuses["feature"];
+    assert_eq!(ci.state, CiState::Failure);
+    assert_eq!(ci.failing, ["ci/deploy", "test"]);
+    assert_eq!(
+        ci.total, 4,
+        "the check run named `lint` comes first and takes precedence over its status"
+    );
+    assert_eq!(ci.sha, sha);
+
+    let ctx = CommandContext::open(project).unwrap();
+    let listing =
+        gitbutler_branch_actions::list_branches(&ctx, None, Some(vec!["feature".into()])).unwrap();
+    assert_eq!(
+        listing[0].ci_status.as_ref(),
+        Some(ci),
+        "the CI status shows in the branch listing"
+    );
+}
+
+#[test]
+fn ci_status_is_cached_while_fresh() {
+    let test = &Test::default();
+    let controller = &test.controller;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, _branch_id) = pushed_branch(test, &server, ForgeKind::Detect);
+    let sha = pushed_head(project);
+    let check_runs = format!("/api/v3/repos/owner/repo/commits/{sha}/check-runs");
+    server.respond(
+        "GET",
+        &check_runs,
+        200,
+        json!({
+            "check_runs": [{ "name": "test", "status": "completed", "conclusion": "success" }],
+        }),
+    );
+    server.respond(
+        "GET",
+        &format!("/api/v3/repos/owner/repo/commits/{sha}/status"),
+        200,
+        json!({ "statuses": [] }),
+    );
+
+    for _ in 0..2 {
+        let statuses = controller
+            .refresh_ci_statuses(project, Some("token"))
+            .unwrap();
+        assert_eq!(statuses["feature"].state, CiState::Success);
+    }
+    assert_eq!(
+        server
+            .requests()
+            .iter()
+            .filter(|request| request.path == check_runs)
+            .count(),
+        1,
+        "the forge is asked only once"
+    );
+}
+
+#[test]
+fn ci_status_is_not_fetched_while_rate_limited() {
+    let test = &Test::default();
+    let controller = &test.controller;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, _branch_id) = pushed_branch(test, &server, ForgeKind::Detect);
+    let sha = pushed_head(project);
+    let check_runs = format!("/api/v3/repos/owner/repo/commits/{sha}/check-runs");
+    let in_an_hour = (std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 3600)
+        .to_string();
+    server.respond_with_headers(
+        "GET",
+        &check_runs,
+        403,
+        &[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", &in_an_hour),
+        ],
+        json!({ "message": "API rate limit exceeded" }),
+    );
+
+    let statuses = controller
+        .refresh_ci_statuses(project, Some("token"))
+        .unwrap();
+    assert!(
+        statuses.is_empty(),
+        "a rate limit isn't an error, there just is no status yet"
+    );
+
+    server.respond("GET", &check_runs, 200, json!({ "check_runs": [] }));
+    controller
+        .refresh_ci_statuses(project, Some("token"))
+        .unwrap();
+    assert_eq!(
+        server
+            .requests()
+            .iter()
+            .filter(|request| request.path == check_runs)
+            .count(),
+        1,
+        "the forge isn't asked again until the limit is lifted"
+    );
+}
+
+#[test]
+fn auto_merged_pull_request_removes_branch_on_base_update() {
+    let test = &Test::default();
+    let Test {
+        repository,
+        controller,
+        ..
+    } = test;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, branch_id) = pushed_branch(test, &server, ForgeKind::Detect);
+    let mut open = api_pull_request("open", false);
+    open["node_id"] = json!("PR_node");
+    server.respond("GET", PULLS, 200, json!([open.clone()]));
+    server.respond("GET", &format!("{PULLS}/7"), 200, open);
+    respond_with_checks(&server);
+    server.respond(
+        "POST",
+        "/api/graphql",
+        200,
+        json!({ "data": { "enablePullRequestAutoMerge": { "clientMutationId": null } } }),
+    );
+
+    let pull_request = controller
+        .enable_auto_merge(
+            project,
+            branch_id,
+            AutoMerge::WhenChecksPass {
+                method: MergeMethod::Squash,
+            },
+            Some("token"),
+        )
+        .unwrap();
+    assert_eq!(
+        pull_request.auto_merge,
+        Some(AutoMerge::WhenChecksPass {
+            method: MergeMethod::Squash
+        })
+    );
+    let mutation = server
+        .requests()
+        .into_iter()
+        .find(|request| request.path == "/api/graphql")
+        .expect("auto-merge is enabled with GraphQL");
+    assert_eq!(
+        mutation.body["variables"],
+        json!({ "id": "PR_node", "method": "SQUASH" })
+    );
+
+    // The forge squashes the branch into the target, which changes the file again afterwards.
+    let repo = git2::Repository::open(&project.path).unwrap();
+    let signature = git2::Signature::now("Forge", "forge@example.com").unwrap();
+    let target = repo
+        .find_reference("refs/remotes/origin/master")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    let pushed = repo
+        .find_commit(pushed_head(project).parse().unwrap())
+        .unwrap();
+    let squashed = repo
+        .commit(
+            None,
+            &signature,
+            &signature,
+            "Add a feature (#7)",
+            &pushed.tree().unwrap(),
+            &[&target],
+        )
+        .unwrap();
+    let mut tree = repo.treebuilder(Some(&pushed.tree().unwrap())).unwrap();
+    tree.insert(
+        "file.txt",
+        repo.blob(b"changed after the merge").unwrap(),
+        0o100644,
+    )
+    .unwrap();
+    let changed = repo
+        .commit(
+            None,
+            &signature,
+            &signature,
+            "Change the feature",
+            &repo.find_tree(tree.write().unwrap()).unwrap(),
+            &[&repo.find_commit(squashed).unwrap()],
+        )
+        .unwrap();
+    repo.reference("refs/remotes/origin/master", changed, true, "fetch")
+        .unwrap();
+
+    let mut merged = api_pull_request("closed", true);
+    merged["merge_commit_sha"] = json!(squashed.to_string());
+    server.respond("GET", &format!("{PULLS}/7"), 200, merged);
+    let changed_pull_requests = controller
+        .refresh_auto_merges(project, Some("token"))
+        .unwrap();
+    assert_eq!(changed_pull_requests.len(), 1);
+    assert_eq!(changed_pull_requests[0].state, PullRequestState::Merged);
+    assert_eq!(
+        changed_pull_requests[0].merge_commit,
+        Some(squashed.to_string())
+    );
+    assert!(
+        controller
+            .refresh_auto_merges(project, Some("token"))
+            .unwrap()
+            .is_empty(),
+        "merged pull requests aren't polled anymore"
+    );
+
+    let unapplied = controller.update_base_branch(project).unwrap();
+    assert!(
+        unapplied.is_empty(),
+        "the branch isn't unapplied for conflicting with the change after the merge"
+    );
+    assert!(!controller
+        .list_virtual_branches(project)
+        .unwrap()
+        .0
+        .iter()
+        .any(|branch| branch.id == branch_id));
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "changed after the merge"
+    );
+}
+
+#[test]
+fn merge_request_joins_merge_train_on_gitlab() {
+    const PROJECT: &str = "/api/v4/projects/owner%2Frepo";
+    let test = &Test::default();
+    let controller = &test.controller;
+
+    let server = ForgeServer::new().unwrap();
+    let (ref project, branch_id) = pushed_branch(test, &server, ForgeKind::GitLab);
+    controller
+        .set_forge_access_token(&server.host(), "token")
+        .unwrap();
+    let merge_request = json!({
+        "iid": 3,
+        "web_url": "https://gitlab.com/owner/repo/-/merge_requests/3",
+        "title": "Add a feature",
+        "state": "opened",
+        "source_branch": "feature",
+        "target_branch": "master",
+    });
+    server.respond(
+        "GET",
+        &format!("{PROJECT}/merge_requests"),
+        200,
+        json!([merge_request.clone()]),
+    );
+    server.respond(
+        "GET",
+        &format!("{PROJECT}/merge_requests/3"),
+        200,
+        merge_request,
+    );
+    server.respond(
+        "POST",
+        &format!("{PROJECT}/merge_trains/merge_requests/3"),
+        201,
+        json!([]),
+    );
+
+    let pull_request = controller
+        .enable_auto_merge(project, branch_id, AutoMerge::MergeQueue, None)
+        .unwrap();
+    assert_eq!(pull_request.auto_merge, Some(AutoMerge::MergeQueue));
+    let train = server.requests().pop().unwrap();
+    assert_eq!(
+        train.path,
+        format!("{PROJECT}/merge_trains/merge_requests/3")
+    );
+    assert_eq!(train.body, json!({ "when_pipeline_succeeds": true }));
+}
@@ -0,0 +1,80 @@
+use gitbutler_branch::{BranchOwnershipClaims, FsckIssue, VirtualBranchesHandle};
+
+use super::*;
+
+#[test]
+fn stale_claims_and_missing_heads_are_found_and_repaired() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let report = controller.fsck(project, false).unwrap();
+    assert!(report.issues.is_empty());
+    assert!(!report.repaired);
+
+    let missing_head = git2::Oid::from_str("abababababababababababababababababababab").unwrap();
+    let vb_state = VirtualBranchesHandle::new(project.gb_dir());
+    let mut branch = vb_state.get_branch_in_workspace(branch_id).unwrap();
+    branch.head = missing_head;
+    branch.ownership = "gone.txt:1-2".parse::<BranchOwnershipClaims>().unwrap();
+    vb_state.set_branch(branch).unwrap();
+
+    let expected_issues = vec![
+        FsckIssue::MissingHead {
+            branch_id,
+            commit: missing_head,
+        },
+        FsckIssue::StaleClaim {
+            branch_id,
+            path: "gone.txt".into(),
+        },
+    ];
+    let report = controller.fsck(project, false).unwrap();
+    assert_eq!(report.issues, expected_issues);
+    assert!(!report.repaired, "nothing changes without asking for it");
+
+    let report = controller.fsck(project, true).unwrap();
+    assert_eq!(report.issues, expected_issues);
+    assert!(report.repaired);
+    assert!(project
+        .gb_dir()
+        .join("virtual_branches.toml.fsck.bak")
+        .exists());
+
+    let branch = vb_state.get_branch_in_workspace(branch_id).unwrap();
+    assert_eq!(branch.head, vb_state.get_default_target().unwrap().sha);
+    assert!(branch.ownership.claims.is_empty());
+    assert!(controller.fsck(project, false).unwrap().issues.is_empty());
+}
+
+#[test]
+fn damaged_state_is_reported_and_rewritten() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let state_path = project.gb_dir().join("virtual_branches.toml");
+    // A change outside of GitButler that doesn't change what the state means.
+    let damaged = format!("\n{}", fs::read_to_string(&state_path).unwrap());
+    fs::write(&state_path, damaged).unwrap();
+    assert!(controller.list_virtual_branches(project).is_err());
+
+    let report = controller.fsck(project, true).unwrap();
+    assert_eq!(report.issues, vec![FsckIssue::ChecksumMismatch]);
+    assert!(report.repaired);
+    controller.list_virtual_branches(project).unwrap();
+}
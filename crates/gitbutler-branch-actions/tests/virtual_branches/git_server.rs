@@ -0,0 +1,262 @@
+use std::time::Duration;
+
+use gitbutler_branch_actions::PushRejection;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_project::FetchFailure;
+use gitbutler_repo::UpdatedRef;
+use gitbutler_testsupport::git_server::{Auth, GitServer};
+
+use super::*;
+
+/// Serve a copy of the remote of `repository`, and return it along with the local repository.
+fn serve_origin(repository: &TestProject) -> (GitServer, git2::Repository) {
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let origin = repo.find_remote("origin").unwrap();
+    let server = GitServer::mirror(path::Path::new(origin.url().unwrap())).unwrap();
+    drop(origin);
+    (server, repo)
+}
+
+fn origin_master(repo: &git2::Repository) -> git2::Oid {
+    repo.refname_to_id("refs/remotes/origin/master").unwrap()
+}
+
+#[test]
+fn fetch_over_http() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let (server, repo) = serve_origin(repository);
+    repo.remote_set_url("origin", &server.http_url()).unwrap();
+    let before = origin_master(&repo);
+    let rewritten = server.rewrite_branch("master").unwrap();
+    server.set_latency(Duration::from_millis(50)).unwrap();
+
+    let report = controller.fetch_from_remotes(project, None).unwrap();
+    assert!(report.is_success(), "{report:?}");
+    assert_eq!(
+        report.remotes[0].updated_refs,
+        [UpdatedRef {
+            name: "refs/remotes/origin/master".into(),
+            old: Some(before),
+            new: Some(rewritten),
+        }],
+        "history rewritten by someone else is fetched"
+    );
+    assert!(server.requests() > 0);
+}
+
+#[test]
+fn fetch_with_rejected_credentials_is_an_auth_failure() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let (server, repo) = serve_origin(repository);
+    server
+        .set_auth(Auth::Basic {
+            username: "user".into(),
+            password: "secret".into(),
+        })
+        .unwrap();
+
+    repo.remote_set_url("origin", &server.http_url_with_credentials("user", "wrong"))
+        .unwrap();
+    let report = controller.fetch_from_remotes(project, None).unwrap();
+    assert_eq!(report.remotes[0].failure, Some(FetchFailure::Auth));
+
+    repo.remote_set_url(
+        "origin",
+        &server.http_url_with_credentials("user", "secret"),
+    )
+    .unwrap();
+    let report = controller.fetch_from_remotes(project, None).unwrap();
+    assert!(report.is_success(), "{report:?}");
+
+    server.reject_next_requests(1);
+    let report = controller.fetch_from_remotes(project, None).unwrap();
+    assert_eq!(
+        report.remotes[0].failure,
+        Some(FetchFailure::Auth),
+        "rejections apply even to the right credentials"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn fetch_over_ssh() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let (server, repo) = serve_origin(repository);
+    server.configure_ssh_command(&repo).unwrap();
+    repo.remote_set_url("origin", &server.ssh_url()).unwrap();
+
+    server.set_auth(Auth::Deny).unwrap();
+    let report = controller.fetch_from_remotes(project, None).unwrap();
+    assert_eq!(report.remotes[0].failure, Some(FetchFailure::Auth));
+
+    server.set_auth(Auth::Anonymous).unwrap();
+    let rewritten = server.rewrite_branch("master").unwrap();
+    let report = controller.fetch_from_remotes(project, None).unwrap();
+    assert!(report.is_success(), "{report:?}");
+    assert_eq!(origin_master(&repo), rewritten);
+}
+
+#[test]
+fn push_over_http_after_force_push_by_others() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let (server, repo) = serve_origin(repository);
+    repo.remote_set_url("origin", &server.http_url()).unwrap();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some("pushed".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+    controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap();
+    assert_eq!(
+        server
+            .repository()
+            .refname_to_id("refs/heads/pushed")
+            .unwrap(),
+        commit_id
+    );
+
+    let rewritten = server.rewrite_branch("pushed").unwrap();
+    let err = controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::PushRejected),
+        "the remote branch diverged"
+    );
+    let rejection = err.downcast_ref::<PushRejection>().unwrap();
+    assert_eq!(rejection.remote_head, Some(rewritten));
+    assert_eq!(
+        rejection
+            .overwritten
+            .iter()
+            .map(|commit| (
+                commit.id,
+                commit.author.name.as_str(),
+                commit.summary.as_str()
+            ))
+            .collect::<Vec<_>>(),
+        [(rewritten, "someone else", "commit (rewritten)")]
+    );
+    controller
+        .push_virtual_branch(project, branch_id, true, None)
+        .unwrap();
+    assert_eq!(
+        server
+            .repository()
+            .refname_to_id("refs/heads/pushed")
+            .unwrap(),
+        commit_id
+    );
+
+    server.rewrite_branch("pushed").unwrap();
+    server.deny_non_fast_forwards(true).unwrap();
+    assert!(
+        controller
+            .push_virtual_branch(project, branch_id, true, None)
+            .is_err(),
+        "the remote doesn't accept force pushes"
+    );
+}
+
+#[test]
+fn push_with_lease_keeps_commits_pushed_by_others() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let (server, repo) = serve_origin(repository);
+    repo.remote_set_url("origin", &server.http_url()).unwrap();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some("leased".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+    controller
+        .push_virtual_branch_with_lease(project, branch_id, None)
+        .unwrap();
+
+    let rewritten = server.rewrite_branch("leased").unwrap();
+    let err = controller
+        .push_virtual_branch_with_lease(project, branch_id, None)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::PushRejected),
+        "the remote branch changed since it was last seen"
+    );
+    let rejection = err.downcast_ref::<PushRejection>().unwrap();
+    assert_eq!(rejection.overwritten.len(), 1);
+    assert_eq!(rejection.overwritten[0].id, rewritten);
+    assert_eq!(
+        server
+            .repository()
+            .refname_to_id("refs/heads/leased")
+            .unwrap(),
+        rewritten,
+        "nothing was overwritten"
+    );
+
+    controller
+        .push_virtual_branch_with_lease(project, branch_id, None)
+        .unwrap();
+    assert_eq!(
+        server
+            .repository()
+            .refname_to_id("refs/heads/leased")
+            .unwrap(),
+        commit_id,
+        "commits that were seen by the rejected push may be overwritten"
+    );
+}
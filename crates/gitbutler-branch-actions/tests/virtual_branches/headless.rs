@@ -0,0 +1,90 @@
+use gitbutler_branch_actions::headless::Session;
+
+use super::*;
+
+fn session(test: &Test) -> Session {
+    Session::open(
+        test.data_dir.as_ref().unwrap().path(),
+        test.repository.path(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn open_reuses_the_project_of_the_worktree() {
+    let test = Test::default();
+    let session = session(&test);
+    assert_eq!(session.project().id, test.project_id);
+    assert_eq!(test.projects.list().unwrap().len(), 1);
+}
+
+#[test]
+fn lanes_are_created_once() {
+    let test = Test::default();
+    let session = session(&test);
+    session
+        .ensure_target(&"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    session
+        .ensure_target(&"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let lane = session.lane("format-bot").unwrap();
+    assert_eq!(session.lane("format-bot").unwrap(), lane);
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].name, "format-bot");
+}
+
+#[test]
+fn commit_to_lane_only_takes_matching_hunks() {
+    let test = Test::default();
+    let session = session(&test);
+    session
+        .ensure_target(&"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let work = test
+        .controller
+        .create_virtual_branch(
+            &test.project,
+            &BranchCreateRequest {
+                name: Some("work".into()),
+                selected_for_changes: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let lane = session.lane("format-bot").unwrap();
+
+    fs::write(test.repository.path().join("notes.txt"), "notes\n").unwrap();
+    fs::write(test.repository.path().join("main.rs"), "fn main() {}\n").unwrap();
+    test.controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    assert_eq!(
+        session
+            .commit_to_lane(lane, "path:*.md", "Format the code")
+            .unwrap(),
+        None
+    );
+
+    let commit = session
+        .commit_to_lane(lane, "path:*.rs", "Format the code")
+        .unwrap()
+        .expect("main.rs matches");
+
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    let lane = branches.iter().find(|branch| branch.id == lane).unwrap();
+    assert_eq!(lane.head, commit);
+    assert!(lane.files.is_empty());
+    let work = branches.iter().find(|branch| branch.id == work).unwrap();
+    assert!(work.commits.is_empty());
+    assert_eq!(work.files.len(), 1);
+    assert_eq!(work.files[0].path, PathBuf::from("notes.txt"));
+}
@@ -0,0 +1,168 @@
+use gitbutler_branch_actions::{GraphRef, HistoryFilter};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+/// Push three commits to the target, and tag the second one as `v1`.
+fn target_with_commits(test: &Test) -> [git2::Oid; 3] {
+    let path = test.repository.path();
+    fs::write(path.join("a.txt"), "a").unwrap();
+    let first = test.repository.commit_all("add a");
+    fs::create_dir(path.join("docs")).unwrap();
+    fs::write(path.join("docs/b.md"), "b").unwrap();
+    let second = test.repository.commit_all("document b");
+    fs::write(path.join("a.txt"), "changed").unwrap();
+    let third = test.repository.commit_all("change a");
+    test.repository.push();
+    test.repository.fetch();
+
+    let repo = git2::Repository::open(path).unwrap();
+    repo.tag_lightweight("v1", repo.find_commit(second).unwrap().as_object(), false)
+        .unwrap();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    [first, second, third]
+}
+
+#[test]
+fn pages_through_history() {
+    let test = Test::default();
+    let [first, second, third] = target_with_commits(&test);
+    let target = "refs/remotes/origin/master".parse().unwrap();
+
+    let page = test
+        .controller
+        .log(&test.project, &target, None, 2, &HistoryFilter::default())
+        .unwrap();
+    let ids: Vec<_> = page.entries.iter().map(|entry| &entry.commit.id).collect();
+    assert_eq!(ids, [&third.to_string(), &second.to_string()]);
+    assert_eq!(page.next_cursor, Some(second.to_string()));
+    assert!(page.entries[0].refs.contains(&GraphRef::Remote {
+        name: "refs/remotes/origin/master".into()
+    }));
+    assert!(page.entries[0].refs.contains(&GraphRef::Base {
+        name: "refs/remotes/origin/master".into()
+    }));
+    assert_eq!(page.entries[1].refs, [GraphRef::Tag { name: "v1".into() }]);
+    assert!(page.entries.iter().all(|entry| entry.signature.is_none()));
+
+    let page = test
+        .controller
+        .log(
+            &test.project,
+            &target,
+            page.next_cursor.as_deref(),
+            2,
+            &HistoryFilter::default(),
+        )
+        .unwrap();
+    assert_eq!(
+        page.entries[0].commit.id,
+        first.to_string(),
+        "the next page starts after the cursor"
+    );
+}
+
+#[test]
+fn filters_commits() {
+    let test = Test::default();
+    let [_, second, third] = target_with_commits(&test);
+    let target = "refs/remotes/origin/master".parse().unwrap();
+    let log = |filter: HistoryFilter| {
+        let page = test
+            .controller
+            .log(&test.project, &target, None, 10, &filter)
+            .unwrap();
+        assert_eq!(page.next_cursor, None);
+        page.entries
+            .into_iter()
+            .map(|entry| entry.commit.id)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        log(HistoryFilter {
+            path: Some("docs".into()),
+            ..Default::default()
+        }),
+        [second.to_string()],
+        "commits that changed a directory"
+    );
+    assert_eq!(
+        log(HistoryFilter {
+            grep: Some("CHANGE".into()),
+            ..Default::default()
+        }),
+        [third.to_string()],
+        "messages are matched ignoring case"
+    );
+    assert_eq!(
+        log(HistoryFilter {
+            author: Some("nobody".into()),
+            ..Default::default()
+        }),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        log(HistoryFilter {
+            author: Some("TEST@email".into()),
+            grep: Some("document".into()),
+            ..Default::default()
+        }),
+        [second.to_string()]
+    );
+    assert_eq!(
+        log(HistoryFilter {
+            since_timestamp_ms: Some(u128::MAX),
+            ..Default::default()
+        }),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn rejects_invalid_requests() {
+    let test = Test::default();
+    target_with_commits(&test);
+    let target = "refs/remotes/origin/master".parse().unwrap();
+    let filter = HistoryFilter::default();
+
+    let err = test
+        .controller
+        .log(&test.project, &target, None, 0, &filter)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+
+    let err = test
+        .controller
+        .log(&test.project, &target, Some("not a commit"), 10, &filter)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+
+    let unrelated = git2::Oid::hash_object(git2::ObjectType::Blob, b"unrelated").unwrap();
+    let err = test
+        .controller
+        .log(
+            &test.project,
+            &target,
+            Some(&unrelated.to_string()),
+            10,
+            &filter,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation),
+        "cursors have to be part of the history"
+    );
+}
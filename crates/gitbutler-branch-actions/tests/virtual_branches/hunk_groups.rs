@@ -0,0 +1,106 @@
+use gitbutler_branch_actions::HunkCategory;
+
+use super::*;
+
+#[test]
+fn hunks_are_grouped_by_kind_of_file() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    for path in [
+        "src/lib.rs",
+        "src/parser.test.ts",
+        "tests/integration.rs",
+        "README.md",
+        "config.toml",
+        "fixtures/input.rs",
+    ] {
+        let path = repository.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "content\n").unwrap();
+    }
+    fs::write(
+        repository.path().join(".gitattributes"),
+        "fixtures/** gitbutler-category=test\n",
+    )
+    .unwrap();
+
+    let groups = controller.hunk_groups(project, branch_id).unwrap();
+    let groups: Vec<_> = groups
+        .into_iter()
+        .map(|group| {
+            let mut files = group.files;
+            files.sort();
+            (group.category, files)
+        })
+        .collect();
+    assert_eq!(
+        groups,
+        [
+            (
+                HunkCategory::Test,
+                vec![
+                    PathBuf::from("fixtures/input.rs"),
+                    PathBuf::from("src/parser.test.ts"),
+                    PathBuf::from("tests/integration.rs"),
+                ]
+            ),
+            (HunkCategory::Docs, vec![PathBuf::from("README.md")]),
+            (
+                HunkCategory::Config,
+                vec![
+                    PathBuf::from(".gitattributes"),
+                    PathBuf::from("config.toml")
+                ]
+            ),
+            (HunkCategory::Source, vec![PathBuf::from("src/lib.rs")]),
+        ]
+    );
+}
+
+#[test]
+fn commit_only_a_group() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::create_dir_all(repository.path().join("tests")).unwrap();
+    fs::write(repository.path().join("tests/it.rs"), "test\n").unwrap();
+    fs::write(repository.path().join("lib.rs"), "source\n").unwrap();
+
+    let tests = controller
+        .hunk_groups(project, branch_id)
+        .unwrap()
+        .into_iter()
+        .find(|group| group.category == HunkCategory::Test)
+        .unwrap();
+    controller
+        .create_commit(project, branch_id, "tests", Some(&tests.ownership), false)
+        .unwrap();
+
+    let groups = controller.hunk_groups(project, branch_id).unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].category, HunkCategory::Source);
+    assert_eq!(groups[0].files, [PathBuf::from("lib.rs")]);
+}
@@ -0,0 +1,139 @@
+use gitbutler_branch::{BranchCreateRequest, BranchOwnershipClaims, BranchUpdateRequest};
+use gitbutler_diff::Hunk;
+
+use super::*;
+
+#[test]
+fn notes_are_listed_with_their_hunk() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content\n").unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let hunk = &branches[0].files[0].hunks[0];
+    assert_eq!(hunk.note, None);
+
+    let hunk = Hunk::new(hunk.start, hunk.end, Some(hunk.hash)).unwrap();
+    controller
+        .set_hunk_note(
+            project,
+            path::Path::new("file.txt"),
+            &hunk,
+            Some("needs test".into()),
+        )
+        .unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(
+        branches[0].files[0].hunks[0].note.as_deref(),
+        Some("needs test")
+    );
+
+    controller
+        .set_hunk_note(project, path::Path::new("file.txt"), &hunk, None)
+        .unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].files[0].hunks[0].note, None);
+}
+
+#[test]
+fn notes_move_with_their_hunk() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let first_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    let second_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content\n").unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let hunk = branches
+        .iter()
+        .find(|branch| branch.id == first_id)
+        .map(|branch| &branch.files[0].hunks[0])
+        .unwrap();
+    let hunk = Hunk::new(hunk.start, hunk.end, Some(hunk.hash)).unwrap();
+    controller
+        .set_hunk_note(
+            project,
+            path::Path::new("file.txt"),
+            &hunk,
+            Some("needs test".into()),
+        )
+        .unwrap();
+
+    controller
+        .update_virtual_branch(
+            project,
+            BranchUpdateRequest {
+                id: second_id,
+                ownership: Some("file.txt:1-2".parse::<BranchOwnershipClaims>().unwrap()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let second = branches
+        .iter()
+        .find(|branch| branch.id == second_id)
+        .unwrap();
+    assert_eq!(second.files[0].hunks[0].note.as_deref(), Some("needs test"));
+}
+
+#[test]
+fn notes_of_committed_hunks_are_dropped() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content\n").unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let hunk = &branches[0].files[0].hunks[0];
+    let hunk = Hunk::new(hunk.start, hunk.end, Some(hunk.hash)).unwrap();
+    controller
+        .set_hunk_note(
+            project,
+            path::Path::new("file.txt"),
+            &hunk,
+            Some("needs test".into()),
+        )
+        .unwrap();
+
+    controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+    controller.list_virtual_branches(project).unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content\nmore\n").unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].files[0].hunks[0].note, None);
+}
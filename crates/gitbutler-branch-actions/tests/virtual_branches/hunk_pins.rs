@@ -0,0 +1,124 @@
+use gitbutler_branch::{BranchCreateRequest, BranchOwnershipClaims, BranchUpdateRequest};
+use gitbutler_diff::Hunk;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+#[test]
+fn pinned_hunks_stay_with_their_branch() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let first_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    let second_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content\n").unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let (owner_id, other_id) = if branches
+        .iter()
+        .find(|branch| branch.id == first_id)
+        .is_some_and(|branch| !branch.files.is_empty())
+    {
+        (first_id, second_id)
+    } else {
+        (second_id, first_id)
+    };
+    let hunk = branches
+        .iter()
+        .find(|branch| branch.id == owner_id)
+        .map(|branch| &branch.files[0].hunks[0])
+        .unwrap();
+    assert!(!hunk.pinned);
+    let hunk = Hunk::new(hunk.start, hunk.end, Some(hunk.hash)).unwrap();
+
+    controller
+        .pin_hunk(project, other_id, path::Path::new("file.txt"), &hunk)
+        .unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let other = branches
+        .iter()
+        .find(|branch| branch.id == other_id)
+        .unwrap();
+    assert_eq!(other.files.len(), 1);
+    assert!(other.files[0].hunks[0].pinned);
+    assert_eq!(controller.list_hunk_pins(project).unwrap().len(), 1);
+
+    let err = controller
+        .update_virtual_branch(
+            project,
+            BranchUpdateRequest {
+                id: owner_id,
+                ownership: Some("file.txt:1-2".parse::<BranchOwnershipClaims>().unwrap()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+
+    assert!(controller
+        .unpin_hunk(project, path::Path::new("file.txt"), &hunk)
+        .unwrap());
+    controller
+        .update_virtual_branch(
+            project,
+            BranchUpdateRequest {
+                id: owner_id,
+                ownership: Some("file.txt:1-2".parse::<BranchOwnershipClaims>().unwrap()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let owner = branches
+        .iter()
+        .find(|branch| branch.id == owner_id)
+        .unwrap();
+    assert_eq!(owner.files.len(), 1);
+    assert!(!owner.files[0].hunks[0].pinned);
+}
+
+#[test]
+fn pins_of_committed_hunks_are_dropped() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content\n").unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let hunk = &branches[0].files[0].hunks[0];
+    let hunk = Hunk::new(hunk.start, hunk.end, Some(hunk.hash)).unwrap();
+    controller
+        .pin_hunk(project, branch_id, path::Path::new("file.txt"), &hunk)
+        .unwrap();
+
+    controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+    controller.list_virtual_branches(project).unwrap();
+    assert!(controller.list_hunk_pins(project).unwrap().is_empty());
+}
@@ -0,0 +1,90 @@
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+fn selected_paths(
+    controller: &VirtualBranchActions,
+    project: &Project,
+    query: &str,
+) -> Vec<PathBuf> {
+    let mut paths: Vec<_> = controller
+        .select_hunks(project, query)
+        .unwrap()
+        .claims
+        .into_iter()
+        .map(|claim| claim.file_path)
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn hunks_are_selected_by_path_content_and_size() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    fs::create_dir_all(repository.path().join("docs")).unwrap();
+    fs::write(repository.path().join("README.md"), "read me\n").unwrap();
+    fs::write(repository.path().join("docs/guide.md"), "TODO: guide\n").unwrap();
+    fs::write(repository.path().join("main.rs"), "fn main() {}\n// TODO\n").unwrap();
+
+    assert_eq!(
+        selected_paths(controller, project, "path:*.md"),
+        [PathBuf::from("README.md"), PathBuf::from("docs/guide.md")]
+    );
+    assert_eq!(
+        selected_paths(controller, project, "path:*.md not content:TODO"),
+        [PathBuf::from("README.md")]
+    );
+    assert_eq!(
+        selected_paths(
+            controller,
+            project,
+            r#"content:"fn main\(\)" or path:docs/"#
+        ),
+        [PathBuf::from("docs/guide.md"), PathBuf::from("main.rs")]
+    );
+    assert_eq!(
+        selected_paths(controller, project, "lines>1 and age<1h"),
+        [PathBuf::from("main.rs")]
+    );
+    assert!(selected_paths(controller, project, "age>1d").is_empty());
+}
+
+#[test]
+fn invalid_queries_are_rejected() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    for query in [
+        "",
+        "path:*.md and",
+        "(path:*.md",
+        "size>3",
+        "lines~3",
+        "age<3y",
+        "content:(",
+    ] {
+        let err = controller.select_hunks(project, query).unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation),
+            "{query}"
+        );
+    }
+}
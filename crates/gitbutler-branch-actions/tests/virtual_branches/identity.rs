@@ -0,0 +1,195 @@
+use gitbutler_branch_actions::{IdentityScope, IdentitySource};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+fn set_base_branch(test: &Test) {
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+}
+
+#[test]
+fn commit_authors_are_mapped_with_the_mailmap() {
+    let test = Test::default();
+    set_base_branch(&test);
+    let mailmap = test.data_dir.as_ref().unwrap().path().join("mailmap");
+    fs::write(
+        &mailmap,
+        "Proper Name <proper@example.com> <gitbutler-test@example.com>\n",
+    )
+    .unwrap();
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_str("mailmap.file", mailmap.to_str().unwrap())
+        .unwrap();
+
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "content\n").unwrap();
+    test.controller
+        .create_commit(&test.project, branch_id, "commit", None, false)
+        .unwrap();
+
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    let author = &branches[0].commits[0].author;
+    assert_eq!(author.name, "Proper Name");
+    assert_eq!(author.email, "proper@example.com");
+}
+
+/// Remove the email from the configuration of the repository and include it from a file conditionally instead.
+fn include_work_email(test: &Test) {
+    let included = test
+        .data_dir
+        .as_ref()
+        .unwrap()
+        .path()
+        .join("work.gitconfig");
+    fs::write(&included, "[user]\n\temail = work@example.com\n").unwrap();
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    let mut config = repo.config().unwrap();
+    config.remove("user.email").unwrap();
+    config
+        .set_str(
+            &format!("includeIf.gitdir:{}.path", repo.path().display()),
+            included.to_str().unwrap(),
+        )
+        .unwrap();
+}
+
+#[test]
+fn identity_is_read_from_conditional_includes() {
+    let test = Test::default();
+    include_work_email(&test);
+
+    let identity = test.controller.identity(&test.project).unwrap();
+    assert_eq!(identity.name.as_deref(), Some("gitbutler-test"));
+    assert_eq!(identity.email.as_deref(), Some("work@example.com"));
+    assert_eq!(
+        identity.name_source,
+        Some(IdentitySource {
+            scope: IdentityScope::Repository,
+            included: false,
+        })
+    );
+    assert_eq!(
+        identity.email_source,
+        Some(IdentitySource {
+            scope: IdentityScope::Repository,
+            included: true,
+        }),
+        "the include is configured in the repository"
+    );
+    assert_eq!(identity.signing_format, "openpgp");
+    assert_eq!(
+        identity.signing_key.as_deref(),
+        Some("gitbutler-test <work@example.com>"),
+        "like Git, the identity is the key if none is set"
+    );
+    assert!(identity.missing().is_empty());
+}
+
+#[test]
+fn commits_without_identity_are_rejected() {
+    let test = Test::default();
+    set_base_branch(&test);
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    repo.config().unwrap().set_str("user.name", "").unwrap();
+
+    let identity = test.controller.identity(&test.project).unwrap();
+    assert_eq!(identity.missing(), ["user.name"]);
+
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "content\n").unwrap();
+    let err = test
+        .controller
+        .create_commit(&test.project, branch_id, "commit", None, false)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+}
+
+#[test]
+fn identity_can_be_set_for_the_repository() {
+    let test = Test::default();
+    set_base_branch(&test);
+
+    let identity = test
+        .controller
+        .set_identity(&test.project, Some("Personal Name"), Some("me@example.org"))
+        .unwrap();
+    assert_eq!(identity.name.as_deref(), Some("Personal Name"));
+    assert_eq!(identity.email.as_deref(), Some("me@example.org"));
+    assert_eq!(
+        identity.email_source,
+        Some(IdentitySource {
+            scope: IdentityScope::Repository,
+            included: false,
+        })
+    );
+
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "content\n").unwrap();
+    test.controller
+        .create_commit(&test.project, branch_id, "commit", None, false)
+        .unwrap();
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    let author = &branches[0].commits[0].author;
+    assert_eq!(author.name, "Personal Name");
+    assert_eq!(author.email, "me@example.org");
+
+    let identity = test
+        .controller
+        .set_identity(&test.project, None, Some(""))
+        .unwrap();
+    assert_eq!(
+        identity.name.as_deref(),
+        Some("Personal Name"),
+        "values that aren't given are kept"
+    );
+    assert_ne!(
+        identity.email.as_deref(),
+        Some("me@example.org"),
+        "empty values are removed"
+    );
+}
+
+#[test]
+fn invalid_identities_are_rejected() {
+    let test = Test::default();
+    for (name, email) in [(None, Some("not-an-email")), (Some("Name <x>"), None)] {
+        let err = test
+            .controller
+            .set_identity(&test.project, name, email)
+            .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation)
+        );
+    }
+    let identity = test.controller.identity(&test.project).unwrap();
+    assert_eq!(
+        identity.email.as_deref(),
+        Some("gitbutler-test@example.com")
+    );
+}
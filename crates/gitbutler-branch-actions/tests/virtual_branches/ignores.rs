@@ -0,0 +1,137 @@
+use gitbutler_branch_actions::IgnoreFile;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+#[test]
+fn ignored_paths_are_explained_by_their_rule() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+    fs::write(
+        repository.path().join(".gitignore"),
+        "# generated\n*.log\n!keep.log\n",
+    )
+    .unwrap();
+
+    let checks = controller
+        .check_ignored(
+            project,
+            &[
+                PathBuf::from("debug.log"),
+                PathBuf::from("keep.log"),
+                PathBuf::from("file.txt"),
+            ],
+        )
+        .unwrap();
+
+    assert!(checks[0].ignored);
+    assert!(!checks[0].tracked);
+    let rule = checks[0].rule.as_ref().unwrap();
+    assert_eq!(rule.pattern, "*.log");
+    assert!(!rule.negated);
+    assert_eq!(rule.source.as_deref(), Some(path::Path::new(".gitignore")));
+    assert_eq!(rule.line, 2);
+
+    assert!(!checks[1].ignored);
+    let rule = checks[1].rule.as_ref().unwrap();
+    assert!(rule.negated);
+    assert_eq!(rule.line, 3);
+
+    assert!(!checks[2].ignored);
+    assert_eq!(checks[2].rule, None);
+}
+
+#[test]
+fn paths_outside_of_the_worktree_are_rejected() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let err = controller
+        .check_ignored(project, &[PathBuf::from("../elsewhere")])
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+}
+
+#[test]
+fn patterns_are_appended_once() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+    let gitignore = repository.path().join(".gitignore");
+    fs::write(&gitignore, "*.log").unwrap();
+
+    let added = controller
+        .add_ignore_patterns(
+            project,
+            IgnoreFile::Gitignore,
+            &[
+                "*.log".to_owned(),
+                "/target".to_owned(),
+                "/target".to_owned(),
+            ],
+        )
+        .unwrap();
+    assert_eq!(added, ["/target"]);
+    assert_eq!(fs::read_to_string(&gitignore).unwrap(), "*.log\n/target\n");
+
+    let checks = controller
+        .check_ignored(project, &[PathBuf::from("target")])
+        .unwrap();
+    assert!(checks[0].ignored);
+}
+
+#[test]
+fn patterns_can_be_excluded_locally() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    controller
+        .add_ignore_patterns(project, IgnoreFile::InfoExclude, &["local.txt".to_owned()])
+        .unwrap();
+
+    assert!(!repository.path().join(".gitignore").exists());
+    let checks = controller
+        .check_ignored(project, &[PathBuf::from("local.txt")])
+        .unwrap();
+    assert!(checks[0].ignored);
+    assert_eq!(
+        checks[0].rule.as_ref().unwrap().source.as_deref(),
+        Some(path::Path::new(".git/info/exclude"))
+    );
+}
+
+#[test]
+fn invalid_patterns_are_rejected() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    for pattern in ["", "a\nb"] {
+        let err = controller
+            .add_ignore_patterns(project, IgnoreFile::Gitignore, &[pattern.to_owned()])
+            .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation)
+        );
+    }
+}
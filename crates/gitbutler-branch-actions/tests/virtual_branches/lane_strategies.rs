@@ -0,0 +1,101 @@
+use gitbutler_project::{DefaultBranchName, LaneOrder, Settings};
+
+use super::*;
+
+fn with_settings(test: &Test, settings: Settings) -> Project {
+    test.projects
+        .update_settings(test.project.id, settings)
+        .unwrap();
+    let project = test.projects.get(test.project.id).unwrap();
+    test.controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    project
+}
+
+fn names(test: &Test, project: &Project) -> Vec<String> {
+    let (branches, _) = test.controller.list_virtual_branches(project).unwrap();
+    branches.into_iter().map(|branch| branch.name).collect()
+}
+
+#[test]
+fn numbered_branches_take_the_lowest_free_number() {
+    let test = Test::default();
+    let project = with_settings(
+        &test,
+        Settings {
+            default_branch_name: DefaultBranchName::Numbered,
+            ..test.project.settings.clone()
+        },
+    );
+
+    let first = test
+        .controller
+        .create_virtual_branch(&project, &BranchCreateRequest::default())
+        .unwrap();
+    test.controller
+        .create_virtual_branch(&project, &BranchCreateRequest::default())
+        .unwrap();
+    assert_eq!(names(&test, &project), ["Lane 1", "Lane 2"]);
+
+    test.controller
+        .convert_to_real_branch(&project, first)
+        .unwrap();
+    test.controller
+        .create_virtual_branch(&project, &BranchCreateRequest::default())
+        .unwrap();
+    let mut names = names(&test, &project);
+    names.sort();
+    assert_eq!(names, ["Lane 1", "Lane 2"]);
+}
+
+#[test]
+fn branches_can_be_named_after_their_summary() {
+    let test = Test::default();
+    let project = with_settings(
+        &test,
+        Settings {
+            default_branch_name: DefaultBranchName::Summary,
+            ..test.project.settings.clone()
+        },
+    );
+
+    test.controller
+        .create_virtual_branch(
+            &project,
+            &BranchCreateRequest {
+                summary: Some("\nFix login\nIt fails on Sundays".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    test.controller
+        .create_virtual_branch(&project, &BranchCreateRequest::default())
+        .unwrap();
+    assert_eq!(names(&test, &project), ["Fix login", "Virtual branch"]);
+}
+
+#[test]
+fn lanes_can_be_listed_alphabetically() {
+    let test = Test::default();
+    let project = with_settings(
+        &test,
+        Settings {
+            lane_order: LaneOrder::Alphabetical,
+            ..test.project.settings.clone()
+        },
+    );
+
+    for name in ["b", "C", "a"] {
+        test.controller
+            .create_virtual_branch(
+                &project,
+                &BranchCreateRequest {
+                    name: Some(name.into()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+    }
+    assert_eq!(names(&test, &project), ["a", "b", "C"]);
+}
@@ -0,0 +1,119 @@
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+#[test]
+fn sets_target_and_lanes() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    fs::write(
+        repository.path().join("gitbutler.toml"),
+        r#"
+target = "origin/master"
+
+[[lanes]]
+name = "docs"
+paths = ["docs/", "*.md"]
+
+[[lanes]]
+name = "app"
+paths = ["src/"]
+selected = true
+"#,
+    )
+    .unwrap();
+
+    let outcome = controller.apply_layout(project, None).unwrap();
+    assert!(outcome.target_changed);
+    assert_eq!(outcome.lanes.len(), 2);
+    assert!(outcome.lanes.iter().all(|lane| lane.created));
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let docs = branches.iter().find(|b| b.name == "docs").unwrap();
+    let app = branches.iter().find(|b| b.name == "app").unwrap();
+    assert_eq!(docs.allowed_paths, ["docs/", "*.md"]);
+    assert_eq!(app.allowed_paths, ["src/"]);
+    assert!(docs.order < app.order);
+    assert!(app.selected_for_changes);
+    assert!(!docs.selected_for_changes);
+
+    let outcome = controller.apply_layout(project, None).unwrap();
+    assert!(!outcome.target_changed, "nothing changes the second time");
+    assert!(outcome.lanes.iter().all(|lane| !lane.created));
+    assert_eq!(outcome.lanes[0].branch_id, docs.id);
+    assert_eq!(outcome.lanes[1].branch_id, app.id);
+    assert_eq!(
+        controller.list_virtual_branches(project).unwrap().0.len(),
+        2
+    );
+}
+
+#[test]
+fn keeps_branches_that_are_not_in_layout() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let other = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some("other".to_owned()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    fs::write(
+        repository.path().join("team-layout.toml"),
+        "[[lanes]]\nname = \"docs\"\npaths = [\"docs/\"]\n",
+    )
+    .unwrap();
+    let outcome = controller
+        .apply_layout(project, Some(path::Path::new("team-layout.toml")))
+        .unwrap();
+    assert!(!outcome.target_changed);
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 2);
+    assert!(branches.iter().any(|b| b.id == other));
+}
+
+#[test]
+fn rejects_invalid_layouts() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    for layout in [
+        "[[lanes]]\nname = \"a\"\n[[lanes]]\nname = \"a\"\n",
+        "[[lanes]]\nname = \"a\"\nselected = true\n[[lanes]]\nname = \"b\"\nselected = true\n",
+        "[[lanes]]\nname = \"a\"\nowner = \"me\"\n",
+    ] {
+        fs::write(repository.path().join("gitbutler.toml"), layout).unwrap();
+        let err = controller.apply_layout(project, None).unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation),
+            "{layout}"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use gitbutler_branch_actions::Leftover;
+
+use super::*;
+
+#[test]
+fn what_previous_runs_left_behind_is_removed() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+
+    let gb_dir = project.gb_dir();
+    let worktree = gb_dir.join(".wt").join("sign");
+    fs::create_dir_all(&worktree).unwrap();
+    let lock_file = gb_dir.join("virtual_branches.toml.lock");
+    fs::write(&lock_file, "").unwrap();
+    fs::write(gb_dir.join("project.lock"), "").unwrap();
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    let orphan = repo
+        .commit(
+            None,
+            &head.author(),
+            &head.committer(),
+            "orphan",
+            &head.tree().unwrap(),
+            &[&head],
+        )
+        .unwrap();
+    repo.reference("refs/gitbutler/orphan", orphan, false, "")
+        .unwrap();
+
+    let leftovers = controller.list_leftovers(project).unwrap();
+    assert_eq!(leftovers.len(), 3);
+    assert!(leftovers.contains(&Leftover::Worktree {
+        path: worktree.clone()
+    }));
+    assert!(leftovers.contains(&Leftover::LockFile {
+        path: lock_file.clone()
+    }));
+    assert!(leftovers.contains(&Leftover::Reference {
+        name: "refs/gitbutler/orphan".into(),
+        target: orphan,
+    }));
+
+    let removed = controller.remove_leftovers(project).unwrap();
+    assert_eq!(removed, leftovers);
+    assert!(!worktree.exists());
+    assert!(!lock_file.exists());
+    assert!(gb_dir.join("project.lock").exists());
+    assert!(repo.find_reference("refs/gitbutler/orphan").is_err());
+    assert_eq!(
+        repo.find_reference("refs/archive/gitbutler/orphan")
+            .unwrap()
+            .target(),
+        Some(orphan),
+        "commits only the leftover reference points to are kept"
+    );
+
+    assert!(controller.list_leftovers(project).unwrap().is_empty());
+    let branches = controller.list_virtual_branches(project).unwrap().0;
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].commits.len(), 1);
+}
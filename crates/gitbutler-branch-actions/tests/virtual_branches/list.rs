@@ -169,9 +169,13 @@ mod util {
             remotes,
             virtual_branch,
             updated_at: _,
+            updated_at_display: _,
             head: _, // NOTE: can't have stable commits while `gitbutler-change-id` is not stable/is a UUID.
             last_commiter: _,
+            last_commiter_display: _,
             has_local,
+            pull_request: _,
+            ci_status: _,
         }: &BranchListing,
         expected: ExpectedBranchListing,
         msg: &str,
@@ -0,0 +1,207 @@
+use gitbutler_branch::BranchId;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_project::{MessageGeneration, MessageGenerationBackend, Settings};
+use gitbutler_testsupport::forge_server::ForgeServer;
+use serde_json::json;
+
+use super::*;
+
+/// Configure `message_generation` and return the project along with a branch that has changes to `file.txt`.
+fn branch_with_changes(test: &Test, message_generation: MessageGeneration) -> (Project, BranchId) {
+    gitbutler_testsupport::secrets::setup_in_memory_store();
+    let settings = Settings {
+        message_generation,
+        ..test.project.settings.clone()
+    };
+    test.projects
+        .update_settings(test.project.id, settings)
+        .unwrap();
+    let project = test.projects.get(test.project.id).unwrap();
+    test.controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "content\n").unwrap();
+    (project, branch_id)
+}
+
+fn prompt_of(request: &gitbutler_testsupport::forge_server::ReceivedRequest) -> &str {
+    request.body["messages"][0]["content"].as_str().unwrap()
+}
+
+#[test]
+fn openai_compatible_endpoints_are_asked_with_the_stored_api_key() {
+    let test = Test::default();
+    let server = ForgeServer::new().unwrap();
+    server.respond(
+        "POST",
+        "/proxy/v1/chat/completions",
+        200,
+        json!({ "choices": [{ "message": { "role": "assistant", "content": "  Add file\n" } }] }),
+    );
+    let (project, branch_id) = branch_with_changes(
+        &test,
+        MessageGeneration {
+            backend: Some(MessageGenerationBackend::OpenAiCompatible {
+                base_url: format!("http://{}/proxy/v1/", server.host()),
+                model: "gpt-4o".into(),
+            }),
+            prompt_template: Some("Describe {branch}:\n{diff}".into()),
+            ..Default::default()
+        },
+    );
+    test.controller
+        .set_message_generation_api_key(&project, "key")
+        .unwrap();
+
+    let message = test
+        .controller
+        .generate_commit_message(&project, branch_id)
+        .unwrap();
+    assert_eq!(message, "Add file", "the answer is trimmed");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].authorization.as_deref(), Some("Bearer key"));
+    assert_eq!(requests[0].body["model"], "gpt-4o");
+    assert_eq!(
+        prompt_of(&requests[0]),
+        "Describe Virtual branch:\n--- file.txt\n@@ -0,0 +1 @@\n+content\n"
+    );
+}
+
+#[test]
+fn ollama_needs_no_api_key() {
+    let test = Test::default();
+    let server = ForgeServer::new().unwrap();
+    server.respond(
+        "POST",
+        "/api/chat",
+        200,
+        json!({ "message": { "role": "assistant", "content": "Add file" } }),
+    );
+    let (project, branch_id) = branch_with_changes(
+        &test,
+        MessageGeneration {
+            backend: Some(MessageGenerationBackend::Ollama {
+                base_url: format!("http://{}", server.host()),
+                model: "llama3".into(),
+            }),
+            ..Default::default()
+        },
+    );
+
+    let message = test
+        .controller
+        .generate_commit_message(&project, branch_id)
+        .unwrap();
+    assert_eq!(message, "Add file");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].authorization, None);
+    assert_eq!(requests[0].body["stream"], false);
+    assert!(prompt_of(&requests[0]).contains("+content"));
+}
+
+#[test]
+fn changes_beyond_the_limit_are_left_out() {
+    let test = Test::default();
+    let server = ForgeServer::new().unwrap();
+    server.respond(
+        "POST",
+        "/api/chat",
+        200,
+        json!({ "message": { "role": "assistant", "content": "Add files" } }),
+    );
+    let (project, branch_id) = branch_with_changes(
+        &test,
+        MessageGeneration {
+            backend: Some(MessageGenerationBackend::Ollama {
+                base_url: format!("http://{}", server.host()),
+                model: "llama3".into(),
+            }),
+            prompt_template: Some("{diff}".into()),
+            max_diff_chars: 100,
+        },
+    );
+    fs::write(test.repository.path().join("large.txt"), "x".repeat(200)).unwrap();
+
+    test.controller
+        .generate_commit_message(&project, branch_id)
+        .unwrap();
+
+    let prompt = prompt_of(&server.requests()[0]).to_owned();
+    assert!(prompt.contains("+content"), "small changes still fit");
+    assert!(!prompt.contains("xxx"));
+    assert!(prompt.ends_with("left out as they are too large: large.txt\n"));
+}
+
+#[test]
+fn failures_of_the_model_are_reported() {
+    let test = Test::default();
+    let server = ForgeServer::new().unwrap();
+    server.respond("POST", "/api/chat", 401, json!({ "error": "unauthorized" }));
+    let (project, branch_id) = branch_with_changes(
+        &test,
+        MessageGeneration {
+            backend: Some(MessageGenerationBackend::Ollama {
+                base_url: format!("http://{}", server.host()),
+                model: "llama3".into(),
+            }),
+            ..Default::default()
+        },
+    );
+
+    let err = test
+        .controller
+        .generate_commit_message(&project, branch_id)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::MessageGeneration)
+    );
+    assert!(format!("{err:#}").contains("unauthorized"));
+}
+
+#[test]
+fn anthropic_requires_an_api_key() {
+    let test = Test::default();
+    let (project, branch_id) = branch_with_changes(
+        &test,
+        MessageGeneration {
+            backend: Some(MessageGenerationBackend::Anthropic {
+                base_url: None,
+                model: "model".into(),
+            }),
+            ..Default::default()
+        },
+    );
+
+    let err = test
+        .controller
+        .generate_commit_message(&project, branch_id)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::MessageGeneration)
+    );
+}
+
+#[test]
+fn nothing_is_generated_without_a_backend() {
+    let test = Test::default();
+    let (project, branch_id) = branch_with_changes(&test, MessageGeneration::default());
+
+    let err = test
+        .controller
+        .generate_commit_message(&project, branch_id)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+}
@@ -56,30 +56,111 @@ impl Test {
     }
 }
 
+mod absorb;
+mod adopt_branch;
+mod allowed_paths;
 mod amend;
 mod apply_virtual_branch;
+mod audit_log;
+mod automation;
+mod blame;
+mod branch_dependencies;
+mod branch_events;
+mod branch_metadata;
+mod branch_naming;
+mod bulk;
+mod cancellation;
+mod checkout_preview;
+mod cherry_pick;
+mod cleanup;
+mod clock_skew;
+mod commit_graph;
+mod commit_message;
+mod commit_provenance;
+mod conflict_prediction;
+mod content_search;
 mod convert_to_real_branch;
 mod create_commit;
 mod create_virtual_branch_from_branch;
+mod create_virtual_branch_from_snapshot;
 mod delete_virtual_branch;
+mod diff_options;
+mod discard;
+mod export;
+mod fetch_from_remotes;
+mod fetch_settings;
+mod file_history;
+mod forge;
+mod fsck;
+mod git_server;
+#[cfg(feature = "headless")]
+mod headless;
+mod history;
+mod hunk_groups;
+mod hunk_notes;
+mod hunk_pins;
+mod hunk_query;
+mod identity;
+mod ignores;
 mod init;
 mod insert_blank_commit;
+mod lane_strategies;
+mod layout;
+mod leftovers;
 mod list;
+mod message_generation;
 mod move_commit_file;
 mod move_commit_to_vbranch;
+mod offline;
+mod operation_journal;
 mod oplog;
+mod ownership_conflicts;
+mod ownership_remap;
+mod parallelism;
+mod partial_apply;
+mod partial_checkout;
+mod patch_import;
+mod patches;
+mod pinned_base;
+mod project_search;
+mod push_preview;
+mod push_protection;
+mod reconcile;
 mod references;
+mod remote_activity;
+mod remote_branch_names;
+mod remotes;
+mod rename;
 mod reorder_commit;
+mod repo_state;
+mod reproducible;
 mod reset_virtual_branch;
+mod resolve_conflict;
+mod revert_commit;
+mod secret_scanning;
 mod selected_for_changes;
 mod set_base_branch;
+mod setup;
+mod shallow_clone;
+mod shelf;
+mod split_commit;
 mod squash;
+mod stacking;
+mod stash;
+mod submodules;
+mod switch_base_branch;
+mod tags;
+mod trash;
 mod unapply_ownership;
 mod undo_commit;
 mod update_base_branch;
 mod update_commit_message;
 mod upstream;
+mod upstream_config;
 mod verify_branch;
+mod workdir_cache;
+mod workspace;
+mod workspace_check;
 
 #[test]
 fn resolve_conflict_flow() {
@@ -0,0 +1,187 @@
+use gitbutler_branch::QueuedOperationKind;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_project::FetchFailure;
+use gitbutler_testsupport::git_server::GitServer;
+
+use super::*;
+
+/// A URL whose host never resolves, as if there was no network.
+const UNREACHABLE_URL: &str = "http://gitbutler-offline.invalid/repo.git";
+
+#[test]
+fn pushes_and_fetches_are_queued_while_offline_and_replayed_once_online() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let origin_url = repo
+        .find_remote("origin")
+        .unwrap()
+        .url()
+        .unwrap()
+        .to_owned();
+    let server = GitServer::mirror(path::Path::new(&origin_url)).unwrap();
+    repo.remote_set_url("origin", &server.http_url()).unwrap();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some("offline".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+
+    repo.remote_set_url("origin", UNREACHABLE_URL).unwrap();
+    let err = controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Offline),
+        "a missing network isn't mistaken for missing credentials"
+    );
+
+    let push = controller
+        .push_virtual_branch_or_queue(project, branch_id, false, None)
+        .unwrap()
+        .expect("the push is queued");
+    let forced = controller
+        .push_virtual_branch_or_queue(project, branch_id, true, None)
+        .unwrap()
+        .expect("the push is queued");
+    assert_eq!(forced.id, push.id, "the branch is only pushed once");
+    assert_eq!(
+        forced.kind,
+        QueuedOperationKind::Push {
+            branch_id,
+            with_force: true,
+            upstream_head: None,
+        },
+        "the branch wasn't pushed before"
+    );
+
+    let report = controller.fetch_from_remotes(project, None).unwrap();
+    assert_eq!(report.remotes[0].failure, Some(FetchFailure::Offline));
+    let queued = controller.list_queued_operations(project).unwrap();
+    assert_eq!(
+        queued
+            .iter()
+            .map(|operation| operation.kind)
+            .collect::<Vec<_>>(),
+        [forced.kind, QueuedOperationKind::Fetch]
+    );
+
+    assert_eq!(
+        controller.replay_queued_operations(project).unwrap(),
+        [],
+        "nothing runs while still offline"
+    );
+    assert_eq!(controller.list_queued_operations(project).unwrap(), queued);
+
+    let cancelled = controller
+        .cancel_queued_operation(project, queued[1].id)
+        .unwrap();
+    assert_eq!(cancelled.kind, QueuedOperationKind::Fetch);
+    assert!(controller
+        .cancel_queued_operation(project, queued[1].id)
+        .is_err());
+
+    repo.remote_set_url("origin", &server.http_url()).unwrap();
+    let report = controller.fetch_from_remotes(project, None).unwrap();
+    assert!(report.is_success(), "{report:?}");
+    assert_eq!(
+        controller.list_queued_operations(project).unwrap(),
+        [],
+        "the push ran once fetching worked again"
+    );
+    assert_eq!(
+        server
+            .repository()
+            .refname_to_id("refs/heads/offline")
+            .unwrap(),
+        commit_id
+    );
+}
+
+#[test]
+fn replayed_forced_pushes_keep_commits_pushed_by_others_while_offline() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let origin_url = repo
+        .find_remote("origin")
+        .unwrap()
+        .url()
+        .unwrap()
+        .to_owned();
+    let server = GitServer::mirror(path::Path::new(&origin_url)).unwrap();
+    repo.remote_set_url("origin", &server.http_url()).unwrap();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some("offline".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+    controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap();
+
+    repo.remote_set_url("origin", UNREACHABLE_URL).unwrap();
+    let push = controller
+        .push_virtual_branch_or_queue(project, branch_id, true, None)
+        .unwrap()
+        .expect("the push is queued");
+    assert_eq!(
+        push.kind,
+        QueuedOperationKind::Push {
+            branch_id,
+            with_force: true,
+            upstream_head: Some(commit_id),
+        }
+    );
+
+    let rewritten = server.rewrite_branch("offline").unwrap();
+    repo.remote_set_url("origin", &server.http_url()).unwrap();
+    let report = controller.fetch_from_remotes(project, None).unwrap();
+    assert!(report.is_success(), "{report:?}");
+    assert_eq!(
+        controller.list_queued_operations(project).unwrap(),
+        [],
+        "the push ran once fetching worked again"
+    );
+    assert_eq!(
+        server
+            .repository()
+            .refname_to_id("refs/heads/offline")
+            .unwrap(),
+        rewritten,
+        "the push expected the upstream branch where it was when the push was queued, not where it was fetched"
+    );
+}
@@ -0,0 +1,174 @@
+use gitbutler_branch::{BranchCreateRequest, BranchId};
+use gitbutler_branch_actions::{PendingOperation, ResumableOperation};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_oplog::OplogExt;
+
+use super::*;
+
+fn create_branch_with_file(
+    controller: &VirtualBranchActions,
+    project: &Project,
+    name: &str,
+) -> BranchId {
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some(name.to_owned()),
+                selected_for_changes: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(project.path.join(format!("{name}.txt")), "content").unwrap();
+    controller.list_virtual_branches(project).unwrap();
+    branch_id
+}
+
+/// Delete `a` and leave the journal behind as if the process ended before `b` was deleted too.
+fn interrupted_deletion(test: &Test) -> (BranchId, BranchId, PendingOperation) {
+    let Test {
+        project,
+        controller,
+        ..
+    } = test;
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let a = create_branch_with_file(controller, project, "a");
+    let b = create_branch_with_file(controller, project, "b");
+
+    controller.delete_virtual_branches(project, &[a]).unwrap();
+    assert_eq!(
+        controller.pending_operation(project).unwrap(),
+        None,
+        "finished operations leave no journal behind"
+    );
+
+    let pending = PendingOperation {
+        operation: ResumableOperation::DeleteBranches {
+            branch_ids: vec![a, b],
+        },
+        snapshot: Some(project.list_snapshots(1, None).unwrap()[0].commit_id),
+        completed_steps: 1,
+        started_timestamp_ms: 0,
+    };
+    fs::write(
+        project.gb_dir().join("operation.json"),
+        serde_json::to_string(&pending).unwrap(),
+    )
+    .unwrap();
+    (a, b, pending)
+}
+
+#[test]
+fn interrupted_operations_are_detected() {
+    let test = Test::default();
+    let (_, b, pending) = interrupted_deletion(&test);
+    let Test {
+        project,
+        controller,
+        ..
+    } = &test;
+
+    assert_eq!(
+        controller.pending_operation(project).unwrap(),
+        Some(pending)
+    );
+
+    let err = controller
+        .delete_virtual_branches(project, &[b])
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+    assert!(err.to_string().contains("resumed or rolled back"));
+}
+
+#[test]
+fn resume_runs_the_remaining_steps() {
+    let test = Test::default();
+    interrupted_deletion(&test);
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &test;
+
+    controller.resume_operation(project).unwrap();
+
+    assert_eq!(controller.pending_operation(project).unwrap(), None);
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert!(branches.is_empty());
+    assert!(!repository.path().join("b.txt").exists());
+}
+
+#[test]
+fn roll_back_restores_the_workspace_from_before_the_operation() {
+    let test = Test::default();
+    let (a, b, _) = interrupted_deletion(&test);
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &test;
+
+    controller.roll_back_operation(project).unwrap();
+
+    assert_eq!(controller.pending_operation(project).unwrap(), None);
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let mut ids: Vec<_> = branches.iter().map(|branch| branch.id).collect();
+    ids.sort();
+    let mut expected = vec![a, b];
+    expected.sort();
+    assert_eq!(ids, expected);
+    assert!(repository.path().join("a.txt").exists());
+}
+
+#[test]
+fn roll_back_requires_a_snapshot() {
+    let test = Test::default();
+    let (_, _, pending) = interrupted_deletion(&test);
+    let Test {
+        project,
+        controller,
+        ..
+    } = &test;
+    fs::write(
+        project.gb_dir().join("operation.json"),
+        serde_json::to_string(&PendingOperation {
+            snapshot: None,
+            ..pending
+        })
+        .unwrap(),
+    )
+    .unwrap();
+
+    let err = controller.roll_back_operation(project).unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+    assert!(controller.pending_operation(project).unwrap().is_some());
+}
+
+#[test]
+fn nothing_to_resume_without_an_interrupted_operation() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let err = controller.resume_operation(project).unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+}
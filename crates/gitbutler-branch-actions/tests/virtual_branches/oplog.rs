@@ -1,7 +1,13 @@
 use std::{io::Write, path::Path, time::Duration};
 
-use gitbutler_branch::{BranchCreateRequest, VirtualBranchesHandle};
-use gitbutler_oplog::OplogExt;
+use gitbutler_branch::{
+    BranchCreateRequest, BranchOwnershipClaims, BranchUpdateRequest, VirtualBranchesHandle,
+};
+use gitbutler_oplog::{
+    entry::{OperationKind, SnapshotDetails},
+    BranchChange, MovedHunk, OplogExt, UndoRedoState,
+};
+use gitbutler_project::{SnapshotRetention, SnapshotTriggers, SnapshotTriggersPreset};
 use itertools::Itertools;
 
 use super::*;
@@ -385,3 +391,475 @@ fn head_corrupt_is_recreated_automatically() {
         "it should have just reset the oplog head, so only 1, not 2"
     );
 }
+
+#[test]
+fn restore_single_path() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        controller,
+        project,
+        ..
+    } = &Test::default();
+
+    controller.set_base_branch(project, &"refs/remotes/origin/master".parse()?)?;
+    let branch_id = controller.create_virtual_branch(project, &BranchCreateRequest::default())?;
+    fs::write(repository.path().join("file.txt"), "one")?;
+    fs::write(repository.path().join("other.txt"), "one")?;
+    controller.create_commit(project, branch_id, "commit one", None, false)?;
+    controller.create_virtual_branch(project, &BranchCreateRequest::default())?;
+    let snapshot = project.list_snapshots(1, None)?[0].commit_id;
+
+    fs::remove_file(repository.path().join("file.txt"))?;
+    fs::write(repository.path().join("other.txt"), "two")?;
+    fs::write(repository.path().join("new.txt"), "new")?;
+
+    project.restore_paths(
+        snapshot,
+        &[PathBuf::from("file.txt"), PathBuf::from("new.txt")],
+    )?;
+
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt"))?,
+        "one"
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("other.txt"))?,
+        "two",
+        "other files are left as they are"
+    );
+    assert!(
+        !repository.path().join("new.txt").exists(),
+        "files that weren't in the snapshot are removed"
+    );
+
+    let snapshots = project.list_snapshots(1, None)?;
+    assert_eq!(
+        snapshots[0].details.as_ref().unwrap().operation,
+        OperationKind::RestoreFromSnapshot
+    );
+    Ok(())
+}
+
+#[test]
+fn restore_single_branch() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        controller,
+        project,
+        ..
+    } = &Test::default();
+
+    controller.set_base_branch(project, &"refs/remotes/origin/master".parse()?)?;
+    let branch_id = controller.create_virtual_branch(
+        project,
+        &BranchCreateRequest {
+            name: Some("restored".into()),
+            ..Default::default()
+        },
+    )?;
+    fs::write(repository.path().join("file.txt"), "content")?;
+    controller.create_commit(project, branch_id, "commit one", None, false)?;
+    let other_id = controller.create_virtual_branch(
+        project,
+        &BranchCreateRequest {
+            name: Some("other".into()),
+            ..Default::default()
+        },
+    )?;
+    let snapshot = project.list_snapshots(1, None)?[0].commit_id;
+
+    controller.delete_virtual_branch(project, branch_id)?;
+    assert!(!repository.path().join("file.txt").exists());
+    let (branches, _) = controller.list_virtual_branches(project)?;
+    assert_eq!(branches.len(), 1);
+
+    project.restore_branch(snapshot, branch_id)?;
+
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt"))?,
+        "content"
+    );
+    let (branches, _) = controller.list_virtual_branches(project)?;
+    assert_eq!(branches.len(), 2, "the other branch is kept");
+    assert!(branches.iter().any(|branch| branch.id == other_id));
+    let restored = branches
+        .iter()
+        .find(|branch| branch.id == branch_id)
+        .unwrap();
+    assert_eq!(restored.name, "restored");
+    assert_eq!(restored.commits.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn gc_drops_snapshots_beyond_retention() -> anyhow::Result<()> {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    for round in 0..3 {
+        controller.create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some(round.to_string()),
+                ..Default::default()
+            },
+        )?;
+    }
+    let snapshots = project.list_snapshots(10, None)?;
+    assert_eq!(
+        snapshots.len(),
+        4,
+        "one for the base branch, and one per branch"
+    );
+
+    let mut project = project.clone();
+    project.settings.snapshot_retention = SnapshotRetention {
+        max_snapshots: Some(2),
+        ..Default::default()
+    };
+    let outcome = project.gc()?;
+    assert_eq!(outcome.pruned_snapshots, 2);
+    assert_eq!(outcome.kept_snapshots, 2);
+
+    let kept = project.list_snapshots(10, None)?;
+    assert_eq!(kept.len(), 2);
+    assert_eq!(
+        kept.iter().map(|snapshot| &snapshot.details).collect_vec(),
+        snapshots[..2]
+            .iter()
+            .map(|snapshot| &snapshot.details)
+            .collect_vec(),
+        "the most recent snapshots are kept as they were"
+    );
+
+    let outcome = project.gc()?;
+    assert_eq!(outcome.pruned_snapshots, 0, "nothing left to drop");
+    assert_eq!(project.list_snapshots(10, None)?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn unchanged_state_is_snapshotted_as_the_same_tree() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller.create_virtual_branch(project, &BranchCreateRequest::default())?;
+    fs::write(repository.path().join("file.txt"), "content")?;
+    controller.create_commit(project, branch_id, "commit", None, false)?;
+
+    let guard = project.exclusive_worktree_access();
+    let first = project.prepare_snapshot(guard.read_permission())?;
+    let index_path = project.gb_dir().join("snapshot-index.toml");
+    assert!(index_path.exists(), "the commits of the branch are indexed");
+    let second = project.prepare_snapshot(guard.read_permission())?;
+    assert_eq!(first, second, "the indexed commits are reused");
+
+    fs::remove_file(index_path)?;
+    let third = project.prepare_snapshot(guard.read_permission())?;
+    assert_eq!(first, third, "the index only saves work");
+    Ok(())
+}
+
+#[test]
+fn snapshot_triggers_exclude_operations() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let mut project = project.clone();
+    project.snapshot_triggers = SnapshotTriggersPreset::Minimal.into();
+    let branch_id = controller.create_virtual_branch(&project, &BranchCreateRequest::default())?;
+    let snapshot_count = project.list_snapshots(10, None)?.len();
+
+    fs::write(repository.path().join("file.txt"), make_lines(50))?;
+    assert!(
+        !project.should_auto_snapshot(Duration::ZERO)?,
+        "changes to the worktree aren't snapshotted"
+    );
+    let commit_id = controller.create_commit(&project, branch_id, "commit", None, false)?;
+    assert_eq!(
+        project.list_snapshots(10, None)?.len(),
+        snapshot_count,
+        "commits aren't snapshotted"
+    );
+
+    controller.update_commit_message(&project, branch_id, commit_id, "reworded", false)?;
+    let snapshots = project.list_snapshots(10, None)?;
+    assert_eq!(snapshots.len(), snapshot_count + 1);
+    assert_eq!(
+        snapshots[0]
+            .details
+            .as_ref()
+            .map(|details| details.operation),
+        Some(OperationKind::UpdateCommitMessage),
+        "rewriting commits is still snapshotted"
+    );
+
+    project.snapshot_triggers = SnapshotTriggers {
+        interval_secs: Some(0),
+        on_lines_changed: false,
+        ..Default::default()
+    };
+    fs::write(repository.path().join("file.txt"), make_lines(51))?;
+    controller.list_virtual_branches(&project)?;
+    assert!(
+        project.should_auto_snapshot(Duration::ZERO)?,
+        "once the interval passed, even small changes are snapshotted"
+    );
+    Ok(())
+}
+
+#[test]
+fn undo_and_redo_navigate_operations() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller.set_base_branch(project, &"refs/remotes/origin/master".parse()?)?;
+    let branch_id = controller.create_virtual_branch(project, &BranchCreateRequest::default())?;
+    fs::write(repository.path().join("file.txt"), "one")?;
+    controller.create_commit(project, branch_id, "commit one", None, false)?;
+    fs::write(repository.path().join("file.txt"), "two")?;
+    controller.create_commit(project, branch_id, "commit two", None, false)?;
+    let commit_count = || -> anyhow::Result<usize> {
+        Ok(controller
+            .list_virtual_branches(project)?
+            .0
+            .into_iter()
+            .find(|branch| branch.id == branch_id)
+            .map_or(0, |branch| branch.commits.len()))
+    };
+
+    assert_eq!(
+        project.undo_redo_state()?,
+        UndoRedoState {
+            undo: Some("Create commit \"commit two\"".into()),
+            redo: None,
+        }
+    );
+    assert_eq!(
+        project.undo()?.as_deref(),
+        Some("Create commit \"commit two\"")
+    );
+    assert_eq!(commit_count()?, 1);
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt"))?,
+        "two",
+        "the changes of the undone commit are uncommitted again"
+    );
+    assert_eq!(
+        project.undo()?.as_deref(),
+        Some("Create commit \"commit one\"")
+    );
+    assert_eq!(commit_count()?, 0);
+
+    assert_eq!(
+        project.redo()?.as_deref(),
+        Some("Create commit \"commit one\"")
+    );
+    assert_eq!(commit_count()?, 1);
+    assert_eq!(
+        project.redo()?.as_deref(),
+        Some("Create commit \"commit two\"")
+    );
+    assert_eq!(commit_count()?, 2);
+    assert_eq!(project.redo()?, None, "everything was redone");
+
+    assert_eq!(
+        project.undo()?.as_deref(),
+        Some("Create commit \"commit two\""),
+        "redone operations can be undone again"
+    );
+    controller.create_virtual_branch(project, &BranchCreateRequest::default())?;
+    assert_eq!(
+        project.undo_redo_state()?.redo,
+        None,
+        "a new operation discards what could be redone"
+    );
+    Ok(())
+}
+
+#[test]
+fn any_two_snapshots_can_be_compared() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        controller,
+        project,
+        ..
+    } = &Test::default();
+    let snapshot = || -> anyhow::Result<git2::Oid> {
+        let mut guard = project.exclusive_worktree_access();
+        Ok(project
+            .create_snapshot(
+                SnapshotDetails::new(OperationKind::FileChanges),
+                guard.write_permission(),
+            )?
+            .expect("the state changed"))
+    };
+
+    controller.set_base_branch(project, &"refs/remotes/origin/master".parse()?)?;
+    let first_id = controller.create_virtual_branch(
+        project,
+        &BranchCreateRequest {
+            name: Some("first".into()),
+            ..Default::default()
+        },
+    )?;
+    fs::write(repository.path().join("a.txt"), "a\n")?;
+    controller.list_virtual_branches(project)?;
+    let old = snapshot()?;
+
+    let second_id = controller.create_virtual_branch(
+        project,
+        &BranchCreateRequest {
+            name: Some("second".into()),
+            ..Default::default()
+        },
+    )?;
+    controller.update_virtual_branch(
+        project,
+        BranchUpdateRequest {
+            id: second_id,
+            ownership: Some("a.txt:1-2".parse::<BranchOwnershipClaims>()?),
+            ..Default::default()
+        },
+    )?;
+    controller.update_virtual_branch(
+        project,
+        BranchUpdateRequest {
+            id: first_id,
+            name: Some("renamed".into()),
+            ..Default::default()
+        },
+    )?;
+    fs::write(repository.path().join("b.txt"), "b\n")?;
+    controller.list_virtual_branches(project)?;
+    let new = snapshot()?;
+
+    let diff = project.diff_snapshots(old, new)?;
+    assert_eq!(diff.files.keys().collect::<Vec<_>>(), [Path::new("b.txt")]);
+    assert_eq!(
+        diff.branches,
+        [
+            BranchChange::Changed {
+                id: first_id,
+                name: "renamed".into(),
+                previous_name: Some("first".into()),
+                commits_changed: false,
+                applied: None,
+            },
+            BranchChange::Created {
+                id: second_id,
+                name: "second".into(),
+            },
+        ]
+    );
+    assert_eq!(
+        diff.moved_hunks,
+        [MovedHunk {
+            path: "a.txt".into(),
+            hunk: "1-2".into(),
+            from: first_id,
+            to: second_id,
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn snapshots_can_be_viewed_without_touching_the_worktree() -> anyhow::Result<()> {
+    let Test {
+        repository,
+        controller,
+        project,
+        ..
+    } = &Test::default();
+
+    controller.set_base_branch(project, &"refs/remotes/origin/master".parse()?)?;
+    let branch_id = controller.create_virtual_branch(
+        project,
+        &BranchCreateRequest {
+            name: Some("first".into()),
+            ..Default::default()
+        },
+    )?;
+    fs::write(repository.path().join("a.txt"), "a\n")?;
+    let commit_id = controller.create_commit(project, branch_id, "add a", None, false)?;
+    fs::write(repository.path().join("b.txt"), "b\n")?;
+    controller.list_virtual_branches(project)?;
+    let snapshot_id = {
+        let mut guard = project.exclusive_worktree_access();
+        project
+            .create_snapshot(
+                SnapshotDetails::new(OperationKind::FileChanges),
+                guard.write_permission(),
+            )?
+            .expect("the state changed")
+    };
+
+    controller.delete_virtual_branch(project, branch_id)?;
+    fs::write(repository.path().join("c.txt"), "c\n")?;
+
+    let view = project.snapshot_view(snapshot_id)?;
+    assert_eq!(view.snapshot_id(), snapshot_id);
+
+    let branches = view.branches()?;
+    assert_eq!(branches.len(), 1);
+    let branch = &branches[0];
+    assert_eq!((branch.id, branch.name.as_str()), (branch_id, "first"));
+    assert!(branch.applied);
+    assert_eq!(branch.owned_files, [Path::new("b.txt")]);
+    assert_eq!(
+        branch
+            .commits
+            .iter()
+            .map(|commit| (commit.id, commit.message.as_str()))
+            .collect::<Vec<_>>(),
+        [(commit_id, "add a")]
+    );
+
+    let files = view.files()?;
+    assert!(files.contains(&"a.txt".into()));
+    assert!(files.contains(&"b.txt".into()));
+    assert!(!files.contains(&"c.txt".into()));
+    assert_eq!(view.file(Path::new("b.txt"))?, Some(b"b\n".to_vec()));
+    assert_eq!(view.file(Path::new("c.txt"))?, None);
+
+    assert_eq!(
+        view.branch_diff(branch_id)?.keys().collect::<Vec<_>>(),
+        [Path::new("b.txt")]
+    );
+    assert_eq!(
+        view.commit_diff(commit_id)?.keys().collect::<Vec<_>>(),
+        [Path::new("a.txt")]
+    );
+
+    assert!(
+        !repository.path().join("b.txt").exists(),
+        "the worktree is left alone"
+    );
+    assert!(repository.path().join("c.txt").exists());
+    Ok(())
+}
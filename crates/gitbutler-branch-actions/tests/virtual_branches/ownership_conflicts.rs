@@ -0,0 +1,68 @@
+use gitbutler_branch::{BranchCreateRequest, BranchOwnershipClaims};
+
+use super::*;
+
+#[test]
+fn hunks_claimed_by_other_branches_are_reported_and_not_taken() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let first_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content\n").unwrap();
+    controller.list_virtual_branches(project).unwrap();
+    let second_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let same_hunk = "file.txt:1-2".parse::<BranchOwnershipClaims>().unwrap();
+    let conflicts = controller
+        .ownership_conflicts(project, second_id, &same_hunk)
+        .unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].path, PathBuf::from("file.txt"));
+    assert_eq!(conflicts[0].hunk, "1-2");
+    assert_eq!(conflicts[0].claimed_by.len(), 1);
+    let claim = &conflicts[0].claimed_by[0];
+    assert_eq!(claim.branch_id, first_id);
+    assert_eq!(claim.hunks, ["1-2"]);
+    assert!(claim.reassigned);
+    assert!(!claim.overlapping);
+
+    let overlapping_hunk = "file.txt:1-1".parse::<BranchOwnershipClaims>().unwrap();
+    let conflicts = controller
+        .ownership_conflicts(project, second_id, &overlapping_hunk)
+        .unwrap();
+    assert!(!conflicts[0].claimed_by[0].reassigned);
+    assert!(conflicts[0].claimed_by[0].overlapping);
+
+    assert!(controller
+        .ownership_conflicts(project, first_id, &same_hunk)
+        .unwrap()
+        .is_empty());
+
+    let refused = controller
+        .claim_ownership_exclusively(project, second_id, &same_hunk)
+        .unwrap();
+    assert_eq!(refused.len(), 1);
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let first = branches
+        .iter()
+        .find(|branch| branch.id == first_id)
+        .unwrap();
+    let second = branches
+        .iter()
+        .find(|branch| branch.id == second_id)
+        .unwrap();
+    assert_eq!(first.files.len(), 1, "the hunk stays where it was");
+    assert!(second.files.is_empty());
+}
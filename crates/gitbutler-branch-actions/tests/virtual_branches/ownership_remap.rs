@@ -0,0 +1,126 @@
+use gitbutler_branch::{BranchCreateRequest, BranchId};
+use gitbutler_branch_actions::{BaseUpdateStrategy, HunkLocation, RemapOutcome};
+
+use super::*;
+
+fn numbered_lines(prefix: &str) -> String {
+    (1..=20).fold(prefix.to_owned(), |mut content, line| {
+        content.push_str(&format!("{line}\n"));
+        content
+    })
+}
+
+/// Push a commit with `upstream` as content of `file.txt` on top of one with `base` as content, and
+/// base the workspace on the latter.
+fn setup_with_upstream_change(test: &Test, base: &str, upstream: &str) -> BranchId {
+    let repository = &test.repository;
+    fs::write(repository.path().join("file.txt"), base).unwrap();
+    let base_commit = repository.commit_all("base");
+    fs::write(repository.path().join("file.txt"), upstream).unwrap();
+    repository.commit_all("upstream");
+    repository.push();
+    repository.reset_hard(Some(base_commit));
+
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    test.controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap()
+}
+
+fn hunk_ids(test: &Test) -> Vec<String> {
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    branches[0]
+        .files
+        .iter()
+        .flat_map(|file| file.hunks.iter().map(|hunk| hunk.id.clone()))
+        .collect()
+}
+
+#[test]
+fn hunks_shifted_by_the_target_are_reported_as_moved() {
+    let test = Test::default();
+    let base = numbered_lines("");
+    let branch_id = setup_with_upstream_change(&test, &base, &numbered_lines("a\nb\nc\n"));
+
+    fs::write(
+        test.repository.path().join("file.txt"),
+        base.replace("15\n", "fifteen\n"),
+    )
+    .unwrap();
+    let before = hunk_ids(&test);
+    assert_eq!(before.len(), 1);
+
+    let (unapplied, remap) = test
+        .controller
+        .update_base_branch_with_remap(&test.project, BaseUpdateStrategy::default())
+        .unwrap();
+    assert!(unapplied.is_empty());
+    let after = hunk_ids(&test);
+    assert_eq!(after.len(), 1);
+    assert_ne!(before, after, "the upstream lines are above the change");
+
+    assert_eq!(remap.branches.len(), 1);
+    let branch = &remap.branches[0];
+    assert_eq!(branch.branch_id, branch_id);
+    assert_eq!(branch.hunks.len(), 1);
+    assert_eq!(branch.hunks[0].file_path, PathBuf::from("file.txt"));
+    assert_eq!(branch.hunks[0].hunk, before[0]);
+    assert_eq!(
+        branch.hunks[0].outcome,
+        RemapOutcome::Moved {
+            to: HunkLocation {
+                branch_id,
+                hunk: after[0].clone(),
+            }
+        }
+    );
+}
+
+#[test]
+fn hunks_in_the_target_are_reported_as_dropped() {
+    let test = Test::default();
+    let base = numbered_lines("");
+    let upstream = base.replace("15\n", "fifteen\n");
+    let branch_id = setup_with_upstream_change(&test, &base, &upstream);
+
+    fs::write(test.repository.path().join("file.txt"), &upstream).unwrap();
+    let before = hunk_ids(&test);
+
+    let (_, remap) = test
+        .controller
+        .update_base_branch_with_remap(&test.project, BaseUpdateStrategy::default())
+        .unwrap();
+    assert!(hunk_ids(&test).is_empty());
+    assert_eq!(remap.branches.len(), 1);
+    assert_eq!(remap.branches[0].branch_id, branch_id);
+    assert_eq!(remap.branches[0].hunks[0].hunk, before[0]);
+    assert_eq!(remap.branches[0].hunks[0].outcome, RemapOutcome::Dropped);
+}
+
+#[test]
+fn hunks_that_are_kept_are_not_reported() {
+    let test = Test::default();
+    let base = numbered_lines("");
+    setup_with_upstream_change(&test, &base, &base.replace("20\n", "twenty\n"));
+
+    fs::write(
+        test.repository.path().join("file.txt"),
+        base.replacen("1\n", "one\n", 1),
+    )
+    .unwrap();
+    hunk_ids(&test);
+
+    let (_, remap) = test
+        .controller
+        .update_base_branch_with_remap(&test.project, BaseUpdateStrategy::default())
+        .unwrap();
+    assert!(remap.is_empty());
+}
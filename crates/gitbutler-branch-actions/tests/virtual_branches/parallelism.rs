@@ -0,0 +1,72 @@
+use gitbutler_branch::BranchCreateRequest;
+
+use super::*;
+
+#[test]
+fn many_files_are_diffed_and_committed_on_several_threads() {
+    let Test {
+        repository,
+        project_id,
+        controller,
+        projects,
+        ..
+    } = &Test::default();
+
+    let project = &projects
+        .update(&projects::UpdateRequest {
+            id: *project_id,
+            parallelism: Some(projects::Parallelism { max_threads: 4 }),
+            ..Default::default()
+        })
+        .unwrap();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let mut expected = Vec::new();
+    for dir in 0..10 {
+        fs::create_dir_all(repository.path().join(format!("dir-{dir}/sub"))).unwrap();
+        for file in 0..30 {
+            let path = PathBuf::from(format!("dir-{dir}/sub/file-{file}.txt"));
+            fs::write(repository.path().join(&path), format!("{dir} {file}\n")).unwrap();
+            expected.push(path);
+        }
+    }
+    expected.sort();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let mut files: Vec<_> = branches[0]
+        .files
+        .iter()
+        .map(|file| file.path.clone())
+        .collect();
+    files.sort();
+    assert_eq!(files, expected);
+
+    let commit_id = controller
+        .create_commit(project, branch_id, "many files", None, false)
+        .unwrap();
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let tree = repo.find_commit(commit_id).unwrap().tree().unwrap();
+    for path in &expected {
+        let blob = tree
+            .get_path(path)
+            .unwrap()
+            .to_object(&repo)
+            .unwrap()
+            .peel_to_blob()
+            .unwrap();
+        assert_eq!(
+            fs::read(repository.path().join(path)).unwrap(),
+            blob.content(),
+            "{} was committed as it is in the worktree",
+            path.display()
+        );
+    }
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert!(branches[0].files.is_empty(), "all changes were committed");
+}
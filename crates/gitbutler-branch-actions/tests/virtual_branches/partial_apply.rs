@@ -0,0 +1,89 @@
+use std::str::FromStr;
+
+use gitbutler_branch::BranchOwnershipClaims;
+
+use super::*;
+
+/// Commit `one.txt` and `two.txt` to a virtual branch and unapply it, returning its reference.
+fn unapplied_branch_with_two_files(
+    project: &Project,
+    controller: &VirtualBranchActions,
+) -> Refname {
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(project.path.join("one.txt"), "one\n").unwrap();
+    fs::write(project.path.join("two.txt"), "two\n").unwrap();
+    controller
+        .create_commit(project, branch_id, "two files", None, false)
+        .unwrap();
+
+    let refname = controller
+        .convert_to_real_branch(project, branch_id)
+        .unwrap();
+    Refname::from_str(&refname).unwrap()
+}
+
+#[test]
+fn apply_selected_file_only() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let refname = unapplied_branch_with_two_files(project, controller);
+    assert!(!repository.path().join("one.txt").exists());
+
+    let selection = BranchOwnershipClaims::from_str("one.txt:1-2").unwrap();
+    let branch_id = controller
+        .apply_branch_partially(project, &refname, &selection)
+        .unwrap();
+
+    assert_eq!(
+        fs::read_to_string(repository.path().join("one.txt")).unwrap(),
+        "one\n"
+    );
+    assert!(!repository.path().join("two.txt").exists());
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].id, branch_id);
+    assert!(branches[0].commits.is_empty());
+    assert_eq!(branches[0].files.len(), 1);
+    assert_eq!(branches[0].files[0].path.display().to_string(), "one.txt");
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let head = repo
+        .find_reference(&refname.to_string())
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert!(
+        head.tree().unwrap().get_name("two.txt").is_some(),
+        "the unapplied branch keeps all of its changes"
+    );
+}
+
+#[test]
+fn selection_must_be_part_of_the_branch() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let refname = unapplied_branch_with_two_files(project, controller);
+
+    let selection = BranchOwnershipClaims::from_str("three.txt:1-2").unwrap();
+    assert!(controller
+        .apply_branch_partially(project, &refname, &selection)
+        .is_err());
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert!(branches.is_empty());
+}
@@ -0,0 +1,98 @@
+use super::*;
+
+fn changed_files(controller: &VirtualBranchActions, project: &Project) -> Vec<PathBuf> {
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let mut files: Vec<_> = branches
+        .into_iter()
+        .flat_map(|branch| branch.files)
+        .map(|file| file.path)
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn only_checked_out_directories_are_diffed() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    for dir in ["a", "b"] {
+        fs::create_dir_all(repository.path().join(dir)).unwrap();
+        fs::write(repository.path().join(dir).join("file"), "content\n").unwrap();
+    }
+    repository.commit_all("two directories");
+    repository.push();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let checkout = controller
+        .enable_partial_checkout(project, &[PathBuf::from("a")])
+        .unwrap();
+    assert!(checkout.enabled);
+    assert_eq!(checkout.directories, [PathBuf::from("a")]);
+    assert!(!repository.path().join("b/file").exists());
+    assert!(
+        changed_files(controller, project).is_empty(),
+        "files that aren't checked out aren't deleted"
+    );
+
+    fs::write(repository.path().join("a/file"), "changed\n").unwrap();
+    assert_eq!(
+        changed_files(controller, project),
+        [PathBuf::from("a/file")]
+    );
+
+    let checkout = controller
+        .hydrate_partial_checkout(project, &[PathBuf::from("b/file")])
+        .unwrap();
+    assert_eq!(
+        checkout.directories,
+        [PathBuf::from("a"), PathBuf::from("b")]
+    );
+    assert!(repository.path().join("b/file").exists());
+
+    let checkout = controller.disable_partial_checkout(project).unwrap();
+    assert!(!checkout.enabled);
+    assert_eq!(
+        changed_files(controller, project),
+        [PathBuf::from("a/file")]
+    );
+}
+
+#[test]
+fn directories_with_changes_stay_checked_out() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    for dir in ["a", "b"] {
+        fs::create_dir_all(repository.path().join(dir)).unwrap();
+        fs::write(repository.path().join(dir).join("file"), "content\n").unwrap();
+    }
+    repository.commit_all("two directories");
+    repository.push();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    fs::write(repository.path().join("b/file"), "changed\n").unwrap();
+
+    let checkout = controller
+        .enable_partial_checkout(project, &[PathBuf::from("a")])
+        .unwrap();
+    assert_eq!(
+        checkout.directories,
+        [PathBuf::from("a"), PathBuf::from("b")]
+    );
+    assert_eq!(
+        changed_files(controller, project),
+        [PathBuf::from("b/file")]
+    );
+}
@@ -0,0 +1,209 @@
+use gitbutler_branch::BranchId;
+use gitbutler_branch_actions::{ImportPatchOptions, PatchConflict};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+const ADD_C: &str = "diff --git a/c.txt b/c.txt
+new file mode 100644
+index 0000000..f2ad6c7
+--- /dev/null
++++ b/c.txt
+@@ -0,0 +1 @@
++c
+";
+
+const CHANGE_C: &str = "diff --git a/c.txt b/c.txt
+index f2ad6c7..4bcfe98 100644
+--- a/c.txt
++++ b/c.txt
+@@ -1 +1 @@
+-c
++d
+";
+
+fn email(author: &str, date: &str, subject: &str, body: &str, diff: &str) -> String {
+    format!(
+        "From 0000000000000000000000000000000000000000 Mon Sep 17 00:00:00 2001\n\
+         From: {author}\n\
+         Date: {date}\n\
+         Subject: {subject}\n\
+         \n\
+         {body}---\n \
+         c.txt | 1 +\n \
+         1 file changed\n\
+         \n\
+         {diff}--\n\
+         2.45.0\n\
+         \n"
+    )
+}
+
+fn applied_branch(test: &Test) -> BranchId {
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    test.controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap()
+}
+
+#[test]
+fn applies_plain_diff_as_single_commit() {
+    let test = Test::default();
+    let branch_id = applied_branch(&test);
+
+    let outcome = test
+        .controller
+        .import_patch(
+            &test.project,
+            branch_id,
+            ADD_C.as_bytes(),
+            &ImportPatchOptions {
+                message: Some("Add c".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(outcome.commits.len(), 1);
+    assert_eq!(outcome.conflict, None);
+    assert_eq!(
+        fs::read_to_string(test.repository.path().join("c.txt")).unwrap(),
+        "c\n"
+    );
+
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    let commit = repo.find_commit(outcome.commits[0]).unwrap();
+    assert_eq!(commit.message(), Some("Add c"));
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    let branch = branches
+        .iter()
+        .find(|branch| branch.id == branch_id)
+        .unwrap();
+    assert_eq!(branch.head, outcome.commits[0]);
+}
+
+#[test]
+fn applies_mailbox_with_authorship() {
+    let test = Test::default();
+    let branch_id = applied_branch(&test);
+    let cover_letter = "From 0000000000000000000000000000000000000000 Mon Sep 17 00:00:00 2001\n\
+                        From: Jane Doe <jane@example.com>\n\
+                        Subject: [PATCH 0/2] c\n\
+                        \n\
+                        Adds c.\n\
+                        \n";
+    let mbox = [
+        cover_letter.to_owned(),
+        email(
+            "Jane Doe <jane@example.com>",
+            "Tue, 2 Jan 2024 10:00:00 +0100",
+            "[PATCH 1/2] Add c",
+            "With details.\n",
+            ADD_C,
+        ),
+        email(
+            "\"John Roe\" <john@example.com>",
+            "Wed, 3 Jan 2024 10:00:00 +0000",
+            "[PATCH 2/2] Change c",
+            "",
+            CHANGE_C,
+        ),
+    ]
+    .concat();
+
+    let outcome = test
+        .controller
+        .import_patch(
+            &test.project,
+            branch_id,
+            mbox.as_bytes(),
+            &ImportPatchOptions::default(),
+        )
+        .unwrap();
+    assert_eq!(outcome.commits.len(), 2, "the cover letter is no commit");
+    assert_eq!(
+        fs::read_to_string(test.repository.path().join("c.txt")).unwrap(),
+        "d\n"
+    );
+
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    let first = repo.find_commit(outcome.commits[0]).unwrap();
+    assert_eq!(first.message(), Some("Add c\n\nWith details.\n"));
+    assert_eq!(first.author().name(), Some("Jane Doe"));
+    assert_eq!(first.author().email(), Some("jane@example.com"));
+    assert_eq!(first.author().when().seconds(), 1704186000);
+    assert_eq!(first.author().when().offset_minutes(), 60);
+    let second = repo.find_commit(outcome.commits[1]).unwrap();
+    assert_eq!(second.message(), Some("Change c"));
+    assert_eq!(second.author().name(), Some("John Roe"));
+    assert_eq!(second.parent_id(0).unwrap(), first.id());
+}
+
+#[test]
+fn reports_patches_that_do_not_apply() {
+    let test = Test::default();
+    let branch_id = applied_branch(&test);
+    let mbox = [
+        email(
+            "Jane Doe <jane@example.com>",
+            "Tue, 2 Jan 2024 10:00:00 +0100",
+            "[PATCH 1/2] Change c",
+            "",
+            CHANGE_C,
+        ),
+        email(
+            "Jane Doe <jane@example.com>",
+            "Tue, 2 Jan 2024 10:00:00 +0100",
+            "[PATCH 2/2] Add c",
+            "",
+            ADD_C,
+        ),
+    ]
+    .concat();
+
+    let outcome = test
+        .controller
+        .import_patch(
+            &test.project,
+            branch_id,
+            mbox.as_bytes(),
+            &ImportPatchOptions {
+                three_way: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(outcome.commits.is_empty());
+    assert_eq!(outcome.skipped, 1);
+    let PatchConflict { subject, paths, .. } = outcome.conflict.unwrap();
+    assert_eq!(subject, "Change c");
+    assert_eq!(paths, [path::PathBuf::from("c.txt")]);
+    assert!(!test.repository.path().join("c.txt").exists());
+}
+
+#[test]
+fn rejects_input_without_changes() {
+    let test = Test::default();
+    let branch_id = applied_branch(&test);
+
+    let err = test
+        .controller
+        .import_patch(
+            &test.project,
+            branch_id,
+            b"just some text\n",
+            &ImportPatchOptions::default(),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+}
@@ -0,0 +1,113 @@
+use gitbutler_branch::{BranchId, BranchUpdateRequest};
+use gitbutler_branch_actions::PatchFormat;
+
+use super::*;
+
+/// Create a branch named `feature` with two commits, described as `description`.
+fn branch_with_commits(test: &Test, description: &str) -> BranchId {
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(
+            &test.project,
+            &BranchCreateRequest {
+                name: Some("feature".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    for (file, message) in [
+        ("a.txt", "Add a\n\nWith details."),
+        ("b.txt", "Add b: now!"),
+    ] {
+        fs::write(test.repository.path().join(file), file).unwrap();
+        test.controller
+            .create_commit(&test.project, branch_id, message, None, false)
+            .unwrap();
+    }
+    test.controller
+        .update_virtual_branch(
+            &test.project,
+            BranchUpdateRequest {
+                id: branch_id,
+                description: Some(description.into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    branch_id
+}
+
+#[test]
+fn one_file_per_patch_with_cover_letter() {
+    let test = Test::default();
+    let branch_id = branch_with_commits(&test, "Adds a and b.");
+    let dir = TempDir::new().unwrap();
+
+    let files = test
+        .controller
+        .export_patches(&test.project, branch_id, dir.path(), PatchFormat::Files)
+        .unwrap();
+    let names: Vec<_> = files
+        .iter()
+        .map(|path| path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "0000-cover-letter.patch",
+            "0001-Add-a.patch",
+            "0002-Add-b-now.patch"
+        ]
+    );
+
+    let cover = fs::read_to_string(&files[0]).unwrap();
+    assert!(cover.contains("Subject: [PATCH 0/2] feature\n"), "{cover}");
+    assert!(cover.contains("\nAdds a and b.\n"), "{cover}");
+    assert!(cover.contains("  Add a\n  Add b: now!\n"), "{cover}");
+
+    let first = fs::read_to_string(&files[1]).unwrap();
+    assert!(first.contains("Subject: [PATCH 1/2] Add a\n"), "{first}");
+    assert!(first.contains("With details."), "{first}");
+    assert!(first.contains("+++ b/a.txt"), "{first}");
+    let second = fs::read_to_string(&files[2]).unwrap();
+    assert!(
+        second.contains("Subject: [PATCH 2/2] Add b: now!\n"),
+        "{second}"
+    );
+    assert!(
+        !second.contains("a.txt"),
+        "each patch has the changes of its commit"
+    );
+}
+
+#[test]
+fn mbox_without_description() {
+    let test = Test::default();
+    let branch_id = branch_with_commits(&test, "");
+    let dir = TempDir::new().unwrap();
+
+    let files = test
+        .controller
+        .export_patches(&test.project, branch_id, dir.path(), PatchFormat::Mbox)
+        .unwrap();
+    assert_eq!(files, [dir.path().join("feature.mbox")]);
+    let mbox = fs::read_to_string(&files[0]).unwrap();
+    let subjects: Vec<_> = mbox
+        .lines()
+        .filter(|line| line.starts_with("Subject: "))
+        .collect();
+    assert_eq!(
+        subjects,
+        [
+            "Subject: [PATCH 1/2] Add a",
+            "Subject: [PATCH 2/2] Add b: now!"
+        ],
+        "there is no cover letter without a description"
+    );
+}
@@ -0,0 +1,76 @@
+use super::*;
+
+#[test]
+fn pin_to_older_commit_and_rebase_onto_target_again() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("file.txt"), "release").unwrap();
+    let release = repository.commit_all("release");
+    fs::write(repository.path().join("other.txt"), "main").unwrap();
+    let main = repository.commit_all("main");
+    repository.push();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("feature.txt"), "feature").unwrap();
+    controller
+        .create_commit(project, branch_id, "feature", None, false)
+        .unwrap();
+
+    controller
+        .pin_branch_base(project, branch_id, release)
+        .unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].pinned_base, Some(release));
+    assert_eq!(branches[0].commits.len(), 1);
+    assert_eq!(branches[0].commits[0].parent_ids, vec![release]);
+    assert!(branches[0].files.is_empty());
+    assert!(
+        repository.path().join("other.txt").exists(),
+        "the workspace still holds the target"
+    );
+    assert!(repository.path().join("feature.txt").exists());
+
+    controller
+        .rebase_branch_onto_target(project, branch_id)
+        .unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].pinned_base, None);
+    assert_eq!(branches[0].commits.len(), 1);
+    assert_eq!(branches[0].commits[0].parent_ids, vec![main]);
+    assert!(branches[0].files.is_empty());
+}
+
+#[test]
+fn rebase_onto_target_requires_pinned_base() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    assert!(controller
+        .rebase_branch_onto_target(project, branch_id)
+        .is_err());
+}
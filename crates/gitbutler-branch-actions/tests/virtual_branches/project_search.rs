@@ -0,0 +1,140 @@
+use gitbutler_branch_actions::SearchLocation;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+/// Add another project with a workspace to `test`, so searches have to tell them apart.
+fn add_other_project(test: &Test) -> (TestProject, Project) {
+    let repository = TestProject::default();
+    let project = test.projects.add(repository.path()).unwrap();
+    test.controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    (repository, project)
+}
+
+#[test]
+fn commits_are_found_in_the_project_that_has_them() {
+    let test = Test::default();
+    let (_other_repository, _other_project) = add_other_project(&test);
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(
+            &test.project,
+            &BranchCreateRequest {
+                name: Some("the-branch".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "content\n").unwrap();
+    let commit_id = test
+        .controller
+        .create_commit(&test.project, branch_id, "the commit", None, false)
+        .unwrap();
+
+    let matches = test
+        .controller
+        .search_projects(&test.projects.list().unwrap(), &commit_id.to_string()[..10])
+        .unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].project_id, test.project_id);
+    let SearchLocation::Commit {
+        commit_id: found,
+        summary,
+        branches,
+    } = &matches[0].location
+    else {
+        panic!("expected a commit, got {:?}", matches[0].location);
+    };
+    assert_eq!(*found, commit_id);
+    assert_eq!(summary, "the commit");
+    assert_eq!(branches, &["the-branch"]);
+}
+
+#[test]
+fn branches_and_changes_are_found_by_their_name_and_content() {
+    let test = Test::default();
+    let (_other_repository, _other_project) = add_other_project(&test);
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(
+            &test.project,
+            &BranchCreateRequest {
+                name: Some("Search-Feature".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(
+        test.repository.path().join("file.txt"),
+        "first\nlet needle = 1;\n",
+    )
+    .unwrap();
+    test.controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+
+    let projects = test.projects.list().unwrap();
+    let matches = test
+        .controller
+        .search_projects(&projects, "search-feat")
+        .unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].project_id, test.project_id);
+    assert_eq!(
+        matches[0].location,
+        SearchLocation::VirtualBranch {
+            branch_id,
+            name: "Search-Feature".into()
+        }
+    );
+
+    let matches = test
+        .controller
+        .search_projects(&projects, "NEEDLE")
+        .unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].project_id, test.project_id);
+    assert_eq!(
+        matches[0].location,
+        SearchLocation::Change {
+            branch_id,
+            branch_name: "Search-Feature".into(),
+            commit_id: None,
+            path: "file.txt".into(),
+            line: "let needle = 1;".into(),
+        }
+    );
+
+    assert!(test
+        .controller
+        .search_projects(&projects, "haystack")
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn empty_searches_are_rejected() {
+    let test = Test::default();
+    let err = test
+        .controller
+        .search_projects(&test.projects.list().unwrap(), "  ")
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+}
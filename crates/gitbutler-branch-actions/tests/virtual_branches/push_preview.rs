@@ -0,0 +1,58 @@
+use gitbutler_branch::BranchCreateRequest;
+
+use super::*;
+
+#[test]
+fn counts_what_the_remote_does_not_have() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::write(repository.path().join("preview.txt"), "first preview").unwrap();
+    controller
+        .create_commit(project, branch_id, "first", None, false)
+        .unwrap();
+    fs::write(repository.path().join("preview.txt"), "second preview").unwrap();
+    controller
+        .create_commit(project, branch_id, "second", None, false)
+        .unwrap();
+
+    let preview = controller.push_preview(project, branch_id).unwrap();
+    assert!(!preview.remote_branch_exists);
+    assert!(!preview.requires_force);
+    assert_eq!(preview.commits, 2);
+    assert_eq!(
+        preview.objects, 6,
+        "a commit, a tree and a blob for each commit"
+    );
+    assert!(preview.size_bytes > 0);
+
+    controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap();
+    let preview = controller.push_preview(project, branch_id).unwrap();
+    assert!(preview.remote_branch_exists);
+    assert_eq!(preview.commits, 0);
+    assert_eq!(preview.objects, 0);
+    assert_eq!(preview.size_bytes, 0);
+
+    fs::write(repository.path().join("preview.txt"), "third preview").unwrap();
+    controller
+        .create_commit(project, branch_id, "third", None, false)
+        .unwrap();
+    let preview = controller.push_preview(project, branch_id).unwrap();
+    assert!(!preview.requires_force);
+    assert_eq!(preview.commits, 1);
+    assert_eq!(preview.objects, 3);
+}
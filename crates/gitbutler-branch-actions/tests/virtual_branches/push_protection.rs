@@ -0,0 +1,84 @@
+use gitbutler_branch::{BranchCreateRequest, BranchId};
+use gitbutler_branch_actions::{ProtectionSource, PushWarning};
+
+use super::*;
+
+fn branch_with_commit(test: &Test, name: &str) -> (BranchId, git2::Oid) {
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(
+            &test.project,
+            &BranchCreateRequest {
+                name: Some(name.into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "content\n").unwrap();
+    let commit_id = test
+        .controller
+        .create_commit(&test.project, branch_id, "commit", None, false)
+        .unwrap();
+    (branch_id, commit_id)
+}
+
+#[test]
+fn rules_are_read_from_git_config_without_a_forge() {
+    let test = Test::default();
+    let (branch_id, commit_id) = branch_with_commit(&test, "release-1");
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    let mut config = repo.config().unwrap();
+    config
+        .set_multivar("gitbutler.protectedBranch", "^$", "main")
+        .unwrap();
+    config
+        .set_multivar("gitbutler.protectedBranch", "^$", "release-*")
+        .unwrap();
+    config
+        .set_bool("gitbutler.requireSignedCommits", true)
+        .unwrap();
+
+    let check = test
+        .controller
+        .check_push_protection(&test.project, branch_id, None)
+        .unwrap();
+    assert_eq!(check.source, ProtectionSource::Config);
+    assert_eq!(check.remote_branch.branch(), "release-1");
+    assert!(check.protection.protected);
+    assert!(check.protection.requires_signed_commits);
+    assert!(!check.protection.requires_linear_history);
+    assert_eq!(
+        check.warnings,
+        [
+            PushWarning::Protected,
+            PushWarning::UnsignedCommits {
+                commits: vec![commit_id],
+                signing_enabled: false,
+            }
+        ]
+    );
+}
+
+#[test]
+fn unprotected_branches_have_no_warnings() {
+    let test = Test::default();
+    let (branch_id, _) = branch_with_commit(&test, "feature");
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_multivar("gitbutler.protectedBranch", "^$", "release-*")
+        .unwrap();
+
+    let check = test
+        .controller
+        .check_push_protection(&test.project, branch_id, None)
+        .unwrap();
+    assert!(!check.protection.protected);
+    assert!(check.warnings.is_empty());
+}
@@ -0,0 +1,252 @@
+use gitbutler_branch::GITBUTLER_INTEGRATION_REFERENCE;
+use gitbutler_branch_actions::{ExternalChangeKind, FoldBlocker, Reconciliation, RecoveryOption};
+
+use super::*;
+
+fn workspace_with_a_branch(test: &Test) -> gitbutler_branch::BranchId {
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    test.controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap()
+}
+
+fn detected(reconciliation: Reconciliation) -> (ExternalChangeKind, Vec<RecoveryOption>) {
+    match reconciliation {
+        Reconciliation::Detected { change } => (change.kind, change.recovery),
+        other => panic!("expected a detected change, got {other:?}"),
+    }
+}
+
+#[test]
+fn commits_on_the_integration_branch_are_folded_into_the_selected_branch() {
+    let test = Test::default();
+    let branch_id = workspace_with_a_branch(&test);
+    assert_eq!(
+        test.controller
+            .reconcile_external_changes(&test.project)
+            .unwrap(),
+        Reconciliation::Unchanged
+    );
+
+    fs::write(test.repository.path().join("file.txt"), "content\n").unwrap();
+    test.repository.commit_all("committed in a terminal");
+    let Reconciliation::Folded {
+        branch_id: folded_into,
+        commits,
+    } = test
+        .controller
+        .reconcile_external_changes(&test.project)
+        .unwrap()
+    else {
+        panic!("the commit should have been folded");
+    };
+    assert_eq!(folded_into, branch_id);
+    assert_eq!(commits.len(), 1);
+
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    assert_eq!(branches[0].id, branch_id);
+    assert_eq!(branches[0].head, commits[0]);
+    assert_eq!(
+        branches[0].commits[0].description,
+        "committed in a terminal"
+    );
+    assert!(branches[0].files.is_empty());
+    assert_eq!(
+        test.controller
+            .reconcile_external_changes(&test.project)
+            .unwrap(),
+        Reconciliation::Unchanged
+    );
+    assert!(test
+        .controller
+        .check_workspace(&test.project)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn merge_commits_are_reported() {
+    let test = Test::default();
+    workspace_with_a_branch(&test);
+
+    // Merge another history into the integration branch, like `git pull` would.
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    let integration = repo
+        .find_reference(&GITBUTLER_INTEGRATION_REFERENCE.to_string())
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    let signature = git2::Signature::now("test", "test@example.com").unwrap();
+    let other = repo
+        .commit(
+            None,
+            &signature,
+            &signature,
+            "other",
+            &integration.tree().unwrap(),
+            &[],
+        )
+        .unwrap();
+    let merge = repo
+        .commit(
+            Some(&GITBUTLER_INTEGRATION_REFERENCE.to_string()),
+            &signature,
+            &signature,
+            "merge",
+            &integration.tree().unwrap(),
+            &[&integration, &repo.find_commit(other).unwrap()],
+        )
+        .unwrap();
+
+    let (kind, recovery) = detected(
+        test.controller
+            .reconcile_external_changes(&test.project)
+            .unwrap(),
+    );
+    assert_eq!(
+        kind,
+        ExternalChangeKind::CommitsOnWorkspace {
+            commits: vec![merge],
+            blocker: FoldBlocker::MergeCommit { commit: merge },
+        }
+    );
+    assert_eq!(
+        recovery,
+        [
+            RecoveryOption::MoveToNewBranch,
+            RecoveryOption::RebuildWorkspace
+        ]
+    );
+
+    test.controller
+        .recover_from_external_change(&test.project, RecoveryOption::RebuildWorkspace)
+        .unwrap();
+    assert_eq!(
+        test.controller
+            .reconcile_external_changes(&test.project)
+            .unwrap(),
+        Reconciliation::Unchanged
+    );
+}
+
+#[test]
+fn commits_without_a_branch_to_fold_into_can_be_moved_to_a_new_branch() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+
+    fs::write(test.repository.path().join("file.txt"), "content\n").unwrap();
+    let commit = test.repository.commit_all("committed in a terminal");
+    let (kind, _) = detected(
+        test.controller
+            .reconcile_external_changes(&test.project)
+            .unwrap(),
+    );
+    assert_eq!(
+        kind,
+        ExternalChangeKind::CommitsOnWorkspace {
+            commits: vec![commit],
+            blocker: FoldBlocker::NoAppliedBranch,
+        }
+    );
+
+    test.controller
+        .recover_from_external_change(&test.project, RecoveryOption::MoveToNewBranch)
+        .unwrap();
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].commits.len(), 1);
+    assert_eq!(
+        test.controller
+            .reconcile_external_changes(&test.project)
+            .unwrap(),
+        Reconciliation::Unchanged
+    );
+}
+
+#[test]
+fn leaving_the_workspace_is_reported_until_it_is_rebuilt() {
+    let test = Test::default();
+    workspace_with_a_branch(&test);
+
+    test.repository
+        .checkout(&"refs/heads/other".parse().unwrap());
+    let (kind, recovery) = detected(
+        test.controller
+            .reconcile_external_changes(&test.project)
+            .unwrap(),
+    );
+    assert_eq!(
+        kind,
+        ExternalChangeKind::LeftWorkspace {
+            head: "refs/heads/other".into()
+        }
+    );
+    assert_eq!(recovery, [RecoveryOption::RebuildWorkspace]);
+
+    test.controller
+        .recover_from_external_change(&test.project, RecoveryOption::RebuildWorkspace)
+        .unwrap();
+    assert_eq!(
+        test.controller
+            .reconcile_external_changes(&test.project)
+            .unwrap(),
+        Reconciliation::Unchanged
+    );
+}
+
+#[test]
+fn resetting_the_integration_branch_is_reported() {
+    let test = Test::default();
+    workspace_with_a_branch(&test);
+
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    let integration = repo
+        .find_reference(&GITBUTLER_INTEGRATION_REFERENCE.to_string())
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    let parent = integration.parent_id(0).unwrap();
+    repo.reference(
+        &GITBUTLER_INTEGRATION_REFERENCE.to_string(),
+        parent,
+        true,
+        "reset",
+    )
+    .unwrap();
+
+    let (kind, _) = detected(
+        test.controller
+            .reconcile_external_changes(&test.project)
+            .unwrap(),
+    );
+    assert_eq!(
+        kind,
+        ExternalChangeKind::IntegrationMoved { commit: parent }
+    );
+
+    test.controller
+        .recover_from_external_change(&test.project, RecoveryOption::RebuildWorkspace)
+        .unwrap();
+    assert_eq!(
+        test.controller
+            .reconcile_external_changes(&test.project)
+            .unwrap(),
+        Reconciliation::Unchanged
+    );
+}
@@ -0,0 +1,102 @@
+use super::*;
+
+fn commit_onto(repo: &git2::Repository, parent: git2::Oid, message: &str) -> git2::Oid {
+    let parent = repo.find_commit(parent).unwrap();
+    let signature = git2::Signature::now("author", "author@example.com").unwrap();
+    repo.commit(
+        None,
+        &signature,
+        &signature,
+        message,
+        &parent.tree().unwrap(),
+        &[&parent],
+    )
+    .unwrap()
+}
+
+#[test]
+fn counts_commits_ahead_and_behind_the_target() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("file.txt"), "one").unwrap();
+    let first = repository.commit_all("first");
+    fs::write(repository.path().join("file.txt"), "two").unwrap();
+    let second = repository.commit_all("second");
+    repository.push();
+    repository.fetch();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let feature = commit_onto(&repo, second, "feature one");
+    let feature = commit_onto(&repo, feature, "feature two");
+    let forked = commit_onto(&repo, first, "forked");
+    for (name, id) in [
+        ("refs/remotes/origin/feature", feature),
+        ("refs/remotes/origin/stale", first),
+        ("refs/remotes/origin/forked", forked),
+    ] {
+        repo.reference(name, id, true, "").unwrap();
+    }
+
+    let counts = |project: &Project| {
+        let mut activity = controller.list_remote_branch_activity(project).unwrap();
+        activity.sort_by_key(|branch| branch.name.to_string());
+        activity
+            .into_iter()
+            .map(|branch| (branch.name.to_string(), branch.ahead, branch.behind))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        counts(project),
+        [
+            ("refs/remotes/origin/feature".to_string(), 2, 0),
+            ("refs/remotes/origin/forked".to_string(), 1, 1),
+            ("refs/remotes/origin/stale".to_string(), 0, 1),
+        ],
+        "the target branch itself isn't listed"
+    );
+
+    let feature = commit_onto(&repo, feature, "feature three");
+    repo.reference("refs/remotes/origin/feature", feature, true, "")
+        .unwrap();
+    assert_eq!(
+        counts(project)[0],
+        ("refs/remotes/origin/feature".to_string(), 3, 0),
+        "branches that moved are counted again"
+    );
+}
+
+#[test]
+fn has_last_commit_metadata() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let head = repo.refname_to_id("refs/remotes/origin/master").unwrap();
+    let feature = commit_onto(&repo, head, "feature");
+    repo.reference("refs/remotes/origin/feature", feature, true, "")
+        .unwrap();
+
+    let activity = controller.list_remote_branch_activity(project).unwrap();
+    assert_eq!(activity.len(), 1);
+    let branch = &activity[0];
+    assert_eq!(branch.sha, feature);
+    assert_eq!(branch.last_commit_author.name, "author");
+    assert_eq!(branch.last_commit_author.email, "author@example.com");
+    let time = repo.find_commit(feature).unwrap().time().seconds();
+    assert_eq!(branch.last_commit_timestamp_ms, time as u128 * 1000);
+}
@@ -0,0 +1,118 @@
+use gitbutler_branch::{BranchCreateRequest, BranchUpdateRequest};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_project::{RemoteBranchNames, Settings};
+
+use super::*;
+
+fn with_policy(test: &Test) -> Project {
+    let settings = Settings {
+        remote_branch_names: RemoteBranchNames {
+            template: "{user}/{normalized-title}".into(),
+            forbidden_characters: "#".into(),
+            max_length: Some(30),
+        },
+        ..test.project.settings.clone()
+    };
+    test.projects
+        .update_settings(test.project.id, settings)
+        .unwrap();
+    let project = test.projects.get(test.project.id).unwrap();
+    test.controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    project
+}
+
+fn branch_with_commit(test: &Test, project: &Project, name: &str) -> gitbutler_branch::BranchId {
+    let branch_id = test
+        .controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some(name.into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(
+        test.repository.path().join(format!("{name}.txt")),
+        "content",
+    )
+    .unwrap();
+    test.controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+    branch_id
+}
+
+#[test]
+fn remote_branches_are_named_after_the_template_of_the_project() {
+    let test = Test::default();
+    let project = with_policy(&test);
+
+    let first = branch_with_commit(&test, &project, "fix #42 login crash");
+    let preview = test.controller.push_preview(&project, first).unwrap();
+    assert_eq!(
+        preview.remote_branch.branch(),
+        "gitbutler-test/fix-42-login-cr",
+        "the user is prefixed, forbidden characters are replaced and the name is shortened"
+    );
+    test.controller
+        .push_virtual_branch(&project, first, false, None)
+        .unwrap();
+
+    // The next name would collide, and the suffix that makes it unique would make it too long.
+    let second = branch_with_commit(&test, &project, "fix #42 login crashes");
+    let preview = test.controller.push_preview(&project, second).unwrap();
+    assert_eq!(
+        preview.remote_branch.branch(),
+        "gitbutler-test/fix-42-login"
+    );
+}
+
+#[test]
+fn upstream_names_given_by_hand_are_validated() {
+    let test = Test::default();
+    let project = with_policy(&test);
+    let branch_id = branch_with_commit(&test, &project, "feature");
+
+    for (name, violation) in [
+        ("feature", "doesn't start with 'gitbutler-test/'"),
+        ("gitbutler-test/fix#42", "the forbidden character '#'"),
+        (
+            "gitbutler-test/a-feature-with-a-long-name",
+            "has 41 characters, more than the maximum of 30",
+        ),
+    ] {
+        let err = test
+            .controller
+            .update_virtual_branch(
+                &project,
+                BranchUpdateRequest {
+                    id: branch_id,
+                    upstream: Some(name.into()),
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation)
+        );
+        assert!(err.to_string().contains(violation), "{err:#}");
+    }
+
+    test.controller
+        .validate_remote_branch_name(&project, "gitbutler-test/feature")
+        .unwrap();
+    test.controller
+        .update_virtual_branch(
+            &project,
+            BranchUpdateRequest {
+                id: branch_id,
+                upstream: Some("gitbutler-test/feature".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+}
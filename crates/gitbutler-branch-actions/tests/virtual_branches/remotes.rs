@@ -0,0 +1,136 @@
+use super::*;
+
+/// Add an empty remote named `name` to the repository of `repository`, and return it.
+fn add_fork(repository: &TestProject, name: &str) -> (TempDir, git2::Repository) {
+    let dir = TempDir::new().unwrap();
+    let fork = git2::Repository::init_bare(dir.path()).unwrap();
+    git2::Repository::open(repository.path())
+        .unwrap()
+        .remote(name, dir.path().to_str().unwrap())
+        .unwrap();
+    (dir, fork)
+}
+
+#[test]
+fn push_to_push_remote_of_branch() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let (_dir, fork) = add_fork(repository, "fork");
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some("feature".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+
+    controller
+        .set_branch_push_remote(project, branch_id, Some("fork"))
+        .unwrap();
+    controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap();
+
+    assert_eq!(fork.refname_to_id("refs/heads/feature").unwrap(), commit_id);
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].push_remote_name.as_deref(), Some("fork"));
+    assert_eq!(
+        branches[0].upstream.as_ref().unwrap().name.to_string(),
+        "refs/remotes/fork/feature"
+    );
+
+    controller
+        .set_branch_push_remote(project, branch_id, None)
+        .unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert!(
+        branches[0].upstream.is_none(),
+        "the upstream on the previous push remote is forgotten"
+    );
+}
+
+#[test]
+fn push_remote_must_exist() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    assert!(controller
+        .set_branch_push_remote(project, branch_id, Some("missing"))
+        .is_err());
+}
+
+#[test]
+fn rename_remote_of_target() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    controller
+        .rename_remote(project, "origin", "upstream")
+        .unwrap();
+
+    let base = VirtualBranchActions::get_base_branch_data(project).unwrap();
+    assert_eq!(base.remote_name, "upstream");
+    assert_eq!(base.branch_name, "upstream/master");
+}
+
+#[test]
+fn remove_remote() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let (_dir, _fork) = add_fork(repository, "fork");
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    controller
+        .set_branch_push_remote(project, branch_id, Some("fork"))
+        .unwrap();
+
+    assert!(
+        controller.remove_remote(project, "origin").is_err(),
+        "the target is fetched from origin"
+    );
+    controller.remove_remote(project, "fork").unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].push_remote_name, None);
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    assert!(repo.find_remote("fork").is_err());
+}
@@ -0,0 +1,58 @@
+use gitbutler_branch::BranchCreateRequest;
+
+use super::*;
+
+#[test]
+fn ownership_follows_renamed_file() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("old.txt"), "one\ntwo\nthree\nfour\n").unwrap();
+    repository.commit_all("add old.txt");
+    repository.push();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let owner_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("old.txt"), "one\ntwo\nthree\n4\n").unwrap();
+    controller.list_virtual_branches(project).unwrap();
+
+    let other_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                selected_for_changes: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::rename(
+        repository.path().join("old.txt"),
+        repository.path().join("new.txt"),
+    )
+    .unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let owner = branches.iter().find(|b| b.id == owner_id).unwrap();
+    let mut paths: Vec<_> = owner
+        .files
+        .iter()
+        .map(|file| file.path.display().to_string())
+        .collect();
+    paths.sort();
+    assert_eq!(paths, ["new.txt", "old.txt"]);
+
+    let other = branches.iter().find(|b| b.id == other_id).unwrap();
+    assert!(
+        other.files.is_empty(),
+        "the renamed file isn't a new change of the selected branch"
+    );
+}
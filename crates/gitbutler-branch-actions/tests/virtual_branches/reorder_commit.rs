@@ -111,3 +111,100 @@ fn reorder_commit_up() {
 
     assert_eq!(descriptions, vec!["commit one", "commit two"]);
 }
+
+#[test]
+fn reorder_commits_into_new_order() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit1_id = controller
+        .create_commit(project, branch_id, "commit one", None, false)
+        .unwrap();
+
+    fs::write(repository.path().join("file2.txt"), "content2").unwrap();
+    let commit2_id = controller
+        .create_commit(project, branch_id, "commit two", None, false)
+        .unwrap();
+
+    let outcome = controller
+        .reorder_commits(project, branch_id, &[commit1_id, commit2_id])
+        .unwrap();
+    assert!(outcome.conflicts.is_empty());
+
+    let branch = controller
+        .list_virtual_branches(project)
+        .unwrap()
+        .0
+        .into_iter()
+        .find(|b| b.id == branch_id)
+        .unwrap();
+
+    assert_eq!(Some(branch.head), outcome.head);
+    let descriptions = branch
+        .commits
+        .iter()
+        .map(|c| c.description.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(descriptions, vec!["commit one", "commit two"]);
+}
+
+#[test]
+fn reorder_commits_reports_conflicts_and_keeps_branch() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "first").unwrap();
+    let commit1_id = controller
+        .create_commit(project, branch_id, "commit one", None, false)
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "second").unwrap();
+    let commit2_id = controller
+        .create_commit(project, branch_id, "commit two", None, false)
+        .unwrap();
+
+    let outcome = controller
+        .reorder_commits(project, branch_id, &[commit1_id, commit2_id])
+        .unwrap();
+    assert_eq!(outcome.head, None, "nothing was changed");
+    assert_eq!(outcome.conflicts[0].commit_id, commit2_id);
+    assert_eq!(outcome.conflicts[0].paths, vec![PathBuf::from("file.txt")]);
+
+    let branch = controller
+        .list_virtual_branches(project)
+        .unwrap()
+        .0
+        .into_iter()
+        .find(|b| b.id == branch_id)
+        .unwrap();
+    assert_eq!(branch.head, commit2_id);
+
+    controller
+        .reorder_commits(project, branch_id, &[commit1_id])
+        .unwrap_err();
+}
@@ -0,0 +1,56 @@
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_repo::{repo_state::GitOperation, RepoCommands};
+
+use super::*;
+
+#[test]
+fn workspace_changes_are_blocked_until_a_merge_in_progress_is_aborted() {
+    let test = Test::default();
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap().id();
+    fs::write(repo.path().join("MERGE_HEAD"), format!("{head}\n")).unwrap();
+
+    let operation = test.project.git_operation_in_progress().unwrap().unwrap();
+    assert_eq!(operation.operation, GitOperation::Merge);
+    assert_eq!(operation.head, Some(head));
+    assert!(operation.can_continue);
+
+    fs::write(test.repository.path().join("file.txt"), "content\n").unwrap();
+    let err = test
+        .controller
+        .create_commit(&test.project, branch_id, "commit", None, false)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::ProjectStateInProgress)
+    );
+
+    test.project.abort_git_operation().unwrap();
+    assert_eq!(test.project.git_operation_in_progress().unwrap(), None);
+    fs::write(test.repository.path().join("file.txt"), "content\n").unwrap();
+    test.controller
+        .create_commit(&test.project, branch_id, "commit", None, false)
+        .unwrap();
+}
+
+#[test]
+fn nothing_to_abort_without_an_operation_in_progress() {
+    let test = Test::default();
+    assert_eq!(test.project.git_operation_in_progress().unwrap(), None);
+    let err = test.project.abort_git_operation().unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+}
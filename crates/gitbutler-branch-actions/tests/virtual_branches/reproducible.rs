@@ -0,0 +1,56 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use gitbutler_id::generator::{use_ids_in_thread, SequentialIds};
+use gitbutler_time::clock::{use_clock_in_thread, SteppingClock};
+
+use super::*;
+
+const START: u64 = 1_700_000_000;
+
+#[test]
+fn branches_and_commits_use_the_injected_clock_and_ids() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let _clock = use_clock_in_thread(SteppingClock::new(
+        UNIX_EPOCH + Duration::from_secs(START),
+        Duration::from_secs(1),
+    ));
+    let _ids = use_ids_in_thread(SequentialIds::default());
+
+    let first = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    let second = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    assert!(first.to_string().starts_with("00000000-0000-0000-0000-"));
+    assert!(first < second, "ids count up");
+
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, first, "commit", None, false)
+        .unwrap();
+    let commit = repository.find_commit(commit_id).unwrap();
+    for time in [commit.author().when(), commit.committer().when()] {
+        let seconds = u64::try_from(time.seconds()).unwrap();
+        assert!(
+            (START..START + 10_000).contains(&seconds),
+            "{seconds} is the time of the injected clock"
+        );
+        assert_eq!(time.offset_minutes(), 0);
+    }
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let branch = branches.iter().find(|branch| branch.id == first).unwrap();
+    assert!(
+        (u128::from(START) * 1000..u128::from(START + 10_000) * 1000).contains(&branch.updated_at)
+    );
+}
@@ -0,0 +1,88 @@
+use gitbutler_branch_actions::conflicts::{ConflictSide, Resolution};
+
+use super::*;
+
+#[test]
+fn resolve_with_manual_content_and_finalize() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    {
+        fs::write(repository.path().join("file.txt"), "first").unwrap();
+        let first_commit_oid = repository.commit_all("first");
+        fs::write(repository.path().join("file.txt"), "second").unwrap();
+        repository.commit_all("second");
+        repository.push();
+        repository.reset_hard(Some(first_commit_oid));
+    }
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "conflict").unwrap();
+
+    assert!(
+        controller
+            .list_conflicted_files(project)
+            .unwrap()
+            .is_empty(),
+        "nothing conflicts yet"
+    );
+
+    let unapplied_branch = {
+        let unapplied_branches = controller.update_base_branch(project).unwrap();
+        Refname::from_str(&unapplied_branches[0]).unwrap()
+    };
+    let branch_id = controller
+        .create_virtual_branch_from_branch(project, &unapplied_branch, None)
+        .unwrap();
+
+    let files = controller.list_conflicted_files(project).unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, PathBuf::from("file.txt"));
+
+    let path = path::Path::new("file.txt");
+    let blob = |side| {
+        controller
+            .conflicted_file_blob(project, path, side)
+            .unwrap()
+            .map(|blob| String::from_utf8(blob).unwrap())
+    };
+    assert_eq!(blob(ConflictSide::Base).as_deref(), Some("first"));
+    assert_eq!(blob(ConflictSide::Ours).as_deref(), Some("conflict"));
+    assert_eq!(blob(ConflictSide::Theirs).as_deref(), Some("second"));
+
+    controller
+        .resolve_conflict(
+            project,
+            path,
+            Resolution::Manual(b"resolved\0\xff".to_vec()),
+        )
+        .unwrap();
+    assert_eq!(
+        fs::read(repository.path().join("file.txt")).unwrap(),
+        b"resolved\0\xff",
+        "binary content is written as is"
+    );
+    assert!(controller
+        .list_conflicted_files(project)
+        .unwrap()
+        .is_empty());
+
+    let commit_oid = controller
+        .finalize_conflict_resolution(project, branch_id, "resolution")
+        .unwrap();
+    let commit = repository.find_commit(commit_oid).unwrap();
+    assert_eq!(commit.parent_count(), 2);
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert!(!branches[0].conflicted);
+}
@@ -0,0 +1,96 @@
+use gitbutler_branch_actions::conflicts::Resolution;
+
+use super::*;
+
+#[test]
+fn commits_the_inverse_of_a_commit() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "add file", None, false)
+        .unwrap();
+
+    let outcome = controller
+        .revert_commit(project, branch_id, commit_id)
+        .unwrap();
+    assert_eq!(
+        outcome.message,
+        format!("Revert \"add file\"\n\nThis reverts commit {commit_id}.\n")
+    );
+    let revert_id = outcome.commit.expect("reverting doesn't conflict");
+    let revert = repository.find_commit(revert_id).unwrap();
+    assert_eq!(revert.message(), Some(outcome.message.as_str()));
+    assert_eq!(revert.parent_id(0).unwrap(), commit_id);
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].commits.len(), 2);
+    assert_eq!(branches[0].head, revert_id);
+    assert!(branches[0].files.is_empty());
+    assert!(!repository.path().join("file.txt").exists());
+}
+
+#[test]
+fn conflicting_revert_is_resolved_with_the_conflict_api() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("file.txt"), "base").unwrap();
+    repository.commit_all("base");
+    repository.push();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "one").unwrap();
+    let reverted = controller
+        .create_commit(project, branch_id, "one", None, false)
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "two").unwrap();
+    controller
+        .create_commit(project, branch_id, "two", None, false)
+        .unwrap();
+
+    let outcome = controller
+        .revert_commit(project, branch_id, reverted)
+        .unwrap();
+    assert_eq!(outcome.commit, None);
+    let files = controller.list_conflicted_files(project).unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, PathBuf::from("file.txt"));
+
+    controller
+        .resolve_conflict(project, path::Path::new("file.txt"), Resolution::Theirs)
+        .unwrap();
+    let commit_id = controller
+        .finalize_conflict_resolution(project, branch_id, &outcome.message)
+        .unwrap();
+    let commit = repository.find_commit(commit_id).unwrap();
+    assert_eq!(commit.parent_count(), 1, "reverts aren't merges");
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "base"
+    );
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].commits.len(), 3);
+    assert!(!branches[0].conflicted);
+    assert!(branches[0].files.is_empty());
+}
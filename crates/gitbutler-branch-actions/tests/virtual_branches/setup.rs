@@ -0,0 +1,91 @@
+use gitbutler_branch_actions::{BranchImport, RemoteAccess};
+
+use super::*;
+
+/// Create the local branch `name` with a commit that adds `path` with `content`, and check out
+/// `master` again.
+fn branch_with_commit(repository: &TestProject, name: &str, path: &str, content: &str) {
+    repository.checkout(&format!("refs/heads/{name}").parse().unwrap());
+    fs::write(repository.path().join(path), content).unwrap();
+    repository.commit_all(name);
+    repository.checkout(&"refs/heads/master".parse().unwrap());
+    // Checking out `master` keeps files that only the branch has.
+    fs::remove_file(repository.path().join(path)).unwrap();
+}
+
+#[test]
+fn plan_proposes_target_and_branches_ahead_of_it() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    branch_with_commit(repository, "feature", "feature.txt", "feature\n");
+
+    let plan = controller.plan_setup(project, None).unwrap();
+    assert_eq!(plan.remotes.len(), 1);
+    assert_eq!(plan.remotes[0].name, "origin");
+    assert_eq!(plan.remotes[0].access, RemoteAccess::Verified);
+    assert_eq!(plan.remotes[0].error, None);
+    assert_eq!(
+        plan.target.unwrap().branch,
+        "refs/remotes/origin/master".parse().unwrap()
+    );
+    assert_eq!(plan.branches.len(), 1);
+    let branch = &plan.branches[0];
+    assert_eq!(branch.refname, "refs/heads/feature".parse().unwrap());
+    assert!(!branch.head);
+    assert_eq!(branch.ahead, 1);
+    assert_eq!(branch.behind, 0);
+    assert_eq!(branch.import, BranchImport::Apply);
+}
+
+#[test]
+fn plan_skips_branches_that_conflict_with_applied_ones() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    branch_with_commit(repository, "one", "conflict.txt", "one\n");
+    branch_with_commit(repository, "two", "conflict.txt", "two\n");
+
+    let plan = controller.plan_setup(project, None).unwrap();
+    let mut imports: Vec<_> = plan.branches.iter().map(|branch| branch.import).collect();
+    imports.sort_by_key(|import| *import == BranchImport::Skip);
+    assert_eq!(imports, vec![BranchImport::Apply, BranchImport::Skip]);
+}
+
+#[test]
+fn set_up_imports_branches() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    branch_with_commit(repository, "feature", "feature.txt", "feature\n");
+
+    let plan = controller.plan_setup(project, None).unwrap();
+    let import: Vec<_> = plan
+        .branches
+        .iter()
+        .filter(|branch| branch.import == BranchImport::Apply)
+        .map(|branch| branch.refname.clone())
+        .collect();
+    controller
+        .set_up_project(project, &plan.target.unwrap().branch, &import)
+        .unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].name, "feature");
+    assert!(branches[0].active);
+    assert_eq!(branches[0].commits.len(), 1);
+    assert!(repository.path().join("feature.txt").exists());
+}
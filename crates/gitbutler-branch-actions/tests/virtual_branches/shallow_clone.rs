@@ -0,0 +1,152 @@
+use std::process::Command;
+
+use gitbutler_project::{FetchSettings, Settings};
+use gitbutler_repo::{progress::NoProgress, Deepen};
+
+use super::*;
+
+fn git(dir: &path::Path, args: &[&str]) {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Give the remote of `test` three more commits on `master`, and a `feature` branch with a commit of its
+/// own that forked off the first commit, and return the URL to clone it from.
+fn remote_with_history(test: &Test) -> String {
+    let dir = test.repository.path();
+    for index in 1..=3 {
+        fs::write(dir.join("file.txt"), format!("{index}\n")).unwrap();
+        test.repository.commit_all(&format!("commit {index}"));
+    }
+    test.repository.push();
+    git(dir, &["checkout", "-q", "-b", "feature", "HEAD~3"]);
+    fs::write(dir.join("feature.txt"), "feature\n").unwrap();
+    git(dir, &["add", "feature.txt"]);
+    git(dir, &["commit", "-q", "-m", "feature"]);
+    git(dir, &["push", "-q", "origin", "feature"]);
+
+    let local = git2::Repository::open(dir).unwrap();
+    let remote = local.find_remote("origin").unwrap();
+    // Local clones ignore the depth unless they go through a transport.
+    format!("file://{}", remote.url().unwrap())
+}
+
+/// Clone `url` with only the last commit of `branch` and of `master`, and add it as a project.
+fn shallow_clone(test: &Test, url: &str, branch: &str) -> (Project, TempDir) {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("shallow");
+    git(
+        tmp.path(),
+        &[
+            "clone",
+            "-q",
+            "--depth=1",
+            "--no-single-branch",
+            "--branch",
+            branch,
+            url,
+            "shallow",
+        ],
+    );
+    let project = test.projects.add(&path).unwrap();
+    (project, tmp)
+}
+
+fn commit_count(project: &Project, refname: &str) -> usize {
+    let repo = git2::Repository::open(&project.path).unwrap();
+    let mut revwalk = repo.revwalk().unwrap();
+    revwalk.push_ref(refname).unwrap();
+    revwalk.count()
+}
+
+#[test]
+fn history_is_deepened_on_request() {
+    let test = Test::default();
+    let url = remote_with_history(&test);
+    let (project, _tmp) = shallow_clone(&test, &url, "master");
+    assert!(test.controller.is_shallow_clone(&project).unwrap());
+    assert_eq!(commit_count(&project, "refs/remotes/origin/master"), 1);
+
+    test.controller
+        .deepen_history(&project, Some("origin"), Deepen::By(1), None, &NoProgress)
+        .unwrap();
+    assert!(test.controller.is_shallow_clone(&project).unwrap());
+    assert_eq!(commit_count(&project, "refs/remotes/origin/master"), 2);
+
+    test.controller
+        .deepen_history(
+            &project,
+            Some("origin"),
+            Deepen::Unshallow,
+            None,
+            &NoProgress,
+        )
+        .unwrap();
+    assert!(!test.controller.is_shallow_clone(&project).unwrap());
+    assert_eq!(commit_count(&project, "refs/remotes/origin/master"), 4);
+}
+
+#[test]
+fn complete_clones_are_not_deepened() {
+    let test = Test::default();
+    assert!(!test.controller.is_shallow_clone(&test.project).unwrap());
+    test.controller
+        .deepen_history(
+            &test.project,
+            Some("origin"),
+            Deepen::Unshallow,
+            None,
+            &NoProgress,
+        )
+        .unwrap();
+}
+
+#[test]
+fn missing_merge_bases_are_fetched_on_demand() {
+    let test = Test::default();
+    let url = remote_with_history(&test);
+    let (project, _tmp) = shallow_clone(&test, &url, "feature");
+    let first_commit = {
+        let repo = git2::Repository::open(test.repository.path()).unwrap();
+        repo.revparse_single("master~3").unwrap().id()
+    };
+
+    let base = test
+        .controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    assert_eq!(
+        base.base_sha, first_commit,
+        "the commit the feature branch forked off is the base"
+    );
+}
+
+#[test]
+fn missing_merge_bases_fail_without_deepening_on_demand() {
+    let test = Test::default();
+    let url = remote_with_history(&test);
+    let (project, _tmp) = shallow_clone(&test, &url, "feature");
+    let settings = Settings {
+        fetch: FetchSettings {
+            deepen_on_demand: false,
+            ..Default::default()
+        },
+        ..project.settings.clone()
+    };
+    test.projects.update_settings(project.id, settings).unwrap();
+    let project = test.projects.get(project.id).unwrap();
+
+    assert!(test
+        .controller
+        .set_base_branch(&project, &"refs/remotes/origin/master".parse().unwrap())
+        .is_err());
+    assert!(test.controller.is_shallow_clone(&project).unwrap());
+}
@@ -0,0 +1,117 @@
+use gitbutler_branch::{BranchCreateRequest, BranchOwnershipClaims};
+
+use super::*;
+
+#[test]
+fn shelved_changes_leave_the_worktree_and_come_back() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "1\n2\n3\n").unwrap();
+    fs::write(repository.path().join("other.txt"), "other\n").unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].files.len(), 2);
+
+    let shelf_id = controller
+        .shelve_changes(
+            project,
+            "wip",
+            &"file.txt:1-4".parse::<BranchOwnershipClaims>().unwrap(),
+        )
+        .unwrap();
+    assert!(repository.path().join("other.txt").exists());
+
+    let shelves = controller.list_shelves(project).unwrap();
+    assert_eq!(shelves.len(), 1);
+    assert_eq!(shelves[0].id, shelf_id);
+    assert_eq!(shelves[0].name, "wip");
+    assert_eq!(shelves[0].files.len(), 1);
+    assert_eq!(shelves[0].files[0].path, PathBuf::from("file.txt"));
+    assert_eq!(
+        shelves[0].files[0].hunks[0].diff,
+        "@@ -0,0 +1,3 @@\n+1\n+2\n+3\n"
+    );
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches[0].files.len(), 1);
+
+    controller
+        .unshelve_changes(project, shelf_id, None)
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "1\n2\n3\n"
+    );
+    assert!(controller.list_shelves(project).unwrap().is_empty());
+}
+
+#[test]
+fn unshelve_onto_another_branch() {
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let first_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content\n").unwrap();
+    let shelf_id = controller
+        .shelve_changes(
+            project,
+            "wip",
+            &"file.txt:1-2".parse::<BranchOwnershipClaims>().unwrap(),
+        )
+        .unwrap();
+
+    let second_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    controller
+        .unshelve_changes(project, shelf_id, Some(second_id))
+        .unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let first = branches.iter().find(|b| b.id == first_id).unwrap();
+    let second = branches.iter().find(|b| b.id == second_id).unwrap();
+    assert!(first.files.is_empty());
+    assert_eq!(second.files.len(), 1);
+    assert_eq!(second.files[0].path, PathBuf::from("file.txt"));
+}
+
+#[test]
+fn nothing_to_shelve() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let err = controller
+        .shelve_changes(
+            project,
+            "wip",
+            &"file.txt:1-2".parse::<BranchOwnershipClaims>().unwrap(),
+        )
+        .unwrap_err();
+    assert_eq!(err.to_string(), "there are no changes to shelve");
+    assert!(controller.list_shelves(project).unwrap().is_empty());
+}
@@ -0,0 +1,173 @@
+use std::path::Path;
+
+use gitbutler_branch::{BranchCreateRequest, BranchOwnershipClaims};
+use gitbutler_commit::commit_ext::CommitExt;
+
+use super::*;
+
+#[test]
+fn split_by_file() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    fs::write(repository.path().join("file2.txt"), "content2").unwrap();
+    fs::write(repository.path().join("file3.txt"), "content3").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit one", None, false)
+        .unwrap();
+    let commit = repository.find_commit(commit_id).unwrap();
+
+    fs::write(repository.path().join("file4.txt"), "content4").unwrap();
+    controller
+        .create_commit(project, branch_id, "commit two", None, false)
+        .unwrap();
+
+    let groups: Vec<BranchOwnershipClaims> = vec![
+        "file2.txt:1-2".parse().unwrap(),
+        "file.txt:1-2".parse().unwrap(),
+    ];
+    controller
+        .split_commit(project, branch_id, commit_id, &groups)
+        .unwrap();
+
+    let branch = controller
+        .list_virtual_branches(project)
+        .unwrap()
+        .0
+        .into_iter()
+        .find(|b| b.id == branch_id)
+        .unwrap();
+
+    let descriptions = branch
+        .commits
+        .iter()
+        .map(|c| c.description.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        descriptions,
+        vec!["commit two", "commit one", "commit one", "commit one"]
+    );
+    let files = branch
+        .commits
+        .iter()
+        .map(|c| {
+            c.files
+                .iter()
+                .map(|f| f.path.display().to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        files,
+        vec![
+            vec!["file4.txt"],
+            vec!["file3.txt"],
+            vec!["file.txt"],
+            vec!["file2.txt"]
+        ],
+        "groups are in order, oldest first, and unclaimed hunks remain in the last commit"
+    );
+    assert_eq!(
+        branch.commits[3].change_id.as_deref(),
+        commit.change_id().as_deref(),
+        "the first commit keeps the change id"
+    );
+    assert_ne!(branch.commits[2].change_id, branch.commits[3].change_id);
+}
+
+#[test]
+fn split_hunks_of_one_file() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let lines: Vec<_> = (1..=20).map(|n| n.to_string()).collect();
+    fs::write(repository.path().join("file.txt"), lines.join("\n") + "\n").unwrap();
+    controller
+        .create_commit(project, branch_id, "add file", None, false)
+        .unwrap();
+
+    let mut changed = lines.clone();
+    changed[0] = "first".into();
+    changed[19] = "last".into();
+    fs::write(
+        repository.path().join("file.txt"),
+        changed.join("\n") + "\n",
+    )
+    .unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "change both ends", None, false)
+        .unwrap();
+
+    let groups: Vec<BranchOwnershipClaims> = vec!["file.txt:1-5".parse().unwrap()];
+    let new_head = controller
+        .split_commit(project, branch_id, commit_id, &groups)
+        .unwrap();
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let head = repo.find_commit(new_head).unwrap();
+    let first = head.parent(0).unwrap();
+    let contents = |commit: &git2::Commit| {
+        let tree = commit.tree().unwrap();
+        let entry = tree.get_path(Path::new("file.txt")).unwrap();
+        let blob = repo.find_blob(entry.id()).unwrap();
+        String::from_utf8(blob.content().to_vec()).unwrap()
+    };
+
+    let mut only_first = lines.clone();
+    only_first[0] = "first".into();
+    assert_eq!(contents(&first), only_first.join("\n") + "\n");
+    assert_eq!(contents(&head), changed.join("\n") + "\n");
+}
+
+#[test]
+fn unknown_hunk() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit one", None, false)
+        .unwrap();
+
+    let groups: Vec<BranchOwnershipClaims> = vec!["other.txt:1-2".parse().unwrap()];
+    assert!(controller
+        .split_commit(project, branch_id, commit_id, &groups)
+        .is_err());
+}
@@ -48,7 +48,7 @@ fn head() {
     };
 
     controller
-        .squash(project, branch_id, commit_four_oid)
+        .squash(project, branch_id, commit_four_oid, false)
         .unwrap();
 
     let branch = controller
@@ -116,7 +116,7 @@ fn middle() {
     };
 
     controller
-        .squash(project, branch_id, commit_two_oid)
+        .squash(project, branch_id, commit_two_oid, false)
         .unwrap();
 
     let branch = controller
@@ -197,7 +197,7 @@ fn forcepush_allowed() {
     };
 
     controller
-        .squash(project, branch_id, commit_two_oid)
+        .squash(project, branch_id, commit_two_oid, false)
         .unwrap();
 
     let branch = controller
@@ -282,7 +282,7 @@ fn forcepush_forbidden() {
 
     assert_eq!(
         controller
-            .squash(project, branch_id, commit_two_oid)
+            .squash(project, branch_id, commit_two_oid, false)
             .unwrap_err()
             .to_string(),
         "force push not allowed"
@@ -315,9 +315,174 @@ fn root_forbidden() {
 
     assert_eq!(
         controller
-            .squash(project, branch_id, commit_one_oid)
+            .squash(project, branch_id, commit_one_oid, false)
             .unwrap_err()
             .to_string(),
         "can not squash root commit"
     );
 }
+
+#[test]
+fn squash_commits_moves_fixups_into_target() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let commit_one_oid = {
+        fs::write(repository.path().join("file one.txt"), "").unwrap();
+        controller
+            .create_commit(project, branch_id, "commit one", None, false)
+            .unwrap()
+    };
+
+    {
+        fs::write(repository.path().join("file two.txt"), "").unwrap();
+        controller
+            .create_commit(project, branch_id, "commit two", None, false)
+            .unwrap()
+    };
+
+    let fixup_oid = {
+        fs::write(repository.path().join("file one.txt"), "fixed").unwrap();
+        controller
+            .create_commit(project, branch_id, "fixup! commit one", None, false)
+            .unwrap()
+    };
+
+    let extra_oid = {
+        fs::write(repository.path().join("file three.txt"), "").unwrap();
+        controller
+            .create_commit(project, branch_id, "commit three", None, false)
+            .unwrap()
+    };
+
+    controller
+        .squash_commits(
+            project,
+            branch_id,
+            &[fixup_oid, extra_oid],
+            commit_one_oid,
+            None,
+        )
+        .unwrap();
+
+    let branch = controller
+        .list_virtual_branches(project)
+        .unwrap()
+        .0
+        .into_iter()
+        .find(|b| b.id == branch_id)
+        .unwrap();
+
+    let descriptions = branch
+        .commits
+        .iter()
+        .map(|c| c.description.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        descriptions,
+        vec!["commit two", "commit one\ncommit three"],
+        "fixup messages are dropped"
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file one.txt")).unwrap(),
+        "fixed"
+    );
+}
+
+#[test]
+fn squash_commits_with_message() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let commit_one_oid = {
+        fs::write(repository.path().join("file one.txt"), "").unwrap();
+        controller
+            .create_commit(project, branch_id, "commit one", None, false)
+            .unwrap()
+    };
+
+    let commit_two_oid = {
+        fs::write(repository.path().join("file two.txt"), "").unwrap();
+        controller
+            .create_commit(project, branch_id, "commit two", None, false)
+            .unwrap()
+    };
+
+    controller
+        .squash_commits(
+            project,
+            branch_id,
+            &[commit_one_oid],
+            commit_two_oid,
+            Some("both"),
+        )
+        .unwrap();
+
+    let branch = controller
+        .list_virtual_branches(project)
+        .unwrap()
+        .0
+        .into_iter()
+        .find(|b| b.id == branch_id)
+        .unwrap();
+
+    assert_eq!(branch.commits.len(), 1);
+    assert_eq!(branch.commits[0].description, "both");
+}
+
+#[test]
+fn squash_commits_into_itself_fails() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+
+    let commit_oid = {
+        fs::write(repository.path().join("file one.txt"), "").unwrap();
+        controller
+            .create_commit(project, branch_id, "commit one", None, false)
+            .unwrap()
+    };
+
+    assert_eq!(
+        controller
+            .squash_commits(project, branch_id, &[commit_oid], commit_oid, None)
+            .unwrap_err()
+            .to_string(),
+        format!("commit {commit_oid} can not be squashed into itself")
+    );
+}
@@ -0,0 +1,157 @@
+use gitbutler_branch::{BranchCreateRequest, BranchId, BranchUpdateRequest};
+use gitbutler_branch_actions::VirtualBranch;
+
+use super::*;
+
+fn branch(controller: &VirtualBranchActions, project: &Project, id: BranchId) -> VirtualBranch {
+    controller
+        .list_virtual_branches(project)
+        .unwrap()
+        .0
+        .into_iter()
+        .find(|branch| branch.id == id)
+        .unwrap()
+}
+
+fn select(controller: &VirtualBranchActions, project: &Project, id: BranchId) {
+    controller
+        .update_virtual_branch(
+            project,
+            BranchUpdateRequest {
+                id,
+                selected_for_changes: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn children_start_on_their_parent_and_follow_it() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let parent_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("parent.txt"), "parent").unwrap();
+    controller
+        .create_commit(project, parent_id, "parent", None, false)
+        .unwrap();
+
+    let child_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                parent: Some(parent_id),
+                selected_for_changes: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let parent_head = branch(controller, project, parent_id).head;
+    let child = branch(controller, project, child_id);
+    assert_eq!(child.parent, Some(parent_id));
+    assert_eq!(child.pinned_base, Some(parent_head));
+    assert!(
+        child.commits.is_empty(),
+        "the commits of the parent aren't listed"
+    );
+
+    fs::write(repository.path().join("child.txt"), "child").unwrap();
+    controller
+        .create_commit(project, child_id, "child", None, false)
+        .unwrap();
+    let child = branch(controller, project, child_id);
+    assert_eq!(child.commits.len(), 1);
+    assert_eq!(child.commits[0].parent_ids, vec![parent_head]);
+
+    select(controller, project, parent_id);
+    fs::write(repository.path().join("parent-2.txt"), "parent").unwrap();
+    controller
+        .create_commit(project, parent_id, "parent 2", None, false)
+        .unwrap();
+
+    let parent_head = branch(controller, project, parent_id).head;
+    let child = branch(controller, project, child_id);
+    assert_eq!(
+        child.pinned_base,
+        Some(parent_head),
+        "the child was restacked"
+    );
+    assert_eq!(child.commits.len(), 1);
+    assert_eq!(child.commits[0].parent_ids, vec![parent_head]);
+    assert!(child.files.is_empty());
+    for file in ["parent.txt", "parent-2.txt", "child.txt"] {
+        assert!(repository.path().join(file).exists());
+    }
+}
+
+#[test]
+fn stacking_onto_another_branch_and_back() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let parent_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("parent.txt"), "parent").unwrap();
+    controller
+        .create_commit(project, parent_id, "parent", None, false)
+        .unwrap();
+
+    let child_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                selected_for_changes: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(repository.path().join("child.txt"), "child").unwrap();
+    controller
+        .create_commit(project, child_id, "child", None, false)
+        .unwrap();
+
+    controller
+        .stack_branch(project, child_id, parent_id)
+        .unwrap();
+    let parent_head = branch(controller, project, parent_id).head;
+    let child = branch(controller, project, child_id);
+    assert_eq!(child.parent, Some(parent_id));
+    assert_eq!(child.commits.len(), 1);
+    assert_eq!(child.commits[0].parent_ids, vec![parent_head]);
+
+    assert!(
+        controller
+            .stack_branch(project, parent_id, child_id)
+            .is_err(),
+        "stacks can't be cyclic"
+    );
+
+    controller
+        .rebase_branch_onto_target(project, child_id)
+        .unwrap();
+    let child = branch(controller, project, child_id);
+    assert_eq!(child.parent, None);
+    assert_eq!(child.pinned_base, None);
+    assert_eq!(child.commits.len(), 1);
+    assert_ne!(child.commits[0].parent_ids, vec![parent_head]);
+}
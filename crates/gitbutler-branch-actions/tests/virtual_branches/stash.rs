@@ -0,0 +1,119 @@
+use gitbutler_branch_actions::StashImport;
+
+use super::*;
+
+/// Stash a change to `file.txt` with the message `wip`, before GitButler manages the workspace.
+fn stash_change(repository: &TestProject, project: &Project, controller: &VirtualBranchActions) {
+    fs::write(repository.path().join("file.txt"), "one").unwrap();
+    repository.commit_all("first");
+    repository.push();
+
+    fs::write(repository.path().join("file.txt"), "two").unwrap();
+    let mut repo = git2::Repository::open(repository.path()).unwrap();
+    let signature = git2::Signature::now("test", "test@example.com").unwrap();
+    repo.stash_save(&signature, "wip", None).unwrap();
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "one"
+    );
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+}
+
+#[test]
+fn list_stashes() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    stash_change(repository, project, controller);
+
+    let stashes = controller.list_stashes(project).unwrap();
+    assert_eq!(stashes.len(), 1);
+    assert_eq!(stashes[0].index, 0);
+    assert_eq!(stashes[0].message, "On master: wip");
+}
+
+#[test]
+fn import_as_uncommitted_changes() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    stash_change(repository, project, controller);
+
+    let branch_id = controller
+        .import_stash(project, 0, StashImport::Uncommitted)
+        .unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].id, branch_id);
+    assert_eq!(branches[0].name, "wip");
+    assert!(branches[0].commits.is_empty());
+    assert_eq!(branches[0].files.len(), 1);
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "two"
+    );
+    assert_eq!(
+        controller.list_stashes(project).unwrap().len(),
+        1,
+        "the stash entry is kept"
+    );
+}
+
+#[test]
+fn import_as_commit() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    stash_change(repository, project, controller);
+
+    let branch_id = controller
+        .import_stash(project, 0, StashImport::Commit)
+        .unwrap();
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].id, branch_id);
+    assert!(branches[0].files.is_empty());
+    assert_eq!(branches[0].commits.len(), 1);
+    assert_eq!(branches[0].commits[0].description, "wip");
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "two"
+    );
+}
+
+#[test]
+fn missing_stash_entry() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    stash_change(repository, project, controller);
+
+    assert_eq!(
+        controller
+            .import_stash(project, 1, StashImport::Uncommitted)
+            .unwrap_err()
+            .to_string(),
+        "there is no stash entry stash@{1}"
+    );
+}
@@ -0,0 +1,126 @@
+use gitbutler_branch_actions::{NestedRepositorySuggestion, SubmoduleStatus};
+use gitbutler_error::error::Code;
+
+use super::*;
+
+fn add_submodule(repository: &TestProject) {
+    let submodule_project = TestProject::default();
+    let submodule_url: gitbutler_url::Url = submodule_project
+        .path()
+        .display()
+        .to_string()
+        .parse()
+        .unwrap();
+    repository.add_submodule(&submodule_url, path::Path::new("submodule"));
+}
+
+#[test]
+fn list_new_submodule() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    add_submodule(repository);
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let submodules = controller.list_submodules(project).unwrap();
+    assert_eq!(submodules.len(), 1);
+    assert_eq!(submodules[0].path, PathBuf::from("submodule"));
+    assert_eq!(submodules[0].status, SubmoduleStatus::New);
+    assert_eq!(submodules[0].head_id, None);
+    assert!(submodules[0].workdir_id.is_some());
+    assert!(!submodules[0].dirty);
+}
+
+#[test]
+fn list_without_submodules() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    assert!(controller.list_submodules(project).unwrap().is_empty());
+}
+
+#[test]
+fn unapplying_refuses_to_drop_new_submodule() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    add_submodule(repository);
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+
+    let err = controller
+        .convert_to_real_branch(project, branches[0].id)
+        .unwrap_err();
+    assert_eq!(err.downcast_ref::<Code>(), Some(&Code::Submodules));
+    assert!(repository.path().join("submodule").exists());
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+    assert!(branches[0].active);
+}
+
+#[test]
+fn nested_repository_is_listed_and_left_out() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+
+    let nested_path = repository.path().join("vendor").join("lib");
+    let nested = git2::Repository::init(&nested_path).unwrap();
+    nested
+        .remote("origin", "https://example.com/lib.git")
+        .unwrap();
+    fs::write(nested_path.join("lib.txt"), "content").unwrap();
+    let mut index = nested.index().unwrap();
+    index.add_path(path::Path::new("lib.txt")).unwrap();
+    let tree = nested.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("test", "test@example.com").unwrap();
+    nested
+        .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+
+    let nested_repositories = controller.list_nested_repositories(project).unwrap();
+    assert_eq!(nested_repositories.len(), 1);
+    assert_eq!(nested_repositories[0].path, PathBuf::from("vendor/lib"));
+    assert_eq!(
+        nested_repositories[0].url.as_deref(),
+        Some("https://example.com/lib.git")
+    );
+    assert_eq!(
+        nested_repositories[0].suggestion,
+        NestedRepositorySuggestion::ConvertToSubmodule
+    );
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].files.len(), 1);
+    assert_eq!(branches[0].files[0].path, PathBuf::from("file.txt"));
+}
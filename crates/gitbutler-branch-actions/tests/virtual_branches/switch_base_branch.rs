@@ -0,0 +1,199 @@
+use gitbutler_branch_actions::SwitchStatus;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+/// Create the branch `release` on the remote at `commit`, next to `master`, and fetch it.
+fn push_release_branch(repository: &TestProject, commit: git2::Oid) {
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    repo.branch("release", &repo.find_commit(commit).unwrap(), false)
+        .unwrap();
+    repository.push_branch(&"refs/heads/release".parse().unwrap());
+    repository.fetch();
+}
+
+#[test]
+fn rebases_applied_branches_onto_new_target() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("release.txt"), "release").unwrap();
+    let release = repository.commit_all("release");
+    push_release_branch(repository, release);
+    let initial = repository
+        .find_commit(release)
+        .unwrap()
+        .parent_id(0)
+        .unwrap();
+    repository.reset_hard(Some(initial));
+    fs::write(repository.path().join("main.txt"), "main").unwrap();
+    repository.commit_all("main");
+    repository.push();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("feature.txt"), "feature").unwrap();
+    controller
+        .create_commit(project, branch_id, "feature", None, false)
+        .unwrap();
+    fs::write(repository.path().join("wip.txt"), "wip").unwrap();
+
+    let switched = controller
+        .switch_base_branch(project, &"refs/remotes/origin/release".parse().unwrap())
+        .unwrap();
+    assert_eq!(switched.len(), 1);
+    assert_eq!(switched[0].branch_id, branch_id);
+    let SwitchStatus::Rebased { new_head } = switched[0].status else {
+        panic!("the branch doesn't conflict with the new target")
+    };
+    assert_eq!(
+        repository
+            .find_commit(new_head)
+            .unwrap()
+            .parent_id(0)
+            .unwrap(),
+        release
+    );
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].head, new_head);
+    assert_eq!(branches[0].commits.len(), 1);
+    assert_eq!(branches[0].files.len(), 1, "uncommitted changes are kept");
+    assert!(repository.path().join("release.txt").exists());
+    assert!(repository.path().join("feature.txt").exists());
+    assert!(repository.path().join("wip.txt").exists());
+    assert!(
+        !repository.path().join("main.txt").exists(),
+        "commits of the old target are left behind"
+    );
+    assert_eq!(
+        VirtualBranchActions::get_base_branch_data(project)
+            .unwrap()
+            .base_sha,
+        release
+    );
+}
+
+#[test]
+fn unapplies_branches_that_conflict_with_new_target() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("file.txt"), "base").unwrap();
+    let base = repository.commit_all("base");
+    repository.push();
+    fs::write(repository.path().join("file.txt"), "release").unwrap();
+    let release = repository.commit_all("release");
+    push_release_branch(repository, release);
+    repository.reset_hard(Some(base));
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "feature").unwrap();
+    controller
+        .create_commit(project, branch_id, "feature", None, false)
+        .unwrap();
+
+    let switched = controller
+        .switch_base_branch(project, &"refs/remotes/origin/release".parse().unwrap())
+        .unwrap();
+    assert_eq!(switched.len(), 1);
+    assert!(matches!(switched[0].status, SwitchStatus::Unapplied { .. }));
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    assert!(branches.is_empty());
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "release"
+    );
+}
+
+#[test]
+fn switching_to_current_target_fails() {
+    let Test {
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    assert!(controller
+        .switch_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .is_err());
+}
+
+#[test]
+fn migrates_to_renamed_default_branch_of_remote() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit = repository.commit_all("commit");
+    repository.push();
+    push_release_branch(repository, commit);
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    assert_eq!(
+        controller.remote_default_branch_change(project).unwrap(),
+        None
+    );
+    let err = controller
+        .migrate_target_to_remote_default(project)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+
+    // what a fetch records once the remote made `release` its default branch
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    repo.reference_symbolic(
+        "refs/remotes/origin/HEAD",
+        "refs/remotes/origin/release",
+        true,
+        "remote HEAD",
+    )
+    .unwrap();
+    assert_eq!(
+        controller.remote_default_branch_change(project).unwrap(),
+        Some("refs/remotes/origin/release".parse().unwrap())
+    );
+
+    controller
+        .migrate_target_to_remote_default(project)
+        .unwrap();
+    assert_eq!(
+        VirtualBranchActions::get_base_branch_data(project)
+            .unwrap()
+            .branch_name,
+        "origin/release"
+    );
+    assert_eq!(
+        controller.remote_default_branch_change(project).unwrap(),
+        None
+    );
+}
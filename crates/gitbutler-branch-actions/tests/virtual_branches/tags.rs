@@ -0,0 +1,126 @@
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+fn head(test: &Test) -> git2::Oid {
+    git2::Repository::open(test.repository.path())
+        .unwrap()
+        .head()
+        .unwrap()
+        .peel_to_commit()
+        .unwrap()
+        .id()
+}
+
+#[test]
+fn lightweight_and_annotated_tags_are_listed_with_their_commit() {
+    let test = Test::default();
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &test;
+    fs::write(repository.path().join("file.txt"), "content\n").unwrap();
+    let commit = repository.commit_all("release it");
+
+    let lightweight = controller
+        .create_tag(project, "v1.0.0", commit, None, false)
+        .unwrap();
+    assert!(!lightweight.annotated);
+    assert_eq!(lightweight.message, None);
+    assert_eq!(lightweight.tagger, None);
+
+    controller
+        .create_tag(project, "v1.0.1", commit, Some("The first release"), false)
+        .unwrap();
+
+    let tags = controller.list_tags(project).unwrap();
+    assert_eq!(tags.len(), 2);
+    assert_eq!(tags[0], lightweight);
+    let annotated = &tags[1];
+    assert_eq!(annotated.name, "v1.0.1");
+    assert!(annotated.annotated);
+    assert!(!annotated.signed);
+    assert_eq!(annotated.message.as_deref(), Some("The first release"));
+    assert!(annotated.tagger.is_some());
+    assert_eq!(annotated.commit, commit);
+    assert_eq!(annotated.commit_summary, "release it");
+    assert_eq!(annotated.commit_author.email, "gitbutler-test@example.com");
+}
+
+#[test]
+fn invalid_and_existing_names_are_rejected() {
+    let test = Test::default();
+    let Test {
+        project,
+        controller,
+        ..
+    } = &test;
+    let commit = head(&test);
+    controller
+        .create_tag(project, "v1", commit, None, false)
+        .unwrap();
+
+    for name in ["v1", "not valid", ""] {
+        let err = controller
+            .create_tag(project, name, commit, None, false)
+            .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn lightweight_tags_cannot_be_signed() {
+    let test = Test::default();
+    let Test {
+        project,
+        controller,
+        ..
+    } = &test;
+
+    let err = controller
+        .create_tag(project, "v1", head(&test), None, true)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+    assert!(controller.list_tags(project).unwrap().is_empty());
+}
+
+#[test]
+fn push_to_the_remote_of_the_target() {
+    let test = Test::default();
+    let Test {
+        project,
+        controller,
+        repository,
+        ..
+    } = &test;
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let commit = head(&test);
+    controller
+        .create_tag(project, "v1", commit, Some("release"), false)
+        .unwrap();
+
+    controller.push_tag(project, "v1", None, None).unwrap();
+
+    let local = git2::Repository::open(repository.path()).unwrap();
+    let remote_url = local
+        .find_remote("origin")
+        .unwrap()
+        .url()
+        .unwrap()
+        .to_owned();
+    let remote = git2::Repository::open(remote_url).unwrap();
+    let pushed = remote.find_reference("refs/tags/v1").unwrap();
+    assert_eq!(pushed.peel_to_commit().unwrap().id(), commit);
+    assert!(pushed.peel_to_tag().is_ok(), "the annotation is pushed too");
+}
@@ -0,0 +1,142 @@
+use gitbutler_branch::TrashOrigin;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+use super::*;
+
+fn lines(range: std::ops::RangeInclusive<u32>) -> String {
+    range.map(|n| format!("line {n}\n")).collect()
+}
+
+/// Set the base branch to a commit with `file.txt`, which has 20 lines.
+fn set_base(
+    Test {
+        repository,
+        project,
+        controller,
+        ..
+    }: &Test,
+) {
+    fs::write(repository.path().join("file.txt"), lines(1..=20)).unwrap();
+    repository.commit_all("twenty lines");
+    repository.push();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+}
+
+/// Discard the hunk of `file.txt` that starts after line 10.
+fn discard_second_hunk(test: &Test) {
+    let (branches, _) = test
+        .controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    let hunk = branches[0].files[0]
+        .hunks
+        .iter()
+        .find(|hunk| hunk.start > 10)
+        .unwrap();
+    test.controller
+        .discard_hunk(&test.project, path::Path::new("file.txt"), &hunk.id)
+        .unwrap();
+}
+
+#[test]
+fn discarded_hunks_can_be_restored() {
+    let test = Test::default();
+    set_base(&test);
+    let path = test.repository.path().join("file.txt");
+    let changed = lines(1..=20)
+        .replace("line 2\n", "changed 2\n")
+        .replace("line 18\n", "changed 18\n");
+    fs::write(&path, &changed).unwrap();
+    discard_second_hunk(&test);
+
+    let entries = test.controller.list_trash(&test.project).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].path, path::Path::new("file.txt"));
+    assert_eq!(entries[0].origin, TrashOrigin::DiscardHunk);
+    assert!(entries[0].hunk_id.is_some());
+    assert!(entries[0].existed_before && entries[0].existed_after);
+
+    test.controller
+        .restore_trash(&test.project, entries[0].id)
+        .unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), changed);
+    assert!(test
+        .controller
+        .list_trash(&test.project)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn restoring_keeps_changes_made_since() {
+    let test = Test::default();
+    set_base(&test);
+    let path = test.repository.path().join("file.txt");
+    fs::write(&path, lines(1..=20).replace("line 18\n", "changed 18\n")).unwrap();
+    discard_second_hunk(&test);
+    fs::write(&path, lines(1..=20).replace("line 2\n", "changed 2\n")).unwrap();
+
+    let entry = test.controller.list_trash(&test.project).unwrap().remove(0);
+    test.controller
+        .restore_trash(&test.project, entry.id)
+        .unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        lines(1..=20)
+            .replace("line 2\n", "changed 2\n")
+            .replace("line 18\n", "changed 18\n")
+    );
+}
+
+#[test]
+fn reset_files_can_be_restored() {
+    let test = Test::default();
+    set_base(&test);
+    let path = test.repository.path().join("new.txt");
+    fs::write(&path, "new\n").unwrap();
+
+    test.controller
+        .reset_files(&test.project, &vec!["new.txt".to_owned()])
+        .unwrap();
+    assert!(!path.exists());
+
+    let entry = test.controller.list_trash(&test.project).unwrap().remove(0);
+    assert_eq!(entry.origin, TrashOrigin::ResetFile);
+    assert!(entry.existed_before);
+    assert!(!entry.existed_after);
+
+    test.controller
+        .restore_trash(&test.project, entry.id)
+        .unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+}
+
+#[test]
+fn conflicting_changes_made_since_prevent_restoring() {
+    let test = Test::default();
+    set_base(&test);
+    let path = test.repository.path().join("file.txt");
+    fs::write(&path, lines(1..=20).replace("line 18\n", "changed 18\n")).unwrap();
+    discard_second_hunk(&test);
+    let conflicting = lines(1..=20).replace("line 18\n", "edited 18\n");
+    fs::write(&path, &conflicting).unwrap();
+
+    let entry = test.controller.list_trash(&test.project).unwrap().remove(0);
+    let err = test
+        .controller
+        .restore_trash(&test.project, entry.id)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), conflicting);
+    assert_eq!(
+        test.controller.list_trash(&test.project).unwrap().len(),
+        1,
+        "the entry stays in the trash"
+    );
+}
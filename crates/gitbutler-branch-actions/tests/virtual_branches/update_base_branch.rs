@@ -1038,3 +1038,105 @@ mod applied_branch {
         assert_eq!(branches[0].files.len(), 0);
     }
 }
+
+mod strategy {
+    use gitbutler_branch::BranchCreateRequest;
+    use gitbutler_branch_actions::BaseUpdateStrategy;
+
+    use super::*;
+
+    /// Set the base to a commit the remote has a commit on top of, and return the id of a branch with a
+    /// commit that was pushed and a commit that wasn't.
+    fn branch_behind_target(
+        Test {
+            repository,
+            project,
+            controller,
+            ..
+        }: &Test,
+    ) -> gitbutler_branch::BranchId {
+        fs::write(repository.path().join("file.txt"), "first").unwrap();
+        let first_commit_oid = repository.commit_all("first");
+        fs::write(repository.path().join("other.txt"), "upstream").unwrap();
+        repository.commit_all("upstream");
+        repository.push();
+        repository.reset_hard(Some(first_commit_oid));
+
+        controller
+            .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+            .unwrap();
+        let branch_id = controller
+            .create_virtual_branch(project, &BranchCreateRequest::default())
+            .unwrap();
+        fs::write(repository.path().join("one.txt"), "one").unwrap();
+        controller
+            .create_commit(project, branch_id, "one", None, false)
+            .unwrap();
+        controller
+            .push_virtual_branch(project, branch_id, false, None)
+            .unwrap();
+        fs::write(repository.path().join("two.txt"), "two").unwrap();
+        controller
+            .create_commit(project, branch_id, "two", None, false)
+            .unwrap();
+        branch_id
+    }
+
+    #[test]
+    fn drop_integrated_leaves_out_commits_the_target_rebased() {
+        let test = Test::default();
+        let branch_id = branch_behind_target(&test);
+        let (branches, _) = test
+            .controller
+            .list_virtual_branches(&test.project)
+            .unwrap();
+        // The pushed commit lands on the target with a new id, like when a pull request is rebased.
+        test.repository
+            .rebase_and_merge(&branches[0].upstream.as_ref().unwrap().name);
+
+        test.controller
+            .update_base_branch_with_remap(&test.project, BaseUpdateStrategy::DropIntegrated)
+            .unwrap();
+
+        let base = VirtualBranchActions::get_base_branch_data(&test.project).unwrap();
+        let (branches, _) = test
+            .controller
+            .list_virtual_branches(&test.project)
+            .unwrap();
+        assert_eq!(branches[0].id, branch_id);
+        assert_eq!(branches[0].commits.len(), 1);
+        assert_eq!(branches[0].commits[0].description, "two");
+        assert_eq!(branches[0].commits[0].parent_ids, vec![base.base_sha]);
+        assert_eq!(
+            fs::read_to_string(test.repository.path().join("one.txt")).unwrap(),
+            "one"
+        );
+    }
+
+    #[test]
+    fn merge_keeps_the_commits_of_branches() {
+        let test = Test::default();
+        branch_behind_target(&test);
+        let (branches, _) = test
+            .controller
+            .list_virtual_branches(&test.project)
+            .unwrap();
+        let old_head = branches[0].commits[0].id;
+
+        test.controller
+            .update_base_branch_with_remap(&test.project, BaseUpdateStrategy::Merge)
+            .unwrap();
+
+        let base = VirtualBranchActions::get_base_branch_data(&test.project).unwrap();
+        let (branches, _) = test
+            .controller
+            .list_virtual_branches(&test.project)
+            .unwrap();
+        let head = &branches[0].commits[0];
+        assert_eq!(head.parent_ids, vec![old_head, base.base_sha]);
+        assert_eq!(
+            fs::read_to_string(test.repository.path().join("other.txt")).unwrap(),
+            "upstream"
+        );
+    }
+}
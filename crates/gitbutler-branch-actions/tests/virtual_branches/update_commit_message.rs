@@ -1,5 +1,8 @@
 use gitbutler_branch::{BranchCreateRequest, BranchUpdateRequest};
+use gitbutler_branch_actions::PushedCommitRewrite;
 use gitbutler_commit::commit_ext::CommitExt;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_project::PushedCommitRewrites;
 
 use super::*;
 
@@ -44,7 +47,13 @@ fn head() {
     let before_change_id = &commit_three.change_id();
 
     controller
-        .update_commit_message(project, branch_id, commit_three_oid, "commit three updated")
+        .update_commit_message(
+            project,
+            branch_id,
+            commit_three_oid,
+            "commit three updated",
+            false,
+        )
         .unwrap();
 
     let branch = controller
@@ -113,7 +122,13 @@ fn middle() {
     };
 
     controller
-        .update_commit_message(project, branch_id, commit_two_oid, "commit two updated")
+        .update_commit_message(
+            project,
+            branch_id,
+            commit_two_oid,
+            "commit two updated",
+            false,
+        )
         .unwrap();
 
     let branch = controller
@@ -173,7 +188,13 @@ fn forcepush_allowed() {
         .unwrap();
 
     controller
-        .update_commit_message(project, branch_id, commit_one_oid, "commit one updated")
+        .update_commit_message(
+            project,
+            branch_id,
+            commit_one_oid,
+            "commit one updated",
+            false,
+        )
         .unwrap();
 
     let branch = controller
@@ -234,7 +255,13 @@ fn forcepush_forbidden() {
 
     assert_eq!(
         controller
-            .update_commit_message(project, branch_id, commit_one_oid, "commit one updated",)
+            .update_commit_message(
+                project,
+                branch_id,
+                commit_one_oid,
+                "commit one updated",
+                false,
+            )
             .unwrap_err()
             .to_string(),
         "force push not allowed"
@@ -280,7 +307,13 @@ fn root() {
     };
 
     controller
-        .update_commit_message(project, branch_id, commit_one_oid, "commit one updated")
+        .update_commit_message(
+            project,
+            branch_id,
+            commit_one_oid,
+            "commit one updated",
+            false,
+        )
         .unwrap();
 
     let branch = controller
@@ -328,9 +361,78 @@ fn empty() {
 
     assert_eq!(
         controller
-            .update_commit_message(project, branch_id, commit_one_oid, "",)
+            .update_commit_message(project, branch_id, commit_one_oid, "", false)
             .unwrap_err()
             .to_string(),
         "commit message can not be empty"
     );
 }
+
+#[test]
+fn pushed_commits_are_protected_by_the_project_policy() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file one.txt"), "").unwrap();
+    let commit_one_oid = controller
+        .create_commit(project, branch_id, "commit one", None, false)
+        .unwrap();
+    controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap();
+    fs::write(repository.path().join("file two.txt"), "").unwrap();
+    let commit_two_oid = controller
+        .create_commit(project, branch_id, "commit two", None, false)
+        .unwrap();
+
+    let mut project = project.clone();
+    project.pushed_commit_rewrites = PushedCommitRewrites::Warn;
+    controller
+        .update_commit_message(&project, branch_id, commit_two_oid, "unpushed", false)
+        .unwrap();
+
+    let err = controller
+        .update_commit_message(&project, branch_id, commit_one_oid, "pushed", false)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::PushedCommitRewrite)
+    );
+    let rewrite = err.downcast_ref::<PushedCommitRewrite>().unwrap();
+    assert_eq!(rewrite.commits, [commit_one_oid]);
+    assert!(!rewrite.blocked);
+
+    project.pushed_commit_rewrites = PushedCommitRewrites::Block;
+    let err = controller
+        .update_commit_message(&project, branch_id, commit_one_oid, "pushed", true)
+        .unwrap_err();
+    assert!(err.downcast_ref::<PushedCommitRewrite>().unwrap().blocked);
+
+    project.pushed_commit_rewrites = PushedCommitRewrites::Warn;
+    controller
+        .update_commit_message(&project, branch_id, commit_one_oid, "pushed", true)
+        .unwrap();
+    let branch = controller
+        .list_virtual_branches(&project)
+        .unwrap()
+        .0
+        .into_iter()
+        .find(|b| b.id == branch_id)
+        .unwrap();
+    let descriptions: Vec<_> = branch
+        .commits
+        .iter()
+        .map(|c| c.description.clone())
+        .collect();
+    assert_eq!(descriptions, ["unpushed", "pushed"]);
+}
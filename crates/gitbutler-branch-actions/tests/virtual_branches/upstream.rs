@@ -1,4 +1,6 @@
-use gitbutler_branch::BranchCreateRequest;
+use gitbutler_branch::{BranchCreateRequest, BranchId};
+use gitbutler_branch_actions::{IntegrationOutcome, IntegrationStrategy};
+use gitbutler_error::error::{AnyhowContextExt, Code};
 
 use super::*;
 
@@ -136,3 +138,216 @@ fn detect_integrated_commits() {
         assert!(branches[0].commits[2].is_integrated);
     }
 }
+
+/// Set up a pushed branch named `feature` with a file `file.txt`, then commit `local_content` to it and
+/// `upstream_content` to its upstream branch, as if someone else pushed to it.
+/// Return the id of the branch and the upstream commit.
+fn diverged_branch(
+    test: &Test,
+    local_content: &str,
+    upstream_content: &str,
+) -> (BranchId, git2::Oid) {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = test;
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some("feature".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    controller
+        .create_commit(project, branch_id, "pushed", None, false)
+        .unwrap();
+    controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap();
+
+    let repo = git2::Repository::open(repository.path()).unwrap();
+    let pushed = repo
+        .find_reference("refs/remotes/origin/feature")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    let mut tree = git2::build::TreeUpdateBuilder::new();
+    tree.upsert(
+        "file.txt",
+        repo.blob(upstream_content.as_bytes()).unwrap(),
+        git2::FileMode::Blob,
+    );
+    let tree = tree.create_updated(&repo, &pushed.tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("someone else", "someone@example.com").unwrap();
+    let upstream = repo
+        .commit(
+            None,
+            &signature,
+            &signature,
+            "upstream",
+            &repo.find_tree(tree).unwrap(),
+            &[&pushed],
+        )
+        .unwrap();
+    repo.reference("refs/remotes/origin/feature", upstream, true, "")
+        .unwrap();
+
+    fs::write(repository.path().join("local.txt"), local_content).unwrap();
+    controller
+        .create_commit(project, branch_id, "local", None, false)
+        .unwrap();
+    (branch_id, upstream)
+}
+
+#[test]
+fn integrate_upstream_with_rebase() {
+    let test = Test::default();
+    let (branch_id, upstream) = diverged_branch(&test, "local", "upstream");
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &test;
+
+    let outcome = controller
+        .integrate_upstream(project, branch_id, IntegrationStrategy::Rebase)
+        .unwrap();
+    let IntegrationOutcome::Integrated { new_head } = outcome else {
+        panic!("expected the upstream commit to be integrated, got {outcome:?}");
+    };
+    let head = repository.find_commit(new_head).unwrap();
+    assert_eq!(head.message(), Some("local"));
+    assert_eq!(
+        head.parent_ids().collect::<Vec<_>>(),
+        [upstream],
+        "local commits are replayed on top of the upstream commits"
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "upstream"
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("local.txt")).unwrap(),
+        "local"
+    );
+
+    assert_eq!(
+        controller
+            .integrate_upstream(project, branch_id, IntegrationStrategy::Rebase)
+            .unwrap(),
+        IntegrationOutcome::UpToDate
+    );
+}
+
+#[test]
+fn integrate_upstream_with_merge() {
+    let test = Test::default();
+    let (branch_id, upstream) = diverged_branch(&test, "local", "upstream");
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &test;
+
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let local_head = branches[0].head;
+    let outcome = controller
+        .integrate_upstream(project, branch_id, IntegrationStrategy::Merge)
+        .unwrap();
+    let IntegrationOutcome::Integrated { new_head } = outcome else {
+        panic!("expected the upstream commit to be integrated, got {outcome:?}");
+    };
+    assert_eq!(
+        repository
+            .find_commit(new_head)
+            .unwrap()
+            .parent_ids()
+            .collect::<Vec<_>>(),
+        [local_head, upstream],
+        "local commits are kept as they are"
+    );
+    assert_eq!(
+        fs::read_to_string(repository.path().join("file.txt")).unwrap(),
+        "upstream"
+    );
+}
+
+#[test]
+fn integrate_upstream_with_merge_is_refused_for_linear_history() {
+    let test = Test::default();
+    let (branch_id, upstream) = diverged_branch(&test, "local", "upstream");
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &test;
+    let mut project = project.clone();
+    project.settings.linear_history = true;
+
+    let (branches, _) = controller.list_virtual_branches(&project).unwrap();
+    let local_head = branches[0].head;
+    let err = controller
+        .integrate_upstream(&project, branch_id, IntegrationStrategy::Merge)
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::LinearHistory)
+    );
+    let (branches, _) = controller.list_virtual_branches(&project).unwrap();
+    assert_eq!(branches[0].head, local_head, "the branch is left as it was");
+
+    let outcome = controller
+        .integrate_upstream(&project, branch_id, IntegrationStrategy::Rebase)
+        .unwrap();
+    let IntegrationOutcome::Integrated { new_head } = outcome else {
+        panic!("expected the upstream commit to be integrated, got {outcome:?}");
+    };
+    assert_eq!(
+        repository
+            .find_commit(new_head)
+            .unwrap()
+            .parent_ids()
+            .collect::<Vec<_>>(),
+        [upstream],
+        "rebasing keeps the history linear"
+    );
+}
+
+#[test]
+fn integrate_upstream_conflicts_are_left_for_resolution() {
+    let test = Test::default();
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &test;
+    let (branch_id, _upstream) = diverged_branch(&test, "local", "upstream");
+    fs::write(repository.path().join("file.txt"), "local change").unwrap();
+    controller
+        .create_commit(project, branch_id, "conflicting", None, false)
+        .unwrap();
+
+    assert_eq!(
+        controller
+            .integrate_upstream(project, branch_id, IntegrationStrategy::Rebase)
+            .unwrap(),
+        IntegrationOutcome::Conflicted,
+        "rebasing falls back to merging, which conflicts just the same"
+    );
+    let files = controller.list_conflicted_files(project).unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, path::Path::new("file.txt"));
+}
@@ -0,0 +1,112 @@
+use gitbutler_branch::BranchCreateRequest;
+
+use super::*;
+
+fn local_config(repository: &TestProject) -> git2::Config {
+    git2::Repository::open(repository.path())
+        .unwrap()
+        .config()
+        .unwrap()
+        .open_level(git2::ConfigLevel::Local)
+        .unwrap()
+}
+
+#[test]
+fn first_push_configures_upstream() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some("tracked".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+
+    assert!(local_config(repository)
+        .get_string("branch.tracked.remote")
+        .is_err());
+    controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap();
+
+    let config = local_config(repository);
+    assert_eq!(
+        config.get_string("branch.tracked.remote").unwrap(),
+        "origin"
+    );
+    assert_eq!(
+        config.get_string("branch.tracked.merge").unwrap(),
+        "refs/heads/tracked"
+    );
+}
+
+#[test]
+fn repair_configures_missing_upstreams() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(
+            project,
+            &BranchCreateRequest {
+                name: Some("tracked".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+    controller
+        .push_virtual_branch(project, branch_id, false, None)
+        .unwrap();
+
+    let mut config = local_config(repository);
+    config.remove("branch.tracked.remote").unwrap();
+    config.remove("branch.tracked.merge").unwrap();
+
+    assert_eq!(
+        controller.repair_upstream_config(project).unwrap(),
+        ["tracked"]
+    );
+    let config = local_config(repository);
+    assert_eq!(
+        config.get_string("branch.tracked.remote").unwrap(),
+        "origin"
+    );
+    assert_eq!(
+        config.get_string("branch.tracked.merge").unwrap(),
+        "refs/heads/tracked"
+    );
+
+    assert!(
+        controller
+            .repair_upstream_config(project)
+            .unwrap()
+            .is_empty(),
+        "configured branches are left alone"
+    );
+}
@@ -1,3 +1,5 @@
+use gitbutler_branch::VirtualBranchesHandle;
+use gitbutler_branch_actions::IntegrationDivergence;
 use gitbutler_reference::LocalRefname;
 
 use super::*;
@@ -22,3 +24,86 @@ fn should_fail_on_incorrect_branch() {
         "<verification-failed>: project is on refs/heads/somebranch. Please checkout gitbutler/integration to continue"
     );
 }
+
+#[test]
+fn integration_matches_applied_branches() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+
+    assert!(controller.is_based_on_target(project, branch_id).unwrap());
+    assert_eq!(controller.verify_integration(project).unwrap(), []);
+}
+
+#[test]
+fn branch_moved_off_its_base_is_reported_and_protected() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    let before_target = repository.commit_all("before target");
+    fs::write(repository.path().join("target.txt"), "target").unwrap();
+    repository.commit_all("target");
+    repository.push();
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let branch_id = controller
+        .create_virtual_branch(project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(repository.path().join("file.txt"), "content").unwrap();
+    let commit_id = controller
+        .create_commit(project, branch_id, "commit", None, false)
+        .unwrap();
+
+    // Record a head that the integration commit doesn't know about.
+    let vb_state = VirtualBranchesHandle::new(project.gb_dir());
+    let mut branch = vb_state.get_branch_in_workspace(branch_id).unwrap();
+    branch.head = before_target;
+    vb_state.set_branch(branch).unwrap();
+
+    assert!(!controller.is_based_on_target(project, branch_id).unwrap());
+    let divergences = controller.verify_integration(project).unwrap();
+    assert_eq!(divergences.len(), 3);
+    assert!(matches!(
+        divergences[0],
+        IntegrationDivergence::NotBasedOnBase { branch_id: id, head, .. }
+            if id == branch_id && head == before_target
+    ));
+    assert!(matches!(
+        divergences[1],
+        IntegrationDivergence::NotIntegrated { branch_id: id, .. } if id == branch_id
+    ));
+    assert_eq!(
+        divergences[2],
+        IntegrationDivergence::UnknownParent { commit: commit_id }
+    );
+
+    assert!(matches!(
+        controller
+            .reset_virtual_branch(project, branch_id, before_target)
+            .unwrap_err()
+            .downcast_ref(),
+        Some(Marker::VerificationFailure)
+    ));
+}
@@ -0,0 +1,176 @@
+use gitbutler_branch_actions::{
+    cache_workdir_diff, invalidate_workdir_cache, StatusInput, StatusPhase,
+};
+use gitbutler_diff::RediffReason;
+
+use super::*;
+
+fn changed_files(controller: &VirtualBranchActions, project: &Project) -> Vec<PathBuf> {
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let mut files: Vec<_> = branches
+        .into_iter()
+        .flat_map(|branch| branch.files)
+        .map(|file| file.path)
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn only_invalidated_files_are_diffed_again() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let _cache = cache_workdir_diff(project.id);
+
+    fs::write(repository.path().join("a.txt"), "a\n").unwrap();
+    assert_eq!(changed_files(controller, project), [PathBuf::from("a.txt")]);
+    // Creating the default branch rewrote the index, which causes a full diff.
+    assert_eq!(changed_files(controller, project), [PathBuf::from("a.txt")]);
+
+    fs::write(repository.path().join("b.txt"), "b\n").unwrap();
+    assert_eq!(
+        changed_files(controller, project),
+        [PathBuf::from("a.txt")],
+        "the new file wasn't reported yet"
+    );
+
+    invalidate_workdir_cache(project.id, [PathBuf::from("b.txt")]);
+    assert_eq!(
+        changed_files(controller, project),
+        [PathBuf::from("a.txt"), PathBuf::from("b.txt")]
+    );
+}
+
+#[test]
+fn files_with_changes_are_checked_for_modifications() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let _cache = cache_workdir_diff(project.id);
+
+    fs::write(repository.path().join("a.txt"), "a\n").unwrap();
+    assert_eq!(changed_files(controller, project), [PathBuf::from("a.txt")]);
+
+    fs::write(repository.path().join("a.txt"), "a\nmore\n").unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let hunks = &branches[0].files[0].hunks;
+    assert_eq!(hunks.len(), 1);
+    assert!(hunks[0].diff.to_string().contains("+more"));
+
+    fs::remove_file(repository.path().join("a.txt")).unwrap();
+    assert!(changed_files(controller, project).is_empty());
+}
+
+#[test]
+fn queries_read_from_the_same_status() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let _cache = cache_workdir_diff(project.id);
+
+    fs::write(repository.path().join("a.txt"), "a\n").unwrap();
+    let (branches, _) = controller.list_virtual_branches(project).unwrap();
+    let ownership = controller.workspace_ownership(project).unwrap();
+    let file = controller
+        .file_status(project, path::Path::new("a.txt"))
+        .unwrap()
+        .expect("the file has changes");
+    assert_eq!(file.generation, ownership.generation);
+    assert_eq!(file.branch_id, branches[0].id);
+    assert_eq!(ownership.branches[0].branch_id, branches[0].id);
+    assert_eq!(
+        ownership.branches[0].ownership.claims[0].file_path,
+        PathBuf::from("a.txt")
+    );
+
+    fs::write(repository.path().join("a.txt"), "a\nmore\n").unwrap();
+    let changed = controller
+        .file_status(project, path::Path::new("a.txt"))
+        .unwrap()
+        .expect("the file still has changes");
+    assert!(changed.generation > file.generation);
+    assert_eq!(
+        controller.workspace_ownership(project).unwrap().generation,
+        changed.generation
+    );
+    assert_eq!(
+        controller
+            .file_status(project, path::Path::new("b.txt"))
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn status_computations_can_be_traced() {
+    let Test {
+        repository,
+        project,
+        controller,
+        ..
+    } = &Test::default();
+
+    controller
+        .set_base_branch(project, &"refs/remotes/origin/master".parse().unwrap())
+        .unwrap();
+    let _cache = cache_workdir_diff(project.id);
+
+    fs::write(repository.path().join("a.txt"), "a\n").unwrap();
+    changed_files(controller, project);
+    assert!(
+        controller.status_traces(project).is_empty(),
+        "nothing is traced by default"
+    );
+
+    controller.set_status_tracing(project, true);
+    changed_files(controller, project);
+    fs::write(repository.path().join("b.txt"), "b\n").unwrap();
+    invalidate_workdir_cache(project.id, [PathBuf::from("b.txt")]);
+    changed_files(controller, project);
+
+    let traces = controller.status_traces(project);
+    let trace = traces.last().unwrap();
+    assert_eq!(trace.changed_inputs, [StatusInput::Worktree]);
+    assert_eq!(trace.files.len(), 2);
+    assert_eq!(trace.files[0].path, PathBuf::from("a.txt"));
+    assert_eq!(
+        trace.files[0].rediffed, None,
+        "a.txt was taken from the cache"
+    );
+    assert_eq!(trace.files[1].path, PathBuf::from("b.txt"));
+    assert_eq!(trace.files[1].rediffed, Some(RediffReason::WatcherEvent));
+    assert!(trace.files.iter().all(|file| file.branch_id.is_some()));
+    assert_eq!(
+        trace
+            .phases
+            .iter()
+            .map(|phase| phase.phase)
+            .collect::<Vec<_>>(),
+        [StatusPhase::DiffWorktree, StatusPhase::AssignHunks]
+    );
+
+    controller.set_status_tracing(project, false);
+    assert!(controller.status_traces(project).is_empty());
+}
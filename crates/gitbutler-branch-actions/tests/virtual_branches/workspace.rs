@@ -0,0 +1,66 @@
+use gitbutler_branch_actions::workspace::Workspace;
+
+use super::*;
+
+#[test]
+fn unpushed_branches_across_projects() {
+    let tests = [Test::default(), Test::default()];
+    for test in &tests {
+        test.controller
+            .set_base_branch(
+                &test.project,
+                &"refs/remotes/origin/master".parse().unwrap(),
+            )
+            .unwrap();
+    }
+    let branch_id = tests[0]
+        .controller
+        .create_virtual_branch(&tests[0].project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(tests[0].repository.path().join("file.txt"), "content").unwrap();
+    tests[0]
+        .controller
+        .create_commit(&tests[0].project, branch_id, "commit", None, false)
+        .unwrap();
+    let workspace = Workspace::new(tests.iter().map(|test| test.project.clone()).collect());
+
+    let outcomes = workspace.unpushed_branches();
+    assert_eq!(outcomes.len(), 2);
+    assert_eq!(outcomes[0].project_id, tests[0].project_id);
+    let unpushed = outcomes[0].value.as_ref().unwrap();
+    assert_eq!(unpushed.len(), 1);
+    assert_eq!(unpushed[0].branch_id, branch_id);
+    assert_eq!(unpushed[0].unpushed_commits, 1);
+    assert_eq!(unpushed[0].upstream, None);
+    assert_eq!(outcomes[1].value, Some(Vec::new()));
+
+    tests[0]
+        .controller
+        .push_virtual_branch(&tests[0].project, branch_id, false, None)
+        .unwrap();
+    let outcomes = workspace.unpushed_branches();
+    assert_eq!(
+        outcomes[0].value,
+        Some(Vec::new()),
+        "pushed branches have nothing left to push"
+    );
+}
+
+#[test]
+fn fetch_all_reports_each_project() {
+    let test = Test::default();
+    let missing = Project {
+        path: test.data_dir.as_ref().unwrap().path().join("missing"),
+        ..Default::default()
+    };
+    let workspace = Workspace::new(vec![test.project.clone(), missing]);
+
+    let outcomes = workspace.fetch_all(None);
+    assert_eq!(outcomes.len(), 2);
+    assert!(outcomes[0].error.is_none(), "{:?}", outcomes[0].error);
+    assert!(outcomes[0].value.is_some());
+    assert!(
+        outcomes[1].error.is_some(),
+        "a project that can't be fetched doesn't keep the others from being fetched"
+    );
+}
@@ -0,0 +1,96 @@
+use gitbutler_branch::GITBUTLER_INTEGRATION_REFERENCE;
+
+use super::*;
+
+fn workspace_with_a_commit(test: &Test) {
+    test.controller
+        .set_base_branch(
+            &test.project,
+            &"refs/remotes/origin/master".parse().unwrap(),
+        )
+        .unwrap();
+    let branch_id = test
+        .controller
+        .create_virtual_branch(&test.project, &BranchCreateRequest::default())
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "content\n").unwrap();
+    test.controller
+        .create_commit(&test.project, branch_id, "commit", None, false)
+        .unwrap();
+    fs::write(test.repository.path().join("file.txt"), "changed\n").unwrap();
+    test.controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+}
+
+#[test]
+fn a_workspace_in_sync_has_nothing_to_report() {
+    let test = Test::default();
+    workspace_with_a_commit(&test);
+
+    let desync = test.controller.check_workspace(&test.project).unwrap();
+    assert!(desync.is_empty(), "{desync:?}");
+}
+
+#[test]
+fn changes_to_the_worktree_are_reported_until_the_branches_are_listed() {
+    let test = Test::default();
+    workspace_with_a_commit(&test);
+
+    fs::write(test.repository.path().join("external.txt"), "external\n").unwrap();
+    let desync = test.controller.check_workspace(&test.project).unwrap();
+    assert_eq!(desync.worktree_paths, [PathBuf::from("external.txt")]);
+    assert!(desync.divergences.is_empty());
+    assert!(desync.integration_paths.is_empty());
+
+    test.controller
+        .list_virtual_branches(&test.project)
+        .unwrap();
+    assert!(test
+        .controller
+        .check_workspace(&test.project)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn an_integration_commit_with_other_content_is_reported() {
+    let test = Test::default();
+    workspace_with_a_commit(&test);
+
+    // Rewrite the integration commit with the same parents but other content, like another tool might.
+    let repo = git2::Repository::open(test.repository.path()).unwrap();
+    let integration = repo
+        .find_reference(&GITBUTLER_INTEGRATION_REFERENCE.to_string())
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    let blob = repo.blob(b"rewritten\n").unwrap();
+    let mut tree = repo
+        .treebuilder(Some(&integration.tree().unwrap()))
+        .unwrap();
+    tree.insert("file.txt", blob, 0o100644).unwrap();
+    let tree = repo.find_tree(tree.write().unwrap()).unwrap();
+    let parents: Vec<_> = integration.parents().collect();
+    let rewritten = repo
+        .commit(
+            None,
+            &integration.author(),
+            &integration.committer(),
+            integration.message().unwrap(),
+            &tree,
+            &parents.iter().collect::<Vec<_>>(),
+        )
+        .unwrap();
+    repo.reference(
+        &GITBUTLER_INTEGRATION_REFERENCE.to_string(),
+        rewritten,
+        true,
+        "rewrite",
+    )
+    .unwrap();
+
+    let desync = test.controller.check_workspace(&test.project).unwrap();
+    assert!(desync.divergences.is_empty());
+    assert_eq!(desync.integration_paths, [PathBuf::from("file.txt")]);
+}
@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use gitbutler_fs::TomlFile;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use serde::{Deserialize, Serialize};
+
+use crate::BranchId;
+
+/// The maximum amount of events kept per branch. Older events are dropped first.
+const MAX_EVENTS_PER_BRANCH: usize = 500;
+
+/// Something that happened to a virtual branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum BranchEventKind {
+    /// A commit was created on the branch.
+    CommitCreated {
+        #[serde(with = "gitbutler_serde::oid")]
+        commit: git2::Oid,
+    },
+    /// The branch was pushed to `remote`, a remote tracking reference like `refs/remotes/origin/feature`.
+    Pushed {
+        #[serde(with = "gitbutler_serde::oid")]
+        head: git2::Oid,
+        remote: String,
+    },
+    /// The commits of the branch were rewritten, moving its head.
+    Rebased {
+        #[serde(rename = "oldHead", with = "gitbutler_serde::oid")]
+        old_head: git2::Oid,
+        #[serde(rename = "newHead", with = "gitbutler_serde::oid")]
+        new_head: git2::Oid,
+    },
+    /// Hunks in `paths` were moved from one branch to another.
+    HunksMoved {
+        from: BranchId,
+        to: BranchId,
+        paths: Vec<PathBuf>,
+    },
+}
+
+/// An entry in the activity feed of a branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchEvent {
+    /// A number that is greater than the cursor of all events recorded before it, across all branches.
+    pub cursor: u64,
+    /// The branch the event belongs to.
+    pub branch_id: BranchId,
+    /// The time at which the event was recorded, in milliseconds since the Unix epoch.
+    pub timestamp_ms: i64,
+    /// What happened.
+    pub event: BranchEventKind,
+}
+
+/// The activity of all branches, as persisted in a TOML file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BranchActivity {
+    /// The cursor to assign to the next event.
+    next_cursor: u64,
+    /// All retained events, in the order they were recorded.
+    events: Vec<BranchEvent>,
+}
+
+/// A handle to the activity feed of virtual branches.
+///
+/// The events are kept in `branch_activity.toml`, and the feed is empty until the first event is recorded.
+pub struct BranchActivityHandle {
+    /// The file containing the activity of all branches.
+    file: TomlFile<BranchActivity>,
+}
+
+impl BranchActivityHandle {
+    /// Creates a new handle to the activity of virtual branches stored in `base_path`.
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        Self {
+            file: TomlFile::new(base_path.as_ref().join("branch_activity.toml")),
+        }
+    }
+
+    /// Records `event` for the branch identified by `branch_id` and returns it along with its cursor.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn record(&self, branch_id: BranchId, event: BranchEventKind) -> Result<BranchEvent> {
+        let mut activity = self.file.read()?;
+        let event = BranchEvent {
+            cursor: activity.next_cursor,
+            branch_id,
+            timestamp_ms: now_since_unix_epoch_ms(),
+            event,
+        };
+        activity.next_cursor += 1;
+        activity.events.push(event.clone());
+
+        let events_of_branch = activity
+            .events
+            .iter()
+            .filter(|event| event.branch_id == branch_id)
+            .count();
+        if let Some(mut excess) = events_of_branch.checked_sub(MAX_EVENTS_PER_BRANCH) {
+            activity.events.retain(|event| {
+                let drop = excess > 0 && event.branch_id == branch_id;
+                if drop {
+                    excess -= 1;
+                }
+                !drop
+            });
+        }
+
+        self.file.write(&activity)?;
+        Ok(event)
+    }
+
+    /// Returns all retained events of the branch identified by `branch_id` that were recorded after
+    /// the event at `cursor`, or all of them if `cursor` is `None`, oldest first.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn events_since(
+        &self,
+        branch_id: BranchId,
+        cursor: Option<u64>,
+    ) -> Result<Vec<BranchEvent>> {
+        let activity = self.file.read()?;
+        Ok(activity
+            .events
+            .into_iter()
+            .filter(|event| event.branch_id == branch_id)
+            .filter(|event| cursor.map_or(true, |cursor| event.cursor > cursor))
+            .collect())
+    }
+
+    /// Removes all events of the branch identified by `branch_id`, without reusing their cursors.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn forget_branch(&self, branch_id: BranchId) -> Result<()> {
+        let mut activity = self.file.read()?;
+        activity.events.retain(|event| event.branch_id != branch_id);
+        self.file.write(&activity)
+    }
+}
@@ -3,7 +3,7 @@ use bstr::{BStr, ByteSlice};
 use gitbutler_id::id::Id;
 use gitbutler_reference::{normalize_branch_name, Refname, RemoteRefname, VirtualRefname};
 use serde::{Deserialize, Serialize, Serializer};
-use std::ops::Deref;
+use std::{collections::BTreeMap, ops::Deref, path::Path};
 
 use crate::ownership::BranchOwnershipClaims;
 
@@ -67,6 +67,48 @@ pub struct Branch {
     pub in_workspace: bool,
     #[serde(default)]
     pub not_in_workspace_wip_change_id: Option<String>,
+    /// If set, the commit the branch is based on instead of the target, like a release tag.
+    /// The branch then doesn't move along when the target is updated.
+    #[serde(with = "gitbutler_serde::oid_opt", default)]
+    pub pinned_base: Option<git2::Oid>,
+    /// If set, the virtual branch this branch is stacked on. Its pinned base is then the head of the parent,
+    /// and it's rebased along whenever the parent moves.
+    #[serde(default)]
+    pub parent: Option<BranchId>,
+    /// If set, the remote the branch is pushed to instead of the push remote of the target,
+    /// like a fork while the target is fetched from `upstream`.
+    #[serde(default)]
+    pub push_remote_name: Option<String>,
+    /// Patterns of the paths, relative to the worktree, the branch may have changes in, like `docs/` for
+    /// a branch that only changes documentation. All paths are allowed if there are none.
+    /// See [`Branch::allows_path()`] for how they match.
+    #[serde(default)]
+    pub allowed_paths: Vec<String>,
+    /// Values that automation, like ticket bots, keeps on the branch, as JSON text by key.
+    /// Kept as text as TOML can't represent all JSON values, like `null`.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// The draft description of the pull request of the branch, in Markdown.
+    #[serde(default)]
+    pub description: String,
+    /// The users to request reviews of the pull request of the branch from, by their name on the forge.
+    #[serde(default)]
+    pub reviewers: Vec<String>,
+    /// Set once the pull request of the branch was seen merged on the forge. The next base update that
+    /// brings in the merge then takes the branch as integrated, even if the target changed it since.
+    #[serde(default)]
+    pub forge_merge: Option<ForgeMerge>,
+}
+
+/// The merge of the pull request of a branch on the forge.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ForgeMerge {
+    /// The head of the branch when the merge was seen, which is what was merged as long as it's still the head.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub head: git2::Oid,
+    /// The commit the pull request was merged as into its base, like a merge or squash commit.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub merge_commit: git2::Oid,
 }
 
 fn default_true() -> bool {
@@ -106,6 +148,50 @@ impl Branch {
     pub fn is_old_unapplied(&self) -> bool {
         !self.applied && self.in_workspace
     }
+
+    /// The commit the branch is based on, which is its pinned base if it has one, or `target_sha` otherwise.
+    pub fn base(&self, target_sha: git2::Oid) -> git2::Oid {
+        self.pinned_base.unwrap_or(target_sha)
+    }
+
+    /// Return `true` if changes to `path`, relative to the worktree, may be assigned to this branch,
+    /// which is if it has no allowed paths or one of them matches `path` or one of its directories.
+    ///
+    /// `*` in a pattern doesn't match across directories while `**` does, and patterns without a `/`
+    /// are matched against file and directory names anywhere, like `*.md`.
+    pub fn allows_path(&self, path: &Path) -> bool {
+        self.allowed_paths.is_empty()
+            || self
+                .allowed_paths
+                .iter()
+                .any(|pattern| path_matches(pattern, path))
+    }
+}
+
+/// Return `true` if `pattern` matches `path`, relative to the worktree, or one of its directories, as described
+/// in [`Branch::allows_path()`].
+pub fn path_matches(pattern: &str, path: &Path) -> bool {
+    // Like in `.gitignore`, a leading `/` only anchors the pattern to the worktree.
+    let pattern = pattern.trim_end_matches('/');
+    let match_names = !pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    path.ancestors()
+        .filter(|path| !path.as_os_str().is_empty())
+        .any(|path| {
+            let matches = |value: &Path| {
+                let value = gix::path::to_unix_separators_on_windows(gix::path::into_bstr(value));
+                gix::glob::wildmatch(
+                    pattern.into(),
+                    value.as_ref(),
+                    gix::glob::wildmatch::Mode::NO_MATCH_SLASH_LITERAL,
+                )
+            };
+            matches(path)
+                || (match_names
+                    && path
+                        .file_name()
+                        .map_or(false, |name| matches(Path::new(name))))
+        })
 }
 
 impl TryFrom<&Branch> for VirtualRefname {
@@ -128,6 +214,9 @@ pub struct BranchUpdateRequest {
     pub upstream: Option<String>, // just the branch name, so not refs/remotes/origin/branchA, just branchA
     pub selected_for_changes: Option<bool>,
     pub allow_rebasing: Option<bool>,
+    pub allowed_paths: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub reviewers: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -136,6 +225,11 @@ pub struct BranchCreateRequest {
     pub ownership: Option<BranchOwnershipClaims>,
     pub order: Option<usize>,
     pub selected_for_changes: Option<bool>,
+    /// The virtual branch to stack the new branch on, if any.
+    pub parent: Option<BranchId>,
+    /// What the branch is for, like the title of a ticket, to let the branch naming of the project
+    /// name it if no `name` is given.
+    pub summary: Option<String>,
 }
 
 /// The identity of a branch as to allow to group similar branches together.
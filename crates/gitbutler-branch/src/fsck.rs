@@ -0,0 +1,189 @@
+//! Find and repair inconsistencies of the state of virtual branches with the repository, like ownership claims
+//! of files that don't exist anywhere or branches whose commits are gone.
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{branch::BranchId, VirtualBranchesHandle};
+
+/// An inconsistency of the state of virtual branches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum FsckIssue {
+    /// The state file doesn't match its checksum, so it was changed outside of GitButler or damaged.
+    /// It's repaired by writing it with a new checksum, if it can still be read.
+    ChecksumMismatch,
+    /// The commit the workspace is based on doesn't exist, which can't be repaired.
+    #[serde(rename_all = "camelCase")]
+    MissingTargetCommit {
+        #[serde(with = "gitbutler_serde::oid")]
+        commit: git2::Oid,
+    },
+    /// A target is kept for a branch that doesn't exist. It's repaired by removing the target.
+    #[serde(rename_all = "camelCase")]
+    OrphanedBranchTarget { branch_id: BranchId },
+    /// The head of a branch doesn't exist. It's repaired by resetting the branch to the target, which keeps
+    /// its uncommitted changes.
+    #[serde(rename_all = "camelCase")]
+    MissingHead {
+        branch_id: BranchId,
+        #[serde(with = "gitbutler_serde::oid")]
+        commit: git2::Oid,
+    },
+    /// The tree with the uncommitted changes of a branch doesn't exist. It's repaired by using the tree of
+    /// its head, which drops these changes from the branch, but not from the worktree.
+    #[serde(rename_all = "camelCase")]
+    MissingTree {
+        branch_id: BranchId,
+        #[serde(with = "gitbutler_serde::oid")]
+        tree: git2::Oid,
+    },
+    /// The commit a branch is pinned to doesn't exist. It's repaired by unpinning the branch.
+    #[serde(rename_all = "camelCase")]
+    MissingPinnedBase {
+        branch_id: BranchId,
+        #[serde(with = "gitbutler_serde::oid")]
+        commit: git2::Oid,
+    },
+    /// A branch claims changes of a file that's neither in the worktree, nor in the target or the head of
+    /// the branch, so there can't be any. It's repaired by removing the claim.
+    #[serde(rename_all = "camelCase")]
+    StaleClaim { branch_id: BranchId, path: PathBuf },
+}
+
+impl FsckIssue {
+    /// Return `true` if [`fsck()`] can repair this issue.
+    pub fn is_repairable(&self) -> bool {
+        !matches!(self, FsckIssue::MissingTargetCommit { .. })
+    }
+}
+
+/// What [`fsck()`] found.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsckReport {
+    pub issues: Vec<FsckIssue>,
+    /// Whether the repairable issues were repaired, after backing up the state file.
+    pub repaired: bool,
+}
+
+/// Check the state of virtual branches in `handle` against `repo`, and repair what can be if `repair` is
+/// set and there is anything to repair. The state file is backed up before it's repaired.
+pub fn fsck(
+    handle: &VirtualBranchesHandle,
+    repo: &git2::Repository,
+    repair: bool,
+) -> Result<FsckReport> {
+    let (mut state, checksum_matches) = handle.read_file_unchecked()?;
+    let mut issues = Vec::new();
+    if !checksum_matches {
+        issues.push(FsckIssue::ChecksumMismatch);
+    }
+
+    let target_tree = match &state.default_target {
+        Some(target) => match repo.find_commit(target.sha) {
+            Ok(commit) => Some(commit.tree()?),
+            Err(_) => {
+                issues.push(FsckIssue::MissingTargetCommit { commit: target.sha });
+                None
+            }
+        },
+        None => None,
+    };
+    let target_sha = target_tree
+        .as_ref()
+        .and(state.default_target.as_ref())
+        .map(|target| target.sha);
+
+    let branch_ids: Vec<BranchId> = state.branches.keys().copied().collect();
+    let orphaned_targets: Vec<BranchId> = state
+        .branch_targets
+        .keys()
+        .filter(|id| !branch_ids.contains(id))
+        .copied()
+        .collect();
+    for branch_id in orphaned_targets {
+        issues.push(FsckIssue::OrphanedBranchTarget { branch_id });
+        if repair {
+            state.branch_targets.remove(&branch_id);
+        }
+    }
+
+    let workdir = repo.workdir();
+    let mut branches: Vec<_> = state.branches.values_mut().collect();
+    branches.sort_by_key(|branch| branch.order);
+    for branch in branches {
+        let branch_id = branch.id;
+        let mut head_commit = repo.find_commit(branch.head).ok();
+        if head_commit.is_none() {
+            issues.push(FsckIssue::MissingHead {
+                branch_id,
+                commit: branch.head,
+            });
+            if let Some(sha) = target_sha.filter(|_| repair) {
+                branch.head = sha;
+                head_commit = repo.find_commit(sha).ok();
+            }
+        }
+        if repo.find_tree(branch.tree).is_err() {
+            issues.push(FsckIssue::MissingTree {
+                branch_id,
+                tree: branch.tree,
+            });
+            if let Some(head_commit) = head_commit.as_ref().filter(|_| repair) {
+                branch.tree = head_commit.tree_id();
+            }
+        }
+        if let Some(pinned_base) = branch.pinned_base {
+            if repo.find_commit(pinned_base).is_err() {
+                issues.push(FsckIssue::MissingPinnedBase {
+                    branch_id,
+                    commit: pinned_base,
+                });
+                if repair {
+                    branch.pinned_base = None;
+                }
+            }
+        }
+
+        let head_tree = head_commit.map(|commit| commit.tree()).transpose()?;
+        let stale_paths: Vec<PathBuf> = branch
+            .ownership
+            .claims
+            .iter()
+            .map(|claim| claim.file_path.clone())
+            .filter(|path| {
+                let in_worktree = workdir.map_or(true, |workdir| workdir.join(path).exists());
+                !in_worktree
+                    && !in_tree(target_tree.as_ref(), path)
+                    && !in_tree(head_tree.as_ref(), path)
+            })
+            .collect();
+        for path in stale_paths {
+            if repair {
+                branch
+                    .ownership
+                    .claims
+                    .retain(|claim| claim.file_path != path);
+            }
+            issues.push(FsckIssue::StaleClaim { branch_id, path });
+        }
+    }
+
+    let repaired = repair && issues.iter().any(FsckIssue::is_repairable);
+    if repaired {
+        let backup_path = handle.backup_path("fsck");
+        if let Ok(contents) = handle.raw_state() {
+            gitbutler_fs::write(&backup_path, contents)
+                .context("failed to back up the virtual branches before repairing them")?;
+        }
+        handle.write_file(&state)?;
+        tracing::info!(?issues, backup = %backup_path.display(), "repaired virtual branches");
+    }
+    Ok(FsckReport { issues, repaired })
+}
+
+fn in_tree(tree: Option<&git2::Tree>, path: &Path) -> bool {
+    tree.is_some_and(|tree| tree.get_path(path).is_ok())
+}
@@ -0,0 +1,137 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use gitbutler_diff::Hunk;
+use gitbutler_fs::TomlFile;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use serde::{Deserialize, Serialize};
+
+/// A note attached to an uncommitted hunk, like `needs test`.
+///
+/// Notes are keyed by file and hunk hash, not by branch, so they stay with a hunk when it's
+/// moved to another branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkNote {
+    /// The path of the file the hunk is in, relative to the worktree.
+    pub file_path: PathBuf,
+    /// The hunk as last seen, like `3-7-<hash>` in ownership claims.
+    pub hunk: String,
+    pub text: String,
+    /// The time at which the note was last changed, in milliseconds since the Unix epoch.
+    pub updated_timestamp_ms: i64,
+}
+
+impl HunkNote {
+    fn parsed_hunk(&self) -> Option<Hunk> {
+        self.hunk.parse().ok()
+    }
+}
+
+/// All hunk notes, as persisted in a TOML file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HunkNotes {
+    notes: Vec<HunkNote>,
+}
+
+/// A handle to the notes attached to uncommitted hunks.
+///
+/// The notes are kept in `hunk_notes.toml`, and hunks have no notes until one is set.
+pub struct HunkNotesHandle {
+    /// The file containing all hunk notes.
+    file: TomlFile<HunkNotes>,
+}
+
+impl HunkNotesHandle {
+    /// Creates a new handle to the hunk notes stored in `base_path`.
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        Self {
+            file: TomlFile::new(base_path.as_ref().join("hunk_notes.toml")),
+        }
+    }
+
+    /// Returns all notes.
+    ///
+    /// Errors if the file cannot be read.
+    pub fn list(&self) -> Result<Vec<HunkNote>> {
+        Ok(self.file.read()?.notes)
+    }
+
+    /// Attaches `text` to `hunk` in the file at `file_path`, replacing a previous note, or removes
+    /// the note if `text` is `None`. `hunk` must have a hash to identify it.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn set(&self, file_path: &Path, hunk: &Hunk, text: Option<String>) -> Result<()> {
+        if hunk.hash.is_none() {
+            return Err(anyhow!("hunk {hunk} needs a hash to be annotated"));
+        }
+        let mut notes = self.file.read()?;
+        notes.notes.retain(|note| {
+            note.file_path != file_path || note.parsed_hunk().as_ref() != Some(hunk)
+        });
+        if let Some(text) = text {
+            notes.notes.push(HunkNote {
+                file_path: file_path.to_owned(),
+                hunk: hunk.to_string(),
+                text,
+                updated_timestamp_ms: now_since_unix_epoch_ms(),
+            });
+        }
+        self.file.write(&notes)
+    }
+
+    /// Match each note to one of the current `hunks` of its file, and return the matches.
+    ///
+    /// A note matches the hunk with the same hash, or otherwise the only hunk overlapping the lines it was last
+    /// seen on, so notes survive small edits to their hunk. Notes are updated to refer to the hunk they matched,
+    /// and notes that didn't match any hunk, for instance because it was committed or discarded, are dropped.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn reconcile<'a>(
+        &self,
+        hunks: impl IntoIterator<Item = (&'a Path, &'a Hunk)>,
+    ) -> Result<Vec<HunkNote>> {
+        let mut notes = self.file.read()?;
+        if notes.notes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let hunks: Vec<_> = hunks.into_iter().collect();
+        let mut changed = false;
+        notes.notes.retain_mut(|note| {
+            let Some(noted) = note.parsed_hunk() else {
+                changed = true;
+                return false;
+            };
+            let file_path = note.file_path.clone();
+            let in_file = || {
+                hunks
+                    .iter()
+                    .filter(|(path, _)| *path == file_path.as_path())
+            };
+            let same_hash = in_file().find(|(_, hunk)| hunk.hash == noted.hash);
+            let overlapping = || {
+                let mut overlapping = in_file()
+                    .filter(|(_, hunk)| hunk.start <= noted.end && noted.start <= hunk.end);
+                overlapping.next().filter(|_| overlapping.next().is_none())
+            };
+            match same_hash.or_else(overlapping) {
+                Some((_, hunk)) => {
+                    let hunk = hunk.to_string();
+                    if note.hunk != hunk {
+                        note.hunk = hunk;
+                        changed = true;
+                    }
+                    true
+                }
+                None => {
+                    changed = true;
+                    false
+                }
+            }
+        });
+        if changed {
+            self.file.write(&notes)?;
+        }
+        Ok(notes.notes)
+    }
+}
@@ -0,0 +1,171 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use gitbutler_diff::Hunk;
+use gitbutler_fs::TomlFile;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use serde::{Deserialize, Serialize};
+
+use crate::BranchId;
+
+/// An uncommitted hunk that always belongs to a branch, so it's never assigned to another one, be it
+/// when the status is computed or after the branches were rebased.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkPin {
+    /// The path of the file the hunk is in, relative to the worktree.
+    pub file_path: PathBuf,
+    /// The hunk as last seen, like `3-7-<hash>` in ownership claims.
+    pub hunk: String,
+    pub branch_id: BranchId,
+    /// The time at which the hunk was pinned, in milliseconds since the Unix epoch.
+    pub created_timestamp_ms: i64,
+}
+
+impl HunkPin {
+    fn parsed_hunk(&self) -> Option<Hunk> {
+        self.hunk.parse().ok()
+    }
+
+    /// Return `true` if `hunk` of the file at `file_path` is the pinned hunk, as it has its hash or,
+    /// lacking one, overlaps the lines it was last seen on.
+    pub fn covers(&self, file_path: &Path, hunk: &Hunk) -> bool {
+        let Some(pinned) = self.parsed_hunk() else {
+            return false;
+        };
+        self.file_path == file_path
+            && match hunk.hash {
+                Some(_) => hunk.hash == pinned.hash,
+                None => hunk.start <= pinned.end && pinned.start <= hunk.end,
+            }
+    }
+}
+
+/// All hunk pins, as persisted in a TOML file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HunkPins {
+    pins: Vec<HunkPin>,
+}
+
+/// A handle to the uncommitted hunks that are pinned to a branch.
+///
+/// The pins are kept in `hunk_pins.toml`, and hunks are assigned as usual until one is pinned.
+pub struct HunkPinsHandle {
+    /// The file containing all hunk pins.
+    file: TomlFile<HunkPins>,
+}
+
+impl HunkPinsHandle {
+    /// Creates a new handle to the hunk pins stored in `base_path`.
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        Self {
+            file: TomlFile::new(base_path.as_ref().join("hunk_pins.toml")),
+        }
+    }
+
+    /// Returns all pins.
+    ///
+    /// Errors if the file cannot be read.
+    pub fn list(&self) -> Result<Vec<HunkPin>> {
+        Ok(self.file.read()?.pins)
+    }
+
+    /// Pins `hunk` in the file at `file_path` to the branch with `branch_id`, replacing a previous pin of
+    /// the hunk. `hunk` must have a hash to identify it.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn pin(&self, file_path: &Path, hunk: &Hunk, branch_id: BranchId) -> Result<()> {
+        if hunk.hash.is_none() {
+            return Err(anyhow!("hunk {hunk} needs a hash to be pinned"));
+        }
+        let mut pins = self.file.read()?;
+        pins.pins
+            .retain(|pin| pin.file_path != file_path || pin.parsed_hunk().as_ref() != Some(hunk));
+        pins.pins.push(HunkPin {
+            file_path: file_path.to_owned(),
+            hunk: hunk.to_string(),
+            branch_id,
+            created_timestamp_ms: now_since_unix_epoch_ms(),
+        });
+        self.file.write(&pins)
+    }
+
+    /// Removes the pin of `hunk` in the file at `file_path`, returning `true` if there was one.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn unpin(&self, file_path: &Path, hunk: &Hunk) -> Result<bool> {
+        let mut pins = self.file.read()?;
+        let len = pins.pins.len();
+        pins.pins.retain(|pin| !pin.covers(file_path, hunk));
+        if pins.pins.len() == len {
+            return Ok(false);
+        }
+        self.file.write(&pins)?;
+        Ok(true)
+    }
+
+    /// Match each pin of the branches in `branch_ids` to one of the current `hunks` of its file, and return
+    /// the pins that matched.
+    ///
+    /// A pin matches the hunk with the same hash, or otherwise the only hunk overlapping the lines it was last
+    /// seen on, so pins survive edits to their hunk. Pins are updated to refer to the hunk they matched, and
+    /// pins that didn't match any hunk, for instance because it was committed or discarded, are dropped.
+    /// Pins of other branches are kept as they are, as their hunks aren't in the worktree.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn reconcile<'a>(
+        &self,
+        hunks: impl IntoIterator<Item = (&'a Path, &'a Hunk)>,
+        branch_ids: &[BranchId],
+    ) -> Result<Vec<HunkPin>> {
+        let mut pins = self.file.read()?;
+        if pins.pins.is_empty() {
+            return Ok(Vec::new());
+        }
+        let hunks: Vec<_> = hunks.into_iter().collect();
+        let mut changed = false;
+        pins.pins.retain_mut(|pin| {
+            if !branch_ids.contains(&pin.branch_id) {
+                return true;
+            }
+            let Some(pinned) = pin.parsed_hunk() else {
+                changed = true;
+                return false;
+            };
+            let file_path = pin.file_path.clone();
+            let in_file = || {
+                hunks
+                    .iter()
+                    .filter(|(path, _)| *path == file_path.as_path())
+            };
+            let same_hash = in_file().find(|(_, hunk)| hunk.hash == pinned.hash);
+            let overlapping = || {
+                let mut overlapping = in_file()
+                    .filter(|(_, hunk)| hunk.start <= pinned.end && pinned.start <= hunk.end);
+                overlapping.next().filter(|_| overlapping.next().is_none())
+            };
+            match same_hash.or_else(overlapping) {
+                Some((_, hunk)) => {
+                    let hunk = hunk.to_string();
+                    if pin.hunk != hunk {
+                        pin.hunk = hunk;
+                        changed = true;
+                    }
+                    true
+                }
+                None => {
+                    changed = true;
+                    false
+                }
+            }
+        });
+        if changed {
+            self.file.write(&pins)?;
+        }
+        Ok(pins
+            .pins
+            .into_iter()
+            .filter(|pin| branch_ids.contains(&pin.branch_id))
+            .collect())
+    }
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use anyhow::Result;
+use gitbutler_fs::TomlFile;
+use serde::{Deserialize, Serialize};
+
+/// A local branch that was seen to be integrated into the target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegratedBranch {
+    /// The name of the branch, like `feature` for `refs/heads/feature`.
+    pub name: String,
+    /// The commit the branch pointed to when it was first seen to be integrated.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub head: git2::Oid,
+    /// The time at which the branch was first seen to be integrated, in milliseconds since the Unix epoch.
+    pub integrated_since_ms: i64,
+}
+
+/// All integrated branches, as persisted in a TOML file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IntegratedBranches {
+    branches: Vec<IntegratedBranch>,
+}
+
+/// A handle to the record of since when local branches are integrated into the target.
+///
+/// The branches are kept in `integrated_branches.toml`, which is only rewritten once they change.
+pub struct IntegratedBranchesHandle {
+    /// The file containing all integrated branches.
+    file: TomlFile<IntegratedBranches>,
+}
+
+impl IntegratedBranchesHandle {
+    /// Creates a new handle to the integrated branches stored in `base_path`.
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        Self {
+            file: TomlFile::new(base_path.as_ref().join("integrated_branches.toml")),
+        }
+    }
+
+    /// Record that exactly the branches in `integrated`, given by name and head, are integrated at `now_ms`,
+    /// and return them along with the time they were first seen to be integrated.
+    ///
+    /// Branches that were seen before with the same head keep their time, all others start at `now_ms`.
+    /// Branches that aren't integrated anymore are forgotten.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn observe(
+        &self,
+        integrated: impl IntoIterator<Item = (String, git2::Oid)>,
+        now_ms: i64,
+    ) -> Result<Vec<IntegratedBranch>> {
+        let previous = self.file.read()?.branches;
+        let branches: Vec<_> = integrated
+            .into_iter()
+            .map(|(name, head)| {
+                previous
+                    .iter()
+                    .find(|branch| branch.name == name && branch.head == head)
+                    .cloned()
+                    .unwrap_or(IntegratedBranch {
+                        name,
+                        head,
+                        integrated_since_ms: now_ms,
+                    })
+            })
+            .collect();
+        if branches != previous {
+            self.file.write(&IntegratedBranches {
+                branches: branches.clone(),
+            })?;
+        }
+        Ok(branches)
+    }
+}
@@ -1,7 +1,10 @@
 mod branch;
 
 use anyhow::Context;
-pub use branch::{Branch, BranchCreateRequest, BranchId, BranchIdentity, BranchUpdateRequest};
+pub use branch::{
+    path_matches, Branch, BranchCreateRequest, BranchId, BranchIdentity, BranchUpdateRequest,
+    ForgeMerge,
+};
 use bstr::ByteSlice;
 mod branch_ext;
 pub use branch_ext::BranchExt;
@@ -17,9 +20,38 @@ pub mod serde;
 mod target;
 pub use target::Target;
 
+mod activity;
+pub use activity::{BranchActivityHandle, BranchEvent, BranchEventKind};
+
+mod hunk_notes;
+pub use hunk_notes::{HunkNote, HunkNotesHandle};
+
+mod hunk_pins;
+pub use hunk_pins::{HunkPin, HunkPinsHandle};
+
+mod provenance;
+pub use provenance::{CommitProvenance, CommittedHunk, ProvenanceHandle};
+
+mod integrated;
+pub use integrated::{IntegratedBranch, IntegratedBranchesHandle};
+
+mod shelf;
+pub use shelf::{Shelf, ShelfId, ShelvedFile, ShelvedHunk, ShelvesHandle};
+
+mod trash;
+pub use trash::{TrashEntry, TrashEntryId, TrashHandle, TrashOrigin};
+
+mod operation_queue;
+pub use operation_queue::{
+    OperationQueueHandle, QueuedOperation, QueuedOperationId, QueuedOperationKind,
+};
+
 mod state;
 use lazy_static::lazy_static;
-pub use state::{VirtualBranches as VirtualBranchesState, VirtualBranchesHandle};
+pub use state::{VirtualBranches as VirtualBranchesState, VirtualBranchesHandle, STATE_VERSION};
+
+mod fsck;
+pub use fsck::{fsck, FsckIssue, FsckReport};
 lazy_static! {
     pub static ref GITBUTLER_INTEGRATION_REFERENCE: gitbutler_reference::LocalRefname =
         gitbutler_reference::LocalRefname::new("gitbutler/integration", None);
@@ -68,10 +100,25 @@ pub fn gix_to_git2_signature(
 }
 
 /// Return the time of a commit as `now` unless the `overriding_variable_name` contains a parseable date,
-/// which is used instead.
+/// which is used instead. A replaced [clock](gitbutler_time::clock) takes precedence over both.
 fn commit_time(overriding_variable_name: &str) -> gix::date::Time {
+    if gitbutler_time::clock::is_overridden() {
+        return current_time();
+    }
     std::env::var(overriding_variable_name)
         .ok()
         .and_then(|time| gix::date::parse(&time, Some(std::time::SystemTime::now())).ok())
-        .unwrap_or_else(gix::date::Time::now_local_or_utc)
+        .unwrap_or_else(current_time)
+}
+
+/// Return the current time according to the [clock](gitbutler_time::clock) in use, in the local time zone
+/// unless the clock was replaced, which is then assumed to be in UTC so the result is the same everywhere.
+pub fn current_time() -> gix::date::Time {
+    if !gitbutler_time::clock::is_overridden() {
+        return gix::date::Time::now_local_or_utc();
+    }
+    let seconds = gitbutler_time::clock::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs() as i64);
+    gix::date::Time::new(seconds, 0)
 }
@@ -0,0 +1,139 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use gitbutler_fs::TomlFile;
+use gitbutler_id::id::Id;
+use serde::{Deserialize, Serialize};
+
+use crate::BranchId;
+
+pub type QueuedOperationId = Id<QueuedOperation>;
+
+/// An operation that needs the network, put off while it was unavailable to run once it's back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedOperation {
+    pub id: QueuedOperationId,
+    pub kind: QueuedOperationKind,
+    /// The time at which the operation was queued, in milliseconds since the Unix epoch.
+    pub queued_timestamp_ms: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum QueuedOperationKind {
+    /// Push the virtual branch identified by `branch_id`, as it is when the push runs.
+    Push {
+        branch_id: BranchId,
+        with_force: bool,
+        /// The commit the upstream branch was at as of the last fetch when the push was queued, or `None`
+        /// if there was no upstream branch. A forced push only replaces the upstream branch if it's still
+        /// there, as fetching before the push runs would otherwise hide what others pushed in the meantime.
+        #[serde(default, with = "gitbutler_serde::oid_opt")]
+        upstream_head: Option<git2::Oid>,
+    },
+    /// Fetch all remotes.
+    Fetch,
+}
+
+/// All queued operations, as persisted in a TOML file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OperationQueue {
+    /// The operations in the order they run in, which is the order they were queued in.
+    operations: Vec<QueuedOperation>,
+}
+
+/// A handle to the operations of a project that wait for the network to be available again.
+///
+/// The operations are kept in `operation_queue.toml`, so they survive restarts until they have run.
+pub struct OperationQueueHandle {
+    /// The file containing all queued operations.
+    file: TomlFile<OperationQueue>,
+}
+
+impl OperationQueueHandle {
+    /// Creates a new handle to the queue stored in `base_path`.
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        Self {
+            file: TomlFile::new(base_path.as_ref().join("operation_queue.toml")),
+        }
+    }
+
+    /// Returns all queued operations in the order they run in.
+    ///
+    /// Errors if the file cannot be read.
+    pub fn list(&self) -> Result<Vec<QueuedOperation>> {
+        Ok(self.file.read()?.operations)
+    }
+
+    /// Queues an operation of `kind` at `now_ms` and returns it.
+    ///
+    /// An operation that is queued already is returned instead, keeping its place. A push of a branch that is
+    /// queued already is forced if either of them is, expecting the upstream head of the first forced one.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn enqueue(&self, kind: QueuedOperationKind, now_ms: i64) -> Result<QueuedOperation> {
+        let mut queue = self.file.read()?;
+        let queued = queue
+            .operations
+            .iter_mut()
+            .find(|operation| match (operation.kind, kind) {
+                (
+                    QueuedOperationKind::Push { branch_id, .. },
+                    QueuedOperationKind::Push {
+                        branch_id: new_branch_id,
+                        ..
+                    },
+                ) => branch_id == new_branch_id,
+                (QueuedOperationKind::Fetch, QueuedOperationKind::Fetch) => true,
+                _ => false,
+            });
+        let operation = match queued {
+            Some(operation) => {
+                if let (
+                    QueuedOperationKind::Push {
+                        with_force: with_force @ false,
+                        upstream_head,
+                        ..
+                    },
+                    QueuedOperationKind::Push {
+                        with_force: true,
+                        upstream_head: new_upstream_head,
+                        ..
+                    },
+                ) = (&mut operation.kind, kind)
+                {
+                    *with_force = true;
+                    *upstream_head = new_upstream_head;
+                }
+                operation.clone()
+            }
+            None => {
+                let operation = QueuedOperation {
+                    id: QueuedOperationId::generate(),
+                    kind,
+                    queued_timestamp_ms: now_ms,
+                };
+                queue.operations.push(operation.clone());
+                operation
+            }
+        };
+        self.file.write(&queue)?;
+        Ok(operation)
+    }
+
+    /// Removes the operation identified by `id` from the queue, and returns it.
+    ///
+    /// Errors if the file cannot be read or written, or if there is no such operation.
+    pub fn remove(&self, id: QueuedOperationId) -> Result<QueuedOperation> {
+        let mut queue = self.file.read()?;
+        let position = queue
+            .operations
+            .iter()
+            .position(|operation| operation.id == id)
+            .ok_or_else(|| anyhow!("queued operation {id} not found"))?;
+        let operation = queue.operations.remove(position);
+        self.file.write(&queue)?;
+        Ok(operation)
+    }
+}
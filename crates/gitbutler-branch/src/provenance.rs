@@ -0,0 +1,116 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use gitbutler_fs::TomlFile;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use serde::{Deserialize, Serialize};
+
+use crate::BranchId;
+
+/// Where a commit that was created in the workspace came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitProvenance {
+    #[serde(with = "gitbutler_serde::oid")]
+    pub commit_id: git2::Oid,
+    /// The branch the commit was created on.
+    pub branch_id: BranchId,
+    /// The time at which the commit was created, in milliseconds since the Unix epoch.
+    pub created_timestamp_ms: i64,
+    /// The uncommitted hunks that went into the commit, fully or partially.
+    pub hunks: Vec<CommittedHunk>,
+    /// The operations log snapshot of the workspace as it was right before the commit was created,
+    /// or `None` if no snapshot was taken.
+    #[serde(default, with = "gitbutler_serde::oid_opt")]
+    pub snapshot_id: Option<git2::Oid>,
+}
+
+/// An uncommitted hunk as it was when it was committed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommittedHunk {
+    /// The path of the file the hunk is in, relative to the worktree.
+    pub file_path: PathBuf,
+    /// The hunk, like `3-7-<hash>` in ownership claims.
+    pub hunk: String,
+}
+
+/// The provenance of all commits, as persisted in a TOML file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Provenance {
+    commits: Vec<CommitProvenance>,
+}
+
+/// A handle to the provenance of commits created in the workspace.
+///
+/// Provenance is keyed by commit id, so it isn't carried over to commits that are rewritten,
+/// like when rebasing or amending.
+///
+/// The provenance is kept in `commit_provenance.toml`, and commits without an entry have no known provenance.
+pub struct ProvenanceHandle {
+    /// The file containing the provenance of all commits.
+    file: TomlFile<Provenance>,
+}
+
+impl ProvenanceHandle {
+    /// Creates a new handle to the commit provenance stored in `base_path`.
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        Self {
+            file: TomlFile::new(base_path.as_ref().join("commit_provenance.toml")),
+        }
+    }
+
+    /// Records that `commit_id` was just created on the branch identified by `branch_id` from `hunks`.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn record(
+        &self,
+        commit_id: git2::Oid,
+        branch_id: BranchId,
+        hunks: Vec<CommittedHunk>,
+    ) -> Result<CommitProvenance> {
+        let mut provenance = self.file.read()?;
+        let commit = CommitProvenance {
+            commit_id,
+            branch_id,
+            created_timestamp_ms: now_since_unix_epoch_ms(),
+            hunks,
+            snapshot_id: None,
+        };
+        provenance
+            .commits
+            .retain(|other| other.commit_id != commit_id);
+        provenance.commits.push(commit.clone());
+        self.file.write(&provenance)?;
+        Ok(commit)
+    }
+
+    /// Sets the operations log snapshot that was taken before `commit_id` was created.
+    /// Nothing happens if there is no provenance for `commit_id`.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn set_snapshot(&self, commit_id: git2::Oid, snapshot_id: git2::Oid) -> Result<()> {
+        let mut provenance = self.file.read()?;
+        let Some(commit) = provenance
+            .commits
+            .iter_mut()
+            .find(|commit| commit.commit_id == commit_id)
+        else {
+            return Ok(());
+        };
+        commit.snapshot_id = Some(snapshot_id);
+        self.file.write(&provenance)
+    }
+
+    /// Returns the provenance of `commit_id`, or `None` if it wasn't created in the workspace.
+    ///
+    /// Errors if the file cannot be read.
+    pub fn get(&self, commit_id: git2::Oid) -> Result<Option<CommitProvenance>> {
+        Ok(self
+            .file
+            .read()?
+            .commits
+            .into_iter()
+            .find(|commit| commit.commit_id == commit_id))
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use gitbutler_diff::{ChangeType, GitHunk};
+use gitbutler_fs::TomlFile;
+use gitbutler_id::id::Id;
+use serde::{Deserialize, Serialize};
+
+pub type ShelfId = Id<Shelf>;
+
+/// Uncommitted changes that were parked outside of any virtual branch, to be applied again later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Shelf {
+    pub id: ShelfId,
+    pub name: String,
+    /// The time at which the changes were shelved, in milliseconds since the Unix epoch.
+    pub created_timestamp_ms: i64,
+    /// The workspace commit the hunks were relative to when they were shelved.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub base: git2::Oid,
+    /// The shelved changes, by file.
+    pub files: Vec<ShelvedFile>,
+}
+
+/// The shelved changes of a single file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShelvedFile {
+    /// The path of the file relative to the worktree.
+    pub path: PathBuf,
+    /// The hunks of the file, ordered by their position.
+    pub hunks: Vec<ShelvedHunk>,
+}
+
+/// A hunk of a [`ShelvedFile`], which is a text-only [`GitHunk`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShelvedHunk {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    /// The hunk header along with the `+`, `-` or ` ` prefixed lines of the diff.
+    pub diff: String,
+    pub change_type: ChangeType,
+}
+
+impl TryFrom<&GitHunk> for ShelvedHunk {
+    type Error = anyhow::Error;
+
+    fn try_from(hunk: &GitHunk) -> Result<Self> {
+        if hunk.binary {
+            return Err(anyhow!("binary changes can't be shelved"));
+        }
+        let diff = String::from_utf8(hunk.diff_lines.to_vec())
+            .map_err(|_| anyhow!("changes that aren't valid UTF-8 can't be shelved"))?;
+        Ok(ShelvedHunk {
+            old_start: hunk.old_start,
+            old_lines: hunk.old_lines,
+            new_start: hunk.new_start,
+            new_lines: hunk.new_lines,
+            diff,
+            change_type: hunk.change_type,
+        })
+    }
+}
+
+impl From<&ShelvedHunk> for GitHunk {
+    fn from(hunk: &ShelvedHunk) -> Self {
+        GitHunk {
+            old_start: hunk.old_start,
+            old_lines: hunk.old_lines,
+            new_start: hunk.new_start,
+            new_lines: hunk.new_lines,
+            diff_lines: hunk.diff.clone().into(),
+            binary: false,
+            change_type: hunk.change_type,
+            highlights: None,
+        }
+    }
+}
+
+/// All shelves, as persisted in a TOML file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Shelves {
+    /// The shelves, oldest first.
+    shelves: Vec<Shelf>,
+}
+
+/// A handle to the shelves of a project.
+///
+/// The shelves are kept in `shelves.toml`, and there are none until the first one is created.
+pub struct ShelvesHandle {
+    /// The file containing all shelves.
+    file: TomlFile<Shelves>,
+}
+
+impl ShelvesHandle {
+    /// Creates a new handle to the shelves stored in `base_path`.
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        Self {
+            file: TomlFile::new(base_path.as_ref().join("shelves.toml")),
+        }
+    }
+
+    /// Returns all shelves, most recent first.
+    ///
+    /// Errors if the file cannot be read.
+    pub fn list(&self) -> Result<Vec<Shelf>> {
+        let mut shelves = self.file.read()?.shelves;
+        shelves.reverse();
+        Ok(shelves)
+    }
+
+    /// Returns the shelf identified by `id`.
+    ///
+    /// Errors if the file cannot be read or if there is no such shelf.
+    pub fn get(&self, id: ShelfId) -> Result<Shelf> {
+        self.file
+            .read()?
+            .shelves
+            .into_iter()
+            .find(|shelf| shelf.id == id)
+            .ok_or_else(|| anyhow!("shelf {id} not found"))
+    }
+
+    /// Stores `shelf`, replacing a shelf with the same id.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn set(&self, shelf: Shelf) -> Result<()> {
+        let mut shelves = self.file.read()?;
+        match shelves
+            .shelves
+            .iter_mut()
+            .find(|other| other.id == shelf.id)
+        {
+            Some(existing) => *existing = shelf,
+            None => shelves.shelves.push(shelf),
+        }
+        self.file.write(&shelves)
+    }
+
+    /// Removes the shelf identified by `id` and returns it.
+    ///
+    /// Errors if the file cannot be read or written, or if there is no such shelf.
+    pub fn remove(&self, id: ShelfId) -> Result<Shelf> {
+        let mut shelves = self.file.read()?;
+        let position = shelves
+            .shelves
+            .iter()
+            .position(|shelf| shelf.id == id)
+            .ok_or_else(|| anyhow!("shelf {id} not found"))?;
+        let shelf = shelves.shelves.remove(position);
+        self.file.write(&shelves)?;
+        Ok(shelf)
+    }
+}
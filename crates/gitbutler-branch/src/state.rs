@@ -3,9 +3,8 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use gitbutler_error::error::Code;
-use gitbutler_fs::read_toml_file_or_default;
 // use gitbutler_project::Project;
 use gitbutler_reference::Refname;
 use itertools::Itertools;
@@ -16,15 +15,24 @@ use crate::{
     target::Target,
 };
 
+/// The version of the format of `virtual_branches.toml` written by this version of the application.
+///
+/// Version `0` is the format before it was versioned, which also had no checksum.
+pub const STATE_VERSION: u32 = 1;
+
+/// The start of the last line of the state file, followed by the checksum of everything before it.
+/// It's a comment so the file stays valid TOML.
+const CHECKSUM_PREFIX: &str = "# checksum: ";
+
 /// The state of virtual branches data, as persisted in a TOML file.
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct VirtualBranches {
     /// This is the target/base that is set when a repo is added to gb
-    default_target: Option<Target>,
+    pub(crate) default_target: Option<Target>,
     /// The targets for each virtual branch
-    branch_targets: HashMap<BranchId, Target>,
+    pub(crate) branch_targets: HashMap<BranchId, Target>,
     /// The current state of the virtual branches
-    branches: HashMap<BranchId, Branch>,
+    pub(crate) branches: HashMap<BranchId, Branch>,
 }
 
 impl VirtualBranches {
@@ -47,6 +55,21 @@ impl VirtualBranches {
                 .collect()
         })
     }
+
+    /// Returns the target the virtual branches are based on, if one is set.
+    pub fn default_target(&self) -> Option<&Target> {
+        self.default_target.as_ref()
+    }
+
+    /// Returns the virtual branch identified by `id`, if there is one.
+    pub fn try_branch(&self, id: BranchId) -> Option<&Branch> {
+        self.branches.get(&id)
+    }
+
+    /// Returns all virtual branches, whether they are in the workspace or not.
+    pub fn branches(&self) -> impl Iterator<Item = &Branch> {
+        self.branches.values()
+    }
 }
 
 /// A handle to the state of virtual branches.
@@ -195,14 +218,130 @@ impl VirtualBranchesHandle {
         })
     }
 
-    /// Reads and parses the state file.
+    /// Returns the target the virtual branches are based on, if one is set.
+    pub fn default_target(&self) -> Option<&Target> {
+        self.default_target.as_ref()
+    }
+
+    /// Returns the virtual branch identified by `id`, if there is one.
+    pub fn try_branch(&self, id: BranchId) -> Option<&Branch> {
+        self.branches.get(&id)
+    }
+
+    /// Returns the contents of the state file as stored, which change whenever the state does.
+    ///
+    /// It's empty if the file doesn't exist yet.
+    pub fn raw_state(&self) -> Result<Vec<u8>> {
+        match std::fs::read(&self.file_path) {
+            Ok(contents) => Ok(contents),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Reads and parses the state file, which is migrated to the [current version](STATE_VERSION) after
+    /// backing it up if it was written by an older one.
     ///
-    /// If the file does not exist, it will be created.
-    fn read_file(&self) -> Result<VirtualBranches> {
-        read_toml_file_or_default(&self.file_path)
+    /// Fails with [`Code::CorruptedState`] if the file doesn't match its checksum, and is empty if it doesn't
+    /// exist.
+    pub(crate) fn read_file(&self) -> Result<VirtualBranches> {
+        let Some(stored) = self.read_stored()? else {
+            return Ok(VirtualBranches::default());
+        };
+        // Newer versions are rejected when migrating, as they may be checked differently.
+        if (1..=STATE_VERSION).contains(&stored.version) && !stored.checksum_matches {
+            return Err(anyhow!(
+                "{} doesn't match its checksum, and needs to be repaired",
+                self.file_path.display()
+            ))
+            .context(Code::CorruptedState);
+        }
+        self.migrate(stored)
+    }
+
+    /// Like [`read_file()`](Self::read_file()), but return whether the file matched its checksum instead of
+    /// failing if it doesn't, so it can be repaired.
+    pub(crate) fn read_file_unchecked(&self) -> Result<(VirtualBranches, bool)> {
+        let Some(stored) = self.read_stored()? else {
+            return Ok((VirtualBranches::default(), true));
+        };
+        let checksum_matches =
+            !(1..=STATE_VERSION).contains(&stored.version) || stored.checksum_matches;
+        Ok((self.migrate(stored)?, checksum_matches))
+    }
+
+    fn read_stored(&self) -> Result<Option<StoredState>> {
+        let contents = match std::fs::read_to_string(&self.file_path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let (body, checksum) = split_checksum(&contents);
+        let value: toml::Value = toml::from_str(body)
+            .with_context(|| format!("Failed to parse {}", self.file_path.display()))
+            .context(Code::CorruptedState)?;
+        let version = match value.get("version") {
+            None => 0,
+            Some(version) => version
+                .as_integer()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| anyhow!("invalid version of {}", self.file_path.display()))
+                .context(Code::CorruptedState)?,
+        };
+        Ok(Some(StoredState {
+            checksum_matches: checksum == Some(checksum_of(body).as_str()),
+            version,
+            value,
+            contents,
+        }))
     }
 
-    fn write_file(&self, virtual_branches: &VirtualBranches) -> Result<()> {
+    /// Bring `stored` to the current version, after backing up the file it came from if it's older.
+    fn migrate(&self, stored: StoredState) -> Result<VirtualBranches> {
+        let StoredState {
+            mut version,
+            mut value,
+            contents,
+            ..
+        } = stored;
+        if version > STATE_VERSION {
+            bail!(
+                "{} of version {version} was written by a newer version of GitButler, \
+                 which supports up to version {STATE_VERSION}",
+                self.file_path.display()
+            );
+        }
+        if version == STATE_VERSION {
+            return Ok(value.try_into()?);
+        }
+
+        let backup_path = self.backup_path(&format!("v{version}"));
+        gitbutler_fs::write(&backup_path, &contents)
+            .with_context(|| format!("failed to back up {}", self.file_path.display()))?;
+        while version < STATE_VERSION {
+            value = migrate(version, value)?;
+            version += 1;
+        }
+        let virtual_branches: VirtualBranches = value
+            .try_into()
+            .context("failed to read migrated virtual branches")?;
+        self.write_file(&virtual_branches)?;
+        tracing::info!(
+            path = %self.file_path.display(),
+            backup = %backup_path.display(),
+            "migrated the state of virtual branches to version {STATE_VERSION}"
+        );
+        Ok(virtual_branches)
+    }
+
+    /// Return where a copy of the state file is kept, marked with `suffix`.
+    pub(crate) fn backup_path(&self, suffix: &str) -> PathBuf {
+        let mut file_name = self.file_path.file_name().unwrap_or_default().to_owned();
+        file_name.push(format!(".{suffix}.bak"));
+        self.file_path.with_file_name(file_name)
+    }
+
+    pub(crate) fn write_file(&self, virtual_branches: &VirtualBranches) -> Result<()> {
         write(self.file_path.as_path(), virtual_branches)
     }
 
@@ -246,6 +385,59 @@ impl VirtualBranchesHandle {
     }
 }
 
+/// The state file as stored, before it's migrated.
+struct StoredState {
+    version: u32,
+    checksum_matches: bool,
+    value: toml::Value,
+    contents: String,
+}
+
+/// Migrate `value`, the state in `version` of the format, to the next version.
+fn migrate(version: u32, mut value: toml::Value) -> Result<toml::Value> {
+    match version {
+        // The unversioned format has the same fields, but kept the targets of branches that were deleted.
+        0 => {
+            let branch_ids: Vec<String> = value
+                .get("branches")
+                .and_then(toml::Value::as_table)
+                .map(|branches| branches.keys().cloned().collect())
+                .unwrap_or_default();
+            if let Some(branch_targets) = value
+                .get_mut("branch_targets")
+                .and_then(toml::Value::as_table_mut)
+            {
+                branch_targets.retain(|id, _| branch_ids.contains(id));
+            }
+            Ok(value)
+        }
+        _ => bail!("there is no migration from virtual branches version {version}"),
+    }
+}
+
+/// Split the state file into its body and the checksum on its last line, if it has one.
+fn split_checksum(contents: &str) -> (&str, Option<&str>) {
+    let last_line_start = contents
+        .trim_end_matches('\n')
+        .rfind('\n')
+        .map_or(0, |idx| idx + 1);
+    match contents[last_line_start..].strip_prefix(CHECKSUM_PREFIX) {
+        Some(checksum) => (&contents[..last_line_start], Some(checksum.trim_end())),
+        None => (contents, None),
+    }
+}
+
+fn checksum_of(body: &str) -> String {
+    format!("{:x}", md5::compute(body))
+}
+
 fn write<P: AsRef<Path>>(file_path: P, virtual_branches: &VirtualBranches) -> Result<()> {
-    gitbutler_fs::write(file_path, toml::to_string(&virtual_branches)?)
+    // Through a value, whose tables are sorted, so the same state is always written the same way.
+    let mut value = toml::Value::try_from(virtual_branches)?;
+    if let Some(table) = value.as_table_mut() {
+        table.insert("version".into(), toml::Value::Integer(STATE_VERSION.into()));
+    }
+    let body = toml::to_string(&value)?;
+    let checksum = checksum_of(&body);
+    gitbutler_fs::write(file_path, format!("{body}{CHECKSUM_PREFIX}{checksum}\n"))
 }
@@ -0,0 +1,177 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_fs::TomlFile;
+use gitbutler_id::id::Id;
+use serde::{Deserialize, Serialize};
+
+pub type TrashEntryId = Id<TrashEntry>;
+
+/// The most entries the trash keeps, with the oldest ones dropped to make room for new ones.
+const MAX_ENTRIES: usize = 100;
+
+/// The content of a file from before some of its uncommitted changes were discarded, so they can be restored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashEntry {
+    pub id: TrashEntryId,
+    /// The path of the file relative to the worktree.
+    pub path: PathBuf,
+    /// The time at which the changes were discarded, in milliseconds since the Unix epoch.
+    pub discarded_timestamp_ms: i64,
+    /// How the changes were discarded.
+    pub origin: TrashOrigin,
+    /// The hunk that was discarded, like `3-7`, if only a hunk or some of its lines were.
+    pub hunk_id: Option<String>,
+    /// `false` if the file didn't exist before the changes were discarded.
+    pub existed_before: bool,
+    /// `false` if discarding the changes removed the file.
+    pub existed_after: bool,
+}
+
+/// How changes ended up in the trash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrashOrigin {
+    /// A single hunk was discarded.
+    DiscardHunk,
+    /// Some lines of a hunk were discarded.
+    DiscardLines,
+    /// Hunks of the file owned by a branch were discarded.
+    DiscardOwnership,
+    /// The file was reset to its state in the index.
+    ResetFile,
+}
+
+/// All entries of the trash, as persisted in a TOML file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Trash {
+    /// The entries, oldest first.
+    entries: Vec<TrashEntry>,
+}
+
+/// A handle to the trash of a project, which keeps the content of each entry in a directory next to the file
+/// listing them.
+///
+/// Entries are listed in `trash.toml`, and the trash is empty until changes are first discarded.
+pub struct TrashHandle {
+    /// The file listing all entries.
+    file: TomlFile<Trash>,
+    /// The directory with the content of the files from before and after the changes were discarded.
+    content_dir: PathBuf,
+}
+
+impl TrashHandle {
+    /// Creates a new handle to the trash stored in `base_path`.
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        let base_path = base_path.as_ref();
+        Self {
+            file: TomlFile::new(base_path.join("trash.toml")),
+            content_dir: base_path.join("trash"),
+        }
+    }
+
+    /// Returns all entries, most recent first.
+    ///
+    /// Errors if the file cannot be read.
+    pub fn list(&self) -> Result<Vec<TrashEntry>> {
+        let mut entries = self.file.read()?.entries;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Returns the entry identified by `id`.
+    ///
+    /// Errors if the file cannot be read or if there is no such entry.
+    pub fn get(&self, id: TrashEntryId) -> Result<TrashEntry> {
+        self.file
+            .read()?
+            .entries
+            .into_iter()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| anyhow!("trash entry {id} not found"))
+    }
+
+    /// Stores `entry` along with the content of the file `before` and `after` the changes were discarded,
+    /// dropping the oldest entries if there are too many.
+    ///
+    /// Errors if the files cannot be read or written.
+    pub fn add(
+        &self,
+        entry: TrashEntry,
+        before: Option<&[u8]>,
+        after: Option<&[u8]>,
+    ) -> Result<()> {
+        for (content, suffix) in [(before, "before"), (after, "after")] {
+            if let Some(content) = content {
+                gitbutler_fs::create_dirs_then_write(self.content_path(entry.id, suffix), content)
+                    .context("failed to write the content of a trash entry")?;
+            }
+        }
+        let mut trash = self.file.read()?;
+        trash.entries.push(entry);
+        let dropped: Vec<_> = trash
+            .entries
+            .drain(..trash.entries.len().saturating_sub(MAX_ENTRIES))
+            .collect();
+        self.file.write(&trash)?;
+        for entry in dropped {
+            self.remove_content(entry.id)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the content of the file before and after the changes of the entry identified by `id` were
+    /// discarded, or `None` if the file didn't exist then.
+    ///
+    /// Errors if the files cannot be read or if there is no such entry.
+    pub fn content(&self, id: TrashEntryId) -> Result<(Option<Vec<u8>>, Option<Vec<u8>>)> {
+        let entry = self.get(id)?;
+        let read = |exists: bool, suffix: &str| -> Result<Option<Vec<u8>>> {
+            if !exists {
+                return Ok(None);
+            }
+            let path = self.content_path(id, suffix);
+            std::fs::read(&path)
+                .map(Some)
+                .with_context(|| format!("failed to read {}", path.display()))
+        };
+        Ok((
+            read(entry.existed_before, "before")?,
+            read(entry.existed_after, "after")?,
+        ))
+    }
+
+    /// Removes the entry identified by `id` along with its content, and returns it.
+    ///
+    /// Errors if the files cannot be read or written, or if there is no such entry.
+    pub fn remove(&self, id: TrashEntryId) -> Result<TrashEntry> {
+        let mut trash = self.file.read()?;
+        let position = trash
+            .entries
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or_else(|| anyhow!("trash entry {id} not found"))?;
+        let entry = trash.entries.remove(position);
+        self.file.write(&trash)?;
+        self.remove_content(id)?;
+        Ok(entry)
+    }
+
+    fn content_path(&self, id: TrashEntryId, suffix: &str) -> PathBuf {
+        self.content_dir.join(format!("{id}.{suffix}"))
+    }
+
+    fn remove_content(&self, id: TrashEntryId) -> Result<()> {
+        for suffix in ["before", "after"] {
+            let path = self.content_path(id, suffix);
+            if let Err(err) = std::fs::remove_file(&path) {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    return Err(err)
+                        .with_context(|| format!("failed to remove {}", path.display()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
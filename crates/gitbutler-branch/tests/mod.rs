@@ -1,2 +1,3 @@
 pub mod file_ownership;
 pub mod ownership;
+pub mod state;
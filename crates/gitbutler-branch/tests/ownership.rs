@@ -38,6 +38,14 @@ fn reconcile_ownership_simple() {
         applied: true,
         in_workspace: true,
         not_in_workspace_wip_change_id: None,
+        pinned_base: None,
+        parent: None,
+        push_remote_name: None,
+        allowed_paths: Vec::new(),
+        metadata: Default::default(),
+        description: String::new(),
+        reviewers: Vec::new(),
+        forge_merge: None,
         source_refname: None,
     };
     let branch_b = Branch {
@@ -66,6 +74,14 @@ fn reconcile_ownership_simple() {
         applied: true,
         in_workspace: true,
         not_in_workspace_wip_change_id: None,
+        pinned_base: None,
+        parent: None,
+        push_remote_name: None,
+        allowed_paths: Vec::new(),
+        metadata: Default::default(),
+        description: String::new(),
+        reviewers: Vec::new(),
+        forge_merge: None,
         source_refname: None,
     };
     let all_branches: Vec<Branch> = vec![branch_a.clone(), branch_b.clone()];
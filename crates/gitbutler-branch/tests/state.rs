@@ -0,0 +1,94 @@
+use gitbutler_branch::{VirtualBranchesHandle, STATE_VERSION};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+const TARGET_SHA: &str = "0123456789012345678901234567890123456789";
+const ORPHANED_BRANCH_ID: &str = "9f4c1f4e-5e5a-4a4f-9c6e-0f2b0d7c1a11";
+
+fn legacy_state() -> String {
+    format!(
+        r#"[default_target]
+branchName = "master"
+remoteName = "origin"
+remoteUrl = "https://example.com/repo.git"
+sha = "{TARGET_SHA}"
+
+[branch_targets.{ORPHANED_BRANCH_ID}]
+branchName = "master"
+remoteName = "origin"
+remoteUrl = "https://example.com/repo.git"
+sha = "{TARGET_SHA}"
+
+[branches]
+"#
+    )
+}
+
+#[test]
+fn unversioned_state_is_migrated_after_a_backup() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state_path = tmp.path().join("virtual_branches.toml");
+    std::fs::write(&state_path, legacy_state()).unwrap();
+
+    let handle = VirtualBranchesHandle::new(tmp.path());
+    assert_eq!(
+        handle.get_default_target().unwrap().sha.to_string(),
+        TARGET_SHA
+    );
+
+    let backup = std::fs::read_to_string(tmp.path().join("virtual_branches.toml.v0.bak")).unwrap();
+    assert_eq!(backup, legacy_state(), "the original is kept as it was");
+
+    let migrated = std::fs::read_to_string(&state_path).unwrap();
+    assert!(migrated.contains(&format!("version = {STATE_VERSION}")));
+    assert!(
+        migrated.lines().last().unwrap().starts_with("# checksum: "),
+        "the checksum is on the last line"
+    );
+    assert!(
+        !migrated.contains(ORPHANED_BRANCH_ID),
+        "targets of branches that don't exist are dropped"
+    );
+}
+
+#[test]
+fn missing_state_is_empty() {
+    let tmp = tempfile::tempdir().unwrap();
+    let handle = VirtualBranchesHandle::new(tmp.path());
+    assert!(handle.list_all_branches().unwrap().is_empty());
+    assert!(!tmp.path().join("virtual_branches.toml.v0.bak").exists());
+}
+
+#[test]
+fn state_changed_outside_of_gitbutler_is_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let state_path = tmp.path().join("virtual_branches.toml");
+    std::fs::write(&state_path, legacy_state()).unwrap();
+    let handle = VirtualBranchesHandle::new(tmp.path());
+    handle.get_default_target().unwrap();
+
+    let tampered = std::fs::read_to_string(&state_path)
+        .unwrap()
+        .replace("origin", "upstream");
+    std::fs::write(&state_path, tampered).unwrap();
+
+    let err = handle.get_default_target().unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::CorruptedState)
+    );
+}
+
+#[test]
+fn state_of_a_newer_version_is_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("virtual_branches.toml"),
+        format!("version = {}\n\n[branches]\n", STATE_VERSION + 1),
+    )
+    .unwrap();
+
+    let err = VirtualBranchesHandle::new(tmp.path())
+        .list_all_branches()
+        .unwrap_err();
+    assert!(err.to_string().contains("newer version"), "{err}");
+}
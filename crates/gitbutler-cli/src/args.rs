@@ -7,6 +7,11 @@ pub struct Args {
     #[clap(short = 'C', long, default_value = ".", value_name = "PATH")]
     pub current_dir: PathBuf,
 
+    /// Make runs reproducible by starting the clock at SECONDS since the Unix epoch, advancing it by
+    /// a second each time it's read, and generating ids by counting up.
+    #[clap(long, env = "GITBUTLER_REPRODUCIBLE_AT", value_name = "SECONDS")]
+    pub reproducible_at: Option<u64>,
+
     #[clap(subcommand)]
     pub cmd: Subcommands,
 }
@@ -25,19 +30,41 @@ pub enum Subcommands {
 }
 
 pub mod vbranch {
+    use crate::porcelain;
+
     #[derive(Debug, clap::Parser)]
     pub struct Platform {
+        /// Print output for scripts in a stable format of the given version, instead of output for humans.
+        ///
+        /// Used by `status`, `commit` and `push`.
+        #[clap(
+            long,
+            global = true,
+            value_name = "VERSION",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "v1"
+        )]
+        pub porcelain: Option<porcelain::Version>,
         #[clap(subcommand)]
         pub cmd: Option<SubCommands>,
     }
 
     #[derive(Debug, clap::Subcommand)]
     pub enum SubCommands {
+        /// Show the applied virtual branches and their uncommitted changes.
+        Status,
         /// Make the named branch the default so all worktree or index changes are associated with it automatically.
         SetDefault {
             /// The name of the new default virtual branch.
             name: String,
         },
+        /// Bring a branch into the workspace as virtual branch.
+        Apply {
+            /// The name of the branch to apply, like `feature`, or its full reference name, like
+            /// `refs/remotes/origin/feature`.
+            name: String,
+        },
         /// Remove a branch from the workspace.
         Unapply {
             /// The name of the virtual branch to unapply.
@@ -48,9 +75,24 @@ pub mod vbranch {
             /// The commit message
             #[clap(short = 'm', long)]
             message: String,
+            /// Only commit the hunks matching QUERY, like `path:*.md`, moving them from other branches
+            /// if needed.
+            ///
+            /// Conditions are `path:GLOB`, `content:REGEX`, `lines<N` and `age<DURATION` like `age>2h`,
+            /// along with the other comparisons, combined with `and`, `or`, `not` and parentheses.
+            #[clap(long, value_name = "QUERY")]
+            hunks: Option<String>,
             /// The name of the virtual to commit all staged and unstaged changes to.
             name: String,
         },
+        /// Push the named virtual branch to its remote.
+        Push {
+            /// Push even if it overwrites commits of the remote branch.
+            #[clap(short = 'f', long)]
+            force: bool,
+            /// The name of the virtual branch to push.
+            name: String,
+        },
         /// Create a new virtual branch
         Create {
             /// Also make this branch the default branch, so it is considered the owner of new edits.
@@ -1,12 +1,18 @@
 pub mod vbranch {
-    use anyhow::{bail, Result};
+    use anyhow::{anyhow, bail, Context, Result};
     use gitbutler_branch::{
-        Branch, BranchCreateRequest, BranchUpdateRequest, VirtualBranchesHandle,
+        Branch, BranchCreateRequest, BranchId, BranchOwnershipClaims, BranchUpdateRequest,
+        VirtualBranchesHandle,
     };
     use gitbutler_branch_actions::VirtualBranchActions;
     use gitbutler_project::Project;
+    use gitbutler_reference::Refname;
 
-    use crate::command::debug_print;
+    use crate::{
+        command::debug_print,
+        exit_code::ExitCode,
+        porcelain::{self, Line},
+    };
 
     pub fn list(project: Project) -> Result<()> {
         let branches = VirtualBranchesHandle::new(project.gb_dir()).list_all_branches()?;
@@ -25,6 +31,83 @@ pub mod vbranch {
         Ok(())
     }
 
+    pub fn status(project: Project, porcelain: Option<porcelain::Version>) -> Result<()> {
+        let (branches, _skipped) = VirtualBranchActions.list_virtual_branches(&project)?;
+        let Some(version) = porcelain else {
+            for branch in &branches {
+                println!(
+                    "{default} {name} ({commits} commits)",
+                    default = if branch.selected_for_changes {
+                        "🌟"
+                    } else {
+                        " "
+                    },
+                    name = branch.name,
+                    commits = branch.commits.len(),
+                );
+                for file in &branch.files {
+                    println!("    {}", file.path.display());
+                }
+            }
+            return Ok(());
+        };
+        porcelain::print(
+            version,
+            branches.iter().flat_map(|branch| {
+                std::iter::once(Line::Branch {
+                    id: branch.id,
+                    head: branch.head,
+                    commits: branch.commits.len(),
+                    default: branch.selected_for_changes,
+                    conflicted: branch.conflicted,
+                    requires_force: branch.requires_force,
+                    name: &branch.name,
+                })
+                .chain(branch.files.iter().map(|file| Line::File {
+                    branch_id: branch.id,
+                    hunks: file.hunks.len(),
+                    path: &file.path,
+                }))
+            }),
+        );
+        Ok(())
+    }
+
+    pub fn push(
+        project: Project,
+        branch_name: String,
+        force: bool,
+        porcelain: Option<porcelain::Version>,
+    ) -> Result<()> {
+        let branch = branch_by_name(&project, &branch_name)?;
+        VirtualBranchActions.push_virtual_branch(&project, branch.id, force, None)?;
+        match porcelain {
+            Some(version) => porcelain::print(
+                version,
+                [Line::Push {
+                    branch_id: branch.id,
+                    head: branch.head,
+                }],
+            ),
+            None => eprintln!("Pushed '{branch_name}'"),
+        }
+        Ok(())
+    }
+
+    pub fn apply(project: Project, branch_name: String) -> Result<()> {
+        let refname: Refname = if branch_name.starts_with("refs/") {
+            branch_name.parse()?
+        } else {
+            format!("refs/heads/{branch_name}").parse()?
+        };
+        let mut results = VirtualBranchActions.apply_branches(&project, &[refname])?;
+        let result = results.pop().context("no outcome for the applied branch")?;
+        if let Some(err) = result.error {
+            bail!("Could not apply '{branch_name}': {err}");
+        }
+        debug_print(result)
+    }
+
     pub fn unapply(project: Project, branch_name: String) -> Result<()> {
         let branch = branch_by_name(&project, &branch_name)?;
         debug_print(VirtualBranchActions.convert_to_real_branch(&project, branch.id)?)
@@ -62,11 +145,20 @@ pub mod vbranch {
                 upstream: None,
                 selected_for_changes: Some(true),
                 allow_rebasing: None,
+                allowed_paths: None,
+                description: None,
+                reviewers: None,
             },
         )
     }
 
-    pub fn commit(project: Project, branch_name: String, message: String) -> Result<()> {
+    pub fn commit(
+        project: Project,
+        branch_name: String,
+        message: String,
+        hunks: Option<String>,
+        porcelain: Option<porcelain::Version>,
+    ) -> Result<()> {
         let branch = branch_by_name(&project, &branch_name)?;
         let (info, skipped) = VirtualBranchActions.list_virtual_branches(&project)?;
 
@@ -81,8 +173,29 @@ pub mod vbranch {
             .iter()
             .find(|b| b.id == branch.id)
             .expect("A populated branch exists for a branch we can list");
+        if let Some(query) = hunks {
+            let selected = VirtualBranchActions.select_hunks(&project, &query)?;
+            if selected.claims.is_empty() {
+                return Err(anyhow!("No uncommitted hunk matches '{query}'"))
+                    .context(ExitCode::NothingToCommit);
+            }
+            // Hunks of other branches have to be moved to this one before they can be committed.
+            let mut ownership = populated_branch.ownership.clone();
+            for claim in &selected.claims {
+                ownership.put(claim.clone());
+            }
+            VirtualBranchActions.update_virtual_branch(
+                &project,
+                BranchUpdateRequest {
+                    id: branch.id,
+                    ownership: Some(ownership),
+                    ..Default::default()
+                },
+            )?;
+            return commit_ownership(&project, branch.id, &message, &selected, porcelain);
+        }
         if populated_branch.ownership.claims.is_empty() {
-            bail!(
+            return Err(anyhow!(
                 "Branch '{branch_name}' has no change to commit{hint}",
                 hint = {
                     let candidate_names = info
@@ -102,17 +215,47 @@ pub mod vbranch {
                     };
                     candidates
                 }
-            )
+            ))
+            .context(ExitCode::NothingToCommit);
         }
 
-        let run_hooks = false;
-        debug_print(VirtualBranchActions.create_commit(
+        commit_ownership(
             &project,
             branch.id,
             &message,
-            Some(&populated_branch.ownership),
+            &populated_branch.ownership,
+            porcelain,
+        )
+    }
+
+    fn commit_ownership(
+        project: &Project,
+        branch_id: BranchId,
+        message: &str,
+        ownership: &BranchOwnershipClaims,
+        porcelain: Option<porcelain::Version>,
+    ) -> Result<()> {
+        let run_hooks = false;
+        let commit_id = VirtualBranchActions.create_commit(
+            project,
+            branch_id,
+            message,
+            Some(ownership),
             run_hooks,
-        )?)
+        )?;
+        match porcelain {
+            Some(version) => {
+                porcelain::print(
+                    version,
+                    [Line::Commit {
+                        branch_id,
+                        commit_id,
+                    }],
+                );
+                Ok(())
+            }
+            None => debug_print(commit_id),
+        }
     }
 
     pub fn branch_by_name(project: &Project, name: &str) -> Result<Branch> {
@@ -201,17 +344,19 @@ pub mod prepare {
     use std::path::PathBuf;
 
     use anyhow::{bail, Context};
-    use gitbutler_project::Project;
+    use gitbutler_project::{Project, Settings};
 
     pub fn project_from_path(path: PathBuf) -> anyhow::Result<Project> {
         let worktree_dir = gix::discover(path)?
             .work_dir()
             .context("Bare repositories aren't supported")?
             .to_owned();
-        Ok(Project {
+        let mut project = Project {
             path: worktree_dir,
             ..Default::default()
-        })
+        };
+        project.settings = Settings::load(&project)?;
+        Ok(project)
     }
 
     pub fn project_controller(
@@ -0,0 +1,91 @@
+//! The exit codes of the CLI, which are [stable](crate::porcelain) so scripts can act on them.
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+/// Why the CLI exited.
+///
+/// Invalid arguments exit with `2`, as is the default of `clap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Success = 0,
+    /// Any failure that doesn't have its own code.
+    Failure = 1,
+    /// There were no changes to commit.
+    NothingToCommit = 3,
+    /// The operation was stopped by conflicts.
+    Conflicts = 4,
+    /// The remote didn't accept any of the credentials.
+    Authentication = 5,
+    /// The remote couldn't be reached, or the network is unavailable.
+    Remote = 6,
+    /// A Git hook rejected the operation.
+    HookFailed = 7,
+}
+
+impl ExitCode {
+    /// Return the code to exit with after `err`.
+    pub fn for_error(err: &anyhow::Error) -> Self {
+        if let Some(code) = err.downcast_ref::<ExitCode>() {
+            return *code;
+        }
+        match err.custom_context().map(|ctx| ctx.code) {
+            Some(Code::ProjectGitAuth) => ExitCode::Authentication,
+            Some(Code::ProjectGitRemote | Code::Offline) => ExitCode::Remote,
+            Some(Code::CommitMergeConflictFailure) => ExitCode::Conflicts,
+            Some(Code::CommitHookFailed | Code::HookFailed) => ExitCode::HookFailed,
+            _ => ExitCode::Failure,
+        }
+    }
+}
+
+impl std::fmt::Display for ExitCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ExitCode::Success => "success",
+            ExitCode::Failure => "failure",
+            ExitCode::NothingToCommit => "nothing to commit",
+            ExitCode::Conflicts => "conflicts",
+            ExitCode::Authentication => "authentication failed",
+            ExitCode::Remote => "remote unreachable",
+            ExitCode::HookFailed => "hook failed",
+        })
+    }
+}
+
+impl From<ExitCode> for std::process::ExitCode {
+    fn from(code: ExitCode) -> Self {
+        std::process::ExitCode::from(code as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Context};
+
+    use super::*;
+
+    #[test]
+    fn codes_are_stable() {
+        assert_eq!(ExitCode::Success as u8, 0);
+        assert_eq!(ExitCode::Failure as u8, 1);
+        assert_eq!(ExitCode::NothingToCommit as u8, 3);
+        assert_eq!(ExitCode::Conflicts as u8, 4);
+        assert_eq!(ExitCode::Authentication as u8, 5);
+        assert_eq!(ExitCode::Remote as u8, 6);
+        assert_eq!(ExitCode::HookFailed as u8, 7);
+    }
+
+    #[test]
+    fn for_error() {
+        let err = Err::<(), _>(anyhow!("no changes"))
+            .context(ExitCode::NothingToCommit)
+            .unwrap_err();
+        assert_eq!(ExitCode::for_error(&err), ExitCode::NothingToCommit);
+
+        let err = Err::<(), _>(anyhow!("denied"))
+            .context(Code::ProjectGitAuth)
+            .unwrap_err();
+        assert_eq!(ExitCode::for_error(&err), ExitCode::Authentication);
+
+        assert_eq!(ExitCode::for_error(&anyhow!("other")), ExitCode::Failure);
+    }
+}
@@ -1,4 +1,11 @@
+use std::{
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
 use anyhow::Result;
+use gitbutler_id::generator::SequentialIds;
+use gitbutler_time::clock::SteppingClock;
 
 mod args;
 use args::Args;
@@ -6,22 +13,50 @@ use args::Args;
 use crate::args::{project, snapshot, vbranch};
 
 mod command;
+mod exit_code;
+mod porcelain;
 
-fn main() -> Result<()> {
+fn main() -> std::process::ExitCode {
+    gitbutler_repo::credentials::use_gpg_agent_for_ssh();
     let args: Args = clap::Parser::parse();
+    match run(args) {
+        Ok(()) => exit_code::ExitCode::Success.into(),
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            exit_code::ExitCode::for_error(&err).into()
+        }
+    }
+}
 
+fn run(args: Args) -> Result<()> {
+    if let Some(seconds) = args.reproducible_at {
+        gitbutler_time::clock::set_clock(Some(Arc::new(SteppingClock::new(
+            UNIX_EPOCH + Duration::from_secs(seconds),
+            Duration::from_secs(1),
+        ))));
+        gitbutler_id::generator::set_id_generator(Some(Arc::new(SequentialIds::default())));
+    }
     match args.cmd {
-        args::Subcommands::Branch(vbranch::Platform { cmd }) => {
+        args::Subcommands::Branch(vbranch::Platform { porcelain, cmd }) => {
             let project = command::prepare::project_from_path(args.current_dir)?;
             match cmd {
+                Some(vbranch::SubCommands::Status) => command::vbranch::status(project, porcelain),
+                Some(vbranch::SubCommands::Apply { name }) => {
+                    command::vbranch::apply(project, name)
+                }
                 Some(vbranch::SubCommands::Unapply { name }) => {
                     command::vbranch::unapply(project, name)
                 }
                 Some(vbranch::SubCommands::SetDefault { name }) => {
                     command::vbranch::set_default(project, name)
                 }
-                Some(vbranch::SubCommands::Commit { message, name }) => {
-                    command::vbranch::commit(project, name, message)
+                Some(vbranch::SubCommands::Commit {
+                    message,
+                    hunks,
+                    name,
+                }) => command::vbranch::commit(project, name, message, hunks, porcelain),
+                Some(vbranch::SubCommands::Push { force, name }) => {
+                    command::vbranch::push(project, name, force, porcelain)
                 }
                 Some(vbranch::SubCommands::Create { set_default, name }) => {
                     command::vbranch::create(project, name, set_default)
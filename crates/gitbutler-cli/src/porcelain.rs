@@ -0,0 +1,192 @@
+//! Output for scripts, printed instead of the output for humans if `--porcelain` is given.
+//!
+//! ### Stability
+//!
+//! - **Stable**: the lines of each released [`Version`], the order of their fields, and the
+//!   [exit codes](crate::exit_code::ExitCode). Changes that would break a parser lead to a new version.
+//! - **Additive**: new kinds of lines may be added to a version, so parsers must skip lines that start
+//!   with a kind they don't know.
+//! - **Unstable**: the output without `--porcelain`, and everything printed to stderr.
+//!
+//! The tests of this module pin the output of each version, and must not be changed for released ones.
+//!
+//! ### Version 1
+//!
+//! Each line has fields separated by a tab, and starts with its kind. Names and paths come last so
+//! they may contain spaces.
+//!
+//! * `branch status`
+//!     - `branch <id> <head> <commits> <flags> <name>` for each applied virtual branch, with `flags` being
+//!       `-` or any of `d` (gets new changes by default), `c` (conflicted) and `f` (requires a force push).
+//!     - `file <branch-id> <hunks> <path>` for each uncommitted file owned by the branch, right after it.
+//! * `branch commit`: `commit <branch-id> <commit-id>`
+//! * `branch push`: `push <branch-id> <head>`
+use std::{fmt::Write, path::Path};
+
+use gitbutler_branch::BranchId;
+
+/// A version of the output format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Version {
+    V1,
+}
+
+/// A line of output.
+#[derive(Debug, Clone, Copy)]
+pub enum Line<'a> {
+    Branch {
+        id: BranchId,
+        head: git2::Oid,
+        commits: usize,
+        default: bool,
+        conflicted: bool,
+        requires_force: bool,
+        name: &'a str,
+    },
+    File {
+        branch_id: BranchId,
+        hunks: usize,
+        path: &'a Path,
+    },
+    Commit {
+        branch_id: BranchId,
+        commit_id: git2::Oid,
+    },
+    Push {
+        branch_id: BranchId,
+        head: git2::Oid,
+    },
+}
+
+impl Line<'_> {
+    /// Return this line as printed in `version`, without line terminator.
+    pub fn format(&self, version: Version) -> String {
+        match version {
+            Version::V1 => self.format_v1(),
+        }
+    }
+
+    fn format_v1(&self) -> String {
+        match *self {
+            Line::Branch {
+                id,
+                head,
+                commits,
+                default,
+                conflicted,
+                requires_force,
+                name,
+            } => {
+                let mut flags = String::new();
+                for (is_set, flag) in [(default, 'd'), (conflicted, 'c'), (requires_force, 'f')] {
+                    if is_set {
+                        flags.push(flag);
+                    }
+                }
+                if flags.is_empty() {
+                    flags.push('-');
+                }
+                format!("branch\t{id}\t{head}\t{commits}\t{flags}\t{name}")
+            }
+            Line::File {
+                branch_id,
+                hunks,
+                path,
+            } => format!("file\t{branch_id}\t{hunks}\t{}", path.display()),
+            Line::Commit {
+                branch_id,
+                commit_id,
+            } => format!("commit\t{branch_id}\t{commit_id}"),
+            Line::Push { branch_id, head } => format!("push\t{branch_id}\t{head}"),
+        }
+    }
+}
+
+/// Print all `lines` in `version` to stdout.
+pub fn print<'a>(version: Version, lines: impl IntoIterator<Item = Line<'a>>) {
+    let mut out = String::new();
+    for line in lines {
+        writeln!(out, "{}", line.format(version)).expect("writing to a string works");
+    }
+    print!("{out}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn branch_id() -> BranchId {
+        "9ae4c8a7-7bb4-4d3a-b1b5-4c1f0b2c9f0e".parse().unwrap()
+    }
+
+    fn oid(hex: char) -> git2::Oid {
+        git2::Oid::from_str(&hex.to_string().repeat(40)).unwrap()
+    }
+
+    #[test]
+    fn v1_branch() {
+        let line = Line::Branch {
+            id: branch_id(),
+            head: oid('a'),
+            commits: 2,
+            default: true,
+            conflicted: false,
+            requires_force: true,
+            name: "my branch",
+        };
+        assert_eq!(
+            line.format(Version::V1),
+            "branch\t9ae4c8a7-7bb4-4d3a-b1b5-4c1f0b2c9f0e\taaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\t2\tdf\tmy branch"
+        );
+    }
+
+    #[test]
+    fn v1_branch_without_flags() {
+        let line = Line::Branch {
+            id: branch_id(),
+            head: oid('a'),
+            commits: 0,
+            default: false,
+            conflicted: false,
+            requires_force: false,
+            name: "b",
+        };
+        assert_eq!(
+            line.format(Version::V1),
+            "branch\t9ae4c8a7-7bb4-4d3a-b1b5-4c1f0b2c9f0e\taaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\t0\t-\tb"
+        );
+    }
+
+    #[test]
+    fn v1_file() {
+        let line = Line::File {
+            branch_id: branch_id(),
+            hunks: 3,
+            path: Path::new("dir/some file.txt"),
+        };
+        assert_eq!(
+            line.format(Version::V1),
+            "file\t9ae4c8a7-7bb4-4d3a-b1b5-4c1f0b2c9f0e\t3\tdir/some file.txt"
+        );
+    }
+
+    #[test]
+    fn v1_commit_and_push() {
+        let commit = Line::Commit {
+            branch_id: branch_id(),
+            commit_id: oid('b'),
+        };
+        assert_eq!(
+            commit.format(Version::V1),
+            "commit\t9ae4c8a7-7bb4-4d3a-b1b5-4c1f0b2c9f0e\tbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"
+        );
+        let push = Line::Push {
+            branch_id: branch_id(),
+            head: oid('c'),
+        };
+        assert_eq!(
+            push.format(Version::V1),
+            "push\t9ae4c8a7-7bb4-4d3a-b1b5-4c1f0b2c9f0e\tcccccccccccccccccccccccccccccccccccccccc"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//! Cooperative cancellation of long-running operations.
+//!
+//! An operation runs with the [`CancellationToken`] that is [current](current()) on its thread, which is set
+//! for the duration of a closure with [`scoped()`]. It checks the token only at points where stopping leaves
+//! the repository consistent, and fails with [`Code::Cancelled`] there. Operations that can't stop halfway
+//! without leaving a mess either roll back what they did or check only before their first write.
+//!
+//! Threads spawned by an operation don't inherit the current token, so operations that spread their work
+//! across threads pass it on with [`scoped()`].
+use std::{
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_error::error::Code;
+
+thread_local! {
+    static CURRENT: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+/// A flag through which an operation can be asked to stop, shared by all of its clones.
+///
+/// The default token is never cancelled, unless one of its clones is.
+#[derive(Debug, Default, Clone)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token that isn't cancelled yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the operations using this token to stop, which they will once they can.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Return `true` if the operations using this token should stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Fail with [`Code::Cancelled`] if the operations using this token should stop.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(anyhow!("The operation was cancelled")).context(Code::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// Run `operation` with `token` as the current token of this thread, and restore the previous one after.
+pub fn scoped<T>(token: &CancellationToken, operation: impl FnOnce() -> T) -> T {
+    struct Restore(Option<CancellationToken>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(CURRENT.with(|current| current.replace(Some(token.clone()))));
+    operation()
+}
+
+/// Return the token of the operation running on this thread, or one that's never cancelled if there is none.
+pub fn current() -> CancellationToken {
+    CURRENT.with(|current| current.borrow().clone().unwrap_or_default())
+}
+
+/// Return `true` if the operation running on this thread should stop.
+pub fn is_cancelled() -> bool {
+    CURRENT.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    })
+}
+
+/// Fail with [`Code::Cancelled`] if the operation running on this thread should stop.
+pub fn check() -> Result<()> {
+    if is_cancelled() {
+        return current().check();
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+//! The [extra environment](gitbutler_project::Project::extra_env) of the project each repository belongs to,
+//! for spawning processes on behalf of a project where only its repository is at hand, like when signing
+//! commits or running filters.
+//!
+//! It's recorded whenever a [`CommandContext`](crate::CommandContext) is opened, so it's always the one
+//! of the latest settings of the project.
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The extra environment of each repository, by the path of its `.git` directory.
+static EXTRA_ENV: Mutex<BTreeMap<PathBuf, BTreeMap<String, String>>> = Mutex::new(BTreeMap::new());
+
+/// Remember `env` as the extra environment of the repository whose `.git` directory is at `git_dir`.
+pub(crate) fn record(git_dir: &Path, env: &BTreeMap<String, String>) {
+    let mut extra_env = EXTRA_ENV.lock().expect("no panics while holding the lock");
+    if env.is_empty() {
+        extra_env.remove(git_dir);
+    } else {
+        extra_env.insert(git_dir.to_owned(), env.clone());
+    }
+}
+
+/// Return the extra environment of the project that `repo` belongs to, which is empty if it has none
+/// or if no [`CommandContext`](crate::CommandContext) was opened for it yet.
+pub fn of_repository(repo: &git2::Repository) -> BTreeMap<String, String> {
+    EXTRA_ENV
+        .lock()
+        .expect("no panics while holding the lock")
+        .get(repo.path())
+        .cloned()
+        .unwrap_or_default()
+}
@@ -1,3 +1,8 @@
+pub mod cancellation;
+pub use cancellation::CancellationToken;
+
+pub mod extra_env;
+
 use anyhow::Result;
 use gitbutler_project::Project;
 
@@ -56,6 +61,8 @@ impl CommandContext {
             );
         }
 
+        extra_env::record(repo.path(), &project.extra_env);
+
         Ok(Self {
             git_repository: repo,
             project: project.clone(),
@@ -9,6 +9,8 @@ pub trait CommitExt {
     /// Obtain the commit-message as bytes, but without assuming any encoding.
     fn message_bstr(&self) -> &BStr;
     fn change_id(&self) -> Option<String>;
+    /// Return the id of the commit this one was cherry-picked from, if it was.
+    fn cherry_picked_from(&self) -> Option<git2::Oid>;
     fn is_signed(&self) -> bool;
 }
 
@@ -20,6 +22,10 @@ impl<'repo> CommitExt for git2::Commit<'repo> {
     fn change_id(&self) -> Option<String> {
         self.gitbutler_headers().map(|headers| headers.change_id)
     }
+
+    fn cherry_picked_from(&self) -> Option<git2::Oid> {
+        self.gitbutler_headers()?.cherry_picked_from?.parse().ok()
+    }
     fn is_signed(&self) -> bool {
         self.header_field_bytes("gpgsig").is_ok()
     }
@@ -1,5 +1,4 @@
 use bstr::{BStr, BString};
-use uuid::Uuid;
 
 use crate::commit_buffer::CommitBuffer;
 
@@ -18,9 +17,12 @@ struct CommitHeadersV1 {
 const V2_HEADERS_VERSION: &str = "2";
 
 const V2_CHANGE_ID_HEADER: &str = "gitbutler-change-id";
+const V2_CHERRY_PICKED_FROM_HEADER: &str = "gitbutler-cherry-picked-from";
 #[derive(Debug, Clone)]
 pub struct CommitHeadersV2 {
     pub change_id: String,
+    /// The id of the commit this one was cherry-picked from, if it was.
+    pub cherry_picked_from: Option<String>,
 }
 
 impl Default for CommitHeadersV2 {
@@ -29,8 +31,9 @@ impl Default for CommitHeadersV2 {
             // Change ID using base16 encoding
             // NOTE(ST): Ideally, this could be a computed hash based on the patch applied, similar
             //           to what would happen during a rebase (if that is even the intention).
-            //           That way, they would be stable, so tests could have reproducible hashes as well.
-            change_id: Uuid::new_v4().to_string(),
+            //           Until then, tests get reproducible hashes by making ids predictable.
+            change_id: gitbutler_id::generator::new_uuid().to_string(),
+            cherry_picked_from: None,
         }
     }
 }
@@ -39,6 +42,7 @@ impl From<CommitHeadersV1> for CommitHeadersV2 {
     fn from(commit_headers_v1: CommitHeadersV1) -> CommitHeadersV2 {
         CommitHeadersV2 {
             change_id: commit_headers_v1.change_id,
+            cherry_picked_from: None,
         }
     }
 }
@@ -57,8 +61,15 @@ impl HasCommitHeaders for git2::Commit<'_> {
                 let change_id = self.header_field_bytes(V2_CHANGE_ID_HEADER).ok()?;
                 // We can safely assume that the change id should be UTF8
                 let change_id = change_id.as_str()?.to_string();
-
-                Some(CommitHeadersV2 { change_id })
+                let cherry_picked_from = self
+                    .header_field_bytes(V2_CHERRY_PICKED_FROM_HEADER)
+                    .ok()
+                    .and_then(|id| id.as_str().map(ToOwned::to_owned));
+
+                Some(CommitHeadersV2 {
+                    change_id,
+                    cherry_picked_from,
+                })
             } else {
                 // Must be for a version we don't recognise
                 None
@@ -92,5 +103,8 @@ impl CommitHeadersV2 {
     pub fn inject_into(&self, commit_buffer: &mut CommitBuffer) {
         commit_buffer.set_header(HEADERS_VERSION_HEADER, V2_HEADERS_VERSION);
         commit_buffer.set_header(V2_CHANGE_ID_HEADER, &self.change_id);
+        if let Some(cherry_picked_from) = &self.cherry_picked_from {
+            commit_buffer.set_header(V2_CHERRY_PICKED_FROM_HEADER, cherry_picked_from);
+        }
     }
 }
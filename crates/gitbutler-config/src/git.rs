@@ -12,6 +12,8 @@ pub struct GbConfig {
     pub gpg_ssh_program: Option<String>,
 }
 const SIGN_COMMITS: &str = "gitbutler.signCommits";
+/// Git's own setting for signing commits, used if [`SIGN_COMMITS`] isn't set.
+const COMMIT_GPG_SIGN: &str = "commit.gpgSign";
 const SIGNING_KEY: &str = "user.signingKey";
 const SIGNING_FORMAT: &str = "gpg.format";
 const GPG_PROGRAM: &str = "gpg.program";
@@ -24,7 +26,10 @@ pub trait GitConfig {
 
 impl GitConfig for git2::Repository {
     fn gb_config(&self) -> Result<GbConfig> {
-        let sign_commits = get_bool(self, SIGN_COMMITS)?;
+        let sign_commits = match get_bool(self, SIGN_COMMITS)? {
+            Some(sign_commits) => Some(sign_commits),
+            None => get_bool(self, COMMIT_GPG_SIGN)?,
+        };
         let signing_key = get_string(self, SIGNING_KEY)?;
         let signing_format = get_string(self, SIGNING_FORMAT)?;
         let gpg_program = get_string(self, GPG_PROGRAM)?;
@@ -0,0 +1,128 @@
+use std::{io::Read, path::Path};
+
+use serde::Serialize;
+
+use crate::diff::DiffByPathMap;
+
+/// The amount of bytes read from the start of an image to find its dimensions.
+const IMAGE_HEADER_LEN: u64 = 64 * 1024;
+
+/// The size of an image in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageDimensions {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Guess the media type of the file at `path` from its extension, like `image/png` for `logo.png`.
+pub fn mime_guess(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "wasm" => "application/wasm",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        _ => return None,
+    })
+}
+
+/// Read the dimensions of a PNG, GIF or JPEG image from the start of its `data`, or return `None`
+/// if it's no such image or the dimensions aren't within `data`.
+pub fn image_dimensions(data: &[u8]) -> Option<ImageDimensions> {
+    let be_u16 = |at: usize| Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?));
+    let le_u16 = |at: usize| Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?));
+    let be_u32 = |at: usize| Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?));
+
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        // The IHDR chunk always comes first.
+        return Some(ImageDimensions {
+            width: be_u32(16)?,
+            height: be_u32(20)?,
+        });
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some(ImageDimensions {
+            width: le_u16(6)?.into(),
+            height: le_u16(8)?.into(),
+        });
+    }
+    if data.starts_with(&[0xff, 0xd8]) {
+        // Walk the segments up to the start of frame, which holds the dimensions.
+        let mut at = 2;
+        loop {
+            if *data.get(at)? != 0xff {
+                return None;
+            }
+            let marker = *data.get(at + 1)?;
+            if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+                return Some(ImageDimensions {
+                    width: be_u16(at + 7)?.into(),
+                    height: be_u16(at + 5)?.into(),
+                });
+            }
+            at += 2 + usize::from(be_u16(at + 2)?);
+        }
+    }
+    None
+}
+
+/// Set the media type and image dimensions of all binary files in `files`, which are the changes of `diff`.
+pub(crate) fn describe_binary_files(
+    repo: &git2::Repository,
+    diff: &git2::Diff<'_>,
+    files: &mut DiffByPathMap,
+) {
+    let Ok(odb) = repo.odb() else {
+        return;
+    };
+    let dimensions = |id: git2::Oid| {
+        if id.is_zero() {
+            return None;
+        }
+        let mut header = Vec::new();
+        odb.reader(id)
+            .ok()?
+            .0
+            .take(IMAGE_HEADER_LEN)
+            .read_to_end(&mut header)
+            .ok()?;
+        image_dimensions(&header)
+    };
+    for delta in diff.deltas() {
+        let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) else {
+            continue;
+        };
+        let Some(file) = files
+            .get_mut(path)
+            .filter(|file| file.binary || file.hunks.iter().any(|hunk| hunk.binary))
+        else {
+            continue;
+        };
+        file.mime_guess = mime_guess(path).map(ToOwned::to_owned);
+        if file
+            .mime_guess
+            .as_deref()
+            .map_or(false, |mime| mime.starts_with("image/"))
+        {
+            file.old_image_dimensions = dimensions(delta.old_file().id());
+            file.new_image_dimensions = dimensions(delta.new_file().id());
+        }
+    }
+}
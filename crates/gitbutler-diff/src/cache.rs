@@ -0,0 +1,279 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{
+    diff::{workdir_of_paths, DiffByPathMap},
+    memory_limits,
+    spill::{text_size, SpilledText},
+    DiffOptions, PathChange,
+};
+
+/// Files whose change affects how all other files are diffed.
+const GLOBAL_FILES: &[&str] = &[".gitignore", ".gitattributes"];
+
+/// A diff of the worktree against a commit, as computed by [`workdir_with_options()`](crate::workdir_with_options()),
+/// which is kept up to date by only diffing the files that changed since it was computed.
+///
+/// It relies on being told about changed files with [`invalidate()`](Self::invalidate()), usually by
+/// watching the worktree. Files that already had changes are checked for modifications by their size and
+/// modification time, and when diffing against another commit only the files that differ between the trees
+/// of both commits are diffed again. All files are diffed again if the index changed, as happens when
+/// the worktree is rewritten by a checkout.
+///
+/// In [low-memory mode](crate::set_low_memory()), the text of large diffs is kept in a temporary file in between.
+#[derive(Debug, Default)]
+pub struct WorkdirCache {
+    state: Option<CachedDiff>,
+    /// Files relative to the worktree that changed since the cached diff was computed.
+    changed_paths: HashSet<PathBuf>,
+    /// Why each file was diffed again by the last diff.
+    rediffed: BTreeMap<PathBuf, RediffReason>,
+}
+
+/// Why a file was diffed again instead of being taken from the cached diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RediffReason {
+    /// All files were diffed, as nothing was cached or the worktree may have been rewritten.
+    FullDiff,
+    /// The file was [reported](WorkdirCache::invalidate()) as changed, usually by the watcher.
+    WatcherEvent,
+    /// The file had changes before, and its size or modification time changed since.
+    StatChanged,
+    /// The file differs between the commit diffed against before and the one diffed against now.
+    CommitChanged,
+    /// The file is one side of a rename or copy whose other side was diffed again.
+    RenamePartner,
+}
+
+#[derive(Debug)]
+struct CachedDiff {
+    /// The stat of the index when the diff was computed. Rewriting the worktree updates the index,
+    /// so if it changed, all files may have changed.
+    index: Option<FileStat>,
+    tree_id: git2::Oid,
+    options: DiffOptions,
+    /// The paths the diff is limited to, if it is.
+    scope: Option<Vec<PathBuf>>,
+    files: DiffByPathMap,
+    /// The text of the hunks of `files` if it was moved to a temporary file while the diff isn't used.
+    spilled: Option<SpilledText>,
+    /// The stats of each file in `files` before it was diffed, or `None` if it didn't exist.
+    stats: HashMap<PathBuf, Option<FileStat>>,
+}
+
+impl CachedDiff {
+    /// Move the text of the diff to a temporary file if it's too large to keep in memory while unused.
+    fn spill_if_large(&mut self) -> Result<()> {
+        let Some(limit) = memory_limits().spill_diffs_above_bytes else {
+            return Ok(());
+        };
+        if self.spilled.is_none() && text_size(&self.files) > limit {
+            self.spilled = Some(SpilledText::spill(&mut self.files)?);
+        }
+        Ok(())
+    }
+
+    /// Bring the text of the diff back into memory if it was spilled.
+    fn unspill(&mut self) -> Result<()> {
+        if let Some(spilled) = self.spilled.take() {
+            spilled.restore(&mut self.files)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStat {
+    modified: Option<SystemTime>,
+    size: u64,
+}
+
+impl FileStat {
+    fn of(workdir: &Path, path: &Path) -> Option<Self> {
+        let metadata = std::fs::symlink_metadata(workdir.join(path)).ok()?;
+        Some(FileStat {
+            modified: metadata.modified().ok(),
+            size: metadata.len(),
+        })
+    }
+}
+
+impl WorkdirCache {
+    /// Note that the files at `paths`, relative to the worktree, changed and have to be diffed again.
+    pub fn invalidate(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        for path in paths {
+            if GLOBAL_FILES.iter().any(|name| {
+                path.file_name()
+                    .map_or(false, |file_name| file_name == *name)
+            }) {
+                self.invalidate_all();
+                return;
+            }
+            self.changed_paths.insert(path);
+        }
+    }
+
+    /// Drop the cached diff so the next diff is computed from scratch.
+    pub fn invalidate_all(&mut self) {
+        self.state = None;
+        self.changed_paths.clear();
+        self.rediffed.clear();
+    }
+
+    /// Return why each file was diffed again by the last diff, with all other files of the diff taken
+    /// from the cache as they were.
+    pub fn rediffed(&self) -> &BTreeMap<PathBuf, RediffReason> {
+        &self.rediffed
+    }
+
+    /// Return the diff of the worktree against `commit_oid` according to `options`, reusing the cached diff
+    /// for all files that didn't change.
+    pub fn workdir(
+        &mut self,
+        repo: &git2::Repository,
+        commit_oid: git2::Oid,
+        options: &DiffOptions,
+    ) -> Result<DiffByPathMap> {
+        self.workdir_in_scope(repo, commit_oid, options, None)
+    }
+
+    /// Like [`workdir()`](Self::workdir()), but if `scope` is set, only the files at or below these paths
+    /// are diffed, just like [`workdir_in_scope()`](crate::workdir_in_scope()) does.
+    pub fn workdir_in_scope(
+        &mut self,
+        repo: &git2::Repository,
+        commit_oid: git2::Oid,
+        options: &DiffOptions,
+        scope: Option<&[PathBuf]>,
+    ) -> Result<DiffByPathMap> {
+        let result = self
+            .update(repo, commit_oid, options, scope)
+            .and_then(|files| {
+                if let Some(state) = &mut self.state {
+                    state.spill_if_large()?;
+                }
+                Ok(files)
+            });
+        if result.is_err() {
+            self.invalidate_all();
+        }
+        result
+    }
+
+    fn update(
+        &mut self,
+        repo: &git2::Repository,
+        commit_oid: git2::Oid,
+        options: &DiffOptions,
+        scope: Option<&[PathBuf]>,
+    ) -> Result<DiffByPathMap> {
+        let workdir = repo.workdir().context("a worktree is needed to diff it")?;
+        let tree_id = repo
+            .find_commit(commit_oid)
+            .context("failed to find commit")?
+            .tree_id();
+        let mut changed_paths: BTreeMap<_, _> = std::mem::take(&mut self.changed_paths)
+            .into_iter()
+            .map(|path| (path, RediffReason::WatcherEvent))
+            .collect();
+        let index = FileStat::of(repo.path(), Path::new("index"));
+        if self.state.as_ref().map_or(true, |state| {
+            state.options != *options || state.index != index || state.scope.as_deref() != scope
+        }) {
+            let files = workdir_of_paths(repo, &commit_oid, options, scope)?;
+            let stats = files
+                .keys()
+                .map(|path| (path.clone(), FileStat::of(workdir, path)))
+                .collect();
+            self.rediffed = files
+                .keys()
+                .map(|path| (path.clone(), RediffReason::FullDiff))
+                .collect();
+            self.state = Some(CachedDiff {
+                index,
+                tree_id,
+                options: *options,
+                scope: scope.map(ToOwned::to_owned),
+                files: files.clone(),
+                spilled: None,
+                stats,
+            });
+            return Ok(files);
+        }
+        let state = self.state.as_mut().expect("present as checked above");
+        state.unspill()?;
+
+        if state.tree_id != tree_id {
+            let old_tree = repo.find_tree(state.tree_id)?;
+            let new_tree = repo.find_tree(tree_id)?;
+            let diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+            for delta in diff.deltas() {
+                for path in [delta.old_file().path(), delta.new_file().path()]
+                    .into_iter()
+                    .flatten()
+                {
+                    changed_paths
+                        .entry(path.to_owned())
+                        .or_insert(RediffReason::CommitChanged);
+                }
+            }
+        }
+        // Changes to files that changed before may have been missed, and checking them is cheap.
+        for (path, stat) in &state.stats {
+            if FileStat::of(workdir, path) != *stat {
+                changed_paths
+                    .entry(path.clone())
+                    .or_insert(RediffReason::StatChanged);
+            }
+        }
+        // Both sides of a rename have to be diffed together to find it again.
+        for file in state.files.values() {
+            if let Some(
+                PathChange::Renamed {
+                    old_path, new_path, ..
+                }
+                | PathChange::Copied {
+                    old_path, new_path, ..
+                },
+            ) = &file.path_change
+            {
+                if changed_paths.contains_key(old_path) || changed_paths.contains_key(new_path) {
+                    for path in [old_path, new_path] {
+                        changed_paths
+                            .entry(path.clone())
+                            .or_insert(RediffReason::RenamePartner);
+                    }
+                }
+            }
+        }
+
+        if let Some(scope) = scope {
+            changed_paths
+                .retain(|path, _reason| scope.iter().any(|in_scope| path.starts_with(in_scope)));
+        }
+        let paths: Vec<_> = changed_paths.keys().cloned().collect();
+        let stats: Vec<_> = paths
+            .iter()
+            .map(|path| FileStat::of(workdir, path))
+            .collect();
+        let files = workdir_of_paths(repo, &commit_oid, options, Some(&paths))?;
+        for (path, stat) in paths.into_iter().zip(stats) {
+            state.files.remove(&path);
+            state.stats.remove(&path);
+            if files.contains_key(&path) {
+                state.stats.insert(path, stat);
+            }
+        }
+        state.files.extend(files);
+        state.tree_id = tree_id;
+        self.rediffed = changed_paths;
+        Ok(state.files.clone())
+    }
+}
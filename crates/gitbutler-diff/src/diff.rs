@@ -1,16 +1,30 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{BTreeSet, HashMap, HashSet},
     path::{Path, PathBuf},
     str,
 };
 
 use anyhow::{Context, Result};
 use bstr::{BStr, BString, ByteSlice, ByteVec};
+use gitbutler_command_context::cancellation;
 use gitbutler_serde::BStringForFrontend;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
+use crate::{
+    binary::{describe_binary_files, mime_guess},
+    intra_line_highlights,
+    lfs::{self, describe_pointers},
+    nested::is_nested_repository,
+    oid_cache, parallel,
+    path_normalization::{describe_respellings, respell_tree},
+    rename::describe_path_changes,
+    semantic,
+    write::file_mode,
+    ImageDimensions, LineHighlight, PathChange,
+};
+
 pub type DiffByPathMap = HashMap<PathBuf, FileDiff>;
 
 /// The type of change
@@ -54,6 +68,9 @@ pub struct GitHunk {
     pub diff_lines: BStringForFrontend,
     pub binary: bool,
     pub change_type: ChangeType,
+    /// The changed parts of lines that were modified, if the diff was computed with [`DiffGranularity::Word`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlights: Option<Vec<LineHighlight>>,
 }
 
 /// Lifecycle
@@ -69,6 +86,7 @@ impl GitHunk {
             diff_lines: hex_id.into(),
             binary: true,
             change_type,
+            highlights: None,
         }
     }
 
@@ -82,6 +100,7 @@ impl GitHunk {
             diff_lines: Default::default(),
             binary: false,
             change_type: ChangeType::Modified,
+            highlights: None,
         }
     }
 }
@@ -123,40 +142,214 @@ pub struct FileDiff {
     pub binary: bool,
     pub old_size_bytes: u64,
     pub new_size_bytes: u64,
+    /// A guess of the media type of binary files from their extension, like `image/png`.
+    pub mime_guess: Option<String>,
+    /// The dimensions of binary files that are images from before the change, if they could be read cheaply.
+    pub old_image_dimensions: Option<ImageDimensions>,
+    /// The dimensions of binary files that are images from after the change, if they could be read cheaply.
+    pub new_image_dimensions: Option<ImageDimensions>,
+    /// How the file got to its path if it was renamed or copied, in which case `hunks` are the changes
+    /// to the content of `old_path`.
+    pub path_change: Option<PathChange>,
+    /// The LFS pointer the file was before the change, if it's stored with LFS. `hunks` are then the
+    /// changes to the pointer file, and should be shown as change of the pointers instead.
+    pub old_lfs_pointer: Option<lfs::Pointer>,
+    /// The LFS pointer the file is after the change, if it's stored with LFS.
+    pub new_lfs_pointer: Option<lfs::Pointer>,
+}
+
+/// How precisely changes are described.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffGranularity {
+    /// Changes are described by the lines they affect.
+    #[default]
+    Line,
+    /// Like [`Line`](Self::Line), but modified lines are also highlighted where their words changed.
+    Word,
+}
+
+/// Options that control how diffs are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiffOptions {
+    pub granularity: DiffGranularity,
+    /// The amount of unchanged lines to show around each change.
+    pub context_lines: u32,
+    /// If `true`, changes that only affect whitespace are ignored.
+    pub ignore_whitespace: bool,
+    /// If `true`, changes that only add or remove blank lines are ignored.
+    pub ignore_blank_lines: bool,
+    /// Files larger than this are treated as binary, and the content of such files in the worktree isn't
+    /// even read.
+    pub max_file_size_bytes: u64,
+    /// If set, files that were renamed or copied with at least this similarity in percent are one change
+    /// of the new path, instead of a deletion and an addition, or an addition respectively.
+    /// Only copies of files that changed as well are found.
+    pub rename_threshold: Option<u16>,
+    /// The maximum amount of threads used to hash the files of the worktree when all of it is diffed.
+    pub threads: usize,
+    /// If `true`, hunks of source files are split where sections like functions start, as long as an
+    /// unchanged line separates the changes of each section. This needs the `semantic-hunks` feature.
+    pub semantic_hunks: bool,
+    /// If `true`, the ids of files that differ from the index are kept in the `.git` directory, and
+    /// `core.fsmonitor` is used if configured, so diffing all of a large worktree doesn't have to look at
+    /// or hash each file again.
+    pub persistent_cache: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            granularity: DiffGranularity::Line,
+            context_lines: 3,
+            ignore_whitespace: false,
+            ignore_blank_lines: false,
+            max_file_size_bytes: 50_000_000,
+            rename_threshold: None,
+            threads: 1,
+            semantic_hunks: false,
+            persistent_cache: false,
+        }
+    }
+}
+
+impl DiffOptions {
+    fn apply(&self, diff_opts: &mut git2::DiffOptions) {
+        diff_opts
+            .context_lines(self.context_lines)
+            .ignore_whitespace(self.ignore_whitespace)
+            .ignore_blank_lines(self.ignore_blank_lines)
+            .max_size(i64::try_from(self.max_file_size_bytes).unwrap_or(i64::MAX));
+    }
+
+    /// Return `true` if all changes in `diff_lines`, the `+`, `-` or ` ` prefixed lines of a text hunk,
+    /// would be ignored with these options, so the hunk can be hidden from views that use them.
+    pub fn ignores(&self, diff_lines: &BStr) -> bool {
+        if !self.ignore_whitespace && !self.ignore_blank_lines {
+            return false;
+        }
+        let normalize = |line: &[u8]| -> Option<Vec<u8>> {
+            if self.ignore_blank_lines && line.trim().is_empty() {
+                None
+            } else if self.ignore_whitespace {
+                Some(
+                    line.iter()
+                        .filter(|byte| !byte.is_ascii_whitespace())
+                        .copied()
+                        .collect(),
+                )
+            } else {
+                Some(line.to_vec())
+            }
+        };
+        let mut has_changes = false;
+        let mut removed: Vec<Vec<u8>> = Vec::new();
+        let mut added: Vec<Vec<u8>> = Vec::new();
+        for line in diff_lines.lines() {
+            match line.first() {
+                Some(b'-') => removed.extend(normalize(&line[1..])),
+                Some(b'+') => added.extend(normalize(&line[1..])),
+                _ => continue,
+            }
+            has_changes = true;
+        }
+        has_changes && removed == added
+    }
+
+    /// Turn deletions and additions in `diff` into renames and copies if requested.
+    fn find_renames(&self, diff: &mut git2::Diff<'_>) -> Result<()> {
+        let Some(threshold) = self.rename_threshold else {
+            return Ok(());
+        };
+        let mut find_opts = git2::DiffFindOptions::new();
+        find_opts
+            .renames(true)
+            .copies(true)
+            .rename_threshold(threshold)
+            .copy_threshold(threshold);
+        diff.find_similar(Some(&mut find_opts))
+            .context("failed to find renamed files")?;
+        Ok(())
+    }
+
+    /// Split the hunks of `files` where sections of their new content start if requested.
+    fn split_semantically(
+        &self,
+        repo: &git2::Repository,
+        diff: &git2::Diff<'_>,
+        files: &mut DiffByPathMap,
+    ) {
+        if self.semantic_hunks {
+            semantic::split_hunks(repo, diff, files);
+        }
+    }
+
+    /// Add word-level highlights to all hunks of `files` if requested.
+    fn highlight(&self, files: &mut DiffByPathMap) {
+        if self.granularity != DiffGranularity::Word {
+            return;
+        }
+        for hunk in files.values_mut().flat_map(|file| &mut file.hunks) {
+            if !hunk.binary {
+                hunk.highlights = Some(intra_line_highlights(
+                    hunk.diff_lines.as_ref(),
+                    self.ignore_whitespace,
+                ));
+            }
+        }
+    }
 }
 
 #[instrument(skip(repo))]
 pub fn workdir(repo: &git2::Repository, commit_oid: &git2::Oid) -> Result<DiffByPathMap> {
+    workdir_with_options(repo, commit_oid, &DiffOptions::default())
+}
+
+/// Like [`workdir()`], but computes the diff according to `options`.
+#[instrument(skip(repo))]
+pub fn workdir_with_options(
+    repo: &git2::Repository,
+    commit_oid: &git2::Oid,
+    options: &DiffOptions,
+) -> Result<DiffByPathMap> {
+    workdir_of_paths(repo, commit_oid, options, None)
+}
+
+/// Like [`workdir_with_options()`], but only the files at or below the paths in `scope`, relative to
+/// the worktree, are diffed. All other files are assumed to be unchanged, which is what they are
+/// in a sparse checkout, even though they are missing from the worktree.
+pub fn workdir_in_scope(
+    repo: &git2::Repository,
+    commit_oid: &git2::Oid,
+    options: &DiffOptions,
+    scope: &[PathBuf],
+) -> Result<DiffByPathMap> {
+    workdir_of_paths(repo, commit_oid, options, Some(scope))
+}
+
+/// Like [`workdir_with_options()`], but if `paths` is set, only these files, relative to the worktree,
+/// are diffed.
+#[instrument(skip(repo, options, paths), fields(operation = "diff", paths = paths.map(<[PathBuf]>::len)))]
+pub(crate) fn workdir_of_paths(
+    repo: &git2::Repository,
+    commit_oid: &git2::Oid,
+    options: &DiffOptions,
+    paths: Option<&[PathBuf]>,
+) -> Result<DiffByPathMap> {
+    if paths.map_or(false, <[PathBuf]>::is_empty) {
+        return Ok(DiffByPathMap::new());
+    }
     let commit = repo
         .find_commit(*commit_oid)
         .context("failed to find commit")?;
     let old_tree = commit.tree().context("failed to find tree")?;
 
-    let mut workdir_index = repo.index()?;
-
-    let mut skipped_files = HashMap::new();
-    let cb = &mut |path: &Path, _matched_spec: &[u8]| -> i32 {
-        let file_size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-        if file_size > 50_000_000 {
-            skipped_files.insert(
-                path.to_path_buf(),
-                FileDiff {
-                    old_path: None,
-                    new_path: None,
-                    hunks: Vec::new(),
-                    skipped: true,
-                    binary: true,
-                    old_size_bytes: 0,
-                    new_size_bytes: 0,
-                },
-            );
-            1 //skips the entry
-        } else {
-            0
-        }
+    let (workdir_tree_id, skipped_files) = match paths {
+        None if options.persistent_cache => oid_cache::worktree_tree(repo, &old_tree, options)?,
+        None if options.threads > 1 => worktree_tree_in_parallel(repo, &old_tree, options)?,
+        _ => worktree_tree(repo, &old_tree, options, paths)?,
     };
-    workdir_index.add_all(["."], git2::IndexAddOption::DEFAULT, Some(cb))?;
-    let workdir_tree_id = workdir_index.write_tree()?;
 
     let new_tree = repo.find_tree(workdir_tree_id)?;
 
@@ -166,12 +359,33 @@ pub fn workdir(repo: &git2::Repository, commit_oid: &git2::Oid) -> Result<DiffBy
         .include_untracked(true)
         .show_binary(true)
         .show_untracked_content(true)
-        .ignore_submodules(true)
-        .context_lines(3);
-
-    let diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), Some(&mut diff_opts))?;
+        .ignore_submodules(true);
+    if let Some(paths) = paths {
+        diff_opts.disable_pathspec_match(true);
+        for path in paths {
+            diff_opts.pathspec(path.as_path());
+        }
+    }
+    options.apply(&mut diff_opts);
+
+    let mut diff =
+        repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), Some(&mut diff_opts))?;
+    let respelled = respell_tree(repo, &old_tree, &diff)?;
+    if let Some((respelled_tree, _)) = &respelled {
+        diff =
+            repo.diff_tree_to_tree(Some(respelled_tree), Some(&new_tree), Some(&mut diff_opts))?;
+    }
+    options.find_renames(&mut diff)?;
     let diff_files = hunks_by_filepath(Some(repo), &diff);
     diff_files.map(|mut df| {
+        options.split_semantically(repo, &diff, &mut df);
+        options.highlight(&mut df);
+        describe_binary_files(repo, &diff, &mut df);
+        describe_path_changes(repo, &diff, &mut df);
+        if let Some((_, pairs)) = &respelled {
+            describe_respellings(repo, &diff, &mut df, pairs);
+        }
+        describe_pointers(repo, &diff, &mut df);
         for (key, value) in skipped_files {
             df.insert(key, value);
         }
@@ -183,6 +397,16 @@ pub fn trees(
     repository: &git2::Repository,
     old_tree: &git2::Tree,
     new_tree: &git2::Tree,
+) -> Result<DiffByPathMap> {
+    trees_with_options(repository, old_tree, new_tree, &DiffOptions::default())
+}
+
+/// Like [`trees()`], but computes the diff according to `options`.
+pub fn trees_with_options(
+    repository: &git2::Repository,
+    old_tree: &git2::Tree,
+    new_tree: &git2::Tree,
+    options: &DiffOptions,
 ) -> Result<DiffByPathMap> {
     let mut diff_opts = git2::DiffOptions::new();
     diff_opts
@@ -190,13 +414,287 @@ pub fn trees(
         .include_untracked(true)
         .show_binary(true)
         .ignore_submodules(true)
-        .context_lines(3)
         .show_untracked_content(true);
+    options.apply(&mut diff_opts);
 
-    let diff =
+    let mut diff =
         repository.diff_tree_to_tree(Some(old_tree), Some(new_tree), Some(&mut diff_opts))?;
+    let respelled = respell_tree(repository, old_tree, &diff)?;
+    if let Some((respelled_tree, _)) = &respelled {
+        diff = repository.diff_tree_to_tree(
+            Some(respelled_tree),
+            Some(new_tree),
+            Some(&mut diff_opts),
+        )?;
+    }
+    options.find_renames(&mut diff)?;
+
+    let mut diff_files = hunks_by_filepath(None, &diff)?;
+    options.split_semantically(repository, &diff, &mut diff_files);
+    options.highlight(&mut diff_files);
+    describe_binary_files(repository, &diff, &mut diff_files);
+    describe_path_changes(repository, &diff, &mut diff_files);
+    if let Some((_, pairs)) = &respelled {
+        describe_respellings(repository, &diff, &mut diff_files, pairs);
+    }
+    describe_pointers(repository, &diff, &mut diff_files);
+    Ok(diff_files)
+}
 
-    hunks_by_filepath(None, &diff)
+/// Write the tree of the worktree on top of the index, and return its id along with the files that
+/// were skipped for being too large. If `paths` is set, only these files, relative to the worktree, are
+/// taken from the worktree.
+pub(crate) fn worktree_tree(
+    repo: &git2::Repository,
+    old_tree: &git2::Tree,
+    options: &DiffOptions,
+    paths: Option<&[PathBuf]>,
+) -> Result<(git2::Oid, HashMap<PathBuf, FileDiff>)> {
+    let mut workdir_index = repo.index()?;
+    let skipped_files = add_worktree_files(repo, &mut workdir_index, old_tree, options, paths)?;
+    Ok((workdir_index.write_tree()?, skipped_files))
+}
+
+/// Add the files of the worktree to `workdir_index`, or only those at `paths` relative to the worktree if
+/// set, and return the files that were skipped for being too large.
+///
+/// Stops with `Code::Cancelled` between files if the [current operation](cancellation::current()) is
+/// cancelled, which leaves `workdir_index` half-updated, but it's never written then.
+pub(crate) fn add_worktree_files(
+    repo: &git2::Repository,
+    workdir_index: &mut git2::Index,
+    old_tree: &git2::Tree,
+    options: &DiffOptions,
+    paths: Option<&[PathBuf]>,
+) -> Result<HashMap<PathBuf, FileDiff>> {
+    let mut skipped_files = HashMap::new();
+    let uses_lfs = lfs::is_used(repo);
+    let mut lfs_files = Vec::new();
+    let cancellation = cancellation::current();
+    let cb = &mut |path: &Path, _matched_spec: &[u8]| -> i32 {
+        if cancellation.is_cancelled() {
+            // Aborts before large files are hashed.
+            return -1;
+        }
+        if is_nested_repository(repo, path) {
+            // Its files belong to another repository, and it isn't a submodule that could be committed.
+            return 1;
+        }
+        if uses_lfs && lfs::is_tracked(repo, path) {
+            // libgit2 can't turn them into pointer files, so that's done below.
+            lfs_files.push(path.to_path_buf());
+            return 1;
+        }
+        let file_size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if file_size > options.max_file_size_bytes {
+            skipped_files.insert(
+                path.to_path_buf(),
+                FileDiff {
+                    old_path: None,
+                    new_path: None,
+                    hunks: Vec::new(),
+                    skipped: true,
+                    binary: true,
+                    old_size_bytes: 0,
+                    new_size_bytes: file_size,
+                    mime_guess: mime_guess(path).map(ToOwned::to_owned),
+                    old_image_dimensions: None,
+                    new_image_dimensions: None,
+                    path_change: None,
+                    old_lfs_pointer: None,
+                    new_lfs_pointer: None,
+                },
+            );
+            1 //skips the entry
+        } else {
+            0
+        }
+    };
+    let added = match paths {
+        Some(paths) => workdir_index.add_all(
+            paths.iter().map(PathBuf::as_path),
+            git2::IndexAddOption::DEFAULT | git2::IndexAddOption::DISABLE_PATHSPEC_MATCH,
+            Some(cb),
+        ),
+        None => workdir_index.add_all(["."], git2::IndexAddOption::DEFAULT, Some(cb)),
+    };
+    cancellation.check()?;
+    added?;
+    for path in lfs_files {
+        add_lfs_pointer(repo, workdir_index, old_tree, &path)?;
+    }
+    Ok(skipped_files)
+}
+
+/// Like [`worktree_tree()`] for the whole worktree, but with the files hashed on up to `options.threads`
+/// threads, which each take care of some parts of the worktree.
+pub(crate) fn worktree_tree_in_parallel(
+    repo: &git2::Repository,
+    old_tree: &git2::Tree,
+    options: &DiffOptions,
+) -> Result<(git2::Oid, HashMap<PathBuf, FileDiff>)> {
+    // More parts than threads keep all threads busy even if some parts are much larger than others.
+    let parts = worktree_parts(repo, options.threads * 4)?;
+    let chunks: Vec<_> = parts
+        .chunks(parts.len().div_ceil(options.threads * 4).max(1))
+        .collect();
+    let old_tree_id = old_tree.id();
+    let trees = parallel::map_with_repo(repo, &chunks, options.threads, 1, |repo, paths| {
+        let old_tree = repo.find_tree(old_tree_id)?;
+        worktree_tree(repo, &old_tree, options, Some(*paths))
+    })?;
+
+    // Each tree has the index for everything but its own parts, so only these are taken from it.
+    let mut builder = git2::build::TreeUpdateBuilder::new();
+    let mut skipped_files = HashMap::new();
+    for (paths, (tree_id, skipped)) in chunks.into_iter().zip(trees) {
+        let tree = repo.find_tree(tree_id)?;
+        for path in paths {
+            if let Ok(entry) = tree.get_path(path) {
+                builder.upsert(path, entry.id(), file_mode(entry.filemode()));
+            }
+        }
+        skipped_files.extend(skipped);
+    }
+    let empty_tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+    let tree_id = builder
+        .create_updated(repo, &empty_tree)
+        .context("failed to write worktree tree")?;
+    Ok((tree_id, skipped_files))
+}
+
+/// The deepest directory that is split into its entries by [`worktree_parts()`].
+const MAX_PART_DEPTH: usize = 3;
+
+/// Return paths relative to the worktree of `repo` that together cover all files of the worktree and
+/// the index without overlapping, with directories split into their entries until there are at least
+/// `min_parts` of them.
+///
+/// Submodules, other repositories and ignored directories aren't split.
+fn worktree_parts(repo: &git2::Repository, min_parts: usize) -> Result<Vec<PathBuf>> {
+    let workdir = repo.workdir().context("a worktree is needed to diff it")?;
+    let index = repo.index()?;
+    let mut index_children: HashMap<PathBuf, BTreeSet<PathBuf>> = HashMap::new();
+    let mut gitlinks = HashSet::new();
+    for entry in index.iter() {
+        let path = entry.path.to_path_lossy();
+        if entry.mode == 0o160000 {
+            gitlinks.insert(path.to_path_buf());
+        }
+        let mut parent = PathBuf::new();
+        for component in path.components().take(MAX_PART_DEPTH + 1) {
+            let child = parent.join(component);
+            index_children
+                .entry(parent)
+                .or_default()
+                .insert(child.clone());
+            parent = child;
+        }
+    }
+
+    let children = |dir: &Path| -> Result<BTreeSet<PathBuf>> {
+        let mut children = index_children.get(dir).cloned().unwrap_or_default();
+        match std::fs::read_dir(workdir.join(dir)) {
+            Ok(entries) => {
+                for entry in entries {
+                    let name = entry?.file_name();
+                    if dir.as_os_str().is_empty() && name == ".git" {
+                        continue;
+                    }
+                    children.insert(dir.join(name));
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(children)
+    };
+    let is_splittable = |path: &Path| {
+        let full_path = workdir.join(path);
+        let is_dir = std::fs::symlink_metadata(&full_path).map_or(false, |m| m.is_dir());
+        if !is_dir {
+            // A deleted directory can only be known by the index.
+            return !full_path.exists() && index_children.contains_key(path);
+        }
+        !gitlinks.contains(path)
+            && !full_path.join(".git").exists()
+            && (index_children.contains_key(path) || !repo.is_path_ignored(path).unwrap_or(true))
+    };
+
+    let mut parts: Vec<_> = children(Path::new(""))?.into_iter().collect();
+    for _ in 1..MAX_PART_DEPTH {
+        if parts.len() >= min_parts {
+            break;
+        }
+        let mut split_parts = Vec::with_capacity(parts.len());
+        for path in parts {
+            if is_splittable(&path) {
+                split_parts.extend(children(&path)?);
+            } else {
+                split_parts.push(path);
+            }
+        }
+        parts = split_parts;
+    }
+    Ok(parts)
+}
+
+/// Add the file at `path`, which is stored with LFS, to `index` as the pointer file it would be committed as.
+///
+/// Cleaning hashes all of the content, so if `old_tree` has a pointer to content of the same size,
+/// the file is assumed to be unchanged and that pointer is used instead, much like Git trusts file stats.
+fn add_lfs_pointer(
+    repo: &git2::Repository,
+    index: &mut git2::Index,
+    old_tree: &git2::Tree,
+    path: &Path,
+) -> Result<()> {
+    let full_path = repo
+        .workdir()
+        .context("LFS needs a repository with a worktree")?
+        .join(path);
+    let size = std::fs::metadata(&full_path)
+        .with_context(|| format!("failed to read metadata of {}", full_path.display()))?
+        .len();
+    let old_pointer = old_tree
+        .get_path(path)
+        .ok()
+        .and_then(|entry| repo.find_blob(entry.id()).ok())
+        .filter(|blob| {
+            lfs::Pointer::parse(blob.content()).map_or(false, |pointer| pointer.size == size)
+        })
+        .map(|blob| blob.content().to_vec());
+    let pointer = match old_pointer {
+        Some(pointer) => pointer,
+        None => {
+            let content = std::fs::read(&full_path)
+                .with_context(|| format!("failed to read {}", full_path.display()))?;
+            // The file may not have been smudged, in which case it's a pointer already.
+            if lfs::Pointer::parse(&content).is_some() {
+                content
+            } else {
+                lfs::clean(repo, path, &content)?
+            }
+        }
+    };
+    let entry = git2::IndexEntry {
+        ctime: git2::IndexTime::new(0, 0),
+        mtime: git2::IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: 0o100644,
+        uid: 0,
+        gid: 0,
+        file_size: u32::try_from(pointer.len()).unwrap_or(u32::MAX),
+        id: git2::Oid::zero(),
+        flags: 0,
+        flags_extended: 0,
+        path: Vec::from_path_lossy(path).into_owned(),
+    };
+    index
+        .add_frombuffer(&entry, &pointer)
+        .with_context(|| format!("failed to add the pointer of {}", path.display()))?;
+    Ok(())
 }
 
 /// Transform `diff` into a mapping of `worktree-relative path -> FileDiff`, where `FileDiff` is
@@ -217,10 +715,14 @@ pub fn hunks_by_filepath(
     // find all the hunks
     let mut diff_files = HashMap::new();
     let mut err = None;
+    let cancellation = cancellation::current();
 
-    diff.print(
+    let printed = diff.print(
         git2::DiffFormat::Patch,
         |delta, hunk, line: git2::DiffLine<'_>| {
+            if cancellation.is_cancelled() {
+                return false;
+            }
             let change_type: ChangeType = delta.status().into();
             let file_path = delta.new_file().path().unwrap_or_else(|| {
                 delta
@@ -276,6 +778,12 @@ pub fn hunks_by_filepath(
                                 binary: delta.new_file().is_binary(),
                                 old_size_bytes: delta.old_file().size(),
                                 new_size_bytes: delta.new_file().size(),
+                                mime_guess: None,
+                                old_image_dimensions: None,
+                                new_image_dimensions: None,
+                                path_change: None,
+                                old_lfs_pointer: None,
+                                new_lfs_pointer: None,
                         });
                     if existing.is_some() {
                         err = Some(format!("Encountered an invalid internal state related to the diff: {existing:?}"));
@@ -311,6 +819,7 @@ pub fn hunks_by_filepath(
                                         diff_lines: line.into_owned().into(),
                                         binary: false,
                                         change_type,
+                                        highlights: None,
                                     }
                                 }
                                 LineOrHexHash::HexHashOfBinaryBlob(id) => {
@@ -324,8 +833,9 @@ pub fn hunks_by_filepath(
             }
             true
         },
-    )
-    .with_context(|| format!("failed to print diff: {err:?}"))?;
+    );
+    cancellation.check()?;
+    printed.with_context(|| format!("failed to print diff: {err:?}"))?;
 
     for file in diff_files.values_mut() {
         if let Some(binary_hunk) = file
@@ -338,7 +848,8 @@ pub fn hunks_by_filepath(
                 // if there are multiple hunks with binary among them, we replace it with a single marker.
                 file.hunks = vec![binary_hunk];
             }
-        } else if file.hunks.is_empty() {
+        } else if file.hunks.is_empty() && file.old_path == file.new_path {
+            // files that were only renamed or copied have no changes to show
             file.hunks = vec![GitHunk::generic_new_file()];
         }
     }
@@ -417,6 +928,7 @@ pub fn reverse_hunk(hunk: &GitHunk) -> Option<GitHunk> {
             diff_lines: diff.into(),
             binary: hunk.binary,
             change_type: hunk.change_type,
+            highlights: None,
         })
     }
 }
@@ -0,0 +1,355 @@
+//! Convert file content between its form in the repository and its form in the worktree like Git does, so
+//! blobs written from worktree files and files written from blobs match what `git add` and `git checkout`
+//! would produce, instead of showing every line as changed.
+//!
+//! * Line endings follow the `text` and `eol` attributes, and `core.autocrlf` and `core.eol` for files
+//!   without them. Like Git, files with `text=auto` that have CRLF line endings in the index are left as they
+//!   are, as converting them would change every line.
+//! * Filter drivers assigned with the `filter` attribute run their `filter.NAME.clean` and
+//!   `filter.NAME.smudge` commands, with `%f` replaced by the path of the file. LFS is handled by
+//!   [`lfs`](crate::lfs), as it needs more than a filter.
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_command_context::extra_env;
+use gitbutler_git::ProcessEnv;
+use serde::Serialize;
+
+use crate::{lfs, STAGE_MASK};
+
+/// Only this many bytes are looked at to tell if content is binary, like Git does.
+const BINARY_DETECTION_BYTES: usize = 8000;
+
+/// How long the command of a filter driver may take for a single file before it's killed.
+const DRIVER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The longest time to wait between checks whether a command run with [`pipe()`] has finished.
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The line endings of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    /// Both LF and CRLF are used.
+    Mixed,
+}
+
+impl LineEnding {
+    /// Return the line endings used by `content`, or `None` if it has no line breaks.
+    pub fn of(content: &[u8]) -> Option<Self> {
+        let crlf = content.find(b"\r\n").is_some();
+        let lf = content
+            .find_iter(b"\n")
+            .any(|idx| idx == 0 || content[idx - 1] != b'\r');
+        match (lf, crlf) {
+            (true, true) => Some(LineEnding::Mixed),
+            (true, false) => Some(LineEnding::Lf),
+            (false, true) => Some(LineEnding::Crlf),
+            (false, false) => None,
+        }
+    }
+}
+
+/// A file whose line endings in the worktree differ from those it would be checked out with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EolChange {
+    /// The path of the file, relative to the worktree.
+    pub path: PathBuf,
+    /// The line endings of the file in the worktree.
+    pub current: LineEnding,
+    /// The line endings the file would have if it was checked out.
+    pub expected: LineEnding,
+}
+
+/// How the line endings of a file are converted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Text {
+    /// The file is never converted.
+    Binary,
+    /// The file is converted if its content looks like text.
+    Auto,
+    /// The file is always converted.
+    Text,
+}
+
+/// Return the content of the file at `path`, relative to the worktree, as it's stored in the repository, with
+/// `content` being the content of the file in the worktree.
+pub fn clean(repo: &git2::Repository, path: &Path, content: &[u8]) -> Result<Vec<u8>> {
+    let content = match attribute(repo, path, "filter")?.as_deref() {
+        Some("lfs") => return lfs::clean(repo, path, content),
+        Some(name) => run_driver(repo, name, "clean", path, content)?,
+        None => content.to_vec(),
+    };
+    let convert = match text(repo, path)? {
+        Text::Binary => false,
+        Text::Text => true,
+        Text::Auto => !is_binary(&content) && !index_has_crlf(repo, path)?,
+    };
+    Ok(if convert {
+        content.replace(b"\r\n", b"\n")
+    } else {
+        content
+    })
+}
+
+/// Return the content of the file at `path`, relative to the worktree, as it's written to the worktree, with
+/// `content` being the content of the file in the repository.
+pub fn smudge(repo: &git2::Repository, path: &Path, content: &[u8]) -> Result<Vec<u8>> {
+    let filter = attribute(repo, path, "filter")?;
+    if filter.as_deref() == Some("lfs") {
+        return lfs::smudge(repo, path, content);
+    }
+    let content = to_worktree_eol(repo, path, content)?;
+    match filter.as_deref() {
+        Some(name) => run_driver(repo, name, "smudge", path, &content),
+        None => Ok(content),
+    }
+}
+
+/// Return the files of the index whose line endings in the worktree differ from the ones they would be
+/// checked out with, sorted by path. These are the files that show changes to every line once they are
+/// written by a checkout, or that do so already.
+///
+/// Binary files, files without line breaks and files with filter drivers are left out.
+pub fn eol_changes(repo: &git2::Repository) -> Result<Vec<EolChange>> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("line endings can only be checked in a worktree"))?;
+    let index = repo.index()?;
+    let mut changes = Vec::new();
+    for entry in index.iter() {
+        let is_file = entry.mode == u32::from(git2::FileMode::Blob)
+            || entry.mode == u32::from(git2::FileMode::BlobExecutable);
+        if entry.flags & STAGE_MASK != 0 || !is_file {
+            continue;
+        }
+        let path = entry.path.to_path_lossy().into_owned();
+        if attribute(repo, &path, "filter")?.is_some() {
+            continue;
+        }
+        let Ok(current) = std::fs::read(workdir.join(&path)) else {
+            continue;
+        };
+        let blob = repo.find_blob(entry.id)?;
+        if is_binary(blob.content()) || is_binary(&current) {
+            continue;
+        }
+        let expected = to_worktree_eol(repo, &path, blob.content())?;
+        let (Some(current), Some(expected)) = (LineEnding::of(&current), LineEnding::of(&expected))
+        else {
+            continue;
+        };
+        if current != expected {
+            changes.push(EolChange {
+                path,
+                current,
+                expected,
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(changes)
+}
+
+/// Convert the line endings of `content` of the file at `path` from LF to those of the worktree, if any.
+fn to_worktree_eol(repo: &git2::Repository, path: &Path, content: &[u8]) -> Result<Vec<u8>> {
+    if worktree_eol(repo, path)? != Some(LineEnding::Crlf) {
+        return Ok(content.to_vec());
+    }
+    let convert = match text(repo, path)? {
+        Text::Binary => false,
+        Text::Text => true,
+        // Like Git, content that already has CRLF is left as it is.
+        Text::Auto => !is_binary(content) && content.find(b"\r\n").is_none(),
+    };
+    if !convert {
+        return Ok(content.to_vec());
+    }
+    let mut converted = Vec::with_capacity(content.len() + content.len() / 32);
+    for (idx, byte) in content.iter().enumerate() {
+        if *byte == b'\n' && (idx == 0 || content[idx - 1] != b'\r') {
+            converted.push(b'\r');
+        }
+        converted.push(*byte);
+    }
+    Ok(converted)
+}
+
+/// Return whether the line endings of the file at `path` are converted, as told by its `text` and `eol`
+/// attributes, and `core.autocrlf` if it has none.
+fn text(repo: &git2::Repository, path: &Path) -> Result<Text> {
+    let value = repo.get_attr(path, "text", git2::AttrCheckFlags::FILE_THEN_INDEX)?;
+    Ok(match git2::AttrValue::from_string(value) {
+        git2::AttrValue::True => Text::Text,
+        git2::AttrValue::False => Text::Binary,
+        git2::AttrValue::String("auto") => Text::Auto,
+        _ if attribute(repo, path, "eol")?.is_some() => Text::Text,
+        _ if normalizes(repo)? => Text::Auto,
+        _ => Text::Binary,
+    })
+}
+
+/// Return `true` if `core.autocrlf` normalizes line endings of files without attributes.
+fn normalizes(repo: &git2::Repository) -> Result<bool> {
+    Ok(matches!(autocrlf(repo)?.as_deref(), Some("true" | "input")))
+}
+
+/// Return the line ending that text files are checked out with, or `None` if they are checked out with LF
+/// without converting anything.
+fn worktree_eol(repo: &git2::Repository, path: &Path) -> Result<Option<LineEnding>> {
+    match attribute(repo, path, "eol")?.as_deref() {
+        Some("crlf") => return Ok(Some(LineEnding::Crlf)),
+        Some("lf") => return Ok(Some(LineEnding::Lf)),
+        _ => {}
+    }
+    match autocrlf(repo)?.as_deref() {
+        Some("true") => return Ok(Some(LineEnding::Crlf)),
+        Some("input") => return Ok(Some(LineEnding::Lf)),
+        _ => {}
+    }
+    let config = repo.config()?;
+    Ok(match config.get_string("core.eol").ok().as_deref() {
+        Some("crlf") => Some(LineEnding::Crlf),
+        Some("lf") => Some(LineEnding::Lf),
+        _ if cfg!(windows) => Some(LineEnding::Crlf),
+        _ => None,
+    })
+}
+
+/// Return the value of `core.autocrlf`, with booleans as `true` or `false`.
+fn autocrlf(repo: &git2::Repository) -> Result<Option<String>> {
+    let config = repo.config()?;
+    Ok(match config.get_string("core.autocrlf") {
+        Ok(value) if value.eq_ignore_ascii_case("input") => Some("input".to_owned()),
+        Ok(_) => config
+            .get_bool("core.autocrlf")
+            .ok()
+            .map(|value| value.to_string()),
+        Err(_) => None,
+    })
+}
+
+/// Return the value of the attribute `name` of the file at `path`, if it's set to a value.
+fn attribute(repo: &git2::Repository, path: &Path, name: &str) -> Result<Option<String>> {
+    let value = repo.get_attr(path, name, git2::AttrCheckFlags::FILE_THEN_INDEX)?;
+    Ok(match git2::AttrValue::from_string(value) {
+        git2::AttrValue::String(value) => Some(value.to_owned()),
+        _ => None,
+    })
+}
+
+/// Return `true` if the file at `path` has CRLF line endings in the index.
+fn index_has_crlf(repo: &git2::Repository, path: &Path) -> Result<bool> {
+    let Some(entry) = repo.index()?.get_path(path, 0) else {
+        return Ok(false);
+    };
+    Ok(repo.find_blob(entry.id)?.content().find(b"\r\n").is_some())
+}
+
+/// Return `true` if `content` looks binary, like Git tells it.
+fn is_binary(content: &[u8]) -> bool {
+    content[..content.len().min(BINARY_DETECTION_BYTES)].contains(&0)
+}
+
+/// Run the `kind` command, `clean` or `smudge`, of the filter driver `name` for the file at `path` with `input`
+/// on stdin, and return its stdout. Without such a command, `input` is returned as is, unless the driver is
+/// configured as required.
+fn run_driver(
+    repo: &git2::Repository,
+    name: &str,
+    kind: &str,
+    path: &Path,
+    input: &[u8],
+) -> Result<Vec<u8>> {
+    let config = repo.config()?;
+    let Ok(command) = config.get_string(&format!("filter.{name}.{kind}")) else {
+        if config
+            .get_bool(&format!("filter.{name}.required"))
+            .unwrap_or(false)
+        {
+            return Err(anyhow!(
+                "the required filter '{name}' has no {kind} command for {}",
+                path.display()
+            ));
+        }
+        return Ok(input.to_vec());
+    };
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("filters need a repository with a worktree"))?;
+    let quoted_path = format!("'{}'", path.display().to_string().replace('\'', r"'\''"));
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command.replace("%f", &quoted_path))
+        .current_dir(workdir);
+    ProcessEnv::new()
+        .extend(extra_env::of_repository(repo))
+        .apply(&mut cmd);
+    let output = pipe(cmd, input, DRIVER_TIMEOUT)
+        .with_context(|| format!("failed to run the {kind} command of filter '{name}'"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "the {kind} command of filter '{name}' failed for {}: {}",
+            path.display(),
+            output.stderr.to_str_lossy().trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Run `cmd` with `input` on stdin, and return its output. It's killed once it runs for longer than `timeout`,
+/// which is an error.
+pub fn pipe(mut cmd: Command, input: &[u8], timeout: Duration) -> Result<Output> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Content can be large, so it's written while the output is read to avoid a deadlock. Processes started
+    // by the command may keep the pipes open after it was killed, so they aren't waited for then.
+    let mut stdin = child.stdin.take().expect("stdin was piped");
+    let input = input.to_owned();
+    // The command may exit without reading all input, which is reported by its exit status.
+    thread::spawn(move || stdin.write_all(&input).ok());
+    let stdout = read_in_background(child.stdout.take().expect("stdout was piped"));
+    let stderr = read_in_background(child.stderr.take().expect("stderr was piped"));
+
+    let started = Instant::now();
+    let mut poll_interval = Duration::from_millis(1);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            child.kill().ok();
+            child.wait().ok();
+            bail!("timed out after {timeout:?}");
+        }
+        thread::sleep(poll_interval);
+        poll_interval = (poll_interval * 2).min(MAX_POLL_INTERVAL);
+    };
+    let collect = |output: thread::JoinHandle<Vec<u8>>| output.join().unwrap_or_default();
+    Ok(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+fn read_in_background(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        pipe.read_to_end(&mut buf).ok();
+        buf
+    })
+}
@@ -0,0 +1,176 @@
+//! Ask the filesystem monitor configured with `core.fsmonitor` which files changed since a previous query,
+//! so the worktree doesn't have to be scanned to find them.
+//!
+//! Both kinds of monitor Git supports are queried: a hook command, like the one for Watchman, with
+//! `core.fsmonitor` set to its path, and the builtin daemon of `git fsmonitor--daemon` with `core.fsmonitor`
+//! set to `true`. The latter is only supported on Unix, where it listens on a socket.
+use std::{
+    path::PathBuf,
+    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, Result};
+use bstr::ByteSlice;
+use gitbutler_command_context::extra_env;
+use gitbutler_git::ProcessEnv;
+
+use crate::filter;
+
+/// The token to query with if there is no previous query, which makes the monitor report that anything may
+/// have changed, along with a token to use next time.
+const INITIAL_TOKEN: &str = "builtin:fake";
+
+/// How long the builtin daemon may take to answer.
+#[cfg(unix)]
+const DAEMON_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a hook may take to answer before it's killed, and the worktree is scanned instead.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The files that changed according to the filesystem monitor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Changes {
+    /// The token to query the changes since this query with.
+    pub token: String,
+    /// The paths that changed relative to the worktree, with directories standing for everything below them,
+    /// or `None` if anything may have changed, as the monitor was restarted or can't tell.
+    pub paths: Option<Vec<PathBuf>>,
+}
+
+/// Return the changes to the worktree of `repo` since the query that returned `since`, or `None` if no
+/// filesystem monitor is configured or it couldn't be queried.
+pub(crate) fn query(repo: &git2::Repository, since: Option<&str>) -> Option<Changes> {
+    let config = repo.config().ok()?;
+    let result = match config.get_bool("core.fsmonitor") {
+        Ok(true) => query_daemon(repo, since.unwrap_or(INITIAL_TOKEN)),
+        Ok(false) => return None,
+        Err(_) => {
+            let hook = config.get_string("core.fsmonitor").ok()?;
+            let version = config.get_i32("core.fsmonitorhookversion").unwrap_or(2);
+            query_hook(repo, &hook, version, since)
+        }
+    };
+    match result {
+        Ok(changes) => Some(changes),
+        Err(err) => {
+            tracing::debug!(?err, "failed to query the filesystem monitor");
+            None
+        }
+    }
+}
+
+/// Run `hook` with the protocol `version` like Git does, and return what it reports.
+///
+/// Version 2 is given the token of the previous query and prints a new token before the changed paths,
+/// while version 1 is given the time of the previous query in nanoseconds, which serves as token then.
+fn query_hook(
+    repo: &git2::Repository,
+    hook: &str,
+    version: i32,
+    since: Option<&str>,
+) -> Result<Changes> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("the filesystem monitor needs a worktree"))?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string();
+    let since = match version {
+        1 => since.unwrap_or("0").to_owned(),
+        2 => since.unwrap_or(INITIAL_TOKEN).to_owned(),
+        _ => bail!("version {version} of the fsmonitor hook protocol isn't supported"),
+    };
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(format!("{hook} \"$@\""))
+        .arg(hook)
+        .arg(version.to_string())
+        .arg(&since)
+        .current_dir(workdir);
+    ProcessEnv::new()
+        .extend(extra_env::of_repository(repo))
+        .apply(&mut cmd);
+    let output = filter::pipe(cmd, &[], HOOK_TIMEOUT)?;
+    if !output.status.success() {
+        bail!(
+            "the fsmonitor hook failed: {}",
+            output.stderr.to_str_lossy().trim()
+        );
+    }
+    if version == 1 {
+        let mut changes = parse_paths(now, output.stdout.split_str("\0"));
+        // Without a token, the hook can't tell if it lost track of changes.
+        if since == "0" {
+            changes.paths = None;
+        }
+        Ok(changes)
+    } else {
+        parse_response(&output.stdout)
+    }
+}
+
+/// Ask the builtin daemon of Git for the changes since `since` over its socket in the `.git` directory,
+/// which takes the token as request and answers like a hook of version 2 does, both in pkt-lines.
+#[cfg(unix)]
+fn query_daemon(repo: &git2::Repository, since: &str) -> Result<Changes> {
+    use std::{
+        io::{Read, Write},
+        os::unix::net::UnixStream,
+    };
+
+    let mut socket = UnixStream::connect(repo.path().join("fsmonitor--daemon.ipc"))?;
+    socket.set_read_timeout(Some(DAEMON_TIMEOUT))?;
+    socket.set_write_timeout(Some(DAEMON_TIMEOUT))?;
+    socket.write_all(format!("{:04x}{since}0000", since.len() + 4).as_bytes())?;
+
+    let mut response = Vec::new();
+    loop {
+        let mut header = [0; 4];
+        socket.read_exact(&mut header)?;
+        let len = usize::from_str_radix(header.to_str()?, 16)?;
+        if len == 0 {
+            break;
+        }
+        let mut data = vec![0; len.saturating_sub(4)];
+        socket.read_exact(&mut data)?;
+        response.extend(data);
+    }
+    parse_response(&response)
+}
+
+#[cfg(not(unix))]
+fn query_daemon(_repo: &git2::Repository, _since: &str) -> Result<Changes> {
+    bail!("the builtin filesystem monitor is only supported on Unix")
+}
+
+/// Parse `response`, a token followed by the changed paths, each terminated by a NUL byte.
+fn parse_response(response: &[u8]) -> Result<Changes> {
+    let mut fields = response.split_str("\0");
+    let token = fields
+        .next()
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| anyhow!("the filesystem monitor didn't send a token"))?;
+    Ok(parse_paths(token.to_str()?.to_owned(), fields))
+}
+
+/// Return the changes with `token` and the changed `paths`, where `/` means that anything may have changed.
+fn parse_paths<'a>(token: String, paths: impl Iterator<Item = &'a [u8]>) -> Changes {
+    let mut changed = Vec::new();
+    for path in paths.filter(|path| !path.is_empty()) {
+        if path == b"/" {
+            return Changes { token, paths: None };
+        }
+        changed.push(
+            path.trim_end_with(|c| c == '/')
+                .to_path_lossy()
+                .into_owned(),
+        );
+    }
+    Changes {
+        token,
+        paths: Some(changed),
+    }
+}
@@ -0,0 +1,203 @@
+use std::ops::Range;
+
+use bstr::{BStr, ByteSlice};
+use serde::Serialize;
+
+/// The most token comparisons made to find the changes between two lines. Lines that would need more
+/// are highlighted in full.
+const MAX_COMPARISONS: usize = 250_000;
+
+/// The parts of a changed line that differ from the line it replaced, or that replaced it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineHighlight {
+    /// The line within the hunk, numbered from 1 and counted from the first line after the hunk header.
+    pub line: u32,
+    /// The changed byte ranges of the line, not counting its `+` or `-` prefix.
+    pub ranges: Vec<Range<usize>>,
+}
+
+/// Compute the word-level changes within `diff_lines`, the `+`, `-` or ` ` prefixed lines of a hunk
+/// along with its header.
+///
+/// Each run of removed lines that is directly followed by added lines is paired up line by line,
+/// and only paired lines are highlighted, as all other changed lines changed in full.
+/// If `ignore_whitespace` is `true`, changes that only affect whitespace aren't highlighted.
+pub fn intra_line_highlights(diff_lines: &BStr, ignore_whitespace: bool) -> Vec<LineHighlight> {
+    let mut lines = diff_lines.lines().peekable();
+    if lines.peek().map_or(false, |line| line.starts_with(b"@@")) {
+        lines.next();
+    }
+
+    let mut highlights = Vec::new();
+    let mut removed: Vec<(u32, &[u8])> = Vec::new();
+    let mut added: Vec<(u32, &[u8])> = Vec::new();
+    for (line, number) in lines.chain(Some(b"".as_slice())).zip(1..) {
+        match line.first() {
+            Some(b'-') if added.is_empty() => removed.push((number, &line[1..])),
+            Some(b'+') if !removed.is_empty() => added.push((number, &line[1..])),
+            // Markers like `\ No newline at end of file` belong to the line before them.
+            Some(b'\\') => {}
+            _ => {
+                for (&(old_number, old), &(new_number, new)) in removed.iter().zip(&added) {
+                    let (old_ranges, new_ranges) = changed_ranges(old, new, ignore_whitespace);
+                    highlights.extend(
+                        [(old_number, old_ranges), (new_number, new_ranges)]
+                            .into_iter()
+                            .filter(|(_, ranges)| !ranges.is_empty())
+                            .map(|(line, ranges)| LineHighlight { line, ranges }),
+                    );
+                }
+                removed.clear();
+                added.clear();
+                if line.first() == Some(&b'-') {
+                    removed.push((number, &line[1..]));
+                }
+            }
+        }
+    }
+    highlights.sort_by_key(|highlight| highlight.line);
+    highlights
+}
+
+/// Return the byte ranges of `old` and `new` that aren't part of the longest common sequence of their words.
+fn changed_ranges(
+    old: &[u8],
+    new: &[u8],
+    ignore_whitespace: bool,
+) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let old_tokens = tokenize(old);
+    let new_tokens = tokenize(new);
+    let same = |a: &Range<usize>, b: &Range<usize>| {
+        let (a, b) = (&old[a.clone()], &new[b.clone()]);
+        a == b || (ignore_whitespace && is_whitespace(a) && is_whitespace(b))
+    };
+
+    let prefix = old_tokens
+        .iter()
+        .zip(&new_tokens)
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let suffix = old_tokens[prefix..]
+        .iter()
+        .rev()
+        .zip(new_tokens[prefix..].iter().rev())
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let old_middle = &old_tokens[prefix..old_tokens.len() - suffix];
+    let new_middle = &new_tokens[prefix..new_tokens.len() - suffix];
+
+    let (old_changed, new_changed) =
+        if old_middle.len().saturating_mul(new_middle.len()) > MAX_COMPARISONS {
+            (vec![true; old_middle.len()], vec![true; new_middle.len()])
+        } else {
+            unmatched_tokens(old_middle, new_middle, same)
+        };
+    let changed_tokens = |text: &[u8], tokens: &[Range<usize>], changed: Vec<bool>| {
+        tokens
+            .iter()
+            .zip(changed)
+            .filter(|(token, changed)| {
+                *changed && !(ignore_whitespace && is_whitespace(&text[(*token).clone()]))
+            })
+            .map(|(token, _)| token.clone())
+            .collect::<Vec<_>>()
+    };
+    (
+        merge_adjacent(changed_tokens(old, old_middle, old_changed)),
+        merge_adjacent(changed_tokens(new, new_middle, new_changed)),
+    )
+}
+
+/// Return which tokens of `old` and `new` aren't part of their longest common subsequence according to `same`.
+fn unmatched_tokens(
+    old: &[Range<usize>],
+    new: &[Range<usize>],
+    same: impl Fn(&Range<usize>, &Range<usize>) -> bool,
+) -> (Vec<bool>, Vec<bool>) {
+    let columns = new.len() + 1;
+    let mut lengths = vec![0usize; (old.len() + 1) * columns];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * columns + j] = if same(&old[i], &new[j]) {
+                lengths[(i + 1) * columns + j + 1] + 1
+            } else {
+                lengths[(i + 1) * columns + j].max(lengths[i * columns + j + 1])
+            };
+        }
+    }
+
+    let mut old_changed = vec![true; old.len()];
+    let mut new_changed = vec![true; new.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if same(&old[i], &new[j]) {
+            old_changed[i] = false;
+            new_changed[j] = false;
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * columns + j] >= lengths[i * columns + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    (old_changed, new_changed)
+}
+
+/// Split `line` into words, runs of whitespace and single punctuation characters, ignoring its line terminator.
+fn tokenize(line: &[u8]) -> Vec<Range<usize>> {
+    let line = line.trim_end_with(|c| c == '\n' || c == '\r');
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    while start < line.len() {
+        let class = TokenClass::of(line[start]);
+        let end = if class == TokenClass::Punctuation {
+            start + 1
+        } else {
+            line[start..]
+                .iter()
+                .position(|&byte| TokenClass::of(byte) != class)
+                .map_or(line.len(), |len| start + len)
+        };
+        tokens.push(start..end);
+        start = end;
+    }
+    tokens
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenClass {
+    Word,
+    Whitespace,
+    Punctuation,
+}
+
+impl TokenClass {
+    fn of(byte: u8) -> Self {
+        // Bytes of multi-byte UTF-8 characters are treated as part of words, so they are never split.
+        if byte.is_ascii_alphanumeric() || byte == b'_' || !byte.is_ascii() {
+            TokenClass::Word
+        } else if byte.is_ascii_whitespace() {
+            TokenClass::Whitespace
+        } else {
+            TokenClass::Punctuation
+        }
+    }
+}
+
+fn is_whitespace(text: &[u8]) -> bool {
+    text.iter().all(u8::is_ascii_whitespace)
+}
+
+/// Join ranges that directly follow each other.
+fn merge_adjacent(ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
@@ -0,0 +1,139 @@
+//! Support for Git LFS, which commits small pointer files in place of the content of large files.
+//!
+//! libgit2 doesn't know the `lfs` filter, so content is cleaned into pointers and pointers are smudged
+//! into content by running `git lfs`, which has to be installed.
+use std::{path::Path, process::Command, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_command_context::extra_env;
+use gitbutler_git::ProcessEnv;
+use serde::Serialize;
+
+use crate::{diff::DiffByPathMap, filter};
+
+/// The first line of every pointer file.
+const POINTER_VERSION_LINE: &[u8] = b"version https://git-lfs.github.com/spec/v1";
+
+/// How long `git lfs` may take to filter a single file, which includes downloading it when smudging.
+const FILTER_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Pointer files are never larger than this, which allows skipping larger blobs without reading them.
+pub const MAX_POINTER_SIZE: usize = 1024;
+
+/// The content of an LFS pointer file, which stands for the actual content of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pointer {
+    /// The hash of the actual content, like `sha256:4d7a…`.
+    pub oid: String,
+    /// The size of the actual content in bytes.
+    pub size: u64,
+}
+
+impl Pointer {
+    /// Parse `data` as pointer file, or return `None` if it isn't one.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() > MAX_POINTER_SIZE {
+            return None;
+        }
+        let mut lines = data.lines();
+        if lines.next()? != POINTER_VERSION_LINE {
+            return None;
+        }
+        let (mut oid, mut size) = (None, None);
+        for line in lines {
+            let (key, value) = line.to_str().ok()?.split_once(' ')?;
+            match key {
+                "oid" => oid = Some(value.to_owned()),
+                "size" => size = Some(value.parse().ok()?),
+                _ => {}
+            }
+        }
+        Some(Pointer {
+            oid: oid.filter(|oid| oid.starts_with("sha256:"))?,
+            size: size?,
+        })
+    }
+}
+
+/// Return `true` if the file at `path`, relative to the worktree, is stored with LFS as told by `.gitattributes`.
+pub fn is_tracked(repo: &git2::Repository, path: &Path) -> bool {
+    repo.get_attr(path, "filter", git2::AttrCheckFlags::FILE_THEN_INDEX)
+        .ok()
+        .flatten()
+        == Some("lfs")
+}
+
+/// Return `true` if the `.gitattributes` file at the root of the worktree stores any files with LFS.
+///
+/// This is a cheap check to avoid looking at every file of repositories that don't use LFS.
+pub fn is_used(repo: &git2::Repository) -> bool {
+    repo.workdir()
+        .and_then(|workdir| std::fs::read(workdir.join(".gitattributes")).ok())
+        .map_or(false, |attributes| attributes.contains_str("filter=lfs"))
+}
+
+/// Turn the `content` of the file at `path` into a pointer file, storing the content in the LFS cache.
+pub fn clean(repo: &git2::Repository, path: &Path, content: &[u8]) -> Result<Vec<u8>> {
+    run_filter(repo, "clean", path, content)
+}
+
+/// Turn the `pointer` file at `path` into its actual content, downloading it if it isn't cached.
+pub fn smudge(repo: &git2::Repository, path: &Path, pointer: &[u8]) -> Result<Vec<u8>> {
+    run_filter(repo, "smudge", path, pointer)
+}
+
+/// Run `git lfs <filter> -- <path>` in the worktree of `repo` with `input` on stdin, and return its stdout.
+///
+/// It runs with the extra environment of the project, as smudging may download content.
+fn run_filter(repo: &git2::Repository, filter: &str, path: &Path, input: &[u8]) -> Result<Vec<u8>> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("LFS needs a repository with a worktree"))?;
+    let mut cmd = Command::new("git");
+    cmd.args(["lfs", filter, "--"])
+        .arg(path)
+        .current_dir(workdir);
+    ProcessEnv::new()
+        .extend(extra_env::of_repository(repo))
+        .apply(&mut cmd);
+    let output = filter::pipe(cmd, input, FILTER_TIMEOUT)
+        .context("failed to run git lfs, is it installed?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git lfs {filter} failed for {}: {}",
+            path.display(),
+            output.stderr.to_str_lossy().trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Set the pointers of all files in `files` that are pointer files before or after the changes of `diff`.
+pub(crate) fn describe_pointers(
+    repo: &git2::Repository,
+    diff: &git2::Diff<'_>,
+    files: &mut DiffByPathMap,
+) {
+    let Ok(odb) = repo.odb() else {
+        return;
+    };
+    let pointer = |file: git2::DiffFile<'_>| {
+        let id = file.id();
+        if id.is_zero() || odb.read_header(id).ok()?.0 > MAX_POINTER_SIZE {
+            return None;
+        }
+        Pointer::parse(odb.read(id).ok()?.data())
+    };
+    for delta in diff.deltas() {
+        let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) else {
+            continue;
+        };
+        let Some(file) = files.get_mut(path) else {
+            continue;
+        };
+        file.old_lfs_pointer = pointer(delta.old_file());
+        file.new_lfs_pointer = pointer(delta.new_file());
+    }
+}
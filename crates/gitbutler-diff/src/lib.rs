@@ -1,8 +1,39 @@
+mod binary;
+mod cache;
 mod diff;
+pub mod filter;
+mod fsmonitor;
+mod highlight;
 mod hunk;
+pub mod lfs;
+mod memory;
+mod nested;
+mod oid_cache;
+mod parallel;
+mod path_normalization;
+mod rename;
+mod selection;
+mod semantic;
+mod spill;
+mod submodule;
+#[cfg(feature = "semantic-hunks")]
+mod syntax;
 pub mod write;
+pub use binary::{image_dimensions, mime_guess, ImageDimensions};
+pub use cache::{RediffReason, WorkdirCache};
 pub use diff::{
-    diff_files_into_hunks, hunks_by_filepath, reverse_hunk, trees, workdir, ChangeType, FileDiff,
-    GitHunk,
+    diff_files_into_hunks, hunks_by_filepath, reverse_hunk, trees, trees_with_options, workdir,
+    workdir_in_scope, workdir_with_options, ChangeType, DiffByPathMap, DiffGranularity,
+    DiffOptions, FileDiff, GitHunk,
 };
+pub use highlight::{intra_line_highlights, LineHighlight};
 pub use hunk::{Hunk, HunkHash};
+pub use memory::{is_low_memory, memory_limits, set_low_memory, MemoryLimits};
+pub use nested::{is_nested_repository, nested_repositories};
+pub use path_normalization::PathNormalization;
+pub use rename::{renames, similarity, PathChange, DEFAULT_RENAME_THRESHOLD};
+pub use selection::{HunkSelection, RangeSet};
+pub use submodule::{submodule_changes, SubmoduleChange};
+
+/// The bits of [`git2::IndexEntry::flags`] that store the merge stage.
+pub const STAGE_MASK: u16 = 0x3000;
@@ -0,0 +1,59 @@
+//! A global mode that trades speed for memory, to stay usable on machines with little of it that work on
+//! large repositories.
+//!
+//! In low-memory mode, caches are capped or disabled, and the text of large cached diffs is kept in
+//! temporary files instead of memory while it isn't used.
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static LOW_MEMORY: AtomicBool = AtomicBool::new(false);
+
+/// How much memory caches may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLimits {
+    /// The most bytes each repository opened with `gix` may cache objects with.
+    pub object_cache_bytes: usize,
+    /// The size of the text of a cached worktree diff in bytes above which it's kept in a temporary file
+    /// while it isn't used, or `None` to always keep it in memory.
+    pub spill_diffs_above_bytes: Option<usize>,
+    /// Whether the status of a watched worktree is kept, so queries in between changes can share it.
+    pub cache_status: bool,
+    /// The most entries other caches may have per project, like the ahead and behind counts of remote branches.
+    pub max_cache_entries: usize,
+}
+
+impl MemoryLimits {
+    /// The limits unless [low-memory mode](set_low_memory()) is on.
+    pub const DEFAULT: MemoryLimits = MemoryLimits {
+        object_cache_bytes: 1024 * 1024,
+        spill_diffs_above_bytes: None,
+        cache_status: true,
+        max_cache_entries: usize::MAX,
+    };
+
+    /// The limits in [low-memory mode](set_low_memory()).
+    pub const LOW: MemoryLimits = MemoryLimits {
+        object_cache_bytes: 64 * 1024,
+        spill_diffs_above_bytes: Some(4 * 1024 * 1024),
+        cache_status: false,
+        max_cache_entries: 1000,
+    };
+}
+
+/// Turn low-memory mode on or off for the whole process. Caches adhere to it the next time they are used.
+pub fn set_low_memory(enabled: bool) {
+    LOW_MEMORY.store(enabled, Ordering::Relaxed);
+}
+
+/// Return `true` if [low-memory mode](set_low_memory()) is on.
+pub fn is_low_memory() -> bool {
+    LOW_MEMORY.load(Ordering::Relaxed)
+}
+
+/// Return the limits of caches in the current mode.
+pub fn memory_limits() -> MemoryLimits {
+    if is_low_memory() {
+        MemoryLimits::LOW
+    } else {
+        MemoryLimits::DEFAULT
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Return `true` if the directory at `path`, relative to the worktree, is the worktree of another
+/// Git repository that isn't a submodule, like a vendored clone.
+///
+/// Its files belong to that repository, so they are left out of [`workdir()`](crate::workdir()).
+pub fn is_nested_repository(repo: &git2::Repository, path: &Path) -> bool {
+    let Some(workdir) = repo.workdir() else {
+        return false;
+    };
+    let path = path.to_string_lossy();
+    let path = path.trim_end_matches('/');
+    !path.is_empty()
+        && workdir.join(path).join(".git").exists()
+        && repo.find_submodule(path).is_err()
+}
+
+/// Return the paths of all [nested repositories](is_nested_repository()) that aren't ignored, relative
+/// to the worktree and ordered by path.
+///
+/// These are either untracked, or committed as bare commit pointer without being a submodule.
+pub fn nested_repositories(repo: &git2::Repository) -> Result<Vec<PathBuf>> {
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .exclude_submodules(true);
+    let statuses = repo
+        .statuses(Some(&mut opts))
+        .context("failed to get the status of the worktree")?;
+    let untracked = statuses
+        .iter()
+        .filter(|entry| entry.status().contains(git2::Status::WT_NEW))
+        .filter_map(|entry| {
+            entry
+                .path()
+                .map(|path| PathBuf::from(path.trim_end_matches('/')))
+        });
+    let index = repo.index().context("failed to read the index")?;
+    let gitlinks = index
+        .iter()
+        .filter(|entry| entry.mode == 0o160000)
+        .map(|entry| PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned()));
+
+    let mut nested: Vec<_> = untracked
+        .chain(gitlinks)
+        .filter(|path| is_nested_repository(repo, path))
+        .collect();
+    nested.sort();
+    nested.dedup();
+    Ok(nested)
+}
@@ -0,0 +1,351 @@
+//! Keep the ids of the files of the worktree that differ from the index in the `.git` directory, along with
+//! their stats, so diffing all of the worktree only has to hash the files that changed since it was last
+//! diffed, even after a restart. The index already does that for all other files.
+//!
+//! Which files to look at is told by the filesystem monitor if `core.fsmonitor` is configured: only the files
+//! it reports as changed and those that differed from the index before are looked at, so the worktree
+//! isn't scanned at all. Without one, or if it lost track of changes, the worktree is scanned for files that
+//! differ from the index like `git status` does, and of these only the ones whose stat changed since they
+//! were cached are hashed.
+//!
+//! Nothing that is cached is trusted once the index changed, as that can make any file differ from it, or
+//! once a `.gitignore` or `.gitattributes` file changed, as they affect which files are seen and what they
+//! are hashed as.
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use bstr::{ByteSlice, ByteVec};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    diff::{add_worktree_files, worktree_tree_in_parallel, FileDiff},
+    fsmonitor, DiffOptions,
+};
+
+/// The file in the `gitbutler` directory of the `.git` directory the cache is kept in.
+const CACHE_FILE: &str = "worktree-oids.json";
+
+/// The version of the format of [`CACHE_FILE`]. Caches of other versions are discarded.
+const VERSION: u32 = 1;
+
+/// Files modified less than this many nanoseconds before they were looked at may have been modified again
+/// without changing their stat, as not all filesystems record modification times precisely.
+const RACY_NANOS: u64 = 1_000_000_000;
+
+/// If more files than this have to be hashed, they are added by scanning the worktree, as that's faster than
+/// matching each of the files against that many paths.
+const MAX_ADDED_PATHS: usize = 1_000;
+
+/// Files whose change affects how all other files are seen and hashed.
+const GLOBAL_FILES: &[&str] = &[".gitignore", ".gitattributes"];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OidCache {
+    version: u32,
+    /// The stat of the index when the files were looked at.
+    index: Option<Stat>,
+    /// When the files were looked at, in nanoseconds since the Unix epoch.
+    scanned_at_ns: u64,
+    /// The token to query the filesystem monitor for the changes since the files were looked at with.
+    fsmonitor_token: Option<String>,
+    /// The size above which files were skipped instead of being hashed.
+    max_file_size_bytes: u64,
+    /// The files that differ from the index, by their path relative to the worktree.
+    files: BTreeMap<PathBuf, CachedFile>,
+    /// The other paths that differ from the index, like deleted files and files that were too large to be
+    /// hashed, which are looked at each time.
+    other_paths: BTreeSet<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedFile {
+    #[serde(with = "gitbutler_serde::oid")]
+    id: git2::Oid,
+    mode: u32,
+    stat: Stat,
+}
+
+/// What tells if a file changed without reading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Stat {
+    size: u64,
+    modified_ns: u64,
+    /// The inode and the time of the last change of the inode, which are only known on Unix and `0` elsewhere.
+    inode: u64,
+    changed_ns: u64,
+}
+
+impl Stat {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::symlink_metadata(path).ok()?;
+        let modified_ns = metadata.modified().map(nanos_since_epoch).unwrap_or(0);
+        #[cfg(unix)]
+        let (inode, changed_ns) = {
+            use std::os::unix::fs::MetadataExt;
+            let changed_ns = u64::try_from(metadata.ctime())
+                .unwrap_or(0)
+                .saturating_mul(1_000_000_000)
+                .saturating_add(u64::try_from(metadata.ctime_nsec()).unwrap_or(0));
+            (metadata.ino(), changed_ns)
+        };
+        #[cfg(not(unix))]
+        let (inode, changed_ns) = (0, 0);
+        Some(Stat {
+            size: metadata.len(),
+            modified_ns,
+            inode,
+            changed_ns,
+        })
+    }
+}
+
+impl OidCache {
+    fn path(repo: &git2::Repository) -> PathBuf {
+        repo.path().join("gitbutler").join(CACHE_FILE)
+    }
+
+    /// Read the cache of `repo`, or return an empty one if there is none that can be used.
+    fn load(repo: &git2::Repository) -> Self {
+        std::fs::read(Self::path(repo))
+            .ok()
+            .and_then(|data| serde_json::from_slice::<OidCache>(&data).ok())
+            .filter(|cache| cache.version == VERSION)
+            .unwrap_or_default()
+    }
+
+    fn save(&self, repo: &git2::Repository) -> Result<()> {
+        let path = Self::path(repo);
+        let dir = path.parent().expect("the cache is in a directory");
+        std::fs::create_dir_all(dir)?;
+        // Readers only ever see a complete cache.
+        let mut file = tempfile::NamedTempFile::new_in(dir)?;
+        file.write_all(&serde_json::to_vec(self)?)?;
+        file.persist(&path)?;
+        Ok(())
+    }
+
+    /// Return the cached file at `path`, which is `full_path` in the worktree, if its stat didn't change and
+    /// it wasn't modified too shortly before it was looked at to be sure its content didn't change since.
+    fn unchanged(&self, full_path: &Path, path: &Path) -> Option<&CachedFile> {
+        let file = self.files.get(path)?;
+        let stat = Stat::of(full_path)?;
+        (file.stat == stat && stat.modified_ns.saturating_add(RACY_NANOS) <= self.scanned_at_ns)
+            .then_some(file)
+    }
+}
+
+/// Write the tree of the worktree on top of the index like [`worktree_tree()`](crate::diff::worktree_tree()),
+/// but look at and hash as few files as possible with the help of the cache and the filesystem monitor, and
+/// update the cache afterwards.
+pub(crate) fn worktree_tree(
+    repo: &git2::Repository,
+    old_tree: &git2::Tree,
+    options: &DiffOptions,
+) -> Result<(git2::Oid, HashMap<PathBuf, FileDiff>)> {
+    let workdir = repo.workdir().context("a worktree is needed to diff it")?;
+    let scanned_at_ns = nanos_since_epoch(SystemTime::now());
+    let index_stat = Stat::of(&repo.path().join("index"));
+    let mut cache = OidCache::load(repo);
+    // Asked first so the files changed while looking at them are reported next time.
+    let changes = fsmonitor::query(repo, cache.fsmonitor_token.as_deref());
+    if cache.index != index_stat || cache.max_file_size_bytes != options.max_file_size_bytes {
+        cache = OidCache::default();
+    }
+    // The index of the repository is also the one the worktree is added to, so it may still have the
+    // files of the last diff.
+    let mut index = repo.index()?;
+    index.read(true)?;
+
+    let monitored_paths = changes
+        .as_ref()
+        .and_then(|changes| changes.paths.as_ref())
+        .filter(|paths| {
+            cache.index.is_some()
+                && !index.has_conflicts()
+                && !paths.iter().any(|path| is_global_file(path))
+        });
+    let candidates: Vec<PathBuf> = match monitored_paths {
+        Some(paths) => {
+            let mut candidates: BTreeSet<PathBuf> = cache
+                .files
+                .keys()
+                .chain(&cache.other_paths)
+                .cloned()
+                .collect();
+            candidates.extend(
+                paths
+                    .iter()
+                    .filter(|path| {
+                        !path.starts_with(".git")
+                            && (index.get_path(path, 0).is_some()
+                                || !repo.is_path_ignored(path).unwrap_or(false))
+                    })
+                    .cloned(),
+            );
+            candidates.into_iter().collect()
+        }
+        None => {
+            let candidates = paths_differing_from_index(repo)?;
+            let global_file_changed = candidates.iter().any(|path| {
+                is_global_file(path) && cache.unchanged(&workdir.join(path), path).is_none()
+            });
+            if global_file_changed {
+                cache.files.clear();
+            }
+            candidates
+        }
+    };
+
+    let mut added_paths = Vec::new();
+    for path in &candidates {
+        match cache.unchanged(&workdir.join(path), path) {
+            Some(file) => index.add(&index_entry(path, file))?,
+            None => added_paths.push(path.clone()),
+        }
+    }
+    let (tree_id, skipped_files) = if added_paths.len() > MAX_ADDED_PATHS {
+        tracing::debug!(
+            added_paths = added_paths.len(),
+            "too many changed files to use the cache"
+        );
+        if options.threads > 1 {
+            worktree_tree_in_parallel(repo, old_tree, options)?
+        } else {
+            crate::diff::worktree_tree(repo, old_tree, options, None)?
+        }
+    } else {
+        let skipped_files = if added_paths.is_empty() {
+            HashMap::new()
+        } else {
+            add_worktree_files(repo, &mut index, old_tree, options, Some(&added_paths))?
+        };
+        (index.write_tree()?, skipped_files)
+    };
+
+    let updated = OidCache {
+        version: VERSION,
+        index: index_stat,
+        scanned_at_ns,
+        fsmonitor_token: changes.map(|changes| changes.token),
+        max_file_size_bytes: options.max_file_size_bytes,
+        ..Default::default()
+    };
+    match updated.with_files_of(repo, tree_id, &skipped_files) {
+        Ok(updated) => {
+            if let Err(err) = updated.save(repo) {
+                tracing::warn!(?err, "failed to save the ids of the changed files");
+            }
+        }
+        Err(err) => tracing::warn!(?err, "failed to collect the ids of the changed files"),
+    }
+    Ok((tree_id, skipped_files))
+}
+
+impl OidCache {
+    /// Return this cache with the files of the tree `tree_id` of the worktree that differ from the index,
+    /// along with the files that were skipped, as `skipped_files`.
+    fn with_files_of(
+        mut self,
+        repo: &git2::Repository,
+        tree_id: git2::Oid,
+        skipped_files: &HashMap<PathBuf, FileDiff>,
+    ) -> Result<Self> {
+        let workdir = repo.workdir().context("a worktree is needed to diff it")?;
+        let tree = repo.find_tree(tree_id)?;
+        let mut diff_opts = git2::DiffOptions::new();
+        diff_opts.ignore_submodules(true);
+        let index = git2::Index::open(&repo.path().join("index"))?;
+        let diff = repo.diff_tree_to_index(Some(&tree), Some(&index), Some(&mut diff_opts))?;
+        for delta in diff.deltas() {
+            // The tree of the worktree is the old side.
+            let file = delta.old_file();
+            let Some(path) = file.path() else {
+                continue;
+            };
+            let stat = if file.exists() {
+                Stat::of(&workdir.join(path))
+            } else {
+                None
+            };
+            match stat {
+                Some(stat) => {
+                    self.files.insert(
+                        path.to_owned(),
+                        CachedFile {
+                            id: file.id(),
+                            mode: u32::from(file.mode()),
+                            stat,
+                        },
+                    );
+                }
+                None => {
+                    self.other_paths.insert(path.to_owned());
+                }
+            }
+        }
+        self.other_paths.extend(skipped_files.keys().cloned());
+        Ok(self)
+    }
+}
+
+/// Return the paths relative to the worktree of the files whose content may differ from the index.
+fn paths_differing_from_index(repo: &git2::Repository) -> Result<Vec<PathBuf>> {
+    let mut status_opts = git2::StatusOptions::new();
+    status_opts
+        .show(git2::StatusShow::Workdir)
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .exclude_submodules(true);
+    let statuses = repo
+        .statuses(Some(&mut status_opts))
+        .context("failed to find the files that differ from the index")?;
+    Ok(statuses
+        .iter()
+        .map(|entry| {
+            entry
+                .path_bytes()
+                .trim_end_with(|c| c == '/')
+                .to_path_lossy()
+                .into_owned()
+        })
+        .collect())
+}
+
+/// Return the entry for the cached `file` at `path` to put into the index of the worktree, which is never
+/// written, so only what's needed to write its tree is set.
+fn index_entry(path: &Path, file: &CachedFile) -> git2::IndexEntry {
+    git2::IndexEntry {
+        ctime: git2::IndexTime::new(0, 0),
+        mtime: git2::IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: file.mode,
+        uid: 0,
+        gid: 0,
+        file_size: u32::try_from(file.stat.size).unwrap_or(u32::MAX),
+        id: file.id,
+        flags: 0,
+        flags_extended: 0,
+        path: Vec::from_path_lossy(path).into_owned(),
+    }
+}
+
+fn is_global_file(path: &Path) -> bool {
+    path.file_name().map_or(false, |name| {
+        GLOBAL_FILES.iter().any(|global| name == *global)
+    })
+}
+
+fn nanos_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |duration| {
+        u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+    })
+}
@@ -0,0 +1,69 @@
+//! Run work that needs a repository on several threads.
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use anyhow::{anyhow, Result};
+use gitbutler_command_context::cancellation;
+
+/// Call `f` with each of `items` and return the results in order, using up to `threads` threads that
+/// each get at least `min_items_per_thread` items.
+///
+/// Repositories can't be shared across threads, so each thread opens its own instance of `repo`,
+/// which only works for repositories that are stored on disk. If only one thread would be used,
+/// everything happens on the calling thread with `repo` itself. All threads run with the
+/// [current cancellation token](cancellation::current()) of the calling thread.
+pub(crate) fn map_with_repo<T, R>(
+    repo: &git2::Repository,
+    items: &[T],
+    threads: usize,
+    min_items_per_thread: usize,
+    f: impl Fn(&git2::Repository, &T) -> Result<R> + Sync,
+) -> Result<Vec<R>>
+where
+    T: Sync,
+    R: Send,
+{
+    let threads = threads.min(items.len() / min_items_per_thread.max(1));
+    if threads <= 1 {
+        return items.iter().map(|item| f(repo, item)).collect();
+    }
+
+    let git_dir = repo.path();
+    let next = AtomicUsize::new(0);
+    let cancellation = cancellation::current();
+    let results: Mutex<Vec<Option<Result<R>>>> = Mutex::new(items.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                let repo = git2::Repository::open(git_dir);
+                loop {
+                    let idx = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(item) = items.get(idx) else {
+                        break;
+                    };
+                    let result = match &repo {
+                        Ok(repo) => cancellation::scoped(&cancellation, || f(repo, item)),
+                        Err(err) => Err(anyhow!("failed to open repository: {err}")),
+                    };
+                    let failed = result.is_err();
+                    results.lock().expect("no panics while holding the lock")[idx] = Some(result);
+                    if failed {
+                        // The result is an error anyway, so leave the remaining items alone.
+                        next.store(items.len(), Ordering::SeqCst);
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    // Items are only left out after an error, which is returned instead.
+    results
+        .into_inner()
+        .expect("no panics while holding the lock")
+        .into_iter()
+        .flatten()
+        .collect()
+}
@@ -0,0 +1,186 @@
+//! Compare paths like the filesystem of the worktree does, which on macOS and Windows doesn't tell apart
+//! paths that only differ in case, and on macOS decomposes unicode characters that Git stores composed.
+//!
+//! Without this, renaming `Readme.md` to `README.md` on such a filesystem, or a path that the filesystem
+//! returns decomposed, shows up as a file that was deleted while another one was added, and the ownership
+//! claims of the branches split across both spellings.
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::{
+    diff::DiffByPathMap,
+    rename::{blob_similarity, PathChange},
+    write::file_mode,
+};
+
+/// How the filesystem of a worktree tells paths apart.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PathNormalization {
+    /// Paths that only differ in case are the same path.
+    pub ignore_case: bool,
+    /// Paths that only differ in the unicode normalization of their characters are the same path.
+    pub precompose_unicode: bool,
+}
+
+impl PathNormalization {
+    /// Return how the filesystem of the worktree of `repo` tells paths apart, as told by `core.ignoreCase` and
+    /// `core.precomposeUnicode`, which Git sets when it creates a repository on such a filesystem.
+    pub fn from_repo(repo: &git2::Repository) -> Self {
+        let Ok(config) = repo.config() else {
+            return Self::default();
+        };
+        PathNormalization {
+            ignore_case: config.get_bool("core.ignorecase").unwrap_or(false),
+            precompose_unicode: config
+                .get_bool("core.precomposeunicode")
+                .unwrap_or(cfg!(target_os = "macos")),
+        }
+    }
+
+    /// Return `true` if any two different paths can be the same path.
+    pub fn is_enabled(&self) -> bool {
+        self.ignore_case || self.precompose_unicode
+    }
+
+    /// Return the form of `path` that all of its spellings have in common. Paths that aren't valid UTF-8 are
+    /// returned as they are.
+    pub fn key<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        let Some(spelling) = path.to_str() else {
+            return Cow::Borrowed(path);
+        };
+        let mut key = Cow::Borrowed(spelling);
+        if self.precompose_unicode && !is_nfc(&key) {
+            key = Cow::Owned(key.nfc().collect());
+        }
+        if self.ignore_case && key.chars().any(char::is_uppercase) {
+            key = Cow::Owned(key.to_lowercase());
+        }
+        match key {
+            Cow::Borrowed(_) => Cow::Borrowed(path),
+            Cow::Owned(key) => Cow::Owned(PathBuf::from(key)),
+        }
+    }
+
+    /// Return `true` if `a` and `b` are spellings of the same path.
+    pub fn same(&self, a: &Path, b: &Path) -> bool {
+        a == b || self.is_enabled() && self.key(a) == self.key(b)
+    }
+
+    /// Return how `tree` spells `path` if it has it with another spelling, or `None` if it spells it the same
+    /// way or doesn't have it.
+    pub fn spelling_in(
+        &self,
+        repo: &git2::Repository,
+        tree: &git2::Tree,
+        path: &Path,
+    ) -> Option<PathBuf> {
+        if !self.is_enabled() || tree.get_path(path).is_ok() {
+            return None;
+        }
+        let mut spelling = PathBuf::new();
+        let mut tree = tree.clone();
+        let mut components = path.components().peekable();
+        while let Some(component) = components.next() {
+            let name = Path::new(component.as_os_str());
+            let entry = tree.iter().find(|entry| {
+                entry
+                    .name()
+                    .map_or(false, |entry_name| self.same(Path::new(entry_name), name))
+            })?;
+            spelling.push(entry.name()?);
+            if components.peek().is_some() {
+                tree = repo.find_tree(entry.id()).ok()?;
+            }
+        }
+        Some(spelling)
+    }
+}
+
+/// Find the files of `diff`, from `old_tree` to another tree, that were deleted and added with another
+/// spelling of the same path, and return `old_tree` with these files at their new spelling, along with the
+/// pairs of old and new path. Return `None` if there are no such files.
+///
+/// Diffing the returned tree shows these files as modified, or not at all if only their spelling changed,
+/// just like the filesystem sees them.
+pub(crate) fn respell_tree<'repo>(
+    repo: &'repo git2::Repository,
+    old_tree: &git2::Tree,
+    diff: &git2::Diff<'_>,
+) -> Result<Option<(git2::Tree<'repo>, Vec<(PathBuf, PathBuf)>)>> {
+    let normalization = PathNormalization::from_repo(repo);
+    if !normalization.is_enabled() {
+        return Ok(None);
+    }
+    let mut deleted = HashMap::new();
+    let mut added = Vec::new();
+    for delta in diff.deltas() {
+        match delta.status() {
+            git2::Delta::Deleted => {
+                if let Some(path) = delta.old_file().path() {
+                    deleted.insert(normalization.key(path).into_owned(), path.to_owned());
+                }
+            }
+            git2::Delta::Added | git2::Delta::Untracked => {
+                if let Some(path) = delta.new_file().path() {
+                    added.push(path.to_owned());
+                }
+            }
+            _ => {}
+        }
+    }
+    let pairs: Vec<(PathBuf, PathBuf)> = added
+        .into_iter()
+        .filter_map(|new_path| {
+            deleted
+                .remove(&*normalization.key(&new_path))
+                .map(|old_path| (old_path, new_path))
+        })
+        .collect();
+    if pairs.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = git2::build::TreeUpdateBuilder::new();
+    for (old_path, new_path) in &pairs {
+        let entry = old_tree.get_path(old_path)?;
+        builder.remove(old_path);
+        builder.upsert(new_path, entry.id(), file_mode(entry.filemode()));
+    }
+    let tree_id = builder.create_updated(repo, old_tree)?;
+    Ok(Some((repo.find_tree(tree_id)?, pairs)))
+}
+
+/// Set the files in `files` whose path was respelled as told by `pairs` of old and new path to be renamed,
+/// with `diff` being the diff of the tree returned by [`respell_tree()`].
+pub(crate) fn describe_respellings(
+    repo: &git2::Repository,
+    diff: &git2::Diff<'_>,
+    files: &mut DiffByPathMap,
+    pairs: &[(PathBuf, PathBuf)],
+) {
+    for delta in diff.deltas() {
+        let Some(new_path) = delta.new_file().path() else {
+            continue;
+        };
+        let Some((old_path, _)) = pairs.iter().find(|(_, new)| new == new_path) else {
+            continue;
+        };
+        let Some(file) = files.get_mut(new_path) else {
+            continue;
+        };
+        let similarity =
+            blob_similarity(repo, delta.old_file().id(), delta.new_file().id()).unwrap_or_default();
+        file.old_path = Some(old_path.clone());
+        file.path_change = Some(PathChange::Renamed {
+            old_path: old_path.clone(),
+            new_path: new_path.to_owned(),
+            similarity,
+        });
+    }
+}
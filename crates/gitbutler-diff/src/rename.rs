@@ -0,0 +1,161 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use bstr::{BString, ByteSlice, ByteVec};
+use serde::Serialize;
+
+use crate::{diff::DiffByPathMap, ChangeType, GitHunk};
+
+/// The similarity in percent above which a deleted and an added file are considered the same file by default,
+/// just like Git does.
+pub const DEFAULT_RENAME_THRESHOLD: u16 = 50;
+
+/// How a file came to be at its new path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PathChange {
+    /// The file at `old_path` was moved to `new_path`.
+    #[serde(rename_all = "camelCase")]
+    Renamed {
+        old_path: PathBuf,
+        new_path: PathBuf,
+        /// How much of the content stayed the same, in percent.
+        similarity: u16,
+    },
+    /// The file at `old_path`, which still exists, was copied to `new_path`.
+    #[serde(rename_all = "camelCase")]
+    Copied {
+        old_path: PathBuf,
+        new_path: PathBuf,
+        /// How much of the content stayed the same, in percent.
+        similarity: u16,
+    },
+}
+
+/// Return how similar `old` and `new` are in percent, as the amount of lines they have in common
+/// relative to the amount of lines of the longer one.
+pub fn similarity(old: &[u8], new: &[u8]) -> u16 {
+    let old_lines: Vec<_> = old.lines_with_terminator().collect();
+    let new_lines: Vec<_> = new.lines_with_terminator().collect();
+    let total = old_lines.len().max(new_lines.len());
+    if total == 0 {
+        return 100;
+    }
+    let mut counts: HashMap<&[u8], usize> = HashMap::new();
+    for line in old_lines {
+        *counts.entry(line).or_default() += 1;
+    }
+    let mut common = 0;
+    for line in new_lines {
+        if let Some(count) = counts.get_mut(line).filter(|count| **count > 0) {
+            *count -= 1;
+            common += 1;
+        }
+    }
+    u16::try_from(common * 100 / total).unwrap_or(100)
+}
+
+/// Find files in `diffs` that were deleted and added elsewhere with at least `threshold` percent of their
+/// content, and return them as pairs of old and new path.
+///
+/// This only needs the hunks of the deletion and the addition, which hold the whole content of either file,
+/// so it works on diffs that were computed without rename detection.
+pub fn renames(diffs: &HashMap<PathBuf, Vec<GitHunk>>, threshold: u16) -> Vec<(PathBuf, PathBuf)> {
+    let content_of = |change_type: ChangeType, prefix: u8| {
+        diffs
+            .iter()
+            .filter(|(_, hunks)| {
+                !hunks.is_empty()
+                    && hunks
+                        .iter()
+                        .all(|hunk| hunk.change_type == change_type && !hunk.binary)
+            })
+            .map(|(path, hunks)| {
+                let mut content = BString::default();
+                for line in hunks
+                    .iter()
+                    .flat_map(|hunk| hunk.diff_lines.lines_with_terminator())
+                {
+                    if line.first() == Some(&prefix) {
+                        content.push_str(&line[1..]);
+                    }
+                }
+                (path, content)
+            })
+            .collect::<Vec<_>>()
+    };
+    let deleted = content_of(ChangeType::Deleted, b'-');
+    let added = content_of(ChangeType::Added, b'+');
+
+    let mut candidates: Vec<(u16, &PathBuf, &PathBuf)> = deleted
+        .iter()
+        .flat_map(|(old_path, old)| {
+            added.iter().filter_map(move |(new_path, new)| {
+                let similarity = similarity(old, new);
+                (similarity >= threshold).then_some((similarity, *old_path, *new_path))
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)).then(a.2.cmp(b.2)));
+
+    let mut pairs: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for (_, old_path, new_path) in candidates {
+        if pairs
+            .iter()
+            .all(|(old, new)| old != old_path && new != new_path)
+        {
+            pairs.push((old_path.clone(), new_path.clone()));
+        }
+    }
+    pairs
+}
+
+/// Set how all files in `files` that were renamed or copied in `diff` got to their new path.
+pub(crate) fn describe_path_changes(
+    repo: &git2::Repository,
+    diff: &git2::Diff<'_>,
+    files: &mut DiffByPathMap,
+) {
+    for delta in diff.deltas() {
+        let status = delta.status();
+        if !matches!(status, git2::Delta::Renamed | git2::Delta::Copied) {
+            continue;
+        }
+        let (Some(old_path), Some(new_path)) = (delta.old_file().path(), delta.new_file().path())
+        else {
+            continue;
+        };
+        let Some(file) = files.get_mut(new_path) else {
+            continue;
+        };
+        let (old_id, new_id) = (delta.old_file().id(), delta.new_file().id());
+        let similarity = if old_id == new_id {
+            100
+        } else {
+            blob_similarity(repo, old_id, new_id).unwrap_or_default()
+        };
+        let (old_path, new_path) = (old_path.to_path_buf(), new_path.to_path_buf());
+        file.path_change = Some(if status == git2::Delta::Renamed {
+            PathChange::Renamed {
+                old_path,
+                new_path,
+                similarity,
+            }
+        } else {
+            PathChange::Copied {
+                old_path,
+                new_path,
+                similarity,
+            }
+        });
+    }
+}
+
+pub(crate) fn blob_similarity(
+    repo: &git2::Repository,
+    old_id: git2::Oid,
+    new_id: git2::Oid,
+) -> Option<u16> {
+    let old = repo.find_blob(old_id).ok()?;
+    let new = repo.find_blob(new_id).ok()?;
+    Some(similarity(old.content(), new.content()))
+}
@@ -0,0 +1,168 @@
+use std::ops::RangeInclusive;
+
+use bstr::{BString, ByteSlice, ByteVec};
+use serde::{Deserialize, Serialize};
+
+use crate::GitHunk;
+
+/// A set of line numbers, stored as sorted, non-overlapping and non-adjacent inclusive ranges.
+///
+/// It's serialized as list of `[first, last]` pairs.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<[u32; 2]>", into = "Vec<[u32; 2]>")]
+pub struct RangeSet {
+    ranges: Vec<RangeInclusive<u32>>,
+}
+
+impl RangeSet {
+    /// Add all lines in `range` to the set.
+    pub fn insert(&mut self, range: RangeInclusive<u32>) {
+        if range.is_empty() {
+            return;
+        }
+        let (mut start, mut end) = range.into_inner();
+        let mut merged = Vec::with_capacity(self.ranges.len() + 1);
+        for existing in self.ranges.drain(..) {
+            if existing.end().saturating_add(1) < start || end.saturating_add(1) < *existing.start()
+            {
+                merged.push(existing);
+            } else {
+                start = start.min(*existing.start());
+                end = end.max(*existing.end());
+            }
+        }
+        merged.push(start..=end);
+        merged.sort_by_key(|range| *range.start());
+        self.ranges = merged;
+    }
+
+    /// Return `true` if `line` is in the set.
+    pub fn contains(&self, line: u32) -> bool {
+        self.ranges.iter().any(|range| range.contains(&line))
+    }
+
+    /// Return `true` if there are no lines in the set.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Return the ranges in ascending order.
+    pub fn ranges(&self) -> &[RangeInclusive<u32>] {
+        &self.ranges
+    }
+}
+
+impl FromIterator<RangeInclusive<u32>> for RangeSet {
+    fn from_iter<T: IntoIterator<Item = RangeInclusive<u32>>>(iter: T) -> Self {
+        let mut set = RangeSet::default();
+        for range in iter {
+            set.insert(range);
+        }
+        set
+    }
+}
+
+impl From<Vec<[u32; 2]>> for RangeSet {
+    fn from(ranges: Vec<[u32; 2]>) -> Self {
+        ranges.into_iter().map(|[start, end]| start..=end).collect()
+    }
+}
+
+impl From<RangeSet> for Vec<[u32; 2]> {
+    fn from(set: RangeSet) -> Self {
+        set.ranges
+            .into_iter()
+            .map(|range| [*range.start(), *range.end()])
+            .collect()
+    }
+}
+
+/// A selection of individual lines of a hunk, to only commit part of it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkSelection {
+    /// The id of the hunk as presented to the user, like `5-9`.
+    pub hunk_id: String,
+    /// The selected lines, numbered from 1 and counted from the first line after the hunk header.
+    pub lines: RangeSet,
+}
+
+impl GitHunk {
+    /// Return a hunk that only contains the changes on the given `lines` of this hunk, similar to
+    /// editing a hunk in `git add -p`.
+    ///
+    /// `lines` are numbered from 1, starting at the first line after the hunk header.
+    /// Unselected additions are dropped, and unselected deletions become context lines.
+    /// Return `None` if no change was selected or if this is a binary hunk.
+    pub fn select_lines(&self, lines: &RangeSet) -> Option<GitHunk> {
+        if self.binary {
+            return None;
+        }
+        let mut diff_lines = self.diff_lines.lines_with_terminator();
+        let header = diff_lines.next().filter(|line| line.starts_with(b"@@"))?;
+
+        let mut body = BString::default();
+        let (mut old_lines, mut new_lines) = (0, 0);
+        let mut has_changes = false;
+        let mut previous_line_kept = true;
+        for (line, number) in diff_lines.zip(1..) {
+            let selected = lines.contains(number);
+            match line.first() {
+                Some(b'+') if selected => {
+                    new_lines += 1;
+                    has_changes = true;
+                }
+                Some(b'+') => {
+                    previous_line_kept = false;
+                    continue;
+                }
+                Some(b'-') if selected => {
+                    old_lines += 1;
+                    has_changes = true;
+                }
+                Some(b'-') => {
+                    body.push(b' ');
+                    body.push_str(&line[1..]);
+                    old_lines += 1;
+                    new_lines += 1;
+                    previous_line_kept = true;
+                    continue;
+                }
+                Some(b' ') => {
+                    old_lines += 1;
+                    new_lines += 1;
+                }
+                // Markers like `\ No newline at end of file` belong to the line before them.
+                _ if !previous_line_kept => continue,
+                _ => {}
+            }
+            body.push_str(line);
+            previous_line_kept = true;
+        }
+        if !has_changes {
+            return None;
+        }
+
+        let header_suffix = header
+            .find(b" @@")
+            .map(|pos| &header[pos + 3..])
+            .unwrap_or(b"\n".as_slice());
+        let mut diff = BString::from(format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_start, old_lines, self.new_start, new_lines
+        ));
+        diff.push_str(header_suffix);
+        diff.push_str(body);
+
+        Some(GitHunk {
+            old_start: self.old_start,
+            old_lines,
+            new_start: self.new_start,
+            new_lines,
+            diff_lines: diff.into(),
+            binary: false,
+            change_type: self.change_type,
+            highlights: None,
+        })
+    }
+}
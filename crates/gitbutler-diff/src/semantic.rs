@@ -0,0 +1,174 @@
+//! Split hunks where sections of source files, like functions, start, so each section can be assigned to
+//! a branch of its own even if its changes are close to those of another.
+use std::collections::BTreeSet;
+
+use bstr::{BString, ByteSlice, ByteVec};
+
+#[cfg(feature = "semantic-hunks")]
+use crate::syntax;
+use crate::{DiffByPathMap, GitHunk};
+
+/// Without `tree-sitter`, no language is supported.
+#[cfg(not(feature = "semantic-hunks"))]
+mod syntax {
+    use std::{collections::BTreeSet, path::Path};
+
+    pub(crate) fn is_supported(_path: &Path) -> bool {
+        false
+    }
+
+    pub(crate) fn boundaries(_path: &Path, _content: &[u8]) -> Option<BTreeSet<u32>> {
+        None
+    }
+}
+
+impl GitHunk {
+    /// Split this hunk into hunks that each only contain the changes between two of `boundaries`, the numbers
+    /// of the lines after the change at which sections start.
+    ///
+    /// A hunk is only split at an unchanged line right before or at a boundary, which is dropped so the hunks
+    /// don't touch. Changes that aren't separated by an unchanged line stay together.
+    /// Return this hunk as only hunk if it isn't split.
+    pub fn split_at_boundaries(&self, boundaries: &BTreeSet<u32>) -> Vec<GitHunk> {
+        if self.binary || boundaries.is_empty() {
+            return vec![self.clone()];
+        }
+        let mut diff_lines = self.diff_lines.lines_with_terminator();
+        let Some(header) = diff_lines.next().filter(|line| line.starts_with(b"@@")) else {
+            return vec![self.clone()];
+        };
+        let lines: Vec<&[u8]> = diff_lines.collect();
+        let Some(last_change) = lines
+            .iter()
+            .rposition(|line| matches!(line.first(), Some(b'+' | b'-')))
+        else {
+            return vec![self.clone()];
+        };
+
+        // The lines of each part, along with the numbers of their first old and new line.
+        let mut parts = Vec::new();
+        let mut part = (0, self.old_start, self.new_start);
+        let (mut old, mut new) = (self.old_start, self.new_start);
+        let mut has_changes = false;
+        for (index, line) in lines.iter().enumerate() {
+            match line.first() {
+                Some(b'+') => {
+                    new += 1;
+                    has_changes = true;
+                }
+                Some(b'-') => {
+                    old += 1;
+                    has_changes = true;
+                }
+                Some(b' ') => {
+                    if has_changes
+                        && index < last_change
+                        && (boundaries.contains(&(new + 1)) || boundaries.contains(&new))
+                    {
+                        parts.push((part.0..index, part.1, part.2));
+                        part = (index + 1, old + 1, new + 1);
+                        has_changes = false;
+                    }
+                    old += 1;
+                    new += 1;
+                }
+                _ => {}
+            }
+        }
+        if parts.is_empty() {
+            return vec![self.clone()];
+        }
+        parts.push((part.0..lines.len(), part.1, part.2));
+
+        let header_suffix = header
+            .find(b" @@")
+            .map(|pos| &header[pos + 3..])
+            .unwrap_or(b"\n".as_slice());
+        parts
+            .into_iter()
+            .enumerate()
+            .map(|(number, (range, old_start, new_start))| {
+                let body = &lines[range];
+                let (mut old_lines, mut new_lines) = (0, 0);
+                for line in body {
+                    match line.first() {
+                        Some(b'+') => new_lines += 1,
+                        Some(b'-') => old_lines += 1,
+                        Some(b' ') => {
+                            old_lines += 1;
+                            new_lines += 1;
+                        }
+                        _ => {}
+                    }
+                }
+                // Like Git, refer to the line before the change if there are no lines on one side.
+                let old_start = if old_lines == 0 {
+                    old_start.saturating_sub(1)
+                } else {
+                    old_start
+                };
+                let new_start = if new_lines == 0 {
+                    new_start.saturating_sub(1)
+                } else {
+                    new_start
+                };
+                let mut diff = BString::from(format!(
+                    "@@ -{old_start},{old_lines} +{new_start},{new_lines} @@"
+                ));
+                // The section the hunk starts in is only known for the first part.
+                diff.push_str(if number == 0 {
+                    header_suffix
+                } else {
+                    b"\n".as_slice()
+                });
+                for line in body {
+                    diff.push_str(line);
+                }
+                GitHunk {
+                    old_start,
+                    old_lines,
+                    new_start,
+                    new_lines,
+                    diff_lines: diff.into(),
+                    binary: false,
+                    change_type: self.change_type,
+                    highlights: None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Split the hunks of the text files in `files` where the sections of their new content start, if the
+/// language of the file is supported.
+pub(crate) fn split_hunks(
+    repo: &git2::Repository,
+    diff: &git2::Diff<'_>,
+    files: &mut DiffByPathMap,
+) {
+    for delta in diff.deltas() {
+        let Some(path) = delta.new_file().path() else {
+            continue;
+        };
+        if delta.new_file().id().is_zero() || !syntax::is_supported(path) {
+            continue;
+        }
+        let Some(file) = files
+            .get_mut(path)
+            .filter(|file| !file.binary && !file.skipped && !file.hunks.is_empty())
+        else {
+            continue;
+        };
+        let Ok(blob) = repo.find_blob(delta.new_file().id()) else {
+            continue;
+        };
+        let Some(boundaries) = syntax::boundaries(path, blob.content()) else {
+            continue;
+        };
+        file.hunks = file
+            .hunks
+            .iter()
+            .flat_map(|hunk| hunk.split_at_boundaries(&boundaries))
+            .collect();
+    }
+}
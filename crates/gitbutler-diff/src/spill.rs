@@ -0,0 +1,66 @@
+//! Move the text of the hunks of a diff to a temporary file and back, which is most of its size.
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use bstr::BString;
+
+use crate::DiffByPathMap;
+
+/// The text of the hunks of a diff, kept in a temporary file that is removed when it's dropped.
+#[derive(Debug)]
+pub(crate) struct SpilledText {
+    file: File,
+    /// The lengths of the text of each hunk of each file, in the order they were written.
+    lengths: Vec<(PathBuf, Vec<usize>)>,
+}
+
+/// Return the size of the text of all hunks of `files` in bytes.
+pub(crate) fn text_size(files: &DiffByPathMap) -> usize {
+    files
+        .values()
+        .flat_map(|file| &file.hunks)
+        .map(|hunk| hunk.diff_lines.len())
+        .sum()
+}
+
+impl SpilledText {
+    /// Move the text of all hunks of `files` to a temporary file, leaving their text empty.
+    pub(crate) fn spill(files: &mut DiffByPathMap) -> Result<Self> {
+        let mut file = tempfile::tempfile().context("failed to create file to spill diff to")?;
+        let mut writer = BufWriter::new(&mut file);
+        let mut lengths = Vec::with_capacity(files.len());
+        for (path, diff) in files.iter_mut() {
+            let mut hunk_lengths = Vec::with_capacity(diff.hunks.len());
+            for hunk in &mut diff.hunks {
+                let text = std::mem::take(&mut hunk.diff_lines);
+                writer.write_all(&text)?;
+                hunk_lengths.push(text.len());
+            }
+            lengths.push((path.clone(), hunk_lengths));
+        }
+        writer.flush()?;
+        drop(writer);
+        Ok(SpilledText { file, lengths })
+    }
+
+    /// Put the text back into the hunks of `files`, which have to be the ones it was taken from.
+    pub(crate) fn restore(mut self, files: &mut DiffByPathMap) -> Result<()> {
+        self.file.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(&self.file);
+        for (path, hunk_lengths) in self.lengths {
+            let diff = files
+                .get_mut(&path)
+                .with_context(|| format!("spilled diff of {} went missing", path.display()))?;
+            for (hunk, length) in diff.hunks.iter_mut().zip(hunk_lengths) {
+                let mut text = vec![0; length];
+                reader.read_exact(&mut text)?;
+                hunk.diff_lines = BString::from(text).into();
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// A change of the commit a submodule points to, which has no content to show as hunks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmoduleChange {
+    /// The path of the submodule, relative to the worktree.
+    pub path: PathBuf,
+    /// The commit the submodule pointed to, or `None` if it was added.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub old_id: Option<git2::Oid>,
+    /// The commit the submodule points to now, or `None` if it was removed.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub new_id: Option<git2::Oid>,
+}
+
+/// Return all submodules that were added, removed or moved to another commit between `old_tree` and `new_tree`.
+///
+/// These are left out of [`trees()`](crate::trees()), which ignores submodules.
+pub fn submodule_changes(
+    repo: &git2::Repository,
+    old_tree: &git2::Tree<'_>,
+    new_tree: &git2::Tree<'_>,
+) -> Result<Vec<SubmoduleChange>> {
+    let diff = repo
+        .diff_tree_to_tree(Some(old_tree), Some(new_tree), None)
+        .context("failed to diff trees")?;
+    let pointer = |file: git2::DiffFile<'_>| {
+        (file.mode() == git2::FileMode::Commit && !file.id().is_zero()).then(|| file.id())
+    };
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| {
+            let (old_id, new_id) = (pointer(delta.old_file()), pointer(delta.new_file()));
+            if old_id.is_none() && new_id.is_none() {
+                return None;
+            }
+            let path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())?
+                .to_path_buf();
+            Some(SubmoduleChange {
+                path,
+                old_id,
+                new_id,
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,133 @@
+//! Find where sections of source files, like functions or classes, start by parsing them with `tree-sitter`.
+use std::{collections::BTreeSet, path::Path};
+
+/// What to parse a language with, and which of its syntax nodes are sections.
+struct Grammar {
+    language: fn() -> tree_sitter::Language,
+    /// The kinds of nodes that are sections, like functions.
+    items: &'static [&'static str],
+    /// The kinds of nodes that belong to the section right after them, like comments.
+    leading: &'static [&'static str],
+}
+
+const RUST: Grammar = Grammar {
+    language: tree_sitter_rust::language,
+    items: &[
+        "const_item",
+        "enum_item",
+        "function_item",
+        "function_signature_item",
+        "impl_item",
+        "macro_definition",
+        "mod_item",
+        "static_item",
+        "struct_item",
+        "trait_item",
+        "type_item",
+        "union_item",
+    ],
+    leading: &["attribute_item", "block_comment", "line_comment"],
+};
+
+const PYTHON: Grammar = Grammar {
+    language: tree_sitter_python::language,
+    items: &[
+        "class_definition",
+        "decorated_definition",
+        "function_definition",
+    ],
+    leading: &["comment"],
+};
+
+const JAVASCRIPT_ITEMS: &[&str] = &[
+    "class_declaration",
+    "export_statement",
+    "function_declaration",
+    "generator_function_declaration",
+    "method_definition",
+];
+
+const JAVASCRIPT: Grammar = Grammar {
+    language: tree_sitter_javascript::language,
+    items: JAVASCRIPT_ITEMS,
+    leading: &["comment"],
+};
+
+const TYPESCRIPT_ITEMS: &[&str] = &[
+    "abstract_class_declaration",
+    "class_declaration",
+    "enum_declaration",
+    "export_statement",
+    "function_declaration",
+    "function_signature",
+    "generator_function_declaration",
+    "interface_declaration",
+    "method_definition",
+    "type_alias_declaration",
+];
+
+const TYPESCRIPT: Grammar = Grammar {
+    language: tree_sitter_typescript::language_typescript,
+    items: TYPESCRIPT_ITEMS,
+    leading: &["comment", "decorator"],
+};
+
+const TSX: Grammar = Grammar {
+    language: tree_sitter_typescript::language_tsx,
+    items: TYPESCRIPT_ITEMS,
+    leading: &["comment", "decorator"],
+};
+
+fn grammar_of(path: &Path) -> Option<&'static Grammar> {
+    Some(match path.extension()?.to_str()? {
+        "rs" => &RUST,
+        "py" | "pyi" => &PYTHON,
+        "js" | "jsx" | "mjs" | "cjs" => &JAVASCRIPT,
+        "ts" | "mts" | "cts" => &TYPESCRIPT,
+        "tsx" => &TSX,
+        _ => return None,
+    })
+}
+
+/// Return `true` if the sections of the file at `path` can be found.
+pub(crate) fn is_supported(path: &Path) -> bool {
+    grammar_of(path).is_some()
+}
+
+/// Return the numbers of the lines at which the sections of `content`, the content of the file at `path`,
+/// start, or `None` if its language isn't supported or it couldn't be parsed.
+///
+/// Sections in sections are found as well, like methods in classes, but not the parts of sections,
+/// like the function a decorator belongs to.
+pub(crate) fn boundaries(path: &Path, content: &[u8]) -> Option<BTreeSet<u32>> {
+    let grammar = grammar_of(path)?;
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(&(grammar.language)()).ok()?;
+    let tree = parser.parse(content, None)?;
+
+    let mut boundaries = BTreeSet::new();
+    let mut nodes = vec![tree.root_node()];
+    while let Some(node) = nodes.pop() {
+        let is_item = grammar.items.contains(&node.kind());
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            if !is_item && grammar.items.contains(&child.kind()) {
+                boundaries.insert(first_line(grammar, child));
+            }
+            nodes.push(child);
+        }
+    }
+    Some(boundaries)
+}
+
+/// Return the number of the first line of `item`, including the comments and attributes right before it.
+fn first_line(grammar: &Grammar, item: tree_sitter::Node<'_>) -> u32 {
+    let mut first = item;
+    while let Some(previous) = first.prev_named_sibling().filter(|previous| {
+        grammar.leading.contains(&previous.kind())
+            && previous.end_position().row + 1 >= first.start_position().row
+    }) {
+        first = previous;
+    }
+    u32::try_from(first.start_position().row + 1).unwrap_or(u32::MAX)
+}
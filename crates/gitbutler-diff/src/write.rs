@@ -1,14 +1,17 @@
 #[cfg(target_family = "unix")]
 use std::os::unix::prelude::PermissionsExt;
-use std::{borrow::Borrow, path::PathBuf};
+use std::{
+    borrow::Borrow,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use bstr::{BString, ByteSlice, ByteVec};
 use diffy::{apply_bytes as diffy_apply, Line, Patch};
 use gitbutler_command_context::CommandContext;
 use hex::ToHex;
 
-use crate::GitHunk;
+use crate::{diff::DiffByPathMap, filter, lfs, parallel, GitHunk, PathNormalization};
 
 // this function takes a list of file ownership,
 // constructs a tree from those changes on top of the target
@@ -50,119 +53,165 @@ where
     T: Into<GitHunk> + Clone,
 {
     let git_repository = ctx.repository();
+    let worktree_path = ctx.project().worktree_path();
+    let files: Vec<(PathBuf, Vec<GitHunk>)> = files
+        .into_iter()
+        .map(|(rel_path, hunks)| {
+            let hunks = hunks.borrow().iter().map(|h| h.clone().into()).collect();
+            (rel_path.borrow().clone(), hunks)
+        })
+        .collect();
+
+    // Files are hashed and patched on their own, so that is spread across threads in large commits.
+    let base_tree_id = base_tree.id();
+    let normalization = PathNormalization::from_repo(git_repository);
+    let updates = parallel::map_with_repo(
+        git_repository,
+        &files,
+        ctx.project().parallelism.threads(),
+        MIN_FILES_PER_THREAD,
+        |repo, (rel_path, hunks)| {
+            let base_tree = repo.find_tree(base_tree_id)?;
+            file_update(
+                repo,
+                &worktree_path,
+                &base_tree,
+                normalization,
+                rel_path,
+                hunks,
+            )
+        },
+    )?;
+
     let mut builder = git2::build::TreeUpdateBuilder::new();
-    // now update the index with content in the working directory for each file
-    for (rel_path, hunks) in files {
-        let rel_path = rel_path.borrow();
-        let hunks: Vec<GitHunk> = hunks.borrow().iter().map(|h| h.clone().into()).collect();
-        let full_path = ctx.project().worktree_path().join(rel_path);
-
-        let is_submodule = full_path.is_dir()
-            && hunks.len() == 1
-            && hunks[0].diff_lines.contains_str(b"Subproject commit");
-
-        // if file exists
-        if full_path.exists() {
-            // if file is executable, use 755, otherwise 644
-            let mut filemode = git2::FileMode::Blob;
-            // check if full_path file is executable
-            if let Ok(metadata) = std::fs::symlink_metadata(&full_path) {
-                #[cfg(target_family = "unix")]
+    for ((rel_path, _), update) in files.iter().zip(updates) {
+        match update {
+            Some(Update::Upsert(blob_oid, filemode)) => {
+                // the file replaces its other spelling if the filesystem sees both as the same file
+                if let Some(spelling) =
+                    normalization.spelling_in(git_repository, base_tree, rel_path)
                 {
-                    if metadata.permissions().mode() & 0o111 != 0 {
-                        filemode = git2::FileMode::BlobExecutable;
-                    }
+                    builder.remove(spelling);
                 }
+                builder.upsert(rel_path, blob_oid, filemode);
+            }
+            Some(Update::Remove) => {
+                builder.remove(rel_path);
+            }
+            None => {}
+        }
+    }
 
-                #[cfg(target_os = "windows")]
-                {
-                    // NOTE: *Keep* the existing executable bit if it was present
-                    //       in the tree already, don't try to derive something from
-                    //       the FS that doesn't exist.
-                    filemode = base_tree
-                        .get_path(rel_path)
-                        .ok()
-                        .and_then(|entry| {
-                            (entry.filemode() & 0o100000 == 0o100000
-                                && entry.filemode() & 0o111 != 0)
-                                .then_some(git2::FileMode::BlobExecutable)
-                        })
-                        .unwrap_or(filemode);
-                }
+    // now write out the tree
+    let tree_oid = builder
+        .create_updated(ctx.repository(), base_tree)
+        .context("failed to write updated tree")?;
 
-                if metadata.file_type().is_symlink() {
-                    filemode = git2::FileMode::Link;
+    Ok(tree_oid)
+}
+
+/// The least amount of files that each thread hashes when building trees, as each thread has to open
+/// the repository first.
+const MIN_FILES_PER_THREAD: usize = 64;
+
+/// How a file changes in the tree that hunks are written onto.
+enum Update {
+    Upsert(git2::Oid, git2::FileMode),
+    Remove,
+}
+
+/// Return how the file at `rel_path` changes if `hunks` of it in the worktree at `worktree_path` are
+/// written onto `base_tree`, or `None` if it stays as it is. The file in `base_tree` may have another
+/// spelling of `rel_path` that is the same path according to `normalization`.
+fn file_update(
+    git_repository: &git2::Repository,
+    worktree_path: &Path,
+    base_tree: &git2::Tree,
+    normalization: PathNormalization,
+    rel_path: &Path,
+    hunks: &[GitHunk],
+) -> Result<Option<Update>> {
+    let full_path = worktree_path.join(rel_path);
+
+    let is_submodule = full_path.is_dir()
+        && hunks.len() == 1
+        && hunks[0].diff_lines.contains_str(b"Subproject commit");
+
+    // if file exists
+    if full_path.exists() {
+        // if file is executable, use 755, otherwise 644
+        let mut filemode = git2::FileMode::Blob;
+        // check if full_path file is executable
+        if let Ok(metadata) = std::fs::symlink_metadata(&full_path) {
+            #[cfg(target_family = "unix")]
+            {
+                if metadata.permissions().mode() & 0o111 != 0 {
+                    filemode = git2::FileMode::BlobExecutable;
                 }
             }
 
-            // get the blob
-            if filemode == git2::FileMode::Link {
-                // it's a symlink, make the content the path of the link
-                let link_target = std::fs::read_link(&full_path)?;
-
-                // if the link target is inside the project repository, make it relative
-                let link_target = link_target
-                    .strip_prefix(ctx.project().worktree_path())
-                    .unwrap_or(&link_target);
-
-                let blob_oid = git_repository.blob(
-                    link_target
-                        .to_str()
-                        .ok_or_else(|| {
-                            anyhow!("path contains invalid utf-8 characters: {link_target:?}")
-                        })?
-                        .as_bytes(),
-                )?;
-                builder.upsert(rel_path, blob_oid, filemode);
-            } else if let Ok(tree_entry) = base_tree.get_path(rel_path) {
-                if hunks.len() == 1 && hunks[0].binary {
-                    let new_blob_oid = &hunks[0].diff_lines;
-                    // convert string to Oid
-                    let new_blob_oid = new_blob_oid
-                        .to_str()
-                        .expect("hex-string")
-                        .parse()
-                        .context("failed to diff as oid")?;
-                    builder.upsert(rel_path, new_blob_oid, filemode);
-                } else {
-                    // blob from tree_entry
-                    let blob = tree_entry
-                        .to_object(git_repository)
-                        .unwrap()
-                        .peel_to_blob()
-                        .context("failed to get blob")?;
-
-                    let blob_contents = blob.content();
-
-                    let mut hunks = hunks.iter().collect::<Vec<_>>();
-                    hunks.sort_by_key(|hunk| hunk.new_start);
-                    let mut all_diffs = BString::default();
-                    for hunk in hunks {
-                        all_diffs.push_str(&hunk.diff_lines);
-                    }
+            #[cfg(target_os = "windows")]
+            {
+                // NOTE: *Keep* the existing executable bit if it was present
+                //       in the tree already, don't try to derive something from
+                //       the FS that doesn't exist.
+                filemode = base_tree
+                    .get_path(rel_path)
+                    .ok()
+                    .and_then(|entry| {
+                        (entry.filemode() & 0o100000 == 0o100000 && entry.filemode() & 0o111 != 0)
+                            .then_some(git2::FileMode::BlobExecutable)
+                    })
+                    .unwrap_or(filemode);
+            }
 
-                    let patch = Patch::from_bytes(&all_diffs)?;
-                    let blob_contents = apply(blob_contents, &patch).context(format!(
-                        "failed to apply\n{}\nonto:\n{}",
-                        all_diffs.as_bstr(),
-                        blob_contents.as_bstr()
-                    ));
-
-                    match blob_contents {
-                        Ok(blob_contents) => {
-                            // create a blob
-                            let new_blob_oid = git_repository.blob(blob_contents.as_bytes())?;
-                            // upsert into the builder
-                            builder.upsert(rel_path, new_blob_oid, filemode);
-                        }
-                        Err(_) => {
-                            // If the patch failed to apply, do nothing, this is handled elsewhere
-                            continue;
-                        }
-                    }
-                }
-            } else if is_submodule {
-                let mut blob_contents = BString::default();
+            if metadata.file_type().is_symlink() {
+                filemode = git2::FileMode::Link;
+            }
+        }
+
+        // get the blob
+        if filemode == git2::FileMode::Link {
+            // it's a symlink, make the content the path of the link
+            let link_target = std::fs::read_link(&full_path)?;
+
+            // if the link target is inside the project repository, make it relative
+            let link_target = link_target
+                .strip_prefix(worktree_path)
+                .unwrap_or(&link_target);
+
+            let blob_oid = git_repository.blob(
+                link_target
+                    .to_str()
+                    .ok_or_else(|| {
+                        anyhow!("path contains invalid utf-8 characters: {link_target:?}")
+                    })?
+                    .as_bytes(),
+            )?;
+            Ok(Some(Update::Upsert(blob_oid, filemode)))
+        } else if let Some(tree_entry) = base_tree.get_path(rel_path).ok().or_else(|| {
+            normalization
+                .spelling_in(git_repository, base_tree, rel_path)
+                .and_then(|spelling| base_tree.get_path(&spelling).ok())
+        }) {
+            if hunks.len() == 1 && hunks[0].binary {
+                let new_blob_oid = &hunks[0].diff_lines;
+                // convert string to Oid
+                let new_blob_oid = new_blob_oid
+                    .to_str()
+                    .expect("hex-string")
+                    .parse()
+                    .context("failed to diff as oid")?;
+                Ok(Some(Update::Upsert(new_blob_oid, filemode)))
+            } else {
+                // blob from tree_entry
+                let blob = tree_entry
+                    .to_object(git_repository)
+                    .unwrap()
+                    .peel_to_blob()
+                    .context("failed to get blob")?;
+
+                let blob_contents = blob.content();
 
                 let mut hunks = hunks.iter().collect::<Vec<_>>();
                 hunks.sort_by_key(|hunk| hunk.new_start);
@@ -170,33 +219,149 @@ where
                 for hunk in hunks {
                     all_diffs.push_str(&hunk.diff_lines);
                 }
+
                 let patch = Patch::from_bytes(&all_diffs)?;
-                blob_contents = apply(&blob_contents, &patch)
-                    .context(format!("failed to apply {}", all_diffs))?;
+                let blob_contents = apply(blob_contents, &patch).context(format!(
+                    "failed to apply\n{}\nonto:\n{}",
+                    all_diffs.as_bstr(),
+                    blob_contents.as_bstr()
+                ));
+
+                match blob_contents {
+                    Ok(blob_contents) => {
+                        // create a blob
+                        let new_blob_oid = git_repository.blob(blob_contents.as_bytes())?;
+                        // upsert into the builder
+                        Ok(Some(Update::Upsert(new_blob_oid, filemode)))
+                    }
+                    // If the patch failed to apply, do nothing, this is handled elsewhere
+                    Err(_) => Ok(None),
+                }
+            }
+        } else if is_submodule {
+            let mut blob_contents = BString::default();
 
-                // create a blob
-                let new_blob_oid = git_repository.blob(blob_contents.as_bytes())?;
-                // upsert into the builder
-                builder.upsert(rel_path, new_blob_oid, filemode);
+            let mut hunks = hunks.iter().collect::<Vec<_>>();
+            hunks.sort_by_key(|hunk| hunk.new_start);
+            let mut all_diffs = BString::default();
+            for hunk in hunks {
+                all_diffs.push_str(&hunk.diff_lines);
+            }
+            let patch = Patch::from_bytes(&all_diffs)?;
+            blob_contents =
+                apply(&blob_contents, &patch).context(format!("failed to apply {}", all_diffs))?;
+
+            // create a blob
+            let new_blob_oid = git_repository.blob(blob_contents.as_bytes())?;
+            // upsert into the builder
+            Ok(Some(Update::Upsert(new_blob_oid, filemode)))
+        } else if lfs::is_used(git_repository) && lfs::is_tracked(git_repository, rel_path) {
+            // commit the pointer file instead of the content, just like the diff shows it
+            let content =
+                std::fs::read(&full_path).context(format!("failed to read {:?}", &full_path))?;
+            let pointer = if lfs::Pointer::parse(&content).is_some() {
+                content
             } else {
-                // create a git blob from a file on disk
-                let blob_oid = git_repository
-                    .blob_path(&full_path)
-                    .context(format!("failed to create blob from path {:?}", &full_path))?;
-                builder.upsert(rel_path, blob_oid, filemode);
+                lfs::clean(git_repository, rel_path, &content)?
+            };
+            let blob_oid = git_repository.blob(&pointer)?;
+            Ok(Some(Update::Upsert(blob_oid, filemode)))
+        } else {
+            // create a git blob from a file on disk, converted like `git add` would
+            let content =
+                std::fs::read(&full_path).context(format!("failed to read {:?}", &full_path))?;
+            let blob_oid = git_repository
+                .blob(&filter::clean(git_repository, rel_path, &content)?)
+                .context(format!("failed to create blob from path {:?}", &full_path))?;
+            Ok(Some(Update::Upsert(blob_oid, filemode)))
+        }
+    } else if base_tree.get_path(rel_path).is_ok() {
+        // remove file from index if it exists in the base tree
+        Ok(Some(Update::Remove))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Return the id of a tree that is `base_tree` with only those hunks applied for which `is_selected` returns `true`.
+/// All hunks are taken from `diffs`, the changes from `base_tree` to `new_tree`.
+///
+/// Unlike [`hunks_onto_tree()`], the worktree isn't consulted, so this works for any pair of trees.
+/// Files whose hunks are all selected are taken from `new_tree` as they are, which includes additions,
+/// deletions, mode changes and binary files.
+pub fn tree_with_selected_hunks(
+    repo: &git2::Repository,
+    base_tree: &git2::Tree,
+    new_tree: &git2::Tree,
+    diffs: &DiffByPathMap,
+    mut is_selected: impl FnMut(&Path, &GitHunk) -> bool,
+) -> Result<git2::Oid> {
+    let mut builder = git2::build::TreeUpdateBuilder::new();
+    for (rel_path, file_diff) in diffs {
+        let mut hunks: Vec<_> = file_diff
+            .hunks
+            .iter()
+            .filter(|hunk| is_selected(rel_path, hunk))
+            .collect();
+        if hunks.is_empty() {
+            continue;
+        }
+        if hunks.len() == file_diff.hunks.len() {
+            match new_tree.get_path(rel_path) {
+                Ok(entry) => {
+                    builder.upsert(rel_path, entry.id(), file_mode(entry.filemode()));
+                }
+                Err(_) => {
+                    builder.remove(rel_path);
+                }
             }
-        } else if base_tree.get_path(rel_path).is_ok() {
-            // remove file from index if it exists in the base tree
-            builder.remove(rel_path);
+            continue;
+        }
+
+        if file_diff.binary {
+            bail!("cannot select parts of binary file {}", rel_path.display());
+        }
+        let base_entry = base_tree.get_path(rel_path).ok();
+        let base_contents = base_entry
+            .as_ref()
+            .map(|entry| {
+                repo.find_blob(entry.id())
+                    .map(|blob| blob.content().to_vec())
+            })
+            .transpose()
+            .context("failed to read blob")?
+            .unwrap_or_default();
+
+        hunks.sort_by_key(|hunk| hunk.old_start);
+        let mut all_diffs = BString::default();
+        for hunk in hunks {
+            all_diffs.push_str(&hunk.diff_lines);
         }
+        let patch = Patch::from_bytes(&all_diffs)?;
+        let contents = apply(&base_contents, &patch)
+            .with_context(|| format!("failed to apply hunks to {}", rel_path.display()))?;
+
+        let mode = new_tree
+            .get_path(rel_path)
+            .ok()
+            .or(base_entry)
+            .map_or(git2::FileMode::Blob, |entry| file_mode(entry.filemode()));
+        builder.upsert(rel_path, repo.blob(&contents)?, mode);
     }
 
-    // now write out the tree
-    let tree_oid = builder
-        .create_updated(ctx.repository(), base_tree)
-        .context("failed to write updated tree")?;
+    builder
+        .create_updated(repo, base_tree)
+        .context("failed to write updated tree")
+}
 
-    Ok(tree_oid)
+pub(crate) fn file_mode(raw: i32) -> git2::FileMode {
+    match raw {
+        0o100755 => git2::FileMode::BlobExecutable,
+        0o120000 => git2::FileMode::Link,
+        0o160000 => git2::FileMode::Commit,
+        0o040000 => git2::FileMode::Tree,
+        _ => git2::FileMode::Blob,
+    }
 }
 
 /// Just like [`diffy::apply()`], but on error it will attach hashes of the input `base_image` and `patch`.
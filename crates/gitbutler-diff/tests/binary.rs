@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use gitbutler_diff::{image_dimensions, mime_guess, ImageDimensions};
+
+#[test]
+fn mime_from_extension() {
+    assert_eq!(mime_guess(Path::new("assets/logo.PNG")), Some("image/png"));
+    assert_eq!(mime_guess(Path::new("font.woff2")), Some("font/woff2"));
+    assert_eq!(mime_guess(Path::new("Makefile")), None);
+}
+
+#[test]
+fn png_dimensions() {
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend_from_slice(&640u32.to_be_bytes());
+    png.extend_from_slice(&480u32.to_be_bytes());
+    assert_eq!(
+        image_dimensions(&png),
+        Some(ImageDimensions {
+            width: 640,
+            height: 480
+        })
+    );
+    assert_eq!(image_dimensions(&png[..20]), None, "truncated header");
+}
+
+#[test]
+fn gif_dimensions() {
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend_from_slice(&16u16.to_le_bytes());
+    gif.extend_from_slice(&9u16.to_le_bytes());
+    assert_eq!(
+        image_dimensions(&gif),
+        Some(ImageDimensions {
+            width: 16,
+            height: 9
+        })
+    );
+}
+
+#[test]
+fn jpeg_dimensions() {
+    let mut jpeg = vec![0xff, 0xd8];
+    // An APP0 segment that needs to be skipped.
+    jpeg.extend_from_slice(&[0xff, 0xe0, 0x00, 0x04, 0x00, 0x00]);
+    // The start of frame with precision, height and width.
+    jpeg.extend_from_slice(&[0xff, 0xc0, 0x00, 0x11, 0x08]);
+    jpeg.extend_from_slice(&200u16.to_be_bytes());
+    jpeg.extend_from_slice(&300u16.to_be_bytes());
+    assert_eq!(
+        image_dimensions(&jpeg),
+        Some(ImageDimensions {
+            width: 300,
+            height: 200
+        })
+    );
+}
+
+#[test]
+fn no_image() {
+    assert_eq!(image_dimensions(b"plain text"), None);
+}
@@ -0,0 +1,65 @@
+use std::{fs, path::Path};
+
+use gitbutler_command_context::{cancellation, CancellationToken};
+use gitbutler_diff::DiffOptions;
+use gitbutler_error::error::{AnyhowContextExt, Code};
+
+/// A repository with a commit of `file.txt`, which is changed in the worktree.
+fn repo_with_change() -> (tempfile::TempDir, git2::Repository, git2::Oid) {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = git2::Repository::init(dir.path()).unwrap();
+    fs::write(dir.path().join("file.txt"), "content\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("file.txt")).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("test", "test@example.com").unwrap();
+    let commit = repo
+        .commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])
+        .unwrap();
+    fs::write(dir.path().join("file.txt"), "changed\n").unwrap();
+    (dir, repo, commit)
+}
+
+#[test]
+fn cancelled_worktree_diffs_fail() {
+    let (_dir, repo, commit) = repo_with_change();
+    let token = CancellationToken::new();
+    token.cancel();
+
+    for threads in [1, 4] {
+        let options = DiffOptions {
+            threads,
+            ..Default::default()
+        };
+        let err = cancellation::scoped(&token, || {
+            gitbutler_diff::workdir_with_options(&repo, &commit, &options)
+        })
+        .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Cancelled),
+            "{threads} threads"
+        );
+    }
+
+    let diff = cancellation::scoped(&CancellationToken::new(), || {
+        gitbutler_diff::workdir(&repo, &commit)
+    })
+    .unwrap();
+    assert_eq!(diff.len(), 1, "tokens that aren't cancelled change nothing");
+}
+
+#[test]
+fn the_previous_token_is_restored() {
+    let token = CancellationToken::new();
+    token.cancel();
+    cancellation::scoped(&token, || {
+        assert!(cancellation::is_cancelled());
+        cancellation::scoped(&CancellationToken::new(), || {
+            assert!(cancellation::check().is_ok());
+        });
+        assert!(cancellation::is_cancelled());
+    });
+    assert!(!cancellation::is_cancelled());
+}
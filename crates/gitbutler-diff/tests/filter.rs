@@ -0,0 +1,181 @@
+use std::{
+    path::Path,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use gitbutler_diff::filter::{self, EolChange, LineEnding};
+
+/// A repository with a worktree, `core.autocrlf` set to `autocrlf` and `attributes` as `.gitattributes`.
+fn repo_with(autocrlf: &str, attributes: &str) -> (tempfile::TempDir, git2::Repository) {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = git2::Repository::init(dir.path()).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("core.autocrlf", autocrlf).unwrap();
+    config.set_str("core.eol", "lf").unwrap();
+    std::fs::write(dir.path().join(".gitattributes"), attributes).unwrap();
+    (dir, repo)
+}
+
+fn clean(repo: &git2::Repository, path: &str, content: &str) -> String {
+    String::from_utf8(filter::clean(repo, Path::new(path), content.as_bytes()).unwrap()).unwrap()
+}
+
+fn smudge(repo: &git2::Repository, path: &str, content: &str) -> String {
+    String::from_utf8(filter::smudge(repo, Path::new(path), content.as_bytes()).unwrap()).unwrap()
+}
+
+#[test]
+fn line_endings_of_content() {
+    assert_eq!(LineEnding::of(b"a\nb\n"), Some(LineEnding::Lf));
+    assert_eq!(LineEnding::of(b"a\r\nb\r\n"), Some(LineEnding::Crlf));
+    assert_eq!(LineEnding::of(b"a\r\nb\n"), Some(LineEnding::Mixed));
+    assert_eq!(LineEnding::of(b"\na"), Some(LineEnding::Lf));
+    assert_eq!(LineEnding::of(b"no line break"), None);
+}
+
+#[test]
+fn clean_matrix() {
+    // (core.autocrlf, .gitattributes, worktree content, expected blob content)
+    let cases = [
+        ("false", "", "a\r\nb\r\n", "a\r\nb\r\n"),
+        ("true", "", "a\r\nb\r\n", "a\nb\n"),
+        ("input", "", "a\r\nb\r\n", "a\nb\n"),
+        ("true", "", "a\nb\n", "a\nb\n"),
+        ("false", "*.txt text\n", "a\r\nb\r\n", "a\nb\n"),
+        ("false", "*.txt text=auto\n", "a\r\nb\n", "a\nb\n"),
+        ("false", "*.txt eol=crlf\n", "a\r\nb\r\n", "a\nb\n"),
+        ("true", "*.txt -text\n", "a\r\nb\r\n", "a\r\nb\r\n"),
+        ("true", "*.txt binary\n", "a\r\nb\r\n", "a\r\nb\r\n"),
+        ("true", "", "a\r\n\0b\r\n", "a\r\n\0b\r\n"),
+    ];
+    for (autocrlf, attributes, content, expected) in cases {
+        let (_dir, repo) = repo_with(autocrlf, attributes);
+        assert_eq!(
+            clean(&repo, "file.txt", content),
+            expected,
+            "autocrlf={autocrlf}, attributes={attributes:?}, content={content:?}"
+        );
+    }
+}
+
+#[test]
+fn smudge_matrix() {
+    // (core.autocrlf, .gitattributes, blob content, expected worktree content)
+    let cases = [
+        ("false", "", "a\nb\n", "a\nb\n"),
+        ("true", "", "a\nb\n", "a\r\nb\r\n"),
+        ("input", "", "a\nb\n", "a\nb\n"),
+        ("false", "*.txt eol=crlf\n", "a\nb\n", "a\r\nb\r\n"),
+        ("true", "*.txt eol=lf\n", "a\nb\n", "a\nb\n"),
+        ("true", "*.txt -text\n", "a\nb\n", "a\nb\n"),
+        ("true", "", "a\r\nb\n", "a\r\nb\n"),
+        ("true", "", "a\n\0b\n", "a\n\0b\n"),
+    ];
+    for (autocrlf, attributes, content, expected) in cases {
+        let (_dir, repo) = repo_with(autocrlf, attributes);
+        assert_eq!(
+            smudge(&repo, "file.txt", content),
+            expected,
+            "autocrlf={autocrlf}, attributes={attributes:?}, content={content:?}"
+        );
+    }
+}
+
+#[test]
+fn clean_keeps_crlf_of_auto_files_that_have_it_in_the_index() {
+    let (_dir, repo) = repo_with("true", "");
+    let mut index = repo.index().unwrap();
+    let entry = git2::IndexEntry {
+        ctime: git2::IndexTime::new(0, 0),
+        mtime: git2::IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: 0o100644,
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id: git2::Oid::zero(),
+        flags: 0,
+        flags_extended: 0,
+        path: b"file.txt".to_vec(),
+    };
+    index.add_frombuffer(&entry, b"a\r\n").unwrap();
+    index.write().unwrap();
+
+    assert_eq!(
+        clean(&repo, "file.txt", "a\r\nb\r\n"),
+        "a\r\nb\r\n",
+        "converting it would change every line"
+    );
+    assert_eq!(clean(&repo, "other.txt", "a\r\nb\r\n"), "a\nb\n");
+}
+
+#[test]
+fn filter_drivers_run_after_cleaning_and_before_smudging() {
+    let (_dir, repo) = repo_with("true", "*.txt filter=upper\n");
+    let mut config = repo.config().unwrap();
+    config.set_str("filter.upper.clean", "tr a-z A-Z").unwrap();
+    config.set_str("filter.upper.smudge", "tr A-Z a-z").unwrap();
+
+    assert_eq!(clean(&repo, "file.txt", "a\r\nb\r\n"), "A\nB\n");
+    assert_eq!(smudge(&repo, "file.txt", "A\nB\n"), "a\r\nb\r\n");
+}
+
+#[test]
+fn filter_drivers_without_commands() {
+    let (_dir, repo) = repo_with("false", "*.txt filter=missing\n");
+    assert_eq!(
+        clean(&repo, "file.txt", "a\n"),
+        "a\n",
+        "drivers that aren't configured are ignored"
+    );
+
+    repo.config()
+        .unwrap()
+        .set_bool("filter.missing.required", true)
+        .unwrap();
+    assert!(filter::clean(&repo, Path::new("file.txt"), b"a\n").is_err());
+}
+
+#[test]
+fn commands_that_run_for_too_long_are_killed() {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg("sleep 10");
+    let started = Instant::now();
+    let err = filter::pipe(cmd, b"input", Duration::from_millis(100)).unwrap_err();
+    assert_eq!(err.to_string(), "timed out after 100ms");
+    assert!(
+        started.elapsed() < Duration::from_secs(5),
+        "the output of processes it started isn't waited for"
+    );
+}
+
+#[test]
+fn eol_changes_list_files_that_would_be_checked_out_differently() {
+    let (dir, repo) = repo_with("false", "crlf.txt eol=crlf\n");
+    for (path, content) in [
+        ("crlf.txt", "a\nb\n"),
+        ("lf.txt", "a\nb\n"),
+        ("binary.txt", "a\n\0b\n"),
+    ] {
+        std::fs::write(dir.path().join(path), content).unwrap();
+    }
+    let mut index = repo.index().unwrap();
+    index
+        .add_all(["*.txt"], git2::IndexAddOption::DEFAULT, None)
+        .unwrap();
+    index.write().unwrap();
+
+    assert_eq!(
+        filter::eol_changes(&repo).unwrap(),
+        vec![EolChange {
+            path: "crlf.txt".into(),
+            current: LineEnding::Lf,
+            expected: LineEnding::Crlf,
+        }]
+    );
+
+    std::fs::write(dir.path().join("crlf.txt"), "a\r\nb\r\n").unwrap();
+    assert_eq!(filter::eol_changes(&repo).unwrap(), vec![]);
+}
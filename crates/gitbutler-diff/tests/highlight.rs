@@ -0,0 +1,35 @@
+use gitbutler_diff::{intra_line_highlights, LineHighlight};
+
+fn highlights(diff: &str, ignore_whitespace: bool) -> Vec<(u32, Vec<std::ops::Range<usize>>)> {
+    intra_line_highlights(diff.into(), ignore_whitespace)
+        .into_iter()
+        .map(|LineHighlight { line, ranges }| (line, ranges))
+        .collect()
+}
+
+#[test]
+fn changed_word_of_modified_line() {
+    assert_eq!(
+        highlights(
+            "@@ -1,2 +1,2 @@\n-let a = 1;\n+let b = 1;\n context\n",
+            false
+        ),
+        [(1, vec![4..5]), (2, vec![4..5])]
+    );
+}
+
+#[test]
+fn only_paired_lines_are_highlighted() {
+    assert_eq!(
+        highlights("@@ -1,1 +1,2 @@\n-one two\n+one three\n+four\n", false),
+        [(1, vec![4..7]), (2, vec![4..9])],
+        "the second added line has no counterpart"
+    );
+}
+
+#[test]
+fn whitespace_changes_can_be_ignored() {
+    let diff = "@@ -1,1 +1,1 @@\n-a  b\n+a b\n";
+    assert_eq!(highlights(diff, false), [(1, vec![1..3]), (2, vec![1..2])]);
+    assert!(highlights(diff, true).is_empty());
+}
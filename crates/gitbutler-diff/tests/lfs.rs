@@ -0,0 +1,66 @@
+use std::path::Path;
+
+use gitbutler_diff::lfs::Pointer;
+
+use crate::support::{in_memory_repo, tree};
+
+const POINTER: &str = "version https://git-lfs.github.com/spec/v1
+oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393
+size 12345
+";
+
+#[test]
+fn parse_pointer() {
+    assert_eq!(
+        Pointer::parse(POINTER.as_bytes()),
+        Some(Pointer {
+            oid: "sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393"
+                .to_owned(),
+            size: 12345,
+        })
+    );
+}
+
+#[test]
+fn parse_rejects_other_content() {
+    assert_eq!(Pointer::parse(b""), None);
+    assert_eq!(Pointer::parse(b"hello\nworld\n"), None);
+    assert_eq!(
+        Pointer::parse(b"version https://git-lfs.github.com/spec/v1\nsize 12\n"),
+        None,
+        "the oid is required"
+    );
+    assert_eq!(
+        Pointer::parse(b"version https://git-lfs.github.com/spec/v1\noid md5:abc\nsize 12\n"),
+        None,
+        "only sha256 is known"
+    );
+    let mut large = POINTER.to_owned();
+    large.push_str(&"x".repeat(2048));
+    assert_eq!(Pointer::parse(large.as_bytes()), None);
+}
+
+#[test]
+fn tree_diffs_describe_pointers() {
+    let repo = in_memory_repo();
+    let old_tree = tree(&repo, &[("large.bin", POINTER)]);
+    let new_pointer = POINTER.replace("size 12345", "size 54321");
+    let new_tree = tree(&repo, &[("large.bin", new_pointer.as_str())]);
+
+    let diffs = gitbutler_diff::trees(&repo, &old_tree, &new_tree).unwrap();
+    let file = &diffs[Path::new("large.bin")];
+    assert_eq!(file.old_lfs_pointer.as_ref().unwrap().size, 12345);
+    assert_eq!(file.new_lfs_pointer.as_ref().unwrap().size, 54321);
+}
+
+#[test]
+fn tree_diffs_of_other_files_have_no_pointers() {
+    let repo = in_memory_repo();
+    let old_tree = tree(&repo, &[("file.txt", "one\n")]);
+    let new_tree = tree(&repo, &[("file.txt", "two\n")]);
+
+    let diffs = gitbutler_diff::trees(&repo, &old_tree, &new_tree).unwrap();
+    let file = &diffs[Path::new("file.txt")];
+    assert_eq!(file.old_lfs_pointer, None);
+    assert_eq!(file.new_lfs_pointer, None);
+}
@@ -0,0 +1,51 @@
+use gitbutler_diff::{set_low_memory, DiffOptions, WorkdirCache};
+
+/// Return a repository with an empty commit, and whose worktree has enough changes for their diff to be spilled
+/// in low-memory mode.
+fn repo_with_large_changes() -> (tempfile::TempDir, git2::Repository, git2::Oid) {
+    let tmp = tempfile::tempdir().unwrap();
+    let repo = git2::Repository::init(tmp.path()).unwrap();
+    let signature = git2::Signature::now("author", "author@example.com").unwrap();
+    let tree = repo
+        .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+        .unwrap();
+    let commit_id = repo
+        .commit(Some("HEAD"), &signature, &signature, "empty", &tree, &[])
+        .unwrap();
+    let content: String = (0..2_000)
+        .map(|line| format!("line {line:0>40}\n"))
+        .collect();
+    for file in 0..60 {
+        std::fs::write(tmp.path().join(format!("file-{file}.txt")), &content).unwrap();
+    }
+    (tmp, repo, commit_id)
+}
+
+#[test]
+fn spilled_diffs_are_read_back_unchanged() {
+    let (tmp, repo, commit_id) = repo_with_large_changes();
+    let options = DiffOptions::default();
+    let expected = gitbutler_diff::workdir_with_options(&repo, &commit_id, &options).unwrap();
+
+    set_low_memory(true);
+    let mut cache = WorkdirCache::default();
+    let first = cache.workdir(&repo, commit_id, &options);
+    std::fs::write(tmp.path().join("file-0.txt"), "changed\n").unwrap();
+    cache.invalidate(["file-0.txt".into()]);
+    let second = cache.workdir(&repo, commit_id, &options);
+    set_low_memory(false);
+
+    assert_eq!(first.unwrap(), expected);
+    let second = second.unwrap();
+    assert_eq!(second.len(), expected.len());
+    for (path, diff) in &expected {
+        if path.as_os_str() != "file-0.txt" {
+            assert_eq!(second[path], *diff, "{} is kept as it was", path.display());
+        }
+    }
+    assert_ne!(
+        second[std::path::Path::new("file-0.txt")],
+        expected[std::path::Path::new("file-0.txt")],
+        "changed files are diffed again"
+    );
+}
@@ -1 +1,16 @@
+pub mod binary;
+pub mod cancellation;
+pub mod filter;
+pub mod highlight;
 pub mod hunk;
+pub mod lfs;
+pub mod memory;
+pub mod oid_cache;
+pub mod options;
+pub mod path_normalization;
+pub mod rename;
+pub mod selection;
+pub mod semantic;
+pub mod submodule;
+pub mod support;
+pub mod write;
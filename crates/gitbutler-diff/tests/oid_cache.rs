@@ -0,0 +1,114 @@
+use std::{fs, path::Path};
+
+use gitbutler_diff::DiffOptions;
+
+fn options() -> DiffOptions {
+    DiffOptions {
+        persistent_cache: true,
+        ..Default::default()
+    }
+}
+
+/// A repository with a commit of `file.txt`, which is also in the index.
+fn repo_with_commit() -> (tempfile::TempDir, git2::Repository, git2::Oid) {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = git2::Repository::init(dir.path()).unwrap();
+    fs::write(dir.path().join("file.txt"), "content\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("file.txt")).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("test", "test@example.com").unwrap();
+    let commit = repo
+        .commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])
+        .unwrap();
+    (dir, repo, commit)
+}
+
+fn changed_paths(repo: &git2::Repository, commit: git2::Oid) -> Vec<String> {
+    let mut paths: Vec<_> = gitbutler_diff::workdir_with_options(repo, &commit, &options())
+        .unwrap()
+        .into_keys()
+        .map(|path| path.display().to_string())
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn changes_are_seen_across_diffs() {
+    let (dir, repo, commit) = repo_with_commit();
+    assert!(changed_paths(&repo, commit).is_empty());
+
+    fs::write(dir.path().join("file.txt"), "changed\n").unwrap();
+    assert_eq!(changed_paths(&repo, commit), ["file.txt"]);
+    assert!(repo.path().join("gitbutler/worktree-oids.json").is_file());
+
+    fs::write(dir.path().join("file.txt"), "changed again\n").unwrap();
+    fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+    let diff = gitbutler_diff::workdir_with_options(&repo, &commit, &options()).unwrap();
+    assert_eq!(
+        diff[Path::new("file.txt")].hunks[0].diff_lines,
+        "@@ -1,1 +1,1 @@\n-content\n+changed again\n"
+    );
+    assert!(diff.contains_key(Path::new("new.txt")));
+
+    fs::write(dir.path().join("file.txt"), "content\n").unwrap();
+    fs::remove_file(dir.path().join("new.txt")).unwrap();
+    assert!(changed_paths(&repo, commit).is_empty());
+}
+
+#[test]
+fn unreadable_cache_is_ignored() {
+    let (dir, repo, commit) = repo_with_commit();
+    fs::create_dir_all(repo.path().join("gitbutler")).unwrap();
+    fs::write(repo.path().join("gitbutler/worktree-oids.json"), "garbage").unwrap();
+
+    fs::write(dir.path().join("file.txt"), "changed\n").unwrap();
+    assert_eq!(changed_paths(&repo, commit), ["file.txt"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn only_files_reported_by_the_fsmonitor_hook_are_looked_at() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (dir, repo, commit) = repo_with_commit();
+    let reported = repo.path().join("reported");
+    let hook = repo.path().join("fsmonitor-hook");
+    fs::write(
+        &hook,
+        format!(
+            "#!/bin/sh\nprintf 'token\\0'\nif [ \"$2\" = builtin:fake ]; then printf '/\\0'; exit 0; fi\ntr '\\n' '\\0' < '{}'\n",
+            reported.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(&reported, "").unwrap();
+    repo.config()
+        .unwrap()
+        .set_str("core.fsmonitor", hook.to_str().unwrap())
+        .unwrap();
+
+    assert!(
+        changed_paths(&repo, commit).is_empty(),
+        "the worktree is scanned as the monitor can't tell what changed yet"
+    );
+
+    fs::write(dir.path().join("file.txt"), "changed\n").unwrap();
+    assert!(
+        changed_paths(&repo, commit).is_empty(),
+        "changes the monitor doesn't report aren't looked for"
+    );
+
+    fs::write(&reported, "file.txt\n").unwrap();
+    assert_eq!(changed_paths(&repo, commit), ["file.txt"]);
+
+    fs::write(&reported, "").unwrap();
+    assert_eq!(
+        changed_paths(&repo, commit),
+        ["file.txt"],
+        "files that differ from the index are remembered"
+    );
+}
@@ -0,0 +1,39 @@
+use gitbutler_diff::DiffOptions;
+
+const WHITESPACE_ONLY: &str = "@@ -1,1 +1,1 @@\n-a  b\n+a b\n";
+const BLANK_LINE_ONLY: &str = "@@ -1,1 +1,2 @@\n a\n+\n";
+
+#[test]
+fn nothing_is_ignored_by_default() {
+    let options = DiffOptions::default();
+    assert!(!options.ignores(WHITESPACE_ONLY.into()));
+    assert!(!options.ignores(BLANK_LINE_ONLY.into()));
+}
+
+#[test]
+fn ignore_whitespace() {
+    let options = DiffOptions {
+        ignore_whitespace: true,
+        ..Default::default()
+    };
+    assert!(options.ignores(WHITESPACE_ONLY.into()));
+    assert!(
+        !options.ignores(BLANK_LINE_ONLY.into()),
+        "added lines aren't whitespace changes"
+    );
+    assert!(!options.ignores("@@ -1,1 +1,1 @@\n-a b\n+a c\n".into()));
+}
+
+#[test]
+fn ignore_blank_lines() {
+    let options = DiffOptions {
+        ignore_blank_lines: true,
+        ..Default::default()
+    };
+    assert!(options.ignores(BLANK_LINE_ONLY.into()));
+    assert!(!options.ignores(WHITESPACE_ONLY.into()));
+    assert!(
+        !options.ignores("".into()),
+        "hunks without changes, like those of empty files, are kept"
+    );
+}
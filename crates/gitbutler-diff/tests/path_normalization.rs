@@ -0,0 +1,114 @@
+use std::path::Path;
+
+use gitbutler_diff::{PathChange, PathNormalization};
+
+/// A repository with a worktree on a filesystem that ignores case, as Git would configure it.
+fn case_insensitive_repo() -> (tempfile::TempDir, git2::Repository) {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = git2::Repository::init(dir.path()).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_bool("core.ignorecase", true).unwrap();
+    config.set_bool("core.precomposeunicode", false).unwrap();
+    (dir, repo)
+}
+
+fn tree<'repo>(repo: &'repo git2::Repository, path: &str, content: &str) -> git2::Tree<'repo> {
+    let mut builder = git2::build::TreeUpdateBuilder::new();
+    let blob = repo.blob(content.as_bytes()).unwrap();
+    builder.upsert(path, blob, git2::FileMode::Blob);
+    let empty = repo
+        .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+        .unwrap();
+    repo.find_tree(builder.create_updated(repo, &empty).unwrap())
+        .unwrap()
+}
+
+#[test]
+fn spellings_of_the_same_path() {
+    let case_insensitive = PathNormalization {
+        ignore_case: true,
+        precompose_unicode: false,
+    };
+    assert!(case_insensitive.same(Path::new("Dir/README.md"), Path::new("dir/readme.md")));
+    assert!(!case_insensitive.same(Path::new("café"), Path::new("cafe\u{301}")));
+
+    let decomposing = PathNormalization {
+        ignore_case: false,
+        precompose_unicode: true,
+    };
+    assert!(decomposing.same(Path::new("café"), Path::new("cafe\u{301}")));
+    assert!(!decomposing.same(Path::new("README.md"), Path::new("readme.md")));
+    assert_eq!(decomposing.key(Path::new("cafe\u{301}")), Path::new("café"));
+
+    let exact = PathNormalization::default();
+    assert!(!exact.is_enabled());
+    assert!(!exact.same(Path::new("README.md"), Path::new("readme.md")));
+}
+
+#[test]
+fn respelled_files_are_renamed_instead_of_deleted_and_added() {
+    let (_dir, repo) = case_insensitive_repo();
+    let old_tree = tree(&repo, "Dir/Readme.md", "first\n");
+    let new_tree = tree(&repo, "dir/README.md", "first\nsecond\n");
+
+    let diff = gitbutler_diff::trees(&repo, &old_tree, &new_tree).unwrap();
+    assert_eq!(diff.len(), 1, "{diff:#?}");
+    let file = &diff[Path::new("dir/README.md")];
+    assert_eq!(file.hunks.len(), 1);
+    assert_eq!(
+        file.hunks[0].diff_lines,
+        "@@ -1,1 +1,2 @@\n first\n+second\n"
+    );
+    assert_eq!(
+        file.path_change,
+        Some(PathChange::Renamed {
+            old_path: "Dir/Readme.md".into(),
+            new_path: "dir/README.md".into(),
+            similarity: 50,
+        })
+    );
+}
+
+#[test]
+fn files_that_were_only_respelled_are_unchanged() {
+    let (_dir, repo) = case_insensitive_repo();
+    let old_tree = tree(&repo, "Readme.md", "content\n");
+    let new_tree = tree(&repo, "README.md", "content\n");
+
+    assert!(gitbutler_diff::trees(&repo, &old_tree, &new_tree)
+        .unwrap()
+        .is_empty());
+
+    repo.config()
+        .unwrap()
+        .set_bool("core.ignorecase", false)
+        .unwrap();
+    assert_eq!(
+        gitbutler_diff::trees(&repo, &old_tree, &new_tree)
+            .unwrap()
+            .len(),
+        2,
+        "filesystems that tell case apart see two files"
+    );
+}
+
+#[test]
+fn spelling_in_tree() {
+    let (_dir, repo) = case_insensitive_repo();
+    let tree = tree(&repo, "Dir/Readme.md", "content\n");
+    let normalization = PathNormalization::from_repo(&repo);
+
+    assert_eq!(
+        normalization.spelling_in(&repo, &tree, Path::new("dir/README.md")),
+        Some("Dir/Readme.md".into())
+    );
+    assert_eq!(
+        normalization.spelling_in(&repo, &tree, Path::new("Dir/Readme.md")),
+        None,
+        "the path is spelled the same way"
+    );
+    assert_eq!(
+        normalization.spelling_in(&repo, &tree, Path::new("dir/other.md")),
+        None
+    );
+}
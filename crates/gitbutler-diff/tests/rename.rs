@@ -0,0 +1,126 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use gitbutler_diff::{
+    renames, similarity, ChangeType, DiffOptions, GitHunk, PathChange, DEFAULT_RENAME_THRESHOLD,
+};
+
+use crate::support::{in_memory_repo, tree};
+
+const CONTENT: &str = "one\ntwo\nthree\nfour\n";
+
+fn hunk(change_type: ChangeType, diff_lines: &str) -> GitHunk {
+    GitHunk {
+        old_start: 0,
+        old_lines: 0,
+        new_start: 0,
+        new_lines: 0,
+        diff_lines: diff_lines.to_owned().into(),
+        binary: false,
+        change_type,
+        highlights: None,
+    }
+}
+
+#[test]
+fn similarity_of_lines() {
+    assert_eq!(similarity(CONTENT.as_bytes(), CONTENT.as_bytes()), 100);
+    assert_eq!(similarity(CONTENT.as_bytes(), b"one\ntwo\nthree\n"), 75);
+    assert_eq!(similarity(CONTENT.as_bytes(), b"five\nsix\n"), 0);
+    assert_eq!(similarity(b"", b""), 100);
+}
+
+#[test]
+fn renames_from_deletions_and_additions() {
+    let diffs: HashMap<PathBuf, Vec<GitHunk>> = [
+        (
+            PathBuf::from("old.txt"),
+            vec![hunk(
+                ChangeType::Deleted,
+                "@@ -1,4 +0,0 @@\n-one\n-two\n-three\n-four\n",
+            )],
+        ),
+        (
+            PathBuf::from("new.txt"),
+            vec![hunk(
+                ChangeType::Added,
+                "@@ -0,0 +1,4 @@\n+one\n+two\n+three\n+4\n",
+            )],
+        ),
+        (
+            PathBuf::from("unrelated.txt"),
+            vec![hunk(ChangeType::Added, "@@ -0,0 +1,1 @@\n+other\n")],
+        ),
+    ]
+    .into();
+
+    assert_eq!(
+        renames(&diffs, DEFAULT_RENAME_THRESHOLD),
+        vec![(PathBuf::from("old.txt"), PathBuf::from("new.txt"))]
+    );
+    assert!(renames(&diffs, 80).is_empty(), "only 75% are the same");
+}
+
+#[test]
+fn renames_are_off_by_default() {
+    let repo = in_memory_repo();
+    let old_tree = tree(&repo, &[("old.txt", CONTENT)]);
+    let new_tree = tree(&repo, &[("new.txt", CONTENT)]);
+
+    let diffs = gitbutler_diff::trees(&repo, &old_tree, &new_tree).unwrap();
+    assert_eq!(diffs.len(), 2, "a deletion and an addition");
+    assert!(diffs.values().all(|file| file.path_change.is_none()));
+}
+
+#[test]
+fn renamed_file_with_changes() {
+    let repo = in_memory_repo();
+    let old_tree = tree(&repo, &[("old.txt", CONTENT)]);
+    let new_tree = tree(&repo, &[("new.txt", "one\ntwo\nthree\n4\n")]);
+
+    let options = DiffOptions {
+        rename_threshold: Some(DEFAULT_RENAME_THRESHOLD),
+        ..Default::default()
+    };
+    let diffs = gitbutler_diff::trees_with_options(&repo, &old_tree, &new_tree, &options).unwrap();
+    assert_eq!(diffs.len(), 1);
+    let file = &diffs[&PathBuf::from("new.txt")];
+    assert_eq!(
+        file.path_change,
+        Some(PathChange::Renamed {
+            old_path: "old.txt".into(),
+            new_path: "new.txt".into(),
+            similarity: 75,
+        })
+    );
+    assert_eq!(file.hunks.len(), 1);
+    assert_eq!(
+        file.hunks[0].diff_lines.to_string(),
+        "@@ -1,4 +1,4 @@\n one\n two\n three\n-four\n+4\n"
+    );
+}
+
+#[test]
+fn renamed_file_without_changes() {
+    let repo = in_memory_repo();
+    let old_tree = tree(&repo, &[("old.txt", CONTENT)]);
+    let new_tree = tree(&repo, &[("new.txt", CONTENT)]);
+
+    let options = DiffOptions {
+        rename_threshold: Some(DEFAULT_RENAME_THRESHOLD),
+        ..Default::default()
+    };
+    let diffs = gitbutler_diff::trees_with_options(&repo, &old_tree, &new_tree, &options).unwrap();
+    let file = &diffs[&PathBuf::from("new.txt")];
+    assert!(
+        file.hunks.is_empty(),
+        "there is nothing to show but the move"
+    );
+    assert_eq!(
+        file.path_change,
+        Some(PathChange::Renamed {
+            old_path: "old.txt".into(),
+            new_path: "new.txt".into(),
+            similarity: 100,
+        })
+    );
+}
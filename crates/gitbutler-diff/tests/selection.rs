@@ -0,0 +1,72 @@
+use gitbutler_diff::{ChangeType, GitHunk, RangeSet};
+
+fn hunk(diff: &str) -> GitHunk {
+    GitHunk {
+        old_start: 1,
+        old_lines: 3,
+        new_start: 1,
+        new_lines: 3,
+        diff_lines: diff.to_owned().into(),
+        binary: false,
+        change_type: ChangeType::Modified,
+        highlights: None,
+    }
+}
+
+const DIFF: &str = "@@ -1,3 +1,3 @@ fn main\n a\n-b\n-c\n+B\n+C\n";
+
+#[test]
+fn range_set_merges_overlapping_and_adjacent_ranges() {
+    let set: RangeSet = [5..=6, 1..=2, 3..=3, 9..=10, 10..=12].into_iter().collect();
+    assert_eq!(set.ranges(), [1..=3, 5..=6, 9..=12]);
+    assert!(set.contains(11));
+    assert!(!set.contains(4));
+}
+
+#[test]
+fn range_set_serializes_as_pairs() {
+    let set: RangeSet = [1..=2, 4..=4].into_iter().collect();
+    let json = serde_json::to_string(&set).unwrap();
+    assert_eq!(json, "[[1,2],[4,4]]");
+    assert_eq!(serde_json::from_str::<RangeSet>(&json).unwrap(), set);
+}
+
+#[test]
+fn select_all_lines_keeps_the_hunk() {
+    let hunk = hunk(DIFF);
+    let selected = hunk.select_lines(&[1..=5].into_iter().collect()).unwrap();
+    assert_eq!(selected, hunk);
+}
+
+#[test]
+fn unselected_deletions_become_context_and_unselected_additions_are_dropped() {
+    let selected = hunk(DIFF)
+        .select_lines(&[2..=2, 4..=4].into_iter().collect())
+        .unwrap();
+    assert_eq!(
+        selected.diff_lines.to_string(),
+        "@@ -1,3 +1,3 @@ fn main\n a\n-b\n c\n+B\n"
+    );
+    assert_eq!((selected.old_lines, selected.new_lines), (3, 3));
+}
+
+#[test]
+fn selecting_only_additions_changes_line_counts() {
+    let selected = hunk(DIFF)
+        .select_lines(&[4..=5].into_iter().collect())
+        .unwrap();
+    assert_eq!(
+        selected.diff_lines.to_string(),
+        "@@ -1,3 +1,5 @@ fn main\n a\n b\n c\n+B\n+C\n"
+    );
+    assert_eq!((selected.old_lines, selected.new_lines), (3, 5));
+}
+
+#[test]
+fn selecting_no_changes_yields_nothing() {
+    assert_eq!(
+        hunk(DIFF).select_lines(&[1..=1].into_iter().collect()),
+        None
+    );
+    assert_eq!(hunk(DIFF).select_lines(&RangeSet::default()), None);
+}
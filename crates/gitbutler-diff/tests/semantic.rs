@@ -0,0 +1,75 @@
+use std::{collections::BTreeSet, path::PathBuf};
+
+use gitbutler_diff::{ChangeType, DiffOptions, GitHunk};
+
+use crate::support::{in_memory_repo, tree};
+
+const OLD: &str = "fn a() {\n    1\n}\n\nfn b() {\n    2\n}\n";
+const NEW: &str = "fn a() {\n    one\n}\n\nfn b() {\n    two\n}\n";
+const FIRST: &str = "@@ -1,3 +1,3 @@\n fn a() {\n-    1\n+    one\n }\n";
+const SECOND: &str = "@@ -5,3 +5,3 @@\n fn b() {\n-    2\n+    two\n }\n";
+
+/// Return the hunks of the file at `path` changing from [`OLD`] to [`NEW`], diffed with semantic hunks.
+fn semantic_diff(path: &str) -> Vec<String> {
+    let repo = in_memory_repo();
+    let old_tree = tree(&repo, &[(path, OLD)]);
+    let new_tree = tree(&repo, &[(path, NEW)]);
+    let options = DiffOptions {
+        semantic_hunks: true,
+        ..Default::default()
+    };
+    let mut diffs =
+        gitbutler_diff::trees_with_options(&repo, &old_tree, &new_tree, &options).unwrap();
+    diffs
+        .remove(&PathBuf::from(path))
+        .unwrap()
+        .hunks
+        .into_iter()
+        .map(|hunk| hunk.diff_lines.to_string())
+        .collect()
+}
+
+#[test]
+fn hunks_are_split_at_unchanged_lines_next_to_boundaries() {
+    let hunk = GitHunk {
+        old_start: 1,
+        old_lines: 7,
+        new_start: 1,
+        new_lines: 7,
+        diff_lines:
+            "@@ -1,7 +1,7 @@\n fn a() {\n-    1\n+    one\n }\n \n fn b() {\n-    2\n+    two\n }\n"
+                .to_owned()
+                .into(),
+        binary: false,
+        change_type: ChangeType::Modified,
+        highlights: None,
+    };
+
+    let parts = hunk.split_at_boundaries(&BTreeSet::from([1, 5]));
+    assert_eq!(parts.len(), 2);
+    assert_eq!(
+        (parts[0].old_start, parts[0].old_lines),
+        (1, 3),
+        "the blank line between the functions is dropped"
+    );
+    assert_eq!(parts[0].diff_lines.to_string(), FIRST);
+    assert_eq!((parts[1].new_start, parts[1].new_lines), (5, 3));
+    assert_eq!(parts[1].diff_lines.to_string(), SECOND);
+
+    assert_eq!(
+        hunk.split_at_boundaries(&BTreeSet::from([2])),
+        [hunk.clone()],
+        "boundaries within changes don't split"
+    );
+}
+
+#[test]
+fn files_of_unknown_languages_are_not_split() {
+    assert_eq!(semantic_diff("file.txt").len(), 1);
+}
+
+#[test]
+#[cfg(feature = "semantic-hunks")]
+fn functions_get_hunks_of_their_own() {
+    assert_eq!(semantic_diff("file.rs"), [FIRST, SECOND]);
+}
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use gitbutler_diff::{submodule_changes, SubmoduleChange};
+
+use crate::support::{in_memory_repo, tree_with_submodules};
+
+/// The files next to the submodules in each tree.
+const FILES: &[(&str, &str)] = &[("file.txt", "content\n")];
+
+fn oid(hex: char) -> git2::Oid {
+    git2::Oid::from_str(&hex.to_string().repeat(40)).unwrap()
+}
+
+#[test]
+fn added_moved_and_removed_submodules() {
+    let repo = in_memory_repo();
+    let old_tree =
+        tree_with_submodules(&repo, FILES, &[("moved", oid('1')), ("removed", oid('2'))]);
+    let new_tree = tree_with_submodules(&repo, FILES, &[("added", oid('3')), ("moved", oid('4'))]);
+
+    let changes = submodule_changes(&repo, &old_tree, &new_tree).unwrap();
+    assert_eq!(
+        changes,
+        vec![
+            SubmoduleChange {
+                path: PathBuf::from("added"),
+                old_id: None,
+                new_id: Some(oid('3')),
+            },
+            SubmoduleChange {
+                path: PathBuf::from("moved"),
+                old_id: Some(oid('1')),
+                new_id: Some(oid('4')),
+            },
+            SubmoduleChange {
+                path: PathBuf::from("removed"),
+                old_id: Some(oid('2')),
+                new_id: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn unchanged_submodules_and_files_are_ignored() {
+    let repo = in_memory_repo();
+    let old_tree = tree_with_submodules(&repo, FILES, &[("submodule", oid('1'))]);
+    let mut builder = repo.treebuilder(Some(&old_tree)).unwrap();
+    let blob = repo.blob(b"other content\n").unwrap();
+    builder.insert("file.txt", blob, 0o100644).unwrap();
+    let new_tree = repo.find_tree(builder.write().unwrap()).unwrap();
+
+    assert!(submodule_changes(&repo, &old_tree, &new_tree)
+        .unwrap()
+        .is_empty());
+}
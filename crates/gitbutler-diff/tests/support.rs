@@ -0,0 +1,30 @@
+//! Fixtures shared by the tests of this crate.
+
+/// A repository whose objects are only kept in memory.
+pub fn in_memory_repo() -> git2::Repository {
+    let odb = git2::Odb::new().unwrap();
+    odb.add_new_mempack_backend(1).unwrap();
+    git2::Repository::from_odb(odb).unwrap()
+}
+
+/// A tree with the files in `files`, given as path and content.
+pub fn tree<'repo>(repo: &'repo git2::Repository, files: &[(&str, &str)]) -> git2::Tree<'repo> {
+    tree_with_submodules(repo, files, &[])
+}
+
+/// Like [`tree()`], but also with the submodules in `submodules`, given as path and commit.
+pub fn tree_with_submodules<'repo>(
+    repo: &'repo git2::Repository,
+    files: &[(&str, &str)],
+    submodules: &[(&str, git2::Oid)],
+) -> git2::Tree<'repo> {
+    let mut builder = repo.treebuilder(None).unwrap();
+    for (path, content) in files {
+        let blob = repo.blob(content.as_bytes()).unwrap();
+        builder.insert(path, blob, 0o100644).unwrap();
+    }
+    for (path, commit_id) in submodules {
+        builder.insert(path, *commit_id, 0o160000).unwrap();
+    }
+    repo.find_tree(builder.write().unwrap()).unwrap()
+}
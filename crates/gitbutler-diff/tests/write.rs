@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use gitbutler_diff::write::tree_with_selected_hunks;
+
+use crate::support::{in_memory_repo, tree};
+
+fn contents(repo: &git2::Repository, tree: &git2::Tree, path: &str) -> Option<String> {
+    let entry = tree.get_path(Path::new(path)).ok()?;
+    let blob = repo.find_blob(entry.id()).unwrap();
+    Some(String::from_utf8(blob.content().to_vec()).unwrap())
+}
+
+#[test]
+fn only_selected_hunks_are_written_onto_the_base_tree() {
+    let repo = in_memory_repo();
+    let lines = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    let changed_lines = lines.replace("1\n", "one\n").replace("10\n", "ten\n");
+    let base_tree = tree(&repo, &[("lines.txt", lines), ("removed.txt", "gone\n")]);
+    let new_tree = tree(
+        &repo,
+        &[
+            ("lines.txt", changed_lines.as_str()),
+            ("added.txt", "new\n"),
+        ],
+    );
+    let diffs = gitbutler_diff::trees(&repo, &base_tree, &new_tree).unwrap();
+    assert_eq!(diffs[Path::new("lines.txt")].hunks.len(), 2);
+
+    let tree_id = tree_with_selected_hunks(&repo, &base_tree, &new_tree, &diffs, |path, hunk| {
+        path == Path::new("added.txt") || (path == Path::new("lines.txt") && hunk.old_start == 1)
+    })
+    .unwrap();
+
+    let tree = repo.find_tree(tree_id).unwrap();
+    assert_eq!(
+        contents(&repo, &tree, "lines.txt").as_deref(),
+        Some(lines.replacen("1\n", "one\n", 1).as_str()),
+        "only the first hunk was applied"
+    );
+    assert_eq!(
+        contents(&repo, &tree, "added.txt").as_deref(),
+        Some("new\n"),
+        "fully selected files are taken from the new tree"
+    );
+    assert_eq!(
+        contents(&repo, &tree, "removed.txt").as_deref(),
+        Some("gone\n"),
+        "unselected deletions aren't applied"
+    );
+}
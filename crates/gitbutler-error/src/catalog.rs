@@ -0,0 +1,122 @@
+//! A catalog of user-facing messages that originate in the backend.
+//!
+//! Each message is identified by a stable [`MessageId`] which is passed along with errors, so that consumers
+//! like the *frontend* can show a localized version instead of the English text.
+//!
+//! ```rust
+//! # use gitbutler_error::catalog::{message, MessageId};
+//! assert_eq!(message(MessageId::MalformedProjectId, "en"), "Malformed project id");
+//! assert_eq!(
+//!     message(MessageId::MalformedProjectId, "xx-YY"),
+//!     "Malformed project id",
+//!     "unknown locales fall back to English"
+//! );
+//! ```
+use std::fmt::{Display, Formatter};
+
+/// A stable identifier for a user-facing message.
+///
+/// ### Important
+///
+/// The string representation of each variant must never change once it was released, as translations
+/// are keyed by it. Remove variants when no longer in use.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MessageId {
+    /// Shown when nothing more specific is known.
+    SomethingWentWrong,
+    /// A directory was expected to be a Git repository, but wasn't.
+    NotAGitRepository,
+    /// A project id could not be parsed.
+    MalformedProjectId,
+    /// A bare repository was to be added as project, which has no worktree.
+    BareRepository,
+    /// A worktree was to be added as project while another worktree of its repository is one already.
+    WorktreeOfExistingProject,
+    /// None of the ways to authenticate with SSH remotes that are configured for a project can be used.
+    NoSshAuth,
+    /// A remote rejected all SSH keys it was offered.
+    SshKeyRejected,
+}
+
+impl MessageId {
+    /// All known message ids, for iterating the whole catalog.
+    pub const ALL: &'static [MessageId] = &[
+        MessageId::SomethingWentWrong,
+        MessageId::NotAGitRepository,
+        MessageId::MalformedProjectId,
+        MessageId::BareRepository,
+        MessageId::WorktreeOfExistingProject,
+        MessageId::NoSshAuth,
+        MessageId::SshKeyRejected,
+    ];
+
+    /// Return the stable string representation of this id.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageId::SomethingWentWrong => "messages.something_went_wrong",
+            MessageId::NotAGitRepository => "messages.projects.not_a_git_repository",
+            MessageId::MalformedProjectId => "messages.projects.malformed_id",
+            MessageId::BareRepository => "messages.projects.bare_repository",
+            MessageId::WorktreeOfExistingProject => {
+                "messages.projects.worktree_of_existing_project"
+            }
+            MessageId::NoSshAuth => "messages.projects.git.auth.no_ssh_auth",
+            MessageId::SshKeyRejected => "messages.projects.git.auth.ssh_key_rejected",
+        }
+    }
+}
+
+impl Display for MessageId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The locale all messages are available in, used if a message isn't translated into the requested locale.
+pub const FALLBACK_LOCALE: &str = "en";
+
+fn english(id: MessageId) -> &'static str {
+    match id {
+        MessageId::SomethingWentWrong => "Something went wrong",
+        MessageId::NotAGitRepository => "must be a Git repository",
+        MessageId::MalformedProjectId => "Malformed project id",
+        MessageId::BareRepository => {
+            "Bare repositories have no worktree, add one of its worktrees instead"
+        }
+        MessageId::WorktreeOfExistingProject => {
+            "Another worktree of this repository was added already"
+        }
+        MessageId::NoSshAuth => {
+            "There is no way to authenticate over SSH, like a key file or a running SSH agent"
+        }
+        MessageId::SshKeyRejected => {
+            "The remote rejected the SSH keys, which may not be added to your account"
+        }
+    }
+}
+
+/// A translation of a set of messages into a language.
+type Translation = fn(MessageId) -> Option<&'static str>;
+
+/// Translations by primary language subtag, like `de` in `de-CH`. English isn't listed as it's the fallback.
+const TRANSLATIONS: &[(&str, Translation)] = &[];
+
+/// Return the message identified by `id` in the language of `locale`, a BCP-47 language tag like `en-US`.
+///
+/// If there is no translation for `locale`, English is returned.
+pub fn message(id: MessageId, locale: &str) -> &'static str {
+    let language = locale.split(['-', '_']).next().unwrap_or(FALLBACK_LOCALE);
+    TRANSLATIONS
+        .iter()
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(language))
+        .and_then(|(_, translate)| translate(id))
+        .unwrap_or_else(|| english(id))
+}
+
+/// Return all messages in the language of `locale` along with their ids, falling back to English
+/// for untranslated ones.
+pub fn messages(locale: &str) -> impl Iterator<Item = (MessageId, &'static str)> + '_ {
+    MessageId::ALL
+        .iter()
+        .map(move |id| (*id, message(*id, locale)))
+}
@@ -113,6 +113,11 @@
 //! By default, `thiserror` instances have no context.
 use std::{borrow::Cow, fmt::Debug};
 
+use crate::{
+    catalog::{self, MessageId},
+    registry::ErrorId,
+};
+
 /// A unique code that consumers of the API may rely on to identify errors.
 ///
 /// ### Important
@@ -121,7 +126,7 @@ use std::{borrow::Cow, fmt::Debug};
 /// Remove variants when no longer in use.
 ///
 /// In practice, it should match its [frontend counterpart](https://github.com/gitbutlerapp/gitbutler/blob/fa973fd8f1ae8807621f47601803d98b8a9cf348/app/src/lib/backend/ipc.ts#L5).
-#[derive(Debug, Default, Copy, Clone, PartialOrd, PartialEq)]
+#[derive(Debug, Default, Copy, Clone, PartialOrd, PartialEq, Eq)]
 pub enum Code {
     /// Much like a catch-all error code. It shouldn't be attached explicitly unless
     /// a message is provided as well as part of a [`Context`].
@@ -129,28 +134,116 @@ pub enum Code {
     Unknown,
     Validation,
     ProjectGitAuth,
+    ProjectGitRemote,
+    /// The network is unavailable, like without a connection or a name server, so no remote can be reached
+    /// regardless of the credentials.
+    Offline,
     DefaultTargetNotFound,
     CommitSigningFailed,
     CommitHookFailed,
+    /// A hook that runs before an operation other than committing, like `pre-push`, rejected it.
+    HookFailed,
     CommitMergeConflictFailure,
     ProjectMissing,
     AuthorMissing,
+    /// A file or directory that an operation needs to write to isn't writable.
+    PermissionDenied,
+    /// An operation would overwrite changes of submodules that GitButler can't keep track of.
+    Submodules,
+    /// A push was rejected as it would drop commits of the remote branch that aren't known locally.
+    PushRejected,
+    /// The forge that hosts the repository, like GitHub, couldn't be reached or refused a request.
+    Forge,
+    /// Files kept changing while a commit was created from them, so nothing was committed.
+    FilesChanged,
+    /// Commits that are on the remote already were about to be rewritten, which the project doesn't allow
+    /// without being asked to anyway.
+    PushedCommitRewrite,
+    /// An operation that Git or another tool started, like a merge or rebase, is still in progress.
+    ProjectStateInProgress,
+    /// An operation would create a merge commit, but the project requires a linear history.
+    LinearHistory,
+    /// An operation was cancelled before it was done, leaving the repository as it was before or after it.
+    Cancelled,
+    /// Another mutating operation held the project for too long, so this one didn't run.
+    ProjectBusy,
+    /// Stored state, like that of the virtual branches, failed its integrity check and needs to be repaired.
+    CorruptedState,
+    /// The model configured to generate commit messages couldn't be reached or didn't answer with a message.
+    MessageGeneration,
+    /// Changes that were about to be committed or pushed contain what looks like secrets, like access keys.
+    SecretsDetected,
+    /// A path is too long for the filesystem, or uses a name that it can't store, like `CON` on Windows.
+    PathUnsupported,
 }
 
-impl std::fmt::Display for Code {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let code = match self {
+impl Code {
+    /// All codes, for iterating the whole [registry](crate::registry).
+    pub const ALL: &'static [Code] = &[
+        Code::Unknown,
+        Code::Validation,
+        Code::ProjectGitAuth,
+        Code::ProjectGitRemote,
+        Code::Offline,
+        Code::DefaultTargetNotFound,
+        Code::CommitSigningFailed,
+        Code::CommitHookFailed,
+        Code::HookFailed,
+        Code::CommitMergeConflictFailure,
+        Code::ProjectMissing,
+        Code::AuthorMissing,
+        Code::PermissionDenied,
+        Code::Submodules,
+        Code::PushRejected,
+        Code::Forge,
+        Code::FilesChanged,
+        Code::PushedCommitRewrite,
+        Code::ProjectStateInProgress,
+        Code::LinearHistory,
+        Code::Cancelled,
+        Code::ProjectBusy,
+        Code::CorruptedState,
+        Code::MessageGeneration,
+        Code::SecretsDetected,
+        Code::PathUnsupported,
+    ];
+
+    /// Return the stable string representation of this code.
+    pub fn as_str(&self) -> &'static str {
+        match self {
             Code::Unknown => "errors.unknown",
             Code::Validation => "errors.validation",
             Code::ProjectGitAuth => "errors.projects.git.auth",
+            Code::ProjectGitRemote => "errors.projects.git.remote",
+            Code::Offline => "errors.offline",
             Code::DefaultTargetNotFound => "errors.projects.default_target.not_found",
             Code::CommitSigningFailed => "errors.commit.signing_failed",
             Code::CommitHookFailed => "errors.commit.hook_failed",
+            Code::HookFailed => "errors.hook_failed",
             Code::CommitMergeConflictFailure => "errors.commit.merge_conflict_failure",
             Code::AuthorMissing => "errors.git.author_missing",
             Code::ProjectMissing => "errors.projects.missing",
-        };
-        f.write_str(code)
+            Code::PermissionDenied => "errors.permission_denied",
+            Code::Submodules => "errors.submodules",
+            Code::PushRejected => "errors.push.rejected",
+            Code::Forge => "errors.forge",
+            Code::FilesChanged => "errors.commit.files_changed",
+            Code::PushedCommitRewrite => "errors.commit.pushed",
+            Code::ProjectStateInProgress => "errors.projects.state_in_progress",
+            Code::LinearHistory => "errors.branch.linear_history",
+            Code::Cancelled => "errors.cancelled",
+            Code::ProjectBusy => "errors.projects.busy",
+            Code::CorruptedState => "errors.state.corrupted",
+            Code::MessageGeneration => "errors.message_generation",
+            Code::SecretsDetected => "errors.secrets_detected",
+            Code::PathUnsupported => "errors.path.unsupported",
+        }
+    }
+}
+
+impl std::fmt::Display for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
     }
 }
 
@@ -158,17 +251,25 @@ impl std::fmt::Display for Code {
 ///
 /// It provides a [`Code`], which may be [unknown](Code::Unknown), and a `message` which explains
 /// more about the problem at hand.
+/// If the message is from the [catalog](crate::catalog), its `message_id` allows it to be localized.
+/// If the error was identified more specifically, its `error_id` is from the [registry](crate::registry).
 #[derive(Default, Debug, Clone)]
 pub struct Context {
     /// The classification of the error.
     pub code: Code,
     /// A description of what went wrong, if available.
     pub message: Option<Cow<'static, str>>,
+    /// The id of `message` in the message catalog, if it is from there.
+    pub message_id: Option<MessageId>,
+    /// The id of the specific error, if one was attached.
+    pub error_id: Option<ErrorId>,
 }
 
 impl std::fmt::Display for Context {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(self.message.as_deref().unwrap_or("Something went wrong"))
+        f.write_str(self.message.as_deref().unwrap_or_else(|| {
+            catalog::message(MessageId::SomethingWentWrong, catalog::FALLBACK_LOCALE)
+        }))
     }
 }
 
@@ -177,6 +278,19 @@ impl From<Code> for Context {
         Context {
             code,
             message: None,
+            message_id: None,
+            error_id: None,
+        }
+    }
+}
+
+impl From<ErrorId> for Context {
+    fn from(error_id: ErrorId) -> Self {
+        Context {
+            code: error_id.code,
+            message: None,
+            message_id: None,
+            error_id: Some(error_id),
         }
     }
 }
@@ -187,6 +301,8 @@ impl Context {
         Context {
             code: Code::Unknown,
             message: Some(Cow::Owned(message.into())),
+            message_id: None,
+            error_id: None,
         }
     }
 
@@ -195,6 +311,29 @@ impl Context {
         Context {
             code,
             message: Some(Cow::Borrowed(message)),
+            message_id: None,
+            error_id: None,
+        }
+    }
+
+    /// Create a new instance with `code` and the English message identified by `id` in the [catalog](crate::catalog).
+    pub fn from_catalog(code: Code, id: MessageId) -> Self {
+        Context {
+            code,
+            message: Some(Cow::Borrowed(catalog::message(
+                id,
+                catalog::FALLBACK_LOCALE,
+            ))),
+            message_id: Some(id),
+            error_id: None,
+        }
+    }
+
+    /// Return our message in the language of `locale` if it is from the catalog, or as is otherwise.
+    pub fn localized_message(&self, locale: &str) -> Option<Cow<'static, str>> {
+        match self.message_id {
+            Some(id) => Some(Cow::Borrowed(catalog::message(id, locale))),
+            None => self.message.clone(),
         }
     }
 
@@ -224,8 +363,14 @@ pub trait AnyhowContextExt: private::Sealed {
 impl private::Sealed for anyhow::Error {}
 impl AnyhowContextExt for anyhow::Error {
     fn custom_context(&self) -> Option<Context> {
+        let error_id = self.downcast_ref::<ErrorId>().copied();
         if let Some(ctx) = self.downcast_ref::<Context>() {
-            Some(ctx.clone())
+            Some(Context {
+                error_id: ctx.error_id.or(error_id),
+                ..ctx.clone()
+            })
+        } else if let Some(error_id) = error_id {
+            Some(error_id.into())
         } else {
             self.downcast_ref::<Code>().map(|code| (*code).into())
         }
@@ -235,6 +380,8 @@ impl AnyhowContextExt for anyhow::Error {
         self.custom_context().unwrap_or_else(|| Context {
             code: Code::Unknown,
             message: Some(self.root_cause().to_string().into()),
+            message_id: None,
+            error_id: None,
         })
     }
 }
@@ -0,0 +1,162 @@
+//! A registry of stable identifiers for specific errors, so consumers like the *frontend* can show a
+//! localized explanation of each from the [catalog](crate::catalog), and link to its documentation.
+//!
+//! A [`Code`] only classifies errors coarsely. Subsystems refine it with an [`ErrorId`] that is namespaced
+//! below the code, like `errors.projects.git.auth.ssh-key-rejected` below `errors.projects.git.auth`,
+//! and [register](register()) it once the application starts. Each code is registered by itself.
+//!
+//! Errors are marked by attaching an id as [`anyhow context`](anyhow::Context) in place of its code, which
+//! is then found through [`custom_context()`](crate::error::AnyhowContextExt::custom_context()) like before.
+//!
+//! ```rust
+//! # use anyhow::anyhow;
+//! # use gitbutler_error::{catalog::MessageId, error::{AnyhowContextExt, Code}, registry::{self, ErrorId}};
+//! const KEY_REJECTED: ErrorId = ErrorId::new(
+//!     "errors.projects.git.auth.key-rejected",
+//!     Code::ProjectGitAuth,
+//!     MessageId::SshKeyRejected,
+//! );
+//! registry::register(&[KEY_REJECTED]).unwrap();
+//! assert_eq!(registry::lookup("errors.projects.git.auth.key-rejected"), Some(KEY_REJECTED));
+//!
+//! let err = anyhow!("denied").context(KEY_REJECTED);
+//! let ctx = err.custom_context().unwrap();
+//! assert_eq!(ctx.code, Code::ProjectGitAuth);
+//! assert_eq!(ctx.error_id, Some(KEY_REJECTED));
+//!
+//! assert!(
+//!     registry::register(&[ErrorId::new(
+//!         "errors.forge.rejected",
+//!         Code::ProjectGitAuth,
+//!         MessageId::SshKeyRejected,
+//!     )])
+//!     .is_err(),
+//!     "ids must be namespaced below their code"
+//! );
+//! ```
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    sync::Mutex,
+};
+
+use anyhow::{bail, Result};
+
+use crate::{catalog::MessageId, error::Code};
+
+/// All ids that were registered, keyed by their string representation.
+static REGISTRY: Mutex<BTreeMap<&'static str, ErrorId>> = Mutex::new(BTreeMap::new());
+
+/// A stable identifier for a specific error, which refines a [`Code`].
+///
+/// ### Important
+///
+/// The `id` must never change once it was released, as consumers key their translations and handling by it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ErrorId {
+    /// The namespaced identifier, like `errors.projects.git.auth.ssh-key-rejected`.
+    pub id: &'static str,
+    /// The code that classifies this error, and whose string representation `id` is namespaced below.
+    pub code: Code,
+    /// The message in the [catalog](crate::catalog) that explains this error, or `None` for the ids of codes,
+    /// which are explained by the message of the error itself.
+    pub message_id: Option<MessageId>,
+    /// A page of the documentation that helps to resolve this error, if there is one.
+    pub help_url: Option<&'static str>,
+}
+
+impl ErrorId {
+    /// Create a new instance identified by `id`, refining `code`, and explained by the message with `message_id`.
+    pub const fn new(id: &'static str, code: Code, message_id: MessageId) -> Self {
+        ErrorId {
+            id,
+            code,
+            message_id: Some(message_id),
+            help_url: None,
+        }
+    }
+
+    /// Link to the documentation at `url` to help resolve this error.
+    pub const fn with_help_url(mut self, url: &'static str) -> Self {
+        self.help_url = Some(url);
+        self
+    }
+
+    /// Return the id that `code` is registered with, which is the code itself.
+    pub fn of_code(code: Code) -> Self {
+        ErrorId {
+            id: code.as_str(),
+            code,
+            message_id: None,
+            help_url: None,
+        }
+    }
+}
+
+impl Display for ErrorId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id)
+    }
+}
+
+fn with_registry<T>(f: impl FnOnce(&mut BTreeMap<&'static str, ErrorId>) -> T) -> T {
+    f(&mut REGISTRY.lock().expect("no panics while holding the lock"))
+}
+
+/// Add `ids` to the registry. Registering an id again is fine as long as it's the same.
+///
+/// Nothing is registered if any id isn't namespaced below its code, if it has a segment that isn't made of
+/// lowercase ASCII letters, digits, `-` or `_`, or if it's registered already with a different code, message
+/// or help URL.
+pub fn register(ids: &[ErrorId]) -> Result<()> {
+    with_registry(|registry| {
+        for id in ids {
+            let Some(name) = id
+                .id
+                .strip_prefix(id.code.as_str())
+                .and_then(|name| name.strip_prefix('.'))
+            else {
+                bail!(
+                    "error id '{id}' isn't namespaced below its code '{}'",
+                    id.code
+                );
+            };
+            let is_valid_segment = |segment: &str| {
+                !segment.is_empty()
+                    && segment.chars().all(|c| {
+                        c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_'
+                    })
+            };
+            if !name.split('.').all(is_valid_segment) {
+                bail!("error id '{id}' must only have segments of lowercase letters, digits, '-' or '_'");
+            }
+            if let Some(registered) = registry.get(id.id) {
+                if registered != id {
+                    bail!("error id '{id}' is registered already as {registered:?}");
+                }
+            }
+        }
+        registry.extend(ids.iter().map(|id| (id.id, *id)));
+        Ok(())
+    })
+}
+
+/// Return the id whose string representation is `id`, if it's that of a code or was registered.
+pub fn lookup(id: &str) -> Option<ErrorId> {
+    Code::ALL
+        .iter()
+        .find(|code| code.as_str() == id)
+        .map(|code| ErrorId::of_code(*code))
+        .or_else(|| with_registry(|registry| registry.get(id).copied()))
+}
+
+/// Return all ids of codes and all registered ones, sorted by their string representation.
+pub fn catalog() -> Vec<ErrorId> {
+    let mut ids: Vec<_> = Code::ALL
+        .iter()
+        .map(|code| ErrorId::of_code(*code))
+        .collect();
+    with_registry(|registry| ids.extend(registry.values().copied()));
+    ids.sort_by_key(|id| id.id);
+    ids
+}
@@ -1,6 +1,7 @@
 use std::{
     fs::File,
     io::{Read, Write},
+    marker::PhantomData,
     path::{Path, PathBuf},
 };
 
@@ -10,9 +11,11 @@ use gix::{
     dir::walk::EmissionMode,
     tempfile::{create_dir::Retries, AutoRemove, ContainingDirectory},
 };
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serialize};
 use walkdir::WalkDir;
 
+pub mod os_path;
+
 // Returns an ordered list of relative paths for files inside a directory recursively.
 pub fn list_files<P: AsRef<Path>>(dir_path: P, ignore_prefixes: &[P]) -> Result<Vec<PathBuf>> {
     let mut files = vec![];
@@ -112,3 +115,39 @@ pub fn read_toml_file_or_default<T: DeserializeOwned + Default>(path: &Path) ->
         toml::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))?;
     Ok(value)
 }
+
+/// Serializes `value` as TOML and writes it to `path` like [`write()`] does.
+pub fn write_toml_file<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    write(path, toml::to_string(value)?)
+}
+
+/// A TOML file holding a `T`, which reads as `T::default()` until it is written for the first time.
+pub struct TomlFile<T> {
+    path: PathBuf,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default> TomlFile<T> {
+    /// Creates a new instance for the file at `path`, which isn't accessed until it's read or written.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        TomlFile {
+            path: path.into(),
+            _value: PhantomData,
+        }
+    }
+
+    /// Returns the path to the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads and parses the file, or returns the default value if it doesn't exist.
+    pub fn read(&self) -> Result<T> {
+        read_toml_file_or_default(&self.path)
+    }
+
+    /// Replaces the file with `value`, creating it if it doesn't exist.
+    pub fn write(&self, value: &T) -> Result<()> {
+        write_toml_file(&self.path, value)
+    }
+}
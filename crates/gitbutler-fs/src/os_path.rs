@@ -0,0 +1,409 @@
+//! Paths as the operating system takes them.
+//!
+//! On Windows, paths are limited to [`MAX_PATH`] characters unless they are passed in their extended form,
+//! `\\?\C:\...` or `\\?\UNC\server\share\...`, some names can't be used at all, and names are compared
+//! without regard to case, as they are on macOS by default. Deep paths in large repositories otherwise
+//! fail with errors that don't say why, so worktree writes go through [`write_file()`] and [`remove_file()`],
+//! which fail with [`UnsupportedPath`] if the filesystem really can't store a path.
+use std::{
+    borrow::Cow,
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+use gitbutler_error::error::Code;
+use serde::Serialize;
+
+/// The amount of characters that Windows paths are limited to if they aren't in their extended form.
+pub const MAX_PATH: usize = 260;
+
+/// Directories are limited further, as they must leave room for an 8.3 file name within them.
+const MAX_DIR_PATH: usize = MAX_PATH - 12;
+
+/// The longest name of a single file or directory on the filesystems we support.
+const MAX_NAME: usize = 255;
+
+/// The longest path that Unix systems accept.
+#[cfg(target_os = "macos")]
+const MAX_UNIX_PATH: usize = 1024;
+#[cfg(not(target_os = "macos"))]
+const MAX_UNIX_PATH: usize = 4096;
+
+/// Names that Windows reserves for devices, with or without an extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Characters that Windows doesn't allow in names, besides control characters.
+const INVALID_CHARACTERS: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+
+/// Return `path` in a form that can be passed to the operating system however long it is.
+///
+/// On Windows, absolute paths that are too long otherwise are turned into their extended form, which
+/// is also normalized, as Windows doesn't do that for extended paths. Everywhere else, `path` is returned as is.
+pub fn for_io(path: &Path) -> Cow<'_, Path> {
+    if !cfg!(windows) || path.as_os_str().len() < MAX_DIR_PATH {
+        return Cow::Borrowed(path);
+    }
+    match path.to_str().and_then(to_extended) {
+        Some(extended) => Cow::Owned(extended.into()),
+        None => Cow::Borrowed(path),
+    }
+}
+
+/// Turn the absolute Windows path `path`, like `C:\dir` or `\\server\share\dir`, into its extended form,
+/// like `\\?\C:\dir` or `\\?\UNC\server\share\dir`, with `.` and `..` resolved.
+///
+/// Return `None` if `path` is relative, in its extended form already, or a device path.
+pub fn to_extended(path: &str) -> Option<String> {
+    let path = path.replace('/', "\\");
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return None;
+    }
+    let (prefix, rest) = if let Some(unc) = path.strip_prefix(r"\\") {
+        let mut parts = unc.splitn(3, '\\');
+        let (server, share) = (parts.next()?, parts.next()?);
+        if server.is_empty() || share.is_empty() {
+            return None;
+        }
+        (
+            format!(r"\\?\UNC\{server}\{share}"),
+            parts.next().unwrap_or_default(),
+        )
+    } else {
+        let mut chars = path.chars();
+        let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+        if chars.next() != Some(':') || chars.next() != Some('\\') {
+            return None;
+        }
+        (format!(r"\\?\{drive}:"), &path[3..])
+    };
+
+    let mut components = Vec::new();
+    for component in rest.split('\\') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            name => components.push(name),
+        }
+    }
+    let mut extended = prefix;
+    for component in components {
+        extended.push('\\');
+        extended.push_str(component);
+    }
+    if extended.ends_with(':') {
+        extended.push('\\');
+    }
+    Some(extended)
+}
+
+/// Return `path` without the prefix of its extended form, like `C:\dir` for `\\?\C:\dir` and
+/// `\\server\share` for `\\?\UNC\server\share`, which is how paths are shown to users and stored.
+pub fn strip_extended(path: &Path) -> Cow<'_, Path> {
+    let Some(path_str) = path.to_str() else {
+        return Cow::Borrowed(path);
+    };
+    if let Some(unc) = path_str.strip_prefix(r"\\?\UNC\") {
+        return Cow::Owned(format!(r"\\{unc}").into());
+    }
+    match path_str.strip_prefix(r"\\?\") {
+        Some(rest) if rest.get(1..2) == Some(":") => Cow::Borrowed(Path::new(rest)),
+        _ => Cow::Borrowed(path),
+    }
+}
+
+/// Return `true` if `path` is on a network share, like `\\server\share\dir` or `\\?\UNC\server\share\dir`.
+pub fn is_unc(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    let is_verbatim_or_device = path.starts_with(r"\\?\") || path.starts_with(r"\\.\");
+    path.starts_with(r"\\?\UNC\") || (path.starts_with(r"\\") && !is_verbatim_or_device)
+}
+
+/// Whether a filesystem tells names apart that only differ in case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseSensitivity {
+    /// `File` and `file` are different names.
+    Sensitive,
+    /// `File` and `file` name the same file, like on Windows and by default on macOS.
+    Insensitive,
+}
+
+impl CaseSensitivity {
+    /// The case sensitivity of the filesystems that the operating system uses by default.
+    pub fn of_platform() -> Self {
+        if cfg!(any(windows, target_os = "macos")) {
+            CaseSensitivity::Insensitive
+        } else {
+            CaseSensitivity::Sensitive
+        }
+    }
+
+    /// Find out if the filesystem that `dir` is on is case-sensitive by looking for `dir` with the case
+    /// of its name swapped, or assume it's [the default](Self::of_platform()) if that can't be told.
+    pub fn detect(dir: &Path) -> Self {
+        let Some(name) = dir.file_name().and_then(|name| name.to_str()) else {
+            return Self::of_platform();
+        };
+        let swapped: String = name
+            .chars()
+            .map(|c| {
+                if c.is_uppercase() {
+                    c.to_ascii_lowercase()
+                } else {
+                    c.to_ascii_uppercase()
+                }
+            })
+            .collect();
+        if swapped == name || !dir.exists() {
+            return Self::of_platform();
+        }
+        if is_same_file(dir, &dir.with_file_name(swapped)) {
+            CaseSensitivity::Insensitive
+        } else {
+            CaseSensitivity::Sensitive
+        }
+    }
+
+    /// Return `true` if `a` and `b` are the same path on a filesystem with this case sensitivity,
+    /// whether they are in their extended form or not.
+    pub fn eq(self, a: &Path, b: &Path) -> bool {
+        let (a, b) = (strip_extended(a), strip_extended(b));
+        let (mut a, mut b) = (a.components(), b.components());
+        loop {
+            match (a.next(), b.next()) {
+                (None, None) => return true,
+                (Some(a), Some(b)) if self.component_eq(a, b) => {}
+                _ => return false,
+            }
+        }
+    }
+
+    /// Return `path` relative to `base` like [`Path::strip_prefix()`], but comparing their components
+    /// with this case sensitivity, and whether they are in their extended form or not.
+    ///
+    /// Return `None` if `path` isn't within `base`.
+    pub fn strip_prefix(self, path: &Path, base: &Path) -> Option<PathBuf> {
+        let (path, base) = (strip_extended(path), strip_extended(base));
+        let mut components = path.components();
+        for base_component in base.components() {
+            if !self.component_eq(components.next()?, base_component) {
+                return None;
+            }
+        }
+        Some(components.as_path().to_owned())
+    }
+
+    fn component_eq(self, a: Component<'_>, b: Component<'_>) -> bool {
+        match self {
+            CaseSensitivity::Sensitive => a == b,
+            CaseSensitivity::Insensitive => {
+                a.as_os_str().to_string_lossy().to_lowercase()
+                    == b.as_os_str().to_string_lossy().to_lowercase()
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_a: &Path, b: &Path) -> bool {
+    b.exists()
+}
+
+/// A path that the filesystem can't store.
+///
+/// It's returned as error along with [`Code::PathUnsupported`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsupportedPath {
+    pub path: PathBuf,
+    pub reason: UnsupportedPathReason,
+}
+
+/// Why the filesystem can't store a path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum UnsupportedPathReason {
+    /// The path, or one of its names, has `length` characters, but at most `max` are supported.
+    TooLong { length: usize, max: usize },
+    /// `name` is reserved for a device on Windows, like `CON` or `nul.txt`.
+    ReservedName { name: String },
+    /// `name` contains `character`, which Windows doesn't allow in names.
+    InvalidCharacter { name: String, character: char },
+    /// `name` ends with a dot or a space, which Windows drops from names.
+    TrailingDotOrSpace { name: String },
+}
+
+impl fmt::Display for UnsupportedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' can't be stored on this filesystem: ",
+            self.path.display()
+        )?;
+        match &self.reason {
+            UnsupportedPathReason::TooLong { length, max } => {
+                write!(
+                    f,
+                    "it is {length} characters long, but at most {max} are supported"
+                )
+            }
+            UnsupportedPathReason::ReservedName { name } => {
+                write!(f, "'{name}' is reserved for a device")
+            }
+            UnsupportedPathReason::InvalidCharacter { name, character } => {
+                write!(f, "'{name}' contains the invalid character '{character}'")
+            }
+            UnsupportedPathReason::TrailingDotOrSpace { name } => {
+                write!(f, "'{name}' ends with a dot or a space")
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnsupportedPath {}
+
+/// Return why Windows can't store a file or directory called `name`, or `None` if it can.
+pub fn check_name(name: &str) -> Option<UnsupportedPathReason> {
+    if let Some(character) = name
+        .chars()
+        .find(|c| c.is_ascii_control() || INVALID_CHARACTERS.contains(c))
+    {
+        return Some(UnsupportedPathReason::InvalidCharacter {
+            name: name.to_owned(),
+            character,
+        });
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Some(UnsupportedPathReason::TrailingDotOrSpace {
+            name: name.to_owned(),
+        });
+    }
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        return Some(UnsupportedPathReason::ReservedName {
+            name: name.to_owned(),
+        });
+    }
+    None
+}
+
+/// Return an error with [`Code::PathUnsupported`] if the names of `path` below `base` can't be stored
+/// on the filesystem, which is only checked on Windows.
+fn check_names(path: &Path, base: Option<&Path>) -> anyhow::Result<()> {
+    if !cfg!(windows) {
+        return Ok(());
+    }
+    let relative = base
+        .and_then(|base| path.strip_prefix(base).ok())
+        .unwrap_or(path);
+    let reason = relative.components().find_map(|component| match component {
+        Component::Normal(name) => check_name(&name.to_string_lossy()),
+        _ => None,
+    });
+    match reason {
+        Some(reason) => Err(unsupported(path, reason)),
+        None => Ok(()),
+    }
+}
+
+fn unsupported(path: &Path, reason: UnsupportedPathReason) -> anyhow::Error {
+    anyhow::Error::from(UnsupportedPath {
+        path: strip_extended(path).into_owned(),
+        reason,
+    })
+    .context(Code::PathUnsupported)
+}
+
+/// Turn `err`, which occurred when accessing `path`, into an error with [`Code::PathUnsupported`]
+/// if it's because the filesystem can't store `path`, or add `path` to it as context otherwise.
+pub fn io_error(path: &Path, err: std::io::Error) -> anyhow::Error {
+    match unsupported_reason(path, &err) {
+        Some(reason) => unsupported(path, reason),
+        None => anyhow::Error::from(err).context(format!("failed to access {}", path.display())),
+    }
+}
+
+fn unsupported_reason(path: &Path, err: &std::io::Error) -> Option<UnsupportedPathReason> {
+    /// `ERROR_FILENAME_EXCED_RANGE` and `ERROR_INVALID_NAME` on Windows, and `ENAMETOOLONG` elsewhere.
+    const NAME_TOO_LONG: i32 = if cfg!(windows) {
+        206
+    } else if cfg!(target_os = "macos") {
+        63
+    } else {
+        36
+    };
+    const INVALID_NAME: i32 = 123;
+
+    let code = err.raw_os_error()?;
+    let longest_name = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().chars().count()),
+            _ => None,
+        })
+        .max()
+        .unwrap_or_default();
+    if code == NAME_TOO_LONG {
+        return Some(if longest_name > MAX_NAME {
+            UnsupportedPathReason::TooLong {
+                length: longest_name,
+                max: MAX_NAME,
+            }
+        } else {
+            let max = if cfg!(windows) {
+                MAX_PATH
+            } else {
+                MAX_UNIX_PATH
+            };
+            UnsupportedPathReason::TooLong {
+                length: strip_extended(path).to_string_lossy().chars().count(),
+                max,
+            }
+        });
+    }
+    if cfg!(windows) && code == INVALID_NAME {
+        return path.components().find_map(|component| match component {
+            Component::Normal(name) => check_name(&name.to_string_lossy()),
+            _ => None,
+        });
+    }
+    None
+}
+
+/// Write `contents` to the file at `path` within the worktree at `worktree_dir`, creating its leading
+/// directories, so that the write either fully succeeds, or fully fails.
+///
+/// Fails with [`UnsupportedPath`] if the filesystem can't store `path`.
+pub fn write_file(
+    worktree_dir: &Path,
+    path: &Path,
+    contents: impl AsRef<[u8]>,
+) -> anyhow::Result<()> {
+    check_names(path, Some(worktree_dir))?;
+    crate::create_dirs_then_write(for_io(path), contents).map_err(|err| io_error(path, err))
+}
+
+/// Remove the file at `path`, doing nothing if it doesn't exist.
+pub fn remove_file(path: &Path) -> anyhow::Result<()> {
+    match std::fs::remove_file(for_io(path)) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(io_error(path, err)),
+    }
+}
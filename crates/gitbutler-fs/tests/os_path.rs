@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_fs::os_path::{
+    self, check_name, CaseSensitivity, UnsupportedPath, UnsupportedPathReason,
+};
+
+#[test]
+fn absolute_paths_are_turned_into_their_normalized_extended_form() {
+    for (path, expected) in [
+        (r"C:\repo\src\main.rs", r"\\?\C:\repo\src\main.rs"),
+        ("C:/repo/src/main.rs", r"\\?\C:\repo\src\main.rs"),
+        (r"C:\repo\.\src\..\lib.rs", r"\\?\C:\repo\lib.rs"),
+        (r"C:\", r"\\?\C:\"),
+        (
+            r"\\server\share\repo\file",
+            r"\\?\UNC\server\share\repo\file",
+        ),
+        (r"\\server\share", r"\\?\UNC\server\share"),
+    ] {
+        assert_eq!(
+            os_path::to_extended(path).as_deref(),
+            Some(expected),
+            "{path}"
+        );
+    }
+}
+
+#[test]
+fn paths_without_an_extended_form_are_left_alone() {
+    for path in [
+        r"repo\src",
+        r"C:repo",
+        r"\\?\C:\repo",
+        r"\\.\pipe\name",
+        r"\\server",
+        "/home/me/repo",
+    ] {
+        assert_eq!(os_path::to_extended(path), None, "{path}");
+    }
+}
+
+#[test]
+fn extended_prefixes_are_stripped() {
+    for (path, expected) in [
+        (r"\\?\C:\repo", r"C:\repo"),
+        (r"\\?\UNC\server\share\repo", r"\\server\share\repo"),
+        (r"C:\repo", r"C:\repo"),
+        (r"\\?\Volume{1234}\repo", r"\\?\Volume{1234}\repo"),
+    ] {
+        assert_eq!(
+            os_path::strip_extended(Path::new(path)),
+            Path::new(expected)
+        );
+    }
+}
+
+#[test]
+fn network_shares_are_recognized() {
+    assert!(os_path::is_unc(Path::new(r"\\server\share\repo")));
+    assert!(os_path::is_unc(Path::new(r"\\?\UNC\server\share\repo")));
+    assert!(!os_path::is_unc(Path::new(r"\\?\C:\repo")));
+    assert!(!os_path::is_unc(Path::new(r"C:\repo")));
+    assert!(!os_path::is_unc(Path::new("/home/me/repo")));
+}
+
+#[test]
+fn names_windows_cannot_store_are_rejected() {
+    for name in [
+        "file.rs",
+        "CONFIG",
+        "con-tribute.md",
+        "nul_device",
+        ".github",
+    ] {
+        assert_eq!(check_name(name), None, "{name}");
+    }
+    for name in ["CON", "con.txt", "Nul", "LPT1.log", "com9"] {
+        assert_eq!(
+            check_name(name),
+            Some(UnsupportedPathReason::ReservedName { name: name.into() })
+        );
+    }
+    assert_eq!(
+        check_name("what?.md"),
+        Some(UnsupportedPathReason::InvalidCharacter {
+            name: "what?.md".into(),
+            character: '?'
+        })
+    );
+    for name in ["trailing.", "trailing "] {
+        assert_eq!(
+            check_name(name),
+            Some(UnsupportedPathReason::TrailingDotOrSpace { name: name.into() })
+        );
+    }
+}
+
+#[test]
+fn paths_are_compared_with_the_case_sensitivity_of_the_filesystem() {
+    let (a, b) = (Path::new("/Work/Repo"), Path::new("/work/repo"));
+    assert!(CaseSensitivity::Insensitive.eq(a, b));
+    assert!(!CaseSensitivity::Sensitive.eq(a, b));
+    assert!(CaseSensitivity::Sensitive.eq(a, Path::new("/Work/Repo/")));
+    assert!(!CaseSensitivity::Insensitive.eq(a, Path::new("/work/repo/sub")));
+
+    let file = Path::new("/WORK/repo/src/Main.rs");
+    assert_eq!(
+        CaseSensitivity::Insensitive.strip_prefix(file, a),
+        Some(PathBuf::from("src/Main.rs"))
+    );
+    assert_eq!(CaseSensitivity::Sensitive.strip_prefix(file, a), None);
+    assert_eq!(
+        CaseSensitivity::Insensitive.strip_prefix(Path::new("/other/repo/file"), a),
+        None
+    );
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn case_sensitivity_is_detected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("Repo");
+    std::fs::create_dir(&dir).unwrap();
+    assert_eq!(CaseSensitivity::detect(&dir), CaseSensitivity::Sensitive);
+}
+
+#[test]
+fn files_are_written_with_their_leading_directories_and_removed() {
+    let tmp = tempfile::tempdir().unwrap();
+    let file = tmp.path().join("a/b/c.txt");
+    os_path::write_file(tmp.path(), &file, "content").unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "content");
+
+    os_path::remove_file(&file).unwrap();
+    assert!(!file.exists());
+    os_path::remove_file(&file).expect("files that are gone already are fine");
+}
+
+#[test]
+#[cfg(unix)]
+fn names_that_are_too_long_fail_with_the_reason() {
+    let tmp = tempfile::tempdir().unwrap();
+    let file = tmp.path().join("dir").join("x".repeat(300));
+    let err = os_path::write_file(tmp.path(), &file, "content").unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::PathUnsupported)
+    );
+    assert_eq!(
+        err.downcast_ref::<UnsupportedPath>(),
+        Some(&UnsupportedPath {
+            path: file,
+            reason: UnsupportedPathReason::TooLong {
+                length: 300,
+                max: 255
+            },
+        })
+    );
+}
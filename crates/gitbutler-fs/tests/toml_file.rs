@@ -0,0 +1,28 @@
+use gitbutler_fs::TomlFile;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Counter {
+    count: u32,
+}
+
+#[test]
+fn missing_files_read_as_default_until_written() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = TomlFile::<Counter>::new(dir.path().join("counter.toml"));
+    assert_eq!(file.read().unwrap(), Counter::default());
+    assert!(!file.path().exists(), "reading doesn't create the file");
+
+    file.write(&Counter { count: 2 }).unwrap();
+    assert_eq!(file.read().unwrap(), Counter { count: 2 });
+    assert_eq!(std::fs::read_to_string(file.path()).unwrap(), "count = 2\n");
+}
+
+#[test]
+fn invalid_files_fail_to_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("counter.toml");
+    std::fs::write(&path, "count = \"many\"").unwrap();
+    let err = TomlFile::<Counter>::new(&path).read().unwrap_err();
+    assert!(err.to_string().starts_with("Failed to parse"), "{err}");
+}
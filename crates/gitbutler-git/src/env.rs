@@ -0,0 +1,153 @@
+//! A controlled environment for the processes we spawn, like `git`, credential helpers,
+//! `ssh` or signing programs.
+//!
+//! The application environment can be very different from the one of a terminal: it may have been
+//! launched from within a Git hook with `GIT_DIR` set, or by a desktop environment with a minimal `PATH`.
+//! [`ProcessEnv`] removes variables that would redirect Git to another repository, adds what is
+//! needed to run non-interactively, and allows users to add their own variables per project.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsString,
+    path::PathBuf,
+};
+
+/// Variables that change which repository Git operates on, or how it's laid out.
+/// Inherited from a parent Git process, they make subprocesses act on the wrong repository.
+const REPOSITORY_VARIABLES: &[&str] = &[
+    "GIT_DIR",
+    "GIT_WORK_TREE",
+    "GIT_INDEX_FILE",
+    "GIT_OBJECT_DIRECTORY",
+    "GIT_ALTERNATE_OBJECT_DIRECTORIES",
+    "GIT_COMMON_DIR",
+    "GIT_NAMESPACE",
+    "GIT_PREFIX",
+    "GIT_QUARANTINE_PATH",
+];
+
+/// Directories that usually contain user-installed tools on macOS, but which aren't in the `PATH`
+/// of applications launched from the Finder or the Dock.
+#[cfg(target_os = "macos")]
+const EXTRA_PATH_DIRS: &[&str] = &["/opt/homebrew/bin", "/usr/local/bin"];
+#[cfg(not(target_os = "macos"))]
+const EXTRA_PATH_DIRS: &[&str] = &[];
+
+/// A builder for the environment of subprocesses.
+///
+/// It starts out with the inherited environment of this process, minus variables that are known
+/// to cause trouble, plus variables we need, and can be extended with per-project variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessEnv {
+    /// Variables to not pass on from the inherited environment.
+    removed: BTreeSet<String>,
+    /// Variables to set, overriding inherited ones.
+    vars: BTreeMap<String, String>,
+    /// Directories to append to `PATH` if they aren't in it yet.
+    path_dirs: Vec<PathBuf>,
+}
+
+impl Default for ProcessEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessEnv {
+    /// Create an environment that removes variables pointing Git to other repositories,
+    /// disables terminal prompts and makes commonly used tool locations available.
+    pub fn new() -> Self {
+        ProcessEnv {
+            removed: REPOSITORY_VARIABLES.iter().map(|&key| key.into()).collect(),
+            vars: [("GIT_TERMINAL_PROMPT".into(), "0".into())].into(),
+            path_dirs: EXTRA_PATH_DIRS.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    /// Set `key` to `value`, overriding the inherited value, if any.
+    pub fn set(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
+        self.removed.remove(&key);
+        self.vars.insert(key, value.into());
+        self
+    }
+
+    /// Do not pass on the variable `key`.
+    pub fn remove(mut self, key: impl Into<String>) -> Self {
+        let key = key.into();
+        self.vars.remove(&key);
+        self.removed.insert(key);
+        self
+    }
+
+    /// Set all `vars`, like the extra environment configured for a project.
+    pub fn extend<K, V>(self, vars: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        vars.into_iter()
+            .fold(self, |env, (key, value)| env.set(key, value))
+    }
+
+    /// Return the complete environment for a subprocess, based on the `inherited` variables.
+    pub fn resolve(
+        &self,
+        inherited: impl IntoIterator<Item = (String, String)>,
+    ) -> BTreeMap<String, String> {
+        let mut env: BTreeMap<_, _> = inherited
+            .into_iter()
+            .filter(|(key, _)| !self.removed.iter().any(|removed| same_key(removed, key)))
+            .filter(|(key, _)| !self.vars.keys().any(|set| same_key(set, key)))
+            .collect();
+        env.extend(self.vars.clone());
+
+        if !self.path_dirs.is_empty() {
+            let path_key = env
+                .keys()
+                .find(|key| same_key(key, "PATH"))
+                .cloned()
+                .unwrap_or_else(|| "PATH".into());
+            let mut dirs: Vec<PathBuf> = env
+                .get(&path_key)
+                .map(|path| std::env::split_paths(path).collect())
+                .unwrap_or_default();
+            for dir in &self.path_dirs {
+                if !dirs.contains(dir) {
+                    dirs.push(dir.clone());
+                }
+            }
+            if let Some(path) = std::env::join_paths(dirs)
+                .ok()
+                .and_then(|path: OsString| path.into_string().ok())
+            {
+                env.insert(path_key, path);
+            }
+        }
+        env
+    }
+
+    /// Return the complete environment for a subprocess, based on the environment of this process.
+    ///
+    /// Variables that aren't valid unicode are not passed on.
+    pub fn resolve_current(&self) -> BTreeMap<String, String> {
+        self.resolve(
+            std::env::vars_os().filter_map(|(key, value)| {
+                Some((key.into_string().ok()?, value.into_string().ok()?))
+            }),
+        )
+    }
+
+    /// Replace the environment of `cmd` with the one of this instance.
+    pub fn apply(&self, cmd: &mut std::process::Command) {
+        cmd.env_clear().envs(self.resolve_current());
+    }
+}
+
+/// Variable names are case-insensitive on Windows.
+fn same_key(a: &str, b: &str) -> bool {
+    if cfg!(windows) {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
@@ -19,6 +19,13 @@ pub enum Error<BE: std::error::Error + core::fmt::Debug + Send + Sync + 'static>
     /// more context.
     #[error("authorization failed: {0}")]
     AuthorizationFailed(BE),
+    /// A push was rejected as the remote branch has commits that it would drop,
+    /// because it isn't forced or the remote branch changed since it was last seen.
+    ///
+    /// The inner error is the backend-specific error that may provide
+    /// more context.
+    #[error("push rejected: {0}")]
+    PushRejected(BE),
     /// An operation interacting with a remote by name failed to find
     /// the remote.
     #[error("no such remote: {0}")]
@@ -9,6 +9,8 @@ use std::{collections::HashMap, path::Path};
 
 use tokio::process::Command;
 
+use crate::ProcessEnv;
+
 #[cfg(unix)]
 pub use self::unix::TokioAskpassServer;
 #[cfg(windows)]
@@ -16,7 +18,19 @@ pub use self::windows::TokioAskpassServer;
 
 /// A Git executor implementation using the `git` command-line tool
 /// via [`tokio::process::Command`].
-pub struct TokioExecutor;
+///
+/// All processes are spawned with the environment of its [`ProcessEnv`].
+#[derive(Default)]
+pub struct TokioExecutor {
+    env: ProcessEnv,
+}
+
+impl TokioExecutor {
+    /// Create an executor which spawns all processes with `env`.
+    pub fn with_env(env: ProcessEnv) -> Self {
+        TokioExecutor { env }
+    }
+}
 
 #[allow(unsafe_code)]
 unsafe impl super::GitExecutor for TokioExecutor {
@@ -52,6 +66,8 @@ unsafe impl super::GitExecutor for TokioExecutor {
 
         cmd.kill_on_drop(true);
         cmd.current_dir(cwd);
+        cmd.env_clear();
+        cmd.envs(self.env.resolve_current());
 
         #[cfg(not(windows))]
         cmd.args(args);
@@ -142,7 +158,7 @@ mod tests {
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_askpass() {
         let secret = "super-secret-secret";
-        let executor = TokioExecutor;
+        let executor = TokioExecutor::default();
         #[allow(unsafe_code)]
         let sock_server: TokioAskpassServer = unsafe { executor.create_askpass_server() }
             .await
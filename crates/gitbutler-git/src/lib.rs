@@ -16,6 +16,7 @@
 ))]
 compile_error!("BUG: in production code this flag should not be set, nor do we run test with `cargo test --release`. Benches must use `--features benches`");
 
+mod env;
 mod error;
 pub(crate) mod executor;
 mod refspec;
@@ -24,7 +25,8 @@ mod repository;
 #[cfg(feature = "tokio")]
 pub use self::executor::tokio;
 pub use self::{
+    env::ProcessEnv,
     error::Error,
     refspec::{Error as RefSpecError, RefSpec},
-    repository::{fetch, push, sign_commit},
+    repository::{fetch, push, sign_commit, Deepen, FetchOptions, ForcePush},
 };
@@ -274,8 +274,39 @@ where
     }
 }
 
-/// Fetches the given refspec from the given remote in the repository
-/// at the given path. Any prompts for the user are passed to the asynchronous
+/// How a fetch treats what the remote no longer has, which tags it brings in, and how much history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchOptions {
+    /// If `true`, remote-tracking branches whose branch was deleted on the remote are deleted.
+    pub prune: bool,
+    /// `Some(true)` to fetch all tags, `Some(false)` to fetch none, or `None` to fetch those that
+    /// point into the fetched history.
+    pub tags: Option<bool>,
+    /// How much further back the history of a shallow clone is fetched, or `None` to keep its depth.
+    pub deepen: Option<Deepen>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        FetchOptions {
+            prune: true,
+            tags: None,
+            deepen: None,
+        }
+    }
+}
+
+/// How much further back the history of a shallow clone is fetched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deepen {
+    /// The given amount of commits beyond where the history ends now.
+    By(u32),
+    /// All of it, which makes the clone a complete one.
+    Unshallow,
+}
+
+/// Fetches the given refspecs from the given remote in the repository
+/// at the given path, as `options` say. Any prompts for the user are passed to the asynchronous
 /// callback `on_prompt` which should return the user's response or `None` if the
 /// operation should be aborted, in which case an `Err` value is returned from this
 /// function.
@@ -283,7 +314,8 @@ pub async fn fetch<P, F, Fut, E, Extra>(
     repo_path: P,
     executor: E,
     remote: &str,
-    refspec: RefSpec,
+    refspecs: &[RefSpec],
+    options: &FetchOptions,
     on_prompt: F,
     extra: Extra,
 ) -> Result<(), crate::Error<Error<E>>>
@@ -294,12 +326,31 @@ where
     Fut: std::future::Future<Output = Option<String>>,
     Extra: Send + Clone,
 {
-    let mut args = vec!["fetch", "--quiet", "--prune"];
+    let mut args = vec!["fetch", "--quiet"];
+    args.push(if options.prune {
+        "--prune"
+    } else {
+        "--no-prune"
+    });
+    match options.tags {
+        Some(true) => args.push("--tags"),
+        Some(false) => args.push("--no-tags"),
+        None => {}
+    }
+    let deepen;
+    match options.deepen {
+        Some(Deepen::By(depth)) => {
+            deepen = format!("--deepen={depth}");
+            args.push(&deepen);
+        }
+        Some(Deepen::Unshallow) => args.push("--unshallow"),
+        None => {}
+    }
 
-    let refspec = refspec.to_string();
+    let refspecs: Vec<_> = refspecs.iter().map(ToString::to_string).collect();
 
     args.push(remote);
-    args.push(&refspec);
+    args.extend(refspecs.iter().map(String::as_str));
 
     let (status, stdout, stderr) =
         execute_with_auth_harness(repo_path, &executor, &args, None, on_prompt, extra).await?;
@@ -314,7 +365,7 @@ where
             .map(|line| line.split_whitespace().last().unwrap_or_default())
         {
             Err(crate::Error::RefNotFound(refname.to_owned()))?
-        } else if stderr.to_lowercase().contains("permission denied") {
+        } else if is_auth_failure(&stderr) {
             Err(crate::Error::AuthorizationFailed(Error::<E>::Failed {
                 status,
                 args: args.into_iter().map(Into::into).collect(),
@@ -332,16 +383,31 @@ where
     }
 }
 
+/// How a push may replace commits of the remote branch that the pushed commit doesn't contain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForcePush {
+    /// The push is rejected unless it's a fast-forward.
+    No,
+    /// The remote branch is replaced, whatever it points to.
+    Yes,
+    /// The remote branch is replaced only if it still points to the given commit, or doesn't
+    /// exist if it's `None`, so commits pushed by someone else in the meantime aren't dropped.
+    WithLease(Option<String>),
+}
+
 /// Pushes a refspec to the given remote in the repository at the given path.
 /// Any prompts for the user are passed to the asynchronous callback `on_prompt`,
 /// which should return the user's response or `None` if the operation should be
 /// aborted, in which case an `Err` value is returned from this function.
+///
+/// If the remote branch has commits that the push would drop without being allowed to by `force`,
+/// [`Error::PushRejected`](crate::Error::PushRejected) is returned.
 pub async fn push<P, F, Fut, E, Extra>(
     repo_path: P,
     executor: E,
     remote: &str,
     refspec: RefSpec,
-    force: bool,
+    force: ForcePush,
     on_prompt: F,
     extra: Extra,
 ) -> Result<(), crate::Error<Error<E>>>
@@ -356,11 +422,21 @@ where
 
     let refspec = refspec.to_string();
 
+    let lease;
     args.push(remote);
     args.push(&refspec);
 
-    if force {
-        args.push("--force");
+    match &force {
+        ForcePush::No => {}
+        ForcePush::Yes => args.push("--force"),
+        ForcePush::WithLease(expected) => {
+            lease = format!(
+                "--force-with-lease={}:{}",
+                refspec_destination(&refspec),
+                expected.as_deref().unwrap_or_default()
+            );
+            args.push(&lease);
+        }
     }
 
     let (status, stdout, stderr) =
@@ -376,13 +452,20 @@ where
             .map(|line| line.split_whitespace().last().unwrap_or_default())
         {
             Err(crate::Error::RefNotFound(refname.to_owned()))?
-        } else if stderr.to_lowercase().contains("permission denied") {
+        } else if is_auth_failure(&stderr) {
             Err(crate::Error::AuthorizationFailed(Error::<E>::Failed {
                 status,
                 args: args.into_iter().map(Into::into).collect(),
                 stdout,
                 stderr,
             }))?
+        } else if stderr.lines().any(|line| line.contains("! [rejected]")) {
+            Err(crate::Error::PushRejected(Error::<E>::Failed {
+                status,
+                args: args.into_iter().map(Into::into).collect(),
+                stdout,
+                stderr,
+            }))?
         } else {
             Err(Error::<E>::Failed {
                 status,
@@ -394,6 +477,14 @@ where
     }
 }
 
+/// Return the destination of `refspec` on the remote, as needed by `--force-with-lease`.
+fn refspec_destination(refspec: &str) -> &str {
+    let refspec = refspec.trim_start_matches('+');
+    refspec
+        .split_once(':')
+        .map_or(refspec, |(_, destination)| destination)
+}
+
 /// Signs the given commit-ish in the repository at the given path.
 /// Returns the newly signed commit SHA.
 ///
@@ -522,3 +613,10 @@ async fn get_core_sshcommand<E: GitExecutor, P: AsRef<Path>>(
         })
         .unwrap_or(None)
 }
+
+/// Return `true` if `stderr` of a `git` invocation tells that the remote rejected the credentials.
+fn is_auth_failure(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    // `ssh` denies access, and Git fails like this if HTTP credentials are rejected.
+    stderr.contains("permission denied") || stderr.contains("authentication failed")
+}
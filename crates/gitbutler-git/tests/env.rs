@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use gitbutler_git::ProcessEnv;
+
+fn inherited(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+    vars.iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn repository_variables_are_removed() {
+    let env = ProcessEnv::new().resolve(inherited(&[
+        ("GIT_DIR", "/elsewhere/.git"),
+        ("GIT_WORK_TREE", "/elsewhere"),
+        ("GIT_INDEX_FILE", "/elsewhere/.git/index"),
+        ("HOME", "/home/user"),
+    ]));
+    assert!(!env.contains_key("GIT_DIR"));
+    assert!(!env.contains_key("GIT_WORK_TREE"));
+    assert!(!env.contains_key("GIT_INDEX_FILE"));
+    assert_eq!(env.get("HOME").map(String::as_str), Some("/home/user"));
+}
+
+#[test]
+fn terminal_prompts_are_disabled() {
+    let env = ProcessEnv::new().resolve(inherited(&[("GIT_TERMINAL_PROMPT", "1")]));
+    assert_eq!(
+        env.get("GIT_TERMINAL_PROMPT").map(String::as_str),
+        Some("0")
+    );
+}
+
+#[test]
+fn extra_variables_override_inherited_and_defaults() {
+    let env = ProcessEnv::new()
+        .extend([("SSH_AUTH_SOCK", "/tmp/agent.sock"), ("GIT_DIR", "/custom")])
+        .resolve(inherited(&[("SSH_AUTH_SOCK", "/tmp/other.sock")]));
+    assert_eq!(
+        env.get("SSH_AUTH_SOCK").map(String::as_str),
+        Some("/tmp/agent.sock")
+    );
+    assert_eq!(
+        env.get("GIT_DIR").map(String::as_str),
+        Some("/custom"),
+        "explicitly set variables are passed even if they'd be removed otherwise"
+    );
+}
+
+#[test]
+fn removed_variables_are_not_passed() {
+    let env = ProcessEnv::new()
+        .set("GIT_SSH_COMMAND", "ssh -v")
+        .remove("GIT_SSH_COMMAND")
+        .remove("EDITOR")
+        .resolve(inherited(&[("EDITOR", "vim"), ("GIT_SSH_COMMAND", "ssh")]));
+    assert!(!env.contains_key("EDITOR"));
+    assert!(!env.contains_key("GIT_SSH_COMMAND"));
+}
+
+#[test]
+fn inherited_path_is_kept() {
+    let path = std::env::join_paths(["/usr/bin", "/bin"])
+        .unwrap()
+        .into_string()
+        .unwrap();
+    let env = ProcessEnv::new().resolve(inherited(&[("PATH", &path)]));
+    let dirs: Vec<PathBuf> = std::env::split_paths(&env["PATH"]).collect();
+    assert_eq!(
+        dirs[..2],
+        [PathBuf::from("/usr/bin"), PathBuf::from("/bin")]
+    );
+}
@@ -1 +1,2 @@
+mod env;
 mod refspec;
@@ -0,0 +1,88 @@
+//! Where new ids come from, so runs can be made reproducible by making them predictable.
+//!
+//! By default, ids are random. Embedders can replace the generator for the whole process with
+//! [`set_id_generator()`], and tests can replace it for the current thread only with
+//! [`use_ids_in_thread()`], so tests running in parallel don't affect each other.
+use std::{
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use uuid::Uuid;
+
+/// A source of new ids.
+pub trait IdGenerator: Send + Sync {
+    /// Return an id that wasn't returned before.
+    fn next(&self) -> Uuid;
+}
+
+/// Random ids, which are used unless another generator is set.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RandomIds;
+
+impl IdGenerator for RandomIds {
+    fn next(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// Ids that count up from `1`, in the lower bits of the id.
+#[derive(Debug, Default)]
+pub struct SequentialIds {
+    last: AtomicU64,
+}
+
+impl IdGenerator for SequentialIds {
+    fn next(&self) -> Uuid {
+        Uuid::from_u128(u128::from(self.last.fetch_add(1, Ordering::Relaxed) + 1))
+    }
+}
+
+static GLOBAL: RwLock<Option<Arc<dyn IdGenerator>>> = RwLock::new(None);
+
+thread_local! {
+    static THREAD: RefCell<Option<Arc<dyn IdGenerator>>> = const { RefCell::new(None) };
+}
+
+/// Use `generator` for new ids in the whole process, or random ids if `None`.
+pub fn set_id_generator(generator: Option<Arc<dyn IdGenerator>>) {
+    *GLOBAL.write().expect("no panics while holding the lock") = generator;
+}
+
+/// Use `generator` for new ids in the current thread until the returned guard is dropped.
+/// It takes precedence over the generator of the process.
+pub fn use_ids_in_thread(generator: impl IdGenerator + 'static) -> ThreadIdsGuard {
+    let previous = THREAD.with(|thread| thread.borrow_mut().replace(Arc::new(generator)));
+    ThreadIdsGuard { previous }
+}
+
+/// Restores the generator the thread used before when dropped.
+#[must_use = "the generator is only used until the guard is dropped"]
+pub struct ThreadIdsGuard {
+    previous: Option<Arc<dyn IdGenerator>>,
+}
+
+impl Drop for ThreadIdsGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        THREAD.with(|thread| *thread.borrow_mut() = previous);
+    }
+}
+
+/// Return a new id from the generator in use.
+pub fn new_uuid() -> Uuid {
+    if let Some(generator) = THREAD.with(|thread| thread.borrow().clone()) {
+        return generator.next();
+    }
+    match GLOBAL
+        .read()
+        .expect("no panics while holding the lock")
+        .as_ref()
+    {
+        Some(generator) => generator.next(),
+        None => Uuid::new_v4(),
+    }
+}
@@ -9,7 +9,7 @@ use uuid::Uuid;
 /// A generic UUID-based newtype.
 ///
 /// `Default` is implemented to generate a new UUID
-/// via [`Uuid::new_v4`].
+/// via [`new_uuid()`](crate::generator::new_uuid()), which is random unless configured otherwise.
 pub struct Id<T>(Uuid, PhantomData<T>);
 
 impl<T> Hash for Id<T> {
@@ -33,7 +33,7 @@ impl<T> Ord for Id<T> {
 impl<T> Id<T> {
     #[must_use]
     pub fn generate() -> Self {
-        Id(Uuid::new_v4(), PhantomData)
+        Id(crate::generator::new_uuid(), PhantomData)
     }
 }
 
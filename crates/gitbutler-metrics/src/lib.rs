@@ -0,0 +1,179 @@
+//! Counters and measurements of how the application performs, kept for the whole process so they can be
+//! exported in the [Prometheus text format](https://prometheus.io/docs/instrumenting/exposition_formats/#text-based-format)
+//! with [`render()`].
+//!
+//! Recording only updates a few numbers in memory, so it's always on, and it's up to the application to
+//! expose them.
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex, time::Duration};
+
+/// The upper bounds in seconds of the buckets the durations of computing the status are counted in.
+const STATUS_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+static METRICS: Mutex<Metrics> = Mutex::new(Metrics {
+    operations: BTreeMap::new(),
+    status: Histogram {
+        buckets: [0; STATUS_BUCKETS.len()],
+        sum_seconds: 0.0,
+        count: 0,
+    },
+    watcher_backlog: 0,
+    workdir_cache_hits: 0,
+    workdir_cache_misses: 0,
+});
+
+struct Metrics {
+    /// How often each kind of operation was performed.
+    operations: BTreeMap<String, u64>,
+    status: Histogram,
+    /// The events that were passed to the watchers, but weren't handled yet.
+    watcher_backlog: u64,
+    /// The files of worktree diffs that were taken from the cache.
+    workdir_cache_hits: u64,
+    /// The files of worktree diffs that had to be diffed again.
+    workdir_cache_misses: u64,
+}
+
+struct Histogram {
+    /// The amount of observations that fell into each of the [`STATUS_BUCKETS`], but not the ones before.
+    buckets: [u64; STATUS_BUCKETS.len()],
+    sum_seconds: f64,
+    count: u64,
+}
+
+fn with_metrics<T>(f: impl FnOnce(&mut Metrics) -> T) -> T {
+    f(&mut METRICS.lock().expect("no panics while holding the lock"))
+}
+
+/// Count that the operation of kind `operation`, like `CreateCommit`, was performed.
+pub fn count_operation(operation: &str) {
+    with_metrics(|metrics| *metrics.operations.entry(operation.to_owned()).or_default() += 1);
+}
+
+/// Note that computing the status of a workspace took `duration`.
+pub fn observe_status_duration(duration: Duration) {
+    let seconds = duration.as_secs_f64();
+    with_metrics(|metrics| {
+        let status = &mut metrics.status;
+        if let Some(bucket) = STATUS_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            status.buckets[bucket] += 1;
+        }
+        status.sum_seconds += seconds;
+        status.count += 1;
+    });
+}
+
+/// Note that an event was passed to a watcher, to be handled later.
+pub fn watcher_event_queued() {
+    with_metrics(|metrics| metrics.watcher_backlog += 1);
+}
+
+/// Note that a watcher finished handling an event that was [queued](watcher_event_queued()) before.
+pub fn watcher_event_handled() {
+    with_metrics(|metrics| metrics.watcher_backlog = metrics.watcher_backlog.saturating_sub(1));
+}
+
+/// Count that a worktree diff took `hits` files from the cache, and had to diff `misses` files again.
+pub fn count_workdir_cache(hits: usize, misses: usize) {
+    with_metrics(|metrics| {
+        metrics.workdir_cache_hits += hits as u64;
+        metrics.workdir_cache_misses += misses as u64;
+    });
+}
+
+/// Return all metrics in the Prometheus text format.
+pub fn render() -> String {
+    with_metrics(|metrics| {
+        let mut out = String::new();
+        metric(
+            &mut out,
+            "gitbutler_operations_total",
+            "counter",
+            "Operations performed, by their kind.",
+        );
+        for (operation, count) in &metrics.operations {
+            writeln!(
+                out,
+                "gitbutler_operations_total{{operation=\"{}\"}} {count}",
+                escape_label(operation)
+            )
+            .ok();
+        }
+
+        metric(
+            &mut out,
+            "gitbutler_status_duration_seconds",
+            "histogram",
+            "Time it took to compute the status of a workspace.",
+        );
+        let mut cumulative = 0;
+        for (bound, count) in STATUS_BUCKETS.iter().zip(metrics.status.buckets) {
+            cumulative += count;
+            writeln!(
+                out,
+                "gitbutler_status_duration_seconds_bucket{{le=\"{bound}\"}} {cumulative}"
+            )
+            .ok();
+        }
+        let status = &metrics.status;
+        writeln!(
+            out,
+            "gitbutler_status_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            status.count
+        )
+        .ok();
+        writeln!(
+            out,
+            "gitbutler_status_duration_seconds_sum {}",
+            status.sum_seconds
+        )
+        .ok();
+        writeln!(
+            out,
+            "gitbutler_status_duration_seconds_count {}",
+            status.count
+        )
+        .ok();
+
+        metric(
+            &mut out,
+            "gitbutler_watcher_backlog",
+            "gauge",
+            "Filesystem events waiting to be handled by the watchers of open projects.",
+        );
+        writeln!(out, "gitbutler_watcher_backlog {}", metrics.watcher_backlog).ok();
+
+        metric(
+            &mut out,
+            "gitbutler_workdir_cache_files_total",
+            "counter",
+            "Files of worktree diffs, by whether they were taken from the cache or diffed again.",
+        );
+        writeln!(
+            out,
+            "gitbutler_workdir_cache_files_total{{result=\"hit\"}} {}",
+            metrics.workdir_cache_hits
+        )
+        .ok();
+        writeln!(
+            out,
+            "gitbutler_workdir_cache_files_total{{result=\"miss\"}} {}",
+            metrics.workdir_cache_misses
+        )
+        .ok();
+        out
+    })
+}
+
+fn metric(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}").ok();
+}
+
+/// Escape `value` to be used as the value of a label.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+/// Return the value of the sample `name`, including its labels, in `rendered`.
+fn sample(rendered: &str, name: &str) -> f64 {
+    rendered
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+        .unwrap_or_else(|| panic!("no sample {name} in:\n{rendered}"))
+        .parse()
+        .unwrap()
+}
+
+// Metrics are global, so they are only checked in this one test that runs alone.
+#[test]
+fn metrics_are_rendered_in_prometheus_text_format() {
+    let before = gitbutler_metrics::render();
+    assert!(before.contains("# TYPE gitbutler_operations_total counter\n"));
+    assert!(before.contains("# TYPE gitbutler_status_duration_seconds histogram\n"));
+    assert_eq!(sample(&before, "gitbutler_watcher_backlog"), 0.0);
+
+    gitbutler_metrics::count_operation("CreateCommit");
+    gitbutler_metrics::count_operation("CreateCommit");
+    gitbutler_metrics::count_operation("Say \"hi\"");
+    gitbutler_metrics::observe_status_duration(Duration::from_millis(20));
+    gitbutler_metrics::observe_status_duration(Duration::from_secs(20));
+    gitbutler_metrics::watcher_event_queued();
+    gitbutler_metrics::watcher_event_queued();
+    gitbutler_metrics::watcher_event_handled();
+    gitbutler_metrics::count_workdir_cache(8, 2);
+
+    let rendered = gitbutler_metrics::render();
+    assert_eq!(
+        sample(
+            &rendered,
+            "gitbutler_operations_total{operation=\"CreateCommit\"}"
+        ),
+        2.0
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "gitbutler_operations_total{operation=\"Say \\\"hi\\\"\"}"
+        ),
+        1.0
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "gitbutler_status_duration_seconds_bucket{le=\"0.01\"}"
+        ),
+        0.0
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "gitbutler_status_duration_seconds_bucket{le=\"0.025\"}"
+        ),
+        1.0
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "gitbutler_status_duration_seconds_bucket{le=\"10\"}"
+        ),
+        1.0
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "gitbutler_status_duration_seconds_bucket{le=\"+Inf\"}"
+        ),
+        2.0
+    );
+    assert_eq!(
+        sample(&rendered, "gitbutler_status_duration_seconds_count"),
+        2.0
+    );
+    assert!((sample(&rendered, "gitbutler_status_duration_seconds_sum") - 20.02).abs() < 1e-9);
+    assert_eq!(sample(&rendered, "gitbutler_watcher_backlog"), 1.0);
+    assert_eq!(
+        sample(
+            &rendered,
+            "gitbutler_workdir_cache_files_total{result=\"hit\"}"
+        ),
+        8.0
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "gitbutler_workdir_cache_files_total{result=\"miss\"}"
+        ),
+        2.0
+    );
+}
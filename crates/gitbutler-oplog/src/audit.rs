@@ -0,0 +1,215 @@
+//! A local, append-only log of the high-level operations performed on a project, like commits and pushes,
+//! to find out what happened to a branch and when. It never leaves the machine.
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use gitbutler_branch::BranchId;
+use gitbutler_project::Project;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use serde::{Deserialize, Serialize};
+
+/// The name of the file in the `GitButler` directory of a project that holds one entry per line.
+pub const AUDIT_LOG_FILE_NAME: &str = "audit.jsonl";
+
+/// An operation that was performed on a project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum AuditOperation {
+    /// `commit` was created on the branch identified by `branch_id` and named `branch_name`.
+    #[serde(rename_all = "camelCase")]
+    CommitCreated {
+        branch_id: BranchId,
+        branch_name: String,
+        #[serde(with = "gitbutler_serde::oid")]
+        commit: git2::Oid,
+    },
+    /// The branch was applied to the workspace.
+    #[serde(rename_all = "camelCase")]
+    BranchApplied {
+        branch_id: BranchId,
+        branch_name: String,
+    },
+    /// The branch was removed from the workspace.
+    #[serde(rename_all = "camelCase")]
+    BranchUnapplied {
+        branch_id: BranchId,
+        branch_name: String,
+    },
+    /// The branch was pushed with `head` to `remote`, a remote tracking reference like `refs/remotes/origin/feature`.
+    #[serde(rename_all = "camelCase")]
+    Pushed {
+        branch_id: BranchId,
+        branch_name: String,
+        #[serde(with = "gitbutler_serde::oid")]
+        head: git2::Oid,
+        remote: String,
+        force: bool,
+    },
+    /// The snapshot `snapshot` of the operations log was restored, which includes undoing and redoing operations.
+    #[serde(rename_all = "camelCase")]
+    SnapshotRestored {
+        #[serde(with = "gitbutler_serde::oid")]
+        snapshot: git2::Oid,
+    },
+}
+
+/// The kind of an [`AuditOperation`], to query entries by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditOperationKind {
+    CommitCreated,
+    BranchApplied,
+    BranchUnapplied,
+    Pushed,
+    SnapshotRestored,
+}
+
+impl AuditOperation {
+    /// Return the kind of this operation.
+    pub fn kind(&self) -> AuditOperationKind {
+        match self {
+            AuditOperation::CommitCreated { .. } => AuditOperationKind::CommitCreated,
+            AuditOperation::BranchApplied { .. } => AuditOperationKind::BranchApplied,
+            AuditOperation::BranchUnapplied { .. } => AuditOperationKind::BranchUnapplied,
+            AuditOperation::Pushed { .. } => AuditOperationKind::Pushed,
+            AuditOperation::SnapshotRestored { .. } => AuditOperationKind::SnapshotRestored,
+        }
+    }
+}
+
+/// An entry of the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// The time at which the operation was performed, in milliseconds since the Unix epoch.
+    pub timestamp_ms: i64,
+    /// The Git identity of the one who performed the operation, like `Jane <jane@example.com>`, if configured.
+    pub actor: Option<String>,
+    /// What was done.
+    pub operation: AuditOperation,
+}
+
+/// Selects entries of the audit log. Entries match if they match all set fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuditQuery {
+    /// The earliest time of entries to return, in milliseconds since the Unix epoch, inclusive.
+    pub since_ms: Option<i64>,
+    /// The latest time of entries to return, in milliseconds since the Unix epoch, exclusive.
+    pub until_ms: Option<i64>,
+    /// The kinds of operations to return, or all of them if empty.
+    pub kinds: Vec<AuditOperationKind>,
+    /// The amount of entries to return at most, the most recent ones if there are more.
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.since_ms
+            .map_or(true, |since| entry.timestamp_ms >= since)
+            && self
+                .until_ms
+                .map_or(true, |until| entry.timestamp_ms < until)
+            && (self.kinds.is_empty() || self.kinds.contains(&entry.operation.kind()))
+    }
+}
+
+/// A handle to the audit log of a project.
+///
+/// The log is created when the first entry is recorded.
+pub struct AuditLogHandle {
+    /// The path to the file with one entry per line.
+    file_path: PathBuf,
+}
+
+impl AuditLogHandle {
+    /// Creates a new handle to the audit log stored in `base_path`.
+    pub fn new<P: AsRef<Path>>(base_path: P) -> Self {
+        let file_path = base_path.as_ref().join(AUDIT_LOG_FILE_NAME);
+        Self { file_path }
+    }
+
+    /// Appends `operation`, performed by `actor` just now, to the log and returns its entry.
+    ///
+    /// Errors if the file cannot be written.
+    pub fn record(&self, actor: Option<String>, operation: AuditOperation) -> Result<AuditEntry> {
+        let entry = AuditEntry {
+            timestamp_ms: now_since_unix_epoch_ms(),
+            actor,
+            operation,
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.file_path)
+            .with_context(|| format!("failed to open {}", self.file_path.display()))?;
+        // Start a line of its own if the last one was cut short, so only that entry is lost.
+        if !ends_with_newline(&mut file)? {
+            line.insert(0, b'\n');
+        }
+        // A single write keeps lines whole even if other processes append at the same time.
+        file.write_all(&line)?;
+        Ok(entry)
+    }
+
+    /// Returns the entries matching `query`, oldest first.
+    ///
+    /// Lines that can't be read, like a line that was cut short by a crash, are skipped.
+    /// Errors if the file exists but cannot be read.
+    pub fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+        let file = match fs::File::open(&self.file_path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<AuditEntry>(&line) {
+                Ok(entry) if query.matches(&entry) => entries.push(entry),
+                Ok(_) => {}
+                Err(err) => tracing::warn!(?err, "skipping unreadable audit log entry"),
+            }
+        }
+        if let Some(limit) = query.limit {
+            entries.drain(..entries.len().saturating_sub(limit));
+        }
+        Ok(entries)
+    }
+}
+
+/// Return `true` if `file` is empty or its last byte is a newline.
+fn ends_with_newline(file: &mut fs::File) -> Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+    file.seek(SeekFrom::End(-1))?;
+    let mut last = [0];
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+/// Record that `operation` was performed on `project` by the Git identity configured in `repo`.
+/// Failures are only logged as the operation already happened.
+pub fn record_operation(project: &Project, repo: &git2::Repository, operation: AuditOperation) {
+    let actor = repo.signature().ok().map(|signature| {
+        format!(
+            "{} <{}>",
+            String::from_utf8_lossy(signature.name_bytes()),
+            String::from_utf8_lossy(signature.email_bytes())
+        )
+    });
+    if let Err(err) = AuditLogHandle::new(project.gb_dir()).record(actor, operation) {
+        tracing::warn!(project_id = %project.id, ?err, "failed to record operation in audit log");
+    }
+}
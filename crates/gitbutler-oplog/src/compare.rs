@@ -0,0 +1,138 @@
+//! Compare the virtual branches of any two snapshots, to tell which branches an operation created,
+//! deleted or changed, and which hunks it moved between branches.
+use std::{collections::HashMap, path::PathBuf};
+
+use gitbutler_branch::{Branch, BranchId, VirtualBranchesState};
+use gitbutler_diff::FileDiff;
+use serde::Serialize;
+
+/// What differs between two snapshots.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotsDiff {
+    /// The changes to the files of the worktree, with all applied branches merged.
+    pub files: HashMap<PathBuf, FileDiff>,
+    /// The virtual branches that were created, deleted or changed, ordered by their name.
+    pub branches: Vec<BranchChange>,
+    /// The uncommitted hunks that are owned by another branch than before.
+    pub moved_hunks: Vec<MovedHunk>,
+}
+
+/// How a virtual branch differs between two snapshots.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum BranchChange {
+    Created {
+        id: BranchId,
+        name: String,
+    },
+    Deleted {
+        id: BranchId,
+        name: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Changed {
+        id: BranchId,
+        name: String,
+        /// The name of the branch before, if it was renamed.
+        previous_name: Option<String>,
+        /// `true` if the commits of the branch changed.
+        commits_changed: bool,
+        /// Whether the branch is in the workspace now, if it was applied or unapplied.
+        applied: Option<bool>,
+    },
+}
+
+/// An uncommitted hunk that is owned by another branch than before.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MovedHunk {
+    pub path: PathBuf,
+    /// The lines of the hunk, as `start-end`.
+    pub hunk: String,
+    pub from: BranchId,
+    pub to: BranchId,
+}
+
+/// Compare the states of the virtual branches `old` and `new`, with `files` being how the worktree changed.
+pub(crate) fn diff_states(
+    files: HashMap<PathBuf, FileDiff>,
+    old: &VirtualBranchesState,
+    new: &VirtualBranchesState,
+) -> SnapshotsDiff {
+    let mut branches = Vec::new();
+    for branch in old.branches() {
+        if new.try_branch(branch.id).is_none() {
+            branches.push(BranchChange::Deleted {
+                id: branch.id,
+                name: branch.name.clone(),
+            });
+        }
+    }
+    for branch in new.branches() {
+        let Some(before) = old.try_branch(branch.id) else {
+            branches.push(BranchChange::Created {
+                id: branch.id,
+                name: branch.name.clone(),
+            });
+            continue;
+        };
+        let previous_name = (before.name != branch.name).then(|| before.name.clone());
+        let commits_changed = before.head != branch.head;
+        let applied = (is_applied(before) != is_applied(branch)).then(|| is_applied(branch));
+        if previous_name.is_some() || commits_changed || applied.is_some() {
+            branches.push(BranchChange::Changed {
+                id: branch.id,
+                name: branch.name.clone(),
+                previous_name,
+                commits_changed,
+                applied,
+            });
+        }
+    }
+    branches.sort_by(|a, b| name(a).cmp(name(b)));
+
+    SnapshotsDiff {
+        files,
+        branches,
+        moved_hunks: moved_hunks(old, new),
+    }
+}
+
+fn moved_hunks(old: &VirtualBranchesState, new: &VirtualBranchesState) -> Vec<MovedHunk> {
+    let mut moved = Vec::new();
+    for branch in new.branches() {
+        for claim in &branch.ownership.claims {
+            for hunk in &claim.hunks {
+                let previous_owner = old.branches().find(|before| {
+                    before.ownership.claims.iter().any(|before_claim| {
+                        before_claim.file_path == claim.file_path
+                            && before_claim.hunks.contains(hunk)
+                    })
+                });
+                if let Some(before) = previous_owner.filter(|before| before.id != branch.id) {
+                    moved.push(MovedHunk {
+                        path: claim.file_path.clone(),
+                        hunk: format!("{}-{}", hunk.start, hunk.end),
+                        from: before.id,
+                        to: branch.id,
+                    });
+                }
+            }
+        }
+    }
+    moved.sort_by(|a, b| (&a.path, &a.hunk).cmp(&(&b.path, &b.hunk)));
+    moved
+}
+
+fn is_applied(branch: &Branch) -> bool {
+    branch.in_workspace && !branch.is_old_unapplied()
+}
+
+fn name(change: &BranchChange) -> &str {
+    match change {
+        BranchChange::Created { name, .. }
+        | BranchChange::Deleted { name, .. }
+        | BranchChange::Changed { name, .. } => name,
+    }
+}
@@ -64,6 +64,22 @@ impl SnapshotDetails {
         self.trailers = trailers;
         self
     }
+
+    /// Return a human-readable description of the operation, like `Create commit "fix typo"`,
+    /// using the commit message or branch name recorded in the trailers, if any.
+    pub fn description(&self) -> String {
+        let label = self.operation.label();
+        let subject = self
+            .trailers
+            .iter()
+            .find(|t| t.key == "message" || t.key == "name")
+            .and_then(|t| t.value.lines().next())
+            .filter(|subject| !subject.is_empty());
+        match subject {
+            Some(subject) => format!("{label} \"{subject}\""),
+            None => label,
+        }
+    }
 }
 
 impl FromStr for SnapshotDetails {
@@ -147,7 +163,9 @@ pub enum OperationKind {
     UndoCommit,
     UnapplyBranch,
     CherryPick,
+    RevertCommit,
     SquashCommit,
+    SplitCommit,
     UpdateCommitMessage,
     MoveCommit,
     RestoreFromSnapshot,
@@ -155,10 +173,75 @@ pub enum OperationKind {
     InsertBlankCommit,
     MoveCommitFile,
     FileChanges,
+    ResolveConflict,
+    ShelveChanges,
+    UnshelveChanges,
+    DeleteBranches,
+    UnapplyBranches,
+    ApplyBranches,
+    ArchiveBranches,
+    ImportStash,
+    ApplyBranchPartially,
+    PinBranchBase,
+    RebaseBranchOntoTarget,
+    StackBranch,
+    ExportToGit,
+    ApplyLayout,
+    NormalizeCommitTimes,
+    AbsorbHunks,
+    CreateBranchFromSnapshot,
+    ReconcileExternalChanges,
+    RestoreFromTrash,
+    ImportPatch,
     #[default]
     Unknown,
 }
 
+impl OperationKind {
+    /// Return a human-readable label, like `Create commit` for [`CreateCommit`](Self::CreateCommit).
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        for (idx, c) in self.to_string().chars().enumerate() {
+            if idx > 0 && c.is_ascii_uppercase() {
+                label.push(' ');
+                label.push(c.to_ascii_lowercase());
+            } else {
+                label.push(c);
+            }
+        }
+        label
+    }
+
+    /// Return `true` if this operation creates a new commit.
+    pub fn creates_commit(&self) -> bool {
+        matches!(self, OperationKind::CreateCommit)
+    }
+
+    /// Return `true` if this operation rewrites existing commits, like by rebasing them.
+    pub fn rewrites_commits(&self) -> bool {
+        matches!(
+            self,
+            OperationKind::MergeUpstream
+                | OperationKind::UpdateWorkspaceBase
+                | OperationKind::AmendCommit
+                | OperationKind::UndoCommit
+                | OperationKind::CherryPick
+                | OperationKind::SquashCommit
+                | OperationKind::SplitCommit
+                | OperationKind::UpdateCommitMessage
+                | OperationKind::MoveCommit
+                | OperationKind::ReorderCommit
+                | OperationKind::InsertBlankCommit
+                | OperationKind::MoveCommitFile
+                | OperationKind::PinBranchBase
+                | OperationKind::RebaseBranchOntoTarget
+                | OperationKind::StackBranch
+                | OperationKind::NormalizeCommitTimes
+                | OperationKind::AbsorbHunks
+        )
+    }
+}
+
 impl From<OperationKind> for SnapshotDetails {
     fn from(value: OperationKind) -> Self {
         SnapshotDetails::new(value)
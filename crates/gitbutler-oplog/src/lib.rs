@@ -1,10 +1,23 @@
+mod audit;
+pub use audit::{
+    record_operation, AuditEntry, AuditLogHandle, AuditOperation, AuditOperationKind, AuditQuery,
+    AUDIT_LOG_FILE_NAME,
+};
+mod compare;
+pub use compare::{BranchChange, MovedHunk, SnapshotsDiff};
 pub mod entry;
 mod oplog;
 pub use oplog::OplogExt;
 mod reflog;
+mod retention;
+pub use retention::GcOutcome;
 mod snapshot;
 pub use snapshot::SnapshotExt;
 mod state;
+mod time_travel;
+pub use time_travel::{SnapshotBranch, SnapshotCommit, SnapshotView};
+mod undo;
+pub use undo::UndoRedoState;
 
 /// The name of the file holding our state, useful for watching for changes.
 pub const OPLOG_FILE_NAME: &str = "operations-log.toml";
@@ -1,14 +1,23 @@
 use std::{
     collections::{hash_map::Entry, HashMap},
     fs,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     str::{from_utf8, FromStr},
     time::Duration,
 };
 
+#[cfg(unix)]
+use std::{
+    ffi::OsStr,
+    os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+};
+
 use anyhow::{anyhow, bail, Context, Result};
 use git2::{DiffOptions, FileMode};
-use gitbutler_branch::{Branch, SignaturePurpose, VirtualBranchesHandle, VirtualBranchesState};
+use gitbutler_branch::{
+    Branch, BranchId, SignaturePurpose, VirtualBranchesHandle, VirtualBranchesState,
+};
+use gitbutler_command_context::cancellation;
 use gitbutler_diff::{hunks_by_filepath, FileDiff};
 use gitbutler_project::{
     access::{WorktreeReadPermission, WorktreeWritePermission},
@@ -18,9 +27,14 @@ use gitbutler_repo::RepositoryExt;
 use tracing::instrument;
 
 use super::{
+    audit::{self, AuditOperation},
+    compare::{self, SnapshotsDiff},
     entry::{OperationKind, Snapshot, SnapshotDetails, Trailer},
     reflog::set_reference_to_oplog,
-    state::OplogHandle,
+    retention::{self, GcOutcome},
+    state::{CommitsTree, OplogHandle, SnapshotIndex, SnapshotIndexHandle},
+    time_travel::SnapshotView,
+    undo::{self, UndoRedoState, REDONE_TRAILER, UNDONE_TRAILER},
 };
 
 const SNAPSHOT_FILE_LIMIT_BYTES: u64 = 32 * 1024 * 1024;
@@ -57,7 +71,8 @@ pub trait OplogExt {
     /// restorable with [`restore_snapshot`](Self::restore_snapshot).
     ///
     /// Returns `Some(snapshot_commit_id)` if it was created or `None` if nothing changed between the previous oplog
-    /// commit and the current one (after comparing trees).
+    /// commit and the current one (after comparing trees), or if the [snapshot triggers](Project::snapshot_triggers)
+    /// of the project exclude the operation.
     fn commit_snapshot(
         &self,
         snapshot_tree_id: git2::Oid,
@@ -70,7 +85,8 @@ pub trait OplogExt {
     /// [`commit_snapshot`](Self::commit_snapshot).
     ///
     /// Returns `Some(snapshot_commit_id)` if it was created or `None` if nothing changed between the previous oplog
-    /// commit and the current one (after comparing trees).
+    /// commit and the current one (after comparing trees), or if the [snapshot triggers](Project::snapshot_triggers)
+    /// of the project exclude the operation.
     ///
     /// Note that errors in snapshot creation is typically ignored, so we want to learn about them.
     fn create_snapshot(
@@ -106,8 +122,54 @@ pub trait OplogExt {
     ///
     /// If there are files that are untracked and larger than `SNAPSHOT_FILE_LIMIT_BYTES`, they are excluded from snapshot creation and restoring.
     /// Returns the sha of the created revert snapshot commit or None if snapshots are disabled.
+    ///
+    /// If the [current operation](gitbutler_command_context::cancellation::current()) is cancelled while the state
+    /// before the restore is recorded, this fails with `Code::Cancelled` without having changed anything.
+    /// Once the worktree is changed, the restore always completes.
     fn restore_snapshot(&self, snapshot_commit_id: git2::Oid) -> Result<Option<git2::Oid>>;
 
+    /// Undoes the most recent operation that wasn't undone yet, like creating or amending a commit,
+    /// by restoring the snapshot taken right before it. Snapshots of worktree changes are skipped.
+    /// Upon success, a new snapshot is created representing the state right before this call.
+    ///
+    /// Returns a human-readable description of the operation that was undone, or `None` if there is nothing to undo.
+    fn undo(&self) -> Result<Option<String>>;
+
+    /// Redoes the operation that was undone most recently by restoring the snapshot taken right before the undo.
+    /// This is only possible as long as no other operation was performed since.
+    /// Upon success, a new snapshot is created representing the state right before this call.
+    ///
+    /// Returns a human-readable description of the operation that was redone, or `None` if there is nothing to redo.
+    fn redo(&self) -> Result<Option<String>>;
+
+    /// Returns the descriptions of the operations that [`undo`](Self::undo) and [`redo`](Self::redo) would affect next.
+    fn undo_redo_state(&self) -> Result<UndoRedoState>;
+
+    /// Restores the files and directories at `paths`, relative to the worktree, to their state in the snapshot
+    /// `snapshot_commit_id`, leaving everything else as is. Paths that didn't exist in the snapshot are removed,
+    /// while files within restored directories that aren't in the snapshot are kept.
+    /// Upon success, a new snapshot is created representing the state right before this call.
+    ///
+    /// Returns the sha of the created snapshot commit or None if snapshots are disabled.
+    fn restore_paths(
+        &self,
+        snapshot_commit_id: git2::Oid,
+        paths: &[PathBuf],
+    ) -> Result<Option<git2::Oid>>;
+
+    /// Restores the virtual branch identified by `branch_id` to its state in the snapshot `snapshot_commit_id`,
+    /// including its commits and uncommitted changes, and brings it back if it was deleted since.
+    /// All other branches and their changes are left as is.
+    /// Upon success, a new snapshot is created representing the state right before this call.
+    ///
+    /// This fails if the branch was applied in the snapshot and the target was updated since.
+    /// Returns the sha of the created snapshot commit or None if snapshots are disabled.
+    fn restore_branch(
+        &self,
+        snapshot_commit_id: git2::Oid,
+        branch_id: BranchId,
+    ) -> Result<Option<git2::Oid>>;
+
     /// Determines if a new snapshot should be created due to file changes being created since the last snapshot.
     /// The needs for the automatic snapshotting are:
     ///  - It needs to facilitate backup of work in progress code
@@ -119,9 +181,11 @@ pub trait OplogExt {
     ///
     /// This implementation returns `true` on the following conditions:
     ///  - Head is pointing to the integration branch.
-    ///  - If it's been more than 5 minutes since the last snapshot,
+    ///  - The [snapshot triggers](Project::snapshot_triggers) of the project snapshot changes to the worktree at all.
+    ///  - If it's been longer than `check_if_last_snapshot_older_than` since the last snapshot,
     ///    check the sum of added and removed lines since the last snapshot, otherwise return `false`.
-    ///      * If the sum of added and removed lines is greater than a configured threshold, return `true`, otherwise return `false`.
+    ///      * If the sum of added and removed lines is greater than a configured threshold, or if any lines
+    ///        changed and the configured snapshot interval passed, return `true`, otherwise return `false`.
     fn should_auto_snapshot(&self, check_if_last_snapshot_older_than: Duration) -> Result<bool>;
 
     /// Returns the diff of the snapshot and it's parent. It only includes the workdir changes.
@@ -129,8 +193,30 @@ pub trait OplogExt {
     /// This is useful to show what has changed in this particular snapshot
     fn snapshot_diff(&self, sha: git2::Oid) -> Result<HashMap<PathBuf, FileDiff>>;
 
+    /// Returns what differs between the snapshots `old` and `new`, which can be any two snapshots: the
+    /// changes to the worktree with all applied branches merged, and to the virtual branches and the
+    /// hunks they own.
+    ///
+    /// This is useful to audit what the operations in between changed before restoring a snapshot.
+    fn diff_snapshots(&self, old: git2::Oid, new: git2::Oid) -> Result<SnapshotsDiff>;
+
+    /// Returns the id of the tree of the worktree in the snapshot `snapshot_commit_id`, with all its applied
+    /// branches merged.
+    fn snapshot_worktree_tree(&self, snapshot_commit_id: git2::Oid) -> Result<git2::Oid>;
+
+    /// Opens the project as of the snapshot `snapshot_commit_id` to list its branches, files and diffs
+    /// without touching the worktree, for instance to browse history before restoring a snapshot.
+    fn snapshot_view(&self, snapshot_commit_id: git2::Oid) -> Result<SnapshotView>;
+
     /// Gets the sha of the last snapshot commit if present.
     fn oplog_head(&self) -> Result<Option<git2::Oid>>;
+
+    /// Drops the snapshots that exceed the [retention policy](gitbutler_project::Settings::snapshot_retention) of the project,
+    /// and runs `git gc` to reclaim the space they took.
+    ///
+    /// The ids of the remaining snapshots change as they are rewritten to form a shorter chain.
+    /// Returns how many snapshots were dropped and how many bytes were reclaimed.
+    fn gc(&self) -> Result<GcOutcome>;
 }
 
 impl OplogExt for Project {
@@ -144,6 +230,7 @@ impl OplogExt for Project {
         details: SnapshotDetails,
         perm: &mut WorktreeWritePermission,
     ) -> Result<Option<git2::Oid>> {
+        gitbutler_metrics::count_operation(&details.operation.to_string());
         commit_snapshot(self, snapshot_tree_id, details, perm)
     }
 
@@ -153,6 +240,10 @@ impl OplogExt for Project {
         details: SnapshotDetails,
         perm: &mut WorktreeWritePermission,
     ) -> Result<Option<git2::Oid>> {
+        gitbutler_metrics::count_operation(&details.operation.to_string());
+        if !is_triggered_by(self, details.operation) {
+            return Ok(None);
+        }
         let tree_id = prepare_snapshot(self, perm.read_permission())?;
         commit_snapshot(self, tree_id, details, perm)
     }
@@ -256,13 +347,101 @@ impl OplogExt for Project {
     }
 
     fn restore_snapshot(&self, snapshot_commit_id: git2::Oid) -> Result<Option<git2::Oid>> {
-        let mut guard = self.exclusive_worktree_access();
-        restore_snapshot(self, snapshot_commit_id, guard.write_permission())
+        let mut guard = self.exclusive_operation_access("restore_snapshot")?;
+        restore_snapshot(
+            self,
+            snapshot_commit_id,
+            "Restored from snapshot",
+            vec![],
+            guard.write_permission(),
+        )
+    }
+
+    fn undo(&self) -> Result<Option<String>> {
+        let mut guard = self.exclusive_operation_access("undo")?;
+        let chain = snapshot_details_chain(self)?;
+        let Some(index) = undo::stack(chain.iter().map(|(_, details)| details.as_ref())).undo
+        else {
+            return Ok(None);
+        };
+        let (snapshot_id, details) = &chain[index];
+        let description = describe(details.as_ref());
+        restore_snapshot(
+            self,
+            *snapshot_id,
+            &format!("Undo {description}"),
+            vec![Trailer {
+                key: UNDONE_TRAILER.to_string(),
+                // The undo snapshot is added in front of the chain.
+                value: (index + 1).to_string(),
+            }],
+            guard.write_permission(),
+        )?;
+        Ok(Some(description))
+    }
+
+    fn redo(&self) -> Result<Option<String>> {
+        let mut guard = self.exclusive_operation_access("redo")?;
+        let chain = snapshot_details_chain(self)?;
+        let Some((index, undone)) =
+            undo::stack(chain.iter().map(|(_, details)| details.as_ref())).redo
+        else {
+            return Ok(None);
+        };
+        let description = describe(chain.get(undone).and_then(|(_, details)| details.as_ref()));
+        restore_snapshot(
+            self,
+            chain[index].0,
+            &format!("Redo {description}"),
+            vec![Trailer {
+                key: REDONE_TRAILER.to_string(),
+                value: (index + 1).to_string(),
+            }],
+            guard.write_permission(),
+        )?;
+        Ok(Some(description))
+    }
+
+    fn undo_redo_state(&self) -> Result<UndoRedoState> {
+        let chain = snapshot_details_chain(self)?;
+        let stack = undo::stack(chain.iter().map(|(_, details)| details.as_ref()));
+        let details_at = |index: usize| chain.get(index).and_then(|(_, details)| details.as_ref());
+        Ok(UndoRedoState {
+            undo: stack.undo.map(|index| describe(details_at(index))),
+            redo: stack.redo.map(|(_, undone)| describe(details_at(undone))),
+        })
+    }
+
+    fn restore_paths(
+        &self,
+        snapshot_commit_id: git2::Oid,
+        paths: &[PathBuf],
+    ) -> Result<Option<git2::Oid>> {
+        let mut guard = self.exclusive_operation_access("restore_paths")?;
+        restore_paths(self, snapshot_commit_id, paths, guard.write_permission())
+    }
+
+    fn restore_branch(
+        &self,
+        snapshot_commit_id: git2::Oid,
+        branch_id: BranchId,
+    ) -> Result<Option<git2::Oid>> {
+        let mut guard = self.exclusive_operation_access("restore_branch")?;
+        restore_branch(
+            self,
+            snapshot_commit_id,
+            branch_id,
+            guard.write_permission(),
+        )
     }
 
     fn should_auto_snapshot(&self, check_if_last_snapshot_older_than: Duration) -> Result<bool> {
+        if !self.snapshot_triggers.snapshots_worktree() {
+            return Ok(false);
+        }
         let last_snapshot_time = OplogHandle::new(&self.gb_dir()).modified_at()?;
-        if last_snapshot_time.elapsed()? <= check_if_last_snapshot_older_than {
+        let elapsed = last_snapshot_time.elapsed()?;
+        if elapsed <= check_if_last_snapshot_older_than {
             return Ok(false);
         }
 
@@ -270,39 +449,37 @@ impl OplogExt for Project {
         if repo.integration_ref_from_head().is_err() {
             return Ok(false);
         }
-        Ok(lines_since_snapshot(self, &repo)? > self.snapshot_lines_threshold())
+        Ok(self.snapshot_triggers.worktree_snapshot_is_due(
+            elapsed,
+            lines_since_snapshot(self, &repo)?,
+            self.snapshot_lines_threshold(),
+        ))
     }
 
     fn snapshot_diff(&self, sha: git2::Oid) -> Result<HashMap<PathBuf, FileDiff>> {
         let worktree_dir = self.path.as_path();
-        let repo = git2::Repository::init(worktree_dir)?;
+        let repo = git2::Repository::open(worktree_dir)?;
 
         let commit = repo.find_commit(sha)?;
+        diff_workdir_trees(&repo, worktree_dir, commit.parent(0)?.id(), commit.id())
+    }
 
-        let wd_tree_id = tree_from_applied_vbranches(&repo, commit.id())?;
-        let wd_tree = repo.find_tree(wd_tree_id)?;
-        let old_wd_tree_id = tree_from_applied_vbranches(&repo, commit.parent(0)?.id())?;
-        let old_wd_tree = repo.find_tree(old_wd_tree_id)?;
-
-        // Exclude files that are larger than the limit (eg. database.sql which may never be intended to be committed)
-        let files_to_exclude =
-            worktree_files_larger_than_limit_as_git2_ignore_rule(&repo, worktree_dir)?;
-        // In-memory, libgit2 internal ignore rule
-        repo.add_ignore_rule(&files_to_exclude)?;
-
-        let mut diff_opts = git2::DiffOptions::new();
-        diff_opts
-            .recurse_untracked_dirs(true)
-            .include_untracked(true)
-            .show_binary(true)
-            .ignore_submodules(true)
-            .show_untracked_content(true);
+    fn diff_snapshots(&self, old: git2::Oid, new: git2::Oid) -> Result<SnapshotsDiff> {
+        let worktree_dir = self.path.as_path();
+        let repo = git2::Repository::open(worktree_dir)?;
+        let files = diff_workdir_trees(&repo, worktree_dir, old, new)?;
+        let old_state = snapshot_state(&repo, &repo.find_commit(old)?.tree()?)?;
+        let new_state = snapshot_state(&repo, &repo.find_commit(new)?.tree()?)?;
+        Ok(compare::diff_states(files, &old_state, &new_state))
+    }
 
-        let diff =
-            repo.diff_tree_to_tree(Some(&old_wd_tree), Some(&wd_tree), Some(&mut diff_opts))?;
+    fn snapshot_worktree_tree(&self, snapshot_commit_id: git2::Oid) -> Result<git2::Oid> {
+        let repo = git2::Repository::open(self.path.as_path())?;
+        tree_from_applied_vbranches(&repo, snapshot_commit_id)
+    }
 
-        let hunks = hunks_by_filepath(None, &diff)?;
-        Ok(hunks)
+    fn snapshot_view(&self, snapshot_commit_id: git2::Oid) -> Result<SnapshotView> {
+        SnapshotView::open(self, snapshot_commit_id)
     }
 
     /// Gets the sha of the last snapshot commit if present.
@@ -310,6 +487,86 @@ impl OplogExt for Project {
         let oplog_state = OplogHandle::new(&self.gb_dir());
         oplog_state.oplog_head()
     }
+
+    fn gc(&self) -> Result<GcOutcome> {
+        let mut guard = self.exclusive_operation_access("gc_snapshots")?;
+        retention::gc(self, guard.write_permission())
+    }
+}
+
+/// Return the ids and details of all snapshots, most recent first.
+/// Like [`list_snapshots()`](OplogExt::list_snapshots()), this stops at merge commits.
+fn snapshot_details_chain(ctx: &Project) -> Result<Vec<(git2::Oid, Option<SnapshotDetails>)>> {
+    let Some(head_id) = OplogHandle::new(&ctx.gb_dir()).oplog_head()? else {
+        return Ok(vec![]);
+    };
+    let repo = git2::Repository::open(&ctx.path)?;
+    let mut chain = Vec::new();
+    let mut next = Some(repo.find_commit(head_id)?);
+    while let Some(commit) = next.take() {
+        if commit.parent_count() > 1 {
+            break;
+        }
+        let details = commit
+            .message()
+            .and_then(|msg| SnapshotDetails::from_str(msg).ok());
+        chain.push((commit.id(), details));
+        next = commit.parent(0).ok();
+    }
+    Ok(chain)
+}
+
+fn describe(details: Option<&SnapshotDetails>) -> String {
+    details.map_or_else(
+        || OperationKind::Unknown.label(),
+        SnapshotDetails::description,
+    )
+}
+
+/// Diff the worktrees of the snapshots `old` and `new`, with all their applied branches merged.
+fn diff_workdir_trees(
+    repo: &git2::Repository,
+    worktree_dir: &Path,
+    old: git2::Oid,
+    new: git2::Oid,
+) -> Result<HashMap<PathBuf, FileDiff>> {
+    let wd_tree_id = tree_from_applied_vbranches(repo, new)?;
+    let wd_tree = repo.find_tree(wd_tree_id)?;
+    let old_wd_tree_id = tree_from_applied_vbranches(repo, old)?;
+    let old_wd_tree = repo.find_tree(old_wd_tree_id)?;
+
+    // Exclude files that are larger than the limit (eg. database.sql which may never be intended to be committed)
+    let files_to_exclude =
+        worktree_files_larger_than_limit_as_git2_ignore_rule(repo, worktree_dir)?;
+    // In-memory, libgit2 internal ignore rule
+    repo.add_ignore_rule(&files_to_exclude)?;
+
+    let mut diff_opts = git2::DiffOptions::new();
+    diff_opts
+        .recurse_untracked_dirs(true)
+        .include_untracked(true)
+        .show_binary(true)
+        .ignore_submodules(true)
+        .show_untracked_content(true);
+
+    let diff = repo.diff_tree_to_tree(Some(&old_wd_tree), Some(&wd_tree), Some(&mut diff_opts))?;
+
+    let hunks = hunks_by_filepath(None, &diff)?;
+    Ok(hunks)
+}
+
+/// Read the state of the virtual branches from the tree of a snapshot.
+pub(crate) fn snapshot_state(
+    repo: &git2::Repository,
+    snapshot_tree: &git2::Tree,
+) -> Result<VirtualBranchesState> {
+    let vb_toml_entry = snapshot_tree
+        .get_name("virtual_branches.toml")
+        .context("failed to get virtual_branches.toml blob")?;
+    let vb_toml_blob = repo
+        .find_blob(vb_toml_entry.id())
+        .context("failed to convert virtual_branches tree entry to blob")?;
+    Ok(toml::from_str(from_utf8(vb_toml_blob.content())?)?)
 }
 
 /// Get a tree of the working dir (applied branches merged)
@@ -330,6 +587,38 @@ fn get_workdir_tree<'a>(
     Ok(wd_tree)
 }
 
+/// Write a tree with an entry for each commit between `head` and `target`, holding its tree and its
+/// data to recreate it, and return its id.
+fn write_commits_tree(
+    repo: &git2::Repository,
+    head: git2::Oid,
+    target: git2::Oid,
+) -> Result<git2::Oid> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head)?;
+    revwalk.hide(target)?;
+
+    let mut commits_tree_builder = repo.treebuilder(None)?;
+    for commit_id in revwalk {
+        let commit_id = commit_id?;
+        let commit = repo.find_commit(commit_id)?;
+        let commit_tree = commit.tree()?;
+
+        let mut commit_tree_builder = repo.treebuilder(None)?;
+        let commit_data_blob_id = repo.blob(&serialize_commit(&commit))?;
+        commit_tree_builder.insert("commit", commit_data_blob_id, FileMode::Blob.into())?;
+        commit_tree_builder.insert("tree", commit_tree.id(), FileMode::Tree.into())?;
+        let commit_tree_id = commit_tree_builder.write()?;
+
+        commits_tree_builder.insert(
+            commit_id.to_string(),
+            commit_tree_id,
+            FileMode::Tree.into(),
+        )?;
+    }
+    Ok(commits_tree_builder.write()?)
+}
+
 fn prepare_snapshot(ctx: &Project, _shared_access: &WorktreeReadPermission) -> Result<git2::Oid> {
     let worktree_dir = ctx.path.as_path();
     let repo = git2::Repository::open(worktree_dir)?;
@@ -346,7 +635,7 @@ fn prepare_snapshot(ctx: &Project, _shared_access: &WorktreeReadPermission) -> R
     let vb_blob_id = repo.blob(&vb_content)?;
 
     // Create a tree out of the conflicts state if present
-    let conflicts_tree_id = write_conflicts_tree(worktree_dir, &repo)?;
+    let conflicts_tree_id = write_conflicts_tree(&repo)?;
 
     // write out the index as a tree to store
     let mut index = repo.index()?;
@@ -362,6 +651,9 @@ fn prepare_snapshot(ctx: &Project, _shared_access: &WorktreeReadPermission) -> R
     // go through all virtual branches and create a subtree for each with the tree and any commits encoded
     let mut branches_tree_builder = repo.treebuilder(None)?;
     let mut head_tree_ids = Vec::new();
+    let index_state = SnapshotIndexHandle::new(&ctx.gb_dir());
+    let last_index = index_state.read();
+    let mut snapshot_index = SnapshotIndex::default();
 
     for branch in vb_state.list_branches_in_workspace()? {
         head_tree_ids.push(branch.tree);
@@ -371,31 +663,21 @@ fn prepare_snapshot(ctx: &Project, _shared_access: &WorktreeReadPermission) -> R
         let mut branch_tree_builder = repo.treebuilder(None)?;
         branch_tree_builder.insert("tree", branch.tree, FileMode::Tree.into())?;
 
-        // let's get all the commits between the branch head and the target
-        let mut revwalk = repo.revwalk()?;
-        revwalk.push(branch.head)?;
-        revwalk.hide(default_target_commit.id())?;
-
-        let mut commits_tree_builder = repo.treebuilder(None)?;
-        for commit_id in revwalk {
-            let commit_id = commit_id?;
-            let commit = repo.find_commit(commit_id)?;
-            let commit_tree = commit.tree()?;
-
-            let mut commit_tree_builder = repo.treebuilder(None)?;
-            let commit_data_blob_id = repo.blob(&serialize_commit(&commit))?;
-            commit_tree_builder.insert("commit", commit_data_blob_id, FileMode::Blob.into())?;
-            commit_tree_builder.insert("tree", commit_tree.id(), FileMode::Tree.into())?;
-            let commit_tree_id = commit_tree_builder.write()?;
-
-            commits_tree_builder.insert(
-                commit_id.to_string(),
-                commit_tree_id,
-                FileMode::Tree.into(),
-            )?;
-        }
-
-        let commits_tree_id = commits_tree_builder.write()?;
+        // branches that didn't change since the last snapshot have the same commits, which are
+        // reused as long as they are still in the object database.
+        let commits_tree_id = match last_index.commits_trees.iter().find(|commits| {
+            commits.head == branch.head
+                && commits.target == default_target_commit.id()
+                && repo.find_tree(commits.tree).is_ok()
+        }) {
+            Some(commits) => commits.tree,
+            None => write_commits_tree(&repo, branch.head, default_target_commit.id())?,
+        };
+        snapshot_index.commits_trees.push(CommitsTree {
+            head: branch.head,
+            target: default_target_commit.id(),
+            tree: commits_tree_id,
+        });
         branch_tree_builder.insert("commits", commits_tree_id, FileMode::Tree.into())?;
 
         let branch_tree_id = branch_tree_builder.write()?;
@@ -405,6 +687,11 @@ fn prepare_snapshot(ctx: &Project, _shared_access: &WorktreeReadPermission) -> R
             FileMode::Tree.into(),
         )?;
     }
+    if snapshot_index != last_index {
+        if let Err(err) = index_state.write(&snapshot_index) {
+            tracing::warn!(?err, "failed to write snapshot index");
+        }
+    }
 
     // also add the gitbutler/integration commit to the branches tree
     let head = repo.head()?;
@@ -445,12 +732,28 @@ fn prepare_snapshot(ctx: &Project, _shared_access: &WorktreeReadPermission) -> R
     Ok(tree_id)
 }
 
+/// Return `true` if the snapshot triggers of `project` allow snapshots of the `operation`.
+/// Only commit creation and operations that rewrite commits can be excluded.
+fn is_triggered_by(project: &Project, operation: OperationKind) -> bool {
+    let triggers = &project.snapshot_triggers;
+    if operation.creates_commit() {
+        triggers.before_commit
+    } else if operation.rewrites_commits() {
+        triggers.before_rebase
+    } else {
+        true
+    }
+}
+
 fn commit_snapshot(
     ctx: &Project,
     snapshot_tree_id: git2::Oid,
     details: SnapshotDetails,
     _exclusive_access: &mut WorktreeWritePermission,
 ) -> Result<Option<git2::Oid>> {
+    if !is_triggered_by(ctx, details.operation) {
+        return Ok(None);
+    }
     let repo = git2::Repository::open(ctx.path.as_path())?;
     let snapshot_tree = repo.find_tree(snapshot_tree_id)?;
 
@@ -484,15 +787,22 @@ fn commit_snapshot(
     Ok(Some(snapshot_commit_id))
 }
 
+/// Restore the snapshot `snapshot_commit_id` and record the state before as a snapshot with `title`,
+/// using `trailers` in addition to the ones describing the restored snapshot.
 fn restore_snapshot(
     ctx: &Project,
     snapshot_commit_id: git2::Oid,
+    title: &str,
+    trailers: Vec<Trailer>,
     exclusive_access: &mut WorktreeWritePermission,
 ) -> Result<Option<git2::Oid>> {
     let worktree_dir = ctx.path.as_path();
     let repo = git2::Repository::open(worktree_dir)?;
 
+    cancellation::check()?;
     let before_restore_snapshot_result = prepare_snapshot(ctx, exclusive_access.read_permission());
+    // Nothing was changed yet, and from here on nothing checks for cancellation.
+    cancellation::check()?;
     let snapshot_commit = repo.find_commit(snapshot_commit_id)?;
 
     let snapshot_tree = snapshot_commit.tree()?;
@@ -535,17 +845,7 @@ fn restore_snapshot(
         // walk through all the commits in the branch
         for commit_entry in commits_tree.iter() {
             // for each commit, recreate the commit from the commit data if it doesn't exist
-            if let Some(commit_id) = commit_entry.name() {
-                // check for the oid in the repo
-                let commit_oid = git2::Oid::from_str(commit_id)?;
-                if repo.find_commit(commit_oid).is_err() {
-                    // commit is not in the repo, let's build it from our data
-                    let new_commit_oid = deserialize_commit(&repo, &commit_entry)?;
-                    if new_commit_oid != commit_oid {
-                        bail!("commit id mismatch: failed to recreate a commit from its parts");
-                    }
-                }
-
+            if let Some(commit_oid) = reconstitute_commit(&repo, &commit_entry)? {
                 // if branch_name is 'integration', we need to create or update the gitbutler/integration branch
                 if branch_name == Some("integration") {
                     // TODO(ST): with `gitoxide`, just update the branch without this dance,
@@ -606,34 +906,162 @@ fn restore_snapshot(
     let mut index = repo.index()?;
     index.read_tree(&index_tree)?;
 
-    let restored_operation = snapshot_commit
-        .message()
-        .and_then(|msg| SnapshotDetails::from_str(msg).ok())
-        .map(|d| d.operation.to_string())
-        .unwrap_or_default();
-
     // create new snapshot
     let before_restore_snapshot_tree_id = before_restore_snapshot_result?;
-    let restored_date_ms = snapshot_commit.time().seconds() * 1000;
     let details = SnapshotDetails {
         version: Default::default(),
         operation: OperationKind::RestoreFromSnapshot,
-        title: "Restored from snapshot".to_string(),
+        title: title.to_string(),
+        body: None,
+        trailers: [restored_from_trailers(&snapshot_commit), trailers].concat(),
+    };
+    let snapshot = commit_snapshot(
+        ctx,
+        before_restore_snapshot_tree_id,
+        details,
+        exclusive_access,
+    )?;
+    audit::record_operation(
+        ctx,
+        &repo,
+        AuditOperation::SnapshotRestored {
+            snapshot: snapshot_commit_id,
+        },
+    );
+    Ok(snapshot)
+}
+
+fn restore_paths(
+    ctx: &Project,
+    snapshot_commit_id: git2::Oid,
+    paths: &[PathBuf],
+    exclusive_access: &mut WorktreeWritePermission,
+) -> Result<Option<git2::Oid>> {
+    let worktree_dir = ctx.path.as_path();
+    let repo = git2::Repository::open(worktree_dir)?;
+    for path in paths {
+        if path.is_absolute()
+            || path
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            bail!("path '{}' must be relative to the worktree", path.display());
+        }
+    }
+
+    let before_restore_snapshot_tree_id =
+        prepare_snapshot(ctx, exclusive_access.read_permission())?;
+    let snapshot_commit = repo.find_commit(snapshot_commit_id)?;
+    let workdir_tree = repo.find_tree(tree_from_applied_vbranches(&repo, snapshot_commit_id)?)?;
+    for path in paths {
+        restore_path(&repo, worktree_dir, &workdir_tree, path)
+            .with_context(|| format!("failed to restore '{}'", path.display()))?;
+    }
+
+    let mut trailers = restored_from_trailers(&snapshot_commit);
+    trailers.extend(paths.iter().map(|path| Trailer {
+        key: "restored_path".to_string(),
+        value: path.display().to_string(),
+    }));
+    let details = SnapshotDetails {
+        version: Default::default(),
+        operation: OperationKind::RestoreFromSnapshot,
+        title: "Restored files from snapshot".to_string(),
+        body: None,
+        trailers,
+    };
+    commit_snapshot(
+        ctx,
+        before_restore_snapshot_tree_id,
+        details,
+        exclusive_access,
+    )
+}
+
+fn restore_branch(
+    ctx: &Project,
+    snapshot_commit_id: git2::Oid,
+    branch_id: BranchId,
+    exclusive_access: &mut WorktreeWritePermission,
+) -> Result<Option<git2::Oid>> {
+    let worktree_dir = ctx.path.as_path();
+    let repo = git2::Repository::open(worktree_dir)?;
+
+    let snapshot_commit = repo.find_commit(snapshot_commit_id)?;
+    let snapshot_tree = snapshot_commit.tree()?;
+    let snapshot_state = snapshot_state(&repo, &snapshot_tree)?;
+    let branch = snapshot_state
+        .try_branch(branch_id)
+        .cloned()
+        .with_context(|| format!("branch {branch_id} is not in the snapshot"))?;
+
+    let vb_state = VirtualBranchesHandle::new(ctx.gb_dir());
+    let target = vb_state.get_default_target()?;
+    let applied = branch.in_workspace && !branch.is_old_unapplied();
+    if applied && snapshot_state.default_target().map(|target| target.sha) != Some(target.sha) {
+        bail!("the target was updated since the snapshot was taken, so the branch can't be restored on its own");
+    }
+    repo.integration_ref_from_head().context(
+        "We will not change a worktree which for some reason isn't on the integration branch",
+    )?;
+
+    let before_restore_snapshot_tree_id =
+        prepare_snapshot(ctx, exclusive_access.read_permission())?;
+
+    // make sure we reconstitute the commits of the branch that are not here for some reason
+    let branch_commits_path = Path::new("virtual_branches")
+        .join(branch_id.to_string())
+        .join("commits");
+    if let Ok(commits_tree_entry) = snapshot_tree.get_path(&branch_commits_path) {
+        let commits_tree = repo
+            .find_tree(commits_tree_entry.id())
+            .context("failed to convert commits tree entry to tree")?;
+        for commit_entry in commits_tree.iter() {
+            reconstitute_commit(&repo, &commit_entry)?;
+        }
+    }
+    repo.find_commit(branch.head)
+        .context("the commits of the branch are missing")?;
+
+    let target_tree = repo.find_commit(target.sha)?.tree()?;
+    let branch_trees = |branches: Vec<Branch>| branches.into_iter().map(|branch| branch.tree);
+    let old_workdir_tree_id = merge_branch_trees(
+        &repo,
+        target_tree.clone(),
+        branch_trees(vb_state.list_branches_in_workspace()?),
+    )?;
+    vb_state.set_branch(branch)?;
+    let new_workdir_tree_id = merge_branch_trees(
+        &repo,
+        target_tree,
+        branch_trees(vb_state.list_branches_in_workspace()?),
+    )?;
+
+    // only touch the files that differ because of the restored branch
+    let old_workdir_tree = repo.find_tree(old_workdir_tree_id)?;
+    let new_workdir_tree = repo.find_tree(new_workdir_tree_id)?;
+    let diff = repo.diff_tree_to_tree(Some(&old_workdir_tree), Some(&new_workdir_tree), None)?;
+    for delta in diff.deltas() {
+        for path in [delta.old_file().path(), delta.new_file().path()]
+            .into_iter()
+            .flatten()
+        {
+            restore_path(&repo, worktree_dir, &new_workdir_tree, path)
+                .with_context(|| format!("failed to restore '{}'", path.display()))?;
+        }
+    }
+
+    let mut trailers = restored_from_trailers(&snapshot_commit);
+    trailers.push(Trailer {
+        key: "restored_branch".to_string(),
+        value: branch_id.to_string(),
+    });
+    let details = SnapshotDetails {
+        version: Default::default(),
+        operation: OperationKind::RestoreFromSnapshot,
+        title: "Restored branch from snapshot".to_string(),
         body: None,
-        trailers: vec![
-            Trailer {
-                key: "restored_from".to_string(),
-                value: snapshot_commit_id.to_string(),
-            },
-            Trailer {
-                key: "restored_operation".to_string(),
-                value: restored_operation,
-            },
-            Trailer {
-                key: "restored_date".to_string(),
-                value: restored_date_ms.to_string(),
-            },
-        ],
+        trailers,
     };
     commit_snapshot(
         ctx,
@@ -643,6 +1071,96 @@ fn restore_snapshot(
     )
 }
 
+/// Return the trailers that describe the snapshot `snapshot_commit` that is restored from.
+fn restored_from_trailers(snapshot_commit: &git2::Commit) -> Vec<Trailer> {
+    let restored_operation = snapshot_commit
+        .message()
+        .and_then(|msg| SnapshotDetails::from_str(msg).ok())
+        .map(|d| d.operation.to_string())
+        .unwrap_or_default();
+    let restored_date_ms = snapshot_commit.time().seconds() * 1000;
+    vec![
+        Trailer {
+            key: "restored_from".to_string(),
+            value: snapshot_commit.id().to_string(),
+        },
+        Trailer {
+            key: "restored_operation".to_string(),
+            value: restored_operation,
+        },
+        Trailer {
+            key: "restored_date".to_string(),
+            value: restored_date_ms.to_string(),
+        },
+    ]
+}
+
+/// Make `path` in `worktree_dir` match its entry in `tree`, recursively for directories,
+/// or remove it if `tree` doesn't have it.
+fn restore_path(
+    repo: &git2::Repository,
+    worktree_dir: &Path,
+    tree: &git2::Tree,
+    path: &Path,
+) -> Result<()> {
+    let full_path = worktree_dir.join(path);
+    let entry = match tree.get_path(path) {
+        Ok(entry) => entry,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+            if full_path.is_dir() && !full_path.is_symlink() {
+                fs::remove_dir_all(&full_path)?;
+            } else if full_path.symlink_metadata().is_ok() {
+                fs::remove_file(&full_path)?;
+            }
+            return Ok(());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    match entry.kind() {
+        Some(git2::ObjectType::Tree) => {
+            if full_path.symlink_metadata().is_ok() && !full_path.is_dir() {
+                fs::remove_file(&full_path)?;
+            }
+            let subtree = repo.find_tree(entry.id())?;
+            for child in subtree.iter() {
+                if let Some(name) = child.name() {
+                    restore_path(repo, worktree_dir, tree, &path.join(name))?;
+                }
+            }
+        }
+        Some(git2::ObjectType::Blob) => {
+            if full_path.is_dir() && !full_path.is_symlink() {
+                fs::remove_dir_all(&full_path)?;
+            } else if full_path.symlink_metadata().is_ok() {
+                fs::remove_file(&full_path)?;
+            }
+            if let Some(parent) = full_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let blob = repo.find_blob(entry.id())?;
+            #[cfg(unix)]
+            let mode = entry.filemode();
+            #[cfg(unix)]
+            if mode == i32::from(FileMode::Link) {
+                let link_target = OsStr::from_bytes(blob.content());
+                std::os::unix::fs::symlink(link_target, &full_path)?;
+                return Ok(());
+            }
+            fs::write(
+                &full_path,
+                gitbutler_diff::filter::smudge(repo, path, blob.content())?,
+            )?;
+            #[cfg(unix)]
+            if mode == i32::from(FileMode::BlobExecutable) {
+                fs::set_permissions(&full_path, fs::Permissions::from_mode(0o755))?;
+            }
+        }
+        // submodules are left alone
+        _ => {}
+    }
+    Ok(())
+}
+
 /// Restore the state of .git/base_merge_parent and .git/conflicts from the snapshot
 /// Will remove those files if they are not present in the snapshot
 fn restore_conflicts_tree(snapshot_tree: &git2::Tree, repo: &git2::Repository) -> Result<()> {
@@ -675,11 +1193,8 @@ fn restore_conflicts_tree(snapshot_tree: &git2::Tree, repo: &git2::Repository) -
     Ok(())
 }
 
-fn write_conflicts_tree(
-    worktree_dir: &std::path::Path,
-    repo: &git2::Repository,
-) -> Result<git2::Oid> {
-    let git_dir = worktree_dir.join(".git");
+fn write_conflicts_tree(repo: &git2::Repository) -> Result<git2::Oid> {
+    let git_dir = repo.path();
     let merge_parent_path = git_dir.join("base_merge_parent");
     let merge_parent_blob = if merge_parent_path.exists() {
         let merge_parent_content = fs::read(merge_parent_path)?;
@@ -806,6 +1321,27 @@ fn serialize_commit(commit: &git2::Commit<'_>) -> Vec<u8> {
     [commit_header, b"\n", commit_message].concat()
 }
 
+/// Recreate the commit stored in `commit_entry` of a snapshot if it's not in the repository anymore,
+/// and return its id, or `None` if the entry isn't a commit.
+pub(crate) fn reconstitute_commit(
+    repo: &git2::Repository,
+    commit_entry: &git2::TreeEntry,
+) -> Result<Option<git2::Oid>> {
+    let Some(commit_id) = commit_entry.name() else {
+        return Ok(None);
+    };
+    // check for the oid in the repo
+    let commit_oid = git2::Oid::from_str(commit_id)?;
+    if repo.find_commit(commit_oid).is_err() {
+        // commit is not in the repo, let's build it from our data
+        let new_commit_oid = deserialize_commit(repo, commit_entry)?;
+        if new_commit_oid != commit_oid {
+            bail!("commit id mismatch: failed to recreate a commit from its parts");
+        }
+    }
+    Ok(Some(commit_oid))
+}
+
 /// we get the data from the blob entry and re-create a commit object from it,
 /// whose returned id should match the one we stored.
 fn deserialize_commit(
@@ -828,7 +1364,7 @@ fn deserialize_commit(
 }
 
 /// Creates a tree that is the merge of all applied branches from a given snapshot and returns the tree id.
-fn tree_from_applied_vbranches(
+pub(crate) fn tree_from_applied_vbranches(
     repo: &git2::Repository,
     snapshot_commit_id: git2::Oid,
 ) -> Result<git2::Oid> {
@@ -842,21 +1378,20 @@ fn tree_from_applied_vbranches(
         .find_tree(target_tree_entry.id())
         .context("failed to convert target tree entry to tree")?;
 
-    let vb_toml_entry = snapshot_tree
-        .get_name("virtual_branches.toml")
-        .context("failed to get virtual_branches.toml blob")?;
-    // virtual_branches.toml blob
-    let vb_toml_blob = repo
-        .find_blob(vb_toml_entry.id())
-        .context("failed to convert virtual_branches tree entry to blob")?;
-
-    let vbs_from_toml: VirtualBranchesState = toml::from_str(from_utf8(vb_toml_blob.content())?)?;
-    let applied_branch_trees: Vec<git2::Oid> = vbs_from_toml
+    let applied_branch_trees = snapshot_state(repo, &snapshot_tree)?
         .list_branches_in_workspace()?
-        .iter()
-        .map(|b| b.tree)
-        .collect();
+        .into_iter()
+        .map(|b| b.tree);
 
+    merge_branch_trees(repo, target_tree, applied_branch_trees)
+}
+
+/// Merges the trees of the applied branches `applied_branch_trees` onto `target_tree` and returns the tree id.
+fn merge_branch_trees<'repo>(
+    repo: &'repo git2::Repository,
+    target_tree: git2::Tree<'repo>,
+    applied_branch_trees: impl IntoIterator<Item = git2::Oid>,
+) -> Result<git2::Oid> {
     let mut workdir_tree_id = target_tree.id();
     let base_tree = target_tree;
     let mut current_ours = base_tree.clone();
@@ -27,14 +27,6 @@ pub(super) fn set_reference_to_oplog(
     target_commit_id: git2::Oid,
     oplog_commit_id: git2::Oid,
 ) -> Result<()> {
-    let reflog_file_path = worktree_dir
-        .join(".git")
-        .join("logs")
-        .join("refs")
-        .join("heads")
-        .join("gitbutler")
-        .join("target");
-
     let mut repo = gix::open_opts(
         worktree_dir,
         // We may override the username as we only write a specific commit log, unrelated to the user.
@@ -46,6 +38,15 @@ pub(super) fn set_reference_to_oplog(
             ]
         }),
     )?;
+    // The log of a branch is shared by all worktrees, so it's in the common dir of linked ones.
+    let reflog_file_path = repo
+        .common_dir()
+        .join("logs")
+        .join("refs")
+        .join("heads")
+        .join("gitbutler")
+        .join("target");
+
     // The check is here only to avoid unnecessary writes
     if repo.try_find_reference("gitbutler/target")?.is_none() {
         repo.refs.write_reflog = gix::refs::store::WriteReflog::Always;
@@ -83,7 +84,7 @@ fn standard_signature() -> gix::actor::SignatureRef<'static> {
     gix::actor::SignatureRef {
         name: GITBUTLER_COMMIT_AUTHOR_NAME.into(),
         email: GITBUTLER_COMMIT_AUTHOR_EMAIL.into(),
-        time: gix::date::Time::now_local_or_utc(),
+        time: gitbutler_branch::current_time(),
     }
 }
 
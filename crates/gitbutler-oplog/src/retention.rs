@@ -0,0 +1,186 @@
+//! Keep the operations log from growing without bounds by dropping old snapshots as configured
+//! in [`SnapshotRetention`], and reclaiming the space they took.
+use std::{
+    path::Path,
+    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+use gitbutler_branch::VirtualBranchesHandle;
+use gitbutler_git::ProcessEnv;
+use gitbutler_project::{access::WorktreeWritePermission, Project, SnapshotRetention};
+use serde::Serialize;
+
+use super::{reflog::set_reference_to_oplog, state::OplogHandle};
+
+/// What a garbage collection of the operations log did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcOutcome {
+    /// The amount of snapshots that were dropped.
+    pub pruned_snapshots: usize,
+    /// The amount of snapshots that are left.
+    pub kept_snapshots: usize,
+    /// The amount of bytes by which the object database of the repository shrank.
+    pub reclaimed_bytes: u64,
+}
+
+/// Drop the snapshots of `project` that exceed its retention policy, and repack the repository.
+///
+/// As snapshots form a chain, the kept snapshots are rewritten on top of the oldest kept one,
+/// which changes their ids. Objects of dropped snapshots are only removed by `git gc` once they
+/// are older than `gc.pruneExpire`, so the space they take may be reclaimed by a later collection.
+pub(crate) fn gc(project: &Project, _perm: &mut WorktreeWritePermission) -> Result<GcOutcome> {
+    let repo = git2::Repository::open(&project.path)?;
+    let objects_dir = repo.path().join("objects");
+    let size_before = dir_size(&objects_dir);
+
+    let mut outcome = GcOutcome::default();
+    let oplog_state = OplogHandle::new(&project.gb_dir());
+    if let Some(head_id) = oplog_state.oplog_head()? {
+        let chain = snapshot_chain(&repo, head_id)?;
+        let keep = retained_count(
+            &repo,
+            &chain,
+            &project.settings.snapshot_retention,
+            gitbutler_time::clock::now(),
+        )?;
+        outcome.kept_snapshots = keep;
+        outcome.pruned_snapshots = chain.len() - keep;
+        if outcome.pruned_snapshots > 0 {
+            let new_head_id = rewrite_chain(&repo, &chain[..keep])?;
+            oplog_state.set_oplog_head(new_head_id)?;
+            let vb_state = VirtualBranchesHandle::new(project.gb_dir());
+            let target_commit_id = vb_state.get_default_target()?.sha;
+            set_reference_to_oplog(&project.path, target_commit_id, new_head_id)?;
+        }
+    }
+
+    repack(project)?;
+    outcome.reclaimed_bytes = size_before.saturating_sub(dir_size(&objects_dir));
+    Ok(outcome)
+}
+
+/// Return the snapshot commits reachable from `head_id`, most recent first.
+///
+/// Like [`list_snapshots()`](crate::OplogExt::list_snapshots()), this stops at merge commits.
+fn snapshot_chain(repo: &git2::Repository, head_id: git2::Oid) -> Result<Vec<git2::Commit<'_>>> {
+    let mut chain = Vec::new();
+    let mut next = Some(repo.find_commit(head_id)?);
+    while let Some(commit) = next.take() {
+        if commit.parent_count() > 1 {
+            break;
+        }
+        next = commit.parent(0).ok();
+        chain.push(commit);
+    }
+    Ok(chain)
+}
+
+/// Return how many of the snapshots in `chain`, most recent first, satisfy all limits of `retention` at `now`.
+/// The most recent snapshot is always retained.
+fn retained_count(
+    repo: &git2::Repository,
+    chain: &[git2::Commit<'_>],
+    retention: &SnapshotRetention,
+    now: SystemTime,
+) -> Result<usize> {
+    let oldest_allowed = retention
+        .max_age()
+        .and_then(|max_age| now.checked_sub(max_age));
+    let mut total_size = 0;
+    for (count, commit) in chain.iter().enumerate() {
+        let created_at = UNIX_EPOCH + Duration::from_secs(commit.time().seconds().max(0) as u64);
+        let within_limits = retention.max_snapshots.map_or(true, |max| count < max)
+            && oldest_allowed.map_or(true, |oldest| created_at >= oldest)
+            && match retention.max_size_bytes {
+                Some(max_size) => {
+                    total_size += snapshot_size(repo, commit)?;
+                    total_size <= max_size
+                }
+                None => true,
+            };
+        if count > 0 && !within_limits {
+            return Ok(count);
+        }
+    }
+    Ok(chain.len())
+}
+
+/// Return the size of the blobs that `commit` added or changed compared to its parent, as an
+/// estimate of the space the snapshot takes.
+fn snapshot_size(repo: &git2::Repository, commit: &git2::Commit<'_>) -> Result<u64> {
+    let parent_tree = commit
+        .parent(0)
+        .ok()
+        .map(|parent| parent.tree())
+        .transpose()?;
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    let odb = repo.odb()?;
+    let mut size = 0;
+    for delta in diff.deltas() {
+        if matches!(delta.status(), git2::Delta::Deleted) {
+            continue;
+        }
+        let id = delta.new_file().id();
+        if let Ok((len, git2::ObjectType::Blob)) = odb.read_header(id) {
+            size += len as u64;
+        }
+    }
+    Ok(size)
+}
+
+/// Recreate the snapshots in `kept`, most recent first, with the oldest one having no parent,
+/// and return the id of the new head of the chain.
+fn rewrite_chain(repo: &git2::Repository, kept: &[git2::Commit<'_>]) -> Result<git2::Oid> {
+    let mut parent: Option<git2::Commit<'_>> = None;
+    for commit in kept.iter().rev() {
+        let message = String::from_utf8_lossy(commit.message_bytes());
+        let parents: Vec<_> = parent.iter().collect();
+        let id = repo.commit(
+            None,
+            &commit.author(),
+            &commit.committer(),
+            &message,
+            &commit.tree()?,
+            &parents,
+        )?;
+        parent = Some(repo.find_commit(id)?);
+    }
+    parent
+        .map(|head| head.id())
+        .context("at least one snapshot must be kept")
+}
+
+/// Run `git gc` so that objects which aren't reachable anymore are removed, and the rest is packed.
+fn repack(project: &Project) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.args(["gc", "--quiet"]).current_dir(&project.path);
+    ProcessEnv::new()
+        .extend(project.extra_env.clone())
+        .apply(&mut cmd);
+    let output = cmd.output().context("failed to run `git gc`")?;
+    if !output.status.success() {
+        bail!(
+            "`git gc` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Return the size of all files within `dir`, ignoring those that can't be read.
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
@@ -27,6 +27,7 @@ pub trait SnapshotExt {
         perm: &mut WorktreeWritePermission,
     ) -> anyhow::Result<()>;
 
+    /// Returns the id of the snapshot, or `None` if snapshots before commits are turned off.
     fn snapshot_commit_creation(
         &self,
         snapshot_tree: git2::Oid,
@@ -34,7 +35,7 @@ pub trait SnapshotExt {
         commit_message: String,
         sha: Option<git2::Oid>,
         perm: &mut WorktreeWritePermission,
-    ) -> anyhow::Result<()>;
+    ) -> anyhow::Result<Option<git2::Oid>>;
 
     fn snapshot_branch_creation(
         &self,
@@ -90,7 +91,7 @@ impl SnapshotExt for Project {
         commit_message: String,
         sha: Option<git2::Oid>,
         perm: &mut WorktreeWritePermission,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<Option<git2::Oid>> {
         let details = SnapshotDetails::new(OperationKind::CreateCommit).with_trailers(
             [
                 vec![
@@ -107,8 +108,7 @@ impl SnapshotExt for Project {
             ]
             .concat(),
         );
-        self.commit_snapshot(snapshot_tree, details, perm)?;
-        Ok(())
+        self.commit_snapshot(snapshot_tree, details, perm)
     }
     fn snapshot_branch_creation(
         &self,
@@ -173,7 +173,10 @@ impl SnapshotExt for Project {
                 ]
                 .concat(),
             )
-        } else if update.notes.is_some() {
+        } else if update.notes.is_some()
+            || update.description.is_some()
+            || update.reviewers.is_some()
+        {
             SnapshotDetails::new(OperationKind::UpdateBranchNotes)
         } else if let Some(order) = update.order {
             SnapshotDetails::new(OperationKind::ReorderBranches).with_trailers(
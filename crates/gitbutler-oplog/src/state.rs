@@ -4,7 +4,7 @@ use std::{
 };
 
 use anyhow::Result;
-use gitbutler_fs::read_toml_file_or_default;
+use gitbutler_fs::{read_toml_file_or_default, TomlFile};
 use serde::{Deserialize, Deserializer, Serialize};
 
 use super::OPLOG_FILE_NAME;
@@ -91,7 +91,51 @@ impl OplogHandle {
     }
 
     fn write_file(&self, mut oplog: Oplog) -> Result<()> {
-        oplog.modified_at = SystemTime::now();
+        oplog.modified_at = gitbutler_time::clock::now();
         gitbutler_fs::write(&self.file_path, toml::to_string(&oplog)?)
     }
 }
+
+/// The file next to the oplog state that holds the [`SnapshotIndex`].
+const SNAPSHOT_INDEX_FILE_NAME: &str = "snapshot-index.toml";
+
+/// What was written to the object database for the last snapshot, so the next one can reuse it
+/// without writing it again. It's only an optimization, so it may be lost or outdated at any time.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct SnapshotIndex {
+    /// The trees with the commits of each branch in the last snapshot.
+    #[serde(default)]
+    pub commits_trees: Vec<CommitsTree>,
+}
+
+/// The tree with the commits between `head` and `target`, as stored in a snapshot.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CommitsTree {
+    #[serde(with = "gitbutler_serde::oid")]
+    pub head: git2::Oid,
+    #[serde(with = "gitbutler_serde::oid")]
+    pub target: git2::Oid,
+    #[serde(with = "gitbutler_serde::oid")]
+    pub tree: git2::Oid,
+}
+
+pub(crate) struct SnapshotIndexHandle {
+    file: TomlFile<SnapshotIndex>,
+}
+
+impl SnapshotIndexHandle {
+    pub fn new(base_path: &Path) -> Self {
+        Self {
+            file: TomlFile::new(base_path.join(SNAPSHOT_INDEX_FILE_NAME)),
+        }
+    }
+
+    /// Reads the index, which is empty if it doesn't exist or can't be read.
+    pub fn read(&self) -> SnapshotIndex {
+        self.file.read().unwrap_or_default()
+    }
+
+    pub fn write(&self, index: &SnapshotIndex) -> Result<()> {
+        self.file.write(index)
+    }
+}
@@ -0,0 +1,252 @@
+//! A read-only view of a project as of one of its snapshots, to browse history before restoring any of it.
+//!
+//! Everything is answered from the snapshot alone. Commits that only the snapshot still has, and the trees
+//! of merges, are written to an in-memory object database, so neither the worktree nor the repository
+//! change.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{Context, Result};
+use gitbutler_branch::{BranchId, VirtualBranchesState};
+use gitbutler_diff::{hunks_by_filepath, FileDiff};
+use gitbutler_project::Project;
+use serde::Serialize;
+
+use super::{
+    entry::SnapshotDetails,
+    oplog::{reconstitute_commit, snapshot_state, tree_from_applied_vbranches},
+};
+
+/// The priority of the in-memory object database, above the ones on disk so it gets all writes.
+const MEMORY_ODB_PRIORITY: i32 = 1000;
+
+/// A virtual branch as of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotBranch {
+    pub id: BranchId,
+    pub name: String,
+    pub notes: String,
+    /// Whether the branch was in the workspace.
+    pub applied: bool,
+    pub order: usize,
+    #[serde(with = "gitbutler_serde::oid")]
+    pub head: git2::Oid,
+    /// The files with uncommitted changes the branch owned.
+    pub owned_files: Vec<PathBuf>,
+    /// The commits of the branch that aren't in the target, most recent first. It's empty if the branch wasn't
+    /// applied and its commits don't exist anymore.
+    pub commits: Vec<SnapshotCommit>,
+}
+
+/// A commit of a virtual branch as of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotCommit {
+    #[serde(with = "gitbutler_serde::oid")]
+    pub id: git2::Oid,
+    pub message: String,
+    pub author_name: String,
+    pub author_email: String,
+    /// The time of the commit in milliseconds since the epoch.
+    pub created_at: u128,
+    #[serde(with = "gitbutler_serde::oid_vec")]
+    pub parent_ids: Vec<git2::Oid>,
+}
+
+/// A project as of the snapshot it was opened at, see [`OplogExt::snapshot_view()`](crate::OplogExt::snapshot_view()).
+pub struct SnapshotView {
+    repo: git2::Repository,
+    snapshot_id: git2::Oid,
+    details: Option<SnapshotDetails>,
+    state: VirtualBranchesState,
+    target_tree: git2::Oid,
+}
+
+impl SnapshotView {
+    pub(crate) fn open(project: &Project, snapshot_id: git2::Oid) -> Result<Self> {
+        let repo = git2::Repository::open(&project.path)?;
+        repo.odb()?
+            .add_new_mempack_backend(MEMORY_ODB_PRIORITY)
+            .context("failed to keep the objects of the snapshot in memory")?;
+
+        let snapshot_commit = repo
+            .find_commit(snapshot_id)
+            .with_context(|| format!("there is no snapshot {snapshot_id}"))?;
+        let details = snapshot_commit
+            .message()
+            .and_then(|msg| SnapshotDetails::from_str(msg).ok());
+        let snapshot_tree = snapshot_commit.tree()?;
+        let state = snapshot_state(&repo, &snapshot_tree)?;
+        let target_tree = snapshot_tree
+            .get_name("target_tree")
+            .context("failed to get target tree entry")?
+            .id();
+
+        if let Some(vb_tree_entry) = snapshot_tree.get_name("virtual_branches") {
+            for branch_entry in repo.find_tree(vb_tree_entry.id())?.iter() {
+                let branch_tree = repo.find_tree(branch_entry.id())?;
+                let Some(commits_tree_entry) = branch_tree.get_name("commits") else {
+                    continue;
+                };
+                for commit_entry in repo.find_tree(commits_tree_entry.id())?.iter() {
+                    reconstitute_commit(&repo, &commit_entry)?;
+                }
+            }
+        }
+
+        drop(snapshot_tree);
+        drop(snapshot_commit);
+        Ok(SnapshotView {
+            repo,
+            snapshot_id,
+            details,
+            state,
+            target_tree,
+        })
+    }
+
+    /// The id of the snapshot this is a view of.
+    pub fn snapshot_id(&self) -> git2::Oid {
+        self.snapshot_id
+    }
+
+    /// What the snapshot was taken for, if it's known.
+    pub fn details(&self) -> Option<&SnapshotDetails> {
+        self.details.as_ref()
+    }
+
+    /// The virtual branches of the snapshot, those in the workspace first, in their order.
+    pub fn branches(&self) -> Result<Vec<SnapshotBranch>> {
+        let mut branches: Vec<_> = self.state.branches().collect();
+        branches.sort_by_key(|branch| (!branch.in_workspace, branch.order));
+        branches
+            .into_iter()
+            .map(|branch| {
+                Ok(SnapshotBranch {
+                    id: branch.id,
+                    name: branch.name.clone(),
+                    notes: branch.notes.clone(),
+                    applied: branch.in_workspace,
+                    order: branch.order,
+                    head: branch.head,
+                    owned_files: branch
+                        .ownership
+                        .claims
+                        .iter()
+                        .map(|claim| claim.file_path.clone())
+                        .collect(),
+                    commits: self.commits(branch.head)?,
+                })
+            })
+            .collect()
+    }
+
+    /// The paths of all files in the worktree of the snapshot, with all its applied branches merged.
+    pub fn files(&self) -> Result<Vec<PathBuf>> {
+        let tree = self.worktree_tree()?;
+        let mut files = Vec::new();
+        tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob) {
+                files.push(PathBuf::from(dir).join(entry.name().unwrap_or_default()));
+            }
+            git2::TreeWalkResult::Ok
+        })?;
+        Ok(files)
+    }
+
+    /// The contents of the file at `path` in the worktree of the snapshot, or `None` if it didn't exist.
+    pub fn file(&self, path: &Path) -> Result<Option<Vec<u8>>> {
+        let tree = self.worktree_tree()?;
+        let Ok(entry) = tree.get_path(path) else {
+            return Ok(None);
+        };
+        let blob = self
+            .repo
+            .find_blob(entry.id())
+            .with_context(|| format!("{} isn't a file", path.display()))?;
+        Ok(Some(blob.content().to_vec()))
+    }
+
+    /// The uncommitted changes of the branch `branch_id` in the snapshot.
+    pub fn branch_diff(&self, branch_id: BranchId) -> Result<HashMap<PathBuf, FileDiff>> {
+        let branch = self
+            .state
+            .try_branch(branch_id)
+            .with_context(|| format!("branch {branch_id} isn't in the snapshot"))?;
+        let base_tree = match self.repo.find_commit(branch.head) {
+            Ok(head) => head.tree()?,
+            Err(_) => self.repo.find_tree(self.target_tree)?,
+        };
+        let tree = self.repo.find_tree(branch.tree)?;
+        self.diff(Some(&base_tree), &tree)
+    }
+
+    /// The changes of the commit `commit_id` of a branch in the snapshot, compared to its first parent.
+    pub fn commit_diff(&self, commit_id: git2::Oid) -> Result<HashMap<PathBuf, FileDiff>> {
+        let commit = self
+            .repo
+            .find_commit(commit_id)
+            .with_context(|| format!("commit {commit_id} isn't in the snapshot"))?;
+        let parent_tree = commit
+            .parent(0)
+            .ok()
+            .map(|parent| parent.tree())
+            .transpose()?;
+        self.diff(parent_tree.as_ref(), &commit.tree()?)
+    }
+
+    fn worktree_tree(&self) -> Result<git2::Tree<'_>> {
+        let tree_id = tree_from_applied_vbranches(&self.repo, self.snapshot_id)?;
+        Ok(self.repo.find_tree(tree_id)?)
+    }
+
+    /// Return the commits from `head` to the target of the snapshot, or none if `head` doesn't exist anymore.
+    fn commits(&self, head: git2::Oid) -> Result<Vec<SnapshotCommit>> {
+        if self.repo.find_commit(head).is_err() {
+            return Ok(Vec::new());
+        }
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push(head)?;
+        if let Some(target) = self.state.default_target() {
+            if self.repo.find_commit(target.sha).is_ok() {
+                revwalk.hide(target.sha)?;
+            }
+        }
+        revwalk
+            .map(|commit_id| {
+                let commit = self.repo.find_commit(commit_id?)?;
+                let author = commit.author();
+                Ok(SnapshotCommit {
+                    id: commit.id(),
+                    message: String::from_utf8_lossy(commit.message_bytes()).into_owned(),
+                    author_name: String::from_utf8_lossy(author.name_bytes()).into_owned(),
+                    author_email: String::from_utf8_lossy(author.email_bytes()).into_owned(),
+                    created_at: u128::try_from(commit.time().seconds()).unwrap_or_default() * 1000,
+                    parent_ids: commit.parent_ids().collect(),
+                })
+            })
+            .collect()
+    }
+
+    fn diff(
+        &self,
+        old: Option<&git2::Tree>,
+        new: &git2::Tree,
+    ) -> Result<HashMap<PathBuf, FileDiff>> {
+        let mut diff_opts = git2::DiffOptions::new();
+        diff_opts
+            .recurse_untracked_dirs(true)
+            .include_untracked(true)
+            .show_binary(true)
+            .ignore_submodules(true)
+            .show_untracked_content(true);
+        let diff = self
+            .repo
+            .diff_tree_to_tree(old, Some(new), Some(&mut diff_opts))?;
+        hunks_by_filepath(None, &diff)
+    }
+}
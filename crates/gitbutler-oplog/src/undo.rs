@@ -0,0 +1,115 @@
+//! Interpret the snapshots of the oplog as an undo/redo stack.
+//!
+//! Undoing an operation restores the snapshot taken right before it, and redoing it restores the
+//! snapshot taken right before the undo. Both are recorded as snapshots themselves, with a trailer
+//! pointing at the snapshot they refer to as the amount of snapshots before them. Unlike ids,
+//! these offsets stay valid when [garbage collection](crate::OplogExt::gc()) rewrites the chain.
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::entry::{OperationKind, SnapshotDetails};
+
+/// The trailer of an undo snapshot, holding the offset to the snapshot of the operation it undid.
+pub(crate) const UNDONE_TRAILER: &str = "undone";
+/// The trailer of a redo snapshot, holding the offset to the undo snapshot it restored.
+pub(crate) const REDONE_TRAILER: &str = "redone";
+
+/// What [`undo()`](crate::OplogExt::undo()) and [`redo()`](crate::OplogExt::redo()) would do next.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoRedoState {
+    /// The description of the operation that would be undone, if there is one.
+    pub undo: Option<String>,
+    /// The description of the operation that would be redone, if there is one.
+    pub redo: Option<String>,
+}
+
+/// The next steps as indices into the snapshot chain, most recent first.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Stack {
+    /// The snapshot taken right before the operation to undo.
+    pub undo: Option<usize>,
+    /// The undo snapshot to restore to redo, and the snapshot of the operation it undid.
+    pub redo: Option<(usize, usize)>,
+}
+
+/// The role of a single snapshot in the stack.
+enum Step {
+    /// An undo of the operation at the given index.
+    Undo { undone: usize },
+    /// A redo of the undo at the given index.
+    Redo { redone: usize },
+    /// An operation that can be undone.
+    Operation,
+    /// A snapshot that isn't an operation, like one taken for changes to the worktree.
+    Other,
+}
+
+impl Step {
+    fn new(index: usize, details: Option<&SnapshotDetails>) -> Self {
+        let Some(details) = details else {
+            return Step::Other;
+        };
+        let target = |key: &str| {
+            details
+                .trailers
+                .iter()
+                .find(|t| t.key == key)
+                .and_then(|t| t.value.parse::<usize>().ok())
+                .map(|offset| index + offset)
+        };
+        match details.operation {
+            OperationKind::RestoreFromSnapshot => {
+                if let Some(undone) = target(UNDONE_TRAILER) {
+                    Step::Undo { undone }
+                } else if let Some(redone) = target(REDONE_TRAILER) {
+                    Step::Redo { redone }
+                } else {
+                    Step::Operation
+                }
+            }
+            OperationKind::FileChanges | OperationKind::Unknown => Step::Other,
+            // Failed operations didn't change anything worth undoing.
+            _ if details.trailers.iter().any(|t| t.key == "error") => Step::Other,
+            _ => Step::Operation,
+        }
+    }
+}
+
+/// Find the next steps in `chain`, the details of all snapshots with the most recent first.
+///
+/// Redo is only possible as long as no other operation was performed after undoing.
+pub(crate) fn stack<'a>(chain: impl IntoIterator<Item = Option<&'a SnapshotDetails>>) -> Stack {
+    let mut undone = HashSet::new();
+    let mut redone = HashSet::new();
+    let mut redo = None;
+    let mut can_redo = true;
+    for (index, details) in chain.into_iter().enumerate() {
+        match Step::new(index, details) {
+            Step::Undo { undone: operation } => {
+                if redone.contains(&index) {
+                    continue;
+                }
+                undone.insert(operation);
+                if can_redo && redo.is_none() {
+                    redo = Some((index, operation));
+                }
+            }
+            Step::Redo { redone: undo } => {
+                redone.insert(undo);
+            }
+            Step::Operation => {
+                can_redo = false;
+                if !undone.contains(&index) {
+                    return Stack {
+                        undo: Some(index),
+                        redo,
+                    };
+                }
+            }
+            Step::Other => {}
+        }
+    }
+    Stack { undo: None, redo }
+}
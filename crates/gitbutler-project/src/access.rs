@@ -1,7 +1,14 @@
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{bail, Context};
+use gitbutler_error::error::Code;
 use parking_lot::RawRwLock;
+use serde::{Deserialize, Serialize};
 
 use crate::{Project, ProjectId};
 
@@ -18,7 +25,7 @@ impl Project {
         //            the CLI.
         std::fs::remove_file(self.gb_dir().join("window.lock").as_os_str()).ok();
 
-        let mut lock = fslock::LockFile::open(self.gb_dir().join("project.lock").as_os_str())?;
+        let mut lock = fslock::LockFile::open(self.gb_dir().join(PROJECT_LOCK_FILE).as_os_str())?;
         let got_lock = lock
             .try_lock()
             .context("Failed to check if lock is taken")?;
@@ -38,23 +45,161 @@ impl Project {
     /// Note that this in-process locking works only under the assumption that no two instances of
     /// GitButler are able to read or write the same repository.
     pub fn exclusive_worktree_access(&self) -> WriteWorkspaceGuard {
-        let mut map = WORKTREE_LOCKS.lock();
         WriteWorkspaceGuard {
-            _inner: map.entry(self.id).or_default().write_arc(),
+            _operation: None,
+            _inner: self.worktree_lock().write_arc(),
             perm: WorktreeWritePermission(()),
         }
     }
 
+    /// Like [`exclusive_worktree_access()`](Self::exclusive_worktree_access()), but for the mutating
+    /// `operation`, which is also serialized with the operations of other GitButler processes on the project.
+    ///
+    /// Waits in line for up to [`OPERATION_LOCK_TIMEOUT`], and fails with [`ProjectBusy`] and
+    /// [`Code::ProjectBusy`] if the operation holding the project by then doesn't finish.
+    pub fn exclusive_operation_access(
+        &self,
+        operation: &str,
+    ) -> anyhow::Result<WriteWorkspaceGuard> {
+        self.exclusive_operation_access_within(operation, OPERATION_LOCK_TIMEOUT)
+    }
+
+    /// Like [`exclusive_operation_access()`](Self::exclusive_operation_access()), but wait for up to
+    /// `timeout` for the project to be available.
+    pub fn exclusive_operation_access_within(
+        &self,
+        operation: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<WriteWorkspaceGuard> {
+        let deadline = Instant::now() + timeout;
+        let inner = self
+            .worktree_lock()
+            .try_write_arc_for(timeout)
+            .ok_or_else(|| {
+                let operation = OPERATIONS
+                    .lock()
+                    .get(&self.id)
+                    .cloned()
+                    .unwrap_or_else(|| "another operation".into());
+                busy(ProjectBusy {
+                    operation,
+                    pid: None,
+                })
+            })?;
+        let lock = OperationLock::acquire(self, operation, deadline)?;
+        Ok(WriteWorkspaceGuard {
+            _operation: Some(lock),
+            _inner: inner,
+            perm: WorktreeWritePermission(()),
+        })
+    }
+
     /// Return a guard for shared (read) worktree access, and block while waiting for writers to disappear.
     /// There can be multiple readers, but only a single writer. Waiting writers will be handled with priority,
     /// thus block readers to prevent writer starvation.
     pub fn shared_worktree_access(&self) -> WorkspaceReadGuard {
-        let mut map = WORKTREE_LOCKS.lock();
-        WorkspaceReadGuard(map.entry(self.id).or_default().read_arc())
+        WorkspaceReadGuard(self.worktree_lock().read_arc())
+    }
+
+    fn worktree_lock(&self) -> Arc<parking_lot::RwLock<()>> {
+        // Cloned so nobody waits for the worktree while holding the locks of all projects.
+        WORKTREE_LOCKS.lock().entry(self.id).or_default().clone()
+    }
+}
+
+/// How long [`Project::exclusive_operation_access()`] waits for the operation holding the project to finish.
+pub const OPERATION_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The file locked by the GitButler process that has the project open, within its `gb_dir`.
+pub const PROJECT_LOCK_FILE: &str = "project.lock";
+/// The file locked by the GitButler process that runs an operation on the project, within its `gb_dir`.
+pub const OPERATION_LOCK_FILE: &str = "operation.lock";
+/// The file telling which operation holds [`OPERATION_LOCK_FILE`], for other processes to report.
+const OPERATION_HOLDER_FILE: &str = "operation-holder.json";
+
+/// The project is busy with another mutating operation, which didn't finish in time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectBusy {
+    /// The name of the operation that holds the project, like `create_commit`.
+    pub operation: String,
+    /// The id of the process that runs the operation if it's another one than this, and it's known.
+    pub pid: Option<u32>,
+}
+
+impl std::fmt::Display for ProjectBusy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The project is busy with '{}'", self.operation)?;
+        if let Some(pid) = self.pid {
+            write!(f, " in another GitButler process ({pid})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ProjectBusy {}
+
+fn busy(busy: ProjectBusy) -> anyhow::Error {
+    anyhow::Error::new(busy).context(Code::ProjectBusy)
+}
+
+/// The inter-process part of an operation's access, which also makes its name known while it's held.
+struct OperationLock {
+    project_id: ProjectId,
+    holder_path: PathBuf,
+    // Dropped last, so nobody else takes the lock while the holder is still being cleaned up.
+    _file: fslock::LockFile,
+}
+
+impl OperationLock {
+    fn acquire(project: &Project, operation: &str, deadline: Instant) -> anyhow::Result<Self> {
+        let gb_dir = project.gb_dir();
+        std::fs::create_dir_all(&gb_dir)?;
+        let mut file = fslock::LockFile::open(gb_dir.join(OPERATION_LOCK_FILE).as_os_str())?;
+        let holder_path = gb_dir.join(OPERATION_HOLDER_FILE);
+        while !file
+            .try_lock()
+            .context("Failed to check if the operation lock is taken")?
+        {
+            if Instant::now() >= deadline {
+                let holder = std::fs::read(&holder_path)
+                    .ok()
+                    .and_then(|content| serde_json::from_slice::<ProjectBusy>(&content).ok());
+                return Err(busy(holder.unwrap_or_else(|| ProjectBusy {
+                    operation: "another operation".into(),
+                    pid: None,
+                })));
+            }
+            std::thread::sleep(Duration::from_millis(25));
+        }
+
+        let holder = ProjectBusy {
+            operation: operation.to_owned(),
+            pid: Some(std::process::id()),
+        };
+        if let Err(err) = std::fs::write(&holder_path, serde_json::to_vec(&holder)?) {
+            // It's only used to tell others what's going on.
+            tracing::warn!(?err, "failed to record the holder of the operation lock");
+        }
+        OPERATIONS.lock().insert(project.id, operation.to_owned());
+        Ok(OperationLock {
+            project_id: project.id,
+            holder_path,
+            _file: file,
+        })
+    }
+}
+
+impl Drop for OperationLock {
+    fn drop(&mut self) {
+        OPERATIONS.lock().remove(&self.project_id);
+        std::fs::remove_file(&self.holder_path).ok();
     }
 }
 
 pub struct WriteWorkspaceGuard {
+    // Released before the worktree, so whoever gets the worktree next also gets the operation lock.
+    _operation: Option<OperationLock>,
     _inner: parking_lot::ArcRwLockWriteGuard<RawRwLock, ()>,
     perm: WorktreeWritePermission,
 }
@@ -103,3 +248,7 @@ impl WorktreeWritePermission {
 
 static WORKTREE_LOCKS: parking_lot::Mutex<BTreeMap<ProjectId, Arc<parking_lot::RwLock<()>>>> =
     parking_lot::Mutex::new(BTreeMap::new());
+
+/// The operations that hold the worktree of projects in this process, by project.
+static OPERATIONS: parking_lot::Mutex<BTreeMap<ProjectId, String>> =
+    parking_lot::Mutex::new(BTreeMap::new());
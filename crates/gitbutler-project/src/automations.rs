@@ -0,0 +1,58 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// A command that is run whenever one of its events happens in a project, to integrate with external tools
+/// like build systems or chats.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Automation {
+    /// The name the automation is told by in its runs, which is unique among the automations of a project.
+    pub name: String,
+    /// The events the command runs on.
+    pub events: BTreeSet<AutomationTrigger>,
+    /// The shell command to run in the worktree, which receives the event as JSON on stdin.
+    pub command: String,
+    /// The amount of seconds after which a running command is killed.
+    pub timeout_secs: u64,
+    /// If `false`, the command isn't run, but stays configured.
+    pub enabled: bool,
+}
+
+impl Default for Automation {
+    fn default() -> Self {
+        Automation {
+            name: String::new(),
+            events: BTreeSet::new(),
+            command: String::new(),
+            timeout_secs: 60,
+            enabled: true,
+        }
+    }
+}
+
+impl Automation {
+    /// Return `true` if the command should run on `trigger`.
+    pub fn runs_on(&self, trigger: AutomationTrigger) -> bool {
+        self.enabled && self.events.contains(&trigger)
+    }
+
+    /// Return the time after which a running command is killed.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// An event that [automations](Automation) can run on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AutomationTrigger {
+    /// A branch was applied to the workspace.
+    BranchApplied,
+    /// A commit was created on a virtual branch.
+    CommitCreated,
+    /// A virtual branch was pushed.
+    PushCompleted,
+    /// The workspace was updated to a new commit of its target.
+    BaseUpdated,
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Controls which branches that were integrated into the target are proposed for cleanup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BranchCleanupPolicy {
+    /// If `false`, no branches are proposed for cleanup.
+    pub enabled: bool,
+    /// The amount of days a branch has to be integrated before it's proposed for cleanup.
+    pub integrated_for_days: u64,
+    /// What happens to branches that are cleaned up.
+    pub action: BranchCleanupAction,
+    /// If `true`, the upstream branch of a cleaned up branch is deleted on its remote as well.
+    pub delete_remote: bool,
+}
+
+/// What happens to a branch that is cleaned up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BranchCleanupAction {
+    /// Move the branch reference out of `refs/heads`, so it can still be restored.
+    #[default]
+    Archive,
+    /// Delete the branch reference.
+    Delete,
+}
+
+impl Default for BranchCleanupPolicy {
+    fn default() -> Self {
+        BranchCleanupPolicy {
+            enabled: true,
+            integrated_for_days: 14,
+            action: BranchCleanupAction::default(),
+            delete_remote: false,
+        }
+    }
+}
+
+impl BranchCleanupPolicy {
+    /// Return how long a branch has to be integrated before it's proposed for cleanup.
+    pub fn grace_period(&self) -> Duration {
+        Duration::from_secs(self.integrated_for_days.saturating_mul(24 * 60 * 60))
+    }
+}
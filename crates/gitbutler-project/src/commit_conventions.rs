@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// A subject pattern for [Conventional Commits](https://www.conventionalcommits.org), like
+/// `feat(parser): accept trailing commas`.
+pub const CONVENTIONAL_COMMITS_PATTERN: &str =
+    r"^(build|chore|ci|docs|feat|fix|perf|refactor|revert|style|test)(\([\w./-]+\))?!?: \S";
+
+/// The template and the rules for the messages of commits created in a project.
+///
+/// Without any rules, all messages are accepted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CommitConventions {
+    /// The template for new commit messages, used instead of `commit.template` of the Git configuration.
+    pub template: Option<String>,
+    /// A regular expression the subject line has to match, like [`CONVENTIONAL_COMMITS_PATTERN`].
+    pub subject_pattern: Option<String>,
+    /// The maximal number of characters of the subject line.
+    pub max_subject_length: Option<usize>,
+    /// Whether a body has to be separated from the subject line by a blank line.
+    pub blank_line_after_subject: bool,
+}
@@ -1,15 +1,20 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, bail, Context, Result};
-use gitbutler_error::error;
+use gitbutler_error::{catalog::MessageId, error};
+use gitbutler_fs::os_path;
+
+use tokio::sync::broadcast;
 
 use super::{storage, storage::UpdateRequest, Project, ProjectId};
-use crate::AuthKey;
+use crate::{AuthKey, Settings, SettingsChanged};
 
 #[derive(Clone)]
 pub struct Controller {
     local_data_dir: PathBuf,
     projects_storage: storage::Storage,
+    /// Shared by all clones, so each of them can notify the subscribers of the others.
+    settings_events: broadcast::Sender<SettingsChanged>,
 }
 
 impl Controller {
@@ -18,16 +23,23 @@ impl Controller {
         Self {
             projects_storage: storage::Storage::from_path(&path),
             local_data_dir: path,
+            settings_events: broadcast::channel(16).0,
         }
     }
 
     pub fn add<P: AsRef<Path>>(&self, path: P) -> Result<Project> {
+        // Projects are stored with the path users know, not its extended form on Windows.
+        let path = os_path::strip_extended(path.as_ref());
         let path = path.as_ref();
         let all_projects = self
             .projects_storage
             .list()
             .context("failed to list projects from storage")?;
-        if all_projects.iter().any(|project| project.path == path) {
+        let case = os_path::CaseSensitivity::detect(path);
+        if all_projects
+            .iter()
+            .any(|project| case.eq(&project.path, path))
+        {
             bail!("project already exists");
         }
         if !path.exists() {
@@ -38,21 +50,45 @@ impl Controller {
         }
         match gix::open_opts(path, gix::open::Options::isolated()) {
             Ok(repo) if repo.is_bare() => {
-                bail!("bare repositories are unsupported");
+                return Err(anyhow!("bare repositories have no worktree to work in")).context(
+                    error::Context::from_catalog(
+                        error::Code::Validation,
+                        MessageId::BareRepository,
+                    ),
+                );
             }
-            Ok(repo) if repo.worktree().map_or(false, |wt| !wt.is_main()) => {
-                if path.join(".git").is_file() {
-                    bail!("can only work in main worktrees");
+            Ok(repo) => {
+                // All worktrees of a repository share their branches, including the one of the workspace.
+                let common_dir = std::fs::canonicalize(repo.common_dir())?;
+                let is_same_repository = |project: &Project| {
+                    gix::open_opts(&project.path, gix::open::Options::isolated())
+                        .ok()
+                        .and_then(|other| std::fs::canonicalize(other.common_dir()).ok())
+                        .map_or(false, |other| other == common_dir)
                 };
+                if let Some(project) = all_projects
+                    .iter()
+                    .find(|project| is_same_repository(project))
+                {
+                    return Err(anyhow!(
+                        "another worktree of this repository is the project '{}' already",
+                        project.title
+                    ))
+                    .context(error::Context::from_catalog(
+                        error::Code::Validation,
+                        MessageId::WorktreeOfExistingProject,
+                    ));
+                }
             }
-            Ok(_repo) => {}
             Err(err) => {
-                return Err(anyhow::Error::from(err))
-                    .context(error::Context::new("must be a Git repository"));
+                return Err(anyhow::Error::from(err)).context(error::Context::from_catalog(
+                    error::Code::Unknown,
+                    MessageId::NotAGitRepository,
+                ));
             }
         }
 
-        let id = uuid::Uuid::new_v4().to_string();
+        let id = gitbutler_id::generator::new_uuid().to_string();
 
         // title is the base name of the file
         let title = path
@@ -72,7 +108,7 @@ impl Controller {
             .add(&project)
             .context("failed to add project to storage")?;
 
-        // Create a .git/gitbutler directory for app data
+        // Create a .git/gitbutler directory for app data, or the one in the git-dir of a linked worktree
         if let Err(error) = std::fs::create_dir_all(project.gb_dir()) {
             tracing::error!(project_id = %project.id, ?error, "failed to create {:?} on project add", project.gb_dir());
         }
@@ -116,7 +152,57 @@ impl Controller {
         #[cfg(windows)]
         let project = &project_owned;
 
-        self.projects_storage.update(project)
+        let mut updated = self.projects_storage.update(project)?;
+        updated.settings = Settings::load(&updated)?;
+        // These used to be fields of the project, and are still accepted here for compatibility.
+        if project.fetch_schedule.is_some()
+            || project.hooks.is_some()
+            || project.snapshot_retention.is_some()
+        {
+            let mut settings = updated.settings.clone();
+            if let Some(fetch_schedule) = &project.fetch_schedule {
+                settings.fetch_schedule = fetch_schedule.clone();
+            }
+            if let Some(hooks) = &project.hooks {
+                settings.hooks = hooks.clone();
+            }
+            if let Some(snapshot_retention) = &project.snapshot_retention {
+                settings.snapshot_retention = snapshot_retention.clone();
+            }
+            updated.settings = self.update_settings(updated.id, settings)?;
+        }
+        Ok(updated)
+    }
+
+    /// Return the settings of the project with `id`.
+    pub fn settings(&self, id: ProjectId) -> Result<Settings> {
+        Ok(self.get(id)?.settings)
+    }
+
+    /// Replace the settings of the project with `id` with `settings`, and notify
+    /// [subscribers](Self::subscribe_to_settings()) of the entries that changed.
+    pub fn update_settings(&self, id: ProjectId, settings: Settings) -> Result<Settings> {
+        settings.validate()?;
+        let project = self.get(id)?;
+        let changed = settings.changed_keys(&project.settings);
+        if changed.is_empty() {
+            return Ok(settings);
+        }
+        settings.store(&project)?;
+        // Sending only fails if nobody is subscribed.
+        self.settings_events
+            .send(SettingsChanged {
+                project_id: id,
+                changed,
+                settings: settings.clone(),
+            })
+            .ok();
+        Ok(settings)
+    }
+
+    /// Receive an event whenever the settings of a project are changed through this controller or one of its clones.
+    pub fn subscribe_to_settings(&self) -> broadcast::Receiver<SettingsChanged> {
+        self.settings_events.subscribe()
     }
 
     pub fn get(&self, id: ProjectId) -> Result<Project> {
@@ -130,7 +216,6 @@ impl Controller {
     }
 
     fn get_inner(&self, id: ProjectId, validate: bool) -> Result<Project> {
-        #[cfg_attr(not(windows), allow(unused_mut))]
         let mut project = self.projects_storage.get(id)?;
         if validate {
             let worktree_dir = &project.path;
@@ -153,7 +238,7 @@ impl Controller {
             }
         }
         // Clean up old virtual_branches.toml that was never used
-        let old_virtual_branches_path = project.path.join(".git").join("virtual_branches.toml");
+        let old_virtual_branches_path = project.git_dir().join("virtual_branches.toml");
         if old_virtual_branches_path.exists() {
             if let Err(error) = std::fs::remove_file(old_virtual_branches_path) {
                 tracing::error!(project_id = %project.id, ?error, "failed to remove old virtual_branches.toml");
@@ -166,6 +251,7 @@ impl Controller {
             project.preferred_key = AuthKey::SystemExecutable;
         }
 
+        project.settings = Settings::load(&project)?;
         Ok(project)
     }
 
@@ -186,7 +272,7 @@ impl Controller {
             tracing::error!(project_id = %id, ?error, "failed to remove project data",);
         }
 
-        if let Err(error) = std::fs::remove_file(project.path.join(".git/gitbutler.json")) {
+        if let Err(error) = std::fs::remove_file(project.git_dir().join("gitbutler.json")) {
             tracing::error!(project_id = %project.id, ?error, "failed to remove .git/gitbutler.json data",);
         }
 
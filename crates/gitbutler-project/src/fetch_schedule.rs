@@ -0,0 +1,91 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// Controls how often the remotes of a project are fetched in the background.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FetchSchedule {
+    /// The amount of seconds between fetches of remotes without an explicit interval.
+    pub default_interval_secs: u64,
+    /// Intervals in seconds for individual remotes, keyed by remote name.
+    pub remote_interval_secs: BTreeMap<String, u64>,
+    /// The maximum amount of seconds by which each fetch may be delayed randomly,
+    /// so that fetches of multiple remotes or projects don't happen at the same time.
+    pub jitter_secs: u64,
+    /// The upper bound in seconds for the delay between retries after consecutive failures.
+    pub max_backoff_secs: u64,
+    /// The maximum amount of remotes that are fetched at the same time, in the background
+    /// as well as when fetching on request.
+    pub max_concurrent_fetches: usize,
+}
+
+impl Default for FetchSchedule {
+    fn default() -> Self {
+        FetchSchedule {
+            default_interval_secs: 15 * 60,
+            remote_interval_secs: BTreeMap::new(),
+            jitter_secs: 30,
+            max_backoff_secs: 60 * 60,
+            max_concurrent_fetches: 4,
+        }
+    }
+}
+
+/// The reason a scheduled fetch failed, which determines how quickly it will be retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FetchFailure {
+    /// Credentials were rejected or missing. Retrying quickly won't help, so it backs off faster.
+    Auth,
+    /// The remote couldn't be reached.
+    Network,
+    /// The network is unavailable, so no remote could be reached. Fetches continue at the regular
+    /// interval, which notices quickly once it's back.
+    Offline,
+    /// Any other failure.
+    Other,
+}
+
+impl FetchSchedule {
+    /// Return the regular interval at which `remote` should be fetched.
+    pub fn interval_for(&self, remote: &str) -> Duration {
+        let secs = self
+            .remote_interval_secs
+            .get(remote)
+            .copied()
+            .unwrap_or(self.default_interval_secs);
+        Duration::from_secs(secs.max(1))
+    }
+
+    /// Return the delay until `remote` should be fetched next after `consecutive_failures` failed
+    /// attempts, the last of which failed due to `failure`.
+    ///
+    /// `jitter_sample` is expected to be in `0.0..1.0` and scales the configured jitter.
+    pub fn next_delay(
+        &self,
+        remote: &str,
+        consecutive_failures: u32,
+        failure: Option<FetchFailure>,
+        jitter_sample: f64,
+    ) -> Duration {
+        let interval = self.interval_for(remote);
+        let delay = match (consecutive_failures, failure) {
+            (0, _) | (_, None) => interval,
+            (failures, Some(failure)) => {
+                // Auth failures need user interaction, so there is no point in trying as often.
+                let exponent = match failure {
+                    FetchFailure::Auth => failures.saturating_add(2),
+                    FetchFailure::Network | FetchFailure::Other => failures,
+                    FetchFailure::Offline => 0,
+                };
+                let factor = 2u32.saturating_pow(exponent.min(16));
+                interval
+                    .saturating_mul(factor)
+                    .min(Duration::from_secs(self.max_backoff_secs).max(interval))
+            }
+        };
+        let jitter = Duration::from_secs(self.jitter_secs).mul_f64(jitter_sample.clamp(0.0, 1.0));
+        delay + jitter
+    }
+}
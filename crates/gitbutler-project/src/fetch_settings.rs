@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// How remotes are fetched, whether in the background or when asked to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FetchSettings {
+    /// Which branches of a remote are fetched.
+    pub refspecs: FetchRefspecs,
+    /// If `true`, remote branches that were deleted on the remote are deleted locally as well.
+    pub prune: bool,
+    /// Which tags of a remote are fetched.
+    pub tags: FetchTags,
+    /// If `true`, the history of a shallow clone is fetched further back whenever it ends before the commits
+    /// that the target and the workspace have in common, instead of failing to find them.
+    pub deepen_on_demand: bool,
+}
+
+impl Default for FetchSettings {
+    fn default() -> Self {
+        FetchSettings {
+            refspecs: FetchRefspecs::default(),
+            prune: true,
+            tags: FetchTags::default(),
+            deepen_on_demand: true,
+        }
+    }
+}
+
+/// Which branches of a remote are fetched.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum FetchRefspecs {
+    /// All branches, into `refs/remotes/<remote>/`.
+    #[default]
+    AllBranches,
+    /// Only the target branch from its remote, which saves time and space in repositories with many branches.
+    /// Other remotes and projects without a target have all of their branches fetched.
+    TargetBranch,
+    /// The given refspecs, like `+refs/heads/main:refs/remotes/{remote}/main`, where `{remote}` is replaced
+    /// with the name of the remote that is fetched.
+    Custom { refspecs: Vec<String> },
+}
+
+impl FetchRefspecs {
+    /// The placeholders that custom refspecs may use.
+    pub const PLACEHOLDERS: [&'static str; 1] = ["{remote}"];
+}
+
+/// Which tags of a remote are fetched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FetchTags {
+    /// The tags that point into the history that is fetched, like Git does by default.
+    #[default]
+    Auto,
+    /// All tags.
+    All,
+    /// No tags.
+    None,
+}
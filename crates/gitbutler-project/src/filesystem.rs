@@ -0,0 +1,163 @@
+//! Detect repositories that are accessed across the boundary between Windows and WSL, like
+//! `\\wsl$\Ubuntu\home\me\project` from Windows or `/mnt/c/Users/me/project` from within WSL.
+//!
+//! Such filesystems don't deliver change notifications, may disagree about line endings and
+//! use paths that the other side can't use as is. [`FilesystemCapabilities`] describes what
+//! works for a project so it can be adjusted for, and shown to the user.
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+/// The prefixes under which Windows exposes the filesystems of WSL distributions,
+/// also in their canonicalized form.
+const WSL_SHARE_PREFIXES: &[&str] = &[
+    r"\\wsl$\",
+    r"\\wsl.localhost\",
+    r"\\?\UNC\wsl$\",
+    r"\\?\UNC\wsl.localhost\",
+];
+
+/// The directory under which WSL mounts Windows drives.
+const WSL_DRIVE_MOUNT: &str = "/mnt/";
+
+/// How a repository is reached by this process.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum FilesystemBoundary {
+    /// The repository is on a filesystem native to the operating system we run on.
+    Native,
+    /// We run on Windows, and the repository is inside the WSL distribution `distro`.
+    WslFromWindows {
+        distro: String,
+        /// The path of the repository as seen from within WSL.
+        #[serde(rename = "linuxPath")]
+        linux_path: PathBuf,
+    },
+    /// We run within WSL, and the repository is on a Windows drive.
+    WindowsFromWsl {
+        /// The path of the repository as seen from Windows.
+        #[serde(rename = "windowsPath")]
+        windows_path: PathBuf,
+    },
+}
+
+impl FilesystemBoundary {
+    /// Determine how `path` is reached, with `in_wsl` being `true` if this process runs within WSL.
+    pub fn of(path: &Path, in_wsl: bool) -> Self {
+        let path = path.to_string_lossy();
+        if let Some((distro, linux_path)) = wsl_share_path(&path) {
+            return FilesystemBoundary::WslFromWindows {
+                distro,
+                linux_path: linux_path.into(),
+            };
+        }
+        if in_wsl {
+            if let Some(windows_path) = windows_drive_path(&path) {
+                return FilesystemBoundary::WindowsFromWsl {
+                    windows_path: windows_path.into(),
+                };
+            }
+        }
+        FilesystemBoundary::Native
+    }
+
+    /// Return `true` if the repository is accessed across the Windows/WSL boundary.
+    pub fn is_crossed(&self) -> bool {
+        !matches!(self, FilesystemBoundary::Native)
+    }
+}
+
+/// What works, and what doesn't, for a repository on its filesystem.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilesystemCapabilities {
+    /// How the repository is reached.
+    pub boundary: FilesystemBoundary,
+    /// If `false`, the filesystem doesn't deliver change notifications, and it has to be polled instead.
+    pub file_events: bool,
+    /// The value of `core.autocrlf`, if set.
+    pub autocrlf: Option<String>,
+    /// Problems to expect with this setup, in a form that can be shown to users.
+    pub warnings: Vec<String>,
+}
+
+impl FilesystemCapabilities {
+    /// Determine the capabilities of the repository with its worktree at `worktree_path`.
+    pub fn detect(worktree_path: &Path) -> Self {
+        let boundary = FilesystemBoundary::of(worktree_path, running_in_wsl());
+        let autocrlf = git2::Repository::open(worktree_path)
+            .and_then(|repo| repo.config())
+            .and_then(|config| config.get_string("core.autocrlf"))
+            .ok();
+        Self::new(boundary, autocrlf)
+    }
+
+    /// Derive the capabilities from `boundary` and the value of `core.autocrlf`.
+    pub fn new(boundary: FilesystemBoundary, autocrlf: Option<String>) -> Self {
+        let mut warnings = Vec::new();
+        let file_events = !boundary.is_crossed();
+        if !file_events {
+            warnings.push(
+                "Changes are detected by polling as this filesystem doesn't report them, \
+                 which may be slow for large repositories."
+                    .to_owned(),
+            );
+        }
+        let converts_line_endings = autocrlf
+            .as_deref()
+            .map_or(false, |value| value.eq_ignore_ascii_case("true"));
+        if boundary.is_crossed() && converts_line_endings {
+            warnings.push(
+                "`core.autocrlf` is `true`, so files checked out by Git on the other side of \
+                 the WSL boundary may appear as changed. Set it to `input` to avoid this."
+                    .to_owned(),
+            );
+        }
+        FilesystemCapabilities {
+            boundary,
+            file_events,
+            autocrlf,
+            warnings,
+        }
+    }
+}
+
+/// Return `true` if this process runs within the Windows Subsystem for Linux.
+fn running_in_wsl() -> bool {
+    if !cfg!(target_os = "linux") {
+        return false;
+    }
+    std::env::var_os("WSL_DISTRO_NAME").is_some()
+        || std::fs::read_to_string("/proc/sys/kernel/osrelease")
+            .map(|release| release.to_lowercase().contains("microsoft"))
+            .unwrap_or(false)
+}
+
+/// Turn `\\wsl$\<distro>\<path>` into `(distro, /<path>)`.
+fn wsl_share_path(path: &str) -> Option<(String, String)> {
+    let rest = WSL_SHARE_PREFIXES.iter().find_map(|prefix| {
+        path.get(..prefix.len())
+            .filter(|candidate| candidate.eq_ignore_ascii_case(prefix))
+            .map(|_| &path[prefix.len()..])
+    })?;
+    let (distro, path) = rest.split_once('\\').unwrap_or((rest, ""));
+    if distro.is_empty() {
+        return None;
+    }
+    Some((distro.to_owned(), format!("/{}", path.replace('\\', "/"))))
+}
+
+/// Turn `/mnt/<drive>/<path>` into `<DRIVE>:\<path>`.
+fn windows_drive_path(path: &str) -> Option<String> {
+    let rest = path.strip_prefix(WSL_DRIVE_MOUNT)?;
+    let (drive, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let mut drive_chars = drive.chars();
+    let drive = drive_chars
+        .next()
+        .filter(|drive| drive.is_ascii_alphabetic() && drive_chars.next().is_none())?;
+    Some(format!(
+        "{}:\\{}",
+        drive.to_ascii_uppercase(),
+        path.replace('/', "\\")
+    ))
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/// The kind of forge that hosts the repository of a project, which decides how pull requests are opened
+/// and looked up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    /// Tell the kind of forge by the host of the remote of the target.
+    #[default]
+    Detect,
+    /// GitHub, or GitHub Enterprise Server.
+    GitHub,
+    GitLab,
+    /// Gitea, or Forgejo, which shares its API.
+    Gitea,
+}
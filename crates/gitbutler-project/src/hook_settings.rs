@@ -0,0 +1,37 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// Controls which Git hooks are run for a project, and for how long.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HookSettings {
+    /// If `false`, no hooks are run at all.
+    pub enabled: bool,
+    /// The names of hooks that are never run, like `pre-push`.
+    pub disabled: BTreeSet<String>,
+    /// The amount of seconds after which a running hook is killed.
+    pub timeout_secs: u64,
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        HookSettings {
+            enabled: true,
+            disabled: BTreeSet::new(),
+            timeout_secs: 5 * 60,
+        }
+    }
+}
+
+impl HookSettings {
+    /// Return `true` if the hook named `name` may run.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.enabled && !self.disabled.contains(name)
+    }
+
+    /// Return the time after which a running hook is killed.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
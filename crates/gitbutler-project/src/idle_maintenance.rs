@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Controls the expensive maintenance that is deferred until the project is idle, like compacting
+/// snapshots or writing the commit-graph, to keep interactions fast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IdleMaintenance {
+    /// Whether maintenance runs at all.
+    pub enabled: bool,
+    /// The amount of seconds without changes to the worktree and without calls to the API after
+    /// which the project counts as idle.
+    pub idle_after_secs: u64,
+    /// The least amount of seconds between two runs of the same kind of maintenance.
+    pub interval_secs: u64,
+}
+
+impl Default for IdleMaintenance {
+    fn default() -> Self {
+        IdleMaintenance {
+            enabled: true,
+            idle_after_secs: 2 * 60,
+            interval_secs: 6 * 60 * 60,
+        }
+    }
+}
+
+impl IdleMaintenance {
+    /// The time without activity after which the project counts as idle.
+    pub fn idle_after(&self) -> Duration {
+        Duration::from_secs(self.idle_after_secs)
+    }
+
+    /// The least time between two runs of the same kind of maintenance.
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
@@ -1,9 +1,52 @@
 pub mod access;
+mod automations;
+mod branch_cleanup;
+mod commit_conventions;
 mod controller;
 mod default_true;
+mod fetch_schedule;
+mod fetch_settings;
+mod filesystem;
+mod forge_kind;
+mod hook_settings;
+mod idle_maintenance;
+mod listing_format;
+mod message_generation;
+mod parallelism;
 mod project;
+mod pushed_commits;
+mod secret_scanning;
+mod settings;
+mod snapshot_retention;
+mod snapshot_triggers;
+mod ssh_auth;
 mod storage;
+mod transfer_retries;
+mod watcher_settings;
 
+pub use automations::{Automation, AutomationTrigger};
+pub use branch_cleanup::{BranchCleanupAction, BranchCleanupPolicy};
+pub use commit_conventions::{CommitConventions, CONVENTIONAL_COMMITS_PATTERN};
 pub use controller::Controller;
+pub use fetch_schedule::{FetchFailure, FetchSchedule};
+pub use fetch_settings::{FetchRefspecs, FetchSettings, FetchTags};
+pub use filesystem::{FilesystemBoundary, FilesystemCapabilities};
+pub use forge_kind::ForgeKind;
+pub use hook_settings::HookSettings;
+pub use idle_maintenance::IdleMaintenance;
+pub use listing_format::{AuthorFormat, ListingFormat, TimeFormat, TimeZone};
+pub use message_generation::{MessageGeneration, MessageGenerationBackend};
+pub use parallelism::Parallelism;
 pub use project::{ApiProject, AuthKey, CodePushState, FetchResult, Project, ProjectId};
+pub use pushed_commits::PushedCommitRewrites;
+pub use secret_scanning::SecretScanning;
+pub use settings::{
+    BranchNaming, DefaultBranchName, DiffSettings, LaneOrder, RemoteBranchNames, Settings,
+    SettingsChanged, SettingsKey, SETTINGS_VERSION,
+};
+pub use snapshot_retention::SnapshotRetention;
+pub use snapshot_triggers::{SnapshotTriggers, SnapshotTriggersPreset};
+pub use ssh_auth::SshAuthMethod;
 pub use storage::UpdateRequest;
+pub use transfer_retries::TransferRetries;
+pub use watcher_settings::WatcherSettings;
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+/// Controls how timestamps and authors are rendered in commit and branch listings,
+/// so all consumers show them the same way.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ListingFormat {
+    /// Whether to show times relative to now, or as date.
+    pub time: TimeFormat,
+    /// The timezone to show absolute times in.
+    pub time_zone: TimeZone,
+    /// How to identify the author of a commit.
+    pub author: AuthorFormat,
+}
+
+/// The way a timestamp is rendered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimeFormat {
+    /// Like `3 hours ago`, similar to `git log --date=relative`.
+    #[default]
+    Relative,
+    /// Like `2024-05-01 13:37:00 +0200`, similar to `git log --date=iso`.
+    Absolute,
+}
+
+/// The timezone in which absolute times are rendered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum TimeZone {
+    /// Use the offset recorded along with the time, like the timezone of the commit author.
+    #[default]
+    Recorded,
+    /// Always use UTC.
+    Utc,
+    /// Use a fixed offset, typically the one of the user.
+    Fixed {
+        /// The offset to UTC in minutes, positive east of Greenwich.
+        #[serde(rename = "offsetMinutes")]
+        offset_minutes: i32,
+    },
+}
+
+/// The way the author of a commit is identified.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuthorFormat {
+    /// Only the name, or the email if there is no name.
+    #[default]
+    Name,
+    /// Only the email, or the name if there is no email.
+    Email,
+    /// Like `Name <email>`.
+    NameAndEmail,
+}
+
+impl ListingFormat {
+    /// Render `seconds` since the Unix epoch, recorded with `offset_minutes` to UTC,
+    /// with `now_seconds` being the current time for relative formats.
+    pub fn format_time(&self, seconds: i64, offset_minutes: i32, now_seconds: i64) -> String {
+        match self.time {
+            TimeFormat::Relative => format_relative(now_seconds.saturating_sub(seconds)),
+            TimeFormat::Absolute => {
+                let offset_minutes = match self.time_zone {
+                    TimeZone::Recorded => offset_minutes,
+                    TimeZone::Utc => 0,
+                    TimeZone::Fixed { offset_minutes } => offset_minutes,
+                };
+                format_absolute(seconds, offset_minutes)
+            }
+        }
+    }
+
+    /// Render the author identified by `name` and `email`.
+    pub fn format_author(&self, name: &str, email: &str) -> String {
+        match self.author {
+            AuthorFormat::Name if !name.is_empty() => name.to_owned(),
+            AuthorFormat::Email if !email.is_empty() => email.to_owned(),
+            AuthorFormat::NameAndEmail if !email.is_empty() => format!("{name} <{email}>"),
+            AuthorFormat::Name | AuthorFormat::NameAndEmail => email.to_owned(),
+            AuthorFormat::Email => name.to_owned(),
+        }
+    }
+}
+
+/// Follows the thresholds of `show_date_relative()` in `git`.
+fn format_relative(seconds_ago: i64) -> String {
+    fn plural(amount: i64, unit: &str) -> String {
+        if amount == 1 {
+            format!("{amount} {unit}")
+        } else {
+            format!("{amount} {unit}s")
+        }
+    }
+
+    if seconds_ago < 0 {
+        return "in the future".into();
+    }
+    if seconds_ago < 90 {
+        return format!("{} ago", plural(seconds_ago, "second"));
+    }
+    let minutes = (seconds_ago + 30) / 60;
+    if minutes < 90 {
+        return format!("{} ago", plural(minutes, "minute"));
+    }
+    let hours = (minutes + 30) / 60;
+    if hours < 36 {
+        return format!("{} ago", plural(hours, "hour"));
+    }
+    let days = (hours + 12) / 24;
+    if days < 14 {
+        return format!("{} ago", plural(days, "day"));
+    }
+    if days < 70 {
+        return format!("{} ago", plural((days + 3) / 7, "week"));
+    }
+    if days < 365 {
+        return format!("{} ago", plural((days + 15) / 30, "month"));
+    }
+    let total_months = (days * 12 * 2 + 365) / (365 * 2);
+    let (years, months) = (total_months / 12, total_months % 12);
+    if months == 0 {
+        format!("{} ago", plural(years, "year"))
+    } else {
+        format!("{}, {} ago", plural(years, "year"), plural(months, "month"))
+    }
+}
+
+fn format_absolute(seconds: i64, offset_minutes: i32) -> String {
+    let local = seconds + i64::from(offset_minutes) * 60;
+    let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+    let secs_of_day = local.rem_euclid(86_400);
+    let sign = if offset_minutes < 0 { '-' } else { '+' };
+    let offset = offset_minutes.unsigned_abs();
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} {sign}{:02}{:02}",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        offset / 60,
+        offset % 60
+    )
+}
+
+/// Convert days since the Unix epoch into `(year, month, day)` of the proleptic Gregorian calendar.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
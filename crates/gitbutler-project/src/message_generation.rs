@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+/// How commit messages are generated from the changes they commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MessageGeneration {
+    /// The model messages are generated with, or `None` if they aren't generated.
+    pub backend: Option<MessageGenerationBackend>,
+    /// The prompt the model is given, where `{diff}` is replaced with the changes and `{branch}` with the
+    /// name of their branch, or `None` to use the built-in prompt.
+    pub prompt_template: Option<String>,
+    /// The amount of characters of the changes that are put into the prompt, above which files are left out.
+    pub max_diff_chars: usize,
+}
+
+impl Default for MessageGeneration {
+    fn default() -> Self {
+        MessageGeneration {
+            backend: None,
+            prompt_template: None,
+            max_diff_chars: 20_000,
+        }
+    }
+}
+
+impl MessageGeneration {
+    /// The placeholders that prompt templates may use.
+    pub const PLACEHOLDERS: [&'static str; 2] = ["{diff}", "{branch}"];
+}
+
+/// A model that commit messages can be generated with.
+///
+/// API keys aren't part of the settings, but are kept in the secret store of the system.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum MessageGenerationBackend {
+    /// An endpoint speaking the chat completions API of OpenAI, like OpenAI itself, a proxy in front of it,
+    /// or a self-hosted server.
+    #[serde(rename_all = "camelCase")]
+    OpenAiCompatible {
+        /// The URL the API is under, like `https://api.openai.com/v1`.
+        base_url: String,
+        model: String,
+    },
+    /// The messages API of Anthropic.
+    #[serde(rename_all = "camelCase")]
+    Anthropic {
+        /// The URL the API is under if it isn't `https://api.anthropic.com`, like that of a proxy.
+        base_url: Option<String>,
+        model: String,
+    },
+    /// A model served by Ollama, usually on the local machine.
+    #[serde(rename_all = "camelCase")]
+    Ollama {
+        /// The URL of the server, like `http://127.0.0.1:11434`.
+        base_url: String,
+        model: String,
+    },
+}
+
+impl MessageGenerationBackend {
+    /// The model messages are generated with.
+    pub fn model(&self) -> &str {
+        match self {
+            MessageGenerationBackend::OpenAiCompatible { model, .. }
+            | MessageGenerationBackend::Anthropic { model, .. }
+            | MessageGenerationBackend::Ollama { model, .. } => model,
+        }
+    }
+
+    /// The URL of the API, if it's set.
+    pub fn base_url(&self) -> Option<&str> {
+        match self {
+            MessageGenerationBackend::OpenAiCompatible { base_url, .. }
+            | MessageGenerationBackend::Ollama { base_url, .. } => Some(base_url),
+            MessageGenerationBackend::Anthropic { base_url, .. } => base_url.as_deref(),
+        }
+    }
+}
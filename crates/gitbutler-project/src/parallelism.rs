@@ -0,0 +1,23 @@
+use std::num::NonZeroUsize;
+
+use serde::{Deserialize, Serialize};
+
+/// Controls how many threads are used to hash files and build trees, which speeds up diffing the
+/// worktree and creating commits in large repositories.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Parallelism {
+    /// The maximum amount of threads to use, with `0` using one per available CPU and `1`
+    /// doing all work on the calling thread.
+    pub max_threads: usize,
+}
+
+impl Parallelism {
+    /// Return the amount of threads to use, which is at least one.
+    pub fn threads(&self) -> usize {
+        match self.max_threads {
+            0 => std::thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            threads => threads,
+        }
+    }
+}
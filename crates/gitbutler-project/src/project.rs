@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     path::{self, PathBuf},
     time,
 };
@@ -6,7 +7,11 @@ use std::{
 use gitbutler_id::id::Id;
 use serde::{Deserialize, Serialize};
 
-use crate::default_true::DefaultTrue;
+use crate::{
+    default_true::DefaultTrue, BranchCleanupPolicy, CommitConventions, FetchSchedule, ForgeKind,
+    HookSettings, IdleMaintenance, ListingFormat, Parallelism, PushedCommitRewrites, Settings,
+    SnapshotRetention, SnapshotTriggers, SshAuthMethod, TransferRetries, WatcherSettings,
+};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -15,6 +20,8 @@ pub enum AuthKey {
     Local {
         private_key_path: path::PathBuf,
     },
+    /// Use the keys of running SSH agents, for keys that can't be read from a file like hardware-backed ones.
+    SshAgent,
     // There used to be more auth option variants that we are deprecating and replacing with this
     #[serde(other)]
     #[default]
@@ -74,8 +81,7 @@ pub struct Project {
     pub title: String,
     pub description: Option<String>,
     /// The worktree directory of the project's repository.
-    // TODO(ST): rename this to `worktree_dir` and while at it, add a `git_dir` if it's retrieved from a repo.
-    //           Then find `.join(".git")` and use the `git_dir` instead.
+    // TODO(ST): rename this to `worktree_dir`.
     pub path: path::PathBuf,
     #[serde(default)]
     pub preferred_key: AuthKey,
@@ -96,9 +102,73 @@ pub struct Project {
     pub snapshot_lines_threshold: Option<usize>,
     #[serde(default)]
     pub ignore_project_semaphore: bool,
+    /// How remotes were fetched in the background before it became part of the [settings](Self::settings).
+    #[serde(default)]
+    pub fetch_schedule: FetchSchedule,
+    /// How times and authors are shown in commit and branch listings.
+    #[serde(default)]
+    pub listing_format: ListingFormat,
+    /// Environment variables to set for all processes spawned on behalf of this project,
+    /// like `git`, credential helpers or `ssh`.
+    #[serde(default)]
+    pub extra_env: BTreeMap<String, String>,
+    /// Which Git hooks were run before it became part of the [settings](Self::settings).
+    #[serde(default)]
+    pub hooks: HookSettings,
+    /// Which snapshots were kept before it became part of the [settings](Self::settings).
+    #[serde(default)]
+    pub snapshot_retention: SnapshotRetention,
+    /// Which events create snapshots in the operations log automatically.
+    #[serde(default)]
+    pub snapshot_triggers: SnapshotTriggers,
+    /// Which integrated branches are proposed for cleanup, and what happens to them.
+    #[serde(default)]
+    pub branch_cleanup: BranchCleanupPolicy,
+    /// Which changes to the worktree are ignored by the watcher.
+    #[serde(default)]
+    pub watcher: WatcherSettings,
+    /// How many threads are used to diff the worktree and build trees.
+    #[serde(default)]
+    pub parallelism: Parallelism,
+    /// The kind of forge pull requests are opened on, detected from the remote of the target by default.
+    #[serde(default)]
+    pub forge: ForgeKind,
+    /// How often pushes and fetches that were interrupted by the network are attempted again.
+    #[serde(default)]
+    pub transfer_retries: TransferRetries,
+    /// The ways to authenticate with SSH remotes in the order they are tried, if the
+    /// [preferred key](Self::preferred_key) is [`AuthKey::Local`] or [`AuthKey::SshAgent`].
+    /// Use [`Self::ssh_auth_methods()`] to get the effective order.
+    #[serde(default)]
+    pub ssh_auth_order: Vec<SshAuthMethod>,
+    /// The template and the rules for commit messages.
+    #[serde(default)]
+    pub commit_conventions: CommitConventions,
+    /// When expensive maintenance runs in the background.
+    #[serde(default)]
+    pub idle_maintenance: IdleMaintenance,
+    /// What happens when commits that were pushed already are amended, reworded or squashed.
+    #[serde(default)]
+    pub pushed_commit_rewrites: PushedCommitRewrites,
+    /// The settings of the project, which are stored separately and loaded along with it.
+    #[serde(skip)]
+    pub settings: Settings,
 }
 
 impl Project {
+    /// Return the ways to authenticate with SSH remotes in the order they should be tried, which
+    /// is the configured order, or only the key file of a local key, or the SSH agent and then
+    /// `gpg-agent` if no order is configured.
+    pub fn ssh_auth_methods(&self) -> Vec<SshAuthMethod> {
+        if !self.ssh_auth_order.is_empty() {
+            return self.ssh_auth_order.clone();
+        }
+        match self.preferred_key {
+            AuthKey::Local { .. } => vec![SshAuthMethod::KeyFile],
+            _ => vec![SshAuthMethod::Agent, SshAuthMethod::GpgAgent],
+        }
+    }
+
     /// Determines if the project Operations log will be synched with the GitButHub
     pub fn oplog_sync_enabled(&self) -> bool {
         let has_url = self.api.as_ref().map(|api| api.git_url.clone()).is_some();
@@ -127,9 +197,27 @@ impl Project {
 
     /// Returns the path to the directory containing the `GitButler` state for this project.
     ///
-    /// Normally this is `.git/gitbutler` in the project's repository.
+    /// Normally this is `.git/gitbutler` in the project's repository, and it's in the [git-dir](Self::git_dir())
+    /// of linked worktrees so each has its own state.
     pub fn gb_dir(&self) -> PathBuf {
-        self.path.join(".git").join("gitbutler")
+        self.git_dir().join("gitbutler")
+    }
+
+    /// Returns the git-dir of the project's repository, which is `.git` in its worktree unless `.git` is a file
+    /// pointing to it, like for linked worktrees of `git worktree` or repositories with a separate git-dir.
+    pub fn git_dir(&self) -> PathBuf {
+        let dot_git = self.path.join(".git");
+        if dot_git.is_file() {
+            if let Some(git_dir) = std::fs::read_to_string(&dot_git)
+                .ok()
+                .as_deref()
+                .and_then(|content| content.strip_prefix("gitdir:"))
+            {
+                // Relative paths are relative to the worktree.
+                return self.path.join(git_dir.trim());
+            }
+        }
+        dot_git
     }
 
     pub fn snapshot_lines_threshold(&self) -> usize {
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// What happens when commits that are on the remote branch of a virtual branch already are amended,
+/// reworded or squashed, which requires a force push that rewrites the history others may build on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PushedCommitRewrites {
+    /// Rewrite them like any other commit.
+    #[default]
+    Allow,
+    /// Refuse to rewrite them unless asked to anyway, so the user can be warned first.
+    Warn,
+    /// Refuse to rewrite them, even if asked to anyway.
+    Block,
+}
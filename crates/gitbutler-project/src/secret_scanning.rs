@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// Whether changes are checked for secrets, like access keys and private keys, before they are committed
+/// or pushed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SecretScanning {
+    /// If `true`, committing and pushing fail if the changes contain secrets, unless asked to go ahead anyway.
+    pub enabled: bool,
+    /// If `true`, long random-looking strings are reported along with the values that match known formats.
+    pub entropy: bool,
+    /// Values that are known not to be secrets, like the example keys of documentation or tests, which
+    /// are never reported.
+    pub allowed_values: Vec<String>,
+}
+
+impl Default for SecretScanning {
+    fn default() -> Self {
+        SecretScanning {
+            enabled: false,
+            entropy: true,
+            allowed_values: Vec::new(),
+        }
+    }
+}
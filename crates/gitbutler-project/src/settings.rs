@@ -0,0 +1,459 @@
+//! The settings of a project, which are kept next to its other `GitButler` state instead of with the list of
+//! projects, so they can evolve with migrations and other parts of the application can be told about changes.
+use anyhow::{anyhow, bail, Context, Result};
+use gitbutler_error::error::Code;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Automation, FetchRefspecs, FetchSchedule, FetchSettings, HookSettings, MessageGeneration,
+    Project, ProjectId, SecretScanning, SnapshotRetention,
+};
+
+/// The version of the settings schema written by this version of the application.
+pub const SETTINGS_VERSION: u32 = 1;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// The settings of a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// How remotes are fetched in the background.
+    pub fetch_schedule: FetchSchedule,
+    /// Which snapshots of the operations log are kept when it's garbage-collected.
+    pub snapshot_retention: SnapshotRetention,
+    /// The remote that branches are pushed to if neither they nor the target have a push remote,
+    /// instead of the remote of the target.
+    pub default_push_remote: Option<String>,
+    /// How the worktree is diffed.
+    pub diff: DiffSettings,
+    /// Which Git hooks to run for virtual branch operations.
+    pub hooks: HookSettings,
+    /// Where new virtual branches get their names from if they are created from a summary of the work,
+    /// like the title of a ticket.
+    pub branch_naming: Option<BranchNaming>,
+    /// How new virtual branches are named if they aren't given a name, and none is received from
+    /// [`branch_naming`](Self::branch_naming).
+    pub default_branch_name: DefaultBranchName,
+    /// How the branches that virtual branches are pushed to for the first time are named.
+    pub remote_branch_names: RemoteBranchNames,
+    /// How the virtual branches of the workspace are ordered when listed.
+    pub lane_order: LaneOrder,
+    /// If `true`, Git's commit-graph file is written after fetching if the repository has none, which speeds
+    /// up finding how branches relate to the target in repositories with a long history.
+    pub write_commit_graph: bool,
+    /// The commands that are run when events happen in the project.
+    pub automations: Vec<Automation>,
+    /// How commit messages are generated from the changes they commit.
+    pub message_generation: MessageGeneration,
+    /// Whether changes are checked for secrets before they are committed or pushed.
+    pub secret_scanning: SecretScanning,
+    /// Which branches and tags are fetched, and how the history of shallow clones is completed.
+    pub fetch: FetchSettings,
+    /// If `true`, the history of branches is kept linear so they can be integrated into the target by
+    /// rebasing or squashing, and operations that would create a merge commit fail instead.
+    pub linear_history: bool,
+}
+
+/// How a new virtual branch is named by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DefaultBranchName {
+    /// `Virtual branch`.
+    #[default]
+    Fixed,
+    /// `Lane 1`, `Lane 2` and so on, using the lowest number that isn't taken.
+    Numbered,
+    /// The date it was created on, like `2024-07-01`.
+    Dated,
+    /// The first line of the summary of the work it was created for, like the title of a ticket,
+    /// or `Virtual branch` if there is none.
+    Summary,
+}
+
+/// The policy for the names of the remote branches that virtual branches are pushed to, so teams with naming
+/// conventions don't have to rename branches after pushing them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RemoteBranchNames {
+    /// What names are made of, where `{user}` is the local part of the email commits are authored with, or the
+    /// name if there is none, and `{normalized-title}` is the name of the virtual branch, both turned into valid
+    /// branch names.
+    pub template: String,
+    /// Characters that names may not contain, which are replaced with `-` in generated names.
+    pub forbidden_characters: String,
+    /// The length of names in characters above which they are shortened, or rejected if given by hand.
+    pub max_length: Option<usize>,
+}
+
+impl RemoteBranchNames {
+    /// The placeholders that templates may use.
+    pub const PLACEHOLDERS: [&'static str; 2] = ["{user}", "{normalized-title}"];
+}
+
+impl Default for RemoteBranchNames {
+    fn default() -> Self {
+        RemoteBranchNames {
+            template: "{normalized-title}".into(),
+            forbidden_characters: String::new(),
+            max_length: None,
+        }
+    }
+}
+
+/// The order in which the virtual branches of the workspace are listed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LaneOrder {
+    /// The order they were arranged in.
+    #[default]
+    Manual,
+    /// The most recently updated first.
+    RecentActivity,
+    /// By name, regardless of case.
+    Alphabetical,
+}
+
+/// Names new virtual branches, to enforce conventions like prefixing them with the ticket they belong to.
+///
+/// It receives `{"summary": "…"}` and answers with `{"name": "…", "description": "…"}`,
+/// where the description is optional and becomes the notes of the branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum BranchNaming {
+    /// A shell command that is run in the worktree, with the request on stdin and the answer on stdout.
+    Command { command: String },
+    /// An endpoint the request is posted to, answering in its response body.
+    Http { url: String },
+}
+
+/// Controls how the worktree is diffed into hunks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiffSettings {
+    /// The amount of unchanged lines to show around each change.
+    pub context_lines: u32,
+    /// If `true`, changes that only affect whitespace are ignored.
+    pub ignore_whitespace: bool,
+    /// If `true`, changes that only add or remove blank lines are ignored.
+    pub ignore_blank_lines: bool,
+    /// If set, files that were renamed with at least this similarity in percent are shown as one change.
+    pub rename_threshold: Option<u16>,
+    /// If `true`, hunks of source files are split where functions and other sections start, so each can be
+    /// assigned to a different branch. Only has an effect if the app is built with support for it.
+    pub semantic_hunks: bool,
+    /// If `true`, the ids of changed files are kept on disk and `core.fsmonitor` is used if configured, so the
+    /// status of large repositories is quick to compute, even right after opening them.
+    pub persistent_cache: bool,
+}
+
+impl Default for DiffSettings {
+    fn default() -> Self {
+        DiffSettings {
+            context_lines: 3,
+            ignore_whitespace: false,
+            ignore_blank_lines: false,
+            rename_threshold: None,
+            semantic_hunks: false,
+            persistent_cache: true,
+        }
+    }
+}
+
+/// Identifies a top-level entry of [`Settings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SettingsKey {
+    FetchSchedule,
+    SnapshotRetention,
+    DefaultPushRemote,
+    Diff,
+    Hooks,
+    BranchNaming,
+    DefaultBranchName,
+    RemoteBranchNames,
+    LaneOrder,
+    WriteCommitGraph,
+    Automations,
+    MessageGeneration,
+    SecretScanning,
+    Fetch,
+    LinearHistory,
+}
+
+/// Sent to [subscribers](crate::Controller::subscribe_to_settings()) when the settings of a project changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsChanged {
+    pub project_id: ProjectId,
+    /// The entries that differ from the previous settings.
+    pub changed: Vec<SettingsKey>,
+    /// All settings as they are now.
+    pub settings: Settings,
+}
+
+/// The settings as they are stored, along with the version of their schema.
+#[derive(Serialize, Deserialize)]
+struct VersionedSettings {
+    version: u32,
+    #[serde(flatten)]
+    settings: serde_json::Value,
+}
+
+impl Settings {
+    /// Read the settings of `project`, migrating them to the [current version](SETTINGS_VERSION) if they
+    /// were written by an older one.
+    ///
+    /// Projects without stored settings take them from the fields they were kept in before, which is version `0`.
+    pub fn load(project: &Project) -> Result<Settings> {
+        let storage = gitbutler_storage::Storage::new(project.gb_dir());
+        let (mut version, mut value) = match storage.read(SETTINGS_FILE)? {
+            Some(content) => {
+                let stored: VersionedSettings = serde_json::from_str(&content)
+                    .with_context(|| format!("failed to parse {SETTINGS_FILE}"))?;
+                (stored.version, stored.settings)
+            }
+            None => (0, serde_json::Value::Null),
+        };
+        if version > SETTINGS_VERSION {
+            bail!(
+                "settings of version {version} were written by a newer version of GitButler, \
+                 which supports up to version {SETTINGS_VERSION}"
+            );
+        }
+        let migrated = version < SETTINGS_VERSION;
+        while version < SETTINGS_VERSION {
+            value = migrate(version, value, project)?;
+            version += 1;
+        }
+        let settings: Settings =
+            serde_json::from_value(value).context("failed to read migrated settings")?;
+        if migrated && project.gb_dir().exists() {
+            if let Err(error) = settings.store(project) {
+                tracing::warn!(project_id = %project.id, ?error, "failed to store migrated settings");
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Write these settings as the ones of `project`.
+    pub(crate) fn store(&self, project: &Project) -> Result<()> {
+        let stored = VersionedSettings {
+            version: SETTINGS_VERSION,
+            settings: serde_json::to_value(self)?,
+        };
+        gitbutler_storage::Storage::new(project.gb_dir())
+            .write(SETTINGS_FILE, &serde_json::to_string_pretty(&stored)?)?;
+        Ok(())
+    }
+
+    /// Fail with a validation error if a value is out of range.
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self
+            .default_push_remote
+            .as_deref()
+            .is_some_and(|remote| remote.trim().is_empty())
+        {
+            return Err(anyhow!("the default push remote can't be empty"))
+                .context(Code::Validation);
+        }
+        if self
+            .diff
+            .rename_threshold
+            .is_some_and(|threshold| threshold > 100)
+        {
+            return Err(anyhow!("the rename threshold is a percentage up to 100"))
+                .context(Code::Validation);
+        }
+        match &self.branch_naming {
+            Some(BranchNaming::Command { command }) if command.trim().is_empty() => {
+                return Err(anyhow!("the branch naming command can't be empty"))
+                    .context(Code::Validation);
+            }
+            Some(BranchNaming::Http { url })
+                if !(url.starts_with("https://") || url.starts_with("http://")) =>
+            {
+                return Err(anyhow!("the branch naming endpoint must be an HTTP URL"))
+                    .context(Code::Validation);
+            }
+            _ => {}
+        }
+        let names = &self.remote_branch_names;
+        if names.template.trim().is_empty() {
+            return Err(anyhow!(
+                "the template of remote branch names can't be empty"
+            ))
+            .context(Code::Validation);
+        }
+        let mut rest = names.template.clone();
+        for placeholder in RemoteBranchNames::PLACEHOLDERS {
+            rest = rest.replace(placeholder, "");
+        }
+        if let Some(start) = rest.find('{') {
+            let placeholder = rest[start..]
+                .split_inclusive('}')
+                .next()
+                .unwrap_or_default();
+            return Err(anyhow!(
+                "the template of remote branch names uses the unknown placeholder '{placeholder}'"
+            ))
+            .context(Code::Validation);
+        }
+        if names.max_length == Some(0) {
+            return Err(anyhow!(
+                "the maximum length of remote branch names can't be 0"
+            ))
+            .context(Code::Validation);
+        }
+        for (index, automation) in self.automations.iter().enumerate() {
+            if automation.name.trim().is_empty() {
+                return Err(anyhow!("automations must have a name")).context(Code::Validation);
+            }
+            if self.automations[..index]
+                .iter()
+                .any(|other| other.name == automation.name)
+            {
+                return Err(anyhow!(
+                    "there is more than one automation named '{}'",
+                    automation.name
+                ))
+                .context(Code::Validation);
+            }
+            if automation.command.trim().is_empty() {
+                return Err(anyhow!(
+                    "the command of automation '{}' can't be empty",
+                    automation.name
+                ))
+                .context(Code::Validation);
+            }
+            if automation.timeout_secs == 0 {
+                return Err(anyhow!(
+                    "the timeout of automation '{}' can't be 0",
+                    automation.name
+                ))
+                .context(Code::Validation);
+            }
+        }
+        let generation = &self.message_generation;
+        if let Some(backend) = &generation.backend {
+            if backend.model().trim().is_empty() {
+                return Err(anyhow!(
+                    "the model to generate commit messages with can't be empty"
+                ))
+                .context(Code::Validation);
+            }
+            if backend
+                .base_url()
+                .is_some_and(|url| !(url.starts_with("https://") || url.starts_with("http://")))
+            {
+                return Err(anyhow!(
+                    "the URL of the API to generate commit messages with must be an HTTP URL"
+                ))
+                .context(Code::Validation);
+            }
+        }
+        if generation
+            .prompt_template
+            .as_ref()
+            .is_some_and(|template| !template.contains("{diff}"))
+        {
+            return Err(anyhow!(
+                "the prompt to generate commit messages with must contain '{{diff}}'"
+            ))
+            .context(Code::Validation);
+        }
+        if generation.max_diff_chars == 0 {
+            return Err(anyhow!(
+                "the amount of characters of changes to generate commit messages from can't be 0"
+            ))
+            .context(Code::Validation);
+        }
+        if let FetchRefspecs::Custom { refspecs } = &self.fetch.refspecs {
+            if refspecs.is_empty() {
+                return Err(anyhow!("at least one refspec to fetch is needed"))
+                    .context(Code::Validation);
+            }
+            if let Some(refspec) = refspecs.iter().find(|refspec| {
+                let refspec = refspec.strip_prefix('+').unwrap_or(refspec);
+                refspec.is_empty()
+                    || refspec.contains(char::is_whitespace)
+                    || refspec.matches(':').count() > 1
+            }) {
+                return Err(anyhow!("'{refspec}' isn't a valid refspec to fetch"))
+                    .context(Code::Validation);
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the entries whose values differ from the ones in `other`.
+    pub fn changed_keys(&self, other: &Settings) -> Vec<SettingsKey> {
+        [
+            (
+                SettingsKey::FetchSchedule,
+                self.fetch_schedule != other.fetch_schedule,
+            ),
+            (
+                SettingsKey::SnapshotRetention,
+                self.snapshot_retention != other.snapshot_retention,
+            ),
+            (
+                SettingsKey::DefaultPushRemote,
+                self.default_push_remote != other.default_push_remote,
+            ),
+            (SettingsKey::Diff, self.diff != other.diff),
+            (SettingsKey::Hooks, self.hooks != other.hooks),
+            (
+                SettingsKey::BranchNaming,
+                self.branch_naming != other.branch_naming,
+            ),
+            (
+                SettingsKey::DefaultBranchName,
+                self.default_branch_name != other.default_branch_name,
+            ),
+            (
+                SettingsKey::RemoteBranchNames,
+                self.remote_branch_names != other.remote_branch_names,
+            ),
+            (SettingsKey::LaneOrder, self.lane_order != other.lane_order),
+            (
+                SettingsKey::WriteCommitGraph,
+                self.write_commit_graph != other.write_commit_graph,
+            ),
+            (
+                SettingsKey::Automations,
+                self.automations != other.automations,
+            ),
+            (
+                SettingsKey::MessageGeneration,
+                self.message_generation != other.message_generation,
+            ),
+            (
+                SettingsKey::SecretScanning,
+                self.secret_scanning != other.secret_scanning,
+            ),
+            (SettingsKey::Fetch, self.fetch != other.fetch),
+            (
+                SettingsKey::LinearHistory,
+                self.linear_history != other.linear_history,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(key, changed)| changed.then_some(key))
+        .collect()
+    }
+}
+
+/// Migrate `value`, the settings in `version` of the schema, to the next version.
+fn migrate(version: u32, value: serde_json::Value, project: &Project) -> Result<serde_json::Value> {
+    match version {
+        // Settings used to be fields of the project, and nothing was stored.
+        0 if value.is_null() => Ok(serde_json::to_value(Settings {
+            fetch_schedule: project.fetch_schedule.clone(),
+            snapshot_retention: project.snapshot_retention.clone(),
+            hooks: project.hooks.clone(),
+            ..Default::default()
+        })?),
+        _ => bail!("there is no migration from settings version {version}"),
+    }
+}
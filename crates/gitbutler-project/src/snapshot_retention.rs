@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Controls how many snapshots of the operations log are kept when it's garbage-collected.
+///
+/// A snapshot is kept only if it satisfies all limits, but the most recent snapshot is always kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SnapshotRetention {
+    /// The maximum amount of snapshots to keep.
+    pub max_snapshots: Option<usize>,
+    /// The maximum age in days of snapshots to keep.
+    pub max_age_days: Option<u64>,
+    /// The maximum amount of bytes the objects introduced by the kept snapshots may take.
+    pub max_size_bytes: Option<u64>,
+}
+
+impl Default for SnapshotRetention {
+    fn default() -> Self {
+        SnapshotRetention {
+            max_snapshots: Some(1000),
+            max_age_days: None,
+            max_size_bytes: None,
+        }
+    }
+}
+
+impl SnapshotRetention {
+    /// Return the age after which snapshots are dropped, if limited.
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age_days
+            .map(|days| Duration::from_secs(days.saturating_mul(24 * 60 * 60)))
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Controls which events create a snapshot in the operations log automatically.
+///
+/// Operations that aren't covered here, like applying branches or discarding changes, always create
+/// a snapshot as it's needed to undo them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SnapshotTriggers {
+    /// Snapshot the workspace before a commit is created.
+    pub before_commit: bool,
+    /// Snapshot the workspace before existing commits are rewritten, like when rebasing, reordering,
+    /// squashing or amending them.
+    pub before_rebase: bool,
+    /// Snapshot changes to the worktree once more lines than the project's `snapshot_lines_threshold`
+    /// changed since the last snapshot.
+    pub on_lines_changed: bool,
+    /// Snapshot any change to the worktree if the last snapshot is at least this many seconds old,
+    /// no matter how few lines changed.
+    pub interval_secs: Option<u64>,
+    /// The minimum amount of seconds between snapshots of changes to the worktree.
+    pub min_interval_secs: u64,
+}
+
+impl Default for SnapshotTriggers {
+    fn default() -> Self {
+        SnapshotTriggers {
+            before_commit: true,
+            before_rebase: true,
+            on_lines_changed: true,
+            interval_secs: None,
+            min_interval_secs: 300,
+        }
+    }
+}
+
+/// Sets of [`SnapshotTriggers`] for common needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SnapshotTriggersPreset {
+    /// The triggers that are used if none are configured.
+    Default,
+    /// Snapshot as often as possible, so even small changes to the worktree can be restored.
+    Paranoid,
+    /// Only snapshot before commits are rewritten, to keep the operations log small.
+    Minimal,
+}
+
+impl From<SnapshotTriggersPreset> for SnapshotTriggers {
+    fn from(preset: SnapshotTriggersPreset) -> Self {
+        match preset {
+            SnapshotTriggersPreset::Default => SnapshotTriggers::default(),
+            SnapshotTriggersPreset::Paranoid => SnapshotTriggers {
+                before_commit: true,
+                before_rebase: true,
+                on_lines_changed: true,
+                interval_secs: Some(60),
+                min_interval_secs: 30,
+            },
+            SnapshotTriggersPreset::Minimal => SnapshotTriggers {
+                before_commit: false,
+                before_rebase: true,
+                on_lines_changed: false,
+                interval_secs: None,
+                min_interval_secs: 300,
+            },
+        }
+    }
+}
+
+impl SnapshotTriggers {
+    /// Return `true` if changes to the worktree should be snapshotted if the last snapshot was made `elapsed` ago,
+    /// and `lines_changed` lines changed since then.
+    /// `lines_threshold` is the amount of lines that must be exceeded for [`on_lines_changed`](Self::on_lines_changed).
+    ///
+    /// Note that [`min_interval`](Self::min_interval()) isn't checked here, as it's up to the caller to not
+    /// even count changed lines before it passed.
+    pub fn worktree_snapshot_is_due(
+        &self,
+        elapsed: Duration,
+        lines_changed: usize,
+        lines_threshold: usize,
+    ) -> bool {
+        if lines_changed == 0 {
+            return false;
+        }
+        let interval_elapsed = self
+            .interval_secs
+            .is_some_and(|secs| elapsed >= Duration::from_secs(secs));
+        interval_elapsed || (self.on_lines_changed && lines_changed > lines_threshold)
+    }
+
+    /// Return `true` if any change to the worktree may lead to a snapshot, so it's worth counting changed lines.
+    pub fn snapshots_worktree(&self) -> bool {
+        self.on_lines_changed || self.interval_secs.is_some()
+    }
+
+    /// Return the minimum time between snapshots of changes to the worktree.
+    pub fn min_interval(&self) -> Duration {
+        Duration::from_secs(self.min_interval_secs)
+    }
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// A way to authenticate with remotes over SSH, tried in the order of the
+/// [SSH auth order](crate::Project::ssh_auth_order) of a project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SshAuthMethod {
+    /// The keys of the SSH agent that `SSH_AUTH_SOCK` points to, or of the OpenSSH agent service
+    /// on Windows, including hardware-backed keys like FIDO2 (`sk-`) keys.
+    Agent,
+    /// The keys `gpg-agent` offers through its SSH support, like the ones on a smartcard.
+    GpgAgent,
+    /// The private key file of the [preferred key](crate::AuthKey::Local) of the project.
+    KeyFile,
+}
@@ -1,9 +1,14 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
-use crate::{ApiProject, AuthKey, CodePushState, FetchResult, Project, ProjectId};
+use crate::{
+    ApiProject, AuthKey, BranchCleanupPolicy, CodePushState, CommitConventions, FetchResult,
+    FetchSchedule, ForgeKind, HookSettings, IdleMaintenance, ListingFormat, Parallelism, Project,
+    ProjectId, PushedCommitRewrites, SnapshotRetention, SnapshotTriggers, SnapshotTriggersPreset,
+    SshAuthMethod, TransferRetries, WatcherSettings,
+};
 
 const PROJECTS_FILE: &str = "projects.json";
 
@@ -27,6 +32,23 @@ pub struct UpdateRequest {
     pub use_diff_context: Option<bool>,
     pub snapshot_lines_threshold: Option<usize>,
     pub ignore_project_semaphore: Option<bool>,
+    pub fetch_schedule: Option<FetchSchedule>,
+    pub listing_format: Option<ListingFormat>,
+    pub extra_env: Option<BTreeMap<String, String>>,
+    pub hooks: Option<HookSettings>,
+    pub snapshot_retention: Option<SnapshotRetention>,
+    pub snapshot_triggers: Option<SnapshotTriggers>,
+    /// Replace the snapshot triggers with the ones of a preset, before `snapshot_triggers` is applied.
+    pub snapshot_triggers_preset: Option<SnapshotTriggersPreset>,
+    pub branch_cleanup: Option<BranchCleanupPolicy>,
+    pub watcher: Option<WatcherSettings>,
+    pub parallelism: Option<Parallelism>,
+    pub forge: Option<ForgeKind>,
+    pub transfer_retries: Option<TransferRetries>,
+    pub ssh_auth_order: Option<Vec<SshAuthMethod>>,
+    pub commit_conventions: Option<CommitConventions>,
+    pub idle_maintenance: Option<IdleMaintenance>,
+    pub pushed_commit_rewrites: Option<PushedCommitRewrites>,
 }
 
 impl Storage {
@@ -123,6 +145,70 @@ impl Storage {
             project.ignore_project_semaphore = ignore_project_semaphore;
         }
 
+        if let Some(fetch_schedule) = &update_request.fetch_schedule {
+            project.fetch_schedule = fetch_schedule.clone();
+        }
+
+        if let Some(listing_format) = update_request.listing_format {
+            project.listing_format = listing_format;
+        }
+
+        if let Some(extra_env) = &update_request.extra_env {
+            project.extra_env = extra_env.clone();
+        }
+
+        if let Some(hooks) = &update_request.hooks {
+            project.hooks = hooks.clone();
+        }
+
+        if let Some(snapshot_retention) = &update_request.snapshot_retention {
+            project.snapshot_retention = snapshot_retention.clone();
+        }
+
+        if let Some(preset) = update_request.snapshot_triggers_preset {
+            project.snapshot_triggers = preset.into();
+        }
+
+        if let Some(snapshot_triggers) = &update_request.snapshot_triggers {
+            project.snapshot_triggers = snapshot_triggers.clone();
+        }
+
+        if let Some(branch_cleanup) = &update_request.branch_cleanup {
+            project.branch_cleanup = branch_cleanup.clone();
+        }
+
+        if let Some(watcher) = &update_request.watcher {
+            project.watcher = watcher.clone();
+        }
+
+        if let Some(parallelism) = update_request.parallelism {
+            project.parallelism = parallelism;
+        }
+
+        if let Some(forge) = update_request.forge {
+            project.forge = forge;
+        }
+
+        if let Some(transfer_retries) = update_request.transfer_retries {
+            project.transfer_retries = transfer_retries;
+        }
+
+        if let Some(ssh_auth_order) = &update_request.ssh_auth_order {
+            project.ssh_auth_order = ssh_auth_order.clone();
+        }
+
+        if let Some(commit_conventions) = &update_request.commit_conventions {
+            project.commit_conventions = commit_conventions.clone();
+        }
+
+        if let Some(idle_maintenance) = update_request.idle_maintenance {
+            project.idle_maintenance = idle_maintenance;
+        }
+
+        if let Some(pushed_commit_rewrites) = update_request.pushed_commit_rewrites {
+            project.pushed_commit_rewrites = pushed_commit_rewrites;
+        }
+
         self.inner
             .write(PROJECTS_FILE, &serde_json::to_string_pretty(&projects)?)?;
 
@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Controls how pushes and fetches that were interrupted by the network are attempted again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TransferRetries {
+    /// How often an interrupted transfer is attempted again, with `0` failing right away.
+    pub max_retries: u32,
+    /// The amount of milliseconds to wait before the first retry, which doubles with each further one.
+    pub initial_backoff_ms: u64,
+    /// The upper bound in milliseconds for the delay between retries.
+    pub max_backoff_ms: u64,
+}
+
+impl Default for TransferRetries {
+    fn default() -> Self {
+        TransferRetries {
+            max_retries: 3,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 30 * 1000,
+        }
+    }
+}
+
+impl TransferRetries {
+    /// Return the delay before the retry numbered `retry`, starting at `1`.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u64.saturating_pow(retry.saturating_sub(1).min(32));
+        Duration::from_millis(
+            self.initial_backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms),
+        )
+    }
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// Controls which changes to the worktree the watcher reacts to.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatcherSettings {
+    /// Patterns in `.gitignore` format, relative to the worktree, of files whose changes are ignored
+    /// in addition to the ones that are ignored by Git, like build output that is committed.
+    /// New changes to these files may only show once the worktree is diffed from scratch, like after a checkout.
+    pub ignore: Vec<String>,
+}
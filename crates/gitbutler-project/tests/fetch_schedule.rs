@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use gitbutler_project::{FetchFailure, FetchSchedule};
+
+fn schedule() -> FetchSchedule {
+    FetchSchedule {
+        default_interval_secs: 60,
+        remote_interval_secs: [("upstream".to_string(), 300)].into_iter().collect(),
+        jitter_secs: 10,
+        max_backoff_secs: 600,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn per_remote_interval_overrides_default() {
+    let schedule = schedule();
+    assert_eq!(schedule.interval_for("origin"), Duration::from_secs(60));
+    assert_eq!(schedule.interval_for("upstream"), Duration::from_secs(300));
+}
+
+#[test]
+fn jitter_is_added_to_regular_interval() {
+    let schedule = schedule();
+    assert_eq!(
+        schedule.next_delay("origin", 0, None, 0.0),
+        Duration::from_secs(60)
+    );
+    assert_eq!(
+        schedule.next_delay("origin", 0, None, 0.5),
+        Duration::from_secs(65)
+    );
+}
+
+#[test]
+fn failures_back_off_exponentially_up_to_the_maximum() {
+    let schedule = schedule();
+    assert_eq!(
+        schedule.next_delay("origin", 1, Some(FetchFailure::Network), 0.0),
+        Duration::from_secs(120)
+    );
+    assert_eq!(
+        schedule.next_delay("origin", 2, Some(FetchFailure::Network), 0.0),
+        Duration::from_secs(240)
+    );
+    assert_eq!(
+        schedule.next_delay("origin", 10, Some(FetchFailure::Network), 0.0),
+        Duration::from_secs(600)
+    );
+}
+
+#[test]
+fn auth_failures_back_off_faster() {
+    let schedule = schedule();
+    assert_eq!(
+        schedule.next_delay("origin", 1, Some(FetchFailure::Auth), 0.0),
+        Duration::from_secs(480)
+    );
+}
+
+#[test]
+fn offline_failures_keep_the_regular_interval() {
+    let schedule = schedule();
+    assert_eq!(
+        schedule.next_delay("origin", 5, Some(FetchFailure::Offline), 0.0),
+        Duration::from_secs(60)
+    );
+}
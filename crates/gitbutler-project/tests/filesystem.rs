@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use gitbutler_project::{FilesystemBoundary, FilesystemCapabilities};
+
+#[test]
+fn wsl_shares_are_translated_to_linux_paths() {
+    for share in [
+        r"\\wsl$\Ubuntu\home\me\project",
+        r"\\wsl.localhost\Ubuntu\home\me\project",
+        r"\\?\UNC\wsl.localhost\Ubuntu\home\me\project",
+    ] {
+        assert_eq!(
+            FilesystemBoundary::of(Path::new(share), false),
+            FilesystemBoundary::WslFromWindows {
+                distro: "Ubuntu".into(),
+                linux_path: "/home/me/project".into(),
+            }
+        );
+    }
+}
+
+#[test]
+fn windows_drives_are_only_crossed_from_within_wsl() {
+    let path = Path::new("/mnt/c/Users/me/project");
+    assert_eq!(
+        FilesystemBoundary::of(path, true),
+        FilesystemBoundary::WindowsFromWsl {
+            windows_path: r"C:\Users\me\project".into(),
+        }
+    );
+    assert_eq!(
+        FilesystemBoundary::of(path, false),
+        FilesystemBoundary::Native,
+        "outside of WSL, /mnt is just a directory"
+    );
+    assert_eq!(
+        FilesystemBoundary::of(Path::new("/mnt/data/project"), true),
+        FilesystemBoundary::Native,
+        "only single letters are drives"
+    );
+}
+
+#[test]
+fn crossed_boundaries_require_polling_and_warn_about_line_endings() {
+    let native = FilesystemCapabilities::new(FilesystemBoundary::Native, Some("true".into()));
+    assert!(native.file_events);
+    assert!(native.warnings.is_empty());
+
+    let boundary = FilesystemBoundary::of(Path::new("/mnt/d/project"), true);
+    let crossed = FilesystemCapabilities::new(boundary.clone(), Some("input".into()));
+    assert!(!crossed.file_events);
+    assert_eq!(crossed.warnings.len(), 1, "only polling is a concern");
+
+    let crossed = FilesystemCapabilities::new(boundary, Some("true".into()));
+    assert_eq!(crossed.warnings.len(), 2, "line endings may differ as well");
+}
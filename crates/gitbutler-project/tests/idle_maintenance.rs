@@ -0,0 +1,17 @@
+use std::time::Duration;
+
+use gitbutler_project::IdleMaintenance;
+
+#[test]
+fn defaults_apply_to_projects_without_settings() {
+    let maintenance: IdleMaintenance = serde_json::from_str(r#"{ "idleAfterSecs": 30 }"#).unwrap();
+    assert_eq!(
+        maintenance,
+        IdleMaintenance {
+            idle_after_secs: 30,
+            ..Default::default()
+        }
+    );
+    assert!(maintenance.enabled);
+    assert_eq!(maintenance.idle_after(), Duration::from_secs(30));
+}
@@ -0,0 +1,86 @@
+use gitbutler_project::{AuthorFormat, ListingFormat, TimeFormat, TimeZone};
+
+const BILLENNIUM: i64 = 1_000_000_000;
+
+fn absolute(time_zone: TimeZone) -> ListingFormat {
+    ListingFormat {
+        time: TimeFormat::Absolute,
+        time_zone,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn relative_times_follow_git() {
+    let format = ListingFormat::default();
+    let at = |seconds_ago: i64| format.format_time(BILLENNIUM - seconds_ago, 0, BILLENNIUM);
+    assert_eq!(at(1), "1 second ago");
+    assert_eq!(at(89), "89 seconds ago");
+    assert_eq!(at(90), "2 minutes ago");
+    assert_eq!(at(3 * 60 * 60), "3 hours ago");
+    assert_eq!(at(3 * 24 * 60 * 60), "3 days ago");
+    assert_eq!(at(21 * 24 * 60 * 60), "3 weeks ago");
+    assert_eq!(at(100 * 24 * 60 * 60), "3 months ago");
+    assert_eq!(at(365 * 24 * 60 * 60), "1 year ago");
+    assert_eq!(at(500 * 24 * 60 * 60), "1 year, 4 months ago");
+    assert_eq!(at(-10), "in the future");
+}
+
+#[test]
+fn absolute_times_respect_time_zone() {
+    assert_eq!(
+        absolute(TimeZone::Recorded).format_time(BILLENNIUM, 120, 0),
+        "2001-09-09 03:46:40 +0200"
+    );
+    assert_eq!(
+        absolute(TimeZone::Utc).format_time(BILLENNIUM, 120, 0),
+        "2001-09-09 01:46:40 +0000"
+    );
+    assert_eq!(
+        absolute(TimeZone::Fixed {
+            offset_minutes: -330
+        })
+        .format_time(BILLENNIUM, 120, 0),
+        "2001-09-08 20:16:40 -0530"
+    );
+}
+
+#[test]
+fn authors_fall_back_to_what_is_available() {
+    let format = |author| ListingFormat {
+        author,
+        ..Default::default()
+    };
+    assert_eq!(
+        format(AuthorFormat::Name).format_author("Jane", "jane@example.com"),
+        "Jane"
+    );
+    assert_eq!(
+        format(AuthorFormat::Name).format_author("", "jane@example.com"),
+        "jane@example.com"
+    );
+    assert_eq!(
+        format(AuthorFormat::Email).format_author("Jane", "jane@example.com"),
+        "jane@example.com"
+    );
+    assert_eq!(
+        format(AuthorFormat::NameAndEmail).format_author("Jane", "jane@example.com"),
+        "Jane <jane@example.com>"
+    );
+}
+
+#[test]
+fn deserializes_from_partial_settings() {
+    let format: ListingFormat = serde_json::from_str(
+        r#"{"time":"absolute","timeZone":{"kind":"fixed","offsetMinutes":60}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        format,
+        ListingFormat {
+            time: TimeFormat::Absolute,
+            time_zone: TimeZone::Fixed { offset_minutes: 60 },
+            author: AuthorFormat::Name,
+        }
+    );
+}
@@ -1 +1,9 @@
+mod fetch_schedule;
+mod filesystem;
+mod idle_maintenance;
+mod listing_format;
+mod operation_lock;
 mod projects;
+mod settings;
+mod snapshot_triggers;
+mod transfer_retries;
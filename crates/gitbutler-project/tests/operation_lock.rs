@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_project::{access::ProjectBusy, ProjectId};
+
+use super::projects::new;
+
+fn busy(err: anyhow::Error) -> ProjectBusy {
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::ProjectBusy)
+    );
+    err.downcast_ref::<ProjectBusy>().cloned().unwrap()
+}
+
+#[test]
+fn operations_in_the_same_process_wait_for_each_other() {
+    let (controller, _tmp) = new();
+    let repository = gitbutler_testsupport::TestProject::default();
+    let project = controller.add(repository.path()).unwrap();
+
+    let guard = project.exclusive_operation_access("create_commit").unwrap();
+    let err = std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                project
+                    .exclusive_operation_access_within("push", Duration::from_millis(50))
+                    .map(drop)
+            })
+            .join()
+            .unwrap()
+    })
+    .unwrap_err();
+    assert_eq!(
+        busy(err),
+        ProjectBusy {
+            operation: "create_commit".into(),
+            pid: None,
+        }
+    );
+
+    std::thread::scope(|scope| {
+        let waiting = scope.spawn(|| {
+            project
+                .exclusive_operation_access_within("push", Duration::from_secs(10))
+                .map(drop)
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        drop(guard);
+        waiting.join().unwrap()
+    })
+    .expect("waiting operations run once the project is available");
+}
+
+#[test]
+fn operations_of_other_processes_are_reported() {
+    let (controller, _tmp) = new();
+    let repository = gitbutler_testsupport::TestProject::default();
+    let project = controller.add(repository.path()).unwrap();
+    // Another instance of the same project only shares the files, like one of another process would.
+    let mut other = project.clone();
+    other.id = ProjectId::generate();
+
+    let _guard = other
+        .exclusive_operation_access("update_base_branch")
+        .unwrap();
+    let err = project
+        .exclusive_operation_access_within("create_commit", Duration::from_millis(50))
+        .map(drop)
+        .unwrap_err();
+    assert_eq!(
+        busy(err),
+        ProjectBusy {
+            operation: "update_base_branch".into(),
+            pid: Some(std::process::id()),
+        }
+    );
+}
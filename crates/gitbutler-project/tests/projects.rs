@@ -79,40 +79,69 @@ mod add {
             create_initial_commit(&repo);
 
             let err = controller.add(repo_dir).unwrap_err();
-            assert_eq!(err.to_string(), "bare repositories are unsupported");
+            assert_eq!(
+                err.to_string(),
+                "Bare repositories have no worktree, add one of its worktrees instead"
+            );
         }
 
         #[test]
-        fn worktree() {
+        fn worktree_of_existing_project() {
             let (controller, _tmp) = new();
             let tmp = tempfile::tempdir().unwrap();
             let main_worktree_dir = tmp.path().join("main");
             let worktree_dir = tmp.path().join("worktree");
 
-            let repo = git2::Repository::init(main_worktree_dir).unwrap();
+            let repo = git2::Repository::init(&main_worktree_dir).unwrap();
             create_initial_commit(&repo);
+            controller.add(&main_worktree_dir).unwrap();
 
             let worktree = repo.worktree("feature", &worktree_dir, None).unwrap();
             let err = controller.add(worktree.path()).unwrap_err();
-            assert_eq!(err.to_string(), "can only work in main worktrees");
+            assert_eq!(
+                err.to_string(),
+                "Another worktree of this repository was added already"
+            );
         }
+    }
 
-        fn create_initial_commit(repo: &git2::Repository) -> git2::Oid {
-            let signature = git2::Signature::now("test", "test@email.com").unwrap();
-
-            let mut index = repo.index().unwrap();
-            let oid = index.write_tree().unwrap();
-
-            repo.commit(
-                Some("HEAD"),
-                &signature,
-                &signature,
-                "initial commit",
-                &repo.find_tree(oid).unwrap(),
-                &[],
-            )
-            .unwrap()
-        }
+    #[test]
+    fn worktree() {
+        let (controller, _tmp) = new();
+        let tmp = tempfile::tempdir().unwrap();
+        let main_worktree_dir = tmp.path().join("main");
+        let worktree_dir = tmp.path().join("worktree");
+
+        let repo = git2::Repository::init(&main_worktree_dir).unwrap();
+        create_initial_commit(&repo);
+
+        let worktree = repo.worktree("feature", &worktree_dir, None).unwrap();
+        let project = controller.add(worktree.path()).unwrap();
+        assert_eq!(
+            std::fs::canonicalize(project.git_dir()).unwrap(),
+            std::fs::canonicalize(main_worktree_dir.join(".git/worktrees/feature")).unwrap()
+        );
+        assert!(
+            project.gb_dir().is_dir(),
+            "the state is kept in the git-dir of the worktree"
+        );
+    }
+
+    fn create_initial_commit(repo: &git2::Repository) -> git2::Oid {
+        let signature = git2::Signature::now("test", "test@email.com").unwrap();
+
+        let mut index = repo.index().unwrap();
+        let oid = index.write_tree().unwrap();
+
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "initial commit",
+            &repo.find_tree(oid).unwrap(),
+            &[],
+        )
+        .unwrap()
     }
 }
 
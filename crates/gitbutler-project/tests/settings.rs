@@ -0,0 +1,375 @@
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_project::{
+    Automation, AutomationTrigger, DiffSettings, FetchRefspecs, FetchSettings, HookSettings,
+    MessageGeneration, MessageGenerationBackend, Project, RemoteBranchNames, Settings, SettingsKey,
+    UpdateRequest, SETTINGS_VERSION,
+};
+
+use crate::projects::new;
+
+#[test]
+fn settings_are_migrated_from_project_fields() {
+    let repository = gitbutler_testsupport::TestProject::default();
+    let project = Project {
+        path: repository.path().to_owned(),
+        hooks: HookSettings {
+            enabled: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    std::fs::create_dir_all(project.gb_dir()).unwrap();
+
+    let settings = Settings::load(&project).unwrap();
+    assert!(!settings.hooks.enabled);
+
+    let stored: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(project.gb_dir().join("settings.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(stored["version"], SETTINGS_VERSION);
+
+    let project = Project {
+        hooks: HookSettings::default(),
+        ..project
+    };
+    assert_eq!(
+        Settings::load(&project).unwrap(),
+        settings,
+        "once stored, the fields of the project aren't read anymore"
+    );
+}
+
+#[test]
+fn settings_of_newer_versions_are_rejected() {
+    let repository = gitbutler_testsupport::TestProject::default();
+    let project = Project {
+        path: repository.path().to_owned(),
+        ..Default::default()
+    };
+    std::fs::create_dir_all(project.gb_dir()).unwrap();
+    std::fs::write(
+        project.gb_dir().join("settings.json"),
+        format!(r#"{{ "version": {} }}"#, SETTINGS_VERSION + 1),
+    )
+    .unwrap();
+
+    let err = Settings::load(&project).unwrap_err();
+    assert!(err.to_string().contains("newer version"), "{err}");
+}
+
+#[test]
+fn updates_notify_subscribers_of_changed_entries() {
+    let (controller, _tmp) = new();
+    let repository = gitbutler_testsupport::TestProject::default();
+    let project = controller.add(repository.path()).unwrap();
+    let mut events = controller.clone().subscribe_to_settings();
+
+    let settings = Settings {
+        default_push_remote: Some("fork".into()),
+        diff: DiffSettings {
+            context_lines: 0,
+            ..Default::default()
+        },
+        ..project.settings.clone()
+    };
+    controller
+        .update_settings(project.id, settings.clone())
+        .unwrap();
+
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.project_id, project.id);
+    assert_eq!(
+        event.changed,
+        [SettingsKey::DefaultPushRemote, SettingsKey::Diff]
+    );
+    assert_eq!(event.settings, settings);
+    assert_eq!(controller.get(project.id).unwrap().settings, settings);
+
+    controller.update_settings(project.id, settings).unwrap();
+    assert!(
+        events.try_recv().is_err(),
+        "nothing is sent if nothing changed"
+    );
+}
+
+#[test]
+fn project_updates_of_former_fields_change_the_settings() {
+    let (controller, _tmp) = new();
+    let repository = gitbutler_testsupport::TestProject::default();
+    let project = controller.add(repository.path()).unwrap();
+    let mut events = controller.subscribe_to_settings();
+
+    let hooks = HookSettings {
+        timeout_secs: 10,
+        ..Default::default()
+    };
+    let updated = controller
+        .update(&UpdateRequest {
+            id: project.id,
+            hooks: Some(hooks.clone()),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(updated.settings.hooks, hooks);
+    assert_eq!(events.try_recv().unwrap().changed, [SettingsKey::Hooks]);
+}
+
+#[test]
+fn invalid_settings_are_rejected() {
+    let (controller, _tmp) = new();
+    let repository = gitbutler_testsupport::TestProject::default();
+    let project = controller.add(repository.path()).unwrap();
+
+    let err = controller
+        .update_settings(
+            project.id,
+            Settings {
+                diff: DiffSettings {
+                    rename_threshold: Some(101),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Validation)
+    );
+    assert_eq!(
+        controller.get(project.id).unwrap().settings,
+        project.settings
+    );
+}
+
+#[test]
+fn remote_branch_name_templates_with_unknown_placeholders_are_rejected() {
+    let (controller, _tmp) = new();
+    let repository = gitbutler_testsupport::TestProject::default();
+    let project = controller.add(repository.path()).unwrap();
+
+    for (template, message) in [
+        ("{team}/{normalized-title}", "unknown placeholder '{team}'"),
+        (" ", "can't be empty"),
+    ] {
+        let err = controller
+            .update_settings(
+                project.id,
+                Settings {
+                    remote_branch_names: RemoteBranchNames {
+                        template: template.into(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation)
+        );
+        assert!(err.to_string().contains(message), "{err:#}");
+    }
+
+    let names = RemoteBranchNames {
+        template: "{user}/{normalized-title}".into(),
+        forbidden_characters: "#".into(),
+        max_length: Some(40),
+    };
+    let updated = controller
+        .update_settings(
+            project.id,
+            Settings {
+                remote_branch_names: names.clone(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(updated.remote_branch_names, names);
+}
+
+#[test]
+fn invalid_automations_are_rejected() {
+    let (controller, _tmp) = new();
+    let repository = gitbutler_testsupport::TestProject::default();
+    let project = controller.add(repository.path()).unwrap();
+
+    let automation = Automation {
+        name: "build".into(),
+        events: [AutomationTrigger::CommitCreated].into(),
+        command: "make".into(),
+        ..Default::default()
+    };
+    for (automations, message) in [
+        (
+            vec![Automation {
+                command: " ".into(),
+                ..automation.clone()
+            }],
+            "can't be empty",
+        ),
+        (
+            vec![Automation {
+                timeout_secs: 0,
+                ..automation.clone()
+            }],
+            "can't be 0",
+        ),
+        (
+            vec![automation.clone(), automation.clone()],
+            "more than one automation named 'build'",
+        ),
+    ] {
+        let err = controller
+            .update_settings(
+                project.id,
+                Settings {
+                    automations,
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation)
+        );
+        assert!(err.to_string().contains(message), "{err:#}");
+    }
+
+    let updated = controller
+        .update_settings(
+            project.id,
+            Settings {
+                automations: vec![automation.clone()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(updated.automations, [automation]);
+}
+
+#[test]
+fn invalid_message_generation_is_rejected() {
+    let (controller, _tmp) = new();
+    let repository = gitbutler_testsupport::TestProject::default();
+    let project = controller.add(repository.path()).unwrap();
+
+    let backend = MessageGenerationBackend::OpenAiCompatible {
+        base_url: "http://localhost:8080/v1".into(),
+        model: "local".into(),
+    };
+    for (message_generation, message) in [
+        (
+            MessageGeneration {
+                backend: Some(MessageGenerationBackend::Ollama {
+                    base_url: "localhost:11434".into(),
+                    model: "llama3".into(),
+                }),
+                ..Default::default()
+            },
+            "must be an HTTP URL",
+        ),
+        (
+            MessageGeneration {
+                backend: Some(MessageGenerationBackend::Anthropic {
+                    base_url: None,
+                    model: " ".into(),
+                }),
+                ..Default::default()
+            },
+            "can't be empty",
+        ),
+        (
+            MessageGeneration {
+                backend: Some(backend.clone()),
+                prompt_template: Some("Describe the changes".into()),
+                ..Default::default()
+            },
+            "must contain '{diff}'",
+        ),
+    ] {
+        let err = controller
+            .update_settings(
+                project.id,
+                Settings {
+                    message_generation,
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation)
+        );
+        assert!(err.to_string().contains(message), "{err:#}");
+    }
+
+    let updated = controller
+        .update_settings(
+            project.id,
+            Settings {
+                message_generation: MessageGeneration {
+                    backend: Some(backend.clone()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(updated.message_generation.backend, Some(backend));
+}
+
+#[test]
+fn invalid_fetch_refspecs_are_rejected() {
+    let (controller, _tmp) = new();
+    let repository = gitbutler_testsupport::TestProject::default();
+    let project = controller.add(repository.path()).unwrap();
+
+    for (refspecs, message) in [
+        (vec![], "at least one refspec"),
+        (
+            vec!["+refs/heads/main refs/remotes/origin/main".into()],
+            "isn't a valid refspec",
+        ),
+        (
+            vec!["refs/heads/a:refs/b:refs/c".into()],
+            "isn't a valid refspec",
+        ),
+    ] {
+        let err = controller
+            .update_settings(
+                project.id,
+                Settings {
+                    fetch: FetchSettings {
+                        refspecs: FetchRefspecs::Custom { refspecs },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::Validation)
+        );
+        assert!(err.to_string().contains(message), "{err:#}");
+    }
+
+    let refspecs = FetchRefspecs::Custom {
+        refspecs: vec!["+refs/heads/main:refs/remotes/{remote}/main".into()],
+    };
+    let updated = controller
+        .update_settings(
+            project.id,
+            Settings {
+                fetch: FetchSettings {
+                    refspecs: refspecs.clone(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(updated.fetch.refspecs, refspecs);
+}
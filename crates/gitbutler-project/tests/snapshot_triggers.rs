@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use gitbutler_project::{SnapshotTriggers, SnapshotTriggersPreset};
+
+const THRESHOLD: usize = 20;
+
+#[test]
+fn lines_changed_above_threshold_are_due() {
+    let triggers = SnapshotTriggers::default();
+    let elapsed = Duration::from_secs(600);
+    assert!(!triggers.worktree_snapshot_is_due(elapsed, THRESHOLD, THRESHOLD));
+    assert!(triggers.worktree_snapshot_is_due(elapsed, THRESHOLD + 1, THRESHOLD));
+}
+
+#[test]
+fn any_change_is_due_once_the_interval_passed() {
+    let triggers = SnapshotTriggers {
+        on_lines_changed: false,
+        interval_secs: Some(60),
+        ..Default::default()
+    };
+    assert!(!triggers.worktree_snapshot_is_due(Duration::from_secs(59), 1, THRESHOLD));
+    assert!(triggers.worktree_snapshot_is_due(Duration::from_secs(60), 1, THRESHOLD));
+    assert!(
+        !triggers.worktree_snapshot_is_due(Duration::from_secs(60), 0, THRESHOLD),
+        "there is nothing to snapshot"
+    );
+}
+
+#[test]
+fn presets() {
+    let paranoid = SnapshotTriggers::from(SnapshotTriggersPreset::Paranoid);
+    assert!(paranoid.before_commit && paranoid.before_rebase);
+    assert!(paranoid.snapshots_worktree());
+    assert!(paranoid.min_interval() < SnapshotTriggers::default().min_interval());
+
+    let minimal = SnapshotTriggers::from(SnapshotTriggersPreset::Minimal);
+    assert!(!minimal.before_commit && minimal.before_rebase);
+    assert!(!minimal.snapshots_worktree());
+
+    assert_eq!(
+        SnapshotTriggers::from(SnapshotTriggersPreset::Default),
+        SnapshotTriggers::default()
+    );
+}
+
+#[test]
+fn missing_fields_are_defaulted() {
+    let triggers: SnapshotTriggers = serde_json::from_str(r#"{"beforeCommit":false}"#).unwrap();
+    assert_eq!(
+        triggers,
+        SnapshotTriggers {
+            before_commit: false,
+            ..Default::default()
+        }
+    );
+}
@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use gitbutler_project::TransferRetries;
+
+#[test]
+fn backoff_doubles_up_to_the_maximum() {
+    let retries = TransferRetries {
+        max_retries: 10,
+        initial_backoff_ms: 500,
+        max_backoff_ms: 3000,
+    };
+    assert_eq!(retries.backoff(1), Duration::from_millis(500));
+    assert_eq!(retries.backoff(2), Duration::from_millis(1000));
+    assert_eq!(retries.backoff(3), Duration::from_millis(2000));
+    assert_eq!(retries.backoff(4), Duration::from_millis(3000));
+    assert_eq!(retries.backoff(100), Duration::from_millis(3000));
+}
+
+#[test]
+fn defaults_apply_to_projects_without_settings() {
+    let retries: TransferRetries = serde_json::from_str(r#"{ "maxRetries": 1 }"#).unwrap();
+    assert_eq!(
+        retries,
+        TransferRetries {
+            max_retries: 1,
+            ..Default::default()
+        }
+    );
+}
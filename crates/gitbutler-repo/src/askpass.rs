@@ -117,7 +117,7 @@ pub fn sign_commit_sync(
             .unwrap()
             .block_on(gitbutler_git::sign_commit(
                 &repo_path,
-                gitbutler_git::tokio::TokioExecutor,
+                gitbutler_git::tokio::TokioExecutor::default(),
                 base_commitish,
                 handle_git_prompt_commit_sign_sync,
                 branch_id,
@@ -1,9 +1,13 @@
 use anyhow::Result;
 use bstr::BString;
 use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
 use gitbutler_project::Project;
 
-use crate::{Config, RepositoryExt};
+use crate::{
+    repo_state::{self, OperationInProgress},
+    Config, RepositoryExt,
+};
 
 pub trait RepoCommands {
     fn add_remote(&self, name: &str, url: &str) -> Result<()>;
@@ -11,6 +15,12 @@ pub trait RepoCommands {
     fn get_local_config(&self, key: &str) -> Result<Option<String>>;
     fn set_local_config(&self, key: &str, value: &str) -> Result<()>;
     fn check_signing_settings(&self) -> Result<bool>;
+    /// Return the merge, rebase or other operation of Git that is in progress, if any.
+    fn git_operation_in_progress(&self) -> Result<Option<OperationInProgress>>;
+    /// Abort the operation of Git that is in progress.
+    fn abort_git_operation(&self) -> Result<()>;
+    /// Continue the operation of Git that is in progress once its conflicts are resolved.
+    fn continue_git_operation(&self) -> Result<()>;
 }
 
 impl RepoCommands for Project {
@@ -26,6 +36,23 @@ impl RepoCommands for Project {
         config.set_local(key, value)
     }
 
+    fn git_operation_in_progress(&self) -> Result<Option<OperationInProgress>> {
+        let ctx = CommandContext::open(self)?;
+        repo_state::in_progress(ctx.repository())
+    }
+
+    fn abort_git_operation(&self) -> Result<()> {
+        let ctx = CommandContext::open(self)?;
+        let _guard = self.exclusive_operation_access("abort_git_operation")?;
+        repo_state::abort(&ctx)
+    }
+
+    fn continue_git_operation(&self) -> Result<()> {
+        let ctx = CommandContext::open(self)?;
+        let _guard = self.exclusive_operation_access("continue_git_operation")?;
+        repo_state::continue_operation(&ctx)
+    }
+
     fn check_signing_settings(&self) -> Result<bool> {
         let repo = CommandContext::open(self)?;
         let signed = repo
@@ -33,7 +60,7 @@ impl RepoCommands for Project {
             .sign_buffer(&BString::new("test".into()).into());
         match signed {
             Ok(_) => Ok(true),
-            Err(e) => Err(e),
+            Err(e) => Err(e.context(Code::CommitSigningFailed)),
         }
     }
 
@@ -0,0 +1,346 @@
+//! Answer the questions about the history that are asked for each branch whenever the workspace is listed,
+//! like where a branch forked off the target and how far ahead and behind of it it is, without walking the
+//! whole history each time.
+//!
+//! Commits never change, so answers are kept in memory by the commits they were asked for, along with the
+//! generation number of each commit that was looked at, which is its distance from the root of the history
+//! plus one. As children always have a greater generation than their parents, a walk that visits commits by
+//! descending generation can stop as soon as the commits left to visit are reachable from both sides.
+//!
+//! The generation numbers and parents are read from Git's commit-graph file if there is one, which can be
+//! [written](write_if_missing()) for that, and are computed for the commits that aren't in it.
+use std::{
+    collections::{hash_map::Entry, BTreeMap, BinaryHeap, HashMap},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_git::ProcessEnv;
+
+/// What's known about the history of each repository, by the path of its `.git` directory.
+static CACHES: Mutex<BTreeMap<PathBuf, History>> = Mutex::new(BTreeMap::new());
+
+/// Marks commits reachable from the first of the two commits that are compared.
+const ONE: u8 = 1;
+/// Marks commits reachable from the second of the two commits that are compared.
+const TWO: u8 = 2;
+const BOTH: u8 = ONE | TWO;
+
+#[derive(Default)]
+struct History {
+    /// The commit-graph of the repository along with the time its file was modified, if it has one.
+    graph: Option<(SystemTime, gix::commitgraph::Graph)>,
+    /// The commits that aren't in the commit-graph.
+    commits: HashMap<git2::Oid, Node>,
+    /// The results of walks by the two commits they compared.
+    walks: HashMap<(git2::Oid, git2::Oid), Walk>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    /// The generation of the commit, or `0` if it's missing, like the commits beyond the end of a shallow clone.
+    generation: u32,
+    parents: Vec<git2::Oid>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Walk {
+    merge_base: Option<git2::Oid>,
+    ahead: usize,
+    behind: usize,
+}
+
+/// Return the best common ancestor of `one` and `two`, like [`git2::Repository::merge_base()`] does.
+///
+/// Fails if they have no common ancestor.
+pub fn merge_base(repo: &git2::Repository, one: git2::Oid, two: git2::Oid) -> Result<git2::Oid> {
+    walk(repo, one, two)?
+        .merge_base
+        .ok_or_else(|| anyhow!("no merge base found between {one} and {two}"))
+}
+
+/// Return the amount of commits reachable from `local` but not from `upstream`, and the other way around,
+/// like [`git2::Repository::graph_ahead_behind()`] does.
+pub fn ahead_behind(
+    repo: &git2::Repository,
+    local: git2::Oid,
+    upstream: git2::Oid,
+) -> Result<(usize, usize)> {
+    let walk = walk(repo, local, upstream)?;
+    Ok((walk.ahead, walk.behind))
+}
+
+/// Forget what's known about the history of `repo`, as it changed in a way that commits can't, like when
+/// the history of a shallow clone was fetched further back.
+pub fn forget(repo: &git2::Repository) {
+    CACHES
+        .lock()
+        .expect("no panics while holding the lock")
+        .remove(repo.path());
+}
+
+/// Write the commit-graph of the repository of `ctx` with `git commit-graph write` if it has none yet, and
+/// return `true` if it was written.
+///
+/// Once it exists, `git gc` keeps it up to date, and commits that aren't in it yet are looked up one by one.
+/// Nothing is written if `core.commitGraph` is turned off.
+pub fn write_if_missing(ctx: &CommandContext) -> Result<bool> {
+    let repo = ctx.repository();
+    if !is_enabled(repo) || graph_modified(repo).is_some() {
+        return Ok(false);
+    }
+    let mut cmd = Command::new("git");
+    cmd.args(["commit-graph", "write", "--reachable"])
+        .current_dir(ctx.project().worktree_path());
+    ProcessEnv::new()
+        .extend(ctx.project().extra_env.clone())
+        .apply(&mut cmd);
+    let output = cmd.output().context("failed to run git commit-graph")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git commit-graph write failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(true)
+}
+
+fn walk(repo: &git2::Repository, one: git2::Oid, two: git2::Oid) -> Result<Walk> {
+    let mut caches = CACHES.lock().expect("no panics while holding the lock");
+    let history = caches.entry(repo.path().to_owned()).or_default();
+    history.refresh_graph(repo);
+
+    let walk = match history.walks.get(&(one, two)) {
+        Some(walk) => *walk,
+        None => {
+            let walk = history.walk(repo, one, two)?;
+            history.walks.insert((one, two), walk);
+            walk
+        }
+    };
+
+    // In low-memory mode, only what's known about the current repository is kept, and only if it's little.
+    let limits = gitbutler_diff::memory_limits();
+    if history.commits.len() > limits.max_cache_entries {
+        history.commits.clear();
+    }
+    if history.walks.len() > limits.max_cache_entries {
+        history.walks.clear();
+    }
+    if gitbutler_diff::is_low_memory() {
+        caches.retain(|git_dir, _| git_dir == repo.path());
+    }
+    Ok(walk)
+}
+
+impl History {
+    /// Read the commit-graph of `repo` again if it was written since it was read.
+    fn refresh_graph(&mut self, repo: &git2::Repository) {
+        let modified = is_enabled(repo).then(|| graph_modified(repo)).flatten();
+        if self.graph.as_ref().map(|(time, _)| *time) == modified {
+            return;
+        }
+        self.graph = modified.and_then(|time| {
+            match gix::commitgraph::at(repo.path().join("objects").join("info")) {
+                Ok(graph) => Some((time, graph)),
+                Err(err) => {
+                    tracing::debug!(?err, "failed to read the commit-graph");
+                    None
+                }
+            }
+        });
+    }
+
+    /// Visit the commits reachable from `one` and `two` by descending generation, until only commits that are
+    /// reachable from both are left.
+    ///
+    /// Each commit is visited after all of its children, so by then it's known which of the two it's
+    /// reachable from. The first one that is reachable from both is their best common ancestor.
+    fn walk(&mut self, repo: &git2::Repository, one: git2::Oid, two: git2::Oid) -> Result<Walk> {
+        let mut walk = Walk {
+            merge_base: None,
+            ahead: 0,
+            behind: 0,
+        };
+        let mut marks: HashMap<git2::Oid, u8> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        // The queued commits that aren't reachable from both yet.
+        let mut unfinished = 0_usize;
+        for (id, mark) in [(one, ONE), (two, TWO)] {
+            match marks.entry(id) {
+                Entry::Occupied(mut entry) => {
+                    *entry.get_mut() |= mark;
+                    unfinished -= 1;
+                }
+                Entry::Vacant(entry) => {
+                    let generation = self.node(repo, id)?.generation;
+                    if generation == 0 {
+                        bail!("failed to find commit {id}");
+                    }
+                    entry.insert(mark);
+                    queue.push((generation, id));
+                    unfinished += 1;
+                }
+            }
+        }
+
+        while unfinished > 0 {
+            let Some((_, id)) = queue.pop() else {
+                break;
+            };
+            let mark = marks[&id];
+            match mark {
+                ONE => walk.ahead += 1,
+                TWO => walk.behind += 1,
+                _ => {
+                    walk.merge_base.get_or_insert(id);
+                }
+            }
+            if mark != BOTH {
+                unfinished -= 1;
+            }
+            // Marks are passed on even by commits reachable from both, so their ancestors aren't counted.
+            for parent in self.node(repo, id)?.parents {
+                match marks.entry(parent) {
+                    Entry::Occupied(mut entry) => {
+                        let parent_mark = entry.get_mut();
+                        if *parent_mark != BOTH && *parent_mark | mark == BOTH {
+                            unfinished -= 1;
+                        }
+                        *parent_mark |= mark;
+                    }
+                    Entry::Vacant(entry) => {
+                        // Missing commits are where the history ends.
+                        let generation = self.node(repo, parent)?.generation;
+                        if generation > 0 {
+                            entry.insert(mark);
+                            queue.push((generation, parent));
+                            if mark != BOTH {
+                                unfinished += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if walk.merge_base.is_none() {
+            walk.merge_base = queue.peek().map(|(_, id)| *id);
+        }
+        Ok(walk)
+    }
+
+    /// Return the generation and parents of the commit `id`, computing them for it and all of its ancestors
+    /// that aren't known yet.
+    fn node(&mut self, repo: &git2::Repository, id: git2::Oid) -> Result<Node> {
+        if let Some(node) = self.known(id) {
+            return Ok(node);
+        }
+        // Ancestors are visited before their descendants, without recursion as histories are deep.
+        let mut pending: HashMap<git2::Oid, Vec<git2::Oid>> = HashMap::new();
+        let mut stack = vec![id];
+        while let Some(&top) = stack.last() {
+            if self.known(top).is_some() {
+                stack.pop();
+                continue;
+            }
+            let parents = match pending.entry(top) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => match repo.find_commit(top) {
+                    Ok(commit) => entry.insert(commit.parent_ids().collect()),
+                    Err(err) if err.code() == git2::ErrorCode::NotFound => {
+                        self.commits.insert(
+                            top,
+                            Node {
+                                generation: 0,
+                                parents: Vec::new(),
+                            },
+                        );
+                        stack.pop();
+                        continue;
+                    }
+                    Err(err) => return Err(err.into()),
+                },
+            };
+            let mut generation = 1;
+            let mut missing = Vec::new();
+            for parent in parents.iter() {
+                match self.known(*parent) {
+                    Some(node) => generation = generation.max(node.generation + 1),
+                    None => missing.push(*parent),
+                }
+            }
+            if missing.is_empty() {
+                let parents = pending.remove(&top).unwrap_or_default();
+                self.commits.insert(
+                    top,
+                    Node {
+                        generation,
+                        parents,
+                    },
+                );
+                stack.pop();
+            } else {
+                stack.extend(missing);
+            }
+        }
+        self.known(id)
+            .ok_or_else(|| anyhow!("failed to compute the generation of {id}"))
+    }
+
+    /// Return what's known about the commit `id` without looking it up in the repository.
+    fn known(&self, id: git2::Oid) -> Option<Node> {
+        if let Some((_, graph)) = &self.graph {
+            let gix_id = gix::ObjectId::try_from(id.as_bytes()).expect("git2 oid is always valid");
+            // Graphs written by old versions of Git have no generation numbers.
+            if let Some(commit) = graph
+                .commit_by_id(gix_id)
+                .filter(|commit| commit.generation() > 0)
+            {
+                let generation = commit.generation();
+                let parents = commit
+                    .iter_parents()
+                    .map(|pos| {
+                        pos.ok().and_then(|pos| {
+                            git2::Oid::from_bytes(graph.commit_at(pos).id().as_bytes()).ok()
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                return Some(Node {
+                    generation,
+                    parents,
+                });
+            }
+        }
+        self.commits.get(&id).cloned()
+    }
+}
+
+/// Return `true` unless the use of the commit-graph is turned off with `core.commitGraph`.
+fn is_enabled(repo: &git2::Repository) -> bool {
+    repo.config()
+        .and_then(|config| config.get_bool("core.commitGraph"))
+        .unwrap_or(true)
+}
+
+/// Return the time the commit-graph of `repo` was last written, or `None` if it has none.
+///
+/// It's either a single file, or a chain of files that is listed in another one if it's written incrementally.
+fn graph_modified(repo: &git2::Repository) -> Option<SystemTime> {
+    let info = repo.path().join("objects").join("info");
+    [
+        info.join("commit-graph"),
+        info.join("commit-graphs").join("commit-graph-chain"),
+    ]
+    .iter()
+    .find_map(|path| modified(path))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -38,6 +38,19 @@ impl Config<'_> {
         }
     }
 
+    /// Remove `key` from the configuration of the repository, if it's set there.
+    pub fn remove_local(&self, key: &str) -> Result<()> {
+        let mut local = self
+            .git_repository
+            .config()?
+            .open_level(git2::ConfigLevel::Local)?;
+        match local.remove(key) {
+            Ok(()) => Ok(()),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
     pub fn get_local(&self, key: &str) -> Result<Option<String>> {
         let config = self.git_repository.config()?;
         match config
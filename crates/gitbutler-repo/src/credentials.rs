@@ -1,22 +1,62 @@
-use std::{path::PathBuf, str::FromStr, vec};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    vec,
+};
 
 use anyhow::Context;
 use gitbutler_command_context::CommandContext;
-use gitbutler_project::AuthKey;
+use gitbutler_git::ProcessEnv;
+use gitbutler_project::{AuthKey, SshAuthMethod};
 use gitbutler_url::{ConvertError, Scheme, Url};
 
+mod git_credential;
+#[cfg(target_os = "macos")]
+mod macos;
+mod ssh_agent;
+#[cfg(windows)]
+mod windows;
+
+/// Point `SSH_AUTH_SOCK` to the socket of `gpg-agent` if it isn't set and `gpg-agent` offers SSH
+/// support, like shells do for it, which apps that aren't started from one lack.
+///
+/// This changes the environment of the process, so it must be called at startup before any other
+/// threads are spawned.
+pub fn use_gpg_agent_for_ssh() {
+    if ssh_agent::agent_socket().is_some() {
+        return;
+    }
+    if let Some(socket) = ssh_agent::gpg_agent_socket() {
+        std::env::set_var("SSH_AUTH_SOCK", socket);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SshCredential {
     Keyfile {
         key_path: PathBuf,
         passphrase: Option<String>,
     },
+    /// Use the keys of a running SSH agent, like the Windows OpenSSH agent service
+    /// which listens on the `\\.\pipe\openssh-ssh-agent` named pipe.
+    Agent,
+    /// Use the keys `gpg-agent` offers on `socket` through its SSH support.
+    GpgAgent { socket: PathBuf },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HttpsCredential {
-    CredentialHelper { username: String, password: String },
+    CredentialHelper {
+        username: String,
+        password: String,
+    },
     GitHubToken(String),
+    /// Credentials stored in the Windows Credential Manager, like the ones of the *Git Credential Manager*.
+    CredentialManager {
+        username: String,
+        password: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -26,6 +66,63 @@ pub enum Credential {
     Https(HttpsCredential),
 }
 
+impl Credential {
+    /// Let the credential helpers know that the remote at `url` accepted this credential, if it
+    /// came from them, and keep it for the rest of the session.
+    pub(crate) fn approve(&self, ctx: &CommandContext, url: &str) {
+        if let Credential::Https(HttpsCredential::CredentialHelper { username, password }) = self {
+            if let Err(err) = git_credential::approve(
+                url,
+                username,
+                password,
+                &ctx.project().worktree_path(),
+                &process_env(ctx),
+            ) {
+                tracing::warn!(?err, "failed to store the accepted credentials");
+            }
+        }
+    }
+
+    /// Let the credential helpers know that the remote at `url` refused this credential, if it
+    /// came from them, and forget it for the rest of the session.
+    pub(crate) fn reject(&self, ctx: &CommandContext, url: &str) {
+        if let Credential::Https(HttpsCredential::CredentialHelper { username, password }) = self {
+            if let Err(err) = git_credential::reject(
+                url,
+                username,
+                password,
+                &ctx.project().worktree_path(),
+                &process_env(ctx),
+            ) {
+                tracing::warn!(?err, "failed to erase the refused credentials");
+            }
+        }
+    }
+}
+
+/// Name the way of authenticating, to tell which one failed.
+impl fmt::Display for Credential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credential::Noop => f.write_str("no credentials"),
+            Credential::Ssh(SshCredential::Keyfile { key_path, .. }) => {
+                write!(f, "key file {}", key_path.display())
+            }
+            Credential::Ssh(SshCredential::Agent) => f.write_str("ssh-agent"),
+            Credential::Ssh(SshCredential::GpgAgent { socket }) => {
+                write!(f, "gpg-agent at {}", socket.display())
+            }
+            Credential::Https(HttpsCredential::CredentialHelper { username, .. }) => {
+                write!(f, "credential helper as '{username}'")
+            }
+            Credential::Https(HttpsCredential::GitHubToken(_)) => f.write_str("GitHub token"),
+            Credential::Https(HttpsCredential::CredentialManager { username, .. }) => {
+                write!(f, "Windows Credential Manager as '{username}'")
+            }
+        }
+    }
+}
+
 impl From<Credential> for git2::RemoteCallbacks<'_> {
     fn from(value: Credential) -> Self {
         let mut remote_callbacks = git2::RemoteCallbacks::new();
@@ -46,6 +143,25 @@ impl From<Credential> for git2::RemoteCallbacks<'_> {
                     git2::Cred::ssh_key("git", None, &key_path, passphrase.as_deref())
                 });
             }
+            Credential::Ssh(SshCredential::Agent) => {
+                remote_callbacks.credentials(|url, username_from_url, _allowed_types| {
+                    let username = username_from_url.unwrap_or("git");
+                    tracing::info!("authenticating with {url} as '{username}' using ssh agent");
+                    git2::Cred::ssh_key_from_agent(username)
+                });
+            }
+            Credential::Ssh(SshCredential::GpgAgent { socket }) => {
+                // The flow made sure that `SSH_AUTH_SOCK` points to the socket of `gpg-agent`,
+                // as that's the only agent libssh2 talks to. See `use_gpg_agent_for_ssh()`.
+                remote_callbacks.credentials(move |url, username_from_url, _allowed_types| {
+                    let username = username_from_url.unwrap_or("git");
+                    tracing::info!(
+                        "authenticating with {url} as '{username}' using gpg-agent at {}",
+                        socket.display()
+                    );
+                    git2::Cred::ssh_key_from_agent(username)
+                });
+            }
             Credential::Https(HttpsCredential::CredentialHelper { username, password }) => {
                 remote_callbacks.credentials(move |url, _username_from_url, _allowed_types| {
                     tracing::info!("authenticating with {url} as '{username}' with password using credential helper");
@@ -58,13 +174,22 @@ impl From<Credential> for git2::RemoteCallbacks<'_> {
                     git2::Cred::userpass_plaintext("git", &token)
                 });
             }
+            Credential::Https(HttpsCredential::CredentialManager { username, password }) => {
+                remote_callbacks.credentials(move |url, _username_from_url, _allowed_types| {
+                    tracing::info!("authenticating with {url} as '{username}' with password from the credential manager");
+                    git2::Cred::userpass_plaintext(&username, &password)
+                });
+            }
         };
         remote_callbacks
     }
 }
 
 #[derive(Clone, Default)]
-pub struct Helper {}
+pub struct Helper {
+    /// The socket of the SSH agent to use instead of the one `SSH_AUTH_SOCK` points to.
+    ssh_agent_socket: Option<PathBuf>,
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum HelpError {
@@ -74,11 +199,20 @@ pub enum HelpError {
     UrlConvertError(#[from] ConvertError),
     #[error(transparent)]
     Git(#[from] git2::Error),
+    /// None of the ways to authenticate with SSH remotes that are configured for the project can be used.
+    #[error("no way to authenticate over SSH is available: {}", .0.join(", "))]
+    NoSshAuth(Vec<String>),
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
 impl Helper {
+    /// Use the SSH agent listening on `socket` instead of the one `SSH_AUTH_SOCK` points to.
+    pub fn with_ssh_agent_socket(mut self, socket: impl Into<PathBuf>) -> Self {
+        self.ssh_agent_socket = Some(socket.into());
+        self
+    }
+
     pub fn help<'a>(
         &'a self,
         ctx: &'a CommandContext,
@@ -94,21 +228,18 @@ impl Helper {
         }
 
         match &ctx.project().preferred_key {
-            AuthKey::Local { private_key_path } => {
+            AuthKey::Local { .. } | AuthKey::SshAgent => {
                 let ssh_remote = if remote_url.scheme == Scheme::Ssh {
                     Ok(remote)
                 } else {
                     let ssh_url = remote_url.as_ssh()?;
                     ctx.repository().remote_anonymous(&ssh_url.to_string())
                 }?;
-
-                Ok(vec![(
-                    ssh_remote,
-                    vec![Credential::Ssh(SshCredential::Keyfile {
-                        key_path: private_key_path.clone(),
-                        passphrase: None,
-                    })],
-                )])
+                let private_key_path = match &ctx.project().preferred_key {
+                    AuthKey::Local { private_key_path } => Some(private_key_path.as_path()),
+                    _ => None,
+                };
+                Ok(vec![(ssh_remote, self.ssh_flow(ctx, private_key_path)?)])
             }
             AuthKey::GitCredentialsHelper => {
                 let https_remote = if remote_url.scheme == Scheme::Https {
@@ -121,6 +252,16 @@ impl Helper {
                     .into_iter()
                     .map(Credential::Https)
                     .collect::<Vec<_>>();
+
+                // On Windows, users typically have their keys loaded into the OpenSSH agent service
+                // instead of having a credential helper set up, so try it first for SSH remotes.
+                if cfg!(windows) && remote_url.scheme == Scheme::Ssh {
+                    let ssh_remote = ctx.repository().find_remote(remote_name)?;
+                    return Ok(vec![
+                        (ssh_remote, vec![Credential::Ssh(SshCredential::Agent)]),
+                        (https_remote, flow),
+                    ]);
+                }
                 Ok(vec![(https_remote, flow)])
             }
             AuthKey::SystemExecutable => {
@@ -130,19 +271,142 @@ impl Helper {
         }
     }
 
+    /// Return the credentials for the ways to authenticate with SSH remotes in the order they are
+    /// configured for the project, leaving out the ones that aren't available.
+    fn ssh_flow(
+        &self,
+        ctx: &CommandContext,
+        private_key_path: Option<&Path>,
+    ) -> Result<Vec<Credential>, HelpError> {
+        let agent_socket = self
+            .ssh_agent_socket
+            .clone()
+            .or_else(ssh_agent::agent_socket);
+        let mut flow = vec![];
+        let mut unavailable = vec![];
+        for method in ctx.project().ssh_auth_methods() {
+            match method {
+                SshAuthMethod::KeyFile => match private_key_path {
+                    Some(key_path) => flow.push(Credential::Ssh(SshCredential::Keyfile {
+                        key_path: key_path.to_owned(),
+                        passphrase: None,
+                    })),
+                    None => unavailable.push("there is no key file for the project".to_owned()),
+                },
+                SshAuthMethod::Agent => {
+                    if cfg!(windows) || agent_socket.is_some() {
+                        flow.push(Credential::Ssh(SshCredential::Agent));
+                    } else {
+                        unavailable
+                            .push("ssh-agent isn't running, as SSH_AUTH_SOCK isn't set".to_owned());
+                    }
+                }
+                SshAuthMethod::GpgAgent => match ssh_agent::gpg_agent_socket() {
+                    None => unavailable.push("gpg-agent doesn't offer SSH support".to_owned()),
+                    Some(socket) => match &agent_socket {
+                        Some(agent_socket) if *agent_socket == socket => {
+                            flow.push(Credential::Ssh(SshCredential::GpgAgent { socket }));
+                        }
+                        Some(agent_socket) => unavailable.push(format!(
+                            "gpg-agent can't be used as SSH_AUTH_SOCK points to {}",
+                            agent_socket.display()
+                        )),
+                        None => unavailable
+                            .push("gpg-agent can't be used as SSH_AUTH_SOCK isn't set".to_owned()),
+                    },
+                },
+            }
+        }
+        if flow.is_empty() {
+            return Err(HelpError::NoSshAuth(unavailable));
+        }
+        for reason in unavailable {
+            tracing::info!(project_id = %ctx.project().id, "skipping SSH authentication method: {reason}");
+        }
+        Ok(flow)
+    }
+
     fn https_flow(
         ctx: &CommandContext,
         remote_url: &Url,
     ) -> Result<Vec<HttpsCredential>, HelpError> {
+        fn push_new(flow: &mut Vec<HttpsCredential>, (username, password): (String, String)) {
+            let credential = HttpsCredential::CredentialHelper { username, password };
+            if !flow.contains(&credential) {
+                flow.push(credential);
+            }
+        }
+
         let mut flow = vec![];
+        let https_url = if remote_url.scheme == Scheme::Https {
+            remote_url.clone()
+        } else {
+            remote_url.as_https()?
+        }
+        .to_string();
+        if let Some(credential) = git_credential::session_credential(&https_url) {
+            push_new(&mut flow, credential);
+        }
+        match git_credential::fill(
+            &https_url,
+            &ctx.project().worktree_path(),
+            &process_env(ctx),
+        ) {
+            Ok(Some(credential)) => push_new(&mut flow, credential),
+            Ok(None) => {}
+            Err(err) => {
+                tracing::debug!(?err, "the credential helpers of git have no credentials");
+            }
+        }
 
-        let mut helper = git2::CredentialHelper::new(&remote_url.to_string());
-        let config = ctx.repository().config()?;
-        helper.config(&config);
-        if let Some((username, password)) = helper.execute() {
-            flow.push(HttpsCredential::CredentialHelper { username, password });
+        // Without a usable `git`, ask the helpers libgit2 knows about.
+        if flow.is_empty() {
+            let mut helper = git2::CredentialHelper::new(&remote_url.to_string());
+            let config = ctx.repository().config()?;
+            helper.config(&config);
+            if let Some(credential) = helper.execute() {
+                push_new(&mut flow, credential);
+            }
+        }
+
+        // `osxkeychain` is typically configured in the system configuration of Apple's or Homebrew's Git,
+        // which libgit2 doesn't see.
+        #[cfg(target_os = "macos")]
+        if flow.is_empty() {
+            match macos::read_keychain_credential(remote_url) {
+                Ok(Some((username, password))) => {
+                    flow.push(HttpsCredential::CredentialHelper { username, password });
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    tracing::warn!(?err, "failed to read credentials from the keychain");
+                }
+            }
+        }
+
+        #[cfg(windows)]
+        match windows::read_credential(remote_url) {
+            Ok(Some((username, password))) => {
+                // Git may be configured to use the credential manager as helper already.
+                let is_known = flow.iter().any(|existing| {
+                    matches!(existing, HttpsCredential::CredentialHelper { username: known_username, password: known_password }
+                        if *known_username == username && *known_password == password)
+                });
+                if !is_known {
+                    flow.push(HttpsCredential::CredentialManager { username, password });
+                }
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::warn!(?err, "failed to read the Windows Credential Manager");
+            }
         }
 
         Ok(flow)
     }
 }
+
+/// The environment to run the credential helpers of the project in.
+fn process_env(ctx: &CommandContext) -> ProcessEnv {
+    ProcessEnv::new().extend(ctx.project().extra_env.clone())
+}
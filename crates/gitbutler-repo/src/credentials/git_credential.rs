@@ -0,0 +1,119 @@
+//! Passing the credentials of HTTPS remotes through the credential helpers Git is configured with,
+//! like `git fetch` and `git push` do.
+//!
+//! Running `git credential` instead of the helpers lets Git resolve the whole `credential.helper`
+//! chain from all of its configuration, including helpers like `manager-core` or `osxkeychain`
+//! that are configured system-wide. Credentials that a remote accepted are kept for the rest of
+//! the session, so helpers that ask for a token or a second factor do so only once.
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    sync::Mutex,
+};
+
+use anyhow::{bail, Context, Result};
+use gitbutler_git::ProcessEnv;
+
+/// The `(username, password)` that remotes accepted during this session, by the URL of the remote.
+static SESSION: Mutex<BTreeMap<String, (String, String)>> = Mutex::new(BTreeMap::new());
+
+/// Return the `(username, password)` the remote at `url` accepted earlier in this session, if any.
+pub(super) fn session_credential(url: &str) -> Option<(String, String)> {
+    SESSION.lock().unwrap().get(url).cloned()
+}
+
+/// Ask the credential helpers for the `(username, password)` of the remote at `url`, with the
+/// configuration of the repository in `worktree`, or return `None` if none of them has any.
+pub(super) fn fill(
+    url: &str,
+    worktree: &Path,
+    env: &ProcessEnv,
+) -> Result<Option<(String, String)>> {
+    let output = run("fill", &format!("url={url}\n\n"), worktree, env)?;
+    Ok(parse_credential(&output))
+}
+
+/// Tell the credential helpers that the remote at `url` accepted `username` and `password`, so
+/// they can store them, and keep them for the rest of the session.
+pub(super) fn approve(
+    url: &str,
+    username: &str,
+    password: &str,
+    worktree: &Path,
+    env: &ProcessEnv,
+) -> Result<()> {
+    SESSION
+        .lock()
+        .unwrap()
+        .insert(url.to_owned(), (username.to_owned(), password.to_owned()));
+    run(
+        "approve",
+        &format!("url={url}\nusername={username}\npassword={password}\n\n"),
+        worktree,
+        env,
+    )
+    .map(|_| ())
+}
+
+/// Tell the credential helpers that the remote at `url` refused `username` and `password`, so
+/// they can erase them, and forget them for the rest of the session.
+pub(super) fn reject(
+    url: &str,
+    username: &str,
+    password: &str,
+    worktree: &Path,
+    env: &ProcessEnv,
+) -> Result<()> {
+    SESSION.lock().unwrap().remove(url);
+    run(
+        "reject",
+        &format!("url={url}\nusername={username}\npassword={password}\n\n"),
+        worktree,
+        env,
+    )
+    .map(|_| ())
+}
+
+/// Run `git credential <action>` with `input` on its stdin, and return what it printed.
+fn run(action: &str, input: &str, worktree: &Path, env: &ProcessEnv) -> Result<String> {
+    let mut cmd = Command::new(gix::path::env::exe_invocation());
+    env.apply(&mut cmd);
+    cmd.args(["credential", action])
+        .current_dir(worktree)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("failed to spawn {cmd:?}"))?;
+    child
+        .stdin
+        .take()
+        .expect("configured")
+        .write_all(input.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        // With prompts disabled, this is also how `fill` ends if no helper has credentials.
+        bail!(
+            "git credential {action} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse the `key=value` lines of the Git credential protocol into `(username, password)`.
+pub(super) fn parse_credential(output: &str) -> Option<(String, String)> {
+    let mut username = None;
+    let mut password = None;
+    for line in output.lines() {
+        match line.split_once('=') {
+            Some(("username", value)) => username = Some(value.to_owned()),
+            Some(("password", value)) => password = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+    username.zip(password)
+}
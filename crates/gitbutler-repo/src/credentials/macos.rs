@@ -0,0 +1,49 @@
+//! Access to the credentials stored in the macOS keychain by the `osxkeychain` Git credential helper.
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result};
+use gitbutler_git::ProcessEnv;
+use gitbutler_url::Url;
+
+use super::git_credential::parse_credential;
+
+/// Return the `(username, password)` that `git credential-osxkeychain` has stored for the host of `remote_url`,
+/// or `None` if there is none.
+pub(super) fn read_keychain_credential(remote_url: &Url) -> Result<Option<(String, String)>> {
+    let Some(host) = remote_url.host.as_deref() else {
+        return Ok(None);
+    };
+    let host = match remote_url.port {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_owned(),
+    };
+
+    let mut cmd = Command::new(gix::path::env::exe_invocation());
+    ProcessEnv::new().apply(&mut cmd);
+    cmd.args(["credential-osxkeychain", "get"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("failed to spawn {cmd:?}"))?;
+    child
+        .stdin
+        .take()
+        .expect("configured")
+        .write_all(format!("protocol=https\nhost={host}\n\n").as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        // The helper isn't installed, or the keychain couldn't be accessed.
+        tracing::debug!(
+            stderr = %String::from_utf8_lossy(&output.stderr),
+            "git credential-osxkeychain failed"
+        );
+        return Ok(None);
+    }
+
+    Ok(parse_credential(&String::from_utf8_lossy(&output.stdout)))
+}
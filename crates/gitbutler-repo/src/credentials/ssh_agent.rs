@@ -0,0 +1,23 @@
+//! Finding the SSH agents to authenticate with.
+use std::{path::PathBuf, process::Command};
+
+/// Return the socket of the SSH agent that `SSH_AUTH_SOCK` points to, if it's set.
+pub(super) fn agent_socket() -> Option<PathBuf> {
+    std::env::var_os("SSH_AUTH_SOCK")
+        .filter(|socket| !socket.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Return the socket through which `gpg-agent` offers its keys to SSH, or `None` if GnuPG isn't
+/// installed or its SSH support isn't enabled.
+pub(super) fn gpg_agent_socket() -> Option<PathBuf> {
+    let output = Command::new("gpgconf")
+        .args(["--list-dirs", "agent-ssh-socket"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let socket = PathBuf::from(String::from_utf8(output.stdout).ok()?.trim());
+    socket.exists().then_some(socket)
+}
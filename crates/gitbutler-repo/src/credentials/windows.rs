@@ -0,0 +1,84 @@
+//! Access to the Windows Credential Manager, where the *Git Credential Manager* and `git-credential-wincred`
+//! store the credentials of HTTPS remotes.
+use anyhow::Result;
+use gitbutler_url::Url;
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::ERROR_NOT_FOUND,
+        Security::Credentials::{CredFree, CredReadW, CREDENTIALW, CRED_TYPE_GENERIC},
+    },
+};
+
+/// Return the `(username, password)` stored for the host of `remote_url`, or `None` if there is none.
+///
+/// Git credential helpers use targets like `git:https://github.com`.
+pub(super) fn read_credential(remote_url: &Url) -> Result<Option<(String, String)>> {
+    let Some(host) = remote_url.host.as_deref() else {
+        return Ok(None);
+    };
+    let target = match remote_url.port {
+        Some(port) => format!("git:https://{host}:{port}"),
+        None => format!("git:https://{host}"),
+    };
+
+    let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+    // SAFETY: `credential` is only read if the call succeeds, and then freed with `CredFree()`.
+    let result = unsafe {
+        CredReadW(
+            &HSTRING::from(target.as_str()),
+            CRED_TYPE_GENERIC,
+            0,
+            &mut credential,
+        )
+    };
+    if let Err(err) = result {
+        if err.code() == ERROR_NOT_FOUND.to_hresult() {
+            return Ok(None);
+        }
+        return Err(err.into());
+    }
+
+    // SAFETY: on success, `credential` points to a valid credential until it's freed.
+    let username_and_password = unsafe {
+        let cred = &*credential;
+        let username = if cred.UserName.is_null() {
+            None
+        } else {
+            cred.UserName.to_string().ok()
+        };
+        let blob = if cred.CredentialBlob.is_null() {
+            &[][..]
+        } else {
+            std::slice::from_raw_parts(cred.CredentialBlob, cred.CredentialBlobSize as usize)
+        };
+        let password = decode_password(blob);
+        username.zip(password)
+    };
+    // SAFETY: `credential` was allocated by `CredReadW()` and isn't used anymore.
+    unsafe { CredFree(credential as *const _) };
+
+    Ok(username_and_password)
+}
+
+/// `git-credential-wincred` stores passwords as UTF-16, while the *Git Credential Manager* uses UTF-8.
+/// As passwords and tokens are typically ASCII, UTF-16 is detected by its zero high bytes.
+fn decode_password(blob: &[u8]) -> Option<String> {
+    if blob.is_empty() {
+        return None;
+    }
+    let looks_like_utf16 = blob.len() % 2 == 0 && blob.iter().skip(1).step_by(2).all(|b| *b == 0);
+    if !looks_like_utf16 {
+        if let Ok(password) = std::str::from_utf8(blob) {
+            return Some(password.to_owned());
+        }
+    }
+    if blob.len() % 2 != 0 {
+        return None;
+    }
+    let wide: Vec<u16> = blob
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&wide).ok()
+}
@@ -0,0 +1,239 @@
+//! Find out which branch of a remote is its default branch, the one its `HEAD` points to.
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_fs::TomlFile;
+use gitbutler_git::ProcessEnv;
+use gitbutler_project::AuthKey;
+use gitbutler_reference::RemoteRefname;
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use serde::{Deserialize, Serialize};
+
+use crate::{credentials::Helper, RepositoryExt};
+
+/// How long the default branch reported by a remote is trusted before the remote is asked again.
+const CACHE_TTL_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Branch names that are commonly used as default branch, most likely first.
+const COMMON_DEFAULT_BRANCHES: [&str; 4] = ["main", "master", "trunk", "develop"];
+
+/// The default branch of a remote.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteDefaultBranch {
+    pub branch: RemoteRefname,
+    pub source: DefaultBranchSource,
+}
+
+/// How the default branch of a remote was found, from most to least reliable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DefaultBranchSource {
+    /// The remote was asked for the branch its `HEAD` points to.
+    Remote,
+    /// The remote was asked for it recently, and the answer was remembered.
+    Cache,
+    /// `refs/remotes/<remote>/HEAD` points to it, which is set when cloning.
+    TrackingRef,
+    /// It's the remote branch with the most common name for a default branch, or its only branch.
+    Guess,
+}
+
+/// Return the default branch of the remote named `remote_name`, or `None` if it can't be told.
+///
+/// The remote is asked like `git ls-remote --symref <remote> HEAD` does, and its answer is remembered for
+/// a day. If it can't be reached, the answer is guessed from the remote tracking branches instead.
+pub fn remote_default_branch(
+    ctx: &CommandContext,
+    remote_name: &str,
+    credentials: &Helper,
+) -> Result<Option<RemoteDefaultBranch>> {
+    let cache = RemoteHeadsHandle::new(ctx.project().gb_dir());
+    if let Some(branch) = cache.get(remote_name)? {
+        return Ok(Some(RemoteDefaultBranch {
+            branch: RemoteRefname::new(remote_name, &branch),
+            source: DefaultBranchSource::Cache,
+        }));
+    }
+
+    match query_remote_head(ctx, remote_name, credentials) {
+        Ok(branch) => {
+            if let Err(err) = cache.set(remote_name, &branch) {
+                tracing::warn!(
+                    ?err,
+                    "failed to remember the default branch of {remote_name}"
+                );
+            }
+            return Ok(Some(RemoteDefaultBranch {
+                branch: RemoteRefname::new(remote_name, &branch),
+                source: DefaultBranchSource::Remote,
+            }));
+        }
+        Err(err) => {
+            tracing::warn!(?err, "failed to ask {remote_name} for its default branch");
+        }
+    }
+    guess_default_branch(ctx.repository(), remote_name)
+}
+
+/// Guess the default branch of the remote named `remote_name` without contacting it.
+pub fn guess_default_branch(
+    repo: &git2::Repository,
+    remote_name: &str,
+) -> Result<Option<RemoteDefaultBranch>> {
+    let tracking_head = repo
+        .find_reference(&format!("refs/remotes/{remote_name}/HEAD"))
+        .ok()
+        .and_then(|head| head.symbolic_target().map(ToOwned::to_owned));
+    if let Some(branch) = tracking_head
+        .as_deref()
+        .and_then(|target| target.strip_prefix(&format!("refs/remotes/{remote_name}/")))
+    {
+        return Ok(Some(RemoteDefaultBranch {
+            branch: RemoteRefname::new(remote_name, branch),
+            source: DefaultBranchSource::TrackingRef,
+        }));
+    }
+
+    let branches: Vec<RemoteRefname> = repo
+        .remote_branches()?
+        .into_iter()
+        .filter(|branch| branch.remote() == remote_name && branch.branch() != "HEAD")
+        .collect();
+    let guess = COMMON_DEFAULT_BRANCHES
+        .iter()
+        .find_map(|name| branches.iter().find(|branch| branch.branch() == *name))
+        .or_else(|| match branches.as_slice() {
+            [only] => Some(only),
+            _ => None,
+        });
+    Ok(guess.map(|branch| RemoteDefaultBranch {
+        branch: branch.clone(),
+        source: DefaultBranchSource::Guess,
+    }))
+}
+
+/// Ask the remote named `remote_name` which branch its `HEAD` points to, and return its short name.
+fn query_remote_head(
+    ctx: &CommandContext,
+    remote_name: &str,
+    credentials: &Helper,
+) -> Result<String> {
+    let head = if ctx.project().preferred_key == AuthKey::SystemExecutable {
+        let mut cmd = std::process::Command::new("git");
+        cmd.args(["ls-remote", "--symref", remote_name, "HEAD"])
+            .current_dir(ctx.project().worktree_path());
+        ProcessEnv::new()
+            .extend(ctx.project().extra_env.clone())
+            .apply(&mut cmd);
+        let output = cmd.output().context("failed to run git ls-remote")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "git ls-remote failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        parse_symref(&String::from_utf8_lossy(&output.stdout))
+            .ok_or_else(|| anyhow!("{remote_name} didn't tell where its HEAD points to"))?
+    } else {
+        let mut last_err = None;
+        let mut head = None;
+        'flows: for (mut remote, callbacks) in credentials.help(ctx, remote_name)? {
+            for callback in callbacks {
+                let mut cbs: git2::RemoteCallbacks = callback.into();
+                if ctx.project().omit_certificate_check.unwrap_or(false) {
+                    cbs.certificate_check(|_, _| Ok(git2::CertificateCheckStatus::CertificateOk));
+                }
+                let result = remote
+                    .connect_auth(git2::Direction::Fetch, Some(cbs), None)
+                    .and_then(|connection| {
+                        connection
+                            .default_branch()
+                            .map(|name| name.as_str().map(ToOwned::to_owned))
+                    });
+                match result {
+                    Ok(name) => {
+                        head = name;
+                        break 'flows;
+                    }
+                    Err(err) => last_err = Some(err),
+                }
+            }
+        }
+        match (head, last_err) {
+            (Some(head), _) => head,
+            (None, Some(err)) => return Err(err.into()),
+            (None, None) => {
+                return Err(anyhow!(
+                    "{remote_name} didn't tell where its HEAD points to"
+                ))
+            }
+        }
+    };
+    head.strip_prefix("refs/heads/")
+        .map(ToOwned::to_owned)
+        .ok_or_else(|| anyhow!("the HEAD of {remote_name} points to {head}, which isn't a branch"))
+}
+
+/// Return the target of `HEAD` from the output of `git ls-remote --symref <remote> HEAD`,
+/// like `refs/heads/main` for `ref: refs/heads/main\tHEAD`.
+fn parse_symref(ls_remote_output: &str) -> Option<String> {
+    ls_remote_output.lines().find_map(|line| {
+        let (target, name) = line.strip_prefix("ref: ")?.split_once('\t')?;
+        (name == "HEAD").then(|| target.to_owned())
+    })
+}
+
+/// A default branch as told by a remote.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteHead {
+    remote: String,
+    branch: String,
+    /// The time at which the remote was asked, in milliseconds since the Unix epoch.
+    queried_timestamp_ms: i64,
+}
+
+/// All remembered default branches, as persisted in a TOML file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RemoteHeads {
+    heads: Vec<RemoteHead>,
+}
+
+/// A handle to the default branches of remotes that were remembered for a while.
+struct RemoteHeadsHandle {
+    /// The file containing all remembered default branches.
+    file: TomlFile<RemoteHeads>,
+}
+
+impl RemoteHeadsHandle {
+    fn new(base_path: impl AsRef<Path>) -> Self {
+        Self {
+            file: TomlFile::new(base_path.as_ref().join("remote_heads.toml")),
+        }
+    }
+
+    /// Return the default branch of `remote`, unless it's unknown or too old to be trusted.
+    fn get(&self, remote: &str) -> Result<Option<String>> {
+        let heads = self.file.read()?;
+        let now = now_since_unix_epoch_ms();
+        Ok(heads
+            .heads
+            .into_iter()
+            .find(|head| head.remote == remote && now - head.queried_timestamp_ms < CACHE_TTL_MS)
+            .map(|head| head.branch))
+    }
+
+    /// Remember `branch` as default branch of `remote`.
+    fn set(&self, remote: &str, branch: &str) -> Result<()> {
+        let mut heads = self.file.read()?;
+        heads.heads.retain(|head| head.remote != remote);
+        heads.heads.push(RemoteHead {
+            remote: remote.to_owned(),
+            branch: branch.to_owned(),
+            queried_timestamp_ms: now_since_unix_epoch_ms(),
+        });
+        self.file.write(&heads)
+    }
+}
@@ -0,0 +1,22 @@
+//! The [error ids](gitbutler_error::registry) of failures to talk to remotes, which refine their [`Code`].
+use gitbutler_error::{catalog::MessageId, error::Code, registry::ErrorId};
+
+/// None of the ways to authenticate with SSH remotes that are configured for the project can be used,
+/// like without a key file or a running agent.
+pub const NO_SSH_AUTH: ErrorId = ErrorId::new(
+    "errors.projects.git.auth.no-ssh-auth",
+    Code::ProjectGitAuth,
+    MessageId::NoSshAuth,
+)
+.with_help_url("https://docs.gitbutler.com/troubleshooting/fetch-push");
+
+/// The remote rejected the SSH keys it was offered, like if they aren't added to the account on the forge.
+pub const SSH_KEY_REJECTED: ErrorId = ErrorId::new(
+    "errors.projects.git.auth.ssh-key-rejected",
+    Code::ProjectGitAuth,
+    MessageId::SshKeyRejected,
+)
+.with_help_url("https://docs.gitbutler.com/troubleshooting/fetch-push");
+
+/// All error ids of this crate, to [register](gitbutler_error::registry::register()) them.
+pub const ALL: &[ErrorId] = &[NO_SSH_AUTH, SSH_KEY_REJECTED];
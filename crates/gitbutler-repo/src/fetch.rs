@@ -0,0 +1,249 @@
+//! Fetch several remotes of a project at once.
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
+use anyhow::Result;
+use gitbutler_command_context::{cancellation, CommandContext};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_project::{FetchFailure, FetchResult};
+use serde::Serialize;
+
+use crate::{
+    credentials::Helper,
+    progress::{Progress, Task},
+    ReadBackend, RepoActionsExt, RepoReader,
+};
+
+/// The results of fetching several remotes, in the order the remotes were given.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchReport {
+    pub remotes: Vec<RemoteFetch>,
+}
+
+/// The result of fetching a single remote.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteFetch {
+    pub remote: String,
+    /// The remote branches that were created, moved or deleted by the fetch.
+    pub updated_refs: Vec<UpdatedRef>,
+    /// What went wrong, or `None` if the remote was fetched.
+    pub error: Option<String>,
+    /// The kind of failure, if the remote couldn't be fetched.
+    pub failure: Option<FetchFailure>,
+    /// The change of the default branch of the remote, if the fetch found that it changed.
+    pub default_branch_change: Option<DefaultBranchChange>,
+}
+
+/// The default branch of a remote, its `HEAD`, pointing to another branch than before.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefaultBranchChange {
+    /// The name of the branch that was the default branch before, like `master`.
+    pub old: String,
+    /// The name of the branch that is the default branch now, like `main`.
+    pub new: String,
+}
+
+/// A remote branch that changed by fetching.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatedRef {
+    /// The full name of the reference, like `refs/remotes/origin/main`.
+    pub name: String,
+    /// Where the reference pointed to before, or `None` if it was created.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub old: Option<git2::Oid>,
+    /// Where the reference points to now, or `None` if it was deleted.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub new: Option<git2::Oid>,
+}
+
+impl RemoteFetch {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl FetchReport {
+    pub fn is_success(&self) -> bool {
+        self.remotes.iter().all(RemoteFetch::is_success)
+    }
+
+    /// Summarize this report as the result of fetching the whole project at `timestamp`.
+    pub fn fetch_result(&self, timestamp: SystemTime) -> FetchResult {
+        let errors: Vec<_> = self
+            .remotes
+            .iter()
+            .filter_map(|remote| remote.error.as_deref())
+            .collect();
+        if errors.is_empty() {
+            FetchResult::Fetched { timestamp }
+        } else {
+            FetchResult::Error {
+                timestamp,
+                error: errors.join("\n"),
+            }
+        }
+    }
+}
+
+/// Fetch all `remotes` of the project of `ctx`, with at most `max_concurrent` of them being fetched
+/// at the same time, and report the outcome of each one.
+///
+/// A remote that fails to fetch doesn't affect the others, so this only fails if the report can't be made.
+/// Each remote is a task of its own as far as `progress` is concerned.
+pub fn fetch_remotes(
+    ctx: &CommandContext,
+    remotes: &[String],
+    askpass: Option<String>,
+    max_concurrent: usize,
+    progress: &dyn Progress,
+) -> Result<FetchReport> {
+    let task = Task::new(progress, "Fetching remotes");
+    task.set_total(remotes.len() as u64);
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<RemoteFetch>>> = Mutex::new(vec![None; remotes.len()]);
+    let workers = max_concurrent.clamp(1, remotes.len().max(1));
+    let project = ctx.project();
+    let cancellation = cancellation::current();
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| cancellation::scoped(&cancellation, || {
+                // Repositories can't be shared across threads, so each worker opens its own.
+                let ctx = CommandContext::open(project);
+                let helper = Helper::default();
+                loop {
+                    let idx = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(remote) = remotes.get(idx) else {
+                        break;
+                    };
+                    let remote_task = task.child(format!("Fetching {remote}"));
+                    let result = match &ctx {
+                        Ok(ctx) => {
+                            fetch_remote(ctx, remote, &helper, askpass.clone(), &remote_task)
+                        }
+                        Err(err) => Err(anyhow::anyhow!("{err:#}")),
+                    };
+                    if result.is_ok() {
+                        remote_task.finish();
+                    }
+                    task.inc();
+                    let fetch = match result {
+                        Ok((updated_refs, default_branch_change)) => RemoteFetch {
+                            remote: remote.clone(),
+                            updated_refs,
+                            error: None,
+                            failure: None,
+                            default_branch_change,
+                        },
+                        Err(err) => {
+                            tracing::warn!(project_id = %project.id, %remote, ?err, "fetch failed");
+                            RemoteFetch {
+                                remote: remote.clone(),
+                                updated_refs: vec![],
+                                error: Some(format!("{err:#}")),
+                                failure: Some(classify_failure(&err)),
+                                default_branch_change: None,
+                            }
+                        }
+                    };
+                    results.lock().expect("no panics while holding the lock")[idx] = Some(fetch);
+                }
+            }));
+        }
+    });
+
+    if project.settings.write_commit_graph {
+        match crate::commit_graph::write_if_missing(ctx) {
+            Ok(true) => tracing::info!(project_id = %project.id, "wrote commit-graph"),
+            Ok(false) => {}
+            Err(err) => {
+                tracing::warn!(project_id = %project.id, ?err, "failed to write commit-graph")
+            }
+        }
+    }
+
+    task.finish();
+    Ok(FetchReport {
+        remotes: results
+            .into_inner()
+            .expect("no panics while holding the lock")
+            .into_iter()
+            .flatten()
+            .collect(),
+    })
+}
+
+/// Return the kind of failure `err`, as returned by a fetch, stands for.
+fn classify_failure(err: &anyhow::Error) -> FetchFailure {
+    match err.custom_context().map(|ctx| ctx.code) {
+        Some(Code::ProjectGitAuth) => FetchFailure::Auth,
+        Some(Code::ProjectGitRemote) => FetchFailure::Network,
+        Some(Code::Offline) => FetchFailure::Offline,
+        _ => FetchFailure::Other,
+    }
+}
+
+/// Fetch `remote` and return the remote branches that changed, along with the change of its default branch.
+fn fetch_remote(
+    ctx: &CommandContext,
+    remote: &str,
+    helper: &Helper,
+    askpass: Option<String>,
+    task: &Task<'_>,
+) -> Result<(Vec<UpdatedRef>, Option<DefaultBranchChange>)> {
+    let before = remote_refs(ctx.repository(), remote)?;
+    let head_before = remote_head_branch(ctx.repository(), remote);
+    ctx.fetch_with_progress(remote, helper, askpass, task)?;
+    let after = remote_refs(ctx.repository(), remote)?;
+    let head_after = remote_head_branch(ctx.repository(), remote);
+
+    let mut names: Vec<_> = before.keys().chain(after.keys()).collect();
+    names.sort();
+    names.dedup();
+    let updated_refs = names
+        .into_iter()
+        .filter_map(|name| {
+            let (old, new) = (before.get(name).copied(), after.get(name).copied());
+            (old != new).then(|| UpdatedRef {
+                name: name.clone(),
+                old,
+                new,
+            })
+        })
+        .collect();
+    // A remote HEAD that appears for the first time is learned, not changed.
+    let default_branch_change = match (head_before, head_after) {
+        (Some(old), Some(new)) if old != new => Some(DefaultBranchChange { old, new }),
+        _ => None,
+    };
+    Ok((updated_refs, default_branch_change))
+}
+
+/// Return the name of the branch that `refs/remotes/<remote>/HEAD` points to, like `main`, or `None` if the
+/// default branch of `remote` isn't known.
+pub fn remote_head_branch(repo: &git2::Repository, remote: &str) -> Option<String> {
+    let head = repo
+        .find_reference(&format!("refs/remotes/{remote}/HEAD"))
+        .ok()?;
+    head.symbolic_target()?
+        .strip_prefix(&format!("refs/remotes/{remote}/"))
+        .map(ToOwned::to_owned)
+}
+
+/// Return the targets of the remote branches of `remote`, by full reference name.
+fn remote_refs(repo: &git2::Repository, remote: &str) -> Result<BTreeMap<String, git2::Oid>> {
+    Ok(RepoReader::new(repo, ReadBackend::Gix)?
+        .references(&format!("refs/remotes/{remote}/"))?
+        .into_iter()
+        .collect())
+}
@@ -0,0 +1,307 @@
+//! Discover and run Git hooks at the points where virtual branch operations correspond to Git operations.
+//!
+//! Hooks are looked up in `core.hooksPath` if set, then in the `hooks` directory of the repository,
+//! and finally in `.husky` at the root of the worktree. Which hooks run, and for how long,
+//! is controlled per project with [`HookSettings`](gitbutler_project::HookSettings).
+//!
+//! Only hooks that run *before* an operation, like `pre-push`, can reject it. Failures of hooks that
+//! run after an operation are logged, as there is nothing left to prevent.
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_git::ProcessEnv;
+use gitbutler_project::Project;
+
+/// A hook that is run by virtual branch operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// Run before a commit is created, without arguments.
+    PreCommit,
+    /// Run before a commit is created, with the path to a file containing the commit message, which it may change.
+    CommitMsg,
+    /// Run after a commit was created, without arguments.
+    PostCommit,
+    /// Run before pushing, with the remote name and url as arguments, and the refs to push on stdin.
+    PrePush,
+    /// Run after the worktree was updated, with the previous and new `HEAD` and `1` as arguments.
+    PostCheckout,
+    /// Run after upstream changes were merged, with `0` as argument.
+    PostMerge,
+}
+
+impl Hook {
+    /// Return the name of the hook, which is also the name of its file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hook::PreCommit => "pre-commit",
+            Hook::CommitMsg => "commit-msg",
+            Hook::PostCommit => "post-commit",
+            Hook::PrePush => "pre-push",
+            Hook::PostCheckout => "post-checkout",
+            Hook::PostMerge => "post-merge",
+        }
+    }
+
+    /// Return `true` if a failure of the hook should abort the operation it was run for.
+    fn can_reject(&self) -> bool {
+        matches!(self, Hook::PreCommit | Hook::CommitMsg | Hook::PrePush)
+    }
+}
+
+/// How long to wait between checks whether a hook has finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Run `hook` in the worktree of `ctx` with `args`, passing `stdin` to it, if it exists and is enabled for the project.
+///
+/// If a hook that can reject operations fails or times out, an error with [`Code::HookFailed`] is returned
+/// which contains everything the hook wrote to stdout and stderr.
+/// Failures of all other hooks are only logged.
+pub fn run(ctx: &CommandContext, hook: Hook, args: &[&str], stdin: &[u8]) -> Result<()> {
+    let project = ctx.project();
+    if !project.settings.hooks.is_enabled(hook.name()) {
+        return Ok(());
+    }
+    let Some(path) = find(ctx.repository(), hook.name()) else {
+        return Ok(());
+    };
+    let mut cmd = command(&path);
+    cmd.args(args);
+    let result = execute(
+        cmd,
+        &format!("{} hook", hook.name()),
+        ctx,
+        stdin,
+        project.settings.hooks.timeout(),
+    );
+    match result.map(|_stdout| ()) {
+        Err(err) if !hook.can_reject() => {
+            tracing::warn!(hook = hook.name(), ?err, "hook failed");
+            Ok(())
+        }
+        result => result.context(Code::HookFailed),
+    }
+}
+
+/// Run the shell `script` in the worktree of `ctx` like a hook, passing `stdin` to it, and return what it
+/// wrote to stdout. `name` describes the script in errors, which it fails with if it fails or times out.
+pub fn run_script(
+    ctx: &CommandContext,
+    name: &str,
+    script: &str,
+    stdin: &[u8],
+    timeout: Duration,
+) -> Result<String> {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(script);
+    execute(cmd, name, ctx, stdin, timeout)
+}
+
+/// What a script run with [`run_script_with_output()`] wrote, and how it ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptOutput {
+    /// The exit code, which is `None` if it was killed by a signal or timed out.
+    pub exit_code: Option<i32>,
+    /// Whether it was killed as it ran for longer than it may.
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl ScriptOutput {
+    /// Return `true` if the script ran to completion and exited with `0`.
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Run the shell `script` in the worktree of `project` like a hook, passing `stdin` to it, and return what
+/// it wrote and how it ended, whether it succeeded or not.
+///
+/// Nothing is captured of scripts that time out, as processes they started may still hold their output open.
+pub fn run_script_with_output(
+    project: &Project,
+    script: &str,
+    stdin: &[u8],
+    timeout: Duration,
+) -> Result<ScriptOutput> {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(script);
+    spawn_and_wait(cmd, script, project, stdin, timeout)
+}
+
+/// Return the commit `HEAD` points to, or the null id if it's unborn, for passing it to [`Hook::PostCheckout`].
+pub fn head_id(repo: &git2::Repository) -> git2::Oid {
+    repo.head()
+        .ok()
+        .and_then(|head| head.target())
+        .unwrap_or_else(git2::Oid::zero)
+}
+
+/// Run [`Hook::CommitMsg`] for `message` like `git commit` does, passing it in `COMMIT_EDITMSG` of the
+/// `.git` directory, and replace `message` with what the hook left in that file.
+pub fn run_commit_msg(ctx: &CommandContext, message: &mut String) -> Result<()> {
+    let path = ctx.repository().path().join("COMMIT_EDITMSG");
+    std::fs::write(&path, &message).context("failed to write the commit message for the hook")?;
+    run(ctx, Hook::CommitMsg, &[&path.to_string_lossy()], &[])?;
+    *message =
+        std::fs::read_to_string(&path).context("failed to read the commit message of the hook")?;
+    Ok(())
+}
+
+/// Run [`Hook::PostCheckout`] after the worktree of `ctx` was updated while `HEAD` moved from `previous_head`.
+pub fn run_post_checkout(ctx: &CommandContext, previous_head: git2::Oid) -> Result<()> {
+    let new_head = head_id(ctx.repository()).to_string();
+    run(
+        ctx,
+        Hook::PostCheckout,
+        &[&previous_head.to_string(), &new_head, "1"],
+        &[],
+    )
+}
+
+/// Return the path to the executable of the hook named `name` in `repo`, if there is one.
+pub fn find(repo: &git2::Repository, name: &str) -> Option<PathBuf> {
+    let workdir = repo.workdir()?;
+    let configured_dir = repo
+        .config()
+        .ok()
+        .and_then(|config| config.get_path("core.hooksPath").ok())
+        .map(|dir| workdir.join(dir));
+    configured_dir
+        .into_iter()
+        .chain([repo.path().join("hooks"), workdir.join(".husky")])
+        .map(|dir| dir.join(name))
+        .find(|path| is_executable(path))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Hooks are usually shell scripts, which can't be executed directly on Windows.
+fn command(path: &Path) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("sh");
+        cmd.arg(path);
+        cmd
+    } else {
+        Command::new(path)
+    }
+}
+
+/// Run `cmd`, which is described as `name`, in the worktree of `ctx` and return what it wrote to stdout.
+fn execute(
+    cmd: Command,
+    name: &str,
+    ctx: &CommandContext,
+    stdin: &[u8],
+    timeout: Duration,
+) -> Result<String> {
+    let output = spawn_and_wait(cmd, name, ctx.project(), stdin, timeout)?;
+    if output.timed_out {
+        return Err(anyhow!("{name} timed out after {}s", timeout.as_secs()));
+    }
+    if output.success() {
+        return Ok(output.stdout);
+    }
+    let status = output
+        .exit_code
+        .map_or_else(|| "a signal".to_owned(), |code| format!("exit code {code}"));
+    Err(
+        anyhow!("STDOUT:\n{}\nSTDERR:\n{}", output.stdout, output.stderr)
+            .context(format!("{name} failed with {status}")),
+    )
+}
+
+/// Run `cmd`, which is described as `name`, in the worktree of `project`, and kill it after `timeout`.
+fn spawn_and_wait(
+    mut cmd: Command,
+    name: &str,
+    project: &Project,
+    stdin: &[u8],
+    timeout: Duration,
+) -> Result<ScriptOutput> {
+    cmd.current_dir(&project.path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    ProcessEnv::new()
+        .extend(project.extra_env.clone())
+        .apply(&mut cmd);
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("failed to spawn {name}"))?;
+
+    // Feed and drain the pipes from threads so a hook that produces a lot of output can't block.
+    let input = child.stdin.take().map(|mut pipe| {
+        let stdin = stdin.to_owned();
+        // A hook may exit without reading its input, which isn't an error.
+        thread::spawn(move || pipe.write_all(&stdin).ok())
+    });
+    let stdout = child.stdout.take().map(read_in_background);
+    let stderr = child.stderr.take().map(read_in_background);
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if started.elapsed() >= timeout {
+            child.kill().ok();
+            child.wait().ok();
+            break None;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let Some(status) = status else {
+        // Processes started by the hook may still hold the pipes open, so don't wait for its output.
+        return Ok(ScriptOutput {
+            exit_code: None,
+            timed_out: true,
+            stdout: String::new(),
+            stderr: String::new(),
+        });
+    };
+
+    if let Some(input) = input {
+        input.join().ok();
+    }
+    let collect = |output: Option<thread::JoinHandle<Vec<u8>>>| {
+        output
+            .and_then(|handle| handle.join().ok())
+            .map(|bytes| String::from_utf8_lossy(&bytes).trim().to_owned())
+            .unwrap_or_default()
+    };
+    Ok(ScriptOutput {
+        exit_code: status.code(),
+        timed_out: false,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+fn read_in_background(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        pipe.read_to_end(&mut buf).ok();
+        buf
+    })
+}
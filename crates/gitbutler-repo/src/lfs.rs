@@ -0,0 +1,89 @@
+//! Keep files stored with Git LFS intact when the worktree is rewritten or branches are pushed.
+//!
+//! See [`gitbutler_diff::lfs`] for how pointer files are read and written.
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_diff::lfs;
+use gitbutler_git::ProcessEnv;
+
+/// Replace all pointer files that were checked out from `tree` with the content they point to,
+/// as libgit2 checks out pointer files as they are.
+///
+/// Files that don't match their pointer in `tree` are left alone, so this is cheap if there is nothing to do.
+pub fn smudge_checked_out_pointers(repo: &git2::Repository, tree: &git2::Tree<'_>) -> Result<()> {
+    if !lfs::is_used(repo) {
+        return Ok(());
+    }
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("LFS needs a repository with a worktree"))?;
+    let odb = repo.odb()?;
+
+    let mut candidates: Vec<(PathBuf, git2::Oid)> = Vec::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() != Some(git2::ObjectType::Blob) {
+            return git2::TreeWalkResult::Ok;
+        }
+        let Some(name) = entry.name() else {
+            return git2::TreeWalkResult::Ok;
+        };
+        let is_small = odb
+            .read_header(entry.id())
+            .map_or(false, |(size, _)| size <= lfs::MAX_POINTER_SIZE);
+        if is_small {
+            candidates.push((Path::new(root).join(name), entry.id()));
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+
+    for (path, id) in candidates {
+        let full_path = workdir.join(&path);
+        let pointer = odb.read(id)?;
+        let pointer = pointer.data();
+        let is_checked_out_pointer = std::fs::metadata(&full_path)
+            .map_or(false, |metadata| metadata.len() == pointer.len() as u64)
+            && std::fs::read(&full_path).map_or(false, |content| content == pointer);
+        if !is_checked_out_pointer
+            || lfs::Pointer::parse(pointer).is_none()
+            || !lfs::is_tracked(repo, &path)
+        {
+            continue;
+        }
+        let content = lfs::smudge(repo, &path, pointer)?;
+        std::fs::write(&full_path, content)
+            .with_context(|| format!("failed to write {}", full_path.display()))?;
+    }
+    Ok(())
+}
+
+/// Upload the LFS objects that the commits up to `head` point to to `remote_name`, before these
+/// commits are pushed.
+///
+/// This is what the `pre-push` hook installed by `git lfs install` does, which doesn't run if
+/// pushing doesn't involve Git.
+pub fn push_objects(ctx: &CommandContext, remote_name: &str, head: git2::Oid) -> Result<()> {
+    if !lfs::is_used(ctx.repository()) {
+        return Ok(());
+    }
+    let mut cmd = Command::new("git");
+    cmd.args(["lfs", "push", remote_name, &head.to_string()])
+        .current_dir(ctx.project().worktree_path());
+    ProcessEnv::new()
+        .extend(ctx.project().extra_env.clone())
+        .apply(&mut cmd);
+    let output = cmd
+        .output()
+        .context("failed to run git lfs, is it installed?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "failed to push LFS objects to {remote_name}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
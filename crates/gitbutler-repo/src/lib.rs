@@ -1,6 +1,7 @@
 pub mod rebase;
 
 mod repository;
+pub use gitbutler_git::{Deepen, ForcePush};
 pub use repository::{LogUntil, RepoActionsExt};
 
 mod commands;
@@ -9,10 +10,47 @@ pub use commands::RepoCommands;
 mod repository_ext;
 pub use repository_ext::RepositoryExt;
 
+mod repo_ext;
+pub use repo_ext::{ReadBackend, RepoReader};
+
 pub mod credentials;
 
+mod default_branch;
+pub use default_branch::{
+    guess_default_branch, remote_default_branch, DefaultBranchSource, RemoteDefaultBranch,
+};
+
+mod fetch;
+pub use fetch::{
+    fetch_remotes, remote_head_branch, DefaultBranchChange, FetchReport, RemoteFetch, UpdatedRef,
+};
+
+pub mod hooks;
+
+pub mod lfs;
+
+pub mod merge_drivers;
+
+pub mod sparse_checkout;
+
+pub mod partial_clone;
+
+pub mod shallow;
+
+pub mod commit_graph;
+
+pub mod repo_state;
+
+pub mod permissions;
+
 mod config;
 
 pub use config::Config;
 
 pub mod askpass;
+
+pub mod progress;
+
+mod transfer;
+
+pub mod errors;
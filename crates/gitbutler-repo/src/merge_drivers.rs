@@ -0,0 +1,222 @@
+//! Merge trees with the merge drivers that `.gitattributes` assign to files, like `git merge` does.
+//!
+//! * `merge=union` keeps the lines of both sides instead of conflicting, which suits lockfiles and changelogs.
+//! * `-merge` and `merge=binary` make files that changed on both sides conflict instead of merging their content.
+//! * `merge=NAME` runs the command configured as `merge.NAME.driver`, with `%O`, `%A` and `%B` replaced by
+//!   files with the content of the ancestor, ours and theirs, `%L` by the conflict marker size and `%P` by
+//!   the path of the file. The driver leaves the result in `%A`, and exits with a non-zero status on conflict.
+//!   It runs with the extra environment of the project, and fails the merge if it doesn't finish within a minute.
+//!
+//! Files without a driver, or with one that isn't configured, are merged as text, like `git2` does on its own.
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::extra_env;
+use gitbutler_diff::{filter, STAGE_MASK};
+use gitbutler_git::ProcessEnv;
+
+/// The size of conflict markers passed to merge drivers, like Git's default.
+const MARKER_SIZE: &str = "7";
+
+/// How long a merge driver may take for a single file before it's killed.
+const DRIVER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How the content of a file that changed on both sides is merged.
+enum Driver {
+    Text,
+    Binary,
+    Union,
+    /// Run the shell command configured for the driver.
+    Custom(String),
+}
+
+/// Merge `ours` and `theirs` on top of `ancestor` like [`git2::Repository::merge_trees()`], but with the merge
+/// drivers that `.gitattributes` assign to the files that changed on both sides, and return the resulting index.
+pub fn merge_trees(
+    repo: &git2::Repository,
+    ancestor: &git2::Tree,
+    ours: &git2::Tree,
+    theirs: &git2::Tree,
+) -> Result<git2::Index> {
+    let mut index = repo.merge_trees(ancestor, ours, theirs, None)?;
+    for path in changed_on_both_sides(repo, ancestor, ours, theirs)? {
+        let (Ok(our_entry), Ok(their_entry)) = (ours.get_path(&path), theirs.get_path(&path))
+        else {
+            continue;
+        };
+        if our_entry.id() == their_entry.id()
+            || our_entry.kind() != Some(git2::ObjectType::Blob)
+            || their_entry.kind() != Some(git2::ObjectType::Blob)
+        {
+            continue;
+        }
+        let ancestor_entry = ancestor.get_path(&path).ok();
+        let content = match driver(repo, &path)? {
+            Driver::Text => continue,
+            Driver::Binary => None,
+            Driver::Union => run_driver(
+                repo,
+                &path,
+                "git merge-file --union %A %O %B",
+                ancestor_entry.as_ref(),
+                &our_entry,
+                &their_entry,
+            )?,
+            Driver::Custom(command) => run_driver(
+                repo,
+                &path,
+                &command,
+                ancestor_entry.as_ref(),
+                &our_entry,
+                &their_entry,
+            )?,
+        };
+        match content {
+            Some(content) => {
+                if index.get_path(&path, 2).is_some() {
+                    index.conflict_remove(&path)?;
+                }
+                let mut entry = index_entry(&path, &our_entry, 0);
+                entry.id = repo.blob(&content)?;
+                entry.file_size = u32::try_from(content.len()).unwrap_or(u32::MAX);
+                index.add(&entry)?;
+                tracing::debug!(path = %path.display(), "resolved with merge driver");
+            }
+            None => {
+                if index.get_path(&path, 0).is_none() {
+                    continue;
+                }
+                index.remove(&path, 0)?;
+                if let Some(ancestor_entry) = &ancestor_entry {
+                    index.add(&index_entry(&path, ancestor_entry, 1))?;
+                }
+                index.add(&index_entry(&path, &our_entry, 2))?;
+                index.add(&index_entry(&path, &their_entry, 3))?;
+            }
+        }
+    }
+    Ok(index)
+}
+
+/// Return the paths of the files that changed between `ancestor` and both `ours` and `theirs`.
+fn changed_on_both_sides(
+    repo: &git2::Repository,
+    ancestor: &git2::Tree,
+    ours: &git2::Tree,
+    theirs: &git2::Tree,
+) -> Result<Vec<PathBuf>> {
+    let changed = |tree: &git2::Tree| -> Result<HashSet<PathBuf>> {
+        if tree.id() == ancestor.id() {
+            return Ok(HashSet::new());
+        }
+        Ok(repo
+            .diff_tree_to_tree(Some(ancestor), Some(tree), None)?
+            .deltas()
+            .filter_map(|delta| delta.new_file().path().map(Path::to_owned))
+            .collect())
+    };
+    let ours_changed = changed(ours)?;
+    if ours_changed.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut paths: Vec<_> = changed(theirs)?
+        .into_iter()
+        .filter(|path| ours_changed.contains(path))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Return the driver that the `merge` attribute of the file at `path` asks for.
+fn driver(repo: &git2::Repository, path: &Path) -> Result<Driver> {
+    let value = repo.get_attr(path, "merge", git2::AttrCheckFlags::FILE_THEN_INDEX)?;
+    Ok(match git2::AttrValue::from_string(value) {
+        git2::AttrValue::False | git2::AttrValue::String("binary") => Driver::Binary,
+        git2::AttrValue::String("union") => Driver::Union,
+        git2::AttrValue::String(name) if name != "text" => {
+            // Like Git, files with a driver that isn't configured are merged as text.
+            match repo.config()?.get_string(&format!("merge.{name}.driver")) {
+                Ok(command) => Driver::Custom(command),
+                Err(_) => Driver::Text,
+            }
+        }
+        _ => Driver::Text,
+    })
+}
+
+/// Run the merge driver `command` for the file at `path`, and return the merged content, or `None` if it
+/// reported a conflict.
+fn run_driver(
+    repo: &git2::Repository,
+    path: &Path,
+    command: &str,
+    ancestor: Option<&git2::TreeEntry>,
+    ours: &git2::TreeEntry,
+    theirs: &git2::TreeEntry,
+) -> Result<Option<Vec<u8>>> {
+    let dir = tempfile::tempdir()?;
+    let write = |name: &str, entry: Option<&git2::TreeEntry>| -> Result<PathBuf> {
+        let file = dir.path().join(name);
+        let content = match entry {
+            Some(entry) => repo.find_blob(entry.id())?.content().to_vec(),
+            None => Vec::new(),
+        };
+        fs::write(&file, content)?;
+        Ok(file)
+    };
+    let ancestor_file = write("ancestor", ancestor)?;
+    let ours_file = write("ours", Some(ours))?;
+    let theirs_file = write("theirs", Some(theirs))?;
+
+    let quote = |path: &Path| format!("'{}'", path.display().to_string().replace('\'', r"'\''"));
+    let script = command
+        .replace("%O", &quote(&ancestor_file))
+        .replace("%A", &quote(&ours_file))
+        .replace("%B", &quote(&theirs_file))
+        .replace("%L", MARKER_SIZE)
+        .replace("%P", &quote(path));
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("merge drivers need a worktree to run in"))?;
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(&script).current_dir(workdir);
+    ProcessEnv::new()
+        .extend(extra_env::of_repository(repo))
+        .apply(&mut cmd);
+    let output = filter::pipe(cmd, &[], DRIVER_TIMEOUT)
+        .with_context(|| format!("failed to run merge driver '{command}'"))?;
+    if !output.status.success() {
+        tracing::debug!(
+            path = %path.display(),
+            command,
+            stderr = %String::from_utf8_lossy(&output.stderr),
+            "merge driver reported a conflict"
+        );
+        return Ok(None);
+    }
+    Ok(Some(fs::read(&ours_file)?))
+}
+
+/// Return an index entry for `entry` of the file at `path` at the merge `stage`.
+fn index_entry(path: &Path, entry: &git2::TreeEntry, stage: u16) -> git2::IndexEntry {
+    git2::IndexEntry {
+        ctime: git2::IndexTime::new(0, 0),
+        mtime: git2::IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: u32::try_from(entry.filemode()).unwrap_or_default(),
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id: entry.id(),
+        flags: (stage << 12) & STAGE_MASK,
+        flags_extended: 0,
+        path: gix::path::into_bstr(path).into_owned().into(),
+    }
+}
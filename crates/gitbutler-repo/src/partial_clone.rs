@@ -0,0 +1,150 @@
+//! Work with blob-less partial clones, whose blobs are only fetched from their promisor remote once they
+//! are needed.
+//!
+//! libgit2 can't fetch missing objects by itself and fails to read them instead, so the blobs that diffs,
+//! merges and checkouts need are [fetched](fetch_missing()) with Git before these run.
+use std::{
+    collections::BTreeSet,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_git::ProcessEnv;
+
+/// Return the name of the remote that missing objects are fetched from, or `None` if the repository isn't
+/// a partial clone.
+pub fn promisor_remote(repo: &git2::Repository) -> Result<Option<String>> {
+    let config = repo.config()?.snapshot()?;
+    if let Ok(remote) = config.get_str("extensions.partialClone") {
+        return Ok(Some(remote.to_owned()));
+    }
+    for entry in &config.entries(Some(r"remote\..*\.promisor"))? {
+        let entry = entry?;
+        if entry.value() != Some("true") {
+            continue;
+        }
+        let remote = entry
+            .name()
+            .and_then(|name| name.strip_prefix("remote.")?.strip_suffix(".promisor"));
+        if let Some(remote) = remote {
+            return Ok(Some(remote.to_owned()));
+        }
+    }
+    Ok(None)
+}
+
+/// Fetch the objects with `ids` that aren't in the repository yet from the promisor remote, all at once.
+///
+/// Does nothing if the repository isn't a partial clone.
+pub fn fetch_missing(ctx: &CommandContext, ids: impl IntoIterator<Item = git2::Oid>) -> Result<()> {
+    let repo = ctx.repository();
+    let Some(remote) = promisor_remote(repo)? else {
+        return Ok(());
+    };
+    let odb = repo.odb()?;
+    let missing: BTreeSet<_> = ids
+        .into_iter()
+        .filter(|id| !id.is_zero() && !odb.exists(*id))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    // This is how Git itself fetches missing objects, without negotiating which objects the remote has to send.
+    let mut cmd = Command::new("git");
+    cmd.args(["-c", "fetch.negotiationAlgorithm=noop", "fetch", &remote])
+        .args([
+            "--no-tags",
+            "--no-write-fetch-head",
+            "--recurse-submodules=no",
+            "--filter=blob:none",
+            "--stdin",
+        ])
+        .current_dir(ctx.project().worktree_path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    ProcessEnv::new()
+        .extend(ctx.project().extra_env.clone())
+        .apply(&mut cmd);
+    let mut child = cmd.spawn().context("failed to run git fetch")?;
+    {
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        for id in &missing {
+            writeln!(stdin, "{id}")?;
+        }
+    }
+    let output = child
+        .wait_with_output()
+        .context("failed to wait for git fetch")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "failed to fetch {} missing object(s) from {remote}: {}",
+            missing.len(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Fetch the blobs of the files that differ between `base` and each tree of `others`, as merging them needs
+/// both versions of each.
+///
+/// Does nothing if the repository isn't a partial clone.
+pub fn fetch_changed_blobs(
+    ctx: &CommandContext,
+    base: &git2::Tree,
+    others: &[&git2::Tree],
+) -> Result<()> {
+    let repo = ctx.repository();
+    if promisor_remote(repo)?.is_none() {
+        return Ok(());
+    }
+    let mut ids = Vec::new();
+    for other in others {
+        let diff = repo
+            .diff_tree_to_tree(Some(base), Some(other), None)
+            .context("failed to diff trees")?;
+        for delta in diff.deltas() {
+            ids.extend(blob_ids(&delta));
+        }
+    }
+    fetch_missing(ctx, ids)
+}
+
+/// Fetch the blobs of the files in the index that changed in the worktree, limited to `scope` if set,
+/// as diffing the worktree needs them.
+///
+/// Does nothing if the repository isn't a partial clone.
+pub fn fetch_worktree_blobs(ctx: &CommandContext, scope: Option<&[PathBuf]>) -> Result<()> {
+    let repo = ctx.repository();
+    if promisor_remote(repo)?.is_none() {
+        return Ok(());
+    }
+    let mut opts = git2::DiffOptions::new();
+    opts.ignore_submodules(true);
+    if let Some(scope) = scope {
+        if scope.is_empty() {
+            return Ok(());
+        }
+        opts.disable_pathspec_match(true);
+        for path in scope {
+            opts.pathspec(path);
+        }
+    }
+    let diff = repo
+        .diff_index_to_workdir(None, Some(&mut opts))
+        .context("failed to diff the index with the worktree")?;
+    fetch_missing(ctx, diff.deltas().flat_map(|delta| blob_ids(&delta)))
+}
+
+fn blob_ids(delta: &git2::DiffDelta<'_>) -> Vec<git2::Oid> {
+    [delta.old_file(), delta.new_file()]
+        .into_iter()
+        .filter(|file| file.mode() != git2::FileMode::Commit)
+        .map(|file| file.id())
+        .collect()
+}
@@ -0,0 +1,85 @@
+//! Check that we can write where operations will write, before they start.
+//!
+//! Failing early with the exact path and who owns it is much easier to act on than an IO error
+//! from somewhere deep within an operation, which may also have left it half-done.
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+
+/// Return an error with [`Code::PermissionDenied`] if the worktree, the Git directory or the
+/// GitButler data within it of the repository in `ctx` can't be written to.
+///
+/// Nothing is written, as files that appear in the worktree or below `refs/` would be picked up
+/// as changes. Instead, the permissions of the current user are checked for each path.
+///
+/// The error names the first directory or file that isn't writable along with its owner.
+pub fn probe_writable(ctx: &CommandContext) -> Result<()> {
+    let repo = ctx.repository();
+    let git_dir = repo.path();
+    let mut paths: Vec<PathBuf> = repo.workdir().into_iter().map(Path::to_owned).collect();
+    paths.extend(
+        [
+            git_dir.to_owned(),
+            git_dir.join("objects"),
+            git_dir.join("refs"),
+            ctx.project().gb_dir(),
+        ]
+        .into_iter()
+        .filter(|dir| dir.is_dir()),
+    );
+    let index = git_dir.join("index");
+    if index.is_file() {
+        paths.push(index);
+    }
+    for path in &paths {
+        check_writable(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn check_writable(path: &Path) -> Result<()> {
+    nix::unistd::access(path, nix::unistd::AccessFlags::W_OK)
+        .map_err(|errno| denied(path, errno.into()))
+}
+
+#[cfg(not(unix))]
+fn check_writable(path: &Path) -> Result<()> {
+    let metadata = path.metadata().map_err(|err| denied(path, err))?;
+    if metadata.permissions().readonly() {
+        return Err(denied(path, std::io::ErrorKind::PermissionDenied.into()));
+    }
+    Ok(())
+}
+
+fn denied(path: &Path, err: std::io::Error) -> anyhow::Error {
+    if err.kind() != std::io::ErrorKind::PermissionDenied {
+        return anyhow!("failed to check if '{}' is writable: {err}", path.display());
+    }
+    anyhow!("'{}' is not writable: {}", path.display(), ownership(path))
+        .context(Code::PermissionDenied)
+}
+
+/// Describe who owns `path`, and how it can be accessed, as far as we can tell.
+fn ownership(path: &Path) -> String {
+    let owner = match gix::sec::identity::is_path_owned_by_current_user(path) {
+        Ok(true) => "owned by the current user",
+        Ok(false) => "owned by another user",
+        Err(_) => "owner unknown",
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let Ok(metadata) = path.metadata() {
+            return format!(
+                "{owner} (uid {}, gid {}, mode {:o})",
+                metadata.uid(),
+                metadata.gid(),
+                metadata.mode() & 0o7777
+            );
+        }
+    }
+    owner.to_owned()
+}
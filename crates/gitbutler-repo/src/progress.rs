@@ -0,0 +1,299 @@
+//! Report how far long operations like fetches, pushes or updates of the workspace got while they run, and let
+//! whoever follows them cancel them.
+//!
+//! Operations report to a [`Progress`] through [tasks](Task), which can have nested tasks, like fetching all
+//! remotes with a task for each remote. Each update of a task is passed on as [`ProgressUpdate`], which is
+//! meant to be shown as is. [`channel()`] makes the updates available to another thread, like one forwarding
+//! them to the app.
+//!
+//! Cancelling a [`ChannelProgress`] cancels its [token](ChannelProgress::cancellation()), and tasks also
+//! consider the operation cancelled if the [current token](cancellation::current()) of their thread is.
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::{cancellation, CancellationToken};
+use gitbutler_error::error::Code;
+use serde::Serialize;
+
+/// Updates of a running task are passed on at most this often, unless it's done.
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The id of the next task, unique within the process.
+static NEXT_TASK_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Receive the progress of an operation.
+pub trait Progress: Send + Sync {
+    /// Take note of `update` of a task.
+    fn update(&self, update: ProgressUpdate);
+
+    /// Return `true` if the operation should stop as soon as it can. Operations check this only where
+    /// stopping leaves the repository in a consistent state.
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// Ignore the progress of an operation, for when nobody follows it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn update(&self, _update: ProgressUpdate) {}
+}
+
+/// Where a task stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskState {
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+/// The state of a task at one point in time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressUpdate {
+    pub task_id: u64,
+    /// The task this one is part of, or `None` if it's the task of the operation itself.
+    pub parent_id: Option<u64>,
+    /// What the task does, like `Fetching origin`.
+    pub name: String,
+    pub state: TaskState,
+    /// How many steps, like objects or branches, are done.
+    pub done: u64,
+    /// How many steps there are, or `None` if that isn't known (yet).
+    pub total: Option<u64>,
+    /// How many percent of the task are done, if the amount of steps is known.
+    pub percent: Option<u8>,
+    /// How many bytes were transferred, for tasks that transfer data.
+    pub bytes: Option<u64>,
+    /// How many bytes were transferred per second on average since the task started.
+    pub bytes_per_second: Option<u64>,
+}
+
+/// Pass the progress of an operation on to the [`Receiver`](mpsc::Receiver) returned along with it, and let
+/// the operation be [cancelled](ChannelProgress::cancel()) from another thread.
+///
+/// The receiver yields updates until all clones of the `ChannelProgress` are dropped.
+pub fn channel() -> (ChannelProgress, mpsc::Receiver<ProgressUpdate>) {
+    let (sender, receiver) = mpsc::channel();
+    let progress = ChannelProgress {
+        sender,
+        cancellation: CancellationToken::new(),
+    };
+    (progress, receiver)
+}
+
+/// A [`Progress`] that sends all updates to a channel, as created by [`channel()`].
+#[derive(Debug, Clone)]
+pub struct ChannelProgress {
+    sender: mpsc::Sender<ProgressUpdate>,
+    cancellation: CancellationToken,
+}
+
+impl ChannelProgress {
+    /// Ask the operation to stop, which it will once it can.
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    /// Return the token that is cancelled along with this instance, to run the operation with it so that
+    /// the parts of it that don't report progress stop as well.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+}
+
+impl Progress for ChannelProgress {
+    fn update(&self, update: ProgressUpdate) {
+        // Nobody listening anymore isn't a reason to stop the operation.
+        self.sender.send(update).ok();
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+}
+
+/// Run `operation` so that neither `progress` nor the current token of this thread can cancel it, for when
+/// stopping midway would leave the repository inconsistent. Updates are still passed on to `progress`.
+pub fn uncancellable<T>(progress: &dyn Progress, operation: impl FnOnce(&dyn Progress) -> T) -> T {
+    struct Uncancellable<'a>(&'a dyn Progress);
+    impl Progress for Uncancellable<'_> {
+        fn update(&self, update: ProgressUpdate) {
+            self.0.update(update);
+        }
+    }
+    cancellation::scoped(&CancellationToken::new(), || {
+        operation(&Uncancellable(progress))
+    })
+}
+
+/// A part of an operation that reports its progress.
+///
+/// Tasks that are dropped without being [finished](Task::finish()) are reported as failed, or as cancelled
+/// if the operation was cancelled, as that's what happens when errors are returned early.
+pub struct Task<'a> {
+    progress: &'a dyn Progress,
+    id: u64,
+    parent_id: Option<u64>,
+    name: String,
+    started: Instant,
+    state: Mutex<Counts>,
+}
+
+#[derive(Default)]
+struct Counts {
+    done: u64,
+    total: Option<u64>,
+    bytes: Option<u64>,
+    last_update: Option<Instant>,
+    ended: bool,
+}
+
+impl<'a> Task<'a> {
+    /// Start the task of an operation, named `name`, which reports to `progress`.
+    pub fn new(progress: &'a dyn Progress, name: impl Into<String>) -> Self {
+        Task::start(progress, None, name.into())
+    }
+
+    /// Start a task named `name` that's part of this one.
+    pub fn child(&self, name: impl Into<String>) -> Task<'a> {
+        Task::start(self.progress, Some(self.id), name.into())
+    }
+
+    fn start(progress: &'a dyn Progress, parent_id: Option<u64>, name: String) -> Self {
+        let task = Task {
+            progress,
+            id: NEXT_TASK_ID.fetch_add(1, Ordering::Relaxed),
+            parent_id,
+            name,
+            started: Instant::now(),
+            state: Mutex::new(Counts::default()),
+        };
+        task.report(TaskState::Running, true);
+        task
+    }
+
+    /// Set how many steps the task has.
+    pub fn set_total(&self, total: u64) {
+        self.counts().total = Some(total);
+        self.report(TaskState::Running, false);
+    }
+
+    /// Set how many steps are done.
+    pub fn set(&self, done: u64) {
+        self.counts().done = done;
+        self.report(TaskState::Running, false);
+    }
+
+    /// Mark one more step as done.
+    pub fn inc(&self) {
+        self.counts().done += 1;
+        self.report(TaskState::Running, false);
+    }
+
+    /// Set that `done` of `total` objects were transferred so far, amounting to `bytes`.
+    pub fn set_transfer(&self, done: u64, total: u64, bytes: u64) {
+        {
+            let mut counts = self.counts();
+            counts.done = done;
+            counts.total = Some(total);
+            counts.bytes = Some(bytes);
+        }
+        self.report(TaskState::Running, false);
+    }
+
+    /// Return `true` if the operation this task is part of should stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.progress.is_cancelled() || cancellation::is_cancelled()
+    }
+
+    /// Fail with [`Code::Cancelled`] if the operation this task is part of should stop.
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(anyhow!("{} was cancelled", self.name)).context(Code::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Report the task as done.
+    pub fn finish(self) {
+        self.end(TaskState::Finished);
+    }
+
+    fn end(&self, state: TaskState) {
+        {
+            let mut counts = self.counts();
+            if counts.ended {
+                return;
+            }
+            counts.ended = true;
+            if state == TaskState::Finished {
+                if let Some(total) = counts.total {
+                    counts.done = total;
+                }
+            }
+        }
+        self.report(state, true);
+    }
+
+    fn counts(&self) -> std::sync::MutexGuard<'_, Counts> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Pass on the current state as `state`, unless the last update was too recent and nothing notable
+    /// changed, unless `force` is set.
+    fn report(&self, state: TaskState, force: bool) {
+        let update = {
+            let mut counts = self.counts();
+            let now = Instant::now();
+            let complete = counts.total == Some(counts.done);
+            let recent = counts
+                .last_update
+                .is_some_and(|last| now.duration_since(last) < MIN_UPDATE_INTERVAL);
+            if !force && !complete && recent {
+                return;
+            }
+            counts.last_update = Some(now);
+            let elapsed = now.duration_since(self.started).as_secs_f64();
+            ProgressUpdate {
+                task_id: self.id,
+                parent_id: self.parent_id,
+                name: self.name.clone(),
+                state,
+                done: counts.done,
+                total: counts.total,
+                percent: counts
+                    .total
+                    .filter(|total| *total > 0)
+                    .map(|total| (counts.done.min(total) * 100 / total) as u8),
+                bytes: counts.bytes,
+                bytes_per_second: counts
+                    .bytes
+                    .filter(|_| elapsed > 0.0)
+                    .map(|bytes| (bytes as f64 / elapsed) as u64),
+            }
+        };
+        self.progress.update(update);
+    }
+}
+
+impl Drop for Task<'_> {
+    fn drop(&mut self) {
+        let state = if self.is_cancelled() {
+            TaskState::Cancelled
+        } else {
+            TaskState::Failed
+        };
+        self.end(state);
+    }
+}
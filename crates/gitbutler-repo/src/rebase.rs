@@ -1,8 +1,12 @@
+use std::path::PathBuf;
+
 use anyhow::{anyhow, Context, Result};
 use bstr::ByteSlice;
 use gitbutler_command_context::CommandContext;
 use gitbutler_commit::{commit_ext::CommitExt, commit_headers::HasCommitHeaders};
+use gitbutler_diff::STAGE_MASK;
 use gitbutler_error::error::Marker;
+use serde::Serialize;
 
 use crate::{LogUntil, RepoActionsExt, RepositoryExt};
 
@@ -95,3 +99,78 @@ pub fn cherry_rebase_group(
 
     Ok(new_head_id)
 }
+
+/// A commit that can't be cherry-picked cleanly onto its new parent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictedCommit {
+    /// The id of the commit before it was rebased.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub commit_id: git2::Oid,
+    /// The paths that would be conflicting after the cherry-pick.
+    pub paths: Vec<PathBuf>,
+}
+
+/// Simulate [`cherry_rebase_group()`] with the same arguments, and return all commits that would conflict,
+/// in the order they would be picked.
+///
+/// Only trees are merged and they are kept in memory, so neither the object database nor the worktree is touched.
+/// Conflicts are resolved in favor of the picked commit, so that all commits after a conflicting one are
+/// checked as well.
+pub fn find_rebase_conflicts(
+    ctx: &CommandContext,
+    target_commit_oid: git2::Oid,
+    ids_to_rebase: &[git2::Oid],
+) -> Result<Vec<ConflictedCommit>> {
+    let repo = ctx.repository().in_memory_repo()?;
+    let mut head_tree = repo
+        .find_commit(target_commit_oid)
+        .context("failed to find new commit")?
+        .tree()?;
+
+    let mut conflicted_commits = Vec::new();
+    for id in ids_to_rebase.iter().rev() {
+        let to_rebase = repo
+            .find_commit(*id)
+            .context("failed to read commit to rebase")?;
+        let parent_tree = to_rebase
+            .parent(0)
+            .context("cannot rebase a root commit")?
+            .tree()?;
+        let mut index = repo
+            .merge_trees(&parent_tree, &head_tree, &to_rebase.tree()?, None)
+            .context("failed to cherry pick")?;
+
+        if index.has_conflicts() {
+            let conflicts = index.conflicts()?.collect::<Result<Vec<_>, _>>()?;
+            let mut paths = Vec::new();
+            for conflict in conflicts {
+                let Some(path) = [&conflict.our, &conflict.their, &conflict.ancestor]
+                    .into_iter()
+                    .flatten()
+                    .map(|entry| gix::path::from_bstr(entry.path.as_bstr()).into_owned())
+                    .next()
+                else {
+                    continue;
+                };
+                index.conflict_remove(&path)?;
+                if let Some(mut theirs) = conflict.their {
+                    // Clear the stage so the entry is added as resolved.
+                    theirs.flags &= !STAGE_MASK;
+                    index.add(&theirs)?;
+                }
+                paths.push(path);
+            }
+            conflicted_commits.push(ConflictedCommit {
+                commit_id: *id,
+                paths,
+            });
+        }
+
+        let tree_oid = index
+            .write_tree_to(&repo)
+            .context("failed to write merge tree")?;
+        head_tree = repo.find_tree(tree_oid)?;
+    }
+    Ok(conflicted_commits)
+}
@@ -0,0 +1,115 @@
+//! Read-heavy operations that can be performed by `gix` instead of `git2`, which is much faster for them
+//! in large repositories. Writes keep using `git2`.
+//!
+//! Each call site chooses its [`ReadBackend`], so they can move over one at a time. The tests assure
+//! that both backends return the same for each operation.
+use anyhow::{anyhow, Context, Result};
+
+/// The library that performs the operations of a [`RepoReader`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReadBackend {
+    #[default]
+    Git2,
+    Gix,
+}
+
+/// Read access to a repository, with all operations performed by one [`ReadBackend`].
+pub struct RepoReader<'repo> {
+    git2: &'repo git2::Repository,
+    /// The same repository opened with `gix`, if it's the backend.
+    gix: Option<gix::Repository>,
+}
+
+impl<'repo> RepoReader<'repo> {
+    /// Read from `repo` with `backend`, which opens the repository once more if it's [`ReadBackend::Gix`].
+    pub fn new(repo: &'repo git2::Repository, backend: ReadBackend) -> Result<Self> {
+        let gix = match backend {
+            ReadBackend::Git2 => None,
+            ReadBackend::Gix => {
+                let mut gix = gix::open(repo.path())?;
+                gix.object_cache_size_if_unset(gitbutler_diff::memory_limits().object_cache_bytes);
+                Some(gix)
+            }
+        };
+        Ok(RepoReader { git2: repo, gix })
+    }
+
+    pub fn backend(&self) -> ReadBackend {
+        match self.gix {
+            Some(_) => ReadBackend::Gix,
+            None => ReadBackend::Git2,
+        }
+    }
+
+    /// Return the full names and targets of all references whose name starts with `prefix`, like
+    /// `refs/remotes/origin/`, sorted by name. Symbolic references are skipped.
+    pub fn references(&self, prefix: &str) -> Result<Vec<(String, git2::Oid)>> {
+        let mut refs = Vec::new();
+        match &self.gix {
+            Some(repo) => {
+                for reference in repo.references()?.prefixed(prefix)? {
+                    let reference = reference.map_err(|err| anyhow!(err))?;
+                    if let Some(id) = reference.target().try_id() {
+                        refs.push((reference.name().as_bstr().to_string(), to_git2(id)));
+                    }
+                }
+            }
+            None => {
+                for reference in self.git2.references_glob(&format!("{prefix}*"))? {
+                    let reference = reference?;
+                    if let (Some(name), Some(target)) = (reference.name(), reference.target()) {
+                        refs.push((name.to_owned(), target));
+                    }
+                }
+            }
+        }
+        refs.sort();
+        Ok(refs)
+    }
+
+    /// Return the commits from `from` along the first parents, newest first, up to but excluding `until`,
+    /// or up to the root commit if `until` isn't one of them.
+    pub fn first_parent_log(
+        &self,
+        from: git2::Oid,
+        until: Option<git2::Oid>,
+    ) -> Result<Vec<git2::Oid>> {
+        let mut commits = Vec::new();
+        let mut next = Some(from);
+        while let Some(id) = next.filter(|id| Some(*id) != until) {
+            commits.push(id);
+            next = match &self.gix {
+                Some(repo) => repo
+                    .find_object(to_gix(id))?
+                    .try_into_commit()?
+                    .parent_ids()
+                    .next()
+                    .map(|parent| to_git2(&parent)),
+                None => self.git2.find_commit(id)?.parent_ids().next(),
+            };
+        }
+        Ok(commits)
+    }
+
+    /// Return the content of the blob with `id`.
+    pub fn blob(&self, id: git2::Oid) -> Result<Vec<u8>> {
+        match &self.gix {
+            Some(repo) => {
+                let mut blob = repo
+                    .find_object(to_gix(id))?
+                    .try_into_blob()
+                    .with_context(|| format!("{id} isn't a blob"))?;
+                Ok(std::mem::take(&mut blob.data))
+            }
+            None => Ok(self.git2.find_blob(id)?.content().to_vec()),
+        }
+    }
+}
+
+fn to_gix(id: git2::Oid) -> gix::ObjectId {
+    gix::ObjectId::try_from(id.as_bytes()).expect("git2 oid is always valid")
+}
+
+fn to_git2(id: &gix::oid) -> git2::Oid {
+    git2::Oid::from_bytes(id.as_bytes()).expect("always valid")
+}
@@ -0,0 +1,165 @@
+//! Detect merges, rebases and other operations that Git or other tools started and that are still in progress,
+//! so GitButler doesn't change the repository underneath them, and abort or continue them.
+use std::{fmt, process::Command};
+
+use anyhow::{anyhow, Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_git::ProcessEnv;
+use serde::Serialize;
+
+/// An operation of Git that stopped before it was done, like a merge with conflicts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GitOperation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+    /// Applying patches from a mailbox with `git am`.
+    ApplyMailbox,
+    Bisect,
+}
+
+impl GitOperation {
+    /// The Git command that controls the operation.
+    fn command(&self) -> &'static str {
+        match self {
+            GitOperation::Merge => "merge",
+            GitOperation::Rebase => "rebase",
+            GitOperation::CherryPick => "cherry-pick",
+            GitOperation::Revert => "revert",
+            GitOperation::ApplyMailbox => "am",
+            GitOperation::Bisect => "bisect",
+        }
+    }
+}
+
+impl fmt::Display for GitOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "git {}", self.command())
+    }
+}
+
+/// An operation in progress in a repository.
+///
+/// It's returned as error along with [`Code::ProjectStateInProgress`] by operations that it would
+/// conflict with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationInProgress {
+    pub operation: GitOperation,
+    /// The commit that is merged, cherry-picked or reverted, as recorded in `MERGE_HEAD`,
+    /// `CHERRY_PICK_HEAD` or `REVERT_HEAD`.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub head: Option<git2::Oid>,
+    /// Whether the index has conflicts that have to be resolved before the operation can continue.
+    pub has_conflicts: bool,
+    /// Whether the operation can be continued, as it has no conflicts and isn't a bisect.
+    pub can_continue: bool,
+}
+
+impl fmt::Display for OperationInProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is in progress in the repository, and has to be continued or aborted first",
+            self.operation
+        )
+    }
+}
+
+impl std::error::Error for OperationInProgress {}
+
+/// Return the operation in progress in `repo`, if any.
+pub fn in_progress(repo: &git2::Repository) -> Result<Option<OperationInProgress>> {
+    use git2::RepositoryState as S;
+    let (operation, head_file) = match repo.state() {
+        S::Clean => return Ok(None),
+        S::Merge => (GitOperation::Merge, Some("MERGE_HEAD")),
+        S::Rebase | S::RebaseInteractive | S::RebaseMerge => (GitOperation::Rebase, None),
+        S::CherryPick | S::CherryPickSequence => {
+            (GitOperation::CherryPick, Some("CHERRY_PICK_HEAD"))
+        }
+        S::Revert | S::RevertSequence => (GitOperation::Revert, Some("REVERT_HEAD")),
+        S::ApplyMailbox | S::ApplyMailboxOrRebase => (GitOperation::ApplyMailbox, None),
+        S::Bisect => (GitOperation::Bisect, None),
+    };
+    let head = head_file.and_then(|name| {
+        let content = std::fs::read_to_string(repo.path().join(name)).ok()?;
+        git2::Oid::from_str(content.lines().next()?.trim()).ok()
+    });
+    let has_conflicts = repo.index()?.has_conflicts();
+    Ok(Some(OperationInProgress {
+        operation,
+        head,
+        has_conflicts,
+        can_continue: !has_conflicts && operation != GitOperation::Bisect,
+    }))
+}
+
+/// Fail with [`Code::ProjectStateInProgress`] if an operation is in progress in `repo`.
+pub fn ensure_none_in_progress(repo: &git2::Repository) -> Result<()> {
+    match in_progress(repo)? {
+        None => Ok(()),
+        Some(operation) => {
+            Err(anyhow::Error::from(operation).context(Code::ProjectStateInProgress))
+        }
+    }
+}
+
+/// Abort the operation in progress in the repository of `ctx`, which restores the state from before it.
+pub fn abort(ctx: &CommandContext) -> Result<()> {
+    let operation = current(ctx)?;
+    let args: &[&str] = match operation.operation {
+        GitOperation::Bisect => &["reset"],
+        _ => &["--abort"],
+    };
+    run(ctx, operation.operation, args)
+}
+
+/// Continue the operation in progress in the repository of `ctx` once its conflicts are resolved,
+/// with the messages Git suggests for the commits it creates.
+pub fn continue_operation(ctx: &CommandContext) -> Result<()> {
+    let operation = current(ctx)?;
+    if !operation.can_continue {
+        return Err(anyhow!(
+            "`{}` can't be continued{}",
+            operation.operation,
+            if operation.has_conflicts {
+                " before its conflicts are resolved"
+            } else {
+                ""
+            }
+        ))
+        .context(Code::Validation);
+    }
+    run(ctx, operation.operation, &["--continue"])
+}
+
+fn current(ctx: &CommandContext) -> Result<OperationInProgress> {
+    in_progress(ctx.repository())?
+        .context("no operation is in progress")
+        .context(Code::Validation)
+}
+
+fn run(ctx: &CommandContext, operation: GitOperation, args: &[&str]) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.arg(operation.command())
+        .args(args)
+        .current_dir(ctx.project().worktree_path());
+    ProcessEnv::new()
+        .extend(ctx.project().extra_env.clone())
+        // Accept the messages Git suggests instead of waiting for an editor.
+        .set("GIT_EDITOR", "true")
+        .apply(&mut cmd);
+    let output = cmd.output().context("failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{operation} {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
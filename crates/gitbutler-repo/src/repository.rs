@@ -1,25 +1,96 @@
-use std::str::FromStr;
+use std::{cell::Cell, str::FromStr};
 
 use anyhow::{anyhow, Context, Result};
-use gitbutler_branch::{gix_to_git2_signature, Branch, BranchId, SignaturePurpose};
+use gitbutler_branch::{
+    gix_to_git2_signature, Branch, BranchId, SignaturePurpose, VirtualBranchesHandle,
+};
 use gitbutler_command_context::CommandContext;
 use gitbutler_commit::commit_headers::CommitHeadersV2;
 use gitbutler_error::error::Code;
-use gitbutler_project::AuthKey;
+use gitbutler_git::{Deepen, ForcePush};
+use gitbutler_project::{AuthKey, FetchRefspecs, FetchTags, Project};
 use gitbutler_reference::{Refname, RemoteRefname};
 
-use crate::{askpass, credentials::Helper, Config, RepositoryExt};
+use crate::{
+    askpass,
+    credentials::{Credential, HelpError, Helper},
+    errors,
+    progress::{NoProgress, Task},
+    shallow,
+    transfer::{self, TransferProgress},
+    Config, RepositoryExt,
+};
 pub trait RepoActionsExt {
-    fn fetch(&self, remote_name: &str, credentials: &Helper, askpass: Option<String>)
-        -> Result<()>;
+    fn fetch(
+        &self,
+        remote_name: &str,
+        credentials: &Helper,
+        askpass: Option<String>,
+    ) -> Result<()> {
+        let task = Task::new(&NoProgress, format!("Fetching {remote_name}"));
+        self.fetch_with_progress(remote_name, credentials, askpass, &task)?;
+        task.finish();
+        Ok(())
+    }
+    /// Like [`Self::fetch()`], but report the objects received so far to `task`, and stop once its
+    /// operation is cancelled, failing with [`Code::Cancelled`]. The Git executable doesn't tell how
+    /// far it got, so the task only starts and ends when it's used.
+    fn fetch_with_progress(
+        &self,
+        remote_name: &str,
+        credentials: &Helper,
+        askpass: Option<String>,
+        task: &Task<'_>,
+    ) -> Result<()> {
+        self.fetch_deepening(remote_name, None, credentials, askpass, task)
+    }
+    /// Like [`Self::fetch_with_progress()`], but also fetch the history of a shallow clone further back
+    /// as `deepen` says, if set.
+    fn fetch_deepening(
+        &self,
+        remote_name: &str,
+        deepen: Option<Deepen>,
+        credentials: &Helper,
+        askpass: Option<String>,
+        task: &Task<'_>,
+    ) -> Result<()>;
+    /// Push `head` to `branch`, replacing commits of it only as allowed by `force`.
+    ///
+    /// Fails with [`Code::PushRejected`] if the remote branch has commits that the push would drop.
     fn push(
         &self,
         head: &git2::Oid,
         branch: &RemoteRefname,
-        with_force: bool,
+        force: ForcePush,
         credentials: &Helper,
         refspec: Option<String>,
         askpass_broker: Option<Option<BranchId>>,
+    ) -> Result<()> {
+        let task = Task::new(&NoProgress, format!("Pushing to {branch}"));
+        self.push_with_progress(
+            head,
+            branch,
+            force,
+            credentials,
+            refspec,
+            askpass_broker,
+            &task,
+        )?;
+        task.finish();
+        Ok(())
+    }
+    /// Like [`Self::push()`], but report the objects sent so far to `task`, and don't start sending once its
+    /// operation is cancelled, failing with [`Code::Cancelled`].
+    #[allow(clippy::too_many_arguments)]
+    fn push_with_progress(
+        &self,
+        head: &git2::Oid,
+        branch: &RemoteRefname,
+        force: ForcePush,
+        credentials: &Helper,
+        refspec: Option<String>,
+        askpass_broker: Option<Option<BranchId>>,
+        task: &Task<'_>,
     ) -> Result<()>;
     fn commit(
         &self,
@@ -67,7 +138,14 @@ impl RepoActionsExt for CommandContext {
         let refname =
             RemoteRefname::from_str(&format!("refs/remotes/{remote_name}/{branch_name}",))?;
 
-        match self.push(&commit_id, &refname, false, credentials, None, askpass) {
+        match self.push(
+            &commit_id,
+            &refname,
+            ForcePush::No,
+            credentials,
+            None,
+            askpass,
+        ) {
             Ok(()) => Ok(()),
             Err(e) => Err(anyhow::anyhow!(e.to_string())),
         }?;
@@ -252,17 +330,20 @@ impl RepoActionsExt for CommandContext {
             .context("failed to commit")
     }
 
-    fn push(
+    fn push_with_progress(
         &self,
         head: &git2::Oid,
         branch: &RemoteRefname,
-        with_force: bool,
+        force: ForcePush,
         credentials: &Helper,
         refspec: Option<String>,
         askpass_broker: Option<Option<BranchId>>,
+        task: &Task<'_>,
     ) -> Result<()> {
+        task.check_cancelled()?;
         let refspec = refspec.unwrap_or_else(|| {
-            if with_force {
+            // The lease is checked on its own, so the refspec has to allow replacing the branch.
+            if force != ForcePush::No {
                 format!("+{}:refs/heads/{}", head, branch.branch())
             } else {
                 format!("{}:refs/heads/{}", head, branch.branch())
@@ -275,48 +356,129 @@ impl RepoActionsExt for CommandContext {
         // NOTE(qix-): work around a time-sensitive change that was necessary
         // NOTE(qix-): without having to refactor a large portion of the codebase.
         if self.project().preferred_key == AuthKey::SystemExecutable {
-            let path = self.project().worktree_path();
-            let remote = branch.remote().to_string();
-            return std::thread::spawn(move || {
-                tokio::runtime::Runtime::new()
-                    .unwrap()
-                    .block_on(gitbutler_git::push(
-                        path,
-                        gitbutler_git::tokio::TokioExecutor,
-                        &remote,
-                        gitbutler_git::RefSpec::parse(refspec).unwrap(),
-                        with_force,
-                        handle_git_prompt_push,
-                        askpass_broker,
-                    ))
-            })
-            .join()
-            .unwrap()
-            .map_err(Into::into);
+            return transfer::retry_interrupted(&self.project().transfer_retries, "push", || {
+                let path = self.project().worktree_path();
+                let remote = branch.remote().to_string();
+                let env = gitbutler_git::ProcessEnv::new().extend(self.project().extra_env.clone());
+                let (refspec, force) = (refspec.clone(), force.clone());
+                std::thread::spawn(move || {
+                    tokio::runtime::Runtime::new()
+                        .unwrap()
+                        .block_on(gitbutler_git::push(
+                            path,
+                            gitbutler_git::tokio::TokioExecutor::with_env(env),
+                            &remote,
+                            gitbutler_git::RefSpec::parse(refspec).unwrap(),
+                            force,
+                            handle_git_prompt_push,
+                            askpass_broker,
+                        ))
+                })
+                .join()
+                .unwrap()
+                .map_err(|err| match err {
+                    gitbutler_git::Error::PushRejected(_) => {
+                        anyhow::Error::from(err).context(Code::PushRejected)
+                    }
+                    err => transfer::offline_or(err.into()),
+                })
+            });
+        }
+
+        // `git push` runs the `pre-push` hook of LFS by itself, but libgit2 doesn't.
+        if !refspec.starts_with(':') {
+            crate::lfs::push_objects(self, branch.remote(), *head)?;
         }
 
-        let auth_flows = credentials.help(self, branch.remote())?;
+        let lease = match &force {
+            ForcePush::WithLease(expected) => Some(
+                expected
+                    .as_deref()
+                    .map(git2::Oid::from_str)
+                    .transpose()?
+                    .unwrap_or_else(git2::Oid::zero),
+            ),
+            ForcePush::No | ForcePush::Yes => None,
+        };
+        let destination = format!("refs/heads/{}", branch.branch());
+        let retries = self.project().transfer_retries;
+        let auth_flows = credentials
+            .help(self, branch.remote())
+            .map_err(help_error)?;
+        let mut failed_auth = vec![];
+        let mut ssh_key_rejected = false;
+        let mut offline_error: Option<git2::Error> = None;
+        let mut reached_remote = false;
         for (mut remote, callbacks) in auth_flows {
             let mut update_refs_error: Option<git2::Error> = None;
+            let mut lease_broken = false;
             for callback in callbacks {
-                let mut cbs: git2::RemoteCallbacks = callback.into();
-                if self.project().omit_certificate_check.unwrap_or(false) {
-                    cbs.certificate_check(|_, _| Ok(git2::CertificateCheckStatus::CertificateOk));
-                }
-                cbs.push_update_reference(|_reference: &str, status: Option<&str>| {
-                    if let Some(status) = status {
-                        update_refs_error = Some(git2::Error::from_str(status));
-                        return Err(git2::Error::from_str(status));
-                    };
-                    Ok(())
-                });
-
-                let push_result = remote.push(
-                    &[refspec.as_str()],
-                    Some(&mut git2::PushOptions::new().remote_callbacks(cbs)),
-                );
+                // Once the remote accepted the credentials, an interrupted push is attempted again with them.
+                let mut retry = 0;
+                let push_result = loop {
+                    let progress = Cell::new(TransferProgress::default());
+                    let mut cbs: git2::RemoteCallbacks = callback.clone().into();
+                    if self.project().omit_certificate_check.unwrap_or(false) {
+                        cbs.certificate_check(|_, _| {
+                            Ok(git2::CertificateCheckStatus::CertificateOk)
+                        });
+                    }
+                    cbs.push_negotiation(|updates| {
+                        // Nothing was sent yet, so stopping here leaves the remote as it was.
+                        if task.is_cancelled() {
+                            return Err(git2::Error::from_str("cancelled"));
+                        }
+                        if let Some(expected) = lease {
+                            // The remote branch moved since it was last seen.
+                            if updates.iter().any(|update| {
+                                update.dst_refname() == Some(destination.as_str())
+                                    && update.src() != expected
+                            }) {
+                                lease_broken = true;
+                                return Err(git2::Error::from_str("stale info"));
+                            }
+                        }
+                        Ok(())
+                    });
+                    cbs.push_update_reference(|_reference: &str, status: Option<&str>| {
+                        if let Some(status) = status {
+                            update_refs_error = Some(git2::Error::from_str(status));
+                            return Err(git2::Error::from_str(status));
+                        };
+                        Ok(())
+                    });
+                    cbs.push_transfer_progress(|objects, total_objects, bytes| {
+                        progress.set(TransferProgress {
+                            objects,
+                            total_objects,
+                            bytes,
+                        });
+                        task.set_transfer(objects as u64, total_objects as u64, bytes as u64);
+                    });
+
+                    let result = remote.push(
+                        &[refspec.as_str()],
+                        Some(&mut git2::PushOptions::new().remote_callbacks(cbs)),
+                    );
+                    let progress = progress.get();
+                    match result {
+                        Err(_) if task.is_cancelled() => return task.check_cancelled(),
+                        Err(err) if transfer::is_interruption(&err) && progress.started() => {
+                            retry += 1;
+                            if !transfer::wait_for_retry(&retries, retry, "push", Some(progress)) {
+                                return Err(anyhow::Error::from(err)
+                                    .context(format!(
+                                        "push was interrupted after sending {progress}"
+                                    ))
+                                    .context(Code::ProjectGitRemote));
+                            }
+                        }
+                        result => break result,
+                    }
+                };
                 match push_result {
                     Ok(()) => {
+                        callback.approve(self, remote.url().unwrap_or_default());
                         tracing::info!(
                             project_id = %self.project().id,
                             remote = %branch.remote(),
@@ -329,14 +491,29 @@ impl RepoActionsExt for CommandContext {
                     Err(err) => match err.class() {
                         git2::ErrorClass::Net | git2::ErrorClass::Http => {
                             tracing::warn!(project_id = %self.project().id, ?err, "push failed due to network");
+                            failed_auth.push(format!("{callback}: {}", err.message()));
+                            if err.code() == git2::ErrorCode::Auth {
+                                reached_remote = true;
+                            } else if transfer::is_offline_message(err.message()) {
+                                offline_error = Some(err);
+                            }
                             continue;
                         }
                         _ => match err.code() {
                             git2::ErrorCode::Auth => {
+                                reached_remote = true;
                                 tracing::warn!(project_id = %self.project().id, ?err, "push failed due to auth");
+                                callback.reject(self, remote.url().unwrap_or_default());
+                                ssh_key_rejected |= matches!(callback, Credential::Ssh(_));
+                                failed_auth.push(format!("{callback}: {}", err.message()));
                                 continue;
                             }
                             _ => {
+                                if lease_broken || err.code() == git2::ErrorCode::NotFastForward {
+                                    return Err(
+                                        anyhow::Error::from(err).context(Code::PushRejected)
+                                    );
+                                }
                                 if let Some(update_refs_err) = update_refs_error {
                                     return Err(update_refs_err).context(err);
                                 }
@@ -348,16 +525,24 @@ impl RepoActionsExt for CommandContext {
             }
         }
 
-        Err(anyhow!("authentication failed").context(Code::ProjectGitAuth))
+        // Without a network, none of the ways to authenticate could even be tried.
+        if let Some(err) = offline_error.filter(|_| !reached_remote) {
+            return Err(anyhow::Error::from(err).context(Code::Offline));
+        }
+        Err(auth_failed(&failed_auth, ssh_key_rejected))
     }
 
-    fn fetch(
+    fn fetch_deepening(
         &self,
         remote_name: &str,
+        deepen: Option<Deepen>,
         credentials: &Helper,
         askpass: Option<String>,
+        task: &Task<'_>,
     ) -> Result<()> {
-        let refspec = format!("+refs/heads/*:refs/remotes/{}/*", remote_name);
+        task.check_cancelled()?;
+        let settings = &self.project().settings.fetch;
+        let refspecs = fetch_refspecs(self.project(), remote_name);
 
         // NOTE(qix-): This is a nasty hack, however the codebase isn't structured
         // NOTE(qix-): in a way that allows us to really incorporate new backends
@@ -365,49 +550,147 @@ impl RepoActionsExt for CommandContext {
         // NOTE(qix-): work around a time-sensitive change that was necessary
         // NOTE(qix-): without having to refactor a large portion of the codebase.
         if self.project().preferred_key == AuthKey::SystemExecutable {
-            let path = self.project().worktree_path();
-            let remote = remote_name.to_string();
-            return std::thread::spawn(move || {
-                tokio::runtime::Runtime::new()
-                    .unwrap()
-                    .block_on(gitbutler_git::fetch(
-                        path,
-                        gitbutler_git::tokio::TokioExecutor,
-                        &remote,
-                        gitbutler_git::RefSpec::parse(refspec).unwrap(),
-                        handle_git_prompt_fetch,
-                        askpass,
-                    ))
-            })
-            .join()
-            .unwrap()
-            .map_err(Into::into);
+            let refspecs = refspecs
+                .iter()
+                .map(|refspec| {
+                    gitbutler_git::RefSpec::parse(refspec)
+                        .with_context(|| format!("'{refspec}' isn't a valid refspec to fetch"))
+                })
+                .collect::<Result<Vec<_>>>()
+                .context(Code::Validation)?;
+            let options = gitbutler_git::FetchOptions {
+                prune: settings.prune,
+                tags: match settings.tags {
+                    FetchTags::Auto => None,
+                    FetchTags::All => Some(true),
+                    FetchTags::None => Some(false),
+                },
+                deepen,
+            };
+            return transfer::retry_interrupted(&self.project().transfer_retries, "fetch", || {
+                let path = self.project().worktree_path();
+                let remote = remote_name.to_string();
+                let env = gitbutler_git::ProcessEnv::new().extend(self.project().extra_env.clone());
+                let (refspecs, options, askpass) =
+                    (refspecs.clone(), options.clone(), askpass.clone());
+                std::thread::spawn(move || {
+                    tokio::runtime::Runtime::new()
+                        .unwrap()
+                        .block_on(gitbutler_git::fetch(
+                            path,
+                            gitbutler_git::tokio::TokioExecutor::with_env(env),
+                            &remote,
+                            &refspecs,
+                            &options,
+                            handle_git_prompt_fetch,
+                            askpass,
+                        ))
+                })
+                .join()
+                .unwrap()
+                .map_err(|err| match err {
+                    gitbutler_git::Error::AuthorizationFailed(_) => {
+                        anyhow::Error::from(err).context(Code::ProjectGitAuth)
+                    }
+                    err => transfer::offline_or(err.into()),
+                })
+            });
         }
 
-        let auth_flows = credentials.help(self, remote_name)?;
+        let retries = self.project().transfer_retries;
+        let depth =
+            deepen.map(|deepen| shallow::depth_to_fetch(self.repository(), remote_name, deepen));
+        let auth_flows = credentials.help(self, remote_name).map_err(help_error)?;
+        let mut failed_auth = vec![];
+        let mut ssh_key_rejected = false;
+        let mut network_error: Option<git2::Error> = None;
+        let mut reached_remote = false;
         for (mut remote, callbacks) in auth_flows {
             for callback in callbacks {
-                let mut fetch_opts = git2::FetchOptions::new();
-                let mut cbs: git2::RemoteCallbacks = callback.into();
-                if self.project().omit_certificate_check.unwrap_or(false) {
-                    cbs.certificate_check(|_, _| Ok(git2::CertificateCheckStatus::CertificateOk));
-                }
-                fetch_opts.remote_callbacks(cbs);
-                fetch_opts.prune(git2::FetchPrune::On);
+                // Once the remote accepted the credentials, an interrupted fetch is attempted again with them.
+                let mut retry = 0;
+                let fetch_result = loop {
+                    let progress = Cell::new(TransferProgress::default());
+                    let mut fetch_opts = git2::FetchOptions::new();
+                    let mut cbs: git2::RemoteCallbacks = callback.clone().into();
+                    if self.project().omit_certificate_check.unwrap_or(false) {
+                        cbs.certificate_check(|_, _| {
+                            Ok(git2::CertificateCheckStatus::CertificateOk)
+                        });
+                    }
+                    cbs.transfer_progress(|stats| {
+                        progress.set(TransferProgress {
+                            objects: stats.received_objects(),
+                            total_objects: stats.total_objects(),
+                            bytes: stats.received_bytes(),
+                        });
+                        task.set_transfer(
+                            stats.received_objects() as u64,
+                            stats.total_objects() as u64,
+                            stats.received_bytes() as u64,
+                        );
+                        // Refs are only updated once all objects arrived, so stopping leaves them as they were.
+                        !task.is_cancelled()
+                    });
+                    fetch_opts.remote_callbacks(cbs);
+                    fetch_opts.prune(if settings.prune {
+                        git2::FetchPrune::On
+                    } else {
+                        git2::FetchPrune::Off
+                    });
+                    fetch_opts.download_tags(match settings.tags {
+                        FetchTags::Auto => git2::AutotagOption::Auto,
+                        FetchTags::All => git2::AutotagOption::All,
+                        FetchTags::None => git2::AutotagOption::None,
+                    });
+                    if let Some(depth) = depth {
+                        fetch_opts.depth(depth);
+                    }
+
+                    let result = remote.fetch(&refspecs, Some(&mut fetch_opts), None);
+                    let progress = progress.get();
+                    match result {
+                        Err(_) if task.is_cancelled() => return task.check_cancelled(),
+                        Err(err) if transfer::is_interruption(&err) && progress.started() => {
+                            retry += 1;
+                            if !transfer::wait_for_retry(&retries, retry, "fetch", Some(progress)) {
+                                return Err(anyhow::Error::from(err)
+                                    .context(format!(
+                                        "fetch was interrupted after receiving {progress}"
+                                    ))
+                                    .context(Code::ProjectGitRemote));
+                            }
+                        }
+                        result => break result,
+                    }
+                };
 
-                match remote.fetch(&[&refspec], Some(&mut fetch_opts), None) {
+                match fetch_result {
                     Ok(()) => {
-                        tracing::info!(project_id = %self.project().id, %refspec, "git fetched");
+                        callback.approve(self, remote.url().unwrap_or_default());
+                        tracing::info!(project_id = %self.project().id, ?refspecs, "git fetched");
+                        remember_remote_head(self.repository(), &remote, remote_name);
                         return Ok(());
                     }
                     Err(err) => match err.class() {
                         git2::ErrorClass::Net | git2::ErrorClass::Http => {
                             tracing::warn!(project_id = %self.project().id, ?err, "fetch failed due to network");
+                            failed_auth.push(format!("{callback}: {}", err.message()));
+                            // Like when HTTP credentials are rejected.
+                            if err.code() == git2::ErrorCode::Auth {
+                                reached_remote = true;
+                            } else {
+                                network_error = Some(err);
+                            }
                             continue;
                         }
                         _ => match err.code() {
                             git2::ErrorCode::Auth => {
+                                reached_remote = true;
                                 tracing::warn!(project_id = %self.project().id, ?err, "fetch failed due to auth");
+                                callback.reject(self, remote.url().unwrap_or_default());
+                                ssh_key_rejected |= matches!(callback, Credential::Ssh(_));
+                                failed_auth.push(format!("{callback}: {}", err.message()));
                                 continue;
                             }
                             _ => {
@@ -419,7 +702,17 @@ impl RepoActionsExt for CommandContext {
             }
         }
 
-        Err(anyhow!("authentication failed")).context(Code::ProjectGitAuth)
+        // Only if no flow got past the network layer it's clear that the remote itself is the problem.
+        // Otherwise, the remote could be reached and it's the credentials that didn't work.
+        if let Some(err) = network_error.filter(|_| !reached_remote) {
+            let code = if transfer::is_offline_message(err.message()) {
+                Code::Offline
+            } else {
+                Code::ProjectGitRemote
+            };
+            return Err(anyhow::Error::from(err).context(code));
+        }
+        Err(auth_failed(&failed_auth, ssh_key_rejected))
     }
 
     fn signatures(&self) -> Result<(git2::Signature, git2::Signature)> {
@@ -428,7 +721,7 @@ impl RepoActionsExt for CommandContext {
         let author = repo
             .author()
             .transpose()?
-            .map(gitbutler_branch::gix_to_git2_signature)
+            .map(|author| gitbutler_branch::gix_to_git2_signature(with_clock_time(author)))
             .transpose()?
             .context("No author is configured in Git")
             .context(Code::AuthorMissing)?;
@@ -437,7 +730,7 @@ impl RepoActionsExt for CommandContext {
         let committer = if config.user_real_comitter()? {
             repo.committer()
                 .transpose()?
-                .map(gix_to_git2_signature)
+                .map(|committer| gix_to_git2_signature(with_clock_time(committer)))
                 .unwrap_or_else(|| gitbutler_branch::signature(SignaturePurpose::Committer))
         } else {
             gitbutler_branch::signature(SignaturePurpose::Committer)
@@ -447,6 +740,16 @@ impl RepoActionsExt for CommandContext {
     }
 }
 
+/// Use the current time of the [clock](gitbutler_time::clock) for `signature` if the clock was replaced,
+/// as Git only knows the system clock. Like for GitButler's own signatures, it takes precedence over
+/// dates set in the environment.
+fn with_clock_time(mut signature: gix::actor::SignatureRef<'_>) -> gix::actor::SignatureRef<'_> {
+    if gitbutler_time::clock::is_overridden() {
+        signature.time = gitbutler_branch::current_time();
+    }
+    signature
+}
+
 type OidFilter = dyn Fn(&git2::Commit) -> Result<bool>;
 
 pub enum LogUntil {
@@ -482,3 +785,83 @@ async fn handle_git_prompt_fetch(prompt: String, askpass: Option<String>) -> Opt
         None
     }
 }
+
+/// Return the refspecs that `remote_name` is fetched with, as the settings of `project` say.
+fn fetch_refspecs(project: &Project, remote_name: &str) -> Vec<String> {
+    let all_branches = || vec![format!("+refs/heads/*:refs/remotes/{remote_name}/*")];
+    match &project.settings.fetch.refspecs {
+        FetchRefspecs::AllBranches => all_branches(),
+        FetchRefspecs::TargetBranch => {
+            match VirtualBranchesHandle::new(project.gb_dir()).get_default_target() {
+                Ok(target) if target.branch.remote() == remote_name => {
+                    let branch = target.branch.branch();
+                    vec![format!(
+                        "+refs/heads/{branch}:refs/remotes/{remote_name}/{branch}"
+                    )]
+                }
+                _ => all_branches(),
+            }
+        }
+        FetchRefspecs::Custom { refspecs } => refspecs
+            .iter()
+            .map(|refspec| refspec.replace("{remote}", remote_name))
+            .collect(),
+    }
+}
+
+/// Point `refs/remotes/<remote_name>/HEAD` to the branch that `remote`, which was just fetched, considers its
+/// default branch, like `git remote set-head --auto` does, so it's noticed once the remote changes it.
+fn remember_remote_head(repo: &git2::Repository, remote: &git2::Remote, remote_name: &str) {
+    let default_branch = match remote.default_branch() {
+        Ok(default_branch) => default_branch,
+        Err(err) => {
+            tracing::debug!(
+                remote_name,
+                ?err,
+                "the default branch of the remote is unknown"
+            );
+            return;
+        }
+    };
+    let Some(branch) = default_branch
+        .as_str()
+        .and_then(|name| name.strip_prefix("refs/heads/"))
+    else {
+        return;
+    };
+    let head = format!("refs/remotes/{remote_name}/HEAD");
+    let target = format!("refs/remotes/{remote_name}/{branch}");
+    let current = repo
+        .find_reference(&head)
+        .ok()
+        .and_then(|reference| reference.symbolic_target().map(ToOwned::to_owned));
+    if current.as_deref() == Some(target.as_str()) || repo.find_reference(&target).is_err() {
+        return;
+    }
+    if let Err(err) = repo.reference_symbolic(&head, &target, true, "fetch: update remote HEAD") {
+        tracing::warn!(remote_name, ?err, "failed to update the HEAD of the remote");
+    }
+}
+
+/// Mark the failure to find any way to authenticate as one of authentication.
+fn help_error(err: HelpError) -> anyhow::Error {
+    match err {
+        HelpError::NoSshAuth(_) => anyhow::Error::from(err).context(errors::NO_SSH_AUTH),
+        err => err.into(),
+    }
+}
+
+/// The error for when each of the ways to authenticate in `failed_auth` failed, naming them along
+/// with why they failed. If `ssh_key_rejected`, the remote rejected at least one SSH key.
+fn auth_failed(failed_auth: &[String], ssh_key_rejected: bool) -> anyhow::Error {
+    let err = if failed_auth.is_empty() {
+        anyhow!("authentication failed")
+    } else {
+        anyhow!("authentication failed with {}", failed_auth.join("; "))
+    };
+    if ssh_key_rejected {
+        err.context(errors::SSH_KEY_REJECTED)
+    } else {
+        err.context(Code::ProjectGitAuth)
+    }
+}
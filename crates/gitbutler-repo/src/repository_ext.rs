@@ -7,9 +7,11 @@ use std::{io::Write, path::Path, process::Stdio, str};
 use anyhow::{anyhow, bail, Context, Result};
 use bstr::BString;
 use git2::{BlameOptions, Tree};
+use gitbutler_command_context::extra_env;
 use gitbutler_commit::{commit_buffer::CommitBuffer, commit_headers::CommitHeadersV2};
 use gitbutler_config::git::{GbConfig, GitConfig};
 use gitbutler_error::error::Code;
+use gitbutler_git::ProcessEnv;
 use gitbutler_reference::{Refname, RemoteRefname};
 use tracing::instrument;
 
@@ -229,22 +231,27 @@ impl RepositoryExt for git2::Repository {
     fn sign_buffer(&self, buffer: &CommitBuffer) -> Result<BString> {
         // check git config for gpg.signingkey
         // TODO: support gpg.ssh.defaultKeyCommand to get the signing key if this value doesn't exist
-        let signing_key = self.config()?.get_string("user.signingkey");
-        if let Ok(signing_key) = signing_key {
-            let sign_format = self.config()?.get_string("gpg.format");
-            let is_ssh = if let Ok(sign_format) = sign_format {
-                sign_format == "ssh"
-            } else {
-                false
-            };
-
+        let config = self.config()?;
+        let sign_format = config
+            .get_string("gpg.format")
+            .unwrap_or_else(|_| "openpgp".into());
+        let is_ssh = sign_format == "ssh";
+        // Like Git, fall back to the committer identity as key for GPG, which finds the key by email.
+        let signing_key = config.get_string("user.signingkey").ok().or_else(|| {
+            if is_ssh {
+                return None;
+            }
+            let signature = self.signature().ok()?;
+            Some(format!("{} <{}>", signature.name()?, signature.email()?))
+        });
+        if let Some(signing_key) = signing_key {
             if is_ssh {
                 // write commit data to a temp file so we can sign it
                 let mut signature_storage = tempfile::NamedTempFile::new()?;
                 signature_storage.write_all(&buffer.as_bstring())?;
                 let buffer_file_to_sign_path = signature_storage.into_temp_path();
 
-                let gpg_program = self.config()?.get_string("gpg.ssh.program");
+                let gpg_program = config.get_string("gpg.ssh.program");
                 let mut gpg_program = gpg_program.unwrap_or("ssh-keygen".to_string());
                 // if cmd is "", use gpg
                 if gpg_program.is_empty() {
@@ -252,6 +259,9 @@ impl RepositoryExt for git2::Repository {
                 }
 
                 let mut cmd = std::process::Command::new(gpg_program);
+                ProcessEnv::new()
+                    .extend(extra_env::of_repository(self))
+                    .apply(&mut cmd);
                 cmd.args(["-Y", "sign", "-n", "git", "-f"]);
 
                 #[cfg(windows)]
@@ -308,15 +318,28 @@ impl RepositoryExt for git2::Repository {
                     bail!("Failed to sign SSH: {}", std_both);
                 }
             } else {
-                // is gpg
-                let gpg_program = self.config()?.get_string("gpg.program");
-                let mut gpg_program = gpg_program.unwrap_or("gpg".to_string());
-                // if cmd is "", use gpg
+                // is gpg, or gpgsm for X.509 certificates
+                let (gpg_program, default_program) = if sign_format == "x509" {
+                    (config.get_string("gpg.x509.program"), "gpgsm")
+                } else {
+                    // `gpg.program` is the historical name of `gpg.openpgp.program`
+                    (
+                        config
+                            .get_string("gpg.openpgp.program")
+                            .or_else(|_| config.get_string("gpg.program")),
+                        "gpg",
+                    )
+                };
+                let mut gpg_program = gpg_program.unwrap_or(default_program.to_string());
+                // if cmd is "", use the default
                 if gpg_program.is_empty() {
-                    gpg_program = "gpg".to_string();
+                    gpg_program = default_program.to_string();
                 }
 
                 let mut cmd = std::process::Command::new(gpg_program);
+                ProcessEnv::new()
+                    .extend(extra_env::of_repository(self))
+                    .apply(&mut cmd);
 
                 cmd.args(["--status-fd=2", "-bsau", &signing_key])
                     //.arg(&signed_storage)
@@ -400,10 +423,12 @@ impl CheckoutTreeBuidler<'_> {
         self
     }
 
+    /// Check out the tree, and replace the pointer files of files stored with LFS with their content.
     pub fn checkout(&mut self) -> Result<()> {
         self.repo
-            .checkout_tree(self.tree.as_object(), Some(&mut self.checkout_builder))
-            .map_err(Into::into)
+            .checkout_tree(self.tree.as_object(), Some(&mut self.checkout_builder))?;
+        crate::lfs::smudge_checked_out_pointers(self.repo, self.tree)
+            .context("failed to check out the content of files stored with LFS")
     }
 }
 
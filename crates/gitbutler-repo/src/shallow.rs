@@ -0,0 +1,121 @@
+//! Work with shallow clones, whose history ends at the commits listed in `.git/shallow` as their parents
+//! weren't fetched.
+//!
+//! Where the history ends, two commits that only have ancestors in common beyond it seem to have none, so
+//! everything that needs their merge base fails. [`merge_base()`] fetches more of the history until it finds
+//! one, unless the project turned [deepening on demand](gitbutler_project::FetchSettings::deepen_on_demand) off.
+use anyhow::Result;
+use gitbutler_command_context::CommandContext;
+use gitbutler_git::Deepen;
+
+use crate::{
+    commit_graph,
+    credentials::Helper,
+    progress::{Progress, Task},
+    RepoActionsExt,
+};
+
+/// The amount of commits the history is deepened by the first time a merge base is missing.
+const INITIAL_DEEPEN: u32 = 64;
+/// The amount of commits from which the whole history is fetched instead of deepening it further.
+const MAX_DEEPEN: u32 = 64 * 1024;
+
+/// Fetch the history of `remote` further back as `deepen` says, reporting the objects received so far
+/// to `progress`.
+///
+/// Does nothing if the repository isn't a shallow clone.
+pub fn deepen(
+    ctx: &CommandContext,
+    remote: &str,
+    deepen: Deepen,
+    askpass: Option<String>,
+    progress: &dyn Progress,
+) -> Result<()> {
+    if !ctx.repository().is_shallow() {
+        return Ok(());
+    }
+    let task = Task::new(progress, format!("Fetching the history of {remote}"));
+    ctx.fetch_deepening(remote, Some(deepen), &Helper::default(), askpass, &task)?;
+    // Commits that seemed to have no parents have them now.
+    commit_graph::forget(ctx.repository());
+    task.finish();
+    Ok(())
+}
+
+/// Return the best common ancestor of `one` and `two` like [`commit_graph::merge_base()`] does, but if
+/// they seem to have none as the history of a shallow clone ends too early, fetch more of it from `remote`
+/// and look again, fetching twice as much each time until the clone is complete.
+///
+/// Fails if they have no common ancestor, or if the project doesn't deepen shallow clones on demand.
+pub fn merge_base(
+    ctx: &CommandContext,
+    remote: &str,
+    one: git2::Oid,
+    two: git2::Oid,
+    progress: &dyn Progress,
+) -> Result<git2::Oid> {
+    let mut reopened = None;
+    let mut by = INITIAL_DEEPEN;
+    loop {
+        // Commits that were read before are kept with the parents they had then, so the history is
+        // read from scratch once it was deepened.
+        let repo = reopened.as_ref().unwrap_or(ctx.repository());
+        let err = match commit_graph::merge_base(repo, one, two) {
+            Ok(merge_base) => return Ok(merge_base),
+            Err(err) => err,
+        };
+        // More history doesn't help with commits that aren't there at all.
+        if !repo.is_shallow()
+            || !ctx.project().settings.fetch.deepen_on_demand
+            || repo.find_commit(one).is_err()
+            || repo.find_commit(two).is_err()
+        {
+            return Err(err);
+        }
+        let how = if by >= MAX_DEEPEN {
+            Deepen::Unshallow
+        } else {
+            Deepen::By(by)
+        };
+        tracing::info!(project_id = %ctx.project().id, %one, %two, ?how, "deepening shallow clone to find merge base");
+        deepen(ctx, remote, how, None, progress)?;
+        reopened = Some(git2::Repository::open(ctx.repository().path())?);
+        by = by.saturating_mul(2);
+    }
+}
+
+/// Return the depth that libgit2 fetches `remote_name` with to deepen the history as `deepen` says.
+///
+/// libgit2 only knows how deep the history is to be from the fetched branches, so the amount of commits
+/// each branch of the remote has already is added for [`Deepen::By`].
+pub(crate) fn depth_to_fetch(repo: &git2::Repository, remote_name: &str, deepen: Deepen) -> i32 {
+    match deepen {
+        // This is what libgit2 takes as the whole history.
+        Deepen::Unshallow => i32::MAX,
+        Deepen::By(by) => {
+            let current = repo
+                .references_glob(&format!("refs/remotes/{remote_name}/*"))
+                .map(|references| {
+                    references
+                        .flatten()
+                        .filter_map(|reference| reference.peel_to_commit().ok())
+                        .map(|commit| depth(&commit))
+                        .max()
+                        .unwrap_or_default()
+                })
+                .unwrap_or_default();
+            i32::try_from(current.saturating_add(by as usize)).unwrap_or(i32::MAX)
+        }
+    }
+}
+
+/// Return the amount of commits from `commit` to where the history ends, following first parents.
+fn depth(commit: &git2::Commit) -> usize {
+    let mut depth = 1;
+    let mut commit = commit.clone();
+    while let Ok(parent) = commit.parent(0) {
+        depth += 1;
+        commit = parent;
+    }
+    depth
+}
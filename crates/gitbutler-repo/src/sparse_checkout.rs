@@ -0,0 +1,156 @@
+//! Check out only some directories of huge repositories, using Git's sparse checkout in cone mode.
+//!
+//! Files outside of the checked out directories are missing from the worktree, but stay in the index
+//! with their skip-worktree bit set. As libgit2 doesn't know about that, diffs of the worktree have
+//! to be limited to the [scope](scope()) of the checkout, or all other files would appear as deleted.
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use bstr::ByteSlice;
+use gitbutler_command_context::CommandContext;
+use gitbutler_git::ProcessEnv;
+
+/// Return the directories that are checked out recursively, or `None` if the whole worktree is checked out.
+pub fn directories(repo: &git2::Repository) -> Result<Option<Vec<PathBuf>>> {
+    let config = repo.config()?;
+    if !config.get_bool("core.sparseCheckout").unwrap_or(false) {
+        return Ok(None);
+    }
+    if !config.get_bool("core.sparseCheckoutCone").unwrap_or(true) {
+        bail!("only sparse checkouts in cone mode are supported");
+    }
+    let patterns_path = repo.path().join("info").join("sparse-checkout");
+    let patterns = match std::fs::read_to_string(&patterns_path) {
+        Ok(patterns) => patterns,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read {}", patterns_path.display()))
+        }
+    };
+    Ok(Some(parse_cone_patterns(&patterns)))
+}
+
+/// Return the worktree-relative paths that are checked out, or `None` if the whole worktree is checked out.
+///
+/// These are the [directories](directories()), along with the files directly inside the root of the
+/// worktree and inside each parent of these directories, as cone mode always checks these out.
+pub fn scope(repo: &git2::Repository) -> Result<Option<Vec<PathBuf>>> {
+    let Some(directories) = directories(repo)? else {
+        return Ok(None);
+    };
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("sparse checkouts need a repository with a worktree"))?;
+
+    let mut parents = BTreeSet::from([PathBuf::new()]);
+    for directory in &directories {
+        parents.extend(directory.ancestors().skip(1).map(Path::to_owned));
+    }
+    let mut scope = BTreeSet::new();
+    // Deleted files are only in the index, new ones only in the worktree.
+    for entry in repo.index()?.iter() {
+        let path = entry.path.to_path_lossy();
+        if path
+            .parent()
+            .map_or(false, |parent| parents.contains(parent))
+        {
+            scope.insert(path.into_owned());
+        }
+    }
+    for parent in parents {
+        let entries = match std::fs::read_dir(workdir.join(&parent)) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                continue;
+            }
+            scope.insert(parent.join(entry.file_name()));
+        }
+    }
+    scope.extend(directories);
+    Ok(Some(scope.into_iter().collect()))
+}
+
+/// Limit the worktree to `directories`, relative to the worktree, and remove all files outside of them
+/// that don't have changes.
+pub fn set(ctx: &CommandContext, directories: &[PathBuf]) -> Result<()> {
+    let mut args = vec!["set".into(), "--cone".into(), "--".into()];
+    args.extend(directories.iter().map(|d| d.as_os_str().to_owned()));
+    run(ctx, &args)
+}
+
+/// Check out `directories`, relative to the worktree, in addition to those that already are.
+pub fn add(ctx: &CommandContext, directories: &[PathBuf]) -> Result<()> {
+    let mut args = vec!["add".into(), "--".into()];
+    args.extend(directories.iter().map(|d| d.as_os_str().to_owned()));
+    run(ctx, &args)
+}
+
+/// Check out the whole worktree again.
+pub fn disable(ctx: &CommandContext) -> Result<()> {
+    run(ctx, &["disable".into()])
+}
+
+/// Remove the files outside of the checked out directories that were written by a checkout, as libgit2
+/// writes all files and drops the skip-worktree bits when it rewrites the index.
+///
+/// Does nothing if the whole worktree is checked out.
+pub fn reapply(ctx: &CommandContext) -> Result<()> {
+    if directories(ctx.repository())?.is_none() {
+        return Ok(());
+    }
+    run(ctx, &["reapply".into()])
+}
+
+fn run(ctx: &CommandContext, args: &[std::ffi::OsString]) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.arg("sparse-checkout")
+        .args(args)
+        .current_dir(ctx.project().worktree_path());
+    ProcessEnv::new()
+        .extend(ctx.project().extra_env.clone())
+        .apply(&mut cmd);
+    let output = cmd.output().context("failed to run git sparse-checkout")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git sparse-checkout {} failed: {}",
+            args.first()
+                .map(|arg| arg.to_string_lossy())
+                .unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Return the directories checked out recursively by cone-mode `patterns`.
+///
+/// Cone mode writes `/dir/` for each directory it includes, and follows it with `!/dir/*/` if only
+/// the files directly inside of it are included, as it does for parents of included directories.
+fn parse_cone_patterns(patterns: &str) -> Vec<PathBuf> {
+    let lines: Vec<_> = patterns
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    let only_files: BTreeSet<_> = lines
+        .iter()
+        .filter_map(|line| line.strip_prefix('!')?.strip_suffix("*/"))
+        .collect();
+    lines
+        .iter()
+        .filter(|line| !line.starts_with('!') && **line != "/*" && !only_files.contains(*line))
+        .filter_map(|line| {
+            let directory = line.strip_prefix('/')?.strip_suffix('/')?;
+            Some(PathBuf::from(directory))
+        })
+        .collect()
+}
@@ -0,0 +1,141 @@
+//! Attempting pushes and fetches again after the network interrupted them.
+//!
+//! Git can't continue a pack where it stopped, so each retry starts the transfer over, but with the
+//! credentials the remote already accepted instead of going through all of them again.
+use std::fmt;
+
+use anyhow::Result;
+use gitbutler_error::error::Code;
+use gitbutler_project::TransferRetries;
+
+/// How far a transfer got, as reported by the transport.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TransferProgress {
+    pub objects: usize,
+    pub total_objects: usize,
+    pub bytes: usize,
+}
+
+impl TransferProgress {
+    /// Return `true` if the remote accepted the connection and objects started to flow.
+    pub(crate) fn started(&self) -> bool {
+        self.total_objects > 0
+    }
+}
+
+impl fmt::Display for TransferProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = (self.objects * 100)
+            .checked_div(self.total_objects)
+            .unwrap_or_default();
+        write!(
+            f,
+            "{} of {} objects ({percent}%, {:.1} MiB)",
+            self.objects,
+            self.total_objects,
+            self.bytes as f64 / (1024.0 * 1024.0)
+        )
+    }
+}
+
+/// Return `true` if `err` of libgit2 means the connection broke down, rather than the remote
+/// refusing the transfer.
+pub(crate) fn is_interruption(err: &git2::Error) -> bool {
+    matches!(
+        err.class(),
+        git2::ErrorClass::Net
+            | git2::ErrorClass::Http
+            | git2::ErrorClass::Ssh
+            | git2::ErrorClass::Os
+    )
+}
+
+/// Return `true` if the `message` of a failed `git` invocation says the connection broke down.
+pub(crate) fn is_interruption_message(message: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "the remote end hung up unexpectedly",
+        "early eof",
+        "rpc failed",
+        "connection reset",
+        "connection timed out",
+        "operation timed out",
+        "broken pipe",
+        "unexpected disconnect",
+    ];
+    let message = message.to_lowercase();
+    MARKERS.iter().any(|marker| message.contains(marker))
+}
+
+/// Return `true` if the `message` of a failed transfer, whether by libgit2 or `git`, says the network
+/// is unavailable, which is the case if host names can't be resolved or there is no route to the remote.
+pub(crate) fn is_offline_message(message: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "failed to resolve address",
+        "could not resolve host",
+        "temporary failure in name resolution",
+        "name or service not known",
+        "nodename nor servname provided",
+        "no such host is known",
+        "network is unreachable",
+        "network is down",
+        "no route to host",
+    ];
+    let message = message.to_lowercase();
+    MARKERS.iter().any(|marker| message.contains(marker))
+}
+
+/// Mark `err` of a failed `git` invocation as [`Code::Offline`] if it says the network is unavailable.
+pub(crate) fn offline_or(err: anyhow::Error) -> anyhow::Error {
+    if is_offline_message(&format!("{err:#}")) {
+        err.context(Code::Offline)
+    } else {
+        err
+    }
+}
+
+/// Wait before the retry numbered `retry` of `operation`, like `push`, if `retries` allow for it,
+/// and return `false` if they don't.
+pub(crate) fn wait_for_retry(
+    retries: &TransferRetries,
+    retry: u32,
+    operation: &str,
+    progress: Option<TransferProgress>,
+) -> bool {
+    if retry > retries.max_retries {
+        return false;
+    }
+    let delay = retries.backoff(retry);
+    match progress {
+        Some(progress) => {
+            tracing::warn!(retry, ?delay, %progress, "{operation} was interrupted, retrying")
+        }
+        None => tracing::warn!(retry, ?delay, "{operation} was interrupted, retrying"),
+    }
+    std::thread::sleep(delay);
+    true
+}
+
+/// Run `attempt` of `operation` through `git` again as `retries` allow, as long as it fails because
+/// the connection broke down.
+pub(crate) fn retry_interrupted(
+    retries: &TransferRetries,
+    operation: &str,
+    mut attempt: impl FnMut() -> Result<()>,
+) -> Result<()> {
+    let mut retry = 0;
+    loop {
+        let err = match attempt() {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        if !is_interruption_message(&format!("{err:#}")) {
+            return Err(err);
+        }
+        retry += 1;
+        if !wait_for_retry(retries, retry, operation, None) {
+            return Err(err.context(format!(
+                "{operation} was interrupted {retry} times, giving up"
+            )));
+        }
+    }
+}
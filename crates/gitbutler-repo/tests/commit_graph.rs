@@ -0,0 +1,110 @@
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::{Project, Settings};
+use gitbutler_repo::commit_graph;
+use gitbutler_testsupport::test_repository;
+
+fn commit(repo: &git2::Repository, parents: &[git2::Oid]) -> git2::Oid {
+    let signature = git2::Signature::now("test", "test@example.com").unwrap();
+    let tree = repo
+        .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+        .unwrap();
+    let parents: Vec<_> = parents
+        .iter()
+        .map(|id| repo.find_commit(*id).unwrap())
+        .collect();
+    repo.commit(
+        None,
+        &signature,
+        &signature,
+        "commit",
+        &tree,
+        &parents.iter().collect::<Vec<_>>(),
+    )
+    .unwrap()
+}
+
+/// Assert that the answers for all pairs of `commits` are the ones of libgit2.
+fn assert_same_as_git2(repo: &git2::Repository, commits: &[git2::Oid]) {
+    for one in commits {
+        for two in commits {
+            assert_eq!(
+                commit_graph::merge_base(repo, *one, *two).unwrap(),
+                repo.merge_base(*one, *two).unwrap(),
+                "merge base of {one} and {two}"
+            );
+            assert_eq!(
+                commit_graph::ahead_behind(repo, *one, *two).unwrap(),
+                repo.graph_ahead_behind(*one, *two).unwrap(),
+                "ahead and behind of {one} and {two}"
+            );
+        }
+    }
+}
+
+/// Return a history where `main` and `feature` fork, get merged, and move on.
+fn history(repo: &git2::Repository) -> Vec<git2::Oid> {
+    let root = commit(repo, &[]);
+    let main_1 = commit(repo, &[root]);
+    let main_2 = commit(repo, &[main_1]);
+    let main_3 = commit(repo, &[main_2]);
+    let feature_1 = commit(repo, &[main_1]);
+    let feature_2 = commit(repo, &[feature_1]);
+    let merge = commit(repo, &[main_3, feature_2]);
+    let after_merge = commit(repo, &[merge]);
+    let feature_3 = commit(repo, &[feature_2]);
+    vec![
+        root,
+        main_1,
+        main_3,
+        feature_2,
+        merge,
+        after_merge,
+        feature_3,
+    ]
+}
+
+#[test]
+fn answers_like_git2_without_commit_graph() {
+    let (repo, _tmp) = test_repository();
+    let commits = history(&repo);
+    assert_same_as_git2(&repo, &commits);
+}
+
+#[test]
+fn answers_like_git2_with_commit_graph() {
+    let (repo, _tmp) = test_repository();
+    let mut commits = history(&repo);
+    repo.branch("feature", &repo.find_commit(commits[6]).unwrap(), true)
+        .unwrap();
+    repo.branch("merged", &repo.find_commit(commits[5]).unwrap(), true)
+        .unwrap();
+
+    let project = Project {
+        path: repo.workdir().unwrap().to_path_buf(),
+        settings: Settings {
+            write_commit_graph: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let ctx = CommandContext::open(&project).unwrap();
+    assert!(commit_graph::write_if_missing(&ctx).unwrap());
+    assert!(
+        !commit_graph::write_if_missing(&ctx).unwrap(),
+        "an existing commit-graph is kept"
+    );
+    assert_same_as_git2(&repo, &commits);
+
+    // Commits that aren't in the commit-graph yet are looked up.
+    commits.push(commit(&repo, &[commits[5], commits[6]]));
+    assert_same_as_git2(&repo, &commits);
+}
+
+#[test]
+fn unrelated_histories_have_no_merge_base() {
+    let (repo, _tmp) = test_repository();
+    let one = commit(&repo, &[]);
+    let two = commit(&repo, &[]);
+    assert!(commit_graph::merge_base(&repo, one, two).is_err());
+    assert_eq!(commit_graph::ahead_behind(&repo, one, two).unwrap(), (1, 1));
+}
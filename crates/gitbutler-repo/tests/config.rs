@@ -0,0 +1,27 @@
+use gitbutler_config::git::GitConfig;
+use gitbutler_testsupport::test_repository;
+
+#[test]
+fn sign_commits_falls_back_to_commit_gpgsign() {
+    let (repo, _tmp) = test_repository();
+    let mut config = repo
+        .config()
+        .unwrap()
+        .open_level(git2::ConfigLevel::Local)
+        .unwrap();
+    assert_eq!(
+        repo.gb_config().unwrap().sign_commits,
+        Some(false),
+        "test repositories disable `commit.gpgSign`"
+    );
+
+    config.set_bool("commit.gpgSign", true).unwrap();
+    assert_eq!(repo.gb_config().unwrap().sign_commits, Some(true));
+
+    config.set_bool("gitbutler.signCommits", false).unwrap();
+    assert_eq!(
+        repo.gb_config().unwrap().sign_commits,
+        Some(false),
+        "our own setting takes precedence"
+    );
+}
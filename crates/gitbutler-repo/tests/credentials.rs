@@ -2,7 +2,7 @@ use std::{path::PathBuf, str};
 
 use gitbutler_command_context::CommandContext;
 use gitbutler_project as projects;
-use gitbutler_repo::credentials::{Credential, Helper, SshCredential};
+use gitbutler_repo::credentials::{Credential, HelpError, Helper, HttpsCredential, SshCredential};
 use gitbutler_testsupport::{temp_dir, test_repository};
 use gitbutler_user as users;
 
@@ -11,10 +11,19 @@ struct TestCase<'a> {
     remote_url: &'a str,
     with_github_login: bool,
     preferred_key: projects::AuthKey,
+    ssh_auth_order: Vec<projects::SshAuthMethod>,
+    /// The `credential.helper` to configure in the repository.
+    credential_helper: Option<&'a str>,
+    /// The socket of the SSH agent to use instead of the one `SSH_AUTH_SOCK` points to.
+    ssh_agent_socket: Option<&'a str>,
 }
 
 impl TestCase<'_> {
     fn run(&self) -> Vec<(String, Vec<Credential>)> {
+        self.try_run().unwrap()
+    }
+
+    fn try_run(&self) -> Result<Vec<(String, Vec<Credential>)>, HelpError> {
         let local_app_data = temp_dir();
 
         gitbutler_testsupport::secrets::setup_blackhole_store();
@@ -27,21 +36,32 @@ impl TestCase<'_> {
         .expect("valid v1 sample user");
         users.set_user(&user).unwrap();
 
-        let helper = Helper::default();
+        let mut helper = Helper::default();
+        if let Some(socket) = self.ssh_agent_socket {
+            helper = helper.with_ssh_agent_socket(socket);
+        }
 
         let (repo, _tmp) = test_repository();
         repo.remote("origin", self.remote_url).unwrap();
+        if let Some(credential_helper) = self.credential_helper {
+            repo.config()
+                .unwrap()
+                .set_str("credential.helper", credential_helper)
+                .unwrap();
+        }
         let project = projects::Project {
             path: repo.workdir().unwrap().to_path_buf(),
             preferred_key: self.preferred_key.clone(),
+            ssh_auth_order: self.ssh_auth_order.clone(),
             ..Default::default()
         };
         let ctx = CommandContext::open(&project).unwrap();
 
-        let flow = helper.help(&ctx, "origin").unwrap();
-        flow.into_iter()
+        let flow = helper.help(&ctx, "origin")?;
+        Ok(flow
+            .into_iter()
             .map(|(remote, credentials)| (remote.url().as_ref().unwrap().to_string(), credentials))
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>())
     }
 }
 
@@ -59,6 +79,7 @@ mod not_github {
                 preferred_key: projects::AuthKey::Local {
                     private_key_path: PathBuf::from("/tmp/id_rsa"),
                 },
+                ..Default::default()
             };
             let flow = test_case.run();
             assert_eq!(flow.len(), 1);
@@ -83,6 +104,7 @@ mod not_github {
                 preferred_key: projects::AuthKey::Local {
                     private_key_path: PathBuf::from("/tmp/id_rsa"),
                 },
+                ..Default::default()
             };
             let flow = test_case.run();
             assert_eq!(flow.len(), 1);
@@ -101,6 +123,35 @@ mod not_github {
     }
 }
 
+mod credential_helper {
+    use super::*;
+
+    #[test]
+    fn https_credentials_come_from_git() {
+        let test_case = TestCase {
+            remote_url: "https://gitlab.com/test-gitbutler/test.git",
+            preferred_key: projects::AuthKey::GitCredentialsHelper,
+            credential_helper: Some(
+                "!f() { test \"$1\" = get && printf 'username=me\\npassword=token\\n'; }; f",
+            ),
+            ..Default::default()
+        };
+        let flow = test_case.run();
+        assert_eq!(flow.len(), 1);
+        assert_eq!(
+            flow[0].0,
+            "https://gitlab.com/test-gitbutler/test.git".to_string(),
+        );
+        assert_eq!(
+            flow[0].1,
+            vec![Credential::Https(HttpsCredential::CredentialHelper {
+                username: "me".into(),
+                password: "token".into(),
+            })]
+        );
+    }
+}
+
 mod github {
     use super::*;
 
@@ -118,6 +169,7 @@ mod github {
                     preferred_key: projects::AuthKey::Local {
                         private_key_path: PathBuf::from("/tmp/id_rsa"),
                     },
+                    ..Default::default()
                 };
                 let flow = test_case.run();
                 assert_eq!(flow.len(), 1);
@@ -142,6 +194,7 @@ mod github {
                     preferred_key: projects::AuthKey::Local {
                         private_key_path: PathBuf::from("/tmp/id_rsa"),
                     },
+                    ..Default::default()
                 };
                 let flow = test_case.run();
                 assert_eq!(flow.len(), 1);
@@ -160,3 +213,51 @@ mod github {
         }
     }
 }
+
+mod ssh_auth_order {
+    use super::*;
+
+    #[test]
+    fn follows_the_configured_order() {
+        let test_case = TestCase {
+            remote_url: "git@gitlab.com:test-gitbutler/test.git",
+            ssh_agent_socket: Some("/tmp/ssh-agent.sock"),
+            preferred_key: projects::AuthKey::Local {
+                private_key_path: PathBuf::from("/tmp/id_rsa"),
+            },
+            ssh_auth_order: vec![
+                projects::SshAuthMethod::Agent,
+                projects::SshAuthMethod::KeyFile,
+            ],
+            ..Default::default()
+        };
+        let flow = test_case.run();
+        assert_eq!(flow.len(), 1);
+        assert_eq!(
+            flow[0].1,
+            vec![
+                Credential::Ssh(SshCredential::Agent),
+                Credential::Ssh(SshCredential::Keyfile {
+                    key_path: PathBuf::from("/tmp/id_rsa"),
+                    passphrase: None,
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn names_the_mechanisms_that_are_unavailable() {
+        let test_case = TestCase {
+            remote_url: "git@gitlab.com:test-gitbutler/test.git",
+            preferred_key: projects::AuthKey::SshAgent,
+            ssh_auth_order: vec![projects::SshAuthMethod::KeyFile],
+            ..Default::default()
+        };
+        let err = test_case.try_run().unwrap_err();
+        assert!(matches!(err, HelpError::NoSshAuth(_)));
+        assert_eq!(
+            err.to_string(),
+            "no way to authenticate over SSH is available: there is no key file for the project"
+        );
+    }
+}
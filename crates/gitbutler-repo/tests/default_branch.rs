@@ -0,0 +1,72 @@
+use gitbutler_reference::RemoteRefname;
+use gitbutler_repo::{guess_default_branch, DefaultBranchSource, RemoteDefaultBranch};
+use gitbutler_testsupport::test_repository;
+
+fn add_remote_branch(repo: &git2::Repository, name: &str) {
+    let head = repo.head().unwrap().target().unwrap();
+    repo.reference(&format!("refs/remotes/origin/{name}"), head, true, "")
+        .unwrap();
+}
+
+#[test]
+fn nothing_to_guess_from() {
+    let (repo, _tmp) = test_repository();
+    assert_eq!(guess_default_branch(&repo, "origin").unwrap(), None);
+}
+
+#[test]
+fn common_name_is_preferred() {
+    let (repo, _tmp) = test_repository();
+    add_remote_branch(&repo, "feature");
+    add_remote_branch(&repo, "master");
+    add_remote_branch(&repo, "main");
+
+    assert_eq!(
+        guess_default_branch(&repo, "origin").unwrap(),
+        Some(RemoteDefaultBranch {
+            branch: RemoteRefname::new("origin", "main"),
+            source: DefaultBranchSource::Guess,
+        })
+    );
+    assert_eq!(
+        guess_default_branch(&repo, "upstream").unwrap(),
+        None,
+        "only branches of the remote are considered"
+    );
+}
+
+#[test]
+fn only_branch_is_the_default() {
+    let (repo, _tmp) = test_repository();
+    add_remote_branch(&repo, "production");
+
+    assert_eq!(
+        guess_default_branch(&repo, "origin").unwrap(),
+        Some(RemoteDefaultBranch {
+            branch: RemoteRefname::new("origin", "production"),
+            source: DefaultBranchSource::Guess,
+        })
+    );
+}
+
+#[test]
+fn tracking_head_wins() {
+    let (repo, _tmp) = test_repository();
+    add_remote_branch(&repo, "main");
+    add_remote_branch(&repo, "develop");
+    repo.reference_symbolic(
+        "refs/remotes/origin/HEAD",
+        "refs/remotes/origin/develop",
+        true,
+        "",
+    )
+    .unwrap();
+
+    assert_eq!(
+        guess_default_branch(&repo, "origin").unwrap(),
+        Some(RemoteDefaultBranch {
+            branch: RemoteRefname::new("origin", "develop"),
+            source: DefaultBranchSource::TrackingRef,
+        })
+    );
+}
@@ -0,0 +1,114 @@
+#![cfg(unix)]
+use std::{os::unix::fs::PermissionsExt, path::Path};
+
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::{HookSettings, Project, Settings};
+use gitbutler_repo::hooks::{self, Hook};
+use gitbutler_testsupport::test_repository;
+
+fn write_hook(dir: &Path, name: &str, script: &str) {
+    std::fs::create_dir_all(dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn context(repo: &git2::Repository, hooks: HookSettings) -> CommandContext {
+    let project = Project {
+        path: repo.workdir().unwrap().to_path_buf(),
+        settings: Settings {
+            hooks,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    CommandContext::open(&project).unwrap()
+}
+
+#[test]
+fn hooks_are_found_in_configured_directory_first() {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap().to_owned();
+    write_hook(&repo.path().join("hooks"), "post-merge", "exit 0");
+    write_hook(&workdir.join(".husky"), "pre-push", "exit 0");
+    assert_eq!(
+        hooks::find(&repo, "post-merge"),
+        Some(repo.path().join("hooks").join("post-merge"))
+    );
+    assert_eq!(
+        hooks::find(&repo, "pre-push"),
+        Some(workdir.join(".husky").join("pre-push"))
+    );
+    assert_eq!(hooks::find(&repo, "post-checkout"), None);
+
+    write_hook(&workdir.join("custom-hooks"), "post-merge", "exit 0");
+    repo.config()
+        .unwrap()
+        .set_str("core.hooksPath", "custom-hooks")
+        .unwrap();
+    assert_eq!(
+        hooks::find(&repo, "post-merge"),
+        Some(workdir.join("custom-hooks").join("post-merge"))
+    );
+}
+
+#[test]
+fn failing_pre_push_rejects_with_output() {
+    let (repo, _tmp) = test_repository();
+    write_hook(
+        &repo.path().join("hooks"),
+        "pre-push",
+        "read line; echo \"refusing $1 $line\"; echo oops >&2; exit 1",
+    );
+    let ctx = context(&repo, HookSettings::default());
+
+    let err = hooks::run(&ctx, Hook::PrePush, &["origin", "url"], b"refs/heads/a\n").unwrap_err();
+    assert_eq!(err.downcast_ref::<Code>(), Some(&Code::HookFailed));
+    let message = format!("{err:#}");
+    assert!(
+        message.contains("refusing origin refs/heads/a"),
+        "{message}"
+    );
+    assert!(message.contains("oops"), "{message}");
+}
+
+#[test]
+fn failing_post_hooks_are_not_errors() {
+    let (repo, _tmp) = test_repository();
+    write_hook(&repo.path().join("hooks"), "post-merge", "exit 1");
+    let ctx = context(&repo, HookSettings::default());
+
+    hooks::run(&ctx, Hook::PostMerge, &["0"], &[]).unwrap();
+}
+
+#[test]
+fn disabled_hooks_do_not_run() {
+    let (repo, _tmp) = test_repository();
+    write_hook(&repo.path().join("hooks"), "pre-push", "exit 1");
+    let ctx = context(
+        &repo,
+        HookSettings {
+            disabled: ["pre-push".to_owned()].into(),
+            ..Default::default()
+        },
+    );
+
+    hooks::run(&ctx, Hook::PrePush, &["origin", "url"], &[]).unwrap();
+}
+
+#[test]
+fn hooks_that_run_too_long_are_killed() {
+    let (repo, _tmp) = test_repository();
+    write_hook(&repo.path().join("hooks"), "pre-push", "exec sleep 10");
+    let ctx = context(
+        &repo,
+        HookSettings {
+            timeout_secs: 0,
+            ..Default::default()
+        },
+    );
+
+    let err = hooks::run(&ctx, Hook::PrePush, &["origin", "url"], &[]).unwrap_err();
+    assert!(format!("{err:#}").contains("timed out"), "{err:#}");
+}
@@ -0,0 +1,110 @@
+use gitbutler_repo::merge_drivers;
+use gitbutler_testsupport::test_repository;
+
+fn tree<'repo>(repo: &'repo git2::Repository, files: &[(&str, &str)]) -> git2::Tree<'repo> {
+    let mut builder = repo.treebuilder(None).unwrap();
+    for (name, content) in files {
+        let blob = repo.blob(content.as_bytes()).unwrap();
+        builder.insert(name, blob, 0o100644).unwrap();
+    }
+    repo.find_tree(builder.write().unwrap()).unwrap()
+}
+
+fn merged_content(repo: &git2::Repository, index: &git2::Index, path: &str) -> String {
+    let entry = index.get_path(std::path::Path::new(path), 0).unwrap();
+    String::from_utf8(repo.find_blob(entry.id).unwrap().content().to_vec()).unwrap()
+}
+
+#[test]
+fn files_are_merged_with_their_driver() {
+    let (repo, _tmp) = test_repository();
+    std::fs::write(
+        repo.workdir().unwrap().join(".gitattributes"),
+        "*.lock merge=union\n*.bin -merge\n*.custom merge=mine\n",
+    )
+    .unwrap();
+    repo.config()
+        .unwrap()
+        .open_level(git2::ConfigLevel::Local)
+        .unwrap()
+        .set_str("merge.mine.driver", "printf 'resolved %P' > %A")
+        .unwrap();
+
+    let ancestor = tree(
+        &repo,
+        &[
+            ("file.lock", "one\n"),
+            ("file.bin", "1\n2\n3\n"),
+            ("file.custom", "base\n"),
+            ("file.txt", "1\n2\n3\n"),
+        ],
+    );
+    let ours = tree(
+        &repo,
+        &[
+            ("file.lock", "one\nours\n"),
+            ("file.bin", "x\n2\n3\n"),
+            ("file.custom", "ours\n"),
+            ("file.txt", "x\n2\n3\n"),
+        ],
+    );
+    let theirs = tree(
+        &repo,
+        &[
+            ("file.lock", "one\ntheirs\n"),
+            ("file.bin", "1\n2\ny\n"),
+            ("file.custom", "theirs\n"),
+            ("file.txt", "1\n2\ny\n"),
+        ],
+    );
+
+    let index = merge_drivers::merge_trees(&repo, &ancestor, &ours, &theirs).unwrap();
+    assert_eq!(
+        merged_content(&repo, &index, "file.lock"),
+        "one\nours\ntheirs\n",
+        "union keeps the lines of both sides"
+    );
+    assert_eq!(
+        merged_content(&repo, &index, "file.custom"),
+        "resolved file.custom",
+        "the configured driver merges the file"
+    );
+    assert_eq!(
+        merged_content(&repo, &index, "file.txt"),
+        "x\n2\ny\n",
+        "other files are merged as text"
+    );
+    let conflicts: Vec<_> = index
+        .conflicts()
+        .unwrap()
+        .map(|conflict| conflict.unwrap().our.unwrap().path)
+        .collect();
+    assert_eq!(
+        conflicts,
+        [b"file.bin".to_vec()],
+        "files that mustn't be merged conflict even if their changes don't overlap"
+    );
+}
+
+#[test]
+fn failing_drivers_leave_a_conflict() {
+    let (repo, _tmp) = test_repository();
+    std::fs::write(
+        repo.workdir().unwrap().join(".gitattributes"),
+        "*.custom merge=failing\n",
+    )
+    .unwrap();
+    repo.config()
+        .unwrap()
+        .open_level(git2::ConfigLevel::Local)
+        .unwrap()
+        .set_str("merge.failing.driver", "false")
+        .unwrap();
+
+    let ancestor = tree(&repo, &[("file.custom", "1\n2\n3\n")]);
+    let ours = tree(&repo, &[("file.custom", "x\n2\n3\n")]);
+    let theirs = tree(&repo, &[("file.custom", "1\n2\ny\n")]);
+
+    let index = merge_drivers::merge_trees(&repo, &ancestor, &ours, &theirs).unwrap();
+    assert!(index.has_conflicts());
+}
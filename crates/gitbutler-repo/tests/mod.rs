@@ -1 +1,11 @@
+mod commit_graph;
+mod config;
 mod credentials;
+mod default_branch;
+mod hooks;
+mod merge_drivers;
+mod partial_clone;
+mod permissions;
+mod progress;
+mod repo_ext;
+mod sparse_checkout;
@@ -0,0 +1,38 @@
+use gitbutler_repo::partial_clone;
+use gitbutler_testsupport::test_repository;
+
+fn set_config(repo: &git2::Repository, key: &str, value: &str) {
+    repo.config()
+        .unwrap()
+        .open_level(git2::ConfigLevel::Local)
+        .unwrap()
+        .set_str(key, value)
+        .unwrap();
+}
+
+#[test]
+fn full_clones_have_no_promisor_remote() {
+    let (repo, _tmp) = test_repository();
+    assert_eq!(partial_clone::promisor_remote(&repo).unwrap(), None);
+}
+
+#[test]
+fn promisor_remote_of_partial_clone() {
+    let (repo, _tmp) = test_repository();
+    set_config(&repo, "extensions.partialClone", "upstream");
+    assert_eq!(
+        partial_clone::promisor_remote(&repo).unwrap().as_deref(),
+        Some("upstream")
+    );
+}
+
+#[test]
+fn promisor_remote_from_remote_config() {
+    let (repo, _tmp) = test_repository();
+    set_config(&repo, "remote.other.promisor", "false");
+    set_config(&repo, "remote.origin.promisor", "true");
+    assert_eq!(
+        partial_clone::promisor_remote(&repo).unwrap().as_deref(),
+        Some("origin")
+    );
+}
@@ -0,0 +1,67 @@
+#![cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+use gitbutler_command_context::CommandContext;
+use gitbutler_error::error::Code;
+use gitbutler_project::Project;
+use gitbutler_repo::permissions::probe_writable;
+use gitbutler_testsupport::test_repository;
+
+#[test]
+fn writable_repositories_pass() {
+    let (repo, _tmp) = test_repository();
+    let project = Project {
+        path: repo.workdir().unwrap().to_path_buf(),
+        ..Default::default()
+    };
+    let ctx = CommandContext::open(&project).unwrap();
+    probe_writable(&ctx).unwrap();
+}
+
+#[test]
+fn probing_leaves_no_files_behind() {
+    let (repo, _tmp) = test_repository();
+    let project = Project {
+        path: repo.workdir().unwrap().to_path_buf(),
+        ..Default::default()
+    };
+    let ctx = CommandContext::open(&project).unwrap();
+    let entries = |dir: &std::path::Path| {
+        let mut names: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        names
+    };
+    let refs = repo.path().join("refs");
+    let (worktree_before, refs_before) = (entries(&project.path), entries(&refs));
+
+    probe_writable(&ctx).unwrap();
+    assert_eq!(entries(&project.path), worktree_before);
+    assert_eq!(entries(&refs), refs_before);
+}
+
+#[test]
+fn read_only_git_dir_is_reported_with_path() {
+    let (repo, _tmp) = test_repository();
+    let project = Project {
+        path: repo.workdir().unwrap().to_path_buf(),
+        ..Default::default()
+    };
+    let ctx = CommandContext::open(&project).unwrap();
+    let refs = repo.path().join("refs");
+    std::fs::set_permissions(&refs, std::fs::Permissions::from_mode(0o555)).unwrap();
+    if tempfile::tempfile_in(&refs).is_ok() {
+        // Privileged users can write anyway, so there is nothing to detect.
+        std::fs::set_permissions(&refs, std::fs::Permissions::from_mode(0o755)).unwrap();
+        return;
+    }
+
+    let err = probe_writable(&ctx).unwrap_err();
+    std::fs::set_permissions(&refs, std::fs::Permissions::from_mode(0o755)).unwrap();
+    assert_eq!(err.downcast_ref::<Code>(), Some(&Code::PermissionDenied));
+    let message = format!("{err:#}");
+    assert!(message.contains(&refs.display().to_string()), "{message}");
+    assert!(message.contains("mode 555"), "{message}");
+}
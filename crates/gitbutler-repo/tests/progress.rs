@@ -0,0 +1,101 @@
+use gitbutler_command_context::{cancellation, CancellationToken};
+use gitbutler_error::error::{AnyhowContextExt, Code};
+use gitbutler_repo::progress::{self, Progress, Task, TaskState};
+
+#[test]
+fn nested_tasks_report_to_the_channel() {
+    let (progress, updates) = progress::channel();
+    {
+        let task = Task::new(&progress, "Fetching remotes");
+        task.set_total(2);
+        let child = task.child("Fetching origin");
+        child.set_transfer(5, 10, 2048);
+        child.finish();
+        task.inc();
+        task.finish();
+    }
+    drop(progress);
+    let updates: Vec<_> = updates.into_iter().collect();
+
+    let root = updates[0].task_id;
+    assert_eq!(updates[0].parent_id, None);
+    assert_eq!(updates[0].state, TaskState::Running);
+    let child_updates: Vec<_> = updates
+        .iter()
+        .filter(|update| update.parent_id == Some(root))
+        .collect();
+    assert_eq!(child_updates[0].name, "Fetching origin");
+    let finished = child_updates.last().unwrap();
+    assert_eq!(finished.state, TaskState::Finished);
+    assert_eq!(
+        (finished.done, finished.total, finished.percent),
+        (10, Some(10), Some(100)),
+        "finished tasks are complete"
+    );
+    assert_eq!(finished.bytes, Some(2048));
+
+    let last = updates.last().unwrap();
+    assert_eq!(last.task_id, root);
+    assert_eq!(last.state, TaskState::Finished);
+    assert_eq!(last.percent, Some(100));
+}
+
+#[test]
+fn unfinished_tasks_fail_or_are_cancelled() {
+    let (progress, updates) = progress::channel();
+    drop(Task::new(&progress, "Pushing"));
+    assert_eq!(updates.try_iter().last().unwrap().state, TaskState::Failed);
+
+    progress.cancel();
+    assert!(progress.is_cancelled());
+    let task = Task::new(&progress, "Pushing");
+    let err = task.check_cancelled().unwrap_err();
+    assert_eq!(
+        err.custom_context().map(|ctx| ctx.code),
+        Some(Code::Cancelled)
+    );
+    drop(task);
+    assert_eq!(
+        updates.try_iter().last().unwrap().state,
+        TaskState::Cancelled
+    );
+}
+
+#[test]
+fn tasks_follow_the_current_token() {
+    let (progress, _updates) = progress::channel();
+    let token = CancellationToken::new();
+    cancellation::scoped(&token, || {
+        let task = Task::new(&progress, "Updating workspace");
+        assert!(!task.is_cancelled());
+        token.cancel();
+        assert!(task.is_cancelled());
+        assert!(
+            !progress.is_cancelled(),
+            "the token of the thread doesn't cancel the progress"
+        );
+    });
+
+    progress.cancel();
+    assert!(progress.cancellation().is_cancelled());
+}
+
+#[test]
+fn uncancellable_operations_still_report() {
+    let (progress, updates) = progress::channel();
+    progress.cancel();
+    let token = CancellationToken::new();
+    token.cancel();
+    cancellation::scoped(&token, || {
+        progress::uncancellable(&progress, |progress| {
+            let task = Task::new(progress, "Restoring");
+            assert!(task.check_cancelled().is_ok());
+            task.finish();
+        });
+        assert!(cancellation::is_cancelled());
+    });
+    assert_eq!(
+        updates.try_iter().last().unwrap().state,
+        TaskState::Finished
+    );
+}
@@ -0,0 +1,131 @@
+use gitbutler_command_context::CommandContext;
+use gitbutler_commit::commit_buffer::CommitBuffer;
+use gitbutler_project::Project;
+use gitbutler_repo::{ReadBackend, RepoReader, RepositoryExt};
+use gitbutler_testsupport::{commit_all, test_repository};
+
+fn readers(repo: &git2::Repository) -> [RepoReader<'_>; 2] {
+    [
+        RepoReader::new(repo, ReadBackend::Git2).unwrap(),
+        RepoReader::new(repo, ReadBackend::Gix).unwrap(),
+    ]
+}
+
+#[test]
+fn references_are_the_same_for_both_backends() {
+    let (repo, _tmp) = test_repository();
+    let head = repo.head().unwrap().target().unwrap();
+    for name in [
+        "refs/remotes/origin/b",
+        "refs/remotes/origin/a/nested",
+        "refs/remotes/other/c",
+    ] {
+        repo.reference(name, head, true, "").unwrap();
+    }
+    repo.reference_symbolic(
+        "refs/remotes/origin/HEAD",
+        "refs/remotes/origin/b",
+        true,
+        "",
+    )
+    .unwrap();
+
+    for reader in readers(&repo) {
+        assert_eq!(
+            reader.references("refs/remotes/origin/").unwrap(),
+            [
+                ("refs/remotes/origin/a/nested".to_string(), head),
+                ("refs/remotes/origin/b".to_string(), head),
+            ],
+            "{:?} skips symbolic references and those with another prefix",
+            reader.backend()
+        );
+    }
+}
+
+#[test]
+fn first_parent_log_is_the_same_for_both_backends() {
+    let (repo, tmp) = test_repository();
+    let root = repo.head().unwrap().target().unwrap();
+    let mut commits = vec![root];
+    for content in ["one", "two", "three"] {
+        std::fs::write(tmp.path().join("file"), content).unwrap();
+        commits.push(commit_all(&repo));
+    }
+    commits.reverse();
+
+    for reader in readers(&repo) {
+        assert_eq!(
+            reader.first_parent_log(commits[0], None).unwrap(),
+            commits,
+            "{:?} goes up to the root",
+            reader.backend()
+        );
+        assert_eq!(
+            reader
+                .first_parent_log(commits[0], Some(commits[2]))
+                .unwrap(),
+            &commits[..2],
+            "{:?} stops before `until`",
+            reader.backend()
+        );
+        assert!(reader
+            .first_parent_log(commits[0], Some(commits[0]))
+            .unwrap()
+            .is_empty());
+    }
+}
+
+#[test]
+fn blobs_are_the_same_for_both_backends() {
+    let (repo, _tmp) = test_repository();
+    let id = repo.blob(b"some content\n").unwrap();
+    for reader in readers(&repo) {
+        assert_eq!(reader.blob(id).unwrap(), b"some content\n");
+    }
+
+    let tree = repo.head().unwrap().peel_to_tree().unwrap().id();
+    for reader in readers(&repo) {
+        assert!(
+            reader.blob(tree).is_err(),
+            "{:?} fails for other objects",
+            reader.backend()
+        );
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn signing_programs_get_the_extra_environment_of_the_project() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (repo, tmp) = test_repository();
+    // Like `ssh-keygen -Y sign`, write the signature next to the file to sign, which is the last argument.
+    let program = tmp.path().join("sign.sh");
+    std::fs::write(
+        &program,
+        "#!/bin/sh\nfor last; do :; done\nprintf '%s' \"$SIGNING_TEST_VAR\" > \"$last.sig\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("gpg.format", "ssh").unwrap();
+    config
+        .set_str("gpg.ssh.program", program.to_str().unwrap())
+        .unwrap();
+    config.set_str("user.signingkey", "/unused/key").unwrap();
+
+    let project = Project {
+        path: repo.workdir().unwrap().to_path_buf(),
+        extra_env: [("SIGNING_TEST_VAR".to_owned(), "from-project".to_owned())].into(),
+        ..Default::default()
+    };
+    let ctx = CommandContext::open(&project).unwrap();
+    let signature = ctx
+        .repository()
+        .sign_buffer(&CommitBuffer::new(
+            b"tree 0000000000000000000000000000000000000000\n",
+        ))
+        .unwrap();
+    assert_eq!(signature, "from-project");
+}
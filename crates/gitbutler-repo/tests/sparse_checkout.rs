@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use gitbutler_repo::sparse_checkout;
+use gitbutler_testsupport::test_repository;
+
+fn write_patterns(repo: &git2::Repository, patterns: &str) {
+    repo.config()
+        .unwrap()
+        .open_level(git2::ConfigLevel::Local)
+        .unwrap()
+        .set_bool("core.sparseCheckout", true)
+        .unwrap();
+    let info = repo.path().join("info");
+    std::fs::create_dir_all(&info).unwrap();
+    std::fs::write(info.join("sparse-checkout"), patterns).unwrap();
+}
+
+#[test]
+fn the_whole_worktree_is_checked_out_by_default() {
+    let (repo, _tmp) = test_repository();
+    assert_eq!(sparse_checkout::directories(&repo).unwrap(), None);
+    assert_eq!(sparse_checkout::scope(&repo).unwrap(), None);
+}
+
+#[test]
+fn only_recursively_checked_out_directories_are_listed() {
+    let (repo, _tmp) = test_repository();
+    write_patterns(&repo, "/*\n!/*/\n/a/\n!/a/*/\n/a/b/\n/c/\n");
+    assert_eq!(
+        sparse_checkout::directories(&repo).unwrap(),
+        Some(vec![PathBuf::from("a/b"), PathBuf::from("c")])
+    );
+}
+
+#[test]
+fn scope_includes_files_of_parent_directories() {
+    let (repo, _tmp) = test_repository();
+    let workdir = repo.workdir().unwrap();
+    std::fs::create_dir_all(workdir.join("a/b")).unwrap();
+    std::fs::create_dir_all(workdir.join("a/other")).unwrap();
+    std::fs::write(workdir.join("a/file"), "").unwrap();
+    std::fs::write(workdir.join("a/other/file"), "").unwrap();
+    std::fs::write(workdir.join("top"), "").unwrap();
+    write_patterns(&repo, "/*\n!/*/\n/a/\n!/a/*/\n/a/b/\n");
+
+    let scope = sparse_checkout::scope(&repo).unwrap().unwrap();
+    assert!(scope.contains(&PathBuf::from("a/b")));
+    assert!(scope.contains(&PathBuf::from("a/file")));
+    assert!(scope.contains(&PathBuf::from("top")));
+    assert!(!scope.iter().any(|path| path.starts_with("a/other")));
+}
@@ -0,0 +1,32 @@
+pub mod commands {
+    use std::collections::BTreeSet;
+
+    use gitbutler_api_tokens::{ApiToken, Controller, CreatedToken, Scope, TokenId};
+    use tauri::State;
+    use tracing::instrument;
+
+    use crate::error::Error;
+
+    #[tauri::command]
+    #[instrument(skip(tokens), err(Debug))]
+    pub fn list_api_tokens(tokens: State<'_, Controller>) -> Result<Vec<ApiToken>, Error> {
+        Ok(tokens.list()?)
+    }
+
+    /// Create a token for the JSON-RPC server. Its secret is only returned here.
+    #[tauri::command]
+    #[instrument(skip(tokens), err(Debug))]
+    pub fn create_api_token(
+        tokens: State<'_, Controller>,
+        name: &str,
+        scopes: BTreeSet<Scope>,
+    ) -> Result<CreatedToken, Error> {
+        Ok(tokens.create(name, scopes)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(tokens), err(Debug))]
+    pub fn revoke_api_token(tokens: State<'_, Controller>, id: TokenId) -> Result<(), Error> {
+        Ok(tokens.revoke(id)?)
+    }
+}
@@ -7,7 +7,12 @@ use gitbutler_command_context::CommandContext;
 use gitbutler_project as projects;
 use gitbutler_project::ProjectId;
 use gitbutler_reference::RemoteRefname;
-use gitbutler_repo::{credentials, RepoActionsExt, RepositoryExt};
+use gitbutler_repo::{
+    credentials, remote_default_branch, RemoteDefaultBranch, RepoActionsExt, RepositoryExt,
+};
+
+/// The key of the global Git configuration that enables low-memory mode.
+const LOW_MEMORY_KEY: &str = "gitbutler.lowMemory";
 
 #[derive(Clone)]
 pub struct App {
@@ -23,6 +28,10 @@ impl App {
     pub fn users(&self) -> gitbutler_user::Controller {
         gitbutler_user::Controller::from_path(&self.app_data_dir)
     }
+
+    pub fn api_tokens(&self) -> gitbutler_api_tokens::Controller {
+        gitbutler_api_tokens::Controller::from_path(&self.app_data_dir)
+    }
 }
 
 impl App {
@@ -40,6 +49,17 @@ impl App {
         ctx.repository().remote_branches()
     }
 
+    pub fn git_remote_default_branch(
+        &self,
+        project_id: ProjectId,
+        remote_name: &str,
+        credentials: &credentials::Helper,
+    ) -> Result<Option<RemoteDefaultBranch>> {
+        let project = self.projects().get(project_id)?;
+        let ctx = CommandContext::open(&project)?;
+        remote_default_branch(&ctx, remote_name, credentials)
+    }
+
     pub fn git_test_push(
         &self,
         project_id: ProjectId,
@@ -112,6 +132,23 @@ impl App {
         }
     }
 
+    /// Turn [low-memory mode](gitbutler_diff::set_low_memory()) on or off, and remember it in the global
+    /// Git configuration for the next start.
+    pub fn set_low_memory_mode(enabled: bool) -> Result<()> {
+        let mut config = git2::Config::open_default()?;
+        config.set_bool(LOW_MEMORY_KEY, enabled)?;
+        gitbutler_diff::set_low_memory(enabled);
+        Ok(())
+    }
+
+    /// Turn on [low-memory mode](gitbutler_diff::set_low_memory()) if it's enabled in the global Git
+    /// configuration.
+    pub fn apply_low_memory_mode() -> Result<()> {
+        let config = git2::Config::open_default()?;
+        gitbutler_diff::set_low_memory(config.get_bool(LOW_MEMORY_KEY).unwrap_or(false));
+        Ok(())
+    }
+
     pub fn delete_all_data(&self) -> Result<()> {
         let controller = self.projects();
         for project in controller.list().context("failed to list projects")? {
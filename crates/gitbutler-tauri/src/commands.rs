@@ -1,12 +1,16 @@
+use std::collections::BTreeMap;
+
+use gitbutler_error::{catalog, registry};
 use gitbutler_project::ProjectId;
 use gitbutler_reference::RemoteRefname;
-use gitbutler_repo::credentials;
+use gitbutler_repo::{credentials, RemoteDefaultBranch};
+use serde::Serialize;
 use tauri::State;
 use tracing::instrument;
 
 use crate::{error::Error, App};
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(app), err(Debug))]
 pub fn git_remote_branches(
     app: State<'_, App>,
@@ -15,7 +19,18 @@ pub fn git_remote_branches(
     Ok(app.git_remote_branches(project_id)?)
 }
 
-#[tauri::command(async)]
+#[tauri::command]
+#[instrument(skip(app, helper), err(Debug))]
+pub fn git_remote_default_branch(
+    app: State<'_, App>,
+    helper: State<'_, credentials::Helper>,
+    project_id: ProjectId,
+    remote_name: &str,
+) -> Result<Option<RemoteDefaultBranch>, Error> {
+    Ok(app.git_remote_default_branch(project_id, remote_name, &helper)?)
+}
+
+#[tauri::command]
 #[instrument(skip(app, helper), err(Debug))]
 pub fn git_test_push(
     app: State<'_, App>,
@@ -34,7 +49,7 @@ pub fn git_test_push(
     )?)
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(app, helper), err(Debug))]
 pub fn git_test_fetch(
     app: State<'_, App>,
@@ -51,46 +66,102 @@ pub fn git_test_fetch(
     )?)
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(app), err(Debug))]
 pub fn git_index_size(app: State<'_, App>, project_id: ProjectId) -> Result<usize, Error> {
     Ok(app.git_index_size(project_id).expect("git index size"))
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(app), err(Debug))]
 pub fn git_head(app: State<'_, App>, project_id: ProjectId) -> Result<String, Error> {
     Ok(app.git_head(project_id)?)
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(app), err(Debug))]
 pub fn delete_all_data(app: State<'_, App>) -> Result<(), Error> {
     app.delete_all_data()?;
     Ok(())
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(app), err(Debug))]
 pub fn mark_resolved(app: State<'_, App>, project_id: ProjectId, path: &str) -> Result<(), Error> {
     app.mark_resolved(project_id, path)?;
     Ok(())
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(err(Debug))]
 pub fn git_set_global_config(key: &str, value: &str) -> Result<String, Error> {
     Ok(App::git_set_global_config(key, value)?)
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(err(Debug))]
 pub fn git_remove_global_config(key: &str) -> Result<(), Error> {
     Ok(App::git_remove_global_config(key)?)
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(err(Debug))]
 pub fn git_get_global_config(key: &str) -> Result<Option<String>, Error> {
     Ok(App::git_get_global_config(key)?)
 }
+
+#[tauri::command]
+#[instrument(err(Debug))]
+pub fn set_low_memory_mode(enabled: bool) -> Result<(), Error> {
+    Ok(App::set_low_memory_mode(enabled)?)
+}
+
+#[tauri::command]
+#[instrument]
+pub fn is_low_memory_mode() -> bool {
+    gitbutler_diff::is_low_memory()
+}
+
+/// All user-facing messages and errors that may originate in the backend.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageCatalog {
+    /// The messages keyed by their stable id, in the requested language as far as they are translated.
+    pub messages: BTreeMap<&'static str, &'static str>,
+    /// The errors from the coarse codes to the specific ones that refine them, sorted by id.
+    pub errors: Vec<ErrorCatalogEntry>,
+}
+
+/// A specific error that may originate in the backend, as listed in the [registry](registry).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorCatalogEntry {
+    /// The stable id of the error, like `errors.projects.git.auth.ssh-key-rejected`.
+    pub id: &'static str,
+    /// The coarse code of the error, which is its `id` for codes themselves.
+    pub code: &'static str,
+    /// The id of the message in `messages` that explains the error, if there is one.
+    pub message_id: Option<&'static str>,
+    pub help_url: Option<&'static str>,
+}
+
+/// Return all user-facing messages that may originate in the backend in the language of `locale`, along
+/// with all errors that are explained by them.
+#[tauri::command]
+#[instrument]
+pub fn get_message_catalog(locale: &str) -> MessageCatalog {
+    MessageCatalog {
+        messages: catalog::messages(locale)
+            .map(|(id, message)| (id.as_str(), message))
+            .collect(),
+        errors: registry::catalog()
+            .into_iter()
+            .map(|error| ErrorCatalogEntry {
+                id: error.id,
+                code: error.code.as_str(),
+                message_id: error.message_id.map(|id| id.as_str()),
+                help_url: error.help_url,
+            })
+            .collect(),
+    }
+}
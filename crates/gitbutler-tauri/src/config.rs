@@ -6,7 +6,7 @@ use tracing::instrument;
 
 use crate::error::Error;
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(projects), err(Debug))]
 pub fn get_gb_config(
     projects: State<'_, projects::Controller>,
@@ -15,7 +15,7 @@ pub fn get_gb_config(
     projects.get(project_id)?.gb_config().map_err(Into::into)
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(projects), err(Debug))]
 pub fn set_gb_config(
     projects: State<'_, projects::Controller>,
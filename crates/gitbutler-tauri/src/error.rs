@@ -18,7 +18,15 @@ pub(crate) use frontend::Error;
 mod frontend {
     use std::borrow::Cow;
 
-    use gitbutler_error::error::AnyhowContextExt;
+    use gitbutler_branch_actions::{
+        FilesChangedDuringCommit, PushRejection, PushedCommitRewrite, SecretsDetected,
+    };
+    use gitbutler_error::{
+        catalog::{self, MessageId},
+        error::AnyhowContextExt,
+    };
+    use gitbutler_fs::os_path::UnsupportedPath;
+    use gitbutler_repo::repo_state::OperationInProgress;
     use serde::{ser::SerializeMap, Serialize};
 
     /// An error type for serialization, dynamically extracting context information during serialization,
@@ -39,15 +47,52 @@ mod frontend {
         {
             let ctx = self.0.custom_context_or_root_cause();
 
-            let mut map = serializer.serialize_map(Some(2))?;
+            let mut map = serializer.serialize_map(None)?;
             map.serialize_entry("code", &ctx.code.to_string())?;
             let message = ctx.message.unwrap_or_else(|| {
                 self.0
                     .source()
                     .map(|err| Cow::Owned(err.to_string()))
-                    .unwrap_or_else(|| Cow::Borrowed("Something went wrong"))
+                    .unwrap_or_else(|| {
+                        Cow::Borrowed(catalog::message(
+                            MessageId::SomethingWentWrong,
+                            catalog::FALLBACK_LOCALE,
+                        ))
+                    })
             });
             map.serialize_entry("message", &message)?;
+            // Allows the frontend to show a localized message instead.
+            if let Some(message_id) = ctx.message_id {
+                map.serialize_entry("messageId", message_id.as_str())?;
+            }
+            // Allows the frontend to explain the specific error, and link to its documentation.
+            if let Some(error_id) = ctx.error_id {
+                map.serialize_entry("errorId", error_id.id)?;
+            }
+            // Lets the frontend show the commits that a rejected push would have overwritten.
+            if let Some(rejection) = self.0.downcast_ref::<PushRejection>() {
+                map.serialize_entry("pushRejection", rejection)?;
+            }
+            // Lets the frontend tell which files were still being written to.
+            if let Some(changed) = self.0.downcast_ref::<FilesChangedDuringCommit>() {
+                map.serialize_entry("filesChanged", changed)?;
+            }
+            // Lets the frontend ask whether to rewrite the pushed commits anyway.
+            if let Some(rewrite) = self.0.downcast_ref::<PushedCommitRewrite>() {
+                map.serialize_entry("pushedCommitRewrite", rewrite)?;
+            }
+            // Lets the frontend show where the secrets are and ask whether to go ahead anyway.
+            if let Some(secrets) = self.0.downcast_ref::<SecretsDetected>() {
+                map.serialize_entry("secretsDetected", secrets)?;
+            }
+            // Lets the frontend offer to abort or continue what Git is doing.
+            if let Some(operation) = self.0.downcast_ref::<OperationInProgress>() {
+                map.serialize_entry("operationInProgress", operation)?;
+            }
+            // Lets the frontend say which path can't be stored, and why.
+            if let Some(unsupported) = self.0.downcast_ref::<UnsupportedPath>() {
+                map.serialize_entry("unsupportedPath", unsupported)?;
+            }
             map.end()
         }
     }
@@ -55,7 +100,10 @@ mod frontend {
     #[cfg(test)]
     mod tests {
         use anyhow::anyhow;
-        use gitbutler_error::error::{Code, Context};
+        use gitbutler_error::{
+            catalog::MessageId,
+            error::{Code, Context},
+        };
 
         use super::*;
 
@@ -123,6 +171,30 @@ mod frontend {
             );
         }
 
+        #[test]
+        fn find_context_from_catalog() {
+            let err = anyhow!("err msg").context(Context::from_catalog(
+                Code::Validation,
+                MessageId::MalformedProjectId,
+            ));
+            assert_eq!(format!("{:#}", err), "Malformed project id: err msg");
+            assert_eq!(
+                json(err),
+                "{\"code\":\"errors.validation\",\"message\":\"Malformed project id\",\"messageId\":\"messages.projects.malformed_id\"}",
+                "the message id is passed along so the frontend can localize the message"
+            );
+        }
+
+        #[test]
+        fn find_error_id() {
+            let err = anyhow!("err msg").context(gitbutler_repo::errors::SSH_KEY_REJECTED);
+            assert_eq!(
+                json(err),
+                "{\"code\":\"errors.projects.git.auth\",\"message\":\"err msg\",\"errorId\":\"errors.projects.git.auth.ssh-key-rejected\"}",
+                "the error id refines the code, which is as coarse as before"
+            );
+        }
+
         #[test]
         fn find_context_without_message() {
             let err = anyhow!("err msg").context(Context::from(Code::Validation));
@@ -0,0 +1,212 @@
+//! Run the commands of each project on a thread of its own, so a long operation in one project, like a rebase,
+//! never keeps the commands of another project from running.
+//!
+//! Commands of the same project run one after another, in the order they came in, so they never observe
+//! each other half-way. Commands that aren't about a single project may run concurrently.
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    sync::{Arc, Mutex},
+};
+
+use gitbutler_project::ProjectId;
+use tauri::{Invoke, Runtime};
+use tokio::runtime;
+
+/// The maximum amount of commands that aren't about a single project that run at the same time.
+const THREADS_WITHOUT_PROJECT: usize = 8;
+
+/// Commands that have to run on the main thread, as they rely on not running concurrently with each other.
+const MAIN_THREAD_COMMANDS: &[&str] = &["open_project_in_window"];
+
+/// The executors of all projects, created once a command for the project comes in.
+#[derive(Clone)]
+pub struct Executors {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    /// The runtime that commands run in the context of, to spawn tasks that outlive them, like watchers.
+    context: runtime::Handle,
+    /// The runtime of each project whose only blocking thread runs its commands, with `None` running the
+    /// commands that aren't about a single project on several threads.
+    by_project: Mutex<BTreeMap<Option<ProjectId>, runtime::Runtime>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let executors = std::mem::take(
+            self.by_project
+                .get_mut()
+                .expect("no panics while holding the lock"),
+        );
+        // Dropping a runtime in an asynchronous context would panic.
+        for executor in executors.into_values() {
+            executor.shutdown_background();
+        }
+    }
+}
+
+impl Executors {
+    /// Create executors whose commands run in the `context` of a runtime, to spawn tasks on it.
+    pub fn new(context: runtime::Handle) -> Self {
+        Executors {
+            inner: Arc::new(Inner {
+                context,
+                by_project: Default::default(),
+            }),
+        }
+    }
+
+    /// Let `handler` run the command of `invoke` on the executor of the project whose id is passed
+    /// as `projectId`, or on the executor of commands without project if there is none.
+    pub fn dispatch<R, F>(&self, handler: &Arc<F>, invoke: Invoke<R>)
+    where
+        R: Runtime,
+        F: Fn(Invoke<R>) + Send + Sync + 'static,
+        Invoke<R>: Send,
+    {
+        if MAIN_THREAD_COMMANDS.contains(&invoke.message.command()) {
+            handler(invoke);
+            return;
+        }
+        let project_id = invoke
+            .message
+            .payload()
+            .get("projectId")
+            .and_then(|id| id.as_str())
+            .and_then(|id| id.parse().ok());
+        let handler = Arc::clone(handler);
+        let context = self.inner.context.clone();
+        self.spawn(project_id, move || {
+            let _context = context.enter();
+            handler(invoke);
+        });
+    }
+
+    /// Stop the executor of the project with `project_id`, letting the commands that are still running finish.
+    pub fn remove(&self, project_id: ProjectId) {
+        let executor = self
+            .inner
+            .by_project
+            .lock()
+            .expect("no panics while holding the lock")
+            .remove(&Some(project_id));
+        if let Some(executor) = executor {
+            executor.shutdown_background();
+        }
+    }
+
+    fn spawn(&self, project_id: Option<ProjectId>, job: impl FnOnce() + Send + 'static) {
+        let mut executors = self
+            .inner
+            .by_project
+            .lock()
+            .expect("no panics while holding the lock");
+        let executor = match executors.entry(project_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => match new_executor(project_id) {
+                Ok(executor) => entry.insert(executor),
+                Err(err) => {
+                    tracing::error!(?project_id, ?err, "failed to create executor");
+                    self.inner.context.spawn_blocking(job);
+                    return;
+                }
+            },
+        };
+        executor.spawn_blocking(job);
+    }
+}
+
+/// Create a runtime that is only used for its blocking threads, which are started as needed and stopped when idle.
+/// Projects get a single thread, so their commands run in order.
+fn new_executor(project_id: Option<ProjectId>) -> std::io::Result<runtime::Runtime> {
+    let (name, threads) = match project_id {
+        Some(project_id) => (format!("project-{project_id}"), 1),
+        None => ("commands".to_owned(), THREADS_WITHOUT_PROJECT),
+    };
+    runtime::Builder::new_current_thread()
+        .max_blocking_threads(threads)
+        .thread_name(name)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc::{self, RecvTimeoutError},
+        time::Duration,
+    };
+
+    use super::*;
+
+    /// Long enough for a job that is free to run to start, even on a busy machine.
+    const STARTS_WITHIN: Duration = Duration::from_secs(10);
+    /// Long enough for a job that was wrongly let through to start.
+    const STAYS_WAITING_FOR: Duration = Duration::from_millis(200);
+
+    fn executors() -> (runtime::Runtime, Executors) {
+        let context = runtime::Builder::new_current_thread().build().unwrap();
+        let executors = Executors::new(context.handle().clone());
+        (context, executors)
+    }
+
+    #[test]
+    fn a_blocked_project_does_not_stall_the_commands_of_another() {
+        let (_context, executors) = executors();
+        let (release, released) = mpsc::channel::<()>();
+        let (started, starts) = mpsc::channel();
+
+        let blocked = ProjectId::generate();
+        executors.spawn(Some(blocked), {
+            let started = started.clone();
+            move || {
+                started.send("rebase").unwrap();
+                released.recv().ok();
+            }
+        });
+        assert_eq!(starts.recv_timeout(STARTS_WITHIN), Ok("rebase"));
+
+        executors.spawn(Some(ProjectId::generate()), {
+            let started = started.clone();
+            move || started.send("status").unwrap()
+        });
+        executors.spawn(None, move || started.send("list projects").unwrap());
+        let mut others = [
+            starts.recv_timeout(STARTS_WITHIN).unwrap(),
+            starts.recv_timeout(STARTS_WITHIN).unwrap(),
+        ];
+        others.sort();
+        assert_eq!(others, ["list projects", "status"]);
+        release.send(()).unwrap();
+    }
+
+    #[test]
+    fn commands_of_the_same_project_run_one_after_another_in_order() {
+        let (_context, executors) = executors();
+        let (release, released) = mpsc::channel::<()>();
+        let (started, starts) = mpsc::channel();
+        let project_id = Some(ProjectId::generate());
+
+        executors.spawn(project_id, {
+            let started = started.clone();
+            move || {
+                started.send("rebase").unwrap();
+                released.recv().ok();
+            }
+        });
+        for command in ["status", "fetch"] {
+            let started = started.clone();
+            executors.spawn(project_id, move || started.send(command).unwrap());
+        }
+        assert_eq!(starts.recv_timeout(STARTS_WITHIN), Ok("rebase"));
+        assert_eq!(
+            starts.recv_timeout(STAYS_WAITING_FOR),
+            Err(RecvTimeoutError::Timeout),
+            "nothing else runs while the project is busy"
+        );
+
+        release.send(()).unwrap();
+        assert_eq!(starts.recv_timeout(STARTS_WITHIN), Ok("status"));
+        assert_eq!(starts.recv_timeout(STARTS_WITHIN), Ok("fetch"));
+    }
+}
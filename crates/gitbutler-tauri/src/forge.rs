@@ -0,0 +1,151 @@
+pub mod commands {
+    use std::collections::BTreeMap;
+
+    use anyhow::Result;
+    use gitbutler_branch::BranchId;
+    use gitbutler_branch_actions::{
+        AutoMerge, CiStatus, NewPullRequest, PullRequest, PushProtectionCheck, VirtualBranchActions,
+    };
+    use gitbutler_project as projects;
+    use gitbutler_project::{ForgeKind, ProjectId};
+    use gitbutler_secret::Sensitive;
+    use gitbutler_user as users;
+    use tauri::State;
+    use tracing::instrument;
+
+    use crate::error::Error;
+
+    #[tauri::command]
+    #[instrument(skip(projects, users, pull_request), err(Debug))]
+    pub fn create_pull_request(
+        projects: State<'_, projects::Controller>,
+        users: State<'_, users::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        pull_request: NewPullRequest,
+    ) -> Result<PullRequest, Error> {
+        let project = projects.get(project_id)?;
+        let token = github_access_token(&users)?;
+        Ok(VirtualBranchActions.create_pull_request(
+            &project,
+            branch_id,
+            &pull_request,
+            token.as_ref().map(|token| token.0.as_str()),
+        )?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn pull_request_description(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.pull_request_description(&project, branch_id)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, users), err(Debug))]
+    pub fn refresh_pull_request(
+        projects: State<'_, projects::Controller>,
+        users: State<'_, users::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+    ) -> Result<Option<PullRequest>, Error> {
+        let project = projects.get(project_id)?;
+        let token = github_access_token(&users)?;
+        Ok(VirtualBranchActions.refresh_pull_request(
+            &project,
+            branch_id,
+            token.as_ref().map(|token| token.0.as_str()),
+        )?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, users), err(Debug))]
+    pub fn enable_auto_merge(
+        projects: State<'_, projects::Controller>,
+        users: State<'_, users::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        auto_merge: AutoMerge,
+    ) -> Result<PullRequest, Error> {
+        let project = projects.get(project_id)?;
+        let token = github_access_token(&users)?;
+        Ok(VirtualBranchActions.enable_auto_merge(
+            &project,
+            branch_id,
+            auto_merge,
+            token.as_ref().map(|token| token.0.as_str()),
+        )?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, users), err(Debug))]
+    pub fn refresh_auto_merges(
+        projects: State<'_, projects::Controller>,
+        users: State<'_, users::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<PullRequest>, Error> {
+        let project = projects.get(project_id)?;
+        let token = github_access_token(&users)?;
+        Ok(VirtualBranchActions
+            .refresh_auto_merges(&project, token.as_ref().map(|token| token.0.as_str()))?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, users), err(Debug))]
+    pub fn refresh_ci_statuses(
+        projects: State<'_, projects::Controller>,
+        users: State<'_, users::Controller>,
+        project_id: ProjectId,
+    ) -> Result<BTreeMap<String, CiStatus>, Error> {
+        let project = projects.get(project_id)?;
+        let token = github_access_token(&users)?;
+        Ok(VirtualBranchActions
+            .refresh_ci_statuses(&project, token.as_ref().map(|token| token.0.as_str()))?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, users), err(Debug))]
+    pub fn check_push_protection(
+        projects: State<'_, projects::Controller>,
+        users: State<'_, users::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+    ) -> Result<PushProtectionCheck, Error> {
+        let project = projects.get(project_id)?;
+        let token = github_access_token(&users)?;
+        Ok(VirtualBranchActions.check_push_protection(
+            &project,
+            branch_id,
+            token.as_ref().map(|token| token.0.as_str()),
+        )?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_forge_kind(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<ForgeKind, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.forge_kind(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(token), err(Debug))]
+    pub fn set_forge_access_token(host: &str, token: &str) -> Result<(), Error> {
+        Ok(VirtualBranchActions.set_forge_access_token(host, token)?)
+    }
+
+    /// The GitHub access token the user stored when logging in to GitHub, if any.
+    fn github_access_token(users: &users::Controller) -> Result<Option<Sensitive<String>>> {
+        Ok(users
+            .get_user()?
+            .map(|user| user.github_access_token())
+            .transpose()?
+            .flatten())
+    }
+}
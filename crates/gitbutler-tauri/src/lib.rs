@@ -15,23 +15,30 @@ mod app;
 pub use app::App;
 
 pub mod commands;
+pub mod executors;
 
 pub mod logs;
 pub mod menu;
 pub mod window;
 pub use window::state::WindowState;
 
+pub mod api_tokens;
 pub mod askpass;
 pub mod config;
 pub mod error;
+pub mod forge;
 pub mod github;
 pub mod modes;
+pub mod progress;
 pub mod projects;
 pub mod remotes;
 pub mod repo;
+pub mod rpc;
 pub mod secret;
+pub mod timings;
 pub mod undo;
 pub mod users;
 pub mod virtual_branches;
+pub mod workspace;
 
 pub mod zip;
@@ -5,6 +5,8 @@ use tracing::{instrument, metadata::LevelFilter, subscriber::set_global_default}
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{fmt::format::FmtSpan, layer::SubscriberExt, Layer};
 
+use crate::timings::OperationTimings;
+
 pub fn init(app_handle: &AppHandle) {
     let logs_dir = app_handle
         .path_resolver()
@@ -30,6 +32,9 @@ pub fn init(app_handle: &AppHandle) {
 
     app_handle.manage(guard); // keep the guard alive for the lifetime of the app
 
+    let timings = OperationTimings::default();
+    app_handle.manage(timings.clone());
+
     let format_for_humans = tracing_subscriber::fmt::format()
         .with_file(true)
         .with_line_number(true)
@@ -69,6 +74,10 @@ pub fn init(app_handle: &AppHandle) {
                 .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
                 .with_writer(file_writer)
                 .with_filter(log_level_filter),
+        )
+        .with(
+            // subscriber that keeps the timings of recent operations for performance reports
+            timings.layer(),
         );
 
     set_global_default(subscriber).expect("failed to set subscriber");
@@ -11,15 +11,19 @@
     clippy::too_many_lines
 )]
 
+use std::sync::Arc;
+
 use gitbutler_repo::credentials;
 use gitbutler_tauri::{
-    askpass, commands, config, github, logs, menu, modes, projects, remotes, repo, secret, undo,
-    users, virtual_branches, zip, App, WindowState,
+    api_tokens, askpass, commands, config, executors::Executors, forge, github, logs, menu, modes,
+    progress, projects, remotes, repo, rpc, secret, timings, undo, users, virtual_branches,
+    workspace, zip, App, WindowState,
 };
 use tauri::{generate_context, Manager};
 use tauri_plugin_log::LogTarget;
 
 fn main() {
+    credentials::use_gpg_agent_for_ssh();
     let tauri_context = generate_context!();
     gitbutler_secret::secret::set_application_namespace(
         &tauri_context.config().tauri.bundle.identifier,
@@ -37,7 +41,12 @@ fn main() {
                 .target(LogTarget::LogDir)
                 .level(log::LevelFilter::Error);
 
+            let activity = gitbutler_watcher::Activity::default();
+            let executors = Executors::new(tokio::runtime::Handle::current());
+
             tauri::Builder::default()
+                .manage(activity.clone())
+                .manage(executors.clone())
                 .setup(move |tauri_app| {
                     let window = gitbutler_tauri::window::create(
                         &tauri_app.handle(),
@@ -61,6 +70,9 @@ fn main() {
 
                     logs::init(&app_handle);
 
+                    gitbutler_error::registry::register(gitbutler_repo::errors::ALL)
+                        .expect("error ids of the backend are valid");
+
                     // On MacOS, in dev mode with debug assertions, we encounter popups each time
                     // the binary is rebuilt. To counter that, use a git-credential based implementation.
                     // This isn't an issue for actual release build (i.e. nightly, production),
@@ -82,6 +94,10 @@ fn main() {
                         });
                     }
 
+                    if let Err(err) = App::apply_low_memory_mode() {
+                        tracing::warn!(?err, "failed to read whether low-memory mode is enabled");
+                    }
+
                     let (app_data_dir, app_cache_dir, app_log_dir) = {
                         let paths = app_handle.path_resolver();
                         (
@@ -102,6 +118,7 @@ fn main() {
                         app_data_dir: app_data_dir.clone(),
                     };
                     app_handle.manage(app.users());
+                    app_handle.manage(app.api_tokens());
                     app_handle.manage(app.projects());
 
                     app_handle.manage(gitbutler_feedback::Archival {
@@ -110,6 +127,41 @@ fn main() {
                         projects_controller: app.projects(),
                     });
                     app_handle.manage(credentials::Helper::default());
+
+                    if let Some(socket) = std::env::var_os(rpc::SOCKET_VAR) {
+                        let windows = app_handle.state::<WindowState>().inner().clone();
+                        let server =
+                            rpc::Server::new(app.projects(), app.api_tokens(), move |project_id| {
+                                // Projects that aren't open in a window have nothing to refresh.
+                                windows
+                                    .post(gitbutler_watcher::Action::CalculateVirtualBranches(
+                                        project_id,
+                                    ))
+                                    .ok();
+                            });
+                        tokio::task::spawn(async move {
+                            if let Err(err) = rpc::serve(server, socket.into()).await {
+                                tracing::error!(?err, "JSON-RPC server stopped");
+                            }
+                        });
+                        match std::env::var(rpc::METRICS_ADDR_VAR).map(|addr| addr.parse()) {
+                            Ok(Ok(addr)) => {
+                                tokio::task::spawn(async move {
+                                    if let Err(err) = rpc::serve_metrics(addr).await {
+                                        tracing::error!(?err, "metrics server stopped");
+                                    }
+                                });
+                            }
+                            Ok(Err(err)) => {
+                                tracing::error!(
+                                    ?err,
+                                    "{} isn't a valid address",
+                                    rpc::METRICS_ADDR_VAR
+                                );
+                            }
+                            Err(_) => {}
+                        }
+                    }
                     app_handle.manage(app);
 
                     Ok(())
@@ -119,83 +171,250 @@ fn main() {
                 .plugin(tauri_plugin_context_menu::init())
                 .plugin(tauri_plugin_store::Builder::default().build())
                 .plugin(log.build())
-                .invoke_handler(tauri::generate_handler![
-                    commands::git_remote_branches,
-                    commands::git_head,
-                    commands::delete_all_data,
-                    commands::mark_resolved,
-                    commands::git_set_global_config,
-                    commands::git_remove_global_config,
-                    commands::git_get_global_config,
-                    commands::git_test_push,
-                    commands::git_test_fetch,
-                    commands::git_index_size,
-                    zip::commands::get_logs_archive_path,
-                    zip::commands::get_project_archive_path,
-                    zip::commands::get_project_data_archive_path,
-                    users::commands::set_user,
-                    users::commands::delete_user,
-                    users::commands::get_user,
-                    projects::commands::add_project,
-                    projects::commands::get_project,
-                    projects::commands::update_project,
-                    projects::commands::delete_project,
-                    projects::commands::list_projects,
-                    projects::commands::set_project_active,
-                    projects::commands::open_project_in_window,
-                    repo::commands::git_get_local_config,
-                    repo::commands::git_set_local_config,
-                    repo::commands::check_signing_settings,
-                    repo::commands::git_clone_repository,
-                    virtual_branches::commands::list_virtual_branches,
-                    virtual_branches::commands::create_virtual_branch,
-                    virtual_branches::commands::delete_local_branch,
-                    virtual_branches::commands::commit_virtual_branch,
-                    virtual_branches::commands::get_base_branch_data,
-                    virtual_branches::commands::set_base_branch,
-                    virtual_branches::commands::update_base_branch,
-                    virtual_branches::commands::integrate_upstream_commits,
-                    virtual_branches::commands::update_virtual_branch,
-                    virtual_branches::commands::update_branch_order,
-                    virtual_branches::commands::delete_virtual_branch,
-                    virtual_branches::commands::convert_to_real_branch,
-                    virtual_branches::commands::unapply_ownership,
-                    virtual_branches::commands::reset_files,
-                    virtual_branches::commands::push_virtual_branch,
-                    virtual_branches::commands::create_virtual_branch_from_branch,
-                    virtual_branches::commands::can_apply_remote_branch,
-                    virtual_branches::commands::list_remote_commit_files,
-                    virtual_branches::commands::reset_virtual_branch,
-                    virtual_branches::commands::amend_virtual_branch,
-                    virtual_branches::commands::move_commit_file,
-                    virtual_branches::commands::undo_commit,
-                    virtual_branches::commands::insert_blank_commit,
-                    virtual_branches::commands::reorder_commit,
-                    virtual_branches::commands::update_commit_message,
-                    virtual_branches::commands::list_remote_branches,
-                    virtual_branches::commands::list_branches,
-                    virtual_branches::commands::get_branch_listing_details,
-                    virtual_branches::commands::get_remote_branch_data,
-                    virtual_branches::commands::squash_branch_commit,
-                    virtual_branches::commands::fetch_from_remotes,
-                    virtual_branches::commands::move_commit,
-                    virtual_branches::commands::normalize_branch_name,
-                    secret::secret_get_global,
-                    secret::secret_set_global,
-                    undo::list_snapshots,
-                    undo::restore_snapshot,
-                    undo::snapshot_diff,
-                    config::get_gb_config,
-                    config::set_gb_config,
-                    menu::menu_item_set_enabled,
-                    menu::get_editor_link_scheme,
-                    github::commands::init_device_oauth,
-                    github::commands::check_auth_status,
-                    askpass::commands::submit_prompt_response,
-                    remotes::list_remotes,
-                    remotes::add_remote,
-                    modes::operating_mode,
-                ])
+                .invoke_handler({
+                    // Any API call means the user is around, so defer idle maintenance.
+                    let commands = Arc::new(tauri::generate_handler![
+                        commands::git_remote_branches,
+                        commands::git_remote_default_branch,
+                        commands::git_head,
+                        commands::delete_all_data,
+                        commands::mark_resolved,
+                        commands::git_set_global_config,
+                        commands::git_remove_global_config,
+                        commands::git_get_global_config,
+                        commands::set_low_memory_mode,
+                        commands::is_low_memory_mode,
+                        commands::git_test_push,
+                        commands::git_test_fetch,
+                        commands::git_index_size,
+                        commands::get_message_catalog,
+                        zip::commands::get_logs_archive_path,
+                        zip::commands::get_project_archive_path,
+                        zip::commands::get_project_data_archive_path,
+                        timings::commands::recent_operation_timings,
+                        users::commands::set_user,
+                        users::commands::delete_user,
+                        users::commands::get_user,
+                        api_tokens::commands::list_api_tokens,
+                        api_tokens::commands::create_api_token,
+                        api_tokens::commands::revoke_api_token,
+                        projects::commands::add_project,
+                        projects::commands::get_project,
+                        projects::commands::update_project,
+                        projects::commands::get_project_settings,
+                        projects::commands::update_project_settings,
+                        projects::commands::delete_project,
+                        projects::commands::get_filesystem_capabilities,
+                        projects::commands::list_projects,
+                        projects::commands::search_projects,
+                        projects::commands::set_project_active,
+                        projects::commands::open_project_in_window,
+                        repo::commands::git_get_local_config,
+                        repo::commands::git_set_local_config,
+                        repo::commands::check_signing_settings,
+                        repo::commands::git_operation_in_progress,
+                        repo::commands::abort_git_operation,
+                        repo::commands::continue_git_operation,
+                        repo::commands::git_clone_repository,
+                        virtual_branches::commands::list_virtual_branches,
+                        virtual_branches::commands::create_virtual_branch,
+                        virtual_branches::commands::delete_local_branch,
+                        virtual_branches::commands::list_pending_branch_cleanups,
+                        virtual_branches::commands::clean_up_branches,
+                        virtual_branches::commands::delete_virtual_branches,
+                        virtual_branches::commands::unapply_all_branches,
+                        virtual_branches::commands::apply_branches,
+                        virtual_branches::commands::archive_integrated_branches,
+                        virtual_branches::commands::pending_operation,
+                        virtual_branches::commands::resume_operation,
+                        virtual_branches::commands::roll_back_operation,
+                        virtual_branches::commands::commit_virtual_branch,
+                        virtual_branches::commands::get_base_branch_data,
+                        virtual_branches::commands::plan_setup,
+                        virtual_branches::commands::set_up_project,
+                        virtual_branches::commands::set_base_branch,
+                        virtual_branches::commands::update_base_branch,
+                        virtual_branches::commands::switch_base_branch,
+                        virtual_branches::commands::remote_default_branch_change,
+                        virtual_branches::commands::migrate_target_to_remote_default,
+                        virtual_branches::commands::set_branch_push_remote,
+                        virtual_branches::commands::pin_branch_base,
+                        virtual_branches::commands::rebase_branch_onto_target,
+                        virtual_branches::commands::stack_branch,
+                        virtual_branches::commands::integrate_upstream_commits,
+                        virtual_branches::commands::integrate_upstream,
+                        virtual_branches::commands::update_virtual_branch,
+                        virtual_branches::commands::update_branch_order,
+                        virtual_branches::commands::delete_virtual_branch,
+                        virtual_branches::commands::convert_to_real_branch,
+                        virtual_branches::commands::unapply_ownership,
+                        virtual_branches::commands::discard_hunk,
+                        virtual_branches::commands::discard_lines,
+                        virtual_branches::commands::reset_files,
+                        virtual_branches::commands::push_virtual_branch,
+                        virtual_branches::commands::push_virtual_branch_with_lease,
+                        virtual_branches::commands::push_virtual_branch_or_queue,
+                        virtual_branches::commands::list_queued_operations,
+                        virtual_branches::commands::cancel_queued_operation,
+                        virtual_branches::commands::replay_queued_operations,
+                        virtual_branches::commands::push_preview,
+                        virtual_branches::commands::validate_remote_branch_name,
+                        virtual_branches::commands::get_commit_template,
+                        virtual_branches::commands::check_commit_message,
+                        virtual_branches::commands::commit_preview,
+                        virtual_branches::commands::generate_commit_message,
+                        virtual_branches::commands::set_message_generation_api_key,
+                        virtual_branches::commands::get_branch_metadata,
+                        virtual_branches::commands::set_branch_metadata,
+                        virtual_branches::commands::is_based_on_target,
+                        virtual_branches::commands::verify_integration,
+                        virtual_branches::commands::reconcile_external_changes,
+                        virtual_branches::commands::recover_from_external_change,
+                        virtual_branches::commands::repair_upstream_config,
+                        virtual_branches::commands::create_tag,
+                        virtual_branches::commands::list_tags,
+                        virtual_branches::commands::push_tag,
+                        virtual_branches::commands::create_virtual_branch_from_branch,
+                        virtual_branches::commands::preview_apply_branch,
+                        virtual_branches::commands::preview_unapply_branch,
+                        virtual_branches::commands::adopt_branch,
+                        virtual_branches::commands::can_apply_remote_branch,
+                        virtual_branches::commands::list_remote_commit_files,
+                        virtual_branches::commands::reset_virtual_branch,
+                        virtual_branches::commands::amend_virtual_branch,
+                        virtual_branches::commands::amend_commit,
+                        virtual_branches::commands::move_hunk_to_commit,
+                        virtual_branches::commands::absorb_hunks,
+                        virtual_branches::commands::move_commit_file,
+                        virtual_branches::commands::undo_commit,
+                        virtual_branches::commands::insert_blank_commit,
+                        virtual_branches::commands::reorder_commit,
+                        virtual_branches::commands::reorder_commits,
+                        virtual_branches::commands::cherry_pick,
+                        virtual_branches::commands::revert_commit,
+                        virtual_branches::commands::update_commit_message,
+                        virtual_branches::commands::normalize_commit_times,
+                        virtual_branches::commands::list_remote_branches,
+                        virtual_branches::commands::list_branches,
+                        virtual_branches::commands::get_branch_listing_details,
+                        virtual_branches::commands::list_remote_branch_activity,
+                        virtual_branches::commands::get_identity,
+                        virtual_branches::commands::set_identity,
+                        virtual_branches::commands::get_commit_graph,
+                        virtual_branches::commands::get_log,
+                        virtual_branches::commands::get_file_history,
+                        virtual_branches::commands::search_content,
+                        virtual_branches::commands::blame_file,
+                        virtual_branches::commands::get_remote_branch_data,
+                        virtual_branches::commands::squash_branch_commit,
+                        virtual_branches::commands::squash_commits,
+                        virtual_branches::commands::split_commit,
+                        virtual_branches::commands::fetch_from_remotes,
+                        virtual_branches::commands::is_shallow_clone,
+                        virtual_branches::commands::deepen_history,
+                        virtual_branches::commands::move_commit,
+                        virtual_branches::commands::normalize_branch_name,
+                        virtual_branches::commands::shelve_changes,
+                        virtual_branches::commands::unshelve_changes,
+                        virtual_branches::commands::list_shelves,
+                        virtual_branches::commands::list_trash,
+                        virtual_branches::commands::restore_trash,
+                        virtual_branches::commands::delete_shelf,
+                        virtual_branches::commands::list_stashes,
+                        virtual_branches::commands::list_submodules,
+                        virtual_branches::commands::list_nested_repositories,
+                        virtual_branches::commands::list_eol_changes,
+                        virtual_branches::commands::import_stash,
+                        virtual_branches::commands::apply_branch_partially,
+                        virtual_branches::commands::create_virtual_branch_from_snapshot,
+                        virtual_branches::commands::list_hunk_groups,
+                        virtual_branches::commands::select_hunks,
+                        virtual_branches::commands::get_file_status,
+                        virtual_branches::commands::check_ignored,
+                        virtual_branches::commands::add_ignore_patterns,
+                        virtual_branches::commands::get_workspace_ownership,
+                        virtual_branches::commands::set_status_tracing,
+                        virtual_branches::commands::get_status_traces,
+                        virtual_branches::commands::get_automation_runs,
+                        virtual_branches::commands::get_ownership_conflicts,
+                        virtual_branches::commands::claim_ownership_exclusively,
+                        virtual_branches::commands::set_hunk_note,
+                        virtual_branches::commands::pin_hunk,
+                        virtual_branches::commands::unpin_hunk,
+                        virtual_branches::commands::list_hunk_pins,
+                        virtual_branches::commands::branch_events_since,
+                        virtual_branches::commands::list_audit_log,
+                        virtual_branches::commands::get_commit_provenance,
+                        virtual_branches::commands::predict_conflicts,
+                        virtual_branches::commands::simulate_merge_orders,
+                        virtual_branches::commands::get_branch_dependencies,
+                        virtual_branches::commands::get_partial_checkout,
+                        virtual_branches::commands::enable_partial_checkout,
+                        virtual_branches::commands::hydrate_partial_checkout,
+                        virtual_branches::commands::disable_partial_checkout,
+                        virtual_branches::commands::list_leftovers,
+                        virtual_branches::commands::remove_leftovers,
+                        virtual_branches::commands::fsck_virtual_branches,
+                        virtual_branches::commands::export_to_git,
+                        virtual_branches::commands::export_patches,
+                        virtual_branches::commands::import_patch,
+                        virtual_branches::commands::eject,
+                        virtual_branches::commands::apply_layout,
+                        virtual_branches::commands::list_conflicted_files,
+                        virtual_branches::commands::get_conflicted_file_blob,
+                        virtual_branches::commands::resolve_conflict,
+                        virtual_branches::commands::finalize_conflict_resolution,
+                        secret::secret_get_global,
+                        secret::secret_set_global,
+                        undo::list_snapshots,
+                        undo::restore_snapshot,
+                        undo::undo,
+                        undo::redo,
+                        undo::undo_redo_state,
+                        undo::restore_snapshot_paths,
+                        undo::restore_snapshot_branch,
+                        undo::snapshot_diff,
+                        undo::diff_snapshots,
+                        undo::snapshot_branches,
+                        undo::snapshot_files,
+                        undo::snapshot_file,
+                        undo::snapshot_branch_diff,
+                        undo::snapshot_commit_diff,
+                        undo::oplog_gc,
+                        config::get_gb_config,
+                        config::set_gb_config,
+                        menu::menu_item_set_enabled,
+                        menu::get_editor_link_scheme,
+                        workspace::commands::workspace_fetch_all,
+                        workspace::commands::workspace_unpushed_branches,
+                        progress::commands::cancel_operation,
+                        forge::commands::create_pull_request,
+                        forge::commands::pull_request_description,
+                        forge::commands::refresh_pull_request,
+                        forge::commands::enable_auto_merge,
+                        forge::commands::refresh_auto_merges,
+                        forge::commands::refresh_ci_statuses,
+                        forge::commands::check_push_protection,
+                        forge::commands::get_forge_kind,
+                        forge::commands::set_forge_access_token,
+                        github::commands::init_device_oauth,
+                        github::commands::check_auth_status,
+                        askpass::commands::submit_prompt_response,
+                        remotes::list_remotes,
+                        remotes::add_remote,
+                        remotes::rename_remote,
+                        remotes::remove_remote,
+                        remotes::set_remote_url,
+                        remotes::start_fetch_scheduler,
+                        remotes::stop_fetch_scheduler,
+                        remotes::fetch_scheduler_status,
+                        modes::operating_mode,
+                    ]);
+                    move |invoke| {
+                        activity.touch();
+                        executors.dispatch(&commands, invoke);
+                    }
+                })
                 .menu(menu::build(tauri_context.package_info()))
                 .on_menu_event(|event| menu::handle_event(&event))
                 .on_window_event(|event| {
@@ -13,7 +13,7 @@ use tracing::instrument;
 
 use crate::error::Error;
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(handle), err(Debug))]
 pub fn menu_item_set_enabled(
     handle: AppHandle,
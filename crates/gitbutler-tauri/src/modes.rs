@@ -5,7 +5,7 @@ use tauri::State;
 
 use crate::error::Error;
 
-#[tauri::command(async)]
+#[tauri::command]
 pub fn operating_mode(
     projects: State<'_, Controller>,
     project_id: ProjectId,
@@ -0,0 +1,72 @@
+//! Forward the progress of long operations to the frontend as `project://<id>/progress` events, and let it
+//! cancel them by the id it chose for the operation.
+//!
+//! Operations run with the cancellation token of their progress as current token, so that the parts of them
+//! that don't report progress, like computing diffs or restoring snapshots, can be cancelled as well.
+use std::{collections::BTreeMap, sync::Mutex};
+
+use anyhow::Result;
+use gitbutler_command_context::cancellation;
+use gitbutler_project::ProjectId;
+use gitbutler_repo::progress::{self, ChannelProgress, Progress};
+use tauri::{AppHandle, Manager};
+
+/// The operations with progress that run right now, by the id the frontend chose for them.
+static RUNNING: Mutex<BTreeMap<String, ChannelProgress>> = Mutex::new(BTreeMap::new());
+
+/// Run `operation` of the project with `project_id`, emitting the progress it reports along with
+/// `operation_id`, through which it can also be cancelled. Without an id, it's still reported but can't be
+/// cancelled.
+pub(crate) fn with_progress<T>(
+    handle: &AppHandle,
+    project_id: ProjectId,
+    operation_id: Option<String>,
+    operation: impl FnOnce(&dyn Progress) -> Result<T>,
+) -> Result<T> {
+    let (progress, updates) = progress::channel();
+    let event = format!("project://{project_id}/progress");
+    let forwarder = {
+        let handle = handle.clone();
+        let operation_id = operation_id.clone();
+        std::thread::spawn(move || {
+            for update in updates {
+                let payload = serde_json::json!({ "operationId": operation_id, "update": update });
+                if let Err(error) = handle.emit_all(&event, Some(payload)) {
+                    tracing::warn!(?error, "failed to emit progress");
+                }
+            }
+        })
+    };
+    if let Some(id) = &operation_id {
+        running().insert(id.clone(), progress.clone());
+    }
+    let result = cancellation::scoped(progress.cancellation(), || operation(&progress));
+    if let Some(id) = &operation_id {
+        running().remove(id);
+    }
+    // The forwarder stops once the last sender is gone.
+    drop(progress);
+    forwarder.join().ok();
+    result
+}
+
+fn running() -> std::sync::MutexGuard<'static, BTreeMap<String, ChannelProgress>> {
+    RUNNING.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+pub mod commands {
+    use tracing::instrument;
+
+    use crate::error::Error;
+
+    /// Ask the operation with `operation_id` to stop, and return `false` if it doesn't run (anymore).
+    #[tauri::command]
+    #[instrument(err(Debug))]
+    pub fn cancel_operation(operation_id: String) -> Result<bool, Error> {
+        let Some(progress) = super::running().get(&operation_id).cloned() else {
+            return Ok(false);
+        };
+        progress.cancel();
+        Ok(true)
+    }
+}
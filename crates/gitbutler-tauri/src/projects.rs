@@ -4,13 +4,16 @@ pub mod commands {
     use std::path;
 
     use anyhow::Context;
+    use gitbutler_branch_actions::{SearchMatch, VirtualBranchActions};
     use gitbutler_project::{self as projects, Controller, ProjectId};
     use tauri::{State, Window};
     use tracing::instrument;
 
-    use crate::{error::Error, projects::ProjectForFrontend, window, WindowState};
+    use crate::{
+        error::Error, executors::Executors, projects::ProjectForFrontend, window, WindowState,
+    };
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
     pub fn update_project(
         projects: State<'_, Controller>,
@@ -19,7 +22,28 @@ pub mod commands {
         Ok(projects.update(&project)?)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_project_settings(
+        projects: State<'_, Controller>,
+        id: ProjectId,
+    ) -> Result<projects::Settings, Error> {
+        Ok(projects.settings(id)?)
+    }
+
+    /// Replace the settings of the project with `id`, which lets the watcher and the fetch scheduler
+    /// of the project pick up the changes right away.
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn update_project_settings(
+        projects: State<'_, Controller>,
+        id: ProjectId,
+        settings: projects::Settings,
+    ) -> Result<projects::Settings, Error> {
+        Ok(projects.update_settings(id, settings)?)
+    }
+
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
     pub fn add_project(
         projects: State<'_, Controller>,
@@ -28,7 +52,7 @@ pub mod commands {
         Ok(projects.add(path)?)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
     pub fn get_project(
         projects: State<'_, Controller>,
@@ -37,7 +61,7 @@ pub mod commands {
         Ok(projects.get_validated(id)?)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, window_state), err(Debug))]
     pub fn list_projects(
         window_state: State<'_, WindowState>,
@@ -55,10 +79,20 @@ pub mod commands {
         })
     }
 
+    /// Search all projects for a commit id, a branch name or a snippet of a change, to find which one contains it.
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn search_projects(
+        projects: State<'_, Controller>,
+        query: &str,
+    ) -> Result<Vec<SearchMatch>, Error> {
+        Ok(VirtualBranchActions.search_projects(&projects.list()?, query)?)
+    }
+
     /// This trigger is the GUI telling us that the project with `id` is now displayed.
     ///
     /// We use it to start watching for filesystem events.
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, window_state, window), err(Debug))]
     pub fn set_project_active(
         projects: State<'_, Controller>,
@@ -86,10 +120,28 @@ pub mod commands {
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
+    #[instrument(skip(projects, executors), err(Debug))]
+    pub fn delete_project(
+        projects: State<'_, Controller>,
+        executors: State<'_, Executors>,
+        id: ProjectId,
+    ) -> Result<(), Error> {
+        projects.delete(id)?;
+        executors.remove(id);
+        Ok(())
+    }
+
+    /// Report what works for the repository of the project with `id` on its filesystem,
+    /// like whether it's accessed across the Windows/WSL boundary.
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
-    pub fn delete_project(projects: State<'_, Controller>, id: ProjectId) -> Result<(), Error> {
-        projects.delete(id).map_err(Into::into)
+    pub fn get_filesystem_capabilities(
+        projects: State<'_, Controller>,
+        id: ProjectId,
+    ) -> Result<projects::FilesystemCapabilities, Error> {
+        let project = projects.get(id)?;
+        Ok(projects::FilesystemCapabilities::detect(&project.path))
     }
 }
 
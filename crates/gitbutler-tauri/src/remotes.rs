@@ -1,12 +1,14 @@
+use gitbutler_branch_actions::VirtualBranchActions;
 use gitbutler_project as projects;
 use gitbutler_project::ProjectId;
 use gitbutler_repo::RepoCommands;
+use gitbutler_watcher::RemoteFetchStatus;
 use tauri::State;
 use tracing::instrument;
 
-use crate::error::Error;
+use crate::{error::Error, WindowState};
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(projects), err(Debug))]
 pub fn list_remotes(
     projects: State<'_, projects::Controller>,
@@ -16,7 +18,7 @@ pub fn list_remotes(
     project.remotes().map_err(Into::into)
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(projects), err(Debug))]
 pub fn add_remote(
     projects: State<'_, projects::Controller>,
@@ -27,3 +29,69 @@ pub fn add_remote(
     let project = projects.get(project_id)?;
     project.add_remote(name, url).map_err(Into::into)
 }
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn rename_remote(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    old_name: &str,
+    new_name: &str,
+) -> Result<(), Error> {
+    let project = projects.get(project_id)?;
+    VirtualBranchActions
+        .rename_remote(&project, old_name, new_name)
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn remove_remote(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    name: &str,
+) -> Result<(), Error> {
+    let project = projects.get(project_id)?;
+    VirtualBranchActions
+        .remove_remote(&project, name)
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn set_remote_url(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    name: &str,
+    url: &str,
+) -> Result<(), Error> {
+    let project = projects.get(project_id)?;
+    VirtualBranchActions
+        .set_remote_url(&project, name, url)
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+#[instrument(skip(windows), err(Debug))]
+pub fn start_fetch_scheduler(
+    windows: State<'_, WindowState>,
+    project_id: ProjectId,
+) -> Result<(), Error> {
+    windows.start_fetch_scheduler(project_id)?;
+    Ok(())
+}
+
+#[tauri::command]
+#[instrument(skip(windows))]
+pub fn stop_fetch_scheduler(windows: State<'_, WindowState>, project_id: ProjectId) {
+    windows.stop_fetch_scheduler(project_id);
+}
+
+#[tauri::command]
+#[instrument(skip(windows))]
+pub fn fetch_scheduler_status(
+    windows: State<'_, WindowState>,
+    project_id: ProjectId,
+) -> Option<Vec<RemoteFetchStatus>> {
+    windows.fetch_scheduler_status(project_id)
+}
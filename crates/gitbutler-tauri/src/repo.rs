@@ -3,14 +3,14 @@ pub mod commands {
     use git2::{self};
     use gitbutler_project as projects;
     use gitbutler_project::ProjectId;
-    use gitbutler_repo::RepoCommands;
+    use gitbutler_repo::{repo_state::OperationInProgress, RepoCommands};
     use std::path::Path;
     use tauri::State;
     use tracing::instrument;
 
     use crate::error::Error;
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
     pub fn git_get_local_config(
         projects: State<'_, projects::Controller>,
@@ -21,7 +21,7 @@ pub mod commands {
         Ok(project.get_local_config(key)?)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
     pub fn git_set_local_config(
         projects: State<'_, projects::Controller>,
@@ -33,7 +33,7 @@ pub mod commands {
         project.set_local_config(key, value).map_err(Into::into)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
     pub fn check_signing_settings(
         projects: State<'_, projects::Controller>,
@@ -43,7 +43,37 @@ pub mod commands {
         project.check_signing_settings().map_err(Into::into)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn git_operation_in_progress(
+        projects: State<'_, projects::Controller>,
+        id: ProjectId,
+    ) -> Result<Option<OperationInProgress>, Error> {
+        let project = projects.get(id)?;
+        Ok(project.git_operation_in_progress()?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn abort_git_operation(
+        projects: State<'_, projects::Controller>,
+        id: ProjectId,
+    ) -> Result<(), Error> {
+        let project = projects.get(id)?;
+        Ok(project.abort_git_operation()?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn continue_git_operation(
+        projects: State<'_, projects::Controller>,
+        id: ProjectId,
+    ) -> Result<(), Error> {
+        let project = projects.get(id)?;
+        Ok(project.continue_git_operation()?)
+    }
+
+    #[tauri::command]
     pub fn git_clone_repository(repository_url: &str, target_dir: &Path) -> Result<(), Error> {
         git2::Repository::clone(repository_url, target_dir).context("Cloning failed")?;
         Ok(())
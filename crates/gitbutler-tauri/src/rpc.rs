@@ -0,0 +1,720 @@
+//! A [JSON-RPC 2.0](https://www.jsonrpc.org/specification) server on a local socket, which exposes the project,
+//! branch and operations log APIs of the running application so editors and other tools can integrate with it
+//! instead of shelling out.
+//!
+//! It only runs if `GITBUTLER_RPC_SOCKET` is set to the path of the Unix domain socket to listen on, which is
+//! only accessible by the current user. Requests and responses are sent one per line.
+//!
+//! Failed operations respond with the error code `-32000`, and their error `data` holds the same `code` and
+//! `message` as the [errors](crate::error) of commands.
+//!
+//! Once an [API token](gitbutler_api_tokens) was created, each connection has to authenticate by calling
+//! `auth.login` with the secret of one as `token`, and can then only call the methods its scopes allow.
+//! Reading is always allowed, while creating branches and committing needs the `commit` scope, pushing needs
+//! `push`, and adding projects and restoring snapshots needs `admin`. Calls without a token respond with the
+//! error code `-32001`, and calls the token doesn't allow with `-32003`.
+//!
+//! The [metrics](gitbutler_metrics) of the application are returned by `metrics.get` in the Prometheus text format.
+//! While serving, they can also be scraped over HTTP at `/metrics` if `GITBUTLER_METRICS_ADDR` is set to the
+//! address to listen on, like `127.0.0.1:9464`.
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use anyhow::Result;
+use gitbutler_api_tokens::{ApiToken, Scope};
+use gitbutler_branch::{BranchCreateRequest, BranchId, BranchOwnershipClaims};
+use gitbutler_branch_actions::{VirtualBranchActions, VirtualBranches};
+use gitbutler_oplog::OplogExt;
+use gitbutler_project::{self as projects, Project, ProjectId};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// The environment variable that holds the path of the socket to listen on.
+pub const SOCKET_VAR: &str = "GITBUTLER_RPC_SOCKET";
+
+/// The environment variable that holds the address to serve metrics over HTTP on.
+pub const METRICS_ADDR_VAR: &str = "GITBUTLER_METRICS_ADDR";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// An operation failed, with the serialized error as data.
+const OPERATION_FAILED: i64 = -32000;
+/// The connection didn't authenticate with a valid token.
+const UNAUTHENTICATED: i64 = -32001;
+/// The token of the connection doesn't have the scope the method needs.
+const FORBIDDEN: i64 = -32003;
+
+/// The amount of snapshots `oplog.list` returns if no `limit` is given.
+const DEFAULT_SNAPSHOT_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// The id to respond with, or `None` for notifications, which get no response.
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ResponseError>,
+}
+
+impl Response {
+    fn new(id: Value, outcome: Result<Value, ResponseError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Response {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ResponseError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl ResponseError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        ResponseError {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for ResponseError {
+    fn from(err: anyhow::Error) -> Self {
+        let data = serde_json::to_value(Error::from(err)).unwrap_or_default();
+        ResponseError {
+            code: OPERATION_FAILED,
+            message: data["message"].as_str().unwrap_or_default().to_owned(),
+            data: Some(data),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LoginParams {
+    token: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectParams {
+    project_id: ProjectId,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddProjectParams {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BranchParams {
+    project_id: ProjectId,
+    branch_id: BranchId,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateBranchParams {
+    project_id: ProjectId,
+    #[serde(default)]
+    branch: BranchCreateRequest,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitParams {
+    project_id: ProjectId,
+    branch_id: BranchId,
+    message: String,
+    /// The changes to commit, or all changes of the branch if unset.
+    ownership: Option<BranchOwnershipClaims>,
+    #[serde(default)]
+    run_hooks: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PushParams {
+    project_id: ProjectId,
+    branch_id: BranchId,
+    #[serde(default)]
+    with_force: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListSnapshotsParams {
+    project_id: ProjectId,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RestoreSnapshotParams {
+    project_id: ProjectId,
+    snapshot_id: String,
+}
+
+/// What the server knows about a connection, which starts out unauthenticated.
+#[derive(Debug, Default)]
+pub struct Session {
+    /// The token the connection authenticated with.
+    token: Option<ApiToken>,
+}
+
+/// Answers requests with the projects of `projects`.
+#[derive(Clone)]
+pub struct Server {
+    projects: projects::Controller,
+    /// The tokens that connections authenticate with.
+    tokens: gitbutler_api_tokens::Controller,
+    /// Called with the id of the project whose branches were changed, so windows showing it can refresh.
+    on_change: Arc<dyn Fn(ProjectId) + Send + Sync>,
+}
+
+impl Server {
+    /// Serve the projects of `projects` to connections authenticated with `tokens`, calling `on_change` with
+    /// the id of each project that a request changed.
+    pub fn new(
+        projects: projects::Controller,
+        tokens: gitbutler_api_tokens::Controller,
+        on_change: impl Fn(ProjectId) + Send + Sync + 'static,
+    ) -> Self {
+        Server {
+            projects,
+            tokens,
+            on_change: Arc::new(on_change),
+        }
+    }
+
+    /// Perform the request in `line` on the connection with `session`, and return the response to it, or
+    /// `None` if it was a notification.
+    ///
+    /// Operations are blocking, so this must not be called on an asynchronous runtime.
+    pub fn handle(&self, session: &mut Session, line: &str) -> Option<String> {
+        let response = match serde_json::from_str::<Request>(line) {
+            Ok(request) if request.jsonrpc == "2.0" => {
+                let outcome = self.authorized_call(session, &request.method, request.params);
+                Response::new(request.id?, outcome)
+            }
+            Ok(request) => Response::new(
+                request.id.unwrap_or_default(),
+                Err(ResponseError::new(
+                    INVALID_REQUEST,
+                    "only JSON-RPC 2.0 is supported",
+                )),
+            ),
+            Err(err) if err.is_syntax() || err.is_eof() => {
+                Response::new(Value::Null, Err(ResponseError::new(PARSE_ERROR, err)))
+            }
+            Err(err) => Response::new(Value::Null, Err(ResponseError::new(INVALID_REQUEST, err))),
+        };
+        Some(serde_json::to_string(&response).expect("responses are always serializable"))
+    }
+
+    /// Perform `method` if the token of `session` allows it, or authenticate `session` for `auth.login`.
+    fn authorized_call(
+        &self,
+        session: &mut Session,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, ResponseError> {
+        if method == "auth.login" {
+            let LoginParams { token } = parse(params)?;
+            let Some(token) = self.tokens.authenticate(&token)? else {
+                return Err(ResponseError::new(
+                    UNAUTHENTICATED,
+                    "the token is invalid or was revoked",
+                ));
+            };
+            session.token = Some(token.clone());
+            return to_result(Ok(token));
+        }
+        let Some(scope) = required_scope(method) else {
+            return Err(ResponseError::new(
+                METHOD_NOT_FOUND,
+                format!("there is no method named '{method}'"),
+            ));
+        };
+        match &session.token {
+            None if self.tokens.is_required()? => {
+                return Err(ResponseError::new(
+                    UNAUTHENTICATED,
+                    "authenticate with 'auth.login' first",
+                ));
+            }
+            None => {}
+            Some(token) => {
+                // Tokens that were revoked since the connection authenticated can't be used anymore.
+                if !self.tokens.list()?.iter().any(|known| known.id == token.id) {
+                    session.token = None;
+                    return Err(ResponseError::new(UNAUTHENTICATED, "the token was revoked"));
+                }
+                if !token.allows(scope) {
+                    return Err(ResponseError::new(
+                        FORBIDDEN,
+                        format!("the token doesn't allow calling '{method}'"),
+                    ));
+                }
+            }
+        }
+        self.call(method, params)
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, ResponseError> {
+        match method {
+            "projects.list" => to_result(self.projects.list()),
+            "projects.get" => {
+                let ProjectParams { project_id } = parse(params)?;
+                to_result(self.projects.get(project_id))
+            }
+            "projects.add" => {
+                let AddProjectParams { path } = parse(params)?;
+                to_result(self.projects.add(path))
+            }
+            "branches.list" => {
+                let ProjectParams { project_id } = parse(params)?;
+                let project = self.project(project_id)?;
+                to_result(VirtualBranchActions.list_virtual_branches(&project).map(
+                    |(branches, skipped_files)| VirtualBranches {
+                        branches,
+                        skipped_files,
+                    },
+                ))
+            }
+            "branches.create" => {
+                let CreateBranchParams { project_id, branch } = parse(params)?;
+                let project = self.project(project_id)?;
+                self.changed(
+                    project_id,
+                    VirtualBranchActions.create_virtual_branch(&project, &branch),
+                )
+            }
+            "branches.commit" => {
+                let CommitParams {
+                    project_id,
+                    branch_id,
+                    message,
+                    ownership,
+                    run_hooks,
+                } = parse(params)?;
+                let project = self.project(project_id)?;
+                self.changed(
+                    project_id,
+                    VirtualBranchActions
+                        .create_commit(&project, branch_id, &message, ownership.as_ref(), run_hooks)
+                        .map(|commit_id| commit_id.to_string()),
+                )
+            }
+            "branches.push" => {
+                let PushParams {
+                    project_id,
+                    branch_id,
+                    with_force,
+                } = parse(params)?;
+                let project = self.project(project_id)?;
+                self.changed(
+                    project_id,
+                    VirtualBranchActions.push_virtual_branch(&project, branch_id, with_force, None),
+                )
+            }
+            "branches.unapply" => {
+                let BranchParams {
+                    project_id,
+                    branch_id,
+                } = parse(params)?;
+                let project = self.project(project_id)?;
+                self.changed(
+                    project_id,
+                    VirtualBranchActions.convert_to_real_branch(&project, branch_id),
+                )
+            }
+            "oplog.list" => {
+                let ListSnapshotsParams { project_id, limit } = parse(params)?;
+                let project = self.project(project_id)?;
+                to_result(project.list_snapshots(limit.unwrap_or(DEFAULT_SNAPSHOT_LIMIT), None))
+            }
+            "oplog.restore" => {
+                let RestoreSnapshotParams {
+                    project_id,
+                    snapshot_id,
+                } = parse(params)?;
+                let project = self.project(project_id)?;
+                let snapshot_id = snapshot_id
+                    .parse()
+                    .map_err(|err| ResponseError::new(INVALID_PARAMS, err))?;
+                self.changed(project_id, project.restore_snapshot(snapshot_id))
+            }
+            "metrics.get" => Ok(Value::String(gitbutler_metrics::render())),
+            _ => Err(ResponseError::new(
+                METHOD_NOT_FOUND,
+                format!("there is no method named '{method}'"),
+            )),
+        }
+    }
+
+    fn project(&self, project_id: ProjectId) -> Result<Project, ResponseError> {
+        Ok(self.projects.get(project_id)?)
+    }
+
+    /// Announce that the project with `project_id` changed if `outcome` is a success, and return it.
+    fn changed(
+        &self,
+        project_id: ProjectId,
+        outcome: Result<impl Serialize>,
+    ) -> Result<Value, ResponseError> {
+        if outcome.is_ok() {
+            (self.on_change)(project_id);
+        }
+        to_result(outcome)
+    }
+}
+
+/// Return the scope a token needs to call `method`, or `None` if there is no such method.
+fn required_scope(method: &str) -> Option<Scope> {
+    Some(match method {
+        "projects.list" | "projects.get" | "branches.list" | "oplog.list" | "metrics.get" => {
+            Scope::ReadOnly
+        }
+        "branches.create" | "branches.commit" | "branches.unapply" => Scope::Commit,
+        "branches.push" => Scope::Push,
+        "projects.add" | "oplog.restore" => Scope::Admin,
+        _ => return None,
+    })
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, ResponseError> {
+    serde_json::from_value(params).map_err(|err| ResponseError::new(INVALID_PARAMS, err))
+}
+
+fn to_result(outcome: Result<impl Serialize>) -> Result<Value, ResponseError> {
+    Ok(serde_json::to_value(outcome?).map_err(anyhow::Error::from)?)
+}
+
+/// Serve connections to the socket at `path` with `server` until the application exits,
+/// replacing the socket a previous run may have left behind.
+#[cfg(unix)]
+pub async fn serve(server: Server, path: PathBuf) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    use anyhow::Context;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::UnixListener,
+    };
+
+    if path.exists() {
+        std::fs::remove_file(&path)
+            .with_context(|| format!("failed to remove stale socket at {}", path.display()))?;
+    }
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("failed to listen on {}", path.display()))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    tracing::info!(path = %path.display(), "serving JSON-RPC");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut session = Session::default();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let server = server.clone();
+                let Ok((returned, response)) = tokio::task::spawn_blocking(move || {
+                    let response = server.handle(&mut session, &line);
+                    (session, response)
+                })
+                .await
+                else {
+                    break;
+                };
+                session = returned;
+                let Some(mut response) = response else {
+                    continue;
+                };
+                response.push('\n');
+                if writer.write_all(response.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn serve(_server: Server, _path: PathBuf) -> Result<()> {
+    anyhow::bail!(
+        "serving JSON-RPC requires Unix domain sockets, which aren't available on this platform"
+    )
+}
+
+/// Serve the metrics of the application at `/metrics` over HTTP on `addr` until the application exits.
+pub async fn serve_metrics(addr: SocketAddr) -> Result<()> {
+    use anyhow::Context;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to listen on {addr}"))?;
+    tracing::info!(%addr, "serving metrics");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let Ok(Some(request_line)) = lines.next_line().await else {
+                return;
+            };
+            // The headers don't matter, but are read so the client isn't cut off while sending them.
+            while let Ok(Some(header)) = lines.next_line().await {
+                if header.is_empty() {
+                    break;
+                }
+            }
+            let response = metrics_response(&request_line);
+            writer.write_all(response.as_bytes()).await.ok();
+            writer.shutdown().await.ok();
+        });
+    }
+}
+
+/// Return the HTTP response to the request with `request_line`, like `GET /metrics HTTP/1.1`.
+fn metrics_response(request_line: &str) -> String {
+    let (status, content_type, body) = match request_line.split_whitespace().collect::<Vec<_>>()[..]
+    {
+        ["GET", "/metrics", _] => (
+            "200 OK",
+            "text/plain; version=0.0.4",
+            gitbutler_metrics::render(),
+        ),
+        ["GET", _, _] => ("404 Not Found", "text/plain", "not found\n".to_owned()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "only GET is supported\n".to_owned(),
+        ),
+    };
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    fn server() -> (Server, Arc<Mutex<Vec<ProjectId>>>, tempfile::TempDir) {
+        let data_dir = gitbutler_testsupport::paths::data_dir();
+        let changed = Arc::new(Mutex::new(Vec::new()));
+        let server = Server::new(
+            projects::Controller::from_path(data_dir.path()),
+            gitbutler_api_tokens::Controller::from_path(data_dir.path()),
+            {
+                let changed = Arc::clone(&changed);
+                move |project_id| changed.lock().unwrap().push(project_id)
+            },
+        );
+        (server, changed, data_dir)
+    }
+
+    fn call(server: &Server, request: &str) -> Value {
+        call_in(server, &mut Session::default(), request)
+    }
+
+    fn call_in(server: &Server, session: &mut Session, request: &str) -> Value {
+        serde_json::from_str(&server.handle(session, request).expect("not a notification")).unwrap()
+    }
+
+    #[test]
+    fn projects_can_be_added_and_listed() {
+        let (server, _changed, _data_dir) = server();
+        let repository = gitbutler_testsupport::TestProject::default();
+
+        let added = call(
+            &server,
+            &serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "projects.add",
+                "params": { "path": repository.path() },
+            })
+            .to_string(),
+        );
+        assert_eq!(added["id"], 1);
+        let project_id = added["result"]["id"].clone();
+
+        let listed = call(
+            &server,
+            r#"{"jsonrpc": "2.0", "id": "two", "method": "projects.list"}"#,
+        );
+        assert_eq!(listed["id"], "two");
+        assert_eq!(listed["result"][0]["id"], project_id);
+    }
+
+    #[test]
+    fn failed_operations_carry_the_error_of_commands() {
+        let (server, changed, _data_dir) = server();
+        let response = call(
+            &server,
+            &serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "branches.list",
+                "params": { "projectId": ProjectId::generate() },
+            })
+            .to_string(),
+        );
+        assert_eq!(response["error"]["code"], OPERATION_FAILED);
+        assert_eq!(response["error"]["data"]["code"], "errors.projects.missing");
+        assert!(response.get("result").is_none());
+        assert!(changed.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn protocol_errors() {
+        let (server, _changed, _data_dir) = server();
+        assert_eq!(call(&server, "{")["error"]["code"], PARSE_ERROR);
+        assert_eq!(
+            call(
+                &server,
+                r#"{"jsonrpc": "1.0", "id": 1, "method": "projects.list"}"#
+            )["error"]["code"],
+            INVALID_REQUEST
+        );
+        assert_eq!(
+            call(&server, r#"{"jsonrpc": "2.0", "id": 1, "method": "nope"}"#)["error"]["code"],
+            METHOD_NOT_FOUND
+        );
+        assert_eq!(
+            call(
+                &server,
+                r#"{"jsonrpc": "2.0", "id": 1, "method": "projects.get", "params": {}}"#
+            )["error"]["code"],
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            server.handle(
+                &mut Session::default(),
+                r#"{"jsonrpc": "2.0", "method": "projects.list"}"#
+            ),
+            None,
+            "notifications get no response"
+        );
+    }
+
+    #[test]
+    fn tokens_limit_what_connections_may_call() {
+        let (server, _changed, _data_dir) = server();
+        let secret = server
+            .tokens
+            .create("editor", [Scope::Commit].into())
+            .unwrap()
+            .secret;
+        let list = r#"{"jsonrpc": "2.0", "id": 1, "method": "projects.list"}"#;
+        let add =
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "projects.add", "params": {"path": "/nope"}}"#;
+
+        let mut session = Session::default();
+        assert_eq!(
+            call_in(&server, &mut session, list)["error"]["code"],
+            UNAUTHENTICATED
+        );
+        let login = |token: &str| {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "auth.login",
+                "params": { "token": token },
+            })
+            .to_string()
+        };
+        assert_eq!(
+            call_in(&server, &mut session, &login("gbt_wrong"))["error"]["code"],
+            UNAUTHENTICATED
+        );
+        let logged_in = call_in(&server, &mut session, &login(&secret));
+        assert_eq!(logged_in["result"]["name"], "editor");
+
+        assert!(call_in(&server, &mut session, list)["result"].is_array());
+        assert_eq!(
+            call_in(&server, &mut session, add)["error"]["code"],
+            FORBIDDEN
+        );
+
+        let token_id = session.token.as_ref().unwrap().id;
+        server.tokens.revoke(token_id).unwrap();
+        server
+            .tokens
+            .create("other", [Scope::Admin].into())
+            .unwrap();
+        assert_eq!(
+            call_in(&server, &mut session, list)["error"]["code"],
+            UNAUTHENTICATED,
+            "revoked tokens stop working right away"
+        );
+    }
+
+    #[test]
+    fn metrics_are_returned_as_text() {
+        let (server, _changed, _data_dir) = server();
+        let response = call(
+            &server,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "metrics.get"}"#,
+        );
+        assert!(response["result"]
+            .as_str()
+            .unwrap()
+            .contains("# TYPE gitbutler_watcher_backlog gauge\n"));
+    }
+
+    #[test]
+    fn metrics_over_http() {
+        let response = metrics_response("GET /metrics HTTP/1.1");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.contains(&format!("Content-Length: {}", body.len())));
+        assert!(body.contains("# TYPE gitbutler_operations_total counter\n"));
+
+        assert!(metrics_response("GET / HTTP/1.1").starts_with("HTTP/1.1 404 "));
+        assert!(metrics_response("POST /metrics HTTP/1.1").starts_with("HTTP/1.1 405 "));
+    }
+}
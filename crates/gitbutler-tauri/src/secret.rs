@@ -5,13 +5,13 @@ use tracing::instrument;
 
 use crate::error::Error;
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(err(Debug))]
 pub fn secret_get_global(handle: &str) -> Result<Option<String>, Error> {
     Ok(secret::retrieve(handle, secret::Namespace::Global)?.map(|s| s.0))
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(secret), err(Debug), fields(secret = "<redacted>"))]
 pub fn secret_set_global(handle: &str, secret: String) -> Result<(), Error> {
     static FAIR_QUEUE: Mutex<()> = Mutex::new(());
@@ -0,0 +1,269 @@
+//! How long the most recent operations took, so slowness can be reported with numbers attached.
+//!
+//! Operations are the spans with an `operation` field, like the ones of committing, pushing or computing the
+//! status. Their fields are kept as they were recorded, and those without a `project_id` take it from the
+//! operation they ran in.
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use gitbutler_time::time::now_since_unix_epoch_ms;
+use serde::Serialize;
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// The amount of operations whose timings are kept, dropping the oldest ones first.
+const MAX_TIMINGS: usize = 100;
+
+/// How long an operation took.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationTiming {
+    /// The kind of operation, like `commit`.
+    pub operation: String,
+    /// The name of the span, which is the function that performed the operation.
+    pub name: String,
+    pub project_id: Option<String>,
+    /// The kind of operation this one ran in, if any.
+    pub parent: Option<String>,
+    /// All other fields of the span, like the branch it operated on.
+    pub fields: BTreeMap<String, String>,
+    /// The time at which the operation started, in milliseconds since the Unix epoch.
+    pub started_timestamp_ms: i64,
+    pub duration_ms: f64,
+    /// Whether an error was logged while performing the operation.
+    pub failed: bool,
+}
+
+/// The timings of the most recent operations, oldest first, as recorded by its [`layer()`](Self::layer()).
+#[derive(Debug, Clone, Default)]
+pub struct OperationTimings {
+    timings: Arc<Mutex<VecDeque<OperationTiming>>>,
+}
+
+impl OperationTimings {
+    /// Return a layer that records the timings of operations into these.
+    pub fn layer(&self) -> TimingLayer {
+        TimingLayer {
+            timings: self.clone(),
+        }
+    }
+
+    /// Return the timings of the last `limit` operations, or all that are kept, oldest first.
+    pub fn recent(&self, limit: Option<usize>) -> Vec<OperationTiming> {
+        let timings = self.lock();
+        let skip = timings.len().saturating_sub(limit.unwrap_or(MAX_TIMINGS));
+        timings.iter().skip(skip).cloned().collect()
+    }
+
+    fn push(&self, timing: OperationTiming) {
+        let mut timings = self.lock();
+        if timings.len() == MAX_TIMINGS {
+            timings.pop_front();
+        }
+        timings.push_back(timing);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<OperationTiming>> {
+        self.timings
+            .lock()
+            .expect("no panics while holding the lock")
+    }
+}
+
+/// A [`Layer`] that records the timings of operations once their spans close.
+pub struct TimingLayer {
+    timings: OperationTimings,
+}
+
+/// An operation that is still running, kept with its span.
+struct Running {
+    fields: BTreeMap<String, String>,
+    parent: Option<String>,
+    started: Instant,
+    started_timestamp_ms: i64,
+    failed: bool,
+}
+
+impl<S> Layer<S> for TimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut fields = FieldMap::default();
+        attrs.record(&mut fields);
+        let mut fields = fields.0;
+        if !fields.contains_key("operation") {
+            return;
+        }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut parent = None;
+        for ancestor in span.scope().skip(1) {
+            if let Some(running) = ancestor.extensions().get::<Running>() {
+                parent = running.fields.get("operation").cloned();
+                if let Some(project_id) = running.fields.get("project_id") {
+                    fields
+                        .entry("project_id".to_owned())
+                        .or_insert_with(|| project_id.clone());
+                }
+                break;
+            }
+        }
+        span.extensions_mut().insert(Running {
+            fields,
+            parent,
+            started: Instant::now(),
+            started_timestamp_ms: now_since_unix_epoch_ms(),
+            failed: false,
+        });
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(running) = span.extensions_mut().get_mut::<Running>() {
+            let mut fields = FieldMap::default();
+            values.record(&mut fields);
+            running.fields.extend(fields.0);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if *event.metadata().level() != Level::ERROR {
+            return;
+        }
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        if let Some(running) = span.extensions_mut().get_mut::<Running>() {
+            running.failed = true;
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(mut running) = span.extensions_mut().remove::<Running>() else {
+            return;
+        };
+        self.timings.push(OperationTiming {
+            operation: running.fields.remove("operation").unwrap_or_default(),
+            name: span.name().to_owned(),
+            project_id: running.fields.remove("project_id"),
+            parent: running.parent,
+            fields: running.fields,
+            started_timestamp_ms: running.started_timestamp_ms,
+            duration_ms: running.started.elapsed().as_secs_f64() * 1000.0,
+            failed: running.failed,
+        });
+    }
+}
+
+/// The fields of a span, formatted like they are logged.
+#[derive(Default)]
+struct FieldMap(BTreeMap<String, String>);
+
+impl Visit for FieldMap {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_owned(), value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_owned(), format!("{value:?}"));
+    }
+}
+
+pub mod commands {
+    #![allow(clippy::used_underscore_binding)]
+    use tauri::State;
+    use tracing::instrument;
+
+    use super::{OperationTiming, OperationTimings};
+    use crate::error::Error;
+
+    #[tauri::command]
+    #[instrument(skip(timings), err(Debug))]
+    pub fn recent_operation_timings(
+        timings: State<'_, OperationTimings>,
+        limit: Option<usize>,
+    ) -> Result<Vec<OperationTiming>, Error> {
+        Ok(timings.recent(limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn operations_are_timed_with_the_project_of_the_operation_they_ran_in() {
+        let timings = OperationTimings::default();
+        let subscriber = tracing_subscriber::registry().with(timings.layer());
+        tracing::subscriber::with_default(subscriber, || {
+            let commit = tracing::info_span!(
+                "create_commit",
+                operation = "commit",
+                project_id = "p1",
+                branch_id = tracing::field::Empty
+            );
+            let _commit = commit.enter();
+            commit.record("branch_id", "b1");
+            {
+                let _diff = tracing::info_span!("workdir", operation = "diff").entered();
+                tracing::error!("failed to diff");
+            }
+            let _unrelated = tracing::info_span!("helper").entered();
+        });
+
+        let recent = timings.recent(None);
+        assert_eq!(
+            recent
+                .iter()
+                .map(|timing| (
+                    timing.operation.as_str(),
+                    timing.project_id.as_deref(),
+                    timing.parent.as_deref(),
+                    timing.failed
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("diff", Some("p1"), Some("commit"), true),
+                ("commit", Some("p1"), None, false)
+            ],
+            "spans without an operation aren't timed"
+        );
+        assert_eq!(recent[1].name, "create_commit");
+        assert_eq!(
+            recent[1].fields,
+            BTreeMap::from([("branch_id".to_owned(), "b1".to_owned())])
+        );
+        assert_eq!(timings.recent(Some(1)), &recent[1..]);
+    }
+
+    #[test]
+    fn only_the_most_recent_operations_are_kept() {
+        let timings = OperationTimings::default();
+        let subscriber = tracing_subscriber::registry().with(timings.layer());
+        tracing::subscriber::with_default(subscriber, || {
+            for step in 0..MAX_TIMINGS + 5 {
+                let _span = tracing::info_span!("step", operation = "status", step).entered();
+            }
+        });
+
+        let recent = timings.recent(None);
+        assert_eq!(recent.len(), MAX_TIMINGS);
+        assert_eq!(recent[0].fields["step"], "5");
+    }
+}
@@ -1,16 +1,21 @@
 use std::{collections::HashMap, path::PathBuf};
 
 use anyhow::Context;
+use gitbutler_branch::{BranchId, VirtualBranchesHandle};
+use gitbutler_branch_actions::update_gitbutler_integration;
+use gitbutler_command_context::CommandContext;
 use gitbutler_diff::FileDiff;
-use gitbutler_oplog::{entry::Snapshot, OplogExt};
+use gitbutler_oplog::{
+    entry::Snapshot, GcOutcome, OplogExt, SnapshotBranch, SnapshotsDiff, UndoRedoState,
+};
 use gitbutler_project as projects;
 use gitbutler_project::ProjectId;
 use tauri::State;
 use tracing::instrument;
 
-use crate::error::Error;
+use crate::{error::Error, progress::with_progress};
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(projects), err(Debug))]
 pub fn list_snapshots(
     projects: State<'_, projects::Controller>,
@@ -27,20 +32,82 @@ pub fn list_snapshots(
     Ok(snapshots)
 }
 
-#[tauri::command(async)]
-#[instrument(skip(projects), err(Debug))]
+#[tauri::command]
+#[instrument(skip(projects, handle), err(Debug))]
 pub fn restore_snapshot(
     projects: State<'_, projects::Controller>,
     handle: tauri::AppHandle,
     project_id: ProjectId,
     sha: String,
+    operation_id: Option<String>,
+) -> Result<(), Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let snapshot = sha.parse().map_err(anyhow::Error::from)?;
+    with_progress(&handle, project_id, operation_id, |_progress| {
+        project.restore_snapshot(snapshot)
+    })?;
+    Ok(())
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn undo(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+) -> Result<Option<String>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(project.undo()?)
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn redo(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+) -> Result<Option<String>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(project.redo()?)
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn undo_redo_state(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+) -> Result<UndoRedoState, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(project.undo_redo_state()?)
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn restore_snapshot_paths(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    sha: String,
+    paths: Vec<PathBuf>,
+) -> Result<(), Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    project.restore_paths(sha.parse().map_err(anyhow::Error::from)?, &paths)?;
+    Ok(())
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn restore_snapshot_branch(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    sha: String,
+    branch_id: BranchId,
 ) -> Result<(), Error> {
     let project = projects.get(project_id).context("failed to get project")?;
-    project.restore_snapshot(sha.parse().map_err(anyhow::Error::from)?)?;
+    project.restore_branch(sha.parse().map_err(anyhow::Error::from)?, branch_id)?;
+    let ctx = CommandContext::open(&project)?;
+    update_gitbutler_integration(&VirtualBranchesHandle::new(project.gb_dir()), &ctx)?;
     Ok(())
 }
 
-#[tauri::command(async)]
+#[tauri::command]
 #[instrument(skip(projects), err(Debug))]
 pub fn snapshot_diff(
     projects: State<'_, projects::Controller>,
@@ -51,3 +118,93 @@ pub fn snapshot_diff(
     let diff = project.snapshot_diff(sha.parse().map_err(anyhow::Error::from)?)?;
     Ok(diff)
 }
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn diff_snapshots(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    old_sha: String,
+    new_sha: String,
+) -> Result<SnapshotsDiff, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let diff = project.diff_snapshots(
+        old_sha.parse().map_err(anyhow::Error::from)?,
+        new_sha.parse().map_err(anyhow::Error::from)?,
+    )?;
+    Ok(diff)
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn snapshot_branches(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    sha: String,
+) -> Result<Vec<SnapshotBranch>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let view = project.snapshot_view(sha.parse().map_err(anyhow::Error::from)?)?;
+    Ok(view.branches()?)
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn snapshot_files(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    sha: String,
+) -> Result<Vec<PathBuf>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let view = project.snapshot_view(sha.parse().map_err(anyhow::Error::from)?)?;
+    Ok(view.files()?)
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn snapshot_file(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    sha: String,
+    path: PathBuf,
+) -> Result<Option<String>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let view = project.snapshot_view(sha.parse().map_err(anyhow::Error::from)?)?;
+    let contents = view.file(&path)?;
+    Ok(contents.map(|contents| String::from_utf8_lossy(&contents).into_owned()))
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn snapshot_branch_diff(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    sha: String,
+    branch_id: BranchId,
+) -> Result<HashMap<PathBuf, FileDiff>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let view = project.snapshot_view(sha.parse().map_err(anyhow::Error::from)?)?;
+    Ok(view.branch_diff(branch_id)?)
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn snapshot_commit_diff(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+    sha: String,
+    commit_id: String,
+) -> Result<HashMap<PathBuf, FileDiff>, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    let view = project.snapshot_view(sha.parse().map_err(anyhow::Error::from)?)?;
+    Ok(view.commit_diff(commit_id.parse().map_err(anyhow::Error::from)?)?)
+}
+
+#[tauri::command]
+#[instrument(skip(projects), err(Debug))]
+pub fn oplog_gc(
+    projects: State<'_, projects::Controller>,
+    project_id: ProjectId,
+) -> Result<GcOutcome, Error> {
+    let project = projects.get(project_id).context("failed to get project")?;
+    Ok(project.gc()?)
+}
@@ -6,7 +6,7 @@ pub mod commands {
 
     use crate::error::Error;
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(login), err(Debug))]
     pub fn get_user(login: State<'_, Controller>) -> Result<Option<UserWithSecrets>, Error> {
         match login.get_user()? {
@@ -21,14 +21,14 @@ pub mod commands {
         }
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(login), err(Debug))]
     pub fn set_user(login: State<'_, Controller>, user: User) -> Result<User, Error> {
         login.set_user(&user)?;
         Ok(user)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(login), err(Debug))]
     pub fn delete_user(login: State<'_, Controller>) -> Result<(), Error> {
         login.delete_user()?;
@@ -1,31 +1,53 @@
 pub mod commands {
+    use std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    };
+
     use anyhow::{anyhow, Context};
     use gitbutler_branch::{
-        BranchCreateRequest, BranchId, BranchOwnershipClaims, BranchUpdateRequest,
+        BranchCreateRequest, BranchEvent, BranchId, BranchOwnershipClaims, BranchUpdateRequest,
+        CommitProvenance, FsckReport, HunkPin, QueuedOperation, QueuedOperationId, Shelf, ShelfId,
+        TrashEntry, TrashEntryId,
     };
     use gitbutler_branch_actions::{
-        BaseBranch, BranchListing, BranchListingDetails, BranchListingFilter, RemoteBranch,
-        RemoteBranchData, RemoteBranchFile, VirtualBranchActions, VirtualBranches,
+        conflicts::{ConflictSide, ConflictedFile, Resolution},
+        AbsorbOutcome, AmendRequest, AutomationRun, BaseBranch, BaseUpdateStrategy, BlameHunk,
+        BranchDependency, BranchListing, BranchListingDetails, BranchListingFilter,
+        BulkBranchResult, CheckoutPreview, CherryPickOutcome, CommitGraph, CommitPreview,
+        CommitTemplate, ContentMatch, ExportOutcome, ExportUncommitted, FileHistoryEntry,
+        FileStatus, HistoryFilter, HistoryPage, HunkGroup, Identity, IgnoreCheck, IgnoreFile,
+        ImportPatchOptions, IntegrationDivergence, IntegrationOutcome, IntegrationStrategy,
+        LayoutOutcome, Leftover, MergeOrderSimulation, NestedRepository, OwnershipConflict,
+        PartialCheckout, PatchFormat, PatchImportOutcome, PendingCleanup, PendingOperation,
+        PredictedConflict, PushPreview, Reconciliation, RecoveryOption, RemoteBranch,
+        RemoteBranchActivity, RemoteBranchData, RemoteBranchFile, ReorderOutcome,
+        ReplayedOperation, RevertOutcome, SetupPlan, StashEntry, StashImport, StatusTrace,
+        Submodule, SwitchedBranch, Tag, VirtualBranchActions, VirtualBranches, WorkspaceOwnership,
     };
     use gitbutler_command_context::CommandContext;
-    use gitbutler_error::error::Code;
+    use gitbutler_diff::{filter::EolChange, DiffOptions, Hunk, HunkSelection, RangeSet};
+    use gitbutler_error::error::{AnyhowContextExt, Code};
+    use gitbutler_oplog::{AuditEntry, AuditQuery};
     use gitbutler_project as projects;
     use gitbutler_project::{FetchResult, ProjectId};
     use gitbutler_reference::{
-        normalize_branch_name as normalize_name, ReferenceName, Refname, RemoteRefname,
+        normalize_branch_name as normalize_name, LocalRefname, ReferenceName, Refname,
+        RemoteRefname,
     };
-    use tauri::State;
+    use gitbutler_repo::Deepen;
+    use tauri::{AppHandle, State};
     use tracing::instrument;
 
-    use crate::{error::Error, WindowState};
+    use crate::{error::Error, progress::with_progress, WindowState};
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(err(Debug))]
     pub fn normalize_branch_name(name: &str) -> Result<String, Error> {
         Ok(normalize_name(name)?)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
     pub fn commit_virtual_branch(
         windows: State<'_, WindowState>,
@@ -34,29 +56,34 @@ pub mod commands {
         branch: BranchId,
         message: &str,
         ownership: Option<BranchOwnershipClaims>,
+        selections: Option<BTreeMap<PathBuf, Vec<HunkSelection>>>,
         run_hooks: bool,
+        allow_secrets: Option<bool>,
     ) -> Result<String, Error> {
         let project = projects.get(project_id)?;
-        let oid = VirtualBranchActions.create_commit(
+        let oid = VirtualBranchActions.create_commit_with_selections(
             &project,
             branch,
             message,
             ownership.as_ref(),
+            &selections.unwrap_or_default(),
             run_hooks,
+            allow_secrets.unwrap_or_default(),
         )?;
         emit_vbranches(&windows, project_id);
         Ok(oid.to_string())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
     pub fn list_virtual_branches(
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
+        options: Option<DiffOptions>,
     ) -> Result<VirtualBranches, Error> {
         let project = projects.get(project_id)?;
         VirtualBranchActions
-            .list_virtual_branches(&project)
+            .list_virtual_branches_with_options(&project, &options.unwrap_or_default())
             .map_err(Into::into)
             .map(|(branches, skipped_files)| VirtualBranches {
                 branches,
@@ -64,7 +91,7 @@ pub mod commands {
             })
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
     pub fn create_virtual_branch(
         windows: State<'_, WindowState>,
@@ -78,7 +105,7 @@ pub mod commands {
         Ok(branch_id)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
     pub fn delete_local_branch(
         windows: State<'_, WindowState>,
@@ -93,7 +120,125 @@ pub mod commands {
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_pending_branch_cleanups(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<PendingCleanup>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_pending_cleanups(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn clean_up_branches(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        names: Vec<String>,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        VirtualBranchActions.clean_up_branches(&project, &names)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn delete_virtual_branches(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_ids: Vec<BranchId>,
+    ) -> Result<Vec<BulkBranchResult>, Error> {
+        let project = projects.get(project_id)?;
+        let results = VirtualBranchActions.delete_virtual_branches(&project, &branch_ids)?;
+        emit_vbranches(&windows, project_id);
+        Ok(results)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn unapply_all_branches(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<BulkBranchResult>, Error> {
+        let project = projects.get(project_id)?;
+        let results = VirtualBranchActions.unapply_all_branches(&project)?;
+        emit_vbranches(&windows, project_id);
+        Ok(results)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows, handle), err(Debug))]
+    pub fn apply_branches(
+        handle: AppHandle,
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branches: Vec<Refname>,
+        operation_id: Option<String>,
+    ) -> Result<Vec<BulkBranchResult>, Error> {
+        let project = projects.get(project_id)?;
+        let results = with_progress(&handle, project_id, operation_id, |progress| {
+            VirtualBranchActions.apply_branches_with_progress(&project, &branches, progress)
+        })?;
+        emit_vbranches(&windows, project_id);
+        Ok(results)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn archive_integrated_branches(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<BulkBranchResult>, Error> {
+        let project = projects.get(project_id)?;
+        let results = VirtualBranchActions.archive_integrated_branches(&project)?;
+        emit_vbranches(&windows, project_id);
+        Ok(results)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn pending_operation(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Option<PendingOperation>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.pending_operation(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn resume_operation(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        VirtualBranchActions.resume_operation(&project)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn roll_back_operation(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        VirtualBranchActions.roll_back_operation(&project)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
     pub fn create_virtual_branch_from_branch(
         windows: State<'_, WindowState>,
@@ -109,7 +254,43 @@ pub mod commands {
         Ok(branch_id)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn preview_apply_branch(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch: Refname,
+    ) -> Result<CheckoutPreview, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.preview_apply_branch(&project, &branch)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn preview_unapply_branch(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+    ) -> Result<CheckoutPreview, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.preview_unapply_branch(&project, branch_id)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn adopt_branch(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        name: String,
+    ) -> Result<BranchId, Error> {
+        let project = projects.get(project_id)?;
+        let branch_id = VirtualBranchActions.adopt_branch(&project, &name)?;
+        emit_vbranches(&windows, project_id);
+        Ok(branch_id)
+    }
+
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
     pub fn integrate_upstream_commits(
         windows: State<'_, WindowState>,
@@ -123,7 +304,22 @@ pub mod commands {
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn integrate_upstream(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch: BranchId,
+        strategy: IntegrationStrategy,
+    ) -> Result<IntegrationOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let outcome = VirtualBranchActions.integrate_upstream(&project, branch, strategy)?;
+        emit_vbranches(&windows, project_id);
+        Ok(outcome)
+    }
+
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
     pub fn get_base_branch_data(
         projects: State<'_, projects::Controller>,
@@ -137,7 +333,36 @@ pub mod commands {
         }
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn plan_setup(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        action: Option<String>,
+    ) -> Result<SetupPlan, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.plan_setup(
+            &project,
+            Some(action.unwrap_or_else(|| "setup".to_string())),
+        )?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn set_up_project(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        target_branch: RemoteRefname,
+        import: Vec<LocalRefname>,
+    ) -> Result<BaseBranch, Error> {
+        let project = projects.get(project_id)?;
+        let base_branch = VirtualBranchActions.set_up_project(&project, &target_branch, &import)?;
+        emit_vbranches(&windows, project_id);
+        Ok(base_branch)
+    }
+
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
     pub fn set_base_branch(
         windows: State<'_, WindowState>,
@@ -160,386 +385,1820 @@ pub mod commands {
         Ok(base_branch)
     }
 
-    #[tauri::command(async)]
-    #[instrument(skip(projects, windows), err(Debug))]
+    #[tauri::command]
+    #[instrument(skip(projects, windows, handle), err(Debug))]
     pub fn update_base_branch(
+        handle: AppHandle,
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
+        strategy: Option<BaseUpdateStrategy>,
+        operation_id: Option<String>,
     ) -> Result<Vec<ReferenceName>, Error> {
         let project = projects.get(project_id)?;
-        let unapplied_branches = VirtualBranchActions.update_base_branch(&project)?;
+        let (unapplied_branches, remap) =
+            with_progress(&handle, project_id, operation_id, |progress| {
+                VirtualBranchActions.update_base_branch_with_progress(
+                    &project,
+                    strategy.unwrap_or_default(),
+                    progress,
+                )
+            })?;
+        if !remap.is_empty() {
+            if let Err(error) = windows.post(gitbutler_watcher::Action::ReportOwnershipRemap(
+                project_id, remap,
+            )) {
+                tracing::error!(?error);
+            }
+        }
         emit_vbranches(&windows, project_id);
         Ok(unapplied_branches)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn update_virtual_branch(
+    pub fn switch_base_branch(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branch: BranchUpdateRequest,
-    ) -> Result<(), Error> {
+        branch: &str,
+    ) -> Result<Vec<SwitchedBranch>, Error> {
         let project = projects.get(project_id)?;
-        VirtualBranchActions.update_virtual_branch(&project, branch)?;
-
+        let branch_name = format!("refs/remotes/{}", branch)
+            .parse()
+            .context("Invalid branch name")?;
+        let switched = VirtualBranchActions.switch_base_branch(&project, &branch_name)?;
         emit_vbranches(&windows, project_id);
-        Ok(())
+        Ok(switched)
     }
 
-    #[tauri::command(async)]
-    #[instrument(skip(projects, windows), err(Debug))]
-    pub fn update_branch_order(
-        windows: State<'_, WindowState>,
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn remote_default_branch_change(
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branches: Vec<BranchUpdateRequest>,
-    ) -> Result<(), Error> {
+    ) -> Result<Option<RemoteRefname>, Error> {
         let project = projects.get(project_id)?;
-        VirtualBranchActions.update_branch_order(&project, branches)?;
-        emit_vbranches(&windows, project_id);
-        Ok(())
+        Ok(VirtualBranchActions.remote_default_branch_change(&project)?)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn delete_virtual_branch(
+    pub fn migrate_target_to_remote_default(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branch_id: BranchId,
-    ) -> Result<(), Error> {
+    ) -> Result<Vec<SwitchedBranch>, Error> {
         let project = projects.get(project_id)?;
-        VirtualBranchActions.delete_virtual_branch(&project, branch_id)?;
+        let switched = VirtualBranchActions.migrate_target_to_remote_default(&project)?;
         emit_vbranches(&windows, project_id);
-        Ok(())
+        Ok(switched)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn convert_to_real_branch(
+    pub fn set_branch_push_remote(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branch: BranchId,
+        branch_id: BranchId,
+        remote_name: Option<&str>,
     ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        VirtualBranchActions.convert_to_real_branch(&project, branch)?;
+        VirtualBranchActions.set_branch_push_remote(&project, branch_id, remote_name)?;
         emit_vbranches(&windows, project_id);
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn unapply_ownership(
+    pub fn pin_branch_base(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        ownership: BranchOwnershipClaims,
+        branch_id: BranchId,
+        base: String,
     ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        VirtualBranchActions.unapply_ownership(&project, &ownership)?;
+        let base = git2::Oid::from_str(&base).map_err(|e| anyhow!(e))?;
+        VirtualBranchActions.pin_branch_base(&project, branch_id, base)?;
         emit_vbranches(&windows, project_id);
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn reset_files(
+    pub fn rebase_branch_onto_target(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        files: &str,
+        branch_id: BranchId,
     ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        // convert files to Vec<String>
-        let files = files
-            .split('\n')
-            .map(std::string::ToString::to_string)
-            .collect::<Vec<String>>();
-        VirtualBranchActions.reset_files(&project, &files)?;
+        VirtualBranchActions.rebase_branch_onto_target(&project, branch_id)?;
         emit_vbranches(&windows, project_id);
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn push_virtual_branch(
+    pub fn stack_branch(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
         branch_id: BranchId,
-        with_force: bool,
+        parent_id: BranchId,
     ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        VirtualBranchActions
-            .push_virtual_branch(&project, branch_id, with_force, Some(Some(branch_id)))
-            .map_err(|err| err.context(Code::Unknown))?;
+        VirtualBranchActions.stack_branch(&project, branch_id, parent_id)?;
         emit_vbranches(&windows, project_id);
         Ok(())
     }
 
-    #[tauri::command(async)]
-    #[instrument(skip(projects), err(Debug))]
-    pub fn can_apply_remote_branch(
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn update_virtual_branch(
+        windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branch: RemoteRefname,
-    ) -> Result<bool, Error> {
+        branch: BranchUpdateRequest,
+    ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        Ok(VirtualBranchActions.can_apply_remote_branch(&project, &branch)?)
+        VirtualBranchActions.update_virtual_branch(&project, branch)?;
+
+        emit_vbranches(&windows, project_id);
+        Ok(())
     }
 
-    #[tauri::command(async)]
-    #[instrument(skip(projects), err(Debug))]
-    pub fn list_remote_commit_files(
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn update_branch_order(
+        windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        commit_oid: String,
-    ) -> Result<Vec<RemoteBranchFile>, Error> {
+        branches: Vec<BranchUpdateRequest>,
+    ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
-        VirtualBranchActions
-            .list_remote_commit_files(&project, commit_oid)
-            .map_err(Into::into)
+        VirtualBranchActions.update_branch_order(&project, branches)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn reset_virtual_branch(
+    pub fn delete_virtual_branch(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
         branch_id: BranchId,
-        target_commit_oid: String,
     ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        let target_commit_oid = git2::Oid::from_str(&target_commit_oid).map_err(|e| anyhow!(e))?;
-        VirtualBranchActions.reset_virtual_branch(&project, branch_id, target_commit_oid)?;
+        VirtualBranchActions.delete_virtual_branch(&project, branch_id)?;
         emit_vbranches(&windows, project_id);
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn amend_virtual_branch(
+    pub fn convert_to_real_branch(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branch_id: BranchId,
-        commit_oid: String,
-        ownership: BranchOwnershipClaims,
-    ) -> Result<String, Error> {
+        branch: BranchId,
+    ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
-        let oid = VirtualBranchActions.amend(&project, branch_id, commit_oid, &ownership)?;
+        VirtualBranchActions.convert_to_real_branch(&project, branch)?;
         emit_vbranches(&windows, project_id);
-        Ok(oid.to_string())
+        Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn move_commit_file(
+    pub fn unapply_ownership(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branch_id: BranchId,
-        from_commit_oid: String,
-        to_commit_oid: String,
         ownership: BranchOwnershipClaims,
-    ) -> Result<String, Error> {
+    ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        let from_commit_oid = git2::Oid::from_str(&from_commit_oid).map_err(|e| anyhow!(e))?;
-        let to_commit_oid = git2::Oid::from_str(&to_commit_oid).map_err(|e| anyhow!(e))?;
-        let oid = VirtualBranchActions.move_commit_file(
-            &project,
-            branch_id,
-            from_commit_oid,
-            to_commit_oid,
-            &ownership,
-        )?;
+        VirtualBranchActions.unapply_ownership(&project, &ownership)?;
         emit_vbranches(&windows, project_id);
-        Ok(oid.to_string())
+        Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn undo_commit(
+    pub fn discard_hunk(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branch_id: BranchId,
-        commit_oid: String,
+        path: PathBuf,
+        hunk_id: String,
     ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
-        VirtualBranchActions.undo_commit(&project, branch_id, commit_oid)?;
+        VirtualBranchActions.discard_hunk(&project, &path, &hunk_id)?;
         emit_vbranches(&windows, project_id);
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn insert_blank_commit(
+    pub fn discard_lines(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branch_id: BranchId,
-        commit_oid: String,
-        offset: i32,
+        path: PathBuf,
+        hunk_id: String,
+        lines: RangeSet,
     ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
-        VirtualBranchActions.insert_blank_commit(&project, branch_id, commit_oid, offset)?;
+        VirtualBranchActions.discard_lines(&project, &path, &hunk_id, &lines)?;
         emit_vbranches(&windows, project_id);
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn reorder_commit(
+    pub fn reset_files(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branch_id: BranchId,
-        commit_oid: String,
-        offset: i32,
+        files: &str,
     ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
-        VirtualBranchActions.reorder_commit(&project, branch_id, commit_oid, offset)?;
+        // convert files to Vec<String>
+        let files = files
+            .split('\n')
+            .map(std::string::ToString::to_string)
+            .collect::<Vec<String>>();
+        VirtualBranchActions.reset_files(&project, &files)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows, handle), err(Debug))]
+    pub fn push_virtual_branch(
+        handle: AppHandle,
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        with_force: bool,
+        operation_id: Option<String>,
+        allow_secrets: Option<bool>,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        with_progress(&handle, project_id, operation_id, |progress| {
+            VirtualBranchActions.push_virtual_branch_with_progress(
+                &project,
+                branch_id,
+                with_force,
+                false,
+                allow_secrets.unwrap_or_default(),
+                Some(Some(branch_id)),
+                progress,
+            )
+        })
+        .map_err(keep_push_rejection_code)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows, handle), err(Debug))]
+    pub fn push_virtual_branch_with_lease(
+        handle: AppHandle,
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        operation_id: Option<String>,
+        allow_secrets: Option<bool>,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        with_progress(&handle, project_id, operation_id, |progress| {
+            VirtualBranchActions.push_virtual_branch_with_progress(
+                &project,
+                branch_id,
+                true,
+                true,
+                allow_secrets.unwrap_or_default(),
+                Some(Some(branch_id)),
+                progress,
+            )
+        })
+        .map_err(keep_push_rejection_code)?;
         emit_vbranches(&windows, project_id);
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn push_virtual_branch_or_queue(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        with_force: bool,
+    ) -> Result<Option<QueuedOperation>, Error> {
+        let project = projects.get(project_id)?;
+        let queued = VirtualBranchActions
+            .push_virtual_branch_or_queue(&project, branch_id, with_force, Some(Some(branch_id)))
+            .map_err(keep_push_rejection_code)?;
+        emit_vbranches(&windows, project_id);
+        Ok(queued)
+    }
+
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
-    pub fn list_remote_branches(
+    pub fn list_queued_operations(
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-    ) -> Result<Vec<RemoteBranch>, Error> {
+    ) -> Result<Vec<QueuedOperation>, Error> {
         let project = projects.get(project_id)?;
-        let branches = VirtualBranchActions::list_remote_branches(project)?;
-        Ok(branches)
+        Ok(VirtualBranchActions.list_queued_operations(&project)?)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
-    pub fn list_branches(
+    pub fn cancel_queued_operation(
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        filter: Option<BranchListingFilter>,
-    ) -> Result<Vec<BranchListing>, Error> {
-        let ctx = CommandContext::open(&projects.get(project_id)?)?;
-        let branches = gitbutler_branch_actions::list_branches(&ctx, filter, None)?;
-        Ok(branches)
+        operation_id: QueuedOperationId,
+    ) -> Result<QueuedOperation, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.cancel_queued_operation(&project, operation_id)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn replay_queued_operations(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<ReplayedOperation>, Error> {
+        let project = projects.get(project_id)?;
+        let replayed = VirtualBranchActions.replay_queued_operations(&project)?;
+        emit_vbranches(&windows, project_id);
+        Ok(replayed)
+    }
+
+    /// Hide the code of push errors, except for rejections, cancellations, secrets and a missing network
+    /// which the frontend handles on their own.
+    fn keep_push_rejection_code(err: anyhow::Error) -> anyhow::Error {
+        if matches!(
+            err.custom_context().map(|ctx| ctx.code),
+            Some(Code::PushRejected | Code::Cancelled | Code::SecretsDetected | Code::Offline)
+        ) {
+            err
+        } else {
+            err.context(Code::Unknown)
+        }
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
-    pub fn get_branch_listing_details(
+    pub fn push_preview(
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branch_names: Vec<String>,
-    ) -> Result<Vec<BranchListingDetails>, Error> {
-        let ctx = CommandContext::open(&projects.get(project_id)?)?;
-        let branches = gitbutler_branch_actions::get_branch_listing_details(&ctx, branch_names)?;
-        Ok(branches)
+        branch_id: BranchId,
+    ) -> Result<PushPreview, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.push_preview(&project, branch_id)?)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
-    pub fn get_remote_branch_data(
+    pub fn validate_remote_branch_name(
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        refname: Refname,
-    ) -> Result<RemoteBranchData, Error> {
+        name: &str,
+    ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        let branch_data = VirtualBranchActions.get_remote_branch_data(&project, &refname)?;
-        Ok(branch_data)
+        Ok(VirtualBranchActions.validate_remote_branch_name(&project, name)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_commit_template(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Option<CommitTemplate>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.commit_template(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn check_commit_message(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        message: &str,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.check_commit_message(&project, message)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn commit_preview(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch: BranchId,
+        message: &str,
+        ownership: Option<BranchOwnershipClaims>,
+    ) -> Result<CommitPreview, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.commit_preview(&project, branch, message, ownership.as_ref())?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn generate_commit_message(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch: BranchId,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.generate_commit_message(&project, branch)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, api_key), err(Debug))]
+    pub fn set_message_generation_api_key(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        api_key: &str,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.set_message_generation_api_key(&project, api_key)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_branch_metadata(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+    ) -> Result<BTreeMap<String, serde_json::Value>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.branch_metadata(&project, branch_id)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, value), err(Debug))]
+    pub fn set_branch_metadata(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        key: &str,
+        value: Option<serde_json::Value>,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.set_branch_metadata(&project, branch_id, key, value.as_ref())?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn is_based_on_target(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+    ) -> Result<bool, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.is_based_on_target(&project, branch_id)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn verify_integration(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<IntegrationDivergence>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.verify_integration(&project)?)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn squash_branch_commit(
+    pub fn reconcile_external_changes(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        branch_id: BranchId,
-        target_commit_oid: String,
+    ) -> Result<Reconciliation, Error> {
+        let project = projects.get(project_id)?;
+        let reconciliation = VirtualBranchActions.reconcile_external_changes(&project)?;
+        if matches!(reconciliation, Reconciliation::Folded { .. }) {
+            emit_vbranches(&windows, project_id);
+        }
+        Ok(reconciliation)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn recover_from_external_change(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        option: RecoveryOption,
     ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        let target_commit_oid = git2::Oid::from_str(&target_commit_oid).map_err(|e| anyhow!(e))?;
-        VirtualBranchActions.squash(&project, branch_id, target_commit_oid)?;
+        VirtualBranchActions.recover_from_external_change(&project, option)?;
         emit_vbranches(&windows, project_id);
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects), err(Debug))]
-    pub fn fetch_from_remotes(
+    pub fn repair_upstream_config(
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        action: Option<String>,
-    ) -> Result<BaseBranch, Error> {
+    ) -> Result<Vec<String>, Error> {
         let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.repair_upstream_config(&project)?)
+    }
 
-        let project_data_last_fetched = VirtualBranchActions.fetch_from_remotes(
-            &project,
-            Some(action.unwrap_or_else(|| "unknown".to_string())),
-        )?;
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn create_tag(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        name: String,
+        target: String,
+        message: Option<String>,
+        sign: bool,
+    ) -> Result<Tag, Error> {
+        let project = projects.get(project_id)?;
+        let target = git2::Oid::from_str(&target).map_err(|e| anyhow!(e))?;
+        Ok(VirtualBranchActions.create_tag(&project, &name, target, message.as_deref(), sign)?)
+    }
 
-        // Updates the project controller with the last fetched timestamp
-        //
-        // TODO: This cross dependency likely indicates that last_fetched is stored in the wrong place - value is coupled with virtual branches state
-        projects
-            .update(&projects::UpdateRequest {
-                id: project.id,
-                project_data_last_fetched: Some(project_data_last_fetched.clone()),
-                ..Default::default()
-            })
-            .context("failed to update project with last fetched timestamp")?;
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_tags(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<Tag>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_tags(&project)?)
+    }
 
-        if let FetchResult::Error { error, .. } = project_data_last_fetched {
-            return Err(anyhow!(error).into());
-        }
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn push_tag(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        name: String,
+        remote: Option<String>,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        VirtualBranchActions.push_tag(&project, &name, remote.as_deref(), Some(None))?;
+        Ok(())
+    }
 
-        let base_branch = VirtualBranchActions::get_base_branch_data(&project)?;
-        Ok(base_branch)
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn can_apply_remote_branch(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch: RemoteRefname,
+    ) -> Result<bool, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.can_apply_remote_branch(&project, &branch)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_remote_commit_files(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        commit_oid: String,
+        options: Option<DiffOptions>,
+    ) -> Result<Vec<RemoteBranchFile>, Error> {
+        let project = projects.get(project_id)?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        VirtualBranchActions
+            .list_remote_commit_files(&project, commit_oid, &options.unwrap_or_default())
+            .map_err(Into::into)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn move_commit(
+    pub fn reset_virtual_branch(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
-        commit_oid: String,
-        target_branch_id: BranchId,
+        branch_id: BranchId,
+        target_commit_oid: String,
     ) -> Result<(), Error> {
         let project = projects.get(project_id)?;
-        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
-        VirtualBranchActions.move_commit(&project, target_branch_id, commit_oid)?;
+        let target_commit_oid = git2::Oid::from_str(&target_commit_oid).map_err(|e| anyhow!(e))?;
+        VirtualBranchActions.reset_virtual_branch(&project, branch_id, target_commit_oid)?;
         emit_vbranches(&windows, project_id);
         Ok(())
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(projects, windows), err(Debug))]
-    pub fn update_commit_message(
+    pub fn amend_virtual_branch(
         windows: State<'_, WindowState>,
         projects: State<'_, projects::Controller>,
         project_id: ProjectId,
         branch_id: BranchId,
         commit_oid: String,
-        message: &str,
-    ) -> Result<(), Error> {
+        ownership: BranchOwnershipClaims,
+        rewrite_pushed: bool,
+    ) -> Result<String, Error> {
         let project = projects.get(project_id)?;
         let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
-        VirtualBranchActions.update_commit_message(&project, branch_id, commit_oid, message)?;
+        let oid = VirtualBranchActions.amend(
+            &project,
+            branch_id,
+            commit_oid,
+            &ownership,
+            rewrite_pushed,
+        )?;
         emit_vbranches(&windows, project_id);
-        Ok(())
+        Ok(oid.to_string())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn amend_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        commit_oid: String,
+        request: AmendRequest,
+        rewrite_pushed: bool,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        let oid = VirtualBranchActions.amend_commit(
+            &project,
+            branch_id,
+            commit_oid,
+            &request,
+            rewrite_pushed,
+        )?;
+        emit_vbranches(&windows, project_id);
+        Ok(oid.to_string())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn move_hunk_to_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        file_path: &Path,
+        hunk_id: &str,
+        commit_oid: String,
+        rewrite_pushed: bool,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        let oid = VirtualBranchActions.move_hunk_to_commit(
+            &project,
+            branch_id,
+            file_path,
+            hunk_id,
+            commit_oid,
+            rewrite_pushed,
+        )?;
+        emit_vbranches(&windows, project_id);
+        Ok(oid.to_string())
+    }
+
+    /// Add each uncommitted hunk of a branch to the most recent of its commits that last touched its lines.
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn absorb_hunks(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        rewrite_pushed: bool,
+    ) -> Result<AbsorbOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let outcome = VirtualBranchActions.absorb(&project, branch_id, rewrite_pushed)?;
+        emit_vbranches(&windows, project_id);
+        Ok(outcome)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn move_commit_file(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        from_commit_oid: String,
+        to_commit_oid: String,
+        ownership: BranchOwnershipClaims,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let from_commit_oid = git2::Oid::from_str(&from_commit_oid).map_err(|e| anyhow!(e))?;
+        let to_commit_oid = git2::Oid::from_str(&to_commit_oid).map_err(|e| anyhow!(e))?;
+        let oid = VirtualBranchActions.move_commit_file(
+            &project,
+            branch_id,
+            from_commit_oid,
+            to_commit_oid,
+            &ownership,
+        )?;
+        emit_vbranches(&windows, project_id);
+        Ok(oid.to_string())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn undo_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        commit_oid: String,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        VirtualBranchActions.undo_commit(&project, branch_id, commit_oid)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn insert_blank_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        commit_oid: String,
+        offset: i32,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        VirtualBranchActions.insert_blank_commit(&project, branch_id, commit_oid, offset)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn reorder_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        commit_oid: String,
+        offset: i32,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        VirtualBranchActions.reorder_commit(&project, branch_id, commit_oid, offset)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn reorder_commits(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        new_order: Vec<String>,
+    ) -> Result<ReorderOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let new_order = new_order
+            .iter()
+            .map(|oid| git2::Oid::from_str(oid).map_err(|e| anyhow!(e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outcome = VirtualBranchActions.reorder_commits(&project, branch_id, &new_order)?;
+        emit_vbranches(&windows, project_id);
+        Ok(outcome)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn cherry_pick(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        commits: Vec<String>,
+    ) -> Result<CherryPickOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let commits = commits
+            .iter()
+            .map(|oid| git2::Oid::from_str(oid).map_err(|e| anyhow!(e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outcome = VirtualBranchActions.cherry_pick(&project, branch_id, &commits)?;
+        emit_vbranches(&windows, project_id);
+        Ok(outcome)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn revert_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        commit_oid: String,
+    ) -> Result<RevertOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        let outcome = VirtualBranchActions.revert_commit(&project, branch_id, commit_oid)?;
+        emit_vbranches(&windows, project_id);
+        Ok(outcome)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_remote_branches(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<RemoteBranch>, Error> {
+        let project = projects.get(project_id)?;
+        let branches = VirtualBranchActions::list_remote_branches(project)?;
+        Ok(branches)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_branches(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        filter: Option<BranchListingFilter>,
+    ) -> Result<Vec<BranchListing>, Error> {
+        let ctx = CommandContext::open(&projects.get(project_id)?)?;
+        let branches = gitbutler_branch_actions::list_branches(&ctx, filter, None)?;
+        Ok(branches)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_branch_listing_details(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_names: Vec<String>,
+    ) -> Result<Vec<BranchListingDetails>, Error> {
+        let ctx = CommandContext::open(&projects.get(project_id)?)?;
+        let branches = gitbutler_branch_actions::get_branch_listing_details(&ctx, branch_names)?;
+        Ok(branches)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_remote_branch_activity(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<RemoteBranchActivity>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_remote_branch_activity(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_commit_graph(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        limit: usize,
+    ) -> Result<CommitGraph, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.commit_graph(&project, limit)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_log(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        reference: RemoteRefname,
+        cursor: Option<String>,
+        limit: usize,
+        filter: HistoryFilter,
+    ) -> Result<HistoryPage, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.log(&project, &reference, cursor.as_deref(), limit, &filter)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn blame_file(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        path: &Path,
+    ) -> Result<Vec<BlameHunk>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.blame(&project, path)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_file_history(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        path: &Path,
+        limit: usize,
+    ) -> Result<Vec<FileHistoryEntry>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.file_history(&project, path, limit)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn search_content(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        pattern: &str,
+        limit: usize,
+    ) -> Result<Vec<ContentMatch>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.search_content(&project, pattern, limit)?)
+    }
+
+    /// Return who commits of the project are created by and how they are signed, with the keys that
+    /// have to be set before committing if it's incomplete.
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_identity(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Identity, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.identity(&project)?)
+    }
+
+    /// Set the name and email commits of the project are authored with in the configuration of its repository,
+    /// where `None` leaves a value as it is and an empty value removes it.
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn set_identity(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        name: Option<&str>,
+        email: Option<&str>,
+    ) -> Result<Identity, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.set_identity(&project, name, email)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_remote_branch_data(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        refname: Refname,
+    ) -> Result<RemoteBranchData, Error> {
+        let project = projects.get(project_id)?;
+        let branch_data = VirtualBranchActions.get_remote_branch_data(&project, &refname)?;
+        Ok(branch_data)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn squash_branch_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        target_commit_oid: String,
+        rewrite_pushed: bool,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let target_commit_oid = git2::Oid::from_str(&target_commit_oid).map_err(|e| anyhow!(e))?;
+        VirtualBranchActions.squash(&project, branch_id, target_commit_oid, rewrite_pushed)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn squash_commits(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        commits: Vec<String>,
+        into: String,
+        new_message: Option<String>,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let commits = commits
+            .iter()
+            .map(|oid| git2::Oid::from_str(oid).map_err(|e| anyhow!(e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let into = git2::Oid::from_str(&into).map_err(|e| anyhow!(e))?;
+        let new_head = VirtualBranchActions.squash_commits(
+            &project,
+            branch_id,
+            &commits,
+            into,
+            new_message.as_deref(),
+        )?;
+        emit_vbranches(&windows, project_id);
+        Ok(new_head.to_string())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn split_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        commit_oid: String,
+        groups: Vec<BranchOwnershipClaims>,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        let new_head =
+            VirtualBranchActions.split_commit(&project, branch_id, commit_oid, &groups)?;
+        emit_vbranches(&windows, project_id);
+        Ok(new_head.to_string())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, handle), err(Debug))]
+    pub fn fetch_from_remotes(
+        handle: AppHandle,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        action: Option<String>,
+        operation_id: Option<String>,
+    ) -> Result<BaseBranch, Error> {
+        let project = projects.get(project_id)?;
+
+        let report = with_progress(&handle, project_id, operation_id, |progress| {
+            VirtualBranchActions.fetch_from_remotes_with_progress(
+                &project,
+                Some(action.unwrap_or_else(|| "unknown".to_string())),
+                progress,
+            )
+        })?;
+        let project_data_last_fetched = report.fetch_result(std::time::SystemTime::now());
+
+        // Updates the project controller with the last fetched timestamp
+        //
+        // TODO: This cross dependency likely indicates that last_fetched is stored in the wrong place - value is coupled with virtual branches state
+        projects
+            .update(&projects::UpdateRequest {
+                id: project.id,
+                project_data_last_fetched: Some(project_data_last_fetched.clone()),
+                ..Default::default()
+            })
+            .context("failed to update project with last fetched timestamp")?;
+
+        if let FetchResult::Error { error, .. } = project_data_last_fetched {
+            return Err(anyhow!(error).into());
+        }
+
+        let base_branch = VirtualBranchActions::get_base_branch_data(&project)?;
+        Ok(base_branch)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn is_shallow_clone(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<bool, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.is_shallow_clone(&project)?)
+    }
+
+    /// Fetch `deepen_by` more commits of the history of a shallow clone, or all of it if `unshallow` is set.
+    #[tauri::command]
+    #[instrument(skip(projects, handle), err(Debug))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn deepen_history(
+        handle: AppHandle,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        remote: Option<String>,
+        deepen_by: Option<u32>,
+        unshallow: bool,
+        action: Option<String>,
+        operation_id: Option<String>,
+    ) -> Result<BaseBranch, Error> {
+        let project = projects.get(project_id)?;
+        let deepen = match (deepen_by, unshallow) {
+            (_, true) => Deepen::Unshallow,
+            (Some(by), false) => Deepen::By(by),
+            (None, false) => {
+                return Err(anyhow!("either deepenBy or unshallow must be set")
+                    .context(Code::Validation)
+                    .into())
+            }
+        };
+        with_progress(&handle, project_id, operation_id, |progress| {
+            VirtualBranchActions.deepen_history(
+                &project,
+                remote.as_deref(),
+                deepen,
+                Some(action.unwrap_or_else(|| "unknown".to_string())),
+                progress,
+            )
+        })?;
+        let base_branch = VirtualBranchActions::get_base_branch_data(&project)?;
+        Ok(base_branch)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn move_commit(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        commit_oid: String,
+        target_branch_id: BranchId,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        VirtualBranchActions.move_commit(&project, target_branch_id, commit_oid)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn update_commit_message(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        commit_oid: String,
+        message: &str,
+        rewrite_pushed: bool,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        VirtualBranchActions.update_commit_message(
+            &project,
+            branch_id,
+            commit_oid,
+            message,
+            rewrite_pushed,
+        )?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn normalize_commit_times(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        rewrite_pushed: bool,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let head =
+            VirtualBranchActions.normalize_commit_times(&project, branch_id, rewrite_pushed)?;
+        emit_vbranches(&windows, project_id);
+        Ok(head.to_string())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn shelve_changes(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        name: String,
+        ownership: BranchOwnershipClaims,
+    ) -> Result<ShelfId, Error> {
+        let project = projects.get(project_id)?;
+        let shelf_id = VirtualBranchActions.shelve_changes(&project, &name, &ownership)?;
+        emit_vbranches(&windows, project_id);
+        Ok(shelf_id)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn unshelve_changes(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        shelf_id: ShelfId,
+        branch_id: Option<BranchId>,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        VirtualBranchActions.unshelve_changes(&project, shelf_id, branch_id)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_shelves(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<Shelf>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_shelves(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_trash(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<TrashEntry>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_trash(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn restore_trash(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        entry_id: TrashEntryId,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        VirtualBranchActions.restore_trash(&project, entry_id)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_hunk_groups(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+    ) -> Result<Vec<HunkGroup>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.hunk_groups(&project, branch_id)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn select_hunks(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        query: String,
+    ) -> Result<BranchOwnershipClaims, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.select_hunks(&project, &query)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_file_status(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        path: PathBuf,
+    ) -> Result<Option<FileStatus>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.file_status(&project, &path)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn check_ignored(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        paths: Vec<PathBuf>,
+    ) -> Result<Vec<IgnoreCheck>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.check_ignored(&project, &paths)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn add_ignore_patterns(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        file: IgnoreFile,
+        patterns: Vec<String>,
+    ) -> Result<Vec<String>, Error> {
+        let project = projects.get(project_id)?;
+        let added = VirtualBranchActions.add_ignore_patterns(&project, file, &patterns)?;
+        emit_vbranches(&windows, project_id);
+        Ok(added)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_workspace_ownership(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<WorkspaceOwnership, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.workspace_ownership(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn set_status_tracing(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        VirtualBranchActions.set_status_tracing(&project, enabled);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_status_traces(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<StatusTrace>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.status_traces(&project))
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_automation_runs(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<AutomationRun>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.automation_runs(&project))
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_ownership_conflicts(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        ownership: BranchOwnershipClaims,
+    ) -> Result<Vec<OwnershipConflict>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.ownership_conflicts(&project, branch_id, &ownership)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn claim_ownership_exclusively(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        ownership: BranchOwnershipClaims,
+    ) -> Result<Vec<OwnershipConflict>, Error> {
+        let project = projects.get(project_id)?;
+        let conflicts =
+            VirtualBranchActions.claim_ownership_exclusively(&project, branch_id, &ownership)?;
+        emit_vbranches(&windows, project_id);
+        Ok(conflicts)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn set_hunk_note(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        file_path: PathBuf,
+        hunk: String,
+        note: Option<String>,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let hunk: Hunk = hunk.parse()?;
+        VirtualBranchActions.set_hunk_note(&project, &file_path, &hunk, note)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn pin_hunk(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        file_path: PathBuf,
+        hunk: String,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let hunk: Hunk = hunk.parse()?;
+        VirtualBranchActions.pin_hunk(&project, branch_id, &file_path, &hunk)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn unpin_hunk(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        file_path: PathBuf,
+        hunk: String,
+    ) -> Result<bool, Error> {
+        let project = projects.get(project_id)?;
+        let hunk: Hunk = hunk.parse()?;
+        let unpinned = VirtualBranchActions.unpin_hunk(&project, &file_path, &hunk)?;
+        emit_vbranches(&windows, project_id);
+        Ok(unpinned)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_hunk_pins(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<HunkPin>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_hunk_pins(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn delete_shelf(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        shelf_id: ShelfId,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.delete_shelf(&project, shelf_id)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_stashes(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<StashEntry>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_stashes(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_submodules(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<Submodule>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_submodules(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_nested_repositories(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<NestedRepository>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_nested_repositories(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_eol_changes(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<EolChange>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_eol_changes(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn import_stash(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        index: usize,
+        import: StashImport,
+    ) -> Result<BranchId, Error> {
+        let project = projects.get(project_id)?;
+        let branch_id = VirtualBranchActions.import_stash(&project, index, import)?;
+        emit_vbranches(&windows, project_id);
+        Ok(branch_id)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn apply_branch_partially(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch: Refname,
+        selection: BranchOwnershipClaims,
+    ) -> Result<BranchId, Error> {
+        let project = projects.get(project_id)?;
+        let branch_id =
+            VirtualBranchActions.apply_branch_partially(&project, &branch, &selection)?;
+        emit_vbranches(&windows, project_id);
+        Ok(branch_id)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn create_virtual_branch_from_snapshot(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        sha: String,
+        paths: Vec<PathBuf>,
+        name: Option<String>,
+    ) -> Result<BranchId, Error> {
+        let project = projects.get(project_id)?;
+        let sha = git2::Oid::from_str(&sha).map_err(|e| anyhow!(e))?;
+        let branch_id = VirtualBranchActions
+            .create_virtual_branch_from_snapshot(&project, sha, &paths, name)?;
+        emit_vbranches(&windows, project_id);
+        Ok(branch_id)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn branch_events_since(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        cursor: Option<u64>,
+    ) -> Result<Vec<BranchEvent>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.branch_events_since(&project, branch_id, cursor)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_audit_log(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        query: AuditQuery,
+    ) -> Result<Vec<AuditEntry>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.audit_log(&project, &query)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_commit_provenance(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        commit_oid: String,
+    ) -> Result<Option<CommitProvenance>, Error> {
+        let project = projects.get(project_id)?;
+        let commit_oid = git2::Oid::from_str(&commit_oid).map_err(|e| anyhow!(e))?;
+        Ok(VirtualBranchActions.commit_provenance(&project, commit_oid)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn predict_conflicts(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<PredictedConflict>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.predict_conflicts(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn simulate_merge_orders(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        limit: usize,
+    ) -> Result<MergeOrderSimulation, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.simulate_merge_orders(&project, limit)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_branch_dependencies(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<BranchDependency>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.branch_dependencies(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_partial_checkout(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<PartialCheckout, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.partial_checkout(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn enable_partial_checkout(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        directories: Vec<PathBuf>,
+    ) -> Result<PartialCheckout, Error> {
+        let project = projects.get(project_id)?;
+        let checkout = VirtualBranchActions.enable_partial_checkout(&project, &directories)?;
+        emit_vbranches(&windows, project_id);
+        Ok(checkout)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn hydrate_partial_checkout(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        paths: Vec<PathBuf>,
+    ) -> Result<PartialCheckout, Error> {
+        let project = projects.get(project_id)?;
+        let checkout = VirtualBranchActions.hydrate_partial_checkout(&project, &paths)?;
+        emit_vbranches(&windows, project_id);
+        Ok(checkout)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn disable_partial_checkout(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<PartialCheckout, Error> {
+        let project = projects.get(project_id)?;
+        let checkout = VirtualBranchActions.disable_partial_checkout(&project)?;
+        emit_vbranches(&windows, project_id);
+        Ok(checkout)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_leftovers(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<Leftover>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_leftovers(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn remove_leftovers(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<Leftover>, Error> {
+        let project = projects.get(project_id)?;
+        let removed = VirtualBranchActions.remove_leftovers(&project)?;
+        emit_vbranches(&windows, project_id);
+        Ok(removed)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn fsck_virtual_branches(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        repair: bool,
+    ) -> Result<FsckReport, Error> {
+        let project = projects.get(project_id)?;
+        let report = VirtualBranchActions.fsck(&project, repair)?;
+        if report.repaired {
+            emit_vbranches(&windows, project_id);
+        }
+        Ok(report)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn export_to_git(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        uncommitted: ExportUncommitted,
+    ) -> Result<ExportOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let outcome = VirtualBranchActions.export_to_git(&project, uncommitted)?;
+        emit_vbranches(&windows, project_id);
+        Ok(outcome)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn export_patches(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        dir: PathBuf,
+        format: PatchFormat,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.export_patches(&project, branch_id, &dir, format)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows, patch), err(Debug))]
+    pub fn import_patch(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        patch: String,
+        options: ImportPatchOptions,
+    ) -> Result<PatchImportOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let outcome =
+            VirtualBranchActions.import_patch(&project, branch_id, patch.as_bytes(), &options)?;
+        emit_vbranches(&windows, project_id);
+        Ok(outcome)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn eject(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        onto: Option<BranchId>,
+    ) -> Result<ExportOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let outcome = VirtualBranchActions.eject(&project, onto)?;
+        emit_vbranches(&windows, project_id);
+        Ok(outcome)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn apply_layout(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        path: Option<PathBuf>,
+    ) -> Result<LayoutOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let outcome = VirtualBranchActions.apply_layout(&project, path.as_deref())?;
+        emit_vbranches(&windows, project_id);
+        Ok(outcome)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn list_conflicted_files(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+    ) -> Result<Vec<ConflictedFile>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.list_conflicted_files(&project)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn get_conflicted_file_blob(
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        path: PathBuf,
+        side: ConflictSide,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let project = projects.get(project_id)?;
+        Ok(VirtualBranchActions.conflicted_file_blob(&project, &path, side)?)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn resolve_conflict(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        path: PathBuf,
+        resolution: Resolution,
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        VirtualBranchActions.resolve_conflict(&project, &path, resolution)?;
+        emit_vbranches(&windows, project_id);
+        Ok(())
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects, windows), err(Debug))]
+    pub fn finalize_conflict_resolution(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        project_id: ProjectId,
+        branch_id: BranchId,
+        message: &str,
+    ) -> Result<String, Error> {
+        let project = projects.get(project_id)?;
+        let oid =
+            VirtualBranchActions.finalize_conflict_resolution(&project, branch_id, message)?;
+        emit_vbranches(&windows, project_id);
+        Ok(oid.to_string())
     }
 
     fn emit_vbranches(windows: &WindowState, project_id: projects::ProjectId) {
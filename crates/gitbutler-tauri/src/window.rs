@@ -2,6 +2,7 @@ pub(super) mod state {
     use std::{collections::BTreeMap, sync::Arc};
 
     use anyhow::{Context, Result};
+    use gitbutler_branch_actions::VirtualBranchActions;
     use gitbutler_project as projects;
     use gitbutler_project::ProjectId;
     use gitbutler_user as users;
@@ -14,6 +15,9 @@ pub(super) mod state {
         use gitbutler_watcher::Change;
         use tauri::Manager;
 
+        /// The name of the event that each change of any project is announced with as well.
+        const WORKSPACE_EVENT: &str = "workspace://project-changed";
+
         /// A change we want to inform the frontend about.
         #[derive(Debug, Clone, PartialEq, Eq)]
         pub(super) struct ChangeForFrontend {
@@ -44,6 +48,25 @@ pub(super) mod state {
                         payload: serde_json::json!({}),
                         project_id,
                     },
+                    Change::Filesystem { project_id, events } => ChangeForFrontend {
+                        name: format!("project://{}/filesystem", project_id),
+                        payload: serde_json::json!({ "events": events }),
+                        project_id,
+                    },
+                    Change::RemoteUpdated { project_id, remote } => ChangeForFrontend {
+                        name: format!("project://{}/git/remote-updated", project_id),
+                        payload: serde_json::json!({ "remote": remote }),
+                        project_id,
+                    },
+                    Change::RemoteDefaultBranchChanged {
+                        project_id,
+                        remote,
+                        change,
+                    } => ChangeForFrontend {
+                        name: format!("project://{}/git/remote-default-branch", project_id),
+                        payload: serde_json::json!({ "remote": remote, "change": change }),
+                        project_id,
+                    },
                     Change::VirtualBranches {
                         project_id,
                         virtual_branches,
@@ -52,15 +75,48 @@ pub(super) mod state {
                         payload: serde_json::json!(virtual_branches),
                         project_id,
                     },
+                    Change::PredictedConflicts {
+                        project_id,
+                        conflicts,
+                    } => ChangeForFrontend {
+                        name: format!("project://{}/predicted-conflicts", project_id),
+                        payload: serde_json::json!({ "conflicts": conflicts }),
+                        project_id,
+                    },
+                    Change::OwnershipRemapped { project_id, remap } => ChangeForFrontend {
+                        name: format!("project://{}/ownership-remap", project_id),
+                        payload: serde_json::json!(remap),
+                        project_id,
+                    },
+                    Change::WorkspaceDesynced { project_id, desync } => ChangeForFrontend {
+                        name: format!("project://{}/workspace-desynced", project_id),
+                        payload: serde_json::json!(desync),
+                        project_id,
+                    },
+                    Change::ExternalChangeDetected { project_id, change } => ChangeForFrontend {
+                        name: format!("project://{}/external-change", project_id),
+                        payload: serde_json::json!(change),
+                        project_id,
+                    },
                 }
             }
         }
 
         impl ChangeForFrontend {
+            /// Send the change under its own name, and announce it as a change of its project to those who
+            /// follow all projects at once, without its payload.
             pub(super) fn send(&self, app_handle: &tauri::AppHandle) -> Result<()> {
                 app_handle
                     .emit_all(&self.name, Some(&self.payload))
                     .context("emit event")?;
+                let prefix = format!("project://{}/", self.project_id);
+                let kind = self.name.strip_prefix(&prefix).unwrap_or(&self.name);
+                app_handle
+                    .emit_all(
+                        WORKSPACE_EVENT,
+                        Some(serde_json::json!({ "projectId": self.project_id, "kind": kind })),
+                    )
+                    .context("emit workspace event")?;
                 tracing::trace!(event_name = self.name);
                 Ok(())
             }
@@ -73,6 +129,12 @@ pub(super) mod state {
         project_id: ProjectId,
         /// The watcher of the currently active project.
         watcher: gitbutler_watcher::WatcherHandle,
+        /// The scheduler fetching the remotes of the currently active project, if it was started.
+        fetch_scheduler: Option<gitbutler_watcher::FetchSchedulerHandle>,
+        /// Expensive maintenance of the currently active project, performed while it is idle.
+        _maintenance: gitbutler_watcher::MaintenanceHandle,
+        /// Checks that the workspace of the currently active project matches its applied branches.
+        _watchdog: gitbutler_watcher::WatchdogHandle,
         /// An active lock to signal that the entire project is locked for the Window this state belongs to.
         exclusive_access: fslock::LockFile,
     }
@@ -134,16 +196,44 @@ pub(super) mod state {
                 }
             }
             let exclusive_access = project.try_exclusive_access()?;
+            // Being the only instance with the project open, whatever a previous run was doing is over.
+            match VirtualBranchActions.remove_leftovers(project) {
+                Ok(removed) => {
+                    for leftover in removed {
+                        tracing::info!(?leftover, "removed what a previous run left behind");
+                    }
+                }
+                Err(err) => tracing::warn!(?err, "failed to remove what previous runs left behind"),
+            }
             let handler = handler_from_app(&self.app_handle)?;
             let worktree_dir = project.path.clone();
             let project_id = project.id;
-            let watcher =
-                gitbutler_watcher::watch_in_background(handler, worktree_dir, project_id)?;
+            let activity = self
+                .app_handle
+                .state::<gitbutler_watcher::Activity>()
+                .inner()
+                .clone();
+            let maintenance = gitbutler_watcher::maintain_when_idle(
+                handler.clone(),
+                project_id,
+                activity.clone(),
+            );
+            let watchdog =
+                gitbutler_watcher::check_workspace_in_background(handler.clone(), project_id);
+            let watcher = gitbutler_watcher::watch_in_background(
+                handler,
+                worktree_dir,
+                project_id,
+                activity,
+            )?;
             state_by_label.insert(
                 window.to_owned(),
                 State {
                     project_id,
                     watcher,
+                    fetch_scheduler: None,
+                    _maintenance: maintenance,
+                    _watchdog: watchdog,
                     exclusive_access,
                 },
             );
@@ -166,6 +256,52 @@ pub(super) mod state {
             }
         }
 
+        /// Start fetching the remotes of the project with `project_id` periodically, if it's open in a window.
+        /// Fetched remotes are announced as `project://<id>/git/remote-updated` events.
+        pub fn start_fetch_scheduler(&self, project_id: ProjectId) -> Result<()> {
+            let mut state_by_label = self.state.lock();
+            let state = state_by_label
+                .values_mut()
+                .find(|state| state.project_id == project_id)
+                .with_context(|| format!("project {project_id} isn't open in any window"))?;
+            if state
+                .fetch_scheduler
+                .as_ref()
+                .map_or(true, |scheduler| scheduler.is_stopped())
+            {
+                let handler = handler_from_app(&self.app_handle)?;
+                state.fetch_scheduler =
+                    Some(gitbutler_watcher::fetch_in_background(handler, project_id));
+            }
+            Ok(())
+        }
+
+        /// Stop fetching the remotes of the project with `project_id` in the background.
+        pub fn stop_fetch_scheduler(&self, project_id: ProjectId) {
+            let mut state_by_label = self.state.lock();
+            for state in state_by_label
+                .values_mut()
+                .filter(|state| state.project_id == project_id)
+            {
+                state.fetch_scheduler.take();
+            }
+        }
+
+        /// Return the state of the background fetches for the project with `project_id`,
+        /// or `None` if the scheduler isn't running.
+        pub fn fetch_scheduler_status(
+            &self,
+            project_id: ProjectId,
+        ) -> Option<Vec<gitbutler_watcher::RemoteFetchStatus>> {
+            let state_by_label = self.state.lock();
+            state_by_label
+                .values()
+                .filter(|state| state.project_id == project_id)
+                .find_map(|state| state.fetch_scheduler.as_ref())
+                .filter(|scheduler| !scheduler.is_stopped())
+                .map(|scheduler| scheduler.status())
+        }
+
         /// Flush file-monitor watcher events once the windows regains focus for it to respond instantly
         /// instead of according to the tick-rate.
         pub fn flush(&self, window: &WindowLabelRef) -> Result<()> {
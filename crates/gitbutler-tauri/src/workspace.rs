@@ -0,0 +1,42 @@
+pub mod commands {
+    use anyhow::Context;
+    use gitbutler_branch_actions::workspace::{ProjectOutcome, UnpushedBranch, Workspace};
+    use gitbutler_project as projects;
+    use gitbutler_repo::FetchReport;
+    use tauri::State;
+    use tracing::instrument;
+
+    use crate::error::Error;
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn workspace_fetch_all(
+        projects: State<'_, projects::Controller>,
+        action: Option<String>,
+    ) -> Result<Vec<ProjectOutcome<FetchReport>>, Error> {
+        let workspace = Workspace::from_controller(&projects)?;
+        let outcomes = workspace.fetch_all(Some(action.unwrap_or_else(|| "unknown".to_string())));
+        let now = std::time::SystemTime::now();
+        for outcome in &outcomes {
+            let Some(report) = &outcome.value else {
+                continue;
+            };
+            projects
+                .update(&projects::UpdateRequest {
+                    id: outcome.project_id,
+                    project_data_last_fetched: Some(report.fetch_result(now)),
+                    ..Default::default()
+                })
+                .context("failed to update project with last fetched timestamp")?;
+        }
+        Ok(outcomes)
+    }
+
+    #[tauri::command]
+    #[instrument(skip(projects), err(Debug))]
+    pub fn workspace_unpushed_branches(
+        projects: State<'_, projects::Controller>,
+    ) -> Result<Vec<ProjectOutcome<Vec<UnpushedBranch>>>, Error> {
+        Ok(Workspace::from_controller(&projects)?.unpushed_branches())
+    }
+}
@@ -3,40 +3,40 @@ pub mod commands {
     use std::path::PathBuf;
 
     use anyhow::Context;
-    use gitbutler_error::{error, error::Code};
+    use gitbutler_error::{catalog::MessageId, error, error::Code};
     use gitbutler_feedback::Archival;
     use tauri::State;
     use tracing::instrument;
 
     use crate::error::Error;
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(archival), err(Debug))]
     pub fn get_project_archive_path(
         archival: State<'_, Archival>,
         project_id: &str,
     ) -> Result<PathBuf, Error> {
-        let project_id = project_id.parse().context(error::Context::new_static(
+        let project_id = project_id.parse().context(error::Context::from_catalog(
             Code::Validation,
-            "Malformed project id",
+            MessageId::MalformedProjectId,
         ))?;
         archival.archive(project_id).map_err(Into::into)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(archival), err(Debug))]
     pub fn get_project_data_archive_path(
         archival: State<'_, Archival>,
         project_id: &str,
     ) -> Result<PathBuf, Error> {
-        let project_id = project_id.parse().context(error::Context::new_static(
+        let project_id = project_id.parse().context(error::Context::from_catalog(
             Code::Validation,
-            "Malformed project id",
+            MessageId::MalformedProjectId,
         ))?;
         archival.data_archive(project_id).map_err(Into::into)
     }
 
-    #[tauri::command(async)]
+    #[tauri::command]
     #[instrument(skip(archival), err(Debug))]
     pub fn get_logs_archive_path(archival: State<'_, Archival>) -> Result<PathBuf, Error> {
         archival.logs_archive().map_err(Into::into)
@@ -0,0 +1,197 @@
+//! A stand-in for the REST API of a forge like GitHub on the local machine, to test working with
+//! pull requests end to end.
+//!
+//! It answers each request with the response that was set up for its method and path, or with
+//! `404 Not Found`, and keeps all requests so tests can check what was sent.
+use std::{
+    io::{BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use anyhow::Result;
+use parking_lot::Mutex;
+
+use crate::git_server::Request;
+
+/// A request as the server received it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedRequest {
+    pub method: String,
+    /// The path of the request, without its query.
+    pub path: String,
+    pub query: String,
+    pub authorization: Option<String>,
+    /// The JSON body of the request, or `Null` if it had none.
+    pub body: serde_json::Value,
+}
+
+#[derive(Debug, Clone)]
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: serde_json::Value,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The response to answer with by method and path.
+    responses: Vec<((String, String), Response)>,
+    requests: Vec<ReceivedRequest>,
+}
+
+/// A server for the API of a forge which stops serving when dropped.
+pub struct ForgeServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ForgeServer {
+    pub fn new() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread = std::thread::spawn({
+            let state = Arc::clone(&state);
+            let shutdown = Arc::clone(&shutdown);
+            move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    if let Err(err) = handle(stream, &state) {
+                        eprintln!("forge server failed to handle a request: {err:#}");
+                    }
+                }
+            }
+        });
+        Ok(ForgeServer {
+            addr,
+            state,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    /// The host of the forge, with its port.
+    pub fn host(&self) -> String {
+        self.addr.to_string()
+    }
+
+    /// The URL of a repository `owner/name` on this forge, to be used as URL of a remote.
+    pub fn repo_url(&self, owner: &str, name: &str) -> String {
+        format!("http://{}/{owner}/{name}.git", self.addr)
+    }
+
+    /// Answer requests to `method` and `path`, like `GET` and `/api/v3/repos/owner/repo/pulls/1`,
+    /// with `status` and the JSON `body` from now on.
+    pub fn respond(&self, method: &str, path: &str, status: u16, body: serde_json::Value) {
+        self.respond_with_headers(method, path, status, &[], body);
+    }
+
+    /// Like [`respond()`](Self::respond()), but also send `headers`, like those that tell about rate limits.
+    pub fn respond_with_headers(
+        &self,
+        method: &str,
+        path: &str,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: serde_json::Value,
+    ) {
+        let mut state = self.state.lock();
+        let key = (method.to_owned(), path.to_owned());
+        state.responses.retain(|(other, _)| *other != key);
+        state.responses.push((
+            key,
+            Response {
+                status,
+                headers: headers
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+                body,
+            },
+        ));
+    }
+
+    /// The requests received so far, in order.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.lock().requests.clone()
+    }
+}
+
+impl Drop for ForgeServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake up the listener so it sees the shutdown.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn handle(mut stream: TcpStream, state: &Mutex<State>) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = Request::read_head(&mut reader)?;
+    request.read_body(&mut reader)?;
+    let response = {
+        let mut state = state.lock();
+        state.requests.push(ReceivedRequest {
+            method: request.method.clone(),
+            path: request.path.clone(),
+            query: request.query.clone(),
+            authorization: request.header("authorization").map(ToOwned::to_owned),
+            body: serde_json::from_slice(&request.body).unwrap_or_default(),
+        });
+        state
+            .responses
+            .iter()
+            .find(|((method, path), _)| *method == request.method && *path == request.path)
+            .map(|(_, response)| response.clone())
+            .unwrap_or_else(|| Response {
+                status: 404,
+                headers: Vec::new(),
+                body: serde_json::json!({ "message": "Not Found" }),
+            })
+    };
+    let body = response.body.to_string();
+    let headers: String = response
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect();
+    stream.write_all(
+        format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{headers}Connection: close\r\n\r\n{body}",
+            response.status,
+            reason(response.status),
+            body.len()
+        )
+        .as_bytes(),
+    )?;
+    Ok(())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        _ => "Unknown",
+    }
+}
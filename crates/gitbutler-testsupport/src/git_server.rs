@@ -0,0 +1,483 @@
+//! A Git server on the local machine, to test fetching, pushing and authentication end to end.
+//!
+//! It serves a single bare repository with the smart HTTP protocol by running `git http-backend`
+//! for each request, and over SSH with a stub that stands in for `ssh` and runs the Git command
+//! it is given locally. Authentication failures, latency and history that was rewritten by someone
+//! else can be set up while it runs.
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use parking_lot::Mutex;
+use tempfile::TempDir;
+
+use crate::init_opts_bare;
+
+/// The name of the served repository, as it appears in its URLs.
+const REPO_NAME: &str = "repo.git";
+/// The file whose presence makes the SSH stub deny access.
+const SSH_DENY_FILE: &str = "ssh-deny";
+/// The file with the seconds the SSH stub waits before running a command.
+const SSH_LATENCY_FILE: &str = "ssh-latency";
+const SSH_STUB_FILE: &str = "ssh-stub";
+
+/// Who the server lets in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Auth {
+    /// Everyone may fetch and push.
+    #[default]
+    Anonymous,
+    /// HTTP requests must carry these credentials. The SSH stub doesn't check them.
+    Basic { username: String, password: String },
+    /// All HTTP requests are rejected as unauthorized, and the SSH stub denies access.
+    Deny,
+}
+
+impl Auth {
+    fn allows(&self, authorization: Option<&str>) -> bool {
+        match self {
+            Auth::Anonymous => true,
+            Auth::Basic { username, password } => {
+                authorization
+                    == Some(&format!(
+                        "Basic {}",
+                        base64(format!("{username}:{password}").as_bytes())
+                    ))
+            }
+            Auth::Deny => false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Behavior {
+    auth: Auth,
+    /// The amount of the next HTTP requests to reject as unauthorized, whatever their credentials.
+    rejections: usize,
+    latency: Duration,
+    requests: usize,
+}
+
+/// A server for a bare repository which stops serving when dropped.
+pub struct GitServer {
+    tmp: TempDir,
+    addr: SocketAddr,
+    behavior: Arc<Mutex<Behavior>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl GitServer {
+    /// Serve an empty bare repository.
+    pub fn new() -> Result<Self> {
+        let tmp = tempfile::tempdir()?;
+        git2::Repository::init_opts(tmp.path().join(REPO_NAME), &init_opts_bare())?;
+        Self::serve(tmp)
+    }
+
+    /// Serve a bare copy of all references of the repository at `source`.
+    pub fn mirror(source: &Path) -> Result<Self> {
+        let tmp = tempfile::tempdir()?;
+        let status = Command::new("git")
+            .args(["clone", "--mirror", "--quiet"])
+            .arg(source)
+            .arg(tmp.path().join(REPO_NAME))
+            .status()
+            .context("failed to run git")?;
+        ensure!(status.success(), "failed to mirror {}", source.display());
+        Self::serve(tmp)
+    }
+
+    fn serve(tmp: TempDir) -> Result<Self> {
+        let repo = git2::Repository::open(tmp.path().join(REPO_NAME))?;
+        // Pushes over HTTP are only accepted from authenticated users otherwise.
+        repo.config()?.set_bool("http.receivepack", true)?;
+        write_ssh_stub(tmp.path())?;
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let behavior = Arc::new(Mutex::new(Behavior::default()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread = std::thread::spawn({
+            let root = tmp.path().to_owned();
+            let behavior = Arc::clone(&behavior);
+            let shutdown = Arc::clone(&shutdown);
+            move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    let root = root.clone();
+                    let behavior = Arc::clone(&behavior);
+                    std::thread::spawn(move || {
+                        if let Err(err) = handle(stream, &root, &behavior) {
+                            eprintln!("git server failed to handle a request: {err:#}");
+                        }
+                    });
+                }
+            }
+        });
+        Ok(GitServer {
+            tmp,
+            addr,
+            behavior,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    /// The path to the served bare repository.
+    pub fn path(&self) -> PathBuf {
+        self.tmp.path().join(REPO_NAME)
+    }
+
+    /// Open the served repository, to look at or change it directly.
+    pub fn repository(&self) -> git2::Repository {
+        git2::Repository::open_bare(self.path()).expect("served repository exists")
+    }
+
+    /// The URL to reach the repository with the smart HTTP protocol.
+    pub fn http_url(&self) -> String {
+        format!("http://{}/{REPO_NAME}", self.addr)
+    }
+
+    /// Like [`http_url()`](Self::http_url()), but with credentials that Git will use without asking.
+    pub fn http_url_with_credentials(&self, username: &str, password: &str) -> String {
+        format!("http://{username}:{password}@{}/{REPO_NAME}", self.addr)
+    }
+
+    /// The URL to reach the repository over SSH, which only works with the
+    /// [SSH stub](Self::configure_ssh_command()).
+    #[cfg(unix)]
+    pub fn ssh_url(&self) -> String {
+        format!("ssh://git@localhost{}", self.path().display())
+    }
+
+    /// Let `repo` use the SSH stub of this server instead of `ssh`, with `core.sshCommand`.
+    #[cfg(unix)]
+    pub fn configure_ssh_command(&self, repo: &git2::Repository) -> Result<()> {
+        let stub = self.tmp.path().join(SSH_STUB_FILE);
+        repo.config()?
+            .open_level(git2::ConfigLevel::Local)?
+            .set_str("core.sshCommand", &stub.to_string_lossy())?;
+        Ok(())
+    }
+
+    /// Set who is let in from now on.
+    pub fn set_auth(&self, auth: Auth) -> Result<()> {
+        let deny_file = self.tmp.path().join(SSH_DENY_FILE);
+        if auth == Auth::Deny {
+            std::fs::write(deny_file, "")?;
+        } else if deny_file.exists() {
+            std::fs::remove_file(deny_file)?;
+        }
+        self.behavior.lock().auth = auth;
+        Ok(())
+    }
+
+    /// Reject the next `count` HTTP requests as unauthorized, even if they carry the right credentials.
+    pub fn reject_next_requests(&self, count: usize) {
+        self.behavior.lock().rejections = count;
+    }
+
+    /// Wait for `latency` before answering each request.
+    pub fn set_latency(&self, latency: Duration) -> Result<()> {
+        std::fs::write(
+            self.tmp.path().join(SSH_LATENCY_FILE),
+            latency.as_secs_f64().to_string(),
+        )?;
+        self.behavior.lock().latency = latency;
+        Ok(())
+    }
+
+    /// The amount of HTTP requests received so far.
+    pub fn requests(&self) -> usize {
+        self.behavior.lock().requests
+    }
+
+    /// Let the repository reject pushes that aren't fast-forwards, even forced ones, if `deny` is `true`.
+    pub fn deny_non_fast_forwards(&self, deny: bool) -> Result<()> {
+        self.repository()
+            .config()?
+            .set_bool("receive.denyNonFastForwards", deny)?;
+        Ok(())
+    }
+
+    /// Replace the last commit of `branch` with one that has the same tree and parents but another
+    /// author and message, as if someone else force-pushed to it, and return the new commit.
+    pub fn rewrite_branch(&self, branch: &str) -> Result<git2::Oid> {
+        let repo = self.repository();
+        let refname = format!("refs/heads/{branch}");
+        let head = repo.find_reference(&refname)?.peel_to_commit()?;
+        let parents: Vec<_> = head.parents().collect();
+        let signature = git2::Signature::now("someone else", "someone@example.com")?;
+        let id = repo.commit(
+            None,
+            &signature,
+            &signature,
+            &format!(
+                "{} (rewritten)",
+                head.message().unwrap_or_default().trim_end()
+            ),
+            &head.tree()?,
+            &parents.iter().collect::<Vec<_>>(),
+        )?;
+        repo.reference(&refname, id, true, "rewritten by someone else")?;
+        Ok(id)
+    }
+}
+
+impl Drop for GitServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake up the listener so it sees the shutdown.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn handle(mut stream: TcpStream, root: &Path, behavior: &Mutex<Behavior>) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = Request::read_head(&mut reader)?;
+    let (authorized, latency) = {
+        let mut behavior = behavior.lock();
+        behavior.requests += 1;
+        let authorized = if behavior.rejections > 0 {
+            behavior.rejections -= 1;
+            false
+        } else {
+            behavior.auth.allows(request.header("authorization"))
+        };
+        (authorized, behavior.latency)
+    };
+    std::thread::sleep(latency);
+    if !authorized {
+        stream.write_all(
+            b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"git\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        )?;
+        return Ok(());
+    }
+    if request
+        .header("expect")
+        .map_or(false, |expect| expect.eq_ignore_ascii_case("100-continue"))
+    {
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+    }
+    request.read_body(&mut reader)?;
+    stream.write_all(&run_http_backend(root, &request)?)?;
+    Ok(())
+}
+
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    pub(crate) query: String,
+    /// The headers with their names in lower case.
+    headers: Vec<(String, String)>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Request {
+    pub(crate) fn read_head(reader: &mut impl BufRead) -> Result<Self> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            bail!("invalid request line: {line:?}");
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let mut request = Request {
+            method: method.to_owned(),
+            path: path.to_owned(),
+            query: query.to_owned(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        };
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                request
+                    .headers
+                    .push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+            }
+        }
+        Ok(request)
+    }
+
+    pub(crate) fn read_body(&mut self, reader: &mut impl BufRead) -> Result<()> {
+        if let Some(length) = self.header("content-length") {
+            self.body = vec![0; length.parse()?];
+            reader.read_exact(&mut self.body)?;
+        } else if self
+            .header("transfer-encoding")
+            .map_or(false, |encoding| encoding.eq_ignore_ascii_case("chunked"))
+        {
+            self.body = read_chunked(reader)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn read_chunked(reader: &mut impl BufRead) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let size = line.trim().split(';').next().unwrap_or_default();
+        let size = usize::from_str_radix(size, 16)
+            .with_context(|| format!("invalid chunk size: {line:?}"))?;
+        if size == 0 {
+            // Skip the trailers up to the empty line that ends the body.
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        // The line break after the chunk.
+        line.clear();
+        reader.read_line(&mut line)?;
+    }
+}
+
+/// Answer `request` with `git http-backend` as CGI script, and return the HTTP response.
+fn run_http_backend(root: &Path, request: &Request) -> Result<Vec<u8>> {
+    let mut cmd = Command::new("git");
+    cmd.arg("http-backend")
+        .env("GIT_PROJECT_ROOT", root)
+        .env("GIT_HTTP_EXPORT_ALL", "1")
+        .env("REQUEST_METHOD", &request.method)
+        .env("PATH_INFO", &request.path)
+        .env("QUERY_STRING", &request.query)
+        .env("CONTENT_LENGTH", request.body.len().to_string())
+        .env("REMOTE_ADDR", "127.0.0.1")
+        .env("SERVER_PROTOCOL", "HTTP/1.1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    for (header, var) in [
+        ("content-type", "CONTENT_TYPE"),
+        ("content-encoding", "HTTP_CONTENT_ENCODING"),
+        ("git-protocol", "GIT_PROTOCOL"),
+    ] {
+        if let Some(value) = request.header(header) {
+            cmd.env(var, value);
+        }
+    }
+    let mut child = cmd.spawn().context("failed to run git http-backend")?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let output = std::thread::scope(|scope| {
+        // The backend may answer before it read the whole body, so it's written on the side.
+        scope.spawn(move || stdin.write_all(&request.body));
+        child.wait_with_output()
+    })?;
+
+    let output = output.stdout;
+    let (head, body) = match output.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(pos) => (&output[..pos], &output[pos + 4..]),
+        None => match output.windows(2).position(|w| w == b"\n\n") {
+            Some(pos) => (&output[..pos], &output[pos + 2..]),
+            None => bail!("git http-backend didn't write any headers"),
+        },
+    };
+    let mut status = "200 OK".to_owned();
+    let mut headers = String::new();
+    for line in String::from_utf8_lossy(head).lines() {
+        match line.split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("status") => {
+                status = value.trim().to_owned();
+            }
+            _ => {
+                headers.push_str(line);
+                headers.push_str("\r\n");
+            }
+        }
+    }
+    let mut response = format!(
+        "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body);
+    Ok(response)
+}
+
+/// Write a script to `dir` that can be used as `ssh` command, and which runs the Git command it is
+/// given locally instead, or fails like `ssh` if access is denied.
+fn write_ssh_stub(dir: &Path) -> Result<()> {
+    let script = format!(
+        r#"#!/bin/sh
+state='{dir}'
+for arg in "$@"; do
+  # Pretend to be OpenSSH when Git probes for the kind of ssh it runs.
+  if [ "$arg" = "-G" ]; then exit 0; fi
+  command="$arg"
+done
+if [ -f "$state/{SSH_LATENCY_FILE}" ]; then sleep "$(cat "$state/{SSH_LATENCY_FILE}")"; fi
+if [ -f "$state/{SSH_DENY_FILE}" ]; then
+  echo "git@localhost: Permission denied (publickey)." >&2
+  exit 255
+fi
+exec sh -c "git ${{command#git-}}"
+"#,
+        dir = dir.display()
+    );
+    let path = dir.join(SSH_STUB_FILE);
+    std::fs::write(&path, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in input.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (idx, byte)| {
+            bits | (u32::from(*byte) << (16 - 8 * idx))
+        });
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                out.push(ALPHABET[((bits >> (18 - 6 * idx)) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
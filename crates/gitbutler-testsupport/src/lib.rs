@@ -7,6 +7,9 @@ pub use test_project::TestProject;
 mod suite;
 pub use suite::*;
 
+pub mod forge_server;
+pub mod git_server;
+
 pub mod paths {
     use tempfile::TempDir;
 
@@ -1,4 +1,4 @@
-use std::any::Any;
+use std::{any::Any, collections::BTreeMap, sync::Mutex};
 
 /// Assure we have a mock secrets store so tests don't start writing secrets into the user's actual store,
 /// as this will affect their GitButler instance.
@@ -43,3 +43,67 @@ impl keyring::credential::CredentialBuilderApi for BlackholeBuilder {
         self
     }
 }
+
+/// Use a secrets store that keeps all secrets in memory, so tests can store secrets and read them back.
+///
+/// The store is shared by all tests of the process, so the secrets they store should have distinct handles.
+pub fn setup_in_memory_store() {
+    keyring::set_default_credential_builder(Box::new(InMemoryBuilder))
+}
+
+static IN_MEMORY_SECRETS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+struct InMemoryBuilder;
+
+struct InMemoryCredential {
+    service: String,
+}
+
+impl keyring::credential::CredentialApi for InMemoryCredential {
+    fn set_password(&self, password: &str) -> keyring::Result<()> {
+        IN_MEMORY_SECRETS
+            .lock()
+            .unwrap()
+            .insert(self.service.clone(), password.to_owned());
+        Ok(())
+    }
+
+    fn get_password(&self) -> keyring::Result<String> {
+        IN_MEMORY_SECRETS
+            .lock()
+            .unwrap()
+            .get(&self.service)
+            .cloned()
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn delete_password(&self) -> keyring::Result<()> {
+        IN_MEMORY_SECRETS
+            .lock()
+            .unwrap()
+            .remove(&self.service)
+            .map(|_| ())
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl keyring::credential::CredentialBuilderApi for InMemoryBuilder {
+    fn build(
+        &self,
+        _target: Option<&str>,
+        service: &str,
+        _user: &str,
+    ) -> keyring::Result<Box<keyring::Credential>> {
+        Ok(Box::new(InMemoryCredential {
+            service: service.to_owned(),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
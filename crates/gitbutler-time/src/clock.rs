@@ -0,0 +1,109 @@
+//! Where the current time comes from, so runs can be made reproducible by making it predictable.
+//!
+//! By default, it's the system clock. Embedders can replace it for the whole process with [`set_clock()`],
+//! and tests can replace it for the current thread only with [`use_clock_in_thread()`], so tests running
+//! in parallel don't affect each other.
+use std::{
+    cell::RefCell,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+/// A source of the current time.
+pub trait Clock: Send + Sync {
+    /// Return the current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// The clock of the system, which is used unless another one is set.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that starts at a fixed time and advances by a fixed step each time it's read,
+/// so consecutive reads are ordered like they would be in reality.
+#[derive(Debug)]
+pub struct SteppingClock {
+    /// The time to return next.
+    next: std::sync::Mutex<SystemTime>,
+    step: Duration,
+}
+
+impl SteppingClock {
+    /// Create a clock that returns `start` first, and advances by `step` after each read.
+    pub fn new(start: SystemTime, step: Duration) -> Self {
+        SteppingClock {
+            next: std::sync::Mutex::new(start),
+            step,
+        }
+    }
+}
+
+impl Clock for SteppingClock {
+    fn now(&self) -> SystemTime {
+        let mut next = self.next.lock().expect("no panics while holding the lock");
+        let now = *next;
+        *next += self.step;
+        now
+    }
+}
+
+static GLOBAL: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+
+thread_local! {
+    static THREAD: RefCell<Option<Arc<dyn Clock>>> = const { RefCell::new(None) };
+}
+
+/// Use `clock` as source of the current time in the whole process, or the system clock if `None`.
+pub fn set_clock(clock: Option<Arc<dyn Clock>>) {
+    *GLOBAL.write().expect("no panics while holding the lock") = clock;
+}
+
+/// Use `clock` as source of the current time in the current thread until the returned guard is dropped.
+/// It takes precedence over the clock of the process.
+pub fn use_clock_in_thread(clock: impl Clock + 'static) -> ThreadClockGuard {
+    let previous = THREAD.with(|thread| thread.borrow_mut().replace(Arc::new(clock)));
+    ThreadClockGuard { previous }
+}
+
+/// Restores the clock the thread used before when dropped.
+#[must_use = "the clock is only used until the guard is dropped"]
+pub struct ThreadClockGuard {
+    previous: Option<Arc<dyn Clock>>,
+}
+
+impl Drop for ThreadClockGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        THREAD.with(|thread| *thread.borrow_mut() = previous);
+    }
+}
+
+/// Return the current time according to the clock in use.
+pub fn now() -> SystemTime {
+    if let Some(clock) = THREAD.with(|thread| thread.borrow().clone()) {
+        return clock.now();
+    }
+    match GLOBAL
+        .read()
+        .expect("no panics while holding the lock")
+        .as_ref()
+    {
+        Some(clock) => clock.now(),
+        None => SystemTime::now(),
+    }
+}
+
+/// Return `true` if the time doesn't come from the system clock, so it must not be mixed with it.
+pub fn is_overridden() -> bool {
+    THREAD.with(|thread| thread.borrow().is_some())
+        || GLOBAL
+            .read()
+            .expect("no panics while holding the lock")
+            .is_some()
+}
@@ -1,24 +1,26 @@
 use std::time::UNIX_EPOCH;
 
-/// Gets the number of milliseconds since the Unix epoch.
+use crate::clock;
+
+/// Gets the number of milliseconds since the Unix epoch, according to the [clock](clock::now()) in use.
 ///
 /// # Panics
-/// Panics if the system time is set before the Unix epoch.
+/// Panics if the time is set before the Unix epoch.
 pub fn now_ms() -> u128 {
-    UNIX_EPOCH
-        .elapsed()
+    clock::now()
+        .duration_since(UNIX_EPOCH)
         .expect("system time is set before the Unix epoch")
         .as_millis()
 }
 
 pub fn now_since_unix_epoch_ms() -> i64 {
-    UNIX_EPOCH
-        .elapsed()
+    let now = clock::now();
+    now.duration_since(UNIX_EPOCH)
         .map(|d| i64::try_from(d.as_millis()).expect("no system date is this far in the future"))
         .unwrap_or_else(|_| {
             -i64::try_from(
                 UNIX_EPOCH
-                    .duration_since(std::time::SystemTime::now())
+                    .duration_since(now)
                     .expect("'now' is in the past")
                     .as_millis(),
             )
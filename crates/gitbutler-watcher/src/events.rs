@@ -1,18 +1,22 @@
 use std::{fmt::Display, path::PathBuf};
 
-use gitbutler_branch_actions::VirtualBranches;
+use gitbutler_branch_actions::{
+    ExternalChange, OwnershipRemap, PredictedConflict, VirtualBranches, WorkspaceDesync,
+};
 use gitbutler_operating_modes::OperatingMode;
 use gitbutler_project::ProjectId;
+use gitbutler_repo::DefaultBranchChange;
+use serde::Serialize;
 
 /// An event for internal use, as merge between [super::file_monitor::Event] and [Action].
 #[derive(Debug)]
 pub(super) enum InternalEvent {
     // From public action API
     CalculateVirtualBranches(ProjectId),
+    OwnershipRemapped(ProjectId, OwnershipRemap),
 
     // From file monitor
-    GitFilesChange(ProjectId, Vec<PathBuf>),
-    ProjectFilesChange(ProjectId, Vec<PathBuf>),
+    FilesystemChanges(ProjectId, Vec<WatchEvent>),
     // Triggered on change in the `.git/gitbutler` directory
     GitButlerOplogChange(ProjectId),
 }
@@ -24,6 +28,8 @@ pub(super) enum InternalEvent {
 #[allow(missing_docs)]
 pub enum Action {
     CalculateVirtualBranches(ProjectId),
+    /// The target was updated, which remapped the hunks of the applied branches like this.
+    ReportOwnershipRemap(ProjectId, OwnershipRemap),
 }
 
 impl Action {
@@ -31,6 +37,7 @@ impl Action {
     pub fn project_id(&self) -> ProjectId {
         match self {
             Action::CalculateVirtualBranches(project_id) => *project_id,
+            Action::ReportOwnershipRemap(project_id, _) => *project_id,
         }
     }
 }
@@ -39,6 +46,9 @@ impl From<Action> for InternalEvent {
     fn from(value: Action) -> Self {
         match value {
             Action::CalculateVirtualBranches(v) => InternalEvent::CalculateVirtualBranches(v),
+            Action::ReportOwnershipRemap(project_id, remap) => {
+                InternalEvent::OwnershipRemapped(project_id, remap)
+            }
         }
     }
 }
@@ -46,26 +56,32 @@ impl From<Action> for InternalEvent {
 impl Display for InternalEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            InternalEvent::GitFilesChange(project_id, paths) => {
-                write!(
-                    f,
-                    "GitFileChange({}, {})",
-                    project_id,
-                    comma_separated_paths(paths)
-                )
+            InternalEvent::FilesystemChanges(project_id, events) => {
+                write!(f, "FilesystemChanges({}", project_id)?;
+                for event in events {
+                    match event {
+                        WatchEvent::WorktreeChanged { paths } => {
+                            write!(f, ", WorktreeChanged({})", comma_separated_paths(paths))?
+                        }
+                        WatchEvent::GitRefsChanged { refs } => {
+                            write!(f, ", GitRefsChanged({})", comma_separated_paths(refs))?
+                        }
+                        WatchEvent::IndexChanged => write!(f, ", IndexChanged")?,
+                    }
+                }
+                write!(f, ")")
             }
             InternalEvent::GitButlerOplogChange(project_id) => {
                 write!(f, "GitButlerOplogChange({})", project_id)
             }
-            InternalEvent::ProjectFilesChange(project_id, paths) => {
+            InternalEvent::CalculateVirtualBranches(pid) => write!(f, "VirtualBranch({})", pid),
+            InternalEvent::OwnershipRemapped(pid, remap) => {
                 write!(
                     f,
-                    "ProjectFileChange({}, {})",
-                    project_id,
-                    comma_separated_paths(paths)
+                    "OwnershipRemapped({pid}, {} branches)",
+                    remap.branches.len()
                 )
             }
-            InternalEvent::CalculateVirtualBranches(pid) => write!(f, "VirtualBranch({})", pid),
         }
     }
 }
@@ -86,6 +102,26 @@ fn comma_separated_paths(paths: &[PathBuf]) -> String {
     }
 }
 
+/// A change to a project as seen on disk, which is one of a batch of changes collected over a short period
+/// of time, so that rapid changes like the ones of a running build are handled all at once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum WatchEvent {
+    /// Files in the worktree changed that aren't ignored by Git or by the [watcher settings](gitbutler_project::WatcherSettings)
+    /// of the project.
+    WorktreeChanged {
+        /// The changed paths, relative to the worktree.
+        paths: Vec<PathBuf>,
+    },
+    /// References changed, like `HEAD`, a branch or the remote branches after a fetch.
+    GitRefsChanged {
+        /// The changed files, relative to the `.git` directory, like `HEAD`, `FETCH_HEAD` or `refs/heads/main`.
+        refs: Vec<PathBuf>,
+    },
+    /// The index changed, for instance by staging files.
+    IndexChanged,
+}
+
 /// An event telling the receiver something about the state of the application which just changed.
 #[derive(Debug, Clone)]
 #[allow(missing_docs)]
@@ -97,8 +133,48 @@ pub enum Change {
         operating_mode: OperatingMode,
     },
     GitActivity(ProjectId),
+    /// A batch of changes to the worktree or repository of a project, before they are handled.
+    Filesystem {
+        project_id: ProjectId,
+        events: Vec<WatchEvent>,
+    },
+    /// A remote was fetched by the fetch scheduler.
+    RemoteUpdated {
+        project_id: ProjectId,
+        remote: String,
+    },
+    /// A fetch found that the default branch of a remote, its `HEAD`, points to another branch than before,
+    /// like after renaming `master` to `main`.
+    RemoteDefaultBranchChanged {
+        project_id: ProjectId,
+        remote: String,
+        change: DefaultBranchChange,
+    },
     VirtualBranches {
         project_id: ProjectId,
         virtual_branches: VirtualBranches,
     },
+    /// The applied branches whose commits change overlapping lines changed, and so did the conflicts
+    /// that are predicted between them. It's empty once no conflict is predicted anymore.
+    PredictedConflicts {
+        project_id: ProjectId,
+        conflicts: Vec<PredictedConflict>,
+    },
+    /// The target was updated, and the hunks of the applied branches had to be matched again as their lines
+    /// shifted. Only sent if a hunk wasn't kept as it was.
+    OwnershipRemapped {
+        project_id: ProjectId,
+        remap: OwnershipRemap,
+    },
+    /// The workspace was found to no longer match what is recorded for the applied branches.
+    WorkspaceDesynced {
+        project_id: ProjectId,
+        desync: WorkspaceDesync,
+    },
+    /// A Git client changed the references of the workspace in a way that couldn't be folded into the applied
+    /// branches, like by checking out another branch.
+    ExternalChangeDetected {
+        project_id: ProjectId,
+        change: ExternalChange,
+    },
 }
@@ -0,0 +1,294 @@
+//! Fetch all remotes of a project periodically, according to the project's [fetch schedule](gitbutler_project::FetchSchedule).
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use gitbutler_command_context::CommandContext;
+use gitbutler_project::{FetchFailure, FetchSchedule, ProjectId, SettingsKey};
+use gitbutler_repo::{fetch_remotes, progress::NoProgress, RepositoryExt};
+use serde::Serialize;
+use tokio::{sync::broadcast::error::RecvError, task};
+use tokio_util::sync::CancellationToken;
+
+use crate::{Change, Handler};
+
+/// The shortest time to wait between two rounds of fetches, to not busy-loop on misconfiguration.
+const MIN_WAIT: Duration = Duration::from_secs(1);
+/// How long to wait before retrying if the project itself couldn't be accessed.
+const PROJECT_ERROR_WAIT: Duration = Duration::from_secs(60);
+
+/// The state of background fetches for a single remote.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteFetchStatus {
+    /// The name of the remote.
+    pub remote: String,
+    /// The last time the remote was fetched successfully.
+    pub last_fetched: Option<SystemTime>,
+    /// The error message of the last fetch, if it failed.
+    pub last_error: Option<String>,
+    /// The classification of the last failure, if the last fetch failed.
+    pub last_failure: Option<FetchFailure>,
+    /// The amount of failed fetches since the last successful one.
+    pub consecutive_failures: u32,
+    /// The time at which the remote will be fetched next.
+    pub next_fetch: SystemTime,
+}
+
+type StatusByRemote = Arc<Mutex<BTreeMap<String, RemoteFetchStatus>>>;
+
+/// A link to the fetch scheduler running in the background.
+/// Drop it or call [`stop()`](Self::stop()) to stop fetching.
+pub struct FetchSchedulerHandle {
+    /// The id of the project whose remotes are fetched.
+    project_id: ProjectId,
+    /// The state of each remote, shared with the background task.
+    status: StatusByRemote,
+    /// A way to tell the background task to stop.
+    cancellation_token: CancellationToken,
+}
+
+impl Drop for FetchSchedulerHandle {
+    fn drop(&mut self) {
+        self.cancellation_token.cancel();
+    }
+}
+
+impl FetchSchedulerHandle {
+    /// Return the id of the project whose remotes we fetch.
+    pub fn project_id(&self) -> ProjectId {
+        self.project_id
+    }
+
+    /// Stop fetching in the background. The current fetch, if any, will still complete.
+    pub fn stop(&self) {
+        self.cancellation_token.cancel();
+    }
+
+    /// Return `true` if the scheduler was stopped.
+    pub fn is_stopped(&self) -> bool {
+        self.cancellation_token.is_cancelled()
+    }
+
+    /// Return the fetch state of all remotes known to the scheduler, sorted by remote name.
+    pub fn status(&self) -> Vec<RemoteFetchStatus> {
+        self.status
+            .lock()
+            .expect("no panics while holding the lock")
+            .values()
+            .cloned()
+            .collect()
+    }
+}
+
+/// Fetch all remotes of the project identified by `project_id` in the background, and let `handler`
+/// emit a [`Change::RemoteUpdated`] for each successfully fetched remote.
+///
+/// Intervals, jitter and backoff are read from the project's fetch schedule before each round,
+/// and remotes are rescheduled as soon as the schedule is changed in the project settings.
+/// Auth failures back off faster than network failures as they typically need user interaction.
+pub fn fetch_in_background(handler: Handler, project_id: ProjectId) -> FetchSchedulerHandle {
+    let status = StatusByRemote::default();
+    let cancellation_token = CancellationToken::new();
+    let handle = FetchSchedulerHandle {
+        project_id,
+        status: status.clone(),
+        cancellation_token: cancellation_token.clone(),
+    };
+    let mut settings_events = handler.projects().subscribe_to_settings();
+
+    tokio::spawn(async move {
+        let mut subscribed = true;
+        loop {
+            let wait = {
+                let handler = handler.clone();
+                let status = status.clone();
+                // NOTE: fetching is blocking IO, see `watch_in_background()` as well.
+                task::spawn_blocking(move || fetch_due_remotes(&handler, project_id, &status)).await
+            };
+            let wait = match wait {
+                Ok(Ok(wait)) => wait,
+                Ok(Err(err)) => {
+                    tracing::warn!(%project_id, ?err, "scheduled fetch could not be performed");
+                    PROJECT_ERROR_WAIT
+                }
+                Err(err) => {
+                    tracing::error!(%project_id, ?err, "scheduled fetch panicked");
+                    PROJECT_ERROR_WAIT
+                }
+            };
+            let sleep = tokio::time::sleep(wait);
+            tokio::pin!(sleep);
+            loop {
+                tokio::select! {
+                    () = &mut sleep => break,
+                    event = settings_events.recv(), if subscribed => match event {
+                        Ok(event)
+                            if event.project_id == project_id
+                                && event.changed.contains(&SettingsKey::FetchSchedule) =>
+                        {
+                            reschedule(&status, &event.settings.fetch_schedule);
+                            break;
+                        }
+                        Ok(_) => {}
+                        // The missed events may have changed the schedule, which is read again in the next round.
+                        Err(RecvError::Lagged(_)) => break,
+                        Err(RecvError::Closed) => subscribed = false,
+                    },
+                    () = cancellation_token.cancelled() => {
+                        tracing::debug!(%project_id, "stopped fetch scheduler");
+                        return;
+                    }
+                }
+            }
+        }
+    });
+
+    handle
+}
+
+/// Fetch all remotes that are due concurrently, update their `status` and return the time until the next remote is due.
+fn fetch_due_remotes(
+    handler: &Handler,
+    project_id: ProjectId,
+    status: &StatusByRemote,
+) -> Result<Duration> {
+    let project = handler
+        .projects()
+        .get(project_id)
+        .context("failed to get project")?;
+    let ctx = CommandContext::open(&project)?;
+    let remotes = ctx.repository().remotes_as_string()?;
+
+    let now = SystemTime::now();
+    let due: Vec<_> = {
+        let mut status = status.lock().expect("no panics while holding the lock");
+        status.retain(|remote, _| remotes.contains(remote));
+        remotes
+            .into_iter()
+            .filter(|remote| {
+                status
+                    .entry(remote.clone())
+                    .or_insert_with(|| RemoteFetchStatus {
+                        remote: remote.clone(),
+                        last_fetched: None,
+                        last_error: None,
+                        last_failure: None,
+                        consecutive_failures: 0,
+                        next_fetch: now,
+                    })
+                    .next_fetch
+                    <= now
+            })
+            .collect()
+    };
+
+    // Never prompt in the background, the user isn't expecting it.
+    let report = fetch_remotes(
+        &ctx,
+        &due,
+        None,
+        project.settings.fetch_schedule.max_concurrent_fetches,
+        &NoProgress,
+    )?;
+    let fetched_at = SystemTime::now();
+    for fetch in &report.remotes {
+        let remote = &fetch.remote;
+        let mut status = status.lock().expect("no panics while holding the lock");
+        let Some(entry) = status.get_mut(remote) else {
+            continue;
+        };
+        match &fetch.error {
+            None => {
+                entry.last_fetched = Some(fetched_at);
+                entry.last_error = None;
+                entry.last_failure = None;
+                entry.consecutive_failures = 0;
+            }
+            Some(err) => {
+                tracing::warn!(%project_id, %remote, %err, "scheduled fetch failed");
+                entry.last_error = Some(err.clone());
+                entry.last_failure = fetch.failure;
+                entry.consecutive_failures = entry.consecutive_failures.saturating_add(1);
+            }
+        }
+        entry.next_fetch = fetched_at
+            + project.settings.fetch_schedule.next_delay(
+                remote,
+                entry.consecutive_failures,
+                entry.last_failure,
+                jitter_sample(),
+            );
+        drop(status);
+
+        if fetch.is_success() {
+            handler.emit_app_event(Change::RemoteUpdated {
+                project_id,
+                remote: remote.clone(),
+            })?;
+        }
+        if let Some(change) = &fetch.default_branch_change {
+            tracing::info!(%project_id, %remote, old = %change.old, new = %change.new, "default branch of remote changed");
+            handler.emit_app_event(Change::RemoteDefaultBranchChanged {
+                project_id,
+                remote: remote.clone(),
+                change: change.clone(),
+            })?;
+        }
+    }
+
+    if !due.is_empty() {
+        handler
+            .projects()
+            .update(&gitbutler_project::UpdateRequest {
+                id: project_id,
+                project_data_last_fetched: Some(report.fetch_result(fetched_at)),
+                ..Default::default()
+            })
+            .context("failed to update project with last fetched timestamp")?;
+    }
+
+    let next_fetch = status
+        .lock()
+        .expect("no panics while holding the lock")
+        .values()
+        .map(|status| status.next_fetch)
+        .min();
+    Ok(next_fetch
+        .and_then(|next_fetch| next_fetch.duration_since(SystemTime::now()).ok())
+        .unwrap_or(PROJECT_ERROR_WAIT)
+        .max(MIN_WAIT))
+}
+
+/// Bring the next fetch of each remote forward if it's due earlier according to the changed `schedule`.
+/// Fetches that are due later according to it are only delayed after they happened.
+fn reschedule(status: &StatusByRemote, schedule: &FetchSchedule) {
+    let now = SystemTime::now();
+    let mut status = status.lock().expect("no panics while holding the lock");
+    for entry in status.values_mut() {
+        let last_attempt = entry
+            .last_fetched
+            .filter(|_| entry.consecutive_failures == 0)
+            .unwrap_or(now);
+        let next_fetch = last_attempt
+            + schedule.next_delay(
+                &entry.remote,
+                entry.consecutive_failures,
+                entry.last_failure,
+                jitter_sample(),
+            );
+        entry.next_fetch = entry.next_fetch.min(next_fetch);
+    }
+}
+
+/// A cheap source of randomness that is good enough to spread fetches apart.
+fn jitter_sample() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or_default();
+    f64::from(nanos % 1000) / 1000.0
+}
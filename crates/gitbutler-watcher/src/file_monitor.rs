@@ -1,14 +1,19 @@
-use std::{collections::HashSet, path::Path, time::Duration};
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::{anyhow, Context, Result};
-use gitbutler_notify_debouncer::{new_debouncer, Debouncer, NoCache};
+use gitbutler_fs::os_path::CaseSensitivity;
+use gitbutler_notify_debouncer::{new_debouncer, new_debouncer_opt, Debouncer, NoCache};
 use gitbutler_oplog::OPLOG_FILE_NAME;
-use gitbutler_project::ProjectId;
-use notify::{RecommendedWatcher, Watcher};
+use gitbutler_project::{FilesystemCapabilities, ProjectId};
+use notify::{PollWatcher, RecommendedWatcher, Watcher};
 use tokio::task;
 use tracing::Level;
 
-use crate::events::InternalEvent;
+use crate::events::{InternalEvent, WatchEvent};
 
 /// We will collect notifications for up to this amount of time at a very
 /// maximum before releasing them. This duration will be hit if e.g. a build
@@ -25,6 +30,43 @@ const TICK_RATE: Duration = Duration::from_millis(250);
 // the pending events, even if DEBOUNCE_TIMEOUT hasn't expired yet
 const FLUSH_AFTER_EMPTY: u32 = 3;
 
+/// How often to scan the worktree for changes if the filesystem doesn't report them,
+/// like for repositories accessed across the Windows/WSL boundary.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The watcher of a worktree, which receives change notifications from the filesystem if it can.
+pub enum FileWatcher {
+    /// Changes are reported by the filesystem.
+    Native(Debouncer<RecommendedWatcher, NoCache>),
+    /// Changes are found by scanning the worktree every [`POLL_INTERVAL`].
+    Polling(Debouncer<PollWatcher, NoCache>),
+}
+
+impl FileWatcher {
+    /// Emit pending events without waiting for the debounce timeout.
+    pub fn flush_nonblocking(&self) {
+        match self {
+            FileWatcher::Native(debouncer) => debouncer.flush_nonblocking(),
+            FileWatcher::Polling(debouncer) => debouncer.flush_nonblocking(),
+        }
+    }
+
+    fn watch(&mut self, path: &Path) -> notify::Result<()> {
+        match self {
+            FileWatcher::Native(debouncer) => debouncer
+                .watcher()
+                .watch(path, notify::RecursiveMode::Recursive),
+            FileWatcher::Polling(debouncer) => debouncer
+                .watcher()
+                .watch(path, notify::RecursiveMode::Recursive),
+        }
+    }
+
+    fn is_polling(&self) -> bool {
+        matches!(self, FileWatcher::Polling(_))
+    }
+}
+
 /// This error is required only because `anyhow::Error` isn't implementing `std::error::Error`, and [`spawn()`]
 /// needs to wrap it into a `backoff::Error` which also has to implement the `Error` trait.
 #[derive(Debug, thiserror::Error)]
@@ -34,9 +76,10 @@ struct RunError {
     source: anyhow::Error,
 }
 
-/// Listen to interesting filesystem events of files in `path` that are not `.gitignore`d,
-/// turn them into [`Events`](Event) which classifies it, and associates it with `project_id`.
-/// These are sent through the passed `out` channel, to indicate either **Git** repository changes
+/// Listen to interesting filesystem events of files in `path` that are not `.gitignore`d or matched by
+/// the patterns returned by `ignore_patterns`, which is called for each batch so changes to them apply right away.
+/// Each batch of events is turned into [`WatchEvents`](WatchEvent) which classify them, and is associated with
+/// `project_id`. These are sent through the passed `out` channel, to indicate either **Git** repository changes
 /// or **ProjectWorktree** changes
 ///
 /// ### Why is this not an iterator?
@@ -49,54 +92,88 @@ struct RunError {
 /// is chosen to allow all this state to live on the stack.
 ///
 /// Additionally, a channel plays better with how events are handled downstream.
+///
+/// If the filesystem of `worktree_path` doesn't report changes, it's polled instead.
 pub fn spawn(
     project_id: ProjectId,
     worktree_path: &std::path::Path,
+    ignore_patterns: impl Fn() -> Vec<String> + Send + 'static,
     out: tokio::sync::mpsc::UnboundedSender<InternalEvent>,
-) -> Result<Debouncer<RecommendedWatcher, NoCache>> {
+) -> Result<FileWatcher> {
     let (notify_tx, notify_rx) = std::sync::mpsc::channel();
-    let mut debouncer = new_debouncer(
-        DEBOUNCE_TIMEOUT,
-        Some(TICK_RATE),
-        Some(FLUSH_AFTER_EMPTY),
-        notify_tx,
-    )
-    .context("failed to create debouncer")?;
+    let capabilities = FilesystemCapabilities::detect(worktree_path);
+    let mut debouncer = if capabilities.file_events {
+        FileWatcher::Native(
+            new_debouncer(
+                DEBOUNCE_TIMEOUT,
+                Some(TICK_RATE),
+                Some(FLUSH_AFTER_EMPTY),
+                notify_tx,
+            )
+            .context("failed to create debouncer")?,
+        )
+    } else {
+        tracing::info!(%project_id, boundary = ?capabilities.boundary, "filesystem doesn't report changes, polling it instead");
+        FileWatcher::Polling(
+            new_debouncer_opt(
+                DEBOUNCE_TIMEOUT,
+                Some(TICK_RATE),
+                Some(FLUSH_AFTER_EMPTY),
+                notify_tx,
+                NoCache,
+                notify::Config::default().with_poll_interval(POLL_INTERVAL),
+            )
+            .context("failed to create polling debouncer")?,
+        )
+    };
+    // Polling can't tell what kind of file was created or removed.
+    let is_interesting: fn(notify::EventKind) -> bool = if debouncer.is_polling() {
+        is_interesting_polled_kind
+    } else {
+        is_interesting_kind
+    };
 
     let policy = backoff::ExponentialBackoffBuilder::new()
         .with_max_elapsed_time(Some(std::time::Duration::from_secs(30)))
         .build();
 
-    let git_dir = gix::open_opts(worktree_path, gix::open::Options::isolated())
-        .context(format!(
-            "failed to open project repository to obtain git-dir: {}",
-            worktree_path.display()
-        ))?
-        .path()
-        .to_owned();
+    let repo = gix::open_opts(worktree_path, gix::open::Options::isolated()).context(format!(
+        "failed to open project repository to obtain git-dir: {}",
+        worktree_path.display()
+    ))?;
+    let git_dir = repo.path().to_owned();
+    // Linked worktrees share the references of the repository they belong to, which are kept outside of
+    // their own git-dir.
+    let common_dir = repo.common_dir().to_owned();
     let extra_git_dir_to_watch = {
-        let mut enclosing_worktree_dir = git_dir.clone();
+        let outermost_git_dir = if git_dir.starts_with(&common_dir) {
+            &common_dir
+        } else {
+            &git_dir
+        };
+        let mut enclosing_worktree_dir = outermost_git_dir.clone();
         enclosing_worktree_dir.pop();
         if enclosing_worktree_dir != worktree_path {
-            Some(git_dir.as_path())
+            Some(outermost_git_dir.as_path())
         } else {
             None
         }
     };
+    let extra_common_dir_to_watch =
+        (!git_dir.starts_with(&common_dir)).then_some(common_dir.as_path());
 
     // Start the watcher, but retry if there are transient errors.
     backoff::retry(policy, || {
         debouncer
-            .watcher()
-            .watch(worktree_path, notify::RecursiveMode::Recursive)
+            .watch(worktree_path)
             .and_then(|()| {
-                if let Some(git_dir) = extra_git_dir_to_watch {
-                    debouncer
-                        .watcher()
-                        .watch(git_dir, notify::RecursiveMode::Recursive)
-                } else {
-                    Ok(())
+                for dir in extra_git_dir_to_watch
+                    .into_iter()
+                    .chain(extra_common_dir_to_watch)
+                {
+                    debouncer.watch(dir)?;
                 }
+                Ok(())
             })
             .map_err(|err| match err.kind {
                 notify::ErrorKind::PathNotFound => backoff::Error::permanent(RunError::from(
@@ -110,6 +187,8 @@ pub fn spawn(
     })
     .context("failed to start watcher")?;
 
+    // Events may name files with another case than the worktree path has, or in its extended form on Windows.
+    let case = CaseSensitivity::detect(worktree_path);
     let worktree_path = worktree_path.to_owned();
     task::spawn_blocking(move || {
         let _runtime = tracing::span!(Level::INFO, "file monitor", %project_id ).entered();
@@ -121,9 +200,7 @@ pub fn spawn(
                 "handle debounced events",
                 ignored = tracing::field::Empty,
                 project = tracing::field::Empty,
-                project_dedup = tracing::field::Empty,
-                git = tracing::field::Empty,
-                git_dedup = tracing::field::Empty,
+                git_refs = tracing::field::Empty,
                 git_noop = tracing::field::Empty,
                 fs_events = tracing::field::Empty,
             )
@@ -137,10 +214,10 @@ pub fn spawn(
                     let num_events = events.len();
                     let mut classified_file_paths: Vec<_> = events
                         .into_iter()
-                        .filter(|event| is_interesting_kind(event.kind))
+                        .filter(|event| is_interesting(event.kind))
                         .flat_map(|event| event.event.paths)
                         .map(|file| {
-                            let kind = classify_file(&git_dir, &file);
+                            let kind = classify_file(case, &git_dir, &common_dir, &file);
                             (file, kind)
                         })
                         .collect();
@@ -148,12 +225,18 @@ pub fn spawn(
                         .iter()
                         .any(|(_, kind)| *kind == FileKind::Project)
                     {
+                        let patterns = ignore_patterns();
+                        let overrides = (!patterns.is_empty())
+                            .then(|| gix::ignore::Search::from_overrides(patterns));
                         if let Ok(repo) = gix::open(&worktree_path) {
                             if let Ok(index) = repo.index_or_empty() {
-                                if let Ok(mut excludes) = repo.excludes(&index, None, gix::worktree::stack::state::ignore::Source::WorktreeThenIdMappingIfNotSkipped) {
+                                if let Ok(mut excludes) = repo.excludes(&index, overrides, gix::worktree::stack::state::ignore::Source::WorktreeThenIdMappingIfNotSkipped) {
+                                    let mut nested_dirs = HashMap::new();
                                     for (file_path, kind) in classified_file_paths.iter_mut() {
-                                        if let Ok(relative_path) = file_path.strip_prefix(&worktree_path) {
-                                            if excludes.at_path(relative_path, None).map(|platform| platform.is_excluded()).unwrap_or(false) {
+                                        if let Some(relative_path) = case.strip_prefix(file_path, &worktree_path) {
+                                            if excludes.at_path(&relative_path, None).map(|platform| platform.is_excluded()).unwrap_or(false)
+                                                || is_in_nested_repository(&worktree_path, &relative_path, &index, &mut nested_dirs)
+                                            {
                                                 *kind = FileKind::ProjectIgnored
                                             }
                                         }
@@ -162,9 +245,9 @@ pub fn spawn(
                             }
                         }
                     }
-                    let mut oplog_changed = false;
-                    let (mut stripped_git_paths, mut worktree_relative_paths) =
-                        (HashSet::new(), HashSet::new());
+                    let (mut oplog_changed, mut index_changed) = (false, false);
+                    let (mut git_refs, mut worktree_relative_paths) =
+                        (BTreeSet::new(), BTreeSet::new());
                     for (file_path, kind) in classified_file_paths {
                         match kind {
                             FileKind::ProjectIgnored => ignored += 1,
@@ -172,46 +255,55 @@ pub fn spawn(
                             FileKind::GitButlerOplog => {
                                 oplog_changed = true;
                             }
-                            FileKind::Project | FileKind::Git => match file_path
-                                .strip_prefix(&worktree_path)
-                            {
-                                Ok(relative_file_path) => {
-                                    if relative_file_path.as_os_str().is_empty() {
-                                        continue;
-                                    }
-                                    if let Ok(stripped) = relative_file_path.strip_prefix(".git") {
-                                        stripped_git_paths.insert(stripped.to_owned());
-                                    } else {
-                                        worktree_relative_paths
-                                            .insert(relative_file_path.to_owned());
-                                    };
+                            FileKind::GitIndex => {
+                                index_changed = true;
+                            }
+                            FileKind::GitRefs => {
+                                if let Some(relative_file_path) = case
+                                    .strip_prefix(&file_path, &git_dir)
+                                    .or_else(|| case.strip_prefix(&file_path, &common_dir))
+                                {
+                                    git_refs.insert(relative_file_path);
                                 }
-                                Err(err) => {
-                                    tracing::error!(%project_id, ?err, "failed to strip prefix");
+                            }
+                            FileKind::Project => {
+                                match case.strip_prefix(&file_path, &worktree_path) {
+                                    Some(relative_file_path) => {
+                                        if relative_file_path.as_os_str().is_empty() {
+                                            continue;
+                                        }
+                                        worktree_relative_paths.insert(relative_file_path);
+                                    }
+                                    None => {
+                                        tracing::error!(%project_id, file_path = %file_path.display(), "file is outside of the worktree");
+                                    }
                                 }
-                            },
+                            }
                         }
                     }
 
                     stats.record("fs_events", num_events);
                     stats.record("ignored", ignored);
                     stats.record("git_noop", git_noop);
-                    stats.record("git", stripped_git_paths.len());
+                    stats.record("git_refs", git_refs.len());
                     stats.record("project", worktree_relative_paths.len());
 
-                    if !stripped_git_paths.is_empty() {
-                        let paths_dedup: Vec<_> = stripped_git_paths.into_iter().collect();
-                        stats.record("git_dedup", paths_dedup.len());
-                        let event = InternalEvent::GitFilesChange(project_id, paths_dedup);
-                        if out.send(event).is_err() {
-                            tracing::info!("channel closed - stopping file watcher");
-                            break 'outer;
-                        }
-                    }
+                    let mut batch = Vec::new();
                     if !worktree_relative_paths.is_empty() {
-                        let paths_dedup: Vec<_> = worktree_relative_paths.into_iter().collect();
-                        stats.record("project_dedup", paths_dedup.len());
-                        let event = InternalEvent::ProjectFilesChange(project_id, paths_dedup);
+                        batch.push(WatchEvent::WorktreeChanged {
+                            paths: worktree_relative_paths.into_iter().collect(),
+                        });
+                    }
+                    if !git_refs.is_empty() {
+                        batch.push(WatchEvent::GitRefsChanged {
+                            refs: git_refs.into_iter().collect(),
+                        });
+                    }
+                    if index_changed {
+                        batch.push(WatchEvent::IndexChanged);
+                    }
+                    if !batch.is_empty() {
+                        let event = InternalEvent::FilesystemChanges(project_id, batch);
                         if out.send(event).is_err() {
                             tracing::info!("channel closed - stopping file watcher");
                             break 'outer;
@@ -231,6 +323,15 @@ pub fn spawn(
     Ok(debouncer)
 }
 
+fn is_interesting_polled_kind(kind: notify::EventKind) -> bool {
+    matches!(
+        kind,
+        notify::EventKind::Create(_)
+            | notify::EventKind::Modify(notify::event::ModifyKind::Data(_))
+            | notify::EventKind::Remove(_)
+    )
+}
+
 #[cfg(target_family = "unix")]
 fn is_interesting_kind(kind: notify::EventKind) -> bool {
     matches!(
@@ -253,9 +354,11 @@ fn is_interesting_kind(kind: notify::EventKind) -> bool {
 /// A classification for a changed file.
 #[derive(Eq, PartialEq)]
 enum FileKind {
-    /// A file in the `.git` repository of the current project itself.
-    Git,
-    /// Like `Git`, but shouldn't have any effect.
+    /// A file in the `.git` repository that stores references.
+    GitRefs,
+    /// The index of the `.git` repository.
+    GitIndex,
+    /// Any other file in the `.git` repository, which shouldn't have any effect.
     GitUninteresting,
     /// A file in the worktree of the current project.
     Project,
@@ -265,17 +368,30 @@ enum FileKind {
     GitButlerOplog,
 }
 
-fn classify_file(git_dir: &Path, file_path: &Path) -> FileKind {
-    if let Ok(check_file_path) = file_path.strip_prefix(git_dir) {
-        if check_file_path == Path::new("FETCH_HEAD")
-            || check_file_path == Path::new("logs/HEAD")
-            || check_file_path == Path::new("HEAD")
-            || check_file_path == Path::new("GB_FLUSH")
-            || check_file_path == Path::new("index")
-        {
-            FileKind::Git
+/// Classify `file_path` with `git_dir` being the git-dir of the project, and `common_dir` the one of the
+/// repository it belongs to, which differ only for linked worktrees, comparing paths with `case` sensitivity.
+fn classify_file(
+    case: CaseSensitivity,
+    git_dir: &Path,
+    common_dir: &Path,
+    file_path: &Path,
+) -> FileKind {
+    if let Some(check_file_path) = case.strip_prefix(file_path, git_dir) {
+        if check_file_path == Path::new("index") {
+            FileKind::GitIndex
         } else if check_file_path == Path::new("gitbutler").join(OPLOG_FILE_NAME) {
             FileKind::GitButlerOplog
+        } else if is_ref_file(&check_file_path) {
+            FileKind::GitRefs
+        } else {
+            FileKind::GitUninteresting
+        }
+    } else if let Some(check_file_path) = case.strip_prefix(file_path, common_dir) {
+        // Everything else in there belongs to other worktrees, like their `HEAD` and index.
+        if check_file_path == Path::new("packed-refs")
+            || (check_file_path.starts_with("refs") && is_ref_file(&check_file_path))
+        {
+            FileKind::GitRefs
         } else {
             FileKind::GitUninteresting
         }
@@ -283,3 +399,43 @@ fn classify_file(git_dir: &Path, file_path: &Path) -> FileKind {
         FileKind::Project
     }
 }
+
+/// Return `true` if `relative_path` is within a Git repository nested in the worktree at `worktree_path` that
+/// isn't a submodule, like a vendored clone, as its files are left out of the workspace.
+/// Whether a directory is such a repository is cached in `nested_dirs`.
+fn is_in_nested_repository(
+    worktree_path: &Path,
+    relative_path: &Path,
+    index: &gix::index::State,
+    nested_dirs: &mut HashMap<PathBuf, bool>,
+) -> bool {
+    relative_path
+        .ancestors()
+        .skip(1)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .any(|dir| {
+            *nested_dirs.entry(dir.to_owned()).or_insert_with(|| {
+                let dir_path = gix::path::to_unix_separators_on_windows(gix::path::into_bstr(dir));
+                let is_submodule = index
+                    .entry_by_path(dir_path.as_ref())
+                    .map_or(false, |entry| entry.mode == gix::index::entry::Mode::COMMIT);
+                !is_submodule && worktree_path.join(dir).join(".git").exists()
+            })
+        })
+}
+
+/// Return `true` if `path`, relative to the `.git` directory, stores references or their log.
+/// Lock files are skipped, as they are renamed into place once the reference is written.
+fn is_ref_file(path: &Path) -> bool {
+    if path
+        .extension()
+        .map_or(false, |extension| extension == "lock")
+    {
+        return false;
+    }
+    path == Path::new("HEAD")
+        || path == Path::new("FETCH_HEAD")
+        || path == Path::new("logs/HEAD")
+        || path == Path::new("packed-refs")
+        || path.starts_with("refs")
+}
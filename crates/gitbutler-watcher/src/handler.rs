@@ -1,7 +1,15 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
 use anyhow::{Context, Result};
-use gitbutler_branch_actions::{VirtualBranchActions, VirtualBranches};
+use gitbutler_branch::BranchId;
+use gitbutler_branch_actions::{
+    invalidate_workdir_cache, ExternalChange, PredictedConflict, Reconciliation,
+    VirtualBranchActions, VirtualBranches,
+};
 use gitbutler_command_context::CommandContext;
 use gitbutler_error::error::Marker;
 use gitbutler_operating_modes::{
@@ -18,7 +26,7 @@ use gitbutler_sync::cloud::{push_oplog, push_repo};
 use gitbutler_user as users;
 use tracing::instrument;
 
-use super::{events, Change};
+use super::{events, Change, WatchEvent};
 
 /// A type that contains enough state to make decisions based on changes in the filesystem, which themselves
 /// may trigger [Changes](Change)
@@ -32,6 +40,13 @@ pub struct Handler {
     // need extra protection.
     projects: projects::Controller,
     users: users::Controller,
+    /// The heads of the applied branches of each project the conflicts between them were last predicted for,
+    /// along with the predicted conflicts, so they are only predicted and sent again if branches changed.
+    #[allow(clippy::type_complexity)]
+    predicted_conflicts:
+        Arc<Mutex<HashMap<ProjectId, (Vec<(BranchId, git2::Oid)>, Vec<PredictedConflict>)>>>,
+    /// The external change last reported for each project, so it's only sent again once it changed.
+    reported_external_changes: Arc<Mutex<HashMap<ProjectId, ExternalChange>>>,
 
     /// A function to send events - decoupled from app-handle for testing purposes.
     #[allow(clippy::type_complexity)]
@@ -49,6 +64,8 @@ impl Handler {
         Handler {
             projects,
             users,
+            predicted_conflicts: Default::default(),
+            reported_external_changes: Default::default(),
             send_event: Arc::new(send_event),
         }
     }
@@ -57,13 +74,9 @@ impl Handler {
     #[instrument(skip(self), fields(event = %event), err(Debug))]
     pub(super) fn handle(&self, event: events::InternalEvent) -> Result<()> {
         match event {
-            events::InternalEvent::ProjectFilesChange(project_id, path) => {
-                self.recalculate_everything(path, project_id)
-            }
-
-            events::InternalEvent::GitFilesChange(project_id, paths) => self
-                .git_files_change(paths, project_id)
-                .context("failed to handle git file change event"),
+            events::InternalEvent::FilesystemChanges(project_id, events) => self
+                .filesystem_changes(events, project_id)
+                .context("failed to handle filesystem changes"),
 
             events::InternalEvent::GitButlerOplogChange(project_id) => self
                 .gitbutler_oplog_change(project_id)
@@ -73,15 +86,23 @@ impl Handler {
             events::InternalEvent::CalculateVirtualBranches(project_id) => self
                 .calculate_virtual_branches(project_id)
                 .context("failed to handle virtual branch event"),
+
+            events::InternalEvent::OwnershipRemapped(project_id, remap) => {
+                self.emit_app_event(Change::OwnershipRemapped { project_id, remap })
+            }
         }
     }
 }
 
 impl Handler {
-    fn emit_app_event(&self, event: Change) -> Result<()> {
+    pub(super) fn emit_app_event(&self, event: Change) -> Result<()> {
         (self.send_event)(event).context("failed to send event")
     }
 
+    pub(super) fn projects(&self) -> &projects::Controller {
+        &self.projects
+    }
+
     fn open_command_context(&self, project_id: ProjectId) -> Result<CommandContext> {
         let project = self
             .projects
@@ -103,13 +124,20 @@ impl Handler {
             .get(project_id)
             .context("failed to get project")?;
         match VirtualBranchActions.list_virtual_branches(&project) {
-            Ok((branches, skipped_files)) => self.emit_app_event(Change::VirtualBranches {
-                project_id: project.id,
-                virtual_branches: VirtualBranches {
-                    branches,
-                    skipped_files,
-                },
-            }),
+            Ok((branches, skipped_files)) => {
+                let heads = branches
+                    .iter()
+                    .map(|branch| (branch.id, branch.head))
+                    .collect();
+                self.emit_app_event(Change::VirtualBranches {
+                    project_id: project.id,
+                    virtual_branches: VirtualBranches {
+                        branches,
+                        skipped_files,
+                    },
+                })?;
+                self.predict_conflicts(&project, heads)
+            }
             Err(err)
                 if matches!(
                     err.downcast_ref::<Marker>(),
@@ -122,8 +150,64 @@ impl Handler {
         }
     }
 
-    #[instrument(skip(self, paths, project_id), fields(paths = paths.len()))]
-    fn recalculate_everything(&self, paths: Vec<PathBuf>, project_id: ProjectId) -> Result<()> {
+    /// Predict conflicts between the applied branches with `heads`, and send them if they changed.
+    fn predict_conflicts(
+        &self,
+        project: &projects::Project,
+        heads: Vec<(BranchId, git2::Oid)>,
+    ) -> Result<()> {
+        let mut predicted_conflicts = self
+            .predicted_conflicts
+            .lock()
+            .expect("no panics while holding the lock");
+        let previous = predicted_conflicts.get(&project.id);
+        if previous.is_some_and(|(previous_heads, _)| *previous_heads == heads) {
+            return Ok(());
+        }
+        let conflicts = VirtualBranchActions
+            .predict_conflicts(project)
+            .context("failed to predict conflicts")?;
+        let changed = previous.map_or(!conflicts.is_empty(), |(_, previous)| {
+            *previous != conflicts
+        });
+        predicted_conflicts.insert(project.id, (heads, conflicts.clone()));
+        drop(predicted_conflicts);
+        if changed {
+            self.emit_app_event(Change::PredictedConflicts {
+                project_id: project.id,
+                conflicts,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Handle a batch of `events` at once, so virtual branches are recalculated at most once per batch.
+    fn filesystem_changes(&self, events: Vec<WatchEvent>, project_id: ProjectId) -> Result<()> {
+        self.emit_app_event(Change::Filesystem {
+            project_id,
+            events: events.clone(),
+        })?;
+        let mut worktree_paths = 0;
+        for event in events {
+            match event {
+                WatchEvent::WorktreeChanged { paths } => {
+                    worktree_paths += paths.len();
+                    invalidate_workdir_cache(project_id, paths);
+                }
+                WatchEvent::GitRefsChanged { refs } => self
+                    .git_files_change(refs, project_id)
+                    .context("failed to handle git file change event")?,
+                WatchEvent::IndexChanged => {}
+            }
+        }
+        if worktree_paths > 0 {
+            self.recalculate_everything(worktree_paths, project_id)?;
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self, project_id))]
+    fn recalculate_everything(&self, paths: usize, project_id: ProjectId) -> Result<()> {
         let ctx = self.open_command_context(project_id)?;
         // Skip if we're not on the open workspace mode
         if !in_open_workspace_mode(&ctx) {
@@ -141,10 +225,10 @@ impl Handler {
             .get(project_id)
             .context("failed to get project")?;
         if project
-            .should_auto_snapshot(std::time::Duration::from_secs(300))
+            .should_auto_snapshot(project.snapshot_triggers.min_interval())
             .unwrap_or_default()
         {
-            let mut guard = project.exclusive_worktree_access();
+            let mut guard = project.exclusive_operation_access("create_snapshot")?;
             project.create_snapshot(
                 SnapshotDetails::new(OperationKind::FileChanges),
                 guard.write_permission(),
@@ -159,6 +243,12 @@ impl Handler {
             .get(project_id)
             .context("failed to get project")?;
 
+        // Reconcile before `HEAD` is looked at below, which removes the integration branch once it's left.
+        if paths.iter().any(|path| is_workspace_ref_file(path)) {
+            if let Err(err) = self.reconcile_external_changes(&project) {
+                tracing::warn!(project_id = %project.id, ?err, "failed to reconcile external changes");
+            }
+        }
         for path in paths {
             let Some(file_name) = path.to_str() else {
                 continue;
@@ -199,6 +289,39 @@ impl Handler {
         Ok(())
     }
 
+    /// Fold changes that Git clients made to the references of the workspace of `project` into its applied
+    /// branches, or report them if that's not possible and they weren't reported before.
+    fn reconcile_external_changes(&self, project: &projects::Project) -> Result<()> {
+        let reconciliation = VirtualBranchActions
+            .reconcile_external_changes(project)
+            .context("failed to reconcile external changes")?;
+        let mut reported = self
+            .reported_external_changes
+            .lock()
+            .expect("no panics while holding the lock");
+        match reconciliation {
+            Reconciliation::Unchanged => {
+                reported.remove(&project.id);
+            }
+            Reconciliation::Folded { .. } => {
+                reported.remove(&project.id);
+                drop(reported);
+                self.calculate_virtual_branches(project.id)?;
+            }
+            Reconciliation::Detected { change } => {
+                if reported.get(&project.id) != Some(&change) {
+                    reported.insert(project.id, change.clone());
+                    drop(reported);
+                    self.emit_app_event(Change::ExternalChangeDetected {
+                        project_id: project.id,
+                        change,
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Invoked whenever there's a new oplog entry.
     /// If synchronizing with GitButler's servers is enabled it will push Oplog refs
     fn gitbutler_oplog_change(&self, project_id: ProjectId) -> Result<()> {
@@ -219,3 +342,10 @@ impl Handler {
         Ok(())
     }
 }
+
+/// Return `true` if `path`, relative to the `.git` directory, is where `HEAD` or the integration branch are stored.
+fn is_workspace_ref_file(path: &Path) -> bool {
+    path == Path::new("HEAD")
+        || path == Path::new("packed-refs")
+        || path == Path::new("refs/heads/gitbutler/integration")
+}
@@ -0,0 +1,183 @@
+//! Run expensive maintenance of a project only while the user isn't interacting with GitButler,
+//! according to the project's [idle maintenance settings](gitbutler_project::IdleMaintenance).
+use std::{
+    collections::BTreeMap,
+    process::Command,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use gitbutler_branch_actions::VirtualBranchActions;
+use gitbutler_command_context::CommandContext;
+use gitbutler_git::ProcessEnv;
+use gitbutler_operating_modes::in_open_workspace_mode;
+use gitbutler_oplog::OplogExt;
+use gitbutler_project::{Project, ProjectId};
+use tokio::task;
+use tokio_util::sync::CancellationToken;
+
+use crate::Handler;
+
+/// How often to check if the project became idle.
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The last time anything happened, be it an API call or a change to the worktree.
+///
+/// Clones share the same state, so one instance can be touched by all sources of activity.
+#[derive(Debug, Clone)]
+pub struct Activity(Arc<Mutex<Instant>>);
+
+impl Default for Activity {
+    fn default() -> Self {
+        Activity(Arc::new(Mutex::new(Instant::now())))
+    }
+}
+
+impl Activity {
+    /// Record that something happened just now.
+    pub fn touch(&self) {
+        *self.0.lock().expect("no panics while holding the lock") = Instant::now();
+    }
+
+    /// Return how long nothing happened.
+    pub fn idle_for(&self) -> Duration {
+        self.0
+            .lock()
+            .expect("no panics while holding the lock")
+            .elapsed()
+    }
+}
+
+/// Work that is deferred until the project is idle, in the order in which it is performed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MaintenanceTask {
+    /// Compute the virtual branches so caches are warm when the user comes back.
+    WarmCaches,
+    /// Drop snapshots according to the retention policy and repack what's left.
+    CompactSnapshots,
+    /// Write the commit-graph of the repository to speed up history traversals.
+    WriteCommitGraph,
+}
+
+const TASKS: [MaintenanceTask; 3] = [
+    MaintenanceTask::WarmCaches,
+    MaintenanceTask::CompactSnapshots,
+    MaintenanceTask::WriteCommitGraph,
+];
+
+/// A link to the idle maintenance running in the background.
+/// Drop it to stop it.
+pub struct MaintenanceHandle {
+    /// The id of the project that is maintained.
+    project_id: ProjectId,
+    /// A way to tell the background task to stop.
+    cancellation_token: CancellationToken,
+}
+
+impl Drop for MaintenanceHandle {
+    fn drop(&mut self) {
+        self.cancellation_token.cancel();
+    }
+}
+
+impl MaintenanceHandle {
+    /// Return the id of the project that is maintained.
+    pub fn project_id(&self) -> ProjectId {
+        self.project_id
+    }
+}
+
+/// Perform expensive maintenance of the project identified by `project_id` in the background,
+/// but only after `activity` was quiet for the configured amount of time.
+///
+/// Only one task runs at a time, and idleness is checked again before the next one, so activity
+/// in between defers the remaining tasks. Each task runs at most once per configured interval.
+pub fn maintain_when_idle(
+    handler: Handler,
+    project_id: ProjectId,
+    activity: Activity,
+) -> MaintenanceHandle {
+    let cancellation_token = CancellationToken::new();
+    let handle = MaintenanceHandle {
+        project_id,
+        cancellation_token: cancellation_token.clone(),
+    };
+
+    tokio::spawn(async move {
+        let mut last_run = BTreeMap::<MaintenanceTask, Instant>::new();
+        loop {
+            tokio::select! {
+                () = tokio::time::sleep(POLL_INTERVAL) => {}
+                () = cancellation_token.cancelled() => {
+                    tracing::debug!(%project_id, "stopped idle maintenance");
+                    break;
+                }
+            }
+            let Ok(project) = handler.projects().get(project_id) else {
+                continue;
+            };
+            let settings = &project.idle_maintenance;
+            if !settings.enabled || activity.idle_for() < settings.idle_after() {
+                continue;
+            }
+            let Some(task) = TASKS.into_iter().find(|task| {
+                last_run
+                    .get(task)
+                    .map_or(true, |at| at.elapsed() >= settings.interval())
+            }) else {
+                continue;
+            };
+            last_run.insert(task, Instant::now());
+
+            // NOTE: maintenance is blocking IO, see `watch_in_background()` as well.
+            match task::spawn_blocking(move || run(task, &project)).await {
+                Ok(Ok(())) => tracing::debug!(%project_id, ?task, "idle maintenance done"),
+                Ok(Err(err)) => {
+                    tracing::warn!(%project_id, ?task, ?err, "idle maintenance failed")
+                }
+                Err(err) => tracing::error!(%project_id, ?task, ?err, "idle maintenance panicked"),
+            }
+        }
+    });
+
+    handle
+}
+
+fn run(task: MaintenanceTask, project: &Project) -> Result<()> {
+    match task {
+        MaintenanceTask::WarmCaches => {
+            let ctx = CommandContext::open(project)?;
+            if in_open_workspace_mode(&ctx) {
+                VirtualBranchActions.list_virtual_branches(project)?;
+            }
+        }
+        MaintenanceTask::CompactSnapshots => {
+            let outcome = project.gc()?;
+            tracing::info!(
+                project_id = %project.id,
+                pruned = outcome.pruned_snapshots,
+                reclaimed_bytes = outcome.reclaimed_bytes,
+                "compacted snapshots"
+            );
+        }
+        MaintenanceTask::WriteCommitGraph => {
+            let mut cmd = Command::new(gix::path::env::exe_invocation());
+            ProcessEnv::new()
+                .extend(project.extra_env.clone())
+                .apply(&mut cmd);
+            let output = cmd
+                .args(["commit-graph", "write", "--reachable"])
+                .current_dir(&project.path)
+                .output()
+                .with_context(|| format!("failed to spawn {cmd:?}"))?;
+            if !output.status.success() {
+                bail!(
+                    "git commit-graph write failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -7,11 +7,15 @@ use std::path::Path;
 
 use anyhow::{Context, Result};
 use events::InternalEvent;
-pub use events::{Action, Change};
-use gitbutler_project::ProjectId;
+pub use events::{Action, Change, WatchEvent};
+use gitbutler_branch_actions::{cache_workdir_diff, WorkdirCacheGuard};
+use gitbutler_project::{ProjectId, SettingsKey};
 pub use handler::Handler;
 use tokio::{
-    sync::mpsc::{unbounded_channel, UnboundedSender},
+    sync::{
+        broadcast::error::RecvError,
+        mpsc::{unbounded_channel, UnboundedSender},
+    },
     task,
 };
 use tokio_util::sync::CancellationToken;
@@ -19,6 +23,15 @@ use tokio_util::sync::CancellationToken;
 mod file_monitor;
 mod handler;
 
+mod fetch_scheduler;
+pub use fetch_scheduler::{fetch_in_background, FetchSchedulerHandle, RemoteFetchStatus};
+
+mod idle;
+pub use idle::{maintain_when_idle, Activity, MaintenanceHandle};
+
+mod watchdog;
+pub use watchdog::{check_workspace_in_background, WatchdogHandle};
+
 /// An abstraction over a link to the spawned watcher, which runs in the background.
 pub struct WatcherHandle {
     /// A way to post events and interact with the actual handler in the background.
@@ -26,6 +39,8 @@ pub struct WatcherHandle {
     /// The id of the project we are watching.
     project_id: ProjectId,
     signal_flush: UnboundedSender<()>,
+    /// Keeps the worktree diff cached for as long as the worktree is watched.
+    _workdir_cache: WorkdirCacheGuard,
     /// A way to tell the background process to stop handling events.
     cancellation_token: CancellationToken,
 }
@@ -62,51 +77,84 @@ impl WatcherHandle {
 ///
 /// ### How it works
 ///
-/// The watcher is a processing loop that relies on filesystem events. These are aggregated so
-/// every ~100ms, the changed paths sorted by 'worktree' and 'git-repository' will be processed,
-/// each of these events is handled in its own thread, while being able to spawn additional processing
-/// tasks as well.
+/// The watcher is a processing loop that relies on filesystem events. These are aggregated until
+/// the disk was quiet for ~750ms, and the changed paths are turned into a batch of [`WatchEvent`]s,
+/// which is handled in its own thread, while being able to spawn additional processing tasks as well.
+/// Virtual branches are recalculated at most once per batch, and files ignored by Git or by the
+/// [watcher settings](gitbutler_project::WatcherSettings) of the project don't cause a batch.
+/// As all changes are seen, the worktree diff is cached while watching, so only changed files are diffed again.
+/// When the diff settings of the project change, virtual branches are recalculated with them right away.
 ///
 /// This also means that when there are continuous changes to the filesystem, these events might pile
-/// up if they take longer to process than the 100ms window between them, causing high-CPU and possibly
+/// up if they take longer to process than the window between them, causing high-CPU and possibly
 /// high-memory. However, the likelihood for this is much lower than it was before the architecture
 /// was changed to what it is now, which should be much less wasteful.
+///
+/// Each event touches `activity`, so [idle maintenance](maintain_when_idle()) waits for the worktree to settle.
 pub fn watch_in_background(
     handler: handler::Handler,
     worktree_path: impl AsRef<Path>,
     project_id: ProjectId,
+    activity: Activity,
 ) -> Result<WatcherHandle, anyhow::Error> {
     let (events_out, mut events_in) = unbounded_channel();
     let (flush_tx, mut flush_rx) = unbounded_channel();
 
-    let debounce = file_monitor::spawn(project_id, worktree_path.as_ref(), events_out.clone())?;
+    let ignore_patterns = {
+        let projects = handler.projects().clone();
+        move || {
+            projects
+                .get(project_id)
+                .map(|project| project.watcher.ignore)
+                .unwrap_or_default()
+        }
+    };
+    let debounce = file_monitor::spawn(
+        project_id,
+        worktree_path.as_ref(),
+        ignore_patterns,
+        events_out.clone(),
+    )?;
 
+    let mut settings_events = handler.projects().subscribe_to_settings();
     let cancellation_token = CancellationToken::new();
     let handle = WatcherHandle {
         tx: events_out,
         project_id,
         signal_flush: flush_tx,
+        _workdir_cache: cache_workdir_diff(project_id),
         cancellation_token: cancellation_token.clone(),
     };
     let handle_event = move |event: InternalEvent| -> Result<()> {
+        activity.touch();
         let handler = handler.clone();
         // NOTE: Traditional parallelization (blocking) is required as `tokio::spawn()` on
         //       the `handler.handle()` future isn't `Send` as it keeps non-Send things
         //       across await points. Further, there is a fair share of `sync` IO happening
         //       as well, so nothing can really be done here.
+        gitbutler_metrics::watcher_event_queued();
         task::spawn_blocking(move || {
             handler.handle(event).ok();
+            gitbutler_metrics::watcher_event_handled();
         });
         Ok(())
     };
 
     tokio::spawn(async move {
+        let mut subscribed = true;
         loop {
             tokio::select! {
                 Some(event) = events_in.recv() => handle_event(event)?,
                 Some(_signal_flush) = flush_rx.recv() => {
                     debounce.flush_nonblocking();
                 }
+                event = settings_events.recv(), if subscribed => match event {
+                    Ok(event) if event.project_id == project_id && event.changed.contains(&SettingsKey::Diff) => {
+                        handle_event(InternalEvent::CalculateVirtualBranches(project_id))?;
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => subscribed = false,
+                },
                 () = cancellation_token.cancelled() => {
                     tracing::debug!(%project_id, "stopped watcher");
                     break;
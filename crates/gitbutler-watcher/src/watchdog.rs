@@ -0,0 +1,106 @@
+//! Check now and then that the workspace of a project still matches what is recorded for its applied branches,
+//! to tell about drift as soon as it happens instead of when the next operation fails on it.
+use std::time::Duration;
+
+use anyhow::Result;
+use gitbutler_branch_actions::{VirtualBranchActions, WorkspaceDesync};
+use gitbutler_command_context::CommandContext;
+use gitbutler_operating_modes::in_open_workspace_mode;
+use gitbutler_project::ProjectId;
+use tokio::task;
+use tokio_util::sync::CancellationToken;
+
+use crate::{Change, Handler};
+
+/// How often to check the workspace.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// A link to the workspace checks running in the background.
+/// Drop it to stop them.
+pub struct WatchdogHandle {
+    /// The id of the project whose workspace is checked.
+    project_id: ProjectId,
+    /// A way to tell the background task to stop.
+    cancellation_token: CancellationToken,
+}
+
+impl Drop for WatchdogHandle {
+    fn drop(&mut self) {
+        self.cancellation_token.cancel();
+    }
+}
+
+impl WatchdogHandle {
+    /// Return the id of the project whose workspace is checked.
+    pub fn project_id(&self) -> ProjectId {
+        self.project_id
+    }
+}
+
+/// Check the workspace of the project identified by `project_id` in the background, and let `handler` emit a
+/// [`Change::WorkspaceDesynced`] once it doesn't match what is recorded for the applied branches.
+///
+/// A difference is only reported once it was seen by two checks in a row, as the worktree may have changed
+/// just before a check without the branches having been listed since, and it isn't reported again until it
+/// changes.
+pub fn check_workspace_in_background(handler: Handler, project_id: ProjectId) -> WatchdogHandle {
+    let cancellation_token = CancellationToken::new();
+    let handle = WatchdogHandle {
+        project_id,
+        cancellation_token: cancellation_token.clone(),
+    };
+
+    tokio::spawn(async move {
+        let mut previous = WorkspaceDesync::default();
+        let mut reported = WorkspaceDesync::default();
+        loop {
+            tokio::select! {
+                () = tokio::time::sleep(CHECK_INTERVAL) => {}
+                () = cancellation_token.cancelled() => {
+                    tracing::debug!(%project_id, "stopped workspace watchdog");
+                    break;
+                }
+            }
+            let desync = {
+                let handler = handler.clone();
+                // NOTE: checking is blocking IO, see `watch_in_background()` as well.
+                task::spawn_blocking(move || check(&handler, project_id)).await
+            };
+            let desync = match desync {
+                Ok(Ok(desync)) => desync,
+                Ok(Err(err)) => {
+                    tracing::warn!(%project_id, ?err, "workspace check failed");
+                    continue;
+                }
+                Err(err) => {
+                    tracing::error!(%project_id, ?err, "workspace check panicked");
+                    continue;
+                }
+            };
+            if desync.is_empty() {
+                reported = WorkspaceDesync::default();
+            } else if desync == previous && desync != reported {
+                tracing::warn!(%project_id, ?desync, "workspace drifted from the applied branches");
+                if let Err(err) = handler.emit_app_event(Change::WorkspaceDesynced {
+                    project_id,
+                    desync: desync.clone(),
+                }) {
+                    tracing::warn!(%project_id, ?err, "failed to report workspace drift");
+                }
+                reported = desync.clone();
+            }
+            previous = desync;
+        }
+    });
+
+    handle
+}
+
+fn check(handler: &Handler, project_id: ProjectId) -> Result<WorkspaceDesync> {
+    let project = handler.projects().get(project_id)?;
+    let ctx = CommandContext::open(&project)?;
+    if !in_open_workspace_mode(&ctx) {
+        return Ok(WorkspaceDesync::default());
+    }
+    VirtualBranchActions.check_workspace(&project)
+}